@@ -0,0 +1,11 @@
+//! Generates the gRPC control plane API from proto/llmnet.proto
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/llmnet.proto");
+
+    // protox parses the proto in pure Rust, so builds don't need protoc
+    let descriptors = protox::compile(["proto/llmnet.proto"], ["proto"])?;
+    tonic_build::configure().compile_fds(descriptors)?;
+
+    Ok(())
+}
@@ -1,5 +1,12 @@
+//! Node adapters
+//!
+//! [`Adapter`] is the extension point for node types the processor doesn't
+//! know about; see [`registry`] for how plugins are registered.
+
 pub mod openai_api;
 pub mod output;
+pub mod registry;
 
 pub use openai_api::OpenAiApiAdapter;
 pub use output::OutputAdapter;
+pub use registry::{Adapter, AdapterContext, AdapterError, AdapterRegistry, BUILT_IN_ADAPTERS};
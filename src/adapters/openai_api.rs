@@ -1,20 +1,7 @@
 use async_trait::async_trait;
-use thiserror::Error;
 
-use crate::client::{ChatCompletionRequest, ClientError, Message, OpenAiClient, OpenAiClientTrait};
-use crate::runtime::PipelineRequest;
-
-#[derive(Error, Debug)]
-pub enum AdapterError {
-    #[error("Client error: {0}")]
-    Client(#[from] ClientError),
-}
-
-/// Trait for adapters that process requests
-#[async_trait]
-pub trait Adapter: Send + Sync {
-    async fn process(&self, request: &PipelineRequest) -> Result<String, AdapterError>;
-}
+use crate::adapters::registry::{Adapter, AdapterContext, AdapterError};
+use crate::client::{ChatCompletionRequest, Message, OpenAiClient, OpenAiClientTrait};
 
 /// OpenAI API adapter for forwarding requests to OpenAI-compatible endpoints
 pub struct OpenAiApiAdapter {
@@ -31,15 +18,21 @@ impl OpenAiApiAdapter {
 
 #[async_trait]
 impl Adapter for OpenAiApiAdapter {
-    async fn process(&self, request: &PipelineRequest) -> Result<String, AdapterError> {
+    async fn handle_input(
+        &self,
+        _ctx: &AdapterContext<'_>,
+        input: &str,
+    ) -> Result<String, AdapterError> {
         let chat_request = ChatCompletionRequest {
             model: self.client.model().to_string(),
             messages: vec![Message {
                 role: "user".to_string(),
-                content: request.current_content.clone(),
+                content: input.to_string(),
+                ..Default::default()
             }],
             max_tokens: Some(2048),
             temperature: Some(0.7),
+            ..Default::default()
         };
 
         let response = self.client.chat_completion(&chat_request).await?;
@@ -1,7 +1,6 @@
 use async_trait::async_trait;
 
-use crate::adapters::openai_api::{Adapter, AdapterError};
-use crate::runtime::PipelineRequest;
+use crate::adapters::registry::{Adapter, AdapterContext, AdapterError};
 
 /// Output adapter - returns the current content without modification
 pub struct OutputAdapter;
@@ -20,22 +19,38 @@ impl Default for OutputAdapter {
 
 #[async_trait]
 impl Adapter for OutputAdapter {
-    async fn process(&self, request: &PipelineRequest) -> Result<String, AdapterError> {
-        // Simply return the current content
-        Ok(request.current_content.clone())
+    async fn handle_input(
+        &self,
+        _ctx: &AdapterContext<'_>,
+        input: &str,
+    ) -> Result<String, AdapterError> {
+        Ok(input.to_string())
+    }
+
+    fn is_output(&self) -> bool {
+        true
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ArchitectureNode;
+    use crate::runtime::PipelineRequest;
 
     #[tokio::test]
     async fn test_output_adapter() {
         let adapter = OutputAdapter::new();
+        let node: ArchitectureNode =
+            serde_json::from_str(r#"{"name": "out", "adapter": "output"}"#).unwrap();
         let request = PipelineRequest::new("Test content".to_string());
+        let ctx = AdapterContext {
+            node: &node,
+            request: &request,
+        };
 
-        let result = adapter.process(&request).await.unwrap();
+        assert!(adapter.is_output());
+        let result = adapter.handle_output(&ctx, "Test content").await.unwrap();
         assert_eq!(result, "Test content");
     }
 }
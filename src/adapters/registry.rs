@@ -0,0 +1,193 @@
+//! Adapter plugins
+//!
+//! Built-in adapters ("openai-api", "output", "retriever", ...) are handled
+//! by the processor directly. Any other adapter name can be served by a
+//! plugin: an [`Adapter`] registered under that name in an
+//! [`AdapterRegistry`] and handed to the processor with
+//! [`PipelineProcessor::with_adapters`](crate::runtime::PipelineProcessor::with_adapters).
+//! Adapters are trait objects, so they can come from a downstream crate or
+//! wrap a dynamically loaded module:
+//!
+//! ```ignore
+//! let mut adapters = AdapterRegistry::new();
+//! adapters.register("slack", SlackAdapter::new(webhook))?;
+//! let processor = PipelineProcessor::new(&composition)?.with_adapters(&adapters);
+//! ```
+//!
+//! A node then uses the plugin by name, passing it settings through
+//! `extra-options`:
+//!
+//! ```json
+//! {"name": "notify", "adapter": "slack", "extra-options": {"channel": "#alerts"}}
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::client::ClientError;
+use crate::config::{ArchitectureNode, ADAPTER_TYPES};
+use crate::runtime::PipelineRequest;
+
+/// Adapter names the processor handles itself; plugins can't replace them
+pub const BUILT_IN_ADAPTERS: &[&str] = ADAPTER_TYPES;
+
+#[derive(Error, Debug)]
+pub enum AdapterError {
+    #[error("Client error: {0}")]
+    Client(#[from] ClientError),
+
+    #[error("Adapter '{0}' is built in and can't be replaced")]
+    BuiltIn(String),
+
+    #[error("Adapter '{0}' is already registered")]
+    AlreadyRegistered(String),
+
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// What an adapter can see of the node it runs for and the request
+pub struct AdapterContext<'a> {
+    /// The node's configuration, including its `extra-options`
+    pub node: &'a ArchitectureNode,
+    pub request: &'a PipelineRequest,
+}
+
+impl AdapterContext<'_> {
+    /// A setting from the node's `extra-options`
+    pub fn option(&self, key: &str) -> Option<&Value> {
+        self.node.extra_options.get(key)
+    }
+}
+
+/// A node type provided by a plugin
+#[async_trait]
+pub trait Adapter: Send + Sync {
+    /// Produce the node's output from the content that reached it
+    async fn handle_input(
+        &self,
+        ctx: &AdapterContext<'_>,
+        input: &str,
+    ) -> Result<String, AdapterError>;
+
+    /// Deliver the pipeline's final answer at an output node. What it
+    /// returns is the answer the client gets.
+    async fn handle_output(
+        &self,
+        _ctx: &AdapterContext<'_>,
+        output: &str,
+    ) -> Result<String, AdapterError> {
+        Ok(output.to_string())
+    }
+
+    /// Whether nodes using this adapter end the pipeline, like "output"
+    fn is_output(&self) -> bool {
+        false
+    }
+}
+
+/// Plugin adapters by the name nodes refer to them with
+#[derive(Clone, Default)]
+pub struct AdapterRegistry {
+    adapters: HashMap<String, Arc<dyn Adapter>>,
+}
+
+impl AdapterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve nodes with `"adapter": name` with this adapter
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        adapter: impl Adapter + 'static,
+    ) -> Result<(), AdapterError> {
+        let name = name.into();
+        if BUILT_IN_ADAPTERS.contains(&name.as_str()) {
+            return Err(AdapterError::BuiltIn(name));
+        }
+        if self.adapters.contains_key(&name) {
+            return Err(AdapterError::AlreadyRegistered(name));
+        }
+        self.adapters.insert(name, Arc::new(adapter));
+        Ok(())
+    }
+
+    /// The adapter registered under a name
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Adapter>> {
+        self.adapters.get(name).cloned()
+    }
+
+    /// Registered adapter names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.adapters.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.adapters.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Upper;
+
+    #[async_trait]
+    impl Adapter for Upper {
+        async fn handle_input(
+            &self,
+            ctx: &AdapterContext<'_>,
+            input: &str,
+        ) -> Result<String, AdapterError> {
+            let suffix = ctx.option("suffix").and_then(Value::as_str).unwrap_or("");
+            Ok(format!("{}{}", input.to_uppercase(), suffix))
+        }
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = AdapterRegistry::new();
+        registry.register("upper", Upper).unwrap();
+        registry.register("grpc", Upper).unwrap();
+        assert_eq!(registry.names(), ["grpc", "upper"]);
+        assert!(registry.get("upper").is_some());
+        assert!(registry.get("slack").is_none());
+
+        assert!(matches!(
+            registry.register("upper", Upper),
+            Err(AdapterError::AlreadyRegistered(_))
+        ));
+        assert!(matches!(
+            registry.register("output", Upper),
+            Err(AdapterError::BuiltIn(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_context_options() {
+        let node: ArchitectureNode = serde_json::from_str(
+            r#"{"name": "shout", "adapter": "upper", "extra-options": {"suffix": "!"}}"#,
+        )
+        .unwrap();
+        let request = PipelineRequest::new("hi".to_string());
+        let ctx = AdapterContext {
+            node: &node,
+            request: &request,
+        };
+
+        let adapter = Upper;
+        assert_eq!(adapter.handle_input(&ctx, "hi").await.unwrap(), "HI!");
+        // Adapters that don't end the pipeline pass the answer through
+        assert!(!adapter.is_output());
+        assert_eq!(adapter.handle_output(&ctx, "done").await.unwrap(), "done");
+    }
+}
@@ -2,13 +2,32 @@
 //!
 //! SBIO pattern: Commands return Results, I/O is handled by caller
 
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use thiserror::Error;
 
-use crate::cluster::Pipeline;
-use crate::config::load_composition_file;
-use crate::context::{self, Config, Context, ContextError, DEFAULT_WORKER_PORT};
+use crate::cluster::job::parse_prompts;
+use crate::cluster::secret::parse_literal;
+use crate::cluster::{
+    Alert, AlertingConfig, ClusterEvent, Job, JobResult, MetadataField, MetadataPatch, Node,
+    NodePool, Pipeline, Region, ScoringWeights, Secret, VirtualEndpoint,
+};
+use crate::config::{
+    load_composition_file_with_values, render_template, Composition, CompositionFormat,
+};
+use crate::context::{
+    self, Config, Context, ContextError, ExecConfig, ExecTokenSource, DEFAULT_WORKER_PORT,
+};
+use crate::runtime::{
+    detect_host_capacity, DeadLetter, HostCapacity, PruneReport, RequestLog, RequestLogQuery,
+    RequestTrace,
+};
+use crate::server::handlers::{
+    CachedModelListResponse, DeadLetterListResponse, PruneModelsRequest, ReplayResponse,
+    RequeueResponse,
+};
 
 /// Errors that can occur during command execution
 #[derive(Error, Debug)]
@@ -38,6 +57,15 @@ pub enum CommandError {
 /// Result type for commands
 pub type CommandResult<T> = Result<T, CommandError>;
 
+/// What deleting a pipeline did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineDeletion {
+    Deleted,
+    /// Kept until its runners are torn down
+    Terminating,
+    NotFound,
+}
+
 // ============================================================================
 // Context Commands (Pure business logic)
 // ============================================================================
@@ -51,6 +79,7 @@ pub fn context_list(config: &Config) -> Vec<ContextInfo> {
         .map(|(name, ctx)| ContextInfo {
             name: name.clone(),
             url: ctx.url.clone(),
+            namespace: ctx.namespace.clone(),
             is_current: Some(name.as_str()) == current,
         })
         .collect();
@@ -59,12 +88,14 @@ pub fn context_list(config: &Config) -> Vec<ContextInfo> {
     contexts.push(ContextInfo {
         name: "local".to_string(),
         url: format!("http://{}:{}", config.local.bind_addr, config.local.port),
+        namespace: config.local.namespace.clone(),
         is_current: current == Some("local") || current.is_none(),
     });
 
     contexts.push(ContextInfo {
         name: "worker".to_string(),
         url: format!("http://localhost:{}", DEFAULT_WORKER_PORT),
+        namespace: config.worker.namespace.clone(),
         is_current: current == Some("worker"),
     });
 
@@ -77,6 +108,8 @@ pub fn context_list(config: &Config) -> Vec<ContextInfo> {
 pub struct ContextInfo {
     pub name: String,
     pub url: String,
+    /// Namespace set on the context, if any
+    pub namespace: Option<String>,
     pub is_current: bool,
 }
 
@@ -99,15 +132,34 @@ pub fn context_add(
     name: &str,
     url: &str,
     api_key: Option<&str>,
+    exec: Option<ExecConfig>,
 ) -> CommandResult<()> {
     let mut ctx = Context::new(name, url);
     if let Some(key) = api_key {
         ctx = ctx.with_api_key(key);
     }
+    if let Some(exec) = exec {
+        ctx = ctx.with_exec(exec);
+    }
     context::add_context(config, ctx);
     Ok(())
 }
 
+/// Set (or with `None`, clear) the default namespace of a context,
+/// the current one unless named; returns the context's name
+pub fn context_set_namespace(
+    config: &mut Config,
+    context: Option<&str>,
+    namespace: Option<String>,
+) -> CommandResult<String> {
+    let name = context
+        .or(config.current_context.as_deref())
+        .unwrap_or("local")
+        .to_string();
+    context::set_context_namespace(config, &name, namespace)?;
+    Ok(name)
+}
+
 /// Delete a context
 pub fn context_delete(config: &mut Config, name: &str) -> CommandResult<bool> {
     let removed = context::remove_context(config, name);
@@ -118,9 +170,14 @@ pub fn context_delete(config: &mut Config, name: &str) -> CommandResult<bool> {
 // Deploy Commands
 // ============================================================================
 
-/// Load and parse a pipeline manifest
-pub fn load_pipeline_manifest(path: &PathBuf) -> CommandResult<Pipeline> {
+/// Load and parse a pipeline manifest, rendering `{{ .values.x }}` placeholders
+pub fn load_pipeline_manifest(
+    path: &PathBuf,
+    values: &serde_json::Value,
+) -> CommandResult<Pipeline> {
     let content = std::fs::read_to_string(path)?;
+    let content =
+        render_template(&content, values).map_err(|e| CommandError::Config(e.to_string()))?;
 
     // Try YAML first, then JSON
     let pipeline: Pipeline = if path.extension().and_then(|e| e.to_str()) == Some("yaml")
@@ -134,35 +191,284 @@ pub fn load_pipeline_manifest(path: &PathBuf) -> CommandResult<Pipeline> {
     Ok(pipeline)
 }
 
+/// Load what `deploy` would send: a pipeline manifest, or a plain
+/// composition (JSON, TOML or YAML) wrapped in a pipeline named after the file
+pub fn load_deploy_manifest(
+    path: &std::path::Path,
+    namespace: &str,
+    values: &serde_json::Value,
+) -> CommandResult<Pipeline> {
+    let content = std::fs::read_to_string(path)?;
+    let content =
+        render_template(&content, values).map_err(|e| CommandError::Config(e.to_string()))?;
+
+    // Try as a pipeline manifest, fall back to composition
+    let format = CompositionFormat::from_path(path);
+    let manifest = match format {
+        CompositionFormat::Json => {
+            serde_json::from_str::<Pipeline>(&content).map_err(|e| e.to_string())
+        }
+        CompositionFormat::Yaml => {
+            serde_yaml::from_str::<Pipeline>(&content).map_err(|e| e.to_string())
+        }
+        CompositionFormat::Toml => Err("pipeline manifests aren't written in TOML".to_string()),
+    };
+    let error = match manifest {
+        Ok(pipeline) => return Ok(pipeline),
+        Err(e) => e,
+    };
+    match Composition::from_str_as(&content, format) {
+        Ok(composition) => {
+            let name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("pipeline");
+            Ok(Pipeline::new(name, composition).with_namespace(namespace))
+        }
+        // A broken manifest is reported as one
+        Err(_) if content.contains("apiVersion") => Err(CommandError::Config(error)),
+        Err(e) => Err(CommandError::Config(e.to_string())),
+    }
+}
+
+/// Load a manifest for `llmnet deploy` if it is a VirtualEndpoint
+///
+/// Returns `None` for any other manifest, which is then deployed as a
+/// pipeline.
+pub fn load_virtual_endpoint_manifest(
+    path: &std::path::Path,
+    values: &serde_json::Value,
+) -> CommandResult<Option<VirtualEndpoint>> {
+    load_manifest_of_kind(path, values, "VirtualEndpoint")
+}
+
+/// Load a manifest for `llmnet deploy` if it is a NodePool
+pub fn load_node_pool_manifest(
+    path: &std::path::Path,
+    values: &serde_json::Value,
+) -> CommandResult<Option<NodePool>> {
+    load_manifest_of_kind(path, values, "NodePool")
+}
+
+/// Load a manifest for `llmnet deploy` if it is a Region
+pub fn load_region_manifest(
+    path: &std::path::Path,
+    values: &serde_json::Value,
+) -> CommandResult<Option<Region>> {
+    load_manifest_of_kind(path, values, "Region")
+}
+
+/// Load a rendered manifest as `T` if its `kind` is `kind`
+fn load_manifest_of_kind<T: serde::de::DeserializeOwned>(
+    path: &std::path::Path,
+    values: &serde_json::Value,
+    kind: &str,
+) -> CommandResult<Option<T>> {
+    let content = std::fs::read_to_string(path)?;
+    let content =
+        render_template(&content, values).map_err(|e| CommandError::Config(e.to_string()))?;
+
+    let manifest: serde_json::Value = if matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml" | "yml")
+    ) {
+        serde_yaml::from_str(&content).map_err(|e| CommandError::Config(e.to_string()))?
+    } else {
+        match serde_json::from_str(&content) {
+            Ok(manifest) => manifest,
+            Err(_) => return Ok(None),
+        }
+    };
+    if manifest["kind"] != kind {
+        return Ok(None);
+    }
+    serde_json::from_value(manifest)
+        .map(Some)
+        .map_err(|e| CommandError::Config(e.to_string()))
+}
+
 /// Create a pipeline from a composition file (legacy format)
-pub fn pipeline_from_composition(path: &std::path::Path, name: &str) -> CommandResult<Pipeline> {
-    let composition =
-        load_composition_file(path).map_err(|e| CommandError::Config(e.to_string()))?;
+pub fn pipeline_from_composition(
+    path: &std::path::Path,
+    name: &str,
+    values: &serde_json::Value,
+) -> CommandResult<Pipeline> {
+    let composition = load_composition_file_with_values(path, values)
+        .map_err(|e| CommandError::Config(e.to_string()))?;
     Ok(Pipeline::new(name, composition))
 }
 
+// ============================================================================
+// Job Commands
+// ============================================================================
+
+/// Build the job `job create` sends
+///
+/// An http(s) input is left for the control plane to fetch; a local file is
+/// read here and its prompts sent inline.
+pub fn build_job(
+    name: &str,
+    pipeline: &str,
+    namespace: &str,
+    input: Option<&str>,
+    prompts: Vec<String>,
+    parallelism: usize,
+) -> CommandResult<Job> {
+    let mut job = Job::new(name, pipeline, prompts).with_namespace(namespace);
+    job.spec.parallelism = parallelism;
+
+    match input {
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            Ok(job.with_input(url))
+        }
+        Some(path) => {
+            let content = std::fs::read_to_string(path)?;
+            job.spec.prompts = parse_prompts(&content)
+                .map_err(|e| CommandError::Config(format!("{}: {}", path, e)))?;
+            if job.spec.prompts.is_empty() {
+                return Err(CommandError::Config(format!("{}: no prompts", path)));
+            }
+            Ok(job)
+        }
+        None => Ok(job),
+    }
+}
+
+// ============================================================================
+// Secret Commands
+// ============================================================================
+
+/// Build the secret `create secret generic` sends from `KEY=VALUE` literals
+/// and `KEY=PATH` files
+///
+/// A file's contents are used as they are, trailing newline included.
+pub fn build_secret(
+    name: &str,
+    namespace: &str,
+    literals: &[String],
+    files: &[String],
+) -> CommandResult<Secret> {
+    let mut values = BTreeMap::new();
+    for literal in literals {
+        let (key, value) = parse_literal(literal).map_err(CommandError::Config)?;
+        values.insert(key, value);
+    }
+    for file in files {
+        let (key, path) = parse_literal(file)
+            .map_err(|_| CommandError::Config(format!("expected KEY=PATH, got '{}'", file)))?;
+        let value = std::fs::read_to_string(&path)
+            .map_err(|e| CommandError::Config(format!("{}: {}", path, e)))?;
+        values.insert(key, value);
+    }
+    Ok(Secret::new(name, values).with_namespace(namespace))
+}
+
+// ============================================================================
+// Label and Annotate Commands
+// ============================================================================
+
+/// Parse the `KEY=VALUE` and `KEY-` arguments of `label` and `annotate`
+/// into a patch that sets the former and removes the latter
+pub fn parse_metadata_changes(changes: &[String]) -> CommandResult<MetadataPatch> {
+    let mut patch = MetadataPatch::new();
+    for change in changes {
+        let (key, value) = match change.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => match change.strip_suffix('-') {
+                Some(key) => (key, None),
+                None => {
+                    return Err(CommandError::Config(format!(
+                        "expected KEY=VALUE or KEY-, got '{}'",
+                        change
+                    )))
+                }
+            },
+        };
+        if key.is_empty() {
+            return Err(CommandError::Config(format!("missing key in '{}'", change)));
+        }
+        patch.insert(key.to_string(), value);
+    }
+    Ok(patch)
+}
+
 // ============================================================================
 // Validate Commands
 // ============================================================================
 
-/// Validate a composition file
-pub fn validate_composition(path: &std::path::Path) -> CommandResult<ValidationResult> {
-    match load_composition_file(path) {
+/// Validate a composition file with the given values applied
+pub fn validate_composition(
+    path: &std::path::Path,
+    values: &serde_json::Value,
+) -> CommandResult<ValidationResult> {
+    match load_composition_file_with_values(path, values) {
         Ok(comp) => Ok(ValidationResult {
             valid: true,
             models: comp.models.len(),
             nodes: comp.architecture.len(),
             error: None,
+            warnings: docker_limit_warnings(&comp, &detect_host_capacity())
+                .into_iter()
+                .chain(image_input_warnings(&comp))
+                .collect(),
         }),
         Err(e) => Ok(ValidationResult {
             valid: false,
             models: 0,
             nodes: 0,
             error: Some(e.to_string()),
+            warnings: Vec::new(),
         }),
     }
 }
 
+/// Docker resource limits in a composition that exceed the host's capacity
+pub fn docker_limit_warnings(composition: &Composition, host: &HostCapacity) -> Vec<String> {
+    let mut names: Vec<&String> = composition.models.keys().collect();
+    names.sort();
+
+    names
+        .into_iter()
+        .filter_map(|name| Some((name, composition.models[name].to_config().docker?)))
+        .flat_map(|(name, docker)| {
+            docker
+                .capacity_warnings(host)
+                .into_iter()
+                .map(move |warning| format!("Model '{}': {}", name, warning))
+        })
+        .collect()
+}
+
+/// Handlers whose models can't accept images, in a composition where other
+/// handlers can
+///
+/// Images are dropped before reaching such a handler, so a request routed
+/// there loses them. Compositions without any vision model aren't flagged.
+pub fn image_input_warnings(composition: &Composition) -> Vec<String> {
+    let handlers: Vec<_> = composition
+        .architecture
+        .iter()
+        .filter(|n| !n.is_router() && n.adapter == "openai-api")
+        .filter_map(|n| Some((n, composition.model_for_node(n)?.to_config())))
+        .collect();
+
+    if !handlers.iter().any(|(_, model)| model.supports_vision()) {
+        return Vec::new();
+    }
+
+    handlers
+        .into_iter()
+        .filter(|(_, model)| !model.supports_vision())
+        .map(|(node, _)| {
+            format!(
+                "Node '{}': model '{}' doesn't accept image input; images are dropped before reaching it",
+                node.name,
+                node.model.as_deref().unwrap_or_default()
+            )
+        })
+        .collect()
+}
+
 /// Result of validating a composition
 #[derive(Debug)]
 pub struct ValidationResult {
@@ -170,6 +476,9 @@ pub struct ValidationResult {
     pub models: usize,
     pub nodes: usize,
     pub error: Option<String>,
+    /// Problems that won't stop the composition loading, such as container
+    /// limits larger than this host
+    pub warnings: Vec<String>,
 }
 
 // ============================================================================
@@ -181,6 +490,7 @@ pub struct ControlPlaneClient {
     client: reqwest::Client,
     base_url: String,
     api_key: Option<String>,
+    exec: Option<Arc<ExecTokenSource>>,
 }
 
 impl ControlPlaneClient {
@@ -190,6 +500,7 @@ impl ControlPlaneClient {
             client: reqwest::Client::new(),
             base_url: base_url.into(),
             api_key: None,
+            exec: None,
         }
     }
 
@@ -199,53 +510,711 @@ impl ControlPlaneClient {
         self
     }
 
+    /// Fetch bearer tokens from a credential helper, in place of the API key
+    pub fn with_exec(mut self, exec: ExecConfig) -> Self {
+        self.exec = Some(Arc::new(ExecTokenSource::new(exec)));
+        self
+    }
+
     /// Create from current context
     pub fn from_context(config: &Config) -> CommandResult<Self> {
         let url = config.current_url()?;
-        let api_key = config
+        let ctx = config
             .current_context
             .as_ref()
-            .and_then(|name| config.contexts.get(name))
-            .and_then(|ctx| ctx.api_key.clone());
+            .and_then(|name| config.contexts.get(name));
 
         let mut client = Self::new(url);
-        if let Some(key) = api_key {
+        if let Some(key) = ctx.and_then(|c| c.api_key.clone()) {
             client = client.with_api_key(key);
         }
+        if let Some(exec) = ctx.and_then(|c| c.exec.clone()) {
+            client = client.with_exec(exec);
+        }
         Ok(client)
     }
 
-    fn build_request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+    async fn build_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+    ) -> CommandResult<reqwest::RequestBuilder> {
         let url = format!("{}{}", self.base_url, path);
         let mut req = self.client.request(method, &url);
-        if let Some(ref key) = self.api_key {
-            req = req.header("Authorization", format!("Bearer {}", key));
+        let token = match &self.exec {
+            Some(exec) => Some(exec.token().await?),
+            None => self.api_key.clone(),
+        };
+        if let Some(token) = token {
+            req = req.header("Authorization", format!("Bearer {}", token));
+        }
+        Ok(req)
+    }
+
+    /// Get cluster status
+    pub async fn status(&self) -> CommandResult<serde_json::Value> {
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/status")
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Status check failed: {}",
+                resp.status()
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Deploy a pipeline, or apply the manifest to it if it already exists
+    pub async fn deploy(&self, pipeline: &Pipeline) -> CommandResult<Pipeline> {
+        self.deploy_with_force(pipeline, false)
+            .await
+            .map(|(pipeline, _)| pipeline)
+    }
+
+    /// Deploy a pipeline, optionally even if it doesn't fit on the current
+    /// nodes, returning the control plane's warnings about it
+    pub async fn deploy_with_force(
+        &self,
+        pipeline: &Pipeline,
+        force: bool,
+    ) -> CommandResult<(Pipeline, Vec<String>)> {
+        let mut path = format!(
+            "/v1/namespaces/{}/pipelines/{}",
+            pipeline.metadata.namespace, pipeline.metadata.name
+        );
+        if force {
+            path.push_str("?force=true");
+        }
+        let resp = self
+            .build_request(reqwest::Method::PUT, &path)
+            .await?
+            .json(pipeline)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        let pipeline: Pipeline = serde_json::from_value(body["pipeline"].clone())?;
+        let warnings: Vec<String> =
+            serde_json::from_value(body["warnings"].clone()).unwrap_or_default();
+        Ok((pipeline, warnings))
+    }
+
+    /// List pipelines of a namespace, or of every namespace and federated
+    /// region
+    pub async fn list_pipelines(&self, namespace: Option<&str>) -> CommandResult<Vec<Pipeline>> {
+        let path = match namespace {
+            Some(ns) => format!("/v1/namespaces/{}/pipelines", ns),
+            None => "/v1/pipelines?federated=true".to_string(),
+        };
+
+        let resp = self
+            .build_request(reqwest::Method::GET, &path)
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to list pipelines: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        let pipelines: Vec<Pipeline> = serde_json::from_value(body["items"].clone())?;
+        Ok(pipelines)
+    }
+
+    /// Get a specific pipeline
+    pub async fn get_pipeline(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> CommandResult<Option<Pipeline>> {
+        let path = format!("/v1/namespaces/{}/pipelines/{}", namespace, name);
+
+        let resp = self
+            .build_request(reqwest::Method::GET, &path)
+            .await?
+            .send()
+            .await?;
+
+        if resp.status().as_u16() == 404 {
+            return Ok(None);
+        }
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to get pipeline: {}",
+                resp.status()
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Delete a pipeline; `force` overrides its deletion protection
+    pub async fn delete_pipeline(
+        &self,
+        namespace: &str,
+        name: &str,
+        force: bool,
+    ) -> CommandResult<PipelineDeletion> {
+        let mut path = format!("/v1/namespaces/{}/pipelines/{}", namespace, name);
+        if force {
+            path.push_str("?force=true");
+        }
+
+        let resp = self
+            .build_request(reqwest::Method::DELETE, &path)
+            .await?
+            .send()
+            .await?;
+
+        match resp.status() {
+            reqwest::StatusCode::ACCEPTED => Ok(PipelineDeletion::Terminating),
+            reqwest::StatusCode::NOT_FOUND => Ok(PipelineDeletion::NotFound),
+            status if status.is_success() => Ok(PipelineDeletion::Deleted),
+            status => {
+                let body: serde_json::Value = resp.json().await.unwrap_or_default();
+                let message = body["message"].as_str().map(str::to_string);
+                Err(CommandError::Server(message.unwrap_or_else(|| {
+                    format!("Failed to delete pipeline: {}", status)
+                })))
+            }
+        }
+    }
+
+    /// Scale a pipeline
+    pub async fn scale_pipeline(
+        &self,
+        namespace: &str,
+        name: &str,
+        replicas: u32,
+    ) -> CommandResult<Pipeline> {
+        let path = format!("/v1/namespaces/{}/pipelines/{}/scale", namespace, name);
+
+        let resp = self
+            .build_request(reqwest::Method::PATCH, &path)
+            .await?
+            .json(&serde_json::json!({ "replicas": replicas }))
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        let pipeline: Pipeline = serde_json::from_value(body["pipeline"].clone())?;
+        Ok(pipeline)
+    }
+
+    /// Set or remove a pipeline's labels or annotations
+    pub async fn patch_pipeline_metadata(
+        &self,
+        namespace: &str,
+        name: &str,
+        field: MetadataField,
+        patch: &MetadataPatch,
+    ) -> CommandResult<Pipeline> {
+        let path = format!(
+            "/v1/namespaces/{}/pipelines/{}/{}",
+            namespace,
+            name,
+            field.as_str()
+        );
+
+        let resp = self
+            .build_request(reqwest::Method::PATCH, &path)
+            .await?
+            .json(patch)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        let pipeline: Pipeline = serde_json::from_value(body["pipeline"].clone())?;
+        Ok(pipeline)
+    }
+
+    /// Create a batch inference job
+    pub async fn create_job(&self, job: &Job) -> CommandResult<Job> {
+        let path = format!("/v1/namespaces/{}/jobs", job.metadata.namespace);
+        let resp = self
+            .build_request(reqwest::Method::POST, &path)
+            .await?
+            .json(job)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        let job: Job = serde_json::from_value(body["job"].clone())?;
+        Ok(job)
+    }
+
+    /// Create or replace a virtual endpoint
+    pub async fn apply_virtual_endpoint(
+        &self,
+        endpoint: &VirtualEndpoint,
+    ) -> CommandResult<VirtualEndpoint> {
+        let path = format!(
+            "/v1/namespaces/{}/endpoints/{}",
+            endpoint.metadata.namespace, endpoint.metadata.name
+        );
+        let resp = self
+            .build_request(reqwest::Method::PUT, &path)
+            .await?
+            .json(endpoint)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        let endpoint: VirtualEndpoint = serde_json::from_value(body["endpoint"].clone())?;
+        Ok(endpoint)
+    }
+
+    /// List virtual endpoints
+    pub async fn list_virtual_endpoints(
+        &self,
+        namespace: Option<&str>,
+    ) -> CommandResult<Vec<VirtualEndpoint>> {
+        let path = match namespace {
+            Some(ns) => format!("/v1/namespaces/{}/endpoints", ns),
+            None => "/v1/endpoints".to_string(),
+        };
+
+        let resp = self
+            .build_request(reqwest::Method::GET, &path)
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to list endpoints: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        let endpoints: Vec<VirtualEndpoint> = serde_json::from_value(body["items"].clone())?;
+        Ok(endpoints)
+    }
+
+    /// Delete a virtual endpoint
+    pub async fn delete_virtual_endpoint(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> CommandResult<bool> {
+        let path = format!("/v1/namespaces/{}/endpoints/{}", namespace, name);
+
+        let resp = self
+            .build_request(reqwest::Method::DELETE, &path)
+            .await?
+            .send()
+            .await?;
+
+        Ok(resp.status().is_success())
+    }
+
+    /// Create a secret; the response carries its values encrypted
+    pub async fn create_secret(&self, secret: &Secret) -> CommandResult<Secret> {
+        let path = format!("/v1/namespaces/{}/secrets", secret.metadata.namespace);
+        let resp = self
+            .build_request(reqwest::Method::POST, &path)
+            .await?
+            .json(secret)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        let secret: Secret = serde_json::from_value(body["secret"].clone())?;
+        Ok(secret)
+    }
+
+    /// List secrets
+    pub async fn list_secrets(&self, namespace: Option<&str>) -> CommandResult<Vec<Secret>> {
+        let path = match namespace {
+            Some(ns) => format!("/v1/namespaces/{}/secrets", ns),
+            None => "/v1/secrets".to_string(),
+        };
+
+        let resp = self
+            .build_request(reqwest::Method::GET, &path)
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to list secrets: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        let secrets: Vec<Secret> = serde_json::from_value(body["items"].clone())?;
+        Ok(secrets)
+    }
+
+    /// Delete a secret
+    pub async fn delete_secret(&self, namespace: &str, name: &str) -> CommandResult<bool> {
+        let path = format!("/v1/namespaces/{}/secrets/{}", namespace, name);
+
+        let resp = self
+            .build_request(reqwest::Method::DELETE, &path)
+            .await?
+            .send()
+            .await?;
+
+        Ok(resp.status().is_success())
+    }
+
+    /// List jobs
+    pub async fn list_jobs(&self, namespace: Option<&str>) -> CommandResult<Vec<Job>> {
+        let path = match namespace {
+            Some(ns) => format!("/v1/namespaces/{}/jobs", ns),
+            None => "/v1/jobs".to_string(),
+        };
+
+        let resp = self
+            .build_request(reqwest::Method::GET, &path)
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to list jobs: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        let jobs: Vec<Job> = serde_json::from_value(body["items"].clone())?;
+        Ok(jobs)
+    }
+
+    /// Delete a job, stopping it if it is running
+    pub async fn delete_job(&self, namespace: &str, name: &str) -> CommandResult<bool> {
+        let path = format!("/v1/namespaces/{}/jobs/{}", namespace, name);
+
+        let resp = self
+            .build_request(reqwest::Method::DELETE, &path)
+            .await?
+            .send()
+            .await?;
+
+        Ok(resp.status().is_success())
+    }
+
+    /// Outputs of a job's prompts so far, or `None` if there's no such job
+    pub async fn job_results(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> CommandResult<Option<Vec<JobResult>>> {
+        let path = format!("/v1/namespaces/{}/jobs/{}/results", namespace, name);
+
+        let resp = self
+            .build_request(reqwest::Method::GET, &path)
+            .await?
+            .send()
+            .await?;
+
+        if resp.status().as_u16() == 404 {
+            return Ok(None);
+        }
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to get job results: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        Ok(Some(serde_json::from_value(body["items"].clone())?))
+    }
+
+    /// Get the weights nodes are scored with for scheduling
+    pub async fn scoring_weights(&self) -> CommandResult<ScoringWeights> {
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/config/scoring")
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to get scoring weights: {}",
+                resp.status()
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Replace the node scoring weights
+    pub async fn set_scoring_weights(
+        &self,
+        weights: &ScoringWeights,
+    ) -> CommandResult<ScoringWeights> {
+        let resp = self
+            .build_request(reqwest::Method::PUT, "/v1/config/scoring")
+            .await?
+            .json(weights)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["message"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        Ok(serde_json::from_value(body)?)
+    }
+
+    /// Get the alert rules and sinks
+    pub async fn alerting_config(&self) -> CommandResult<AlertingConfig> {
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/config/alerting")
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to get alerting config: {}",
+                resp.status()
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Replace the alert rules and sinks
+    pub async fn set_alerting_config(
+        &self,
+        alerting: &AlertingConfig,
+    ) -> CommandResult<AlertingConfig> {
+        let resp = self
+            .build_request(reqwest::Method::PUT, "/v1/config/alerting")
+            .await?
+            .json(alerting)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["message"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        Ok(serde_json::from_value(body)?)
+    }
+
+    /// List broken alert rules
+    pub async fn list_alerts(&self) -> CommandResult<Vec<Alert>> {
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/alerts")
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to list alerts: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        Ok(serde_json::from_value(body["items"].clone())?)
+    }
+
+    /// List nodes
+    pub async fn list_nodes(&self) -> CommandResult<Vec<serde_json::Value>> {
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/nodes")
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to list nodes: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        let nodes: Vec<serde_json::Value> = serde_json::from_value(body["items"].clone())?;
+        Ok(nodes)
+    }
+
+    /// Get a specific node
+    pub async fn get_node(&self, name: &str) -> CommandResult<Option<Node>> {
+        let path = format!("/v1/nodes/{}", name);
+
+        let resp = self
+            .build_request(reqwest::Method::GET, &path)
+            .await?
+            .send()
+            .await?;
+
+        if resp.status().as_u16() == 404 {
+            return Ok(None);
+        }
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to get node: {}",
+                resp.status()
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Stop or allow scheduling pipelines onto a node
+    pub async fn cordon_node(&self, name: &str, cordon: bool) -> CommandResult<()> {
+        let action = if cordon { "cordon" } else { "uncordon" };
+        let path = format!("/v1/nodes/{}/{}", name, action);
+        let resp = self
+            .build_request(reqwest::Method::POST, &path)
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            let error = body["message"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(format!(
+                "Failed to {} node: {}",
+                action, error
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Replace a node's labels, annotations and spec
+    pub async fn update_node(&self, node: &Node) -> CommandResult<Node> {
+        let path = format!("/v1/nodes/{}", node.metadata.name);
+        let resp = self
+            .build_request(reqwest::Method::PUT, &path)
+            .await?
+            .json(node)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+
+        Ok(serde_json::from_value(body["node"].clone())?)
+    }
+
+    /// Set or remove a node's labels or annotations
+    pub async fn patch_node_metadata(
+        &self,
+        name: &str,
+        field: MetadataField,
+        patch: &MetadataPatch,
+    ) -> CommandResult<Node> {
+        let path = format!("/v1/nodes/{}/{}", name, field.as_str());
+        let resp = self
+            .build_request(reqwest::Method::PATCH, &path)
+            .await?
+            .json(patch)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        let body: serde_json::Value = resp.json().await?;
+
+        if !status.is_success() {
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
         }
-        req
+
+        Ok(serde_json::from_value(body["node"].clone())?)
     }
 
-    /// Get cluster status
-    pub async fn status(&self) -> CommandResult<serde_json::Value> {
+    /// Delete a node
+    pub async fn delete_node(&self, name: &str) -> CommandResult<bool> {
+        let path = format!("/v1/nodes/{}", name);
+
         let resp = self
-            .build_request(reqwest::Method::GET, "/v1/status")
+            .build_request(reqwest::Method::DELETE, &path)
+            .await?
             .send()
             .await?;
 
-        if !resp.status().is_success() {
-            return Err(CommandError::Server(format!(
-                "Status check failed: {}",
-                resp.status()
-            )));
-        }
-
-        Ok(resp.json().await?)
+        Ok(resp.status().is_success())
     }
 
-    /// Deploy a pipeline
-    pub async fn deploy(&self, pipeline: &Pipeline) -> CommandResult<Pipeline> {
+    /// Create or replace a node pool
+    pub async fn apply_node_pool(&self, pool: &NodePool) -> CommandResult<NodePool> {
+        let path = format!("/v1/nodepools/{}", pool.metadata.name);
         let resp = self
-            .build_request(reqwest::Method::POST, "/v1/pipelines")
-            .json(pipeline)
+            .build_request(reqwest::Method::PUT, &path)
+            .await?
+            .json(pool)
             .send()
             .await?;
 
@@ -257,44 +1226,35 @@ impl ControlPlaneClient {
             return Err(CommandError::Server(error.to_string()));
         }
 
-        let pipeline: Pipeline = serde_json::from_value(body["pipeline"].clone())?;
-        Ok(pipeline)
+        Ok(serde_json::from_value(body["pool"].clone())?)
     }
 
-    /// List pipelines
-    pub async fn list_pipelines(&self, namespace: Option<&str>) -> CommandResult<Vec<Pipeline>> {
-        let path = match namespace {
-            Some(ns) => format!("/v1/namespaces/{}/pipelines", ns),
-            None => "/v1/pipelines".to_string(),
-        };
-
+    /// List node pools
+    pub async fn list_node_pools(&self) -> CommandResult<Vec<NodePool>> {
         let resp = self
-            .build_request(reqwest::Method::GET, &path)
+            .build_request(reqwest::Method::GET, "/v1/nodepools")
+            .await?
             .send()
             .await?;
 
         if !resp.status().is_success() {
             return Err(CommandError::Server(format!(
-                "Failed to list pipelines: {}",
+                "Failed to list node pools: {}",
                 resp.status()
             )));
         }
 
         let body: serde_json::Value = resp.json().await?;
-        let pipelines: Vec<Pipeline> = serde_json::from_value(body["items"].clone())?;
-        Ok(pipelines)
+        Ok(serde_json::from_value(body["items"].clone())?)
     }
 
-    /// Get a specific pipeline
-    pub async fn get_pipeline(
-        &self,
-        namespace: &str,
-        name: &str,
-    ) -> CommandResult<Option<Pipeline>> {
-        let path = format!("/v1/namespaces/{}/pipelines/{}", namespace, name);
+    /// Get a specific node pool
+    pub async fn get_node_pool(&self, name: &str) -> CommandResult<Option<NodePool>> {
+        let path = format!("/v1/nodepools/{}", name);
 
         let resp = self
             .build_request(reqwest::Method::GET, &path)
+            .await?
             .send()
             .await?;
 
@@ -304,7 +1264,7 @@ impl ControlPlaneClient {
 
         if !resp.status().is_success() {
             return Err(CommandError::Server(format!(
-                "Failed to get pipeline: {}",
+                "Failed to get node pool: {}",
                 resp.status()
             )));
         }
@@ -312,30 +1272,36 @@ impl ControlPlaneClient {
         Ok(resp.json().await?)
     }
 
-    /// Delete a pipeline
-    pub async fn delete_pipeline(&self, namespace: &str, name: &str) -> CommandResult<bool> {
-        let path = format!("/v1/namespaces/{}/pipelines/{}", namespace, name);
+    /// Delete a node pool; refused while pipelines target it
+    pub async fn delete_node_pool(&self, name: &str) -> CommandResult<bool> {
+        let path = format!("/v1/nodepools/{}", name);
 
         let resp = self
             .build_request(reqwest::Method::DELETE, &path)
+            .await?
             .send()
             .await?;
 
-        Ok(resp.status().is_success())
-    }
+        if resp.status().as_u16() == 404 {
+            return Ok(false);
+        }
 
-    /// Scale a pipeline
-    pub async fn scale_pipeline(
-        &self,
-        namespace: &str,
-        name: &str,
-        replicas: u32,
-    ) -> CommandResult<Pipeline> {
-        let path = format!("/v1/namespaces/{}/pipelines/{}/scale", namespace, name);
+        let status = resp.status();
+        if !status.is_success() {
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+        Ok(true)
+    }
 
+    /// Register or replace a region
+    pub async fn apply_region(&self, region: &Region) -> CommandResult<Region> {
+        let path = format!("/v1/regions/{}", region.metadata.name);
         let resp = self
-            .build_request(reqwest::Method::PATCH, &path)
-            .json(&serde_json::json!({ "replicas": replicas }))
+            .build_request(reqwest::Method::PUT, &path)
+            .await?
+            .json(region)
             .send()
             .await?;
 
@@ -347,45 +1313,56 @@ impl ControlPlaneClient {
             return Err(CommandError::Server(error.to_string()));
         }
 
-        let pipeline: Pipeline = serde_json::from_value(body["pipeline"].clone())?;
-        Ok(pipeline)
+        Ok(serde_json::from_value(body["region"].clone())?)
     }
 
-    /// List nodes
-    pub async fn list_nodes(&self) -> CommandResult<Vec<serde_json::Value>> {
+    /// List regions
+    pub async fn list_regions(&self) -> CommandResult<Vec<Region>> {
         let resp = self
-            .build_request(reqwest::Method::GET, "/v1/nodes")
+            .build_request(reqwest::Method::GET, "/v1/regions")
+            .await?
             .send()
             .await?;
 
         if !resp.status().is_success() {
             return Err(CommandError::Server(format!(
-                "Failed to list nodes: {}",
+                "Failed to list regions: {}",
                 resp.status()
             )));
         }
 
         let body: serde_json::Value = resp.json().await?;
-        let nodes: Vec<serde_json::Value> = serde_json::from_value(body["items"].clone())?;
-        Ok(nodes)
+        Ok(serde_json::from_value(body["items"].clone())?)
     }
 
-    /// Delete a node
-    pub async fn delete_node(&self, name: &str) -> CommandResult<bool> {
-        let path = format!("/v1/nodes/{}", name);
+    /// Delete a region; refused while pipelines are delegated to it
+    pub async fn delete_region(&self, name: &str) -> CommandResult<bool> {
+        let path = format!("/v1/regions/{}", name);
 
         let resp = self
             .build_request(reqwest::Method::DELETE, &path)
+            .await?
             .send()
             .await?;
 
-        Ok(resp.status().is_success())
+        if resp.status().as_u16() == 404 {
+            return Ok(false);
+        }
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            let error = body["error"].as_str().unwrap_or("Unknown error");
+            return Err(CommandError::Server(error.to_string()));
+        }
+        Ok(true)
     }
 
     /// List namespaces
     pub async fn list_namespaces(&self) -> CommandResult<Vec<serde_json::Value>> {
         let resp = self
             .build_request(reqwest::Method::GET, "/v1/namespaces")
+            .await?
             .send()
             .await?;
 
@@ -401,6 +1378,58 @@ impl ControlPlaneClient {
         Ok(namespaces)
     }
 
+    /// Read the requests workers logged, oldest first
+    pub async fn list_request_logs(
+        &self,
+        query: &RequestLogQuery,
+    ) -> CommandResult<Vec<RequestLog>> {
+        let mut params = vec![("failed", query.failed.to_string())];
+        if let Some(source) = &query.source {
+            params.push(("source", source.clone()));
+        }
+        if let Some(limit) = query.limit {
+            params.push(("limit", limit.to_string()));
+        }
+
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/requestlogs")
+            .await?
+            .query(&params)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to list request logs: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        let logs: Vec<RequestLog> = serde_json::from_value(body["items"].clone())?;
+        Ok(logs)
+    }
+
+    /// List recent cluster events, most recent last
+    pub async fn list_events(&self) -> CommandResult<Vec<ClusterEvent>> {
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/events")
+            .await?
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(CommandError::Server(format!(
+                "Failed to list events: {}",
+                resp.status()
+            )));
+        }
+
+        let body: serde_json::Value = resp.json().await?;
+        let events: Vec<ClusterEvent> = serde_json::from_value(body["items"].clone())?;
+        Ok(events)
+    }
+
     /// Stream logs for a pipeline
     /// Returns a Response that can be streamed
     pub async fn stream_logs(
@@ -417,6 +1446,7 @@ impl ControlPlaneClient {
 
         let resp = self
             .build_request(reqwest::Method::GET, &path)
+            .await?
             .send()
             .await?;
 
@@ -527,6 +1557,179 @@ impl WorkerClient {
         Ok(runners)
     }
 
+    /// List the model artifacts cached on the worker's disk
+    pub async fn list_cached_models(&self) -> CommandResult<CachedModelListResponse> {
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/cache/models")
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            return Err(CommandError::Server(format!(
+                "Failed to list cached models ({}): {}",
+                status,
+                body["error"].as_str().unwrap_or("unknown error")
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Evict the least recently used cached models down to `budget` bytes,
+    /// or the worker's own budget
+    pub async fn prune_models(
+        &self,
+        budget: Option<u64>,
+        dry_run: bool,
+    ) -> CommandResult<PruneReport> {
+        let resp = self
+            .build_request(reqwest::Method::POST, "/v1/cache/models/prune")
+            .json(&PruneModelsRequest { budget, dry_run })
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            return Err(CommandError::Server(format!(
+                "Failed to prune cached models ({}): {}",
+                status,
+                body["error"].as_str().unwrap_or("unknown error")
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Fetch the trace of a request the worker's pipeline handled
+    pub async fn request_trace(&self, request_id: &str) -> CommandResult<RequestTrace> {
+        let resp = self
+            .build_request(
+                reqwest::Method::GET,
+                &format!("/v1/requests/{}", request_id),
+            )
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            return Err(CommandError::Server(format!(
+                "Failed to get trace ({}): {}",
+                status,
+                body["error"].as_str().unwrap_or("unknown error")
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// List requests the worker's pipeline failed to answer
+    pub async fn list_dead_letters(&self) -> CommandResult<Vec<DeadLetter>> {
+        let resp = self
+            .build_request(reqwest::Method::GET, "/v1/deadletters")
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            return Err(CommandError::Server(format!(
+                "Failed to list dead letters ({}): {}",
+                status,
+                body["error"].as_str().unwrap_or("unknown error")
+            )));
+        }
+
+        let list: DeadLetterListResponse = resp.json().await?;
+        Ok(list.dead_letters)
+    }
+
+    /// Run a failed request through the worker's pipeline again
+    pub async fn requeue(&self, request_id: &str) -> CommandResult<RequeueResponse> {
+        let resp = self
+            .build_request(
+                reqwest::Method::POST,
+                &format!("/v1/deadletters/{}/requeue", request_id),
+            )
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            return Err(CommandError::Server(format!(
+                "Failed to requeue {} ({}): {}",
+                request_id,
+                status,
+                body["error"].as_str().unwrap_or("unknown error")
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Run a recent request's input through the pipeline again, or through
+    /// a single handler `node`
+    pub async fn replay(
+        &self,
+        request_id: &str,
+        node: Option<&str>,
+    ) -> CommandResult<ReplayResponse> {
+        let mut req = self.build_request(
+            reqwest::Method::POST,
+            &format!("/v1/requests/{}/replay", request_id),
+        );
+        if let Some(node) = node {
+            req = req.query(&[("node", node)]);
+        }
+        let resp = req.send().await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body: serde_json::Value = resp.json().await.unwrap_or_default();
+            return Err(CommandError::Server(format!(
+                "Failed to replay {} ({}): {}",
+                request_id,
+                status,
+                body["error"].as_str().unwrap_or("unknown error")
+            )));
+        }
+
+        Ok(resp.json().await?)
+    }
+
+    /// Stream the output of a model runner
+    pub async fn stream_runner_logs(
+        &self,
+        runner: &str,
+        follow: bool,
+        tail: usize,
+    ) -> CommandResult<reqwest::Response> {
+        let path = format!(
+            "/v1/runners/{}/logs?follow={}&tail={}",
+            runner, follow, tail
+        );
+
+        let resp = self
+            .build_request(reqwest::Method::GET, &path)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(CommandError::Server(format!(
+                "Failed to stream runner logs ({}): {}",
+                status, body
+            )));
+        }
+
+        Ok(resp)
+    }
+
     /// Stream container logs
     pub async fn stream_logs(
         &self,
@@ -612,6 +1815,237 @@ pub async fn check_server_status(addr: &str) -> ServerStatus {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_build_job() {
+        let job = build_job(
+            "nightly",
+            "chat",
+            "prod",
+            Some("https://example.com/p.jsonl"),
+            vec![],
+            8,
+        )
+        .unwrap();
+        assert_eq!(
+            job.spec.input.as_deref(),
+            Some("https://example.com/p.jsonl")
+        );
+        assert_eq!(job.metadata.namespace, "prod");
+        assert_eq!(job.spec.parallelism, 8);
+
+        // Local files are sent inline
+        let path = std::env::temp_dir().join(format!("llmnet-job-{}.jsonl", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "{\"prompt\": \"one\"}\ntwo\n").unwrap();
+        let job = build_job("j", "chat", "default", path.to_str(), vec![], 4).unwrap();
+        assert_eq!(job.spec.prompts, vec!["one", "two"]);
+        assert!(job.spec.input.is_none());
+
+        std::fs::write(&path, "\n").unwrap();
+        assert!(build_job("j", "chat", "default", path.to_str(), vec![], 4).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_build_secret() {
+        let path = std::env::temp_dir().join(format!("llmnet-secret-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "-----BEGIN KEY-----\n").unwrap();
+        let file = format!("cert={}", path.display());
+
+        let secret = build_secret(
+            "openai",
+            "prod",
+            &["key=sk-a=b".to_string()],
+            std::slice::from_ref(&file),
+        )
+        .unwrap();
+        assert_eq!(secret.qualified_name(), "prod/openai");
+        assert_eq!(secret.string_data["key"], "sk-a=b");
+        assert_eq!(secret.string_data["cert"], "-----BEGIN KEY-----\n");
+        std::fs::remove_file(path).unwrap();
+
+        assert!(build_secret("s", "default", &["novalue".to_string()], &[]).is_err());
+        assert!(build_secret("s", "default", &[], &["k=/nonexistent/file".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_metadata_changes() {
+        let changes = [
+            "gpu=a100".to_string(),
+            "tier-".to_string(),
+            "note=".to_string(),
+        ];
+        let patch = parse_metadata_changes(&changes).unwrap();
+        assert_eq!(patch["gpu"], Some("a100".to_string()));
+        assert_eq!(patch["tier"], None);
+        assert_eq!(patch["note"], Some(String::new()));
+
+        assert!(parse_metadata_changes(&["gpu".to_string()]).is_err());
+        assert!(parse_metadata_changes(&["=a100".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_load_deploy_manifest_formats() {
+        let dir = std::env::temp_dir().join(format!("llmnet-deploy-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let values = serde_json::Value::Null;
+
+        // A YAML composition is wrapped like a JSON one
+        let path = dir.join("chat.yaml");
+        std::fs::write(
+            &path,
+            "models: {}\n---\narchitecture:\n  - {name: router, layer: 0, adapter: openai-api}\n  - {name: output, adapter: output}\n",
+        )
+        .unwrap();
+        let pipeline = load_deploy_manifest(&path, "dev", &values).unwrap();
+        assert_eq!(pipeline.metadata.name, "chat");
+        assert_eq!(pipeline.metadata.namespace, "dev");
+
+        let path = dir.join("code.toml");
+        std::fs::write(
+            &path,
+            "[models]\n[[architecture]]\nname = \"router\"\nlayer = 0\nadapter = \"openai-api\"\n[[architecture]]\nname = \"output\"\nadapter = \"output\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            load_deploy_manifest(&path, "dev", &values)
+                .unwrap()
+                .metadata
+                .name,
+            "code"
+        );
+
+        // YAML pipeline manifests still load as manifests
+        let manifest = serde_yaml::to_string(&pipeline.clone().with_replicas(3)).unwrap();
+        let path = dir.join("manifest.yaml");
+        std::fs::write(&path, manifest).unwrap();
+        assert_eq!(
+            load_deploy_manifest(&path, "default", &values)
+                .unwrap()
+                .spec
+                .replicas,
+            3
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_virtual_endpoint_manifest() {
+        let dir = std::env::temp_dir().join(format!("llmnet-endpoint-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("chat.yaml");
+        std::fs::write(
+            &path,
+            "apiVersion: llmnet/v1\nkind: VirtualEndpoint\nmetadata:\n  name: chat\nspec:\n  backends:\n    - pipeline: chat-v1\n      weight: {{ .values.stable }}\n",
+        )
+        .unwrap();
+        let endpoint = load_virtual_endpoint_manifest(&path, &serde_json::json!({"stable": 90}))
+            .unwrap()
+            .unwrap();
+        assert_eq!(endpoint.metadata.namespace, "default");
+        assert_eq!(endpoint.spec.backends[0].weight, 90);
+
+        let path = dir.join("gpu-pool.yaml");
+        std::fs::write(
+            &path,
+            "apiVersion: llmnet/v1\nkind: NodePool\nmetadata:\n  name: gpu-pool\nspec:\n  nodeSelector:\n    accelerator: nvidia\n  maxPipelines: 8\n",
+        )
+        .unwrap();
+        let pool = load_node_pool_manifest(&path, &serde_json::Value::Null)
+            .unwrap()
+            .unwrap();
+        assert_eq!(pool.name(), "gpu-pool");
+        assert_eq!(pool.spec.max_pipelines, Some(8));
+
+        let path = dir.join("eu-west.yaml");
+        std::fs::write(
+            &path,
+            "apiVersion: llmnet/v1\nkind: Region\nmetadata:\n  name: eu-west\nspec:\n  url: http://cp.eu:8181\n",
+        )
+        .unwrap();
+        let region = load_region_manifest(&path, &serde_json::Value::Null)
+            .unwrap()
+            .unwrap();
+        assert_eq!(region.spec.url, "http://cp.eu:8181");
+        assert!(
+            load_virtual_endpoint_manifest(&path, &serde_json::Value::Null)
+                .unwrap()
+                .is_none()
+        );
+
+        // Pipelines and compositions are left for load_deploy_manifest
+        let path = dir.join("chat.json");
+        std::fs::write(&path, r#"{"models": {}, "architecture": []}"#).unwrap();
+        assert!(
+            load_virtual_endpoint_manifest(&path, &serde_json::Value::Null)
+                .unwrap()
+                .is_none()
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_docker_limit_warnings() {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {
+                    "big": {
+                        "runner": "docker",
+                        "source": "org/model",
+                        "docker": {"image": "vllm/vllm-openai", "cpus": 64, "memory": "8g"}
+                    },
+                    "gpt": {"type": "external", "interface": "openai-api", "url": "http://a"}
+                },
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let host = HostCapacity {
+            cpus: 16,
+            memory: 64 * 1024 * 1024 * 1024,
+            gpus: None,
+        };
+        let warnings = docker_limit_warnings(&composition, &host);
+        assert_eq!(
+            warnings,
+            vec!["Model 'big': cpus limit 64 exceeds the host's 16 cores".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_image_input_warnings() {
+        let composition = |vision: bool| {
+            Composition::from_str(&format!(
+                r#"{{
+                    "models": {{
+                        "small": {{"runner": "external", "endpoint": "http://a"}},
+                        "seer": {{"runner": "external", "endpoint": "http://b", "vision": {vision}}}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "small", "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "chat", "layer": 1, "model": "small", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "look", "layer": 1, "model": "seer", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(
+            image_input_warnings(&composition(true)),
+            vec![
+                "Node 'chat': model 'small' doesn't accept image input; images are dropped before reaching it"
+                    .to_string()
+            ]
+        );
+        assert!(image_input_warnings(&composition(false)).is_empty());
+    }
+
     #[test]
     fn test_context_list() {
         let config = Config::default();
@@ -624,7 +2058,7 @@ mod tests {
     #[test]
     fn test_context_add_and_list() {
         let mut config = Config::default();
-        context_add(&mut config, "test", "http://localhost:8181", None).unwrap();
+        context_add(&mut config, "test", "http://localhost:8181", None, None).unwrap();
 
         let contexts = context_list(&config);
         assert!(contexts.iter().any(|c| c.name == "test"));
@@ -633,7 +2067,7 @@ mod tests {
     #[test]
     fn test_context_use() {
         let mut config = Config::default();
-        context_add(&mut config, "test", "http://localhost:8181", None).unwrap();
+        context_add(&mut config, "test", "http://localhost:8181", None, None).unwrap();
         context_use(&mut config, "test").unwrap();
 
         let (current, _) = context_current(&config).unwrap();
@@ -643,7 +2077,7 @@ mod tests {
     #[test]
     fn test_context_delete() {
         let mut config = Config::default();
-        context_add(&mut config, "test", "http://localhost:8181", None).unwrap();
+        context_add(&mut config, "test", "http://localhost:8181", None, None).unwrap();
 
         let removed = context_delete(&mut config, "test").unwrap();
         assert!(removed);
@@ -652,10 +2086,30 @@ mod tests {
         assert!(!contexts.iter().any(|c| c.name == "test"));
     }
 
+    #[test]
+    fn test_context_set_namespace() {
+        let mut config = Config::default();
+        context_add(&mut config, "test", "http://localhost:8181", None, None).unwrap();
+
+        let name = context_set_namespace(&mut config, None, Some("team-a".to_string())).unwrap();
+        assert_eq!(name, "local");
+        assert_eq!(config.current_namespace(), "team-a");
+
+        context_set_namespace(&mut config, Some("test"), Some("prod".to_string())).unwrap();
+        let contexts = context_list(&config);
+        let test = contexts.iter().find(|c| c.name == "test").unwrap();
+        assert_eq!(test.namespace.as_deref(), Some("prod"));
+
+        assert!(context_set_namespace(&mut config, Some("missing"), None).is_err());
+    }
+
     #[test]
     fn test_validation_result() {
         // Test with a non-existent file
-        let result = validate_composition(&PathBuf::from("/nonexistent/file.json"));
+        let result = validate_composition(
+            &PathBuf::from("/nonexistent/file.json"),
+            &serde_json::Value::Null,
+        );
         // Should return an error or invalid result
         assert!(result.is_err() || !result.unwrap().valid);
     }
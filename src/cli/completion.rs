@@ -0,0 +1,241 @@
+//! Shell completions and man pages
+//!
+//! SBIO pattern: candidate lists are pure functions of the context config;
+//! the completers and generators below read the config or write output.
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use clap::{Command, CommandFactory, ValueEnum};
+use clap_complete::env::EnvCompleter;
+use clap_complete::CompletionCandidate;
+
+use super::commands::ControlPlaneClient;
+use super::Cli;
+use crate::context::{self, Config};
+
+/// Environment variable the shell sets when asking llmnet for completions
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Contexts that exist without being added to the config
+const BUILTIN_CONTEXTS: [&str; 2] = ["local", "worker"];
+
+/// Shells `llmnet completion` can generate scripts for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(name = "powershell")]
+    PowerShell,
+}
+
+// ============================================================================
+// SBIO: Pure business logic (no I/O)
+// ============================================================================
+
+/// Context names to offer: the built-in ones plus everything in the config
+pub fn context_candidates(config: &Config) -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_CONTEXTS.iter().map(|s| s.to_string()).collect();
+    names.extend(context::list_contexts(config).into_iter().map(String::from));
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Namespaces to offer for `-n/--namespace`: "default", those the config's
+/// contexts use, and `cluster`, the namespaces the cluster has pipelines in
+pub fn namespace_candidates(config: &Config, cluster: &[String]) -> Vec<String> {
+    let mut names = vec!["default".to_string()];
+    names.extend(
+        config
+            .contexts
+            .values()
+            .filter_map(|c| c.namespace.clone())
+            .chain(config.local.namespace.clone())
+            .chain(config.worker.namespace.clone()),
+    );
+    names.extend(cluster.iter().cloned());
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Write the script that hooks `shell` up to llmnet's completions
+///
+/// The dynamic script calls back into `bin` on every completion, so context
+/// names and namespaces stay current; the static one only knows the
+/// subcommands and flags.
+pub fn write_completion_script(
+    shell: CompletionShell,
+    dynamic: bool,
+    bin: &str,
+    buf: &mut dyn Write,
+) -> io::Result<()> {
+    let mut cmd = Cli::command();
+
+    if dynamic {
+        let completer: &dyn EnvCompleter = match shell {
+            CompletionShell::Bash => &clap_complete::env::Bash,
+            CompletionShell::Zsh => &clap_complete::env::Zsh,
+            CompletionShell::Fish => &clap_complete::env::Fish,
+            CompletionShell::PowerShell => &clap_complete::env::Powershell,
+        };
+        completer.write_registration(COMPLETE_VAR, cmd.get_name(), bin, bin, buf)
+    } else {
+        let generator = match shell {
+            CompletionShell::Bash => clap_complete::Shell::Bash,
+            CompletionShell::Zsh => clap_complete::Shell::Zsh,
+            CompletionShell::Fish => clap_complete::Shell::Fish,
+            CompletionShell::PowerShell => clap_complete::Shell::PowerShell,
+        };
+        clap_complete::generate(generator, &mut cmd, bin, buf);
+        Ok(())
+    }
+}
+
+/// Render the man page for a command
+pub fn render_man_page(cmd: Command, buf: &mut dyn Write) -> io::Result<()> {
+    clap_mangen::Man::new(cmd).render(buf)
+}
+
+// ============================================================================
+// I/O: Completers and generators
+// ============================================================================
+
+/// Load the context config for completers, which run without parsed arguments
+fn completion_config() -> Config {
+    context::load_config_from(&context::default_config_path()).unwrap_or_default()
+}
+
+/// Completer for context name arguments
+pub fn complete_contexts() -> Vec<CompletionCandidate> {
+    context_candidates(&completion_config())
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// How long completion waits for the cluster's namespaces
+const CLUSTER_LOOKUP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Namespaces the current context's cluster has pipelines in, or none when
+/// it can't be reached in time
+fn cluster_namespaces(config: &Config) -> Vec<String> {
+    let Ok(client) = ControlPlaneClient::from_context(config) else {
+        return Vec::new();
+    };
+    // Completers run synchronously, possibly inside main's runtime, so the
+    // lookup gets a thread and runtime of its own
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .ok()?;
+        runtime.block_on(async {
+            tokio::time::timeout(CLUSTER_LOOKUP_TIMEOUT, client.list_pipelines(None))
+                .await
+                .ok()?
+                .ok()
+        })
+    })
+    .join()
+    .ok()
+    .flatten()
+    .map(|pipelines| {
+        pipelines
+            .into_iter()
+            .map(|p| p.metadata.namespace)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Completer for namespace arguments
+pub fn complete_namespaces() -> Vec<CompletionCandidate> {
+    let config = completion_config();
+    namespace_candidates(&config, &cluster_namespaces(&config))
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Answer a completion request from the shell, if this is one
+///
+/// Exits the process after printing completions; returns normally when
+/// `COMPLETE` isn't set.
+pub fn complete_from_env() {
+    clap_complete::CompleteEnv::with_factory(Cli::command)
+        .var(COMPLETE_VAR)
+        .complete();
+}
+
+/// Write one man page per command into `dir` (llmnet.1, llmnet-deploy.1, ...)
+pub fn write_man_pages(dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    clap_mangen::generate_to(Cli::command(), dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+
+    #[test]
+    fn test_context_candidates_include_builtins() {
+        let mut config = Config::default();
+        context::add_context(&mut config, Context::new("prod", "http://prod:8181"));
+        context::add_context(&mut config, Context::new("local", "http://other:8181"));
+
+        assert_eq!(context_candidates(&config), vec!["local", "prod", "worker"]);
+    }
+
+    #[test]
+    fn test_namespace_candidates_from_contexts() {
+        let mut config = Config::default();
+        let mut prod = Context::new("prod", "http://prod:8181");
+        prod.namespace = Some("billing".to_string());
+        let mut staging = Context::new("staging", "http://staging:8181");
+        staging.namespace = Some("support".to_string());
+        context::add_context(&mut config, prod);
+        context::add_context(&mut config, staging);
+        context::add_context(&mut config, Context::new("dev", "http://dev:8181"));
+
+        assert_eq!(
+            namespace_candidates(&config, &[]),
+            vec!["billing", "default", "support"]
+        );
+        assert_eq!(
+            namespace_candidates(&config, &["search".to_string(), "billing".to_string()]),
+            vec!["billing", "default", "search", "support"]
+        );
+    }
+
+    #[test]
+    fn test_static_completion_lists_subcommands() {
+        let mut buf = Vec::new();
+        write_completion_script(CompletionShell::Bash, false, "llmnet", &mut buf).unwrap();
+        let script = String::from_utf8(buf).unwrap();
+        assert!(script.contains("deploy"));
+        assert!(script.contains("completion"));
+    }
+
+    #[test]
+    fn test_dynamic_completion_calls_back_into_binary() {
+        for shell in CompletionShell::value_variants() {
+            let mut buf = Vec::new();
+            write_completion_script(*shell, true, "llmnet", &mut buf).unwrap();
+            let script = String::from_utf8(buf).unwrap();
+            assert!(script.contains(COMPLETE_VAR), "{:?}", shell);
+        }
+    }
+
+    #[test]
+    fn test_man_page_renders() {
+        let mut buf = Vec::new();
+        render_man_page(Cli::command(), &mut buf).unwrap();
+        let page = String::from_utf8(buf).unwrap();
+        assert!(page.contains(".TH llmnet"));
+    }
+}
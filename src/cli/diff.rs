@@ -0,0 +1,251 @@
+//! Structural diff between a local manifest and the live pipeline
+//!
+//! SBIO pattern: Pure functions, the caller fetches the live pipeline
+
+use serde_json::{Map, Value};
+
+use crate::cluster::Pipeline;
+
+/// How a field changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A single field that differs between the live and local pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecChange {
+    /// Dotted path, with architecture nodes addressed by name
+    /// (e.g. `spec.composition.architecture[router].model`)
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// Diff the spec of the live pipeline (if any) against a local manifest
+///
+/// A pipeline that does not exist yet diffs against an empty spec, so every
+/// top-level field shows up as added.
+pub fn diff_pipelines(live: Option<&Pipeline>, local: &Pipeline) -> Vec<SpecChange> {
+    let old = live
+        .map(|p| serde_json::to_value(&p.spec).unwrap_or(Value::Null))
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    let new = serde_json::to_value(&local.spec).unwrap_or(Value::Null);
+
+    let mut changes = Vec::new();
+    diff_values("spec", &old, &new, &mut changes);
+    changes
+}
+
+/// Recursively collect differences between two JSON values
+pub fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<SpecChange>) {
+    if old == new {
+        return;
+    }
+
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            diff_maps(path, old_map, new_map, changes);
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            match (keyed_by_name(old_items), keyed_by_name(new_items)) {
+                (Some(old_nodes), Some(new_nodes)) => {
+                    diff_named(path, &old_nodes, &new_nodes, changes)
+                }
+                _ => changes.push(changed(path, old, new)),
+            }
+        }
+        _ => changes.push(changed(path, old, new)),
+    }
+}
+
+fn diff_maps(
+    path: &str,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    changes: &mut Vec<SpecChange>,
+) {
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        let child = format!("{}.{}", path, key);
+        match (old.get(key), new.get(key)) {
+            (Some(o), Some(n)) => diff_values(&child, o, n, changes),
+            (Some(o), None) => changes.push(removed(&child, o)),
+            (None, Some(n)) => changes.push(added(&child, n)),
+            (None, None) => {}
+        }
+    }
+}
+
+/// Diff arrays of named objects (architecture nodes) by name, not position
+fn diff_named(
+    path: &str,
+    old: &[(&str, &Value)],
+    new: &[(&str, &Value)],
+    changes: &mut Vec<SpecChange>,
+) {
+    for (name, old_item) in old {
+        let child = format!("{}[{}]", path, name);
+        match find(new, name) {
+            Some(new_item) => diff_values(&child, old_item, new_item, changes),
+            None => changes.push(removed(&child, old_item)),
+        }
+    }
+    for (name, new_item) in new {
+        if find(old, name).is_none() {
+            changes.push(added(&format!("{}[{}]", path, name), new_item));
+        }
+    }
+}
+
+fn find<'a>(items: &[(&str, &'a Value)], name: &str) -> Option<&'a Value> {
+    items.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+}
+
+/// Name each element of an array, if every element is an object with a name
+fn keyed_by_name(items: &[Value]) -> Option<Vec<(&str, &Value)>> {
+    items
+        .iter()
+        .map(|item| item.get("name").and_then(|n| n.as_str()).map(|n| (n, item)))
+        .collect()
+}
+
+fn added(path: &str, value: &Value) -> SpecChange {
+    SpecChange {
+        path: path.to_string(),
+        kind: ChangeKind::Added,
+        old: None,
+        new: Some(value.clone()),
+    }
+}
+
+fn removed(path: &str, value: &Value) -> SpecChange {
+    SpecChange {
+        path: path.to_string(),
+        kind: ChangeKind::Removed,
+        old: Some(value.clone()),
+        new: None,
+    }
+}
+
+fn changed(path: &str, old: &Value, new: &Value) -> SpecChange {
+    SpecChange {
+        path: path.to_string(),
+        kind: ChangeKind::Changed,
+        old: Some(old.clone()),
+        new: Some(new.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Composition;
+    use serde_json::json;
+
+    fn pipeline(architecture: &str, replicas: u32) -> Pipeline {
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "gpt": {{"type": "external", "interface": "openai-api", "url": "http://a"}}
+                }},
+                "architecture": {}
+            }}"#,
+            architecture
+        );
+        Pipeline::new("bot", Composition::from_str(&json).unwrap()).with_replicas(replicas)
+    }
+
+    const TWO_NODES: &str = r#"[
+        {"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": ["output"]},
+        {"name": "output", "adapter": "output"}
+    ]"#;
+
+    #[test]
+    fn test_identical_pipelines_have_no_changes() {
+        let local = pipeline(TWO_NODES, 1);
+        let live = pipeline(TWO_NODES, 1);
+        assert!(diff_pipelines(Some(&live), &local).is_empty());
+    }
+
+    #[test]
+    fn test_diff_replicas_and_nodes() {
+        let live = pipeline(TWO_NODES, 1);
+        let local = pipeline(
+            r#"[
+                {"name": "output", "adapter": "output"},
+                {"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": [1]},
+                {"name": "coder", "layer": 1, "model": "gpt", "adapter": "openai-api", "output-to": ["output"]}
+            ]"#,
+            3,
+        );
+
+        let changes = diff_pipelines(Some(&live), &local);
+        let summary: Vec<(&str, ChangeKind)> =
+            changes.iter().map(|c| (c.path.as_str(), c.kind)).collect();
+
+        // Reordering nodes is not a change; only real edits show up
+        assert!(summary.contains(&(
+            "spec.composition.architecture[router].output-to",
+            ChangeKind::Changed
+        )));
+        assert!(summary.contains(&("spec.composition.architecture[coder]", ChangeKind::Added)));
+        assert!(summary.contains(&("spec.replicas", ChangeKind::Changed)));
+        assert_eq!(changes.len(), 3);
+
+        let replicas = changes.iter().find(|c| c.path == "spec.replicas").unwrap();
+        assert_eq!(replicas.old, Some(json!(1)));
+        assert_eq!(replicas.new, Some(json!(3)));
+    }
+
+    #[test]
+    fn test_diff_against_missing_pipeline() {
+        let local = pipeline(TWO_NODES, 2);
+        let changes = diff_pipelines(None, &local);
+
+        assert!(!changes.is_empty());
+        assert!(changes.iter().all(|c| c.kind == ChangeKind::Added));
+        assert!(changes.iter().any(|c| c.path == "spec.composition"));
+    }
+
+    #[test]
+    fn test_diff_values_maps_and_plain_arrays() {
+        let mut changes = Vec::new();
+        diff_values(
+            "m",
+            &json!({"a": 1, "gone": true, "list": [1, 2]}),
+            &json!({"a": 1, "new": "x", "list": [2, 1]}),
+            &mut changes,
+        );
+
+        assert_eq!(
+            changes,
+            vec![
+                SpecChange {
+                    path: "m.gone".to_string(),
+                    kind: ChangeKind::Removed,
+                    old: Some(json!(true)),
+                    new: None,
+                },
+                SpecChange {
+                    path: "m.list".to_string(),
+                    kind: ChangeKind::Changed,
+                    old: Some(json!([1, 2])),
+                    new: Some(json!([2, 1])),
+                },
+                SpecChange {
+                    path: "m.new".to_string(),
+                    kind: ChangeKind::Added,
+                    old: None,
+                    new: Some(json!("x")),
+                },
+            ]
+        );
+    }
+}
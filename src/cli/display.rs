@@ -3,8 +3,17 @@
 //! SBIO pattern: Pure functions that format data for display
 
 use super::commands::{ContextInfo, ValidationResult};
-use crate::cluster::Pipeline;
+use super::diff::{ChangeKind, SpecChange};
+use super::preflight::ClusterCheck;
+use super::PipelineOutput;
+use crate::cluster::{
+    Alert, AlertState, Job, JobResult, NodePool, Pipeline, Region, ScoringWeights, Secret,
+    VirtualEndpoint,
+};
 use crate::config::Composition;
+use crate::runtime::docker::format_memory_size;
+use crate::runtime::{CachedArtifact, DeadLetter, PruneReport, RequestLog, RequestTrace};
+use crate::server::handlers::CachedModelListResponse;
 
 // ============================================================================
 // Table formatting helpers
@@ -65,7 +74,7 @@ pub fn format_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
 
 /// Format context list for display
 pub fn format_context_list(contexts: &[ContextInfo]) -> String {
-    let headers = &["", "NAME", "URL"];
+    let headers = &["", "NAME", "URL", "NAMESPACE"];
     let rows: Vec<Vec<String>> = contexts
         .iter()
         .map(|ctx| {
@@ -73,6 +82,7 @@ pub fn format_context_list(contexts: &[ContextInfo]) -> String {
                 if ctx.is_current { "*" } else { " " }.to_string(),
                 ctx.name.clone(),
                 ctx.url.clone(),
+                ctx.namespace.clone().unwrap_or_else(|| "-".to_string()),
             ]
         })
         .collect();
@@ -90,8 +100,14 @@ pub fn format_current_context(name: &str, url: &str) -> String {
 // ============================================================================
 
 /// Format pipeline list for display
+///
+/// A REGION column is added when any pipeline is delegated to a region.
 pub fn format_pipeline_list(pipelines: &[Pipeline]) -> String {
-    let headers = &["NAMESPACE", "NAME", "REPLICAS", "READY", "STATUS"];
+    let federated = pipelines.iter().any(|p| p.region().is_some());
+    let mut headers = vec!["NAMESPACE", "NAME", "REPLICAS", "READY", "STATUS"];
+    if federated {
+        headers.push("REGION");
+    }
     let rows: Vec<Vec<String>> = pipelines
         .iter()
         .map(|p| {
@@ -103,23 +119,47 @@ pub fn format_pipeline_list(pipelines: &[Pipeline]) -> String {
 
             let status = if p.is_ready() {
                 "Running"
+            } else if p.blocked_reason().is_some() {
+                "Blocked"
             } else if p.status.is_some() {
                 "Pending"
             } else {
                 "Unknown"
             };
 
-            vec![
+            let mut row = vec![
                 p.metadata.namespace.clone(),
                 p.metadata.name.clone(),
                 p.spec.replicas.to_string(),
                 format!("{}/{}", ready, total),
                 status.to_string(),
-            ]
+            ];
+            if federated {
+                row.push(p.region().unwrap_or("-").to_string());
+            }
+            row
         })
         .collect();
 
-    format_table(headers, rows)
+    format_table(&headers, rows)
+}
+
+/// Print a pipeline in an output format; `composition` prints only the
+/// composition, in the format `llmnet deploy` and `llmnet run` read
+pub fn format_pipeline_output(
+    pipeline: &Pipeline,
+    output: PipelineOutput,
+) -> Result<String, String> {
+    let text = match output {
+        PipelineOutput::Json => {
+            serde_json::to_string_pretty(pipeline).map_err(|e| e.to_string())?
+        }
+        PipelineOutput::Yaml => return serde_yaml::to_string(pipeline).map_err(|e| e.to_string()),
+        PipelineOutput::Composition => {
+            serde_json::to_string_pretty(&pipeline.spec.composition).map_err(|e| e.to_string())?
+        }
+    };
+    Ok(text + "\n")
 }
 
 /// Format a single pipeline for detailed display
@@ -136,6 +176,9 @@ pub fn format_pipeline_detail(pipeline: &Pipeline) -> String {
 
     output.push_str(&format!("Replicas:   {}\n", pipeline.spec.replicas));
     output.push_str(&format!("Port:       {}\n", pipeline.spec.port));
+    if let Some(hash) = pipeline.stored_composition_hash() {
+        output.push_str(&format!("Version:    {}\n", hash));
+    }
 
     if !pipeline.metadata.labels.is_empty() {
         output.push_str("Labels:\n");
@@ -144,8 +187,23 @@ pub fn format_pipeline_detail(pipeline: &Pipeline) -> String {
         }
     }
 
+    if !pipeline.spec.depends_on.is_empty() {
+        output.push_str("Depends On:\n");
+        for dependency in &pipeline.spec.depends_on {
+            if let Some(name) = &dependency.pipeline {
+                output.push_str(&format!("  pipeline/{}\n", name));
+            }
+            if let Some(name) = &dependency.function {
+                output.push_str(&format!("  function/{}\n", name));
+            }
+        }
+    }
+
     if let Some(status) = &pipeline.status {
         output.push_str("\nStatus:\n");
+        if let Some(reason) = pipeline.blocked_reason() {
+            output.push_str(&format!("  Blocked:              {}\n", reason));
+        }
         output.push_str(&format!(
             "  Ready Replicas:       {}\n",
             status.ready_replicas
@@ -158,6 +216,23 @@ pub fn format_pipeline_detail(pipeline: &Pipeline) -> String {
             "  Unavailable Replicas: {}\n",
             status.unavailable_replicas
         ));
+        if let Some(hash) = &status.composition_hash {
+            output.push_str(&format!("  Observed Version:     {}\n", hash));
+        }
+
+        if !status.replica_statuses.is_empty() {
+            output.push_str("  Replicas:\n");
+            for replica in &status.replica_statuses {
+                output.push_str(&format!(
+                    "    - {}:{} {:?} (restarts: {})",
+                    replica.node, replica.port, replica.status, replica.restarts
+                ));
+                if let Some(reason) = &replica.reason {
+                    output.push_str(&format!(": {}", reason));
+                }
+                output.push('\n');
+            }
+        }
 
         if !status.endpoints.is_empty() {
             output.push_str("  Endpoints:\n");
@@ -166,6 +241,24 @@ pub fn format_pipeline_detail(pipeline: &Pipeline) -> String {
             }
         }
 
+        if let Some(rollout) = &status.rollout {
+            output.push_str(&format!(
+                "  Rollout:              {:?} ({}% to canary)\n",
+                rollout.phase, rollout.weight
+            ));
+            output.push_str(&format!(
+                "    Stable:             {} requests, {} errors\n",
+                rollout.stable.requests, rollout.stable.errors
+            ));
+            output.push_str(&format!(
+                "    Canary:             {} requests, {} errors\n",
+                rollout.canary.requests, rollout.canary.errors
+            ));
+            if !rollout.message.is_empty() {
+                output.push_str(&format!("    Message:            {}\n", rollout.message));
+            }
+        }
+
         if !status.conditions.is_empty() {
             output.push_str("  Conditions:\n");
             for cond in &status.conditions {
@@ -190,6 +283,226 @@ pub fn format_pipeline_detail(pipeline: &Pipeline) -> String {
     output
 }
 
+// ============================================================================
+// Job display
+// ============================================================================
+
+/// Format job list for display
+pub fn format_job_list(jobs: &[Job]) -> String {
+    let headers = &[
+        "NAMESPACE",
+        "NAME",
+        "PIPELINE",
+        "PROGRESS",
+        "FAILED",
+        "STATUS",
+    ];
+    let rows: Vec<Vec<String>> = jobs
+        .iter()
+        .map(|j| {
+            let (done, failed, total) = j
+                .status
+                .as_ref()
+                .map(|s| (s.completed + s.failed, s.failed, s.total))
+                .unwrap_or((0, 0, 0));
+            // The total is only known once the input has been read
+            let progress = if total == 0 && done == 0 {
+                "-".to_string()
+            } else {
+                format!("{}/{}", done, total)
+            };
+
+            vec![
+                j.metadata.namespace.clone(),
+                j.metadata.name.clone(),
+                j.spec.pipeline.clone(),
+                progress,
+                failed.to_string(),
+                j.phase().to_string(),
+            ]
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
+/// Format a list of virtual endpoints, one row per backend with its
+/// traffic so far
+pub fn format_virtual_endpoint_list(endpoints: &[VirtualEndpoint]) -> String {
+    let headers = &[
+        "NAMESPACE",
+        "NAME",
+        "BACKEND",
+        "WEIGHT",
+        "REQUESTS",
+        "ERRORS",
+        "AVG LATENCY",
+    ];
+    let rows: Vec<Vec<String>> = endpoints
+        .iter()
+        .flat_map(|e| {
+            e.spec.backends.iter().map(move |backend| {
+                let stats = e
+                    .status
+                    .as_ref()
+                    .and_then(|s| s.variants.get(&backend.pipeline))
+                    .copied()
+                    .unwrap_or_default();
+                let latency = if stats.requests == 0 {
+                    "-".to_string()
+                } else {
+                    format!("{:.0}ms", stats.mean_latency_ms())
+                };
+
+                vec![
+                    e.metadata.namespace.clone(),
+                    e.metadata.name.clone(),
+                    backend.pipeline.clone(),
+                    backend.weight.to_string(),
+                    stats.requests.to_string(),
+                    stats.errors.to_string(),
+                    latency,
+                ]
+            })
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
+/// Format a list of node pools with their members and load
+pub fn format_node_pool_list(pools: &[NodePool]) -> String {
+    let headers = &["NAME", "NODES", "PIPELINES", "NAMESPACES", "SELECTOR"];
+    let rows: Vec<Vec<String>> = pools
+        .iter()
+        .map(|p| {
+            let status = p.status.clone().unwrap_or_default();
+            let pipelines = match p.spec.max_pipelines {
+                Some(max) => format!("{}/{}", status.pipelines, max),
+                None => status.pipelines.to_string(),
+            };
+            let namespaces = if p.spec.allowed_namespaces.is_empty() {
+                "*".to_string()
+            } else {
+                p.spec.allowed_namespaces.join(",")
+            };
+            let mut selector: Vec<String> = p
+                .spec
+                .node_selector
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            selector.sort();
+            vec![
+                p.name().to_string(),
+                status.nodes.len().to_string(),
+                pipelines,
+                namespaces,
+                if selector.is_empty() {
+                    "<all>".to_string()
+                } else {
+                    selector.join(",")
+                },
+            ]
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
+/// Format a list of regions with the outcome of their last sync
+pub fn format_region_list(regions: &[Region]) -> String {
+    let headers = &["NAME", "URL", "REACHABLE", "PIPELINES", "LAST SYNC"];
+    let rows: Vec<Vec<String>> = regions
+        .iter()
+        .map(|r| {
+            let status = r.status.clone().unwrap_or_default();
+            let reachable = match (&r.status, status.reachable) {
+                (None, _) => "Unknown",
+                (Some(_), true) => "True",
+                (Some(_), false) => "False",
+            };
+            vec![
+                r.name().to_string(),
+                r.spec.url.clone(),
+                reachable.to_string(),
+                status.pipelines.to_string(),
+                status
+                    .last_sync
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
+/// Format broken alert rules, firing ones first
+pub fn format_alert_list(alerts: &[Alert]) -> String {
+    let headers = &["RULE", "OBJECT", "SEVERITY", "STATE", "SINCE", "MESSAGE"];
+    let mut alerts: Vec<&Alert> = alerts.iter().collect();
+    alerts.sort_by_key(|a| (a.state != AlertState::Firing, a.since));
+    let rows: Vec<Vec<String>> = alerts
+        .iter()
+        .map(|a| {
+            vec![
+                a.rule.clone(),
+                a.object.clone(),
+                a.severity.as_str().to_string(),
+                format!("{:?}", a.state),
+                a.since.format("%Y-%m-%d %H:%M:%S").to_string(),
+                truncate_str(&a.message, 60),
+            ]
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
+/// Format a list of secrets; only key names are shown, never values
+pub fn format_secret_list(secrets: &[Secret]) -> String {
+    let headers = &["NAMESPACE", "NAME", "TYPE", "KEYS"];
+    let rows: Vec<Vec<String>> = secrets
+        .iter()
+        .map(|s| {
+            vec![
+                s.metadata.namespace.clone(),
+                s.metadata.name.clone(),
+                s.secret_type.clone(),
+                s.keys().join(","),
+            ]
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
+/// Format the results of a job, one block per prompt
+pub fn format_job_results(results: &[JobResult]) -> String {
+    if results.is_empty() {
+        return "No results yet.\n".to_string();
+    }
+
+    let mut output = String::new();
+    for (i, result) in results.iter().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+        output.push_str(&format!("[{}] {}\n", result.index + 1, result.prompt));
+        let (label, text) = match (&result.output, &result.error) {
+            (_, Some(error)) => ("error: ", error.as_str()),
+            (Some(answer), None) => ("", answer.as_str()),
+            (None, None) => ("", ""),
+        };
+        for (n, line) in text.lines().enumerate() {
+            let label = if n == 0 { label } else { "" };
+            output.push_str(&format!("    {}{}\n", label, line));
+        }
+    }
+    output
+}
+
 // ============================================================================
 // Node display
 // ============================================================================
@@ -239,6 +552,24 @@ pub fn format_namespace_list(namespaces: &[serde_json::Value]) -> String {
     format_table(headers, rows)
 }
 
+/// Format node scoring weights for display
+pub fn format_scoring_weights(weights: &ScoringWeights) -> String {
+    let headers = &["RESOURCE", "WEIGHT"];
+    let rows = [
+        ("cpu", weights.cpu),
+        ("memory", weights.memory),
+        ("gpu", weights.gpu),
+        ("disk", weights.disk),
+        ("load", weights.load),
+        ("latency", weights.latency),
+    ]
+    .iter()
+    .map(|(name, weight)| vec![name.to_string(), format!("{:.2}", weight)])
+    .collect();
+
+    format_table(headers, rows)
+}
+
 // ============================================================================
 // Worker resource display (containers, runners)
 // ============================================================================
@@ -269,6 +600,64 @@ pub fn format_runner_list(runners: &[serde_json::Value]) -> String {
     format_table(headers, rows)
 }
 
+/// Format the models cached on a worker, least recently used first
+pub fn format_cached_model_list(list: &CachedModelListResponse) -> String {
+    let mut output = format_artifact_table(&list.models);
+    if !list.models.is_empty() {
+        output.push_str(&format!("\nTotal: {}", format_memory_size(list.total)));
+        if let Some(budget) = list.budget {
+            output.push_str(&format!(" of {} budget", format_memory_size(budget)));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Format what `llmnet prune models` evicted
+pub fn format_prune_report(report: &PruneReport) -> String {
+    if report.evicted.is_empty() {
+        return format!(
+            "Cache already fits in {} ({} used), nothing to evict\n",
+            format_memory_size(report.budget),
+            format_memory_size(report.before)
+        );
+    }
+
+    let verb = if report.dry_run {
+        "Would evict"
+    } else {
+        "Evicted"
+    };
+    let mut output = format_artifact_table(&report.evicted);
+    output.push_str(&format!(
+        "\n{} {} artifact(s): {} -> {} (budget {})\n",
+        verb,
+        report.evicted.len(),
+        format_memory_size(report.before),
+        format_memory_size(report.after),
+        format_memory_size(report.budget)
+    ));
+    output
+}
+
+fn format_artifact_table(artifacts: &[CachedArtifact]) -> String {
+    let headers = &["KIND", "NAME", "SIZE", "LAST USED", "PATH"];
+    let rows: Vec<Vec<String>> = artifacts
+        .iter()
+        .map(|a| {
+            vec![
+                a.kind.to_string(),
+                truncate_str(&a.name, 40),
+                format_memory_size(a.size),
+                a.last_used.format("%Y-%m-%d %H:%M:%S").to_string(),
+                a.path.display().to_string(),
+            ]
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
 // ============================================================================
 // Validation display
 // ============================================================================
@@ -281,6 +670,12 @@ pub fn format_validation_result(result: &ValidationResult, path: &str) -> String
         output.push_str(&format!("✓ {} is valid\n\n", path));
         output.push_str(&format!("  Models: {}\n", result.models));
         output.push_str(&format!("  Nodes:  {}\n", result.nodes));
+        if !result.warnings.is_empty() {
+            output.push('\n');
+            for warning in &result.warnings {
+                output.push_str(&format!("  ⚠ {}\n", warning));
+            }
+        }
     } else {
         output.push_str(&format!("✗ {} is invalid\n\n", path));
         if let Some(ref error) = result.error {
@@ -291,6 +686,28 @@ pub fn format_validation_result(result: &ValidationResult, path: &str) -> String
     output
 }
 
+/// Format the result of checking a composition against a cluster
+pub fn format_cluster_check(check: &ClusterCheck, context: &str) -> String {
+    let mut output = String::new();
+
+    if check.problems.is_empty() {
+        output.push_str(&format!("\n✓ Fits cluster {}\n", context));
+    } else {
+        output.push_str(&format!("\n✗ Won't run on cluster {}\n\n", context));
+        for problem in &check.problems {
+            output.push_str(&format!("  ✗ {}\n", problem));
+        }
+    }
+    if !check.warnings.is_empty() {
+        output.push('\n');
+        for warning in &check.warnings {
+            output.push_str(&format!("  ⚠ {}\n", warning));
+        }
+    }
+
+    output
+}
+
 // ============================================================================
 // Cluster status display
 // ============================================================================
@@ -422,15 +839,241 @@ pub fn format_cluster_status(status: &serde_json::Value) -> String {
     output
 }
 
+// ============================================================================
+// Watch mode display
+// ============================================================================
+
+/// ANSI sequence that clears the screen and moves the cursor home
+pub const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// Mark lines that differ from the previous render.
+/// Pure function - the first render (no previous) is returned unchanged.
+pub fn highlight_changes(previous: Option<&str>, current: &str) -> String {
+    let Some(previous) = previous else {
+        return current.to_string();
+    };
+
+    let old_lines: Vec<&str> = previous.lines().collect();
+    let mut output = String::with_capacity(current.len());
+
+    for (i, line) in current.lines().enumerate() {
+        if !line.trim().is_empty() && old_lines.get(i) != Some(&line) {
+            // Bold yellow for lines that changed since the last refresh
+            output.push_str(&format!("\x1b[1;33m{}\x1b[0m\n", line));
+        } else {
+            output.push_str(line);
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+/// Format the banner shown above each watch-mode refresh
+pub fn format_watch_header(interval_secs: u64, context: &str) -> String {
+    format!(
+        "Every {}s: llmnet status ({})    {}    (Ctrl+C to exit)\n",
+        interval_secs,
+        context,
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    )
+}
+
+// ============================================================================
+// Request trace display
+// ============================================================================
+
+/// Format a request trace: summary, then one row per hop
+pub fn format_request_trace(trace: &RequestTrace) -> String {
+    let mut output = String::new();
+
+    output.push_str(&format!("Request:   {}\n", trace.request_id));
+    output.push_str(&format!("Started:   {}\n", trace.started_at));
+    output.push_str(&format!("Duration:  {}ms\n", trace.duration_ms));
+    output.push_str(&format!("Tokens:    {}\n", trace.total_tokens));
+    output.push_str(&format!("Prompt:    {}\n", truncate_str(&trace.prompt, 60)));
+    match (&trace.output, &trace.error) {
+        (_, Some(error)) => output.push_str(&format!("Error:     {}\n", error)),
+        (Some(answer), None) => {
+            output.push_str(&format!("Output:    {}\n", truncate_str(answer, 60)))
+        }
+        (None, None) => {}
+    }
+    output.push('\n');
+
+    let headers = &[
+        "#",
+        "NODE",
+        "LAYER",
+        "LATENCY",
+        "TOKENS",
+        "SCORE",
+        "DOCUMENTS",
+    ];
+    let rows: Vec<Vec<String>> = trace
+        .hops
+        .iter()
+        .enumerate()
+        .map(|(i, hop)| {
+            let tokens = match (hop.prompt_tokens, hop.completion_tokens) {
+                (Some(p), Some(c)) => format!("{}/{}", p, c),
+                _ => "-".to_string(),
+            };
+            vec![
+                (i + 1).to_string(),
+                hop.node.clone(),
+                hop.layer.to_string(),
+                hop.latency_ms
+                    .map(|ms| format!("{}ms", ms))
+                    .unwrap_or_else(|| "-".to_string()),
+                tokens,
+                hop.score
+                    .map(|score| format!("{:.2}", score))
+                    .unwrap_or_else(|| "-".to_string()),
+                if hop.documents.is_empty() {
+                    "-".to_string()
+                } else {
+                    hop.documents.join(",")
+                },
+            ]
+        })
+        .collect();
+
+    output.push_str(&format_table(headers, rows));
+    output
+}
+
+/// Format dead letters for display, oldest first
+pub fn format_dead_letter_list(letters: &[DeadLetter]) -> String {
+    let headers = &["REQUEST ID", "FAILED", "ATTEMPTS", "PROMPT", "ERROR"];
+    let rows: Vec<Vec<String>> = letters
+        .iter()
+        .map(|l| {
+            vec![
+                l.request_id.to_string(),
+                l.failed_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                l.attempts.to_string(),
+                truncate_str(&l.input.prompt, 30),
+                truncate_str(&l.error, 50),
+            ]
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
+/// Format logged requests, oldest first
+pub fn format_request_log_list(logs: &[RequestLog]) -> String {
+    let headers = &[
+        "TIME",
+        "SOURCE",
+        "REQUEST ID",
+        "DURATION",
+        "ROUTE",
+        "PROMPT",
+        "RESULT",
+    ];
+    let rows: Vec<Vec<String>> = logs
+        .iter()
+        .map(|l| {
+            let result = match (&l.error, &l.output) {
+                (Some(error), _) => format!("error: {}", error),
+                (None, Some(output)) => output.clone(),
+                (None, None) => "-".to_string(),
+            };
+            vec![
+                l.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                l.source.clone(),
+                l.request_id.to_string(),
+                format!("{}ms", l.duration_ms),
+                l.route.join(" → "),
+                truncate_str(&l.prompt, 30),
+                truncate_str(&result, 40),
+            ]
+        })
+        .collect();
+
+    format_table(headers, rows)
+}
+
 /// Truncate a string to max length with ellipsis
 fn truncate_str(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    if s.chars().count() <= max_len {
         s.to_string()
     } else {
-        format!("{}…", &s[..max_len - 1])
+        let kept: String = s.chars().take(max_len - 1).collect();
+        format!("{}…", kept)
     }
 }
 
+// ============================================================================
+// Pipeline diff display
+// ============================================================================
+
+/// Format a pipeline diff, one line per changed field.
+/// Pure function - `color` wraps lines in ANSI green/red/yellow.
+pub fn format_pipeline_diff(
+    name: &str,
+    namespace: &str,
+    exists: bool,
+    changes: &[SpecChange],
+    color: bool,
+) -> String {
+    let mut output = format!("pipeline.llmnet/{} ({})", name, namespace);
+    if !exists {
+        output.push_str(" [new]");
+    }
+    output.push('\n');
+    push_changes(&mut output, changes, color);
+    output
+}
+
+/// Format the changes `llmnet edit` is about to apply to a resource
+/// (e.g. "node.llmnet/gpu-1"). Pure function, colored like the pipeline diff.
+pub fn format_edit_diff(resource: &str, changes: &[SpecChange], color: bool) -> String {
+    let mut output = format!("{}\n", resource);
+    push_changes(&mut output, changes, color);
+    output
+}
+
+fn push_changes(output: &mut String, changes: &[SpecChange], color: bool) {
+    if changes.is_empty() {
+        output.push_str("  no changes\n");
+        return;
+    }
+
+    for change in changes {
+        let (sign, code, detail) = match change.kind {
+            ChangeKind::Added => ("+", "32", render_diff_value(change.new.as_ref())),
+            ChangeKind::Removed => ("-", "31", render_diff_value(change.old.as_ref())),
+            ChangeKind::Changed => (
+                "~",
+                "33",
+                format!(
+                    "{} -> {}",
+                    render_diff_value(change.old.as_ref()),
+                    render_diff_value(change.new.as_ref())
+                ),
+            ),
+        };
+        let line = format!("{} {}: {}", sign, change.path, detail);
+        if color {
+            output.push_str(&format!("\x1b[{}m{}\x1b[0m\n", code, line));
+        } else {
+            output.push_str(&line);
+            output.push('\n');
+        }
+    }
+
+    output.push_str(&format!("\n{} change(s)\n", changes.len()));
+}
+
+fn render_diff_value(value: Option<&serde_json::Value>) -> String {
+    value
+        .map(|v| truncate_str(&v.to_string(), 120))
+        .unwrap_or_default()
+}
+
 // ============================================================================
 // Dry-run display (legacy)
 // ============================================================================
@@ -545,6 +1188,385 @@ pub fn format_dry_run(composition: &Composition, args: &RunArgs) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_format_jobs() {
+        use crate::cluster::JobStatus;
+
+        let mut job = Job::new("nightly", "chat", vec!["a".into()]);
+        assert!(format_job_list(&[job.clone()]).contains("-"));
+
+        let mut status = JobStatus::pending();
+        status.phase = crate::cluster::JobPhase::Running;
+        status.total = 10;
+        status.completed = 6;
+        status.failed = 1;
+        job.status = Some(status);
+        let table = format_job_list(&[job]);
+        assert!(table.contains("PROGRESS"));
+        let row = table.lines().nth(1).unwrap();
+        assert!(row.contains("nightly") && row.contains("7/10") && row.contains("Running"));
+
+        let results = vec![
+            JobResult {
+                index: 0,
+                prompt: "Say hi".into(),
+                output: Some("Hi!\nHow can I help?".into()),
+                error: None,
+            },
+            JobResult {
+                index: 1,
+                prompt: "Say bye".into(),
+                output: None,
+                error: Some("HTTP 500".into()),
+            },
+        ];
+        assert_eq!(
+            format_job_results(&results),
+            "[1] Say hi\n    Hi!\n    How can I help?\n\n[2] Say bye\n    error: HTTP 500\n"
+        );
+        assert_eq!(format_job_results(&[]), "No results yet.\n");
+    }
+
+    #[test]
+    fn test_format_virtual_endpoints() {
+        use crate::cluster::{EndpointBackend, VariantStats, VirtualEndpointStatus};
+
+        let mut endpoint = VirtualEndpoint::new(
+            "chat",
+            vec![
+                EndpointBackend::new("v1", 90),
+                EndpointBackend::new("v2", 10),
+            ],
+        );
+        let mut status = VirtualEndpointStatus::default();
+        status.variants.insert(
+            "v1".into(),
+            VariantStats {
+                requests: 4,
+                errors: 1,
+                total_latency_ms: 500,
+            },
+        );
+        endpoint.status = Some(status);
+
+        let table = format_virtual_endpoint_list(&[endpoint]);
+        let rows: Vec<&str> = table.lines().collect();
+        assert!(rows[0].contains("AVG LATENCY"));
+        assert!(rows[1].contains("v1") && rows[1].contains("125ms"));
+        assert!(rows[2].contains("v2") && rows[2].trim_end().ends_with('-'));
+    }
+
+    #[test]
+    fn test_format_node_pool_list() {
+        use crate::cluster::NodePoolStatus;
+
+        let mut gpu = NodePool::new(
+            "gpu-pool",
+            [("accelerator".to_string(), "nvidia".to_string())].into(),
+        )
+        .with_max_pipelines(8)
+        .with_allowed_namespaces(vec!["prod".to_string()]);
+        gpu.status = Some(NodePoolStatus {
+            nodes: vec!["gpu-1".to_string(), "gpu-2".to_string()],
+            pipelines: 3,
+        });
+        let all = NodePool::new("all", Default::default());
+
+        let table = format_node_pool_list(&[gpu, all]);
+        let rows: Vec<&str> = table.lines().collect();
+        assert!(rows[1].contains("3/8") && rows[1].contains("accelerator=nvidia"));
+        assert!(rows[1].contains("prod"));
+        assert!(rows[2].contains('*') && rows[2].contains("<all>"));
+    }
+
+    #[test]
+    fn test_format_alert_list() {
+        use crate::cluster::AlertSeverity;
+        use chrono::{Duration, Utc};
+
+        let now = Utc::now();
+        let alert = |object: &str, state, since| Alert {
+            rule: "node-down".to_string(),
+            object: object.to_string(),
+            severity: AlertSeverity::Critical,
+            state,
+            message: format!("{} is NotReady", object),
+            since,
+            fired_at: None,
+        };
+        let table = format_alert_list(&[
+            alert("node/a", AlertState::Pending, now),
+            alert("node/b", AlertState::Firing, now - Duration::minutes(5)),
+        ]);
+        let rows: Vec<&str> = table.lines().collect();
+        assert!(rows[0].starts_with("RULE"));
+        assert!(rows[1].contains("node/b") && rows[1].contains("Firing"));
+        assert!(rows[2].contains("node/a") && rows[2].contains("critical"));
+        assert_eq!(format_alert_list(&[]), "No resources found.\n");
+    }
+
+    #[test]
+    fn test_format_region_list_and_column() {
+        use crate::cluster::{RegionStatus, REGION_ANNOTATION};
+
+        let mut eu = Region::new("eu-west", "http://cp.eu:8181");
+        eu.status = Some(RegionStatus {
+            reachable: true,
+            pipelines: 2,
+            ..Default::default()
+        });
+        let us = Region::new("us-east", "http://cp.us:8181");
+        let table = format_region_list(&[eu, us]);
+        let rows: Vec<&str> = table.lines().collect();
+        assert!(rows[1].contains("True") && rows[1].contains("http://cp.eu:8181"));
+        assert!(rows[2].contains("Unknown"));
+
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let local = Pipeline::new("chat", composition.clone());
+        assert!(!format_pipeline_list(std::slice::from_ref(&local)).contains("REGION"));
+        let remote =
+            Pipeline::new("code", composition).with_annotation(REGION_ANNOTATION, "eu-west");
+        let table = format_pipeline_list(&[local, remote]);
+        let rows: Vec<&str> = table.lines().collect();
+        assert!(rows[0].contains("REGION"));
+        assert!(rows[1].trim_end().ends_with('-'));
+        assert!(rows[2].trim_end().ends_with("eu-west"));
+    }
+
+    #[test]
+    fn test_format_secret_list() {
+        let mut secret = Secret::new("openai", Default::default()).with_namespace("prod");
+        secret.data.insert("key".into(), "c2VhbGVk".into());
+        secret.data.insert("org".into(), "c2VhbGVk".into());
+
+        let table = format_secret_list(&[secret]);
+        let row = table.lines().nth(1).unwrap();
+        assert!(row.contains("prod") && row.contains("Opaque") && row.contains("key,org"));
+        assert!(!table.contains("c2VhbGVk"));
+    }
+
+    #[test]
+    fn test_format_pipeline_output_composition() {
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::new("bot", composition.clone());
+
+        let output = format_pipeline_output(&pipeline, PipelineOutput::Composition).unwrap();
+        let exported = Composition::from_str(&output).unwrap();
+        assert_eq!(exported.architecture.len(), 2);
+        assert!(!output.contains("\"metadata\""));
+
+        let yaml = format_pipeline_output(&pipeline, PipelineOutput::Yaml).unwrap();
+        assert!(yaml.contains("name: bot"));
+    }
+
+    #[test]
+    fn test_format_pipeline_detail_status() {
+        use crate::cluster::node::ReplicaStatus;
+        use crate::cluster::{
+            PipelineCondition, PipelineDependency, PipelineReplica, PipelineStatus, RolloutStatus,
+            BLOCKED_CONDITION,
+        };
+        use crate::config::Composition;
+
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::new("bot", composition);
+        pipeline.spec.strategy.strategy_type = "Canary".to_string();
+        let mut rollout = RolloutStatus::start(pipeline.spec.clone(), &pipeline.spec.strategy);
+        rollout.canary.requests = 12;
+        rollout.canary.errors = 1;
+        let mut status = PipelineStatus::initial();
+        status.rollout = Some(rollout);
+        status.replica_statuses.push(PipelineReplica {
+            node: "worker-1".to_string(),
+            port: 8080,
+            status: ReplicaStatus::Failed,
+            reason: Some("runner exited".to_string()),
+            restarts: 3,
+        });
+        pipeline.status = Some(status);
+
+        let output = format_pipeline_detail(&pipeline);
+        assert!(output.contains("- worker-1:8080 Failed (restarts: 3): runner exited"));
+        assert!(output.contains("Rollout:              Progressing (10% to canary)"));
+        assert!(output.contains("Canary:             12 requests, 1 errors"));
+
+        // Waiting for a dependency
+        let mut pipeline = pipeline.with_dependency(PipelineDependency::pipeline("embedder"));
+        let mut status = PipelineStatus::initial();
+        status.add_condition(PipelineCondition::new(
+            BLOCKED_CONDITION,
+            "True",
+            "DependenciesNotReady",
+            "Pipeline default/embedder is not ready",
+        ));
+        pipeline.status = Some(status);
+        let output = format_pipeline_detail(&pipeline);
+        assert!(output.contains("Depends On:\n  pipeline/embedder\n"));
+        assert!(output.contains("Blocked:              Pipeline default/embedder is not ready"));
+        assert!(format_pipeline_list(&[pipeline]).contains("Blocked"));
+    }
+
+    #[test]
+    fn test_format_request_trace() {
+        let trace: RequestTrace = serde_json::from_value(serde_json::json!({
+            "request_id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26",
+            "prompt": "Hello",
+            "started_at": "2026-01-01T00:00:00Z",
+            "duration_ms": 120,
+            "output": "Hi there",
+            "total_tokens": 30,
+            "hops": [
+                {"node": "docs", "layer": 1, "timestamp": "2026-01-01T00:00:00Z",
+                 "latency_ms": 15, "documents": ["7", "12"]},
+                {"node": "answer", "layer": 2, "timestamp": "2026-01-01T00:00:00Z",
+                 "latency_ms": 100, "prompt_tokens": 20, "completion_tokens": 10},
+                {"node": "judge", "layer": 3, "timestamp": "2026-01-01T00:00:00Z",
+                 "latency_ms": 1, "score": 0.85},
+                {"node": "output", "layer": 4, "timestamp": "2026-01-01T00:00:00Z"}
+            ]
+        }))
+        .unwrap();
+
+        let output = format_request_trace(&trace);
+        assert!(output.contains("Duration:  120ms"));
+        assert!(output.contains("Output:    Hi there"));
+        assert!(output.contains("7,12"));
+        assert!(output.contains("20/10"));
+        assert!(output.contains("0.85"));
+        assert_eq!(output.lines().filter(|l| l.contains("output")).count(), 1);
+    }
+
+    #[test]
+    fn test_format_prune_report() {
+        let artifact: CachedArtifact = serde_json::from_value(serde_json::json!({
+            "kind": "ollama-blob",
+            "name": "llama3:8b",
+            "path": "/root/.ollama/models/blobs/sha256-111",
+            "size": 4u64 << 30,
+            "last_used": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        let mut report = PruneReport {
+            budget: 10 << 30,
+            before: 12 << 30,
+            after: 8 << 30,
+            evicted: vec![artifact],
+            dry_run: true,
+        };
+
+        let output = format_prune_report(&report);
+        assert!(output.contains("ollama-blob"));
+        assert!(output.contains("llama3:8b"));
+        assert!(output.contains("Would evict 1 artifact(s): 12.0g -> 8.0g (budget 10.0g)"));
+
+        report.evicted.clear();
+        assert!(format_prune_report(&report).contains("nothing to evict"));
+    }
+
+    #[test]
+    fn test_format_dead_letter_list() {
+        assert_eq!(format_dead_letter_list(&[]), "No resources found.\n");
+
+        let letter: DeadLetter = serde_json::from_value(serde_json::json!({
+            "request_id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26",
+            "input": {"prompt": "Summarize the quarterly report for the board"},
+            "error": "Circuit breaker open for 'summarizer'",
+            "trace": {
+                "request_id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26",
+                "prompt": "Summarize the quarterly report for the board",
+                "started_at": "2026-01-01T00:00:00Z",
+                "duration_ms": 12,
+                "error": "Circuit breaker open for 'summarizer'",
+                "total_tokens": 0,
+                "hops": []
+            },
+            "attempts": 2,
+            "failed_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+
+        let output = format_dead_letter_list(&[letter]);
+        assert!(output.starts_with("REQUEST ID"));
+        assert!(output.contains("5c56c793-69f3-4fbf-87e6-c4bf54c28c26"));
+        assert!(output.contains("2026-01-01 00:00:00"));
+        assert!(output.contains("Summarize the quarterly repor…"));
+        assert!(output.contains("Circuit breaker open for 'summarizer'"));
+    }
+
+    #[test]
+    fn test_format_request_log_list() {
+        let log: RequestLog = serde_json::from_value(serde_json::json!({
+            "request_id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26",
+            "source": "gpu-1",
+            "started_at": "2026-01-01T00:00:00Z",
+            "duration_ms": 840,
+            "prompt": "Email [REDACTED] the report",
+            "error": "Circuit breaker open for 'summarizer'",
+            "route": ["router", "summarizer"],
+            "total_tokens": 0
+        }))
+        .unwrap();
+
+        let output = format_request_log_list(&[log]);
+        let row = output.lines().nth(1).unwrap();
+        assert!(row.contains("gpu-1") && row.contains("840ms"));
+        assert!(row.contains("router → summarizer"));
+        assert!(row.contains("Email [REDACTED] the report"));
+        assert!(row.contains("error: Circuit breaker open"));
+    }
+
+    #[test]
+    fn test_format_pipeline_diff() {
+        let changes = vec![
+            SpecChange {
+                path: "spec.replicas".to_string(),
+                kind: ChangeKind::Changed,
+                old: Some(serde_json::json!(1)),
+                new: Some(serde_json::json!(3)),
+            },
+            SpecChange {
+                path: "spec.composition.models.old".to_string(),
+                kind: ChangeKind::Removed,
+                old: Some(serde_json::json!({"runner": "ollama"})),
+                new: None,
+            },
+        ];
+
+        let plain = format_pipeline_diff("bot", "prod", true, &changes, false);
+        let lines: Vec<&str> = plain.lines().collect();
+        assert_eq!(lines[0], "pipeline.llmnet/bot (prod)");
+        assert_eq!(lines[1], "~ spec.replicas: 1 -> 3");
+        assert_eq!(
+            lines[2],
+            r#"- spec.composition.models.old: {"runner":"ollama"}"#
+        );
+        assert!(plain.ends_with("2 change(s)\n"));
+
+        let colored = format_pipeline_diff("bot", "prod", true, &changes, true);
+        assert!(colored.contains("\x1b[33m~ spec.replicas: 1 -> 3\x1b[0m"));
+
+        let new = format_pipeline_diff("bot", "prod", false, &[], false);
+        assert_eq!(new, "pipeline.llmnet/bot (prod) [new]\n  no changes\n");
+    }
+
     #[test]
     fn test_format_table() {
         let headers = &["NAME", "AGE"];
@@ -574,11 +1596,13 @@ mod tests {
             ContextInfo {
                 name: "local".to_string(),
                 url: "http://localhost:8181".to_string(),
+                namespace: None,
                 is_current: true,
             },
             ContextInfo {
                 name: "remote".to_string(),
                 url: "http://10.0.0.1:8181".to_string(),
+                namespace: Some("team-a".to_string()),
                 is_current: false,
             },
         ];
@@ -586,6 +1610,7 @@ mod tests {
         let output = format_context_list(&contexts);
         assert!(output.contains("local"));
         assert!(output.contains("remote"));
+        assert!(output.contains("team-a"));
         assert!(output.contains("*")); // Current marker
     }
 
@@ -603,6 +1628,7 @@ mod tests {
             models: 2,
             nodes: 5,
             error: None,
+            warnings: Vec::new(),
         };
 
         let output = format_validation_result(&result, "test.json");
@@ -611,6 +1637,21 @@ mod tests {
         assert!(output.contains("Models: 2"));
     }
 
+    #[test]
+    fn test_format_validation_warnings() {
+        let result = ValidationResult {
+            valid: true,
+            models: 1,
+            nodes: 2,
+            error: None,
+            warnings: vec!["Model 'vllm': cpus limit 64 exceeds the host's 8 cores".to_string()],
+        };
+
+        let output = format_validation_result(&result, "test.json");
+        assert!(output.contains("✓"));
+        assert!(output.contains("⚠ Model 'vllm': cpus limit 64"));
+    }
+
     #[test]
     fn test_format_validation_invalid() {
         let result = ValidationResult {
@@ -618,6 +1659,7 @@ mod tests {
             models: 0,
             nodes: 0,
             error: Some("Parse error".to_string()),
+            warnings: Vec::new(),
         };
 
         let output = format_validation_result(&result, "test.json");
@@ -625,4 +1667,49 @@ mod tests {
         assert!(output.contains("invalid"));
         assert!(output.contains("Parse error"));
     }
+
+    #[test]
+    fn test_format_cluster_check() {
+        let output = format_cluster_check(&ClusterCheck::default(), "prod");
+        assert!(output.contains("✓ Fits cluster prod"));
+
+        let check = ClusterCheck {
+            problems: vec!["Secret 'openai' is referenced but not declared".to_string()],
+            warnings: vec!["No schedulable nodes".to_string()],
+        };
+        let output = format_cluster_check(&check, "prod");
+        assert!(output.contains("✗ Won't run on cluster prod"));
+        assert!(output.contains("  ✗ Secret 'openai'"));
+        assert!(output.contains("  ⚠ No schedulable nodes"));
+    }
+
+    #[test]
+    fn test_format_scoring_weights() {
+        let weights = ScoringWeights::default().with_latency(0.25);
+        let output = format_scoring_weights(&weights);
+        assert!(output.contains("RESOURCE"));
+        assert!(output.contains("WEIGHT"));
+        assert!(output
+            .lines()
+            .any(|l| l.starts_with("latency") && l.trim_end().ends_with("0.25")));
+    }
+
+    #[test]
+    fn test_highlight_changes_first_render() {
+        let output = highlight_changes(None, "Nodes: 1\nPipelines: 2\n");
+        assert_eq!(output, "Nodes: 1\nPipelines: 2\n");
+    }
+
+    #[test]
+    fn test_highlight_changes_marks_changed_lines() {
+        let previous = "Nodes: 1\nPipelines: 2\n";
+        let current = "Nodes: 1\nPipelines: 3\nNamespaces: 1\n";
+
+        let output = highlight_changes(Some(previous), current);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "Nodes: 1");
+        assert_eq!(lines[1], "\x1b[1;33mPipelines: 3\x1b[0m");
+        assert_eq!(lines[2], "\x1b[1;33mNamespaces: 1\x1b[0m");
+    }
 }
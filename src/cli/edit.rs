@@ -0,0 +1,313 @@
+//! `llmnet edit`: change a live resource in $EDITOR
+//!
+//! The resource is written out as YAML without its status, the user edits
+//! it, and the result is checked and diffed before the caller applies it.
+//! An edit that doesn't parse or validate reopens in the editor with the
+//! error at the top, like `kubectl edit`.
+//!
+//! SBIO pattern: Pure document handling; `open_in_editor` is the only I/O.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::diff::{diff_values, SpecChange};
+use crate::cluster::{Node, Pipeline};
+use crate::config::validate_composition;
+
+/// Editor used when neither $VISUAL nor $EDITOR is set
+pub const DEFAULT_EDITOR: &str = "vi";
+
+const HEADER: &str = "\
+# Edit the resource below; it is applied when you save and quit.
+# Lines starting with '#' are ignored. Save an unchanged file to cancel.
+# Status is managed by the cluster and can't be edited.
+";
+
+/// A resource `llmnet edit` can change
+pub trait Editable: Serialize + DeserializeOwned + Clone {
+    /// Fields that must not change (kind, name, ...), as `(field, value)`
+    fn identity(&self) -> Vec<(&'static str, String)>;
+
+    /// Check an edited copy before it is sent to the control plane
+    fn validate_edit(&self) -> Result<(), String>;
+
+    /// The resource with its status removed
+    fn without_status(&self) -> Self;
+
+    /// Carry over the fields `without_status` removed
+    fn restore_status(&mut self, live: &Self);
+}
+
+impl Editable for Pipeline {
+    fn identity(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("kind", self.kind.clone()),
+            ("metadata.name", self.metadata.name.clone()),
+            ("metadata.namespace", self.metadata.namespace.clone()),
+        ]
+    }
+
+    fn validate_edit(&self) -> Result<(), String> {
+        validate_composition(&self.spec.composition).map_err(|e| e.to_string())
+    }
+
+    fn without_status(&self) -> Self {
+        Self {
+            status: None,
+            ..self.clone()
+        }
+    }
+
+    fn restore_status(&mut self, live: &Self) {
+        self.status = live.status.clone();
+    }
+}
+
+impl Editable for Node {
+    fn identity(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("kind", self.kind.clone()),
+            ("metadata.name", self.metadata.name.clone()),
+        ]
+    }
+
+    fn validate_edit(&self) -> Result<(), String> {
+        crate::cluster::maintenance::validate_windows(&self.spec.maintenance_windows)
+    }
+
+    fn without_status(&self) -> Self {
+        Self {
+            status: None,
+            ..self.clone()
+        }
+    }
+
+    fn restore_status(&mut self, live: &Self) {
+        self.status = live.status.clone();
+    }
+}
+
+/// The YAML document first opened in the editor
+pub fn edit_document<T: Editable>(resource: &T) -> Result<String, String> {
+    let yaml = serde_yaml::to_string(&resource.without_status()).map_err(|e| e.to_string())?;
+    Ok(with_header(&yaml, None))
+}
+
+/// An edit that was rejected, reopened with the error above the user's changes
+pub fn reopen_with_error(edited: &str, error: &str) -> String {
+    // Drop the header (and any earlier error) but keep the edited body
+    let body: Vec<&str> = edited
+        .lines()
+        .skip_while(|line| line.starts_with('#'))
+        .collect();
+    with_header(&format!("{}\n", body.join("\n")), Some(error))
+}
+
+fn with_header(body: &str, error: Option<&str>) -> String {
+    let mut document = HEADER.to_string();
+    if let Some(error) = error {
+        document.push_str("#\n");
+        for line in error.lines() {
+            document.push_str(&format!("# error: {}\n", line));
+        }
+    }
+    document.push_str("#\n");
+    document.push_str(body);
+    document
+}
+
+/// Read back an edited document
+///
+/// Returns `Ok(None)` when nothing changed, and an error message when the
+/// edit doesn't parse, renames the resource or fails validation.
+pub fn parse_edit<T: Editable>(live: &T, edited: &str) -> Result<Option<T>, String> {
+    let blank = edited.lines().all(|line| {
+        let line = line.trim();
+        line.is_empty() || line.starts_with('#')
+    });
+    if blank {
+        return Ok(None);
+    }
+
+    let mut updated: T =
+        serde_yaml::from_str(edited).map_err(|e| format!("invalid YAML: {}", e))?;
+
+    for ((field, before), (_, after)) in live.identity().into_iter().zip(updated.identity()) {
+        if before != after {
+            return Err(format!(
+                "{} can't be changed (was '{}', now '{}')",
+                field, before, after
+            ));
+        }
+    }
+
+    if to_value(&updated.without_status()) == to_value(&live.without_status()) {
+        return Ok(None);
+    }
+
+    updated.validate_edit()?;
+    updated.restore_status(live);
+    Ok(Some(updated))
+}
+
+/// Fields changed by an edit, addressed from the resource root
+/// (e.g. `metadata.labels.gpu`, `spec.replicas`)
+pub fn diff_edit<T: Editable>(live: &T, updated: &T) -> Vec<SpecChange> {
+    let old = to_value(&live.without_status());
+    let new = to_value(&updated.without_status());
+
+    let mut changes = Vec::new();
+    for section in ["metadata", "spec"] {
+        diff_values(section, &old[section], &new[section], &mut changes);
+    }
+    changes
+}
+
+fn to_value<T: Serialize>(resource: &T) -> Value {
+    serde_json::to_value(resource).unwrap_or(Value::Null)
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// The user's editor command, split into program and arguments
+/// (e.g. `code --wait`)
+pub fn editor_command(visual: Option<String>, editor: Option<String>) -> Vec<String> {
+    let command = visual
+        .filter(|v| !v.trim().is_empty())
+        .or(editor.filter(|e| !e.trim().is_empty()))
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
+    command.split_whitespace().map(String::from).collect()
+}
+
+/// Open a file in $VISUAL or $EDITOR and wait for the editor to exit
+pub fn open_in_editor(path: &Path) -> std::io::Result<()> {
+    let command = editor_command(std::env::var("VISUAL").ok(), std::env::var("EDITOR").ok());
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .arg(path)
+        .status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!(
+            "editor '{}' exited with {}",
+            command.join(" "),
+            status
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{NodeCapacity, NodeInfo, NodeStatus};
+    use crate::cluster::PipelineStatus;
+    use crate::config::Composition;
+
+    fn pipeline() -> Pipeline {
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::new("chatbot", composition);
+        pipeline.status = Some(PipelineStatus::initial());
+        pipeline
+    }
+
+    #[test]
+    fn test_edit_document_leaves_out_status() {
+        let document = edit_document(&pipeline()).unwrap();
+        assert!(document.starts_with("# Edit the resource"));
+        assert!(document.contains("name: chatbot"));
+        assert!(!document.contains("status:"));
+    }
+
+    #[test]
+    fn test_reopen_with_error_keeps_changes() {
+        let document = edit_document(&pipeline()).unwrap();
+        let edited = document.replace("replicas: 1", "replicas: lots");
+
+        let reopened = reopen_with_error(&edited, "first");
+        let reopened = reopen_with_error(&reopened, "replicas must be a number");
+        assert!(reopened.starts_with("# Edit the resource"));
+        assert!(!reopened.contains("first"));
+        assert!(reopened.contains("# error: replicas must be a number\n"));
+        assert!(reopened.contains("replicas: lots"));
+
+        // The error is a comment, so fixing the value is enough
+        let fixed = reopened.replace("replicas: lots", "replicas: 2");
+        let updated = parse_edit(&pipeline(), &fixed).unwrap().unwrap();
+        assert_eq!(updated.spec.replicas, 2);
+    }
+
+    #[test]
+    fn test_parse_edit() {
+        let live = pipeline();
+        let document = edit_document(&live).unwrap();
+
+        let edited = document.replace("replicas: 1", "replicas: 3");
+        let updated = parse_edit(&live, &edited).unwrap().unwrap();
+        assert_eq!(updated.spec.replicas, 3);
+        // Status comes from the live pipeline, not the document
+        assert!(updated.status.is_some());
+
+        let changes = diff_edit(&live, &updated);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "spec.replicas");
+
+        // Emptying the file cancels
+        assert!(matches!(parse_edit(&live, "# nothing\n"), Ok(None)));
+
+        let renamed = document.replace("name: chatbot", "name: other");
+        assert!(parse_edit(&live, &renamed)
+            .unwrap_err()
+            .starts_with("metadata.name can't be changed"));
+
+        assert!(parse_edit(&live, "spec: [")
+            .unwrap_err()
+            .starts_with("invalid YAML"));
+
+        // The composition is validated (the output node goes missing)
+        let broken = document.replace("adapter: output", "adapter: openai-api");
+        assert!(parse_edit(&live, &broken).is_err());
+    }
+
+    #[test]
+    fn test_edit_node_labels() {
+        let mut live = Node::new("gpu-1", "10.0.0.1");
+        live.metadata.labels.clear();
+        live.status = Some(NodeStatus::new(
+            NodeCapacity::default(),
+            NodeInfo::from_system(),
+        ));
+        let document = edit_document(&live).unwrap();
+
+        let edited = document.replace("labels: {}", "labels:\n    gpu: a100");
+        let updated = parse_edit(&live, &edited).unwrap().unwrap();
+        assert_eq!(updated.metadata.labels["gpu"], "a100");
+        assert!(updated.status.is_some());
+        assert_eq!(
+            diff_edit(&live, &updated)[0].path,
+            "metadata.labels.gpu".to_string()
+        );
+    }
+
+    #[test]
+    fn test_editor_command() {
+        assert_eq!(editor_command(None, None), vec!["vi"]);
+        assert_eq!(editor_command(None, Some("nano".into())), vec!["nano"]);
+        assert_eq!(
+            editor_command(Some("code --wait".into()), Some("nano".into())),
+            vec!["code", "--wait"]
+        );
+        assert_eq!(editor_command(Some(" ".into()), None), vec!["vi"]);
+    }
+}
@@ -0,0 +1,406 @@
+//! Installing `llmnet serve` as a service that starts on boot
+//!
+//! SBIO pattern: the systemd unit, launchd plist and Windows service commands
+//! are rendered by pure functions; `install_service` writes them and asks the
+//! service manager to start the service.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use clap::ValueEnum;
+
+/// Seconds the service manager waits before restarting a crashed server
+pub const RESTART_DELAY_SECS: u64 = 5;
+
+/// Where systemd units are installed
+const SYSTEMD_UNIT_DIR: &str = "/etc/systemd/system";
+
+/// Where launchd daemons are installed
+const LAUNCHD_DAEMON_DIR: &str = "/Library/LaunchDaemons";
+
+/// Service managers `llmnet install` can register with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ServiceManager {
+    /// systemd (Linux)
+    Systemd,
+    /// launchd (macOS)
+    Launchd,
+    /// The Windows service control manager
+    Windows,
+}
+
+impl ServiceManager {
+    /// The service manager of the platform llmnet was built for
+    pub fn detect() -> Option<Self> {
+        if cfg!(target_os = "linux") {
+            Some(ServiceManager::Systemd)
+        } else if cfg!(target_os = "macos") {
+            Some(ServiceManager::Launchd)
+        } else if cfg!(windows) {
+            Some(ServiceManager::Windows)
+        } else {
+            None
+        }
+    }
+}
+
+/// A service that runs `llmnet serve`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceSpec {
+    /// Service name (systemd unit, launchd label, Windows service name)
+    pub name: String,
+
+    /// Human-readable description
+    pub description: String,
+
+    /// Path to the llmnet binary
+    pub binary: PathBuf,
+
+    /// Arguments after the binary, starting with `serve`
+    pub args: Vec<String>,
+}
+
+/// What `install_service` did, or would do
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallPlan {
+    /// File to write and its content; Windows services have none
+    pub file: Option<(PathBuf, String)>,
+
+    /// Commands to run afterwards, in order
+    pub commands: Vec<Vec<String>>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Quote one ExecStart word the way systemd splits it
+fn systemd_quote(word: &str) -> String {
+    let escaped = word.replace('%', "%%");
+    if !escaped.is_empty()
+        && !escaped
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';' | '$'))
+    {
+        return escaped;
+    }
+    let escaped = escaped
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "$$");
+    format!("\"{}\"", escaped)
+}
+
+/// Render a systemd unit that restarts the server on exit and starts it on
+/// boot once the network is up
+pub fn render_systemd_unit(spec: &ServiceSpec) -> String {
+    let exec_start: Vec<String> = std::iter::once(spec.binary.display().to_string())
+        .chain(spec.args.iter().cloned())
+        .map(|w| systemd_quote(&w))
+        .collect();
+    format!(
+        "[Unit]\n\
+         Description={description}\n\
+         Wants=network-online.target\n\
+         After=network-online.target docker.service\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart={exec_start}\n\
+         Restart=always\n\
+         RestartSec={delay}\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        description = spec.description,
+        exec_start = exec_start.join(" "),
+        delay = RESTART_DELAY_SECS,
+    )
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render a launchd daemon plist that keeps the server running
+pub fn render_launchd_plist(spec: &ServiceSpec) -> String {
+    let arguments: String = std::iter::once(spec.binary.display().to_string())
+        .chain(spec.args.iter().cloned())
+        .map(|w| format!("        <string>{}</string>\n", xml_escape(&w)))
+        .collect();
+    let log = format!("/var/log/{}.log", spec.name);
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \x20   <key>Label</key>\n\
+         \x20   <string>{label}</string>\n\
+         \x20   <key>ProgramArguments</key>\n\
+         \x20   <array>\n\
+         {arguments}\
+         \x20   </array>\n\
+         \x20   <key>RunAtLoad</key>\n\
+         \x20   <true/>\n\
+         \x20   <key>KeepAlive</key>\n\
+         \x20   <true/>\n\
+         \x20   <key>ThrottleInterval</key>\n\
+         \x20   <integer>{delay}</integer>\n\
+         \x20   <key>StandardOutPath</key>\n\
+         \x20   <string>{log}</string>\n\
+         \x20   <key>StandardErrorPath</key>\n\
+         \x20   <string>{log}</string>\n\
+         </dict>\n\
+         </plist>\n",
+        label = xml_escape(&spec.name),
+        arguments = arguments,
+        delay = RESTART_DELAY_SECS,
+        log = xml_escape(&log),
+    )
+}
+
+/// Quote one word of a Windows command line
+fn windows_quote(word: &str) -> String {
+    if !word.is_empty() && !word.chars().any(|c| c.is_whitespace() || c == '"') {
+        return word.to_string();
+    }
+    format!("\"{}\"", word.replace('"', "\\\""))
+}
+
+/// `sc.exe` commands that create the Windows service, restart it when it
+/// crashes and start it
+///
+/// The service runs `llmnet serve --windows-service <name>`, which reports
+/// to the service control manager while serving.
+pub fn windows_service_commands(spec: &ServiceSpec) -> Vec<Vec<String>> {
+    let bin_path: Vec<String> = std::iter::once(spec.binary.display().to_string())
+        .chain(spec.args.iter().cloned())
+        .chain(["--windows-service".to_string(), spec.name.clone()])
+        .map(|w| windows_quote(&w))
+        .collect();
+    let bin_path = bin_path.join(" ");
+    let restart = format!("restart/{}", RESTART_DELAY_SECS * 1000);
+    let actions = format!("{restart}/{restart}/{restart}");
+    let commands: [&[&str]; 4] = [
+        &[
+            "create",
+            &spec.name,
+            "binPath=",
+            &bin_path,
+            "start=",
+            "auto",
+            "DisplayName=",
+            &spec.description,
+        ],
+        &[
+            "failure", &spec.name, "reset=", "86400", "actions=", &actions,
+        ],
+        &["description", &spec.name, &spec.description],
+        &["start", &spec.name],
+    ];
+    commands
+        .iter()
+        .map(|args| {
+            std::iter::once("sc.exe")
+                .chain(args.iter().copied())
+                .map(String::from)
+                .collect()
+        })
+        .collect()
+}
+
+/// What installing `spec` with `manager` writes and runs
+pub fn install_plan(manager: ServiceManager, spec: &ServiceSpec) -> InstallPlan {
+    match manager {
+        ServiceManager::Systemd => {
+            let path = Path::new(SYSTEMD_UNIT_DIR).join(format!("{}.service", spec.name));
+            InstallPlan {
+                file: Some((path, render_systemd_unit(spec))),
+                commands: vec![
+                    vec!["systemctl".into(), "daemon-reload".into()],
+                    vec![
+                        "systemctl".into(),
+                        "enable".into(),
+                        "--now".into(),
+                        spec.name.clone(),
+                    ],
+                ],
+            }
+        }
+        ServiceManager::Launchd => {
+            let path = Path::new(LAUNCHD_DAEMON_DIR).join(format!("{}.plist", spec.name));
+            InstallPlan {
+                commands: vec![vec![
+                    "launchctl".into(),
+                    "bootstrap".into(),
+                    "system".into(),
+                    path.display().to_string(),
+                ]],
+                file: Some((path, render_launchd_plist(spec))),
+            }
+        }
+        ServiceManager::Windows => InstallPlan {
+            file: None,
+            commands: windows_service_commands(spec),
+        },
+    }
+}
+
+// ============================================================================
+// I/O: Installing the service
+// ============================================================================
+
+/// Write the service file and run the service manager's commands
+pub fn install_service(plan: &InstallPlan) -> io::Result<()> {
+    if let Some((path, content)) = &plan.file {
+        std::fs::write(path, content)?;
+    }
+    for command in &plan.commands {
+        let status = Command::new(&command[0]).args(&command[1..]).status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "'{}' failed with {}",
+                command.join(" "),
+                status
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Report to the Windows service control manager while `llmnet serve` runs
+#[cfg(windows)]
+pub mod windows_service_host {
+    use std::ffi::OsString;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    use windows_service::service::{
+        ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+        ServiceType,
+    };
+    use windows_service::service_control_handler::{
+        self, ServiceControlHandlerResult, ServiceStatusHandle,
+    };
+    use windows_service::{define_windows_service, service_dispatcher};
+
+    static SERVICE_NAME: OnceLock<String> = OnceLock::new();
+    static STATUS_HANDLE: OnceLock<ServiceStatusHandle> = OnceLock::new();
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    fn status(state: ServiceState) -> ServiceStatus {
+        ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted: if state == ServiceState::Running {
+                ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+            } else {
+                ServiceControlAccept::empty()
+            },
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        }
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let name = SERVICE_NAME.get().cloned().unwrap_or_default();
+        let registered = service_control_handler::register(&name, |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                if let Some(handle) = STATUS_HANDLE.get() {
+                    let _ = handle.set_service_status(status(ServiceState::Stopped));
+                }
+                std::process::exit(0);
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        });
+        let Ok(handle) = registered else {
+            return;
+        };
+        let _ = handle.set_service_status(status(ServiceState::Running));
+        let _ = STATUS_HANDLE.set(handle);
+
+        // The server runs on the main thread; this one only waits for the
+        // service manager to stop it
+        loop {
+            std::thread::park();
+        }
+    }
+
+    /// Connect to the service control manager as service `name`
+    ///
+    /// Blocks until the service is stopped, so it is run on its own thread.
+    pub fn run(name: &str) -> windows_service::Result<()> {
+        let _ = SERVICE_NAME.set(name.to_string());
+        service_dispatcher::start(name, ffi_service_main)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(args: &[&str]) -> ServiceSpec {
+        ServiceSpec {
+            name: "llmnet-worker".to_string(),
+            description: "LLMNet worker".to_string(),
+            binary: PathBuf::from("/usr/local/bin/llmnet"),
+            args: args.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_render_systemd_unit() {
+        let unit = render_systemd_unit(&spec(&["serve", "--node-name", "my node 100%"]));
+        assert!(
+            unit.contains("ExecStart=/usr/local/bin/llmnet serve --node-name \"my node 100%%\"\n")
+        );
+        assert!(unit.contains("Restart=always\n"));
+        assert!(unit.contains("WantedBy=multi-user.target\n"));
+
+        let plan = install_plan(ServiceManager::Systemd, &spec(&["serve"]));
+        assert_eq!(
+            plan.file.unwrap().0,
+            PathBuf::from("/etc/systemd/system/llmnet-worker.service")
+        );
+        assert_eq!(
+            plan.commands.last().unwrap(),
+            &["systemctl", "enable", "--now", "llmnet-worker"]
+        );
+    }
+
+    #[test]
+    fn test_render_launchd_plist() {
+        let plist = render_launchd_plist(&spec(&["serve", "--node-name", "a&b"]));
+        assert!(plist.contains("<string>llmnet-worker</string>"));
+        assert!(plist
+            .contains("<string>/usr/local/bin/llmnet</string>\n        <string>serve</string>"));
+        assert!(plist.contains("<string>a&amp;b</string>"));
+        assert!(plist.contains("<key>KeepAlive</key>\n    <true/>"));
+    }
+
+    #[test]
+    fn test_windows_service_commands() {
+        let mut spec = spec(&["serve", "--node-name", "edge 1"]);
+        spec.binary = PathBuf::from(r"C:\Program Files\llmnet\llmnet.exe");
+        let commands = windows_service_commands(&spec);
+        assert_eq!(
+            commands[0][..4],
+            ["sc.exe", "create", "llmnet-worker", "binPath="]
+        );
+        assert_eq!(
+            commands[0][4],
+            r#""C:\Program Files\llmnet\llmnet.exe" serve --node-name "edge 1" --windows-service llmnet-worker"#
+        );
+        assert_eq!(commands[1][6], "restart/5000/restart/5000/restart/5000");
+        assert_eq!(commands[3], ["sc.exe", "start", "llmnet-worker"]);
+        assert!(install_plan(ServiceManager::Windows, &spec).file.is_none());
+    }
+}
@@ -3,20 +3,47 @@
 //! Provides kubectl-like subcommands:
 //! - `llmnet serve` - Run as control plane or local pipeline server
 //! - `llmnet deploy` - Deploy a pipeline to the current context
-//! - `llmnet get` - List resources (pipelines, nodes, namespaces)
+//! - `llmnet get` - List resources (pipelines, jobs, endpoints, nodes, namespaces)
 //! - `llmnet delete` - Delete resources
 //! - `llmnet scale` - Scale pipelines
+//! - `llmnet job` - Run batch inference jobs and read their results
 //! - `llmnet context` - Manage contexts
 //! - `llmnet logs` - View pipeline logs
-
-use clap::{ArgAction, Parser, Subcommand};
+//! - `llmnet trace` - Show how a request moved through a pipeline
+//! - `llmnet prune models` - Evict cached models down to a worker's disk budget
+//! - `llmnet diff` - Compare a local manifest with the deployed pipeline
+//! - `llmnet edit` - Change a live pipeline or node in $EDITOR
+//! - `llmnet ui` - Browse and operate the cluster in a terminal dashboard
+//! - `llmnet config scoring` - View or change how nodes are scored for scheduling
+//! - `llmnet install` - Run a worker or control plane as a service on boot
+//! - `llmnet completion` / `llmnet docs man` - Shell completions and man pages
+
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::ArgValueCandidates;
 use std::path::PathBuf;
 
+use crate::cluster::{
+    ScoringWeights, DEFAULT_AUDIT_RETENTION_DAYS, DEFAULT_DRAIN_TIMEOUT_SECS,
+    HEARTBEAT_INTERVAL_SECS, SCORING_PRESETS,
+};
+
 mod commands;
+mod completion;
+mod diff;
 mod display;
+mod edit;
+mod install;
+mod preflight;
+mod ui;
 
 pub use commands::*;
+pub use completion::*;
+pub use diff::*;
 pub use display::*;
+pub use edit::*;
+pub use install::*;
+pub use preflight::*;
+pub use ui::*;
 
 #[derive(Parser, Debug)]
 #[command(name = "llmnet")]
@@ -31,6 +58,10 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub config: Option<PathBuf>,
 
+    /// Context to use for this command instead of the current one
+    #[arg(long, global = true, value_name = "NAME")]
+    pub context: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -43,6 +74,21 @@ pub enum Commands {
     /// Deploy a pipeline to the current context
     Deploy(DeployArgs),
 
+    /// Show what deploying a manifest would change
+    Diff(DiffArgs),
+
+    /// Edit a live pipeline or node in $EDITOR
+    Edit(EditArgs),
+
+    /// Add or remove labels of a pipeline or node
+    Label(MetadataArgs),
+
+    /// Add or remove annotations of a pipeline or node
+    Annotate(MetadataArgs),
+
+    /// Create a resource from the command line
+    Create(CreateArgs),
+
     /// Get/list resources
     Get(GetArgs),
 
@@ -52,18 +98,41 @@ pub enum Commands {
     /// Scale a pipeline
     Scale(ScaleArgs),
 
+    /// Run a batch of prompts through a pipeline
+    Job(JobArgs),
+
     /// Manage cluster contexts
     Context(ContextArgs),
 
+    /// View or change cluster configuration
+    #[command(name = "config")]
+    ClusterConfig(ClusterConfigArgs),
+
     /// View pipeline logs
     Logs(LogsArgs),
 
     /// Show cluster status
-    Status,
+    Status(StatusArgs),
+
+    /// Browse and operate the cluster in a terminal dashboard
+    Ui(UiArgs),
+
+    /// Show the trace of a request handled by a worker
+    Trace(TraceArgs),
+
+    /// Run failed requests through a worker's pipeline again
+    Requeue(RequeueArgs),
+
+    /// Run a past request's input through a worker's pipeline, or one of
+    /// its nodes, again
+    Replay(ReplayArgs),
 
     /// Validate a composition file
     Validate(ValidateArgs),
 
+    /// Free disk space on a worker
+    Prune(PruneArgs),
+
     /// Run a local pipeline server (legacy mode)
     #[command(name = "run")]
     Run(RunArgs),
@@ -73,6 +142,16 @@ pub enum Commands {
 
     /// Kill a running container (force shutdown)
     Kill(KillArgs),
+
+    /// Install `llmnet serve` as a service that starts on boot and restarts
+    /// when it crashes (systemd, launchd or Windows service)
+    Install(InstallArgs),
+
+    /// Print a shell completion script
+    Completion(CompletionArgs),
+
+    /// Generate reference documentation
+    Docs(DocsArgs),
 }
 
 /// Arguments for the serve command
@@ -95,6 +174,45 @@ pub struct ServeArgs {
     #[arg(short, long)]
     pub port: Option<u16>,
 
+    /// Also serve the gRPC API on this port (control plane only, e.g. 8182)
+    #[arg(long, value_name = "PORT")]
+    pub grpc_port: Option<u16>,
+
+    /// Append the audit log to this JSON Lines file (control plane only;
+    /// kept in memory otherwise)
+    #[arg(long, value_name = "FILE")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Directory jobs may read input files from, given relative to it
+    /// (control plane only; without it jobs take URLs and inline prompts)
+    #[arg(long, value_name = "DIR")]
+    pub jobs_dir: Option<PathBuf>,
+
+    /// Days to keep audit log entries
+    #[arg(long, value_name = "DAYS", default_value_t = DEFAULT_AUDIT_RETENTION_DAYS)]
+    pub audit_retention_days: u64,
+
+    /// URL of a webhook that reviews pipelines before they are stored, and
+    /// may reject or change them (control plane only; repeatable, called in
+    /// order)
+    #[arg(long = "admission-webhook", value_name = "URL")]
+    pub admission_webhooks: Vec<String>,
+
+    /// File holding the base64 master key that encrypts secrets (control
+    /// plane only; e.g. from `openssl rand -base64 32`)
+    #[arg(long, value_name = "FILE")]
+    pub secret_key_file: Option<PathBuf>,
+
+    /// YAML or JSON file of the API keys requests must carry, each scoped
+    /// to namespaces (control plane only; open to everyone otherwise)
+    #[arg(long, value_name = "FILE")]
+    pub api_keys: Option<PathBuf>,
+
+    /// Key this worker sends to a control plane started with --api-keys;
+    /// it needs access to all namespaces (worker only)
+    #[arg(long, env = "LLMNET_API_KEY", hide_env_values = true)]
+    pub api_key: Option<String>,
+
     /// Path to a .env file for loading API keys
     #[arg(long, value_name = "FILE")]
     pub env_file: Option<PathBuf>,
@@ -107,24 +225,121 @@ pub struct ServeArgs {
     #[arg(long)]
     pub control_plane_url: Option<String>,
 
+    /// File recording assignments and runner containers across restarts
+    /// (worker only; default: ~/.llmnet/worker-state.json)
+    #[arg(long, value_name = "FILE")]
+    pub state_file: Option<PathBuf>,
+
+    /// Seconds between heartbeats to the control plane (worker only)
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = HEARTBEAT_INTERVAL_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub heartbeat_interval: u64,
+
+    /// Heartbeat over HTTP only, without the WebSocket session that
+    /// receives assignments as soon as they are made (worker only)
+    #[arg(long)]
+    pub no_session: bool,
+
+    /// Seconds to let in-flight requests finish on SIGTERM or Ctrl+C before
+    /// the worker deregisters and stops its runners (worker only)
+    #[arg(long, value_name = "SECS", default_value_t = DEFAULT_DRAIN_TIMEOUT_SECS)]
+    pub drain_timeout: u64,
+
+    /// CPU cores to keep for the system, left out of the capacity offered
+    /// to pipelines (worker only)
+    #[arg(long, value_name = "CORES", default_value_t = 0)]
+    pub reserve_cpu: u32,
+
+    /// Memory to keep for the system, e.g. 2Gi (worker only)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "0")]
+    pub reserve_memory: u64,
+
+    /// GPUs to keep for the system (worker only)
+    #[arg(long, value_name = "COUNT", default_value_t = 0)]
+    pub reserve_gpu: u32,
+
+    /// Leave orphaned `llmnet-` containers alone instead of removing them
+    /// every few minutes (worker only)
+    #[arg(long)]
+    pub no_gc: bool,
+
+    /// Disk space cached models may take, e.g. 100Gi; `llmnet prune models`
+    /// evicts the least recently used ones down to it (worker only)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub model_cache_budget: Option<u64>,
+
     /// Force restart even if already running and healthy
     #[arg(long)]
     pub force: bool,
+
+    /// Report to the Windows service control manager as this service (set
+    /// by `llmnet install`)
+    #[arg(long, value_name = "NAME", hide = true)]
+    pub windows_service: Option<String>,
 }
 
 /// Arguments for the deploy command
 #[derive(Parser, Debug)]
 pub struct DeployArgs {
-    /// Path to the pipeline manifest (JSON or YAML)
+    /// Path to the pipeline or virtual endpoint manifest (JSON or YAML)
     pub file: PathBuf,
 
-    /// Namespace to deploy to (default: "default")
-    #[arg(short, long, default_value = "default")]
-    pub namespace: String,
+    /// Namespace to deploy to (default: the context's namespace, or "default")
+    #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+    pub namespace: Option<String>,
 
     /// Dry-run mode: validate without deploying
     #[arg(long)]
     pub dry_run: bool,
+
+    /// Deploy a new pipeline even if it doesn't fit on the current nodes
+    #[arg(long)]
+    pub force: bool,
+
+    #[command(flatten)]
+    pub values: ValuesArgs,
+}
+
+/// Arguments for the diff command
+#[derive(Parser, Debug)]
+pub struct DiffArgs {
+    /// Path to the pipeline manifest (JSON or YAML)
+    pub file: PathBuf,
+
+    /// Namespace used when the file is a plain composition (default: the
+    /// context's namespace)
+    #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+    pub namespace: Option<String>,
+
+    /// Disable colored output
+    #[arg(long)]
+    pub no_color: bool,
+
+    #[command(flatten)]
+    pub values: ValuesArgs,
+}
+
+/// Values for `{{ .values.x }}` placeholders in a composition
+#[derive(Args, Debug, Default, Clone)]
+pub struct ValuesArgs {
+    /// YAML file of values (repeatable; later files override earlier ones)
+    #[arg(short = 'f', long = "values", value_name = "FILE")]
+    pub files: Vec<PathBuf>,
+
+    /// Set a value, e.g. --set router.url=http://gpu-1:8080 (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub sets: Vec<String>,
+}
+
+impl ValuesArgs {
+    /// Merge the values files and `--set` overrides
+    pub fn load(&self) -> Result<serde_json::Value, crate::config::ValuesError> {
+        crate::config::load_values(&self.files, &self.sets)
+    }
 }
 
 /// Arguments for the get command
@@ -135,16 +350,69 @@ pub struct GetArgs {
     pub resource: GetResource,
 }
 
+/// How `get pipeline <name> -o` prints a pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PipelineOutput {
+    Json,
+    Yaml,
+    /// The composition as JSON, ready to edit and deploy again
+    Composition,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum GetResource {
     // ============================================================================
     // Control plane resources (require control plane context)
     // ============================================================================
-    /// List pipelines
+    /// List pipelines, or show one
     #[command(name = "pipelines", visible_alias = "pipeline", visible_alias = "pl")]
     Pipelines {
-        /// Namespace (omit for all namespaces)
-        #[arg(short, long)]
+        /// Show only this pipeline
+        name: Option<String>,
+
+        /// Namespace (default: the context's namespace if set, else all)
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+
+        /// Show all namespaces
+        #[arg(short = 'A', long)]
+        all_namespaces: bool,
+
+        /// Print the named pipeline as JSON or YAML, or just its
+        /// composition document
+        #[arg(short, long, value_enum, requires = "name")]
+        output: Option<PipelineOutput>,
+    },
+
+    /// List batch inference jobs
+    #[command(name = "jobs", visible_alias = "job")]
+    Jobs {
+        /// Namespace (default: the context's namespace if set, else all)
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+
+        /// Show all namespaces
+        #[arg(short = 'A', long)]
+        all_namespaces: bool,
+    },
+
+    /// List virtual endpoints and their traffic per backend
+    #[command(name = "endpoints", visible_alias = "endpoint", visible_alias = "ep")]
+    Endpoints {
+        /// Namespace (default: the context's namespace if set, else all)
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+
+        /// Show all namespaces
+        #[arg(short = 'A', long)]
+        all_namespaces: bool,
+    },
+
+    /// List secrets and the keys they hold
+    #[command(name = "secrets", visible_alias = "secret")]
+    Secrets {
+        /// Namespace (default: the context's namespace if set, else all)
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
         namespace: Option<String>,
 
         /// Show all namespaces
@@ -156,10 +424,46 @@ pub enum GetResource {
     #[command(name = "nodes", visible_alias = "node", visible_alias = "no")]
     Nodes,
 
+    /// List node pools with their members and pipeline load
+    #[command(name = "nodepools", visible_alias = "nodepool", visible_alias = "np")]
+    NodePools,
+
+    /// List federated regions and whether they could be synced
+    #[command(name = "regions", visible_alias = "region")]
+    Regions,
+
     /// List namespaces
     #[command(name = "namespaces", visible_alias = "namespace", visible_alias = "ns")]
     Namespaces,
 
+    /// List broken alert rules, pending or firing
+    #[command(name = "alerts", visible_alias = "alert")]
+    Alerts,
+
+    /// List the sample of requests workers shipped to the control plane
+    #[command(
+        name = "requestlogs",
+        visible_alias = "requestlog",
+        visible_alias = "rl"
+    )]
+    RequestLogs {
+        /// Only requests answered by this worker
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Only failed requests
+        #[arg(long)]
+        failed: bool,
+
+        /// Show at most this many of the most recent requests
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+
+        /// Print the raw JSON, including full prompts and answers
+        #[arg(long)]
+        json: bool,
+    },
+
     // ============================================================================
     // Worker resources (require worker context)
     // ============================================================================
@@ -170,6 +474,30 @@ pub enum GetResource {
     /// List local model runners (worker mode)
     #[command(name = "runners", visible_alias = "runner", visible_alias = "r")]
     Runners,
+
+    /// List model artifacts cached on a worker's disk (worker mode)
+    #[command(name = "models", visible_alias = "model")]
+    Models {
+        /// Print the raw JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List requests a worker's pipeline failed to answer
+    #[command(
+        name = "deadletters",
+        visible_alias = "deadletter",
+        visible_alias = "dl"
+    )]
+    DeadLetters {
+        /// Worker URL (default: the worker context, or http://localhost:8080)
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Print the raw JSON, including inputs and traces
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 /// Arguments for the delete command
@@ -182,15 +510,52 @@ pub struct DeleteArgs {
 
 #[derive(Subcommand, Debug)]
 pub enum DeleteResource {
-    /// Delete a pipeline
+    /// Delete a pipeline, tearing down its runners on the workers
     #[command(name = "pipeline", visible_alias = "pl")]
     Pipeline {
         /// Pipeline name
         name: String,
 
-        /// Namespace
-        #[arg(short, long, default_value = "default")]
-        namespace: String,
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+
+        /// Delete even if the pipeline has the deletionProtection annotation
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Delete a job and its results, stopping it if it is running
+    #[command(name = "job")]
+    Job {
+        /// Job name
+        name: String,
+
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+    },
+
+    /// Delete a virtual endpoint, leaving its pipelines running
+    #[command(name = "endpoint", visible_alias = "ep")]
+    Endpoint {
+        /// Endpoint name
+        name: String,
+
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+    },
+
+    /// Delete a secret; runners already started with it keep their values
+    #[command(name = "secret")]
+    Secret {
+        /// Secret name
+        name: String,
+
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
     },
 
     /// Delete a node
@@ -199,6 +564,126 @@ pub enum DeleteResource {
         /// Node name
         name: String,
     },
+
+    /// Delete a node pool no pipeline targets; its nodes are left as they are
+    #[command(name = "nodepool", visible_alias = "np")]
+    NodePool {
+        /// Node pool name
+        name: String,
+    },
+
+    /// Unregister a region no pipeline is delegated to; what it runs is
+    /// left alone
+    #[command(name = "region")]
+    Region {
+        /// Region name
+        name: String,
+    },
+}
+
+/// Arguments for the create command
+#[derive(Parser, Debug)]
+pub struct CreateArgs {
+    /// Resource type to create
+    #[command(subcommand)]
+    pub resource: CreateResource,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CreateResource {
+    /// Create a secret, stored encrypted on the control plane
+    #[command(name = "secret")]
+    Secret {
+        #[command(subcommand)]
+        kind: SecretKind,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SecretKind {
+    /// Create a secret from literal values and files
+    Generic {
+        /// Secret name
+        name: String,
+
+        /// A key and its value, e.g. --from-literal=key=sk-... (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", required_unless_present = "from_file")]
+        from_literal: Vec<String>,
+
+        /// A key and a file holding its value (repeatable)
+        #[arg(long, value_name = "KEY=PATH")]
+        from_file: Vec<String>,
+
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+    },
+}
+
+/// Arguments for the edit command
+#[derive(Parser, Debug)]
+pub struct EditArgs {
+    /// Resource type and name (e.g., "pipeline my-pipeline")
+    #[command(subcommand)]
+    pub resource: EditResource,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EditResource {
+    /// Edit a pipeline
+    #[command(name = "pipeline", visible_alias = "pl")]
+    Pipeline {
+        /// Pipeline name
+        name: String,
+
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+    },
+
+    /// Edit a node's labels, annotations and spec
+    #[command(name = "node", visible_alias = "no")]
+    Node {
+        /// Node name
+        name: String,
+    },
+}
+
+/// Arguments for the label and annotate commands
+#[derive(Parser, Debug)]
+pub struct MetadataArgs {
+    /// Resource type, name and changes (e.g., "node gpu-1 gpu=a100")
+    #[command(subcommand)]
+    pub resource: MetadataResource,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MetadataResource {
+    /// Change a pipeline's metadata
+    #[command(name = "pipeline", visible_alias = "pl")]
+    Pipeline {
+        /// Pipeline name
+        name: String,
+
+        /// KEY=VALUE to set a key, KEY- to remove it
+        #[arg(required = true, value_name = "KEY=VALUE|KEY-")]
+        changes: Vec<String>,
+
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+    },
+
+    /// Change a node's metadata
+    #[command(name = "node", visible_alias = "no")]
+    Node {
+        /// Node name
+        name: String,
+
+        /// KEY=VALUE to set a key, KEY- to remove it
+        #[arg(required = true, value_name = "KEY=VALUE|KEY-")]
+        changes: Vec<String>,
+    },
 }
 
 /// Arguments for the scale command
@@ -211,9 +696,67 @@ pub struct ScaleArgs {
     #[arg(long)]
     pub replicas: u32,
 
-    /// Namespace
-    #[arg(short, long, default_value = "default")]
-    pub namespace: String,
+    /// Namespace (default: the context's namespace, or "default")
+    #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+    pub namespace: Option<String>,
+}
+
+/// Arguments for the job command
+#[derive(Parser, Debug)]
+pub struct JobArgs {
+    #[command(subcommand)]
+    pub action: JobAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum JobAction {
+    /// Create a job that sends prompts to a pipeline
+    ///
+    /// A local --input file is read and sent with the job. An http(s) URL
+    /// is fetched by the control plane when the job starts.
+    Create {
+        /// Job name
+        name: String,
+
+        /// Pipeline to send the prompts to
+        #[arg(long)]
+        pipeline: String,
+
+        /// File or URL with one prompt per line (plain text or JSONL with a "prompt" field)
+        #[arg(
+            short,
+            long,
+            required_unless_present = "prompt",
+            conflicts_with = "prompt"
+        )]
+        input: Option<String>,
+
+        /// A prompt to send (repeatable)
+        #[arg(long)]
+        prompt: Vec<String>,
+
+        /// Prompts sent to the pipeline at once
+        #[arg(long, default_value = "4")]
+        parallelism: usize,
+
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+    },
+
+    /// Show the outputs of a job's prompts
+    Results {
+        /// Job name
+        name: String,
+
+        /// Namespace (default: the context's namespace, or "default")
+        #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+
+        /// Print one JSON object per line instead
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 /// Arguments for the context command
@@ -234,6 +777,7 @@ pub enum ContextAction {
     /// Switch to a context
     Use {
         /// Context name
+        #[arg(add = ArgValueCandidates::new(complete_contexts))]
         name: String,
     },
 
@@ -247,26 +791,63 @@ pub enum ContextAction {
         url: String,
 
         /// API key for authentication
-        #[arg(long)]
+        #[arg(long, conflicts_with = "exec_command")]
         api_key: Option<String>,
+
+        /// Command that prints a short-lived bearer token (e.g. an SSO helper)
+        #[arg(long)]
+        exec_command: Option<String>,
+
+        /// Argument for the credential helper (repeatable)
+        #[arg(
+            long = "exec-arg",
+            requires = "exec_command",
+            allow_hyphen_values = true
+        )]
+        exec_args: Vec<String>,
     },
 
     /// Delete a context
     Delete {
         /// Context name
+        #[arg(add = ArgValueCandidates::new(complete_contexts))]
         name: String,
     },
+
+    /// Set the namespace commands use when -n isn't given
+    SetNamespace {
+        /// Namespace to use
+        #[arg(required_unless_present = "clear", add = ArgValueCandidates::new(complete_namespaces))]
+        namespace: Option<String>,
+
+        /// Context to change (default: the current context)
+        #[arg(long, add = ArgValueCandidates::new(complete_contexts))]
+        context: Option<String>,
+
+        /// Forget the context's namespace and fall back to "default"
+        #[arg(long, conflicts_with = "namespace")]
+        clear: bool,
+    },
 }
 
 /// Arguments for the logs command
 #[derive(Parser, Debug)]
 pub struct LogsArgs {
     /// Pipeline name
-    pub name: String,
+    #[arg(required_unless_present = "runner")]
+    pub name: Option<String>,
+
+    /// Show the output of a model runner on a worker instead
+    #[arg(long, conflicts_with = "name")]
+    pub runner: Option<String>,
+
+    /// Worker URL for --runner (default: the worker context, or http://localhost:8080)
+    #[arg(long, requires = "runner")]
+    pub url: Option<String>,
 
-    /// Namespace
-    #[arg(short, long, default_value = "default")]
-    pub namespace: String,
+    /// Namespace (default: the context's namespace, or "default")
+    #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+    pub namespace: Option<String>,
 
     /// Follow logs (like tail -f)
     #[arg(short, long)]
@@ -277,42 +858,193 @@ pub struct LogsArgs {
     pub tail: usize,
 }
 
-/// Arguments for the validate command
+/// Arguments for the status command
 #[derive(Parser, Debug)]
-pub struct ValidateArgs {
-    /// Path to the composition file
-    pub file: PathBuf,
+pub struct StatusArgs {
+    /// Keep refreshing the status in place until Ctrl+C
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Seconds between refreshes in watch mode
+    #[arg(long, default_value = "2", value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
 }
 
-/// Arguments for the legacy run command
+/// Arguments for the ui command
 #[derive(Parser, Debug)]
-pub struct RunArgs {
-    /// Path to the composition file (JSON or JSONC)
-    pub composition_file: PathBuf,
+pub struct UiArgs {
+    /// Seconds between refreshes
+    #[arg(long, default_value = "2", value_parser = clap::value_parser!(u64).range(1..))]
+    pub refresh: u64,
+}
 
-    /// Dry-run mode: validate config and show pipeline without running
-    #[arg(long)]
-    pub dry_run: bool,
+/// Arguments for the trace command
+#[derive(Parser, Debug)]
+pub struct TraceArgs {
+    /// Request ID (returned in the x-request-id response header)
+    pub request_id: String,
 
-    /// Override the default bind address
-    #[arg(long, value_name = "ADDR")]
-    pub bind_addr: Option<String>,
+    /// Worker URL (default: the worker context, or http://localhost:8080)
+    #[arg(long)]
+    pub url: Option<String>,
 
-    /// Override the starting port
-    #[arg(short, long, value_name = "PORT")]
-    pub port: Option<u16>,
+    /// Print the raw JSON trace
+    #[arg(long)]
+    pub json: bool,
+}
 
-    /// Path to a .env file for loading API keys
-    #[arg(long, value_name = "FILE")]
-    pub env_file: Option<PathBuf>,
+/// Arguments for the requeue command
+#[derive(Parser, Debug)]
+pub struct RequeueArgs {
+    /// Request IDs to requeue (see `llmnet get deadletters`)
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    pub request_ids: Vec<String>,
 
-    /// Request timeout in seconds
-    #[arg(long, default_value = "30")]
-    pub timeout: u64,
+    /// Requeue every dead letter
+    #[arg(long)]
+    pub all: bool,
 
-    /// Maximum concurrent requests per node
-    #[arg(long, default_value = "100")]
-    pub max_concurrent: usize,
+    /// Worker URL (default: the worker context, or http://localhost:8080)
+    #[arg(long)]
+    pub url: Option<String>,
+}
+
+/// Arguments for the replay command
+#[derive(Parser, Debug)]
+pub struct ReplayArgs {
+    /// Request ID (see `llmnet trace` and `llmnet get deadletters`)
+    pub request_id: String,
+
+    /// Run only this handler node, on the request's original prompt
+    #[arg(long)]
+    pub node: Option<String>,
+
+    /// Worker URL (default: the worker context, or http://localhost:8080)
+    #[arg(long)]
+    pub url: Option<String>,
+}
+
+/// Arguments for the validate command
+#[derive(Parser, Debug)]
+pub struct ValidateArgs {
+    /// Path to the composition file
+    pub file: PathBuf,
+
+    /// Also check against the current context's cluster: secrets, nodes,
+    /// runners and external endpoints
+    #[arg(long)]
+    pub cluster: bool,
+
+    /// Namespace the pipeline would be deployed to (with --cluster)
+    #[arg(short, long, add = ArgValueCandidates::new(complete_namespaces))]
+    pub namespace: Option<String>,
+
+    #[command(flatten)]
+    pub values: ValuesArgs,
+}
+
+/// Arguments for the prune command
+#[derive(Parser, Debug)]
+pub struct PruneArgs {
+    /// What to prune
+    #[command(subcommand)]
+    pub resource: PruneResource,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PruneResource {
+    /// Evict the least recently used cached models down to a disk budget
+    /// (worker mode)
+    #[command(name = "models", visible_alias = "model")]
+    Models {
+        /// Disk space to keep, e.g. 50Gi (default: the worker's
+        /// --model-cache-budget)
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        budget: Option<u64>,
+
+        /// Show what would be evicted without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Arguments for the legacy run command
+#[derive(Parser, Debug)]
+pub struct RunArgs {
+    /// Path to the composition file (JSON or JSONC)
+    pub composition_file: PathBuf,
+
+    /// Dry-run mode: validate config and show pipeline without running
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Override the default bind address
+    #[arg(long, value_name = "ADDR")]
+    pub bind_addr: Option<String>,
+
+    /// Override the starting port
+    #[arg(short, long, value_name = "PORT")]
+    pub port: Option<u16>,
+
+    /// Path to a .env file for loading API keys
+    #[arg(long, value_name = "FILE")]
+    pub env_file: Option<PathBuf>,
+
+    /// Request timeout in seconds
+    #[arg(long, default_value = "30")]
+    pub timeout: u64,
+
+    /// Maximum concurrent requests per node
+    #[arg(long, default_value = "100")]
+    pub max_concurrent: usize,
+
+    /// Keep failed requests in this JSON file across restarts (kept in
+    /// memory otherwise)
+    #[arg(long, value_name = "FILE")]
+    pub dead_letter_file: Option<PathBuf>,
+
+    /// Log this percentage of requests (0-100); off unless set
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percent)]
+    pub request_log_sample: Option<f64>,
+
+    /// Redact text matching this regex from logged requests, or a preset:
+    /// email, api-key (repeatable)
+    #[arg(long, value_name = "PATTERN")]
+    pub request_log_redact: Vec<String>,
+
+    /// Append logged requests to this JSON Lines file
+    #[arg(long, value_name = "FILE")]
+    pub request_log_file: Option<PathBuf>,
+
+    /// Ship logged requests to this control plane
+    #[arg(long, value_name = "URL")]
+    pub request_log_url: Option<String>,
+
+    /// Key sent with the shipped requests, if the control plane requires one
+    #[arg(long, env = "LLMNET_API_KEY", hide_env_values = true)]
+    pub api_key: Option<String>,
+
+    /// Don't reload the composition when its file changes
+    #[arg(long)]
+    pub no_watch: bool,
+
+    #[command(flatten)]
+    pub values: ValuesArgs,
+}
+
+fn parse_size(value: &str) -> Result<u64, String> {
+    crate::cluster::parse_quantity(value)
+        .ok_or_else(|| format!("expected a size like 2Gi or 512Mi, got '{}'", value))
+}
+
+fn parse_percent(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(percent),
+        _ => Err(format!(
+            "expected a percentage from 0 to 100, got '{}'",
+            value
+        )),
+    }
 }
 
 /// Arguments for the stop command
@@ -333,6 +1065,227 @@ pub struct KillArgs {
     pub name: String,
 }
 
+/// Arguments for the config command
+#[derive(Parser, Debug)]
+pub struct ClusterConfigArgs {
+    #[command(subcommand)]
+    pub action: ClusterConfigAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClusterConfigAction {
+    /// Show or set the weights nodes are scored with for scheduling
+    ///
+    /// Without options the current weights are shown. Options change only
+    /// the weights they name, starting from the current weights or --preset.
+    Scoring(ScoringArgs),
+
+    /// Show or replace the alert rules and the sinks they notify
+    ///
+    /// Without -f the current rules are printed as YAML, ready to edit and
+    /// apply again.
+    Alerting(AlertingArgs),
+}
+
+/// Arguments for `config scoring`
+#[derive(Args, Debug, Default)]
+pub struct ScoringArgs {
+    /// Start from a preset instead of the current weights
+    #[arg(long, value_parser = SCORING_PRESETS)]
+    pub preset: Option<String>,
+
+    #[arg(long)]
+    pub cpu: Option<f64>,
+
+    #[arg(long)]
+    pub memory: Option<f64>,
+
+    #[arg(long)]
+    pub gpu: Option<f64>,
+
+    #[arg(long)]
+    pub disk: Option<f64>,
+
+    /// Weight for active requests on the node
+    #[arg(long)]
+    pub load: Option<f64>,
+
+    /// Weight for the node's average request latency
+    #[arg(long)]
+    pub latency: Option<f64>,
+}
+
+impl ScoringArgs {
+    /// Whether any option asks for a change
+    pub fn is_update(&self) -> bool {
+        self.preset.is_some()
+            || [
+                self.cpu,
+                self.memory,
+                self.gpu,
+                self.disk,
+                self.load,
+                self.latency,
+            ]
+            .iter()
+            .any(Option::is_some)
+    }
+
+    /// The weights to set, given the cluster's current ones
+    pub fn apply(&self, current: ScoringWeights) -> ScoringWeights {
+        let base = self
+            .preset
+            .as_deref()
+            .and_then(ScoringWeights::preset)
+            .unwrap_or(current);
+        ScoringWeights {
+            cpu: self.cpu.unwrap_or(base.cpu),
+            memory: self.memory.unwrap_or(base.memory),
+            gpu: self.gpu.unwrap_or(base.gpu),
+            disk: self.disk.unwrap_or(base.disk),
+            load: self.load.unwrap_or(base.load),
+            latency: self.latency.unwrap_or(base.latency),
+        }
+    }
+}
+
+/// Arguments for `config alerting`
+#[derive(Args, Debug, Default)]
+pub struct AlertingArgs {
+    /// YAML or JSON file with the rules and sinks to set
+    #[arg(short = 'f', long)]
+    pub file: Option<PathBuf>,
+}
+
+/// Arguments for the install command
+#[derive(Parser, Debug)]
+#[command(group(ArgGroup::new("role").required(true).args(["worker", "control_plane"])))]
+pub struct InstallArgs {
+    /// Install a worker that registers with --control-plane-url
+    #[arg(long, requires = "control_plane_url")]
+    pub worker: bool,
+
+    /// Install the control plane
+    #[arg(long)]
+    pub control_plane: bool,
+
+    /// Control plane URL the worker registers with
+    #[arg(long)]
+    pub control_plane_url: Option<String>,
+
+    /// Node name the worker registers as (default: the hostname)
+    #[arg(long)]
+    pub node_name: Option<String>,
+
+    /// Address the control plane reaches the worker at
+    #[arg(long)]
+    pub advertise_addr: Option<String>,
+
+    /// Port to listen on (default: 8181 for control plane, 8080 for worker)
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// .env file with API keys, loaded by the service on start
+    #[arg(long, value_name = "FILE")]
+    pub env_file: Option<PathBuf>,
+
+    /// Service name (default: llmnet-worker or llmnet-control-plane)
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Service manager to install with (default: this platform's)
+    #[arg(long, value_enum)]
+    pub manager: Option<ServiceManager>,
+
+    /// llmnet binary the service runs (default: this one)
+    #[arg(long, value_name = "PATH")]
+    pub binary: Option<PathBuf>,
+
+    /// Print the service file and commands instead of installing
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// More arguments for `llmnet serve`, after `--`
+    #[arg(last = true, value_name = "SERVE_ARGS")]
+    pub serve_args: Vec<String>,
+}
+
+impl InstallArgs {
+    /// The service running `llmnet serve` with these options
+    pub fn service_spec(&self, binary: PathBuf) -> ServiceSpec {
+        let (role, mut args) = match &self.control_plane_url {
+            Some(url) if self.worker => (
+                "worker",
+                vec![
+                    "serve".to_string(),
+                    "--control-plane-url".to_string(),
+                    url.clone(),
+                ],
+            ),
+            _ => (
+                "control-plane",
+                vec!["serve".to_string(), "--control-plane".to_string()],
+            ),
+        };
+        let options = [
+            ("--node-name", self.node_name.clone()),
+            ("--advertise-addr", self.advertise_addr.clone()),
+            ("--port", self.port.map(|p| p.to_string())),
+            (
+                "--env-file",
+                self.env_file.as_ref().map(|f| f.display().to_string()),
+            ),
+        ];
+        for (flag, value) in options {
+            if let Some(value) = value {
+                args.extend([flag.to_string(), value]);
+            }
+        }
+        args.extend(self.serve_args.iter().cloned());
+
+        ServiceSpec {
+            name: self
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("llmnet-{}", role)),
+            description: format!("LLMNet {}", role.replace('-', " ")),
+            binary,
+            args,
+        }
+    }
+}
+
+/// Arguments for the completion command
+#[derive(Parser, Debug)]
+pub struct CompletionArgs {
+    /// Shell to generate the script for
+    #[arg(value_enum)]
+    pub shell: CompletionShell,
+
+    /// Generate a self-contained script that doesn't call back into llmnet
+    /// (no context or namespace suggestions)
+    #[arg(long = "static")]
+    pub static_script: bool,
+}
+
+/// Arguments for the docs command
+#[derive(Parser, Debug)]
+pub struct DocsArgs {
+    #[command(subcommand)]
+    pub action: DocsAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DocsAction {
+    /// Generate man pages
+    Man {
+        /// Write a page per subcommand into this directory instead of
+        /// printing the top-level page
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -343,18 +1296,145 @@ mod tests {
         match cli.command {
             Commands::Serve(args) => {
                 assert!(args.control_plane);
+                assert_eq!(args.grpc_port, None);
             }
             _ => panic!("Expected Serve command"),
         }
     }
 
+    #[test]
+    fn test_parse_serve_grpc_port() {
+        let cli = Cli::parse_from(["llmnet", "serve", "--control-plane", "--grpc-port", "8182"]);
+        match cli.command {
+            Commands::Serve(args) => assert_eq!(args.grpc_port, Some(8182)),
+            _ => panic!("Expected Serve command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_serve_audit_log() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "serve",
+            "--control-plane",
+            "--audit-log",
+            "/var/log/llmnet/audit.jsonl",
+            "--audit-retention-days",
+            "30",
+        ]);
+        match cli.command {
+            Commands::Serve(args) => {
+                assert_eq!(
+                    args.audit_log,
+                    Some(PathBuf::from("/var/log/llmnet/audit.jsonl"))
+                );
+                assert_eq!(args.audit_retention_days, 30);
+            }
+            _ => panic!("Expected Serve command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "serve", "--control-plane"]);
+        match cli.command {
+            Commands::Serve(args) => {
+                assert_eq!(args.audit_log, None);
+                assert_eq!(args.audit_retention_days, DEFAULT_AUDIT_RETENTION_DAYS);
+            }
+            _ => panic!("Expected Serve command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_serve_state_file() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "serve",
+            "--state-file",
+            "/var/lib/llmnet/worker-state.json",
+        ]);
+        match cli.command {
+            Commands::Serve(args) => assert_eq!(
+                args.state_file,
+                Some(PathBuf::from("/var/lib/llmnet/worker-state.json"))
+            ),
+            _ => panic!("Expected Serve command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_serve_heartbeat_interval() {
+        let cli = Cli::parse_from(["llmnet", "serve", "--heartbeat-interval", "10"]);
+        match cli.command {
+            Commands::Serve(args) => assert_eq!(args.heartbeat_interval, 10),
+            _ => panic!("Expected Serve command"),
+        }
+        match Cli::parse_from(["llmnet", "serve"]).command {
+            Commands::Serve(args) => {
+                assert_eq!(args.heartbeat_interval, HEARTBEAT_INTERVAL_SECS);
+                assert_eq!(args.drain_timeout, DEFAULT_DRAIN_TIMEOUT_SECS);
+            }
+            _ => panic!("Expected Serve command"),
+        }
+        assert!(Cli::try_parse_from(["llmnet", "serve", "--heartbeat-interval", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_serve_reservations() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "serve",
+            "--reserve-cpu",
+            "1",
+            "--reserve-memory",
+            "2Gi",
+        ]);
+        match cli.command {
+            Commands::Serve(args) => {
+                assert_eq!(args.reserve_cpu, 1);
+                assert_eq!(args.reserve_memory, 2 << 30);
+                assert_eq!(args.reserve_gpu, 0);
+                assert!(!args.no_gc);
+            }
+            _ => panic!("Expected Serve command"),
+        }
+        assert!(Cli::try_parse_from(["llmnet", "serve", "--reserve-memory", "lots"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_model_cache() {
+        let cli = Cli::parse_from(["llmnet", "get", "models", "--context", "worker"]);
+        assert_eq!(cli.context.as_deref(), Some("worker"));
+        assert!(matches!(
+            cli.command,
+            Commands::Get(GetArgs {
+                resource: GetResource::Models { json: false }
+            })
+        ));
+
+        let cli = Cli::parse_from(["llmnet", "prune", "models", "--budget", "50Gi", "--dry-run"]);
+        match cli.command {
+            Commands::Prune(PruneArgs {
+                resource: PruneResource::Models { budget, dry_run },
+            }) => {
+                assert_eq!(budget, Some(50 << 30));
+                assert!(dry_run);
+            }
+            _ => panic!("Expected Prune command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "serve", "--model-cache-budget", "100Gi"]);
+        match cli.command {
+            Commands::Serve(args) => assert_eq!(args.model_cache_budget, Some(100 << 30)),
+            _ => panic!("Expected Serve command"),
+        }
+    }
+
     #[test]
     fn test_parse_deploy() {
         let cli = Cli::parse_from(["llmnet", "deploy", "pipeline.json"]);
         match cli.command {
             Commands::Deploy(args) => {
                 assert_eq!(args.file, PathBuf::from("pipeline.json"));
-                assert_eq!(args.namespace, "default");
+                assert_eq!(args.namespace, None);
             }
             _ => panic!("Expected Deploy command"),
         }
@@ -372,6 +1452,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_get_pipeline_composition() {
+        let cli = Cli::parse_from(["llmnet", "get", "pipeline", "chat", "-o", "composition"]);
+        match cli.command {
+            Commands::Get(args) => match args.resource {
+                GetResource::Pipelines { name, output, .. } => {
+                    assert_eq!(name.as_deref(), Some("chat"));
+                    assert_eq!(output, Some(PipelineOutput::Composition));
+                }
+                _ => panic!("Expected Pipelines resource"),
+            },
+            _ => panic!("Expected Get command"),
+        }
+
+        // An output format needs a pipeline to print
+        assert!(Cli::try_parse_from(["llmnet", "get", "pipelines", "-o", "json"]).is_err());
+    }
+
     #[test]
     fn test_parse_get_nodes() {
         let cli = Cli::parse_from(["llmnet", "get", "nodes"]);
@@ -398,6 +1496,85 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_context_set_namespace() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "context",
+            "set-namespace",
+            "team-a",
+            "--context",
+            "prod",
+        ]);
+        match cli.command {
+            Commands::Context(args) => match args.action {
+                ContextAction::SetNamespace {
+                    namespace,
+                    context,
+                    clear,
+                } => {
+                    assert_eq!(namespace.as_deref(), Some("team-a"));
+                    assert_eq!(context.as_deref(), Some("prod"));
+                    assert!(!clear);
+                }
+                _ => panic!("Expected SetNamespace action"),
+            },
+            _ => panic!("Expected Context command"),
+        }
+
+        assert!(Cli::try_parse_from(["llmnet", "context", "set-namespace"]).is_err());
+        assert!(Cli::try_parse_from(["llmnet", "context", "set-namespace", "--clear"]).is_ok());
+        assert!(
+            Cli::try_parse_from(["llmnet", "context", "set-namespace", "a", "--clear"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_context_add_exec() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "context",
+            "add",
+            "sso",
+            "--url",
+            "https://llmnet.example.com",
+            "--exec-command",
+            "sso-helper",
+            "--exec-arg",
+            "token",
+            "--exec-arg",
+            "--json",
+        ]);
+        match cli.command {
+            Commands::Context(args) => match args.action {
+                ContextAction::Add {
+                    exec_command,
+                    exec_args,
+                    ..
+                } => {
+                    assert_eq!(exec_command.as_deref(), Some("sso-helper"));
+                    assert_eq!(exec_args, vec!["token", "--json"]);
+                }
+                _ => panic!("Expected Add action"),
+            },
+            _ => panic!("Expected Context command"),
+        }
+
+        let result = Cli::try_parse_from([
+            "llmnet",
+            "context",
+            "add",
+            "sso",
+            "--url",
+            "https://llmnet.example.com",
+            "--api-key",
+            "secret",
+            "--exec-command",
+            "sso-helper",
+        ]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_parse_context_add() {
         let cli = Cli::parse_from([
@@ -437,13 +1614,212 @@ mod tests {
         let cli = Cli::parse_from(["llmnet", "delete", "pipeline", "my-pipeline"]);
         match cli.command {
             Commands::Delete(args) => match args.resource {
-                DeleteResource::Pipeline { name, .. } => {
+                DeleteResource::Pipeline { name, force, .. } => {
                     assert_eq!(name, "my-pipeline");
+                    assert!(!force);
                 }
                 _ => panic!("Expected Pipeline delete"),
             },
             _ => panic!("Expected Delete command"),
         }
+
+        let cli = Cli::parse_from(["llmnet", "delete", "pipeline", "my-pipeline", "--force"]);
+        match cli.command {
+            Commands::Delete(args) => match args.resource {
+                DeleteResource::Pipeline { force, .. } => assert!(force),
+                _ => panic!("Expected Pipeline delete"),
+            },
+            _ => panic!("Expected Delete command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_create_secret() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "create",
+            "secret",
+            "generic",
+            "openai",
+            "--from-literal=key=sk-a=b",
+            "--from-file",
+            "org=org.txt",
+            "-n",
+            "prod",
+        ]);
+        match cli.command {
+            Commands::Create(args) => match args.resource {
+                CreateResource::Secret {
+                    kind:
+                        SecretKind::Generic {
+                            name,
+                            from_literal,
+                            from_file,
+                            namespace,
+                        },
+                } => {
+                    assert_eq!(name, "openai");
+                    assert_eq!(from_literal, ["key=sk-a=b"]);
+                    assert_eq!(from_file, ["org=org.txt"]);
+                    assert_eq!(namespace.as_deref(), Some("prod"));
+                }
+            },
+            _ => panic!("Expected Create command"),
+        }
+
+        // A secret needs at least one value
+        assert!(Cli::try_parse_from(["llmnet", "create", "secret", "generic", "openai"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_edit() {
+        let cli = Cli::parse_from(["llmnet", "edit", "pl", "chatbot", "-n", "prod"]);
+        match cli.command {
+            Commands::Edit(args) => match args.resource {
+                EditResource::Pipeline { name, namespace } => {
+                    assert_eq!(name, "chatbot");
+                    assert_eq!(namespace.as_deref(), Some("prod"));
+                }
+                _ => panic!("Expected Pipeline edit"),
+            },
+            _ => panic!("Expected Edit command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "edit", "node", "gpu-1"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Edit(EditArgs {
+                resource: EditResource::Node { .. }
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_label() {
+        let cli = Cli::parse_from(["llmnet", "label", "node", "gpu-1", "gpu=a100", "spot-"]);
+        match cli.command {
+            Commands::Label(args) => match args.resource {
+                MetadataResource::Node { name, changes } => {
+                    assert_eq!(name, "gpu-1");
+                    assert_eq!(changes, ["gpu=a100", "spot-"]);
+                }
+                _ => panic!("Expected Node label"),
+            },
+            _ => panic!("Expected Label command"),
+        }
+
+        assert!(
+            Cli::try_parse_from(["llmnet", "annotate", "pl", "chatbot", "-n", "prod"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_logs_runner() {
+        let cli = Cli::try_parse_from(["llmnet", "logs", "chatbot", "-f"]).unwrap();
+        match cli.command {
+            Commands::Logs(args) => {
+                assert_eq!(args.name.as_deref(), Some("chatbot"));
+                assert!(args.follow);
+                assert!(args.runner.is_none());
+            }
+            _ => panic!("Expected Logs command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "llmnet",
+            "logs",
+            "--runner",
+            "llama-1",
+            "--url",
+            "http://10.0.0.5:8080",
+            "--tail",
+            "20",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Logs(args) => {
+                assert!(args.name.is_none());
+                assert_eq!(args.runner.as_deref(), Some("llama-1"));
+                assert_eq!(args.url.as_deref(), Some("http://10.0.0.5:8080"));
+                assert_eq!(args.tail, 20);
+            }
+            _ => panic!("Expected Logs command"),
+        }
+
+        assert!(Cli::try_parse_from(["llmnet", "logs"]).is_err());
+        assert!(Cli::try_parse_from(["llmnet", "logs", "chatbot", "--runner", "llama"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_job() {
+        let cli = Cli::try_parse_from([
+            "llmnet",
+            "job",
+            "create",
+            "nightly",
+            "--pipeline",
+            "chat",
+            "-i",
+            "prompts.jsonl",
+            "-n",
+            "prod",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Job(JobArgs {
+                action:
+                    JobAction::Create {
+                        name,
+                        pipeline,
+                        input,
+                        parallelism,
+                        namespace,
+                        ..
+                    },
+            }) => {
+                assert_eq!(name, "nightly");
+                assert_eq!(pipeline, "chat");
+                assert_eq!(input.as_deref(), Some("prompts.jsonl"));
+                assert_eq!(parallelism, 4);
+                assert_eq!(namespace.as_deref(), Some("prod"));
+            }
+            _ => panic!("Expected job create"),
+        }
+
+        // Needs prompts from somewhere, but not from both places
+        assert!(Cli::try_parse_from(["llmnet", "job", "create", "j", "--pipeline", "p"]).is_err());
+        assert!(Cli::try_parse_from([
+            "llmnet",
+            "job",
+            "create",
+            "j",
+            "--pipeline",
+            "p",
+            "-i",
+            "f",
+            "--prompt",
+            "hi"
+        ])
+        .is_err());
+
+        let cli = Cli::try_parse_from(["llmnet", "job", "results", "nightly", "--json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Job(JobArgs {
+                action: JobAction::Results { json: true, .. }
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["llmnet", "get", "jobs", "-A"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Get(GetArgs {
+                resource: GetResource::Jobs {
+                    all_namespaces: true,
+                    ..
+                }
+            })
+        ));
     }
 
     #[test]
@@ -452,9 +1828,89 @@ mod tests {
         match cli.command {
             Commands::Run(args) => {
                 assert_eq!(args.composition_file, PathBuf::from("config.json"));
+                assert!(args.request_log_sample.is_none());
+            }
+            _ => panic!("Expected Run command"),
+        }
+
+        let cli = Cli::parse_from([
+            "llmnet",
+            "run",
+            "config.json",
+            "--request-log-sample",
+            "12.5",
+            "--request-log-redact",
+            "email",
+            "--request-log-redact",
+            r"\d{16}",
+        ]);
+        match cli.command {
+            Commands::Run(args) => {
+                assert_eq!(args.request_log_sample, Some(12.5));
+                assert_eq!(args.request_log_redact, ["email", r"\d{16}"]);
             }
             _ => panic!("Expected Run command"),
         }
+        assert!(
+            Cli::try_parse_from(["llmnet", "run", "c.json", "--request-log-sample", "150"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_diff() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "diff",
+            "pipeline.yaml",
+            "-n",
+            "prod",
+            "--no-color",
+        ]);
+        match cli.command {
+            Commands::Diff(args) => {
+                assert_eq!(args.file, PathBuf::from("pipeline.yaml"));
+                assert_eq!(args.namespace.as_deref(), Some("prod"));
+                assert!(args.no_color);
+            }
+            _ => panic!("Expected Diff command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_values_flags() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "deploy",
+            "pipeline.json",
+            "-f",
+            "base.yaml",
+            "--values",
+            "prod.yaml",
+            "--set",
+            "router.replicas=3",
+            "--set",
+            "api.key=sk-test",
+        ]);
+        match cli.command {
+            Commands::Deploy(args) => {
+                assert_eq!(
+                    args.values.files,
+                    vec![PathBuf::from("base.yaml"), PathBuf::from("prod.yaml")]
+                );
+                assert_eq!(
+                    args.values.sets,
+                    vec!["router.replicas=3", "api.key=sk-test"]
+                );
+            }
+            _ => panic!("Expected Deploy command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "validate", "c.json", "--set", "a=1"]);
+        match cli.command {
+            Commands::Validate(args) => assert_eq!(args.values.sets, vec!["a=1"]),
+            _ => panic!("Expected Validate command"),
+        }
     }
 
     #[test]
@@ -462,4 +1918,226 @@ mod tests {
         let cli = Cli::parse_from(["llmnet", "-vvv", "status"]);
         assert_eq!(cli.verbose, 3);
     }
+
+    #[test]
+    fn test_parse_status_watch() {
+        let cli = Cli::parse_from(["llmnet", "status"]);
+        match cli.command {
+            Commands::Status(args) => {
+                assert!(!args.watch);
+                assert_eq!(args.interval, 2);
+            }
+            _ => panic!("Expected Status command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "status", "-w", "--interval", "5"]);
+        match cli.command {
+            Commands::Status(args) => {
+                assert!(args.watch);
+                assert_eq!(args.interval, 5);
+            }
+            _ => panic!("Expected Status command"),
+        }
+
+        assert!(Cli::try_parse_from(["llmnet", "status", "--interval", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_ui() {
+        let cli = Cli::parse_from(["llmnet", "ui", "--refresh", "5"]);
+        match cli.command {
+            Commands::Ui(args) => assert_eq!(args.refresh, 5),
+            _ => panic!("Expected Ui command"),
+        }
+        assert!(Cli::try_parse_from(["llmnet", "ui", "--refresh", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_trace() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "trace",
+            "5c56c793-69f3-4fbf-87e6-c4bf54c28c26",
+            "--url",
+            "http://worker:8080",
+        ]);
+        match cli.command {
+            Commands::Trace(args) => {
+                assert_eq!(args.request_id, "5c56c793-69f3-4fbf-87e6-c4bf54c28c26");
+                assert_eq!(args.url.as_deref(), Some("http://worker:8080"));
+                assert!(!args.json);
+            }
+            _ => panic!("Expected Trace command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_dead_letters_and_requeue() {
+        let cli = Cli::parse_from(["llmnet", "get", "dl", "--url", "http://worker:8080"]);
+        match cli.command {
+            Commands::Get(GetArgs {
+                resource: GetResource::DeadLetters { url, json },
+            }) => {
+                assert_eq!(url.as_deref(), Some("http://worker:8080"));
+                assert!(!json);
+            }
+            _ => panic!("Expected Get deadletters command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "get", "rl", "--source", "w1", "--failed"]);
+        match cli.command {
+            Commands::Get(GetArgs {
+                resource:
+                    GetResource::RequestLogs {
+                        source,
+                        failed,
+                        limit,
+                        json,
+                    },
+            }) => {
+                assert_eq!(source.as_deref(), Some("w1"));
+                assert!(failed && !json);
+                assert_eq!(limit, 50);
+            }
+            _ => panic!("Expected Get requestlogs command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "requeue", "a", "b"]);
+        match cli.command {
+            Commands::Requeue(args) => {
+                assert_eq!(args.request_ids, ["a", "b"]);
+                assert!(!args.all);
+            }
+            _ => panic!("Expected Requeue command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "requeue", "--all"]);
+        assert!(matches!(cli.command, Commands::Requeue(args) if args.all));
+        assert!(Cli::try_parse_from(["llmnet", "requeue"]).is_err());
+        assert!(Cli::try_parse_from(["llmnet", "requeue", "a", "--all"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_completion_and_docs() {
+        let cli = Cli::parse_from(["llmnet", "completion", "powershell", "--static"]);
+        match cli.command {
+            Commands::Completion(args) => {
+                assert_eq!(args.shell, CompletionShell::PowerShell);
+                assert!(args.static_script);
+            }
+            _ => panic!("Expected Completion command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "docs", "man", "--out-dir", "man"]);
+        match cli.command {
+            Commands::Docs(DocsArgs {
+                action: DocsAction::Man { out_dir },
+            }) => assert_eq!(out_dir, Some(PathBuf::from("man"))),
+            _ => panic!("Expected Docs command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_config_scoring() {
+        let cli = Cli::parse_from(["llmnet", "config", "scoring"]);
+        match cli.command {
+            Commands::ClusterConfig(ClusterConfigArgs {
+                action: ClusterConfigAction::Scoring(args),
+            }) => assert!(!args.is_update()),
+            _ => panic!("Expected config scoring command"),
+        }
+
+        let cli = Cli::parse_from([
+            "llmnet",
+            "config",
+            "scoring",
+            "--preset",
+            "gpu-heavy",
+            "--latency",
+            "0.2",
+        ]);
+        let Commands::ClusterConfig(ClusterConfigArgs {
+            action: ClusterConfigAction::Scoring(args),
+        }) = cli.command
+        else {
+            panic!("Expected config scoring command");
+        };
+        assert!(args.is_update());
+        let weights = args.apply(ScoringWeights::default());
+        assert_eq!(weights.gpu, ScoringWeights::gpu_heavy().gpu);
+        assert_eq!(weights.latency, 0.2);
+
+        // Without a preset, unnamed weights keep their current values
+        let args = ScoringArgs {
+            cpu: Some(0.5),
+            ..Default::default()
+        };
+        let current = ScoringWeights::cpu_heavy();
+        assert_eq!(args.apply(current.clone()).memory, current.memory);
+        assert_eq!(args.apply(current).cpu, 0.5);
+
+        assert!(Cli::try_parse_from(["llmnet", "config", "scoring", "--preset", "fast"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_install() {
+        let cli = Cli::parse_from([
+            "llmnet",
+            "install",
+            "--worker",
+            "--control-plane-url",
+            "http://cp:8181",
+            "--node-name",
+            "edge-1",
+            "--",
+            "--no-gc",
+        ]);
+        let Commands::Install(args) = cli.command else {
+            panic!("Expected install command");
+        };
+        let spec = args.service_spec(PathBuf::from("/usr/local/bin/llmnet"));
+        assert_eq!(spec.name, "llmnet-worker");
+        assert_eq!(
+            spec.args,
+            [
+                "serve",
+                "--control-plane-url",
+                "http://cp:8181",
+                "--node-name",
+                "edge-1",
+                "--no-gc"
+            ]
+        );
+
+        let cli = Cli::parse_from(["llmnet", "install", "--control-plane", "--name", "cp"]);
+        let Commands::Install(args) = cli.command else {
+            panic!("Expected install command");
+        };
+        let spec = args.service_spec(PathBuf::from("llmnet"));
+        assert_eq!(spec.name, "cp");
+        assert_eq!(spec.args, ["serve", "--control-plane"]);
+
+        // A worker needs a control plane to register with, and one role
+        assert!(Cli::try_parse_from(["llmnet", "install", "--worker"]).is_err());
+        assert!(Cli::try_parse_from(["llmnet", "install"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_config_alerting() {
+        let cli = Cli::parse_from(["llmnet", "config", "alerting", "-f", "alerts.yaml"]);
+        match cli.command {
+            Commands::ClusterConfig(ClusterConfigArgs {
+                action: ClusterConfigAction::Alerting(args),
+            }) => assert_eq!(args.file, Some(PathBuf::from("alerts.yaml"))),
+            _ => panic!("Expected config alerting command"),
+        }
+
+        let cli = Cli::parse_from(["llmnet", "get", "alerts"]);
+        assert!(matches!(
+            cli.command,
+            Commands::Get(GetArgs {
+                resource: GetResource::Alerts
+            })
+        ));
+    }
 }
@@ -0,0 +1,407 @@
+//! `llmnet validate --cluster`: checking a composition against the live
+//! cluster before deploying it
+//!
+//! On top of the offline validation it checks that:
+//! - every `$secrets.name.VAR` reference names a declared secret that resolves
+//! - some schedulable node can run the models' runners and fit the replicas,
+//!   and the `nodePool` it targets exists and admits its namespace
+//! - external endpoints answer, and serve the model names the nodes ask for
+//!
+//! Secrets from the environment or an env file are resolved on the worker,
+//! so one missing on this machine is only a warning; a Vault secret resolves
+//! the same everywhere and is an error.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::cluster::{admission_problems, Node, Pipeline};
+use crate::config::models::RunnerType;
+use crate::config::secrets::find_secret_references;
+use crate::config::{Composition, SecretSource, SecretsManager};
+
+use super::commands::ControlPlaneClient;
+
+/// How long to wait for an external endpoint to answer
+const ENDPOINT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of checking a composition against the cluster
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClusterCheck {
+    /// Problems that would stop the pipeline from running
+    pub problems: Vec<String>,
+    /// Things that may still work, such as secrets only set on the workers
+    pub warnings: Vec<String>,
+}
+
+/// An external endpoint and the model names nodes request from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointCheck {
+    pub model: String,
+    pub url: String,
+    pub interface: String,
+    pub api_key: Option<String>,
+    /// Model names sent in requests (a node's `model_override`, else the
+    /// model's key)
+    pub requested: Vec<String>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Secret references in a composition, by secret name
+pub fn secret_references(composition: &Composition) -> BTreeMap<String, BTreeSet<String>> {
+    let json = serde_json::to_string(composition).unwrap_or_default();
+    let mut references: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for reference in find_secret_references(&json) {
+        references
+            .entry(reference.secret_name)
+            .or_default()
+            .insert(reference.variable);
+    }
+    references
+}
+
+/// References to secrets the composition doesn't declare
+pub fn undeclared_secrets(composition: &Composition) -> Vec<String> {
+    secret_references(composition)
+        .keys()
+        .filter(|name| !composition.secrets.contains_key(*name))
+        .map(|name| format!("Secret '{}' is referenced but not declared", name))
+        .collect()
+}
+
+/// Models whose runner no schedulable node offers
+///
+/// Nothing is reported for a cluster with no schedulable nodes yet.
+pub fn runner_problems(composition: &Composition, nodes: &[Node]) -> Vec<String> {
+    let schedulable: Vec<&Node> = nodes.iter().filter(|n| n.can_schedule()).collect();
+    if schedulable.is_empty() {
+        return Vec::new();
+    }
+
+    let mut names: Vec<&String> = composition.models.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let runner = composition.models[name].to_config().runner;
+            let offered = runner == RunnerType::External
+                || schedulable
+                    .iter()
+                    .any(|n| n.supports_runners(std::slice::from_ref(&runner)));
+            (!offered).then(|| {
+                format!(
+                    "Model '{}' needs the {} runner, which no schedulable node has",
+                    name,
+                    runner.as_str()
+                )
+            })
+        })
+        .collect()
+}
+
+/// External endpoints of a composition and the names requested from each
+pub fn endpoint_checks(composition: &Composition) -> Vec<EndpointCheck> {
+    let mut checks: Vec<EndpointCheck> = Vec::new();
+    let mut names: Vec<&String> = composition.models.keys().collect();
+    names.sort();
+
+    for name in names {
+        let config = composition.models[name].to_config();
+        let Some(url) = config
+            .endpoint
+            .clone()
+            .filter(|_| config.runner == RunnerType::External)
+        else {
+            continue;
+        };
+        let mut requested: Vec<String> = composition
+            .architecture
+            .iter()
+            .filter(|node| node.model.as_deref() == Some(name.as_str()))
+            .map(|node| {
+                node.extra_options
+                    .get("model_override")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(name)
+                    .to_string()
+            })
+            .collect();
+        requested.sort();
+        requested.dedup();
+        checks.push(EndpointCheck {
+            model: name.clone(),
+            url,
+            interface: config.interface.clone(),
+            api_key: config.api_key.clone(),
+            requested,
+        });
+    }
+    checks
+}
+
+/// Requested model names missing from an OpenAI-compatible `/models` list
+///
+/// None when the body isn't such a list, so nothing can be said.
+pub fn missing_models(
+    requested: &[String],
+    models_body: &serde_json::Value,
+) -> Option<Vec<String>> {
+    let served: BTreeSet<&str> = models_body["data"]
+        .as_array()?
+        .iter()
+        .filter_map(|m| m["id"].as_str())
+        .collect();
+    Some(
+        requested
+            .iter()
+            .filter(|name| !served.contains(name.as_str()))
+            .cloned()
+            .collect(),
+    )
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Check a pipeline against the cluster of the client's context
+pub async fn check_against_cluster(
+    client: &ControlPlaneClient,
+    pipeline: &Pipeline,
+) -> ClusterCheck {
+    let mut check = ClusterCheck::default();
+    let composition = &pipeline.spec.composition;
+
+    match client.list_nodes().await {
+        Ok(nodes) => {
+            let nodes: Vec<Node> = nodes
+                .into_iter()
+                .filter_map(|n| serde_json::from_value(n).ok())
+                .collect();
+            if !nodes.iter().any(|n| n.can_schedule()) {
+                check.warnings.push(
+                    "No schedulable nodes; the pipeline would wait for a worker to join"
+                        .to_string(),
+                );
+            }
+            let runners = runner_problems(composition, &nodes);
+            let pool = match pipeline.spec.node_pool.as_deref() {
+                Some(name) => match client.get_node_pool(name).await {
+                    Ok(Some(pool)) if pool.allows_namespace(&pipeline.metadata.namespace) => {
+                        Some(pool)
+                    }
+                    Ok(Some(_)) => {
+                        check.problems.push(format!(
+                            "Node pool '{}' doesn't allow namespace '{}'",
+                            name, pipeline.metadata.namespace
+                        ));
+                        None
+                    }
+                    Ok(None) => {
+                        check
+                            .problems
+                            .push(format!("Node pool '{}' doesn't exist", name));
+                        None
+                    }
+                    Err(e) => {
+                        check
+                            .problems
+                            .push(format!("Can't get node pool '{}': {}", name, e));
+                        None
+                    }
+                },
+                None => None,
+            };
+            if runners.is_empty() {
+                check
+                    .problems
+                    .extend(admission_problems(pipeline, &nodes, pool.as_ref()));
+            } else {
+                check.problems.extend(runners);
+            }
+        }
+        Err(e) => check
+            .problems
+            .push(format!("Can't list the cluster's nodes: {}", e)),
+    }
+
+    check.problems.extend(undeclared_secrets(composition));
+    let secrets = SecretsManager::new();
+    for (name, variables) in secret_references(composition) {
+        let Some(source) = composition.secrets.get(&name) else {
+            continue;
+        };
+        let on_worker = !matches!(source, SecretSource::Vault { .. });
+        let loaded = secrets
+            .load_all(&HashMap::from([(name.clone(), source.clone())]))
+            .await;
+        let problem = match loaded {
+            Err(e) => Some(format!("Secret '{}' doesn't resolve: {}", name, e)),
+            Ok(()) => {
+                let missing: Vec<&String> = variables
+                    .iter()
+                    .filter(|v| secrets.resolve(&name, v).is_none())
+                    .collect();
+                (!missing.is_empty()).then(|| {
+                    format!(
+                        "Secret '{}' has no {}",
+                        name,
+                        missing
+                            .iter()
+                            .map(|v| v.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })
+            }
+        };
+        match problem {
+            Some(p) if on_worker => check.warnings.push(format!(
+                "{} on this machine; make sure the workers have it",
+                p
+            )),
+            Some(p) => check.problems.push(p),
+            None => {}
+        }
+    }
+
+    let http = reqwest::Client::builder()
+        .timeout(ENDPOINT_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    for endpoint in endpoint_checks(composition) {
+        check_endpoint(&http, &secrets, &endpoint, &mut check).await;
+    }
+
+    check
+}
+
+/// Check an external endpoint answers and serves the requested models
+async fn check_endpoint(
+    http: &reqwest::Client,
+    secrets: &SecretsManager,
+    endpoint: &EndpointCheck,
+    check: &mut ClusterCheck,
+) {
+    let base = secrets.substitute(&endpoint.url);
+    let base = base.trim_end_matches('/');
+    let openai = endpoint.interface == "openai-api";
+    let url = if openai {
+        format!("{}/models", base)
+    } else {
+        base.to_string()
+    };
+
+    let mut request = http.get(&url);
+    if let Some(key) = &endpoint.api_key {
+        request = request.bearer_auth(secrets.substitute(key));
+    }
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            check.problems.push(format!(
+                "Model '{}': can't reach {}: {}",
+                endpoint.model, base, e
+            ));
+            return;
+        }
+    };
+
+    // Any answer means the endpoint is up; only a model list says more
+    if !openai || !response.status().is_success() {
+        return;
+    }
+    let Ok(body) = response.json::<serde_json::Value>().await else {
+        return;
+    };
+    if let Some(missing) = missing_models(&endpoint.requested, &body) {
+        for name in missing {
+            check.problems.push(format!(
+                "Model '{}': {} doesn't serve '{}'",
+                endpoint.model, base, name
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{NodeCapabilities, NodeCapacity, NodeInfo, NodeStatus};
+    use serde_json::json;
+
+    fn composition() -> Composition {
+        Composition::from_str(
+            r#"{
+                "secrets": {"openai": {"source": "env", "variable": "OPENAI_API_KEY"}},
+                "models": {
+                    "gpt": {"type": "external", "interface": "openai-api", "url": "https://api.openai.com/v1", "api-key": "$secrets.openai.OPENAI_API_KEY"},
+                    "local": {"runner": "ollama", "source": "llama3"}
+                },
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "extra-options": {"model_override": "gpt-4o-mini"}, "output-to": [1]},
+                    {"name": "support", "layer": 1, "model": "gpt", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "chat", "layer": 1, "model": "local", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output", "extra-options": {"webhook": "$secrets.webhook.URL"}}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn node(runners: &[&str]) -> Node {
+        let mut node = Node::new("worker-1", "10.0.0.1").with_capabilities(NodeCapabilities::new(
+            runners.iter().map(|r| r.to_string()).collect(),
+        ));
+        node.status = Some(NodeStatus::new(
+            NodeCapacity::default(),
+            NodeInfo::from_system(),
+        ));
+        node
+    }
+
+    #[test]
+    fn test_secret_references() {
+        let composition = composition();
+        let references = secret_references(&composition);
+        assert_eq!(references.len(), 2);
+        assert!(references["openai"].contains("OPENAI_API_KEY"));
+        assert_eq!(
+            undeclared_secrets(&composition),
+            ["Secret 'webhook' is referenced but not declared"]
+        );
+    }
+
+    #[test]
+    fn test_runner_problems() {
+        let composition = composition();
+        assert!(runner_problems(&composition, &[]).is_empty());
+        assert!(runner_problems(&composition, &[node(&["ollama"])]).is_empty());
+        assert_eq!(
+            runner_problems(&composition, &[node(&["vllm"])]),
+            ["Model 'local' needs the ollama runner, which no schedulable node has"]
+        );
+    }
+
+    #[test]
+    fn test_endpoint_checks() {
+        let checks = endpoint_checks(&composition());
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].model, "gpt");
+        assert_eq!(checks[0].requested, ["gpt", "gpt-4o-mini"]);
+
+        let body = json!({"data": [{"id": "gpt-4o-mini"}, {"id": "gpt-4o"}]});
+        assert_eq!(
+            missing_models(&checks[0].requested, &body),
+            Some(vec!["gpt".to_string()])
+        );
+        assert_eq!(
+            missing_models(&checks[0].requested, &json!({"ok": true})),
+            None
+        );
+    }
+}
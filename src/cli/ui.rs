@@ -0,0 +1,656 @@
+//! Terminal dashboard (`llmnet ui`)
+//!
+//! Shows the cluster's nodes with their live metrics, its pipelines and the
+//! control plane's events, refreshed on an interval, for operators who can't
+//! reach a web dashboard. The selected pipeline can be scaled or have its
+//! logs shown, and the selected node cordoned or uncordoned.
+
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState, Tabs, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::mpsc;
+
+use super::commands::{CommandResult, ControlPlaneClient};
+use crate::cluster::{ClusterEvent, Node, Pipeline};
+
+/// Log lines fetched when showing a pipeline's logs
+pub const LOG_TAIL_LINES: usize = 200;
+
+const HELP: &str = "Tab view  j/k move  s scale  c cordon/uncordon  l logs  r refresh  q quit";
+
+/// The list the dashboard shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Nodes,
+    Pipelines,
+    Events,
+}
+
+impl View {
+    const ALL: [View; 3] = [View::Nodes, View::Pipelines, View::Events];
+
+    fn title(self) -> &'static str {
+        match self {
+            View::Nodes => "Nodes",
+            View::Pipelines => "Pipelines",
+            View::Events => "Events",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            View::Nodes => View::Pipelines,
+            View::Pipelines => View::Events,
+            View::Events => View::Nodes,
+        }
+    }
+}
+
+/// Something a key asks the control plane for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Refresh,
+    Scale {
+        namespace: String,
+        name: String,
+        replicas: u32,
+    },
+    Cordon {
+        node: String,
+        cordon: bool,
+    },
+    Logs {
+        namespace: String,
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Browse,
+    /// Typing the replica count for the selected pipeline
+    Scale(String),
+    Logs {
+        title: String,
+        lines: Vec<String>,
+        scroll: u16,
+    },
+}
+
+/// What the dashboard shows and where the operator is in it
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    pub nodes: Vec<Node>,
+    pub pipelines: Vec<Pipeline>,
+    pub events: Vec<ClusterEvent>,
+    pub view: View,
+    selected: usize,
+    mode: Mode,
+    /// Result of the last action, or why the last refresh failed
+    pub message: Option<String>,
+}
+
+impl Default for Dashboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            pipelines: Vec::new(),
+            events: Vec::new(),
+            view: View::Nodes,
+            selected: 0,
+            mode: Mode::Browse,
+            message: None,
+        }
+    }
+
+    /// Show freshly fetched resources, keeping the selection in range
+    pub fn update(
+        &mut self,
+        nodes: Vec<Node>,
+        pipelines: Vec<Pipeline>,
+        events: Vec<ClusterEvent>,
+    ) {
+        self.nodes = nodes;
+        self.pipelines = pipelines;
+        self.events = events;
+        self.selected = self.selected.min(self.len().saturating_sub(1));
+    }
+
+    /// Show a pipeline's logs until Esc
+    pub fn show_logs(&mut self, title: impl Into<String>, text: &str) {
+        self.mode = Mode::Logs {
+            title: title.into(),
+            lines: text.lines().map(String::from).collect(),
+            scroll: 0,
+        };
+    }
+
+    fn len(&self) -> usize {
+        match self.view {
+            View::Nodes => self.nodes.len(),
+            View::Pipelines => self.pipelines.len(),
+            View::Events => self.events.len(),
+        }
+    }
+
+    fn selected_pipeline(&self) -> Option<&Pipeline> {
+        (self.view == View::Pipelines)
+            .then(|| self.pipelines.get(self.selected))
+            .flatten()
+    }
+
+    fn selected_node(&self) -> Option<&Node> {
+        (self.view == View::Nodes)
+            .then(|| self.nodes.get(self.selected))
+            .flatten()
+    }
+
+    /// Apply a key press, returning what it asks the control plane for
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+        match &mut self.mode {
+            Mode::Scale(input) => {
+                match key.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() && input.len() < 4 => input.push(c),
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Esc => self.mode = Mode::Browse,
+                    KeyCode::Enter => {
+                        let replicas = input.parse().ok();
+                        self.mode = Mode::Browse;
+                        let pipeline = self.selected_pipeline()?;
+                        return Some(Action::Scale {
+                            namespace: pipeline.metadata.namespace.clone(),
+                            name: pipeline.metadata.name.clone(),
+                            replicas: replicas?,
+                        });
+                    }
+                    _ => {}
+                }
+                return None;
+            }
+            Mode::Logs { scroll, .. } => {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => self.mode = Mode::Browse,
+                    KeyCode::Down | KeyCode::Char('j') => *scroll = scroll.saturating_add(1),
+                    KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+                    _ => {}
+                }
+                return None;
+            }
+            Mode::Browse => {}
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Action::Quit),
+            KeyCode::Char('r') => return Some(Action::Refresh),
+            KeyCode::Tab => self.select_view(self.view.next()),
+            KeyCode::Char('1') => self.select_view(View::Nodes),
+            KeyCode::Char('2') => self.select_view(View::Pipelines),
+            KeyCode::Char('3') => self.select_view(View::Events),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.len().saturating_sub(1));
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('s') if self.selected_pipeline().is_some() => {
+                self.mode = Mode::Scale(String::new());
+            }
+            KeyCode::Char('l') => {
+                let pipeline = self.selected_pipeline()?;
+                return Some(Action::Logs {
+                    namespace: pipeline.metadata.namespace.clone(),
+                    name: pipeline.metadata.name.clone(),
+                });
+            }
+            KeyCode::Char('c') => {
+                let node = self.selected_node()?;
+                return Some(Action::Cordon {
+                    node: node.metadata.name.clone(),
+                    cordon: node.spec.schedulable,
+                });
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn select_view(&mut self, view: View) {
+        if self.view != view {
+            self.view = view;
+            self.selected = 0;
+        }
+    }
+
+    /// Draw the dashboard
+    pub fn render(&self, frame: &mut Frame) {
+        let [tabs, main, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let titles = View::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("{} {}", i + 1, v.title()));
+        let selected = View::ALL.iter().position(|v| *v == self.view).unwrap_or(0);
+        let ready_nodes = self.nodes.iter().filter(|n| n.is_ready()).count();
+        let ready_pipelines = self.pipelines.iter().filter(|p| p.is_ready()).count();
+        let summary = format!(
+            " llmnet: {}/{} nodes ready, {}/{} pipelines ready ",
+            ready_nodes,
+            self.nodes.len(),
+            ready_pipelines,
+            self.pipelines.len()
+        );
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(selected)
+                .highlight_style(Style::new().add_modifier(Modifier::BOLD | Modifier::REVERSED))
+                .block(Block::new().borders(Borders::ALL).title(summary)),
+            tabs,
+        );
+
+        match &self.mode {
+            Mode::Logs {
+                title,
+                lines,
+                scroll,
+            } => {
+                let text: Vec<Line> = lines.iter().map(|l| Line::raw(l.as_str())).collect();
+                frame.render_widget(
+                    Paragraph::new(text)
+                        .wrap(Wrap { trim: false })
+                        .scroll((*scroll, 0))
+                        .block(Block::new().borders(Borders::ALL).title(title.as_str())),
+                    main,
+                );
+            }
+            _ => self.render_table(frame, main),
+        }
+
+        let footer_text = match &self.mode {
+            Mode::Scale(input) => format!(
+                "Replicas for {}: {}_  (Enter to scale, Esc to cancel)",
+                self.selected_pipeline()
+                    .map(|p| p.qualified_name())
+                    .unwrap_or_default(),
+                input
+            ),
+            Mode::Logs { .. } => "j/k scroll  Esc back".to_string(),
+            Mode::Browse => match &self.message {
+                Some(message) => message.clone(),
+                None => HELP.to_string(),
+            },
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+
+    fn render_table(&self, frame: &mut Frame, area: Rect) {
+        let (headers, rows, widths): (&[&str], Vec<Row>, Vec<Constraint>) = match self.view {
+            View::Nodes => (
+                &[
+                    "NAME",
+                    "STATUS",
+                    "CPU",
+                    "MEM",
+                    "GPU",
+                    "PIPELINES",
+                    "REQUESTS",
+                    "LATENCY",
+                ],
+                self.nodes.iter().map(|n| Row::new(node_row(n))).collect(),
+                vec![
+                    Constraint::Fill(2),
+                    Constraint::Fill(2),
+                    Constraint::Length(6),
+                    Constraint::Length(6),
+                    Constraint::Length(6),
+                    Constraint::Length(9),
+                    Constraint::Length(9),
+                    Constraint::Length(9),
+                ],
+            ),
+            View::Pipelines => (
+                &["NAMESPACE", "NAME", "READY", "AVAILABLE", "STATUS"],
+                self.pipelines
+                    .iter()
+                    .map(|p| Row::new(pipeline_row(p)))
+                    .collect(),
+                vec![
+                    Constraint::Fill(1),
+                    Constraint::Fill(2),
+                    Constraint::Length(7),
+                    Constraint::Length(9),
+                    Constraint::Fill(1),
+                ],
+            ),
+            View::Events => (
+                &["TIME", "OBJECT", "REASON", "MESSAGE"],
+                // Most recent first
+                self.events
+                    .iter()
+                    .rev()
+                    .map(|e| {
+                        Row::new(vec![
+                            e.timestamp.format("%H:%M:%S").to_string(),
+                            e.object.clone(),
+                            e.reason.clone(),
+                            e.message.clone(),
+                        ])
+                    })
+                    .collect(),
+                vec![
+                    Constraint::Length(8),
+                    Constraint::Fill(1),
+                    Constraint::Fill(1),
+                    Constraint::Fill(3),
+                ],
+            ),
+        };
+
+        let table = Table::new(rows, widths)
+            .header(Row::new(headers.to_vec()).style(Style::new().add_modifier(Modifier::BOLD)))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(Block::new().borders(Borders::ALL));
+        let mut state = TableState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(table, area, &mut state);
+    }
+}
+
+/// A node's row: phase, scheduling and the metrics of its last heartbeat
+fn node_row(node: &Node) -> Vec<String> {
+    let status = node.status.as_ref();
+    let mut phase = status
+        .map(|s| format!("{:?}", s.phase))
+        .unwrap_or_else(|| "Unknown".to_string());
+    if !node.spec.schedulable {
+        phase.push_str(",Cordoned");
+    }
+    let metrics = status.and_then(|s| s.metrics.as_ref());
+    let percent = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.0}%", v));
+    vec![
+        node.metadata.name.clone(),
+        phase,
+        percent(metrics.map(|m| m.cpu_usage_percent)),
+        percent(metrics.map(|m| m.memory_usage_percent)),
+        percent(metrics.and_then(|m| m.gpu_usage_percent)),
+        node.pipeline_count().to_string(),
+        metrics.map_or("-".to_string(), |m| m.request_count.to_string()),
+        metrics.map_or("-".to_string(), |m| format!("{:.0}ms", m.avg_latency_ms)),
+    ]
+}
+
+/// A pipeline's row: replica counts and whether it's running
+fn pipeline_row(pipeline: &Pipeline) -> Vec<String> {
+    let (ready, available) = pipeline
+        .status
+        .as_ref()
+        .map_or((0, 0), |s| (s.ready_replicas, s.available_replicas));
+    let status = if pipeline.is_ready() {
+        "Running"
+    } else if pipeline.status.is_some() {
+        "Pending"
+    } else {
+        "Unknown"
+    };
+    vec![
+        pipeline.metadata.namespace.clone(),
+        pipeline.metadata.name.clone(),
+        format!("{}/{}", ready, pipeline.spec.replicas),
+        available.to_string(),
+        status.to_string(),
+    ]
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Run the dashboard until the operator quits
+pub async fn run_dashboard(client: ControlPlaneClient, refresh: Duration) -> CommandResult<()> {
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &client, refresh).await;
+    ratatui::restore();
+    result
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    client: &ControlPlaneClient,
+    refresh: Duration,
+) -> CommandResult<()> {
+    // crossterm reads keys blocking, so they're read on their own thread
+    let (tx, mut keys) = mpsc::unbounded_channel();
+    std::thread::spawn(move || loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if tx.send(key).is_err() {
+                    break;
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    });
+
+    let mut dashboard = Dashboard::new();
+    let mut interval = tokio::time::interval(refresh);
+    loop {
+        let action = tokio::select! {
+            _ = interval.tick() => Some(Action::Refresh),
+            key = keys.recv() => match key {
+                Some(key) => dashboard.handle_key(key),
+                None => Some(Action::Quit),
+            },
+        };
+
+        match action {
+            Some(Action::Quit) => return Ok(()),
+            Some(Action::Refresh) => refresh_dashboard(&mut dashboard, client).await,
+            Some(action) => {
+                dashboard.message = Some(perform(&mut dashboard, client, action).await);
+                refresh_dashboard(&mut dashboard, client).await;
+            }
+            None => {}
+        }
+        terminal.draw(|frame| dashboard.render(frame))?;
+    }
+}
+
+async fn refresh_dashboard(dashboard: &mut Dashboard, client: &ControlPlaneClient) {
+    match fetch(client).await {
+        Ok((nodes, pipelines, events)) => dashboard.update(nodes, pipelines, events),
+        Err(e) => dashboard.message = Some(format!("Refresh failed: {}", e)),
+    }
+}
+
+async fn fetch(
+    client: &ControlPlaneClient,
+) -> CommandResult<(Vec<Node>, Vec<Pipeline>, Vec<ClusterEvent>)> {
+    let nodes = client
+        .list_nodes()
+        .await?
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<_, _>>()?;
+    let pipelines = client.list_pipelines(None).await?;
+    let events = client.list_events().await?;
+    Ok((nodes, pipelines, events))
+}
+
+/// Carry out an action, returning what to tell the operator
+async fn perform(dashboard: &mut Dashboard, client: &ControlPlaneClient, action: Action) -> String {
+    let result = match &action {
+        Action::Scale {
+            namespace,
+            name,
+            replicas,
+        } => client
+            .scale_pipeline(namespace, name, *replicas)
+            .await
+            .map(|_| format!("Scaled {}/{} to {} replicas", namespace, name, replicas)),
+        Action::Cordon { node, cordon } => client.cordon_node(node, *cordon).await.map(|_| {
+            let verb = if *cordon { "Cordoned" } else { "Uncordoned" };
+            format!("{} node {}", verb, node)
+        }),
+        Action::Logs { namespace, name } => {
+            match client
+                .stream_logs(namespace, name, false, LOG_TAIL_LINES)
+                .await
+            {
+                Ok(response) => match response.text().await {
+                    Ok(text) => {
+                        dashboard.show_logs(format!("Logs of {}/{}", namespace, name), &text);
+                        Ok(String::new())
+                    }
+                    Err(e) => Err(e.into()),
+                },
+                Err(e) => Err(e),
+            }
+        }
+        Action::Quit | Action::Refresh => Ok(String::new()),
+    };
+    result.unwrap_or_else(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Composition;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn dashboard() -> Dashboard {
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut dashboard = Dashboard::new();
+        dashboard.update(
+            vec![
+                Node::new("worker-1", "10.0.0.1"),
+                Node::new("worker-2", "10.0.0.2").cordon(),
+            ],
+            vec![Pipeline::new("chat", composition).with_replicas(2)],
+            vec![ClusterEvent::new(
+                "node/worker-2",
+                "Cordoned",
+                "by operator",
+            )],
+        );
+        dashboard
+    }
+
+    #[test]
+    fn test_navigation_and_actions() {
+        let mut dashboard = dashboard();
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Char('c'))),
+            Some(Action::Cordon {
+                node: "worker-1".to_string(),
+                cordon: true
+            })
+        );
+        dashboard.handle_key(key(KeyCode::Down));
+        dashboard.handle_key(key(KeyCode::Down));
+        // worker-2 is cordoned already, so `c` uncordons it
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Char('c'))),
+            Some(Action::Cordon {
+                node: "worker-2".to_string(),
+                cordon: false
+            })
+        );
+        // Logs and scaling only apply to pipelines
+        assert_eq!(dashboard.handle_key(key(KeyCode::Char('l'))), None);
+
+        dashboard.handle_key(key(KeyCode::Tab));
+        assert_eq!(dashboard.view, View::Pipelines);
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Char('l'))),
+            Some(Action::Logs {
+                namespace: "default".to_string(),
+                name: "chat".to_string()
+            })
+        );
+
+        dashboard.handle_key(key(KeyCode::Char('s')));
+        dashboard.handle_key(key(KeyCode::Char('1')));
+        dashboard.handle_key(key(KeyCode::Char('x')));
+        dashboard.handle_key(key(KeyCode::Char('0')));
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Enter)),
+            Some(Action::Scale {
+                namespace: "default".to_string(),
+                name: "chat".to_string(),
+                replicas: 10
+            })
+        );
+
+        // Typing digits while browsing switches views instead
+        dashboard.handle_key(key(KeyCode::Char('3')));
+        assert_eq!(dashboard.view, View::Events);
+        assert_eq!(
+            dashboard.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn test_update_keeps_selection_in_range() {
+        let mut dashboard = dashboard();
+        dashboard.handle_key(key(KeyCode::Down));
+        dashboard.update(vec![Node::new("worker-1", "10.0.0.1")], vec![], vec![]);
+        assert_eq!(
+            dashboard.handle_key(key(KeyCode::Char('c'))),
+            Some(Action::Cordon {
+                node: "worker-1".to_string(),
+                cordon: true
+            })
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let mut dashboard = dashboard();
+        let mut terminal = Terminal::new(TestBackend::new(100, 12)).unwrap();
+        terminal.draw(|frame| dashboard.render(frame)).unwrap();
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("0/2 nodes ready"));
+        assert!(screen.contains("Unknown,Cordoned"));
+
+        dashboard.show_logs("Logs of default/chat", "line one\nline two");
+        terminal.draw(|frame| dashboard.render(frame)).unwrap();
+        let screen = format!("{:?}", terminal.backend().buffer());
+        assert!(screen.contains("line two"));
+        assert!(screen.contains("Esc back"));
+    }
+}
@@ -0,0 +1,792 @@
+//! Google Gemini client
+//!
+//! Models with `"interface": "gemini"` are served through the Gemini API's
+//! `generateContent`. The pipeline keeps speaking OpenAI chat completions:
+//! requests and responses are translated here, so Gemini nodes mix freely
+//! with OpenAI-compatible ones in a composition.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::client::openai::{
+    ChatCompletionRequest, ChatCompletionResponse, Choice, ClientError, Embedding,
+    EmbeddingRequest, EmbeddingResponse, FunctionCall, ImageUrl, Message, OpenAiClientTrait, Tool,
+    ToolCall, ToolChoice, Usage,
+};
+
+/// Gemini API endpoint used when a model doesn't set one
+pub const GEMINI_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+// ============================================================================
+// Data structures (pure, no I/O)
+// ============================================================================
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentRequest {
+    pub contents: Vec<Content>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_instruction: Option<Content>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<GeminiTool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_config: Option<ToolConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub safety_settings: Vec<SafetySetting>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_config: Option<GenerationConfig>,
+}
+
+/// A turn of the conversation: "user" or "model"
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Content {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<Part>,
+}
+
+/// One part of a turn; exactly one field is set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Part {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline_data: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_data: Option<FileData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<GeminiFunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_response: Option<FunctionResponse>,
+}
+
+/// Base64 data, e.g. an image from a `data:` URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Blob {
+    pub mime_type: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileData {
+    pub mime_type: String,
+    pub file_uri: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeminiFunctionCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    pub response: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiTool {
+    pub function_declarations: Vec<FunctionDeclaration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolConfig {
+    pub function_calling_config: FunctionCallingConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionCallingConfig {
+    /// "AUTO", "ANY" or "NONE"
+    pub mode: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_function_names: Vec<String>,
+}
+
+/// How strictly Gemini blocks a category of harmful content, e.g.
+/// `{"category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafetySetting {
+    pub category: String,
+    pub threshold: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateContentResponse {
+    #[serde(default)]
+    pub candidates: Vec<Candidate>,
+    #[serde(default)]
+    pub prompt_feedback: Option<PromptFeedback>,
+    #[serde(default)]
+    pub usage_metadata: Option<UsageMetadata>,
+    #[serde(default)]
+    pub response_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    #[serde(default)]
+    pub content: Option<Content>,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptFeedback {
+    #[serde(default)]
+    pub block_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageMetadata {
+    #[serde(default)]
+    pub prompt_token_count: u32,
+    #[serde(default)]
+    pub candidates_token_count: u32,
+    #[serde(default)]
+    pub total_token_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct BatchEmbedRequest {
+    requests: Vec<EmbedContentRequest>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct EmbedContentRequest {
+    model: String,
+    content: Content,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BatchEmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<ContentEmbedding>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ContentEmbedding {
+    values: Vec<f32>,
+}
+
+// ============================================================================
+// SBIO: Pure functions
+// ============================================================================
+
+fn text_part(text: String) -> Part {
+    Part {
+        text: Some(text),
+        ..Default::default()
+    }
+}
+
+/// An image as an inline blob (`data:` URLs) or a file reference
+fn image_part(image: &ImageUrl) -> Part {
+    if let Some((mime_type, data)) = image
+        .url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"))
+    {
+        return Part {
+            inline_data: Some(Blob {
+                mime_type: mime_type.to_string(),
+                data: data.to_string(),
+            }),
+            ..Default::default()
+        };
+    }
+
+    let extension = image
+        .url
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let mime_type = match extension.as_str() {
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "heic" => "image/heic",
+        _ => "image/jpeg",
+    };
+    Part {
+        file_data: Some(FileData {
+            mime_type: mime_type.to_string(),
+            file_uri: image.url.clone(),
+        }),
+        ..Default::default()
+    }
+}
+
+/// A tool result as a function response; Gemini wants an object, so
+/// anything else is wrapped in `{"content": ...}`
+fn function_response_part(message: &Message, call_names: &HashMap<&str, &str>) -> Part {
+    let id = message.tool_call_id.clone();
+    let name = id
+        .as_deref()
+        .and_then(|id| call_names.get(id))
+        .map(|name| name.to_string())
+        .or_else(|| message.name.clone())
+        .unwrap_or_default();
+    let response = match serde_json::from_str::<Value>(&message.content) {
+        Ok(object @ Value::Object(_)) => object,
+        Ok(value) => serde_json::json!({ "content": value }),
+        Err(_) => serde_json::json!({ "content": message.content }),
+    };
+
+    Part {
+        function_response: Some(FunctionResponse { id, name, response }),
+        ..Default::default()
+    }
+}
+
+fn function_call_part(call: &ToolCall) -> Part {
+    let args = serde_json::from_str(&call.function.arguments)
+        .unwrap_or_else(|_| Value::Object(Default::default()));
+    Part {
+        function_call: Some(GeminiFunctionCall {
+            id: Some(call.id.clone()),
+            name: call.function.name.clone(),
+            args,
+        }),
+        ..Default::default()
+    }
+}
+
+fn tool_config(choice: &ToolChoice) -> ToolConfig {
+    let (mode, allowed_function_names) = match choice {
+        ToolChoice::Mode(mode) => match mode.as_str() {
+            "none" => ("NONE", Vec::new()),
+            "required" => ("ANY", Vec::new()),
+            _ => ("AUTO", Vec::new()),
+        },
+        ToolChoice::Function { function, .. } => ("ANY", vec![function.name.clone()]),
+    };
+    ToolConfig {
+        function_calling_config: FunctionCallingConfig {
+            mode: mode.to_string(),
+            allowed_function_names,
+        },
+    }
+}
+
+fn function_declarations(tools: &[Tool]) -> Vec<GeminiTool> {
+    if tools.is_empty() {
+        return Vec::new();
+    }
+    let function_declarations = tools
+        .iter()
+        .map(|tool| FunctionDeclaration {
+            name: tool.function.name.clone(),
+            description: tool.function.description.clone(),
+            parameters: tool.function.parameters.clone(),
+        })
+        .collect();
+    vec![GeminiTool {
+        function_declarations,
+    }]
+}
+
+/// Translate a chat completion request into a `generateContent` request
+///
+/// System messages become the system instruction, assistant turns the
+/// "model" role and tool results function responses. Consecutive turns of
+/// the same role are merged, as Gemini expects them to alternate.
+pub fn to_generate_content(
+    request: &ChatCompletionRequest,
+    safety_settings: &[SafetySetting],
+) -> GenerateContentRequest {
+    let call_names: HashMap<&str, &str> = request
+        .messages
+        .iter()
+        .flat_map(|m| &m.tool_calls)
+        .map(|call| (call.id.as_str(), call.function.name.as_str()))
+        .collect();
+
+    let mut system = Vec::new();
+    let mut contents: Vec<Content> = Vec::new();
+    for message in &request.messages {
+        let (role, parts) = match message.role.as_str() {
+            "system" | "developer" => {
+                system.push(text_part(message.content.clone()));
+                continue;
+            }
+            "assistant" => {
+                let text =
+                    (!message.content.is_empty()).then(|| text_part(message.content.clone()));
+                let calls = message.tool_calls.iter().map(function_call_part);
+                ("model", text.into_iter().chain(calls).collect())
+            }
+            "tool" => ("user", vec![function_response_part(message, &call_names)]),
+            _ => {
+                let text = (!message.content.is_empty() || message.images.is_empty())
+                    .then(|| text_part(message.content.clone()));
+                let images = message.images.iter().map(image_part);
+                ("user", text.into_iter().chain(images).collect())
+            }
+        };
+
+        match contents.last_mut() {
+            Some(last) if last.role.as_deref() == Some(role) => last.parts.extend(parts),
+            _ => contents.push(Content {
+                role: Some(role.to_string()),
+                parts,
+            }),
+        }
+    }
+
+    let generation_config = (request.max_tokens.is_some() || request.temperature.is_some())
+        .then_some(GenerationConfig {
+            max_output_tokens: request.max_tokens,
+            temperature: request.temperature,
+        });
+
+    GenerateContentRequest {
+        contents,
+        system_instruction: (!system.is_empty()).then_some(Content {
+            role: None,
+            parts: system,
+        }),
+        tools: function_declarations(&request.tools),
+        tool_config: request.tool_choice.as_ref().map(tool_config),
+        safety_settings: safety_settings.to_vec(),
+        generation_config,
+    }
+}
+
+/// The OpenAI `finish_reason` for a Gemini one
+fn finish_reason(reason: &str) -> &'static str {
+    match reason {
+        "STOP" => "stop",
+        "MAX_TOKENS" => "length",
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "IMAGE_SAFETY" => {
+            "content_filter"
+        }
+        _ => "stop",
+    }
+}
+
+/// Translate a `generateContent` response into a chat completion response
+///
+/// A prompt Gemini refused to answer at all is an error.
+pub fn from_generate_content(
+    response: GenerateContentResponse,
+) -> Result<ChatCompletionResponse, ClientError> {
+    if response.candidates.is_empty() {
+        let reason = response
+            .prompt_feedback
+            .and_then(|f| f.block_reason)
+            .unwrap_or_else(|| "no candidates returned".to_string());
+        return Err(ClientError::Blocked(reason));
+    }
+
+    let choices = response
+        .candidates
+        .into_iter()
+        .map(|candidate| {
+            let mut texts = Vec::new();
+            let mut tool_calls = Vec::new();
+            for part in candidate.content.map(|c| c.parts).unwrap_or_default() {
+                if let Some(text) = part.text {
+                    texts.push(text);
+                }
+                if let Some(call) = part.function_call {
+                    tool_calls.push(ToolCall {
+                        id: call
+                            .id
+                            .unwrap_or_else(|| format!("call_{}", tool_calls.len())),
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: call.name,
+                            arguments: call.args.to_string(),
+                        },
+                    });
+                }
+            }
+
+            let finish_reason = if tool_calls.is_empty() {
+                candidate.finish_reason.as_deref().map(finish_reason)
+            } else {
+                Some("tool_calls")
+            };
+            Choice {
+                index: candidate.index,
+                message: Message {
+                    role: "assistant".to_string(),
+                    content: texts.concat(),
+                    tool_calls,
+                    ..Default::default()
+                },
+                finish_reason: finish_reason.map(str::to_string),
+            }
+        })
+        .collect();
+
+    Ok(ChatCompletionResponse {
+        id: response.response_id.unwrap_or_default(),
+        choices,
+        usage: response.usage_metadata.map(|u| Usage {
+            prompt_tokens: u.prompt_token_count,
+            completion_tokens: u.candidates_token_count,
+            total_tokens: u.total_token_count,
+        }),
+    })
+}
+
+/// The API's name for a model, e.g. `models/gemini-2.0-flash`
+fn model_resource(model: &str) -> String {
+    if model.starts_with("models/") || model.starts_with("tunedModels/") {
+        model.to_string()
+    } else {
+        format!("models/{}", model)
+    }
+}
+
+// ============================================================================
+// SBIO: I/O implementation (real HTTP client)
+// ============================================================================
+
+#[derive(Clone)]
+pub struct GeminiClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    safety_settings: Vec<SafetySetting>,
+}
+
+impl GeminiClient {
+    pub fn new(base_url: String, api_key: Option<String>, model: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url,
+            api_key,
+            model,
+            safety_settings: Vec::new(),
+        }
+    }
+
+    /// Send these safety settings with every request
+    pub fn with_safety_settings(mut self, safety_settings: Vec<SafetySetting>) -> Self {
+        self.safety_settings = safety_settings;
+        self
+    }
+
+    /// The Gemini model every call goes to; the model named in requests is
+    /// ignored, as it is part of the URL
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The same client pointed at another server, sharing its connection pool
+    pub fn with_base_url(&self, base_url: String) -> Self {
+        Self {
+            base_url,
+            ..self.clone()
+        }
+    }
+
+    /// POST a JSON body to a method of the model and decode the response
+    async fn post_json<B: Serialize + Sync, R: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        body: &B,
+    ) -> Result<R, ClientError> {
+        let url = format!(
+            "{}/{}:{}",
+            self.base_url.trim_end_matches('/'),
+            model_resource(&self.model),
+            method
+        );
+
+        let mut req = self.client.post(&url).json(body);
+
+        if let Some(ref key) = self.api_key {
+            req = req.header("x-goog-api-key", key);
+        }
+
+        let response = req
+            .send()
+            .await
+            .map_err(|e| ClientError::Http(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(ClientError::Api {
+                status: status.as_u16(),
+                message: text,
+            });
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| ClientError::Parse(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl OpenAiClientTrait for GeminiClient {
+    async fn chat_completion(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, ClientError> {
+        let body = to_generate_content(request, &self.safety_settings);
+        let response = self.post_json("generateContent", &body).await?;
+        from_generate_content(response)
+    }
+
+    async fn embeddings(
+        &self,
+        request: &EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, ClientError> {
+        let model = model_resource(&self.model);
+        let body = BatchEmbedRequest {
+            requests: request
+                .input
+                .clone()
+                .into_vec()
+                .into_iter()
+                .map(|text| EmbedContentRequest {
+                    model: model.clone(),
+                    content: Content {
+                        role: None,
+                        parts: vec![text_part(text)],
+                    },
+                })
+                .collect(),
+        };
+        let response: BatchEmbedResponse = self.post_json("batchEmbedContents", &body).await?;
+
+        Ok(EmbeddingResponse {
+            object: "list".to_string(),
+            data: response
+                .embeddings
+                .into_iter()
+                .enumerate()
+                .map(|(i, e)| Embedding {
+                    object: "embedding".to_string(),
+                    index: i as u32,
+                    embedding: e.values,
+                })
+                .collect(),
+            model: self.model.clone(),
+            usage: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::openai::{FunctionDefinition, ToolChoiceFunction};
+    use serde_json::json;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_to_generate_content() {
+        let mut user = message("user", "What is in this picture?");
+        user.images = vec![
+            ImageUrl {
+                url: "data:image/png;base64,iVBORw0K".to_string(),
+                detail: None,
+            },
+            ImageUrl {
+                url: "https://example.com/cat.webp".to_string(),
+                detail: None,
+            },
+        ];
+        let request = ChatCompletionRequest {
+            model: "ignored".to_string(),
+            messages: vec![
+                message("system", "Be brief."),
+                user,
+                message("assistant", "A cat."),
+                message("user", "What colour?"),
+                message("user", "One word."),
+            ],
+            max_tokens: Some(100),
+            temperature: Some(0.5),
+            ..Default::default()
+        };
+        let safety = vec![SafetySetting {
+            category: "HARM_CATEGORY_HARASSMENT".to_string(),
+            threshold: "BLOCK_ONLY_HIGH".to_string(),
+        }];
+
+        let body = serde_json::to_value(to_generate_content(&request, &safety)).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "systemInstruction": {"parts": [{"text": "Be brief."}]},
+                "contents": [
+                    {"role": "user", "parts": [
+                        {"text": "What is in this picture?"},
+                        {"inlineData": {"mimeType": "image/png", "data": "iVBORw0K"}},
+                        {"fileData": {"mimeType": "image/webp", "fileUri": "https://example.com/cat.webp"}}
+                    ]},
+                    {"role": "model", "parts": [{"text": "A cat."}]},
+                    {"role": "user", "parts": [{"text": "What colour?"}, {"text": "One word."}]}
+                ],
+                "safetySettings": [{"category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_ONLY_HIGH"}],
+                "generationConfig": {"maxOutputTokens": 100, "temperature": 0.5}
+            })
+        );
+    }
+
+    #[test]
+    fn test_tool_round_trip() {
+        let mut assistant = message("assistant", "");
+        assistant.tool_calls = vec![ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: "get_weather".to_string(),
+                arguments: r#"{"city":"Oslo"}"#.to_string(),
+            },
+        }];
+        let mut result = message("tool", "12C and raining");
+        result.tool_call_id = Some("call_1".to_string());
+
+        let request = ChatCompletionRequest {
+            messages: vec![message("user", "Weather in Oslo?"), assistant, result],
+            tools: vec![Tool {
+                tool_type: "function".to_string(),
+                function: FunctionDefinition {
+                    name: "get_weather".to_string(),
+                    description: Some("Current weather".to_string()),
+                    parameters: Some(json!({"type": "object"})),
+                    strict: None,
+                },
+            }],
+            tool_choice: Some(ToolChoice::Function {
+                choice_type: "function".to_string(),
+                function: ToolChoiceFunction {
+                    name: "get_weather".to_string(),
+                },
+            }),
+            ..Default::default()
+        };
+
+        let body = serde_json::to_value(to_generate_content(&request, &[])).unwrap();
+        assert_eq!(
+            body["contents"][1],
+            json!({"role": "model", "parts": [
+                {"functionCall": {"id": "call_1", "name": "get_weather", "args": {"city": "Oslo"}}}
+            ]})
+        );
+        assert_eq!(
+            body["contents"][2],
+            json!({"role": "user", "parts": [
+                {"functionResponse": {"id": "call_1", "name": "get_weather", "response": {"content": "12C and raining"}}}
+            ]})
+        );
+        assert_eq!(
+            body["tools"],
+            json!([{"functionDeclarations": [
+                {"name": "get_weather", "description": "Current weather", "parameters": {"type": "object"}}
+            ]}])
+        );
+        assert_eq!(
+            body["toolConfig"],
+            json!({"functionCallingConfig": {"mode": "ANY", "allowedFunctionNames": ["get_weather"]}})
+        );
+    }
+
+    #[test]
+    fn test_from_generate_content() {
+        let response: GenerateContentResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "Checking. "},
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Oslo"}}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 12, "candidatesTokenCount": 5, "totalTokenCount": 17},
+            "responseId": "resp-1"
+        }))
+        .unwrap();
+
+        let response = from_generate_content(response).unwrap();
+        assert_eq!(response.id, "resp-1");
+        let choice = &response.choices[0];
+        assert_eq!(choice.message.content, "Checking. ");
+        assert_eq!(choice.message.tool_calls[0].id, "call_0");
+        assert_eq!(
+            choice.message.tool_calls[0].function.arguments,
+            r#"{"city":"Oslo"}"#
+        );
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(response.usage.unwrap().total_tokens, 17);
+
+        let filtered: GenerateContentResponse =
+            serde_json::from_value(json!({"candidates": [{"finishReason": "SAFETY"}]})).unwrap();
+        let filtered = from_generate_content(filtered).unwrap();
+        assert_eq!(
+            filtered.choices[0].finish_reason.as_deref(),
+            Some("content_filter")
+        );
+
+        let blocked: GenerateContentResponse =
+            serde_json::from_value(json!({"promptFeedback": {"blockReason": "SAFETY"}})).unwrap();
+        assert!(matches!(
+            from_generate_content(blocked),
+            Err(ClientError::Blocked(reason)) if reason == "SAFETY"
+        ));
+    }
+}
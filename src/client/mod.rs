@@ -1,6 +1,13 @@
+pub mod gemini;
 pub mod openai;
+pub mod provider;
 
+pub use gemini::{GeminiClient, SafetySetting, GEMINI_API_URL};
 pub use openai::{
-    ChatCompletionRequest, ChatCompletionResponse, Choice, ClientError, Message, OpenAiClient,
-    OpenAiClientTrait,
+    CacheControl, ChatCompletionRequest, ChatCompletionResponse, Choice, ChunkStream, ClientError,
+    ContentPart, Embedding, EmbeddingInput, EmbeddingRequest, EmbeddingResponse, EmbeddingUsage,
+    FunctionCall, FunctionDefinition, ImageUrl, Message, MessageContent, OpenAiClient,
+    OpenAiClientTrait, Tool, ToolCall, ToolChoice, ToolChoiceFunction, TranscriptionRequest,
+    TranscriptionResponse, Usage,
 };
+pub use provider::ModelClient;
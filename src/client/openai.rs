@@ -1,18 +1,235 @@
+use std::pin::Pin;
+use std::time::Duration;
+
 use async_trait::async_trait;
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use utoipa::ToSchema;
 
 // ============================================================================
 // Data structures (pure, no I/O)
 // ============================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A chat message
+///
+/// On the wire `content` is either a string or an array of text and image
+/// parts; images are kept apart from the text so the pipeline can route on
+/// it and only send them to models that accept images.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(from = "WireMessage", into = "WireMessage")]
 pub struct Message {
     pub role: String,
+    /// Text of the message, with text parts joined by newlines. Assistant
+    /// messages that only call tools carry `null` content.
+    #[schema(value_type = MessageContent)]
     pub content: String,
+    /// Images from `image_url` parts, sent after the text
+    #[schema(ignore)]
+    pub images: Vec<ImageUrl>,
+    /// Prompt cache breakpoint, sent on the message's text part
+    #[schema(ignore)]
+    pub cache_control: Option<CacheControl>,
+    /// Tools the assistant asked to call
+    pub tool_calls: Vec<ToolCall>,
+    /// For `tool` messages: the call this message answers
+    pub tool_call_id: Option<String>,
+    pub name: Option<String>,
+}
+
+/// An image as a URL, or a `data:image/...;base64,` URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ImageUrl {
+    pub url: String,
+    /// "low", "high" or "auto"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Anthropic-style prompt cache breakpoint: the provider caches the
+/// prompt up to and including the part carrying it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CacheControl {
+    /// Always "ephemeral" today
+    #[serde(rename = "type")]
+    pub cache_type: String,
+    /// "5m" or "1h"; the provider's default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
+}
+
+impl CacheControl {
+    pub fn ephemeral(ttl: Option<&str>) -> Self {
+        Self {
+            cache_type: "ephemeral".to_string(),
+            ttl: ttl.map(str::to_string),
+        }
+    }
+}
+
+/// One part of array-of-parts message content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    ImageUrl {
+        image_url: ImageUrl,
+    },
+}
+
+/// Message content: plain text, or text and image parts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// [`Message`] as it appears in requests and responses
+#[derive(Serialize, Deserialize)]
+struct WireMessage {
+    role: String,
+    #[serde(default)]
+    content: Option<MessageContent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+impl From<WireMessage> for Message {
+    fn from(wire: WireMessage) -> Self {
+        let mut cache_control = None;
+        let (content, images) = match wire.content {
+            None => (String::new(), Vec::new()),
+            Some(MessageContent::Text(text)) => (text, Vec::new()),
+            Some(MessageContent::Parts(parts)) => {
+                let mut texts = Vec::new();
+                let mut images = Vec::new();
+                for part in parts {
+                    match part {
+                        ContentPart::Text {
+                            text,
+                            cache_control: marker,
+                        } => {
+                            texts.push(text);
+                            cache_control = marker.or(cache_control);
+                        }
+                        ContentPart::ImageUrl { image_url } => images.push(image_url),
+                    }
+                }
+                (texts.join("\n"), images)
+            }
+        };
+
+        Self {
+            role: wire.role,
+            content,
+            images,
+            cache_control,
+            tool_calls: wire.tool_calls,
+            tool_call_id: wire.tool_call_id,
+            name: wire.name,
+        }
+    }
+}
+
+impl From<Message> for WireMessage {
+    fn from(message: Message) -> Self {
+        // Cache markers only ride on parts, so a marked message is sent as
+        // parts even without images
+        let marked = message.cache_control.is_some() && !message.content.is_empty();
+        let content = if message.images.is_empty() && !marked {
+            MessageContent::Text(message.content)
+        } else {
+            let text = (!message.content.is_empty()).then_some(ContentPart::Text {
+                text: message.content,
+                cache_control: message.cache_control,
+            });
+            let images = message
+                .images
+                .into_iter()
+                .map(|image_url| ContentPart::ImageUrl { image_url });
+            MessageContent::Parts(text.into_iter().chain(images).collect())
+        };
+
+        Self {
+            role: message.role,
+            content: Some(content),
+            tool_calls: message.tool_calls,
+            tool_call_id: message.tool_call_id,
+            name: message.name,
+        }
+    }
+}
+
+/// A tool the model may call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Tool {
+    /// Always "function" today
+    #[serde(rename = "type", default = "default_tool_type")]
+    pub tool_type: String,
+    pub function: FunctionDefinition,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON Schema for the arguments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// A tool call made by the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "default_tool_type")]
+    pub call_type: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FunctionCall {
+    pub name: String,
+    /// Arguments as a JSON-encoded string, exactly as the model produced them
+    #[serde(default)]
+    pub arguments: String,
+}
+
+/// How the model should pick tools: "none", "auto", "required", or a
+/// specific function
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    Mode(String),
+    Function {
+        #[serde(rename = "type")]
+        choice_type: String,
+        function: ToolChoiceFunction,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ToolChoiceFunction {
+    pub name: String,
+}
+
+fn default_tool_type() -> String {
+    "function".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<Message>,
@@ -20,6 +237,13 @@ pub struct ChatCompletionRequest {
     pub max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// How long the call may take, answer included
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -36,13 +260,87 @@ pub struct Choice {
     pub finish_reason: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
 }
 
+/// Embedding input: a single string or a batch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+impl EmbeddingInput {
+    /// Flatten into a list of inputs
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            EmbeddingInput::Single(s) => vec![s],
+            EmbeddingInput::Batch(v) => v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: EmbeddingInput,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmbeddingResponse {
+    #[serde(default = "default_list_object")]
+    pub object: String,
+    pub data: Vec<Embedding>,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub usage: Option<EmbeddingUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Embedding {
+    #[serde(default = "default_embedding_object")]
+    pub object: String,
+    pub index: u32,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmbeddingUsage {
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+}
+
+/// Audio to transcribe, sent as a multipart upload
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptionRequest {
+    pub model: String,
+    /// Name of the uploaded file; servers guess the format from its extension
+    pub file_name: String,
+    pub content_type: String,
+    pub audio: Vec<u8>,
+    /// ISO-639-1 code of the spoken language; detected when not set
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TranscriptionResponse {
+    pub text: String,
+}
+
+fn default_list_object() -> String {
+    "list".to_string()
+}
+
+fn default_embedding_object() -> String {
+    "embedding".to_string()
+}
+
 #[derive(Error, Debug)]
 pub enum ClientError {
     #[error("HTTP error: {0}")]
@@ -53,6 +351,113 @@ pub enum ClientError {
 
     #[error("API error: {status} - {message}")]
     Api { status: u16, message: String },
+
+    #[error("Blocked by the provider: {0}")]
+    Blocked(String),
+}
+
+/// Content deltas of a streamed chat completion, in order
+pub type ChunkStream = Pin<Box<dyn Stream<Item = Result<String, ClientError>> + Send>>;
+
+/// A chat completion request asking for a streamed answer
+#[derive(Serialize)]
+struct StreamingRequest<'a> {
+    #[serde(flatten)]
+    request: &'a ChatCompletionRequest,
+    stream: bool,
+}
+
+/// One `data:` event of a streamed chat completion
+#[derive(Debug, Deserialize)]
+struct StreamEvent {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: StreamDelta,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StreamDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// The content delta of one line of a server-sent event stream, if it has
+/// one; `[DONE]`, comments and role-only deltas have none
+pub fn parse_stream_line(line: &str) -> Result<Option<String>, ClientError> {
+    let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+        return Ok(None);
+    };
+    if data.is_empty() || data == "[DONE]" {
+        return Ok(None);
+    }
+    let event: StreamEvent =
+        serde_json::from_str(data).map_err(|e| ClientError::Parse(e.to_string()))?;
+    Ok(event
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|c| c.delta.content)
+        .filter(|c| !c.is_empty()))
+}
+
+/// Splits a streamed response body into lines, which may straddle the
+/// body's chunks
+#[derive(Debug, Default)]
+pub struct SseLines {
+    buffer: Vec<u8>,
+}
+
+impl SseLines {
+    /// Content deltas of the lines completed by `bytes`
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Result<String, ClientError>> {
+        self.buffer.extend_from_slice(bytes);
+        let mut deltas = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(delta) = parse_stream_line(line.trim_end()).transpose() {
+                deltas.push(delta);
+            }
+        }
+        deltas
+    }
+}
+
+/// Encode a transcription request as `multipart/form-data`
+pub fn multipart_body(boundary: &str, request: &TranscriptionRequest) -> Vec<u8> {
+    let mut body = Vec::with_capacity(request.audio.len() + 512);
+    let mut field = |name: &str, value: &str| {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    };
+    field("model", &request.model);
+    field("response_format", "json");
+    if let Some(language) = &request.language {
+        field("language", language);
+    }
+
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+            boundary,
+            request.file_name.replace('"', ""),
+            request.content_type
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(&request.audio);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
 }
 
 // ============================================================================
@@ -65,6 +470,27 @@ pub trait OpenAiClientTrait: Send + Sync {
         &self,
         request: &ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse, ClientError>;
+
+    async fn embeddings(
+        &self,
+        request: &EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, ClientError>;
+
+    /// Stream a chat completion's answer as it is generated. Clients that
+    /// can't stream yield the whole answer as one chunk.
+    async fn chat_completion_stream(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChunkStream, ClientError> {
+        let response = self.chat_completion(request).await?;
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content)
+            .unwrap_or_default();
+        Ok(Box::pin(futures::stream::once(async move { Ok(content) })))
+    }
 }
 
 // ============================================================================
@@ -96,21 +522,82 @@ impl OpenAiClient {
     pub fn base_url(&self) -> &str {
         &self.base_url
     }
+
+    /// The same client pointed at another server, sharing its connection pool
+    pub fn with_base_url(&self, base_url: String) -> Self {
+        Self {
+            base_url,
+            ..self.clone()
+        }
+    }
 }
 
-#[async_trait]
-impl OpenAiClientTrait for OpenAiClient {
-    async fn chat_completion(
+impl OpenAiClient {
+    /// POST a JSON body to an API path and decode the response
+    async fn post_json<B: Serialize + Sync, R: serde::de::DeserializeOwned>(
         &self,
-        request: &ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse, ClientError> {
+        path: &str,
+        body: &B,
+    ) -> Result<R, ClientError> {
+        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
+        self.send(self.client.post(&url).json(body)).await
+    }
+
+    /// A POST of a chat completion, bounded by the request's timeout
+    fn chat_post(
+        &self,
+        body: &impl Serialize,
+        timeout: Option<Duration>,
+    ) -> reqwest::RequestBuilder {
         let url = format!(
             "{}/v1/chat/completions",
             self.base_url.trim_end_matches('/')
         );
+        let req = self.client.post(&url).json(body);
+        match timeout {
+            Some(timeout) => req.timeout(timeout),
+            None => req,
+        }
+    }
+
+    /// Transcribe audio with `/v1/audio/transcriptions`
+    pub async fn transcribe(
+        &self,
+        request: &TranscriptionRequest,
+    ) -> Result<TranscriptionResponse, ClientError> {
+        let url = format!(
+            "{}/v1/audio/transcriptions",
+            self.base_url.trim_end_matches('/')
+        );
+        let boundary = format!("llmnet-{}", uuid::Uuid::new_v4().simple());
+        let req = self
+            .client
+            .post(&url)
+            .header(
+                "Content-Type",
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(multipart_body(&boundary, request));
+        self.send(req).await
+    }
 
-        let mut req = self.client.post(&url).json(request);
+    /// Send a request with the API key and decode the JSON response
+    async fn send<R: serde::de::DeserializeOwned>(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<R, ClientError> {
+        self.send_raw(req)
+            .await?
+            .json()
+            .await
+            .map_err(|e| ClientError::Parse(e.to_string()))
+    }
 
+    /// Send a request with the API key, failing on an error status
+    async fn send_raw(
+        &self,
+        mut req: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ClientError> {
         if let Some(ref key) = self.api_key {
             req = req.header("Authorization", format!("Bearer {}", key));
         }
@@ -132,15 +619,70 @@ impl OpenAiClientTrait for OpenAiClient {
             });
         }
 
-        let response: ChatCompletionResponse = response
-            .json()
-            .await
-            .map_err(|e| ClientError::Parse(e.to_string()))?;
-
         Ok(response)
     }
 }
 
+#[async_trait]
+impl OpenAiClientTrait for OpenAiClient {
+    async fn chat_completion(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, ClientError> {
+        self.send(self.chat_post(request, request.timeout)).await
+    }
+
+    async fn embeddings(
+        &self,
+        request: &EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, ClientError> {
+        self.post_json("/v1/embeddings", request).await
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChunkStream, ClientError> {
+        let body = StreamingRequest {
+            request,
+            stream: true,
+        };
+        let response = self
+            .send_raw(self.chat_post(&body, request.timeout))
+            .await?;
+
+        // Servers that can't stream answer with the whole completion
+        let is_json = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"));
+        if is_json {
+            let completion: ChatCompletionResponse = response
+                .json()
+                .await
+                .map_err(|e| ClientError::Parse(e.to_string()))?;
+            let content = completion
+                .choices
+                .into_iter()
+                .next()
+                .map(|c| c.message.content)
+                .unwrap_or_default();
+            return Ok(Box::pin(futures::stream::once(async move { Ok(content) })));
+        }
+
+        let mut lines = SseLines::default();
+        let deltas = response
+            .bytes_stream()
+            .map(move |bytes| match bytes {
+                Ok(bytes) => lines.push(&bytes),
+                Err(e) => vec![Err(ClientError::Http(e.to_string()))],
+            })
+            .flat_map(futures::stream::iter);
+        Ok(Box::pin(deltas))
+    }
+}
+
 // ============================================================================
 // SBIO: Mock implementation for testing (no I/O)
 // ============================================================================
@@ -189,6 +731,7 @@ pub mod mock {
                     message: Message {
                         role: "assistant".to_string(),
                         content,
+                        ..Default::default()
                     },
                     finish_reason: Some("stop".to_string()),
                 }],
@@ -199,6 +742,32 @@ pub mod mock {
                 }),
             })
         }
+
+        async fn embeddings(
+            &self,
+            request: &EmbeddingRequest,
+        ) -> Result<EmbeddingResponse, ClientError> {
+            self.call_count.fetch_add(1, Ordering::SeqCst);
+            let data = request
+                .input
+                .clone()
+                .into_vec()
+                .iter()
+                .enumerate()
+                .map(|(i, text)| Embedding {
+                    object: "embedding".to_string(),
+                    index: i as u32,
+                    embedding: vec![text.len() as f32, 1.0],
+                })
+                .collect();
+
+            Ok(EmbeddingResponse {
+                object: "list".to_string(),
+                data,
+                model: request.model.clone(),
+                usage: None,
+            })
+        }
     }
 }
 
@@ -211,12 +780,75 @@ mod tests {
         let msg = Message {
             role: "user".to_string(),
             content: "Hello".to_string(),
+            ..Default::default()
         };
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("user"));
         assert!(json.contains("Hello"));
     }
 
+    #[test]
+    fn test_multimodal_message() {
+        let json = r#"{
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "What is in this picture?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0K", "detail": "low"}},
+                {"type": "text", "text": "Answer briefly."}
+            ]
+        }"#;
+        let msg: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(msg.content, "What is in this picture?\nAnswer briefly.");
+        assert_eq!(msg.images.len(), 1);
+        assert_eq!(msg.images[0].url, "data:image/png;base64,iVBORw0K");
+        assert_eq!(msg.images[0].detail.as_deref(), Some("low"));
+
+        // Images go back out as parts after the text
+        let value = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            value["content"],
+            serde_json::json!([
+                {"type": "text", "text": "What is in this picture?\nAnswer briefly."},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0K", "detail": "low"}}
+            ])
+        );
+
+        // Text-only messages stay plain strings
+        let msg = Message {
+            role: "user".to_string(),
+            content: "Hello".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(&msg).unwrap()["content"], "Hello");
+    }
+
+    #[test]
+    fn test_cache_control_message() {
+        let msg = Message {
+            role: "system".to_string(),
+            content: "You are a support agent.".to_string(),
+            cache_control: Some(CacheControl::ephemeral(Some("1h"))),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            value["content"],
+            serde_json::json!([{
+                "type": "text",
+                "text": "You are a support agent.",
+                "cache_control": {"type": "ephemeral", "ttl": "1h"}
+            }])
+        );
+        // Clients' markers are kept
+        assert_eq!(serde_json::from_value::<Message>(value).unwrap(), msg);
+
+        let empty = Message {
+            cache_control: Some(CacheControl::ephemeral(None)),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(&empty).unwrap()["content"], "");
+    }
+
     #[test]
     fn test_request_serialization() {
         let req = ChatCompletionRequest {
@@ -224,9 +856,11 @@ mod tests {
             messages: vec![Message {
                 role: "user".to_string(),
                 content: "test".to_string(),
+                ..Default::default()
             }],
             max_tokens: Some(100),
             temperature: None,
+            ..Default::default()
         };
         let json = serde_json::to_string(&req).unwrap();
         assert!(json.contains("gpt-4"));
@@ -261,6 +895,62 @@ mod tests {
         assert_eq!(resp.choices[0].message.content, "Hello!");
     }
 
+    #[test]
+    fn test_tool_call_response_deserialization() {
+        let json = r#"{
+            "id": "chatcmpl-456",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_abc",
+                        "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        }"#;
+
+        let resp: ChatCompletionResponse = serde_json::from_str(json).unwrap();
+        let message = &resp.choices[0].message;
+        assert_eq!(message.content, "");
+        assert_eq!(message.tool_calls[0].function.name, "get_weather");
+        assert_eq!(
+            message.tool_calls[0].function.arguments,
+            r#"{"city":"Oslo"}"#
+        );
+    }
+
+    #[test]
+    fn test_tools_serialization() {
+        let req = ChatCompletionRequest {
+            model: "gpt-4".to_string(),
+            tools: vec![Tool {
+                tool_type: "function".to_string(),
+                function: FunctionDefinition {
+                    name: "lookup".to_string(),
+                    description: None,
+                    parameters: Some(serde_json::json!({"type": "object"})),
+                    strict: None,
+                },
+            }],
+            tool_choice: Some(ToolChoice::Mode("required".to_string())),
+            ..Default::default()
+        };
+        let json: serde_json::Value = serde_json::to_value(&req).unwrap();
+        assert_eq!(json["tools"][0]["type"], "function");
+        assert_eq!(json["tools"][0]["function"]["name"], "lookup");
+        assert_eq!(json["tool_choice"], "required");
+
+        // Requests without tools look exactly as before
+        let plain = serde_json::to_value(ChatCompletionRequest::default()).unwrap();
+        assert!(plain.get("tools").is_none());
+        assert!(plain.get("tool_choice").is_none());
+    }
+
     #[tokio::test]
     async fn test_mock_client() {
         let client =
@@ -271,6 +961,7 @@ mod tests {
             messages: vec![],
             max_tokens: None,
             temperature: None,
+            ..Default::default()
         };
 
         let resp1 = client.chat_completion(&req).await.unwrap();
@@ -281,4 +972,96 @@ mod tests {
 
         assert_eq!(client.call_count(), 2);
     }
+
+    #[test]
+    fn test_embedding_input_forms() {
+        let single: EmbeddingRequest =
+            serde_json::from_str(r#"{"model": "m", "input": "hello"}"#).unwrap();
+        assert_eq!(single.input.into_vec(), vec!["hello"]);
+
+        let batch: EmbeddingRequest =
+            serde_json::from_str(r#"{"model": "m", "input": ["a", "b"]}"#).unwrap();
+        assert_eq!(batch.input.into_vec(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_embedding_response_deserialization() {
+        let json = r#"{
+            "data": [{"index": 0, "embedding": [0.1, 0.2, 0.3]}],
+            "model": "nomic-embed-text"
+        }"#;
+
+        let resp: EmbeddingResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.object, "list");
+        assert_eq!(resp.data[0].embedding.len(), 3);
+        assert_eq!(resp.data[0].object, "embedding");
+    }
+
+    #[test]
+    fn test_streaming_request_serialization() {
+        let req = ChatCompletionRequest {
+            model: "gpt-4".to_string(),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(StreamingRequest {
+            request: &req,
+            stream: true,
+        })
+        .unwrap();
+        assert_eq!(json["model"], "gpt-4");
+        assert_eq!(json["stream"], true);
+    }
+
+    #[test]
+    fn test_sse_lines() {
+        let mut lines = SseLines::default();
+        let first = lines
+            .push(b"data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\ndata: {\"choi");
+        assert!(first.is_empty());
+
+        let rest = lines.push(
+            b"ces\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n: keep-alive\ndata: {\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\ndata: [DONE]\n\n",
+        );
+        let deltas: Vec<String> = rest.into_iter().map(Result::unwrap).collect();
+        assert_eq!(deltas, ["Hel", "lo"]);
+
+        assert!(matches!(
+            parse_stream_line("data: {not json"),
+            Err(ClientError::Parse(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_default_stream_is_one_chunk() {
+        let client = mock::MockOpenAiClient::new(vec!["whole answer".to_string()]);
+        let chunks: Vec<String> = client
+            .chat_completion_stream(&ChatCompletionRequest::default())
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(chunks, ["whole answer"]);
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let request = TranscriptionRequest {
+            model: "whisper-1".to_string(),
+            file_name: "clip.wav".to_string(),
+            content_type: "audio/wav".to_string(),
+            audio: b"RIFF".to_vec(),
+            language: Some("en".to_string()),
+        };
+        let body = String::from_utf8(multipart_body("b0", &request)).unwrap();
+
+        assert!(body.starts_with(
+            "--b0\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n"
+        ));
+        assert!(body.contains("name=\"language\"\r\n\r\nen\r\n"));
+        assert!(body.contains(
+            "name=\"file\"; filename=\"clip.wav\"\r\nContent-Type: audio/wav\r\n\r\nRIFF\r\n"
+        ));
+        assert!(body.ends_with("--b0--\r\n"));
+    }
 }
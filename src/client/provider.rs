@@ -0,0 +1,102 @@
+//! A model's client, whichever API the model speaks
+
+use async_trait::async_trait;
+
+use crate::client::gemini::GeminiClient;
+use crate::client::openai::{
+    ChatCompletionRequest, ChatCompletionResponse, ChunkStream, ClientError, EmbeddingRequest,
+    EmbeddingResponse, OpenAiClient, OpenAiClientTrait, TranscriptionRequest,
+    TranscriptionResponse,
+};
+
+/// Client for a model, chosen by its `interface`
+#[derive(Clone)]
+pub enum ModelClient {
+    /// `"interface": "openai-api"`, the default
+    OpenAi(OpenAiClient),
+    /// `"interface": "gemini"`
+    Gemini(GeminiClient),
+}
+
+impl ModelClient {
+    pub fn model(&self) -> &str {
+        match self {
+            ModelClient::OpenAi(client) => client.model(),
+            ModelClient::Gemini(client) => client.model(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        match self {
+            ModelClient::OpenAi(client) => client.base_url(),
+            ModelClient::Gemini(client) => client.base_url(),
+        }
+    }
+
+    /// The same client pointed at another server, sharing its connection pool
+    pub fn with_base_url(&self, base_url: String) -> Self {
+        match self {
+            ModelClient::OpenAi(client) => ModelClient::OpenAi(client.with_base_url(base_url)),
+            ModelClient::Gemini(client) => ModelClient::Gemini(client.with_base_url(base_url)),
+        }
+    }
+
+    /// Transcribe audio; only OpenAI-compatible servers take audio uploads
+    pub async fn transcribe(
+        &self,
+        request: &TranscriptionRequest,
+    ) -> Result<TranscriptionResponse, ClientError> {
+        match self {
+            ModelClient::OpenAi(client) => client.transcribe(request).await,
+            ModelClient::Gemini(_) => Err(ClientError::Api {
+                status: 400,
+                message: "Gemini models can't transcribe audio uploads".to_string(),
+            }),
+        }
+    }
+}
+
+impl From<OpenAiClient> for ModelClient {
+    fn from(client: OpenAiClient) -> Self {
+        ModelClient::OpenAi(client)
+    }
+}
+
+impl From<GeminiClient> for ModelClient {
+    fn from(client: GeminiClient) -> Self {
+        ModelClient::Gemini(client)
+    }
+}
+
+#[async_trait]
+impl OpenAiClientTrait for ModelClient {
+    async fn chat_completion(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse, ClientError> {
+        match self {
+            ModelClient::OpenAi(client) => client.chat_completion(request).await,
+            ModelClient::Gemini(client) => client.chat_completion(request).await,
+        }
+    }
+
+    async fn embeddings(
+        &self,
+        request: &EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, ClientError> {
+        match self {
+            ModelClient::OpenAi(client) => client.embeddings(request).await,
+            ModelClient::Gemini(client) => client.embeddings(request).await,
+        }
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        request: &ChatCompletionRequest,
+    ) -> Result<ChunkStream, ClientError> {
+        match self {
+            ModelClient::OpenAi(client) => client.chat_completion_stream(request).await,
+            ModelClient::Gemini(client) => client.chat_completion_stream(request).await,
+        }
+    }
+}
@@ -0,0 +1,571 @@
+//! Capacity-aware admission of new pipelines
+//!
+//! Before a pipeline is accepted the control plane does a dry run of
+//! scheduling it: which schedulable nodes match its node pool, node
+//! selector and runners, and how many of its replicas fit on them given
+//! each node's free pipeline slots, the pool's `maxPipelines`, and
+//! allocatable CPU, memory and GPUs against the pipeline's `resources`. A
+//! pipeline that can't fit is rejected unless the deploy is forced.
+//!
+//! Resources a node doesn't report (zero) aren't checked on that node, and
+//! a cluster with no schedulable nodes at all admits anything: the pipeline
+//! waits for workers to join, as it always has.
+//!
+//! The pipeline's local models are also checked against each node's memory
+//! with the edge device validation ([`validate_model_for_device`]), taking
+//! their quantization into account. A node a model can't fit in is skipped,
+//! and memory pressure or a heavy quantization is reported as a warning.
+
+use super::node::{Node, NodeCapacity, CUDA_LABEL};
+use super::node_pool::NodePool;
+use super::pipeline::{Pipeline, ResourceRequirements};
+use crate::config::{validate_model_for_device, DeviceProfile, RunnerType, ValidationSeverity};
+use crate::runtime::docker::{format_memory_size, parse_memory_size};
+use crate::runtime::HostCapacity;
+
+/// What one replica of a pipeline needs, parsed from its `resources`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplicaDemand {
+    /// CPU cores
+    pub cpu: f64,
+    /// Memory in bytes
+    pub memory: u64,
+    pub gpu: u32,
+    /// GPU memory in bytes
+    pub gpu_memory: u64,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Parse a memory quantity ("16Gi", "512Mi", "16g", "1073741824") into bytes
+pub fn parse_quantity(quantity: &str) -> Option<u64> {
+    let quantity = quantity.trim();
+    let quantity = quantity
+        .strip_suffix('i')
+        .or_else(|| quantity.strip_suffix("iB"))
+        .unwrap_or(quantity);
+    parse_memory_size(quantity)
+}
+
+/// Parse a CPU quantity ("4", "0.5", "500m") into cores
+pub fn parse_cpu(quantity: &str) -> Option<f64> {
+    let quantity = quantity.trim();
+    let cores = match quantity.strip_suffix('m') {
+        Some(millis) => millis.parse::<f64>().ok()? / 1000.0,
+        None => quantity.parse::<f64>().ok()?,
+    };
+    (cores.is_finite() && cores >= 0.0).then_some(cores)
+}
+
+impl ReplicaDemand {
+    /// Parse a pipeline's resource requirements
+    pub fn from_requirements(resources: &ResourceRequirements) -> Result<Self, String> {
+        let memory = |field: &str, value: &Option<String>| match value {
+            Some(v) => {
+                parse_quantity(v).ok_or_else(|| format!("resources.{} '{}' isn't a size", field, v))
+            }
+            None => Ok(0),
+        };
+        let cpu = match &resources.cpu {
+            Some(v) => {
+                parse_cpu(v).ok_or_else(|| format!("resources.cpu '{}' isn't a number", v))?
+            }
+            None => 0.0,
+        };
+        Ok(Self {
+            cpu,
+            memory: memory("memory", &resources.memory)?,
+            gpu: resources.gpu.unwrap_or(0),
+            gpu_memory: memory("gpu_memory", &resources.gpu_memory)?,
+        })
+    }
+
+    /// How many replicas fit in a node's allocatable resources, ignoring
+    /// resources the node doesn't report
+    pub fn replicas_fitting(&self, allocatable: &NodeCapacity) -> usize {
+        let fits = |needed: f64, available: f64| {
+            if needed <= 0.0 || available <= 0.0 {
+                usize::MAX
+            } else {
+                (available / needed).floor() as usize
+            }
+        };
+        [
+            fits(self.cpu, allocatable.cpu as f64),
+            fits(self.memory as f64, allocatable.memory as f64),
+            fits(self.gpu as f64, allocatable.gpu as f64),
+            fits(self.gpu_memory as f64, allocatable.gpu_memory as f64),
+        ]
+        .into_iter()
+        .min()
+        .unwrap_or(usize::MAX)
+    }
+
+    /// Resources of this demand that exceed a node's allocatable capacity,
+    /// described for an error message
+    fn shortfalls(&self, allocatable: &NodeCapacity) -> Vec<String> {
+        let mut shortfalls = Vec::new();
+        if allocatable.cpu > 0 && self.cpu > allocatable.cpu as f64 {
+            shortfalls.push(format!("{} CPUs (has {})", self.cpu, allocatable.cpu));
+        }
+        if allocatable.memory > 0 && self.memory > allocatable.memory {
+            shortfalls.push(format!(
+                "{} of memory (has {})",
+                format_memory_size(self.memory),
+                format_memory_size(allocatable.memory)
+            ));
+        }
+        if allocatable.gpu > 0 && self.gpu > allocatable.gpu {
+            shortfalls.push(format!("{} GPUs (has {})", self.gpu, allocatable.gpu));
+        }
+        if allocatable.gpu_memory > 0 && self.gpu_memory > allocatable.gpu_memory {
+            shortfalls.push(format!(
+                "{} of GPU memory (has {})",
+                format_memory_size(self.gpu_memory),
+                format_memory_size(allocatable.gpu_memory)
+            ));
+        }
+        shortfalls
+    }
+}
+
+impl NodeCapacity {
+    /// Capacity of the host a worker runs on, as detected at startup
+    ///
+    /// GPUs that can't be counted are reported as none, so they aren't
+    /// checked at admission.
+    pub fn from_host(host: &HostCapacity) -> Self {
+        Self {
+            cpu: host.cpus,
+            memory: host.memory,
+            gpu: host.gpus.unwrap_or(0),
+            ..Self::default()
+        }
+    }
+
+    /// What's left for pipelines once `reserved` is set aside for the system
+    ///
+    /// A reservation can't take all of a resource the node reports, since
+    /// zero would read as "not reported" and switch its checks off.
+    /// Resources the node doesn't report stay unreported.
+    pub fn without_reserved(&self, reserved: &NodeCapacity) -> Result<Self, String> {
+        fn remaining(
+            total: u64,
+            reserved: u64,
+            describe: impl Fn(u64) -> String,
+        ) -> Result<u64, String> {
+            if total > 0 && reserved >= total {
+                return Err(format!(
+                    "reserving {} leaves nothing of the node's {}",
+                    describe(reserved),
+                    describe(total)
+                ));
+            }
+            Ok(total.saturating_sub(reserved))
+        }
+
+        let cpus = |n: u64| format!("{} CPUs", n);
+        let gpus = |n: u64| format!("{} GPUs", n);
+        Ok(Self {
+            cpu: remaining(self.cpu.into(), reserved.cpu.into(), cpus)? as u32,
+            memory: remaining(self.memory, reserved.memory, format_memory_size)?,
+            gpu: remaining(self.gpu.into(), reserved.gpu.into(), gpus)? as u32,
+            gpu_memory: remaining(self.gpu_memory, reserved.gpu_memory, format_memory_size)?,
+            max_pipelines: self.max_pipelines,
+        })
+    }
+}
+
+/// What checking a pipeline's models against a node found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelFit {
+    /// Why a model can't run on the node, e.g. "model 'llm': ..."
+    pub errors: Vec<String>,
+    /// What may make a model run poorly on the node
+    pub warnings: Vec<String>,
+}
+
+/// Device profile of a node from the memory it has left for pipelines: GPU
+/// memory when it reports any, else system memory
+///
+/// None when the node reports neither, so its models aren't checked.
+pub fn node_device_profile(node: &Node) -> Option<DeviceProfile> {
+    let status = node.status.as_ref()?;
+    let reported = |c: &NodeCapacity| {
+        if c.gpu_memory > 0 {
+            c.gpu_memory
+        } else {
+            c.memory
+        }
+    };
+    let memory = match reported(&status.allocatable) {
+        0 => reported(&status.capacity),
+        memory => memory,
+    };
+    if memory == 0 {
+        return None;
+    }
+    let cuda = status.capacity.gpu > 0 || node.metadata.labels.contains_key(CUDA_LABEL);
+    Some(DeviceProfile::for_memory(
+        &format!("node '{}'", node.metadata.name),
+        memory as f32 / (1024.0 * 1024.0 * 1024.0),
+        status.allocatable.cpu,
+        cuda,
+    ))
+}
+
+/// Check the pipeline's local models against a node's memory and runners
+pub fn model_fit(pipeline: &Pipeline, node: &Node) -> ModelFit {
+    let mut fit = ModelFit::default();
+    let Some(profile) = node_device_profile(node) else {
+        return fit;
+    };
+
+    let mut models: Vec<_> = pipeline.spec.composition.models.iter().collect();
+    models.sort_by_key(|(name, _)| name.as_str());
+    for (name, model) in models {
+        let config = model.to_config();
+        if config.runner == RunnerType::External {
+            continue;
+        }
+        for message in validate_model_for_device(&config, &profile).messages {
+            let line = format!("model '{}': {}", name, message.message);
+            match message.severity {
+                ValidationSeverity::Error => fit.errors.push(line),
+                ValidationSeverity::Warning => fit.warnings.push(line),
+                ValidationSeverity::Info => {}
+            }
+        }
+    }
+    fit
+}
+
+/// Schedulable nodes matching the pipeline's node pool, node selector and
+/// runners
+fn candidate_nodes<'a>(
+    pipeline: &Pipeline,
+    nodes: &'a [Node],
+    pool: Option<&NodePool>,
+) -> Vec<&'a Node> {
+    let selector = &pipeline.spec.node_selector;
+    let runners = pipeline.required_runners();
+    nodes
+        .iter()
+        .filter(|n| n.can_schedule())
+        .filter(|n| {
+            selector
+                .iter()
+                .all(|(k, v)| n.metadata.labels.get(k) == Some(v))
+        })
+        .filter(|n| n.supports_runners(&runners))
+        .filter(|n| pool.is_none_or(|p| p.contains(n)))
+        .collect()
+}
+
+/// Warnings about how the pipeline's models fit on the nodes it could be
+/// scheduled to, each prefixed with the node
+pub fn model_warnings(pipeline: &Pipeline, nodes: &[Node], pool: Option<&NodePool>) -> Vec<String> {
+    candidate_nodes(pipeline, nodes, pool)
+        .into_iter()
+        .flat_map(|node| {
+            let fit = model_fit(pipeline, node);
+            // Nodes a model doesn't fit in aren't scheduled to
+            let warnings = if fit.errors.is_empty() {
+                fit.warnings
+            } else {
+                Vec::new()
+            };
+            warnings
+                .into_iter()
+                .map(move |w| format!("node '{}': {}", node.metadata.name, w))
+        })
+        .collect()
+}
+
+/// Why a new pipeline can't run on the current nodes; empty if it fits
+///
+/// `nodes` is every registered node; those that aren't schedulable are left
+/// out here. `pool` is the node pool the pipeline targets, if any.
+pub fn admission_problems(
+    pipeline: &Pipeline,
+    nodes: &[Node],
+    pool: Option<&NodePool>,
+) -> Vec<String> {
+    let demand = match ReplicaDemand::from_requirements(&pipeline.spec.resources) {
+        Ok(demand) => demand,
+        Err(e) => return vec![e],
+    };
+
+    if !nodes.iter().any(|n| n.can_schedule()) {
+        return Vec::new();
+    }
+
+    let selector = &pipeline.spec.node_selector;
+    let runners = pipeline.required_runners();
+    let candidates = candidate_nodes(pipeline, nodes, pool);
+    if candidates.is_empty() {
+        let mut wanted = Vec::new();
+        if pool.is_some() {
+            wanted.push("nodePool");
+        }
+        if !selector.is_empty() {
+            wanted.push("nodeSelector");
+        }
+        if !runners.is_empty() {
+            wanted.push("runners");
+        }
+        return vec![format!(
+            "no schedulable node matches the pipeline's {}",
+            wanted.join(" and ")
+        )];
+    }
+
+    // Leave out nodes the pipeline's models don't fit in
+    let mut model_errors = Vec::new();
+    let candidates: Vec<&Node> = candidates
+        .into_iter()
+        .filter(|node| {
+            let fit = model_fit(pipeline, node);
+            model_errors.extend(
+                fit.errors
+                    .iter()
+                    .map(|e| format!("node '{}': {}", node.metadata.name, e)),
+            );
+            fit.errors.is_empty()
+        })
+        .collect();
+    if candidates.is_empty() {
+        return model_errors;
+    }
+
+    let fitting: Vec<(&Node, NodeCapacity, usize)> = candidates
+        .into_iter()
+        .map(|n| {
+            let allocatable = n
+                .status
+                .as_ref()
+                .map(|s| s.allocatable.clone())
+                .unwrap_or_default();
+            let fits = demand.replicas_fitting(&allocatable).min(n.free_slots());
+            (n, allocatable, fits)
+        })
+        .collect();
+
+    if fitting.iter().all(|(_, _, fits)| *fits == 0) {
+        return fitting
+            .iter()
+            .map(|(node, allocatable, _)| {
+                let shortfalls = demand.shortfalls(allocatable);
+                if shortfalls.is_empty() {
+                    format!("node '{}' has no free pipeline slots", node.metadata.name)
+                } else {
+                    format!(
+                        "node '{}' can't provide {} per replica",
+                        node.metadata.name,
+                        shortfalls.join(", ")
+                    )
+                }
+            })
+            .collect();
+    }
+
+    let total: usize = fitting
+        .iter()
+        .map(|(_, _, fits)| *fits)
+        .fold(0, usize::saturating_add);
+    let replicas = pipeline.spec.replicas as usize;
+    if let Some((pool, room)) = pool.and_then(|p| Some((p, p.room_for(pipeline, nodes)?))) {
+        if (room as usize) < replicas {
+            return vec![format!(
+                "{} replicas requested but node pool '{}' has room for {} more",
+                replicas,
+                pool.name(),
+                room
+            )];
+        }
+    }
+    if total < replicas {
+        return vec![format!(
+            "{} replicas requested but the schedulable nodes have room for {}",
+            replicas, total
+        )];
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{NodeInfo, NodeStatus};
+    use crate::config::Composition;
+    use std::collections::HashMap;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn node(name: &str, memory: u64, gpu: u32) -> Node {
+        let capacity = NodeCapacity {
+            memory,
+            gpu,
+            ..NodeCapacity::default()
+        };
+        let mut node = Node::new(name, "127.0.0.1");
+        node.status = Some(NodeStatus::new(capacity, NodeInfo::from_system()));
+        node
+    }
+
+    fn pipeline(replicas: u32, memory: &str, gpu: u32) -> Pipeline {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {"m": {"type": "external", "interface": "openai-api", "url": "http://a"}},
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "m", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::new("chat", composition).with_replicas(replicas);
+        pipeline.spec.resources.memory = Some(memory.to_string());
+        pipeline.spec.resources.gpu = Some(gpu);
+        pipeline
+    }
+
+    #[test]
+    fn test_parse_quantities() {
+        assert_eq!(parse_quantity("16Gi"), Some(16 * GIB));
+        assert_eq!(parse_quantity("512Mi"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_quantity("16g"), Some(16 * GIB));
+        assert_eq!(parse_quantity("lots"), None);
+        assert_eq!(parse_cpu("500m"), Some(0.5));
+        assert_eq!(parse_cpu("4"), Some(4.0));
+        assert_eq!(parse_cpu("-1"), None);
+    }
+
+    #[test]
+    fn test_without_reserved() {
+        let host = NodeCapacity {
+            cpu: 8,
+            memory: 32 * GIB,
+            gpu: 2,
+            ..NodeCapacity::default()
+        };
+        let reserved = NodeCapacity {
+            cpu: 1,
+            memory: 2 * GIB,
+            gpu_memory: GIB,
+            ..NodeCapacity::default()
+        };
+
+        let allocatable = host.without_reserved(&reserved).unwrap();
+        assert_eq!(allocatable.cpu, 7);
+        assert_eq!(allocatable.memory, 30 * GIB);
+        assert_eq!(allocatable.gpu, 2);
+        // GPU memory isn't reported, so there's nothing to reserve from
+        assert_eq!(allocatable.gpu_memory, 0);
+        assert_eq!(allocatable.max_pipelines, host.max_pipelines);
+
+        let everything = NodeCapacity {
+            memory: 32 * GIB,
+            ..NodeCapacity::default()
+        };
+        let err = host.without_reserved(&everything).unwrap_err();
+        assert!(err.contains("leaves nothing"), "{}", err);
+    }
+
+    #[test]
+    fn test_admission_problems() {
+        let nodes = [node("small", 16 * GIB, 1), node("big", 64 * GIB, 2)];
+
+        assert!(admission_problems(&pipeline(2, "32Gi", 1), &nodes, None).is_empty());
+        assert_eq!(
+            admission_problems(&pipeline(3, "32Gi", 1), &nodes, None),
+            ["3 replicas requested but the schedulable nodes have room for 2"]
+        );
+        assert_eq!(
+            admission_problems(&pipeline(1, "128Gi", 4), &nodes, None),
+            [
+                "node 'small' can't provide 128.0g of memory (has 16.0g), 4 GPUs (has 1) per replica",
+                "node 'big' can't provide 128.0g of memory (has 64.0g), 4 GPUs (has 2) per replica",
+            ]
+        );
+        assert_eq!(
+            admission_problems(&pipeline(1, "lots", 0), &nodes, None),
+            ["resources.memory 'lots' isn't a size"]
+        );
+
+        let mut selective = pipeline(1, "1Gi", 0);
+        selective
+            .spec
+            .node_selector
+            .insert("zone".to_string(), "eu".to_string());
+        assert_eq!(
+            admission_problems(&selective, &nodes, None),
+            ["no schedulable node matches the pipeline's nodeSelector"]
+        );
+
+        let mut pooled = pipeline(2, "1Gi", 0);
+        pooled.spec.node_pool = Some("gpu".to_string());
+        let pool = NodePool::new(
+            "gpu",
+            HashMap::from([("accelerator".to_string(), "nvidia".to_string())]),
+        );
+        assert_eq!(
+            admission_problems(&pooled, &nodes, Some(&pool)),
+            ["no schedulable node matches the pipeline's nodePool"]
+        );
+        let nodes = nodes.map(|n| n.with_label("accelerator", "nvidia"));
+        assert!(admission_problems(&pooled, &nodes, Some(&pool)).is_empty());
+        assert_eq!(
+            admission_problems(&pooled, &nodes, Some(&pool.with_max_pipelines(1))),
+            ["2 replicas requested but node pool 'gpu' has room for 1 more"]
+        );
+
+        // Nothing to judge against until a worker joins
+        assert!(admission_problems(&pipeline(4, "128Gi", 4), &[], None).is_empty());
+    }
+
+    #[test]
+    fn test_model_fit() {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {"llm": {"runner": "llama-cpp", "source": "/models/llama-13b.Q4_K_M.gguf"}},
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "llm", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::new("chat", composition);
+
+        // 13B at Q4_K_M needs about 8.5GB
+        let small = node("small", 8 * GIB, 0);
+        let fit = model_fit(&pipeline, &small);
+        assert!(fit.errors[0].starts_with("model 'llm': Model (13.0B params) exceeds"));
+        let problems = admission_problems(&pipeline, std::slice::from_ref(&small), None);
+        assert!(problems
+            .iter()
+            .all(|p| p.starts_with("node 'small': model 'llm'")));
+
+        let nodes = [small, node("mid", 10 * GIB, 0), node("big", 64 * GIB, 0)];
+        assert!(admission_problems(&pipeline, &nodes, None).is_empty());
+        assert_eq!(
+            model_warnings(&pipeline, &nodes, None),
+            ["node 'mid': model 'llm': Model (13.0B params) is close to device limit (15.4B)"]
+        );
+
+        // Nodes that report no memory aren't checked
+        assert_eq!(
+            model_fit(&pipeline, &node("unknown", 0, 0)),
+            ModelFit::default()
+        );
+    }
+
+    #[test]
+    fn test_unreported_resources_are_not_checked() {
+        let nodes = [node("unknown", 0, 0)];
+        assert!(admission_problems(&pipeline(2, "128Gi", 4), &nodes, None).is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+//! Admission webhooks - external review of pipeline manifests
+//!
+//! Operators can have their own services review every pipeline before the
+//! control plane stores it (`serve --control-plane --admission-webhook URL`).
+//! Each webhook is POSTed the manifest of a pipeline being created or
+//! updated:
+//!
+//! ```json
+//! {"operation": "create", "pipeline": {"apiVersion": "llmnet/v1", ...}}
+//! ```
+//!
+//! and answers whether the pipeline is allowed, why not, and optionally the
+//! pipeline to store instead, e.g. with labels injected or resource limits
+//! enforced:
+//!
+//! ```json
+//! {"allowed": true, "pipeline": {"apiVersion": "llmnet/v1", ...}}
+//! ```
+//!
+//! Webhooks are called in the order they were given, each seeing the
+//! pipeline as the previous one left it. A webhook that can't be reached or
+//! gives an answer that can't be used rejects the pipeline.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::pipeline::Pipeline;
+
+/// Seconds a webhook may take to answer
+pub const DEFAULT_WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// Why a pipeline wasn't admitted
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum WebhookError {
+    #[error("Admission webhook {url} denied the pipeline: {message}")]
+    Denied { url: String, message: String },
+
+    #[error("Admission webhook {url} failed: {error}")]
+    Failed { url: String, error: String },
+}
+
+/// What is being done to the pipeline under review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdmissionOperation {
+    Create,
+    Update,
+}
+
+/// The body POSTed to a webhook
+#[derive(Debug, Serialize)]
+pub struct AdmissionReview<'a> {
+    pub operation: AdmissionOperation,
+    pub pipeline: &'a Pipeline,
+}
+
+/// A webhook's answer
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdmissionVerdict {
+    pub allowed: bool,
+    /// Why the pipeline was denied
+    #[serde(default)]
+    pub message: Option<String>,
+    /// The pipeline to store in place of the one reviewed
+    #[serde(default)]
+    pub pipeline: Option<Pipeline>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// The pipeline to go on with after a webhook's verdict. A webhook may
+/// change anything but the pipeline's name and namespace.
+pub fn apply_verdict(
+    url: &str,
+    pipeline: Pipeline,
+    verdict: AdmissionVerdict,
+) -> Result<Pipeline, WebhookError> {
+    if !verdict.allowed {
+        return Err(WebhookError::Denied {
+            url: url.to_string(),
+            message: verdict
+                .message
+                .unwrap_or_else(|| "no reason given".to_string()),
+        });
+    }
+    let Some(mut mutated) = verdict.pipeline else {
+        return Ok(pipeline);
+    };
+    if mutated.qualified_name() != pipeline.qualified_name() {
+        return Err(WebhookError::Failed {
+            url: url.to_string(),
+            error: format!(
+                "it renamed pipeline {} to {}",
+                pipeline.qualified_name(),
+                mutated.qualified_name()
+            ),
+        });
+    }
+    mutated.status = pipeline.status;
+    Ok(mutated)
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// The webhooks pipelines are reviewed by
+#[derive(Debug, Clone)]
+pub struct AdmissionWebhooks {
+    urls: Vec<String>,
+    client: reqwest::Client,
+    timeout: Duration,
+}
+
+impl Default for AdmissionWebhooks {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl AdmissionWebhooks {
+    pub fn new(urls: Vec<String>) -> Self {
+        Self {
+            urls,
+            client: reqwest::Client::new(),
+            timeout: Duration::from_secs(DEFAULT_WEBHOOK_TIMEOUT_SECS),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Have every webhook review a pipeline, returning the one to store
+    pub async fn review(
+        &self,
+        operation: AdmissionOperation,
+        mut pipeline: Pipeline,
+    ) -> Result<Pipeline, WebhookError> {
+        for url in &self.urls {
+            let verdict = self.call(url, operation, &pipeline).await?;
+            pipeline = apply_verdict(url, pipeline, verdict)?;
+        }
+        Ok(pipeline)
+    }
+
+    async fn call(
+        &self,
+        url: &str,
+        operation: AdmissionOperation,
+        pipeline: &Pipeline,
+    ) -> Result<AdmissionVerdict, WebhookError> {
+        let failed = |error: String| WebhookError::Failed {
+            url: url.to_string(),
+            error,
+        };
+        let response = self
+            .client
+            .post(url)
+            .timeout(self.timeout)
+            .json(&AdmissionReview {
+                operation,
+                pipeline,
+            })
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| failed(e.to_string()))?;
+        response
+            .json()
+            .await
+            .map_err(|e| failed(format!("invalid answer: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Composition;
+    use axum::Json;
+    use serde_json::Value;
+
+    fn pipeline() -> Pipeline {
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        Pipeline::new("chat", composition)
+    }
+
+    fn verdict(allowed: bool, pipeline: Option<Pipeline>) -> AdmissionVerdict {
+        AdmissionVerdict {
+            allowed,
+            message: None,
+            pipeline,
+        }
+    }
+
+    #[test]
+    fn test_apply_verdict() {
+        let denied = apply_verdict("http://hook", pipeline(), verdict(false, None));
+        assert!(
+            matches!(denied, Err(WebhookError::Denied { message, .. }) if message == "no reason given")
+        );
+
+        let mut scaled = pipeline();
+        scaled.spec.replicas = 3;
+        let admitted = apply_verdict("http://hook", pipeline(), verdict(true, Some(scaled)));
+        assert_eq!(admitted.unwrap().spec.replicas, 3);
+
+        let renamed = Pipeline::new("other", pipeline().spec.composition);
+        assert!(matches!(
+            apply_verdict("http://hook", pipeline(), verdict(true, Some(renamed))),
+            Err(WebhookError::Failed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_review_chains_webhooks() {
+        // Labels every pipeline, then caps replicas at 2
+        let app = axum::Router::new()
+            .route(
+                "/label",
+                axum::routing::post(|Json(mut review): Json<Value>| async move {
+                    assert_eq!(review["operation"], "create");
+                    review["pipeline"]["metadata"]["labels"]["team"] = "ml".into();
+                    Json(serde_json::json!({"allowed": true, "pipeline": review["pipeline"]}))
+                }),
+            )
+            .route(
+                "/limit",
+                axum::routing::post(|Json(review): Json<Value>| async move {
+                    let allowed = review["pipeline"]["spec"]["replicas"].as_u64() <= Some(2);
+                    Json(serde_json::json!({"allowed": allowed, "message": "at most 2 replicas"}))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let webhooks = AdmissionWebhooks::new(vec![
+            format!("http://{}/label", addr),
+            format!("http://{}/limit", addr),
+        ]);
+        let admitted = webhooks
+            .review(AdmissionOperation::Create, pipeline())
+            .await
+            .unwrap();
+        assert_eq!(admitted.metadata.labels["team"], "ml");
+
+        let mut big = pipeline();
+        big.spec.replicas = 5;
+        let denied = webhooks.review(AdmissionOperation::Create, big).await;
+        assert!(
+            matches!(denied, Err(WebhookError::Denied { message, .. }) if message == "at most 2 replicas")
+        );
+
+        let unreachable = AdmissionWebhooks::new(vec!["http://127.0.0.1:1/hook".to_string()]);
+        assert!(matches!(
+            unreachable
+                .review(AdmissionOperation::Update, pipeline())
+                .await,
+            Err(WebhookError::Failed { .. })
+        ));
+    }
+}
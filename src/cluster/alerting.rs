@@ -0,0 +1,744 @@
+//! Alerting - rules the control plane checks against the cluster's state
+//!
+//! Operators describe what should page them in the alerting config
+//! (`PUT /v1/config/alerting`, or `llmnet config alerting -f alerts.yaml`):
+//!
+//! ```yaml
+//! rules:
+//!   - name: node-down
+//!     condition: NodeNotReady
+//!     forSecs: 300
+//!     severity: critical
+//!   - name: pipeline-degraded
+//!     condition: PipelineNotReady
+//!     namespace: production
+//!   - name: errors
+//!     condition: ErrorRate
+//!     threshold: 5
+//! sinks:
+//!   - type: slack
+//!     url: https://hooks.slack.com/services/...
+//!   - type: pagerduty
+//!     routingKey: R0UT1NGK3Y
+//! ```
+//!
+//! Every few seconds each rule is checked: a node that isn't Ready, a
+//! pipeline with fewer ready replicas than it asks for, or a worker whose
+//! logged requests failed more than `threshold` percent of the time over
+//! the last `windowSecs`. A broken rule is a pending alert until it has held
+//! for `forSecs`, then it fires and every sink is notified; once the rule
+//! holds again the sinks are told it resolved. Alerts are listed by
+//! `GET /v1/alerts` (`llmnet get alerts`).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use super::controller::ClusterController;
+use super::node::Node;
+use super::pipeline::Pipeline;
+use crate::runtime::RequestLog;
+
+/// Seconds between evaluations of the alert rules
+pub const ALERT_EVALUATION_INTERVAL_SECS: u64 = 15;
+
+/// Seconds a sink may take to accept a notification
+pub const ALERT_SINK_TIMEOUT_SECS: u64 = 10;
+
+/// Where PagerDuty events are sent unless a sink names another URL
+pub const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Alert rules and where their notifications go
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AlertingConfig {
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+
+    #[serde(default)]
+    pub sinks: Vec<AlertSink>,
+}
+
+/// What an alert rule watches for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum AlertCondition {
+    /// A node isn't Ready (missed heartbeats or failed health checks)
+    NodeNotReady,
+    /// A pipeline has fewer ready replicas than it asks for
+    PipelineNotReady,
+    /// A worker's logged requests fail more often than `threshold` percent
+    ErrorRate,
+}
+
+/// How urgent an alert is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl AlertSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertSeverity::Info => "info",
+            AlertSeverity::Warning => "warning",
+            AlertSeverity::Critical => "critical",
+        }
+    }
+}
+
+/// A condition operators want to hear about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AlertRule {
+    pub name: String,
+
+    pub condition: AlertCondition,
+
+    /// Seconds the condition must hold before the alert fires
+    #[serde(rename = "forSecs", default)]
+    pub for_secs: u64,
+
+    #[serde(default)]
+    pub severity: AlertSeverity,
+
+    /// Only pipelines of this namespace (PipelineNotReady)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    /// Failed requests, in percent, above which the rule is broken
+    /// (ErrorRate)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+
+    /// Seconds of request logs the error rate is taken over (ErrorRate)
+    #[serde(rename = "windowSecs", default = "default_window_secs")]
+    pub window_secs: u64,
+
+    /// Fewest logged requests in the window for the error rate to count
+    /// (ErrorRate)
+    #[serde(rename = "minRequests", default = "default_min_requests")]
+    pub min_requests: usize,
+}
+
+fn default_window_secs() -> u64 {
+    300
+}
+
+fn default_min_requests() -> usize {
+    10
+}
+
+/// Where notifications are sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AlertSink {
+    /// POSTs each notification as JSON
+    Webhook { url: String },
+
+    /// Posts a message to a Slack incoming webhook
+    Slack { url: String },
+
+    /// Triggers and resolves PagerDuty incidents (Events API v2)
+    #[serde(rename = "pagerduty")]
+    PagerDuty {
+        #[serde(rename = "routingKey")]
+        routing_key: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+    },
+}
+
+/// Whether an alert has held long enough to fire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum AlertState {
+    /// The rule is broken but hasn't been for `forSecs` yet
+    Pending,
+    /// The rule has been broken for `forSecs`; sinks were notified
+    Firing,
+}
+
+/// A rule broken by one object, e.g. one node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Alert {
+    pub rule: String,
+
+    /// What breaks the rule (e.g. "node/worker-1")
+    pub object: String,
+
+    pub severity: AlertSeverity,
+
+    pub state: AlertState,
+
+    pub message: String,
+
+    /// When the rule was first seen broken
+    pub since: DateTime<Utc>,
+
+    /// When the alert fired
+    #[serde(rename = "firedAt", default, skip_serializing_if = "Option::is_none")]
+    pub fired_at: Option<DateTime<Utc>>,
+}
+
+/// A change sinks are told about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertNotification {
+    /// "firing" or "resolved"
+    pub status: String,
+    pub alert: Alert,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl AlertingConfig {
+    /// Check every rule and sink can be used
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names = HashSet::new();
+        for rule in &self.rules {
+            if rule.name.is_empty() {
+                return Err("every alert rule needs a name".to_string());
+            }
+            if !names.insert(&rule.name) {
+                return Err(format!("alert rule '{}' is defined twice", rule.name));
+            }
+            if rule.condition == AlertCondition::ErrorRate {
+                if !rule.threshold.is_some_and(|t| (0.0..=100.0).contains(&t)) {
+                    return Err(format!(
+                        "alert rule '{}' needs a threshold between 0 and 100",
+                        rule.name
+                    ));
+                }
+                if rule.window_secs == 0 {
+                    return Err(format!(
+                        "alert rule '{}' needs a windowSecs of at least 1",
+                        rule.name
+                    ));
+                }
+            }
+        }
+
+        for sink in &self.sinks {
+            match sink {
+                AlertSink::Webhook { url } | AlertSink::Slack { url } => check_url(url)?,
+                AlertSink::PagerDuty { routing_key, url } => {
+                    if routing_key.is_empty() {
+                        return Err("pagerduty sinks need a routingKey".to_string());
+                    }
+                    if let Some(url) = url {
+                        check_url(url)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_url(url: &str) -> Result<(), String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(format!("alert sink URL '{}' must be http(s)", url))
+    }
+}
+
+/// The objects breaking a rule right now, each with a message
+pub fn violations(
+    rule: &AlertRule,
+    nodes: &[Node],
+    pipelines: &[Pipeline],
+    logs: &[RequestLog],
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    match rule.condition {
+        AlertCondition::NodeNotReady => nodes
+            .iter()
+            .filter(|n| !n.is_ready())
+            .map(|n| {
+                let phase = n
+                    .status
+                    .as_ref()
+                    .map_or("Unknown".to_string(), |s| format!("{:?}", s.phase));
+                (
+                    format!("node/{}", n.metadata.name),
+                    format!("Node {} is {}", n.metadata.name, phase),
+                )
+            })
+            .collect(),
+        AlertCondition::PipelineNotReady => pipelines
+            .iter()
+            .filter(|p| !p.is_terminating())
+            .filter(|p| {
+                rule.namespace
+                    .as_deref()
+                    .is_none_or(|ns| p.metadata.namespace == ns)
+            })
+            .filter_map(|p| {
+                let ready = p.status.as_ref().map_or(0, |s| s.ready_replicas);
+                (ready < p.spec.replicas).then(|| {
+                    (
+                        format!("pipeline/{}", p.qualified_name()),
+                        format!(
+                            "Pipeline {} has {}/{} replicas ready",
+                            p.qualified_name(),
+                            ready,
+                            p.spec.replicas
+                        ),
+                    )
+                })
+            })
+            .collect(),
+        AlertCondition::ErrorRate => {
+            let threshold = rule.threshold.unwrap_or(100.0);
+            let since = now - chrono::Duration::seconds(rule.window_secs as i64);
+            let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+            for log in logs.iter().filter(|l| l.started_at >= since) {
+                let (total, failed) = counts.entry(log.source.as_str()).or_default();
+                *total += 1;
+                if log.error.is_some() {
+                    *failed += 1;
+                }
+            }
+
+            let mut broken: Vec<(String, String)> = counts
+                .into_iter()
+                .filter(|(_, (total, _))| *total >= rule.min_requests.max(1))
+                .filter_map(|(source, (total, failed))| {
+                    let rate = failed as f64 * 100.0 / total as f64;
+                    (rate > threshold).then(|| {
+                        (
+                            format!("worker/{}", source),
+                            format!(
+                                "{:.1}% of {} requests on {} failed in the last {}s",
+                                rate, total, source, rule.window_secs
+                            ),
+                        )
+                    })
+                })
+                .collect();
+            broken.sort();
+            broken
+        }
+    }
+}
+
+/// The alerts after a check, and what sinks should be told
+///
+/// `broken` lists each rule's current violations. New violations start as
+/// pending, pending alerts fire once their rule's `forSecs` has passed, and
+/// alerts whose violation is gone are dropped; the firing ones among those
+/// are reported resolved.
+pub fn step_alerts(
+    rules: &[AlertRule],
+    previous: &[Alert],
+    broken: &[(String, Vec<(String, String)>)],
+    now: DateTime<Utc>,
+) -> (Vec<Alert>, Vec<AlertNotification>) {
+    let mut alerts = Vec::new();
+    let mut notifications = Vec::new();
+
+    for (rule_name, objects) in broken {
+        let Some(rule) = rules.iter().find(|r| &r.name == rule_name) else {
+            continue;
+        };
+        for (object, message) in objects {
+            let mut alert = previous
+                .iter()
+                .find(|a| &a.rule == rule_name && &a.object == object)
+                .cloned()
+                .unwrap_or_else(|| Alert {
+                    rule: rule.name.clone(),
+                    object: object.clone(),
+                    severity: rule.severity,
+                    state: AlertState::Pending,
+                    message: message.clone(),
+                    since: now,
+                    fired_at: None,
+                });
+            alert.severity = rule.severity;
+            alert.message = message.clone();
+
+            let held = (now - alert.since).num_seconds() >= rule.for_secs as i64;
+            if alert.state == AlertState::Pending && held {
+                alert.state = AlertState::Firing;
+                alert.fired_at = Some(now);
+                notifications.push(AlertNotification {
+                    status: "firing".to_string(),
+                    alert: alert.clone(),
+                });
+            }
+            alerts.push(alert);
+        }
+    }
+
+    for alert in previous {
+        let still_broken = alerts
+            .iter()
+            .any(|a| a.rule == alert.rule && a.object == alert.object);
+        if !still_broken && alert.state == AlertState::Firing {
+            notifications.push(AlertNotification {
+                status: "resolved".to_string(),
+                alert: alert.clone(),
+            });
+        }
+    }
+
+    (alerts, notifications)
+}
+
+/// The body a sink is sent for a notification
+pub fn sink_payload(sink: &AlertSink, notification: &AlertNotification) -> serde_json::Value {
+    let alert = &notification.alert;
+    let resolved = notification.status == "resolved";
+    match sink {
+        AlertSink::Webhook { .. } => serde_json::json!(notification),
+        AlertSink::Slack { .. } => {
+            let text = if resolved {
+                format!(
+                    ":white_check_mark: Resolved {}: {}",
+                    alert.rule, alert.message
+                )
+            } else {
+                format!(
+                    ":rotating_light: [{}] {}: {}",
+                    alert.severity.as_str(),
+                    alert.rule,
+                    alert.message
+                )
+            };
+            serde_json::json!({ "text": text })
+        }
+        AlertSink::PagerDuty { routing_key, .. } => serde_json::json!({
+            "routing_key": routing_key,
+            "event_action": if resolved { "resolve" } else { "trigger" },
+            "dedup_key": format!("llmnet/{}/{}", alert.rule, alert.object),
+            "payload": {
+                "summary": alert.message,
+                "source": alert.object,
+                "severity": alert.severity,
+                "component": "llmnet",
+            },
+        }),
+    }
+}
+
+fn sink_url(sink: &AlertSink) -> &str {
+    match sink {
+        AlertSink::Webhook { url } | AlertSink::Slack { url } => url,
+        AlertSink::PagerDuty { url, .. } => url.as_deref().unwrap_or(PAGERDUTY_EVENTS_URL),
+    }
+}
+
+// ============================================================================
+// I/O: Evaluating rules and notifying sinks
+// ============================================================================
+
+/// Send a notification to every sink; a sink that fails is logged and
+/// skipped
+pub async fn notify(
+    client: &reqwest::Client,
+    sinks: &[AlertSink],
+    notification: &AlertNotification,
+) {
+    for sink in sinks {
+        let url = sink_url(sink);
+        let result = client
+            .post(url)
+            .json(&sink_payload(sink, notification))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        if let Err(e) = result {
+            warn!(
+                "Failed to send alert {} for {} to {}: {}",
+                notification.alert.rule, notification.alert.object, url, e
+            );
+        }
+    }
+}
+
+/// Evaluate the alert rules every `interval` and notify the sinks of
+/// alerts that fire or resolve
+pub fn spawn_alerting(controller: Arc<ClusterController>, interval: Duration) -> JoinHandle<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(ALERT_SINK_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let notifications = controller.evaluate_alerts(Utc::now());
+            if notifications.is_empty() {
+                continue;
+            }
+            let sinks = controller.alerting_config().sinks;
+            for notification in &notifications {
+                notify(&client, &sinks, notification).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{NodeCapacity, NodeInfo, NodePhase, NodeStatus};
+    use crate::cluster::pipeline::PipelineStatus;
+    use crate::config::Composition;
+    use uuid::Uuid;
+
+    fn rule(name: &str, condition: AlertCondition, for_secs: u64) -> AlertRule {
+        AlertRule {
+            name: name.to_string(),
+            condition,
+            for_secs,
+            severity: AlertSeverity::Critical,
+            namespace: None,
+            threshold: Some(10.0),
+            window_secs: 300,
+            min_requests: 2,
+        }
+    }
+
+    fn node(name: &str, phase: NodePhase) -> Node {
+        let mut node = Node::new(name, "10.0.0.1");
+        let mut status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        status.phase = phase;
+        node.status = Some(status);
+        node
+    }
+
+    fn pipeline(namespace: &str, replicas: u32, ready: u32) -> Pipeline {
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::new("chat", composition)
+            .with_namespace(namespace)
+            .with_replicas(replicas);
+        let mut status = PipelineStatus::initial();
+        status.ready_replicas = ready;
+        pipeline.status = Some(status);
+        pipeline
+    }
+
+    fn log(source: &str, failed: bool, at: DateTime<Utc>) -> RequestLog {
+        RequestLog {
+            request_id: Uuid::new_v4(),
+            source: source.to_string(),
+            started_at: at,
+            duration_ms: 10,
+            prompt: "hi".to_string(),
+            output: None,
+            error: failed.then(|| "boom".to_string()),
+            route: vec![],
+            total_tokens: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_alerting_config() {
+        let config: AlertingConfig = serde_yaml::from_str(
+            r#"
+rules:
+  - name: node-down
+    condition: NodeNotReady
+    forSecs: 300
+    severity: critical
+  - name: errors
+    condition: ErrorRate
+    threshold: 5
+sinks:
+  - type: slack
+    url: https://hooks.slack.com/services/T000
+  - type: pagerduty
+    routingKey: abc
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.rules[0].for_secs, 300);
+        assert_eq!(config.rules[1].severity, AlertSeverity::Warning);
+        assert_eq!(config.rules[1].window_secs, 300);
+        assert_eq!(
+            config.sinks[1],
+            AlertSink::PagerDuty {
+                routing_key: "abc".to_string(),
+                url: None
+            }
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_alerting_config() {
+        let mut config = AlertingConfig {
+            rules: vec![rule("a", AlertCondition::ErrorRate, 0)],
+            sinks: vec![],
+        };
+        config.rules[0].threshold = None;
+        assert!(config.validate().unwrap_err().contains("threshold"));
+
+        config.rules[0].threshold = Some(5.0);
+        config
+            .rules
+            .push(rule("a", AlertCondition::NodeNotReady, 0));
+        assert!(config.validate().unwrap_err().contains("defined twice"));
+
+        config.rules.pop();
+        config.sinks.push(AlertSink::Webhook {
+            url: "hooks.internal".to_string(),
+        });
+        assert!(config.validate().unwrap_err().contains("http(s)"));
+    }
+
+    #[test]
+    fn test_violations() {
+        let now = Utc::now();
+        let nodes = vec![
+            node("up", NodePhase::Ready),
+            node("down", NodePhase::NotReady),
+        ];
+        let broken = violations(
+            &rule("n", AlertCondition::NodeNotReady, 0),
+            &nodes,
+            &[],
+            &[],
+            now,
+        );
+        assert_eq!(
+            broken,
+            vec![("node/down".to_string(), "Node down is NotReady".to_string())]
+        );
+
+        let pipelines = vec![pipeline("prod", 3, 1), pipeline("dev", 1, 1)];
+        let broken = violations(
+            &rule("p", AlertCondition::PipelineNotReady, 0),
+            &[],
+            &pipelines,
+            &[],
+            now,
+        );
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].0, "pipeline/prod/chat");
+        assert!(broken[0].1.contains("1/3"));
+
+        let mut dev_only = rule("p", AlertCondition::PipelineNotReady, 0);
+        dev_only.namespace = Some("dev".to_string());
+        assert!(violations(&dev_only, &[], &pipelines, &[], now).is_empty());
+
+        let old = now - chrono::Duration::seconds(600);
+        let logs = vec![
+            log("gpu-1", true, now),
+            log("gpu-1", false, now),
+            log("gpu-1", true, old),
+            log("gpu-2", false, now),
+            log("gpu-2", false, now),
+            log("gpu-3", true, now),
+        ];
+        let broken = violations(
+            &rule("e", AlertCondition::ErrorRate, 0),
+            &[],
+            &[],
+            &logs,
+            now,
+        );
+        // gpu-3 has too few requests to count, the old failure is outside
+        // the window
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].0, "worker/gpu-1");
+        assert!(broken[0].1.starts_with("50.0% of 2 requests"));
+    }
+
+    #[test]
+    fn test_step_alerts_pending_firing_resolved() {
+        let rules = vec![rule("node-down", AlertCondition::NodeNotReady, 300)];
+        let start = Utc::now();
+        let broken = vec![(
+            "node-down".to_string(),
+            vec![("node/a".to_string(), "Node a is NotReady".to_string())],
+        )];
+
+        let (alerts, notifications) = step_alerts(&rules, &[], &broken, start);
+        assert_eq!(alerts[0].state, AlertState::Pending);
+        assert!(notifications.is_empty());
+
+        let later = start + chrono::Duration::seconds(301);
+        let (alerts, notifications) = step_alerts(&rules, &alerts, &broken, later);
+        assert_eq!(alerts[0].state, AlertState::Firing);
+        assert_eq!(alerts[0].since, start);
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].status, "firing");
+
+        // Still broken: no second notification
+        let (alerts, notifications) = step_alerts(&rules, &alerts, &broken, later);
+        assert!(notifications.is_empty());
+
+        let (left, notifications) = step_alerts(&rules, &alerts, &[], later);
+        assert!(left.is_empty());
+        assert_eq!(notifications[0].status, "resolved");
+
+        // A pending alert that clears is dropped quietly
+        let (pending, _) = step_alerts(&rules, &[], &broken, start);
+        let (_, notifications) = step_alerts(&rules, &pending, &[], start);
+        assert!(notifications.is_empty());
+    }
+
+    #[test]
+    fn test_sink_payloads() {
+        let notification = AlertNotification {
+            status: "firing".to_string(),
+            alert: Alert {
+                rule: "node-down".to_string(),
+                object: "node/a".to_string(),
+                severity: AlertSeverity::Critical,
+                state: AlertState::Firing,
+                message: "Node a is NotReady".to_string(),
+                since: Utc::now(),
+                fired_at: Some(Utc::now()),
+            },
+        };
+
+        let slack = sink_payload(
+            &AlertSink::Slack {
+                url: "https://hooks.slack.com/x".to_string(),
+            },
+            &notification,
+        );
+        assert_eq!(
+            slack["text"],
+            ":rotating_light: [critical] node-down: Node a is NotReady"
+        );
+
+        let pagerduty = AlertSink::PagerDuty {
+            routing_key: "key".to_string(),
+            url: None,
+        };
+        let event = sink_payload(&pagerduty, &notification);
+        assert_eq!(event["event_action"], "trigger");
+        assert_eq!(event["dedup_key"], "llmnet/node-down/node/a");
+        assert_eq!(event["payload"]["severity"], "critical");
+        assert_eq!(sink_url(&pagerduty), PAGERDUTY_EVENTS_URL);
+
+        let resolved = AlertNotification {
+            status: "resolved".to_string(),
+            ..notification
+        };
+        assert_eq!(
+            sink_payload(&pagerduty, &resolved)["event_action"],
+            "resolve"
+        );
+    }
+}
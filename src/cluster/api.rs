@@ -1,51 +1,130 @@
 //! Control Plane API Server
 //!
 //! Provides REST endpoints for managing the LLMNet cluster:
-//! - Pipelines: deploy, list, get, delete, scale
-//! - Nodes: register, list, heartbeat
+//! - Pipelines: deploy, apply, list, get, delete, scale
+//! - Inference: proxy chat completions to a pipeline, splitting traffic
+//!   during canary rollouts
+//! - Virtual endpoints: split a route's traffic between pipelines by
+//!   weight and time window, with traffic counted per pipeline
+//! - Jobs: create, list, get, delete batch inference jobs and read results
+//! - Secrets: create, list, get, delete encrypted values; workers read the
+//!   decrypted values when starting runners
+//! - Nodes: register, list, heartbeat, cordon, maintenance windows, and a
+//!   WebSocket session for heartbeats and pushed assignments
+//! - Node pools: apply, list, get, delete groups of nodes pipelines target
+//!   by name
+//! - Regions: apply, list, get, delete child control planes pipelines are
+//!   delegated to
+//! - Events: actions the controller took on its own
 //! - Namespaces: list
 //! - Status: cluster health
+//! - Audit: log of mutating operations
+//! - Auth: with API keys loaded, every request needs one, and each key only
+//!   reaches its namespaces
+//! - OpenAPI: this API described at `/openapi.json`
 
 use axum::{
-    body::Body,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{get, patch, post},
+    body::{Body, Bytes},
+    extract::{ws::WebSocketUpgrade, Extension, Path, Query, Request, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, patch, post, put},
     Json, Router,
 };
+use dashmap::DashMap;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tracing::warn;
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
 use super::{
-    controller::ClusterController,
+    admission_webhook::{AdmissionOperation, AdmissionWebhooks, WebhookError},
+    alerting::{Alert, AlertingConfig},
+    audit::{audit_actor, is_audited, resource_for_request, AuditEntry, AuditLog, AuditQuery},
+    auth::{required_access, Access, ApiKeys, AuthError, Caller},
+    controller::{ClusterController, ControllerError},
     health_checker::{get_cluster_health_summary, ClusterHealthSummary},
-    node::{Node, NodeScore, NodeStatus},
-    pipeline::{AutoscalingConfig, Pipeline},
-    resources::{OperationStatus, ResourceList},
-    ClusterStats,
+    job::{Job, JobResult},
+    maintenance::MaintenanceWindow,
+    node::{Node, NodeScore, NodeStatus, NodeStatusDelta},
+    node_pool::NodePool,
+    node_session::serve_node_session,
+    pipeline::{AutoscalingConfig, Pipeline, PipelineStatus},
+    proxy::{pick_replica, replica_targets},
+    region::Region,
+    resources::{
+        ClusterEvent, MetadataField, MetadataPatch, Namespace, OperationStatus, ResourceList,
+    },
+    rollout::routes_to_canary,
+    scoring::ScoringWeights,
+    secret::{Secret, SECRET_GRANT_HEADER},
+    virtual_endpoint::{pick_backend, VirtualEndpoint},
+    ClusterStats, API_VERSION,
 };
+use crate::runtime::budget::BudgetSpend;
+use crate::runtime::handoff::HANDOFF_DEPTH_HEADER;
+use crate::runtime::request_log::{RequestLog, RequestLogQuery};
+
+/// Response header naming the pipeline a virtual endpoint sent a request to
+pub const VARIANT_HEADER: &str = "x-llmnet-variant";
 
 /// Shared state for the control plane API
 #[derive(Clone)]
 pub struct ControlPlaneState {
     pub controller: Arc<ClusterController>,
+    pub audit: Arc<AuditLog>,
+    /// External services that review pipelines before they are stored
+    pub admission_webhooks: Arc<AdmissionWebhooks>,
+    /// Keys requests must carry; none lets everyone in
+    pub api_keys: Arc<ApiKeys>,
+    /// Client for proxying inference requests to workers
+    pub http: reqwest::Client,
+    /// Requests proxied so far, for spreading traffic across replicas
+    proxied: Arc<AtomicU64>,
+    /// Requests each virtual endpoint has split so far, by qualified name
+    split: Arc<DashMap<String, u64>>,
 }
 
 impl ControlPlaneState {
     pub fn new() -> Self {
-        Self {
-            controller: Arc::new(ClusterController::new()),
-        }
+        Self::with_controller(ClusterController::new())
     }
 
     pub fn with_controller(controller: ClusterController) -> Self {
         Self {
             controller: Arc::new(controller),
+            audit: Arc::new(AuditLog::in_memory()),
+            admission_webhooks: Arc::new(AdmissionWebhooks::default()),
+            api_keys: Arc::new(ApiKeys::default()),
+            http: reqwest::Client::new(),
+            proxied: Arc::new(AtomicU64::new(0)),
+            split: Arc::new(DashMap::new()),
         }
     }
+
+    /// Record mutating operations to this audit log
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Arc::new(audit);
+        self
+    }
+
+    /// Have these webhooks review pipelines before they are stored
+    pub fn with_admission_webhooks(mut self, webhooks: AdmissionWebhooks) -> Self {
+        self.admission_webhooks = Arc::new(webhooks);
+        self
+    }
+
+    /// Require one of these keys on every request
+    pub fn with_api_keys(mut self, keys: ApiKeys) -> Self {
+        self.api_keys = Arc::new(keys);
+        self
+    }
 }
 
 impl Default for ControlPlaneState {
@@ -70,12 +149,26 @@ pub fn create_control_plane_router(state: ControlPlaneState) -> Router {
         )
         .route(
             "/v1/namespaces/{namespace}/pipelines/{name}",
-            get(get_pipeline).delete(delete_pipeline),
+            get(get_pipeline)
+                .put(apply_pipeline)
+                .delete(delete_pipeline),
+        )
+        .route(
+            "/v1/namespaces/{namespace}/pipelines/{name}/chat/completions",
+            post(proxy_chat_completions),
         )
         .route(
             "/v1/namespaces/{namespace}/pipelines/{name}/scale",
             patch(scale_pipeline),
         )
+        .route(
+            "/v1/namespaces/{namespace}/pipelines/{name}/labels",
+            patch(patch_pipeline_labels),
+        )
+        .route(
+            "/v1/namespaces/{namespace}/pipelines/{name}/annotations",
+            patch(patch_pipeline_annotations),
+        )
         // Autoscaling
         .route(
             "/v1/namespaces/{namespace}/pipelines/{name}/autoscaling",
@@ -86,28 +179,251 @@ pub fn create_control_plane_router(state: ControlPlaneState) -> Router {
             "/v1/namespaces/{namespace}/pipelines/{name}/logs",
             get(stream_pipeline_logs),
         )
+        // Virtual endpoints
+        .route("/v1/endpoints", get(list_all_virtual_endpoints))
+        .route(
+            "/v1/namespaces/{namespace}/endpoints",
+            get(list_virtual_endpoints_in_namespace),
+        )
+        .route(
+            "/v1/namespaces/{namespace}/endpoints/{name}",
+            get(get_virtual_endpoint)
+                .put(apply_virtual_endpoint)
+                .delete(delete_virtual_endpoint),
+        )
+        .route(
+            "/v1/namespaces/{namespace}/endpoints/{name}/chat/completions",
+            post(proxy_endpoint_chat_completions),
+        )
+        // Jobs
+        .route("/v1/jobs", get(list_all_jobs))
+        .route(
+            "/v1/namespaces/{namespace}/jobs",
+            get(list_jobs_in_namespace).post(create_job),
+        )
+        .route(
+            "/v1/namespaces/{namespace}/jobs/{name}",
+            get(get_job).delete(delete_job),
+        )
+        .route(
+            "/v1/namespaces/{namespace}/jobs/{name}/results",
+            get(get_job_results),
+        )
+        // Secrets
+        .route("/v1/secrets", get(list_all_secrets))
+        .route(
+            "/v1/namespaces/{namespace}/secrets",
+            get(list_secrets_in_namespace).post(create_secret),
+        )
+        .route(
+            "/v1/namespaces/{namespace}/secrets/{name}",
+            get(get_secret).delete(delete_secret),
+        )
+        .route(
+            "/v1/namespaces/{namespace}/secrets/{name}/values",
+            get(get_secret_values),
+        )
         // Nodes
         .route("/v1/nodes", get(list_nodes).post(register_node))
-        .route("/v1/nodes/{name}", get(get_node).delete(unregister_node))
-        .route("/v1/nodes/{name}/heartbeat", post(node_heartbeat))
+        .route(
+            "/v1/nodes/{name}",
+            get(get_node).put(update_node).delete(unregister_node),
+        )
+        .route(
+            "/v1/nodes/{name}/heartbeat",
+            post(node_heartbeat).patch(node_heartbeat_delta),
+        )
+        .route("/v1/nodes/{name}/session", get(node_session))
         .route("/v1/nodes/{name}/score", get(get_node_score))
         .route("/v1/nodes/{name}/cordon", post(cordon_node))
         .route("/v1/nodes/{name}/uncordon", post(uncordon_node))
+        .route("/v1/nodes/{name}/maintenance", put(set_maintenance_windows))
+        .route("/v1/nodes/{name}/labels", patch(patch_node_labels))
+        .route(
+            "/v1/nodes/{name}/annotations",
+            patch(patch_node_annotations),
+        )
+        // Node pools
+        .route("/v1/nodepools", get(list_node_pools))
+        .route(
+            "/v1/nodepools/{name}",
+            get(get_node_pool)
+                .put(apply_node_pool)
+                .delete(delete_node_pool),
+        )
+        // Regions
+        .route("/v1/regions", get(list_regions))
+        .route(
+            "/v1/regions/{name}",
+            get(get_region).put(apply_region).delete(delete_region),
+        )
         // Namespaces
         .route("/v1/namespaces", get(list_namespaces))
+        // Cluster configuration
+        .route(
+            "/v1/config/scoring",
+            get(get_scoring_weights).put(update_scoring_weights),
+        )
+        .route(
+            "/v1/config/alerting",
+            get(get_alerting_config).put(update_alerting_config),
+        )
+        // Alerts
+        .route("/v1/alerts", get(list_alerts))
+        // Audit log
+        .route("/v1/audit", get(list_audit_entries))
+        // Events
+        .route("/v1/events", get(list_events).post(report_events))
+        // Request logs
+        .route(
+            "/v1/requestlogs",
+            get(list_request_logs).post(ship_request_logs),
+        )
+        // Budgets
+        .route("/v1/budgets", post(report_budget_spend))
         // Health check
         .route("/health", get(health_check))
+        // API description
+        .route("/openapi.json", get(openapi_json))
+        .merge(crate::server::openapi::swagger_ui())
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            authorize_requests,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            audit_requests,
+        ))
         .with_state(state)
 }
 
+// ============================================================================
+// OpenAPI
+// ============================================================================
+
+/// OpenAPI document for the control plane API, generated from the handlers
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "LLMNet Control Plane API",
+        description = "Manage pipelines, worker nodes and cluster settings"
+    ),
+    paths(
+        health_check,
+        cluster_status,
+        list_all_pipelines,
+        deploy_pipeline,
+        list_pipelines_in_namespace,
+        get_pipeline,
+        apply_pipeline,
+        delete_pipeline,
+        scale_pipeline,
+        patch_pipeline_labels,
+        patch_pipeline_annotations,
+        get_autoscaling,
+        update_autoscaling,
+        stream_pipeline_logs,
+        proxy_chat_completions,
+        list_all_virtual_endpoints,
+        list_virtual_endpoints_in_namespace,
+        get_virtual_endpoint,
+        apply_virtual_endpoint,
+        delete_virtual_endpoint,
+        proxy_endpoint_chat_completions,
+        list_all_jobs,
+        list_jobs_in_namespace,
+        create_job,
+        get_job,
+        delete_job,
+        get_job_results,
+        list_all_secrets,
+        list_secrets_in_namespace,
+        create_secret,
+        get_secret,
+        delete_secret,
+        get_secret_values,
+        list_nodes,
+        register_node,
+        get_node,
+        update_node,
+        unregister_node,
+        node_heartbeat,
+        node_heartbeat_delta,
+        node_session,
+        get_node_score,
+        cordon_node,
+        uncordon_node,
+        set_maintenance_windows,
+        patch_node_labels,
+        patch_node_annotations,
+        list_node_pools,
+        get_node_pool,
+        apply_node_pool,
+        delete_node_pool,
+        list_regions,
+        get_region,
+        apply_region,
+        delete_region,
+        list_namespaces,
+        get_scoring_weights,
+        update_scoring_weights,
+        get_alerting_config,
+        update_alerting_config,
+        list_alerts,
+        list_audit_entries,
+        list_events,
+        report_events,
+        list_request_logs,
+        ship_request_logs,
+        report_budget_spend,
+    ),
+    tags(
+        (name = "status", description = "Cluster health"),
+        (name = "pipelines", description = "Deploy and manage pipelines"),
+        (name = "inference", description = "Chat completions proxied to pipeline replicas"),
+        (name = "endpoints", description = "Routes split between pipelines"),
+        (name = "jobs", description = "Batch inference runs through a pipeline"),
+        (name = "secrets", description = "Values stored encrypted for pipeline runners"),
+        (name = "nodes", description = "Worker registration and heartbeats"),
+        (name = "nodepools", description = "Groups of nodes pipelines target by name"),
+        (name = "regions", description = "Child control planes pipelines are delegated to"),
+        (name = "namespaces", description = "Namespaces"),
+        (name = "config", description = "Cluster-wide settings"),
+        (name = "alerts", description = "Alert rules broken by nodes, pipelines and workers"),
+        (name = "audit", description = "Log of mutating operations"),
+        (name = "events", description = "Actions the control plane took on its own"),
+        (name = "requestlogs", description = "Sampled requests shipped by workers"),
+        (name = "budgets", description = "Spend on budgeted models across the cluster")
+    )
+)]
+pub struct ControlPlaneApi;
+
+async fn openapi_json() -> impl IntoResponse {
+    Json(ControlPlaneApi::openapi())
+}
+
 // ============================================================================
 // Health & Status
 // ============================================================================
 
+/// Liveness check
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "status",
+    responses((status = 200, description = "Control plane is up"))
+)]
 async fn health_check() -> impl IntoResponse {
     StatusCode::OK
 }
 
+/// Node and pipeline counts with replica health
+#[utoipa::path(
+    get,
+    path = "/v1/status",
+    tag = "status",
+    responses((status = 200, body = ClusterStatusResponse))
+)]
 async fn cluster_status(State(state): State<ControlPlaneState>) -> impl IntoResponse {
     let stats = state.controller.cluster_stats();
     let health = get_cluster_health_summary(&state.controller);
@@ -118,23 +434,361 @@ async fn cluster_status(State(state): State<ControlPlaneState>) -> impl IntoResp
     })
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct ClusterStatusResponse {
     status: String,
     stats: ClusterStats,
     health: ClusterHealthSummary,
 }
 
+// ============================================================================
+// Auth
+// ============================================================================
+
+/// Check the request's key reaches what it asks for, and hand the caller to
+/// the handlers that narrow lists to the caller's namespaces, and with the
+/// response to the audit log
+async fn authorize_requests(
+    State(state): State<ControlPlaneState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let access = required_access(request.method(), request.uri().path());
+    // Health checks and the API description need no key at all
+    if access == Access::Public {
+        return next.run(request).await;
+    }
+
+    let authorization = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    let authorized = state
+        .api_keys
+        .authenticate(authorization)
+        .and_then(|caller| caller.authorize(&access).map(|()| caller));
+    match authorized {
+        Ok(caller) => {
+            request.extensions_mut().insert(caller.clone());
+            let mut response = next.run(request).await;
+            response.extensions_mut().insert(caller);
+            response
+        }
+        Err(e) => auth_rejection(e),
+    }
+}
+
+/// Response refusing a request its key doesn't allow
+fn auth_rejection(error: AuthError) -> Response {
+    let status = match error {
+        AuthError::MissingKey | AuthError::UnknownKey => StatusCode::UNAUTHORIZED,
+        _ => StatusCode::FORBIDDEN,
+    };
+    (status, Json(OperationStatus::failure(error.to_string()))).into_response()
+}
+
+// ============================================================================
+// Audit
+// ============================================================================
+
+/// Largest request body buffered to name the resource a request created
+const MAX_AUDITED_BODY: usize = 16 * 1024 * 1024;
+
+/// Record mutating requests, with the status they were answered with
+async fn audit_requests(
+    State(state): State<ControlPlaneState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if !is_audited(&method, &path) {
+        return next.run(request).await;
+    }
+
+    let authorization = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+
+    // Creation endpoints name the resource in the manifest, not the path
+    let (request, resource) = match resource_for_request(&path, None) {
+        Some(resource) => (request, Some(resource)),
+        None => {
+            let (parts, body) = request.into_parts();
+            let bytes = match axum::body::to_bytes(body, MAX_AUDITED_BODY).await {
+                Ok(bytes) => bytes,
+                Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+            };
+            let manifest = serde_json::from_slice(&bytes).ok();
+            let resource = resource_for_request(&path, manifest.as_ref());
+            (Request::from_parts(parts, Body::from(bytes)), resource)
+        }
+    };
+
+    let response = next.run(request).await;
+
+    let actor = audit_actor(response.extensions().get(), authorization.as_deref());
+    state
+        .audit
+        .record_operation(
+            actor,
+            method.as_str(),
+            &path,
+            resource,
+            response.status().as_u16(),
+        )
+        .await;
+
+    response
+}
+
+/// Read the audit log, most recent last
+#[utoipa::path(
+    get,
+    path = "/v1/audit",
+    tag = "audit",
+    params(AuditQuery),
+    responses(
+        (status = 200, body = ResourceList<AuditEntry>),
+        (status = 500, body = OperationStatus)
+    )
+)]
+async fn list_audit_entries(
+    State(state): State<ControlPlaneState>,
+    Query(query): Query<AuditQuery>,
+) -> impl IntoResponse {
+    match state.audit.query(&query).await {
+        Ok(entries) => (
+            StatusCode::OK,
+            Json(ResourceList::new("AuditEntryList", entries)),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(OperationStatus::failure(e.to_string())),
+        )
+            .into_response(),
+    }
+}
+
+/// Recent actions the controller took on its own, most recent last
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    tag = "events",
+    responses((status = 200, body = ResourceList<ClusterEvent>))
+)]
+async fn list_events(State(state): State<ControlPlaneState>) -> impl IntoResponse {
+    Json(ResourceList::new(
+        "EventList",
+        state.controller.list_events(),
+    ))
+}
+
+/// Record events a worker reported
+#[utoipa::path(
+    post,
+    path = "/v1/events",
+    tag = "events",
+    request_body = Vec<ClusterEvent>,
+    responses((status = 202, description = "Events recorded"))
+)]
+async fn report_events(
+    State(state): State<ControlPlaneState>,
+    Json(events): Json<Vec<ClusterEvent>>,
+) -> impl IntoResponse {
+    state.controller.record_reported_events(events);
+    StatusCode::ACCEPTED
+}
+
+/// Read the requests workers logged, most recent last
+#[utoipa::path(
+    get,
+    path = "/v1/requestlogs",
+    tag = "requestlogs",
+    params(RequestLogQuery),
+    responses((status = 200, body = ResourceList<RequestLog>))
+)]
+async fn list_request_logs(
+    State(state): State<ControlPlaneState>,
+    Query(query): Query<RequestLogQuery>,
+) -> impl IntoResponse {
+    Json(ResourceList::new(
+        "RequestLogList",
+        state.controller.query_request_logs(&query),
+    ))
+}
+
+/// Store request logs a worker shipped
+#[utoipa::path(
+    post,
+    path = "/v1/requestlogs",
+    tag = "requestlogs",
+    request_body = Vec<RequestLog>,
+    responses((status = 202, description = "Logs stored"))
+)]
+async fn ship_request_logs(
+    State(state): State<ControlPlaneState>,
+    Json(logs): Json<Vec<RequestLog>>,
+) -> impl IntoResponse {
+    state.controller.record_request_logs(logs);
+    StatusCode::ACCEPTED
+}
+
+/// Add a worker's spend on budgeted models to the cluster-wide totals
+///
+/// Answers with the month's totals of the models reported on, which the
+/// worker checks the budgets against.
+#[utoipa::path(
+    post,
+    path = "/v1/budgets",
+    tag = "budgets",
+    request_body = Vec<BudgetSpend>,
+    responses((status = 200, body = Vec<BudgetSpend>))
+)]
+async fn report_budget_spend(
+    State(state): State<ControlPlaneState>,
+    Json(report): Json<Vec<BudgetSpend>>,
+) -> impl IntoResponse {
+    Json(state.controller.record_budget_spend(report))
+}
+
 // ============================================================================
 // Pipeline Endpoints
 // ============================================================================
 
+/// Deploy a new pipeline
+///
+/// The admission webhooks review the pipeline first and may change it. A
+/// pipeline that doesn't fit on the current schedulable nodes is rejected
+/// unless `force` is set, in which case it is deployed with warnings.
+#[utoipa::path(
+    post,
+    path = "/v1/pipelines",
+    tag = "pipelines",
+    params(DeployQuery),
+    request_body = Pipeline,
+    responses(
+        (status = 201, body = DeployResponse),
+        (status = 400, body = DeployResponse),
+        (status = 403, description = "An admission webhook denied the pipeline", body = DeployResponse),
+        (status = 422, description = "Pipeline doesn't fit on the cluster", body = DeployResponse),
+        (status = 502, description = "An admission webhook failed", body = DeployResponse)
+    )
+)]
 async fn deploy_pipeline(
     State(state): State<ControlPlaneState>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<DeployQuery>,
     Json(pipeline): Json<Pipeline>,
 ) -> impl IntoResponse {
+    if !caller.allows(&pipeline.metadata.namespace) {
+        let e = AuthError::NamespaceForbidden(caller.name, pipeline.metadata.namespace);
+        return (
+            StatusCode::FORBIDDEN,
+            Json(DeployResponse::error(e.to_string())),
+        );
+    }
+    let pipeline = match state
+        .admission_webhooks
+        .review(AdmissionOperation::Create, pipeline)
+        .await
+    {
+        Ok(pipeline) => pipeline,
+        Err(e) => return webhook_rejection(e),
+    };
+    let warnings = match admission_warnings(&state, &pipeline, query.force) {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(DeployResponse::error(e)),
+            )
+        }
+    };
     match state.controller.deploy_pipeline(pipeline) {
-        Ok(deployed) => (StatusCode::CREATED, Json(DeployResponse::success(deployed))),
+        Ok(deployed) => (
+            StatusCode::CREATED,
+            Json(DeployResponse::success(deployed).with_warnings(warnings)),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(DeployResponse::error(e.to_string())),
+        ),
+    }
+}
+
+/// Create or update a pipeline
+///
+/// The path names the pipeline; a manifest naming a different one is
+/// rejected. The admission webhooks review it as an update, or as a create
+/// when it's new, and a new pipeline is admitted as by `POST /v1/pipelines`.
+#[utoipa::path(
+    put,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name"),
+        DeployQuery
+    ),
+    request_body = Pipeline,
+    responses(
+        (status = 201, description = "Pipeline created", body = DeployResponse),
+        (status = 200, description = "Pipeline updated", body = DeployResponse),
+        (status = 400, body = DeployResponse),
+        (status = 403, description = "An admission webhook denied the pipeline", body = DeployResponse),
+        (status = 422, description = "New pipeline doesn't fit on the cluster", body = DeployResponse),
+        (status = 502, description = "An admission webhook failed", body = DeployResponse)
+    )
+)]
+async fn apply_pipeline(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(query): Query<DeployQuery>,
+    Json(pipeline): Json<Pipeline>,
+) -> impl IntoResponse {
+    if pipeline.metadata.namespace != namespace || pipeline.metadata.name != name {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(DeployResponse::error(format!(
+                "Manifest is for pipeline {}, not {}/{}",
+                pipeline.qualified_name(),
+                namespace,
+                name
+            ))),
+        );
+    }
+
+    let operation = match state.controller.get_pipeline(&namespace, &name) {
+        Some(_) => AdmissionOperation::Update,
+        None => AdmissionOperation::Create,
+    };
+    let pipeline = match state.admission_webhooks.review(operation, pipeline).await {
+        Ok(pipeline) => pipeline,
+        Err(e) => return webhook_rejection(e),
+    };
+    let warnings = match admission_warnings(&state, &pipeline, query.force) {
+        Ok(warnings) => warnings,
+        Err(e) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(DeployResponse::error(e)),
+            )
+        }
+    };
+    match state.controller.apply_pipeline(pipeline) {
+        Ok((applied, true)) => (
+            StatusCode::CREATED,
+            Json(DeployResponse::success(applied).with_warnings(warnings)),
+        ),
+        Ok((applied, false)) => (StatusCode::OK, Json(DeployResponse::success(applied))),
+        Err(e @ ControllerError::PipelineTerminating(..)) => (
+            StatusCode::CONFLICT,
+            Json(DeployResponse::error(e.to_string())),
+        ),
         Err(e) => (
             StatusCode::BAD_REQUEST,
             Json(DeployResponse::error(e.to_string())),
@@ -142,13 +796,54 @@ async fn deploy_pipeline(
     }
 }
 
-#[derive(Serialize)]
+/// Options for deploying a pipeline
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DeployQuery {
+    /// Deploy even if the pipeline doesn't fit on the current nodes
+    #[serde(default)]
+    force: bool,
+}
+
+/// The response to a pipeline an admission webhook rejected
+fn webhook_rejection(e: WebhookError) -> (StatusCode, Json<DeployResponse>) {
+    let status = match e {
+        WebhookError::Denied { .. } => StatusCode::FORBIDDEN,
+        WebhookError::Failed { .. } => StatusCode::BAD_GATEWAY,
+    };
+    (status, Json(DeployResponse::error(e.to_string())))
+}
+
+/// Admission problems of a new pipeline: why it's rejected, or warnings
+/// when the deploy is forced, followed by warnings about how its models fit
+/// on the nodes
+fn admission_warnings(
+    state: &ControlPlaneState,
+    pipeline: &Pipeline,
+    force: bool,
+) -> Result<Vec<String>, String> {
+    let mut problems = state.controller.admission_problems(pipeline);
+    if problems.is_empty() || force {
+        problems.extend(state.controller.model_warnings(pipeline));
+        return Ok(problems);
+    }
+    Err(format!(
+        "{}. Deploy with force to accept it anyway",
+        ControllerError::InsufficientCapacity(problems.join("; "))
+    ))
+}
+
+#[derive(Serialize, ToSchema)]
 struct DeployResponse {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pipeline: Option<Pipeline>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
+    /// Why a forced deploy might not be scheduled, and models that may run
+    /// poorly on the nodes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
 }
 
 impl DeployResponse {
@@ -157,6 +852,7 @@ impl DeployResponse {
             success: true,
             pipeline: Some(pipeline),
             error: None,
+            warnings: Vec::new(),
         }
     }
 
@@ -165,15 +861,55 @@ impl DeployResponse {
             success: false,
             pipeline: None,
             error: Some(msg),
+            warnings: Vec::new(),
         }
     }
+
+    fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+}
+
+/// Options for listing pipelines
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListPipelinesQuery {
+    /// Also list the pipelines federated regions run
+    #[serde(default)]
+    federated: bool,
 }
 
-async fn list_all_pipelines(State(state): State<ControlPlaneState>) -> impl IntoResponse {
-    let pipelines = state.controller.list_all_pipelines();
+/// List pipelines in every namespace
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines",
+    tag = "pipelines",
+    params(ListPipelinesQuery),
+    responses((status = 200, body = ResourceList<Pipeline>))
+)]
+async fn list_all_pipelines(
+    State(state): State<ControlPlaneState>,
+    Extension(caller): Extension<Caller>,
+    Query(query): Query<ListPipelinesQuery>,
+) -> impl IntoResponse {
+    let mut pipelines = if query.federated {
+        state.controller.list_federated_pipelines()
+    } else {
+        state.controller.list_all_pipelines()
+    };
+    pipelines.retain(|p| caller.allows(&p.metadata.namespace));
     Json(ResourceList::new("PipelineList", pipelines))
 }
 
+/// List pipelines in a namespace
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/pipelines",
+    tag = "pipelines",
+    params(("namespace" = String, Path, description = "Pipeline namespace")),
+    responses((status = 200, body = ResourceList<Pipeline>))
+)]
 async fn list_pipelines_in_namespace(
     State(state): State<ControlPlaneState>,
     Path(namespace): Path<String>,
@@ -182,6 +918,20 @@ async fn list_pipelines_in_namespace(
     Json(ResourceList::new("PipelineList", pipelines))
 }
 
+/// Get a pipeline
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name")
+    ),
+    responses(
+        (status = 200, body = Pipeline),
+        (status = 404, description = "Pipeline not found")
+    )
+)]
 async fn get_pipeline(
     State(state): State<ControlPlaneState>,
     Path((namespace, name)): Path<(String, String)>,
@@ -192,15 +942,58 @@ async fn get_pipeline(
     }
 }
 
+/// Options for deleting a pipeline
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DeleteQuery {
+    /// Delete even if the pipeline has deletion protection
+    #[serde(default)]
+    force: bool,
+}
+
+/// Delete a pipeline
+///
+/// A pipeline that was scheduled is kept, terminating, until the
+/// orchestrator has torn down its runners on the workers.
+#[utoipa::path(
+    delete,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name"),
+        DeleteQuery
+    ),
+    responses(
+        (status = 200, description = "Pipeline deleted", body = OperationStatus),
+        (status = 202, description = "Pipeline terminating", body = OperationStatus),
+        (status = 404, body = OperationStatus),
+        (status = 409, description = "Pipeline has deletion protection", body = OperationStatus)
+    )
+)]
 async fn delete_pipeline(
     State(state): State<ControlPlaneState>,
     Path((namespace, name)): Path<(String, String)>,
+    Query(query): Query<DeleteQuery>,
 ) -> impl IntoResponse {
-    match state.controller.delete_pipeline(&namespace, &name) {
-        Ok(_) => (
+    match state
+        .controller
+        .request_pipeline_deletion(&namespace, &name, query.force)
+    {
+        Ok((_, true)) => (
             StatusCode::OK,
             Json(OperationStatus::success("Pipeline deleted")),
         ),
+        Ok((_, false)) => (
+            StatusCode::ACCEPTED,
+            Json(OperationStatus::success(
+                "Pipeline terminating; it is removed once its runners are torn down",
+            )),
+        ),
+        Err(e @ ControllerError::DeletionProtected(..)) => (
+            StatusCode::CONFLICT,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
         Err(e) => (
             StatusCode::NOT_FOUND,
             Json(OperationStatus::failure(e.to_string())),
@@ -208,11 +1001,26 @@ async fn delete_pipeline(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct ScaleRequest {
     replicas: u32,
 }
 
+/// Change the number of replicas of a pipeline
+#[utoipa::path(
+    patch,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}/scale",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name")
+    ),
+    request_body = ScaleRequest,
+    responses(
+        (status = 200, body = DeployResponse),
+        (status = 404, body = DeployResponse)
+    )
+)]
 async fn scale_pipeline(
     State(state): State<ControlPlaneState>,
     Path((namespace, name)): Path<(String, String)>,
@@ -230,10 +1038,95 @@ async fn scale_pipeline(
     }
 }
 
+/// Set or remove labels of a pipeline
+#[utoipa::path(
+    patch,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}/labels",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name")
+    ),
+    request_body(content = Object, description = "Labels to set; null removes the label"),
+    responses(
+        (status = 200, body = DeployResponse),
+        (status = 404, body = DeployResponse)
+    )
+)]
+async fn patch_pipeline_labels(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(patch): Json<MetadataPatch>,
+) -> impl IntoResponse {
+    patch_pipeline_metadata(&state, &namespace, &name, MetadataField::Labels, &patch)
+}
+
+/// Set or remove annotations of a pipeline
+#[utoipa::path(
+    patch,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}/annotations",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name")
+    ),
+    request_body(content = Object, description = "Annotations to set; null removes the annotation"),
+    responses(
+        (status = 200, body = DeployResponse),
+        (status = 404, body = DeployResponse)
+    )
+)]
+async fn patch_pipeline_annotations(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(patch): Json<MetadataPatch>,
+) -> impl IntoResponse {
+    patch_pipeline_metadata(
+        &state,
+        &namespace,
+        &name,
+        MetadataField::Annotations,
+        &patch,
+    )
+}
+
+fn patch_pipeline_metadata(
+    state: &ControlPlaneState,
+    namespace: &str,
+    name: &str,
+    field: MetadataField,
+    patch: &MetadataPatch,
+) -> (StatusCode, Json<DeployResponse>) {
+    match state
+        .controller
+        .patch_pipeline_metadata(namespace, name, field, patch)
+    {
+        Ok(pipeline) => (StatusCode::OK, Json(DeployResponse::success(pipeline))),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(DeployResponse::error(e.to_string())),
+        ),
+    }
+}
+
 // ============================================================================
 // Autoscaling Endpoints
 // ============================================================================
 
+/// Get the autoscaling settings of a pipeline
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}/autoscaling",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name")
+    ),
+    responses(
+        (status = 200, body = AutoscalingResponse),
+        (status = 404, body = OperationStatus)
+    )
+)]
 async fn get_autoscaling(
     State(state): State<ControlPlaneState>,
     Path((namespace, name)): Path<(String, String)>,
@@ -256,7 +1149,7 @@ async fn get_autoscaling(
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct AutoscalingResponse {
     #[serde(rename = "pipelineName")]
     pipeline_name: String,
@@ -267,6 +1160,21 @@ struct AutoscalingResponse {
     autoscaling: Option<AutoscalingConfig>,
 }
 
+/// Replace the autoscaling settings of a pipeline
+#[utoipa::path(
+    put,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}/autoscaling",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name")
+    ),
+    request_body = AutoscalingConfig,
+    responses(
+        (status = 200, body = DeployResponse),
+        (status = 404, body = DeployResponse)
+    )
+)]
 async fn update_autoscaling(
     State(state): State<ControlPlaneState>,
     Path((namespace, name)): Path<(String, String)>,
@@ -291,41 +1199,70 @@ async fn update_autoscaling(
 }
 
 // ============================================================================
-// Node Endpoints
+// Virtual Endpoint Endpoints
 // ============================================================================
 
-async fn list_nodes(State(state): State<ControlPlaneState>) -> impl IntoResponse {
-    let nodes = state.controller.list_nodes();
-    Json(ResourceList::new("NodeList", nodes))
-}
-
-async fn register_node(
+/// Create or replace a virtual endpoint
+///
+/// Traffic counts of backends that stay listed are kept. A manifest whose
+/// name or namespace differs from the path is rejected.
+#[utoipa::path(
+    put,
+    path = "/v1/namespaces/{namespace}/endpoints/{name}",
+    tag = "endpoints",
+    params(
+        ("namespace" = String, Path, description = "Endpoint namespace"),
+        ("name" = String, Path, description = "Endpoint name")
+    ),
+    request_body = VirtualEndpoint,
+    responses(
+        (status = 200, body = VirtualEndpointResponse),
+        (status = 400, body = VirtualEndpointResponse)
+    )
+)]
+async fn apply_virtual_endpoint(
     State(state): State<ControlPlaneState>,
-    Json(node): Json<Node>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(endpoint): Json<VirtualEndpoint>,
 ) -> impl IntoResponse {
-    match state.controller.register_node(node.clone()) {
-        Ok(_) => (StatusCode::CREATED, Json(NodeResponse::success(Some(node)))),
+    if endpoint.metadata.namespace != namespace || endpoint.metadata.name != name {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(VirtualEndpointResponse::error(format!(
+                "Manifest is for {}, not {}/{}",
+                endpoint.qualified_name(),
+                namespace,
+                name
+            ))),
+        );
+    }
+
+    match state.controller.apply_virtual_endpoint(endpoint) {
+        Ok(applied) => (
+            StatusCode::OK,
+            Json(VirtualEndpointResponse::success(applied)),
+        ),
         Err(e) => (
             StatusCode::BAD_REQUEST,
-            Json(NodeResponse::error(e.to_string())),
+            Json(VirtualEndpointResponse::error(e.to_string())),
         ),
     }
 }
 
-#[derive(Serialize)]
-struct NodeResponse {
+#[derive(Serialize, ToSchema)]
+struct VirtualEndpointResponse {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    node: Option<Node>,
+    endpoint: Option<VirtualEndpoint>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
 }
 
-impl NodeResponse {
-    fn success(node: Option<Node>) -> Self {
+impl VirtualEndpointResponse {
+    fn success(endpoint: VirtualEndpoint) -> Self {
         Self {
             success: true,
-            node,
+            endpoint: Some(endpoint),
             error: None,
         }
     }
@@ -333,47 +1270,90 @@ impl NodeResponse {
     fn error(msg: String) -> Self {
         Self {
             success: false,
-            node: None,
+            endpoint: None,
             error: Some(msg),
         }
     }
 }
 
-async fn get_node(
+/// List virtual endpoints in every namespace
+#[utoipa::path(
+    get,
+    path = "/v1/endpoints",
+    tag = "endpoints",
+    responses((status = 200, body = ResourceList<VirtualEndpoint>))
+)]
+async fn list_all_virtual_endpoints(
     State(state): State<ControlPlaneState>,
-    Path(name): Path<String>,
+    Extension(caller): Extension<Caller>,
 ) -> impl IntoResponse {
-    match state.controller.get_node(&name) {
-        Some(node) => (StatusCode::OK, Json(Some(node))).into_response(),
-        None => (StatusCode::NOT_FOUND, Json::<Option<Node>>(None)).into_response(),
-    }
+    let mut endpoints = state.controller.list_all_virtual_endpoints();
+    endpoints.retain(|e| caller.allows(&e.metadata.namespace));
+    Json(ResourceList::new("VirtualEndpointList", endpoints))
 }
 
-async fn unregister_node(
+/// List virtual endpoints in a namespace
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/endpoints",
+    tag = "endpoints",
+    params(("namespace" = String, Path, description = "Endpoint namespace")),
+    responses((status = 200, body = ResourceList<VirtualEndpoint>))
+)]
+async fn list_virtual_endpoints_in_namespace(
     State(state): State<ControlPlaneState>,
-    Path(name): Path<String>,
+    Path(namespace): Path<String>,
 ) -> impl IntoResponse {
-    match state.controller.unregister_node(&name) {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(OperationStatus::success("Node unregistered")),
-        ),
-        Err(e) => (
-            StatusCode::NOT_FOUND,
-            Json(OperationStatus::failure(e.to_string())),
-        ),
+    let endpoints = state.controller.list_virtual_endpoints(&namespace);
+    Json(ResourceList::new("VirtualEndpointList", endpoints))
+}
+
+/// Get a virtual endpoint and its traffic per backend
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/endpoints/{name}",
+    tag = "endpoints",
+    params(
+        ("namespace" = String, Path, description = "Endpoint namespace"),
+        ("name" = String, Path, description = "Endpoint name")
+    ),
+    responses(
+        (status = 200, body = VirtualEndpoint),
+        (status = 404, description = "Virtual endpoint not found")
+    )
+)]
+async fn get_virtual_endpoint(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.controller.get_virtual_endpoint(&namespace, &name) {
+        Some(endpoint) => (StatusCode::OK, Json(Some(endpoint))).into_response(),
+        None => (StatusCode::NOT_FOUND, Json::<Option<VirtualEndpoint>>(None)).into_response(),
     }
 }
 
-async fn node_heartbeat(
+/// Delete a virtual endpoint, leaving its pipelines running
+#[utoipa::path(
+    delete,
+    path = "/v1/namespaces/{namespace}/endpoints/{name}",
+    tag = "endpoints",
+    params(
+        ("namespace" = String, Path, description = "Endpoint namespace"),
+        ("name" = String, Path, description = "Endpoint name")
+    ),
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn delete_virtual_endpoint(
     State(state): State<ControlPlaneState>,
-    Path(name): Path<String>,
-    Json(status): Json<NodeStatus>,
+    Path((namespace, name)): Path<(String, String)>,
 ) -> impl IntoResponse {
-    match state.controller.update_node_status(&name, status) {
+    match state.controller.delete_virtual_endpoint(&namespace, &name) {
         Ok(_) => (
             StatusCode::OK,
-            Json(OperationStatus::success("Heartbeat received")),
+            Json(OperationStatus::success("Virtual endpoint deleted")),
         ),
         Err(e) => (
             StatusCode::NOT_FOUND,
@@ -382,64 +1362,222 @@ async fn node_heartbeat(
     }
 }
 
-async fn get_node_score(
+/// Forward a chat completion to one of a virtual endpoint's pipelines
+///
+/// The pipeline is picked by weight among the backends active now and named
+/// in the `X-LLMNet-Variant` response header. Each request counts toward
+/// that backend's requests, errors and latency.
+#[utoipa::path(
+    post,
+    path = "/v1/namespaces/{namespace}/endpoints/{name}/chat/completions",
+    tag = "inference",
+    params(
+        ("namespace" = String, Path, description = "Endpoint namespace"),
+        ("name" = String, Path, description = "Endpoint name")
+    ),
+    request_body(content = Object, description = "OpenAI chat completion request"),
+    responses(
+        (status = 200, description = "Chat completion from a backend pipeline", body = Object),
+        (status = 404, description = "Virtual endpoint or backend pipeline not found"),
+        (status = 502, description = "Replica unreachable"),
+        (status = 503, description = "No backend is active or healthy")
+    )
+)]
+async fn proxy_endpoint_chat_completions(
     State(state): State<ControlPlaneState>,
-    Path(name): Path<String>,
-) -> impl IntoResponse {
-    match state.controller.get_node(&name) {
-        Some(node) => {
-            let score = node.status.as_ref().and_then(|s| s.score.clone());
-            match score {
-                Some(s) => {
-                    (StatusCode::OK, Json(NodeScoreResponse::with_score(name, s))).into_response()
-                }
-                None => (StatusCode::OK, Json(NodeScoreResponse::no_score(name))).into_response(),
-            }
-        }
-        None => (
+    Path((namespace, name)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(endpoint) = state.controller.get_virtual_endpoint(&namespace, &name) else {
+        return proxy_error(
             StatusCode::NOT_FOUND,
-            Json(OperationStatus::failure("Node not found")),
-        )
-            .into_response(),
+            ControllerError::VirtualEndpointNotFound(name, namespace).to_string(),
+        );
+    };
+
+    let n = {
+        let mut split = state.split.entry(endpoint.qualified_name()).or_default();
+        *split += 1;
+        *split - 1
+    };
+    let Some(backend) = pick_backend(&endpoint.spec.backends, chrono::Utc::now(), n) else {
+        return proxy_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Endpoint {}/{} has no active backends", namespace, name),
+        );
+    };
+    let pipeline = backend.pipeline.clone();
+
+    let started = std::time::Instant::now();
+    let mut response = forward_to_pipeline(&state, &namespace, &pipeline, &headers, body).await;
+    let status = response.status();
+    state.controller.record_variant_traffic(
+        &namespace,
+        &name,
+        &pipeline,
+        !status.is_server_error() && status != StatusCode::NOT_FOUND,
+        started.elapsed().as_millis() as u64,
+    );
+    if let Ok(value) = HeaderValue::from_str(&pipeline) {
+        response.headers_mut().insert(VARIANT_HEADER, value);
     }
+    response
 }
 
-#[derive(Serialize)]
-struct NodeScoreResponse {
-    #[serde(rename = "nodeName")]
-    node_name: String,
+// ============================================================================
+// Job Endpoints
+// ============================================================================
+
+/// Create a batch inference job
+///
+/// The job runs once its pipeline has a ready worker. A manifest for a
+/// different namespace than the path is rejected.
+#[utoipa::path(
+    post,
+    path = "/v1/namespaces/{namespace}/jobs",
+    tag = "jobs",
+    params(("namespace" = String, Path, description = "Job namespace")),
+    request_body = Job,
+    responses(
+        (status = 201, body = JobResponse),
+        (status = 400, body = JobResponse),
+        (status = 409, body = JobResponse)
+    )
+)]
+async fn create_job(
+    State(state): State<ControlPlaneState>,
+    Path(namespace): Path<String>,
+    Json(job): Json<Job>,
+) -> impl IntoResponse {
+    if job.metadata.namespace != namespace {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(JobResponse::error(format!(
+                "Manifest is for namespace {}, not {}",
+                job.metadata.namespace, namespace
+            ))),
+        );
+    }
+
+    match state.controller.create_job(job) {
+        Ok(created) => (StatusCode::CREATED, Json(JobResponse::success(created))),
+        Err(e @ ControllerError::JobExists(..)) => (
+            StatusCode::CONFLICT,
+            Json(JobResponse::error(e.to_string())),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(JobResponse::error(e.to_string())),
+        ),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct JobResponse {
+    success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    score: Option<NodeScore>,
+    job: Option<Job>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    message: Option<String>,
+    error: Option<String>,
 }
 
-impl NodeScoreResponse {
-    fn with_score(name: String, score: NodeScore) -> Self {
+impl JobResponse {
+    fn success(job: Job) -> Self {
         Self {
-            node_name: name,
-            score: Some(score),
-            message: None,
+            success: true,
+            job: Some(job),
+            error: None,
         }
     }
 
-    fn no_score(name: String) -> Self {
+    fn error(msg: String) -> Self {
         Self {
-            node_name: name,
-            score: None,
-            message: Some("No metrics available yet".to_string()),
+            success: false,
+            job: None,
+            error: Some(msg),
         }
     }
 }
 
-async fn cordon_node(
+/// List jobs in every namespace
+#[utoipa::path(
+    get,
+    path = "/v1/jobs",
+    tag = "jobs",
+    responses((status = 200, body = ResourceList<Job>))
+)]
+async fn list_all_jobs(
     State(state): State<ControlPlaneState>,
-    Path(name): Path<String>,
+    Extension(caller): Extension<Caller>,
 ) -> impl IntoResponse {
-    match state.controller.cordon_node(&name) {
+    let mut jobs = state.controller.list_all_jobs();
+    jobs.retain(|j| caller.allows(&j.metadata.namespace));
+    Json(ResourceList::new("JobList", jobs))
+}
+
+/// List jobs in a namespace
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/jobs",
+    tag = "jobs",
+    params(("namespace" = String, Path, description = "Job namespace")),
+    responses((status = 200, body = ResourceList<Job>))
+)]
+async fn list_jobs_in_namespace(
+    State(state): State<ControlPlaneState>,
+    Path(namespace): Path<String>,
+) -> impl IntoResponse {
+    let jobs = state.controller.list_jobs(&namespace);
+    Json(ResourceList::new("JobList", jobs))
+}
+
+/// Get a job and its progress
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/jobs/{name}",
+    tag = "jobs",
+    params(
+        ("namespace" = String, Path, description = "Job namespace"),
+        ("name" = String, Path, description = "Job name")
+    ),
+    responses(
+        (status = 200, body = Job),
+        (status = 404, description = "Job not found")
+    )
+)]
+async fn get_job(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.controller.get_job(&namespace, &name) {
+        Some(job) => (StatusCode::OK, Json(Some(job))).into_response(),
+        None => (StatusCode::NOT_FOUND, Json::<Option<Job>>(None)).into_response(),
+    }
+}
+
+/// Delete a job and its results, stopping it if it is running
+#[utoipa::path(
+    delete,
+    path = "/v1/namespaces/{namespace}/jobs/{name}",
+    tag = "jobs",
+    params(
+        ("namespace" = String, Path, description = "Job namespace"),
+        ("name" = String, Path, description = "Job name")
+    ),
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn delete_job(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.controller.delete_job(&namespace, &name) {
         Ok(_) => (
             StatusCode::OK,
-            Json(OperationStatus::success("Node cordoned")),
+            Json(OperationStatus::success("Job deleted")),
         ),
         Err(e) => (
             StatusCode::NOT_FOUND,
@@ -448,134 +1586,1252 @@ async fn cordon_node(
     }
 }
 
-async fn uncordon_node(
+/// Outputs of a job's prompts so far, in input order
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/jobs/{name}/results",
+    tag = "jobs",
+    params(
+        ("namespace" = String, Path, description = "Job namespace"),
+        ("name" = String, Path, description = "Job name")
+    ),
+    responses(
+        (status = 200, body = ResourceList<JobResult>),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn get_job_results(
     State(state): State<ControlPlaneState>,
-    Path(name): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
 ) -> impl IntoResponse {
-    match state.controller.uncordon_node(&name) {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(OperationStatus::success("Node uncordoned")),
-        ),
-        Err(e) => (
+    match state.controller.job_results(&namespace, &name) {
+        Some(results) => Json(ResourceList::new("JobResultList", results)).into_response(),
+        None => (
             StatusCode::NOT_FOUND,
-            Json(OperationStatus::failure(e.to_string())),
-        ),
+            Json(OperationStatus::failure(
+                ControllerError::JobNotFound(name, namespace).to_string(),
+            )),
+        )
+            .into_response(),
     }
 }
 
 // ============================================================================
-// Namespace Endpoints
+// Secret Endpoints
 // ============================================================================
 
-async fn list_namespaces(State(state): State<ControlPlaneState>) -> impl IntoResponse {
-    let namespaces = state.controller.list_namespaces();
-    Json(ResourceList::new("NamespaceList", namespaces))
+/// Create a secret from the plaintext values in its `stringData`
+///
+/// The values are encrypted before they are stored and never returned by
+/// this endpoint. A manifest for a different namespace than the path is
+/// rejected.
+#[utoipa::path(
+    post,
+    path = "/v1/namespaces/{namespace}/secrets",
+    tag = "secrets",
+    params(("namespace" = String, Path, description = "Secret namespace")),
+    request_body = Secret,
+    responses(
+        (status = 201, body = SecretResponse),
+        (status = 400, body = SecretResponse),
+        (status = 409, body = SecretResponse)
+    )
+)]
+async fn create_secret(
+    State(state): State<ControlPlaneState>,
+    Path(namespace): Path<String>,
+    Json(secret): Json<Secret>,
+) -> impl IntoResponse {
+    if secret.metadata.namespace != namespace {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SecretResponse::error(format!(
+                "Manifest is for namespace {}, not {}",
+                secret.metadata.namespace, namespace
+            ))),
+        );
+    }
+
+    match state.controller.create_secret(secret) {
+        Ok(created) => (StatusCode::CREATED, Json(SecretResponse::success(created))),
+        Err(e @ ControllerError::SecretExists(..)) => (
+            StatusCode::CONFLICT,
+            Json(SecretResponse::error(e.to_string())),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(SecretResponse::error(e.to_string())),
+        ),
+    }
 }
 
-// ============================================================================
-// Pipeline Logs
-// ============================================================================
+#[derive(Serialize, ToSchema)]
+struct SecretResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<Secret>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
 
-/// Query parameters for logs endpoint
-#[derive(Debug, Deserialize)]
-pub struct LogsQuery {
-    /// Follow log output (like tail -f)
-    #[serde(default)]
-    pub follow: bool,
-    /// Number of lines to show from the end
-    #[serde(default = "default_tail")]
-    pub tail: usize,
-    /// Container name (optional, defaults to first docker container in composition)
-    pub container: Option<String>,
+impl SecretResponse {
+    fn success(secret: Secret) -> Self {
+        Self {
+            success: true,
+            secret: Some(secret),
+            error: None,
+        }
+    }
+
+    fn error(msg: String) -> Self {
+        Self {
+            success: false,
+            secret: None,
+            error: Some(msg),
+        }
+    }
 }
 
-fn default_tail() -> usize {
-    100
+/// List secrets in every namespace, with their values encrypted
+#[utoipa::path(
+    get,
+    path = "/v1/secrets",
+    tag = "secrets",
+    responses((status = 200, body = ResourceList<Secret>))
+)]
+async fn list_all_secrets(
+    State(state): State<ControlPlaneState>,
+    Extension(caller): Extension<Caller>,
+) -> impl IntoResponse {
+    let mut secrets = state.controller.list_all_secrets();
+    secrets.retain(|s| caller.allows(&s.metadata.namespace));
+    Json(ResourceList::new("SecretList", secrets))
 }
 
-/// Stream logs for a pipeline's containers
-async fn stream_pipeline_logs(
+/// List secrets in a namespace, with their values encrypted
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/secrets",
+    tag = "secrets",
+    params(("namespace" = String, Path, description = "Secret namespace")),
+    responses((status = 200, body = ResourceList<Secret>))
+)]
+async fn list_secrets_in_namespace(
+    State(state): State<ControlPlaneState>,
+    Path(namespace): Path<String>,
+) -> impl IntoResponse {
+    let secrets = state.controller.list_secrets(&namespace);
+    Json(ResourceList::new("SecretList", secrets))
+}
+
+/// Get a secret, with its values encrypted
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/secrets/{name}",
+    tag = "secrets",
+    params(
+        ("namespace" = String, Path, description = "Secret namespace"),
+        ("name" = String, Path, description = "Secret name")
+    ),
+    responses(
+        (status = 200, body = Secret),
+        (status = 404, description = "Secret not found")
+    )
+)]
+async fn get_secret(
     State(state): State<ControlPlaneState>,
     Path((namespace, name)): Path<(String, String)>,
-    Query(params): Query<LogsQuery>,
 ) -> impl IntoResponse {
-    // Get the pipeline
-    let pipeline = match state.controller.get_pipeline(&namespace, &name) {
-        Some(p) => p,
-        None => {
-            return (
-                StatusCode::NOT_FOUND,
-                Body::from(format!("Pipeline '{}/{}' not found", namespace, name)),
-            )
-                .into_response();
-        }
-    };
+    match state.controller.get_secret(&namespace, &name) {
+        Some(secret) => (StatusCode::OK, Json(Some(secret))).into_response(),
+        None => (StatusCode::NOT_FOUND, Json::<Option<Secret>>(None)).into_response(),
+    }
+}
 
-    // Find the container name from the composition
-    let container_name = if let Some(name) = params.container {
-        name
-    } else {
-        // Find first model with docker config and a name
-        let mut found: Option<String> = None;
-        for model_def in pipeline.spec.composition.models.values() {
-            let config = model_def.to_config();
-            if let Some(docker) = &config.docker {
-                if let Some(name) = &docker.name {
-                    found = Some(name.clone());
-                    break;
-                }
-            }
-        }
-        match found {
-            Some(n) => n,
-            None => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Body::from(
-                        "No docker container name found in composition. Specify ?container=NAME",
-                    ),
-                )
-                    .into_response();
-            }
-        }
+/// Delete a secret; runners already started with it keep their values
+#[utoipa::path(
+    delete,
+    path = "/v1/namespaces/{namespace}/secrets/{name}",
+    tag = "secrets",
+    params(
+        ("namespace" = String, Path, description = "Secret namespace"),
+        ("name" = String, Path, description = "Secret name")
+    ),
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn delete_secret(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.controller.delete_secret(&namespace, &name) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Secret deleted")),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+/// A secret's decrypted values by key, read by workers when they start a
+/// pipeline's runners
+///
+/// Workers present the secret grant of the pipeline's assignment in the
+/// `x-llmnet-secret-grant` header; only pipelines that reference the secret
+/// get one that reads it.
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/secrets/{name}/values",
+    tag = "secrets",
+    params(
+        ("namespace" = String, Path, description = "Secret namespace"),
+        ("name" = String, Path, description = "Secret name"),
+        ("x-llmnet-secret-grant" = String, Header, description = "Secret grant of a pipeline assignment")
+    ),
+    responses(
+        (status = 200, description = "Values by key", body = BTreeMap<String, String>),
+        (status = 401, description = "No secret grant", body = OperationStatus),
+        (status = 403, description = "The grant doesn't cover this secret", body = OperationStatus),
+        (status = 404, body = OperationStatus),
+        (status = 500, body = OperationStatus)
+    )
+)]
+async fn get_secret_values(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let Some(grant) = headers
+        .get(SECRET_GRANT_HEADER)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(OperationStatus::failure("Missing secret grant")),
+        )
+            .into_response();
     };
 
-    // Find the worker node that has this pipeline
-    // First try pipeline tracking, then fallback to checking which worker has the container
-    let nodes = state.controller.list_nodes();
+    match state
+        .controller
+        .granted_secret_values(grant, &namespace, &name)
+    {
+        Ok(values) => Json(values).into_response(),
+        Err(e @ ControllerError::SecretAccessDenied(..)) => (
+            StatusCode::FORBIDDEN,
+            Json(OperationStatus::failure(e.to_string())),
+        )
+            .into_response(),
+        Err(e @ ControllerError::SecretNotFound(..)) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(OperationStatus::failure(e.to_string())),
+        )
+            .into_response(),
+    }
+}
 
-    // Method 1: Check node pipeline tracking
-    let found_node = nodes.iter().find(|n| {
-        n.status
-            .as_ref()
-            .map(|s| {
-                s.pipelines
-                    .iter()
-                    .any(|p| p.namespace == namespace && p.name == name)
-            })
-            .unwrap_or(false)
-    });
+// ============================================================================
+// Node Endpoints
+// ============================================================================
 
-    let worker_url = if let Some(node) = found_node {
-        format!("http://{}:{}", node.spec.address, node.spec.port)
-    } else {
-        // Method 2: Query each node to see if it has the container
-        // This is a fallback for when pipeline tracking isn't working
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(2))
-            .build()
-            .unwrap();
+/// List registered worker nodes
+#[utoipa::path(
+    get,
+    path = "/v1/nodes",
+    tag = "nodes",
+    responses((status = 200, body = ResourceList<Node>))
+)]
+async fn list_nodes(State(state): State<ControlPlaneState>) -> impl IntoResponse {
+    let nodes = state.controller.list_nodes();
+    Json(ResourceList::new("NodeList", nodes))
+}
 
-        let mut found_worker: Option<String> = None;
-        for node in &nodes {
-            let url = format!(
-                "http://{}:{}/v1/containers",
-                node.spec.address, node.spec.port
-            );
-            if let Ok(resp) = client.get(&url).send().await {
-                if let Ok(body) = resp.json::<serde_json::Value>().await {
-                    if let Some(containers) = body["containers"].as_array() {
+/// Register a worker node
+#[utoipa::path(
+    post,
+    path = "/v1/nodes",
+    tag = "nodes",
+    request_body = Node,
+    responses(
+        (status = 201, body = NodeResponse),
+        (status = 400, body = NodeResponse),
+        (status = 409, body = NodeResponse)
+    )
+)]
+async fn register_node(
+    State(state): State<ControlPlaneState>,
+    Json(node): Json<Node>,
+) -> impl IntoResponse {
+    // Capability negotiation: flag version skew so operators can act on it
+    let warnings = node
+        .spec
+        .capabilities
+        .as_ref()
+        .map(|caps| caps.compatibility_warnings(API_VERSION, env!("CARGO_PKG_VERSION")))
+        .unwrap_or_default();
+    for warning in &warnings {
+        warn!("Node '{}': {}", node.metadata.name, warning);
+    }
+
+    match state.controller.register_node(node.clone()) {
+        Ok(_) => (
+            StatusCode::CREATED,
+            Json(NodeResponse::success(Some(node)).with_warnings(warnings)),
+        ),
+        Err(e @ ControllerError::NodeExists(_)) => (
+            StatusCode::CONFLICT,
+            Json(NodeResponse::error(e.to_string())),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(NodeResponse::error(e.to_string())),
+        ),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct NodeResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node: Option<Node>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+    #[serde(rename = "apiVersion")]
+    #[schema(value_type = String)]
+    api_version: &'static str,
+}
+
+impl NodeResponse {
+    fn success(node: Option<Node>) -> Self {
+        Self {
+            success: true,
+            node,
+            error: None,
+            warnings: vec![],
+            api_version: API_VERSION,
+        }
+    }
+
+    fn error(msg: String) -> Self {
+        Self {
+            success: false,
+            node: None,
+            error: Some(msg),
+            warnings: vec![],
+            api_version: API_VERSION,
+        }
+    }
+
+    fn with_warnings(mut self, warnings: Vec<String>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+}
+
+/// Get a worker node
+#[utoipa::path(
+    get,
+    path = "/v1/nodes/{name}",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    responses(
+        (status = 200, body = Node),
+        (status = 404, description = "Node not found")
+    )
+)]
+async fn get_node(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.get_node(&name) {
+        Some(node) => (StatusCode::OK, Json(Some(node))).into_response(),
+        None => (StatusCode::NOT_FOUND, Json::<Option<Node>>(None)).into_response(),
+    }
+}
+
+/// Replace a worker node's labels, annotations and spec
+///
+/// The node's status is kept; it comes from the node's heartbeats.
+#[utoipa::path(
+    put,
+    path = "/v1/nodes/{name}",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    request_body = Node,
+    responses(
+        (status = 200, body = NodeResponse),
+        (status = 400, body = NodeResponse),
+        (status = 404, body = NodeResponse)
+    )
+)]
+async fn update_node(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    Json(node): Json<Node>,
+) -> impl IntoResponse {
+    if node.metadata.name != name {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(NodeResponse::error(format!(
+                "Manifest is for node {}, not {}",
+                node.metadata.name, name
+            ))),
+        );
+    }
+
+    match state.controller.update_node(node) {
+        Ok(node) => (StatusCode::OK, Json(NodeResponse::success(Some(node)))),
+        Err(e @ ControllerError::NodeNotFound(_)) => (
+            StatusCode::NOT_FOUND,
+            Json(NodeResponse::error(e.to_string())),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(NodeResponse::error(e.to_string())),
+        ),
+    }
+}
+
+/// Remove a worker node
+#[utoipa::path(
+    delete,
+    path = "/v1/nodes/{name}",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn unregister_node(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.unregister_node(&name) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Node unregistered")),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+/// Report a node's full status
+#[utoipa::path(
+    post,
+    path = "/v1/nodes/{name}/heartbeat",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    request_body = NodeStatus,
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn node_heartbeat(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    Json(status): Json<NodeStatus>,
+) -> impl IntoResponse {
+    match state.controller.update_node_status(&name, status) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Heartbeat received")),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+/// Report what changed in a node's status since the last heartbeat
+#[utoipa::path(
+    patch,
+    path = "/v1/nodes/{name}/heartbeat",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    request_body = NodeStatusDelta,
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus),
+        (status = 409, description = "No full status to apply the delta to", body = OperationStatus)
+    )
+)]
+async fn node_heartbeat_delta(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    Json(delta): Json<NodeStatusDelta>,
+) -> impl IntoResponse {
+    match state.controller.apply_node_status_delta(&name, delta) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Heartbeat received")),
+        ),
+        // No baseline to merge into: the worker must resend its full status
+        Err(e @ ControllerError::ValidationError(_)) => (
+            StatusCode::CONFLICT,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+/// Open a node's session: a WebSocket carrying the worker's heartbeats
+/// upstream and assignments downstream
+#[utoipa::path(
+    get,
+    path = "/v1/nodes/{name}/session",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol"),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn node_session(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if state.controller.get_node(&name).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(
+                ControllerError::NodeNotFound(name).to_string(),
+            )),
+        )
+            .into_response();
+    }
+    let controller = state.controller.clone();
+    ws.on_upgrade(move |socket| serve_node_session(socket, controller, name))
+}
+
+/// Get the scheduling score of a node
+#[utoipa::path(
+    get,
+    path = "/v1/nodes/{name}/score",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    responses(
+        (status = 200, body = NodeScoreResponse),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn get_node_score(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.get_node(&name) {
+        Some(node) => {
+            let score = node.status.as_ref().and_then(|s| s.score.clone());
+            match score {
+                Some(s) => {
+                    (StatusCode::OK, Json(NodeScoreResponse::with_score(name, s))).into_response()
+                }
+                None => (StatusCode::OK, Json(NodeScoreResponse::no_score(name))).into_response(),
+            }
+        }
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure("Node not found")),
+        )
+            .into_response(),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct NodeScoreResponse {
+    #[serde(rename = "nodeName")]
+    node_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<NodeScore>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl NodeScoreResponse {
+    fn with_score(name: String, score: NodeScore) -> Self {
+        Self {
+            node_name: name,
+            score: Some(score),
+            message: None,
+        }
+    }
+
+    fn no_score(name: String) -> Self {
+        Self {
+            node_name: name,
+            score: None,
+            message: Some("No metrics available yet".to_string()),
+        }
+    }
+}
+
+/// Stop scheduling new pipelines onto a node
+#[utoipa::path(
+    post,
+    path = "/v1/nodes/{name}/cordon",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn cordon_node(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.cordon_node(&name) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Node cordoned")),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+/// Allow scheduling onto a node again
+#[utoipa::path(
+    post,
+    path = "/v1/nodes/{name}/uncordon",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn uncordon_node(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.uncordon_node(&name) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Node uncordoned")),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+/// Replace a node's recurring maintenance windows
+#[utoipa::path(
+    put,
+    path = "/v1/nodes/{name}/maintenance",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    request_body = Vec<MaintenanceWindow>,
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 400, body = OperationStatus),
+        (status = 404, body = OperationStatus)
+    )
+)]
+async fn set_maintenance_windows(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    Json(windows): Json<Vec<MaintenanceWindow>>,
+) -> impl IntoResponse {
+    match state.controller.set_maintenance_windows(&name, windows) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Maintenance windows updated")),
+        ),
+        Err(e @ ControllerError::NodeNotFound(_)) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+/// Set or remove labels of a worker node
+#[utoipa::path(
+    patch,
+    path = "/v1/nodes/{name}/labels",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    request_body(content = Object, description = "Labels to set; null removes the label"),
+    responses(
+        (status = 200, body = NodeResponse),
+        (status = 404, body = NodeResponse)
+    )
+)]
+async fn patch_node_labels(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    Json(patch): Json<MetadataPatch>,
+) -> impl IntoResponse {
+    patch_node_metadata(&state, &name, MetadataField::Labels, &patch)
+}
+
+/// Set or remove annotations of a worker node
+#[utoipa::path(
+    patch,
+    path = "/v1/nodes/{name}/annotations",
+    tag = "nodes",
+    params(("name" = String, Path, description = "Node name")),
+    request_body(content = Object, description = "Annotations to set; null removes the annotation"),
+    responses(
+        (status = 200, body = NodeResponse),
+        (status = 404, body = NodeResponse)
+    )
+)]
+async fn patch_node_annotations(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    Json(patch): Json<MetadataPatch>,
+) -> impl IntoResponse {
+    patch_node_metadata(&state, &name, MetadataField::Annotations, &patch)
+}
+
+fn patch_node_metadata(
+    state: &ControlPlaneState,
+    name: &str,
+    field: MetadataField,
+    patch: &MetadataPatch,
+) -> (StatusCode, Json<NodeResponse>) {
+    match state.controller.patch_node_metadata(name, field, patch) {
+        Ok(node) => (StatusCode::OK, Json(NodeResponse::success(Some(node)))),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(NodeResponse::error(e.to_string())),
+        ),
+    }
+}
+
+// ============================================================================
+// Node Pool Endpoints
+// ============================================================================
+
+/// List node pools with their members
+#[utoipa::path(
+    get,
+    path = "/v1/nodepools",
+    tag = "nodepools",
+    responses((status = 200, body = ResourceList<NodePool>))
+)]
+async fn list_node_pools(State(state): State<ControlPlaneState>) -> impl IntoResponse {
+    let pools = state.controller.list_node_pools();
+    Json(ResourceList::new("NodePoolList", pools))
+}
+
+/// Get a node pool with its members and the pipeline replicas they host
+#[utoipa::path(
+    get,
+    path = "/v1/nodepools/{name}",
+    tag = "nodepools",
+    params(("name" = String, Path, description = "Node pool name")),
+    responses(
+        (status = 200, body = NodePool),
+        (status = 404, description = "Node pool not found")
+    )
+)]
+async fn get_node_pool(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.get_node_pool(&name) {
+        Some(pool) => (StatusCode::OK, Json(Some(pool))).into_response(),
+        None => (StatusCode::NOT_FOUND, Json::<Option<NodePool>>(None)).into_response(),
+    }
+}
+
+/// Create or replace a node pool
+///
+/// Pipelines already scheduled on the pool keep their replicas; the new
+/// settings apply from their next scheduling.
+#[utoipa::path(
+    put,
+    path = "/v1/nodepools/{name}",
+    tag = "nodepools",
+    params(("name" = String, Path, description = "Node pool name")),
+    request_body = NodePool,
+    responses(
+        (status = 200, body = NodePoolResponse),
+        (status = 400, body = NodePoolResponse)
+    )
+)]
+async fn apply_node_pool(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    Json(pool): Json<NodePool>,
+) -> impl IntoResponse {
+    if pool.metadata.name != name {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(NodePoolResponse::error(format!(
+                "Manifest is for node pool {}, not {}",
+                pool.metadata.name, name
+            ))),
+        );
+    }
+
+    match state.controller.apply_node_pool(pool) {
+        Ok(applied) => (StatusCode::OK, Json(NodePoolResponse::success(applied))),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(NodePoolResponse::error(e.to_string())),
+        ),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct NodePoolResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pool: Option<NodePool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl NodePoolResponse {
+    fn success(pool: NodePool) -> Self {
+        Self {
+            success: true,
+            pool: Some(pool),
+            error: None,
+        }
+    }
+
+    fn error(msg: String) -> Self {
+        Self {
+            success: false,
+            pool: None,
+            error: Some(msg),
+        }
+    }
+}
+
+/// Delete a node pool
+///
+/// Refused while pipelines target the pool; its nodes are left as they are.
+#[utoipa::path(
+    delete,
+    path = "/v1/nodepools/{name}",
+    tag = "nodepools",
+    params(("name" = String, Path, description = "Node pool name")),
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus),
+        (status = 409, description = "Pipelines target the pool", body = OperationStatus)
+    )
+)]
+async fn delete_node_pool(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.delete_node_pool(&name) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Node pool deleted")),
+        ),
+        Err(e @ ControllerError::NodePoolInUse(..)) => (
+            StatusCode::CONFLICT,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+// ============================================================================
+// Region Endpoints
+// ============================================================================
+
+/// List regions with the outcome of their last sync
+#[utoipa::path(
+    get,
+    path = "/v1/regions",
+    tag = "regions",
+    responses((status = 200, body = ResourceList<Region>))
+)]
+async fn list_regions(State(state): State<ControlPlaneState>) -> impl IntoResponse {
+    let regions = state.controller.list_regions();
+    Json(ResourceList::new("RegionList", regions))
+}
+
+/// Get a region
+#[utoipa::path(
+    get,
+    path = "/v1/regions/{name}",
+    tag = "regions",
+    params(("name" = String, Path, description = "Region name")),
+    responses(
+        (status = 200, body = Region),
+        (status = 404, description = "Region not found")
+    )
+)]
+async fn get_region(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.get_region(&name) {
+        Some(region) => (StatusCode::OK, Json(Some(region))).into_response(),
+        None => (StatusCode::NOT_FOUND, Json::<Option<Region>>(None)).into_response(),
+    }
+}
+
+/// Register or replace a region
+///
+/// Pipelines annotated with the region are forwarded to it from the next
+/// federation pass.
+#[utoipa::path(
+    put,
+    path = "/v1/regions/{name}",
+    tag = "regions",
+    params(("name" = String, Path, description = "Region name")),
+    request_body = Region,
+    responses(
+        (status = 200, body = RegionResponse),
+        (status = 400, body = RegionResponse)
+    )
+)]
+async fn apply_region(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+    Json(region): Json<Region>,
+) -> impl IntoResponse {
+    if region.metadata.name != name {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(RegionResponse::error(format!(
+                "Manifest is for region {}, not {}",
+                region.metadata.name, name
+            ))),
+        );
+    }
+
+    match state.controller.apply_region(region) {
+        Ok(applied) => (StatusCode::OK, Json(RegionResponse::success(applied))),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(RegionResponse::error(e.to_string())),
+        ),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct RegionResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl RegionResponse {
+    fn success(region: Region) -> Self {
+        Self {
+            success: true,
+            region: Some(region),
+            error: None,
+        }
+    }
+
+    fn error(msg: String) -> Self {
+        Self {
+            success: false,
+            region: None,
+            error: Some(msg),
+        }
+    }
+}
+
+/// Unregister a region
+///
+/// Refused while pipelines are delegated to the region; what it runs is
+/// left alone.
+#[utoipa::path(
+    delete,
+    path = "/v1/regions/{name}",
+    tag = "regions",
+    params(("name" = String, Path, description = "Region name")),
+    responses(
+        (status = 200, body = OperationStatus),
+        (status = 404, body = OperationStatus),
+        (status = 409, description = "Pipelines are delegated to the region", body = OperationStatus)
+    )
+)]
+async fn delete_region(
+    State(state): State<ControlPlaneState>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    match state.controller.delete_region(&name) {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(OperationStatus::success("Region deleted")),
+        ),
+        Err(e @ ControllerError::RegionInUse(..)) => (
+            StatusCode::CONFLICT,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+        Err(e) => (
+            StatusCode::NOT_FOUND,
+            Json(OperationStatus::failure(e.to_string())),
+        ),
+    }
+}
+
+// ============================================================================
+// Cluster Configuration Endpoints
+// ============================================================================
+
+/// Get the node scoring weights
+#[utoipa::path(
+    get,
+    path = "/v1/config/scoring",
+    tag = "config",
+    responses((status = 200, body = ScoringWeights))
+)]
+async fn get_scoring_weights(State(state): State<ControlPlaneState>) -> impl IntoResponse {
+    Json(state.controller.scoring_weights())
+}
+
+/// Replace the node scoring weights; every node is rescored right away
+#[utoipa::path(
+    put,
+    path = "/v1/config/scoring",
+    tag = "config",
+    request_body = ScoringWeights,
+    responses(
+        (status = 200, body = ScoringWeights),
+        (status = 400, body = OperationStatus)
+    )
+)]
+async fn update_scoring_weights(
+    State(state): State<ControlPlaneState>,
+    Json(weights): Json<ScoringWeights>,
+) -> impl IntoResponse {
+    match state.controller.set_scoring_weights(weights) {
+        Ok(()) => (StatusCode::OK, Json(state.controller.scoring_weights())).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(OperationStatus::failure(e.to_string())),
+        )
+            .into_response(),
+    }
+}
+
+/// Get the alert rules and sinks
+#[utoipa::path(
+    get,
+    path = "/v1/config/alerting",
+    tag = "config",
+    responses((status = 200, body = AlertingConfig))
+)]
+async fn get_alerting_config(State(state): State<ControlPlaneState>) -> impl IntoResponse {
+    Json(state.controller.alerting_config())
+}
+
+/// Replace the alert rules and sinks
+#[utoipa::path(
+    put,
+    path = "/v1/config/alerting",
+    tag = "config",
+    request_body = AlertingConfig,
+    responses(
+        (status = 200, body = AlertingConfig),
+        (status = 400, body = OperationStatus)
+    )
+)]
+async fn update_alerting_config(
+    State(state): State<ControlPlaneState>,
+    Json(alerting): Json<AlertingConfig>,
+) -> impl IntoResponse {
+    match state.controller.set_alerting_config(alerting) {
+        Ok(()) => (StatusCode::OK, Json(state.controller.alerting_config())).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(OperationStatus::failure(e.to_string())),
+        )
+            .into_response(),
+    }
+}
+
+/// Alerts pending or firing, oldest first
+#[utoipa::path(
+    get,
+    path = "/v1/alerts",
+    tag = "alerts",
+    responses((status = 200, body = ResourceList<Alert>))
+)]
+async fn list_alerts(State(state): State<ControlPlaneState>) -> impl IntoResponse {
+    Json(ResourceList::new(
+        "AlertList",
+        state.controller.list_alerts(),
+    ))
+}
+
+// ============================================================================
+// Namespace Endpoints
+// ============================================================================
+
+/// List namespaces
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces",
+    tag = "namespaces",
+    responses((status = 200, body = ResourceList<Namespace>))
+)]
+async fn list_namespaces(
+    State(state): State<ControlPlaneState>,
+    Extension(caller): Extension<Caller>,
+) -> impl IntoResponse {
+    let mut namespaces = state.controller.list_namespaces();
+    namespaces.retain(|n| caller.allows(&n.metadata.name));
+    Json(ResourceList::new("NamespaceList", namespaces))
+}
+
+// ============================================================================
+// Pipeline Logs
+// ============================================================================
+
+/// Query parameters for logs endpoint
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LogsQuery {
+    /// Follow log output (like tail -f)
+    #[serde(default)]
+    pub follow: bool,
+    /// Number of lines to show from the end
+    #[serde(default = "default_tail")]
+    pub tail: usize,
+    /// Container name (optional, defaults to first docker container in composition)
+    pub container: Option<String>,
+}
+
+fn default_tail() -> usize {
+    100
+}
+
+/// Stream logs for a pipeline's containers
+#[utoipa::path(
+    get,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}/logs",
+    tag = "pipelines",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name"),
+        LogsQuery
+    ),
+    responses(
+        (status = 200, description = "Log lines, streamed while following", content_type = "text/plain", body = String),
+        (status = 404, description = "Pipeline not found"),
+        (status = 503, description = "Pipeline not scheduled to any worker")
+    )
+)]
+async fn stream_pipeline_logs(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(params): Query<LogsQuery>,
+) -> impl IntoResponse {
+    // Get the pipeline
+    let pipeline = match state.controller.get_pipeline(&namespace, &name) {
+        Some(p) => p,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                Body::from(format!("Pipeline '{}/{}' not found", namespace, name)),
+            )
+                .into_response();
+        }
+    };
+
+    // Find the container name from the composition
+    let container_name = if let Some(name) = params.container {
+        name
+    } else {
+        // Find first model with docker config and a name
+        let mut found: Option<String> = None;
+        for model_def in pipeline.spec.composition.models.values() {
+            let config = model_def.to_config();
+            if let Some(docker) = &config.docker {
+                if let Some(name) = &docker.name {
+                    found = Some(name.clone());
+                    break;
+                }
+            }
+        }
+        match found {
+            Some(n) => n,
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Body::from(
+                        "No docker container name found in composition. Specify ?container=NAME",
+                    ),
+                )
+                    .into_response();
+            }
+        }
+    };
+
+    // Find the worker node that has this pipeline
+    // First try pipeline tracking, then fallback to checking which worker has the container
+    let nodes = state.controller.list_nodes();
+
+    // Method 1: Check node pipeline tracking
+    let found_node = nodes.iter().find(|n| {
+        n.status
+            .as_ref()
+            .map(|s| {
+                s.pipelines
+                    .iter()
+                    .any(|p| p.namespace == namespace && p.name == name)
+            })
+            .unwrap_or(false)
+    });
+
+    let worker_url = if let Some(node) = found_node {
+        format!("http://{}:{}", node.spec.address, node.spec.port)
+    } else {
+        // Method 2: Query each node to see if it has the container
+        // This is a fallback for when pipeline tracking isn't working
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(2))
+            .build()
+            .unwrap();
+
+        let mut found_worker: Option<String> = None;
+        for node in &nodes {
+            let url = format!(
+                "http://{}:{}/v1/containers",
+                node.spec.address, node.spec.port
+            );
+            if let Ok(resp) = client.get(&url).send().await {
+                if let Ok(body) = resp.json::<serde_json::Value>().await {
+                    if let Some(containers) = body["containers"].as_array() {
                         if containers
                             .iter()
                             .any(|c| c.as_str() == Some(&container_name))
@@ -621,24 +2877,147 @@ async fn stream_pipeline_logs(
                     .into_response();
             }
 
-            // Stream the response body
+            // Stream the response body
+            let stream = response
+                .bytes_stream()
+                .map(|result| result.map_err(std::io::Error::other));
+            let body = Body::from_stream(stream);
+            (StatusCode::OK, body).into_response()
+        }
+        Err(e) => {
+            warn!("Failed to proxy logs request to {}: {}", worker_url, e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Body::from(format!("Failed to connect to worker: {}", e)),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Forward a chat completion to one of the pipeline's healthy replicas
+///
+/// Replicas are picked per the pipeline's `loadBalancing`. While a canary
+/// rollout is in progress, `weight` percent of requests go to the canary
+/// replicas. Each outcome counts toward the rollout's error rate; transport
+/// errors and 5xx responses are failures.
+#[utoipa::path(
+    post,
+    path = "/v1/namespaces/{namespace}/pipelines/{name}/chat/completions",
+    tag = "inference",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name")
+    ),
+    request_body(content = Object, description = "OpenAI chat completion request"),
+    responses(
+        (status = 200, description = "Chat completion from a replica", body = Object),
+        (status = 404, description = "Pipeline not found"),
+        (status = 502, description = "Replica unreachable"),
+        (status = 503, description = "Pipeline has no healthy replicas")
+    )
+)]
+async fn proxy_chat_completions(
+    State(state): State<ControlPlaneState>,
+    Path((namespace, name)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    forward_to_pipeline(&state, &namespace, &name, &headers, body).await
+}
+
+/// Send a chat completion to a replica of a pipeline, streaming back its
+/// response. The handoff depth of requests from other pipelines goes along.
+async fn forward_to_pipeline(
+    state: &ControlPlaneState,
+    namespace: &str,
+    name: &str,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(pipeline) = state.controller.get_pipeline(namespace, name) else {
+        return proxy_error(
+            StatusCode::NOT_FOUND,
+            format!("Pipeline '{}' not found in namespace '{}'", name, namespace),
+        );
+    };
+
+    let status = pipeline.status.unwrap_or_else(PipelineStatus::initial);
+    let n = state.proxied.fetch_add(1, Ordering::Relaxed);
+    let canary_endpoints = status
+        .rollout
+        .as_ref()
+        .filter(|r| r.is_progressing() && routes_to_canary(n, r.weight))
+        .map(|r| r.canary_endpoints.clone())
+        .filter(|endpoints| !endpoints.is_empty());
+    let canary = canary_endpoints.is_some();
+    let endpoints = canary_endpoints.unwrap_or(status.endpoints);
+
+    if endpoints.is_empty() {
+        return proxy_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Pipeline {}/{} has no endpoints yet", namespace, name),
+        );
+    }
+    let targets = replica_targets(namespace, name, &endpoints, &state.controller.list_nodes());
+    let Some(target) = pick_replica(&targets, pipeline.spec.load_balancing, n) else {
+        return proxy_error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Pipeline {}/{} has no healthy replicas", namespace, name),
+        );
+    };
+    let endpoint = &target.endpoint;
+    let url = format!("{}/v1/chat/completions", endpoint.trim_end_matches('/'));
+
+    let mut forwarded = state
+        .http
+        .post(&url)
+        .header(CONTENT_TYPE, "application/json");
+    if let Some(depth) = headers.get(HANDOFF_DEPTH_HEADER) {
+        forwarded = forwarded.header(HANDOFF_DEPTH_HEADER, depth.clone());
+    }
+    let result = forwarded.body(body).send().await;
+
+    match result {
+        Ok(response) => {
+            let status = response.status();
+            state.controller.record_rollout_traffic(
+                namespace,
+                name,
+                canary,
+                !status.is_server_error(),
+            );
+            let content_type = response.headers().get(CONTENT_TYPE).cloned();
             let stream = response
                 .bytes_stream()
                 .map(|result| result.map_err(std::io::Error::other));
-            let body = Body::from_stream(stream);
-            (StatusCode::OK, body).into_response()
+            let mut proxied = (
+                StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY),
+                Body::from_stream(stream),
+            )
+                .into_response();
+            if let Some(content_type) = content_type {
+                proxied.headers_mut().insert(CONTENT_TYPE, content_type);
+            }
+            proxied
         }
         Err(e) => {
-            warn!("Failed to proxy logs request to {}: {}", worker_url, e);
-            (
+            warn!("Failed to proxy chat completion to {}: {}", url, e);
+            state
+                .controller
+                .record_rollout_traffic(namespace, name, canary, false);
+            proxy_error(
                 StatusCode::BAD_GATEWAY,
-                Body::from(format!("Failed to connect to worker: {}", e)),
+                format!("Failed to reach replica {}: {}", endpoint, e),
             )
-                .into_response()
         }
     }
 }
 
+fn proxy_error(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -654,103 +3033,865 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_health_check() {
+    async fn test_health_check() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cluster_status() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/status")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_list_pipelines_empty() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/pipelines")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_list_nodes_empty() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/nodes")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_list_namespaces() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/namespaces")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_deploy_pipeline() {
+        let app = create_test_app();
+
+        let pipeline_json = r#"{
+            "apiVersion": "llmnet/v1",
+            "kind": "Pipeline",
+            "metadata": {
+                "name": "test-pipeline",
+                "namespace": "default"
+            },
+            "spec": {
+                "replicas": 1,
+                "composition": {
+                    "models": {},
+                    "architecture": [
+                        {"name": "router", "layer": 0, "adapter": "openai-api"},
+                        {"name": "output", "adapter": "output"}
+                    ]
+                }
+            }
+        }"#;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/pipelines")
+                    .header("content-type", "application/json")
+                    .body(Body::from(pipeline_json))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_job_endpoints() {
+        let state = ControlPlaneState::new();
+        let composition = crate::config::Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        state
+            .controller
+            .deploy_pipeline(Pipeline::new("chat", composition))
+            .unwrap();
+        let app = create_control_plane_router(state);
+
+        let send = |method: &str, uri: &str, body: &str| {
+            let body = if body.is_empty() {
+                Body::empty()
+            } else {
+                Body::from(body.to_string())
+            };
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(body)
+                    .unwrap(),
+            )
+        };
+        let job_json = r#"{
+            "apiVersion": "llmnet/v1",
+            "kind": "Job",
+            "metadata": {"name": "batch"},
+            "spec": {"pipeline": "chat", "prompts": ["hello", "bye"]}
+        }"#;
+
+        let response = send("POST", "/v1/namespaces/default/jobs", job_json)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = send("POST", "/v1/namespaces/default/jobs", job_json)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = send("POST", "/v1/namespaces/prod/jobs", job_json)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send("GET", "/v1/jobs", "").await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["kind"], "JobList");
+        assert_eq!(json["items"][0]["status"]["phase"], "Pending");
+
+        let response = send("GET", "/v1/namespaces/default/jobs/batch/results", "")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send("DELETE", "/v1/namespaces/default/jobs/batch", "")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("GET", "/v1/namespaces/default/jobs/batch/results", "")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_secret_endpoints() {
+        let state = ControlPlaneState::new();
+        let controller = state.controller.clone();
+        let app = create_control_plane_router(state);
+        let send = |method: &str, uri: &str, body: &str| {
+            let body = if body.is_empty() {
+                Body::empty()
+            } else {
+                Body::from(body.to_string())
+            };
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(body)
+                    .unwrap(),
+            )
+        };
+        let read_json = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let secret_json = r#"{
+            "apiVersion": "llmnet/v1",
+            "kind": "Secret",
+            "metadata": {"name": "openai", "namespace": "prod"},
+            "stringData": {"key": "sk-123"}
+        }"#;
+
+        let response = send("POST", "/v1/namespaces/prod/secrets", secret_json)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let created = read_json(response).await;
+        assert!(!created.to_string().contains("sk-123"));
+        let response = send("POST", "/v1/namespaces/prod/secrets", secret_json)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let response = send("POST", "/v1/namespaces/default/secrets", secret_json)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let list = read_json(send("GET", "/v1/secrets", "").await.unwrap()).await;
+        assert_eq!(list["kind"], "SecretList");
+        assert!(list["items"][0]["data"]["key"].is_string());
+        assert!(!list.to_string().contains("sk-123"));
+
+        // Values are only read with the grant of a pipeline that uses them
+        let read_values = |grant: Option<String>| {
+            let mut request = Request::builder().uri("/v1/namespaces/prod/secrets/openai/values");
+            if let Some(grant) = grant {
+                request = request.header(SECRET_GRANT_HEADER, grant);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+        let response = read_values(None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let grant = controller.secret_grant("prod", "bot");
+        let response = read_values(Some(grant.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let composition = crate::config::Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::new("bot", composition)
+            .with_namespace("prod")
+            .with_secret_ref(crate::cluster::SecretEnvRef {
+                name: "OPENAI_API_KEY".to_string(),
+                secret: "openai".to_string(),
+                key: Some("key".to_string()),
+            });
+        controller.deploy_pipeline(pipeline).unwrap();
+        let values = read_json(read_values(Some(grant.clone())).await.unwrap()).await;
+        assert_eq!(values, serde_json::json!({"key": "sk-123"}));
+
+        let response = send("DELETE", "/v1/namespaces/prod/secrets/openai", "")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = read_values(Some(grant)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_node_pool_endpoints() {
+        let app = create_test_app();
+        let send = |method: &str, uri: &str, body: &str| {
+            let body = if body.is_empty() {
+                Body::empty()
+            } else {
+                Body::from(body.to_string())
+            };
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(body)
+                    .unwrap(),
+            )
+        };
+        let read_json = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let pool_json = r#"{
+            "apiVersion": "llmnet/v1",
+            "kind": "NodePool",
+            "metadata": {"name": "gpu-pool"},
+            "spec": {"nodeSelector": {"accelerator": "nvidia"}, "maxPipelines": 4}
+        }"#;
+
+        let response = send("PUT", "/v1/nodepools/cpu-pool", pool_json)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = send("PUT", "/v1/nodepools/gpu-pool", pool_json)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let applied = read_json(response).await;
+        assert_eq!(applied["pool"]["status"]["pipelines"], 0);
+
+        let list = read_json(send("GET", "/v1/nodepools", "").await.unwrap()).await;
+        assert_eq!(list["kind"], "NodePoolList");
+        assert_eq!(list["items"][0]["spec"]["maxPipelines"], 4);
+
+        let response = send("DELETE", "/v1/nodepools/gpu-pool", "").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("GET", "/v1/nodepools/gpu-pool", "").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = send("DELETE", "/v1/nodepools/gpu-pool", "").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_region_endpoints() {
+        let state = ControlPlaneState::new();
+        let app = create_control_plane_router(state.clone());
+        let send = |method: &str, uri: &str, body: &str| {
+            let body = if body.is_empty() {
+                Body::empty()
+            } else {
+                Body::from(body.to_string())
+            };
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("content-type", "application/json")
+                    .body(body)
+                    .unwrap(),
+            )
+        };
+        let read_json = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let bad = r#"{"apiVersion": "llmnet/v1", "kind": "Region",
+            "metadata": {"name": "eu-west"}, "spec": {"url": "cp.eu:8181"}}"#;
+        let response = send("PUT", "/v1/regions/eu-west", bad).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let region = r#"{"apiVersion": "llmnet/v1", "kind": "Region",
+            "metadata": {"name": "eu-west"}, "spec": {"url": "http://cp.eu:8181"}}"#;
+        let response = send("PUT", "/v1/regions/eu-west", region).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let list = read_json(send("GET", "/v1/regions", "").await.unwrap()).await;
+        assert_eq!(list["kind"], "RegionList");
+        assert_eq!(list["items"][0]["spec"]["url"], "http://cp.eu:8181");
+
+        // Pipelines the region reported are only listed when federated
+        let composition = crate::config::Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        state
+            .controller
+            .set_region_pipelines("eu-west", vec![Pipeline::new("chat", composition)]);
+        let local = read_json(send("GET", "/v1/pipelines", "").await.unwrap()).await;
+        assert!(local["items"].as_array().unwrap().is_empty());
+        let federated = read_json(
+            send("GET", "/v1/pipelines?federated=true", "")
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(
+            federated["items"][0]["metadata"]["annotations"]["llmnet.io/region"],
+            "eu-west"
+        );
+
+        let response = send("DELETE", "/v1/regions/eu-west", "").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("GET", "/v1/regions/eu-west", "").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_request_log_endpoints() {
+        let app = create_test_app();
+        let log = |source: &str, error: Option<&str>| {
+            serde_json::json!({
+                "request_id": uuid::Uuid::new_v4(),
+                "source": source,
+                "started_at": "2026-01-01T00:00:00Z",
+                "duration_ms": 12,
+                "prompt": "hi",
+                "error": error,
+                "route": ["router"],
+                "total_tokens": 3
+            })
+        };
+        let logs = serde_json::json!([log("w1", None), log("w2", Some("boom")), log("w1", None)]);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/requestlogs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(logs.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let get = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+        let all = get("/v1/requestlogs").await;
+        assert_eq!(all["kind"], "RequestLogList");
+        assert_eq!(all["items"].as_array().unwrap().len(), 3);
+        let w1 = get("/v1/requestlogs?source=w1&limit=1").await;
+        assert_eq!(w1["items"].as_array().unwrap().len(), 1);
+        let failed = get("/v1/requestlogs?failed=true").await;
+        assert_eq!(failed["items"][0]["source"], "w2");
+    }
+
+    #[tokio::test]
+    async fn test_report_events() {
         let app = create_test_app();
+        let events = serde_json::json!([{
+            "timestamp": "2026-01-01T00:00:00Z",
+            "object": "node/gpu-1",
+            "reason": "ContainerCollected",
+            "message": "Removed orphaned runner container llmnet-llama"
+        }]);
 
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
-                    .uri("/health")
+                    .method("POST")
+                    .uri("/v1/events")
+                    .header("content-type", "application/json")
+                    .body(Body::from(events.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/events")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let list: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let items = list["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["reason"], "ContainerCollected");
+        assert_eq!(items[0]["timestamp"], "2026-01-01T00:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn test_delete_protected_pipeline() {
+        let state = ControlPlaneState::new();
+        let composition = crate::config::Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::new("chat", composition.clone());
+        pipeline.metadata.annotations.insert(
+            crate::cluster::DELETION_PROTECTION_ANNOTATION.to_string(),
+            "true".to_string(),
+        );
+        state.controller.deploy_pipeline(pipeline).unwrap();
+        state
+            .controller
+            .deploy_pipeline(Pipeline::new("code", composition))
+            .unwrap();
+        let mut status = PipelineStatus::initial();
+        status.endpoints = vec!["http://10.0.0.1:8080".to_string()];
+        state
+            .controller
+            .update_pipeline_status("default", "code", status)
+            .unwrap();
+        let app = create_control_plane_router(state.clone());
 
+        let delete = |uri: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let response = delete("/v1/namespaces/default/pipelines/chat")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert!(state.controller.get_pipeline("default", "chat").is_some());
+        let response = delete("/v1/namespaces/default/pipelines/chat?force=true")
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.controller.get_pipeline("default", "chat").is_none());
+
+        // Scheduled pipelines wait for the finalizer
+        let response = delete("/v1/namespaces/default/pipelines/code")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(state
+            .controller
+            .get_pipeline("default", "code")
+            .unwrap()
+            .is_terminating());
     }
 
     #[tokio::test]
-    async fn test_cluster_status() {
+    async fn test_get_pipeline_not_found() {
         let app = create_test_app();
 
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/v1/status")
+                    .uri("/v1/namespaces/default/pipelines/nonexistent")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn test_list_pipelines_empty() {
+    async fn test_register_node() {
+        let app = create_test_app();
+
+        let node_json = r#"{
+            "apiVersion": "llmnet/v1",
+            "kind": "Node",
+            "metadata": {
+                "name": "worker-1"
+            },
+            "spec": {
+                "address": "192.168.1.100",
+                "port": 8080
+            }
+        }"#;
+
+        let register = || {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/nodes")
+                .header("content-type", "application/json")
+                .body(Body::from(node_json))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(register()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        // Registering the same name again is a conflict, not a bad request
+        let response = app.oneshot(register()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_register_node_reports_version_skew() {
         let app = create_test_app();
 
+        let node_json = r#"{
+            "apiVersion": "llmnet/v1",
+            "kind": "Node",
+            "metadata": {"name": "old-worker"},
+            "spec": {
+                "address": "192.168.1.101",
+                "capabilities": {
+                    "apiVersion": "llmnet/v0",
+                    "llmnetVersion": "0.0.1",
+                    "runners": ["ollama"],
+                    "adapters": ["openai-api", "output"]
+                }
+            }
+        }"#;
+
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/v1/pipelines")
-                    .body(Body::empty())
+                    .method("POST")
+                    .uri("/v1/nodes")
+                    .header("content-type", "application/json")
+                    .body(Body::from(node_json))
                     .unwrap(),
             )
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["apiVersion"], API_VERSION);
+        assert_eq!(json["warnings"].as_array().unwrap().len(), 2);
+        assert_eq!(json["node"]["spec"]["capabilities"]["runners"][0], "ollama");
     }
 
     #[tokio::test]
-    async fn test_list_nodes_empty() {
-        let app = create_test_app();
+    async fn test_scoring_weights_endpoint() {
+        let state = ControlPlaneState::new();
+        let app = create_control_plane_router(state.clone());
+
+        let put = |body: &'static str| {
+            Request::builder()
+                .method("PUT")
+                .uri("/v1/config/scoring")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(put(r#"{"gpu": 0.5, "latency": 0.3}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.controller.scoring_weights().latency, 0.3);
+
+        let response = app.clone().oneshot(put(r#"{"cpu": -1}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/v1/nodes")
+                    .uri("/v1/config/scoring")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
-
-        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["gpu"], 0.5);
+        assert_eq!(json["cpu"], 0.2);
     }
 
     #[tokio::test]
-    async fn test_list_namespaces() {
-        let app = create_test_app();
+    async fn test_alerting_endpoints() {
+        let state = ControlPlaneState::new();
+        state
+            .controller
+            .register_node(Node::new("node-1", "10.0.0.1"))
+            .unwrap();
+        let app = create_control_plane_router(state.clone());
+
+        let put = |body: serde_json::Value| {
+            Request::builder()
+                .method("PUT")
+                .uri("/v1/config/alerting")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let invalid = serde_json::json!({
+            "rules": [{"name": "errors", "condition": "ErrorRate"}]
+        });
+        let response = app.clone().oneshot(put(invalid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let config = serde_json::json!({
+            "rules": [{"name": "node-down", "condition": "NodeNotReady"}],
+            "sinks": [{"type": "webhook", "url": "http://127.0.0.1:1/alerts"}]
+        });
+        let response = app.clone().oneshot(put(config)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        state.controller.evaluate_alerts(chrono::Utc::now());
 
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/v1/namespaces")
+                    .uri("/v1/alerts")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["items"][0]["object"], "node/node-1");
+        assert_eq!(json["items"][0]["state"], "Firing");
+    }
+
+    #[tokio::test]
+    async fn test_set_maintenance_windows() {
+        let state = ControlPlaneState::new();
+        state
+            .controller
+            .register_node(Node::new("gpu-1", "10.0.0.1"))
+            .unwrap();
+        let app = create_control_plane_router(state.clone());
 
+        let put = |node: &str, body: &'static str| {
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/v1/nodes/{}/maintenance", node))
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(put(
+                "gpu-1",
+                r#"[{"days": ["sat"], "start": "02:00", "durationMinutes": 120}]"#,
+            ))
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let node = state.controller.get_node("gpu-1").unwrap();
+        assert_eq!(node.spec.maintenance_windows.len(), 1);
+
+        let response = app
+            .clone()
+            .oneshot(put(
+                "gpu-1",
+                r#"[{"start": "noon", "durationMinutes": 120}]"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app.oneshot(put("gpu-9", "[]")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
-    async fn test_deploy_pipeline() {
-        let app = create_test_app();
+    async fn test_update_node_keeps_status() {
+        let state = ControlPlaneState::new();
+        let mut node = Node::new("gpu-1", "10.0.0.1");
+        node.status = Some(NodeStatus::new(
+            crate::cluster::NodeCapacity::default(),
+            crate::cluster::node::NodeInfo::from_system(),
+        ));
+        state.controller.register_node(node).unwrap();
+        let app = create_control_plane_router(state.clone());
+
+        let put = |path: &str, node: &Node| {
+            Request::builder()
+                .method("PUT")
+                .uri(path)
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(node).unwrap()))
+                .unwrap()
+        };
+
+        // Edited copy without status, as `llmnet edit` sends it
+        let edited = Node::new("gpu-1", "10.0.0.1").with_label("gpu", "a100");
+        let response = app
+            .clone()
+            .oneshot(put("/v1/nodes/gpu-1", &edited))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let node = state.controller.get_node("gpu-1").unwrap();
+        assert_eq!(node.metadata.labels["gpu"], "a100");
+        assert!(node.status.is_some());
+
+        let response = app
+            .clone()
+            .oneshot(put("/v1/nodes/gpu-2", &edited))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let missing = Node::new("gpu-9", "10.0.0.9");
+        let response = app.oneshot(put("/v1/nodes/gpu-9", &missing)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_deploy_rejects_pipeline_that_does_not_fit() {
+        let state = ControlPlaneState::new();
+        let mut node = Node::new("small", "10.0.0.1");
+        node.status = Some(NodeStatus::new(
+            crate::cluster::NodeCapacity {
+                memory: 16 * 1024 * 1024 * 1024,
+                ..Default::default()
+            },
+            crate::cluster::node::NodeInfo::from_system(),
+        ));
+        state.controller.register_node(node).unwrap();
+        let app = create_control_plane_router(state);
 
         let pipeline_json = r#"{
             "apiVersion": "llmnet/v1",
             "kind": "Pipeline",
-            "metadata": {
-                "name": "test-pipeline",
-                "namespace": "default"
-            },
+            "metadata": {"name": "big", "namespace": "default"},
             "spec": {
                 "replicas": 1,
+                "resources": {"memory": "64Gi"},
                 "composition": {
                     "models": {},
                     "architecture": [
@@ -760,67 +3901,405 @@ mod tests {
                 }
             }
         }"#;
+        let deploy = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(pipeline_json))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(deploy("/v1/pipelines")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .contains("node 'small' can't provide 64.0g of memory"));
 
         let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/v1/pipelines")
-                    .header("content-type", "application/json")
-                    .body(Body::from(pipeline_json))
-                    .unwrap(),
-            )
+            .oneshot(deploy("/v1/pipelines?force=true"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["warnings"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_admission_webhooks_review_pipelines() {
+        // Labels new pipelines and denies updates past 2 replicas
+        let webhook = Router::new().route(
+            "/review",
+            post(|Json(mut review): Json<serde_json::Value>| async move {
+                if review["operation"] == "update" {
+                    let allowed = review["pipeline"]["spec"]["replicas"].as_u64() <= Some(2);
+                    return Json(
+                        serde_json::json!({"allowed": allowed, "message": "too many replicas"}),
+                    );
+                }
+                review["pipeline"]["metadata"]["labels"]["owner"] = "platform".into();
+                Json(serde_json::json!({"allowed": true, "pipeline": review["pipeline"]}))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, webhook).await.unwrap() });
 
+        let state = ControlPlaneState::new().with_admission_webhooks(AdmissionWebhooks::new(vec![
+            format!("http://{}/review", addr),
+        ]));
+        let app = create_control_plane_router(state.clone());
+        let apply = |replicas: u32| {
+            let manifest = serde_json::json!({
+                "apiVersion": "llmnet/v1",
+                "kind": "Pipeline",
+                "metadata": {"name": "chat", "namespace": "default"},
+                "spec": {
+                    "replicas": replicas,
+                    "composition": {
+                        "models": {},
+                        "architecture": [
+                            {"name": "router", "layer": 0, "adapter": "openai-api"},
+                            {"name": "output", "adapter": "output"}
+                        ]
+                    }
+                }
+            });
+            Request::builder()
+                .method("PUT")
+                .uri("/v1/namespaces/default/pipelines/chat")
+                .header("content-type", "application/json")
+                .body(Body::from(manifest.to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(apply(1)).await.unwrap();
         assert_eq!(response.status(), StatusCode::CREATED);
+        let stored = state.controller.get_pipeline("default", "chat").unwrap();
+        assert_eq!(stored.metadata.labels["owner"], "platform");
+
+        let response = app.clone().oneshot(apply(2)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.oneshot(apply(5)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .ends_with("denied the pipeline: too many replicas"));
+        let stored = state.controller.get_pipeline("default", "chat").unwrap();
+        assert_eq!(stored.spec.replicas, 2);
     }
 
     #[tokio::test]
-    async fn test_get_pipeline_not_found() {
-        let app = create_test_app();
+    async fn test_openapi_document_covers_routes() {
+        let app = create_control_plane_router(ControlPlaneState::new());
 
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/v1/namespaces/default/pipelines/nonexistent")
+                    .uri("/openapi.json")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+        let mut paths: Vec<&str> = doc["paths"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        paths.sort_unstable();
+        assert_eq!(
+            paths,
+            [
+                "/health",
+                "/v1/alerts",
+                "/v1/audit",
+                "/v1/budgets",
+                "/v1/config/alerting",
+                "/v1/config/scoring",
+                "/v1/endpoints",
+                "/v1/events",
+                "/v1/jobs",
+                "/v1/namespaces",
+                "/v1/namespaces/{namespace}/endpoints",
+                "/v1/namespaces/{namespace}/endpoints/{name}",
+                "/v1/namespaces/{namespace}/endpoints/{name}/chat/completions",
+                "/v1/namespaces/{namespace}/jobs",
+                "/v1/namespaces/{namespace}/jobs/{name}",
+                "/v1/namespaces/{namespace}/jobs/{name}/results",
+                "/v1/namespaces/{namespace}/pipelines",
+                "/v1/namespaces/{namespace}/pipelines/{name}",
+                "/v1/namespaces/{namespace}/pipelines/{name}/annotations",
+                "/v1/namespaces/{namespace}/pipelines/{name}/autoscaling",
+                "/v1/namespaces/{namespace}/pipelines/{name}/chat/completions",
+                "/v1/namespaces/{namespace}/pipelines/{name}/labels",
+                "/v1/namespaces/{namespace}/pipelines/{name}/logs",
+                "/v1/namespaces/{namespace}/pipelines/{name}/scale",
+                "/v1/namespaces/{namespace}/secrets",
+                "/v1/namespaces/{namespace}/secrets/{name}",
+                "/v1/namespaces/{namespace}/secrets/{name}/values",
+                "/v1/nodepools",
+                "/v1/nodepools/{name}",
+                "/v1/nodes",
+                "/v1/nodes/{name}",
+                "/v1/nodes/{name}/annotations",
+                "/v1/nodes/{name}/cordon",
+                "/v1/nodes/{name}/heartbeat",
+                "/v1/nodes/{name}/labels",
+                "/v1/nodes/{name}/maintenance",
+                "/v1/nodes/{name}/score",
+                "/v1/nodes/{name}/session",
+                "/v1/nodes/{name}/uncordon",
+                "/v1/pipelines",
+                "/v1/regions",
+                "/v1/regions/{name}",
+                "/v1/requestlogs",
+                "/v1/secrets",
+                "/v1/status",
+            ]
+        );
+
+        let pipeline = &doc["paths"]["/v1/namespaces/{namespace}/pipelines/{name}"];
+        for method in ["get", "put", "delete"] {
+            assert!(pipeline[method].is_object(), "missing {}", method);
+        }
+        let heartbeat = &doc["paths"]["/v1/nodes/{name}/heartbeat"];
+        assert!(heartbeat["post"].is_object() && heartbeat["patch"].is_object());
+
+        // Manifests are described by the resource types themselves
+        let schemas = &doc["components"]["schemas"];
+        for schema in [
+            "Pipeline",
+            "PipelineSpec",
+            "Node",
+            "NodeStatus",
+            "ScoringWeights",
+        ] {
+            assert!(schemas[schema].is_object(), "missing schema {}", schema);
+        }
+        assert!(schemas["PipelineSpec"]["properties"]["composition"].is_object());
     }
 
     #[tokio::test]
-    async fn test_register_node() {
-        let app = create_test_app();
+    async fn test_mutations_are_audited() {
+        let keys = serde_yaml::from_str("keys: [{name: admin, key: sk-admin, namespaces: ['*']}]");
+        let state = ControlPlaneState::new().with_api_keys(ApiKeys::new(keys.unwrap()).unwrap());
+        let app = create_control_plane_router(state.clone());
 
-        let node_json = r#"{
+        let pipeline_json = r#"{
             "apiVersion": "llmnet/v1",
-            "kind": "Node",
-            "metadata": {
-                "name": "worker-1"
-            },
+            "kind": "Pipeline",
+            "metadata": {"name": "bot", "namespace": "prod"},
             "spec": {
-                "address": "192.168.1.100",
-                "port": 8080
+                "replicas": 1,
+                "composition": {
+                    "models": {},
+                    "architecture": [
+                        {"name": "router", "layer": 0, "adapter": "openai-api"},
+                        {"name": "output", "adapter": "output"}
+                    ]
+                }
             }
         }"#;
 
+        let requests = [
+            ("POST", "/v1/pipelines", Body::from(pipeline_json)),
+            ("GET", "/v1/pipelines", Body::empty()),
+            (
+                "PATCH",
+                "/v1/namespaces/prod/pipelines/bot/scale",
+                Body::from(r#"{"replicas": 3}"#),
+            ),
+            (
+                "DELETE",
+                "/v1/namespaces/prod/pipelines/missing",
+                Body::empty(),
+            ),
+        ];
+        for (method, uri, body) in requests {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(uri)
+                        .header("content-type", "application/json")
+                        .header("authorization", "Bearer sk-admin")
+                        .body(body)
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_ne!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        }
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/audit?resource=pipeline/prod/bot")
+                    .header("authorization", "Bearer sk-admin")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let items = json["items"].as_array().unwrap();
+        assert_eq!(json["kind"], "AuditEntryList");
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["method"], "POST");
+        assert_eq!(items[0]["status"], 201);
+        assert_eq!(items[1]["method"], "PATCH");
+        assert_eq!(items[0]["actor"], "admin");
+        assert!(!body.windows(8).any(|w| w == b"sk-admin"));
+
+        // Failed mutations are recorded too; reads never are
+        let all = state.audit.query(&AuditQuery::default()).await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[2].status, 404);
+
+        // A token that doesn't authenticate is recorded by its fingerprint
+        let rejected = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/v1/namespaces/prod/pipelines/bot")
+                    .header("authorization", "Bearer sk-guess")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), StatusCode::UNAUTHORIZED);
+        let all = state.audit.query(&AuditQuery::default()).await.unwrap();
+        assert_eq!(all.len(), 4);
+        assert!(all[3].actor.starts_with("key:"));
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_scope_namespaces() {
+        let keys: crate::cluster::auth::ApiKeyConfig = serde_yaml::from_str(
+            r#"
+keys:
+  - {name: admin, key: sk-admin, namespaces: ["*"]}
+  - {name: team-a, key: sk-team-a, namespaces: [team-a]}
+"#,
+        )
+        .unwrap();
+        let state = ControlPlaneState::new().with_api_keys(ApiKeys::new(keys).unwrap());
+        let composition = crate::config::Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        for namespace in ["team-a", "team-b"] {
+            let mut pipeline = Pipeline::new("bot", composition.clone());
+            pipeline.metadata.namespace = namespace.to_string();
+            state.controller.deploy_pipeline(pipeline).unwrap();
+        }
+        let app = create_control_plane_router(state);
+
+        let send = |method: &str, uri: &str, key: Option<&str>| {
+            let mut request = Request::builder().method(method).uri(uri);
+            if let Some(key) = key {
+                request = request.header("authorization", format!("Bearer {}", key));
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        let response = send("GET", "/health", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send("GET", "/v1/pipelines", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send("GET", "/v1/pipelines", Some("sk-other"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Lists only show the key's namespaces
+        let response = send("GET", "/v1/pipelines", Some("sk-team-a"))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let items = json["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["metadata"]["namespace"], "team-a");
+
+        let response = send(
+            "GET",
+            "/v1/namespaces/team-a/pipelines/bot",
+            Some("sk-team-a"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(
+            "GET",
+            "/v1/namespaces/team-b/pipelines/bot",
+            Some("sk-team-a"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send("GET", "/v1/nodes", Some("sk-team-a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send("GET", "/v1/nodes", Some("sk-admin")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deploying names the namespace in the manifest
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
                     .method("POST")
-                    .uri("/v1/nodes")
+                    .uri("/v1/pipelines")
                     .header("content-type", "application/json")
-                    .body(Body::from(node_json))
+                    .header("authorization", "Bearer sk-team-a")
+                    .body(Body::from(
+                        r#"{
+                            "apiVersion": "llmnet/v1",
+                            "kind": "Pipeline",
+                            "metadata": {"name": "other", "namespace": "team-b"},
+                            "spec": {"replicas": 1, "composition": {"models": {}, "architecture": []}}
+                        }"#,
+                    ))
                     .unwrap(),
             )
             .await
             .unwrap();
-
-        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
     }
 }
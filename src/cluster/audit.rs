@@ -0,0 +1,622 @@
+//! Audit log of mutating control plane operations
+//!
+//! Every POST, PUT, PATCH and DELETE on the control plane REST API, and
+//! every call that changes pipelines over the gRPC API, is recorded with who
+//! made it (the name of the caller's API key), what it touched (method, path
+//! and resource) and when. Entries go to an append-only sink and are served
+//! back by `GET /v1/audit`.
+//!
+//! Node heartbeats and shipped request logs are not audited: workers send
+//! them every few seconds, and they report status rather than change the
+//! cluster.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::warn;
+use utoipa::{IntoParams, ToSchema};
+
+use super::auth::Caller;
+
+/// Default number of days audit entries are kept
+pub const DEFAULT_AUDIT_RETENTION_DAYS: u64 = 90;
+
+/// Actor recorded for requests without an API key
+pub const ANONYMOUS_ACTOR: &str = "anonymous";
+
+/// Errors that can occur while writing or reading the audit log
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("Audit log I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid audit entry: {0}")]
+    InvalidEntry(#[from] serde_json::Error),
+}
+
+/// A single recorded operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+
+    /// Name of the API key, a fingerprint of a token that didn't
+    /// authenticate, or "anonymous"
+    pub actor: String,
+
+    pub method: String,
+
+    pub path: String,
+
+    /// Resource the operation targeted (e.g. "pipeline/default/chatbot")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+
+    /// HTTP status the control plane answered with
+    pub status: u16,
+}
+
+/// Filters for reading the audit log
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+
+    /// Match resources starting with this prefix (e.g. "pipeline/prod")
+    pub resource: Option<String>,
+
+    /// Only entries at or after this time
+    pub since: Option<DateTime<Utc>>,
+
+    /// Return at most this many of the most recent entries
+    pub limit: Option<usize>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Whether a request should be recorded
+///
+/// Heartbeats, shipped request logs, reported events and budget spend, and
+/// proxied inference requests are POSTs too, but don't change cluster state.
+pub fn is_audited(method: &Method, path: &str) -> bool {
+    let mutating = matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    mutating
+        && !path.ends_with("/heartbeat")
+        && !path.ends_with("/chat/completions")
+        && path != "/v1/requestlogs"
+        && path != "/v1/events"
+        && path != "/v1/budgets"
+}
+
+/// Identify the caller by a fingerprint of their bearer token
+///
+/// The key itself is never written to the log; the fingerprint is stable,
+/// so entries from the same key can still be correlated.
+pub fn actor_from_authorization(header: Option<&str>) -> String {
+    let token = header
+        .map(|h| h.strip_prefix("Bearer ").unwrap_or(h).trim())
+        .filter(|t| !t.is_empty());
+
+    match token {
+        Some(token) => {
+            let hash = Sha256::digest(token.as_bytes());
+            format!("key:{}", &format!("{:x}", hash)[..12])
+        }
+        None => ANONYMOUS_ACTOR.to_string(),
+    }
+}
+
+/// Identify the caller by the name of the key it authenticated with,
+/// falling back to a fingerprint of the token it presented
+pub fn audit_actor(caller: Option<&Caller>, authorization: Option<&str>) -> String {
+    match caller {
+        Some(caller) => caller.name.clone(),
+        None => actor_from_authorization(authorization),
+    }
+}
+
+/// Work out which resource a request targeted
+///
+/// The path identifies it for most endpoints. Creation endpoints
+/// (`POST /v1/pipelines`, `POST /v1/nodes`, `POST .../jobs`,
+/// `POST .../secrets`) carry the name
+/// in the manifest's metadata instead.
+pub fn resource_for_request(path: &str, body: Option<&Value>) -> Option<String> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match segments.as_slice() {
+        ["v1", "namespaces", namespace, "pipelines", name, ..] => {
+            Some(format!("pipeline/{}/{}", namespace, name))
+        }
+        ["v1", "namespaces", namespace, "jobs", name, ..] => {
+            Some(format!("job/{}/{}", namespace, name))
+        }
+        ["v1", "namespaces", namespace, "endpoints", name, ..] => {
+            Some(format!("endpoint/{}/{}", namespace, name))
+        }
+        ["v1", "namespaces", namespace, "secrets", name, ..] => {
+            Some(format!("secret/{}/{}", namespace, name))
+        }
+        ["v1", "namespaces", namespace, "secrets"] => body
+            .and_then(|manifest| manifest.get("metadata")?.get("name")?.as_str())
+            .map(|name| format!("secret/{}/{}", namespace, name)),
+        ["v1", "namespaces", namespace, "jobs"] => body
+            .and_then(|manifest| manifest.get("metadata")?.get("name")?.as_str())
+            .map(|name| format!("job/{}/{}", namespace, name)),
+        ["v1", "nodes", name, ..] => Some(format!("node/{}", name)),
+        ["v1", "nodepools", name] => Some(format!("nodepool/{}", name)),
+        ["v1", "regions", name] => Some(format!("region/{}", name)),
+        ["v1", "config", name] => Some(format!("config/{}", name)),
+        ["v1", "pipelines"] => body.and_then(|manifest| {
+            let metadata = manifest.get("metadata")?;
+            let name = metadata.get("name")?.as_str()?;
+            let namespace = metadata
+                .get("namespace")
+                .and_then(|n| n.as_str())
+                .unwrap_or("default");
+            Some(format!("pipeline/{}/{}", namespace, name))
+        }),
+        ["v1", "nodes"] => body
+            .and_then(|manifest| manifest.get("metadata")?.get("name")?.as_str())
+            .map(|name| format!("node/{}", name)),
+        _ => None,
+    }
+}
+
+/// Apply query filters to entries in the order they were recorded
+pub fn filter_entries(entries: Vec<AuditEntry>, query: &AuditQuery) -> Vec<AuditEntry> {
+    let mut matched: Vec<AuditEntry> = entries
+        .into_iter()
+        .filter(|e| query.actor.as_ref().is_none_or(|a| &e.actor == a))
+        .filter(|e| {
+            query.resource.as_ref().is_none_or(|prefix| {
+                e.resource
+                    .as_ref()
+                    .is_some_and(|r| r.starts_with(prefix.as_str()))
+            })
+        })
+        .filter(|e| query.since.is_none_or(|since| e.timestamp >= since))
+        .collect();
+
+    if let Some(limit) = query.limit {
+        let skip = matched.len().saturating_sub(limit);
+        matched.drain(..skip);
+    }
+    matched
+}
+
+// ============================================================================
+// SBIO: Trait for abstraction (allows mocking in tests)
+// ============================================================================
+
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    /// Record an entry after all existing ones
+    async fn append(&self, entry: &AuditEntry) -> Result<(), AuditError>;
+
+    /// All retained entries, oldest first
+    async fn entries(&self) -> Result<Vec<AuditEntry>, AuditError>;
+
+    /// Remove entries older than `cutoff`, returning how many were dropped
+    async fn prune(&self, cutoff: DateTime<Utc>) -> Result<usize, AuditError>;
+}
+
+// ============================================================================
+// SBIO: I/O implementations
+// ============================================================================
+
+/// Entries kept in the control plane's memory (lost on restart)
+#[derive(Default)]
+pub struct MemoryAuditSink {
+    entries: Mutex<Vec<AuditEntry>>,
+}
+
+impl MemoryAuditSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AuditSink for MemoryAuditSink {
+    async fn append(&self, entry: &AuditEntry) -> Result<(), AuditError> {
+        self.entries.lock().await.push(entry.clone());
+        Ok(())
+    }
+
+    async fn entries(&self) -> Result<Vec<AuditEntry>, AuditError> {
+        Ok(self.entries.lock().await.clone())
+    }
+
+    async fn prune(&self, cutoff: DateTime<Utc>) -> Result<usize, AuditError> {
+        let mut entries = self.entries.lock().await;
+        let before = entries.len();
+        entries.retain(|e| e.timestamp >= cutoff);
+        Ok(before - entries.len())
+    }
+}
+
+/// Entries appended to a JSON Lines file
+///
+/// Entries are only ever appended; the file is rewritten only when
+/// retention drops expired entries from its head.
+pub struct FileAuditSink {
+    path: PathBuf,
+    // Serializes appends against retention rewrites
+    lock: Mutex<()>,
+}
+
+impl FileAuditSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn read_entries(&self) -> Result<Vec<AuditEntry>, AuditError> {
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!("Skipping unreadable audit entry: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl AuditSink for FileAuditSink {
+    async fn append(&self, entry: &AuditEntry) -> Result<(), AuditError> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    async fn entries(&self) -> Result<Vec<AuditEntry>, AuditError> {
+        let _guard = self.lock.lock().await;
+        self.read_entries().await
+    }
+
+    async fn prune(&self, cutoff: DateTime<Utc>) -> Result<usize, AuditError> {
+        let _guard = self.lock.lock().await;
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        // Lines that don't parse are kept as they are, not silently lost
+        let mut kept = String::new();
+        let mut dropped = 0;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let expired = match serde_json::from_str::<AuditEntry>(line) {
+                Ok(entry) => entry.timestamp < cutoff,
+                Err(e) => {
+                    warn!("Keeping unreadable audit entry: {}", e);
+                    false
+                }
+            };
+            if expired {
+                dropped += 1;
+            } else {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+        if dropped == 0 {
+            return Ok(0);
+        }
+
+        // Write aside and rename so a crash never leaves a truncated log
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, kept).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(dropped)
+    }
+}
+
+/// The control plane's audit log: a sink plus its retention policy
+pub struct AuditLog {
+    sink: Box<dyn AuditSink>,
+    retention: Duration,
+}
+
+impl AuditLog {
+    pub fn new(sink: Box<dyn AuditSink>) -> Self {
+        Self {
+            sink,
+            retention: Duration::from_secs(DEFAULT_AUDIT_RETENTION_DAYS * 24 * 60 * 60),
+        }
+    }
+
+    /// An in-memory log, used when no audit file is configured
+    pub fn in_memory() -> Self {
+        Self::new(Box::new(MemoryAuditSink::new()))
+    }
+
+    /// Keep entries for this long
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Record an operation made over the REST or gRPC API, now
+    pub async fn record_operation(
+        &self,
+        actor: String,
+        method: &str,
+        path: &str,
+        resource: Option<String>,
+        status: u16,
+    ) {
+        self.record(AuditEntry {
+            timestamp: Utc::now(),
+            actor,
+            method: method.to_string(),
+            path: path.to_string(),
+            resource,
+            status,
+        })
+        .await;
+    }
+
+    /// Record an entry; failures are logged rather than failing the request
+    pub async fn record(&self, entry: AuditEntry) {
+        if let Err(e) = self.sink.append(&entry).await {
+            warn!(
+                "Failed to record audit entry for {} {}: {}",
+                entry.method, entry.path, e
+            );
+        }
+    }
+
+    /// Read entries matching a query, oldest first
+    pub async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, AuditError> {
+        Ok(filter_entries(self.sink.entries().await?, query))
+    }
+
+    /// Drop entries older than the retention period
+    pub async fn prune_expired(&self) -> Result<usize, AuditError> {
+        let retention = chrono::Duration::from_std(self.retention).unwrap_or(chrono::Duration::MAX);
+        let cutoff = Utc::now()
+            .checked_sub_signed(retention)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        self.sink.prune(cutoff).await
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(actor: &str, resource: Option<&str>, age_days: i64) -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc::now() - chrono::Duration::days(age_days),
+            actor: actor.to_string(),
+            method: "POST".to_string(),
+            path: "/v1/pipelines".to_string(),
+            resource: resource.map(String::from),
+            status: 201,
+        }
+    }
+
+    #[test]
+    fn test_is_audited() {
+        assert!(is_audited(&Method::POST, "/v1/pipelines"));
+        assert!(is_audited(
+            &Method::PUT,
+            "/v1/namespaces/a/pipelines/b/autoscaling"
+        ));
+        assert!(is_audited(&Method::DELETE, "/v1/nodes/w1"));
+        assert!(!is_audited(&Method::GET, "/v1/pipelines"));
+        assert!(!is_audited(&Method::POST, "/v1/nodes/w1/heartbeat"));
+        assert!(!is_audited(&Method::PATCH, "/v1/nodes/w1/heartbeat"));
+        assert!(!is_audited(&Method::POST, "/v1/requestlogs"));
+        assert!(!is_audited(&Method::POST, "/v1/events"));
+        assert!(!is_audited(&Method::POST, "/v1/budgets"));
+        assert!(!is_audited(
+            &Method::POST,
+            "/v1/namespaces/a/pipelines/b/chat/completions"
+        ));
+    }
+
+    #[test]
+    fn test_actor_fingerprints_key() {
+        let actor = actor_from_authorization(Some("Bearer sk-secret"));
+        assert!(actor.starts_with("key:"));
+        assert_eq!(actor.len(), "key:".len() + 12);
+        assert!(!actor.contains("sk-secret"));
+        assert_eq!(actor, actor_from_authorization(Some("Bearer sk-secret")));
+        assert_ne!(actor, actor_from_authorization(Some("Bearer other")));
+
+        assert_eq!(actor_from_authorization(None), ANONYMOUS_ACTOR);
+        assert_eq!(actor_from_authorization(Some("Bearer ")), ANONYMOUS_ACTOR);
+    }
+
+    #[test]
+    fn test_audit_actor_names_authenticated_key() {
+        let caller = Caller {
+            name: "team-a".to_string(),
+            scope: crate::cluster::auth::Scope::All,
+        };
+        assert_eq!(audit_actor(Some(&caller), Some("Bearer sk-a")), "team-a");
+        assert_eq!(
+            audit_actor(None, Some("Bearer sk-unknown")),
+            actor_from_authorization(Some("Bearer sk-unknown"))
+        );
+        assert_eq!(audit_actor(None, None), ANONYMOUS_ACTOR);
+    }
+
+    #[test]
+    fn test_resource_for_request() {
+        assert_eq!(
+            resource_for_request("/v1/namespaces/prod/pipelines/bot/scale", None).as_deref(),
+            Some("pipeline/prod/bot")
+        );
+        assert_eq!(
+            resource_for_request("/v1/nodes/w1/cordon", None).as_deref(),
+            Some("node/w1")
+        );
+
+        let manifest = json!({"kind": "Pipeline", "metadata": {"name": "bot"}});
+        assert_eq!(
+            resource_for_request("/v1/pipelines", Some(&manifest)).as_deref(),
+            Some("pipeline/default/bot")
+        );
+        let node = json!({"kind": "Node", "metadata": {"name": "w2"}});
+        assert_eq!(
+            resource_for_request("/v1/nodes", Some(&node)).as_deref(),
+            Some("node/w2")
+        );
+        assert_eq!(
+            resource_for_request("/v1/namespaces/prod/endpoints/chat", None).as_deref(),
+            Some("endpoint/prod/chat")
+        );
+        assert_eq!(
+            resource_for_request("/v1/nodepools/gpu-pool", None).as_deref(),
+            Some("nodepool/gpu-pool")
+        );
+        assert_eq!(
+            resource_for_request("/v1/regions/eu-west", None).as_deref(),
+            Some("region/eu-west")
+        );
+        let job = json!({"kind": "Job", "metadata": {"name": "batch"}});
+        assert_eq!(
+            resource_for_request("/v1/namespaces/prod/jobs", Some(&job)).as_deref(),
+            Some("job/prod/batch")
+        );
+        let secret = json!({"kind": "Secret", "metadata": {"name": "openai"}});
+        assert_eq!(
+            resource_for_request("/v1/namespaces/prod/secrets", Some(&secret)).as_deref(),
+            Some("secret/prod/openai")
+        );
+        assert_eq!(
+            resource_for_request("/v1/config/scoring", None).as_deref(),
+            Some("config/scoring")
+        );
+        assert_eq!(resource_for_request("/v1/pipelines", None), None);
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let entries = vec![
+            entry("key:a", Some("pipeline/prod/bot"), 3),
+            entry("key:b", Some("node/w1"), 2),
+            entry("key:a", Some("pipeline/dev/bot"), 1),
+        ];
+
+        let by_actor = AuditQuery {
+            actor: Some("key:a".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(filter_entries(entries.clone(), &by_actor).len(), 2);
+
+        let by_resource = AuditQuery {
+            resource: Some("pipeline/prod".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(filter_entries(entries.clone(), &by_resource).len(), 1);
+
+        let recent = AuditQuery {
+            since: Some(Utc::now() - chrono::Duration::hours(36)),
+            ..Default::default()
+        };
+        assert_eq!(filter_entries(entries.clone(), &recent).len(), 1);
+
+        // The limit keeps the most recent entries
+        let limited = AuditQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let latest = filter_entries(entries, &limited);
+        assert_eq!(latest[0].actor, "key:b");
+        assert_eq!(latest[1].resource.as_deref(), Some("pipeline/dev/bot"));
+    }
+
+    #[tokio::test]
+    async fn test_file_sink_appends_and_prunes() {
+        let dir = std::env::temp_dir().join(format!("llmnet-audit-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("audit.jsonl");
+        let log = AuditLog::new(Box::new(FileAuditSink::new(&path)))
+            .with_retention(Duration::from_secs(7 * 24 * 60 * 60));
+
+        log.record(entry("key:a", Some("pipeline/default/old"), 30))
+            .await;
+        log.record(entry("key:a", Some("pipeline/default/new"), 0))
+            .await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        std::io::Write::write_all(&mut file, b"{not an entry\n").unwrap();
+
+        assert_eq!(log.prune_expired().await.unwrap(), 1);
+        let remaining = log.query(&AuditQuery::default()).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(
+            remaining[0].resource.as_deref(),
+            Some("pipeline/default/new")
+        );
+        // Lines that don't parse survive pruning
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().last(), Some("{not an entry"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_memory_sink_prune() {
+        let sink = MemoryAuditSink::new();
+        sink.append(&entry("key:a", None, 10)).await.unwrap();
+        sink.append(&entry("key:a", None, 0)).await.unwrap();
+
+        let dropped = sink
+            .prune(Utc::now() - chrono::Duration::days(1))
+            .await
+            .unwrap();
+        assert_eq!(dropped, 1);
+        assert_eq!(sink.entries().await.unwrap().len(), 1);
+    }
+}
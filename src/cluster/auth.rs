@@ -0,0 +1,384 @@
+//! API keys scoped to namespaces
+//!
+//! Without keys the control plane serves everyone, as before. Once a key
+//! file is loaded every request needs one of its keys as a bearer token,
+//! and each key only reaches the namespaces it lists: requests under
+//! `/v1/namespaces/{namespace}/` are refused outside them, and the lists
+//! that span namespaces only show theirs. Keys listing `"*"` reach every
+//! namespace and the cluster-wide resources (nodes, node pools, regions,
+//! configuration, alerts, events, the audit log); workers use one of them.
+//!
+//! SBIO pattern: matching keys and deciding access are pure functions; only
+//! reading the key file does I/O.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use axum::http::{HeaderMap, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Namespace entry granting every namespace and the cluster-wide resources
+pub const ALL_NAMESPACES: &str = "*";
+
+/// One API key and what it may reach
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+    /// Who holds the key, e.g. the team
+    pub name: String,
+
+    /// The key itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// Hex SHA-256 of the key, in place of the key so the file doesn't
+    /// hold it (e.g. from `printf %s "$KEY" | sha256sum`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    /// Namespaces the key reaches; `"*"` for all of them
+    pub namespaces: Vec<String>,
+}
+
+/// Contents of the API key file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    pub keys: Vec<ApiKey>,
+}
+
+/// Errors loading keys or authorizing a request
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum AuthError {
+    #[error("Failed to read API keys: {0}")]
+    Io(String),
+
+    #[error("Invalid API keys: {0}")]
+    Invalid(String),
+
+    #[error("An API key is required")]
+    MissingKey,
+
+    #[error("Unknown API key")]
+    UnknownKey,
+
+    #[error("API key '{0}' can't access namespace '{1}'")]
+    NamespaceForbidden(String, String),
+
+    #[error("API key '{0}' can't access cluster-wide resources")]
+    ClusterForbidden(String),
+}
+
+/// Namespaces a caller reaches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    /// Every namespace and the cluster-wide resources
+    All,
+    /// Only these namespaces
+    Namespaces(BTreeSet<String>),
+}
+
+impl Scope {
+    /// Whether the caller reaches `namespace`
+    pub fn allows(&self, namespace: &str) -> bool {
+        match self {
+            Scope::All => true,
+            Scope::Namespaces(namespaces) => namespaces.contains(namespace),
+        }
+    }
+}
+
+/// The holder of the key a request was made with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caller {
+    /// Name of the key; "anonymous" when the control plane has no keys
+    pub name: String,
+    pub scope: Scope,
+}
+
+impl Caller {
+    /// The caller of a control plane without keys, which reaches everything
+    pub fn anonymous() -> Self {
+        Self {
+            name: super::audit::ANONYMOUS_ACTOR.to_string(),
+            scope: Scope::All,
+        }
+    }
+
+    /// Whether the caller reaches `namespace`
+    pub fn allows(&self, namespace: &str) -> bool {
+        self.scope.allows(namespace)
+    }
+
+    /// Check the caller may make a request needing `access`
+    pub fn authorize(&self, access: &Access) -> Result<(), AuthError> {
+        match access {
+            Access::Public | Access::Filtered => Ok(()),
+            Access::Namespace(namespace) if self.allows(namespace) => Ok(()),
+            Access::Namespace(namespace) => Err(AuthError::NamespaceForbidden(
+                self.name.clone(),
+                namespace.clone(),
+            )),
+            Access::ClusterWide if self.scope == Scope::All => Ok(()),
+            Access::ClusterWide => Err(AuthError::ClusterForbidden(self.name.clone())),
+        }
+    }
+}
+
+/// What a request needs its caller to reach
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Access {
+    /// Nothing: health checks and the API description
+    Public,
+    /// One namespace
+    Namespace(String),
+    /// Any namespace; the handler narrows the answer to the caller's
+    Filtered,
+    /// The cluster-wide resources
+    ClusterWide,
+}
+
+/// Paths that list or create resources across namespaces, narrowed to the
+/// caller's namespaces by their handlers
+const FILTERED_PATHS: [&str; 5] = [
+    "/v1/pipelines",
+    "/v1/endpoints",
+    "/v1/jobs",
+    "/v1/secrets",
+    "/v1/namespaces",
+];
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Hex SHA-256 of a key
+fn key_hash(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// The bearer token of an `Authorization` header
+pub fn bearer_token(authorization: Option<&str>) -> Option<&str> {
+    authorization
+        .map(|h| h.strip_prefix("Bearer ").unwrap_or(h).trim())
+        .filter(|t| !t.is_empty())
+}
+
+/// Headers sending `api_key` as a bearer token, for the clients workers
+/// reach the control plane with
+pub fn bearer_headers(api_key: Option<&str>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let value = api_key.and_then(|key| HeaderValue::from_str(&format!("Bearer {}", key)).ok());
+    if let Some(mut value) = value {
+        value.set_sensitive(true);
+        headers.insert(axum::http::header::AUTHORIZATION, value);
+    }
+    headers
+}
+
+/// Client for a worker's requests to the control plane, sending `api_key`
+/// with each one
+pub fn control_plane_client(api_key: Option<&str>) -> reqwest::Client {
+    reqwest::Client::builder()
+        .default_headers(bearer_headers(api_key))
+        .build()
+        .unwrap_or_default()
+}
+
+/// What a control plane request needs its caller to reach
+pub fn required_access(method: &Method, path: &str) -> Access {
+    if path == "/health" || path == "/openapi.json" || path.starts_with("/docs") {
+        return Access::Public;
+    }
+    if let Some(rest) = path.strip_prefix("/v1/namespaces/") {
+        let namespace = rest.split('/').next().unwrap_or_default();
+        if !namespace.is_empty() {
+            return Access::Namespace(namespace.to_string());
+        }
+    }
+    let lists = *method == Method::GET;
+    let deploys = *method == Method::POST && path == "/v1/pipelines";
+    if (lists || deploys) && FILTERED_PATHS.contains(&path) {
+        return Access::Filtered;
+    }
+    Access::ClusterWide
+}
+
+/// Keys the control plane accepts, with their hashes worked out
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: Vec<(String, Caller)>,
+}
+
+impl ApiKeys {
+    /// Check a key file's keys: each has a name, one of `key` or `sha256`
+    /// and at least one namespace
+    pub fn new(config: ApiKeyConfig) -> Result<Self, AuthError> {
+        let mut keys = Vec::new();
+        for key in config.keys {
+            let invalid =
+                |problem: &str| AuthError::Invalid(format!("key '{}' {}", key.name, problem));
+            if key.name.is_empty() {
+                return Err(AuthError::Invalid("every key needs a name".to_string()));
+            }
+            let hash = match (&key.key, &key.sha256) {
+                (Some(plain), None) if !plain.is_empty() => key_hash(plain),
+                (None, Some(hash))
+                    if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+                {
+                    hash.to_lowercase()
+                }
+                (None, Some(_)) => return Err(invalid("has a sha256 that isn't 64 hex digits")),
+                _ => return Err(invalid("needs exactly one of key and sha256")),
+            };
+            if key.namespaces.is_empty() {
+                return Err(invalid("reaches no namespaces"));
+            }
+            let scope = if key.namespaces.iter().any(|n| n == ALL_NAMESPACES) {
+                Scope::All
+            } else {
+                Scope::Namespaces(key.namespaces.into_iter().collect())
+            };
+            let caller = Caller {
+                name: key.name,
+                scope,
+            };
+            keys.push((hash, caller));
+        }
+        Ok(Self { keys })
+    }
+
+    /// Whether requests need a key
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Who a request with this `Authorization` header comes from
+    pub fn authenticate(&self, authorization: Option<&str>) -> Result<Caller, AuthError> {
+        if !self.is_enabled() {
+            return Ok(Caller::anonymous());
+        }
+        let token = bearer_token(authorization).ok_or(AuthError::MissingKey)?;
+        let hash = key_hash(token);
+        self.keys
+            .iter()
+            .find(|(known, _)| *known == hash)
+            .map(|(_, caller)| caller.clone())
+            .ok_or(AuthError::UnknownKey)
+    }
+}
+
+// ============================================================================
+// I/O: Loading keys
+// ============================================================================
+
+/// Read the keys of a YAML or JSON key file
+pub fn load_api_keys(path: &Path) -> Result<ApiKeys, AuthError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| AuthError::Io(format!("{}: {}", path.display(), e)))?;
+    let config: ApiKeyConfig =
+        serde_yaml::from_str(&content).map_err(|e| AuthError::Invalid(e.to_string()))?;
+    ApiKeys::new(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> ApiKeys {
+        let yaml = format!(
+            r#"
+keys:
+  - name: admin
+    key: sk-admin
+    namespaces: ["*"]
+  - name: team-a
+    sha256: {}
+    namespaces: [team-a, team-a-staging]
+"#,
+            key_hash("sk-team-a")
+        );
+        ApiKeys::new(serde_yaml::from_str(&yaml).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let keys = keys();
+        assert_eq!(
+            keys.authenticate(Some("Bearer sk-admin")).unwrap().scope,
+            Scope::All
+        );
+
+        let team = keys.authenticate(Some("Bearer sk-team-a")).unwrap();
+        assert_eq!(team.name, "team-a");
+        assert!(team.allows("team-a-staging"));
+        assert!(!team.allows("default"));
+
+        assert_eq!(keys.authenticate(None), Err(AuthError::MissingKey));
+        assert_eq!(
+            keys.authenticate(Some("Bearer sk-other")),
+            Err(AuthError::UnknownKey)
+        );
+        assert_eq!(
+            ApiKeys::default().authenticate(None),
+            Ok(Caller::anonymous())
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let config = |key: &str| -> ApiKeyConfig {
+            serde_yaml::from_str(&format!("keys: [{{name: a, namespaces: [x], {key}}}]")).unwrap()
+        };
+        assert!(ApiKeys::new(config("key: k")).is_ok());
+        assert!(ApiKeys::new(config("sha256: abc")).is_err());
+        assert!(ApiKeys::new(config("key: k, sha256: abc")).is_err());
+        let no_namespaces: ApiKeyConfig =
+            serde_yaml::from_str("keys: [{name: a, key: k, namespaces: []}]").unwrap();
+        assert!(ApiKeys::new(no_namespaces).is_err());
+    }
+
+    #[test]
+    fn test_required_access() {
+        assert_eq!(required_access(&Method::GET, "/health"), Access::Public);
+        assert_eq!(
+            required_access(&Method::PUT, "/v1/namespaces/team-a/pipelines/bot"),
+            Access::Namespace("team-a".to_string())
+        );
+        assert_eq!(
+            required_access(&Method::GET, "/v1/pipelines"),
+            Access::Filtered
+        );
+        assert_eq!(
+            required_access(&Method::POST, "/v1/pipelines"),
+            Access::Filtered
+        );
+        assert_eq!(
+            required_access(&Method::GET, "/v1/namespaces"),
+            Access::Filtered
+        );
+        assert_eq!(
+            required_access(&Method::GET, "/v1/nodes"),
+            Access::ClusterWide
+        );
+        assert_eq!(
+            required_access(&Method::POST, "/v1/nodes/gpu-1/cordon"),
+            Access::ClusterWide
+        );
+
+        let team = keys().authenticate(Some("Bearer sk-team-a")).unwrap();
+        assert!(team
+            .authorize(&Access::Namespace("team-a".to_string()))
+            .is_ok());
+        assert_eq!(
+            team.authorize(&Access::Namespace("default".to_string())),
+            Err(AuthError::NamespaceForbidden(
+                "team-a".to_string(),
+                "default".to_string()
+            ))
+        );
+        assert!(team.authorize(&Access::ClusterWide).is_err());
+        assert!(Caller::anonymous().authorize(&Access::ClusterWide).is_ok());
+    }
+}
@@ -387,6 +387,7 @@ mod tests {
                     memory_usage_percent: 70.0,
                     gpu_usage_percent: None,
                     gpu_memory_usage_percent: None,
+                    gpus: Vec::new(),
                     disk_usage_percent: 50.0,
                     request_count: 100,
                     avg_latency_ms: 50.0,
@@ -401,6 +402,7 @@ mod tests {
                     memory_usage_percent: 90.0,
                     gpu_usage_percent: None,
                     gpu_memory_usage_percent: None,
+                    gpus: Vec::new(),
                     disk_usage_percent: 60.0,
                     request_count: 200,
                     avg_latency_ms: 100.0,
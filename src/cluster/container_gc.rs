@@ -0,0 +1,252 @@
+//! Garbage collection of orphaned runner containers
+//!
+//! A worker that crashed, or was killed while starting a runner, can leave
+//! `llmnet-` containers behind that no runner tracks. Workers list them
+//! periodically and remove the ones found untracked on two passes in a row,
+//! so a container that is still being started isn't taken for an orphan.
+//! Each removal is logged and, with a control plane, recorded there as a
+//! `ContainerCollected` event on the node.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use super::auth::control_plane_client;
+use super::resources::ClusterEvent;
+use crate::runtime::{docker, SharedRunnerManager};
+
+/// Name prefix of the containers runners start
+pub const CONTAINER_PREFIX: &str = "llmnet-";
+
+/// Default time between collection passes
+pub const DEFAULT_GC_INTERVAL_SECS: u64 = 300;
+
+/// Reason of the event recorded per removed container
+pub const CONTAINER_COLLECTED: &str = "ContainerCollected";
+
+/// Where and how often a worker collects orphaned containers
+#[derive(Debug, Clone)]
+pub struct ContainerGcConfig {
+    pub node_name: String,
+    pub interval: Duration,
+    /// Control plane the events are reported to
+    pub control_plane_url: Option<String>,
+    /// Key sent with the events
+    pub api_key: Option<String>,
+}
+
+impl ContainerGcConfig {
+    pub fn new(node_name: impl Into<String>) -> Self {
+        Self {
+            node_name: node_name.into(),
+            interval: Duration::from_secs(DEFAULT_GC_INTERVAL_SECS),
+            control_plane_url: None,
+            api_key: None,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_control_plane(mut self, url: impl Into<String>) -> Self {
+        self.control_plane_url = Some(url.into());
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Runner containers no runner tracks
+pub fn find_orphans<'a>(
+    containers: impl IntoIterator<Item = &'a str>,
+    tracked: &BTreeSet<String>,
+) -> BTreeSet<String> {
+    containers
+        .into_iter()
+        .filter(|c| c.starts_with(CONTAINER_PREFIX) && !tracked.contains(*c))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Orphans remembered between passes
+#[derive(Debug, Clone, Default)]
+pub struct OrphanTracker {
+    suspects: BTreeSet<String>,
+}
+
+impl OrphanTracker {
+    /// Containers to collect this pass: orphans that were orphans last pass
+    /// too. The others are remembered for the next one.
+    pub fn collect(&mut self, orphans: BTreeSet<String>) -> BTreeSet<String> {
+        let (collect, suspects) = orphans.into_iter().partition(|c| self.suspects.contains(c));
+        self.suspects = suspects;
+        collect
+    }
+}
+
+/// The event recorded when a container is collected
+pub fn collected_event(node_name: &str, container: &str) -> ClusterEvent {
+    ClusterEvent::new(
+        format!("node/{}", node_name),
+        CONTAINER_COLLECTED,
+        format!("Removed orphaned runner container {}", container),
+    )
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Names of the runner containers Docker knows, stopped ones included
+async fn runner_containers() -> Result<Vec<String>, String> {
+    let args = docker::generate_ps_all_names_args(CONTAINER_PREFIX);
+    let output = Command::new("docker")
+        .args(&args)
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Force-remove a container
+async fn remove_container(container: &str) -> Result<(), String> {
+    let args = docker::generate_rm_args(container);
+    let output = Command::new("docker")
+        .args(&args)
+        .output()
+        .await
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Run one collection pass, returning the containers removed
+pub async fn collect_orphans(
+    manager: &SharedRunnerManager,
+    tracker: &mut OrphanTracker,
+) -> Vec<String> {
+    let containers = match runner_containers().await {
+        Ok(containers) => containers,
+        Err(e) => {
+            debug!(
+                "Skipping container collection, can't list containers: {}",
+                e
+            );
+            return Vec::new();
+        }
+    };
+    let tracked: BTreeSet<String> = manager.list_containers().into_iter().collect();
+    let orphans = find_orphans(containers.iter().map(String::as_str), &tracked);
+
+    let mut removed = Vec::new();
+    for container in tracker.collect(orphans) {
+        // Adopted or started since the listing
+        if manager.list_containers().contains(&container) {
+            continue;
+        }
+        match remove_container(&container).await {
+            Ok(()) => {
+                info!("Collected orphaned runner container {}", container);
+                removed.push(container);
+            }
+            Err(e) => warn!("Failed to collect orphaned container {}: {}", container, e),
+        }
+    }
+    removed
+}
+
+/// Collect orphaned containers every `config.interval`
+pub fn spawn_container_gc(
+    manager: SharedRunnerManager,
+    config: ContainerGcConfig,
+) -> JoinHandle<()> {
+    let events_url = config
+        .control_plane_url
+        .as_ref()
+        .map(|url| format!("{}/v1/events", url.trim_end_matches('/')));
+    let client = control_plane_client(config.api_key.as_deref());
+    tokio::spawn(async move {
+        let mut tracker = OrphanTracker::default();
+        let mut ticker = tokio::time::interval(config.interval);
+        loop {
+            ticker.tick().await;
+            let removed = collect_orphans(&manager, &mut tracker).await;
+            let Some(url) = events_url.as_ref().filter(|_| !removed.is_empty()) else {
+                continue;
+            };
+            let events: Vec<ClusterEvent> = removed
+                .iter()
+                .map(|c| collected_event(&config.node_name, c))
+                .collect();
+            let sent = client
+                .post(url)
+                .json(&events)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+            if let Err(e) = sent {
+                warn!("Failed to report collected containers: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_orphans() {
+        let tracked = BTreeSet::from(["llmnet-llama".to_string()]);
+        let orphans = find_orphans(
+            ["llmnet-llama", "llmnet-llama-1", "postgres", "my-llmnet-x"],
+            &tracked,
+        );
+        assert_eq!(orphans, BTreeSet::from(["llmnet-llama-1".to_string()]));
+    }
+
+    #[test]
+    fn test_orphans_collected_on_second_pass() {
+        let set = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        let mut tracker = OrphanTracker::default();
+
+        assert!(tracker.collect(set(&["llmnet-a", "llmnet-b"])).is_empty());
+        // llmnet-b was adopted meanwhile; llmnet-c is new
+        assert_eq!(
+            tracker.collect(set(&["llmnet-a", "llmnet-c"])),
+            set(&["llmnet-a"])
+        );
+        assert_eq!(tracker.collect(set(&["llmnet-c"])), set(&["llmnet-c"]));
+        assert!(tracker.collect(set(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_collected_event() {
+        let event = collected_event("gpu-1", "llmnet-llama");
+        assert_eq!(event.object, "node/gpu-1");
+        assert_eq!(event.reason, CONTAINER_COLLECTED);
+        assert!(event.message.contains("llmnet-llama"));
+    }
+}
@@ -4,20 +4,45 @@
 //! - Tracking registered nodes
 //! - Managing deployed pipelines
 //! - Scheduling pipeline replicas to nodes
+//! - Tracking federated regions and the pipelines they run
 //! - Health monitoring and recovery
 
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 
+use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use thiserror::Error;
-use tokio::sync::RwLock;
+use tokio::sync::broadcast;
+use tracing::info;
+use uuid::Uuid;
 
+use super::admission::{admission_problems, model_fit, model_warnings};
+use super::alerting::{step_alerts, violations, Alert, AlertNotification, AlertingConfig};
+use super::gpus::{plan_gpu_allocations, GpuAllocation};
 use super::health_checker::ReplicaHealthState;
-use super::node::{Node, NodePhase, NodePipelineInfo, NodeStatus, ReplicaStatus};
-use super::pipeline::{Pipeline, PipelineStatus};
-use super::resources::{LabelSelector, Namespace};
+use super::job::{Job, JobPhase, JobResult, JobStatus};
+use super::maintenance::{
+    maintenance_until, validate_windows, MaintenanceWindow, MAINTENANCE_ANNOTATION,
+};
+use super::node::{Node, NodePhase, NodePipelineInfo, NodeStatus, NodeStatusDelta, ReplicaStatus};
+use super::node_pool::NodePool;
+use super::node_session::NodeSessions;
+use super::pipeline::{
+    composition_hash, Pipeline, PipelineCondition, PipelineReplica, PipelineStatus,
+    COMPOSITION_HASH_ANNOTATION,
+};
+use super::region::{in_region, Region, RegionStatus};
+use super::resources::{
+    apply_metadata_patch, ClusterEvent, LabelSelector, MetadataField, MetadataPatch, Namespace,
+};
+use super::scoring::{calculate_node_score, ScoringWeights};
+use super::secret::{MasterKey, Secret, SecretStoreError};
+use super::virtual_endpoint::{VirtualEndpoint, VirtualEndpointStatus};
 use super::HEARTBEAT_INTERVAL_SECS;
+use crate::runtime::budget::BudgetSpend;
+use crate::runtime::request_log::{filter_logs, push_logs, RequestLog, RequestLogQuery};
 
 /// Errors that can occur in the cluster controller
 #[derive(Error, Debug)]
@@ -28,6 +53,39 @@ pub enum ControllerError {
     #[error("Pipeline '{0}' already exists in namespace '{1}'")]
     PipelineExists(String, String),
 
+    #[error("Pipeline '{0}' in namespace '{1}' has deletion protection; delete it with --force")]
+    DeletionProtected(String, String),
+
+    #[error("Pipeline '{0}' in namespace '{1}' is being deleted")]
+    PipelineTerminating(String, String),
+
+    #[error("Pipeline '{0}' in namespace '{1}' has a different composition; apply the pipeline to change it")]
+    CompositionChanged(String, String),
+
+    #[error("Status of pipeline '{0}' in namespace '{1}' describes composition {2}, which the pipeline no longer has")]
+    StaleStatus(String, String, String),
+
+    #[error("Job '{0}' not found in namespace '{1}'")]
+    JobNotFound(String, String),
+
+    #[error("Job '{0}' already exists in namespace '{1}'")]
+    JobExists(String, String),
+
+    #[error("Virtual endpoint '{0}' not found in namespace '{1}'")]
+    VirtualEndpointNotFound(String, String),
+
+    #[error("Secret '{0}' not found in namespace '{1}'")]
+    SecretNotFound(String, String),
+
+    #[error("Secret '{0}' already exists in namespace '{1}'")]
+    SecretExists(String, String),
+
+    #[error("Not allowed to read secret '{0}' in namespace '{1}'")]
+    SecretAccessDenied(String, String),
+
+    #[error(transparent)]
+    SecretStore(#[from] SecretStoreError),
+
     #[error("Node '{0}' not found")]
     NodeNotFound(String),
 
@@ -37,6 +95,21 @@ pub enum ControllerError {
     #[error("Namespace '{0}' not found")]
     NamespaceNotFound(String),
 
+    #[error("Node pool '{0}' not found")]
+    NodePoolNotFound(String),
+
+    #[error("Node pool '{0}' doesn't allow pipelines of namespace '{1}'")]
+    NamespaceNotAllowed(String, String),
+
+    #[error("Node pool '{0}' is still targeted by {1} pipeline(s)")]
+    NodePoolInUse(String, usize),
+
+    #[error("Region '{0}' not found")]
+    RegionNotFound(String),
+
+    #[error("Region '{0}' still runs {1} pipeline(s)")]
+    RegionInUse(String, usize),
+
     #[error("No available nodes for scheduling")]
     NoAvailableNodes,
 
@@ -50,6 +123,59 @@ pub enum ControllerError {
     InternalError(String),
 }
 
+/// Number of pipeline events buffered for slow watchers
+const WATCH_BUFFER: usize = 256;
+
+/// Number of cluster events kept for `GET /v1/events`
+const MAX_CLUSTER_EVENTS: usize = 1000;
+
+/// Number of request logs kept for `GET /v1/requestlogs`
+const MAX_REQUEST_LOGS: usize = 10_000;
+
+/// A change to a stored pipeline, published to watchers
+#[derive(Debug, Clone)]
+pub enum PipelineWatchEvent {
+    Added(Pipeline),
+    Modified(Pipeline),
+    Deleted(Pipeline),
+}
+
+impl PipelineWatchEvent {
+    /// The pipeline as it was after the change (or before deletion)
+    pub fn pipeline(&self) -> &Pipeline {
+        match self {
+            Self::Added(p) | Self::Modified(p) | Self::Deleted(p) => p,
+        }
+    }
+}
+
+/// Calculate the node score if the status includes metrics
+fn score_status(status: &mut NodeStatus, weights: &ScoringWeights) {
+    if let Some(ref metrics) = status.metrics {
+        let has_gpu = status.capacity.gpu > 0;
+        let score = calculate_node_score(metrics, has_gpu, Some(weights));
+        status.score = Some(score);
+    }
+}
+
+/// Stamp a pipeline being stored with its composition's hash; a status
+/// that names no composition describes this one
+fn stamp_composition_hash(pipeline: &mut Pipeline) {
+    let hash = pipeline.composition_hash();
+    pipeline
+        .metadata
+        .annotations
+        .insert(COMPOSITION_HASH_ANNOTATION.to_string(), hash.clone());
+    if let Some(status) = pipeline.status.as_mut() {
+        status.composition_hash.get_or_insert(hash);
+    }
+}
+
+/// Whether a pipeline endpoint is served from the node at `address`
+fn endpoint_on(endpoint: &str, address: &str) -> bool {
+    reqwest::Url::parse(endpoint).is_ok_and(|url| url.host_str() == Some(address))
+}
+
 /// The cluster controller manages all cluster state
 #[derive(Clone)]
 pub struct ClusterController {
@@ -62,11 +188,71 @@ pub struct ClusterController {
     /// Namespaces indexed by name
     namespaces: Arc<DashMap<String, Namespace>>,
 
+    /// Batch jobs indexed by qualified name (namespace/name)
+    jobs: Arc<DashMap<String, Job>>,
+
+    /// Outputs of each job's prompts in the order they finished, indexed by
+    /// qualified name (namespace/name)
+    job_results: Arc<DashMap<String, Vec<JobResult>>>,
+
+    /// Virtual endpoints indexed by qualified name (namespace/name)
+    virtual_endpoints: Arc<DashMap<String, VirtualEndpoint>>,
+
+    /// Secrets, with sealed values, indexed by qualified name (namespace/name)
+    secrets: Arc<DashMap<String, Secret>>,
+
+    /// Node pools indexed by name
+    node_pools: Arc<DashMap<String, NodePool>>,
+
+    /// Federated control planes indexed by name
+    regions: Arc<DashMap<String, Region>>,
+
+    /// Pipelines each region reported on its last sync, indexed by region
+    region_pipelines: Arc<DashMap<String, Vec<Pipeline>>>,
+
+    /// Key secret values are sealed with
+    master_key: Arc<MasterKey>,
+
+    /// Id of the secret grants issued for each pipeline, indexed by
+    /// qualified name (namespace/name); removed to revoke them
+    secret_grants: Arc<DashMap<String, Uuid>>,
+
     /// Health state for each replica, indexed by key (node:namespace:pipeline:port)
     replica_health: Arc<DashMap<String, ReplicaHealthState>>,
 
+    /// Nodes each pipeline was evicted from after failing health checks,
+    /// indexed by qualified name (namespace/name)
+    evicted_replicas: Arc<DashMap<String, HashSet<String>>>,
+
+    /// Replicas moved off nodes that stopped sending heartbeats, indexed by
+    /// node name; kept until the node is back and its copies are stopped
+    failed_over: Arc<DashMap<String, Vec<NodePipelineInfo>>>,
+
+    /// GPUs the models of each node's pipelines are pinned to, indexed by
+    /// node name
+    gpu_allocations: Arc<DashMap<String, Vec<GpuAllocation>>>,
+
     /// Controller configuration
     config: Arc<RwLock<ControllerConfig>>,
+
+    /// Pipeline changes, for watch subscribers
+    events: broadcast::Sender<PipelineWatchEvent>,
+
+    /// Recent actions the controller took on its own, oldest first
+    cluster_events: Arc<RwLock<VecDeque<ClusterEvent>>>,
+
+    /// Requests workers logged and shipped, oldest first
+    request_logs: Arc<RwLock<VecDeque<RequestLog>>>,
+
+    /// This month's spend on each budgeted model across the cluster,
+    /// indexed by namespace/model
+    budget_spend: Arc<DashMap<String, BudgetSpend>>,
+
+    /// Open WebSocket sessions of worker nodes
+    sessions: NodeSessions,
+
+    /// Alerts pending or firing as of the last evaluation
+    alerts: Arc<RwLock<Vec<Alert>>>,
 }
 
 /// Controller configuration
@@ -78,8 +264,22 @@ pub struct ControllerConfig {
     /// Threshold for marking node as unknown (seconds)
     pub node_heartbeat_timeout: i64,
 
+    /// How long a node stays unknown before its replicas are rescheduled
+    /// elsewhere (seconds)
+    pub node_failover_grace_period: i64,
+
     /// Maximum pipelines per node (can be overridden per-node)
     pub default_max_pipelines_per_node: u32,
+
+    /// How much each resource counts when scoring nodes for scheduling
+    pub scoring: ScoringWeights,
+
+    /// Alert rules and where their notifications go
+    pub alerting: AlertingConfig,
+
+    /// Directory jobs may read their input files from; without one jobs
+    /// take URLs and inline prompts only
+    pub jobs_dir: Option<PathBuf>,
 }
 
 impl Default for ControllerConfig {
@@ -87,7 +287,11 @@ impl Default for ControllerConfig {
         Self {
             health_check_interval: 10,
             node_heartbeat_timeout: (HEARTBEAT_INTERVAL_SECS * 3) as i64,
+            node_failover_grace_period: (HEARTBEAT_INTERVAL_SECS * 2) as i64,
             default_max_pipelines_per_node: 10,
+            scoring: ScoringWeights::default(),
+            alerting: AlertingConfig::default(),
+            jobs_dir: None,
         }
     }
 }
@@ -99,8 +303,26 @@ impl ClusterController {
             nodes: Arc::new(DashMap::new()),
             pipelines: Arc::new(DashMap::new()),
             namespaces: Arc::new(DashMap::new()),
+            jobs: Arc::new(DashMap::new()),
+            job_results: Arc::new(DashMap::new()),
+            virtual_endpoints: Arc::new(DashMap::new()),
+            secrets: Arc::new(DashMap::new()),
+            node_pools: Arc::new(DashMap::new()),
+            regions: Arc::new(DashMap::new()),
+            region_pipelines: Arc::new(DashMap::new()),
+            master_key: Arc::new(MasterKey::generate()),
+            secret_grants: Arc::new(DashMap::new()),
             replica_health: Arc::new(DashMap::new()),
+            evicted_replicas: Arc::new(DashMap::new()),
+            failed_over: Arc::new(DashMap::new()),
+            gpu_allocations: Arc::new(DashMap::new()),
             config: Arc::new(RwLock::new(ControllerConfig::default())),
+            events: broadcast::channel(WATCH_BUFFER).0,
+            cluster_events: Arc::new(RwLock::new(VecDeque::new())),
+            request_logs: Arc::new(RwLock::new(VecDeque::new())),
+            budget_spend: Arc::new(DashMap::new()),
+            sessions: NodeSessions::new(),
+            alerts: Arc::new(RwLock::new(Vec::new())),
         };
 
         // Create default namespace
@@ -114,12 +336,114 @@ impl ClusterController {
     /// Create with custom configuration
     pub fn with_config(config: ControllerConfig) -> Self {
         let controller = Self::new();
-        let mut cfg = futures::executor::block_on(controller.config.write());
-        *cfg = config;
-        drop(cfg);
+        *controller.config.write().unwrap() = config;
         controller
     }
 
+    /// Seal secret values with this key instead of one generated at startup
+    pub fn with_master_key(mut self, key: MasterKey) -> Self {
+        self.master_key = Arc::new(key);
+        self
+    }
+
+    /// Let jobs read input files from `dir`
+    pub fn with_jobs_dir(self, dir: impl Into<PathBuf>) -> Self {
+        self.config.write().unwrap().jobs_dir = Some(dir.into());
+        self
+    }
+
+    /// Directory jobs read their input files from, if any
+    pub fn jobs_dir(&self) -> Option<PathBuf> {
+        self.config.read().unwrap().jobs_dir.clone()
+    }
+
+    /// The weights nodes are currently scored with
+    pub fn scoring_weights(&self) -> ScoringWeights {
+        self.config.read().unwrap().scoring.clone()
+    }
+
+    /// Change the scoring weights and rescore every node from its last metrics
+    pub fn set_scoring_weights(&self, weights: ScoringWeights) -> Result<(), ControllerError> {
+        weights
+            .validate()
+            .map_err(ControllerError::ValidationError)?;
+
+        self.config.write().unwrap().scoring = weights.clone();
+        for mut node in self.nodes.iter_mut() {
+            if let Some(status) = node.status.as_mut() {
+                score_status(status, &weights);
+            }
+        }
+        Ok(())
+    }
+
+    /// The alert rules and sinks
+    pub fn alerting_config(&self) -> AlertingConfig {
+        self.config.read().unwrap().alerting.clone()
+    }
+
+    /// Replace the alert rules and sinks; alerts of rules that are gone are
+    /// dropped without notifying anyone
+    pub fn set_alerting_config(&self, alerting: AlertingConfig) -> Result<(), ControllerError> {
+        alerting
+            .validate()
+            .map_err(ControllerError::ValidationError)?;
+
+        self.alerts
+            .write()
+            .unwrap()
+            .retain(|a| alerting.rules.iter().any(|r| r.name == a.rule));
+        self.config.write().unwrap().alerting = alerting;
+        Ok(())
+    }
+
+    /// Alerts pending or firing, oldest first
+    pub fn list_alerts(&self) -> Vec<Alert> {
+        let mut alerts = self.alerts.read().unwrap().clone();
+        alerts.sort_by_key(|a| a.since);
+        alerts
+    }
+
+    /// Check every alert rule against the cluster's state, returning the
+    /// alerts that fired or resolved
+    pub fn evaluate_alerts(&self, now: DateTime<Utc>) -> Vec<AlertNotification> {
+        let rules = self.alerting_config().rules;
+        if rules.is_empty() && self.alerts.read().unwrap().is_empty() {
+            return Vec::new();
+        }
+
+        let nodes = self.list_nodes();
+        let pipelines = self.list_all_pipelines();
+        let logs: Vec<RequestLog> = self.request_logs.read().unwrap().iter().cloned().collect();
+        let broken: Vec<(String, Vec<(String, String)>)> = rules
+            .iter()
+            .map(|rule| {
+                let objects = violations(rule, &nodes, &pipelines, &logs, now);
+                (rule.name.clone(), objects)
+            })
+            .collect();
+
+        let mut alerts = self.alerts.write().unwrap();
+        let (next, notifications) = step_alerts(&rules, &alerts, &broken, now);
+        *alerts = next;
+        drop(alerts);
+
+        for notification in &notifications {
+            let alert = &notification.alert;
+            let reason = if notification.status == "resolved" {
+                "AlertResolved"
+            } else {
+                "AlertFiring"
+            };
+            self.record_event(
+                &alert.object,
+                reason,
+                format!("{}: {}", alert.rule, alert.message),
+            );
+        }
+        notifications
+    }
+
     // =========================================================================
     // Node Management
     // =========================================================================
@@ -146,25 +470,115 @@ impl ClusterController {
             .get_mut(name)
             .ok_or_else(|| ControllerError::NodeNotFound(name.to_string()))?;
 
-        // Calculate score if metrics are present
-        if let Some(ref metrics) = status.metrics {
-            let has_gpu = status.capacity.gpu > 0;
-            let score = super::scoring::calculate_node_score(metrics, has_gpu, None);
-            status.score = Some(score);
+        score_status(&mut status, &self.scoring_weights());
+        self.drop_evicted_pipelines(name, &mut status);
+        if node.is_in_maintenance() {
+            status.pipelines.clear();
         }
-
         node.status = Some(status);
         Ok(())
     }
 
+    /// Merge a delta heartbeat into the stored node status
+    ///
+    /// Fails with `ValidationError` if the node has never sent a full status,
+    /// since there is nothing to merge into.
+    pub fn apply_node_status_delta(
+        &self,
+        name: &str,
+        delta: NodeStatusDelta,
+    ) -> Result<(), ControllerError> {
+        let mut node = self
+            .nodes
+            .get_mut(name)
+            .ok_or_else(|| ControllerError::NodeNotFound(name.to_string()))?;
+        let in_maintenance = node.is_in_maintenance();
+
+        let status = node.status.as_mut().ok_or_else(|| {
+            ControllerError::ValidationError(format!(
+                "Node '{}' has no status yet; a full heartbeat is required",
+                name
+            ))
+        })?;
+
+        // Unknown only records that heartbeats stopped, so a delta that
+        // leaves the phase out still brings the node back
+        if delta.phase.is_none() && status.phase == NodePhase::Unknown {
+            status.phase = NodePhase::Ready;
+        }
+        status.apply(delta);
+        score_status(status, &self.scoring_weights());
+        self.drop_evicted_pipelines(name, status);
+        if in_maintenance {
+            status.pipelines.clear();
+        }
+        Ok(())
+    }
+
+    /// Forget pipelines a node still reports after they were evicted from it
+    /// or failed over to other nodes
+    fn drop_evicted_pipelines(&self, node_name: &str, status: &mut NodeStatus) {
+        let failed_over = self.failed_over.get(node_name);
+        status.pipelines.retain(|p| {
+            let evicted = self
+                .evicted_replicas
+                .get(&format!("{}/{}", p.namespace, p.name))
+                .is_some_and(|nodes| nodes.contains(node_name));
+            let moved = failed_over.as_ref().is_some_and(|replicas| {
+                replicas
+                    .iter()
+                    .any(|r| r.namespace == p.namespace && r.name == p.name)
+            });
+            !evicted && !moved
+        });
+    }
+
+    /// Replace a node's metadata and spec, keeping the status it reported
+    pub fn update_node(&self, node: Node) -> Result<Node, ControllerError> {
+        validate_windows(&node.spec.maintenance_windows)
+            .map_err(ControllerError::ValidationError)?;
+        let mut live = self
+            .nodes
+            .get_mut(&node.metadata.name)
+            .ok_or_else(|| ControllerError::NodeNotFound(node.metadata.name.clone()))?;
+        live.metadata = node.metadata;
+        live.spec = node.spec;
+        Ok(live.clone())
+    }
+
+    /// Set or remove a node's labels or annotations
+    pub fn patch_node_metadata(
+        &self,
+        name: &str,
+        field: MetadataField,
+        patch: &MetadataPatch,
+    ) -> Result<Node, ControllerError> {
+        let mut node = self
+            .nodes
+            .get_mut(name)
+            .ok_or_else(|| ControllerError::NodeNotFound(name.to_string()))?;
+        let map = match field {
+            MetadataField::Labels => &mut node.metadata.labels,
+            MetadataField::Annotations => &mut node.metadata.annotations,
+        };
+        apply_metadata_patch(map, patch);
+        Ok(node.clone())
+    }
+
     /// Unregister a node
     pub fn unregister_node(&self, name: &str) -> Result<Node, ControllerError> {
+        self.gpu_allocations.remove(name);
         self.nodes
             .remove(name)
             .map(|(_, n)| n)
             .ok_or_else(|| ControllerError::NodeNotFound(name.to_string()))
     }
 
+    /// Open WebSocket sessions of worker nodes
+    pub fn sessions(&self) -> &NodeSessions {
+        &self.sessions
+    }
+
     /// Get a node by name
     pub fn get_node(&self, name: &str) -> Option<Node> {
         self.nodes.get(name).map(|r| r.clone())
@@ -213,13 +627,115 @@ impl ClusterController {
         Ok(())
     }
 
+    /// Replace a node's maintenance windows
+    pub fn set_maintenance_windows(
+        &self,
+        name: &str,
+        windows: Vec<MaintenanceWindow>,
+    ) -> Result<(), ControllerError> {
+        validate_windows(&windows).map_err(ControllerError::ValidationError)?;
+        let mut node = self
+            .nodes
+            .get_mut(name)
+            .ok_or_else(|| ControllerError::NodeNotFound(name.to_string()))?;
+        node.spec.maintenance_windows = windows;
+        Ok(())
+    }
+
+    /// Cordon and drain nodes whose maintenance window is about to open,
+    /// and uncordon the ones whose window has closed
+    ///
+    /// Nodes that were already cordoned when their window came up are left
+    /// alone, so a manual cordon outlives any window.
+    pub fn reconcile_maintenance(&self, now: DateTime<Utc>) {
+        for node in self.list_nodes() {
+            let name = &node.metadata.name;
+            let until = maintenance_until(&node.spec.maintenance_windows, now);
+            match (until, node.is_in_maintenance()) {
+                (Some(until), false) if node.spec.schedulable => {
+                    self.start_maintenance(name, until)
+                }
+                (Some(until), true) => {
+                    // A later overlapping window keeps the node out longer
+                    if let Some(mut node) = self.nodes.get_mut(name) {
+                        node.metadata
+                            .annotations
+                            .insert(MAINTENANCE_ANNOTATION.to_string(), until.to_rfc3339());
+                    }
+                }
+                (None, true) => self.end_maintenance(name),
+                _ => {}
+            }
+        }
+    }
+
+    fn start_maintenance(&self, name: &str, until: DateTime<Utc>) {
+        let drained = {
+            let Some(mut node) = self.nodes.get_mut(name) else {
+                return;
+            };
+            node.spec.schedulable = false;
+            node.metadata
+                .annotations
+                .insert(MAINTENANCE_ANNOTATION.to_string(), until.to_rfc3339());
+            node.status
+                .as_mut()
+                .map(|s| std::mem::take(&mut s.pipelines))
+                .unwrap_or_default()
+        };
+        self.record_event(
+            format!("node/{}", name),
+            "MaintenanceCordoned",
+            format!("Cordoned for maintenance until {}", until.to_rfc3339()),
+        );
+
+        if drained.is_empty() {
+            return;
+        }
+        for replica in &drained {
+            // Zero replicas makes the orchestrator schedule the pipeline again
+            if let Some(mut status) = self
+                .get_pipeline(&replica.namespace, &replica.name)
+                .and_then(|p| p.status)
+            {
+                status.replicas = 0;
+                status.ready_replicas = 0;
+                let _ = self.update_pipeline_status(&replica.namespace, &replica.name, status);
+            }
+        }
+        let names: Vec<String> = drained
+            .iter()
+            .map(|p| format!("{}/{}", p.namespace, p.name))
+            .collect();
+        self.record_event(
+            format!("node/{}", name),
+            "Drained",
+            format!(
+                "Rescheduling {} replica(s) for maintenance: {}",
+                drained.len(),
+                names.join(", ")
+            ),
+        );
+    }
+
+    fn end_maintenance(&self, name: &str) {
+        if let Some(mut node) = self.nodes.get_mut(name) {
+            node.metadata.annotations.remove(MAINTENANCE_ANNOTATION);
+            node.spec.schedulable = true;
+        }
+        self.record_event(
+            format!("node/{}", name),
+            "MaintenanceUncordoned",
+            "Maintenance window closed",
+        );
+    }
+
     /// Check for stale nodes and mark them as unknown
     pub async fn check_node_health(&self) {
-        let config = self.config.read().await;
-        let threshold = config.node_heartbeat_timeout;
-        drop(config);
+        let threshold = self.config.read().unwrap().node_heartbeat_timeout;
 
         for mut node in self.nodes.iter_mut() {
+            let threshold = node.heartbeat_timeout(threshold);
             if let Some(status) = &mut node.status {
                 if status.is_stale(threshold) {
                     status.phase = NodePhase::Unknown;
@@ -228,6 +744,118 @@ impl ClusterController {
         }
     }
 
+    /// Reschedule the replicas of nodes that have been unknown for longer
+    /// than the failover grace period
+    ///
+    /// Their replicas are marked Failed and untracked, and their pipelines go
+    /// back to the orchestrator to replace them. Replicas on other nodes stay
+    /// where they are, unless the pipeline places layers across nodes; its
+    /// mesh is then scheduled again as a whole. Returns the nodes failed over.
+    pub fn fail_over_lost_nodes(&self) -> Vec<String> {
+        let (timeout, grace) = {
+            let config = self.config.read().unwrap();
+            (
+                config.node_heartbeat_timeout,
+                config.node_failover_grace_period,
+            )
+        };
+
+        let mut lost = Vec::new();
+        for mut node in self.nodes.iter_mut() {
+            let name = node.metadata.name.clone();
+            if self.failed_over.contains_key(&name) {
+                continue;
+            }
+            let timeout = node.heartbeat_timeout(timeout);
+            let Some(status) = node.status.as_mut() else {
+                continue;
+            };
+            if !status.is_stale(timeout + grace) || status.pipelines.is_empty() {
+                continue;
+            }
+
+            status.phase = NodePhase::Unknown;
+            let mut replicas = std::mem::take(&mut status.pipelines);
+            for replica in &mut replicas {
+                replica.status = ReplicaStatus::Failed;
+            }
+            let address = node.spec.address.clone();
+            lost.push((name, address, replicas, timeout));
+        }
+
+        let mut names = Vec::new();
+        for (name, address, replicas, timeout) in lost {
+            for replica in &replicas {
+                let Some(pipeline) = self.get_pipeline(&replica.namespace, &replica.name) else {
+                    continue;
+                };
+                let Some(mut status) = pipeline.status else {
+                    continue;
+                };
+                let surviving = self.hosting_nodes(&replica.namespace, &replica.name).len() as u32;
+                if surviving == 0 || !pipeline.spec.layer_placement.is_empty() {
+                    // Zero replicas makes the orchestrator schedule the pipeline again
+                    status.replicas = 0;
+                    status.ready_replicas = 0;
+                } else {
+                    // The orchestrator tops the pipeline back up to its replicas
+                    status.replicas = status.replicas.min(surviving);
+                    status.endpoints.retain(|e| !endpoint_on(e, &address));
+                }
+                let _ = self.update_pipeline_status(&replica.namespace, &replica.name, status);
+            }
+            let pipelines: Vec<String> = replicas
+                .iter()
+                .map(|p| format!("{}/{}", p.namespace, p.name))
+                .collect();
+            self.record_event(
+                format!("node/{}", name),
+                "NodeLost",
+                format!(
+                    "No heartbeat for over {}s, rescheduling {} replica(s): {}",
+                    timeout + grace,
+                    replicas.len(),
+                    pipelines.join(", ")
+                ),
+            );
+            self.failed_over.insert(name.clone(), replicas);
+            names.push(name);
+        }
+        names
+    }
+
+    /// Failed-over nodes that are sending heartbeats again, with the
+    /// replicas that were moved off them
+    ///
+    /// The worker may still run its old copies; once they are stopped,
+    /// `finish_failover` forgets the node's failover.
+    pub fn recovered_nodes(&self) -> Vec<(Node, Vec<NodePipelineInfo>)> {
+        let timeout = self.config.read().unwrap().node_heartbeat_timeout;
+        self.failed_over
+            .iter()
+            .filter_map(|entry| {
+                let node = self.get_node(entry.key())?;
+                let timeout = node.heartbeat_timeout(timeout);
+                let back = node.status.as_ref().is_some_and(|s| !s.is_stale(timeout));
+                back.then(|| (node, entry.value().clone()))
+            })
+            .collect()
+    }
+
+    /// Forget a node's failover once its old replicas were cleaned up
+    pub fn finish_failover(&self, node_name: &str) {
+        if let Some((_, replicas)) = self.failed_over.remove(node_name) {
+            self.record_event(
+                format!("node/{}", node_name),
+                "NodeRecovered",
+                format!(
+                    "Node is back; stopped {} replica(s) that were rescheduled elsewhere",
+                    replicas.len()
+                ),
+            );
+        }
+    }
+
     /// Add a pipeline to a node's tracked pipelines
     pub fn add_pipeline_to_node(
         &self,
@@ -260,6 +888,8 @@ impl ClusterController {
                 namespace: namespace.to_string(),
                 port,
                 status: ReplicaStatus::Running,
+                reason: None,
+                restarts: 0,
             });
         }
 
@@ -283,86 +913,532 @@ impl ClusterController {
                 .pipelines
                 .retain(|p| !(p.namespace == namespace && p.name == name));
         }
+        self.release_gpus(node_name, namespace, name);
 
         Ok(())
     }
 
-    // =========================================================================
-    // Namespace Management
-    // =========================================================================
+    /// Set the status of a pipeline replica tracked on a node
+    pub fn set_replica_status(
+        &self,
+        node_name: &str,
+        namespace: &str,
+        name: &str,
+        replica_status: ReplicaStatus,
+    ) -> Result<(), ControllerError> {
+        let mut node = self
+            .nodes
+            .get_mut(node_name)
+            .ok_or_else(|| ControllerError::NodeNotFound(node_name.to_string()))?;
 
-    /// Create a namespace
-    pub fn create_namespace(&self, ns: Namespace) -> Result<(), ControllerError> {
-        if self.namespaces.contains_key(&ns.metadata.name) {
-            return Ok(()); // Idempotent
+        if let Some(status) = &mut node.status {
+            for p in status
+                .pipelines
+                .iter_mut()
+                .filter(|p| p.namespace == namespace && p.name == name)
+            {
+                p.status = replica_status;
+            }
         }
-        self.namespaces.insert(ns.metadata.name.clone(), ns);
+
         Ok(())
     }
 
-    /// List all namespaces
-    pub fn list_namespaces(&self) -> Vec<Namespace> {
-        self.namespaces.iter().map(|r| r.clone()).collect()
+    /// Evict a pipeline replica from a node
+    ///
+    /// The replica stops being tracked on the node, later heartbeats that
+    /// still report it are ignored, and the scheduler won't place the
+    /// pipeline there again until the pipeline is deleted.
+    pub fn evict_replica(
+        &self,
+        node_name: &str,
+        namespace: &str,
+        name: &str,
+    ) -> Result<(), ControllerError> {
+        self.remove_pipeline_from_node(node_name, namespace, name)?;
+        self.evicted_replicas
+            .entry(format!("{}/{}", namespace, name))
+            .or_default()
+            .insert(node_name.to_string());
+        Ok(())
     }
 
-    // =========================================================================
-    // Pipeline Management
-    // =========================================================================
+    /// Nodes a pipeline has been evicted from
+    pub fn evicted_nodes(&self, namespace: &str, name: &str) -> HashSet<String> {
+        self.evicted_replicas
+            .get(&format!("{}/{}", namespace, name))
+            .map(|r| r.clone())
+            .unwrap_or_default()
+    }
 
-    /// Deploy a pipeline
-    pub fn deploy_pipeline(&self, mut pipeline: Pipeline) -> Result<Pipeline, ControllerError> {
-        let qualified_name = pipeline.qualified_name();
+    /// Nodes tracking a replica of a pipeline
+    pub fn hosting_nodes(&self, namespace: &str, name: &str) -> HashSet<String> {
+        self.nodes
+            .iter()
+            .filter(|n| {
+                n.status.as_ref().is_some_and(|s| {
+                    s.pipelines
+                        .iter()
+                        .any(|p| p.namespace == namespace && p.name == name)
+                })
+            })
+            .map(|n| n.metadata.name.clone())
+            .collect()
+    }
 
-        // Ensure namespace exists
-        if !self.namespaces.contains_key(&pipeline.metadata.namespace) {
-            self.create_namespace(Namespace::new(&pipeline.metadata.namespace))?;
-        }
+    /// Pin the models of a pipeline to GPUs of a multi-GPU node, away from
+    /// the models already there
+    ///
+    /// Replaces what the pipeline had on the node before. Returns no
+    /// allocations for nodes with fewer than two GPUs.
+    pub fn allocate_gpus(
+        &self,
+        node_name: &str,
+        pipeline: &Pipeline,
+    ) -> Result<Vec<GpuAllocation>, ControllerError> {
+        let node = self
+            .get_node(node_name)
+            .ok_or_else(|| ControllerError::NodeNotFound(node_name.to_string()))?;
+        let namespace = &pipeline.metadata.namespace;
+        let name = &pipeline.metadata.name;
+
+        let mut allocations = self
+            .gpu_allocations
+            .entry(node_name.to_string())
+            .or_default();
+        allocations.retain(|a| !(&a.namespace == namespace && &a.pipeline == name));
+        let planned = plan_gpu_allocations(pipeline, &node, &allocations);
+        allocations.extend(planned.iter().cloned());
+        Ok(planned)
+    }
 
-        // Check if already exists
-        if self.pipelines.contains_key(&qualified_name) {
-            return Err(ControllerError::PipelineExists(
-                pipeline.metadata.name.clone(),
-                pipeline.metadata.namespace.clone(),
-            ));
+    /// Free the GPUs a pipeline's models were pinned to on a node
+    pub fn release_gpus(&self, node_name: &str, namespace: &str, name: &str) {
+        if let Some(mut allocations) = self.gpu_allocations.get_mut(node_name) {
+            allocations.retain(|a| !(a.namespace == namespace && a.pipeline == name));
         }
+    }
 
-        // Initialize status
-        pipeline.status = Some(PipelineStatus::initial());
+    /// GPUs the models on a node are pinned to
+    pub fn gpu_allocations(&self, node_name: &str) -> Vec<GpuAllocation> {
+        self.gpu_allocations
+            .get(node_name)
+            .map(|a| a.clone())
+            .unwrap_or_default()
+    }
 
-        // Store pipeline
-        self.pipelines.insert(qualified_name, pipeline.clone());
+    // =========================================================================
+    // Node Pool Management
+    // =========================================================================
 
-        Ok(pipeline)
+    /// Create or replace a node pool
+    pub fn apply_node_pool(&self, mut pool: NodePool) -> Result<NodePool, ControllerError> {
+        pool.spec
+            .validate()
+            .map_err(ControllerError::ValidationError)?;
+        pool.status = None;
+        self.node_pools
+            .insert(pool.metadata.name.clone(), pool.clone());
+        Ok(self.with_pool_status(pool))
     }
 
-    /// Update an existing pipeline
-    pub fn update_pipeline(&self, pipeline: Pipeline) -> Result<Pipeline, ControllerError> {
-        let qualified_name = pipeline.qualified_name();
-
-        if !self.pipelines.contains_key(&qualified_name) {
-            return Err(ControllerError::PipelineNotFound(
-                pipeline.metadata.name.clone(),
-                pipeline.metadata.namespace.clone(),
-            ));
-        }
+    /// Get a node pool with its current members
+    pub fn get_node_pool(&self, name: &str) -> Option<NodePool> {
+        let pool = self.node_pools.get(name).map(|r| r.clone())?;
+        Some(self.with_pool_status(pool))
+    }
 
+    /// List node pools with their current members
+    pub fn list_node_pools(&self) -> Vec<NodePool> {
+        let pools: Vec<NodePool> = self.node_pools.iter().map(|r| r.clone()).collect();
+        pools
+            .into_iter()
+            .map(|pool| self.with_pool_status(pool))
+            .collect()
+    }
+
+    /// Delete a node pool no pipeline targets
+    pub fn delete_node_pool(&self, name: &str) -> Result<NodePool, ControllerError> {
+        if !self.node_pools.contains_key(name) {
+            return Err(ControllerError::NodePoolNotFound(name.to_string()));
+        }
+        let targeting = self
+            .pipelines
+            .iter()
+            .filter(|p| p.spec.node_pool.as_deref() == Some(name))
+            .count();
+        if targeting > 0 {
+            return Err(ControllerError::NodePoolInUse(name.to_string(), targeting));
+        }
+        self.node_pools
+            .remove(name)
+            .map(|(_, pool)| pool)
+            .ok_or_else(|| ControllerError::NodePoolNotFound(name.to_string()))
+    }
+
+    fn with_pool_status(&self, mut pool: NodePool) -> NodePool {
+        pool.status = Some(pool.observe(&self.list_nodes()));
+        pool
+    }
+
+    /// The pool a pipeline targets, if any, checking it exists and admits
+    /// the pipeline's namespace
+    pub fn node_pool_for(&self, pipeline: &Pipeline) -> Result<Option<NodePool>, ControllerError> {
+        let Some(name) = &pipeline.spec.node_pool else {
+            return Ok(None);
+        };
+        let pool = self
+            .node_pools
+            .get(name)
+            .map(|r| r.clone())
+            .ok_or_else(|| ControllerError::NodePoolNotFound(name.clone()))?;
+        if !pool.allows_namespace(&pipeline.metadata.namespace) {
+            return Err(ControllerError::NamespaceNotAllowed(
+                name.clone(),
+                pipeline.metadata.namespace.clone(),
+            ));
+        }
+        Ok(Some(pool))
+    }
+
+    // =========================================================================
+    // Region Management
+    // =========================================================================
+
+    /// Register or replace a region, keeping the status of its last sync
+    pub fn apply_region(&self, mut region: Region) -> Result<Region, ControllerError> {
+        region
+            .spec
+            .validate()
+            .map_err(ControllerError::ValidationError)?;
+        region.status = self
+            .regions
+            .get(region.name())
+            .and_then(|r| r.status.clone());
+        self.regions
+            .insert(region.metadata.name.clone(), region.clone());
+        Ok(region)
+    }
+
+    /// Get a region
+    pub fn get_region(&self, name: &str) -> Option<Region> {
+        self.regions.get(name).map(|r| r.clone())
+    }
+
+    /// List regions by name
+    pub fn list_regions(&self) -> Vec<Region> {
+        let mut regions: Vec<Region> = self.regions.iter().map(|r| r.clone()).collect();
+        regions.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+        regions
+    }
+
+    /// Unregister a region no pipeline is delegated to
+    pub fn delete_region(&self, name: &str) -> Result<Region, ControllerError> {
+        if !self.regions.contains_key(name) {
+            return Err(ControllerError::RegionNotFound(name.to_string()));
+        }
+        let delegated = self
+            .pipelines
+            .iter()
+            .filter(|p| p.region() == Some(name))
+            .count();
+        if delegated > 0 {
+            return Err(ControllerError::RegionInUse(name.to_string(), delegated));
+        }
+        self.region_pipelines.remove(name);
+        self.regions
+            .remove(name)
+            .map(|(_, region)| region)
+            .ok_or_else(|| ControllerError::RegionNotFound(name.to_string()))
+    }
+
+    /// Record the outcome of a region's sync
+    pub fn set_region_status(&self, name: &str, status: RegionStatus) {
+        if let Some(mut region) = self.regions.get_mut(name) {
+            region.status = Some(status);
+        }
+    }
+
+    /// Remember the pipelines a region reported
+    pub fn set_region_pipelines(&self, name: &str, pipelines: Vec<Pipeline>) {
+        if self.regions.contains_key(name) {
+            self.region_pipelines.insert(name.to_string(), pipelines);
+        }
+    }
+
+    /// Check the region a pipeline is delegated to, if any, is registered
+    fn check_region(&self, pipeline: &Pipeline) -> Result<(), ControllerError> {
+        match pipeline.region() {
+            Some(region) if !self.regions.contains_key(region) => {
+                Err(ControllerError::RegionNotFound(region.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // =========================================================================
+    // Namespace Management
+    // =========================================================================
+
+    /// Create a namespace
+    pub fn create_namespace(&self, ns: Namespace) -> Result<(), ControllerError> {
+        if self.namespaces.contains_key(&ns.metadata.name) {
+            return Ok(()); // Idempotent
+        }
+        self.namespaces.insert(ns.metadata.name.clone(), ns);
+        Ok(())
+    }
+
+    /// List all namespaces
+    pub fn list_namespaces(&self) -> Vec<Namespace> {
+        self.namespaces.iter().map(|r| r.clone()).collect()
+    }
+
+    // =========================================================================
+    // Pipeline Management
+    // =========================================================================
+
+    /// Deploy a pipeline
+    pub fn deploy_pipeline(&self, mut pipeline: Pipeline) -> Result<Pipeline, ControllerError> {
+        let qualified_name = pipeline.qualified_name();
+
+        // Ensure namespace exists
+        if !self.namespaces.contains_key(&pipeline.metadata.namespace) {
+            self.create_namespace(Namespace::new(&pipeline.metadata.namespace))?;
+        }
+
+        // Check if already exists
+        if self.pipelines.contains_key(&qualified_name) {
+            return Err(ControllerError::PipelineExists(
+                pipeline.metadata.name.clone(),
+                pipeline.metadata.namespace.clone(),
+            ));
+        }
+        self.node_pool_for(&pipeline)?;
+        self.check_region(&pipeline)?;
+
+        // Initialize status
+        pipeline.status = Some(PipelineStatus::initial());
+        stamp_composition_hash(&mut pipeline);
+
+        // Store pipeline
         self.pipelines.insert(qualified_name, pipeline.clone());
+        self.publish(PipelineWatchEvent::Added(pipeline.clone()));
+
         Ok(pipeline)
     }
 
-    /// Delete a pipeline
+    /// Why a new pipeline can't run on the current nodes; empty if it fits
+    ///
+    /// See [`admission_problems`] for what is checked. A pipeline that
+    /// already exists isn't checked again, and one delegated to a region is
+    /// left for that region to check.
+    pub fn admission_problems(&self, pipeline: &Pipeline) -> Vec<String> {
+        if self
+            .get_pipeline(&pipeline.metadata.namespace, &pipeline.metadata.name)
+            .is_some()
+        {
+            return Vec::new();
+        }
+        if let Err(e) = self.check_region(pipeline) {
+            return vec![e.to_string()];
+        }
+        if pipeline.region().is_some() {
+            return Vec::new();
+        }
+        let pool = match self.node_pool_for(pipeline) {
+            Ok(pool) => pool,
+            Err(e) => return vec![e.to_string()],
+        };
+        admission_problems(pipeline, &self.list_nodes(), pool.as_ref())
+    }
+
+    /// Warnings about how a new pipeline's models fit on the nodes it could
+    /// be scheduled to; see [`model_warnings`]
+    pub fn model_warnings(&self, pipeline: &Pipeline) -> Vec<String> {
+        if pipeline.region().is_some()
+            || self
+                .get_pipeline(&pipeline.metadata.namespace, &pipeline.metadata.name)
+                .is_some()
+        {
+            return Vec::new();
+        }
+        match self.node_pool_for(pipeline) {
+            Ok(pool) => model_warnings(pipeline, &self.list_nodes(), pool.as_ref()),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Check a new pipeline fits on the current schedulable nodes
+    pub fn admit_pipeline(&self, pipeline: &Pipeline) -> Result<(), ControllerError> {
+        let problems = self.admission_problems(pipeline);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ControllerError::InsufficientCapacity(problems.join("; ")))
+        }
+    }
+
+    /// Update an existing pipeline, keeping its composition
+    ///
+    /// The composition only changes through [`apply_pipeline`](Self::apply_pipeline)
+    /// or a rollout ([`revise_pipeline`](Self::revise_pipeline)), so the
+    /// pipeline's status always describes a version of it the control plane
+    /// stored.
+    pub fn update_pipeline(&self, pipeline: Pipeline) -> Result<Pipeline, ControllerError> {
+        if let Some(live) = self.get_pipeline(&pipeline.metadata.namespace, &pipeline.metadata.name)
+        {
+            if live.composition_hash() != pipeline.composition_hash() {
+                return Err(ControllerError::CompositionChanged(
+                    pipeline.metadata.name.clone(),
+                    pipeline.metadata.namespace.clone(),
+                ));
+            }
+        }
+        self.revise_pipeline(pipeline)
+    }
+
+    /// Replace an existing pipeline, composition included, as applying it or
+    /// rolling it back does
+    pub fn revise_pipeline(&self, mut pipeline: Pipeline) -> Result<Pipeline, ControllerError> {
+        let qualified_name = pipeline.qualified_name();
+
+        match self.pipelines.get(&qualified_name) {
+            None => {
+                return Err(ControllerError::PipelineNotFound(
+                    pipeline.metadata.name.clone(),
+                    pipeline.metadata.namespace.clone(),
+                ))
+            }
+            Some(live) if live.is_terminating() => {
+                return Err(ControllerError::PipelineTerminating(
+                    pipeline.metadata.name.clone(),
+                    pipeline.metadata.namespace.clone(),
+                ))
+            }
+            Some(_) => {}
+        }
+        self.node_pool_for(&pipeline)?;
+        self.check_region(&pipeline)?;
+        stamp_composition_hash(&mut pipeline);
+
+        self.pipelines.insert(qualified_name, pipeline.clone());
+        self.publish(PipelineWatchEvent::Modified(pipeline.clone()));
+        Ok(pipeline)
+    }
+
+    /// Create a pipeline, or update it if it exists
+    ///
+    /// Updates go through [`apply_update`](super::rollout::apply_update), so a
+    /// changed composition with a canary or blue/green strategy starts a
+    /// rollout instead of a redeploy. Returns whether the pipeline was created.
+    pub fn apply_pipeline(&self, pipeline: Pipeline) -> Result<(Pipeline, bool), ControllerError> {
+        match self.get_pipeline(&pipeline.metadata.namespace, &pipeline.metadata.name) {
+            Some(live) => {
+                let updated = super::rollout::apply_update(&live, pipeline);
+                self.revise_pipeline(updated).map(|p| (p, false))
+            }
+            None => self.deploy_pipeline(pipeline).map(|p| (p, true)),
+        }
+    }
+
+    /// Count a proxied request toward the pipeline's rollout
+    ///
+    /// Counters change on every request, so this doesn't notify watchers; the
+    /// orchestrator's next status update carries them.
+    pub fn record_rollout_traffic(&self, namespace: &str, name: &str, canary: bool, success: bool) {
+        let qualified_name = format!("{}/{}", namespace, name);
+        let Some(mut pipeline) = self.pipelines.get_mut(&qualified_name) else {
+            return;
+        };
+        let Some(rollout) = pipeline
+            .status
+            .as_mut()
+            .and_then(|s| s.rollout.as_mut())
+            .filter(|r| r.is_progressing())
+        else {
+            return;
+        };
+
+        let stats = if canary {
+            &mut rollout.canary
+        } else {
+            &mut rollout.stable
+        };
+        stats.requests += 1;
+        if !success {
+            stats.errors += 1;
+        }
+    }
+
+    /// Ask for a pipeline to be deleted
+    ///
+    /// A pipeline with deletion protection needs `force`. One that was never
+    /// scheduled is removed right away; otherwise it is marked terminating
+    /// and the orchestrator removes it once its runners are torn down. A
+    /// pipeline delegated to a region is always marked terminating, and
+    /// removed once the region deleted it too. Returns whether the pipeline
+    /// was removed right away.
+    pub fn request_pipeline_deletion(
+        &self,
+        namespace: &str,
+        name: &str,
+        force: bool,
+    ) -> Result<(Pipeline, bool), ControllerError> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        let mut pipeline = self.pipelines.get_mut(&qualified_name).ok_or_else(|| {
+            ControllerError::PipelineNotFound(name.to_string(), namespace.to_string())
+        })?;
+        if pipeline.is_deletion_protected() && !force {
+            return Err(ControllerError::DeletionProtected(
+                name.to_string(),
+                namespace.to_string(),
+            ));
+        }
+        if !pipeline.has_replicas() && pipeline.region().is_none() {
+            drop(pipeline);
+            return self.delete_pipeline(namespace, name).map(|p| (p, true));
+        }
+        if pipeline.is_terminating() {
+            return Ok((pipeline.clone(), false));
+        }
+
+        pipeline.metadata.deletion_timestamp = Some(Utc::now());
+        let status = pipeline.status.get_or_insert_with(PipelineStatus::initial);
+        status.conditions.push(PipelineCondition::new(
+            "Terminating",
+            "True",
+            "DeletionRequested",
+            "Tearing down runners before removing the pipeline",
+        ));
+        let pipeline = {
+            let terminating = pipeline.clone();
+            drop(pipeline);
+            terminating
+        };
+        self.publish(PipelineWatchEvent::Modified(pipeline.clone()));
+        self.record_event(
+            format!("pipeline/{}", qualified_name),
+            "Terminating",
+            "Deletion requested; tearing down runners on workers",
+        );
+        Ok((pipeline, false))
+    }
+
+    /// Remove a pipeline's record; what it runs on workers is left alone
     pub fn delete_pipeline(
         &self,
         namespace: &str,
         name: &str,
     ) -> Result<Pipeline, ControllerError> {
         let qualified_name = format!("{}/{}", namespace, name);
-        self.pipelines
-            .remove(&qualified_name)
-            .map(|(_, p)| p)
-            .ok_or_else(|| {
-                ControllerError::PipelineNotFound(name.to_string(), namespace.to_string())
-            })
+        let (_, pipeline) = self.pipelines.remove(&qualified_name).ok_or_else(|| {
+            ControllerError::PipelineNotFound(name.to_string(), namespace.to_string())
+        })?;
+        self.evicted_replicas.remove(&qualified_name);
+        self.secret_grants.remove(&qualified_name);
+        self.publish(PipelineWatchEvent::Deleted(pipeline.clone()));
+        Ok(pipeline)
     }
 
     /// Get a pipeline by name
@@ -386,6 +1462,34 @@ impl ClusterController {
         self.pipelines.iter().map(|r| r.clone()).collect()
     }
 
+    /// List the pipelines scheduled on this control plane's own nodes,
+    /// leaving out those delegated to regions
+    pub fn list_local_pipelines(&self) -> Vec<Pipeline> {
+        self.pipelines
+            .iter()
+            .filter(|r| r.region().is_none())
+            .map(|r| r.clone())
+            .collect()
+    }
+
+    /// List pipelines across all namespaces and every federated region
+    ///
+    /// Pipelines regions run that weren't delegated from here are listed as
+    /// their region last reported them, annotated with the region.
+    pub fn list_federated_pipelines(&self) -> Vec<Pipeline> {
+        let mut pipelines = self.list_all_pipelines();
+        for region in self.region_pipelines.iter() {
+            let remote = region
+                .value()
+                .iter()
+                .filter(|p| !self.pipelines.contains_key(&p.qualified_name()))
+                .cloned()
+                .collect();
+            pipelines.extend(in_region(region.key(), remote));
+        }
+        pipelines
+    }
+
     /// Scale a pipeline
     pub fn scale_pipeline(
         &self,
@@ -400,16 +1504,47 @@ impl ClusterController {
         })?;
 
         pipeline.spec.replicas = replicas;
+        let pipeline = pipeline.clone();
+        self.publish(PipelineWatchEvent::Modified(pipeline.clone()));
+
+        Ok(pipeline)
+    }
 
-        Ok(pipeline.clone())
+    /// Set or remove a pipeline's labels or annotations
+    pub fn patch_pipeline_metadata(
+        &self,
+        namespace: &str,
+        name: &str,
+        field: MetadataField,
+        patch: &MetadataPatch,
+    ) -> Result<Pipeline, ControllerError> {
+        let qualified_name = format!("{}/{}", namespace, name);
+
+        let mut pipeline = self.pipelines.get_mut(&qualified_name).ok_or_else(|| {
+            ControllerError::PipelineNotFound(name.to_string(), namespace.to_string())
+        })?;
+
+        let map = match field {
+            MetadataField::Labels => &mut pipeline.metadata.labels,
+            MetadataField::Annotations => &mut pipeline.metadata.annotations,
+        };
+        apply_metadata_patch(map, patch);
+        let pipeline = pipeline.clone();
+        self.publish(PipelineWatchEvent::Modified(pipeline.clone()));
+
+        Ok(pipeline)
     }
 
     /// Update pipeline status
+    ///
+    /// A status without a composition hash describes the pipeline's current
+    /// composition. One describing a composition the pipeline no longer has
+    /// (or, mid-rollout, whose stable spec it isn't) is refused.
     pub fn update_pipeline_status(
         &self,
         namespace: &str,
         name: &str,
-        status: PipelineStatus,
+        mut status: PipelineStatus,
     ) -> Result<(), ControllerError> {
         let qualified_name = format!("{}/{}", namespace, name);
 
@@ -417,11 +1552,646 @@ impl ClusterController {
             ControllerError::PipelineNotFound(name.to_string(), namespace.to_string())
         })?;
 
+        let current = pipeline.composition_hash();
+        match &status.composition_hash {
+            None => status.composition_hash = Some(current),
+            Some(hash) if *hash == current => {}
+            Some(hash) => {
+                let stable = pipeline
+                    .status
+                    .as_ref()
+                    .and_then(|s| s.rollout.as_ref())
+                    .and_then(|r| r.stable_spec.as_deref())
+                    .map(|spec| composition_hash(&spec.composition));
+                if stable.as_ref() != Some(hash) {
+                    return Err(ControllerError::StaleStatus(
+                        name.to_string(),
+                        namespace.to_string(),
+                        hash.clone(),
+                    ));
+                }
+            }
+        }
+
+        // The orchestrator reports status every pass; only real changes
+        // are worth waking watchers for
+        let changed =
+            serde_json::to_value(&pipeline.status).ok() != serde_json::to_value(Some(&status)).ok();
         pipeline.status = Some(status);
+        if changed {
+            let pipeline = pipeline.clone();
+            self.publish(PipelineWatchEvent::Modified(pipeline));
+        }
 
         Ok(())
     }
 
+    /// Roll the replica statuses workers reported into a pipeline's status
+    ///
+    /// A replica is ready when its worker reports it Running, and available
+    /// when health probes (`probed`, by replica key) don't say otherwise.
+    /// Replicas workers report Failed are named in a `ReplicaFailure`
+    /// condition. Returns the new status, if anything changed.
+    pub fn roll_up_replicas(
+        &self,
+        namespace: &str,
+        name: &str,
+        probed: &HashMap<String, ReplicaStatus>,
+    ) -> Result<Option<PipelineStatus>, ControllerError> {
+        let pipeline = self.get_pipeline(namespace, name).ok_or_else(|| {
+            ControllerError::PipelineNotFound(name.to_string(), namespace.to_string())
+        })?;
+
+        let mut reported = Vec::new();
+        for node in self.nodes.iter() {
+            let Some(status) = &node.status else {
+                continue;
+            };
+            for replica in status
+                .pipelines
+                .iter()
+                .filter(|p| p.namespace == namespace && p.name == name)
+            {
+                let key = format!(
+                    "{}:{}:{}:{}",
+                    node.metadata.name, namespace, name, replica.port
+                );
+                reported.push((
+                    node.metadata.name.clone(),
+                    replica.clone(),
+                    probed.get(&key),
+                ));
+            }
+        }
+        reported.sort_by(|a, b| (&a.0, a.1.port).cmp(&(&b.0, b.1.port)));
+
+        let ready = reported
+            .iter()
+            .filter(|(_, r, _)| r.status == ReplicaStatus::Running)
+            .count() as u32;
+        let replicas: Vec<PipelineReplica> = reported
+            .into_iter()
+            .map(|(node, r, probe)| PipelineReplica {
+                node,
+                port: r.port,
+                status: match (r.status, probe) {
+                    (ReplicaStatus::Running, Some(probe)) => *probe,
+                    (status, _) => status,
+                },
+                reason: r.reason,
+                restarts: r.restarts,
+            })
+            .collect();
+        let available = replicas
+            .iter()
+            .filter(|r| r.status == ReplicaStatus::Running)
+            .count() as u32;
+
+        let current = pipeline.status.unwrap_or_else(PipelineStatus::initial);
+        if current.ready_replicas == ready
+            && current.available_replicas == available
+            && current.replica_statuses == replicas
+        {
+            return Ok(None);
+        }
+
+        let mut status = current;
+        status.ready_replicas = ready;
+        status.available_replicas = available;
+        status.unavailable_replicas = pipeline.spec.replicas.saturating_sub(available);
+        let failures: Vec<String> = replicas
+            .iter()
+            .filter(|r| r.status == ReplicaStatus::Failed)
+            .filter_map(|r| {
+                let reason = r.reason.as_ref()?;
+                Some(format!("{}:{}: {}", r.node, r.port, reason))
+            })
+            .collect();
+        status.replica_statuses = replicas;
+
+        if available >= pipeline.spec.replicas {
+            status.conditions.retain(|c| {
+                c.condition_type != "Available" && c.condition_type != "ReplicaFailure"
+            });
+            status.conditions.push(PipelineCondition::new(
+                "Available",
+                "True",
+                "MinimumReplicasAvailable",
+                format!(
+                    "{}/{} replicas available",
+                    available, pipeline.spec.replicas
+                ),
+            ));
+        } else if !failures.is_empty() {
+            status.add_condition(PipelineCondition::new(
+                "ReplicaFailure",
+                "True",
+                "ReplicaFailed",
+                failures.join("; "),
+            ));
+        }
+
+        self.update_pipeline_status(namespace, name, status.clone())?;
+        Ok(Some(status))
+    }
+
+    /// Subscribe to pipeline changes
+    ///
+    /// Events are only delivered for changes made after subscribing; a
+    /// receiver that falls more than a few hundred events behind is told it
+    /// lagged and skips ahead.
+    pub fn watch_pipelines(&self) -> broadcast::Receiver<PipelineWatchEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, event: PipelineWatchEvent) {
+        // No receivers is not an error: nobody is watching
+        let _ = self.events.send(event);
+    }
+
+    // =========================================================================
+    // Job Management
+    // =========================================================================
+
+    /// Create a job for a pipeline in the job's namespace
+    pub fn create_job(&self, mut job: Job) -> Result<Job, ControllerError> {
+        job.spec
+            .validate()
+            .map_err(ControllerError::ValidationError)?;
+
+        let namespace = job.metadata.namespace.clone();
+        if self.get_pipeline(&namespace, &job.spec.pipeline).is_none() {
+            return Err(ControllerError::PipelineNotFound(
+                job.spec.pipeline.clone(),
+                namespace,
+            ));
+        }
+
+        let qualified_name = job.qualified_name();
+        if self.jobs.contains_key(&qualified_name) {
+            return Err(ControllerError::JobExists(
+                job.metadata.name.clone(),
+                namespace,
+            ));
+        }
+
+        job.status = Some(JobStatus::pending());
+        self.jobs.insert(qualified_name.clone(), job.clone());
+        self.job_results.insert(qualified_name, Vec::new());
+        Ok(job)
+    }
+
+    /// Get a job by name
+    pub fn get_job(&self, namespace: &str, name: &str) -> Option<Job> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        self.jobs.get(&qualified_name).map(|r| r.clone())
+    }
+
+    /// List all jobs in a namespace
+    pub fn list_jobs(&self, namespace: &str) -> Vec<Job> {
+        let prefix = format!("{}/", namespace);
+        self.jobs
+            .iter()
+            .filter(|r| r.key().starts_with(&prefix))
+            .map(|r| r.clone())
+            .collect()
+    }
+
+    /// List all jobs across all namespaces
+    pub fn list_all_jobs(&self) -> Vec<Job> {
+        self.jobs.iter().map(|r| r.clone()).collect()
+    }
+
+    /// Delete a job and its results. A running job stops sending prompts.
+    pub fn delete_job(&self, namespace: &str, name: &str) -> Result<Job, ControllerError> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        let (_, job) = self
+            .jobs
+            .remove(&qualified_name)
+            .ok_or_else(|| ControllerError::JobNotFound(name.to_string(), namespace.to_string()))?;
+        self.job_results.remove(&qualified_name);
+        Ok(job)
+    }
+
+    /// Results of a job's prompts, in input order
+    pub fn job_results(&self, namespace: &str, name: &str) -> Option<Vec<JobResult>> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        let mut results = self.job_results.get(&qualified_name)?.clone();
+        results.sort_by_key(|r| r.index);
+        Some(results)
+    }
+
+    /// Mark a pending job as running on a worker endpoint
+    pub fn start_job(
+        &self,
+        namespace: &str,
+        name: &str,
+        endpoint: &str,
+    ) -> Result<Job, ControllerError> {
+        self.update_job_status(namespace, name, |status| {
+            status.phase = JobPhase::Running;
+            status.endpoint = Some(endpoint.to_string());
+            status.start_time = Some(Utc::now());
+            status.message = None;
+        })
+        .ok_or_else(|| ControllerError::JobNotFound(name.to_string(), namespace.to_string()))
+    }
+
+    /// Record how many prompts a running job has. Returns false if the job
+    /// was deleted.
+    pub fn set_job_total(&self, namespace: &str, name: &str, total: usize) -> bool {
+        self.update_job_status(namespace, name, |status| status.total = total)
+            .is_some()
+    }
+
+    /// Store the outcome of one prompt. Returns false if the job was
+    /// deleted, so the runner can stop.
+    pub fn record_job_result(&self, namespace: &str, name: &str, result: JobResult) -> bool {
+        let failed = result.error.is_some();
+        let updated = self.update_job_status(namespace, name, |status| {
+            if failed {
+                status.failed += 1;
+            } else {
+                status.completed += 1;
+            }
+        });
+        if updated.is_none() {
+            return false;
+        }
+        let qualified_name = format!("{}/{}", namespace, name);
+        self.job_results
+            .entry(qualified_name)
+            .or_default()
+            .push(result);
+        true
+    }
+
+    /// Close a job once all of its prompts have been sent
+    pub fn finish_job(&self, namespace: &str, name: &str) {
+        if let Some(job) = self.update_job_status(namespace, name, |s| s.finish(Utc::now())) {
+            self.record_job_event(&job);
+        }
+    }
+
+    /// Fail a job that couldn't run its prompts
+    pub fn fail_job(&self, namespace: &str, name: &str, message: impl Into<String>) {
+        let message = message.into();
+        if let Some(job) = self.update_job_status(namespace, name, |s| s.fail(message, Utc::now()))
+        {
+            self.record_job_event(&job);
+        }
+    }
+
+    fn update_job_status(
+        &self,
+        namespace: &str,
+        name: &str,
+        update: impl FnOnce(&mut JobStatus),
+    ) -> Option<Job> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        let mut job = self.jobs.get_mut(&qualified_name)?;
+        update(job.status.get_or_insert_with(JobStatus::pending));
+        Some(job.clone())
+    }
+
+    fn record_job_event(&self, job: &Job) {
+        let Some(status) = &job.status else {
+            return;
+        };
+        let object = format!("job/{}", job.qualified_name());
+        match status.phase {
+            JobPhase::Succeeded => self.record_event(
+                object,
+                "JobSucceeded",
+                format!("{} prompts completed", status.completed),
+            ),
+            _ => self.record_event(
+                object,
+                "JobFailed",
+                status.message.clone().unwrap_or_default(),
+            ),
+        }
+    }
+
+    // =========================================================================
+    // Virtual Endpoint Management
+    // =========================================================================
+
+    /// Create or replace a virtual endpoint
+    ///
+    /// Traffic counted for backends that are still listed is kept.
+    pub fn apply_virtual_endpoint(
+        &self,
+        mut endpoint: VirtualEndpoint,
+    ) -> Result<VirtualEndpoint, ControllerError> {
+        endpoint
+            .spec
+            .validate()
+            .map_err(ControllerError::ValidationError)?;
+
+        let qualified_name = endpoint.qualified_name();
+        let mut status = self
+            .virtual_endpoints
+            .get(&qualified_name)
+            .and_then(|live| live.status.clone())
+            .unwrap_or_default();
+        status.variants.retain(|pipeline, _| {
+            endpoint
+                .spec
+                .backends
+                .iter()
+                .any(|b| &b.pipeline == pipeline)
+        });
+        endpoint.status = Some(status);
+
+        self.virtual_endpoints
+            .insert(qualified_name, endpoint.clone());
+        Ok(endpoint)
+    }
+
+    /// Get a virtual endpoint by name
+    pub fn get_virtual_endpoint(&self, namespace: &str, name: &str) -> Option<VirtualEndpoint> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        self.virtual_endpoints
+            .get(&qualified_name)
+            .map(|r| r.clone())
+    }
+
+    /// List all virtual endpoints in a namespace
+    pub fn list_virtual_endpoints(&self, namespace: &str) -> Vec<VirtualEndpoint> {
+        let prefix = format!("{}/", namespace);
+        self.virtual_endpoints
+            .iter()
+            .filter(|r| r.key().starts_with(&prefix))
+            .map(|r| r.clone())
+            .collect()
+    }
+
+    /// List all virtual endpoints across all namespaces
+    pub fn list_all_virtual_endpoints(&self) -> Vec<VirtualEndpoint> {
+        self.virtual_endpoints.iter().map(|r| r.clone()).collect()
+    }
+
+    /// Delete a virtual endpoint. Its backing pipelines are left running.
+    pub fn delete_virtual_endpoint(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<VirtualEndpoint, ControllerError> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        self.virtual_endpoints
+            .remove(&qualified_name)
+            .map(|(_, endpoint)| endpoint)
+            .ok_or_else(|| {
+                ControllerError::VirtualEndpointNotFound(name.to_string(), namespace.to_string())
+            })
+    }
+
+    /// Count a request a virtual endpoint sent to one of its backends
+    pub fn record_variant_traffic(
+        &self,
+        namespace: &str,
+        name: &str,
+        pipeline: &str,
+        success: bool,
+        latency_ms: u64,
+    ) {
+        let qualified_name = format!("{}/{}", namespace, name);
+        let Some(mut endpoint) = self.virtual_endpoints.get_mut(&qualified_name) else {
+            return;
+        };
+        let stats = endpoint
+            .status
+            .get_or_insert_with(VirtualEndpointStatus::default)
+            .variants
+            .entry(pipeline.to_string())
+            .or_default();
+        stats.requests += 1;
+        stats.total_latency_ms += latency_ms;
+        if !success {
+            stats.errors += 1;
+        }
+    }
+
+    // =========================================================================
+    // Secret Management
+    // =========================================================================
+
+    /// Store a new secret, sealing its `stringData` values into `data`
+    pub fn create_secret(&self, mut secret: Secret) -> Result<Secret, ControllerError> {
+        if secret.string_data.is_empty() && secret.data.is_empty() {
+            return Err(ControllerError::ValidationError(
+                "Secret has no values".to_string(),
+            ));
+        }
+        // Sealed values only decrypt under the key that sealed them
+        for sealed in secret.data.values() {
+            self.master_key.open(sealed)?;
+        }
+
+        let qualified_name = secret.qualified_name();
+        if self.secrets.contains_key(&qualified_name) {
+            return Err(ControllerError::SecretExists(
+                secret.metadata.name.clone(),
+                secret.metadata.namespace.clone(),
+            ));
+        }
+
+        for (key, value) in std::mem::take(&mut secret.string_data) {
+            secret.data.insert(key, self.master_key.seal(&value));
+        }
+        self.secrets.insert(qualified_name, secret.clone());
+        Ok(secret)
+    }
+
+    /// Get a secret by name, with its values sealed
+    pub fn get_secret(&self, namespace: &str, name: &str) -> Option<Secret> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        self.secrets.get(&qualified_name).map(|r| r.clone())
+    }
+
+    /// List all secrets in a namespace
+    pub fn list_secrets(&self, namespace: &str) -> Vec<Secret> {
+        let prefix = format!("{}/", namespace);
+        self.secrets
+            .iter()
+            .filter(|r| r.key().starts_with(&prefix))
+            .map(|r| r.clone())
+            .collect()
+    }
+
+    /// List all secrets across all namespaces
+    pub fn list_all_secrets(&self) -> Vec<Secret> {
+        self.secrets.iter().map(|r| r.clone()).collect()
+    }
+
+    /// Delete a secret. Runners already started with it keep their values.
+    pub fn delete_secret(&self, namespace: &str, name: &str) -> Result<Secret, ControllerError> {
+        let qualified_name = format!("{}/{}", namespace, name);
+        self.secrets
+            .remove(&qualified_name)
+            .map(|(_, secret)| secret)
+            .ok_or_else(|| ControllerError::SecretNotFound(name.to_string(), namespace.to_string()))
+    }
+
+    /// A secret's values, decrypted
+    pub fn secret_values(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<BTreeMap<String, String>, ControllerError> {
+        let secret = self.get_secret(namespace, name).ok_or_else(|| {
+            ControllerError::SecretNotFound(name.to_string(), namespace.to_string())
+        })?;
+        secret
+            .data
+            .iter()
+            .map(|(key, sealed)| Ok((key.clone(), self.master_key.open(sealed)?)))
+            .collect()
+    }
+
+    /// Grant sent with a pipeline's assignments, letting the workers that run
+    /// it read the cluster Secrets it references
+    ///
+    /// Grants are revoked when the pipeline is deleted, so they don't carry
+    /// over to a pipeline deployed again under the same name.
+    pub fn secret_grant(&self, namespace: &str, pipeline: &str) -> String {
+        let qualified_name = format!("{}/{}", namespace, pipeline);
+        let id = *self
+            .secret_grants
+            .entry(qualified_name.clone())
+            .or_insert_with(Uuid::new_v4);
+        self.master_key.seal(&format!("{}/{}", qualified_name, id))
+    }
+
+    /// A secret's values, decrypted for the holder of a pipeline's grant
+    ///
+    /// The grant must have been issued by this control plane, and not revoked
+    /// since, for a pipeline in the secret's namespace that still references
+    /// the secret.
+    pub fn granted_secret_values(
+        &self,
+        grant: &str,
+        namespace: &str,
+        name: &str,
+    ) -> Result<BTreeMap<String, String>, ControllerError> {
+        let denied =
+            || ControllerError::SecretAccessDenied(name.to_string(), namespace.to_string());
+        let granted = self.master_key.open(grant).map_err(|_| denied())?;
+        let references_secret = granted
+            .rsplit_once('/')
+            .filter(|(qualified_name, id)| {
+                self.secret_grants
+                    .get(*qualified_name)
+                    .is_some_and(|issued| issued.to_string() == *id)
+            })
+            .and_then(|(qualified_name, _)| qualified_name.split_once('/'))
+            .filter(|(granted_namespace, _)| *granted_namespace == namespace)
+            .and_then(|(_, pipeline)| self.get_pipeline(namespace, pipeline))
+            .is_some_and(|pipeline| {
+                pipeline
+                    .cluster_secret_refs()
+                    .iter()
+                    .any(|r| r.secret == name)
+            });
+        if !references_secret {
+            return Err(denied());
+        }
+        self.secret_values(namespace, name)
+    }
+
+    // =========================================================================
+    // Cluster Events
+    // =========================================================================
+
+    /// Record something the controller did on its own
+    pub fn record_event(
+        &self,
+        object: impl Into<String>,
+        reason: impl Into<String>,
+        message: impl Into<String>,
+    ) {
+        let event = ClusterEvent::new(object, reason, message);
+        info!("{} {}: {}", event.object, event.reason, event.message);
+        self.push_event(event);
+    }
+
+    /// Record events a worker reported, e.g. containers it collected
+    pub fn record_reported_events(&self, events: Vec<ClusterEvent>) {
+        for event in events {
+            info!("{} {}: {}", event.object, event.reason, event.message);
+            self.push_event(event);
+        }
+    }
+
+    fn push_event(&self, event: ClusterEvent) {
+        let mut events = self.cluster_events.write().unwrap();
+        if events.len() == MAX_CLUSTER_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// Recent cluster events, oldest first
+    pub fn list_events(&self) -> Vec<ClusterEvent> {
+        self.cluster_events
+            .read()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    // =========================================================================
+    // Request Logs
+    // =========================================================================
+
+    /// Keep request logs a worker shipped, dropping the oldest once full
+    pub fn record_request_logs(&self, logs: Vec<RequestLog>) {
+        push_logs(
+            &mut self.request_logs.write().unwrap(),
+            logs,
+            MAX_REQUEST_LOGS,
+        );
+    }
+
+    /// Request logs matching a query, oldest first
+    pub fn query_request_logs(&self, query: &RequestLogQuery) -> Vec<RequestLog> {
+        filter_logs(self.request_logs.read().unwrap().iter(), query)
+    }
+
+    // =========================================================================
+    // Budgets
+    // =========================================================================
+
+    /// Add the spend a worker reported to the cluster-wide totals, returning
+    /// the totals of the models it reported on
+    ///
+    /// Spend for a new month starts its totals over; spend for a month
+    /// that's already over is dropped.
+    pub fn record_budget_spend(&self, report: Vec<BudgetSpend>) -> Vec<BudgetSpend> {
+        report
+            .into_iter()
+            .filter_map(|spend| {
+                let key = format!("{}/{}", spend.namespace, spend.model);
+                let mut total = self.budget_spend.entry(key).or_insert_with(|| BudgetSpend {
+                    tokens: 0,
+                    dollars: 0.0,
+                    ..spend.clone()
+                });
+                match spend.month.cmp(&total.month) {
+                    std::cmp::Ordering::Less => return None,
+                    std::cmp::Ordering::Equal => {
+                        total.tokens += spend.tokens;
+                        total.dollars += spend.dollars;
+                    }
+                    std::cmp::Ordering::Greater => *total = spend,
+                }
+                Some(total.clone())
+            })
+            .collect()
+    }
+
     // =========================================================================
     // Scheduling
     // =========================================================================
@@ -435,8 +2205,20 @@ impl ClusterController {
     pub fn schedule_replicas(
         &self,
         pipeline: &Pipeline,
+    ) -> Result<HashMap<String, u32>, ControllerError> {
+        self.schedule_replicas_excluding(pipeline, &HashSet::new())
+    }
+
+    /// Schedule a pipeline's replicas away from the given nodes, such as
+    /// those already running one
+    pub fn schedule_replicas_excluding(
+        &self,
+        pipeline: &Pipeline,
+        exclude: &HashSet<String>,
     ) -> Result<HashMap<String, u32>, ControllerError> {
         let selector = &pipeline.spec.node_selector;
+        let required_runners = pipeline.required_runners();
+        let pool = self.node_pool_for(pipeline)?;
         let mut nodes: Vec<Node> = if selector.is_empty() {
             self.get_schedulable_nodes()
         } else {
@@ -449,8 +2231,50 @@ impl ClusterController {
                 .collect()
         };
 
+        // Never place a pipeline on a node missing one of its runners
+        nodes.retain(|n| n.supports_runners(&required_runners));
+
+        // Keep to the pipeline's pool, within the pool's limit
+        if let Some(pool) = &pool {
+            nodes.retain(|n| pool.contains(n));
+            if let Some(room) = pool.room_for(pipeline, &self.list_nodes()) {
+                if room < pipeline.spec.replicas {
+                    return Err(ControllerError::InsufficientCapacity(format!(
+                        "node pool '{}' has room for {} more pipeline replicas, {} requested",
+                        pool.name(),
+                        room,
+                        pipeline.spec.replicas
+                    )));
+                }
+            }
+        }
+
+        // Nor back on a node it was evicted from for failing health checks,
+        // or one excluded
+        let evicted = self.evicted_nodes(&pipeline.metadata.namespace, &pipeline.metadata.name);
+        nodes
+            .retain(|n| !evicted.contains(&n.metadata.name) && !exclude.contains(&n.metadata.name));
+
+        if nodes.is_empty() {
+            return Err(ControllerError::NoAvailableNodes);
+        }
+
+        // Nor on a node its models don't fit in
+        let mut model_errors = Vec::new();
+        nodes.retain(|n| {
+            let fit = model_fit(pipeline, n);
+            model_errors.extend(
+                fit.errors
+                    .iter()
+                    .map(|e| format!("node '{}': {}", n.metadata.name, e)),
+            );
+            fit.errors.is_empty()
+        });
         if nodes.is_empty() {
-            return Err(ControllerError::NoAvailableNodes);
+            return Err(ControllerError::InsufficientCapacity(format!(
+                "the pipeline's models don't fit on any node: {}",
+                model_errors.join("; ")
+            )));
         }
 
         // Sort nodes by score (highest first)
@@ -593,192 +2417,1165 @@ impl Default for ClusterController {
     }
 }
 
-/// Cluster statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ClusterStats {
-    pub total_nodes: usize,
-    pub ready_nodes: usize,
-    pub total_pipelines: usize,
-    pub ready_pipelines: usize,
-    pub namespaces: usize,
-}
+/// Cluster statistics
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClusterStats {
+    pub total_nodes: usize,
+    pub ready_nodes: usize,
+    pub total_pipelines: usize,
+    pub ready_pipelines: usize,
+    pub namespaces: usize,
+}
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{NodeCapabilities, NodeCapacity, NodeInfo};
+    use crate::cluster::pipeline::{DELETION_PROTECTION_ANNOTATION, REGION_ANNOTATION};
+    use crate::config::Composition;
+
+    fn create_test_composition() -> Composition {
+        let json = r#"{
+            "models": {},
+            "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+        Composition::from_str(json).unwrap()
+    }
+
+    fn create_test_node(name: &str) -> Node {
+        let mut node = Node::new(name, "localhost");
+        node.status = Some(NodeStatus::new(
+            NodeCapacity::default(),
+            NodeInfo::from_system(),
+        ));
+        node
+    }
+
+    #[test]
+    fn test_register_node() {
+        let controller = ClusterController::new();
+        let node = create_test_node("node-1");
+
+        controller.register_node(node.clone()).unwrap();
+
+        let retrieved = controller.get_node("node-1");
+        assert!(retrieved.is_some());
+        assert_eq!(retrieved.unwrap().metadata.name, "node-1");
+    }
+
+    #[test]
+    fn test_register_duplicate_node() {
+        let controller = ClusterController::new();
+        let node = create_test_node("node-1");
+
+        controller.register_node(node.clone()).unwrap();
+        let result = controller.register_node(node);
+
+        assert!(matches!(result, Err(ControllerError::NodeExists(_))));
+    }
+
+    #[test]
+    fn test_list_nodes() {
+        let controller = ClusterController::new();
+        controller
+            .register_node(create_test_node("node-1"))
+            .unwrap();
+        controller
+            .register_node(create_test_node("node-2"))
+            .unwrap();
+
+        let nodes = controller.list_nodes();
+        assert_eq!(nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_deploy_pipeline() {
+        let controller = ClusterController::new();
+        let comp = create_test_composition();
+        let pipeline = Pipeline::new("my-pipeline", comp);
+
+        let deployed = controller.deploy_pipeline(pipeline).unwrap();
+
+        assert_eq!(deployed.metadata.name, "my-pipeline");
+        assert!(deployed.status.is_some());
+    }
+
+    #[test]
+    fn test_deploy_duplicate_pipeline() {
+        let controller = ClusterController::new();
+        let comp = create_test_composition();
+        let pipeline = Pipeline::new("my-pipeline", comp.clone());
+
+        controller.deploy_pipeline(pipeline).unwrap();
+
+        let pipeline2 = Pipeline::new("my-pipeline", comp);
+        let result = controller.deploy_pipeline(pipeline2);
+
+        assert!(matches!(result, Err(ControllerError::PipelineExists(_, _))));
+    }
+
+    #[test]
+    fn test_get_pipeline() {
+        let controller = ClusterController::new();
+        let comp = create_test_composition();
+        let pipeline = Pipeline::new("test", comp).with_namespace("prod");
+
+        controller.deploy_pipeline(pipeline).unwrap();
+
+        let retrieved = controller.get_pipeline("prod", "test");
+        assert!(retrieved.is_some());
+    }
+
+    #[test]
+    fn test_delete_pipeline() {
+        let controller = ClusterController::new();
+        let comp = create_test_composition();
+        let pipeline = Pipeline::new("test", comp);
+
+        controller.deploy_pipeline(pipeline).unwrap();
+
+        let deleted = controller.delete_pipeline("default", "test");
+        assert!(deleted.is_ok());
+
+        let retrieved = controller.get_pipeline("default", "test");
+        assert!(retrieved.is_none());
+    }
+
+    #[test]
+    fn test_request_pipeline_deletion() {
+        let controller = ClusterController::new();
+        let mut pipeline = Pipeline::new("test", create_test_composition());
+        pipeline.metadata.annotations.insert(
+            DELETION_PROTECTION_ANNOTATION.to_string(),
+            "true".to_string(),
+        );
+        controller.deploy_pipeline(pipeline).unwrap();
+
+        assert!(matches!(
+            controller.request_pipeline_deletion("default", "test", false),
+            Err(ControllerError::DeletionProtected(..))
+        ));
+
+        // Scheduled: kept until the orchestrator tears it down
+        let mut status = PipelineStatus::initial();
+        status.endpoints = vec!["http://10.0.0.1:8080".to_string()];
+        controller
+            .update_pipeline_status("default", "test", status)
+            .unwrap();
+        let (terminating, removed) = controller
+            .request_pipeline_deletion("default", "test", true)
+            .unwrap();
+        assert!(!removed);
+        assert!(terminating.is_terminating());
+        let live = controller.get_pipeline("default", "test").unwrap();
+        assert!(live.is_terminating());
+        assert!(matches!(
+            controller.update_pipeline(live.clone()),
+            Err(ControllerError::PipelineTerminating(..))
+        ));
+        // Asking again changes nothing
+        let (again, _) = controller
+            .request_pipeline_deletion("default", "test", true)
+            .unwrap();
+        assert_eq!(
+            again.metadata.deletion_timestamp,
+            live.metadata.deletion_timestamp
+        );
+
+        // Never scheduled: removed right away
+        controller
+            .deploy_pipeline(Pipeline::new("idle", create_test_composition()))
+            .unwrap();
+        let (_, removed) = controller
+            .request_pipeline_deletion("default", "idle", false)
+            .unwrap();
+        assert!(removed);
+        assert!(controller.get_pipeline("default", "idle").is_none());
+    }
+
+    #[test]
+    fn test_scale_pipeline() {
+        let controller = ClusterController::new();
+        let comp = create_test_composition();
+        let pipeline = Pipeline::new("test", comp);
+
+        controller.deploy_pipeline(pipeline).unwrap();
+
+        let scaled = controller.scale_pipeline("default", "test", 5).unwrap();
+        assert_eq!(scaled.spec.replicas, 5);
+    }
+
+    #[test]
+    fn test_patch_metadata() {
+        let controller = ClusterController::new();
+        let pipeline = Pipeline::new("test", create_test_composition()).with_label("tier", "free");
+        controller.deploy_pipeline(pipeline).unwrap();
+        controller
+            .register_node(create_test_node("node-1"))
+            .unwrap();
+
+        let patch = MetadataPatch::from([
+            ("tier".to_string(), None),
+            ("team".to_string(), Some("search".to_string())),
+        ]);
+        let pipeline = controller
+            .patch_pipeline_metadata("default", "test", MetadataField::Labels, &patch)
+            .unwrap();
+        assert_eq!(pipeline.metadata.labels.get("team").unwrap(), "search");
+        assert!(!pipeline.metadata.labels.contains_key("tier"));
+
+        let node = controller
+            .patch_node_metadata("node-1", MetadataField::Annotations, &patch)
+            .unwrap();
+        assert_eq!(node.metadata.annotations.get("team").unwrap(), "search");
+        assert!(!node.metadata.labels.contains_key("team"));
+
+        assert!(matches!(
+            controller.patch_node_metadata("missing", MetadataField::Labels, &patch),
+            Err(ControllerError::NodeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_schedule_replicas() {
+        let controller = ClusterController::new();
+
+        // Register some nodes
+        controller
+            .register_node(create_test_node("node-1"))
+            .unwrap();
+        controller
+            .register_node(create_test_node("node-2"))
+            .unwrap();
+
+        let comp = create_test_composition();
+        let pipeline = Pipeline::new("test", comp).with_replicas(4);
+
+        let schedule = controller.schedule_replicas(&pipeline).unwrap();
+
+        // Should distribute across 2 nodes
+        assert_eq!(schedule.values().sum::<u32>(), 4);
+        assert!(schedule.len() <= 2);
+    }
+
+    #[test]
+    fn test_schedule_skips_nodes_models_dont_fit() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        let controller = ClusterController::new();
+        let node = |name: &str, memory: u64| {
+            let mut node = create_test_node(name);
+            node.status = Some(NodeStatus::new(
+                NodeCapacity {
+                    memory,
+                    ..NodeCapacity::default()
+                },
+                NodeInfo::from_system(),
+            ));
+            node
+        };
+        controller.register_node(node("small", 8 * GIB)).unwrap();
+
+        let composition = Composition::from_str(
+            r#"{
+                "models": {"llm": {"runner": "llama-cpp", "source": "/models/llama-13b.Q8_0.gguf"}},
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "llm", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::new("test", composition).with_replicas(2);
+        let err = controller.schedule_replicas(&pipeline).unwrap_err();
+        assert!(
+            err.to_string().contains("node 'small': model 'llm'"),
+            "{}",
+            err
+        );
+
+        controller.register_node(node("big", 64 * GIB)).unwrap();
+        let schedule = controller.schedule_replicas(&pipeline).unwrap();
+        assert_eq!(schedule, HashMap::from([("big".to_string(), 2)]));
+    }
+
+    #[test]
+    fn test_evicted_replica_is_not_rescheduled_or_reported() {
+        let controller = ClusterController::new();
+        controller
+            .register_node(create_test_node("node-1"))
+            .unwrap();
+        controller
+            .register_node(create_test_node("node-2"))
+            .unwrap();
+
+        let pipeline = Pipeline::new("test", create_test_composition());
+        controller
+            .add_pipeline_to_node("node-1", "default", "test", 8080)
+            .unwrap();
+        controller
+            .evict_replica("node-1", "default", "test")
+            .unwrap();
+
+        let schedule = controller.schedule_replicas(&pipeline).unwrap();
+        assert_eq!(schedule.keys().collect::<Vec<_>>(), vec!["node-2"]);
+
+        // A heartbeat still listing the evicted replica doesn't bring it back
+        let mut status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        status.pipelines.push(NodePipelineInfo {
+            name: "test".to_string(),
+            namespace: "default".to_string(),
+            port: 8080,
+            status: ReplicaStatus::Running,
+            reason: None,
+            restarts: 0,
+        });
+        controller.update_node_status("node-1", status).unwrap();
+        let node = controller.get_node("node-1").unwrap();
+        assert!(node.status.unwrap().pipelines.is_empty());
+
+        controller.deploy_pipeline(pipeline).unwrap();
+        controller.delete_pipeline("default", "test").unwrap();
+        assert!(controller.evicted_nodes("default", "test").is_empty());
+    }
+
+    #[test]
+    fn test_set_replica_status() {
+        let controller = ClusterController::new();
+        controller
+            .register_node(create_test_node("node-1"))
+            .unwrap();
+        controller
+            .add_pipeline_to_node("node-1", "default", "test", 8080)
+            .unwrap();
+        controller
+            .set_replica_status("node-1", "default", "test", ReplicaStatus::Unhealthy)
+            .unwrap();
+
+        let node = controller.get_node("node-1").unwrap();
+        assert_eq!(
+            node.status.unwrap().pipelines[0].status,
+            ReplicaStatus::Unhealthy
+        );
+    }
+
+    #[test]
+    fn test_composition_versioning() {
+        let controller = ClusterController::new();
+        let deployed = controller
+            .deploy_pipeline(Pipeline::new("test", create_test_composition()))
+            .unwrap();
+        let v1 = deployed.composition_hash();
+        assert_eq!(deployed.stored_composition_hash(), Some(v1.as_str()));
+        let status = deployed.status.clone().unwrap();
+        assert_eq!(status.composition_hash.as_deref(), Some(v1.as_str()));
+
+        // Updates may change anything but the composition
+        let mut changed = deployed.clone();
+        changed.spec.composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api", "output-to": ["output"]},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            controller.update_pipeline(changed.clone()),
+            Err(ControllerError::CompositionChanged(..))
+        ));
+        assert!(controller
+            .update_pipeline(deployed.clone().with_replicas(2))
+            .is_ok());
+
+        // Applying does, and the old status no longer fits
+        let (applied, _) = controller.apply_pipeline(changed).unwrap();
+        let v2 = applied.composition_hash();
+        assert_ne!(v1, v2);
+        assert_eq!(applied.stored_composition_hash(), Some(v2.as_str()));
+        assert!(matches!(
+            controller.update_pipeline_status("default", "test", status),
+            Err(ControllerError::StaleStatus(_, _, hash)) if hash == v1
+        ));
+
+        // A status naming no composition describes the current one
+        controller
+            .update_pipeline_status("default", "test", PipelineStatus::initial())
+            .unwrap();
+        let stored = controller.get_pipeline("default", "test").unwrap();
+        assert_eq!(stored.status.unwrap().composition_hash, Some(v2));
+    }
+
+    #[test]
+    fn test_roll_up_replicas() {
+        let controller = ClusterController::new();
+        let pipeline = Pipeline::new("test", create_test_composition()).with_replicas(2);
+        controller.deploy_pipeline(pipeline).unwrap();
+        for (node, status, reason) in [
+            ("node-1", ReplicaStatus::Running, None),
+            (
+                "node-2",
+                ReplicaStatus::Failed,
+                Some("runner failed to start"),
+            ),
+        ] {
+            controller.register_node(create_test_node(node)).unwrap();
+            let mut node_status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+            node_status.pipelines.push(NodePipelineInfo {
+                name: "test".to_string(),
+                namespace: "default".to_string(),
+                port: 8080,
+                status,
+                reason: reason.map(String::from),
+                restarts: 2,
+            });
+            controller.update_node_status(node, node_status).unwrap();
+        }
+
+        let status = controller
+            .roll_up_replicas("default", "test", &HashMap::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.ready_replicas, 1);
+        assert_eq!(status.available_replicas, 1);
+        assert_eq!(status.replica_statuses[1].node, "node-2");
+        assert_eq!(status.replica_statuses[1].restarts, 2);
+        let failure = status
+            .conditions
+            .iter()
+            .find(|c| c.condition_type == "ReplicaFailure")
+            .unwrap();
+        assert_eq!(failure.message, "node-2:8080: runner failed to start");
+
+        // Nothing changed, nothing to update
+        assert!(controller
+            .roll_up_replicas("default", "test", &HashMap::new())
+            .unwrap()
+            .is_none());
+
+        // A failing probe makes a running replica unavailable, not unready
+        let probed = HashMap::from([(
+            "node-1:default:test:8080".to_string(),
+            ReplicaStatus::Unhealthy,
+        )]);
+        let status = controller
+            .roll_up_replicas("default", "test", &probed)
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.ready_replicas, 1);
+        assert_eq!(status.available_replicas, 0);
+        let stored = controller.get_pipeline("default", "test").unwrap().status;
+        assert_eq!(stored.unwrap().replica_statuses, status.replica_statuses);
+    }
+
+    #[test]
+    fn test_schedule_no_nodes() {
+        let controller = ClusterController::new();
+        let comp = create_test_composition();
+        let pipeline = Pipeline::new("test", comp);
 
-use serde::{Deserialize, Serialize};
+        let result = controller.schedule_replicas(&pipeline);
+        assert!(matches!(result, Err(ControllerError::NoAvailableNodes)));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::cluster::node::{NodeCapacity, NodeInfo};
-    use crate::config::Composition;
+    #[test]
+    fn test_schedule_respects_runner_capabilities() {
+        let controller = ClusterController::new();
+        controller
+            .register_node(
+                create_test_node("cpu-only")
+                    .with_capabilities(NodeCapabilities::new(vec!["llama-cpp".to_string()])),
+            )
+            .unwrap();
+        controller
+            .register_node(
+                create_test_node("gpu")
+                    .with_capabilities(NodeCapabilities::new(vec!["vllm".to_string()])),
+            )
+            .unwrap();
 
-    fn create_test_composition() -> Composition {
         let json = r#"{
-            "models": {},
+            "models": {"big": {"runner": "vllm", "interface": "openai-api", "source": "m"}},
             "architecture": [
-                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "router", "layer": 0, "model": "big", "adapter": "openai-api"},
                 {"name": "output", "adapter": "output"}
             ]
         }"#;
-        Composition::from_str(json).unwrap()
-    }
+        let pipeline = Pipeline::new("test", Composition::from_str(json).unwrap()).with_replicas(3);
 
-    fn create_test_node(name: &str) -> Node {
-        let mut node = Node::new(name, "localhost");
-        node.status = Some(NodeStatus::new(
-            NodeCapacity::default(),
-            NodeInfo::from_system(),
-        ));
-        node
+        let schedule = controller.schedule_replicas(&pipeline).unwrap();
+        assert_eq!(schedule.get("gpu"), Some(&3));
+        assert!(!schedule.contains_key("cpu-only"));
+
+        controller.unregister_node("gpu").unwrap();
+        let result = controller.schedule_replicas(&pipeline);
+        assert!(matches!(result, Err(ControllerError::NoAvailableNodes)));
     }
 
     #[test]
-    fn test_register_node() {
+    fn test_node_pool_scheduling() {
         let controller = ClusterController::new();
-        let node = create_test_node("node-1");
+        controller
+            .register_node(create_test_node("gpu-1").with_label("accelerator", "nvidia"))
+            .unwrap();
+        controller.register_node(create_test_node("cpu-1")).unwrap();
+        let pool = NodePool::new(
+            "gpu-pool",
+            HashMap::from([("accelerator".to_string(), "nvidia".to_string())]),
+        )
+        .with_max_pipelines(2)
+        .with_allowed_namespaces(vec!["default".to_string()]);
+        let applied = controller.apply_node_pool(pool).unwrap();
+        assert_eq!(applied.status.unwrap().nodes, ["gpu-1"]);
+
+        let mut pipeline = Pipeline::new("test", create_test_composition()).with_replicas(2);
+        pipeline.spec.node_pool = Some("gpu-pool".to_string());
+        let schedule = controller.schedule_replicas(&pipeline).unwrap();
+        assert_eq!(schedule, HashMap::from([("gpu-1".to_string(), 2)]));
 
-        controller.register_node(node.clone()).unwrap();
+        pipeline.spec.replicas = 3;
+        assert!(matches!(
+            controller.schedule_replicas(&pipeline),
+            Err(ControllerError::InsufficientCapacity(_))
+        ));
 
-        let retrieved = controller.get_node("node-1");
-        assert!(retrieved.is_some());
-        assert_eq!(retrieved.unwrap().metadata.name, "node-1");
+        let elsewhere = pipeline.clone().with_namespace("dev");
+        assert!(matches!(
+            controller.deploy_pipeline(elsewhere),
+            Err(ControllerError::NamespaceNotAllowed(..))
+        ));
+        let mut unknown = pipeline.clone();
+        unknown.spec.node_pool = Some("tpu-pool".to_string());
+        assert!(!controller.admission_problems(&unknown).is_empty());
+
+        // A pool can't be deleted while pipelines target it
+        pipeline.spec.replicas = 1;
+        controller.deploy_pipeline(pipeline).unwrap();
+        assert!(matches!(
+            controller.delete_node_pool("gpu-pool"),
+            Err(ControllerError::NodePoolInUse(_, 1))
+        ));
+        controller.delete_pipeline("default", "test").unwrap();
+        controller.delete_node_pool("gpu-pool").unwrap();
+        assert!(controller.list_node_pools().is_empty());
     }
 
     #[test]
-    fn test_register_duplicate_node() {
+    fn test_regional_pipelines() {
         let controller = ClusterController::new();
-        let node = create_test_node("node-1");
-
-        controller.register_node(node.clone()).unwrap();
-        let result = controller.register_node(node);
+        let regional = Pipeline::new("chat", create_test_composition())
+            .with_annotation(REGION_ANNOTATION, "eu-west");
+        assert!(matches!(
+            controller.deploy_pipeline(regional.clone()),
+            Err(ControllerError::RegionNotFound(_))
+        ));
 
-        assert!(matches!(result, Err(ControllerError::NodeExists(_))));
+        controller
+            .apply_region(Region::new("eu-west", "http://cp.eu:8181"))
+            .unwrap();
+        // No local nodes, but the region admits it
+        assert!(controller.admission_problems(&regional).is_empty());
+        controller.deploy_pipeline(regional).unwrap();
+        assert!(controller.list_local_pipelines().is_empty());
+
+        let remote = Pipeline::new("code", create_test_composition()).with_namespace("dev");
+        controller.set_region_pipelines("eu-west", vec![remote]);
+        let federated = controller.list_federated_pipelines();
+        assert_eq!(federated.len(), 2);
+        assert!(federated.iter().all(|p| p.region() == Some("eu-west")));
+
+        assert!(matches!(
+            controller.delete_region("eu-west"),
+            Err(ControllerError::RegionInUse(_, 1))
+        ));
+        // Kept until the region deleted it too
+        let (_, removed) = controller
+            .request_pipeline_deletion("default", "chat", false)
+            .unwrap();
+        assert!(!removed);
+        controller.delete_pipeline("default", "chat").unwrap();
+        controller.delete_region("eu-west").unwrap();
+        assert!(controller.list_federated_pipelines().is_empty());
     }
 
     #[test]
-    fn test_list_nodes() {
+    fn test_apply_node_status_delta() {
         let controller = ClusterController::new();
         controller
-            .register_node(create_test_node("node-1"))
+            .register_node(Node::new("node-1", "localhost"))
             .unwrap();
+
+        // No baseline status yet
+        let result = controller.apply_node_status_delta("node-1", NodeStatusDelta::default());
+        assert!(matches!(result, Err(ControllerError::ValidationError(_))));
+
         controller
-            .register_node(create_test_node("node-2"))
+            .update_node_status(
+                "node-1",
+                NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system()),
+            )
             .unwrap();
 
-        let nodes = controller.list_nodes();
-        assert_eq!(nodes.len(), 2);
+        let delta = NodeStatusDelta {
+            metrics: Some(crate::cluster::NodeMetrics::default()),
+            ..Default::default()
+        };
+        controller.apply_node_status_delta("node-1", delta).unwrap();
+
+        let status = controller.get_node("node-1").unwrap().status.unwrap();
+        assert!(status.metrics.is_some());
+        assert!(status.score.is_some());
+
+        let result = controller.apply_node_status_delta("missing", NodeStatusDelta::default());
+        assert!(matches!(result, Err(ControllerError::NodeNotFound(_))));
     }
 
     #[test]
-    fn test_deploy_pipeline() {
+    fn test_set_scoring_weights_rescores_nodes() {
         let controller = ClusterController::new();
-        let comp = create_test_composition();
-        let pipeline = Pipeline::new("my-pipeline", comp);
+        controller
+            .register_node(Node::new("node-1", "localhost"))
+            .unwrap();
+        let mut status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        status.metrics = Some(crate::cluster::NodeMetrics {
+            avg_latency_ms: 900.0,
+            ..Default::default()
+        });
+        controller.update_node_status("node-1", status).unwrap();
+        let score = |c: &ClusterController| {
+            c.get_node("node-1")
+                .and_then(|n| n.status)
+                .and_then(|s| s.score)
+                .unwrap()
+                .score
+        };
+        let before = score(&controller);
 
-        let deployed = controller.deploy_pipeline(pipeline).unwrap();
+        let invalid = ScoringWeights::default().with_latency(-1.0);
+        assert!(matches!(
+            controller.set_scoring_weights(invalid),
+            Err(ControllerError::ValidationError(_))
+        ));
+        assert_eq!(controller.scoring_weights(), ScoringWeights::default());
 
-        assert_eq!(deployed.metadata.name, "my-pipeline");
-        assert!(deployed.status.is_some());
+        controller
+            .set_scoring_weights(ScoringWeights::latency_sensitive())
+            .unwrap();
+        assert_eq!(
+            controller.scoring_weights(),
+            ScoringWeights::latency_sensitive()
+        );
+        assert!(score(&controller) < before);
     }
 
     #[test]
-    fn test_deploy_duplicate_pipeline() {
-        let controller = ClusterController::new();
-        let comp = create_test_composition();
-        let pipeline = Pipeline::new("my-pipeline", comp.clone());
+    fn test_evaluate_alerts() {
+        use crate::cluster::alerting::{AlertCondition, AlertRule, AlertSeverity, AlertState};
 
-        controller.deploy_pipeline(pipeline).unwrap();
+        let controller = ClusterController::new();
+        controller
+            .register_node(Node::new("node-1", "localhost"))
+            .unwrap();
+        assert!(controller.evaluate_alerts(Utc::now()).is_empty());
+
+        let rule = AlertRule {
+            name: "node-down".to_string(),
+            condition: AlertCondition::NodeNotReady,
+            for_secs: 0,
+            severity: AlertSeverity::Critical,
+            namespace: None,
+            threshold: None,
+            window_secs: 300,
+            min_requests: 10,
+        };
+        controller
+            .set_alerting_config(AlertingConfig {
+                rules: vec![rule],
+                sinks: vec![],
+            })
+            .unwrap();
 
-        let pipeline2 = Pipeline::new("my-pipeline", comp);
-        let result = controller.deploy_pipeline(pipeline2);
+        let fired = controller.evaluate_alerts(Utc::now());
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].status, "firing");
+        let alerts = controller.list_alerts();
+        assert_eq!(alerts[0].object, "node/node-1");
+        assert_eq!(alerts[0].state, AlertState::Firing);
 
-        assert!(matches!(result, Err(ControllerError::PipelineExists(_, _))));
+        controller
+            .update_node_status(
+                "node-1",
+                NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system()),
+            )
+            .unwrap();
+        let resolved = controller.evaluate_alerts(Utc::now());
+        assert_eq!(resolved[0].status, "resolved");
+        assert!(controller.list_alerts().is_empty());
+        let reasons: Vec<String> = controller
+            .list_events()
+            .into_iter()
+            .map(|e| e.reason)
+            .collect();
+        assert!(reasons.contains(&"AlertFiring".to_string()));
+        assert!(reasons.contains(&"AlertResolved".to_string()));
     }
 
     #[test]
-    fn test_get_pipeline() {
+    fn test_cordon_uncordon() {
         let controller = ClusterController::new();
-        let comp = create_test_composition();
-        let pipeline = Pipeline::new("test", comp).with_namespace("prod");
+        controller
+            .register_node(create_test_node("node-1"))
+            .unwrap();
 
-        controller.deploy_pipeline(pipeline).unwrap();
+        controller.cordon_node("node-1").unwrap();
+        let node = controller.get_node("node-1").unwrap();
+        assert!(!node.spec.schedulable);
 
-        let retrieved = controller.get_pipeline("prod", "test");
-        assert!(retrieved.is_some());
+        controller.uncordon_node("node-1").unwrap();
+        let node = controller.get_node("node-1").unwrap();
+        assert!(node.spec.schedulable);
     }
 
     #[test]
-    fn test_delete_pipeline() {
+    fn test_maintenance_window_cordons_drains_and_uncordons() {
+        use crate::cluster::maintenance::MaintenanceWindow;
+        use chrono::TimeZone;
+
         let controller = ClusterController::new();
-        let comp = create_test_composition();
-        let pipeline = Pipeline::new("test", comp);
+        controller
+            .register_node(
+                create_test_node("gpu-1")
+                    .with_maintenance_window(MaintenanceWindow::daily("02:00", 60)),
+            )
+            .unwrap();
+        controller
+            .register_node(create_test_node("gpu-2").cordon())
+            .unwrap();
+        controller
+            .set_maintenance_windows("gpu-2", vec![MaintenanceWindow::daily("02:00", 60)])
+            .unwrap();
+        assert!(matches!(
+            controller.set_maintenance_windows("gpu-1", vec![MaintenanceWindow::daily("2am", 60)]),
+            Err(ControllerError::ValidationError(_))
+        ));
 
-        controller.deploy_pipeline(pipeline).unwrap();
+        controller
+            .deploy_pipeline(Pipeline::new("chatbot", create_test_composition()))
+            .unwrap();
+        let mut status = PipelineStatus::initial();
+        status.replicas = 1;
+        status.ready_replicas = 1;
+        controller
+            .update_pipeline_status("default", "chatbot", status)
+            .unwrap();
+        controller
+            .add_pipeline_to_node("gpu-1", "default", "chatbot", 8080)
+            .unwrap();
 
-        let deleted = controller.delete_pipeline("default", "test");
-        assert!(deleted.is_ok());
+        let at = |h, m| Utc.with_ymd_and_hms(2026, 3, 7, h, m, 0).unwrap();
+        controller.reconcile_maintenance(at(1, 0));
+        assert!(controller.get_node("gpu-1").unwrap().spec.schedulable);
 
-        let retrieved = controller.get_pipeline("default", "test");
-        assert!(retrieved.is_none());
+        // Drain period before the window
+        controller.reconcile_maintenance(at(1, 50));
+        let node = controller.get_node("gpu-1").unwrap();
+        assert!(!node.spec.schedulable);
+        assert!(node.is_in_maintenance());
+        assert_eq!(node.pipeline_count(), 0);
+        let status = controller
+            .get_pipeline("default", "chatbot")
+            .unwrap()
+            .status;
+        assert_eq!(status.unwrap().replicas, 0);
+
+        // Heartbeats from the node don't bring the replica back
+        let mut heartbeat = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        heartbeat.pipelines.push(NodePipelineInfo {
+            name: "chatbot".to_string(),
+            namespace: "default".to_string(),
+            port: 8080,
+            status: ReplicaStatus::Running,
+            reason: None,
+            restarts: 0,
+        });
+        controller.update_node_status("gpu-1", heartbeat).unwrap();
+        assert_eq!(controller.get_node("gpu-1").unwrap().pipeline_count(), 0);
+
+        controller.reconcile_maintenance(at(3, 0));
+        let node = controller.get_node("gpu-1").unwrap();
+        assert!(node.spec.schedulable);
+        assert!(!node.is_in_maintenance());
+
+        // The manually cordoned node stays cordoned
+        let node = controller.get_node("gpu-2").unwrap();
+        assert!(!node.spec.schedulable);
+        assert!(!node.is_in_maintenance());
+
+        let reasons: Vec<String> = controller
+            .list_events()
+            .into_iter()
+            .map(|e| format!("{} {}", e.object, e.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                "node/gpu-1 MaintenanceCordoned",
+                "node/gpu-1 Drained",
+                "node/gpu-1 MaintenanceUncordoned"
+            ]
+        );
     }
 
     #[test]
-    fn test_scale_pipeline() {
+    fn test_lost_node_fails_over_and_recovers() {
         let controller = ClusterController::new();
-        let comp = create_test_composition();
-        let pipeline = Pipeline::new("test", comp);
+        controller
+            .register_node(create_test_node("node-1"))
+            .unwrap();
+        controller
+            .register_node(create_test_node("node-2"))
+            .unwrap();
+        controller
+            .deploy_pipeline(Pipeline::new("chatbot", create_test_composition()))
+            .unwrap();
+        let mut status = PipelineStatus::initial();
+        status.replicas = 1;
+        status.ready_replicas = 1;
+        controller
+            .update_pipeline_status("default", "chatbot", status)
+            .unwrap();
+        controller
+            .add_pipeline_to_node("node-1", "default", "chatbot", 8080)
+            .unwrap();
 
-        controller.deploy_pipeline(pipeline).unwrap();
+        // Silent, but still inside the grace period
+        let silent_for = |secs| {
+            let mut node = controller.nodes.get_mut("node-1").unwrap();
+            node.status.as_mut().unwrap().last_heartbeat =
+                Utc::now() - chrono::Duration::seconds(secs);
+        };
+        silent_for(120);
+        assert!(controller.fail_over_lost_nodes().is_empty());
 
-        let scaled = controller.scale_pipeline("default", "test", 5).unwrap();
-        assert_eq!(scaled.spec.replicas, 5);
+        silent_for(200);
+        assert_eq!(controller.fail_over_lost_nodes(), vec!["node-1"]);
+        let node = controller.get_node("node-1").unwrap();
+        assert_eq!(node.status.as_ref().unwrap().phase, NodePhase::Unknown);
+        assert_eq!(node.pipeline_count(), 0);
+        let status = controller
+            .get_pipeline("default", "chatbot")
+            .unwrap()
+            .status;
+        assert_eq!(status.unwrap().replicas, 0);
+        let pipeline = controller.get_pipeline("default", "chatbot").unwrap();
+        let schedule = controller.schedule_replicas(&pipeline).unwrap();
+        assert_eq!(schedule.keys().collect::<Vec<_>>(), vec!["node-2"]);
+
+        // Only failed over once
+        assert!(controller.fail_over_lost_nodes().is_empty());
+        assert!(controller.recovered_nodes().is_empty());
+
+        // The node comes back still running its old copy
+        let mut heartbeat = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        heartbeat.pipelines.push(NodePipelineInfo {
+            name: "chatbot".to_string(),
+            namespace: "default".to_string(),
+            port: 8080,
+            status: ReplicaStatus::Running,
+            reason: None,
+            restarts: 0,
+        });
+        controller
+            .update_node_status("node-1", heartbeat.clone())
+            .unwrap();
+        assert_eq!(controller.get_node("node-1").unwrap().pipeline_count(), 0);
+
+        let recovered = controller.recovered_nodes();
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].1[0].status, ReplicaStatus::Failed);
+
+        controller.finish_failover("node-1");
+        assert!(controller.recovered_nodes().is_empty());
+        controller.update_node_status("node-1", heartbeat).unwrap();
+        assert_eq!(controller.get_node("node-1").unwrap().pipeline_count(), 1);
+
+        let reasons: Vec<String> = controller
+            .list_events()
+            .into_iter()
+            .map(|e| e.reason)
+            .collect();
+        assert_eq!(reasons, ["NodeLost", "NodeRecovered"]);
     }
 
     #[test]
-    fn test_schedule_replicas() {
+    fn test_lost_node_keeps_replicas_on_surviving_nodes() {
         let controller = ClusterController::new();
+        for (name, address) in [
+            ("node-1", "10.0.0.1"),
+            ("node-2", "10.0.0.2"),
+            ("node-3", "10.0.0.3"),
+        ] {
+            let mut node = Node::new(name, address);
+            node.status = Some(NodeStatus::new(
+                NodeCapacity::default(),
+                NodeInfo::from_system(),
+            ));
+            controller.register_node(node).unwrap();
+        }
+        let mut pipeline = Pipeline::new("chatbot", create_test_composition());
+        pipeline.spec.replicas = 2;
+        controller.deploy_pipeline(pipeline).unwrap();
+        let mut status = PipelineStatus::initial();
+        status.replicas = 2;
+        status.ready_replicas = 2;
+        status.endpoints = vec![
+            "http://10.0.0.1:8080".to_string(),
+            "http://10.0.0.2:8080".to_string(),
+        ];
+        controller
+            .update_pipeline_status("default", "chatbot", status)
+            .unwrap();
+        for node in ["node-1", "node-2"] {
+            controller
+                .add_pipeline_to_node(node, "default", "chatbot", 8080)
+                .unwrap();
+        }
 
-        // Register some nodes
         controller
-            .register_node(create_test_node("node-1"))
+            .nodes
+            .get_mut("node-1")
+            .unwrap()
+            .status
+            .as_mut()
+            .unwrap()
+            .last_heartbeat = Utc::now() - chrono::Duration::seconds(200);
+        assert_eq!(controller.fail_over_lost_nodes(), vec!["node-1"]);
+
+        // node-2's replica stays, and only node-1's is missing
+        assert_eq!(controller.get_node("node-2").unwrap().pipeline_count(), 1);
+        let status = controller
+            .get_pipeline("default", "chatbot")
+            .unwrap()
+            .status
             .unwrap();
+        assert_eq!(status.replicas, 1);
+        assert_eq!(status.ready_replicas, 2);
+        assert_eq!(status.endpoints, ["http://10.0.0.2:8080"]);
+
+        // The replacement goes next to it, not onto it
+        let hosting = controller.hosting_nodes("default", "chatbot");
+        assert_eq!(hosting, HashSet::from(["node-2".to_string()]));
+        let mut shortfall = controller.get_pipeline("default", "chatbot").unwrap();
+        shortfall.spec.replicas = 1;
+        let schedule = controller
+            .schedule_replicas_excluding(&shortfall, &hosting)
+            .unwrap();
+        assert_eq!(schedule.keys().collect::<Vec<_>>(), vec!["node-3"]);
+    }
+
+    #[tokio::test]
+    async fn test_node_heartbeat_interval_extends_timeout() {
+        let controller = ClusterController::new();
+        controller.register_node(create_test_node("fast")).unwrap();
         controller
-            .register_node(create_test_node("node-2"))
+            .register_node(create_test_node("slow").with_heartbeat_interval(120))
             .unwrap();
+        for name in ["fast", "slow"] {
+            let mut node = controller.nodes.get_mut(name).unwrap();
+            node.status.as_mut().unwrap().last_heartbeat =
+                Utc::now() - chrono::Duration::seconds(200);
+        }
 
-        let comp = create_test_composition();
-        let pipeline = Pipeline::new("test", comp).with_replicas(4);
+        controller.check_node_health().await;
+        let phase = |name: &str| controller.get_node(name).unwrap().status.unwrap().phase;
+        assert_eq!(phase("fast"), NodePhase::Unknown);
+        assert_eq!(phase("slow"), NodePhase::Ready);
+    }
 
-        let schedule = controller.schedule_replicas(&pipeline).unwrap();
+    #[test]
+    fn test_job_lifecycle() {
+        let controller = ClusterController::new();
+        let job = Job::new("batch", "chat", vec!["a".into(), "b".into()]);
+        assert!(matches!(
+            controller.create_job(job.clone()),
+            Err(ControllerError::PipelineNotFound(..))
+        ));
 
-        // Should distribute across 2 nodes
-        assert_eq!(schedule.values().sum::<u32>(), 4);
-        assert!(schedule.len() <= 2);
+        controller
+            .deploy_pipeline(Pipeline::new("chat", create_test_composition()))
+            .unwrap();
+        controller.create_job(job.clone()).unwrap();
+        assert!(matches!(
+            controller.create_job(job),
+            Err(ControllerError::JobExists(..))
+        ));
+
+        controller
+            .start_job("default", "batch", "http://w1:8080")
+            .unwrap();
+        assert!(controller.set_job_total("default", "batch", 2));
+        let result = |index: usize, error: Option<&str>| JobResult {
+            index,
+            prompt: "p".into(),
+            output: error.is_none().then(|| "ok".to_string()),
+            error: error.map(String::from),
+        };
+        assert!(controller.record_job_result("default", "batch", result(1, Some("timeout"))));
+        assert!(controller.record_job_result("default", "batch", result(0, None)));
+        controller.finish_job("default", "batch");
+
+        let status = controller
+            .get_job("default", "batch")
+            .unwrap()
+            .status
+            .unwrap();
+        assert_eq!(status.phase, JobPhase::Failed);
+        assert_eq!((status.completed, status.failed), (1, 1));
+        let indexes: Vec<usize> = controller
+            .job_results("default", "batch")
+            .unwrap()
+            .iter()
+            .map(|r| r.index)
+            .collect();
+        assert_eq!(indexes, [0, 1]);
+        assert_eq!(controller.list_events()[0].reason, "JobFailed");
+
+        // A deleted job tells its runner to stop
+        controller.delete_job("default", "batch").unwrap();
+        assert!(!controller.record_job_result("default", "batch", result(2, None)));
+        assert!(controller.job_results("default", "batch").is_none());
     }
 
     #[test]
-    fn test_schedule_no_nodes() {
+    fn test_secrets_are_sealed() {
+        use crate::cluster::{Secret, SecretEnvRef};
+        use std::collections::BTreeMap;
+
         let controller = ClusterController::new();
-        let comp = create_test_composition();
-        let pipeline = Pipeline::new("test", comp);
+        let values = BTreeMap::from([("key".to_string(), "sk-123".to_string())]);
+        let created = controller
+            .create_secret(Secret::new("openai", values.clone()).with_namespace("prod"))
+            .unwrap();
+        assert!(created.string_data.is_empty());
+        assert_ne!(created.data["key"], "sk-123");
+        assert_eq!(controller.list_secrets("prod").len(), 1);
+        assert!(controller.list_secrets("default").is_empty());
+
+        assert_eq!(controller.secret_values("prod", "openai").unwrap(), values);
+        assert!(matches!(
+            controller.create_secret(Secret::new("openai", values.clone()).with_namespace("prod")),
+            Err(ControllerError::SecretExists(..))
+        ));
+        assert!(matches!(
+            controller.create_secret(Secret::new("empty", BTreeMap::new())),
+            Err(ControllerError::ValidationError(_))
+        ));
 
-        let result = controller.schedule_replicas(&pipeline);
-        assert!(matches!(result, Err(ControllerError::NoAvailableNodes)));
+        // Only a grant for a pipeline that references the secret reads it
+        let pipeline = Pipeline::new("bot", create_test_composition())
+            .with_namespace("prod")
+            .with_secret_ref(SecretEnvRef {
+                name: "OPENAI_API_KEY".to_string(),
+                secret: "openai".to_string(),
+                key: Some("key".to_string()),
+            });
+        controller.deploy_pipeline(pipeline).unwrap();
+        let grant = controller.secret_grant("prod", "bot");
+        assert_eq!(
+            controller
+                .granted_secret_values(&grant, "prod", "openai")
+                .unwrap(),
+            values
+        );
+        for (grant, namespace) in [
+            (controller.secret_grant("prod", "other"), "prod"),
+            (controller.secret_grant("default", "bot"), "prod"),
+            (grant.clone(), "default"),
+            ("prod/bot".to_string(), "prod"),
+        ] {
+            assert!(matches!(
+                controller.granted_secret_values(&grant, namespace, "openai"),
+                Err(ControllerError::SecretAccessDenied(..))
+            ));
+        }
+
+        // Deleting the pipeline revokes its grants, even once it's back
+        let pipeline = controller.delete_pipeline("prod", "bot").unwrap();
+        controller.deploy_pipeline(pipeline).unwrap();
+        assert!(matches!(
+            controller.granted_secret_values(&grant, "prod", "openai"),
+            Err(ControllerError::SecretAccessDenied(..))
+        ));
+        let regranted = controller.secret_grant("prod", "bot");
+        assert_eq!(
+            controller
+                .granted_secret_values(&regranted, "prod", "openai")
+                .unwrap(),
+            values
+        );
+
+        // Values sealed by another control plane don't open here
+        let other = ClusterController::new().with_master_key(MasterKey::generate());
+        assert!(matches!(
+            other.granted_secret_values(&grant, "prod", "openai"),
+            Err(ControllerError::SecretAccessDenied(..))
+        ));
+        assert!(matches!(
+            other.create_secret(created),
+            Err(ControllerError::SecretStore(_))
+        ));
+
+        controller.delete_secret("prod", "openai").unwrap();
+        assert!(matches!(
+            controller.secret_values("prod", "openai"),
+            Err(ControllerError::SecretNotFound(..))
+        ));
     }
 
     #[test]
-    fn test_cordon_uncordon() {
+    fn test_virtual_endpoint_keeps_traffic_of_listed_backends() {
+        use crate::cluster::virtual_endpoint::EndpointBackend;
+
         let controller = ClusterController::new();
-        controller
-            .register_node(create_test_node("node-1"))
+        let endpoint = VirtualEndpoint::new(
+            "chat",
+            vec![
+                EndpointBackend::new("v1", 90),
+                EndpointBackend::new("v2", 10),
+            ],
+        );
+        controller.apply_virtual_endpoint(endpoint).unwrap();
+        controller.record_variant_traffic("default", "chat", "v1", true, 40);
+        controller.record_variant_traffic("default", "chat", "v2", false, 100);
+        controller.record_variant_traffic("default", "chat", "v2", true, 20);
+
+        let status = controller
+            .get_virtual_endpoint("default", "chat")
+            .unwrap()
+            .status
             .unwrap();
+        assert_eq!(status.variants["v2"].requests, 2);
+        assert_eq!(status.variants["v2"].errors, 1);
+        assert_eq!(status.variants["v2"].mean_latency_ms(), 60.0);
 
-        controller.cordon_node("node-1").unwrap();
-        let node = controller.get_node("node-1").unwrap();
-        assert!(!node.spec.schedulable);
+        // Promoting v2 drops v1's numbers and keeps v2's
+        let promoted = VirtualEndpoint::new("chat", vec![EndpointBackend::new("v2", 100)]);
+        let applied = controller.apply_virtual_endpoint(promoted).unwrap();
+        let variants = applied.status.unwrap().variants;
+        assert_eq!(variants.keys().collect::<Vec<_>>(), ["v2"]);
+        assert_eq!(variants["v2"].requests, 2);
 
-        controller.uncordon_node("node-1").unwrap();
-        let node = controller.get_node("node-1").unwrap();
-        assert!(node.spec.schedulable);
+        controller
+            .delete_virtual_endpoint("default", "chat")
+            .unwrap();
+        assert!(matches!(
+            controller.delete_virtual_endpoint("default", "chat"),
+            Err(ControllerError::VirtualEndpointNotFound(..))
+        ));
     }
 
     #[test]
@@ -808,4 +3605,38 @@ mod tests {
 
         assert!(namespaces.iter().any(|ns| ns.metadata.name == "default"));
     }
+
+    #[test]
+    fn test_budget_spend_totals_across_workers() {
+        let controller = ClusterController::new();
+        let spend = |model: &str, month: &str, tokens| BudgetSpend {
+            namespace: "team-a".to_string(),
+            model: model.to_string(),
+            month: month.to_string(),
+            tokens,
+            dollars: tokens as f64,
+        };
+
+        // Each worker gets back what the whole cluster spent
+        assert_eq!(
+            controller.record_budget_spend(vec![spend("gpt", "2026-10", 300)]),
+            [spend("gpt", "2026-10", 300)]
+        );
+        assert_eq!(
+            controller.record_budget_spend(vec![
+                spend("gpt", "2026-10", 200),
+                spend("claude", "2026-10", 0)
+            ]),
+            [spend("gpt", "2026-10", 500), spend("claude", "2026-10", 0)]
+        );
+
+        // A new month starts over, and late spend for the old one is dropped
+        assert_eq!(
+            controller.record_budget_spend(vec![spend("gpt", "2026-11", 10)]),
+            [spend("gpt", "2026-11", 10)]
+        );
+        assert!(controller
+            .record_budget_spend(vec![spend("gpt", "2026-10", 100)])
+            .is_empty());
+    }
 }
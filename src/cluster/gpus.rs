@@ -0,0 +1,251 @@
+//! Pinning models to the GPUs of multi-GPU workers
+//!
+//! When a pipeline is scheduled to a worker with more than one GPU, the
+//! control plane gives each of its local models its own devices and tracks
+//! which devices it handed out, so the next model placed on the worker goes
+//! to the GPUs with the fewest models and the least memory in use. The
+//! worker then starts the model's runners on those devices only, through
+//! `CUDA_VISIBLE_DEVICES` or Docker's `--gpus device=...`.
+//!
+//! A model takes one GPU, the `tensor_parallel_size` it asks for, or the
+//! number of GPUs its Docker `gpus` setting names. Models pinned to device
+//! IDs by hand, given `gpus: all`, or run by an external API aren't pinned.
+//! When every device already has a model, the least busy ones are shared.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::node::{GpuMetrics, Node};
+use super::pipeline::Pipeline;
+use crate::config::models::ModelConfig;
+use crate::config::RunnerType;
+
+/// Environment variable runners read the devices they may use from
+pub const CUDA_VISIBLE_DEVICES: &str = "CUDA_VISIBLE_DEVICES";
+
+/// GPUs a model of a pipeline was pinned to on a node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GpuAllocation {
+    pub namespace: String,
+    pub pipeline: String,
+    pub model: String,
+    /// Device indexes, as in `CUDA_VISIBLE_DEVICES`
+    pub devices: Vec<u32>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// How many GPUs a model's runners should be pinned to, or None if the
+/// model isn't pinned
+pub fn model_gpu_count(config: &ModelConfig) -> Option<u32> {
+    match config.runner {
+        RunnerType::External => None,
+        RunnerType::Docker => {
+            let gpus = config.docker.as_ref()?.gpus.as_deref()?.trim();
+            gpus.parse::<u32>().ok().filter(|&n| n > 0)
+        }
+        _ => Some(
+            config
+                .parameters
+                .get("tensor_parallel_size")
+                .and_then(|v| v.as_u64())
+                .map_or(1, |n| n.max(1) as u32),
+        ),
+    }
+}
+
+/// GPUs a node has, from its capacity or else the devices it reports
+/// metrics for
+pub fn node_gpu_count(node: &Node) -> u32 {
+    let Some(status) = &node.status else {
+        return 0;
+    };
+    match status.capacity.gpu {
+        0 => status
+            .metrics
+            .as_ref()
+            .and_then(|m| m.gpus.iter().map(|g| g.index + 1).max())
+            .unwrap_or(0),
+        gpus => gpus,
+    }
+}
+
+/// The `count` least busy of a node's `device_count` GPUs: those with the
+/// fewest models allocated, then the least memory in use
+pub fn pick_gpus(
+    device_count: u32,
+    metrics: &[GpuMetrics],
+    allocated: &[GpuAllocation],
+    count: u32,
+) -> Vec<u32> {
+    let models_on = |device: u32| {
+        allocated
+            .iter()
+            .filter(|a| a.devices.contains(&device))
+            .count()
+    };
+    let memory_used = |device: u32| {
+        metrics
+            .iter()
+            .find(|g| g.index == device)
+            .map_or(0, |g| g.memory_used)
+    };
+
+    let mut devices: Vec<u32> = (0..device_count).collect();
+    devices.sort_by_key(|&d| (models_on(d), memory_used(d), d));
+    devices.truncate(count as usize);
+    devices.sort_unstable();
+    devices
+}
+
+/// GPUs to pin each of a pipeline's models to on a node, given the
+/// allocations of the other pipelines there
+///
+/// Empty unless the node has more than one GPU.
+pub fn plan_gpu_allocations(
+    pipeline: &Pipeline,
+    node: &Node,
+    allocated: &[GpuAllocation],
+) -> Vec<GpuAllocation> {
+    let device_count = node_gpu_count(node);
+    if device_count < 2 {
+        return Vec::new();
+    }
+    let metrics = node
+        .status
+        .as_ref()
+        .and_then(|s| s.metrics.as_ref())
+        .map(|m| m.gpus.as_slice())
+        .unwrap_or_default();
+
+    let mut models: Vec<_> = pipeline.spec.composition.models.iter().collect();
+    models.sort_by_key(|(name, _)| name.as_str());
+
+    let mut taken = allocated.to_vec();
+    let mut planned = Vec::new();
+    for (name, model) in models {
+        let Some(count) = model_gpu_count(&model.to_config()) else {
+            continue;
+        };
+        let allocation = GpuAllocation {
+            namespace: pipeline.metadata.namespace.clone(),
+            pipeline: pipeline.metadata.name.clone(),
+            model: name.clone(),
+            devices: pick_gpus(device_count, metrics, &taken, count),
+        };
+        taken.push(allocation.clone());
+        planned.push(allocation);
+    }
+    planned
+}
+
+/// The devices of each model, as sent to the worker
+pub fn devices_by_model(allocations: &[GpuAllocation]) -> BTreeMap<String, Vec<u32>> {
+    allocations
+        .iter()
+        .map(|a| (a.model.clone(), a.devices.clone()))
+        .collect()
+}
+
+/// Restrict a model's runners to `devices`
+///
+/// Docker runners get the devices as their `--gpus`, the others through
+/// `CUDA_VISIBLE_DEVICES`.
+pub fn pin_to_gpus(config: &mut ModelConfig, devices: &[u32]) {
+    if devices.is_empty() {
+        return;
+    }
+    let list = devices
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    match config.docker.as_mut() {
+        Some(docker) if config.runner == RunnerType::Docker => docker.gpus = Some(list),
+        _ => {
+            config.env.insert(CUDA_VISIBLE_DEVICES.to_string(), list);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{NodeCapacity, NodeInfo, NodeMetrics, NodeStatus};
+    use crate::config::Composition;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn gpu_node(gpus: &[u64]) -> Node {
+        let metrics = NodeMetrics {
+            gpus: gpus
+                .iter()
+                .enumerate()
+                .map(|(index, &used)| GpuMetrics {
+                    index: index as u32,
+                    memory_used: used * GIB,
+                    memory_total: 80 * GIB,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut node = Node::new("gpu-1", "10.0.0.5");
+        node.status = Some(
+            NodeStatus::new(
+                NodeCapacity::with_gpu(gpus.len() as u32, 80),
+                NodeInfo::from_system(),
+            )
+            .with_metrics(metrics),
+        );
+        node
+    }
+
+    #[test]
+    fn test_plan_gpu_allocations() {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {
+                    "chat": {"runner": "vllm", "interface": "openai-api",
+                             "source": "meta-llama/Llama-3.1-70B",
+                             "parameters": {"tensor_parallel_size": 2}},
+                    "embed": {"runner": "ollama", "interface": "openai-api",
+                              "source": "nomic-embed-text"},
+                    "api": {"type": "external", "interface": "openai-api",
+                            "url": "https://api.openai.com/v1"}
+                },
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "chat", "adapter": "openai-api"},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let pipeline = Pipeline::new("bot", composition);
+
+        // Device 0 already runs another model, device 3 is the fullest
+        let node = gpu_node(&[10, 5, 0, 40]);
+        let other = GpuAllocation {
+            namespace: "default".to_string(),
+            pipeline: "other".to_string(),
+            model: "llm".to_string(),
+            devices: vec![0],
+        };
+        let planned = plan_gpu_allocations(&pipeline, &node, &[other]);
+        let devices = devices_by_model(&planned);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices["chat"], vec![1, 2]);
+        assert_eq!(devices["embed"], vec![3]);
+
+        // A single GPU isn't worth pinning to
+        assert!(plan_gpu_allocations(&pipeline, &gpu_node(&[0]), &[]).is_empty());
+
+        let mut config = pipeline.spec.composition.models["chat"].to_config();
+        pin_to_gpus(&mut config, &devices["chat"]);
+        assert_eq!(config.env[CUDA_VISIBLE_DEVICES], "1,2");
+    }
+}
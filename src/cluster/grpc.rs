@@ -0,0 +1,712 @@
+//! gRPC Control Plane API
+//!
+//! A tonic service mirroring the REST endpoints in [`super::api`], for
+//! integrators who want generated, typed clients. It shares the same
+//! [`ClusterController`], so pipelines deployed over either API are visible
+//! to both. `WatchPipelines` streams changes instead of requiring clients to
+//! poll `GET /v1/pipelines`. Requests carry API keys in `authorization`
+//! metadata and reach the same namespaces they would over REST. Calls that
+//! change pipelines go to the same audit log as REST requests.
+//!
+//! The service definition lives in `proto/llmnet.proto`.
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tonic::{Request, Response, Status};
+
+use super::admission_webhook::{AdmissionOperation, AdmissionWebhooks, WebhookError};
+use super::api::ControlPlaneState;
+use super::audit::{audit_actor, AuditLog};
+use super::auth::{Access, ApiKeys, AuthError, Caller};
+use super::controller::{ClusterController, ControllerError, PipelineWatchEvent};
+use super::node::Node;
+use super::pipeline::Pipeline;
+
+/// Types and client/server stubs generated from `proto/llmnet.proto`
+pub mod proto {
+    tonic::include_proto!("llmnet.v1");
+}
+
+use proto::control_plane_server::{ControlPlane, ControlPlaneServer};
+
+/// Default port for the gRPC API
+pub const GRPC_PORT: u16 = 8182;
+
+/// Path the service's methods are called under
+const SERVICE_PATH: &str = "/llmnet.v1.ControlPlane";
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Convert a pipeline to its gRPC representation
+pub fn pipeline_to_proto(pipeline: &Pipeline) -> proto::Pipeline {
+    proto::Pipeline {
+        name: pipeline.metadata.name.clone(),
+        namespace: pipeline.metadata.namespace.clone(),
+        replicas: pipeline.spec.replicas,
+        ready_replicas: pipeline
+            .status
+            .as_ref()
+            .map(|s| s.ready_replicas)
+            .unwrap_or(0),
+        labels: pipeline.metadata.labels.clone(),
+        manifest_json: serde_json::to_string(pipeline).unwrap_or_default(),
+    }
+}
+
+/// Convert a node to its gRPC representation
+pub fn node_to_proto(node: &Node) -> proto::Node {
+    let status = node.status.as_ref();
+    proto::Node {
+        name: node.metadata.name.clone(),
+        address: node.spec.address.clone(),
+        port: node.spec.port as u32,
+        schedulable: node.spec.schedulable,
+        phase: status
+            .map(|s| format!("{:?}", s.phase))
+            .unwrap_or_else(|| "Unknown".to_string()),
+        labels: node.metadata.labels.clone(),
+        pipelines: status.map(|s| s.pipelines.len() as u32).unwrap_or(0),
+    }
+}
+
+/// Convert a controller change to a watch event
+pub fn event_to_proto(event: &PipelineWatchEvent) -> proto::PipelineEvent {
+    let event_type = match event {
+        PipelineWatchEvent::Added(_) => proto::EventType::Added,
+        PipelineWatchEvent::Modified(_) => proto::EventType::Modified,
+        PipelineWatchEvent::Deleted(_) => proto::EventType::Deleted,
+    };
+    proto::PipelineEvent {
+        r#type: event_type as i32,
+        pipeline: Some(pipeline_to_proto(event.pipeline())),
+    }
+}
+
+/// Map a controller error to the closest gRPC status
+pub fn controller_status(error: ControllerError) -> Status {
+    let message = error.to_string();
+    match error {
+        ControllerError::PipelineNotFound(..)
+        | ControllerError::JobNotFound(..)
+        | ControllerError::VirtualEndpointNotFound(..)
+        | ControllerError::SecretNotFound(..)
+        | ControllerError::NodeNotFound(_)
+        | ControllerError::NodePoolNotFound(_)
+        | ControllerError::RegionNotFound(_)
+        | ControllerError::NamespaceNotFound(_) => Status::not_found(message),
+        ControllerError::NamespaceNotAllowed(..) => Status::permission_denied(message),
+        ControllerError::PipelineExists(..)
+        | ControllerError::JobExists(..)
+        | ControllerError::SecretExists(..)
+        | ControllerError::NodeExists(_) => Status::already_exists(message),
+        ControllerError::ValidationError(_) | ControllerError::SecretStore(_) => {
+            Status::invalid_argument(message)
+        }
+        ControllerError::NoAvailableNodes
+        | ControllerError::InsufficientCapacity(_)
+        | ControllerError::DeletionProtected(..)
+        | ControllerError::PipelineTerminating(..)
+        | ControllerError::CompositionChanged(..)
+        | ControllerError::StaleStatus(..)
+        | ControllerError::NodePoolInUse(..)
+        | ControllerError::RegionInUse(..) => Status::failed_precondition(message),
+        ControllerError::SecretAccessDenied(..) => Status::permission_denied(message),
+        ControllerError::InternalError(_) => Status::internal(message),
+    }
+}
+
+/// Map an admission webhook's rejection to a gRPC status
+pub fn webhook_status(error: WebhookError) -> Status {
+    let message = error.to_string();
+    match error {
+        WebhookError::Denied { .. } => Status::permission_denied(message),
+        WebhookError::Failed { .. } => Status::unavailable(message),
+    }
+}
+
+/// Map a refused API key to a gRPC status
+pub fn auth_status(error: AuthError) -> Status {
+    let message = error.to_string();
+    match error {
+        AuthError::MissingKey | AuthError::UnknownKey => Status::unauthenticated(message),
+        _ => Status::permission_denied(message),
+    }
+}
+
+/// The HTTP status a REST request failing like this would get, for the
+/// audit log
+pub fn http_status(code: tonic::Code) -> u16 {
+    match code {
+        tonic::Code::Ok => 200,
+        tonic::Code::InvalidArgument | tonic::Code::OutOfRange => 400,
+        tonic::Code::Unauthenticated => 401,
+        tonic::Code::PermissionDenied => 403,
+        tonic::Code::NotFound => 404,
+        tonic::Code::AlreadyExists | tonic::Code::Aborted => 409,
+        tonic::Code::FailedPrecondition => 412,
+        tonic::Code::ResourceExhausted => 429,
+        tonic::Code::Cancelled => 499,
+        tonic::Code::Unimplemented => 501,
+        tonic::Code::Unavailable => 503,
+        tonic::Code::DeadlineExceeded => 504,
+        _ => 500,
+    }
+}
+
+/// Empty namespaces in requests mean "default", as in the REST API
+fn namespace_or_default(namespace: &str) -> &str {
+    if namespace.is_empty() {
+        "default"
+    } else {
+        namespace
+    }
+}
+
+// ============================================================================
+// SBIO: I/O implementations
+// ============================================================================
+
+/// gRPC service backed by the control plane's controller
+#[derive(Clone)]
+pub struct ControlPlaneGrpc {
+    controller: Arc<ClusterController>,
+    admission_webhooks: Arc<AdmissionWebhooks>,
+    api_keys: Arc<ApiKeys>,
+    audit: Arc<AuditLog>,
+}
+
+/// A call that changes the cluster, recorded in the audit log once answered
+struct AuditedCall {
+    method: &'static str,
+    authorization: Option<String>,
+    caller: Option<Caller>,
+    resource: Option<String>,
+}
+
+impl AuditedCall {
+    fn new<T>(request: &Request<T>, method: &'static str) -> Self {
+        Self {
+            method,
+            authorization: request
+                .metadata()
+                .get("authorization")
+                .and_then(|v| v.to_str().ok())
+                .map(String::from),
+            caller: None,
+            resource: None,
+        }
+    }
+}
+
+impl ControlPlaneGrpc {
+    pub fn new(state: &ControlPlaneState) -> Self {
+        Self {
+            controller: state.controller.clone(),
+            admission_webhooks: state.admission_webhooks.clone(),
+            api_keys: state.api_keys.clone(),
+            audit: state.audit.clone(),
+        }
+    }
+
+    /// Who sent a request, checked against what it needs to reach
+    // tonic's handlers return `Status`; the size is not ours to change
+    #[allow(clippy::result_large_err)]
+    fn authorize<T>(&self, request: &Request<T>, access: Access) -> Result<Caller, Status> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok());
+        let caller = self
+            .api_keys
+            .authenticate(authorization)
+            .map_err(auth_status)?;
+        caller.authorize(&access).map_err(auth_status)?;
+        Ok(caller)
+    }
+
+    /// Who made an audited call, checked against what it needs to reach
+    // tonic's handlers return `Status`; the size is not ours to change
+    #[allow(clippy::result_large_err)]
+    fn authorize_call(&self, call: &mut AuditedCall, access: Access) -> Result<Caller, Status> {
+        let caller = self
+            .api_keys
+            .authenticate(call.authorization.as_deref())
+            .map_err(auth_status)?;
+        call.caller = Some(caller.clone());
+        caller.authorize(&access).map_err(auth_status)?;
+        Ok(caller)
+    }
+
+    /// Record an audited call with how it was answered
+    async fn record<T>(&self, call: AuditedCall, result: &Result<T, Status>) {
+        let code = match result {
+            Ok(_) => tonic::Code::Ok,
+            Err(status) => status.code(),
+        };
+        let actor = audit_actor(call.caller.as_ref(), call.authorization.as_deref());
+        // gRPC calls are POSTs to the method's path
+        let path = format!("{}/{}", SERVICE_PATH, call.method);
+        self.audit
+            .record_operation(actor, "POST", &path, call.resource, http_status(code))
+            .await;
+    }
+
+    async fn deploy(
+        &self,
+        request: proto::DeployPipelineRequest,
+        call: &mut AuditedCall,
+    ) -> Result<Response<proto::PipelineResponse>, Status> {
+        let caller = self.authorize_call(call, Access::Filtered)?;
+        let pipeline: Pipeline = serde_json::from_str(&request.manifest_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid pipeline manifest: {}", e)))?;
+        call.resource = Some(format!(
+            "pipeline/{}/{}",
+            pipeline.metadata.namespace, pipeline.metadata.name
+        ));
+        caller
+            .authorize(&Access::Namespace(pipeline.metadata.namespace.clone()))
+            .map_err(auth_status)?;
+        let pipeline = self
+            .admission_webhooks
+            .review(AdmissionOperation::Create, pipeline)
+            .await
+            .map_err(webhook_status)?;
+
+        if !request.force {
+            self.controller
+                .admit_pipeline(&pipeline)
+                .map_err(controller_status)?;
+        }
+        let deployed = self
+            .controller
+            .deploy_pipeline(pipeline)
+            .map_err(controller_status)?;
+
+        Ok(Response::new(proto::PipelineResponse {
+            pipeline: Some(pipeline_to_proto(&deployed)),
+        }))
+    }
+
+    async fn delete(
+        &self,
+        request: proto::PipelineRef,
+        call: &mut AuditedCall,
+    ) -> Result<Response<proto::PipelineResponse>, Status> {
+        let namespace = namespace_or_default(&request.namespace);
+        call.resource = Some(format!("pipeline/{}/{}", namespace, request.name));
+        self.authorize_call(call, Access::Namespace(namespace.to_string()))?;
+        // Protected pipelines can't be forced from here
+        let (deleted, _) = self
+            .controller
+            .request_pipeline_deletion(namespace, &request.name, false)
+            .map_err(controller_status)?;
+
+        Ok(Response::new(proto::PipelineResponse {
+            pipeline: Some(pipeline_to_proto(&deleted)),
+        }))
+    }
+
+    async fn scale(
+        &self,
+        request: proto::ScalePipelineRequest,
+        call: &mut AuditedCall,
+    ) -> Result<Response<proto::PipelineResponse>, Status> {
+        let namespace = namespace_or_default(&request.namespace);
+        call.resource = Some(format!("pipeline/{}/{}", namespace, request.name));
+        self.authorize_call(call, Access::Namespace(namespace.to_string()))?;
+        let scaled = self
+            .controller
+            .scale_pipeline(namespace, &request.name, request.replicas)
+            .map_err(controller_status)?;
+
+        Ok(Response::new(proto::PipelineResponse {
+            pipeline: Some(pipeline_to_proto(&scaled)),
+        }))
+    }
+
+    /// Wrap the service for a tonic server
+    pub fn into_server(self) -> ControlPlaneServer<Self> {
+        ControlPlaneServer::new(self)
+    }
+}
+
+type PipelineEventStream = Pin<Box<dyn Stream<Item = Result<proto::PipelineEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl ControlPlane for ControlPlaneGrpc {
+    async fn deploy_pipeline(
+        &self,
+        request: Request<proto::DeployPipelineRequest>,
+    ) -> Result<Response<proto::PipelineResponse>, Status> {
+        let mut call = AuditedCall::new(&request, "DeployPipeline");
+        let result = self.deploy(request.into_inner(), &mut call).await;
+        self.record(call, &result).await;
+        result
+    }
+
+    async fn get_pipeline(
+        &self,
+        request: Request<proto::PipelineRef>,
+    ) -> Result<Response<proto::PipelineResponse>, Status> {
+        let namespace = namespace_or_default(&request.get_ref().namespace).to_string();
+        self.authorize(&request, Access::Namespace(namespace.clone()))?;
+        let request = request.into_inner();
+
+        let pipeline = self
+            .controller
+            .get_pipeline(&namespace, &request.name)
+            .ok_or_else(|| {
+                controller_status(ControllerError::PipelineNotFound(
+                    request.name.clone(),
+                    namespace.clone(),
+                ))
+            })?;
+
+        Ok(Response::new(proto::PipelineResponse {
+            pipeline: Some(pipeline_to_proto(&pipeline)),
+        }))
+    }
+
+    async fn list_pipelines(
+        &self,
+        request: Request<proto::ListPipelinesRequest>,
+    ) -> Result<Response<proto::ListPipelinesResponse>, Status> {
+        let namespace = request.get_ref().namespace.clone();
+        let caller = if namespace.is_empty() {
+            self.authorize(&request, Access::Filtered)?
+        } else {
+            self.authorize(&request, Access::Namespace(namespace.clone()))?
+        };
+        let mut pipelines = if namespace.is_empty() {
+            self.controller.list_all_pipelines()
+        } else {
+            self.controller.list_pipelines(&namespace)
+        };
+        pipelines.retain(|p| caller.allows(&p.metadata.namespace));
+        pipelines.sort_by_key(|p| p.qualified_name());
+
+        Ok(Response::new(proto::ListPipelinesResponse {
+            pipelines: pipelines.iter().map(pipeline_to_proto).collect(),
+        }))
+    }
+
+    async fn delete_pipeline(
+        &self,
+        request: Request<proto::PipelineRef>,
+    ) -> Result<Response<proto::PipelineResponse>, Status> {
+        let mut call = AuditedCall::new(&request, "DeletePipeline");
+        let result = self.delete(request.into_inner(), &mut call).await;
+        self.record(call, &result).await;
+        result
+    }
+
+    async fn scale_pipeline(
+        &self,
+        request: Request<proto::ScalePipelineRequest>,
+    ) -> Result<Response<proto::PipelineResponse>, Status> {
+        let mut call = AuditedCall::new(&request, "ScalePipeline");
+        let result = self.scale(request.into_inner(), &mut call).await;
+        self.record(call, &result).await;
+        result
+    }
+
+    async fn list_nodes(
+        &self,
+        request: Request<proto::ListNodesRequest>,
+    ) -> Result<Response<proto::ListNodesResponse>, Status> {
+        self.authorize(&request, Access::ClusterWide)?;
+        let mut nodes = self.controller.list_nodes();
+        nodes.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+
+        Ok(Response::new(proto::ListNodesResponse {
+            nodes: nodes.iter().map(node_to_proto).collect(),
+        }))
+    }
+
+    type WatchPipelinesStream = PipelineEventStream;
+
+    // tonic's stream items are `Result<_, Status>`; the size is not ours to change
+    #[allow(clippy::result_large_err)]
+    async fn watch_pipelines(
+        &self,
+        request: Request<proto::WatchPipelinesRequest>,
+    ) -> Result<Response<Self::WatchPipelinesStream>, Status> {
+        let namespace = request.get_ref().namespace.clone();
+        let caller = if namespace.is_empty() {
+            self.authorize(&request, Access::Filtered)?
+        } else {
+            self.authorize(&request, Access::Namespace(namespace.clone()))?
+        };
+        let in_scope = move |pipeline: &Pipeline| {
+            (namespace.is_empty() || pipeline.metadata.namespace == namespace)
+                && caller.allows(&pipeline.metadata.namespace)
+        };
+
+        // Subscribe before listing so no change falls between the two
+        let receiver = self.controller.watch_pipelines();
+        let mut existing = self.controller.list_all_pipelines();
+        existing.retain(|p| in_scope(p));
+        existing.sort_by_key(|p| p.qualified_name());
+
+        let initial = futures::stream::iter(
+            existing
+                .into_iter()
+                .map(|p| Ok(event_to_proto(&PipelineWatchEvent::Added(p)))),
+        );
+
+        let changes = futures::stream::unfold(receiver, move |mut receiver| {
+            let in_scope = in_scope.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) if in_scope(event.pipeline()) => {
+                            return Some((Ok(event_to_proto(&event)), receiver));
+                        }
+                        Ok(_) => continue,
+                        // A slow client misses events rather than stalling the controller
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Pipeline watcher lagged, skipped {} events", skipped);
+                            continue;
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(initial.chain(changes))))
+    }
+}
+
+/// Serve the gRPC API until the server fails
+pub async fn serve_grpc(
+    state: ControlPlaneState,
+    addr: SocketAddr,
+) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(ControlPlaneGrpc::new(&state).into_server())
+        .serve(addr)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Composition;
+
+    fn pipeline(name: &str, namespace: &str) -> Pipeline {
+        let json = r#"{
+            "models": {
+                "gpt": {"type": "external", "interface": "openai-api", "url": "http://a"}
+            },
+            "architecture": [
+                {"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": ["output"]},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+        Pipeline::new(name, Composition::from_str(json).unwrap())
+            .with_namespace(namespace)
+            .with_replicas(2)
+    }
+
+    #[test]
+    fn test_pipeline_to_proto_round_trips_manifest() {
+        let p = pipeline("bot", "prod");
+        let proto = pipeline_to_proto(&p);
+
+        assert_eq!(proto.name, "bot");
+        assert_eq!(proto.namespace, "prod");
+        assert_eq!(proto.replicas, 2);
+        assert_eq!(proto.ready_replicas, 0);
+
+        let parsed: Pipeline = serde_json::from_str(&proto.manifest_json).unwrap();
+        assert_eq!(parsed.qualified_name(), "prod/bot");
+    }
+
+    #[test]
+    fn test_event_to_proto() {
+        let event = event_to_proto(&PipelineWatchEvent::Deleted(pipeline("bot", "default")));
+        assert_eq!(event.r#type(), proto::EventType::Deleted);
+        assert_eq!(event.pipeline.unwrap().name, "bot");
+    }
+
+    #[test]
+    fn test_controller_status_codes() {
+        let code = |e| controller_status(e).code();
+        assert_eq!(
+            code(ControllerError::PipelineNotFound("a".into(), "b".into())),
+            tonic::Code::NotFound
+        );
+        assert_eq!(
+            code(ControllerError::PipelineExists("a".into(), "b".into())),
+            tonic::Code::AlreadyExists
+        );
+        assert_eq!(
+            code(ControllerError::ValidationError("bad".into())),
+            tonic::Code::InvalidArgument
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_streams_existing_then_changes() {
+        let state = ControlPlaneState::new();
+        state
+            .controller
+            .deploy_pipeline(pipeline("first", "default"))
+            .unwrap();
+        state
+            .controller
+            .deploy_pipeline(pipeline("other", "staging"))
+            .unwrap();
+
+        let service = ControlPlaneGrpc::new(&state);
+        let mut stream = service
+            .watch_pipelines(Request::new(proto::WatchPipelinesRequest {
+                namespace: "default".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let existing = stream.next().await.unwrap().unwrap();
+        assert_eq!(existing.r#type(), proto::EventType::Added);
+        assert_eq!(existing.pipeline.unwrap().name, "first");
+
+        state
+            .controller
+            .deploy_pipeline(pipeline("ignored", "staging"))
+            .unwrap();
+        state
+            .controller
+            .scale_pipeline("default", "first", 5)
+            .unwrap();
+
+        let scaled = stream.next().await.unwrap().unwrap();
+        assert_eq!(scaled.r#type(), proto::EventType::Modified);
+        assert_eq!(scaled.pipeline.unwrap().replicas, 5);
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_scope_requests() {
+        let keys = serde_yaml::from_str("keys: [{name: team-a, key: sk-a, namespaces: [prod]}]");
+        let state = ControlPlaneState::new().with_api_keys(ApiKeys::new(keys.unwrap()).unwrap());
+        for namespace in ["prod", "staging"] {
+            state
+                .controller
+                .deploy_pipeline(pipeline("bot", namespace))
+                .unwrap();
+        }
+        let service = ControlPlaneGrpc::new(&state);
+        fn with_key<T>(mut request: Request<T>) -> Request<T> {
+            request
+                .metadata_mut()
+                .insert("authorization", "Bearer sk-a".parse().unwrap());
+            request
+        }
+
+        let listed = service
+            .list_pipelines(with_key(Request::new(proto::ListPipelinesRequest {
+                namespace: String::new(),
+            })))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(listed.pipelines.len(), 1);
+        assert_eq!(listed.pipelines[0].namespace, "prod");
+
+        let other = proto::PipelineRef {
+            name: "bot".to_string(),
+            namespace: "staging".to_string(),
+        };
+        let denied = service.get_pipeline(with_key(Request::new(other.clone())));
+        assert_eq!(
+            denied.await.unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+        let anonymous = service.get_pipeline(Request::new(other));
+        assert_eq!(
+            anonymous.await.unwrap_err().code(),
+            tonic::Code::Unauthenticated
+        );
+        let nodes = service.list_nodes(with_key(Request::new(proto::ListNodesRequest {})));
+        assert_eq!(
+            nodes.await.unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_audited() {
+        use crate::cluster::audit::AuditQuery;
+
+        let keys = serde_yaml::from_str("keys: [{name: team-a, key: sk-a, namespaces: [prod]}]");
+        let state = ControlPlaneState::new().with_api_keys(ApiKeys::new(keys.unwrap()).unwrap());
+        let service = ControlPlaneGrpc::new(&state);
+        fn with_key<T>(mut request: Request<T>, key: &str) -> Request<T> {
+            let value = format!("Bearer {}", key).parse().unwrap();
+            request.metadata_mut().insert("authorization", value);
+            request
+        }
+
+        let manifest_json = serde_json::to_string(&pipeline("bot", "prod")).unwrap();
+        let deploy = proto::DeployPipelineRequest {
+            manifest_json,
+            force: true,
+        };
+        service
+            .deploy_pipeline(with_key(Request::new(deploy), "sk-a"))
+            .await
+            .unwrap();
+        let scale = proto::ScalePipelineRequest {
+            name: "bot".to_string(),
+            namespace: "prod".to_string(),
+            replicas: 3,
+        };
+        service
+            .scale_pipeline(with_key(Request::new(scale), "sk-a"))
+            .await
+            .unwrap();
+        let bot = proto::PipelineRef {
+            name: "bot".to_string(),
+            namespace: "prod".to_string(),
+        };
+        let denied = service.delete_pipeline(with_key(Request::new(bot), "sk-guess"));
+        assert_eq!(
+            denied.await.unwrap_err().code(),
+            tonic::Code::Unauthenticated
+        );
+        // Reads are not audited
+        let listed = service.list_pipelines(with_key(
+            Request::new(proto::ListPipelinesRequest {
+                namespace: String::new(),
+            }),
+            "sk-a",
+        ));
+        listed.await.unwrap();
+
+        let entries = state.audit.query(&AuditQuery::default()).await.unwrap();
+        let recorded: Vec<(&str, &str, u16)> = entries
+            .iter()
+            .map(|e| (e.actor.as_str(), e.path.as_str(), e.status))
+            .collect();
+        assert_eq!(
+            recorded[..2],
+            [
+                ("team-a", "/llmnet.v1.ControlPlane/DeployPipeline", 200),
+                ("team-a", "/llmnet.v1.ControlPlane/ScalePipeline", 200),
+            ]
+        );
+        assert_eq!(entries.len(), 3);
+        assert!(entries[2].actor.starts_with("key:"));
+        assert_eq!(entries[2].status, 401);
+        assert!(entries
+            .iter()
+            .all(|e| e.resource.as_deref() == Some("pipeline/prod/bot")));
+    }
+}
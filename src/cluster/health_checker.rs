@@ -2,8 +2,10 @@
 //!
 //! This module provides active HTTP health probing for deployed pipelines.
 //! It runs as part of the orchestrator loop and updates replica health status
-//! by probing each replica's health endpoint.
+//! by probing each replica's health endpoint, following the probe settings in
+//! the pipeline's `health` block.
 
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -11,10 +13,14 @@ use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use tokio::time::timeout;
-use tracing::{debug, trace, warn};
+use tracing::{debug, info, trace, warn};
+use utoipa::ToSchema;
 
 use super::controller::ClusterController;
 use super::node::ReplicaStatus;
+use super::pipeline::{
+    HealthAction, HealthConfig, PipelineCondition, PipelineStatus, CANARY_SUFFIX,
+};
 
 /// Configuration for the health checker
 #[derive(Debug, Clone)]
@@ -40,8 +46,23 @@ impl Default for HealthCheckerConfig {
     }
 }
 
+impl HealthCheckerConfig {
+    /// Probe settings for replicas that don't belong to a known pipeline
+    pub fn fallback_health(&self) -> HealthConfig {
+        HealthConfig {
+            liveness_path: self.health_path.clone(),
+            readiness_path: self.health_path.clone(),
+            initial_delay_seconds: 0,
+            period_seconds: 0,
+            timeout_seconds: self.timeout_secs as u32,
+            failure_threshold: self.failure_threshold,
+            failure_action: HealthAction::UpdateStatus,
+        }
+    }
+}
+
 /// Result of a single health probe
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct HealthProbeResult {
     /// Whether the probe succeeded
     pub success: bool,
@@ -57,7 +78,7 @@ pub struct HealthProbeResult {
 }
 
 /// Health state for a replica, stored in the controller
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ReplicaHealthState {
     /// Unique key: "node_name:namespace:pipeline_name:port"
     pub key: String,
@@ -194,23 +215,92 @@ async fn probe_endpoint(
     }
 }
 
+/// Whether a replica is due for a probe under its pipeline's health settings
+///
+/// Nothing is probed until `initialDelaySeconds` after the replica was first
+/// seen, and after that at most once every `periodSeconds`.
+pub fn probe_due(state: &ReplicaHealthState, health: &HealthConfig, now: DateTime<Utc>) -> bool {
+    let delay = chrono::Duration::seconds(health.initial_delay_seconds as i64);
+    if now < state.first_seen + delay {
+        return false;
+    }
+
+    let period = chrono::Duration::seconds(health.period_seconds as i64);
+    state
+        .last_probe
+        .as_ref()
+        .is_none_or(|p| now - p.timestamp >= period)
+}
+
+/// Path to probe: the readiness path until the replica first becomes ready,
+/// the liveness path after that
+pub fn probe_path<'a>(state: &ReplicaHealthState, health: &'a HealthConfig) -> &'a str {
+    if state.status == ReplicaStatus::Starting {
+        &health.readiness_path
+    } else {
+        &health.liveness_path
+    }
+}
+
+/// Fold a probe result into a replica's health state
+///
+/// A replica turns Unhealthy after `failureThreshold` consecutive failures and
+/// Failed after twice that many; enough successes bring it back to Running.
+/// Returns the new status when the probe changed it.
+pub fn apply_probe(
+    state: &mut ReplicaHealthState,
+    probe: HealthProbeResult,
+    health: &HealthConfig,
+    success_threshold: u32,
+) -> Option<ReplicaStatus> {
+    let previous = state.status;
+    let threshold = health.failure_threshold.max(1);
+
+    if probe.success {
+        state.consecutive_successes += 1;
+        state.consecutive_failures = 0;
+
+        if state.consecutive_successes >= success_threshold
+            && state.status != ReplicaStatus::Running
+        {
+            state.status = ReplicaStatus::Running;
+            state.ready_since = Some(probe.timestamp);
+        }
+    } else {
+        state.consecutive_failures += 1;
+        state.consecutive_successes = 0;
+
+        if state.consecutive_failures >= threshold * 2 {
+            state.status = ReplicaStatus::Failed;
+            state.ready_since = None;
+        } else if state.consecutive_failures >= threshold && state.status != ReplicaStatus::Failed {
+            state.status = ReplicaStatus::Unhealthy;
+            state.ready_since = None;
+        }
+    }
+
+    state.last_probe = Some(probe);
+    (state.status != previous).then_some(state.status)
+}
+
 /// Run health checks for all replicas in the cluster
 ///
 /// This function:
 /// 1. Collects all known replicas from node statuses
-/// 2. Probes each replica's health endpoint
-/// 3. Updates the health state in the controller
-/// 4. Updates replica status based on consecutive failures/successes
+/// 2. Probes each replica that is due, using its pipeline's health settings
+/// 3. Updates the health state in the controller and the replica status on its node
+/// 4. Evicts Failed replicas whose pipeline asks for a restart or reschedule
 pub async fn check_cluster_health(
     controller: &Arc<ClusterController>,
     client: &Client,
     config: &HealthCheckerConfig,
 ) {
     let nodes = controller.list_nodes();
-    let timeout_duration = Duration::from_secs(config.timeout_secs);
+    let now = Utc::now();
 
-    // Collect all replicas to probe
-    let mut replicas_to_probe: Vec<(String, String, String, String, u16)> = Vec::new();
+    // Collect all replicas, along with the probe settings of their pipeline
+    let mut health_configs: HashMap<(String, String), HealthConfig> = HashMap::new();
+    let mut replicas: Vec<ReplicaHealthState> = Vec::new();
 
     for node in &nodes {
         let node_name = &node.metadata.name;
@@ -218,96 +308,173 @@ pub async fn check_cluster_health(
 
         if let Some(status) = &node.status {
             for pipeline_info in &status.pipelines {
-                replicas_to_probe.push((
-                    node_name.clone(),
-                    node_address.clone(),
-                    pipeline_info.namespace.clone(),
-                    pipeline_info.name.clone(),
-                    pipeline_info.port,
-                ));
+                let pipeline_key = (pipeline_info.namespace.clone(), pipeline_info.name.clone());
+                health_configs.entry(pipeline_key).or_insert_with(|| {
+                    // Canary replicas are probed like the pipeline they belong to
+                    controller
+                        .get_pipeline(&pipeline_info.namespace, &pipeline_info.name)
+                        .or_else(|| {
+                            let base = pipeline_info.name.strip_suffix(CANARY_SUFFIX)?;
+                            controller.get_pipeline(&pipeline_info.namespace, base)
+                        })
+                        .map(|p| p.spec.health)
+                        .unwrap_or_else(|| config.fallback_health())
+                });
+
+                let key = format!(
+                    "{}:{}:{}:{}",
+                    node_name, pipeline_info.namespace, pipeline_info.name, pipeline_info.port
+                );
+                replicas.push(controller.get_replica_health(&key).unwrap_or_else(|| {
+                    ReplicaHealthState::new(
+                        node_name,
+                        node_address,
+                        &pipeline_info.namespace,
+                        &pipeline_info.name,
+                        pipeline_info.port,
+                    )
+                }));
             }
         }
     }
 
-    if replicas_to_probe.is_empty() {
+    // Clean up stale health states (replicas that no longer exist)
+    let active_keys: HashSet<_> = replicas.iter().map(|r| r.key.clone()).collect();
+    controller.cleanup_stale_health_states(&active_keys);
+
+    if replicas.is_empty() {
         trace!("No replicas to probe");
         return;
     }
 
-    debug!("Probing {} replicas", replicas_to_probe.len());
+    let (due, waiting): (Vec<_>, Vec<_>) = replicas.into_iter().partition(|r| {
+        probe_due(
+            r,
+            &health_configs[&(r.namespace.clone(), r.pipeline_name.clone())],
+            now,
+        )
+    });
 
-    // Probe all replicas concurrently
-    let probe_futures: Vec<_> = replicas_to_probe
-        .iter()
-        .map(|(node_name, node_address, namespace, pipeline_name, port)| {
-            let endpoint = format!("http://{}:{}", node_address, port);
-            let health_path = config.health_path.clone();
+    for state in waiting {
+        controller.update_replica_health(state.key.clone(), state);
+    }
+
+    debug!("Probing {} replicas", due.len());
+
+    // Probe all due replicas concurrently
+    let probe_futures: Vec<_> = due
+        .into_iter()
+        .map(|state| {
+            let health = &health_configs[&(state.namespace.clone(), state.pipeline_name.clone())];
+            let path = probe_path(&state, health).to_string();
+            let timeout_duration = Duration::from_secs(health.timeout_seconds as u64);
             let client = client.clone();
 
             async move {
-                let result = probe_endpoint(&client, &endpoint, &health_path, timeout_duration).await;
-                (node_name.clone(), node_address.clone(), namespace.clone(), pipeline_name.clone(), *port, result)
+                let result =
+                    probe_endpoint(&client, &state.endpoint, &path, timeout_duration).await;
+                (state, result)
             }
         })
         .collect();
 
     let results = futures::future::join_all(probe_futures).await;
 
-    // Update health states
-    for (node_name, node_address, namespace, pipeline_name, port, probe_result) in results {
-        let key = format!("{}:{}:{}:{}", node_name, namespace, pipeline_name, port);
+    for (mut state, probe_result) in results {
+        let health = &health_configs[&(state.namespace.clone(), state.pipeline_name.clone())];
+        let error = probe_result.error.clone();
+        let latency_ms = probe_result.latency_ms;
 
-        // Get or create health state
-        let mut health_state = controller
-            .get_replica_health(&key)
-            .unwrap_or_else(|| ReplicaHealthState::new(&node_name, &node_address, &namespace, &pipeline_name, port));
-
-        // Update counters
-        if probe_result.success {
-            health_state.consecutive_successes += 1;
-            health_state.consecutive_failures = 0;
-
-            // Mark as running if we hit success threshold
-            if health_state.consecutive_successes >= config.success_threshold {
-                if health_state.status != ReplicaStatus::Running {
-                    health_state.status = ReplicaStatus::Running;
-                    health_state.ready_since = Some(Utc::now());
-                    debug!(
-                        "Replica {} is now healthy ({}ms)",
-                        key, probe_result.latency_ms
-                    );
-                }
+        match apply_probe(&mut state, probe_result, health, config.success_threshold) {
+            Some(ReplicaStatus::Running) => {
+                debug!("Replica {} is now healthy ({}ms)", state.key, latency_ms);
             }
-        } else {
-            health_state.consecutive_failures += 1;
-            health_state.consecutive_successes = 0;
-
-            // Mark as unhealthy if we hit failure threshold
-            if health_state.consecutive_failures >= config.failure_threshold {
-                if health_state.status == ReplicaStatus::Running {
-                    warn!(
-                        "Replica {} is now unhealthy after {} failures: {:?}",
-                        key, health_state.consecutive_failures, probe_result.error
-                    );
-                    health_state.status = ReplicaStatus::Unhealthy;
-                    health_state.ready_since = None;
+            Some(status) => {
+                warn!(
+                    "Replica {} is now {:?} after {} failures: {:?}",
+                    state.key, status, state.consecutive_failures, error
+                );
+                if status == ReplicaStatus::Failed {
+                    handle_failed_replica(controller, &state, health);
                 }
             }
+            None => {}
         }
 
-        health_state.last_probe = Some(probe_result);
+        if let Err(e) = controller.set_replica_status(
+            &state.node_name,
+            &state.namespace,
+            &state.pipeline_name,
+            state.status,
+        ) {
+            debug!("Failed to record status of replica {}: {}", state.key, e);
+        }
 
-        // Store updated state
-        controller.update_replica_health(key, health_state);
+        controller.update_replica_health(state.key.clone(), state);
     }
+}
 
-    // Clean up stale health states (replicas that no longer exist)
-    let active_keys: std::collections::HashSet<_> = replicas_to_probe
-        .iter()
-        .map(|(node, _, ns, name, port)| format!("{}:{}:{}:{}", node, ns, name, port))
-        .collect();
+/// Record a newly Failed replica on its pipeline and, when the pipeline's
+/// `failureAction` asks for it, pull the replica so it gets scheduled again
+fn handle_failed_replica(
+    controller: &ClusterController,
+    state: &ReplicaHealthState,
+    health: &HealthConfig,
+) {
+    let Some(pipeline) = controller.get_pipeline(&state.namespace, &state.pipeline_name) else {
+        return;
+    };
+
+    let evicted = match health.failure_action {
+        HealthAction::UpdateStatus => Ok(false),
+        HealthAction::Restart => controller
+            .remove_pipeline_from_node(&state.node_name, &state.namespace, &state.pipeline_name)
+            .map(|_| true),
+        HealthAction::Reschedule => controller
+            .evict_replica(&state.node_name, &state.namespace, &state.pipeline_name)
+            .map(|_| true),
+    };
+
+    let evicted = match evicted {
+        Ok(evicted) => evicted,
+        Err(e) => {
+            warn!("Failed to evict replica {}: {}", state.key, e);
+            false
+        }
+    };
+
+    let mut status = pipeline.status.unwrap_or_else(PipelineStatus::initial);
+    status
+        .conditions
+        .retain(|c| c.condition_type != "ReplicaFailure");
+    status.conditions.push(PipelineCondition::new(
+        "ReplicaFailure",
+        "True",
+        "HealthCheckFailed",
+        format!(
+            "Replica on node {} failed {} consecutive health checks",
+            state.node_name, state.consecutive_failures
+        ),
+    ));
+
+    if evicted {
+        // Zero replicas makes the orchestrator schedule the pipeline again
+        info!(
+            "Replica {} evicted ({:?}), rescheduling {}/{}",
+            state.key, health.failure_action, state.namespace, state.pipeline_name
+        );
+        status.replicas = 0;
+        status.ready_replicas = 0;
+    }
 
-    controller.cleanup_stale_health_states(&active_keys);
+    if let Err(e) =
+        controller.update_pipeline_status(&state.namespace, &state.pipeline_name, status)
+    {
+        warn!(
+            "Failed to update pipeline {}/{} status: {}",
+            state.namespace, state.pipeline_name, e
+        );
+    }
 }
 
 /// Get a summary of cluster health
@@ -365,7 +532,7 @@ pub fn get_cluster_health_summary(controller: &ClusterController) -> ClusterHeal
 }
 
 /// Summary of cluster health
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ClusterHealthSummary {
     pub total_replicas: u32,
     pub healthy_replicas: u32,
@@ -422,7 +589,8 @@ impl ClusterHealthSummary {
                 output,
                 "║  {:<12} │ {:<20} │ {:<10} │ {:<8} │ {:<10} │ {:<8} │ {:<6} ║",
                 "NODE", "PIPELINE", "STATUS", "UPTIME", "LAST CHECK", "LATENCY", "ERRORS"
-            ).unwrap();
+            )
+            .unwrap();
             writeln!(output, "╟──────────────┼──────────────────────┼────────────┼──────────┼────────────┼──────────┼────────╢").unwrap();
 
             for replica in &self.replicas {
@@ -434,12 +602,18 @@ impl ClusterHealthSummary {
                     ReplicaStatus::Terminating => "◑ Stopping",
                 };
 
-                let last_check = replica.last_probe.as_ref().map(|p| {
-                    let ago = Utc::now() - p.timestamp;
-                    format!("{}s ago", ago.num_seconds())
-                }).unwrap_or_else(|| "-".to_string());
+                let last_check = replica
+                    .last_probe
+                    .as_ref()
+                    .map(|p| {
+                        let ago = Utc::now() - p.timestamp;
+                        format!("{}s ago", ago.num_seconds())
+                    })
+                    .unwrap_or_else(|| "-".to_string());
 
-                let latency = replica.last_probe.as_ref()
+                let latency = replica
+                    .last_probe
+                    .as_ref()
                     .map(|p| format!("{}ms", p.latency_ms))
                     .unwrap_or_else(|| "-".to_string());
 
@@ -459,7 +633,8 @@ impl ClusterHealthSummary {
                     last_check,
                     latency,
                     replica.consecutive_failures
-                ).unwrap();
+                )
+                .unwrap();
             }
         }
 
@@ -520,6 +695,158 @@ mod tests {
         assert_eq!(summary.status(), "Degraded");
     }
 
+    fn probe(success: bool) -> HealthProbeResult {
+        HealthProbeResult {
+            success,
+            status_code: Some(if success { 200 } else { 503 }),
+            latency_ms: 1,
+            timestamp: Utc::now(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_probe_due_honors_delay_and_period() {
+        let health = HealthConfig {
+            initial_delay_seconds: 30,
+            period_seconds: 10,
+            ..HealthConfig::default()
+        };
+        let mut state = ReplicaHealthState::new("dgx", "10.0.0.1", "default", "chatbot", 8080);
+        let start = state.first_seen;
+
+        assert!(!probe_due(
+            &state,
+            &health,
+            start + chrono::Duration::seconds(29)
+        ));
+        assert!(probe_due(
+            &state,
+            &health,
+            start + chrono::Duration::seconds(30)
+        ));
+
+        let mut last = probe(true);
+        last.timestamp = start + chrono::Duration::seconds(30);
+        state.last_probe = Some(last);
+        assert!(!probe_due(
+            &state,
+            &health,
+            start + chrono::Duration::seconds(35)
+        ));
+        assert!(probe_due(
+            &state,
+            &health,
+            start + chrono::Duration::seconds(40)
+        ));
+    }
+
+    #[test]
+    fn test_probe_path_switches_to_liveness_once_ready() {
+        let health = HealthConfig {
+            liveness_path: "/live".to_string(),
+            readiness_path: "/ready".to_string(),
+            ..HealthConfig::default()
+        };
+        let mut state = ReplicaHealthState::new("dgx", "10.0.0.1", "default", "chatbot", 8080);
+        assert_eq!(probe_path(&state, &health), "/ready");
+
+        apply_probe(&mut state, probe(true), &health, 1);
+        assert_eq!(probe_path(&state, &health), "/live");
+    }
+
+    #[test]
+    fn test_apply_probe_transitions() {
+        let health = HealthConfig {
+            failure_threshold: 2,
+            ..HealthConfig::default()
+        };
+        let mut state = ReplicaHealthState::new("dgx", "10.0.0.1", "default", "chatbot", 8080);
+
+        assert_eq!(
+            apply_probe(&mut state, probe(true), &health, 1),
+            Some(ReplicaStatus::Running)
+        );
+        assert!(state.ready_since.is_some());
+
+        assert_eq!(apply_probe(&mut state, probe(false), &health, 1), None);
+        assert_eq!(
+            apply_probe(&mut state, probe(false), &health, 1),
+            Some(ReplicaStatus::Unhealthy)
+        );
+        assert!(state.ready_since.is_none());
+        assert_eq!(apply_probe(&mut state, probe(false), &health, 1), None);
+        assert_eq!(
+            apply_probe(&mut state, probe(false), &health, 1),
+            Some(ReplicaStatus::Failed)
+        );
+        assert_eq!(apply_probe(&mut state, probe(false), &health, 1), None);
+        assert_eq!(state.status, ReplicaStatus::Failed);
+
+        assert_eq!(
+            apply_probe(&mut state, probe(true), &health, 1),
+            Some(ReplicaStatus::Running)
+        );
+        assert_eq!(state.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_failed_replica_is_rescheduled_elsewhere() {
+        use crate::cluster::node::{Node, NodeCapacity, NodeInfo, NodeStatus};
+        use crate::cluster::pipeline::Pipeline;
+        use crate::config::Composition;
+
+        let controller = ClusterController::new();
+        for name in ["node-1", "node-2"] {
+            let mut node = Node::new(name, "localhost");
+            node.status = Some(NodeStatus::new(
+                NodeCapacity::default(),
+                NodeInfo::from_system(),
+            ));
+            controller.register_node(node).unwrap();
+        }
+
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::new("chatbot", composition);
+        pipeline.spec.health.failure_action = HealthAction::Reschedule;
+        let pipeline = controller.deploy_pipeline(pipeline).unwrap();
+        let mut status = PipelineStatus::initial();
+        status.replicas = 1;
+        status.ready_replicas = 1;
+        controller
+            .update_pipeline_status("default", "chatbot", status)
+            .unwrap();
+        controller
+            .add_pipeline_to_node("node-1", "default", "chatbot", 8080)
+            .unwrap();
+
+        let mut state = ReplicaHealthState::new("node-1", "localhost", "default", "chatbot", 8080);
+        state.status = ReplicaStatus::Failed;
+        state.consecutive_failures = 6;
+        handle_failed_replica(&controller, &state, &pipeline.spec.health);
+
+        let status = controller
+            .get_pipeline("default", "chatbot")
+            .unwrap()
+            .status
+            .unwrap();
+        assert_eq!(status.replicas, 0);
+        assert!(status
+            .conditions
+            .iter()
+            .any(|c| c.condition_type == "ReplicaFailure" && c.reason == "HealthCheckFailed"));
+
+        let pipeline = controller.get_pipeline("default", "chatbot").unwrap();
+        let schedule = controller.schedule_replicas(&pipeline).unwrap();
+        assert_eq!(schedule.keys().collect::<Vec<_>>(), vec!["node-2"]);
+    }
+
     #[test]
     fn test_truncate() {
         assert_eq!(truncate("hello", 10), "hello");
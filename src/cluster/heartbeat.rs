@@ -2,17 +2,64 @@
 //!
 //! This module provides a background task that periodically sends heartbeats
 //! to the control plane, including node metrics for scoring and scheduling.
+//!
+//! After the first full status, heartbeats only carry fields that changed
+//! (PATCH), with a periodic full resync. The interval shrinks while load is
+//! changing rapidly, and the control plane can ask for an immediate
+//! heartbeat through the trigger.
+//!
+//! Given the node's spec, the client also registers the node, retrying with
+//! backoff until the control plane accepts it, and registers it again when
+//! a heartbeat finds the node unknown (e.g. the control plane restarted and
+//! lost it).
+//!
+//! Failed heartbeats are retried with jittered exponential backoff rather
+//! than on the regular interval. Request counts sampled for a heartbeat that
+//! didn't arrive are carried into the next one, and the number of
+//! consecutive failures is kept in the local metrics collector.
+//!
+//! Given somewhere to send assignments, the client also keeps a node session
+//! open (see [`super::node_session`]): heartbeats go over the WebSocket, and
+//! assignments and heartbeat requests pushed by the control plane arrive on
+//! it. While the session is down heartbeats fall back to HTTP, and the
+//! session is opened again before the next one.
+//!
+//! Given the worker's budget ledger, the client also reports the spend on
+//! budgeted models after each heartbeat and takes the cluster-wide totals
+//! the control plane answers with (see [`crate::runtime::budget`]).
+//!
+//! A client configured to deregister sends one last heartbeat reporting the
+//! node NotReady when shut down, then removes the node from the control
+//! plane, so its pipelines are scheduled elsewhere right away instead of
+//! once its heartbeats are missed.
 
+use std::sync::Arc;
 use std::time::Duration;
 
 use chrono::Utc;
-use reqwest::Client;
-use tokio::sync::watch;
+use futures::{SinkExt, StreamExt};
+use reqwest::{Client, Method, StatusCode};
+use serde::Serialize;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, watch, Notify};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
 use tracing::{debug, error, info, warn};
+use uuid::Uuid;
 
-use super::node::{NodeCapacity, NodeInfo, NodePhase, NodePipelineInfo, NodeStatus, ReplicaStatus};
+use super::auth::bearer_headers;
+use super::node::{
+    Node, NodeCapacity, NodeCondition, NodeInfo, NodeMetrics, NodePhase, NodePipelineInfo,
+    NodeStatus, ReplicaStatus,
+};
+use super::node_session::{session_url, AssignmentRequest, ControlMessage, NodeMessage};
+use super::orchestrator::{AssignmentResponse, PipelineAssignment};
+use super::replica_reports::ReplicaReports;
 use super::HEARTBEAT_INTERVAL_SECS;
 use crate::metrics::SharedMetricsCollector;
+use crate::runtime::budget::{BudgetLedger, BudgetSpend};
 use crate::runtime::SharedRunnerManager;
 
 /// Configuration for the heartbeat client
@@ -30,10 +77,65 @@ pub struct HeartbeatConfig {
     /// Node capacity for reporting
     pub capacity: NodeCapacity,
 
+    /// Capacity left for pipelines after system reservations (default: all
+    /// of `capacity`)
+    pub allocatable: Option<NodeCapacity>,
+
     /// Retry count before considering control plane unreachable
     pub max_retries: u32,
+
+    /// Shortest interval used while load is changing rapidly
+    pub min_interval_secs: u64,
+
+    /// Utilization change (percentage points), or relative change of the
+    /// active requests or request rate (percent), between samples that
+    /// switches to the short interval
+    pub load_change_threshold: f64,
+
+    /// Metric drift (percentage points) below which metrics are left out
+    /// of delta heartbeats
+    pub metrics_delta_threshold: f64,
+
+    /// Send a full status every N heartbeats to resynchronize
+    pub full_sync_every: u32,
+
+    /// Wakes the heartbeat loop early when notified
+    pub trigger: Option<Arc<Notify>>,
+
+    /// Conditions reported with every status (e.g. startup reconciliation)
+    pub conditions: Vec<NodeCondition>,
+
+    /// Node to register before heartbeating and whenever the control plane
+    /// has lost it; without one the node must be registered already
+    pub registration: Option<Node>,
+
+    /// Longest wait between registration attempts or failed heartbeats
+    pub max_backoff_secs: u64,
+
+    /// Where assignments pushed over the node session go; without one no
+    /// session is opened and heartbeats only use HTTP
+    pub assignments: Option<mpsc::UnboundedSender<AssignmentRequest>>,
+
+    /// Status of the replicas the worker hosts; without it every running
+    /// runner is reported as a Running replica
+    pub replicas: Option<ReplicaReports>,
+
+    /// Spend on budgeted models to report; without it none is reported
+    pub budgets: Option<Arc<BudgetLedger>>,
+
+    /// Key sent to a control plane that requires one
+    pub api_key: Option<String>,
+
+    /// Report the node NotReady and deregister it on shutdown
+    pub deregister: bool,
 }
 
+/// How long to wait for the control plane to accept a node session
+const SESSION_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// WebSocket of an open node session
+type SessionStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
 impl HeartbeatConfig {
     /// Create a new heartbeat config
     pub fn new(control_plane_url: impl Into<String>, node_name: impl Into<String>) -> Self {
@@ -42,7 +144,21 @@ impl HeartbeatConfig {
             node_name: node_name.into(),
             interval_secs: HEARTBEAT_INTERVAL_SECS,
             capacity: NodeCapacity::default(),
+            allocatable: None,
             max_retries: 3,
+            min_interval_secs: 5,
+            load_change_threshold: 10.0,
+            metrics_delta_threshold: 2.0,
+            full_sync_every: 10,
+            trigger: None,
+            conditions: Vec::new(),
+            registration: None,
+            max_backoff_secs: 60,
+            assignments: None,
+            replicas: None,
+            budgets: None,
+            api_key: None,
+            deregister: false,
         }
     }
 
@@ -57,6 +173,130 @@ impl HeartbeatConfig {
         self.capacity = capacity;
         self
     }
+
+    /// Set the capacity pipelines may use, when less than the node's
+    pub fn with_allocatable(mut self, allocatable: NodeCapacity) -> Self {
+        self.allocatable = Some(allocatable);
+        self
+    }
+
+    /// Set the shortest adaptive interval
+    pub fn with_min_interval(mut self, secs: u64) -> Self {
+        self.min_interval_secs = secs;
+        self
+    }
+
+    /// Set the notifier that requests an immediate heartbeat
+    pub fn with_trigger(mut self, trigger: Arc<Notify>) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    /// Report a condition with every status
+    pub fn with_condition(mut self, condition: NodeCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Register this node, and register it again if the control plane
+    /// loses it
+    pub fn with_registration(mut self, node: Node) -> Self {
+        self.registration = Some(node);
+        self
+    }
+
+    /// Keep a node session open, sending the assignments pushed over it to
+    /// `assignments`
+    pub fn with_session(mut self, assignments: mpsc::UnboundedSender<AssignmentRequest>) -> Self {
+        self.assignments = Some(assignments);
+        self
+    }
+
+    /// Report the replicas recorded here
+    pub fn with_replicas(mut self, replicas: ReplicaReports) -> Self {
+        self.replicas = Some(replicas);
+        self
+    }
+
+    /// Report the spend counted in this ledger, and check its budgets
+    /// against the cluster-wide totals
+    pub fn with_budgets(mut self, budgets: Arc<BudgetLedger>) -> Self {
+        self.budgets = Some(budgets);
+        self
+    }
+
+    /// Authenticate to the control plane with this key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Report the node NotReady and deregister it when shut down
+    pub fn with_deregistration(mut self) -> Self {
+        self.deregister = true;
+        self
+    }
+}
+
+// ============================================================================
+// SBIO: Pure interval selection
+// ============================================================================
+
+/// Choose the delay before the next heartbeat.
+/// Rapidly changing load uses the short interval so the scheduler sees it.
+///
+/// Each sample comes with the time its request count was collected over.
+pub fn adaptive_interval(
+    config: &HeartbeatConfig,
+    previous: Option<(&NodeMetrics, Duration)>,
+    current: Option<(&NodeMetrics, Duration)>,
+) -> Duration {
+    let changing = match (previous, current) {
+        (Some((prev, prev_period)), Some((curr, period))) => {
+            curr.load_changed(prev, config.load_change_threshold, period, prev_period)
+        }
+        _ => false,
+    };
+
+    if changing {
+        Duration::from_secs(config.min_interval_secs.min(config.interval_secs))
+    } else {
+        Duration::from_secs(config.interval_secs)
+    }
+}
+
+/// Delay before retry `attempt` (0-based) of a registration or heartbeat:
+/// one second, doubling each attempt up to `max`
+pub fn retry_backoff(attempt: u32, max: Duration) -> Duration {
+    Duration::from_secs(1u64 << attempt.min(16)).min(max)
+}
+
+/// Spread a retry delay so workers that lost the control plane together
+/// don't retry together: `sample` in [0, 1] picks a delay between half and
+/// all of `delay`
+pub fn with_jitter(delay: Duration, sample: f64) -> Duration {
+    let half = delay / 2;
+    half + half.mul_f64(sample.clamp(0.0, 1.0))
+}
+
+/// Fold the metrics of a heartbeat that never arrived into the current
+/// sample, so its requests still get counted
+///
+/// Utilization and active requests are gauges and come from `current`.
+pub fn carry_over(current: NodeMetrics, unsent: &NodeMetrics) -> NodeMetrics {
+    let request_count = current.request_count + unsent.request_count;
+    let avg_latency_ms = if request_count > 0 {
+        (current.avg_latency_ms * current.request_count as f64
+            + unsent.avg_latency_ms * unsent.request_count as f64)
+            / request_count as f64
+    } else {
+        0.0
+    };
+    NodeMetrics {
+        request_count,
+        avg_latency_ms,
+        ..current
+    }
 }
 
 /// Heartbeat client that runs as a background task
@@ -65,6 +305,18 @@ pub struct HeartbeatClient {
     http_client: Client,
     metrics_collector: SharedMetricsCollector,
     runner_manager: Option<SharedRunnerManager>,
+    /// Status as the control plane last acknowledged it
+    acknowledged: Option<NodeStatus>,
+    /// Metrics from the previous sample and the time its request count
+    /// was collected over, for the adaptive interval
+    last_metrics: Option<(NodeMetrics, Duration)>,
+    /// When metrics were last collected, resetting the request count
+    last_sampled: Instant,
+    /// Metrics of the last heartbeat, kept until the control plane has it
+    unsent_metrics: Option<NodeMetrics>,
+    heartbeats_since_full: u32,
+    /// Open node session, if any
+    session: Option<SessionStream>,
 }
 
 impl HeartbeatClient {
@@ -72,6 +324,7 @@ impl HeartbeatClient {
     pub fn new(config: HeartbeatConfig, metrics_collector: SharedMetricsCollector) -> Self {
         let http_client = Client::builder()
             .timeout(Duration::from_secs(10))
+            .default_headers(bearer_headers(config.api_key.as_deref()))
             .build()
             .expect("Failed to create HTTP client");
 
@@ -80,6 +333,12 @@ impl HeartbeatClient {
             http_client,
             metrics_collector,
             runner_manager: None,
+            acknowledged: None,
+            last_metrics: None,
+            last_sampled: Instant::now(),
+            unsent_metrics: None,
+            heartbeats_since_full: 0,
+            session: None,
         }
     }
 
@@ -93,59 +352,431 @@ impl HeartbeatClient {
     ///
     /// This should be spawned as a background task. It will run until the
     /// shutdown signal is received.
-    pub async fn run(self, mut shutdown: watch::Receiver<bool>) {
-        let interval = Duration::from_secs(self.config.interval_secs);
+    pub async fn run(mut self, mut shutdown: watch::Receiver<bool>) {
+        let mut interval = Duration::from_secs(self.config.interval_secs);
         let mut consecutive_failures = 0u32;
+        let trigger = self.config.trigger.clone();
 
         info!(
             "Starting heartbeat client: node={}, control_plane={}, interval={}s",
             self.config.node_name, self.config.control_plane_url, self.config.interval_secs
         );
 
+        if !self.register_with_retry(&mut shutdown).await {
+            info!("Heartbeat client shutting down");
+            return;
+        }
+
+        // Workers' answers to pushed assignments, sent back over the session
+        let (answers_tx, mut answers) = mpsc::unbounded_channel();
+        self.open_session().await;
+        let mut next_heartbeat = Instant::now() + interval;
+
         loop {
+            let triggered = async {
+                match &trigger {
+                    Some(t) => t.notified().await,
+                    None => std::future::pending().await,
+                }
+            };
+
             tokio::select! {
-                _ = tokio::time::sleep(interval) => {
-                    match self.send_heartbeat().await {
-                        Ok(_) => {
-                            if consecutive_failures > 0 {
-                                info!("Heartbeat recovered after {} failures", consecutive_failures);
-                            }
-                            consecutive_failures = 0;
-                            debug!("Heartbeat sent successfully");
-                        }
-                        Err(e) => {
-                            consecutive_failures += 1;
-                            if consecutive_failures >= self.config.max_retries {
-                                error!(
-                                    "Heartbeat failed {} consecutive times: {}",
-                                    consecutive_failures, e
-                                );
-                            } else {
-                                warn!("Heartbeat failed (attempt {}): {}", consecutive_failures, e);
-                            }
+                _ = tokio::time::sleep_until(next_heartbeat) => {}
+                _ = triggered => {
+                    debug!("Immediate heartbeat requested");
+                }
+                message = next_control(&mut self.session) => match message {
+                    Some(ControlMessage::Assign { id, assignment }) => {
+                        self.forward_assignment(id, *assignment, &answers_tx);
+                        continue;
+                    }
+                    Some(ControlMessage::HeartbeatNow) => {
+                        debug!("Control plane requested a heartbeat");
+                    }
+                    Some(ControlMessage::Resync) => {
+                        debug!("Control plane requested a full heartbeat");
+                        self.acknowledged = None;
+                    }
+                    Some(ControlMessage::Unregistered) => {
+                        warn!(
+                            "Control plane no longer knows node '{}', registering again",
+                            self.config.node_name
+                        );
+                        self.acknowledged = None;
+                        if !self.register_with_retry(&mut shutdown).await {
+                            info!("Heartbeat client shutting down");
+                            break;
                         }
                     }
+                    None => {
+                        warn!("Session with control plane closed, heartbeating over HTTP");
+                        self.session = None;
+                        continue;
+                    }
+                },
+                Some(answer) = answers.recv() => {
+                    if !self.push(&answer).await {
+                        warn!("Session closed before an assignment could be answered");
+                    }
+                    continue;
                 }
                 _ = shutdown.changed() => {
                     if *shutdown.borrow() {
                         info!("Heartbeat client shutting down");
+                        if self.config.deregister {
+                            self.leave().await;
+                        }
                         break;
                     }
+                    continue;
                 }
             }
+
+            self.open_session().await;
+            match self.send_heartbeat().await {
+                Ok(next) => {
+                    if consecutive_failures > 0 {
+                        info!(
+                            "Heartbeat recovered after {} failures",
+                            consecutive_failures
+                        );
+                        self.record_failures(0).await;
+                    }
+                    consecutive_failures = 0;
+                    if next != interval {
+                        debug!("Heartbeat interval now {}s", next.as_secs());
+                    }
+                    interval = next;
+                    debug!("Heartbeat sent successfully");
+                    self.report_budget_spend().await;
+                }
+                Err(HeartbeatError::ServerError { status, .. })
+                    if status == StatusCode::NOT_FOUND.as_u16()
+                        && self.config.registration.is_some() =>
+                {
+                    warn!(
+                        "Control plane no longer knows node '{}', registering again",
+                        self.config.node_name
+                    );
+                    self.acknowledged = None;
+                    if !self.register_with_retry(&mut shutdown).await {
+                        info!("Heartbeat client shutting down");
+                        break;
+                    }
+                    // Report the full status right away
+                    interval = Duration::ZERO;
+                }
+                Err(e) => {
+                    consecutive_failures += 1;
+                    self.record_failures(consecutive_failures).await;
+                    interval = with_jitter(
+                        retry_backoff(
+                            consecutive_failures - 1,
+                            Duration::from_secs(self.config.max_backoff_secs),
+                        ),
+                        jitter_sample(),
+                    );
+                    if consecutive_failures >= self.config.max_retries {
+                        error!(
+                            "Heartbeat failed {} consecutive times (retrying in {}ms): {}",
+                            consecutive_failures,
+                            interval.as_millis(),
+                            e
+                        );
+                    } else {
+                        warn!(
+                            "Heartbeat failed (attempt {}, retrying in {}ms): {}",
+                            consecutive_failures,
+                            interval.as_millis(),
+                            e
+                        );
+                    }
+                }
+            }
+            next_heartbeat = Instant::now() + interval;
         }
     }
 
+    /// Register the node until the control plane accepts it
+    ///
+    /// Returns false if shut down first. Does nothing without a
+    /// registration.
+    async fn register_with_retry(&self, shutdown: &mut watch::Receiver<bool>) -> bool {
+        let Some(node) = &self.config.registration else {
+            return true;
+        };
+        let max_backoff = Duration::from_secs(self.config.max_backoff_secs);
+
+        let mut attempt = 0;
+        loop {
+            let Err(e) = self.register(node).await else {
+                return true;
+            };
+            let delay = with_jitter(retry_backoff(attempt, max_backoff), jitter_sample());
+            attempt += 1;
+            warn!(
+                "Failed to register node '{}' (retrying in {}ms): {}",
+                self.config.node_name,
+                delay.as_millis(),
+                e
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown.changed() => {
+                    if *shutdown.borrow() {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Register the node once; a node the control plane already knows
+    /// counts as registered
+    async fn register(&self, node: &Node) -> Result<(), HeartbeatError> {
+        let url = format!("{}/v1/nodes", self.config.control_plane_url);
+        let response = self
+            .http_client
+            .post(&url)
+            .json(node)
+            .send()
+            .await
+            .map_err(HeartbeatError::RequestFailed)?;
+
+        let status = response.status();
+        if status == StatusCode::CONFLICT {
+            info!(
+                "Node '{}' already registered with control plane",
+                self.config.node_name
+            );
+            return Ok(());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(HeartbeatError::ServerError {
+                status: status.as_u16(),
+                message: body,
+            });
+        }
+
+        info!(
+            "Node '{}' registered with control plane",
+            self.config.node_name
+        );
+        if let Ok(body) = response.json::<serde_json::Value>().await {
+            for warning in body["warnings"].as_array().into_iter().flatten() {
+                warn!("Control plane: {}", warning.as_str().unwrap_or_default());
+            }
+        }
+        Ok(())
+    }
+
     /// Send a single heartbeat to the control plane
-    async fn send_heartbeat(&self) -> Result<(), HeartbeatError> {
+    ///
+    /// Returns the delay before the next heartbeat.
+    async fn send_heartbeat(&mut self) -> Result<Duration, HeartbeatError> {
+        let mut status = self.build_status().await;
+        let sampled = Instant::now();
+        let period = sampled - std::mem::replace(&mut self.last_sampled, sampled);
+        let next = adaptive_interval(
+            &self.config,
+            self.last_metrics.as_ref().map(|(m, p)| (m, *p)),
+            status.metrics.as_ref().map(|m| (m, period)),
+        );
+        self.last_metrics = status.metrics.clone().map(|m| (m, period));
+        if let (Some(current), Some(unsent)) = (status.metrics.take(), &self.unsent_metrics) {
+            status.metrics = Some(carry_over(current, unsent));
+        }
+        self.unsent_metrics = status.metrics.clone();
+
+        let url = format!(
+            "{}/v1/nodes/{}/heartbeat",
+            self.config.control_plane_url, self.config.node_name
+        );
+
+        let full_due = self.heartbeats_since_full + 1 >= self.config.full_sync_every;
+        let delta = match (&self.acknowledged, full_due) {
+            (Some(acknowledged), false) => {
+                Some(status.diff(acknowledged, self.config.metrics_delta_threshold))
+            }
+            _ => None,
+        };
+        if let Some(delta) = delta {
+            let message = NodeMessage::HeartbeatDelta {
+                delta: delta.clone(),
+            };
+            let sent = match self.push(&message).await {
+                true => Ok(()),
+                false => self.send(Method::PATCH, &url, &delta).await,
+            };
+            match sent {
+                Ok(()) => {
+                    if let Some(acknowledged) = self.acknowledged.as_mut() {
+                        acknowledged.apply(delta);
+                    }
+                    self.unsent_metrics = None;
+                    self.heartbeats_since_full += 1;
+                    return Ok(next);
+                }
+                // Control plane lost our baseline (e.g. restarted): resend in full
+                Err(HeartbeatError::ServerError { status, .. })
+                    if status == StatusCode::CONFLICT.as_u16() =>
+                {
+                    debug!("Control plane requested a full heartbeat");
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let message = NodeMessage::Heartbeat {
+            status: status.clone(),
+        };
+        if !self.push(&message).await {
+            self.send(Method::POST, &url, &status).await?;
+        }
+        self.acknowledged = Some(status);
+        self.unsent_metrics = None;
+        self.heartbeats_since_full = 0;
+        Ok(next)
+    }
+
+    /// Send a last heartbeat reporting the node NotReady, then deregister it
+    ///
+    /// Failures are only logged: the node is going away either way, and the
+    /// control plane notices once its heartbeats stop.
+    async fn leave(&mut self) {
+        let mut status = self.build_status().await;
+        status.phase = NodePhase::NotReady;
+        let url = format!(
+            "{}/v1/nodes/{}/heartbeat",
+            self.config.control_plane_url, self.config.node_name
+        );
+        if let Err(e) = self.send(Method::POST, &url, &status).await {
+            warn!("Failed to send final heartbeat: {}", e);
+        }
+        self.report_budget_spend().await;
+
+        let url = format!(
+            "{}/v1/nodes/{}",
+            self.config.control_plane_url, self.config.node_name
+        );
+        match self.http_client.delete(&url).send().await {
+            Ok(response) if response.status().is_success() => info!(
+                "Node '{}' deregistered from control plane",
+                self.config.node_name
+            ),
+            Ok(response) => warn!(
+                "Failed to deregister node '{}': {}",
+                self.config.node_name,
+                response.status()
+            ),
+            Err(e) => warn!(
+                "Failed to deregister node '{}': {}",
+                self.config.node_name, e
+            ),
+        }
+        self.session = None;
+    }
+
+    /// Open the node session, if configured and not open already
+    async fn open_session(&mut self) {
+        if self.session.is_some() || self.config.assignments.is_none() {
+            return;
+        }
+        let url = session_url(&self.config.control_plane_url, &self.config.node_name);
+        let mut request = match url.as_str().into_client_request() {
+            Ok(request) => request,
+            Err(e) => {
+                debug!("Invalid node session URL {}: {}", url, e);
+                return;
+            }
+        };
+        request
+            .headers_mut()
+            .extend(bearer_headers(self.config.api_key.as_deref()));
+        match tokio::time::timeout(SESSION_CONNECT_TIMEOUT, connect_async(request)).await {
+            Ok(Ok((stream, _))) => {
+                info!("Opened node session at {}", url);
+                self.session = Some(stream);
+            }
+            Ok(Err(e)) => debug!("Failed to open node session at {}: {}", url, e),
+            Err(_) => debug!("Timed out opening node session at {}", url),
+        }
+    }
+
+    /// Send a message over the node session
+    ///
+    /// Returns false without an open session, or if sending failed (which
+    /// closes the session).
+    async fn push(&mut self, message: &NodeMessage) -> bool {
+        let Some(session) = self.session.as_mut() else {
+            return false;
+        };
+        let payload = serde_json::to_string(message).unwrap_or_default();
+        match session.send(Message::Text(payload.into())).await {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Node session failed, heartbeating over HTTP: {}", e);
+                self.session = None;
+                false
+            }
+        }
+    }
+
+    /// Hand an assignment pushed over the session to the worker; its answer
+    /// is sent to `answers`
+    fn forward_assignment(
+        &self,
+        id: Uuid,
+        assignment: PipelineAssignment,
+        answers: &mpsc::UnboundedSender<NodeMessage>,
+    ) {
+        let Some(assignments) = &self.config.assignments else {
+            return;
+        };
+        info!(
+            "Control plane pushed pipeline {}/{}",
+            assignment.namespace, assignment.name
+        );
+        let (reply, response) = oneshot::channel();
+        // A worker that has stopped taking assignments drops the reply
+        let _ = assignments.send(AssignmentRequest { assignment, reply });
+
+        let answers = answers.clone();
+        tokio::spawn(async move {
+            let response = response.await.unwrap_or_else(|_| AssignmentResponse {
+                success: false,
+                endpoint: None,
+                error: Some("Worker isn't accepting assignments".to_string()),
+            });
+            let _ = answers.send(NodeMessage::AssignmentResult { id, response });
+        });
+    }
+
+    /// Publish the count of consecutive failed heartbeats locally
+    async fn record_failures(&self, failures: u32) {
+        self.metrics_collector
+            .read()
+            .await
+            .set_heartbeat_failures(failures);
+    }
+
+    /// Collect the current node status
+    async fn build_status(&self) -> NodeStatus {
         // Collect metrics
         let metrics = {
             let mut collector = self.metrics_collector.write().await;
             collector.collect()
         };
 
-        // Collect running pipelines from runner manager
-        let pipelines = if let Some(ref rm) = self.runner_manager {
+        // Collect replicas as the worker recorded them, or approximate them
+        // from the runner manager
+        let pipelines = if let Some(replicas) = &self.config.replicas {
+            match &self.runner_manager {
+                Some(rm) => replicas.observe(|runner| rm.is_running(runner)),
+                None => replicas.observe(|_| true),
+            }
+        } else if let Some(ref rm) = self.runner_manager {
             rm.list_running()
                 .into_iter()
                 .filter_map(|name| {
@@ -164,6 +795,8 @@ impl HeartbeatClient {
                             namespace: "default".to_string(),
                             port,
                             status: ReplicaStatus::Running,
+                            reason: None,
+                            restarts: 0,
                         }
                     })
                 })
@@ -172,29 +805,71 @@ impl HeartbeatClient {
             vec![]
         };
 
-        // Build node status
-        let status = NodeStatus {
+        NodeStatus {
             phase: NodePhase::Ready,
-            conditions: vec![],
+            conditions: self.config.conditions.clone(),
             capacity: self.config.capacity.clone(),
-            allocatable: self.config.capacity.clone(),
+            allocatable: self
+                .config
+                .allocatable
+                .clone()
+                .unwrap_or_else(|| self.config.capacity.clone()),
             pipelines,
             last_heartbeat: Utc::now(),
             node_info: NodeInfo::from_system(),
             metrics: Some(metrics),
             score: None, // Calculated by control plane
+        }
+    }
+
+    /// Report the spend on budgeted models the control plane hasn't
+    /// counted, and take the cluster-wide totals it answers with
+    ///
+    /// Failures are only logged: the spend is reported after the next
+    /// heartbeat.
+    async fn report_budget_spend(&self) {
+        let Some(budgets) = &self.config.budgets else {
+            return;
         };
+        let report = budgets.unreported(Utc::now());
+        if report.is_empty() {
+            return;
+        }
 
-        // Send heartbeat
-        let url = format!(
-            "{}/v1/nodes/{}/heartbeat",
-            self.config.control_plane_url, self.config.node_name
-        );
+        let url = format!("{}/v1/budgets", self.config.control_plane_url);
+        let response = match self.http_client.post(&url).json(&report).send().await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                warn!("Failed to report budget spend: {}", response.status());
+                return;
+            }
+            Err(e) => {
+                warn!("Failed to report budget spend: {}", e);
+                return;
+            }
+        };
+        // The spend is counted even if the totals can't be read
+        let totals = response
+            .json::<Vec<BudgetSpend>>()
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to read cluster-wide budget spend: {}", e);
+                Vec::new()
+            });
+        budgets.settle(&report, &totals, Utc::now());
+    }
 
+    /// Send a heartbeat body to the control plane
+    async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        body: &impl Serialize,
+    ) -> Result<(), HeartbeatError> {
         let response = self
             .http_client
-            .post(&url)
-            .json(&status)
+            .request(method, url)
+            .json(body)
             .send()
             .await
             .map_err(HeartbeatError::RequestFailed)?;
@@ -212,6 +887,30 @@ impl HeartbeatClient {
     }
 }
 
+/// The next message on the node session; None once it has closed, and never
+/// without a session
+async fn next_control(session: &mut Option<SessionStream>) -> Option<ControlMessage> {
+    let Some(stream) = session else {
+        return std::future::pending().await;
+    };
+    while let Some(frame) = stream.next().await {
+        match frame {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(message) => return Some(message),
+                Err(e) => warn!("Ignoring invalid message from control plane: {}", e),
+            },
+            Ok(Message::Close(_)) | Err(_) => return None,
+            Ok(_) => {}
+        }
+    }
+    None
+}
+
+/// A random sample in [0, 1] for [`with_jitter`]
+fn jitter_sample() -> f64 {
+    Uuid::new_v4().as_u64_pair().0 as f64 / u64::MAX as f64
+}
+
 /// Errors that can occur during heartbeat
 #[derive(Debug, thiserror::Error)]
 pub enum HeartbeatError {
@@ -276,4 +975,297 @@ mod tests {
         assert_eq!(config.interval_secs, HEARTBEAT_INTERVAL_SECS);
         assert_eq!(config.max_retries, 3);
     }
+
+    #[tokio::test]
+    async fn test_status_reports_allocatable() {
+        let capacity = NodeCapacity {
+            memory: 16 << 30,
+            ..NodeCapacity::default()
+        };
+        let allocatable = NodeCapacity {
+            memory: 14 << 30,
+            ..NodeCapacity::default()
+        };
+        let config = HeartbeatConfig::new("http://localhost:8181", "worker-1")
+            .with_capacity(capacity.clone())
+            .with_allocatable(allocatable.clone());
+        let client = HeartbeatClient::new(config, crate::metrics::new_shared_collector());
+
+        let status = client.build_status().await;
+        assert_eq!(status.capacity, capacity);
+        assert_eq!(status.allocatable, allocatable);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let max = Duration::from_secs(60);
+        let delays: Vec<u64> = (0..8)
+            .map(|attempt| retry_backoff(attempt, max).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(retry_backoff(u32::MAX, max), max);
+    }
+
+    #[test]
+    fn test_with_jitter() {
+        let delay = Duration::from_secs(8);
+        assert_eq!(with_jitter(delay, 0.0), Duration::from_secs(4));
+        assert_eq!(with_jitter(delay, 0.5), Duration::from_secs(6));
+        assert_eq!(with_jitter(delay, 1.0), delay);
+        assert_eq!(with_jitter(delay, 7.0), delay);
+
+        let sample = jitter_sample();
+        assert!((0.0..=1.0).contains(&sample));
+    }
+
+    #[test]
+    fn test_carry_over() {
+        let unsent = NodeMetrics {
+            cpu_usage_percent: 90.0,
+            request_count: 3,
+            avg_latency_ms: 100.0,
+            ..Default::default()
+        };
+        let current = NodeMetrics {
+            cpu_usage_percent: 20.0,
+            request_count: 1,
+            avg_latency_ms: 500.0,
+            ..Default::default()
+        };
+
+        let merged = carry_over(current, &unsent);
+        assert_eq!(merged.cpu_usage_percent, 20.0);
+        assert_eq!(merged.request_count, 4);
+        assert_eq!(merged.avg_latency_ms, 200.0);
+
+        let idle = carry_over(NodeMetrics::default(), &NodeMetrics::default());
+        assert_eq!(idle.avg_latency_ms, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_reregisters_when_control_plane_loses_node() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use axum::http::StatusCode as Status;
+        use axum::routing::post;
+
+        let registrations = Arc::new(AtomicUsize::new(0));
+        let heartbeats = Arc::new(AtomicUsize::new(0));
+        // The first registration fails; the first heartbeat finds the node
+        // gone, as after a control plane restart
+        let app = axum::Router::new()
+            .route(
+                "/v1/nodes",
+                post({
+                    let registrations = registrations.clone();
+                    move || async move {
+                        match registrations.fetch_add(1, Ordering::SeqCst) {
+                            0 => Status::SERVICE_UNAVAILABLE,
+                            _ => Status::CREATED,
+                        }
+                    }
+                }),
+            )
+            .route(
+                "/v1/nodes/{name}/heartbeat",
+                post({
+                    let heartbeats = heartbeats.clone();
+                    move || async move {
+                        match heartbeats.fetch_add(1, Ordering::SeqCst) {
+                            0 => Status::NOT_FOUND,
+                            _ => Status::OK,
+                        }
+                    }
+                })
+                .patch(|| async { Status::OK }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut config = HeartbeatConfig::new(format!("http://{}", addr), "worker-1")
+            .with_interval(0)
+            .with_registration(Node::new("worker-1", "127.0.0.1"));
+        config.max_backoff_secs = 0;
+        let shutdown = spawn_heartbeat(config, crate::metrics::new_shared_collector());
+
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while heartbeats.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        shutdown.send(true).unwrap();
+
+        // Failed, registered, then registered again after the 404
+        assert_eq!(registrations.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reports_budget_spend() {
+        use axum::routing::post;
+        use axum::Json;
+
+        use crate::config::ModelBudget;
+        use crate::runtime::budget::Spend;
+
+        // Another worker already spent 900 of the month's tokens
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let app = axum::Router::new().route(
+            "/v1/budgets",
+            post({
+                let reports = reports.clone();
+                move |Json(report): Json<Vec<BudgetSpend>>| async move {
+                    reports.lock().unwrap().push(report.clone());
+                    let totals: Vec<BudgetSpend> = report
+                        .into_iter()
+                        .map(|spend| BudgetSpend {
+                            tokens: spend.tokens + 900,
+                            ..spend
+                        })
+                        .collect();
+                    Json(totals)
+                }
+            }),
+        );
+        let addr = crate::test_util::serve(app).await;
+
+        let budgets = Arc::new(BudgetLedger::new());
+        let budget = ModelBudget {
+            monthly_dollars: None,
+            monthly_tokens: Some(1000),
+            fallback: None,
+        };
+        budgets.open("team-a", "gpt", &budget);
+        let spent = Spend {
+            tokens: 100,
+            dollars: 0.0,
+        };
+        budgets.charge("team-a", "gpt", spent, Utc::now());
+
+        let config = HeartbeatConfig::new(format!("http://{}", addr), "worker-1")
+            .with_budgets(budgets.clone());
+        let client = HeartbeatClient::new(config, crate::metrics::new_shared_collector());
+        client.report_budget_spend().await;
+
+        assert_eq!(reports.lock().unwrap()[0][0].tokens, 100);
+        assert!(budgets.is_exhausted("team-a", "gpt", Utc::now()));
+        assert_eq!(budgets.unreported(Utc::now())[0].tokens, 0);
+    }
+
+    #[tokio::test]
+    async fn test_leave_reports_not_ready_then_deregisters() {
+        use axum::http::StatusCode as Status;
+        use axum::routing::post;
+
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let app = axum::Router::new()
+            .route(
+                "/v1/nodes/{name}/heartbeat",
+                post({
+                    let calls = calls.clone();
+                    move |axum::Json(status): axum::Json<NodeStatus>| async move {
+                        calls
+                            .lock()
+                            .unwrap()
+                            .push(format!("heartbeat {:?}", status.phase));
+                        Status::OK
+                    }
+                }),
+            )
+            .route(
+                "/v1/nodes/{name}",
+                axum::routing::delete({
+                    let calls = calls.clone();
+                    move |axum::extract::Path(name): axum::extract::Path<String>| async move {
+                        calls.lock().unwrap().push(format!("delete {}", name));
+                        Status::OK
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let config =
+            HeartbeatConfig::new(format!("http://{}", addr), "worker-1").with_deregistration();
+        let mut client = HeartbeatClient::new(config, crate::metrics::new_shared_collector());
+        client.leave().await;
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["heartbeat NotReady", "delete worker-1"]
+        );
+    }
+
+    #[test]
+    fn test_adaptive_interval() {
+        let config = HeartbeatConfig::new("http://localhost:8181", "worker-1")
+            .with_interval(30)
+            .with_min_interval(5);
+        let secs = Duration::from_secs;
+
+        let calm = NodeMetrics {
+            cpu_usage_percent: 20.0,
+            ..Default::default()
+        };
+        let busy = NodeMetrics {
+            cpu_usage_percent: 85.0,
+            ..Default::default()
+        };
+
+        // First sample has nothing to compare against
+        assert_eq!(
+            adaptive_interval(&config, None, Some((&calm, secs(30)))),
+            secs(30)
+        );
+        assert_eq!(
+            adaptive_interval(&config, Some((&calm, secs(30))), Some((&calm, secs(30)))),
+            secs(30)
+        );
+        assert_eq!(
+            adaptive_interval(&config, Some((&calm, secs(30))), Some((&busy, secs(30)))),
+            secs(5)
+        );
+
+        // Jitter in the request counters backs off all the same
+        let serving = |request_count, active_requests| NodeMetrics {
+            request_count,
+            active_requests,
+            ..calm.clone()
+        };
+        let interval = |prev: &NodeMetrics, prev_period, curr: &NodeMetrics, period| {
+            adaptive_interval(&config, Some((prev, prev_period)), Some((curr, period)))
+        };
+        assert_eq!(
+            interval(&serving(100, 20), secs(30), &serving(104, 21), secs(30)),
+            secs(30)
+        );
+        assert_eq!(
+            interval(&serving(100, 20), secs(30), &serving(200, 20), secs(30)),
+            secs(5)
+        );
+
+        // A steady 10 requests/s counts 50 over the short interval and 300
+        // over the long one, and is the same load either way
+        assert_eq!(
+            interval(&serving(50, 20), secs(5), &serving(300, 20), secs(30)),
+            secs(30)
+        );
+        let mut period = secs(5);
+        let mut previous = (serving(50, 20), period);
+        for _ in 0..4 {
+            let current = serving(10 * period.as_secs(), 20);
+            let next = interval(&previous.0, previous.1, &current, period);
+            assert_eq!(next, secs(30));
+            previous = (current, period);
+            period = next;
+        }
+        // A rate that doubles is a change, however long it was counted over
+        assert_eq!(
+            interval(&serving(50, 20), secs(5), &serving(600, 20), secs(30)),
+            secs(5)
+        );
+    }
 }
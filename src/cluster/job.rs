@@ -0,0 +1,696 @@
+//! Job resource - batch inference against a pipeline
+//!
+//! A Job is analogous to a Kubernetes Job. It runs a list of prompts through
+//! a deployed pipeline and keeps every answer:
+//!
+//! ```yaml
+//! apiVersion: llmnet/v1
+//! kind: Job
+//! metadata:
+//!   name: nightly-summaries
+//! spec:
+//!   pipeline: summarizer
+//!   input: https://example.com/prompts.jsonl
+//!   parallelism: 8
+//! ```
+//!
+//! The input is an http(s) URL, or a file in the control plane's jobs
+//! directory (`--jobs-dir`), with one prompt per line, either plain text or
+//! a JSON object with a `prompt` field. Prompts can also be given inline in
+//! `spec.prompts`. URLs of loopback, private and link-local addresses are
+//! refused, so a job can't make the control plane reach its own network,
+//! and files outside the jobs directory can't be read. The
+//! orchestrator assigns each pending job to one of the pipeline's workers
+//! and sends the prompts to it, recording completed and failed items as
+//! they finish.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::controller::ClusterController;
+use super::pipeline::PipelineMetadata;
+
+/// How long a single prompt may take before it counts as failed
+pub const JOB_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A batch of prompts to run through a pipeline
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Job {
+    /// API version (e.g., "llmnet/v1")
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+
+    /// Kind is always "Job"
+    pub kind: String,
+
+    /// Name, namespace and labels of the job
+    pub metadata: PipelineMetadata,
+
+    /// What to run
+    pub spec: JobSpec,
+
+    /// Progress (populated by controller)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<JobStatus>,
+}
+
+/// Specification of a Job
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JobSpec {
+    /// Pipeline in the job's namespace to send the prompts to
+    pub pipeline: String,
+
+    /// File on the control plane or http(s) URL to read prompts from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+
+    /// Prompts given inline instead of an input
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prompts: Vec<String>,
+
+    /// Prompts sent to the pipeline at once (default: 4)
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+}
+
+fn default_parallelism() -> usize {
+    4
+}
+
+/// Where a job is in its lifecycle
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub enum JobPhase {
+    /// Waiting for the pipeline to have a worker
+    Pending,
+    /// Prompts are being sent
+    Running,
+    /// Every prompt was answered
+    Succeeded,
+    /// The input couldn't be read or some prompts failed
+    Failed,
+}
+
+impl std::fmt::Display for JobPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Observed progress of a Job
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JobStatus {
+    pub phase: JobPhase,
+
+    /// Number of prompts, once the input has been read
+    pub total: usize,
+
+    /// Prompts answered
+    pub completed: usize,
+
+    /// Prompts that got an error
+    pub failed: usize,
+
+    /// Worker the prompts are sent to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    #[serde(rename = "startTime")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<DateTime<Utc>>,
+
+    #[serde(rename = "completionTime")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_time: Option<DateTime<Utc>>,
+
+    /// Why the job is waiting or failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Outcome of one prompt of a job
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct JobResult {
+    /// Position of the prompt in the input, from 0
+    pub index: usize,
+
+    pub prompt: String,
+
+    /// The pipeline's answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+
+    /// Why the prompt failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl Job {
+    /// Create a job sending inline prompts to a pipeline
+    pub fn new(name: impl Into<String>, pipeline: impl Into<String>, prompts: Vec<String>) -> Self {
+        Self {
+            api_version: "llmnet/v1".to_string(),
+            kind: "Job".to_string(),
+            metadata: PipelineMetadata {
+                name: name.into(),
+                namespace: "default".to_string(),
+                uid: Uuid::new_v4(),
+                labels: HashMap::new(),
+                annotations: HashMap::new(),
+                creation_timestamp: Some(Utc::now()),
+                deletion_timestamp: None,
+            },
+            spec: JobSpec {
+                pipeline: pipeline.into(),
+                input: None,
+                prompts,
+                parallelism: default_parallelism(),
+            },
+            status: None,
+        }
+    }
+
+    /// Read prompts from a file or URL instead
+    pub fn with_input(mut self, input: impl Into<String>) -> Self {
+        self.spec.input = Some(input.into());
+        self.spec.prompts.clear();
+        self
+    }
+
+    /// Set the namespace
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.metadata.namespace = namespace.into();
+        self
+    }
+
+    /// Get the qualified name (namespace/name)
+    pub fn qualified_name(&self) -> String {
+        format!("{}/{}", self.metadata.namespace, self.metadata.name)
+    }
+
+    /// The job's phase, `Pending` before the controller has seen it
+    pub fn phase(&self) -> JobPhase {
+        self.status
+            .as_ref()
+            .map(|s| s.phase)
+            .unwrap_or(JobPhase::Pending)
+    }
+}
+
+impl JobSpec {
+    /// Check the spec can be run
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pipeline.is_empty() {
+            return Err("spec.pipeline is required".to_string());
+        }
+        match (&self.input, self.prompts.is_empty()) {
+            (Some(_), false) => Err("spec.input and spec.prompts can't both be set".to_string()),
+            (None, true) => Err("spec.input or spec.prompts is required".to_string()),
+            _ if self.parallelism == 0 => Err("spec.parallelism must be at least 1".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl JobStatus {
+    /// Status of a job that hasn't started
+    pub fn pending() -> Self {
+        Self {
+            phase: JobPhase::Pending,
+            total: 0,
+            completed: 0,
+            failed: 0,
+            endpoint: None,
+            start_time: None,
+            completion_time: None,
+            message: None,
+        }
+    }
+
+    /// Whether the job has stopped for good
+    pub fn is_finished(&self) -> bool {
+        matches!(self.phase, JobPhase::Succeeded | JobPhase::Failed)
+    }
+
+    /// Close the job, failing it if any prompt failed
+    pub fn finish(&mut self, now: DateTime<Utc>) {
+        if self.failed == 0 {
+            self.phase = JobPhase::Succeeded;
+        } else {
+            self.phase = JobPhase::Failed;
+            self.message = Some(format!("{} of {} prompts failed", self.failed, self.total));
+        }
+        self.completion_time = Some(now);
+    }
+
+    /// Close the job before it could send its prompts
+    pub fn fail(&mut self, message: impl Into<String>, now: DateTime<Utc>) {
+        self.phase = JobPhase::Failed;
+        self.message = Some(message.into());
+        self.completion_time = Some(now);
+    }
+}
+
+/// Prompts from an input file: one per line, plain text or a JSON object
+/// with a `prompt` field. Blank lines are skipped.
+pub fn parse_prompts(content: &str) -> Result<Vec<String>, String> {
+    let mut prompts = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('{') {
+            prompts.push(line.to_string());
+            continue;
+        }
+        let value: Value = serde_json::from_str(line)
+            .map_err(|e| format!("line {}: invalid JSON: {}", i + 1, e))?;
+        match value.get("prompt").and_then(Value::as_str) {
+            Some(prompt) => prompts.push(prompt.to_string()),
+            None => return Err(format!("line {}: no \"prompt\" string", i + 1)),
+        }
+    }
+    Ok(prompts)
+}
+
+/// Whether a job input URL may be fetched from `ip`: loopback, private,
+/// link-local, shared, multicast and unspecified addresses are refused
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                // 0.0.0.0/8 and the shared 100.64.0.0/10
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public_address(IpAddr::V4(v4)),
+            None => {
+                let first = v6.segments()[0];
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    || v6.is_multicast()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Path of a file input within the jobs directory
+///
+/// Inputs are relative to `jobs_dir`; absolute paths and `..` are refused.
+pub fn job_input_path(jobs_dir: &Path, input: &str) -> Result<PathBuf, String> {
+    let path = Path::new(input);
+    if !path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!(
+            "{}: file inputs must be relative to the jobs directory, without \"..\"",
+            input
+        ));
+    }
+    Ok(jobs_dir.join(path))
+}
+
+/// Chat completion request for one prompt
+pub fn completion_request(pipeline: &str, prompt: &str) -> Value {
+    serde_json::json!({
+        "model": pipeline,
+        "messages": [{"role": "user", "content": prompt}],
+    })
+}
+
+/// The answer text of a chat completion response
+pub fn extract_answer(response: &Value) -> Option<String> {
+    response
+        .pointer("/choices/0/message/content")
+        .and_then(Value::as_str)
+        .map(String::from)
+}
+
+/// The endpoint running the fewest jobs, for spreading jobs across replicas
+pub fn pick_endpoint(endpoints: &[String], running: &[Job]) -> Option<String> {
+    endpoints
+        .iter()
+        .min_by_key(|endpoint| {
+            running
+                .iter()
+                .filter(|job| {
+                    job.status.as_ref().and_then(|s| s.endpoint.as_ref()) == Some(*endpoint)
+                })
+                .count()
+        })
+        .cloned()
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Read a job's prompts from its input, or take the inline ones
+pub async fn load_prompts(spec: &JobSpec, jobs_dir: Option<&Path>) -> Result<Vec<String>, String> {
+    let Some(input) = &spec.input else {
+        return Ok(spec.prompts.clone());
+    };
+
+    let content = if input.starts_with("http://") || input.starts_with("https://") {
+        fetch_input(input).await?
+    } else {
+        let jobs_dir = jobs_dir.ok_or_else(|| {
+            format!(
+                "{}: file inputs are off, the control plane has no jobs directory",
+                input
+            )
+        })?;
+        read_input(jobs_dir, input).await?
+    };
+    parse_prompts(&content)
+}
+
+/// Fetch a URL input from a public address
+///
+/// The host is resolved once and the request pinned to the checked
+/// address, and redirects aren't followed, so neither DNS nor the server
+/// can point the fetch elsewhere.
+async fn fetch_input(input: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(input).map_err(|e| format!("{}: {}", input, e))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("{}: no host", input))?
+        .to_string();
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| format!("{}: no port", input))?;
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .map_err(|e| format!("failed to resolve {}: {}", input, e))?
+        .collect();
+    let addr = match addrs.first() {
+        Some(_) if addrs.iter().any(|a| !is_public_address(a.ip())) => {
+            return Err(format!(
+                "{}: refusing to fetch from a non-public address",
+                input
+            ));
+        }
+        Some(addr) => *addr,
+        None => return Err(format!("failed to resolve {}", input)),
+    };
+
+    let client = Client::builder()
+        .timeout(JOB_REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(&host, addr)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("failed to fetch {}: {}", input, e))?;
+    response
+        .text()
+        .await
+        .map_err(|e| format!("failed to read {}: {}", input, e))
+}
+
+/// Read a file input from the jobs directory, following symlinks only
+/// within it
+async fn read_input(jobs_dir: &Path, input: &str) -> Result<String, String> {
+    let path = job_input_path(jobs_dir, input)?;
+    let canonical = |p: PathBuf| async move {
+        tokio::fs::canonicalize(&p)
+            .await
+            .map_err(|e| format!("failed to read {}: {}", input, e))
+    };
+    let dir = canonical(jobs_dir.to_path_buf()).await?;
+    let path = canonical(path).await?;
+    if !path.starts_with(&dir) {
+        return Err(format!("{}: outside the jobs directory", input));
+    }
+    tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("failed to read {}: {}", input, e))
+}
+
+/// Send one prompt to a worker
+async fn run_prompt(
+    client: &Client,
+    endpoint: &str,
+    pipeline: &str,
+    index: usize,
+    prompt: String,
+) -> JobResult {
+    let url = format!("{}/v1/chat/completions", endpoint.trim_end_matches('/'));
+    let answer = async {
+        let response = client
+            .post(&url)
+            .json(&completion_request(pipeline, &prompt))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("HTTP {}: {}", status, body.trim()));
+        }
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        extract_answer(&body).ok_or_else(|| "response has no answer".to_string())
+    };
+
+    match answer.await {
+        Ok(output) => JobResult {
+            index,
+            prompt,
+            output: Some(output),
+            error: None,
+        },
+        Err(error) => JobResult {
+            index,
+            prompt,
+            output: None,
+            error: Some(error),
+        },
+    }
+}
+
+/// Run a job that the controller has started on `endpoint`
+///
+/// Results are recorded as they come in. A job deleted while running stops
+/// sending prompts.
+pub async fn run_job(controller: ClusterController, namespace: String, name: String) {
+    let Some(job) = controller.get_job(&namespace, &name) else {
+        return;
+    };
+    let Some(endpoint) = job.status.as_ref().and_then(|s| s.endpoint.clone()) else {
+        return;
+    };
+    let client = Client::builder()
+        .timeout(JOB_REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    let prompts = match load_prompts(&job.spec, controller.jobs_dir().as_deref()).await {
+        Ok(prompts) => prompts,
+        Err(e) => {
+            warn!("Job {}/{} failed: {}", namespace, name, e);
+            controller.fail_job(&namespace, &name, e);
+            return;
+        }
+    };
+    if !controller.set_job_total(&namespace, &name, prompts.len()) {
+        return;
+    }
+
+    let pipeline = &job.spec.pipeline;
+    let mut results = futures::stream::iter(prompts.into_iter().enumerate())
+        .map(|(index, prompt)| run_prompt(&client, &endpoint, pipeline, index, prompt))
+        .buffer_unordered(job.spec.parallelism.max(1));
+    while let Some(result) = results.next().await {
+        if !controller.record_job_result(&namespace, &name, result) {
+            info!("Job {}/{} was deleted, stopping", namespace, name);
+            return;
+        }
+    }
+
+    controller.finish_job(&namespace, &name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_job_manifest() {
+        let job: Job = serde_yaml::from_str(
+            r#"
+apiVersion: llmnet/v1
+kind: Job
+metadata:
+  name: nightly
+spec:
+  pipeline: summarizer
+  input: /data/prompts.jsonl
+"#,
+        )
+        .unwrap();
+        assert_eq!(job.metadata.namespace, "default");
+        assert_eq!(job.spec.parallelism, 4);
+        assert_eq!(job.phase(), JobPhase::Pending);
+        assert!(job.spec.validate().is_ok());
+
+        let mut both = job.spec.clone();
+        both.prompts = vec!["hi".into()];
+        assert!(both.validate().is_err());
+        let neither = Job::new("j", "p", vec![]);
+        assert!(neither.spec.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_prompts() {
+        let prompts = parse_prompts(
+            "Summarize the news\n\n{\"prompt\": \"Translate: bonjour\", \"id\": 7}\n  trailing  \n",
+        )
+        .unwrap();
+        assert_eq!(
+            prompts,
+            vec!["Summarize the news", "Translate: bonjour", "trailing"]
+        );
+
+        assert_eq!(
+            parse_prompts("ok\n{\"text\": \"x\"}").unwrap_err(),
+            "line 2: no \"prompt\" string"
+        );
+        assert!(parse_prompts("{not json")
+            .unwrap_err()
+            .starts_with("line 1: invalid JSON"));
+    }
+
+    #[test]
+    fn test_answer_and_finish() {
+        let response = serde_json::json!({
+            "choices": [{"message": {"role": "assistant", "content": "42"}}]
+        });
+        assert_eq!(extract_answer(&response), Some("42".to_string()));
+        assert_eq!(extract_answer(&serde_json::json!({})), None);
+
+        let mut status = JobStatus::pending();
+        status.total = 3;
+        status.completed = 3;
+        status.finish(Utc::now());
+        assert_eq!(status.phase, JobPhase::Succeeded);
+
+        status.completed = 2;
+        status.failed = 1;
+        status.finish(Utc::now());
+        assert_eq!(status.phase, JobPhase::Failed);
+        assert_eq!(status.message.as_deref(), Some("1 of 3 prompts failed"));
+    }
+
+    #[test]
+    fn test_is_public_address() {
+        for refused in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_address(refused.parse().unwrap()), "{}", refused);
+        }
+        for allowed in ["93.184.216.34", "2606:2800:220:1::1"] {
+            assert!(is_public_address(allowed.parse().unwrap()), "{}", allowed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_input_refuses_internal_urls_and_outside_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("prompts.txt"), "one\ntwo\n").unwrap();
+        let input = |input: &str| {
+            let mut job = Job::new("j", "p", vec![]);
+            job.spec.input = Some(input.to_string());
+            job.spec
+        };
+
+        let metadata = load_prompts(&input("http://169.254.169.254/"), None).await;
+        assert!(metadata.unwrap_err().contains("non-public"));
+        let loopback = load_prompts(&input("http://localhost:8181/v1/secrets"), None).await;
+        assert!(loopback.unwrap_err().contains("non-public"));
+
+        // No file inputs without a jobs directory, and none outside it
+        assert!(load_prompts(&input("/etc/passwd"), None).await.is_err());
+        for outside in ["/etc/passwd", "../../../../etc/passwd", "a/../../passwd"] {
+            assert!(
+                load_prompts(&input(outside), Some(dir.path()))
+                    .await
+                    .is_err(),
+                "{}",
+                outside
+            );
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc/passwd", dir.path().join("passwd")).unwrap();
+            let linked = load_prompts(&input("passwd"), Some(dir.path())).await;
+            assert!(linked.unwrap_err().contains("outside the jobs directory"));
+        }
+
+        assert_eq!(
+            load_prompts(&input("prompts.txt"), Some(dir.path()))
+                .await
+                .unwrap(),
+            vec!["one", "two"]
+        );
+    }
+
+    #[test]
+    fn test_pick_endpoint() {
+        let endpoints = vec!["http://a:8080".to_string(), "http://b:8080".to_string()];
+        let mut busy = Job::new("j", "p", vec!["x".into()]);
+        let mut status = JobStatus::pending();
+        status.endpoint = Some("http://a:8080".to_string());
+        busy.status = Some(status);
+
+        assert_eq!(
+            pick_endpoint(&endpoints, &[busy]),
+            Some("http://b:8080".to_string())
+        );
+        assert_eq!(
+            pick_endpoint(&endpoints, &[]),
+            Some("http://a:8080".to_string())
+        );
+        assert_eq!(pick_endpoint(&[], &[]), None);
+    }
+}
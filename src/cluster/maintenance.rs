@@ -0,0 +1,200 @@
+//! Recurring node maintenance windows
+//!
+//! A node can declare weekly windows (`spec.maintenanceWindows`) during which
+//! it may be patched or rebooted. Shortly before a window opens the control
+//! plane cordons the node and moves its replicas elsewhere; once the window
+//! closes it uncordons the node again:
+//!
+//! ```yaml
+//! spec:
+//!   address: 10.0.0.12
+//!   maintenanceWindows:
+//!     - days: [sat, sun]
+//!       start: "02:00"
+//!       durationMinutes: 120
+//!       drainBeforeMinutes: 15
+//! ```
+//!
+//! Times are UTC. Only nodes the controller cordoned itself are uncordoned,
+//! so a manual cordon is never undone by a window ending.
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Annotation the controller sets on nodes it cordoned for maintenance,
+/// holding the RFC 3339 time the window closes
+pub const MAINTENANCE_ANNOTATION: &str = "llmnet.io/maintenance-until";
+
+/// A weekly maintenance window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceWindow {
+    /// Days the window opens on (e.g. "sat"); empty means every day
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(value_type = Vec<String>)]
+    pub days: Vec<Weekday>,
+
+    /// Opening time, "HH:MM" in UTC
+    pub start: String,
+
+    /// How long the window stays open
+    #[serde(rename = "durationMinutes")]
+    pub duration_minutes: u32,
+
+    /// How long before the window opens to cordon and drain the node
+    #[serde(rename = "drainBeforeMinutes", default = "default_drain_before")]
+    pub drain_before_minutes: u32,
+}
+
+fn default_drain_before() -> u32 {
+    15
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl MaintenanceWindow {
+    /// Window opening at `start` ("HH:MM") on every day
+    pub fn daily(start: impl Into<String>, duration_minutes: u32) -> Self {
+        Self {
+            days: Vec::new(),
+            start: start.into(),
+            duration_minutes,
+            drain_before_minutes: default_drain_before(),
+        }
+    }
+
+    /// Restrict the window to some days of the week
+    pub fn on(mut self, days: Vec<Weekday>) -> Self {
+        self.days = days;
+        self
+    }
+
+    /// Check the window can be evaluated
+    pub fn validate(&self) -> Result<(), String> {
+        self.start_time()?;
+        if self.duration_minutes == 0 {
+            return Err("durationMinutes must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
+    fn start_time(&self) -> Result<NaiveTime, String> {
+        NaiveTime::parse_from_str(&self.start, "%H:%M")
+            .map_err(|_| format!("start '{}' is not a HH:MM time", self.start))
+    }
+
+    /// The occurrence whose drain period or window contains `now`, as its
+    /// opening and closing times
+    pub fn occurrence_at(&self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let start_time = self.start_time().ok()?;
+        let duration = Duration::minutes(self.duration_minutes as i64);
+        let lead = Duration::minutes(self.drain_before_minutes as i64);
+
+        // Windows opening tomorrow may already be draining, and long windows
+        // from several days back may still be open
+        let days_back = self.duration_minutes as i64 / (24 * 60) + 1;
+        (-days_back..=1).find_map(|offset| {
+            let day = (now + Duration::days(offset)).date_naive();
+            if !self.days.is_empty() && !self.days.contains(&day.weekday()) {
+                return None;
+            }
+            let opens = day.and_time(start_time).and_utc();
+            let closes = opens + duration;
+            (opens - lead <= now && now < closes).then_some((opens, closes))
+        })
+    }
+}
+
+/// When the node's current maintenance closes, if `now` falls in one of its
+/// windows or the drain period before it. Overlapping windows are merged.
+pub fn maintenance_until(
+    windows: &[MaintenanceWindow],
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    windows
+        .iter()
+        .filter_map(|w| w.occurrence_at(now))
+        .map(|(_, closes)| closes)
+        .max()
+}
+
+/// Check every window of a node
+pub fn validate_windows(windows: &[MaintenanceWindow]) -> Result<(), String> {
+    for (i, window) in windows.iter().enumerate() {
+        window
+            .validate()
+            .map_err(|e| format!("maintenance window {}: {}", i + 1, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        // 2026-03-07 is a Saturday
+        Utc.with_ymd_and_hms(2026, 3, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_window() {
+        let window: MaintenanceWindow = serde_json::from_str(
+            r#"{"days": ["sat", "Sunday"], "start": "02:00", "durationMinutes": 120}"#,
+        )
+        .unwrap();
+        assert_eq!(window.days, vec![Weekday::Sat, Weekday::Sun]);
+        assert_eq!(window.drain_before_minutes, 15);
+        assert!(window.validate().is_ok());
+
+        assert!(MaintenanceWindow::daily("25:00", 60).validate().is_err());
+        assert!(MaintenanceWindow::daily("02:00", 0).validate().is_err());
+        assert!(validate_windows(&[MaintenanceWindow::daily("2am", 60)])
+            .unwrap_err()
+            .starts_with("maintenance window 1"));
+    }
+
+    #[test]
+    fn test_occurrence_at() {
+        let window = MaintenanceWindow::daily("02:00", 120).on(vec![Weekday::Sat]);
+
+        assert_eq!(window.occurrence_at(at(7, 1, 0)), None);
+        // Draining starts 15 minutes early
+        assert_eq!(
+            window.occurrence_at(at(7, 1, 45)),
+            Some((at(7, 2, 0), at(7, 4, 0)))
+        );
+        assert!(window.occurrence_at(at(7, 3, 59)).is_some());
+        assert_eq!(window.occurrence_at(at(7, 4, 0)), None);
+        // Sunday isn't a maintenance day
+        assert_eq!(window.occurrence_at(at(8, 2, 30)), None);
+    }
+
+    #[test]
+    fn test_window_crossing_midnight() {
+        let window = MaintenanceWindow::daily("23:30", 60).on(vec![Weekday::Sat]);
+        // Still open early on Sunday
+        assert_eq!(
+            window.occurrence_at(at(8, 0, 15)),
+            Some((at(7, 23, 30), at(8, 0, 30)))
+        );
+
+        // Drain period of a window opening just after midnight starts the day before
+        let window = MaintenanceWindow::daily("00:05", 30).on(vec![Weekday::Sun]);
+        assert!(window.occurrence_at(at(7, 23, 55)).is_some());
+    }
+
+    #[test]
+    fn test_maintenance_until_merges_windows() {
+        let windows = vec![
+            MaintenanceWindow::daily("02:00", 60),
+            MaintenanceWindow::daily("02:30", 90),
+        ];
+        assert_eq!(maintenance_until(&windows, at(7, 2, 45)), Some(at(7, 4, 0)));
+        assert_eq!(maintenance_until(&windows, at(7, 12, 0)), None);
+        assert_eq!(maintenance_until(&[], at(7, 2, 45)), None);
+    }
+}
@@ -14,7 +14,21 @@
 //! 6. **Horizontal Scaling**: Replicate pipelines across nodes
 //! 7. **kubectl-like CLI**: `llmnet get`, `deploy`, `delete`, `logs`
 //! 8. **Labels & Selectors**: Organize and query resources
-//! 9. **Rollouts**: Gradual deployment updates
+//! 9. **Rollouts**: Gradual deployment updates, canary and blue/green
+//! 10. **Cordon & Drain**: Including scheduled node maintenance windows
+//! 11. **Jobs**: Batch inference runs of a list of prompts through a pipeline
+//! 12. **Virtual Endpoints**: Weighted and time-windowed traffic splitting
+//!     between pipelines, e.g. A/B tests
+//! 13. **Secrets**: API keys and other values stored encrypted on the
+//!     control plane and handed to pipeline runners
+//! 14. **Node Pools**: Named groups of nodes that pipelines target by name,
+//!     with pool-wide limits
+//! 15. **Federation**: Pipelines delegated to child control planes by
+//!     region, with their status reported back
+//! 16. **Alerting**: Rules on node, pipeline and error-rate health that
+//!     notify webhooks, Slack or PagerDuty
+//! 17. **API Keys**: Bearer keys scoped to namespaces, so teams sharing a
+//!     control plane only see and change their own pipelines
 //!
 //! ### ❌ EXCLUDED FEATURES (Too complex for LLM orchestration)
 //!
@@ -23,7 +37,7 @@
 //! 3. **CRDs**: No custom resource definitions (yet).
 //! 4. **Network Policies**: All nodes in cluster can communicate.
 //! 5. **Ingress Controllers**: Just expose OpenAI-compatible API.
-//! 6. **RBAC**: Simple API key auth, not role-based.
+//! 6. **RBAC**: API keys scoped to namespaces, not role-based.
 //! 7. **StatefulSets/DaemonSets**: All pipelines are stateless.
 //!
 //! ## Core Resources
@@ -31,6 +45,11 @@
 //! - **Pipeline**: A deployable LLM routing configuration
 //! - **Node**: A machine running LLMNet that can host pipelines
 //! - **Namespace**: Logical isolation for pipelines
+//! - **Job**: A batch of prompts run through a pipeline, with stored outputs
+//! - **VirtualEndpoint**: A route whose traffic is split between pipelines
+//! - **Secret**: Named values kept encrypted, used by pipelines' runners
+//! - **NodePool**: Nodes grouped by labels, with shared scheduling limits
+//! - **Region**: A child control plane regional pipelines are forwarded to
 //!
 //! ## Architecture
 //!
@@ -53,39 +72,115 @@
 //!   └───────────┘        └───────────┘        └───────────┘
 //! ```
 
+pub mod admission;
+pub mod admission_webhook;
+pub mod alerting;
 pub mod api;
+pub mod audit;
+pub mod auth;
 pub mod autoscaler;
+pub mod container_gc;
 pub mod controller;
+pub mod gpus;
+pub mod grpc;
 pub mod health_checker;
 pub mod heartbeat;
+pub mod job;
+pub mod maintenance;
 pub mod node;
+pub mod node_pool;
+pub mod node_session;
 pub mod orchestrator;
 pub mod pipeline;
+pub mod proxy;
+pub mod region;
+pub mod replica_reports;
 pub mod resources;
+pub mod rollout;
 pub mod scoring;
+pub mod secret;
+pub mod virtual_endpoint;
+pub mod worker_state;
 
+pub use admission::{
+    admission_problems, model_fit, model_warnings, node_device_profile, parse_quantity, ModelFit,
+    ReplicaDemand,
+};
+pub use admission_webhook::{AdmissionOperation, AdmissionWebhooks, WebhookError};
+pub use alerting::{
+    spawn_alerting, Alert, AlertCondition, AlertNotification, AlertRule, AlertSeverity, AlertSink,
+    AlertState, AlertingConfig, ALERT_EVALUATION_INTERVAL_SECS,
+};
 pub use api::{create_control_plane_router, ControlPlaneState};
+pub use audit::{
+    AuditEntry, AuditError, AuditLog, AuditQuery, AuditSink, FileAuditSink, MemoryAuditSink,
+    DEFAULT_AUDIT_RETENTION_DAYS,
+};
+pub use auth::{
+    load_api_keys, required_access, Access, ApiKey, ApiKeyConfig, ApiKeys, AuthError, Caller,
+    Scope, ALL_NAMESPACES,
+};
 pub use autoscaler::{AutoscalerState, ScalingDecision};
-pub use controller::{ClusterController, ClusterStats, ControllerConfig};
+pub use container_gc::{
+    collect_orphans, find_orphans, spawn_container_gc, ContainerGcConfig, OrphanTracker,
+    CONTAINER_COLLECTED, DEFAULT_GC_INTERVAL_SECS,
+};
+pub use controller::{ClusterController, ClusterStats, ControllerConfig, PipelineWatchEvent};
+pub use gpus::{
+    model_gpu_count, pick_gpus, pin_to_gpus, plan_gpu_allocations, GpuAllocation,
+    CUDA_VISIBLE_DEVICES,
+};
+pub use grpc::{serve_grpc, ControlPlaneGrpc, GRPC_PORT};
 pub use health_checker::{
     check_cluster_health, get_cluster_health_summary, ClusterHealthSummary, HealthCheckerConfig,
     HealthProbeResult, ReplicaHealthState,
 };
 pub use heartbeat::{
-    spawn_heartbeat, spawn_heartbeat_with_runner, HeartbeatClient, HeartbeatConfig,
+    adaptive_interval, spawn_heartbeat, spawn_heartbeat_with_runner, HeartbeatClient,
+    HeartbeatConfig,
 };
+pub use job::{Job, JobPhase, JobResult, JobSpec, JobStatus};
+pub use maintenance::{maintenance_until, MaintenanceWindow, MAINTENANCE_ANNOTATION};
 pub use node::{
-    Node, NodeCapacity, NodeCondition, NodeMetrics, NodePhase, NodeScore, NodeStatus,
-    ScoreBreakdown,
+    GpuMetrics, Node, NodeCapabilities, NodeCapacity, NodeCondition, NodeConditionType,
+    NodeMetrics, NodePhase, NodeScore, NodeStatus, NodeStatusDelta, ScoreBreakdown,
+};
+pub use node_pool::{NodePool, NodePoolSpec, NodePoolStatus};
+pub use node_session::{
+    serve_node_session, session_url, AssignmentRequest, ControlMessage, NodeMessage, NodeSessions,
+    SessionError,
 };
 pub use orchestrator::{
     spawn_orchestrator, AssignmentResponse, OrchestratorConfig, PipelineAssignment,
 };
 pub use pipeline::{
-    AutoscalingConfig, Pipeline, PipelineCondition, PipelineSpec, PipelineStatus, ScalingBehavior,
+    composition_hash, AutoscalingConfig, CanaryParams, LayerPlacement, Pipeline, PipelineCondition,
+    PipelineDependency, PipelineReplica, PipelineSpec, PipelineStatus, ReplicaBalancing,
+    RolloutKind, RolloutPhase, RolloutStatus, ScalingBehavior, SecretEnvRef, TrafficStats,
+    BLOCKED_CONDITION, COMPOSITION_HASH_ANNOTATION, DELETION_PROTECTION_ANNOTATION,
+    REGION_ANNOTATION,
+};
+pub use proxy::{pick_replica, replica_targets, ReplicaTarget};
+pub use region::{
+    spawn_federation, sync_region, Region, RegionSpec, RegionStatus,
+    DEFAULT_FEDERATION_INTERVAL_SECS,
 };
+pub use replica_reports::ReplicaReports;
 pub use resources::*;
-pub use scoring::{calculate_node_score, ScoringWeights};
+pub use rollout::{apply_update, rollout_decision, routes_to_canary, RolloutDecision};
+pub use scoring::{calculate_node_score, ScoringWeights, SCORING_PRESETS};
+pub use secret::{MasterKey, Secret, SecretStoreError, SECRET_GRANT_HEADER};
+pub use virtual_endpoint::{
+    pick_backend, EndpointBackend, VariantStats, VirtualEndpoint, VirtualEndpointSpec,
+    VirtualEndpointStatus,
+};
+pub use worker_state::{
+    default_state_file, plan_reconcile, reconcile_runners, spawn_assignment_runners,
+    AdoptionReport, ReconcilePlan, WorkerState, WorkerStateError, WorkerStateStore,
+};
+
+/// API version for cluster resources and the registration handshake
+pub const API_VERSION: &str = "llmnet/v1";
 
 /// Default control plane API port
 pub const CONTROL_PLANE_PORT: u16 = 8181;
@@ -95,3 +190,6 @@ pub const WORKER_PORT: u16 = 8080;
 
 /// Default heartbeat interval in seconds
 pub const HEARTBEAT_INTERVAL_SECS: u64 = 30;
+
+/// Default time in seconds a stopping worker lets in-flight requests finish
+pub const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
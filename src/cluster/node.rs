@@ -5,14 +5,34 @@
 //! - Reports its capacity (GPU, memory, etc.)
 //! - Receives pipeline deployments
 //! - Sends heartbeats to stay registered
+//!
+//! Nodes are labeled with the hardware they run on when created, so
+//! pipelines can select them without manual labeling:
+//!
+//! | Label | Example |
+//! |-------|---------|
+//! | `llmnet.io/os` | `linux` |
+//! | `llmnet.io/arch` | `arm64` |
+//! | `llmnet.io/cuda` | `12.2` |
+//! | `llmnet.io/gpu` | `orin` |
+//! | `llmnet.io/vram-gb` | `80` |
+//!
+//! The GPU labels are left out on machines without an NVIDIA GPU.
 
 use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::maintenance::{MaintenanceWindow, MAINTENANCE_ANNOTATION};
+use super::API_VERSION;
+use crate::config::{RunnerType, ADAPTER_TYPES};
 
 /// A Node in the LLMNet cluster
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Node {
     /// API version
     #[serde(rename = "apiVersion")]
@@ -33,7 +53,7 @@ pub struct Node {
 }
 
 /// Metadata for a Node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NodeMetadata {
     /// Unique name for this node
     pub name: String,
@@ -48,7 +68,7 @@ pub struct NodeMetadata {
 }
 
 /// Node specification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NodeSpec {
     /// Address where this node can be reached (e.g., "192.168.1.100")
     pub address: String,
@@ -60,6 +80,51 @@ pub struct NodeSpec {
     /// Whether this node can accept new pipelines
     #[serde(default = "default_true")]
     pub schedulable: bool,
+
+    /// Features advertised by the worker at registration time.
+    /// Nodes registered by older workers have no capabilities and are
+    /// treated as able to run anything.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<NodeCapabilities>,
+
+    /// Recurring windows during which the node is cordoned and drained
+    #[serde(rename = "maintenanceWindows")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+
+    /// Seconds between this node's heartbeats, when it doesn't use the
+    /// cluster default; the node is considered stale after three missed
+    #[serde(rename = "heartbeatIntervalSecs")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval_secs: Option<u64>,
+}
+
+/// Features a worker supports, exchanged during registration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NodeCapabilities {
+    /// API version spoken by the worker (e.g., "llmnet/v1")
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+
+    /// LLMNet version of the worker binary
+    #[serde(rename = "llmnetVersion")]
+    pub llmnet_version: String,
+
+    /// Runner types installed on the worker (e.g., "vllm", "ollama")
+    #[serde(default)]
+    pub runners: Vec<String>,
+
+    /// Adapters compiled into the worker (e.g., "openai-api", "output")
+    #[serde(default)]
+    pub adapters: Vec<String>,
+
+    /// Maximum pipelines the worker is willing to host
+    #[serde(rename = "maxPipelines")]
+    #[serde(default = "default_max_pipelines")]
+    pub max_pipelines: u32,
 }
 
 fn default_node_port() -> u16 {
@@ -71,7 +136,7 @@ fn default_true() -> bool {
 }
 
 /// Current status of a Node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NodeStatus {
     /// Overall phase: Ready, NotReady, Unknown
     pub phase: NodePhase,
@@ -109,8 +174,44 @@ pub struct NodeStatus {
     pub score: Option<NodeScore>,
 }
 
+/// Partial node status sent by heartbeats when little has changed
+///
+/// Only fields that differ from the previously sent status are populated;
+/// the control plane merges them into the stored status.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NodeStatusDelta {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<NodePhase>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conditions: Option<Vec<NodeCondition>>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<NodeCapacity>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allocatable: Option<NodeCapacity>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipelines: Option<Vec<NodePipelineInfo>>,
+
+    #[serde(rename = "nodeInfo")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_info: Option<NodeInfo>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<NodeMetrics>,
+}
+
 /// Phase of a node
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default, ToSchema)]
 pub enum NodePhase {
     /// Node is ready to accept pipelines
     Ready,
@@ -124,7 +225,7 @@ pub enum NodePhase {
 }
 
 /// A condition of a Node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct NodeCondition {
     /// Type of condition
     #[serde(rename = "type")]
@@ -149,7 +250,7 @@ pub struct NodeCondition {
 }
 
 /// Types of node conditions
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum NodeConditionType {
     /// Node is ready to accept pipelines
     Ready,
@@ -163,10 +264,12 @@ pub enum NodeConditionType {
     NetworkUnavailable,
     /// GPU is available
     GPUAvailable,
+    /// Runner containers from before the worker restarted were reconciled
+    RunnersAdopted,
 }
 
 /// Real-time metrics reported by a node
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, ToSchema)]
 pub struct NodeMetrics {
     /// CPU utilization percentage (0.0 - 100.0)
     #[serde(rename = "cpuUsagePercent")]
@@ -188,6 +291,10 @@ pub struct NodeMetrics {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gpu_memory_usage_percent: Option<f64>,
 
+    /// Utilization and memory of each GPU, empty if none were read
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gpus: Vec<GpuMetrics>,
+
     /// Disk utilization percentage (0.0 - 100.0)
     #[serde(rename = "diskUsagePercent")]
     #[serde(default)]
@@ -214,8 +321,41 @@ pub struct NodeMetrics {
     pub collected_at: DateTime<Utc>,
 }
 
+/// Real-time metrics of one GPU on a node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, ToSchema)]
+pub struct GpuMetrics {
+    /// Device index, as in `CUDA_VISIBLE_DEVICES`
+    pub index: u32,
+
+    /// GPU utilization percentage (0.0 - 100.0)
+    #[serde(rename = "usagePercent")]
+    #[serde(default)]
+    pub usage_percent: f64,
+
+    /// GPU memory in use, in bytes
+    #[serde(rename = "memoryUsed")]
+    #[serde(default)]
+    pub memory_used: u64,
+
+    /// GPU memory in total, in bytes
+    #[serde(rename = "memoryTotal")]
+    #[serde(default)]
+    pub memory_total: u64,
+}
+
+impl GpuMetrics {
+    /// GPU memory utilization percentage (0.0 - 100.0)
+    pub fn memory_usage_percent(&self) -> f64 {
+        if self.memory_total > 0 {
+            (self.memory_used as f64 / self.memory_total as f64) * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
 /// Calculated score for a node (higher = more preferred for scheduling)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NodeScore {
     /// Overall score (0.0 - 100.0)
     pub score: f64,
@@ -239,7 +379,7 @@ impl Default for NodeScore {
 }
 
 /// Breakdown of score components
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct ScoreBreakdown {
     /// CPU availability score (100 - usage%)
     #[serde(rename = "cpuScore")]
@@ -261,10 +401,14 @@ pub struct ScoreBreakdown {
     /// Load score based on active requests
     #[serde(rename = "loadScore")]
     pub load_score: f64,
+
+    /// Latency score based on average request latency
+    #[serde(rename = "latencyScore", default)]
+    pub latency_score: f64,
 }
 
 /// Resource capacity of a node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct NodeCapacity {
     /// Number of CPU cores
     #[serde(default)]
@@ -306,7 +450,7 @@ fn default_max_pipelines() -> u32 {
 }
 
 /// Information about a pipeline running on this node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct NodePipelineInfo {
     /// Pipeline name
     pub name: String,
@@ -319,10 +463,18 @@ pub struct NodePipelineInfo {
 
     /// Status of this replica
     pub status: ReplicaStatus,
+
+    /// Why the replica is unhealthy or failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Times the worker restarted the replica
+    #[serde(default)]
+    pub restarts: u32,
 }
 
 /// Status of a pipeline replica on a node
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum ReplicaStatus {
     /// Replica is starting up
     Starting,
@@ -337,7 +489,7 @@ pub enum ReplicaStatus {
 }
 
 /// System information about a node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct NodeInfo {
     /// Operating system
     pub os: String,
@@ -366,20 +518,23 @@ pub struct NodeInfo {
 }
 
 impl Node {
-    /// Create a new Node with minimal configuration
+    /// Create a new Node for this machine, labeled with its hardware
     pub fn new(name: impl Into<String>, address: impl Into<String>) -> Self {
         Self {
             api_version: "llmnet/v1".to_string(),
             kind: "Node".to_string(),
             metadata: NodeMetadata {
                 name: name.into(),
-                labels: HashMap::new(),
+                labels: system_labels(&NodeInfo::from_system(), host_gpus().total_memory_bytes),
                 annotations: HashMap::new(),
             },
             spec: NodeSpec {
                 address: address.into(),
                 port: default_node_port(),
                 schedulable: true,
+                capabilities: None,
+                maintenance_windows: Vec::new(),
+                heartbeat_interval_secs: None,
             },
             status: None,
         }
@@ -397,6 +552,24 @@ impl Node {
         self
     }
 
+    /// Set the capabilities advertised at registration
+    pub fn with_capabilities(mut self, capabilities: NodeCapabilities) -> Self {
+        self.spec.capabilities = Some(capabilities);
+        self
+    }
+
+    /// Set the interval this node sends heartbeats at
+    pub fn with_heartbeat_interval(mut self, secs: u64) -> Self {
+        self.spec.heartbeat_interval_secs = Some(secs);
+        self
+    }
+
+    /// Add a recurring maintenance window
+    pub fn with_maintenance_window(mut self, window: MaintenanceWindow) -> Self {
+        self.spec.maintenance_windows.push(window);
+        self
+    }
+
     /// Mark as unschedulable (cordon)
     pub fn cordon(mut self) -> Self {
         self.spec.schedulable = false;
@@ -416,6 +589,22 @@ impl Node {
             .unwrap_or(false)
     }
 
+    /// Whether the controller cordoned this node for a maintenance window
+    pub fn is_in_maintenance(&self) -> bool {
+        self.metadata
+            .annotations
+            .contains_key(MAINTENANCE_ANNOTATION)
+    }
+
+    /// Seconds without a heartbeat before this node is stale: the cluster's
+    /// `default_secs`, or three of the node's own intervals if that is longer
+    pub fn heartbeat_timeout(&self, default_secs: i64) -> i64 {
+        let own = self.spec.heartbeat_interval_secs.map_or(0, |secs| {
+            i64::try_from(secs.saturating_mul(3)).unwrap_or(i64::MAX)
+        });
+        default_secs.max(own)
+    }
+
     /// Check if node can accept new pipelines
     pub fn can_schedule(&self) -> bool {
         self.spec.schedulable && self.is_ready()
@@ -428,10 +617,33 @@ impl Node {
 
     /// Check if node has capacity for more pipelines
     pub fn has_capacity(&self) -> bool {
+        self.free_slots() > 0
+    }
+
+    /// How many more pipelines the node can take
+    ///
+    /// The advertised `maxPipelines` capability caps the reported capacity.
+    pub fn free_slots(&self) -> usize {
+        let advertised = self.spec.capabilities.as_ref().map(|c| c.max_pipelines);
         self.status
             .as_ref()
-            .map(|s| s.pipelines.len() < s.capacity.max_pipelines as usize)
-            .unwrap_or(false)
+            .map(|s| {
+                let max = advertised.map_or(s.capacity.max_pipelines, |a| {
+                    a.min(s.capacity.max_pipelines)
+                });
+                (max as usize).saturating_sub(s.pipelines.len())
+            })
+            .unwrap_or(0)
+    }
+
+    /// Check if this node can run every runner type in `runners`
+    ///
+    /// Nodes without advertised capabilities are assumed to support everything.
+    pub fn supports_runners(&self, runners: &[RunnerType]) -> bool {
+        match &self.spec.capabilities {
+            Some(caps) => runners.iter().all(|r| caps.supports_runner(r)),
+            None => true,
+        }
     }
 }
 
@@ -467,6 +679,118 @@ impl NodeStatus {
         let now = Utc::now();
         (now - self.last_heartbeat).num_seconds() > threshold_secs
     }
+
+    /// Compute the fields that changed since `previous` was sent
+    ///
+    /// Metrics are only included when they moved by at least
+    /// `metrics_threshold` percentage points (or request counts changed).
+    pub fn diff(&self, previous: &NodeStatus, metrics_threshold: f64) -> NodeStatusDelta {
+        fn changed<T: PartialEq + Clone>(current: &T, previous: &T) -> Option<T> {
+            (current != previous).then(|| current.clone())
+        }
+
+        let metrics = match (&self.metrics, &previous.metrics) {
+            (Some(current), Some(prev)) if !current.differs_from(prev, metrics_threshold) => None,
+            (current, _) => current.clone(),
+        };
+
+        NodeStatusDelta {
+            phase: changed(&self.phase, &previous.phase),
+            conditions: changed(&self.conditions, &previous.conditions),
+            capacity: changed(&self.capacity, &previous.capacity),
+            allocatable: changed(&self.allocatable, &previous.allocatable),
+            pipelines: changed(&self.pipelines, &previous.pipelines),
+            node_info: changed(&self.node_info, &previous.node_info),
+            metrics,
+        }
+    }
+
+    /// Merge a delta into this status and refresh the heartbeat timestamp
+    pub fn apply(&mut self, delta: NodeStatusDelta) {
+        if let Some(phase) = delta.phase {
+            self.phase = phase;
+        }
+        if let Some(conditions) = delta.conditions {
+            self.conditions = conditions;
+        }
+        if let Some(capacity) = delta.capacity {
+            self.capacity = capacity;
+        }
+        if let Some(allocatable) = delta.allocatable {
+            self.allocatable = allocatable;
+        }
+        if let Some(pipelines) = delta.pipelines {
+            self.pipelines = pipelines;
+        }
+        if let Some(node_info) = delta.node_info {
+            self.node_info = node_info;
+        }
+        if let Some(metrics) = delta.metrics {
+            self.metrics = Some(metrics);
+        }
+        self.heartbeat();
+    }
+}
+
+impl NodeMetrics {
+    /// Whether utilization moved by at least `threshold` percentage points,
+    /// or the request counters changed
+    pub fn differs_from(&self, other: &NodeMetrics, threshold: f64) -> bool {
+        self.utilization_moved(other, threshold)
+            || self.active_requests != other.active_requests
+            || self.request_count != other.request_count
+    }
+
+    /// Whether utilization moved by at least `threshold` percentage points,
+    /// or active requests or the request rate by at least `threshold`
+    /// percent of the larger value
+    ///
+    /// `request_count` only counts requests since the previous sample, so
+    /// it is compared per second of `period` and `other_period`, the time
+    /// each sample's count was collected over.
+    pub fn load_changed(
+        &self,
+        other: &NodeMetrics,
+        threshold: f64,
+        period: Duration,
+        other_period: Duration,
+    ) -> bool {
+        let moved = |a: f64, b: f64| {
+            let larger = a.max(b);
+            larger > 0.0 && (a - b).abs() * 100.0 / larger >= threshold
+        };
+        let rate = |count: u64, period: Duration| count as f64 / period.as_secs_f64().max(1.0);
+
+        self.utilization_moved(other, threshold)
+            || moved(self.active_requests.into(), other.active_requests.into())
+            || moved(
+                rate(self.request_count, period),
+                rate(other.request_count, other_period),
+            )
+    }
+
+    fn utilization_moved(&self, other: &NodeMetrics, threshold: f64) -> bool {
+        let moved = |a: f64, b: f64| (a - b).abs() >= threshold;
+        let moved_opt = |a: Option<f64>, b: Option<f64>| match (a, b) {
+            (Some(a), Some(b)) => moved(a, b),
+            (None, None) => false,
+            _ => true,
+        };
+
+        moved(self.cpu_usage_percent, other.cpu_usage_percent)
+            || moved(self.memory_usage_percent, other.memory_usage_percent)
+            || moved(self.disk_usage_percent, other.disk_usage_percent)
+            || moved_opt(self.gpu_usage_percent, other.gpu_usage_percent)
+            || moved_opt(
+                self.gpu_memory_usage_percent,
+                other.gpu_memory_usage_percent,
+            )
+            || self.gpus.len() != other.gpus.len()
+            || self.gpus.iter().zip(&other.gpus).any(|(a, b)| {
+                moved(a.usage_percent, b.usage_percent)
+                    || moved(a.memory_usage_percent(), b.memory_usage_percent())
+            })
+    }
 }
 
 impl NodeInfo {
@@ -477,18 +801,289 @@ impl NodeInfo {
             architecture: std::env::consts::ARCH.to_string(),
             llmnet_version: env!("CARGO_PKG_VERSION").to_string(),
             kernel_version: None,
-            cuda_version: None,
-            gpu_models: vec![],
+            cuda_version: host_gpus().cuda_version.clone(),
+            gpu_models: host_gpus().models.clone(),
         }
     }
 }
 
+// ============================================================================
+// SBIO: Pure system labels
+// ============================================================================
+
+/// Operating system label, e.g. "linux"
+pub const OS_LABEL: &str = "llmnet.io/os";
+
+/// CPU architecture label, in Docker's naming, e.g. "arm64"
+pub const ARCH_LABEL: &str = "llmnet.io/arch";
+
+/// CUDA version label, e.g. "12.2"
+pub const CUDA_LABEL: &str = "llmnet.io/cuda";
+
+/// Label of the first GPU's model, e.g. "a100-sxm4-80gb" or "orin"
+pub const GPU_LABEL: &str = "llmnet.io/gpu";
+
+/// Label of the memory of all GPUs together, in whole GiB
+pub const VRAM_LABEL: &str = "llmnet.io/vram-gb";
+
+/// The GPUs of a machine
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostGpus {
+    pub cuda_version: Option<String>,
+    pub models: Vec<String>,
+    pub total_memory_bytes: u64,
+}
+
+/// Labels describing a machine's hardware
+pub fn system_labels(info: &NodeInfo, vram_bytes: u64) -> HashMap<String, String> {
+    let arch = match info.architecture.as_str() {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        other => other,
+    };
+    let mut labels = HashMap::from([
+        (OS_LABEL.to_string(), info.os.clone()),
+        (ARCH_LABEL.to_string(), arch.to_string()),
+    ]);
+    if let Some(cuda) = &info.cuda_version {
+        labels.insert(CUDA_LABEL.to_string(), cuda.clone());
+    }
+    if let Some(gpu) = info.gpu_models.first().map(|m| gpu_label(m)) {
+        labels.insert(GPU_LABEL.to_string(), gpu);
+    }
+    if vram_bytes > 0 {
+        let gib = (vram_bytes as f64 / (1024.0 * 1024.0 * 1024.0)).round();
+        labels.insert(VRAM_LABEL.to_string(), gib.to_string());
+    }
+    labels
+}
+
+/// A GPU model name as a label value: lowercase words joined by dashes,
+/// without the vendor and product line or anything in parentheses
+/// ("NVIDIA GeForce RTX 4090" is "rtx-4090", "Orin (nvgpu)" is "orin")
+pub fn gpu_label(model: &str) -> String {
+    let model = model.split('(').next().unwrap_or_default().to_lowercase();
+    model
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+        .filter(|word| !word.is_empty())
+        .filter(|word| !matches!(*word, "nvidia" | "geforce" | "tesla" | "jetson"))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Parse `nvidia-smi --query-gpu=name,memory.total --format=csv,noheader,nounits`
+/// into GPU models and their total memory in bytes
+pub fn parse_gpu_query(output: &str) -> (Vec<String>, u64) {
+    let mut models = Vec::new();
+    let mut memory = 0;
+    for line in output.lines() {
+        let Some((name, mib)) = line.rsplit_once(',') else {
+            continue;
+        };
+        models.push(name.trim().to_string());
+        memory += mib.trim().parse::<u64>().unwrap_or(0) * 1024 * 1024;
+    }
+    (models, memory)
+}
+
+/// The "major.minor" CUDA version in `nvidia-smi`'s banner, or in
+/// `/usr/local/cuda/version.json` where the driver tools are missing
+pub fn parse_cuda_version(output: &str) -> Option<String> {
+    let version = match output.split_once("CUDA Version:") {
+        Some((_, rest)) => rest.split_whitespace().next()?.to_string(),
+        None => {
+            let json: serde_json::Value = serde_json::from_str(output).ok()?;
+            json["cuda"]["version"].as_str()?.to_string()
+        }
+    };
+    Some(major_minor(&version).to_string())
+}
+
+/// The SoC of a Jetson from its device-tree model, e.g. "Orin" from
+/// "NVIDIA Jetson AGX Orin Developer Kit"
+pub fn parse_jetson_model(model: &str) -> Option<String> {
+    const FAMILIES: &[&str] = &["Thor", "Orin", "Xavier", "TX2", "TX1", "Nano"];
+    let model = model.trim_end_matches('\0');
+    if !model.contains("Jetson") {
+        return None;
+    }
+    FAMILIES
+        .iter()
+        .find(|family| model.split_whitespace().any(|word| word == **family))
+        .map(|family| family.to_string())
+}
+
+// ============================================================================
+// SBIO: Pure capability negotiation
+// ============================================================================
+
+/// Runner types paired with the binary whose presence indicates support
+const RUNNER_BINARIES: &[(RunnerType, &str)] = &[
+    (RunnerType::Ollama, "ollama"),
+    (RunnerType::Vllm, "vllm"),
+    (RunnerType::LlamaCpp, "llama-server"),
+    // A llamafile brings its own server; sh starts it where the kernel can't
+    (RunnerType::Llamafile, "sh"),
+    (RunnerType::Docker, "docker"),
+    (RunnerType::TensorRtLlm, "trtllm-serve"),
+    // TGI runs in a container, so Docker is all it needs
+    (RunnerType::Tgi, "docker"),
+    // whisper.cpp's server, or faster-whisper in a container
+    (RunnerType::Whisper, "whisper-server"),
+    (RunnerType::Whisper, "docker"),
+];
+
+impl NodeCapabilities {
+    /// Create capabilities for this build with the given runners
+    pub fn new(runners: Vec<String>) -> Self {
+        Self {
+            api_version: API_VERSION.to_string(),
+            llmnet_version: env!("CARGO_PKG_VERSION").to_string(),
+            runners,
+            adapters: ADAPTER_TYPES.iter().map(|a| a.to_string()).collect(),
+            max_pipelines: default_max_pipelines(),
+        }
+    }
+
+    /// Set the maximum number of pipelines
+    pub fn with_max_pipelines(mut self, max: u32) -> Self {
+        self.max_pipelines = max;
+        self
+    }
+
+    /// Check if a runner type is available on this node
+    ///
+    /// External runners only need network access, so they are always supported.
+    pub fn supports_runner(&self, runner: &RunnerType) -> bool {
+        *runner == RunnerType::External || self.runners.iter().any(|r| r == runner.as_str())
+    }
+
+    /// Compare against the control plane's versions and describe any skew
+    pub fn compatibility_warnings(&self, api_version: &str, llmnet_version: &str) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if self.api_version != api_version {
+            warnings.push(format!(
+                "worker speaks API {} but control plane speaks {}",
+                self.api_version, api_version
+            ));
+        }
+
+        if major_minor(&self.llmnet_version) != major_minor(llmnet_version) {
+            warnings.push(format!(
+                "version skew: worker is llmnet {} but control plane is {}",
+                self.llmnet_version, llmnet_version
+            ));
+        }
+
+        warnings
+    }
+}
+
+/// Extract the "major.minor" prefix of a semantic version
+fn major_minor(version: &str) -> &str {
+    match version.match_indices('.').nth(1) {
+        Some((idx, _)) => &version[..idx],
+        None => version,
+    }
+}
+
+/// Select the runners whose binaries were found.
+/// This is a pure function - the lookup is injected.
+pub fn detect_runners(is_installed: impl Fn(&str) -> bool) -> Vec<String> {
+    RUNNER_BINARIES
+        .iter()
+        .filter(|(_, binary)| is_installed(binary))
+        .map(|(runner, _)| runner.as_str().to_string())
+        .fold(Vec::new(), |mut runners, runner| {
+            if !runners.contains(&runner) {
+                runners.push(runner);
+            }
+            runners
+        })
+}
+
+// ============================================================================
+// I/O: Capability detection
+// ============================================================================
+
+impl NodeCapabilities {
+    /// Detect capabilities of the current system by probing PATH
+    pub fn detect() -> Self {
+        Self::new(detect_runners(binary_on_path))
+    }
+}
+
+/// Check whether an executable with this name exists on PATH
+fn binary_on_path(binary: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(binary).is_file()))
+        .unwrap_or(false)
+}
+
+// ============================================================================
+// I/O: GPU detection
+// ============================================================================
+
+/// The GPUs of this machine, detected on first use
+pub fn host_gpus() -> &'static HostGpus {
+    static GPUS: OnceLock<HostGpus> = OnceLock::new();
+    GPUS.get_or_init(detect_gpus)
+}
+
+/// Ask `nvidia-smi`, falling back to the device tree and CUDA toolkit on
+/// Jetsons without it
+fn detect_gpus() -> HostGpus {
+    let run = |args: &[&str]| {
+        std::process::Command::new("nvidia-smi")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+    };
+    if !binary_on_path("nvidia-smi") {
+        let jetson = std::fs::read_to_string("/proc/device-tree/model")
+            .ok()
+            .and_then(|model| parse_jetson_model(&model));
+        return HostGpus {
+            cuda_version: jetson.as_ref().and_then(|_| {
+                let json = std::fs::read_to_string("/usr/local/cuda/version.json").ok()?;
+                parse_cuda_version(&json)
+            }),
+            models: jetson.into_iter().collect(),
+            total_memory_bytes: 0,
+        };
+    }
+    let (models, total_memory_bytes) = run(&[
+        "--query-gpu=name,memory.total",
+        "--format=csv,noheader,nounits",
+    ])
+    .map(|out| parse_gpu_query(&out))
+    .unwrap_or_default();
+    HostGpus {
+        cuda_version: run(&[]).and_then(|out| parse_cuda_version(&out)),
+        models,
+        total_memory_bytes,
+    }
+}
+
 impl NodeCondition {
     /// Create a Ready condition
     pub fn ready(status: bool, reason: &str, message: &str) -> Self {
+        Self::new(NodeConditionType::Ready, status, reason, message)
+    }
+
+    /// Create a condition of any type
+    pub fn new(
+        condition_type: NodeConditionType,
+        status: bool,
+        reason: &str,
+        message: &str,
+    ) -> Self {
         let now = Utc::now();
         Self {
-            condition_type: NodeConditionType::Ready,
+            condition_type,
             status: if status { "True" } else { "False" }.to_string(),
             last_heartbeat_time: now,
             last_transition_time: now,
@@ -537,6 +1132,63 @@ mod tests {
         assert!(node.spec.schedulable);
     }
 
+    #[test]
+    fn test_system_labels() {
+        let info = NodeInfo {
+            os: "linux".to_string(),
+            architecture: "aarch64".to_string(),
+            llmnet_version: "0.1.0".to_string(),
+            kernel_version: None,
+            cuda_version: Some("12.2".to_string()),
+            gpu_models: vec!["Orin (nvgpu)".to_string()],
+        };
+        let labels = system_labels(&info, 0);
+        assert_eq!(labels[ARCH_LABEL], "arm64");
+        assert_eq!(labels[GPU_LABEL], "orin");
+        assert_eq!(labels[CUDA_LABEL], "12.2");
+        assert!(!labels.contains_key(VRAM_LABEL));
+
+        let (models, vram) =
+            parse_gpu_query("NVIDIA A100-SXM4-80GB, 81920\nNVIDIA A100-SXM4-80GB, 81920\n");
+        assert_eq!(models.len(), 2);
+        let info = NodeInfo {
+            architecture: "x86_64".to_string(),
+            gpu_models: models,
+            cuda_version: None,
+            ..info
+        };
+        let labels = system_labels(&info, vram);
+        assert_eq!(labels[ARCH_LABEL], "amd64");
+        assert_eq!(labels[GPU_LABEL], "a100-sxm4-80gb");
+        assert_eq!(labels[VRAM_LABEL], "160");
+        assert!(!labels.contains_key(CUDA_LABEL));
+
+        // Machine labels are there for selectors, and can be overridden
+        let node = Node::new("node", "localhost").with_label(OS_LABEL, "custom");
+        assert_eq!(node.metadata.labels[OS_LABEL], "custom");
+        assert!(node.metadata.labels.contains_key(ARCH_LABEL));
+    }
+
+    #[test]
+    fn test_gpu_detection_parsing() {
+        assert_eq!(gpu_label("NVIDIA GeForce RTX 4090"), "rtx-4090");
+        assert_eq!(gpu_label("Tesla T4"), "t4");
+        assert_eq!(
+            parse_cuda_version("| NVIDIA-SMI 550.54  Driver Version: 550.54  CUDA Version: 12.4 |"),
+            Some("12.4".to_string())
+        );
+        assert_eq!(
+            parse_cuda_version(r#"{"cuda": {"name": "CUDA SDK", "version": "12.2.140"}}"#),
+            Some("12.2".to_string())
+        );
+        assert_eq!(parse_cuda_version("no driver"), None);
+        assert_eq!(
+            parse_jetson_model("NVIDIA Jetson AGX Orin Developer Kit\0"),
+            Some("Orin".to_string())
+        );
+        assert_eq!(parse_jetson_model("Raspberry Pi 5 Model B"), None);
+    }
+
     #[test]
     fn test_node_builder() {
         let node = Node::new("gpu-node", "10.0.0.1")
@@ -592,6 +1244,140 @@ mod tests {
         assert!(!info.llmnet_version.is_empty());
     }
 
+    #[test]
+    fn test_capabilities_roundtrip() {
+        let node = Node::new("node", "localhost").with_capabilities(
+            NodeCapabilities::new(vec!["vllm".to_string()]).with_max_pipelines(2),
+        );
+
+        let json = serde_json::to_string(&node).unwrap();
+        assert!(json.contains("\"maxPipelines\":2"));
+
+        let parsed: Node = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.spec.capabilities, node.spec.capabilities);
+
+        // Every adapter type a composition can use is advertised
+        let adapters = &node.spec.capabilities.unwrap().adapters;
+        assert_eq!(adapters, ADAPTER_TYPES);
+    }
+
+    #[test]
+    fn test_legacy_node_has_no_capabilities() {
+        let json = r#"{"apiVersion":"llmnet/v1","kind":"Node","metadata":{"name":"old"},"spec":{"address":"10.0.0.1"}}"#;
+        let node: Node = serde_json::from_str(json).unwrap();
+
+        assert!(node.spec.capabilities.is_none());
+        assert!(node.supports_runners(&[RunnerType::Vllm]));
+    }
+
+    #[test]
+    fn test_supports_runners() {
+        let node = Node::new("node", "localhost")
+            .with_capabilities(NodeCapabilities::new(vec!["ollama".to_string()]));
+
+        assert!(node.supports_runners(&[RunnerType::Ollama, RunnerType::External]));
+        assert!(!node.supports_runners(&[RunnerType::Vllm]));
+        assert!(node.supports_runners(&[]));
+    }
+
+    #[test]
+    fn test_detect_runners() {
+        let runners = detect_runners(|bin| bin == "llama-server" || bin == "docker");
+        assert_eq!(runners, vec!["llama-cpp", "docker", "tgi", "whisper"]);
+        assert_eq!(
+            detect_runners(|bin| bin == "whisper-server"),
+            vec!["whisper"]
+        );
+        assert_eq!(detect_runners(|bin| bin == "sh"), vec!["llamafile"]);
+        assert!(detect_runners(|_| false).is_empty());
+    }
+
+    #[test]
+    fn test_compatibility_warnings() {
+        let caps = NodeCapabilities::new(vec![]);
+        assert!(caps
+            .compatibility_warnings(API_VERSION, &caps.llmnet_version.clone())
+            .is_empty());
+
+        let mut old = caps.clone();
+        old.llmnet_version = "0.0.1".to_string();
+        old.api_version = "llmnet/v0".to_string();
+        let warnings = old.compatibility_warnings(API_VERSION, "1.2.3");
+        assert_eq!(warnings.len(), 2);
+
+        // Patch releases are compatible
+        old.api_version = API_VERSION.to_string();
+        old.llmnet_version = "1.2.0".to_string();
+        assert!(old.compatibility_warnings(API_VERSION, "1.2.9").is_empty());
+    }
+
+    #[test]
+    fn test_advertised_max_pipelines_caps_capacity() {
+        let mut node = Node::new("node", "localhost")
+            .with_capabilities(NodeCapabilities::new(vec![]).with_max_pipelines(1));
+        let mut status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        node.status = Some(status.clone());
+        assert!(node.has_capacity());
+
+        status.pipelines.push(NodePipelineInfo {
+            name: "p".to_string(),
+            namespace: "default".to_string(),
+            port: 8080,
+            status: ReplicaStatus::Running,
+            reason: None,
+            restarts: 0,
+        });
+        node.status = Some(status);
+        assert!(!node.has_capacity());
+    }
+
+    #[test]
+    fn test_status_diff_only_includes_changes() {
+        let previous = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system())
+            .with_metrics(NodeMetrics {
+                cpu_usage_percent: 10.0,
+                ..Default::default()
+            });
+
+        let mut current = previous.clone();
+        let delta = current.diff(&previous, 5.0);
+        assert_eq!(delta, NodeStatusDelta::default());
+
+        // Small metric drift is suppressed, large changes are sent
+        current.metrics.as_mut().unwrap().cpu_usage_percent = 12.0;
+        assert!(current.diff(&previous, 5.0).metrics.is_none());
+        current.metrics.as_mut().unwrap().cpu_usage_percent = 40.0;
+        current.phase = NodePhase::NotReady;
+
+        let delta = current.diff(&previous, 5.0);
+        assert_eq!(delta.phase, Some(NodePhase::NotReady));
+        assert!(delta.metrics.is_some());
+        assert!(delta.pipelines.is_none());
+        assert!(delta.capacity.is_none());
+    }
+
+    #[test]
+    fn test_status_apply_delta() {
+        let mut status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        status.last_heartbeat = Utc::now() - chrono::Duration::seconds(120);
+
+        status.apply(NodeStatusDelta {
+            pipelines: Some(vec![NodePipelineInfo {
+                name: "p".to_string(),
+                namespace: "default".to_string(),
+                port: 8080,
+                status: ReplicaStatus::Running,
+                reason: None,
+                restarts: 0,
+            }]),
+            ..Default::default()
+        });
+
+        assert_eq!(status.pipelines.len(), 1);
+        assert_eq!(status.phase, NodePhase::Ready);
+        assert!(!status.is_stale(60));
+    }
+
     #[test]
     fn test_heartbeat_staleness() {
         let mut status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
@@ -603,4 +1389,17 @@ mod tests {
         status.last_heartbeat = Utc::now() - chrono::Duration::seconds(120);
         assert!(status.is_stale(60));
     }
+
+    #[test]
+    fn test_heartbeat_timeout() {
+        let node = Node::new("worker-1", "10.0.0.1");
+        assert_eq!(node.heartbeat_timeout(90), 90);
+        assert_eq!(
+            node.clone()
+                .with_heartbeat_interval(10)
+                .heartbeat_timeout(90),
+            90
+        );
+        assert_eq!(node.with_heartbeat_interval(60).heartbeat_timeout(90), 180);
+    }
 }
@@ -0,0 +1,281 @@
+//! NodePool resource - a named group of nodes with shared limits
+//!
+//! A NodePool gathers the nodes whose labels match its selector, so a
+//! pipeline can ask for `nodePool: gpu-pool` instead of repeating the
+//! labels of every GPU node:
+//!
+//! ```yaml
+//! apiVersion: llmnet/v1
+//! kind: NodePool
+//! metadata:
+//!   name: gpu-pool
+//! spec:
+//!   nodeSelector:
+//!     accelerator: nvidia
+//!   maxPipelines: 8
+//!   allowedNamespaces: [production, staging]
+//! ```
+//!
+//! `maxPipelines` caps the pipeline replicas all of the pool's nodes host
+//! together, and `allowedNamespaces` limits which namespaces may target
+//! the pool. Pools are cluster-wide, like nodes; a node can be in several.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::node::{Node, NodeMetadata};
+use super::pipeline::Pipeline;
+use super::API_VERSION;
+
+/// A named group of nodes
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NodePool {
+    /// API version (e.g., "llmnet/v1")
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+
+    /// Kind is always "NodePool"
+    pub kind: String,
+
+    /// Name, labels and annotations of the pool
+    pub metadata: NodeMetadata,
+
+    /// Which nodes are in the pool and what it allows
+    pub spec: NodePoolSpec,
+
+    /// Members and load (populated by controller)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<NodePoolStatus>,
+}
+
+/// Specification of a NodePool
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NodePoolSpec {
+    /// Labels a node needs to be in the pool; empty takes every node
+    #[serde(rename = "nodeSelector")]
+    #[serde(default)]
+    pub node_selector: HashMap<String, String>,
+
+    /// Most pipeline replicas the pool's nodes host together
+    #[serde(rename = "maxPipelines")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pipelines: Option<u32>,
+
+    /// Namespaces whose pipelines may target the pool; empty allows all
+    #[serde(rename = "allowedNamespaces")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_namespaces: Vec<String>,
+}
+
+/// Observed state of a NodePool
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NodePoolStatus {
+    /// Names of the nodes in the pool
+    pub nodes: Vec<String>,
+
+    /// Pipeline replicas the pool's nodes host
+    pub pipelines: u32,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl NodePool {
+    /// Create a pool of the nodes with the given labels
+    pub fn new(name: impl Into<String>, node_selector: HashMap<String, String>) -> Self {
+        Self {
+            api_version: API_VERSION.to_string(),
+            kind: "NodePool".to_string(),
+            metadata: NodeMetadata {
+                name: name.into(),
+                labels: HashMap::new(),
+                annotations: HashMap::new(),
+            },
+            spec: NodePoolSpec {
+                node_selector,
+                ..Default::default()
+            },
+            status: None,
+        }
+    }
+
+    /// Cap the pipeline replicas the pool hosts
+    pub fn with_max_pipelines(mut self, max: u32) -> Self {
+        self.spec.max_pipelines = Some(max);
+        self
+    }
+
+    /// Only let these namespaces target the pool
+    pub fn with_allowed_namespaces(mut self, namespaces: Vec<String>) -> Self {
+        self.spec.allowed_namespaces = namespaces;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.metadata.name
+    }
+
+    /// Whether a node is in the pool
+    pub fn contains(&self, node: &Node) -> bool {
+        self.spec
+            .node_selector
+            .iter()
+            .all(|(k, v)| node.metadata.labels.get(k) == Some(v))
+    }
+
+    /// Whether pipelines of a namespace may target the pool
+    pub fn allows_namespace(&self, namespace: &str) -> bool {
+        self.spec.allowed_namespaces.is_empty()
+            || self.spec.allowed_namespaces.iter().any(|n| n == namespace)
+    }
+
+    /// Members and load of the pool among `nodes`
+    pub fn observe(&self, nodes: &[Node]) -> NodePoolStatus {
+        let members: Vec<&Node> = nodes.iter().filter(|n| self.contains(n)).collect();
+        let mut names: Vec<String> = members.iter().map(|n| n.metadata.name.clone()).collect();
+        names.sort();
+        NodePoolStatus {
+            nodes: names,
+            pipelines: members.iter().map(|n| n.pipeline_count() as u32).sum(),
+        }
+    }
+
+    /// Replicas of `pipeline` the pool has room for under `maxPipelines`,
+    /// counting the ones it already hosts as free; `None` without a cap
+    pub fn room_for(&self, pipeline: &Pipeline, nodes: &[Node]) -> Option<u32> {
+        let max = self.spec.max_pipelines?;
+        let hosted: usize = nodes
+            .iter()
+            .filter(|n| self.contains(n))
+            .filter_map(|n| n.status.as_ref())
+            .flat_map(|s| &s.pipelines)
+            .filter(|p| {
+                p.namespace != pipeline.metadata.namespace || p.name != pipeline.metadata.name
+            })
+            .count();
+        Some(max.saturating_sub(hosted as u32))
+    }
+}
+
+impl NodePoolSpec {
+    /// Check the spec can admit pipelines
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_pipelines == Some(0) {
+            return Err("spec.maxPipelines must be at least 1".to_string());
+        }
+        if self.allowed_namespaces.iter().any(|n| n.is_empty()) {
+            return Err("spec.allowedNamespaces can't contain an empty name".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{
+        NodeCapacity, NodeInfo, NodePipelineInfo, NodeStatus, ReplicaStatus,
+    };
+    use crate::config::Composition;
+
+    fn node(name: &str, accelerator: &str, pipelines: &[&str]) -> Node {
+        let mut node = Node::new(name, "10.0.0.1").with_label("accelerator", accelerator);
+        let mut status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        status.pipelines = pipelines
+            .iter()
+            .map(|p| NodePipelineInfo {
+                name: p.to_string(),
+                namespace: "default".to_string(),
+                port: 8080,
+                status: ReplicaStatus::Running,
+                reason: None,
+                restarts: 0,
+            })
+            .collect();
+        node.status = Some(status);
+        node
+    }
+
+    fn gpu_pool() -> NodePool {
+        NodePool::new(
+            "gpu-pool",
+            HashMap::from([("accelerator".to_string(), "nvidia".to_string())]),
+        )
+    }
+
+    #[test]
+    fn test_membership_and_status() {
+        let nodes = vec![
+            node("gpu-2", "nvidia", &["chat", "code"]),
+            node("gpu-1", "nvidia", &["chat"]),
+            node("cpu-1", "none", &["embed"]),
+        ];
+        let pool = gpu_pool();
+        assert!(pool.contains(&nodes[0]));
+        assert!(!pool.contains(&nodes[2]));
+
+        let status = pool.observe(&nodes);
+        assert_eq!(status.nodes, ["gpu-1", "gpu-2"]);
+        assert_eq!(status.pipelines, 3);
+
+        // An empty selector takes every node
+        assert_eq!(
+            NodePool::new("all", HashMap::new())
+                .observe(&nodes)
+                .pipelines,
+            4
+        );
+    }
+
+    #[test]
+    fn test_allowed_namespaces() {
+        let pool = gpu_pool();
+        assert!(pool.allows_namespace("anything"));
+
+        let pool = pool.with_allowed_namespaces(vec!["production".to_string()]);
+        assert!(pool.allows_namespace("production"));
+        assert!(!pool.allows_namespace("default"));
+    }
+
+    #[test]
+    fn test_room_for() {
+        let nodes = vec![
+            node("gpu-1", "nvidia", &["chat", "code"]),
+            node("gpu-2", "nvidia", &["chat"]),
+            node("cpu-1", "none", &["embed", "rerank"]),
+        ];
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let chat = Pipeline::new("chat", composition.clone());
+        let other = Pipeline::new("other", composition);
+
+        assert_eq!(gpu_pool().room_for(&chat, &nodes), None);
+        let pool = gpu_pool().with_max_pipelines(4);
+        // chat's own replicas would be replaced, so they don't count
+        assert_eq!(pool.room_for(&chat, &nodes), Some(3));
+        assert_eq!(pool.room_for(&other, &nodes), Some(1));
+        assert_eq!(
+            gpu_pool().with_max_pipelines(2).room_for(&other, &nodes),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(gpu_pool().spec.validate().is_ok());
+        assert!(gpu_pool().with_max_pipelines(0).spec.validate().is_err());
+        assert!(gpu_pool()
+            .with_allowed_namespaces(vec![String::new()])
+            .spec
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,429 @@
+//! Node sessions - a persistent WebSocket between a worker and the control plane
+//!
+//! After registering, a worker opens `GET /v1/nodes/{name}/session` and
+//! keeps it open. Heartbeats travel upstream over the session instead of
+//! HTTP, and the control plane pushes assignments and heartbeat requests
+//! downstream as soon as it makes them, so a pipeline is running on a worker
+//! within a second of being scheduled rather than after the next poll.
+//!
+//! The session complements the HTTP endpoints rather than replacing them:
+//! while it is down the worker heartbeats over HTTP and opens it again
+//! before its next heartbeat, and the orchestrator sends assignments with
+//! `POST /v1/assignments` to workers without a session.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::ws::{Message as WsMessage, WebSocket};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use super::controller::{ClusterController, ControllerError};
+use super::node::{NodeStatus, NodeStatusDelta};
+use super::orchestrator::{AssignmentResponse, PipelineAssignment};
+
+/// How long the control plane waits for a worker to answer an assignment
+/// pushed over its session
+pub const SESSION_ASSIGNMENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Errors pushing an assignment over a node session
+#[derive(Error, Debug, PartialEq)]
+pub enum SessionError {
+    #[error("Node '{0}' has no open session")]
+    NotConnected(String),
+
+    #[error("Session of node '{0}' closed before the worker answered")]
+    Closed(String),
+
+    #[error("Node '{0}' didn't answer within {1:?}")]
+    Timeout(String, Duration),
+}
+
+/// A message from a worker to the control plane
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum NodeMessage {
+    /// The node's full status, as `POST /v1/nodes/{name}/heartbeat`
+    Heartbeat { status: NodeStatus },
+    /// What changed since the last status, as `PATCH /v1/nodes/{name}/heartbeat`
+    HeartbeatDelta { delta: NodeStatusDelta },
+    /// The worker's answer to an [`ControlMessage::Assign`]
+    AssignmentResult {
+        id: Uuid,
+        response: AssignmentResponse,
+    },
+}
+
+/// A message from the control plane to a worker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ControlMessage {
+    /// Run a pipeline, as `POST /v1/assignments`; answered with an
+    /// [`NodeMessage::AssignmentResult`] carrying the same ID
+    Assign {
+        id: Uuid,
+        assignment: Box<PipelineAssignment>,
+    },
+    /// Send a heartbeat now
+    HeartbeatNow,
+    /// A delta arrived without a full status to apply it to; send the full
+    /// status
+    Resync,
+    /// The control plane doesn't know this node; register it again
+    Unregistered,
+}
+
+/// An assignment pushed over the session, for the worker to apply and answer
+#[derive(Debug)]
+pub struct AssignmentRequest {
+    pub assignment: PipelineAssignment,
+    pub reply: oneshot::Sender<AssignmentResponse>,
+}
+
+/// Open node sessions on the control plane, and assignments waiting for
+/// their worker's answer
+#[derive(Clone, Default)]
+pub struct NodeSessions {
+    /// Outgoing messages of each node's session, with the session's ID
+    sessions: Arc<DashMap<String, (Uuid, mpsc::UnboundedSender<ControlMessage>)>>,
+
+    /// Pushed assignments by ID, with the node they went to
+    pending: Arc<DashMap<Uuid, (String, oneshot::Sender<AssignmentResponse>)>>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// The session URL of a node on the control plane at `control_plane_url`
+pub fn session_url(control_plane_url: &str, node_name: &str) -> String {
+    let base = control_plane_url.trim_end_matches('/');
+    let base = if let Some(rest) = base.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = base.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        base.to_string()
+    };
+    format!("{}/v1/nodes/{}/session", base, node_name)
+}
+
+/// Apply a message from a node's worker; returns the reply to send, if any
+pub fn handle_node_message(
+    controller: &ClusterController,
+    node_name: &str,
+    message: NodeMessage,
+) -> Option<ControlMessage> {
+    let result = match message {
+        NodeMessage::Heartbeat { status } => controller.update_node_status(node_name, status),
+        NodeMessage::HeartbeatDelta { delta } => {
+            controller.apply_node_status_delta(node_name, delta)
+        }
+        NodeMessage::AssignmentResult { id, response } => {
+            controller.sessions().complete(id, response);
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => None,
+        Err(ControllerError::NodeNotFound(_)) => Some(ControlMessage::Unregistered),
+        Err(e) => {
+            debug!("Asking node '{}' for a full status: {}", node_name, e);
+            Some(ControlMessage::Resync)
+        }
+    }
+}
+
+impl NodeSessions {
+    /// Create an empty session registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a session for a node, replacing (and so closing) any earlier one
+    ///
+    /// Returns the session's ID and the messages to send to the worker.
+    pub fn open(&self, node_name: &str) -> (Uuid, mpsc::UnboundedReceiver<ControlMessage>) {
+        let id = Uuid::new_v4();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.sessions.insert(node_name.to_string(), (id, tx));
+        (id, rx)
+    }
+
+    /// Close a node's session, unless a newer one has replaced it
+    ///
+    /// Assignments still waiting on the node fail with
+    /// [`SessionError::Closed`].
+    pub fn close(&self, node_name: &str, id: Uuid) {
+        if self
+            .sessions
+            .remove_if(node_name, |_, (current, _)| *current == id)
+            .is_some()
+        {
+            self.pending.retain(|_, (node, _)| node != node_name);
+        }
+    }
+
+    /// Whether a node has an open session
+    pub fn is_connected(&self, node_name: &str) -> bool {
+        self.sessions.contains_key(node_name)
+    }
+
+    /// Queue a message for a node's worker; false without an open session
+    pub fn send(&self, node_name: &str, message: ControlMessage) -> bool {
+        self.sessions
+            .get(node_name)
+            .is_some_and(|session| session.1.send(message).is_ok())
+    }
+
+    /// Hand a worker's answer to the assignment waiting for it
+    pub fn complete(&self, id: Uuid, response: AssignmentResponse) {
+        match self.pending.remove(&id) {
+            Some((_, (_, waiter))) => {
+                let _ = waiter.send(response);
+            }
+            None => debug!("Dropping answer to unknown or expired assignment {}", id),
+        }
+    }
+
+    /// Push an assignment to a node's worker and wait for its answer
+    pub async fn assign(
+        &self,
+        node_name: &str,
+        assignment: PipelineAssignment,
+        timeout: Duration,
+    ) -> Result<AssignmentResponse, SessionError> {
+        let id = Uuid::new_v4();
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(id, (node_name.to_string(), tx));
+
+        let message = ControlMessage::Assign {
+            id,
+            assignment: Box::new(assignment),
+        };
+        if !self.send(node_name, message) {
+            self.pending.remove(&id);
+            return Err(SessionError::NotConnected(node_name.to_string()));
+        }
+
+        let result = tokio::time::timeout(timeout, rx).await;
+        self.pending.remove(&id);
+        match result {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(SessionError::Closed(node_name.to_string())),
+            Err(_) => Err(SessionError::Timeout(node_name.to_string(), timeout)),
+        }
+    }
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Serve a node's session until the worker disconnects or opens a new one
+pub async fn serve_node_session(
+    mut socket: WebSocket,
+    controller: Arc<ClusterController>,
+    node_name: String,
+) {
+    let sessions = controller.sessions().clone();
+    let (id, mut outgoing) = sessions.open(&node_name);
+    info!("Node '{}' opened a session", node_name);
+
+    loop {
+        tokio::select! {
+            message = outgoing.recv() => {
+                // None once a newer session replaced this one
+                let Some(message) = message else { break };
+                let payload = serde_json::to_string(&message).unwrap_or_default();
+                if socket.send(WsMessage::Text(payload.into())).await.is_err() {
+                    break;
+                }
+            }
+            frame = socket.recv() => {
+                let text = match frame {
+                    Some(Ok(WsMessage::Text(text))) => text,
+                    Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let reply = match serde_json::from_str::<NodeMessage>(&text) {
+                    Ok(message) => handle_node_message(&controller, &node_name, message),
+                    Err(e) => {
+                        warn!("Ignoring invalid message from node '{}': {}", node_name, e);
+                        None
+                    }
+                };
+                if let Some(reply) = reply {
+                    let payload = serde_json::to_string(&reply).unwrap_or_default();
+                    if socket.send(WsMessage::Text(payload.into())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    sessions.close(&node_name, id);
+    info!("Session of node '{}' closed", node_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{Node, NodeCapacity, NodeInfo};
+    use crate::config::Composition;
+
+    fn assignment() -> PipelineAssignment {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {},
+                "architecture": [
+                    {"name": "router", "layer": 0, "adapter": "openai-api"},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        PipelineAssignment {
+            namespace: "default".to_string(),
+            name: "chat".to_string(),
+            composition,
+            port: 8080,
+            replicas: 1,
+            env: Default::default(),
+            secret_refs: Vec::new(),
+            secret_grant: None,
+            gpu_devices: Default::default(),
+            peers: Default::default(),
+            mesh_nodes: Vec::new(),
+            mesh_key: None,
+        }
+    }
+
+    #[test]
+    fn test_session_url() {
+        assert_eq!(
+            session_url("http://cp:8181/", "worker-1"),
+            "ws://cp:8181/v1/nodes/worker-1/session"
+        );
+        assert_eq!(
+            session_url("https://cp.example.com", "worker-1"),
+            "wss://cp.example.com/v1/nodes/worker-1/session"
+        );
+    }
+
+    #[test]
+    fn test_message_format() {
+        let json = serde_json::to_value(ControlMessage::HeartbeatNow).unwrap();
+        assert_eq!(json, serde_json::json!({"type": "heartbeat-now"}));
+
+        let message: NodeMessage = serde_json::from_str(
+            r#"{"type": "assignment-result", "id": "00000000-0000-0000-0000-000000000000", "response": {"success": true}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            message,
+            NodeMessage::AssignmentResult { response, .. } if response.success
+        ));
+    }
+
+    #[test]
+    fn test_handle_node_message() {
+        let controller = ClusterController::new();
+        let delta = NodeMessage::HeartbeatDelta {
+            delta: NodeStatusDelta::default(),
+        };
+        assert!(matches!(
+            handle_node_message(&controller, "worker-1", delta.clone()),
+            Some(ControlMessage::Unregistered)
+        ));
+
+        controller
+            .register_node(Node::new("worker-1", "127.0.0.1"))
+            .unwrap();
+        // No full status yet to apply the delta to
+        assert!(matches!(
+            handle_node_message(&controller, "worker-1", delta),
+            Some(ControlMessage::Resync)
+        ));
+
+        let heartbeat = NodeMessage::Heartbeat {
+            status: NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system()),
+        };
+        assert!(handle_node_message(&controller, "worker-1", heartbeat).is_none());
+        assert!(controller.get_node("worker-1").unwrap().status.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_assign_over_session() {
+        let sessions = NodeSessions::new();
+        assert_eq!(
+            sessions
+                .assign("worker-1", assignment(), Duration::from_secs(1))
+                .await
+                .unwrap_err(),
+            SessionError::NotConnected("worker-1".to_string())
+        );
+
+        let (id, mut outgoing) = sessions.open("worker-1");
+        assert!(sessions.is_connected("worker-1"));
+        let worker = sessions.clone();
+        tokio::spawn(async move {
+            let Some(ControlMessage::Assign { id, assignment }) = outgoing.recv().await else {
+                panic!("expected an assignment");
+            };
+            assert_eq!(assignment.name, "chat");
+            worker.complete(
+                id,
+                AssignmentResponse {
+                    success: true,
+                    endpoint: Some("http://worker-1:8080".to_string()),
+                    error: None,
+                },
+            );
+        });
+        let response = sessions
+            .assign("worker-1", assignment(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.endpoint.as_deref(), Some("http://worker-1:8080"));
+
+        // A replaced session isn't closed by its old connection
+        let (_, _newer) = sessions.open("worker-1");
+        sessions.close("worker-1", id);
+        assert!(sessions.is_connected("worker-1"));
+    }
+
+    #[tokio::test]
+    async fn test_assign_times_out() {
+        let sessions = NodeSessions::new();
+        let (_, _outgoing) = sessions.open("worker-1");
+        let err = sessions
+            .assign("worker-1", assignment(), Duration::from_millis(20))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SessionError::Timeout(..)));
+        assert!(sessions.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_assign_fails_when_session_closes() {
+        let sessions = NodeSessions::new();
+        let (id, _outgoing) = sessions.open("worker-1");
+        let closer = sessions.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            closer.close("worker-1", id);
+        });
+        let err = sessions
+            .assign("worker-1", assignment(), Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err, SessionError::Closed("worker-1".to_string()));
+        assert!(!sessions.is_connected("worker-1"));
+    }
+}
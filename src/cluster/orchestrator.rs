@@ -2,24 +2,47 @@
 //!
 //! The orchestrator runs as a background task on the control plane and:
 //! - Watches for pipelines in "Pending" state
-//! - Schedules them to available workers using the scheduler
-//! - Sends pipeline assignments to workers via HTTP
+//! - Holds back pipelines whose dependencies aren't ready, marking them
+//!   Blocked
+//! - Schedules them to available workers using the scheduler, placing
+//!   layers split off with `layerPlacement` on workers of their own
+//! - Sends pipeline assignments to workers over their node session, or via
+//!   HTTP to workers without one
 //! - Updates pipeline status based on worker feedback
+//! - Probes replicas and reschedules the ones that keep failing health checks
+//! - Drives canary and blue/green rollouts
+//! - Cordons and drains nodes around their maintenance windows
+//! - Fails over replicas of nodes that stop sending heartbeats
+//! - Starts batch jobs on a worker of their pipeline
+//! - Finalizes deleted pipelines: tears down their runners on the workers
+//!   before the pipeline record is removed
+//!
+//! Pipelines delegated to a region are left to the federation loop in
+//! [`region`](super::region).
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 
+use chrono::Utc;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use tokio::sync::watch;
+use tokio::sync::{broadcast, watch};
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
 
-use super::controller::ClusterController;
+use super::admission::model_fit;
+use super::controller::{ClusterController, PipelineWatchEvent};
+use super::gpus::devices_by_model;
 use super::health_checker::{check_cluster_health, HealthCheckerConfig};
-use super::node::ReplicaStatus;
-use super::pipeline::{PipelineCondition, PipelineStatus};
-use crate::config::Composition;
+use super::job::{pick_endpoint, run_job, JobPhase};
+use super::node::{Node, ReplicaStatus};
+use super::node_session::{ControlMessage, SessionError, SESSION_ASSIGNMENT_TIMEOUT};
+use super::pipeline::{PipelineCondition, PipelineStatus, SecretEnvRef, BLOCKED_CONDITION};
+use super::rollout::{promote, roll_back, rollout_decision, RolloutDecision};
+use crate::config::{Composition, FunctionType, SecretsManager};
+use crate::runtime::mesh::{Mesh, MeshKey};
 
 /// Configuration for the orchestrator
 #[derive(Debug, Clone)]
@@ -40,22 +63,87 @@ impl Default for OrchestratorConfig {
 }
 
 /// Assignment sent to a worker node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PipelineAssignment {
     /// Pipeline namespace
     pub namespace: String,
     /// Pipeline name
     pub name: String,
     /// The composition to run
+    #[schema(value_type = Object)]
     pub composition: Composition,
     /// Port to serve the pipeline on
     pub port: u16,
     /// Number of replicas this worker should run
     pub replicas: u32,
+    /// Environment variables for the pipeline's runners, with secret
+    /// references already resolved
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Environment variables the worker fills from cluster Secrets before
+    /// spawning runners
+    #[serde(rename = "secretRefs")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secret_refs: Vec<SecretEnvRef>,
+    /// Presented when fetching `secret_refs` values, proving the control
+    /// plane assigned a pipeline that references them
+    #[serde(rename = "secretGrant")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_grant: Option<String>,
+    /// GPUs to start each model's runners on, by model name; models not
+    /// listed may use any GPU
+    #[serde(rename = "gpuDevices")]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub gpu_devices: BTreeMap<String, Vec<u32>>,
+    /// Where the pipeline's placed handlers run: its endpoint on the worker
+    /// hosting each, keyed by node name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub peers: BTreeMap<String, String>,
+    /// Placed handlers this worker hosts for the pipeline's replicas; empty
+    /// when it runs a replica
+    #[serde(rename = "meshNodes")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mesh_nodes: Vec<String>,
+    /// Key the pipeline's workers sign the hops they send each other with
+    #[serde(rename = "meshKey")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh_key: Option<String>,
+}
+
+impl PipelineAssignment {
+    /// Whether this worker needs runners for `model`
+    ///
+    /// A replica leaves the models only its peers' handlers use to them; a
+    /// peer only runs the models of the handlers it hosts. Models no node
+    /// names, such as hedge fallbacks, always run.
+    pub fn runs_model(&self, model: &str) -> bool {
+        let mut users = self
+            .composition
+            .architecture
+            .iter()
+            .filter(|n| n.model.as_deref() == Some(model))
+            .peekable();
+        if users.peek().is_none() {
+            return true;
+        }
+        if self.mesh_nodes.is_empty() {
+            users.any(|n| !self.peers.contains_key(&n.name))
+        } else {
+            users.any(|n| self.mesh_nodes.contains(&n.name))
+        }
+    }
+
+    /// The pipeline's mesh, when it is split across workers
+    pub fn mesh(&self) -> Option<Mesh> {
+        self.mesh_key.as_ref().map(|key| Mesh {
+            key: MeshKey::new(key),
+            peers: self.peers.clone(),
+        })
+    }
 }
 
 /// Response from worker after receiving assignment
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AssignmentResponse {
     pub success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -64,6 +152,9 @@ pub struct AssignmentResponse {
     pub error: Option<String>,
 }
 
+/// How long a function dependency's endpoint gets to answer
+const DEPENDENCY_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Spawn the orchestrator as a background task
 pub fn spawn_orchestrator(
     controller: Arc<ClusterController>,
@@ -79,6 +170,7 @@ pub fn spawn_orchestrator(
 
         let health_config = HealthCheckerConfig::default();
         let mut ticker = interval(Duration::from_secs(config.reconcile_interval_secs));
+        let mut pipeline_events = controller.watch_pipelines();
 
         info!(
             "Orchestrator started, reconciling every {}s",
@@ -88,11 +180,31 @@ pub fn spawn_orchestrator(
         loop {
             tokio::select! {
                 _ = ticker.tick() => {
+                    // Drained and failed-over replicas are rescheduled in the same pass
+                    controller.reconcile_maintenance(Utc::now());
+                    reconcile_failover(&controller, &client).await;
+                    reconcile_deletions(&controller, &client).await;
                     reconcile_pipelines(&controller, &client).await;
+                    reconcile_rollouts(&controller, &client).await;
+                    reconcile_jobs(&controller);
                     reconcile_health(&controller);
                     // Active health probing of all replicas
                     check_cluster_health(&controller, &client, &health_config).await;
                 }
+                // New pipelines are scheduled right away rather than on the next tick
+                event = pipeline_events.recv() => {
+                    match event {
+                        Ok(PipelineWatchEvent::Added(_)) => {
+                            reconcile_pipelines(&controller, &client).await;
+                        }
+                        // Deletion requested: start tearing down right away
+                        Ok(PipelineWatchEvent::Modified(p)) if p.is_terminating() => {
+                            reconcile_deletions(&controller, &client).await;
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
                 _ = shutdown_rx.changed() => {
                     info!("Orchestrator shutting down");
                     break;
@@ -106,9 +218,9 @@ pub fn spawn_orchestrator(
 
 /// Reconcile all pipelines - the main orchestration loop
 async fn reconcile_pipelines(controller: &ClusterController, client: &Client) {
-    let pipelines = controller.list_all_pipelines();
+    let pipelines = controller.list_local_pipelines();
 
-    for pipeline in pipelines {
+    for pipeline in pipelines.into_iter().filter(|p| !p.is_terminating()) {
         let status = pipeline.status.as_ref();
 
         // Check if pipeline needs scheduling (no replicas running yet)
@@ -117,6 +229,7 @@ async fn reconcile_pipelines(controller: &ClusterController, client: &Client) {
             .unwrap_or(true);
 
         if !needs_scheduling {
+            top_up_pipeline(controller, client, &pipeline).await;
             continue;
         }
 
@@ -125,11 +238,39 @@ async fn reconcile_pipelines(controller: &ClusterController, client: &Client) {
             pipeline.metadata.namespace, pipeline.metadata.name
         );
 
+        // Dependencies come first, like init containers
+        if let Some(reason) = unmet_dependency(controller, client, &pipeline).await {
+            if pipeline.blocked_reason() != Some(reason.as_str()) {
+                info!(
+                    "Pipeline {}/{} blocked: {}",
+                    pipeline.metadata.namespace, pipeline.metadata.name, reason
+                );
+                let mut new_status = status.cloned().unwrap_or_else(PipelineStatus::initial);
+                new_status.add_condition(PipelineCondition::new(
+                    BLOCKED_CONDITION,
+                    "True",
+                    "DependenciesNotReady",
+                    reason,
+                ));
+                if let Err(e) = controller.update_pipeline_status(
+                    &pipeline.metadata.namespace,
+                    &pipeline.metadata.name,
+                    new_status,
+                ) {
+                    error!("Failed to update pipeline status: {}", e);
+                }
+            }
+            continue;
+        }
+
         // Try to schedule the pipeline
-        match schedule_pipeline(controller, client, &pipeline).await {
-            Ok(endpoints) => {
+        match schedule_pipeline(controller, client, &pipeline, &HashSet::new()).await {
+            Ok((endpoints, model_warnings)) => {
                 // Update pipeline status
                 let mut new_status = status.cloned().unwrap_or_else(PipelineStatus::initial);
+                new_status
+                    .conditions
+                    .retain(|c| c.condition_type != BLOCKED_CONDITION);
                 new_status.replicas = pipeline.spec.replicas;
                 new_status.endpoints = endpoints;
                 new_status.conditions.push(PipelineCondition::new(
@@ -138,6 +279,14 @@ async fn reconcile_pipelines(controller: &ClusterController, client: &Client) {
                     "ReplicasScheduled",
                     format!("{} replica(s) scheduled to workers", pipeline.spec.replicas),
                 ));
+                if !model_warnings.is_empty() {
+                    new_status.add_condition(PipelineCondition::new(
+                        "ModelFit",
+                        "False",
+                        "ModelMemoryWarnings",
+                        model_warnings.join("; "),
+                    ));
+                }
 
                 if let Err(e) = controller.update_pipeline_status(
                     &pipeline.metadata.namespace,
@@ -160,6 +309,9 @@ async fn reconcile_pipelines(controller: &ClusterController, client: &Client) {
 
                 // Update status with failure condition
                 let mut new_status = status.cloned().unwrap_or_else(PipelineStatus::initial);
+                new_status
+                    .conditions
+                    .retain(|c| c.condition_type != BLOCKED_CONDITION);
                 new_status.conditions.push(PipelineCondition::new(
                     "Scheduled",
                     "False",
@@ -177,31 +329,162 @@ async fn reconcile_pipelines(controller: &ClusterController, client: &Client) {
     }
 }
 
+/// The first of a pipeline's dependencies that isn't ready, and why
+async fn unmet_dependency(
+    controller: &ClusterController,
+    client: &Client,
+    pipeline: &super::Pipeline,
+) -> Option<String> {
+    for dependency in &pipeline.spec.depends_on {
+        if let Some((namespace, name)) = dependency.pipeline_ref(&pipeline.metadata.namespace) {
+            match controller.get_pipeline(&namespace, &name) {
+                None => return Some(format!("Pipeline {}/{} not found", namespace, name)),
+                Some(p) if !p.is_ready() => {
+                    return Some(format!("Pipeline {}/{} is not ready", namespace, name))
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(function) = &dependency.function {
+            if let Err(reason) = probe_function(client, &pipeline.spec.composition, function).await
+            {
+                return Some(reason);
+            }
+        }
+    }
+    None
+}
+
+/// Check that a REST function of the composition answers without a server
+/// error
+async fn probe_function(
+    client: &Client,
+    composition: &Composition,
+    name: &str,
+) -> Result<(), String> {
+    let Some(FunctionType::Rest { url, .. }) = composition.functions.get(name) else {
+        return Err(format!(
+            "Function '{}' is not a REST function of the composition",
+            name
+        ));
+    };
+    match client
+        .get(url)
+        .timeout(DEPENDENCY_PROBE_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_server_error() => Err(format!(
+            "Function '{}' at {} answered {}",
+            name,
+            url,
+            response.status()
+        )),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!(
+            "Function '{}' at {} is unreachable: {}",
+            name, url, e
+        )),
+    }
+}
+
+/// Start pending jobs on the least busy worker of their pipeline
+///
+/// Jobs wait while their pipeline has no endpoints, and fail if the
+/// pipeline has been deleted.
+fn reconcile_jobs(controller: &ClusterController) {
+    let jobs = controller.list_all_jobs();
+    let mut running: Vec<_> = jobs
+        .iter()
+        .filter(|j| j.phase() == JobPhase::Running)
+        .cloned()
+        .collect();
+
+    for job in jobs.iter().filter(|j| j.phase() == JobPhase::Pending) {
+        let namespace = &job.metadata.namespace;
+        let name = &job.metadata.name;
+        let Some(pipeline) = controller.get_pipeline(namespace, &job.spec.pipeline) else {
+            controller.fail_job(
+                namespace,
+                name,
+                format!("Pipeline '{}' not found", job.spec.pipeline),
+            );
+            continue;
+        };
+        let endpoints = pipeline.status.map(|s| s.endpoints).unwrap_or_default();
+        let Some(endpoint) = pick_endpoint(&endpoints, &running) else {
+            debug!(
+                "Job {}/{} waiting for pipeline {} to have endpoints",
+                namespace, name, job.spec.pipeline
+            );
+            continue;
+        };
+
+        match controller.start_job(namespace, name, &endpoint) {
+            Ok(started) => {
+                info!("Job {}/{} started on {}", namespace, name, endpoint);
+                running.push(started);
+                tokio::spawn(run_job(controller.clone(), namespace.clone(), name.clone()));
+            }
+            Err(e) => warn!("Failed to start job {}/{}: {}", namespace, name, e),
+        }
+    }
+}
+
 /// Schedule a single pipeline to workers
+///
+/// Returns the endpoints of the replicas, and warnings about how the
+/// pipeline's models fit on the nodes that accepted them.
 async fn schedule_pipeline(
     controller: &ClusterController,
     client: &Client,
     pipeline: &super::Pipeline,
-) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    exclude: &HashSet<String>,
+) -> Result<(Vec<String>, Vec<String>), Box<dyn std::error::Error + Send + Sync>> {
     // Get scheduling decisions
-    let schedule = controller.schedule_replicas(pipeline)?;
+    let schedule = controller.schedule_replicas_excluding(pipeline, exclude)?;
 
     if schedule.is_empty() {
         return Err("No nodes available for scheduling".into());
     }
 
+    let env = resolve_runner_env(pipeline).await?;
+    let secret_refs = check_cluster_secrets(controller, pipeline)?;
+    let secret_grant = (!secret_refs.is_empty())
+        .then(|| controller.secret_grant(&pipeline.metadata.namespace, &pipeline.metadata.name));
+    let placed = place_layers(controller, pipeline, &schedule)?;
+    let mesh_key = (!placed.is_empty()).then(MeshKey::generate);
+    let mut peers = BTreeMap::new();
     let mut endpoints = Vec::new();
+    let mut model_warnings = Vec::new();
 
-    // Send assignment to each worker
-    for (node_name, replica_count) in schedule {
+    // Send assignment to each worker, the ones hosting placed handlers
+    // first so the replicas learn where those run
+    let workers = placed
+        .into_iter()
+        .map(|(node_name, handlers)| (node_name, 1, handlers))
+        .chain(
+            schedule
+                .into_iter()
+                .map(|(node_name, replica_count)| (node_name, replica_count, Vec::new())),
+        );
+    for (node_name, replica_count, mesh_nodes) in workers {
         let node = controller
             .get_node(&node_name)
             .ok_or_else(|| format!("Node {} not found", node_name))?;
 
-        let worker_url = format!(
-            "http://{}:{}/v1/assignments",
-            node.spec.address, node.spec.port
-        );
+        // Models sharing a multi-GPU worker each get their own GPUs
+        let gpus = controller.allocate_gpus(&node_name, pipeline)?;
+        for allocation in &gpus {
+            debug!(
+                "Pinning model '{}' of {}/{} to GPUs {:?} of {}",
+                allocation.model,
+                pipeline.metadata.namespace,
+                pipeline.metadata.name,
+                allocation.devices,
+                node_name
+            );
+        }
 
         let assignment = PipelineAssignment {
             namespace: pipeline.metadata.namespace.clone(),
@@ -209,134 +492,549 @@ async fn schedule_pipeline(
             composition: pipeline.spec.composition.clone(),
             port: pipeline.spec.port,
             replicas: replica_count,
+            env: env.clone(),
+            secret_refs: secret_refs.clone(),
+            secret_grant: secret_grant.clone(),
+            gpu_devices: devices_by_model(&gpus),
+            peers: if mesh_nodes.is_empty() {
+                peers.clone()
+            } else {
+                BTreeMap::new()
+            },
+            mesh_nodes: mesh_nodes.clone(),
+            mesh_key: mesh_key.clone(),
         };
 
-        debug!(
-            "Sending assignment to worker {} at {}",
-            node_name, worker_url
-        );
-
-        match client.post(&worker_url).json(&assignment).send().await {
-            Ok(resp) if resp.status().is_success() => {
-                match resp.json::<AssignmentResponse>().await {
-                    Ok(ar) if ar.success => {
-                        if let Some(endpoint) = ar.endpoint {
-                            endpoints.push(endpoint);
-                        }
-                        // Track pipeline on this node
-                        if let Err(e) = controller.add_pipeline_to_node(
-                            &node_name,
-                            &pipeline.metadata.namespace,
-                            &pipeline.metadata.name,
-                            pipeline.spec.port,
-                        ) {
-                            warn!("Failed to track pipeline on node {}: {}", node_name, e);
+        let accepted = match send_assignment(controller, client, &node, assignment).await {
+            Ok(ar) if ar.success => {
+                match ar.endpoint {
+                    Some(endpoint) if mesh_nodes.is_empty() => endpoints.push(endpoint),
+                    Some(endpoint) => {
+                        let endpoint = peer_endpoint(&endpoint, &node.spec.address);
+                        for handler in &mesh_nodes {
+                            peers.insert(handler.clone(), endpoint.clone());
                         }
-                        info!(
-                            "Worker {} accepted assignment for {}/{}",
-                            node_name, pipeline.metadata.namespace, pipeline.metadata.name
-                        );
-                    }
-                    Ok(ar) => {
-                        warn!(
-                            "Worker {} rejected assignment: {}",
-                            node_name,
-                            ar.error.unwrap_or_else(|| "unknown error".to_string())
-                        );
-                    }
-                    Err(e) => {
-                        warn!("Failed to parse worker response from {}: {}", node_name, e);
                     }
+                    None => {}
                 }
+                model_warnings.extend(
+                    model_fit(pipeline, &node)
+                        .warnings
+                        .into_iter()
+                        .map(|w| format!("node '{}': {}", node_name, w)),
+                );
+                // Track pipeline on this node
+                if let Err(e) = controller.add_pipeline_to_node(
+                    &node_name,
+                    &pipeline.metadata.namespace,
+                    &pipeline.metadata.name,
+                    pipeline.spec.port,
+                ) {
+                    warn!("Failed to track pipeline on node {}: {}", node_name, e);
+                }
+                info!(
+                    "Worker {} accepted assignment for {}/{}",
+                    node_name, pipeline.metadata.namespace, pipeline.metadata.name
+                );
+
+                // Ask for a heartbeat now so node state reflects the
+                // new pipeline before the next scheduling pass
+                request_heartbeat(controller, client, &node).await;
+                true
             }
-            Ok(resp) => {
-                let status = resp.status();
-                let body = resp.text().await.unwrap_or_default();
-                warn!("Worker {} returned error {}: {}", node_name, status, body);
+            Ok(ar) => {
+                warn!(
+                    "Worker {} rejected assignment: {}",
+                    node_name,
+                    ar.error.unwrap_or_else(|| "unknown error".to_string())
+                );
+                false
             }
             Err(e) => {
-                warn!("Failed to contact worker {}: {}", node_name, e);
+                warn!("Failed to send assignment to worker {}: {}", node_name, e);
+                false
             }
+        };
+        if !accepted {
+            controller.release_gpus(
+                &node_name,
+                &pipeline.metadata.namespace,
+                &pipeline.metadata.name,
+            );
         }
     }
 
     if endpoints.is_empty() {
         Err("No workers successfully accepted the assignment".into())
     } else {
-        Ok(endpoints)
+        Ok((endpoints, model_warnings))
     }
 }
 
-/// Reconcile pipeline health status based on node heartbeats
+/// Replace the replicas a running pipeline is short of, such as those of a
+/// failed-over node, on nodes not already running one
 ///
-/// This function updates pipeline ready_replicas and available_replicas
-/// by counting replicas reported in node status.pipelines
-fn reconcile_health(controller: &ClusterController) {
-    let pipelines = controller.list_all_pipelines();
-    let nodes = controller.list_nodes();
+/// Pipelines placing layers across nodes, or in a rollout, are left alone.
+async fn top_up_pipeline(
+    controller: &ClusterController,
+    client: &Client,
+    pipeline: &super::Pipeline,
+) {
+    let Some(status) = pipeline.status.as_ref() else {
+        return;
+    };
+    let missing = pipeline.spec.replicas.saturating_sub(status.replicas);
+    if missing == 0 || status.rollout.is_some() || !pipeline.spec.layer_placement.is_empty() {
+        return;
+    }
+    let namespace = &pipeline.metadata.namespace;
+    let name = &pipeline.metadata.name;
+    debug!(
+        "Pipeline {}/{} is short {} replica(s)",
+        namespace, name, missing
+    );
 
-    for pipeline in pipelines {
+    let mut shortfall = pipeline.clone();
+    shortfall.spec.replicas = missing;
+    let hosting = controller.hosting_nodes(namespace, name);
+    let mut new_status = status.clone();
+    match schedule_pipeline(controller, client, &shortfall, &hosting).await {
+        Ok((endpoints, _)) => {
+            info!(
+                "Pipeline {}/{} topped up with {} replica(s)",
+                namespace, name, missing
+            );
+            new_status.replicas = pipeline.spec.replicas;
+            new_status.endpoints.extend(endpoints);
+        }
+        Err(e) => {
+            warn!(
+                "Failed to replace replicas of pipeline {}/{}: {}",
+                namespace, name, e
+            );
+            new_status.add_condition(PipelineCondition::new(
+                "Scheduled",
+                "False",
+                "SchedulingFailed",
+                e.to_string(),
+            ));
+        }
+    }
+    if let Err(e) = controller.update_pipeline_status(namespace, name, new_status) {
+        error!("Failed to update pipeline status: {}", e);
+    }
+}
+
+/// Workers to host the handlers of the pipeline's placed layers, with the
+/// handlers each one hosts
+///
+/// Each placement goes to a node matching its own `nodeSelector`. Layers
+/// that land on a node already running a replica stay in the replica.
+fn place_layers(
+    controller: &ClusterController,
+    pipeline: &super::Pipeline,
+    replicas: &HashMap<String, u32>,
+) -> Result<BTreeMap<String, Vec<String>>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut placed: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for placement in &pipeline.spec.layer_placement {
+        let handlers = placement.handlers(&pipeline.spec.composition);
+        if handlers.is_empty() {
+            continue;
+        }
+        let mut group = pipeline.clone();
+        group.spec.replicas = 1;
+        group.spec.node_selector = placement.node_selector.clone();
+        let node_name = controller
+            .schedule_replicas(&group)?
+            .into_keys()
+            .next()
+            .ok_or_else(|| format!("No nodes available for layers {:?}", placement.layers))?;
+        if replicas.contains_key(&node_name) {
+            continue;
+        }
+        placed.entry(node_name).or_default().extend(handlers);
+    }
+    Ok(placed)
+}
+
+/// A pipeline endpoint a worker reported, at the address the cluster
+/// reaches the worker on, for its peers to call
+fn peer_endpoint(endpoint: &str, address: &str) -> String {
+    match reqwest::Url::parse(endpoint) {
+        Ok(mut url) => match url.set_host(Some(address)) {
+            Ok(()) => url.as_str().trim_end_matches('/').to_string(),
+            Err(_) => endpoint.to_string(),
+        },
+        Err(_) => endpoint.to_string(),
+    }
+}
+
+/// Send an assignment over the node's session, or POST it to the worker
+/// when it has none
+async fn send_assignment(
+    controller: &ClusterController,
+    client: &Client,
+    node: &Node,
+    assignment: PipelineAssignment,
+) -> Result<AssignmentResponse, String> {
+    let node_name = &node.metadata.name;
+    let assignment = match controller
+        .sessions()
+        .assign(node_name, assignment.clone(), SESSION_ASSIGNMENT_TIMEOUT)
+        .await
+    {
+        Err(SessionError::NotConnected(_)) => assignment,
+        result => {
+            debug!("Sent assignment to worker {} over its session", node_name);
+            return result.map_err(|e| e.to_string());
+        }
+    };
+
+    let worker_url = format!(
+        "http://{}:{}/v1/assignments",
+        node.spec.address, node.spec.port
+    );
+    debug!(
+        "Sending assignment to worker {} at {}",
+        node_name, worker_url
+    );
+
+    let resp = client
+        .post(&worker_url)
+        .json(&assignment)
+        .send()
+        .await
+        .map_err(|e| format!("failed to contact worker: {}", e))?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        return Err(format!("worker returned error {}: {}", status, body));
+    }
+    resp.json::<AssignmentResponse>()
+        .await
+        .map_err(|e| format!("failed to parse worker response: {}", e))
+}
+
+/// Ask a worker for an immediate heartbeat, over its session if it has one
+async fn request_heartbeat(controller: &ClusterController, client: &Client, node: &Node) {
+    if controller
+        .sessions()
+        .send(&node.metadata.name, ControlMessage::HeartbeatNow)
+    {
+        return;
+    }
+    let heartbeat_url = format!(
+        "http://{}:{}/v1/heartbeat",
+        node.spec.address, node.spec.port
+    );
+    if let Err(e) = client.post(&heartbeat_url).send().await {
+        debug!(
+            "Failed to request heartbeat from {}: {}",
+            node.metadata.name, e
+        );
+    }
+}
+
+/// Resolve a pipeline's runner environment from the secrets its
+/// composition declares, loaded here on the control plane
+async fn resolve_runner_env(
+    pipeline: &super::Pipeline,
+) -> Result<BTreeMap<String, String>, String> {
+    let declared = &pipeline.spec.composition.secrets;
+    let sources = pipeline
+        .spec
+        .secret_refs
+        .iter()
+        .filter_map(|r| declared.get_key_value(&r.secret))
+        .map(|(name, source)| (name.clone(), source.clone()))
+        .collect();
+
+    let secrets = SecretsManager::new();
+    secrets
+        .load_all(&sources)
+        .await
+        .map_err(|e| format!("Failed to load secrets: {}", e))?;
+    pipeline.runner_env(|secret, key| secrets.resolve(secret, key))
+}
+
+/// The pipeline's references to cluster Secrets, once each has been checked
+/// to exist with the referenced key
+///
+/// Values stay on the control plane; workers fetch them at spawn time.
+fn check_cluster_secrets(
+    controller: &ClusterController,
+    pipeline: &super::Pipeline,
+) -> Result<Vec<SecretEnvRef>, String> {
+    let refs = pipeline.cluster_secret_refs();
+    for secret_ref in &refs {
+        let secret = controller
+            .get_secret(&pipeline.metadata.namespace, &secret_ref.secret)
+            .ok_or_else(|| {
+                format!(
+                    "Secret '{}' not found in namespace '{}'",
+                    secret_ref.secret, pipeline.metadata.namespace
+                )
+            })?;
+        if !secret.data.contains_key(secret_ref.key()) {
+            return Err(format!(
+                "Secret '{}' has no {}",
+                secret_ref.secret,
+                secret_ref.key()
+            ));
+        }
+    }
+    Ok(refs)
+}
+
+/// Drive canary and blue/green rollouts
+///
+/// Schedules the canary replica set of each rollout in progress, then
+/// promotes or rolls back once `rollout_decision` says so.
+async fn reconcile_rollouts(controller: &ClusterController, client: &Client) {
+    for pipeline in controller
+        .list_local_pipelines()
+        .into_iter()
+        .filter(|p| !p.is_terminating())
+    {
+        let Some(rollout) = pipeline
+            .status
+            .as_ref()
+            .and_then(|s| s.rollout.as_ref())
+            .filter(|r| r.is_progressing())
+        else {
+            continue;
+        };
         let namespace = &pipeline.metadata.namespace;
         let name = &pipeline.metadata.name;
+        let canary = pipeline.canary_pipeline();
 
-        // Count replicas across all nodes
-        let mut ready = 0u32;
-        let mut available = 0u32;
-
-        for node in &nodes {
-            if let Some(status) = &node.status {
-                for np in &status.pipelines {
-                    if &np.namespace == namespace && &np.name == name {
-                        ready += 1;
-                        if np.status == ReplicaStatus::Running {
-                            available += 1;
-                        }
-                    }
+        if rollout.canary_endpoints.is_empty() {
+            let result = schedule_pipeline(controller, client, &canary, &HashSet::new()).await;
+
+            // Traffic counters moved on while the workers were busy
+            let Some(mut status) = controller
+                .get_pipeline(namespace, name)
+                .and_then(|p| p.status)
+            else {
+                continue;
+            };
+            let Some(rollout) = status.rollout.as_mut() else {
+                continue;
+            };
+            match result {
+                Ok((endpoints, model_warnings)) => {
+                    info!("Canary of {}/{} scheduled", namespace, name);
+                    rollout.canary_endpoints = endpoints;
+                    rollout.message = if model_warnings.is_empty() {
+                        "Canary replicas scheduled".to_string()
+                    } else {
+                        format!("Canary replicas scheduled; {}", model_warnings.join("; "))
+                    };
                 }
+                Err(e) => {
+                    warn!("Failed to schedule canary of {}/{}: {}", namespace, name, e);
+                    rollout.message = format!("Failed to schedule canary replicas: {}", e);
+                }
+            }
+            if let Err(e) = controller.update_pipeline_status(namespace, name, status) {
+                error!("Failed to update pipeline status: {}", e);
             }
+            continue;
         }
 
-        // Only update if status differs from current
-        let current_status = pipeline.status.as_ref();
-        let needs_update = current_status
-            .map(|s| s.ready_replicas != ready || s.available_replicas != available)
-            .unwrap_or(true);
-
-        if needs_update {
-            let mut new_status = current_status
-                .cloned()
-                .unwrap_or_else(PipelineStatus::initial);
-            new_status.ready_replicas = ready;
-            new_status.available_replicas = available;
-            new_status.unavailable_replicas = pipeline.spec.replicas.saturating_sub(available);
+        let canary_ready = replicas_ready(controller, namespace, &canary.metadata.name);
+        let strategy = &pipeline.spec.strategy;
+        match rollout_decision(
+            rollout,
+            strategy.kind(),
+            &strategy.canary_params(),
+            canary_ready,
+        ) {
+            RolloutDecision::Wait => {}
+            RolloutDecision::Promote(message) => {
+                info!("Promoting rollout of {}/{}: {}", namespace, name, message);
+                let mut status = pipeline
+                    .status
+                    .clone()
+                    .unwrap_or_else(PipelineStatus::initial);
+                promote(&mut status, message);
+                status.composition_hash = Some(pipeline.composition_hash());
+                untrack_replicas(controller, namespace, name);
+                untrack_replicas(controller, namespace, &canary.metadata.name);
+                if let Err(e) = controller.update_pipeline_status(namespace, name, status) {
+                    error!("Failed to update pipeline status: {}", e);
+                }
+            }
+            RolloutDecision::RollBack(message) => {
+                warn!("Rolling back {}/{}: {}", namespace, name, message);
+                let mut pipeline = pipeline.clone();
+                roll_back(&mut pipeline, message);
+                untrack_replicas(controller, namespace, &canary.metadata.name);
+                if let Err(e) = controller.revise_pipeline(pipeline) {
+                    error!("Failed to roll back pipeline: {}", e);
+                }
+            }
+        }
+    }
+}
 
-            // Add/update Available condition
-            if available >= pipeline.spec.replicas {
-                new_status
-                    .conditions
-                    .retain(|c| c.condition_type != "Available");
-                new_status.conditions.push(PipelineCondition::new(
-                    "Available",
-                    "True",
-                    "MinimumReplicasAvailable",
-                    format!(
-                        "{}/{} replicas available",
-                        available, pipeline.spec.replicas
-                    ),
-                ));
+/// Finalize pipelines being deleted
+///
+/// Each Ready worker is asked to drop the pipeline and its canary, which
+/// stops runners no other pipeline uses. Once every worker did (or never
+/// had it), the pipeline record is removed. Workers that can't be reached
+/// are retried next pass; ones that aren't Ready are skipped, since their
+/// container collection removes what's left once they're back.
+async fn reconcile_deletions(controller: &ClusterController, client: &Client) {
+    for pipeline in controller
+        .list_local_pipelines()
+        .into_iter()
+        .filter(|p| p.is_terminating())
+    {
+        let namespace = &pipeline.metadata.namespace;
+        let name = &pipeline.metadata.name;
+        let mut torn_down = true;
+        for node in controller.list_nodes().into_iter().filter(Node::is_ready) {
+            for replica_set in [name.clone(), pipeline.canary_name()] {
+                if let Err(e) = remove_assignment(client, &node, namespace, &replica_set).await {
+                    warn!(
+                        "Failed to tear down {}/{} on {}: {}",
+                        namespace, replica_set, node.metadata.name, e
+                    );
+                    torn_down = false;
+                }
             }
+        }
+        if !torn_down {
+            continue;
+        }
 
-            if let Err(e) = controller.update_pipeline_status(namespace, name, new_status) {
-                warn!(
-                    "Failed to update pipeline {}/{} health status: {}",
-                    namespace, name, e
-                );
-            } else {
-                debug!(
-                    "Pipeline {}/{} health: ready={}, available={}",
-                    namespace, name, ready, available
+        untrack_replicas(controller, namespace, name);
+        untrack_replicas(controller, namespace, &pipeline.canary_name());
+        match controller.delete_pipeline(namespace, name) {
+            Ok(_) => {
+                info!("Pipeline {}/{} torn down and deleted", namespace, name);
+                controller.record_event(
+                    format!("pipeline/{}", pipeline.qualified_name()),
+                    "Deleted",
+                    "Runners torn down on all workers",
                 );
             }
+            Err(e) => debug!("Pipeline {}/{} already gone: {}", namespace, name, e),
+        }
+    }
+}
+
+/// Ask a worker to stop hosting a pipeline; one it doesn't host counts as
+/// done
+async fn remove_assignment(
+    client: &Client,
+    node: &Node,
+    namespace: &str,
+    name: &str,
+) -> Result<(), String> {
+    let url = format!(
+        "http://{}:{}/v1/assignments/{}/{}",
+        node.spec.address, node.spec.port, namespace, name
+    );
+    let resp = client
+        .delete(&url)
+        .send()
+        .await
+        .map_err(|e| format!("failed to contact worker: {}", e))?;
+    if resp.status().is_success() || resp.status() == reqwest::StatusCode::NOT_FOUND {
+        Ok(())
+    } else {
+        Err(format!("worker returned {}", resp.status()))
+    }
+}
+
+/// Whether every probed replica of a pipeline is Running (and there is one)
+fn replicas_ready(controller: &ClusterController, namespace: &str, name: &str) -> bool {
+    let states: Vec<_> = controller
+        .list_replica_health()
+        .into_iter()
+        .filter(|s| s.namespace == namespace && s.pipeline_name == name)
+        .collect();
+    !states.is_empty() && states.iter().all(|s| s.status == ReplicaStatus::Running)
+}
+
+/// Stop tracking a pipeline's replicas on every node
+fn untrack_replicas(controller: &ClusterController, namespace: &str, name: &str) {
+    for node in controller.list_nodes() {
+        if let Err(e) = controller.remove_pipeline_from_node(&node.metadata.name, namespace, name) {
+            debug!(
+                "Failed to untrack {}/{} on {}: {}",
+                namespace, name, node.metadata.name, e
+            );
+        }
+    }
+}
+
+/// Fail over replicas of nodes that went silent, and stop the old copies
+/// once such a node sends heartbeats again
+async fn reconcile_failover(controller: &ClusterController, client: &Client) {
+    controller.check_node_health().await;
+    controller.fail_over_lost_nodes();
+
+    for (node, replicas) in controller.recovered_nodes() {
+        let mut cleaned = true;
+        for replica in &replicas {
+            let url = format!(
+                "http://{}:{}/v1/runners/{}",
+                node.spec.address, node.spec.port, replica.name
+            );
+            match client.delete(&url).send().await {
+                // Not found means the runner didn't survive either
+                Ok(resp) if resp.status().is_success() || resp.status().as_u16() == 404 => {}
+                Ok(resp) => {
+                    warn!(
+                        "Worker {} failed to stop old replica of {}/{}: {}",
+                        node.metadata.name,
+                        replica.namespace,
+                        replica.name,
+                        resp.status()
+                    );
+                    cleaned = false;
+                }
+                Err(e) => {
+                    debug!("Failed to contact worker {}: {}", node.metadata.name, e);
+                    cleaned = false;
+                }
+            }
+        }
+        // Retried next pass otherwise
+        if cleaned {
+            controller.finish_failover(&node.metadata.name);
+        }
+    }
+}
+
+/// Reconcile pipeline health status based on node heartbeats
+///
+/// Each pipeline's ready and available replicas are rolled up from the
+/// replica statuses workers report, with the last probe result winning
+/// over a replica reported Running.
+fn reconcile_health(controller: &ClusterController) {
+    let probed: HashMap<String, ReplicaStatus> = controller
+        .list_replica_health()
+        .into_iter()
+        .map(|state| (state.key, state.status))
+        .collect();
+
+    for pipeline in controller.list_local_pipelines() {
+        let namespace = &pipeline.metadata.namespace;
+        let name = &pipeline.metadata.name;
+        match controller.roll_up_replicas(namespace, name, &probed) {
+            Ok(Some(status)) => debug!(
+                "Pipeline {}/{} health: ready={}, available={}",
+                namespace, name, status.ready_replicas, status.available_replicas
+            ),
+            Ok(None) => {}
+            Err(e) => warn!(
+                "Failed to update pipeline {}/{} health status: {}",
+                namespace, name, e
+            ),
         }
     }
 }
@@ -369,10 +1067,271 @@ mod tests {
             composition,
             port: 8080,
             replicas: 1,
+            env: BTreeMap::new(),
+            secret_refs: vec![],
+            secret_grant: None,
+            gpu_devices: BTreeMap::new(),
+            peers: BTreeMap::new(),
+            mesh_nodes: vec![],
+            mesh_key: None,
         };
 
         let serialized = serde_json::to_string(&assignment).unwrap();
         assert!(serialized.contains("test-pipeline"));
         assert!(serialized.contains("default"));
+        assert!(!serialized.contains("meshKey"));
+    }
+
+    #[test]
+    fn test_split_pipeline_models() {
+        let json = r#"{
+            "models": {
+                "small": {"runner": "external", "endpoint": "http://small:8000/v1"},
+                "large": {"runner": "external", "endpoint": "http://large:8000/v1"},
+                "spare": {"runner": "external", "endpoint": "http://spare:8000/v1"}
+            },
+            "architecture": [
+                {"name": "router", "layer": 0, "model": "small", "adapter": "openai-api",
+                 "output-to": ["coder"]},
+                {"name": "coder", "layer": 1, "model": "large", "adapter": "openai-api",
+                 "output-to": ["output"]},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+        let replica = PipelineAssignment {
+            namespace: "default".to_string(),
+            name: "bot".to_string(),
+            composition: crate::config::Composition::from_str(json).unwrap(),
+            port: 8080,
+            replicas: 1,
+            env: BTreeMap::new(),
+            secret_refs: vec![],
+            secret_grant: None,
+            gpu_devices: BTreeMap::new(),
+            peers: BTreeMap::from([("coder".to_string(), "http://10.0.0.9:8080".to_string())]),
+            mesh_nodes: vec![],
+            mesh_key: Some("secret".to_string()),
+        };
+        assert!(replica.runs_model("small"));
+        assert!(!replica.runs_model("large"));
+        assert!(replica.runs_model("spare"));
+        assert_eq!(replica.mesh().unwrap().peers, replica.peers);
+
+        let peer = PipelineAssignment {
+            peers: BTreeMap::new(),
+            mesh_nodes: vec!["coder".to_string()],
+            ..replica.clone()
+        };
+        assert!(!peer.runs_model("small"));
+        assert!(peer.runs_model("large"));
+
+        assert_eq!(
+            peer_endpoint("http://0.0.0.0:8080/pipelines/bot", "10.0.0.9"),
+            "http://10.0.0.9:8080/pipelines/bot"
+        );
+        assert_eq!(
+            peer_endpoint("http://0.0.0.0:9000", "10.0.0.9"),
+            "http://10.0.0.9:9000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_runner_env() {
+        use crate::cluster::{Pipeline, SecretEnvRef};
+
+        std::env::set_var("LLMNET_TEST_RUNNER_TOKEN", "tok-123");
+        let json = r#"{
+            "secrets": {"hf": {"source": "env", "variable": "LLMNET_TEST_RUNNER_TOKEN"}},
+            "models": {},
+            "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+        let composition = crate::config::Composition::from_str(json).unwrap();
+        let pipeline = Pipeline::new("bot", composition)
+            .with_env("LOG_LEVEL", "debug")
+            .with_secret_ref(SecretEnvRef {
+                name: "HF_TOKEN".to_string(),
+                secret: "hf".to_string(),
+                key: Some("LLMNET_TEST_RUNNER_TOKEN".to_string()),
+            });
+
+        let env = resolve_runner_env(&pipeline).await.unwrap();
+        assert_eq!(env["HF_TOKEN"], "tok-123");
+        assert_eq!(env["LOG_LEVEL"], "debug");
+
+        let missing = pipeline.with_secret_ref(SecretEnvRef {
+            name: "OTHER".to_string(),
+            secret: "hf".to_string(),
+            key: None,
+        });
+        assert!(resolve_runner_env(&missing).await.is_err());
+    }
+
+    #[test]
+    fn test_check_cluster_secrets() {
+        use crate::cluster::{Pipeline, Secret, SecretEnvRef};
+
+        let json = r#"{
+            "models": {},
+            "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+        let composition = crate::config::Composition::from_str(json).unwrap();
+        let pipeline = Pipeline::new("bot", composition).with_secret_ref(SecretEnvRef {
+            name: "OPENAI_API_KEY".to_string(),
+            secret: "openai".to_string(),
+            key: Some("key".to_string()),
+        });
+        let controller = ClusterController::new();
+
+        let err = check_cluster_secrets(&controller, &pipeline).unwrap_err();
+        assert_eq!(err, "Secret 'openai' not found in namespace 'default'");
+
+        let values = BTreeMap::from([("org".to_string(), "acme".to_string())]);
+        controller
+            .create_secret(Secret::new("openai", values))
+            .unwrap();
+        let err = check_cluster_secrets(&controller, &pipeline).unwrap_err();
+        assert_eq!(err, "Secret 'openai' has no key");
+
+        controller.delete_secret("default", "openai").unwrap();
+        let values = BTreeMap::from([("key".to_string(), "sk-123".to_string())]);
+        controller
+            .create_secret(Secret::new("openai", values))
+            .unwrap();
+        let refs = check_cluster_secrets(&controller, &pipeline).unwrap();
+        assert_eq!(refs, pipeline.spec.secret_refs);
+    }
+
+    #[tokio::test]
+    async fn test_unmet_dependency() {
+        use crate::cluster::{Pipeline, PipelineDependency};
+        use axum::{http::StatusCode, routing::get, Router};
+
+        // A function endpoint that's up, and one that's failing
+        let app = Router::new()
+            .route("/up", get(|| async { StatusCode::OK }))
+            .route("/down", get(|| async { StatusCode::SERVICE_UNAVAILABLE }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let composition = |path: &str| {
+            crate::config::Composition::from_str(&format!(
+                r#"{{"models": {{}},
+                    "functions": {{"lookup": {{"type": "rest", "url": "http://{addr}{path}"}}}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api"}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]}}"#
+            ))
+            .unwrap()
+        };
+        let controller = ClusterController::new();
+        let client = Client::new();
+
+        let chat = Pipeline::new("chat", composition("/up"))
+            .with_dependency(PipelineDependency::pipeline("embedder"))
+            .with_dependency(PipelineDependency::function("lookup"));
+        assert_eq!(
+            unmet_dependency(&controller, &client, &chat).await.unwrap(),
+            "Pipeline default/embedder not found"
+        );
+
+        controller
+            .deploy_pipeline(Pipeline::new("embedder", composition("/up")))
+            .unwrap();
+        assert_eq!(
+            unmet_dependency(&controller, &client, &chat).await.unwrap(),
+            "Pipeline default/embedder is not ready"
+        );
+
+        let mut status = PipelineStatus::initial();
+        status.available_replicas = 1;
+        controller
+            .update_pipeline_status("default", "embedder", status)
+            .unwrap();
+        assert_eq!(unmet_dependency(&controller, &client, &chat).await, None);
+
+        let mut failing = chat.clone();
+        failing.spec.composition = composition("/down");
+        let reason = unmet_dependency(&controller, &client, &failing)
+            .await
+            .unwrap();
+        assert!(reason.ends_with("answered 503 Service Unavailable"));
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_jobs_runs_prompts() {
+        use crate::cluster::{Job, Pipeline};
+        use axum::{routing::post, Json, Router};
+
+        // A worker that echoes the prompt, failing on "boom"
+        let app = Router::new().route(
+            "/v1/chat/completions",
+            post(|Json(body): Json<serde_json::Value>| async move {
+                let prompt = body["messages"][0]["content"].as_str().unwrap().to_string();
+                if prompt == "boom" {
+                    return Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+                }
+                Ok(Json(serde_json::json!({
+                    "choices": [{"message": {"role": "assistant", "content": format!("echo: {}", prompt)}}]
+                })))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let controller = ClusterController::new();
+        let composition = crate::config::Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        controller
+            .deploy_pipeline(Pipeline::new("chat", composition))
+            .unwrap();
+        let prompts = vec!["one".to_string(), "boom".to_string(), "three".to_string()];
+        controller
+            .create_job(Job::new("batch", "chat", prompts))
+            .unwrap();
+
+        // Waits while the pipeline has nowhere to run
+        reconcile_jobs(&controller);
+        assert_eq!(
+            controller.get_job("default", "batch").unwrap().phase(),
+            JobPhase::Pending
+        );
+
+        let mut status = PipelineStatus::initial();
+        status.endpoints = vec![format!("http://{}", addr)];
+        controller
+            .update_pipeline_status("default", "chat", status)
+            .unwrap();
+        reconcile_jobs(&controller);
+
+        let mut job = controller.get_job("default", "batch").unwrap();
+        for _ in 0..100 {
+            if job.status.as_ref().unwrap().is_finished() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            job = controller.get_job("default", "batch").unwrap();
+        }
+        let status = job.status.unwrap();
+        assert_eq!(status.phase, JobPhase::Failed);
+        assert_eq!((status.total, status.completed, status.failed), (3, 2, 1));
+
+        let results = controller.job_results("default", "batch").unwrap();
+        assert_eq!(results[0].output.as_deref(), Some("echo: one"));
+        assert!(results[1].error.as_deref().unwrap().starts_with("HTTP 500"));
+        assert_eq!(results[2].output.as_deref(), Some("echo: three"));
     }
 }
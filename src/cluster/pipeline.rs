@@ -4,18 +4,21 @@
 //! - The LLM routing configuration (composition)
 //! - Desired number of replicas
 //! - Health check configuration
-//! - Rollout strategy
+//! - Rollout strategy (including canary and blue/green)
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::config::Composition;
+use super::node::ReplicaStatus;
+use crate::config::{Composition, RunnerType};
 
 /// A Pipeline is the deployable unit in LLMNet
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Pipeline {
     /// API version (e.g., "llmnet/v1")
     #[serde(rename = "apiVersion")]
@@ -36,7 +39,7 @@ pub struct Pipeline {
 }
 
 /// Metadata for a Pipeline
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PipelineMetadata {
     /// Unique name within a namespace
     pub name: String,
@@ -61,20 +64,42 @@ pub struct PipelineMetadata {
     #[serde(rename = "creationTimestamp")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub creation_timestamp: Option<DateTime<Utc>>,
+
+    /// When deletion was requested; the resource is kept until its
+    /// finalizer has torn down what it runs
+    #[serde(rename = "deletionTimestamp")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deletion_timestamp: Option<DateTime<Utc>>,
 }
 
 fn default_namespace() -> String {
     "default".to_string()
 }
 
+/// Annotation that, set to "true", makes deleting the pipeline require
+/// `--force`
+pub const DELETION_PROTECTION_ANNOTATION: &str = "deletionProtection";
+
+/// Annotation naming the region a pipeline is delegated to, instead of
+/// being scheduled on this control plane's nodes
+pub const REGION_ANNOTATION: &str = "llmnet.io/region";
+
+/// Annotation the control plane stamps with the content hash of the
+/// pipeline's composition; see [`composition_hash`]
+pub const COMPOSITION_HASH_ANNOTATION: &str = "llmnet.io/composition-hash";
+
+/// Appended to a pipeline's name for its canary replica set
+pub const CANARY_SUFFIX: &str = "-canary";
+
 /// Specification of desired Pipeline state
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PipelineSpec {
     /// Number of desired replicas (default: 1)
     #[serde(default = "default_replicas")]
     pub replicas: u32,
 
     /// The LLM routing composition
+    #[schema(value_type = Object)]
     pub composition: Composition,
 
     /// Port to expose the OpenAI-compatible API
@@ -94,6 +119,11 @@ pub struct PipelineSpec {
     #[serde(default)]
     pub node_selector: HashMap<String, String>,
 
+    /// NodePool to place the replicas in; `nodeSelector` narrows it further
+    #[serde(rename = "nodePool")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_pool: Option<String>,
+
     /// Resource requirements
     #[serde(default)]
     pub resources: ResourceRequirements,
@@ -102,6 +132,134 @@ pub struct PipelineSpec {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub autoscaling: Option<AutoscalingConfig>,
+
+    /// How requests proxied by the control plane are spread across replicas
+    #[serde(rename = "loadBalancing")]
+    #[serde(default)]
+    pub load_balancing: ReplicaBalancing,
+
+    /// Environment variables set on the pipeline's runners
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+
+    /// Environment variables whose values come from the composition's
+    /// secrets, resolved on the control plane when the pipeline is assigned
+    #[serde(rename = "secretRefs")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secret_refs: Vec<SecretEnvRef>,
+
+    /// Pipelines and functions that must be ready before the pipeline is
+    /// scheduled
+    #[serde(rename = "dependsOn")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<PipelineDependency>,
+
+    /// Layers whose handlers run on other workers than the replicas
+    #[serde(rename = "layerPlacement")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layer_placement: Vec<LayerPlacement>,
+}
+
+/// Layers of a pipeline placed on a worker of their own
+///
+/// The replicas keep the router and send requests reaching one of the
+/// layers' handlers straight to the worker hosting them. Routers, outputs
+/// and nodes with other adapters stay on the replicas.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct LayerPlacement {
+    /// Architecture layers to place together
+    pub layers: Vec<u32>,
+    /// Labels the worker hosting them must have
+    #[serde(rename = "nodeSelector")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub node_selector: HashMap<String, String>,
+}
+
+impl LayerPlacement {
+    /// Place `layers` on a worker with every label of `node_selector`
+    pub fn new(layers: Vec<u32>, node_selector: HashMap<String, String>) -> Self {
+        Self {
+            layers,
+            node_selector,
+        }
+    }
+
+    /// Names of the composition's handlers in these layers
+    pub fn handlers(&self, composition: &Composition) -> Vec<String> {
+        composition
+            .architecture
+            .iter()
+            .filter(|n| n.layer.is_some_and(|layer| self.layers.contains(&layer)))
+            .filter(|n| !n.is_router() && n.adapter == "openai-api" && n.model.is_some())
+            .map(|n| n.name.clone())
+            .collect()
+    }
+}
+
+/// Condition type set while a pipeline waits for its dependencies
+pub const BLOCKED_CONDITION: &str = "Blocked";
+
+/// Something a pipeline waits for before it's scheduled, like an init
+/// container: another pipeline being Ready or a function's endpoint
+/// answering
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PipelineDependency {
+    /// Pipeline that must be ready: a name in the same namespace, or
+    /// `namespace/name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<String>,
+    /// REST function of the composition whose URL must answer without a
+    /// server error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+}
+
+impl PipelineDependency {
+    /// Wait for a pipeline, `name` or `namespace/name`
+    pub fn pipeline(name: impl Into<String>) -> Self {
+        Self {
+            pipeline: Some(name.into()),
+            function: None,
+        }
+    }
+
+    /// Wait for one of the composition's REST functions
+    pub fn function(name: impl Into<String>) -> Self {
+        Self {
+            pipeline: None,
+            function: Some(name.into()),
+        }
+    }
+
+    /// Namespace and name of the pipeline waited for, resolved against the
+    /// namespace of the pipeline that depends on it
+    pub fn pipeline_ref(&self, namespace: &str) -> Option<(String, String)> {
+        let pipeline = self.pipeline.as_deref()?;
+        Some(match pipeline.split_once('/') {
+            Some((namespace, name)) => (namespace.to_string(), name.to_string()),
+            None => (namespace.to_string(), pipeline.to_string()),
+        })
+    }
+}
+
+/// An environment variable taken from a secret
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SecretEnvRef {
+    /// Environment variable to set
+    pub name: String,
+    /// Secret declared in the composition's `secrets` section, or else a
+    /// cluster Secret in the pipeline's namespace
+    pub secret: String,
+    /// Variable of the secret to use (default: `name`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl SecretEnvRef {
+    /// The secret's variable this reference reads
+    pub fn key(&self) -> &str {
+        self.key.as_deref().unwrap_or(&self.name)
+    }
 }
 
 fn default_replicas() -> u32 {
@@ -112,8 +270,20 @@ fn default_port() -> u16 {
     8080
 }
 
+/// How the control plane spreads proxied requests across a pipeline's
+/// healthy replicas
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "PascalCase")]
+pub enum ReplicaBalancing {
+    /// Take replicas in turn
+    #[default]
+    RoundRobin,
+    /// Prefer the replica on the node with the best score
+    LeastLoaded,
+}
+
 /// Action to take when health check fails
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "PascalCase")]
 pub enum HealthAction {
     /// Just update status (mark replica as unhealthy) - passive tracking
@@ -126,7 +296,7 @@ pub enum HealthAction {
 }
 
 /// Health check configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct HealthConfig {
     /// Liveness check path (default: /health)
     #[serde(rename = "livenessPath")]
@@ -199,9 +369,9 @@ fn default_failure_threshold() -> u32 {
 }
 
 /// Rollout strategy for pipeline updates
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RolloutStrategy {
-    /// Type of rollout: "RollingUpdate" or "Recreate"
+    /// Type of rollout: "RollingUpdate", "Recreate", "Canary" or "BlueGreen"
     #[serde(rename = "type")]
     #[serde(default = "default_strategy_type")]
     pub strategy_type: String,
@@ -210,6 +380,10 @@ pub struct RolloutStrategy {
     #[serde(rename = "rollingUpdate")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rolling_update: Option<RollingUpdateParams>,
+
+    /// Canary parameters (if type is Canary or BlueGreen)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canary: Option<CanaryParams>,
 }
 
 impl Default for RolloutStrategy {
@@ -217,6 +391,7 @@ impl Default for RolloutStrategy {
         Self {
             strategy_type: default_strategy_type(),
             rolling_update: Some(RollingUpdateParams::default()),
+            canary: None,
         }
     }
 }
@@ -225,8 +400,93 @@ fn default_strategy_type() -> String {
     "RollingUpdate".to_string()
 }
 
+/// The kinds of rollout a strategy type names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RolloutKind {
+    RollingUpdate,
+    Recreate,
+    /// Run the new composition next to the old one and send it a share of traffic
+    Canary,
+    /// Run the new composition next to the old one and switch over once it's up
+    BlueGreen,
+}
+
+impl RolloutStrategy {
+    /// Kind of rollout, matching the type case-insensitively
+    /// (`canary`, `blue-green` and `blue_green` are accepted too)
+    pub fn kind(&self) -> RolloutKind {
+        let normalized: String = self
+            .strategy_type
+            .chars()
+            .filter(|c| *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_lowercase();
+        match normalized.as_str() {
+            "recreate" => RolloutKind::Recreate,
+            "canary" => RolloutKind::Canary,
+            "bluegreen" => RolloutKind::BlueGreen,
+            _ => RolloutKind::RollingUpdate,
+        }
+    }
+
+    /// Whether updates run a second replica set before replacing the first
+    pub fn is_progressive(&self) -> bool {
+        matches!(self.kind(), RolloutKind::Canary | RolloutKind::BlueGreen)
+    }
+
+    /// Canary parameters, or the defaults if none were given
+    pub fn canary_params(&self) -> CanaryParams {
+        self.canary.clone().unwrap_or_default()
+    }
+}
+
+/// Parameters for canary and blue/green rollouts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CanaryParams {
+    /// Percentage of requests sent to the canary (Canary only)
+    #[serde(default = "default_canary_weight")]
+    pub weight: u32,
+
+    /// Canary error rate (0.0-1.0) above which the rollout is rolled back
+    #[serde(rename = "maxErrorRate")]
+    #[serde(default = "default_max_error_rate")]
+    pub max_error_rate: f64,
+
+    /// Canary requests to observe before promoting
+    #[serde(rename = "minRequests")]
+    #[serde(default = "default_min_requests")]
+    pub min_requests: u64,
+
+    /// Port for the canary replicas (default: the pipeline port + 1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl Default for CanaryParams {
+    fn default() -> Self {
+        Self {
+            weight: default_canary_weight(),
+            max_error_rate: default_max_error_rate(),
+            min_requests: default_min_requests(),
+            port: None,
+        }
+    }
+}
+
+fn default_canary_weight() -> u32 {
+    10
+}
+
+fn default_max_error_rate() -> f64 {
+    0.05
+}
+
+fn default_min_requests() -> u64 {
+    50
+}
+
 /// Parameters for rolling update
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RollingUpdateParams {
     /// Maximum number of replicas that can be unavailable during update
     #[serde(rename = "maxUnavailable")]
@@ -257,8 +517,12 @@ fn default_max_surge() -> u32 {
 }
 
 /// Resource requirements for the pipeline
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
 pub struct ResourceRequirements {
+    /// GPUs per replica
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gpu: Option<u32>,
+
     /// GPU memory requirement (e.g., "16Gi")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gpu_memory: Option<String>,
@@ -273,7 +537,7 @@ pub struct ResourceRequirements {
 }
 
 /// Auto-scaling configuration for a pipeline
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AutoscalingConfig {
     /// Minimum number of replicas (default: 1)
     #[serde(rename = "minReplicas")]
@@ -349,7 +613,7 @@ fn default_scale_down_cooldown() -> u64 {
 }
 
 /// Scaling behavior configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ScalingBehavior {
     /// Maximum replicas to add per scale-up event
     #[serde(rename = "maxScaleUp")]
@@ -376,7 +640,7 @@ fn default_max_scale_step() -> u32 {
 }
 
 /// Current status of a Pipeline (observed state)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PipelineStatus {
     /// Total replicas currently managed
     pub replicas: u32,
@@ -404,10 +668,137 @@ pub struct PipelineStatus {
     /// Endpoints where pipeline is accessible
     #[serde(default)]
     pub endpoints: Vec<String>,
+
+    /// Canary or blue/green rollout, if one has been started
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollout: Option<RolloutStatus>,
+
+    /// Each replica as its worker last reported it
+    #[serde(rename = "replicaStatuses")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replica_statuses: Vec<PipelineReplica>,
+
+    /// Content hash of the composition this status describes
+    #[serde(rename = "compositionHash")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub composition_hash: Option<String>,
+}
+
+/// A replica of a pipeline on one node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PipelineReplica {
+    /// Node hosting the replica
+    pub node: String,
+
+    /// Port the replica is served on
+    pub port: u16,
+
+    /// Status reported by the worker, or found by health probes
+    pub status: ReplicaStatus,
+
+    /// Why the replica is unhealthy or failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Times the worker restarted the replica
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+/// Progress of a canary or blue/green rollout
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RolloutStatus {
+    /// Where the rollout stands
+    pub phase: RolloutPhase,
+
+    /// The spec that was serving before the rollout, kept until it finishes
+    #[serde(rename = "stableSpec")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stable_spec: Option<Box<PipelineSpec>>,
+
+    /// Percentage of proxied requests currently sent to the canary
+    pub weight: u32,
+
+    /// Endpoints of the canary replicas
+    #[serde(rename = "canaryEndpoints")]
+    #[serde(default)]
+    pub canary_endpoints: Vec<String>,
+
+    /// Proxied requests served by the stable replicas
+    #[serde(default)]
+    pub stable: TrafficStats,
+
+    /// Proxied requests served by the canary replicas
+    #[serde(default)]
+    pub canary: TrafficStats,
+
+    /// When the rollout started
+    #[serde(rename = "startTime")]
+    pub start_time: DateTime<Utc>,
+
+    /// Human-readable reason for the current phase
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Phase of a rollout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum RolloutPhase {
+    /// Canary replicas are starting or being evaluated
+    Progressing,
+    /// The new spec replaced the stable one
+    Promoted,
+    /// The canary was removed and the stable spec restored
+    RolledBack,
+}
+
+/// Request and error counts for one replica set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TrafficStats {
+    pub requests: u64,
+    pub errors: u64,
+}
+
+impl TrafficStats {
+    /// Fraction of requests that failed (0.0 with no requests)
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+impl RolloutStatus {
+    /// Start a rollout away from `stable_spec`
+    pub fn start(stable_spec: PipelineSpec, strategy: &RolloutStrategy) -> Self {
+        // Blue/green sends nothing to the new replicas until it switches over
+        let weight = match strategy.kind() {
+            RolloutKind::Canary => strategy.canary_params().weight.min(100),
+            _ => 0,
+        };
+        Self {
+            phase: RolloutPhase::Progressing,
+            stable_spec: Some(Box::new(stable_spec)),
+            weight,
+            canary_endpoints: vec![],
+            stable: TrafficStats::default(),
+            canary: TrafficStats::default(),
+            start_time: Utc::now(),
+            message: "Waiting for canary replicas".to_string(),
+        }
+    }
+
+    /// Whether the rollout is still running
+    pub fn is_progressing(&self) -> bool {
+        self.phase == RolloutPhase::Progressing
+    }
 }
 
 /// A condition of a Pipeline
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PipelineCondition {
     /// Type of condition (Available, Progressing, ReplicaFailure)
     #[serde(rename = "type")]
@@ -444,6 +835,7 @@ impl Pipeline {
                 labels: HashMap::new(),
                 annotations: HashMap::new(),
                 creation_timestamp: Some(Utc::now()),
+                deletion_timestamp: None,
             },
             spec: PipelineSpec {
                 replicas: 1,
@@ -452,8 +844,14 @@ impl Pipeline {
                 health: HealthConfig::default(),
                 strategy: RolloutStrategy::default(),
                 node_selector: HashMap::new(),
+                node_pool: None,
                 resources: ResourceRequirements::default(),
                 autoscaling: None,
+                load_balancing: ReplicaBalancing::default(),
+                env: BTreeMap::new(),
+                secret_refs: vec![],
+                depends_on: vec![],
+                layer_placement: vec![],
             },
             status: None,
         }
@@ -477,6 +875,12 @@ impl Pipeline {
         self
     }
 
+    /// Add an annotation
+    pub fn with_annotation(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.annotations.insert(key.into(), value.into());
+        self
+    }
+
     /// Get the full qualified name (namespace/name)
     pub fn qualified_name(&self) -> String {
         format!("{}/{}", self.metadata.namespace, self.metadata.name)
@@ -489,6 +893,193 @@ impl Pipeline {
             .map(|s| s.available_replicas >= self.spec.replicas)
             .unwrap_or(false)
     }
+
+    /// Whether deleting the pipeline needs `--force`
+    pub fn is_deletion_protected(&self) -> bool {
+        self.metadata
+            .annotations
+            .get(DELETION_PROTECTION_ANNOTATION)
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    }
+
+    /// Content hash of the pipeline's composition
+    pub fn composition_hash(&self) -> String {
+        composition_hash(&self.spec.composition)
+    }
+
+    /// The hash the control plane stamped on the pipeline when it was
+    /// stored
+    pub fn stored_composition_hash(&self) -> Option<&str> {
+        self.metadata
+            .annotations
+            .get(COMPOSITION_HASH_ANNOTATION)
+            .map(String::as_str)
+    }
+
+    /// The region the pipeline is delegated to, if any
+    pub fn region(&self) -> Option<&str> {
+        self.metadata
+            .annotations
+            .get(REGION_ANNOTATION)
+            .map(String::as_str)
+            .filter(|r| !r.is_empty())
+    }
+
+    /// Whether the pipeline is being deleted, waiting for its runners to be
+    /// torn down
+    pub fn is_terminating(&self) -> bool {
+        self.metadata.deletion_timestamp.is_some()
+    }
+
+    /// Whether the pipeline may run on workers: it has endpoints, or a
+    /// rollout with canary endpoints
+    pub fn has_replicas(&self) -> bool {
+        self.status.as_ref().is_some_and(|s| {
+            !s.endpoints.is_empty()
+                || s.rollout
+                    .as_ref()
+                    .is_some_and(|r| !r.canary_endpoints.is_empty())
+        })
+    }
+
+    /// Name the canary replica set of this pipeline runs under
+    pub fn canary_name(&self) -> String {
+        format!("{}{}", self.metadata.name, CANARY_SUFFIX)
+    }
+
+    /// The pipeline as deployed for its canary replica set: the current spec
+    /// under the canary name and port
+    pub fn canary_pipeline(&self) -> Pipeline {
+        let mut canary = self.clone();
+        canary.metadata.name = self.canary_name();
+        canary.spec.port = self
+            .spec
+            .strategy
+            .canary_params()
+            .port
+            .unwrap_or_else(|| self.spec.port.saturating_add(1));
+        canary.status = None;
+        canary
+    }
+
+    /// Wait for a dependency before being scheduled
+    pub fn with_dependency(mut self, dependency: PipelineDependency) -> Self {
+        self.spec.depends_on.push(dependency);
+        self
+    }
+
+    /// Run the handlers of some layers on a worker of their own
+    pub fn with_layer_placement(mut self, placement: LayerPlacement) -> Self {
+        self.spec.layer_placement.push(placement);
+        self
+    }
+
+    /// Why the pipeline is waiting for its dependencies, if it is
+    pub fn blocked_reason(&self) -> Option<&str> {
+        self.status
+            .as_ref()?
+            .conditions
+            .iter()
+            .find(|c| c.condition_type == BLOCKED_CONDITION && c.status == "True")
+            .map(|c| c.message.as_str())
+    }
+
+    /// Set an environment variable on the pipeline's runners
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.spec.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set an environment variable from one of the composition's secrets
+    pub fn with_secret_ref(mut self, secret_ref: SecretEnvRef) -> Self {
+        self.spec.secret_refs.push(secret_ref);
+        self
+    }
+
+    /// Environment for the pipeline's runners: `spec.env` plus each secret
+    /// reference, read with `lookup(secret, key)`
+    pub fn runner_env(
+        &self,
+        lookup: impl Fn(&str, &str) -> Option<String>,
+    ) -> Result<BTreeMap<String, String>, String> {
+        let mut env = self.spec.env.clone();
+        for secret_ref in &self.spec.secret_refs {
+            if !self.declares_secret(&secret_ref.secret) {
+                continue;
+            }
+            let value = lookup(&secret_ref.secret, secret_ref.key()).ok_or_else(|| {
+                format!("Secret '{}' has no {}", secret_ref.secret, secret_ref.key())
+            })?;
+            env.insert(secret_ref.name.clone(), value);
+        }
+        Ok(env)
+    }
+
+    /// References to cluster Secrets, which workers resolve when they spawn
+    /// the pipeline's runners
+    pub fn cluster_secret_refs(&self) -> Vec<SecretEnvRef> {
+        self.spec
+            .secret_refs
+            .iter()
+            .filter(|r| !self.declares_secret(&r.secret))
+            .cloned()
+            .collect()
+    }
+
+    fn declares_secret(&self, secret: &str) -> bool {
+        self.spec.composition.secrets.contains_key(secret)
+    }
+
+    /// Runner types a node must have installed to host this pipeline
+    pub fn required_runners(&self) -> Vec<RunnerType> {
+        let mut runners = Vec::new();
+        for model in self.spec.composition.models.values() {
+            let runner = model.to_config().runner;
+            if runner != RunnerType::External && !runners.contains(&runner) {
+                runners.push(runner);
+            }
+        }
+        runners
+    }
+}
+
+/// Content hash of a composition: `sha256:` and the hex digest of its JSON
+/// with object keys sorted, so equal compositions always hash alike
+pub fn composition_hash(composition: &Composition) -> String {
+    let value = serde_json::to_value(composition).unwrap_or_default();
+    let mut canonical = String::new();
+    write_canonical(&value, &mut canonical);
+    format!("sha256:{:x}", Sha256::digest(canonical.as_bytes()))
+}
+
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
 }
 
 impl PipelineStatus {
@@ -502,6 +1093,9 @@ impl PipelineStatus {
             observed_generation: 0,
             conditions: vec![],
             endpoints: vec![],
+            rollout: None,
+            replica_statuses: vec![],
+            composition_hash: None,
         }
     }
 
@@ -585,6 +1179,29 @@ mod tests {
         assert_eq!(pipeline.qualified_name(), "prod/my-pipeline");
     }
 
+    #[test]
+    fn test_composition_hash() {
+        let models = |first: &str, second: &str| {
+            Composition::from_str(&format!(
+                r#"{{"models": {{{}, {}}}, "architecture": [
+                    {{"name": "router", "layer": 0, "model": "a", "adapter": "openai-api"}},
+                    {{"name": "output", "adapter": "output"}}
+                ]}}"#,
+                first, second
+            ))
+            .unwrap()
+        };
+        let a = r#""a": {"type": "external", "interface": "openai-api", "url": "http://a/v1"}"#;
+        let b = r#""b": {"type": "external", "interface": "openai-api", "url": "http://b/v1"}"#;
+
+        let hash = composition_hash(&models(a, b));
+        assert!(hash.starts_with("sha256:"));
+        assert_eq!(hash.len(), "sha256:".len() + 64);
+        // Key order doesn't matter, content does
+        assert_eq!(composition_hash(&models(b, a)), hash);
+        assert_ne!(composition_hash(&create_test_composition()), hash);
+    }
+
     #[test]
     fn test_is_ready() {
         let comp = create_test_composition();
@@ -602,6 +1219,9 @@ mod tests {
             observed_generation: 1,
             conditions: vec![],
             endpoints: vec![],
+            rollout: None,
+            replica_statuses: vec![],
+            composition_hash: None,
         });
         assert!(!pipeline.is_ready());
 
@@ -614,6 +1234,9 @@ mod tests {
             observed_generation: 1,
             conditions: vec![],
             endpoints: vec![],
+            rollout: None,
+            replica_statuses: vec![],
+            composition_hash: None,
         });
         assert!(pipeline.is_ready());
     }
@@ -648,6 +1271,204 @@ mod tests {
         assert_eq!(rolling.max_surge, 1);
     }
 
+    #[test]
+    fn test_canary_strategy() {
+        let yaml = r#"
+type: canary
+canary:
+  weight: 25
+  maxErrorRate: 0.1
+"#;
+        let strategy: RolloutStrategy = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(strategy.kind(), RolloutKind::Canary);
+        assert!(strategy.is_progressive());
+        let params = strategy.canary_params();
+        assert_eq!(params.weight, 25);
+        assert_eq!(params.min_requests, 50);
+
+        let blue_green = RolloutStrategy {
+            strategy_type: "blue-green".to_string(),
+            ..RolloutStrategy::default()
+        };
+        assert_eq!(blue_green.kind(), RolloutKind::BlueGreen);
+        assert!(!RolloutStrategy::default().is_progressive());
+
+        // Blue/green sends nothing to the new replicas while progressing
+        let comp = create_test_composition();
+        let spec = Pipeline::new("bot", comp).spec;
+        assert_eq!(RolloutStatus::start(spec.clone(), &strategy).weight, 25);
+        assert_eq!(RolloutStatus::start(spec, &blue_green).weight, 0);
+    }
+
+    #[test]
+    fn test_canary_pipeline() {
+        let comp = create_test_composition();
+        let mut pipeline = Pipeline::new("bot", comp);
+        pipeline.status = Some(PipelineStatus::initial());
+
+        let canary = pipeline.canary_pipeline();
+        assert_eq!(canary.metadata.name, "bot-canary");
+        assert_eq!(canary.spec.port, 8081);
+        assert!(canary.status.is_none());
+
+        pipeline.spec.strategy.canary = Some(CanaryParams {
+            port: Some(9000),
+            ..CanaryParams::default()
+        });
+        assert_eq!(pipeline.canary_pipeline().spec.port, 9000);
+    }
+
+    #[test]
+    fn test_runner_env() {
+        let yaml = r#"
+apiVersion: llmnet/v1
+kind: Pipeline
+metadata:
+  name: bot
+spec:
+  composition:
+    secrets:
+      openai: {source: env, variable: OPENAI_API_KEY}
+    models: {}
+    architecture:
+      - {name: router, layer: 0, adapter: openai-api}
+      - {name: output, adapter: output}
+  env:
+    LOG_LEVEL: debug
+  secretRefs:
+    - {name: OPENAI_API_KEY, secret: openai}
+    - {name: API_KEY, secret: openai, key: OPENAI_API_KEY}
+"#;
+        let pipeline: Pipeline = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(pipeline.spec.secret_refs[0].key(), "OPENAI_API_KEY");
+
+        let env = pipeline
+            .runner_env(|secret, key| {
+                (secret == "openai" && key == "OPENAI_API_KEY").then(|| "sk-123".to_string())
+            })
+            .unwrap();
+        assert_eq!(env.len(), 3);
+        assert_eq!(env["LOG_LEVEL"], "debug");
+        assert_eq!(env["OPENAI_API_KEY"], "sk-123");
+        assert_eq!(env["API_KEY"], "sk-123");
+
+        assert_eq!(
+            pipeline.runner_env(|_, _| None),
+            Err("Secret 'openai' has no OPENAI_API_KEY".to_string())
+        );
+        assert!(pipeline.cluster_secret_refs().is_empty());
+
+        // Secrets the composition doesn't declare are cluster Secrets
+        let cluster = pipeline.with_secret_ref(SecretEnvRef {
+            name: "TOKEN".to_string(),
+            secret: "vault".to_string(),
+            key: None,
+        });
+        let env = cluster
+            .runner_env(|_, _| Some("sk-123".to_string()))
+            .unwrap();
+        assert!(!env.contains_key("TOKEN"));
+        let refs = cluster.cluster_secret_refs();
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].secret, "vault");
+
+        // Unset fields stay out of serialized specs
+        let plain = serde_json::to_value(Pipeline::new("bot", create_test_composition())).unwrap();
+        assert!(plain["spec"].get("env").is_none());
+        assert!(plain["spec"].get("secretRefs").is_none());
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let json = serde_json::json!({
+            "replicas": 1,
+            "composition": serde_json::to_value(create_test_composition()).unwrap(),
+            "dependsOn": [{"pipeline": "embedder"}, {"pipeline": "shared/db"}, {"function": "lookup"}]
+        });
+        let spec: PipelineSpec = serde_json::from_value(json).unwrap();
+        let refs: Vec<_> = spec
+            .depends_on
+            .iter()
+            .map(|d| d.pipeline_ref("prod"))
+            .collect();
+        assert_eq!(
+            refs,
+            vec![
+                Some(("prod".to_string(), "embedder".to_string())),
+                Some(("shared".to_string(), "db".to_string())),
+                None
+            ]
+        );
+        assert_eq!(spec.depends_on[2], PipelineDependency::function("lookup"));
+
+        let mut pipeline = Pipeline::new("bot", create_test_composition())
+            .with_dependency(PipelineDependency::pipeline("embedder"));
+        assert_eq!(pipeline.blocked_reason(), None);
+        let mut status = PipelineStatus::initial();
+        status.add_condition(PipelineCondition::new(
+            BLOCKED_CONDITION,
+            "True",
+            "DependenciesNotReady",
+            "Pipeline default/embedder is not ready",
+        ));
+        pipeline.status = Some(status);
+        assert_eq!(
+            pipeline.blocked_reason(),
+            Some("Pipeline default/embedder is not ready")
+        );
+    }
+
+    #[test]
+    fn test_layer_placement() {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {
+                    "small": {"runner": "external", "endpoint": "http://small:8000/v1"},
+                    "large": {"runner": "external", "endpoint": "http://large:8000/v1"}
+                },
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "small", "adapter": "openai-api",
+                     "output-to": ["coder", "checker"]},
+                    {"name": "coder", "layer": 1, "model": "large", "adapter": "openai-api",
+                     "output-to": ["output"]},
+                    {"name": "checker", "layer": 1, "adapter": "guard",
+                     "guard": {"denylist": ["password"]},
+                     "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let json = serde_json::json!({
+            "composition": serde_json::to_value(&composition).unwrap(),
+            "layerPlacement": [{"layers": [1], "nodeSelector": {"gpu": "a100"}}]
+        });
+        let spec: PipelineSpec = serde_json::from_value(json).unwrap();
+        let placement = &spec.layer_placement[0];
+        assert_eq!(placement.node_selector["gpu"], "a100");
+        // Only model handlers move; the guard stays with the router
+        assert_eq!(placement.handlers(&composition), vec!["coder"]);
+
+        // The router can't be moved off the replicas
+        let router_only = LayerPlacement::new(vec![0], HashMap::new());
+        assert!(router_only.handlers(&composition).is_empty());
+
+        let pipeline = Pipeline::new("bot", composition)
+            .with_layer_placement(LayerPlacement::new(vec![1], HashMap::new()));
+        let json = serde_json::to_value(&pipeline).unwrap();
+        assert_eq!(json["spec"]["layerPlacement"][0]["layers"][0], 1);
+    }
+
+    #[test]
+    fn test_traffic_stats_error_rate() {
+        assert_eq!(TrafficStats::default().error_rate(), 0.0);
+        let stats = TrafficStats {
+            requests: 20,
+            errors: 5,
+        };
+        assert_eq!(stats.error_rate(), 0.25);
+    }
+
     #[test]
     fn test_pipeline_condition() {
         let condition = PipelineCondition::new("Available", "True", "MinimumReplicasAvailable", "");
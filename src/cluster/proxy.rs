@@ -0,0 +1,214 @@
+//! Replica selection for inference proxied through the control plane
+//!
+//! `POST /v1/namespaces/{ns}/pipelines/{name}/chat/completions` forwards to
+//! one of the pipeline's replicas, so clients need a single stable endpoint.
+//! Replicas on nodes that aren't ready, and replicas the health checker
+//! marked unhealthy, are skipped. The rest are taken in turn or, with
+//! `loadBalancing: LeastLoaded`, by the best node score.
+
+use super::node::{Node, ReplicaStatus};
+use super::pipeline::ReplicaBalancing;
+
+/// A pipeline endpoint and what the control plane knows about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicaTarget {
+    pub endpoint: String,
+    /// Node serving the endpoint, when it could be matched to one
+    pub node: Option<String>,
+    /// The node's score (0-100, higher is less loaded)
+    pub score: Option<f64>,
+    pub healthy: bool,
+}
+
+// ============================================================================
+// SBIO: Pure functions
+// ============================================================================
+
+/// Host and port of an endpoint URL
+fn host_port(endpoint: &str) -> Option<(String, u16)> {
+    let url = reqwest::Url::parse(endpoint).ok()?;
+    Some((url.host_str()?.to_string(), url.port_or_known_default()?))
+}
+
+/// Match each endpoint to the node serving it and judge its health
+///
+/// An endpoint belongs to the node tracking the pipeline on its port at
+/// the endpoint's address, or to the only node tracking it on that port.
+/// Endpoints no node claims are assumed healthy.
+pub fn replica_targets(
+    namespace: &str,
+    name: &str,
+    endpoints: &[String],
+    nodes: &[Node],
+) -> Vec<ReplicaTarget> {
+    endpoints
+        .iter()
+        .map(|endpoint| {
+            let serving: Vec<(&Node, ReplicaStatus)> = host_port(endpoint)
+                .map(|(_, port)| {
+                    nodes
+                        .iter()
+                        .filter_map(|node| {
+                            let replica = node.status.as_ref()?.pipelines.iter().find(|p| {
+                                p.namespace == namespace && p.name == name && p.port == port
+                            })?;
+                            Some((node, replica.status))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let host = host_port(endpoint).map(|(host, _)| host);
+            let matched = serving
+                .iter()
+                .find(|(node, _)| Some(&node.spec.address) == host.as_ref())
+                .or_else(|| (serving.len() == 1).then(|| &serving[0]));
+
+            match matched {
+                Some((node, status)) => ReplicaTarget {
+                    endpoint: endpoint.clone(),
+                    node: Some(node.metadata.name.clone()),
+                    score: node
+                        .status
+                        .as_ref()
+                        .and_then(|s| s.score.as_ref())
+                        .map(|s| s.score),
+                    healthy: node.is_ready()
+                        && matches!(status, ReplicaStatus::Running | ReplicaStatus::Starting),
+                },
+                None => ReplicaTarget {
+                    endpoint: endpoint.clone(),
+                    node: None,
+                    score: None,
+                    healthy: true,
+                },
+            }
+        })
+        .collect()
+}
+
+/// Pick the healthy replica for request number `n`
+///
+/// Round-robin takes healthy replicas in turn. Least-loaded takes the one
+/// on the best-scoring node; equally scored replicas still take turns.
+pub fn pick_replica(
+    targets: &[ReplicaTarget],
+    balancing: ReplicaBalancing,
+    n: u64,
+) -> Option<&ReplicaTarget> {
+    let healthy: Vec<&ReplicaTarget> = targets.iter().filter(|t| t.healthy).collect();
+    if healthy.is_empty() {
+        return None;
+    }
+
+    let start = n as usize % healthy.len();
+    let mut in_turn = (0..healthy.len()).map(|offset| healthy[(start + offset) % healthy.len()]);
+    match balancing {
+        ReplicaBalancing::RoundRobin => in_turn.next(),
+        ReplicaBalancing::LeastLoaded => in_turn.reduce(|best, target| {
+            if target.score.unwrap_or(0.0) > best.score.unwrap_or(0.0) {
+                target
+            } else {
+                best
+            }
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::node::{
+        NodeCapacity, NodeInfo, NodePhase, NodePipelineInfo, NodeScore, NodeStatus, ScoreBreakdown,
+    };
+
+    fn node(name: &str, address: &str, score: f64, replica: ReplicaStatus) -> Node {
+        let mut node = Node::new(name, address);
+        let mut status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+        status.phase = NodePhase::Ready;
+        status.pipelines = vec![NodePipelineInfo {
+            name: "bot".to_string(),
+            namespace: "default".to_string(),
+            port: 8080,
+            status: replica,
+            reason: None,
+            restarts: 0,
+        }];
+        status.score = Some(NodeScore {
+            score,
+            breakdown: ScoreBreakdown::default(),
+            calculated_at: chrono::Utc::now(),
+        });
+        node.status = Some(status);
+        node
+    }
+
+    fn endpoints(hosts: &[&str]) -> Vec<String> {
+        hosts.iter().map(|h| format!("http://{}:8080", h)).collect()
+    }
+
+    #[test]
+    fn test_replica_targets() {
+        let nodes = vec![
+            node("a", "10.0.0.1", 80.0, ReplicaStatus::Running),
+            node("b", "10.0.0.2", 40.0, ReplicaStatus::Unhealthy),
+        ];
+        let targets = replica_targets(
+            "default",
+            "bot",
+            &endpoints(&["10.0.0.1", "10.0.0.2", "10.0.0.3"]),
+            &nodes,
+        );
+
+        assert_eq!(targets[0].node.as_deref(), Some("a"));
+        assert_eq!(targets[0].score, Some(80.0));
+        assert!(targets[0].healthy);
+        assert_eq!(targets[1].node.as_deref(), Some("b"));
+        assert!(!targets[1].healthy);
+        // Two nodes track the pipeline on this port, neither at this address
+        assert_eq!(targets[2].node, None);
+        assert!(targets[2].healthy);
+
+        // A lone node claims its replica's endpoint whatever address the
+        // worker reported
+        let targets = replica_targets("default", "bot", &endpoints(&["0.0.0.0"]), &nodes[..1]);
+        assert_eq!(targets[0].node.as_deref(), Some("a"));
+
+        let mut not_ready = node("a", "10.0.0.1", 80.0, ReplicaStatus::Running);
+        not_ready.status.as_mut().unwrap().phase = NodePhase::NotReady;
+        let targets = replica_targets("default", "bot", &endpoints(&["10.0.0.1"]), &[not_ready]);
+        assert!(!targets[0].healthy);
+    }
+
+    #[test]
+    fn test_pick_replica() {
+        let target = |endpoint: &str, score: f64, healthy: bool| ReplicaTarget {
+            endpoint: endpoint.to_string(),
+            node: None,
+            score: Some(score),
+            healthy,
+        };
+        let targets = vec![
+            target("a", 50.0, true),
+            target("b", 90.0, false),
+            target("c", 70.0, true),
+            target("d", 70.0, true),
+        ];
+        let picks = |balancing| -> Vec<&str> {
+            (0..4)
+                .map(|n| {
+                    pick_replica(&targets, balancing, n)
+                        .unwrap()
+                        .endpoint
+                        .as_str()
+                })
+                .collect()
+        };
+
+        assert_eq!(picks(ReplicaBalancing::RoundRobin), ["a", "c", "d", "a"]);
+        // The unhealthy "b" scores best but is skipped; "c" and "d" alternate
+        assert_eq!(picks(ReplicaBalancing::LeastLoaded), ["c", "c", "d", "c"]);
+
+        let down = vec![target("a", 50.0, false)];
+        assert!(pick_replica(&down, ReplicaBalancing::RoundRobin, 0).is_none());
+    }
+}
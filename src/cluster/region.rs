@@ -0,0 +1,469 @@
+//! Region resource - a child control plane pipelines are delegated to
+//!
+//! One control plane can federate others: each is registered as a Region,
+//! and pipelines annotated with `llmnet.io/region: <name>` are forwarded to
+//! that region's control plane instead of being scheduled on local nodes:
+//!
+//! ```yaml
+//! apiVersion: llmnet/v1
+//! kind: Region
+//! metadata:
+//!   name: eu-west
+//! spec:
+//!   url: http://cp.eu-west.internal:8181
+//! ```
+//!
+//! The federation loop applies each regional pipeline to its region,
+//! copies the status the region reports back onto it, and deletes it there
+//! once it's deleted here. What every region runs is also kept, so
+//! `GET /v1/pipelines?federated=true` (`llmnet get pipelines -A`) spans all
+//! federated clusters.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use super::controller::ClusterController;
+use super::node::NodeMetadata;
+use super::pipeline::{Pipeline, PipelineCondition, PipelineStatus, REGION_ANNOTATION};
+use super::resources::ResourceList;
+use super::API_VERSION;
+
+/// Default time between federation passes
+pub const DEFAULT_FEDERATION_INTERVAL_SECS: u64 = 10;
+
+/// Seconds a region's control plane may take to answer
+const REGION_TIMEOUT_SECS: u64 = 10;
+
+/// A child control plane
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Region {
+    /// API version (e.g., "llmnet/v1")
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+
+    /// Kind is always "Region"
+    pub kind: String,
+
+    /// Name, labels and annotations of the region
+    pub metadata: NodeMetadata,
+
+    /// Where the region's control plane is
+    pub spec: RegionSpec,
+
+    /// Outcome of the last sync (populated by controller)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<RegionStatus>,
+}
+
+/// Specification of a Region
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RegionSpec {
+    /// Base URL of the region's control plane API
+    pub url: String,
+}
+
+/// Observed state of a Region
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RegionStatus {
+    /// Whether the last sync reached the region
+    pub reachable: bool,
+
+    /// Pipelines the region runs
+    pub pipelines: u32,
+
+    /// When the region was last synced
+    #[serde(rename = "lastSync")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sync: Option<DateTime<Utc>>,
+
+    /// Why the last sync failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl Region {
+    /// Create a region for the control plane at `url`
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            api_version: API_VERSION.to_string(),
+            kind: "Region".to_string(),
+            metadata: NodeMetadata {
+                name: name.into(),
+                labels: HashMap::new(),
+                annotations: HashMap::new(),
+            },
+            spec: RegionSpec { url: url.into() },
+            status: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.metadata.name
+    }
+
+    /// The region's control plane URL, without a trailing slash
+    pub fn base_url(&self) -> &str {
+        self.spec.url.trim_end_matches('/')
+    }
+}
+
+impl RegionSpec {
+    /// Check the spec points at a control plane
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(format!(
+                "spec.url must be an http:// or https:// URL, got '{}'",
+                self.url
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The manifest a regional pipeline is applied to its region with: the
+/// same pipeline, without the region annotation or local state
+pub fn forwarded_manifest(pipeline: &Pipeline) -> Pipeline {
+    let mut forwarded = pipeline.clone();
+    forwarded.metadata.annotations.remove(REGION_ANNOTATION);
+    forwarded.metadata.deletion_timestamp = None;
+    forwarded.status = None;
+    forwarded
+}
+
+/// Whether the region's copy of a pipeline is missing or out of date
+pub fn needs_forwarding(pipeline: &Pipeline, remote: Option<&Pipeline>) -> bool {
+    let Some(remote) = remote else {
+        return true;
+    };
+    let forwarded = forwarded_manifest(pipeline);
+    forwarded.metadata.labels != remote.metadata.labels
+        || forwarded.metadata.annotations != remote.metadata.annotations
+        || serde_json::to_value(&forwarded.spec).ok() != serde_json::to_value(&remote.spec).ok()
+}
+
+/// Pipelines a region runs, annotated with the region they run in
+pub fn in_region(region: &str, pipelines: Vec<Pipeline>) -> Vec<Pipeline> {
+    pipelines
+        .into_iter()
+        .map(|mut p| {
+            p.metadata
+                .annotations
+                .insert(REGION_ANNOTATION.to_string(), region.to_string());
+            p
+        })
+        .collect()
+}
+
+/// A regional pipeline's status after its region refused it, keeping one
+/// `Forwarded` condition with the latest reason
+pub fn rejected_status(pipeline: &Pipeline, region: &str, error: &str) -> PipelineStatus {
+    let mut status = pipeline
+        .status
+        .clone()
+        .unwrap_or_else(PipelineStatus::initial);
+    status
+        .conditions
+        .retain(|c| c.condition_type != "Forwarded");
+    status.conditions.push(PipelineCondition::new(
+        "Forwarded",
+        "False",
+        "RegionRejected",
+        format!("Region {} didn't accept the pipeline: {}", region, error),
+    ));
+    status
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Pipelines the region's control plane runs
+async fn list_remote(client: &reqwest::Client, region: &Region) -> Result<Vec<Pipeline>, String> {
+    let list: ResourceList<Pipeline> = client
+        .get(format!("{}/v1/pipelines", region.base_url()))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| format!("invalid pipeline list: {}", e))?;
+    Ok(list.items)
+}
+
+/// Create or update a pipeline on the region's control plane
+async fn apply_remote(
+    client: &reqwest::Client,
+    region: &Region,
+    pipeline: &Pipeline,
+) -> Result<(), String> {
+    let response = client
+        .put(pipeline_url(region, pipeline))
+        .json(&forwarded_manifest(pipeline))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    Err(body["error"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| status.to_string()))
+}
+
+/// Ask the region's control plane to delete a pipeline
+async fn delete_remote(
+    client: &reqwest::Client,
+    region: &Region,
+    pipeline: &Pipeline,
+) -> Result<(), String> {
+    client
+        .delete(format!("{}?force=true", pipeline_url(region, pipeline)))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn pipeline_url(region: &Region, pipeline: &Pipeline) -> String {
+    format!(
+        "{}/v1/namespaces/{}/pipelines/{}",
+        region.base_url(),
+        pipeline.metadata.namespace,
+        pipeline.metadata.name
+    )
+}
+
+/// Sync one region: forward its pipelines, copy their status back and
+/// finish deleting the ones the region no longer runs
+pub async fn sync_region(
+    controller: &ClusterController,
+    client: &reqwest::Client,
+    region: &Region,
+) {
+    let name = region.name();
+    let remote = match list_remote(client, region).await {
+        Ok(remote) => remote,
+        Err(e) => {
+            debug!("Region {} unreachable: {}", name, e);
+            let pipelines = region.status.as_ref().map_or(0, |s| s.pipelines);
+            controller.set_region_status(
+                name,
+                RegionStatus {
+                    reachable: false,
+                    pipelines,
+                    last_sync: region.status.as_ref().and_then(|s| s.last_sync),
+                    message: Some(e),
+                },
+            );
+            return;
+        }
+    };
+    let by_name: HashMap<String, &Pipeline> =
+        remote.iter().map(|p| (p.qualified_name(), p)).collect();
+
+    for pipeline in controller
+        .list_all_pipelines()
+        .into_iter()
+        .filter(|p| p.region() == Some(name))
+    {
+        let namespace = &pipeline.metadata.namespace;
+        let pipeline_name = &pipeline.metadata.name;
+        let copy = by_name.get(&pipeline.qualified_name()).copied();
+
+        if pipeline.is_terminating() {
+            if copy.is_none() {
+                let _ = controller.delete_pipeline(namespace, pipeline_name);
+                controller.record_event(
+                    format!("pipeline/{}", pipeline.qualified_name()),
+                    "Deleted",
+                    format!("Pipeline deleted in region {}", name),
+                );
+            } else if let Err(e) = delete_remote(client, region, &pipeline).await {
+                warn!(
+                    "Failed to delete {} in region {}: {}",
+                    pipeline.qualified_name(),
+                    name,
+                    e
+                );
+            }
+            continue;
+        }
+
+        if needs_forwarding(&pipeline, copy) {
+            if let Err(e) = apply_remote(client, region, &pipeline).await {
+                warn!(
+                    "Region {} rejected {}: {}",
+                    name,
+                    pipeline.qualified_name(),
+                    e
+                );
+                let status = rejected_status(&pipeline, name, &e);
+                let _ = controller.update_pipeline_status(namespace, pipeline_name, status);
+                continue;
+            }
+        }
+        if let Some(status) = copy.and_then(|p| p.status.clone()) {
+            let _ = controller.update_pipeline_status(namespace, pipeline_name, status);
+        }
+    }
+
+    controller.set_region_status(
+        name,
+        RegionStatus {
+            reachable: true,
+            pipelines: remote.len() as u32,
+            last_sync: Some(Utc::now()),
+            message: None,
+        },
+    );
+    controller.set_region_pipelines(name, remote);
+}
+
+/// Sync every region every `interval`
+pub fn spawn_federation(controller: Arc<ClusterController>, interval: Duration) -> JoinHandle<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(REGION_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for region in controller.list_regions() {
+                sync_region(&controller, &client, &region).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Composition;
+
+    fn pipeline() -> Pipeline {
+        let composition = Composition::from_str(
+            r#"{"models": {}, "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]}"#,
+        )
+        .unwrap();
+        let mut pipeline =
+            Pipeline::new("chat", composition).with_annotation(REGION_ANNOTATION, "eu-west");
+        pipeline.status = Some(PipelineStatus::initial());
+        pipeline
+    }
+
+    #[test]
+    fn test_forwarded_manifest() {
+        let local = pipeline();
+        assert_eq!(local.region(), Some("eu-west"));
+
+        let forwarded = forwarded_manifest(&local);
+        assert_eq!(forwarded.region(), None);
+        assert!(forwarded.status.is_none());
+
+        assert!(needs_forwarding(&local, None));
+        assert!(!needs_forwarding(&local, Some(&forwarded)));
+        let mut scaled = local.clone();
+        scaled.spec.replicas = 3;
+        assert!(needs_forwarding(&scaled, Some(&forwarded)));
+    }
+
+    #[test]
+    fn test_rejected_status_keeps_one_condition() {
+        let mut local = pipeline();
+        local.status = Some(rejected_status(&local, "eu-west", "no nodes"));
+        let status = rejected_status(&local, "eu-west", "still no nodes");
+        let forwarded: Vec<_> = status
+            .conditions
+            .iter()
+            .filter(|c| c.condition_type == "Forwarded")
+            .collect();
+        assert_eq!(forwarded.len(), 1);
+        assert!(forwarded[0].message.contains("still no nodes"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_region() {
+        let child = crate::cluster::ControlPlaneState::new();
+        let app = crate::cluster::create_control_plane_router(child.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let parent = ClusterController::new();
+        let region = parent
+            .apply_region(Region::new("eu-west", format!("http://{}", addr)))
+            .unwrap();
+        let mut local = pipeline();
+        local.status = None;
+        parent.deploy_pipeline(local).unwrap();
+        let client = reqwest::Client::new();
+
+        // Forwarded without the region annotation
+        sync_region(&parent, &client, &region).await;
+        let forwarded = child.controller.get_pipeline("default", "chat").unwrap();
+        assert_eq!(forwarded.region(), None);
+
+        // The region's status is copied back
+        let mut status = PipelineStatus::initial();
+        status.ready_replicas = 1;
+        child
+            .controller
+            .update_pipeline_status("default", "chat", status)
+            .unwrap();
+        sync_region(&parent, &client, &region).await;
+        let synced = parent.get_pipeline("default", "chat").unwrap();
+        assert_eq!(synced.status.unwrap().ready_replicas, 1);
+        let region = parent.get_region("eu-west").unwrap();
+        let region_status = region.status.clone().unwrap();
+        assert!(region_status.reachable);
+        assert_eq!(region_status.pipelines, 1);
+
+        // Deleted in the region first, then here
+        parent
+            .request_pipeline_deletion("default", "chat", false)
+            .unwrap();
+        sync_region(&parent, &client, &region).await;
+        assert!(child.controller.get_pipeline("default", "chat").is_none());
+        assert!(parent.get_pipeline("default", "chat").is_some());
+        sync_region(&parent, &client, &region).await;
+        assert!(parent.get_pipeline("default", "chat").is_none());
+
+        let unreachable = parent
+            .apply_region(Region::new("eu-west", "http://127.0.0.1:1"))
+            .unwrap();
+        sync_region(&parent, &client, &unreachable).await;
+        let status = parent.get_region("eu-west").unwrap().status.unwrap();
+        assert!(!status.reachable);
+        assert!(status.message.is_some());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Region::new("eu", "http://cp:8181").spec.validate().is_ok());
+        assert!(Region::new("eu", "cp:8181").spec.validate().is_err());
+        assert_eq!(
+            Region::new("eu", "http://cp:8181/").base_url(),
+            "http://cp:8181"
+        );
+    }
+}
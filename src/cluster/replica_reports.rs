@@ -0,0 +1,158 @@
+//! Replica status a worker reports with its heartbeats
+//!
+//! The worker records what happens to each pipeline assigned to it:
+//! Starting while its runners start, Running once it's served, and Failed
+//! with the reason when it can't be. Each new assignment of a pipeline it
+//! already hosts counts as a restart. Heartbeats report the replicas as
+//! recorded, except that a Running replica whose runners have stopped is
+//! reported Unhealthy.
+
+use std::sync::Arc;
+
+use dashmap::DashMap;
+
+use super::node::{NodePipelineInfo, ReplicaStatus};
+
+/// A replica and the runners it needs
+#[derive(Debug, Clone)]
+struct TrackedReplica {
+    info: NodePipelineInfo,
+    runners: Vec<String>,
+}
+
+/// Status of the replicas a worker hosts, shared between the assignment
+/// handlers and the heartbeat client
+#[derive(Debug, Clone, Default)]
+pub struct ReplicaReports {
+    replicas: Arc<DashMap<String, TrackedReplica>>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// How a replica is reported, given whether each runner is still up
+pub fn observed(
+    info: &NodePipelineInfo,
+    runners: &[String],
+    is_running: impl Fn(&str) -> bool,
+) -> NodePipelineInfo {
+    let mut info = info.clone();
+    if info.status != ReplicaStatus::Running {
+        return info;
+    }
+    if let Some(stopped) = runners.iter().find(|r| !is_running(r)) {
+        info.status = ReplicaStatus::Unhealthy;
+        info.reason = Some(format!("runner for model '{}' isn't running", stopped));
+    }
+    info
+}
+
+impl ReplicaReports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A pipeline's replica is being started, or restarted if it was
+    /// hosted already
+    pub fn starting(&self, namespace: &str, name: &str, port: u16) {
+        let key = key(namespace, name);
+        let restarts = self.replicas.get(&key).map_or(0, |r| r.info.restarts + 1);
+        self.replicas.insert(
+            key,
+            TrackedReplica {
+                info: NodePipelineInfo {
+                    name: name.to_string(),
+                    namespace: namespace.to_string(),
+                    port,
+                    status: ReplicaStatus::Starting,
+                    reason: None,
+                    restarts,
+                },
+                runners: Vec::new(),
+            },
+        );
+    }
+
+    /// A pipeline's replica is served, using these runners
+    pub fn running(&self, namespace: &str, name: &str, runners: Vec<String>) {
+        if let Some(mut replica) = self.replicas.get_mut(&key(namespace, name)) {
+            replica.info.status = ReplicaStatus::Running;
+            replica.info.reason = None;
+            replica.runners = runners;
+        }
+    }
+
+    /// A pipeline's replica couldn't be started
+    pub fn failed(&self, namespace: &str, name: &str, reason: impl Into<String>) {
+        if let Some(mut replica) = self.replicas.get_mut(&key(namespace, name)) {
+            replica.info.status = ReplicaStatus::Failed;
+            replica.info.reason = Some(reason.into());
+        }
+    }
+
+    /// Stop reporting a pipeline's replica
+    pub fn remove(&self, namespace: &str, name: &str) {
+        self.replicas.remove(&key(namespace, name));
+    }
+
+    /// Every replica as heartbeats report it, by namespace and name
+    pub fn observe(&self, is_running: impl Fn(&str) -> bool) -> Vec<NodePipelineInfo> {
+        let mut replicas: Vec<NodePipelineInfo> = self
+            .replicas
+            .iter()
+            .map(|r| observed(&r.info, &r.runners, &is_running))
+            .collect();
+        replicas.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+        replicas
+    }
+}
+
+fn key(namespace: &str, name: &str) -> String {
+    format!("{}/{}", namespace, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replica_lifecycle() {
+        let reports = ReplicaReports::new();
+        reports.starting("default", "chat", 8081);
+        assert_eq!(reports.observe(|_| true)[0].status, ReplicaStatus::Starting);
+
+        reports.failed("default", "chat", "runner failed to start");
+        let failed = &reports.observe(|_| true)[0];
+        assert_eq!(failed.status, ReplicaStatus::Failed);
+        assert_eq!(failed.reason.as_deref(), Some("runner failed to start"));
+
+        // Assigned again: a restart
+        reports.starting("default", "chat", 8081);
+        reports.running("default", "chat", vec!["llama".to_string()]);
+        let running = &reports.observe(|_| true)[0];
+        assert_eq!(running.status, ReplicaStatus::Running);
+        assert_eq!(running.reason, None);
+        assert_eq!(running.restarts, 1);
+
+        reports.remove("default", "chat");
+        assert!(reports.observe(|_| true).is_empty());
+    }
+
+    #[test]
+    fn test_stopped_runner_is_unhealthy() {
+        let reports = ReplicaReports::new();
+        reports.starting("default", "chat", 8081);
+        reports.running("default", "chat", vec!["llama".to_string()]);
+        let replica = &reports.observe(|runner| runner != "llama")[0];
+        assert_eq!(replica.status, ReplicaStatus::Unhealthy);
+        assert!(replica.reason.as_ref().unwrap().contains("llama"));
+
+        // Only Running replicas are checked
+        reports.failed("default", "chat", "gone");
+        assert_eq!(
+            reports.observe(|_| false)[0].reason.as_deref(),
+            Some("gone")
+        );
+    }
+}
@@ -1,9 +1,11 @@
 //! Shared resource types for LLMNet cluster management
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// A namespace for organizing pipelines
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Namespace {
     /// API version
     #[serde(rename = "apiVersion")]
@@ -17,7 +19,7 @@ pub struct Namespace {
 }
 
 /// Namespace metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct NamespaceMetadata {
     /// Namespace name
     pub name: String,
@@ -41,7 +43,7 @@ impl Default for Namespace {
 }
 
 /// Response for listing resources
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ResourceList<T> {
     /// API version
     #[serde(rename = "apiVersion")]
@@ -93,7 +95,7 @@ impl LabelSelector {
 }
 
 /// Status of an operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct OperationStatus {
     /// Success or failure
     pub success: bool,
@@ -132,6 +134,73 @@ impl OperationStatus {
     }
 }
 
+/// Something the control plane did on its own, e.g. cordoning a node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ClusterEvent {
+    pub timestamp: DateTime<Utc>,
+
+    /// Resource the event is about (e.g. "node/worker-1")
+    pub object: String,
+
+    /// Short machine-readable cause (e.g. "MaintenanceCordoned")
+    pub reason: String,
+
+    pub message: String,
+}
+
+impl ClusterEvent {
+    /// Create an event stamped with the current time
+    pub fn new(
+        object: impl Into<String>,
+        reason: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            object: object.into(),
+            reason: reason.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Metadata map a label or annotate patch changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataField {
+    Labels,
+    Annotations,
+}
+
+impl MetadataField {
+    /// Name of the field, as in manifests and API paths
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetadataField::Labels => "labels",
+            MetadataField::Annotations => "annotations",
+        }
+    }
+}
+
+/// Changes to labels or annotations: a value sets the key, null removes it
+pub type MetadataPatch = std::collections::BTreeMap<String, Option<String>>;
+
+/// Apply a metadata patch to a resource's labels or annotations
+pub fn apply_metadata_patch(
+    map: &mut std::collections::HashMap<String, String>,
+    patch: &MetadataPatch,
+) {
+    for (key, value) in patch {
+        match value {
+            Some(value) => {
+                map.insert(key.clone(), value.clone());
+            }
+            None => {
+                map.remove(key);
+            }
+        }
+    }
+}
+
 /// Watch event for resource changes (for future streaming updates)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WatchEvent<T> {
@@ -0,0 +1,349 @@
+//! Canary and blue/green rollouts
+//!
+//! Applying a changed composition to a running pipeline whose strategy is
+//! `Canary` or `BlueGreen` starts a rollout: the new spec becomes the
+//! pipeline's spec, the previous one is kept in `status.rollout.stableSpec`,
+//! and the orchestrator runs the new spec as a second replica set
+//! (`<name>-canary`). The control plane proxy sends `weight` percent of
+//! requests to it and counts errors; once enough requests have been seen the
+//! rollout is promoted or rolled back.
+//!
+//! SBIO pattern: pure functions; the orchestrator and proxy do the I/O.
+
+use super::pipeline::{
+    CanaryParams, Pipeline, PipelineCondition, PipelineStatus, RolloutKind, RolloutPhase,
+    RolloutStatus,
+};
+
+/// What to do with a rollout in progress
+#[derive(Debug, Clone, PartialEq)]
+pub enum RolloutDecision {
+    /// Keep collecting traffic
+    Wait,
+    /// Replace the stable replicas with the new spec
+    Promote(String),
+    /// Drop the canary and restore the stable spec
+    RollBack(String),
+}
+
+/// Apply an updated manifest on top of the live pipeline
+///
+/// A running pipeline with a canary or blue/green strategy whose composition
+/// changed keeps serving and starts a rollout. An unchanged composition keeps
+/// the live status. Anything else is redeployed from scratch.
+pub fn apply_update(live: &Pipeline, mut update: Pipeline) -> Pipeline {
+    update.metadata.uid = live.metadata.uid;
+    update.metadata.creation_timestamp = live.metadata.creation_timestamp;
+
+    let running = live
+        .status
+        .as_ref()
+        .is_some_and(|s| !s.endpoints.is_empty());
+    let changed = live.composition_hash() != update.composition_hash();
+
+    update.status = match &live.status {
+        Some(status) if running && changed && update.spec.strategy.is_progressive() => {
+            let mut status = status.clone();
+            // Replacing a rollout in flight: the stable spec is still the one serving
+            let stable = match &status.rollout {
+                Some(r) if r.is_progressing() => r
+                    .stable_spec
+                    .as_deref()
+                    .cloned()
+                    .unwrap_or_else(|| live.spec.clone()),
+                _ => live.spec.clone(),
+            };
+            let kind = update.spec.strategy.kind();
+            status.rollout = Some(RolloutStatus::start(stable, &update.spec.strategy));
+            status.add_condition(PipelineCondition::new(
+                "Progressing",
+                "True",
+                "RolloutStarted",
+                format!("{:?} rollout of the new composition started", kind),
+            ));
+            Some(status)
+        }
+        Some(status) if running && !changed => Some(status.clone()),
+        _ => None,
+    };
+
+    update
+}
+
+/// Whether the `n`th proxied request should go to the canary
+///
+/// Spreads canary requests evenly, e.g. every tenth request at weight 10,
+/// rather than in bursts.
+pub fn routes_to_canary(n: u64, weight: u32) -> bool {
+    let weight = weight.min(100) as u64;
+    (n + 1) * weight / 100 != n * weight / 100
+}
+
+/// Decide what to do with a rollout in progress
+///
+/// `canary_ready` says whether every canary replica has passed its
+/// readiness probe. Blue/green switches over as soon as that's the case;
+/// a canary has to serve `minRequests` requests within `maxErrorRate`, and
+/// is rolled back early once it has failed more requests than that would
+/// allow.
+pub fn rollout_decision(
+    rollout: &RolloutStatus,
+    kind: RolloutKind,
+    params: &CanaryParams,
+    canary_ready: bool,
+) -> RolloutDecision {
+    if !canary_ready {
+        return RolloutDecision::Wait;
+    }
+
+    if kind == RolloutKind::BlueGreen {
+        return RolloutDecision::Promote("Canary replicas are ready".to_string());
+    }
+
+    let canary = &rollout.canary;
+    let allowed_errors = params.max_error_rate * params.min_requests as f64;
+    if canary.requests < params.min_requests {
+        if canary.errors as f64 > allowed_errors {
+            return RolloutDecision::RollBack(format!(
+                "Canary failed {} of {} requests",
+                canary.errors, canary.requests
+            ));
+        }
+        return RolloutDecision::Wait;
+    }
+
+    let error_rate = canary.error_rate();
+    if error_rate > params.max_error_rate {
+        RolloutDecision::RollBack(format!(
+            "Canary error rate {:.1}% exceeds {:.1}%",
+            error_rate * 100.0,
+            params.max_error_rate * 100.0
+        ))
+    } else {
+        RolloutDecision::Promote(format!(
+            "Canary error rate {:.1}% over {} requests",
+            error_rate * 100.0,
+            canary.requests
+        ))
+    }
+}
+
+/// Finish a rollout by promoting the new spec
+///
+/// The stable replica set has to be redeployed with the new spec; until the
+/// orchestrator does that, traffic goes to the canary replicas.
+pub fn promote(status: &mut PipelineStatus, message: String) {
+    let Some(rollout) = status.rollout.as_mut() else {
+        return;
+    };
+
+    status.endpoints = std::mem::take(&mut rollout.canary_endpoints);
+    rollout.phase = RolloutPhase::Promoted;
+    rollout.stable_spec = None;
+    rollout.weight = 0;
+    rollout.message = message.clone();
+
+    // Zero replicas makes the orchestrator schedule the new spec
+    status.replicas = 0;
+    status.ready_replicas = 0;
+    status.add_condition(PipelineCondition::new(
+        "Progressing",
+        "True",
+        "RolloutPromoted",
+        message,
+    ));
+}
+
+/// Finish a rollout by restoring the stable spec
+pub fn roll_back(pipeline: &mut Pipeline, message: String) {
+    let Some(status) = pipeline.status.as_mut() else {
+        return;
+    };
+    let Some(rollout) = status.rollout.as_mut() else {
+        return;
+    };
+
+    if let Some(stable) = rollout.stable_spec.take() {
+        pipeline.spec = *stable;
+    }
+    rollout.phase = RolloutPhase::RolledBack;
+    rollout.canary_endpoints.clear();
+    rollout.weight = 0;
+    rollout.message = message.clone();
+    status.add_condition(PipelineCondition::new(
+        "Progressing",
+        "False",
+        "RolloutRolledBack",
+        message,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::pipeline::{RolloutStrategy, TrafficStats};
+    use crate::config::Composition;
+
+    fn composition(model: &str) -> Composition {
+        Composition::from_str(&format!(
+            r#"{{
+                "models": {{"{model}": {{"type": "external", "interface": "openai-api", "url": "http://a"}}}},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "{model}", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    fn canary_strategy() -> RolloutStrategy {
+        RolloutStrategy {
+            strategy_type: "Canary".to_string(),
+            canary: Some(CanaryParams {
+                weight: 20,
+                max_error_rate: 0.1,
+                min_requests: 10,
+                port: None,
+            }),
+            ..RolloutStrategy::default()
+        }
+    }
+
+    fn running(mut pipeline: Pipeline) -> Pipeline {
+        let mut status = PipelineStatus::initial();
+        status.replicas = 1;
+        status.endpoints = vec!["http://worker:8080".to_string()];
+        pipeline.status = Some(status);
+        pipeline
+    }
+
+    #[test]
+    fn test_apply_update_starts_rollout() {
+        let mut live = Pipeline::new("bot", composition("v1"));
+        live.spec.strategy = canary_strategy();
+        let live = running(live);
+
+        let mut update = Pipeline::new("bot", composition("v2"));
+        update.spec.strategy = canary_strategy();
+        let applied = apply_update(&live, update);
+
+        assert_eq!(applied.metadata.uid, live.metadata.uid);
+        let status = applied.status.unwrap();
+        assert_eq!(status.endpoints, vec!["http://worker:8080"]);
+        let rollout = status.rollout.unwrap();
+        assert_eq!(rollout.phase, RolloutPhase::Progressing);
+        assert_eq!(rollout.weight, 20);
+        assert!(rollout
+            .stable_spec
+            .unwrap()
+            .composition
+            .models
+            .contains_key("v1"));
+    }
+
+    #[test]
+    fn test_apply_update_without_progressive_strategy_redeploys() {
+        let live = running(Pipeline::new("bot", composition("v1")));
+
+        let applied = apply_update(&live, Pipeline::new("bot", composition("v2")));
+        assert!(applied.status.is_none());
+
+        // Same composition: nothing to redeploy
+        let applied = apply_update(&live, Pipeline::new("bot", composition("v1")));
+        assert_eq!(applied.status.unwrap().replicas, 1);
+    }
+
+    #[test]
+    fn test_routes_to_canary_spreads_weight() {
+        let canary = (0..100).filter(|n| routes_to_canary(*n, 10)).count();
+        assert_eq!(canary, 10);
+        assert!(!routes_to_canary(0, 0));
+        assert!((0..10).all(|n| routes_to_canary(n, 100)));
+
+        // No two canary requests in a row at 10%
+        let hits: Vec<u64> = (0..30).filter(|n| routes_to_canary(*n, 10)).collect();
+        assert!(hits.windows(2).all(|w| w[1] - w[0] == 10));
+    }
+
+    #[test]
+    fn test_rollout_decision() {
+        let strategy = canary_strategy();
+        let params = strategy.canary_params();
+        let spec = Pipeline::new("bot", composition("v1")).spec;
+        let mut rollout = RolloutStatus::start(spec, &strategy);
+
+        assert_eq!(
+            rollout_decision(&rollout, RolloutKind::Canary, &params, false),
+            RolloutDecision::Wait
+        );
+        assert_eq!(
+            rollout_decision(&rollout, RolloutKind::Canary, &params, true),
+            RolloutDecision::Wait
+        );
+        assert!(matches!(
+            rollout_decision(&rollout, RolloutKind::BlueGreen, &params, true),
+            RolloutDecision::Promote(_)
+        ));
+
+        rollout.canary = TrafficStats {
+            requests: 10,
+            errors: 1,
+        };
+        assert!(matches!(
+            rollout_decision(&rollout, RolloutKind::Canary, &params, true),
+            RolloutDecision::Promote(_)
+        ));
+
+        rollout.canary = TrafficStats {
+            requests: 20,
+            errors: 3,
+        };
+        assert!(matches!(
+            rollout_decision(&rollout, RolloutKind::Canary, &params, true),
+            RolloutDecision::RollBack(_)
+        ));
+
+        // Too many failures to ever meet the threshold: give up early
+        rollout.canary = TrafficStats {
+            requests: 3,
+            errors: 2,
+        };
+        assert!(matches!(
+            rollout_decision(&rollout, RolloutKind::Canary, &params, true),
+            RolloutDecision::RollBack(_)
+        ));
+    }
+
+    #[test]
+    fn test_promote_and_roll_back() {
+        let mut live = Pipeline::new("bot", composition("v1"));
+        live.spec.strategy = canary_strategy();
+        let live = running(live);
+        let mut update = Pipeline::new("bot", composition("v2"));
+        update.spec.strategy = canary_strategy();
+        let mut pipeline = apply_update(&live, update);
+        pipeline
+            .status
+            .as_mut()
+            .unwrap()
+            .rollout
+            .as_mut()
+            .unwrap()
+            .canary_endpoints = vec!["http://worker:8081".to_string()];
+
+        let mut promoted = pipeline.status.clone().unwrap();
+        promote(&mut promoted, "ok".to_string());
+        assert_eq!(promoted.endpoints, vec!["http://worker:8081"]);
+        assert_eq!(promoted.replicas, 0);
+        let rollout = promoted.rollout.unwrap();
+        assert_eq!(rollout.phase, RolloutPhase::Promoted);
+        assert!(rollout.stable_spec.is_none());
+
+        roll_back(&mut pipeline, "errors".to_string());
+        assert!(pipeline.spec.composition.models.contains_key("v1"));
+        let status = pipeline.status.unwrap();
+        assert_eq!(status.endpoints, vec!["http://worker:8080"]);
+        assert_eq!(status.rollout.unwrap().phase, RolloutPhase::RolledBack);
+    }
+}
@@ -5,14 +5,18 @@
 //! resources and thus more suitable for scheduling new pipeline replicas.
 
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use super::node::{NodeMetrics, NodeScore, ScoreBreakdown};
 
 /// Weight configuration for node scoring
 ///
 /// Each weight determines how much that resource contributes to the
-/// overall node score. Weights should sum to approximately 1.0.
-#[derive(Debug, Clone)]
+/// overall node score. Weights should sum to approximately 1.0; weights
+/// left out of a serialized config take their default values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
 pub struct ScoringWeights {
     /// Weight for CPU availability (default: 0.20)
     pub cpu: f64,
@@ -24,8 +28,13 @@ pub struct ScoringWeights {
     pub disk: f64,
     /// Weight for request load (default: 0.15)
     pub load: f64,
+    /// Weight for average request latency (default: 0.0)
+    pub latency: f64,
 }
 
+/// Names accepted by [`ScoringWeights::preset`]
+pub const SCORING_PRESETS: [&str; 4] = ["default", "gpu-heavy", "cpu-heavy", "latency"];
+
 impl Default for ScoringWeights {
     fn default() -> Self {
         Self {
@@ -34,6 +43,7 @@ impl Default for ScoringWeights {
             gpu: 0.30,
             disk: 0.10,
             load: 0.15,
+            latency: 0.0,
         }
     }
 }
@@ -47,6 +57,24 @@ impl ScoringWeights {
             gpu,
             disk,
             load,
+            latency: 0.0,
+        }
+    }
+
+    /// Set the weight for average request latency
+    pub fn with_latency(mut self, latency: f64) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Look up a named preset (see [`SCORING_PRESETS`])
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "gpu-heavy" => Some(Self::gpu_heavy()),
+            "cpu-heavy" => Some(Self::cpu_heavy()),
+            "latency" => Some(Self::latency_sensitive()),
+            _ => None,
         }
     }
 
@@ -58,6 +86,7 @@ impl ScoringWeights {
             gpu: 0.50,
             disk: 0.05,
             load: 0.20,
+            latency: 0.0,
         }
     }
 
@@ -69,7 +98,43 @@ impl ScoringWeights {
             gpu: 0.10,
             disk: 0.10,
             load: 0.15,
+            latency: 0.0,
+        }
+    }
+
+    /// Create weights that favor nodes answering requests quickly
+    pub fn latency_sensitive() -> Self {
+        Self {
+            cpu: 0.15,
+            memory: 0.15,
+            gpu: 0.20,
+            disk: 0.05,
+            load: 0.20,
+            latency: 0.25,
+        }
+    }
+
+    /// Check that every weight is a non-negative number and that at least
+    /// one of them counts
+    pub fn validate(&self) -> Result<(), String> {
+        let weights = [
+            ("cpu", self.cpu),
+            ("memory", self.memory),
+            ("gpu", self.gpu),
+            ("disk", self.disk),
+            ("load", self.load),
+            ("latency", self.latency),
+        ];
+        if let Some((name, value)) = weights.iter().find(|(_, w)| !w.is_finite() || *w < 0.0) {
+            return Err(format!(
+                "Scoring weight '{}' must be a non-negative number, got {}",
+                name, value
+            ));
         }
+        if weights.iter().all(|(_, w)| *w == 0.0) {
+            return Err("At least one scoring weight must be greater than zero".to_string());
+        }
+        Ok(())
     }
 
     /// Redistribute GPU weight when node has no GPU
@@ -81,6 +146,7 @@ impl ScoringWeights {
             gpu: 0.0,
             disk: self.disk + redistribution,
             load: self.load + redistribution,
+            latency: self.latency,
         }
     }
 }
@@ -117,6 +183,9 @@ pub fn calculate_node_score(
     // With 0 requests: 100, with 10 requests: ~50, with 100 requests: ~9
     let load_score = 100.0 / (1.0 + metrics.active_requests as f64 * 0.1);
 
+    // Latency score: 100 at 0ms, 50 at 100ms, ~9 at 1s
+    let latency_score = 100.0 / (1.0 + metrics.avg_latency_ms.max(0.0) / 100.0);
+
     // Handle GPU scoring
     let (gpu_score, adjusted_weights) = if has_gpu {
         let gpu_score = metrics
@@ -133,7 +202,8 @@ pub fn calculate_node_score(
     let mut total = cpu_score * adjusted_weights.cpu
         + memory_score * adjusted_weights.memory
         + disk_score * adjusted_weights.disk
-        + load_score * adjusted_weights.load;
+        + load_score * adjusted_weights.load
+        + latency_score * adjusted_weights.latency;
 
     if let Some(gs) = gpu_score {
         total += gs * adjusted_weights.gpu;
@@ -147,6 +217,7 @@ pub fn calculate_node_score(
             gpu_score,
             disk_score,
             load_score,
+            latency_score,
         },
         calculated_at: Utc::now(),
     }
@@ -171,6 +242,7 @@ mod tests {
             memory_usage_percent: memory,
             gpu_usage_percent: None,
             gpu_memory_usage_percent: None,
+            gpus: Vec::new(),
             disk_usage_percent: disk,
             request_count: 0,
             avg_latency_ms: 0.0,
@@ -259,6 +331,54 @@ mod tests {
         assert!(score_cpu.score > 0.0);
     }
 
+    #[test]
+    fn test_latency_weight() {
+        let mut fast = make_metrics(50.0, 50.0, 50.0, 5);
+        fast.avg_latency_ms = 20.0;
+        let mut slow = fast.clone();
+        slow.avg_latency_ms = 2000.0;
+
+        // Latency doesn't count by default
+        assert_eq!(
+            calculate_node_score(&fast, false, None).score,
+            calculate_node_score(&slow, false, None).score
+        );
+
+        let weights = ScoringWeights::latency_sensitive();
+        let fast_score = calculate_node_score(&fast, false, Some(&weights));
+        let slow_score = calculate_node_score(&slow, false, Some(&weights));
+        assert!(fast_score.score > slow_score.score + 10.0);
+        assert_eq!(slow_score.breakdown.latency_score, 100.0 / 21.0);
+    }
+
+    #[test]
+    fn test_presets_and_validation() {
+        for name in SCORING_PRESETS {
+            let weights = ScoringWeights::preset(name).unwrap();
+            assert!(weights.validate().is_ok(), "{}", name);
+        }
+        assert!(ScoringWeights::preset("fastest").is_none());
+
+        assert!(ScoringWeights::default()
+            .with_latency(-0.1)
+            .validate()
+            .is_err());
+        assert!(ScoringWeights::new(0.0, 0.0, 0.0, 0.0, 0.0)
+            .validate()
+            .is_err());
+        assert!(ScoringWeights::new(f64::NAN, 0.2, 0.2, 0.2, 0.2)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_weights_deserialize_with_defaults() {
+        let weights: ScoringWeights = serde_json::from_str(r#"{"gpu": 0.6}"#).unwrap();
+        assert_eq!(weights.gpu, 0.6);
+        assert_eq!(weights.cpu, ScoringWeights::default().cpu);
+        assert_eq!(weights.latency, 0.0);
+    }
+
     #[test]
     fn test_breakdown_components() {
         let metrics = make_metrics(30.0, 40.0, 50.0, 10);
@@ -279,6 +399,7 @@ mod tests {
             memory_usage_percent: -10.0, // Invalid but should be handled
             gpu_usage_percent: None,
             gpu_memory_usage_percent: None,
+            gpus: Vec::new(),
             disk_usage_percent: 50.0,
             request_count: 0,
             avg_latency_ms: 0.0,
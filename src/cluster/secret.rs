@@ -0,0 +1,284 @@
+//! Secret resource - named values kept encrypted on the control plane
+//!
+//! A Secret holds key/value pairs such as API keys, created with
+//! `llmnet create secret generic` or a manifest:
+//!
+//! ```yaml
+//! apiVersion: llmnet/v1
+//! kind: Secret
+//! metadata:
+//!   name: openai
+//! stringData:
+//!   key: sk-...
+//! ```
+//!
+//! `stringData` is write-only: the control plane seals each value with
+//! AES-256-GCM under its master key and keeps only the sealed form in
+//! `data`. Pipelines name the secret in `spec.secretRefs`, and workers fetch
+//! the plaintext values when they spawn the pipeline's runners, presenting
+//! the grant that came with the pipeline's assignment.
+
+use std::collections::{BTreeMap, HashMap};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::pipeline::PipelineMetadata;
+
+/// Length of a master key in bytes
+pub const MASTER_KEY_LEN: usize = 32;
+
+/// Request header carrying an assignment's secret grant
+pub const SECRET_GRANT_HEADER: &str = "x-llmnet-secret-grant";
+
+/// Length of the nonce in front of each sealed value
+const NONCE_LEN: usize = 12;
+
+/// Errors sealing or opening secret values
+#[derive(Error, Debug, PartialEq)]
+pub enum SecretStoreError {
+    #[error("Invalid master key: {0}")]
+    InvalidKey(String),
+
+    #[error("Sealed value is malformed")]
+    Malformed,
+
+    #[error("Sealed value doesn't decrypt with this master key")]
+    Decrypt,
+}
+
+/// A set of named values stored encrypted
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Secret {
+    /// API version (e.g., "llmnet/v1")
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+
+    /// Kind is always "Secret"
+    pub kind: String,
+
+    /// Name, namespace and labels of the secret
+    pub metadata: PipelineMetadata,
+
+    /// Kind of secret; only "Opaque" (arbitrary key/value pairs) for now
+    #[serde(rename = "type", default = "default_secret_type")]
+    pub secret_type: String,
+
+    /// Sealed values by key (set by the control plane)
+    #[serde(default)]
+    pub data: BTreeMap<String, String>,
+
+    /// Plaintext values by key; sealed into `data` on creation and never
+    /// returned
+    #[serde(rename = "stringData")]
+    #[serde(default, skip_serializing)]
+    pub string_data: BTreeMap<String, String>,
+}
+
+fn default_secret_type() -> String {
+    "Opaque".to_string()
+}
+
+/// Key the control plane seals secret values with
+#[derive(Clone)]
+pub struct MasterKey([u8; MASTER_KEY_LEN]);
+
+impl std::fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MasterKey(..)")
+    }
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl Secret {
+    /// Create a secret from plaintext values
+    pub fn new(name: impl Into<String>, values: BTreeMap<String, String>) -> Self {
+        Self {
+            api_version: "llmnet/v1".to_string(),
+            kind: "Secret".to_string(),
+            metadata: PipelineMetadata {
+                name: name.into(),
+                namespace: "default".to_string(),
+                uid: Uuid::new_v4(),
+                labels: HashMap::new(),
+                annotations: HashMap::new(),
+                creation_timestamp: Some(Utc::now()),
+                deletion_timestamp: None,
+            },
+            secret_type: default_secret_type(),
+            data: BTreeMap::new(),
+            string_data: values,
+        }
+    }
+
+    /// Set the namespace
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.metadata.namespace = namespace.into();
+        self
+    }
+
+    /// Get the full qualified name (namespace/name)
+    pub fn qualified_name(&self) -> String {
+        format!("{}/{}", self.metadata.namespace, self.metadata.name)
+    }
+
+    /// Keys the secret has values for
+    pub fn keys(&self) -> Vec<&str> {
+        self.data
+            .keys()
+            .chain(self.string_data.keys())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Split a `key=value` literal; the value may itself contain `=`
+pub fn parse_literal(literal: &str) -> Result<(String, String), String> {
+    match literal.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", literal)),
+    }
+}
+
+impl MasterKey {
+    /// Parse a base64-encoded 32-byte key, e.g. from `openssl rand -base64 32`
+    pub fn from_base64(encoded: &str) -> Result<Self, SecretStoreError> {
+        let bytes = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| SecretStoreError::InvalidKey(e.to_string()))?;
+        let key: [u8; MASTER_KEY_LEN] = bytes.try_into().map_err(|b: Vec<u8>| {
+            SecretStoreError::InvalidKey(format!(
+                "expected {} bytes, got {}",
+                MASTER_KEY_LEN,
+                b.len()
+            ))
+        })?;
+        Ok(Self(key))
+    }
+
+    /// The key base64-encoded, as read by [`MasterKey::from_base64`]
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0)
+    }
+
+    /// Decrypt a value sealed with [`MasterKey::seal`]
+    pub fn open(&self, sealed: &str) -> Result<String, SecretStoreError> {
+        let bytes = BASE64
+            .decode(sealed)
+            .map_err(|_| SecretStoreError::Malformed)?;
+        if bytes.len() < NONCE_LEN {
+            return Err(SecretStoreError::Malformed);
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SecretStoreError::Decrypt)?;
+        String::from_utf8(plaintext).map_err(|_| SecretStoreError::Malformed)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.0))
+    }
+}
+
+// ============================================================================
+// I/O boundary (randomness)
+// ============================================================================
+
+impl MasterKey {
+    /// A new random key
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(OsRng).into())
+    }
+
+    /// Encrypt a value under a fresh random nonce; the result is the
+    /// base64 of the nonce followed by the ciphertext
+    pub fn seal(&self, plaintext: &str) -> String {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, plaintext.as_bytes())
+            .expect("AES-GCM encryption of an in-memory value cannot fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        BASE64.encode(sealed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = MasterKey::generate();
+        let sealed = key.seal("sk-123");
+        assert!(!sealed.contains("sk-123"));
+        assert_ne!(sealed, key.seal("sk-123"));
+        assert_eq!(key.open(&sealed), Ok("sk-123".to_string()));
+
+        assert_eq!(
+            MasterKey::generate().open(&sealed),
+            Err(SecretStoreError::Decrypt)
+        );
+        assert_eq!(key.open("not base64!"), Err(SecretStoreError::Malformed));
+        assert_eq!(key.open("c2hvcnQ="), Err(SecretStoreError::Malformed));
+    }
+
+    #[test]
+    fn test_master_key_from_base64() {
+        let key = MasterKey::generate();
+        let parsed = MasterKey::from_base64(&format!("{}\n", key.to_base64())).unwrap();
+        assert_eq!(parsed.open(&key.seal("value")), Ok("value".to_string()));
+
+        assert!(matches!(
+            MasterKey::from_base64("c2hvcnQ="),
+            Err(SecretStoreError::InvalidKey(_))
+        ));
+        assert!(format!("{:?}", key).ends_with("(..)"));
+    }
+
+    #[test]
+    fn test_parse_literal() {
+        assert_eq!(
+            parse_literal("key=sk-a=b"),
+            Ok(("key".to_string(), "sk-a=b".to_string()))
+        );
+        assert_eq!(
+            parse_literal("empty="),
+            Ok(("empty".to_string(), String::new()))
+        );
+        assert!(parse_literal("novalue").is_err());
+        assert!(parse_literal("=value").is_err());
+    }
+
+    #[test]
+    fn test_string_data_is_write_only() {
+        let values = BTreeMap::from([("key".to_string(), "sk-123".to_string())]);
+        let secret = Secret::new("openai", values).with_namespace("prod");
+        assert_eq!(secret.qualified_name(), "prod/openai");
+        assert_eq!(secret.keys(), ["key"]);
+
+        let json = serde_json::to_string(&secret).unwrap();
+        assert!(!json.contains("sk-123"));
+        assert!(json.contains("\"type\":\"Opaque\""));
+
+        let parsed: Secret = serde_json::from_str(
+            r#"{"apiVersion": "llmnet/v1", "kind": "Secret", "metadata": {"name": "openai"}, "stringData": {"key": "sk-123"}}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.string_data["key"], "sk-123");
+        assert_eq!(parsed.metadata.namespace, "default");
+    }
+}
@@ -0,0 +1,324 @@
+//! VirtualEndpoint resource - one route split across several pipelines
+//!
+//! A VirtualEndpoint gives clients a stable address whose traffic the
+//! control plane proxy spreads over backing pipelines by weight, e.g. an
+//! A/B test of two model versions:
+//!
+//! ```yaml
+//! apiVersion: llmnet/v1
+//! kind: VirtualEndpoint
+//! metadata:
+//!   name: chat
+//! spec:
+//!   backends:
+//!     - pipeline: chat-v1
+//!       weight: 90
+//!     - pipeline: chat-v2
+//!       weight: 10
+//!       activeFrom: "2026-11-01T00:00:00Z"
+//! ```
+//!
+//! A backend with `activeFrom`/`activeUntil` only takes traffic inside that
+//! window, and the weights of the backends active at the time are shared
+//! out between them. Requests, errors and latency are counted per backend.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::pipeline::PipelineMetadata;
+
+/// A route whose traffic is split between pipelines
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VirtualEndpoint {
+    /// API version (e.g., "llmnet/v1")
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+
+    /// Kind is always "VirtualEndpoint"
+    pub kind: String,
+
+    /// Name, namespace and labels of the endpoint
+    pub metadata: PipelineMetadata,
+
+    /// How to split the traffic
+    pub spec: VirtualEndpointSpec,
+
+    /// Traffic per backend (populated by controller)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<VirtualEndpointStatus>,
+}
+
+/// Specification of a VirtualEndpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VirtualEndpointSpec {
+    /// Pipelines in the endpoint's namespace that serve its traffic
+    pub backends: Vec<EndpointBackend>,
+}
+
+/// One pipeline behind a VirtualEndpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EndpointBackend {
+    /// Pipeline name
+    pub pipeline: String,
+
+    /// Share of the traffic, relative to the other active backends
+    pub weight: u32,
+
+    /// Take no traffic before this time
+    #[serde(rename = "activeFrom")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_from: Option<DateTime<Utc>>,
+
+    /// Take no traffic from this time on
+    #[serde(rename = "activeUntil")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_until: Option<DateTime<Utc>>,
+}
+
+/// Observed traffic of a VirtualEndpoint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VirtualEndpointStatus {
+    /// Traffic per backend pipeline
+    pub variants: BTreeMap<String, VariantStats>,
+}
+
+/// Requests one backend has served through the endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct VariantStats {
+    pub requests: u64,
+
+    /// Requests that got a 5xx, a 404 or no response
+    pub errors: u64,
+
+    /// Sum of the time to the response headers of every request
+    #[serde(rename = "totalLatencyMs")]
+    pub total_latency_ms: u64,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl VirtualEndpoint {
+    /// Create an endpoint over weighted backends
+    pub fn new(name: impl Into<String>, backends: Vec<EndpointBackend>) -> Self {
+        Self {
+            api_version: "llmnet/v1".to_string(),
+            kind: "VirtualEndpoint".to_string(),
+            metadata: PipelineMetadata {
+                name: name.into(),
+                namespace: "default".to_string(),
+                uid: Uuid::new_v4(),
+                labels: HashMap::new(),
+                annotations: HashMap::new(),
+                creation_timestamp: Some(Utc::now()),
+                deletion_timestamp: None,
+            },
+            spec: VirtualEndpointSpec { backends },
+            status: None,
+        }
+    }
+
+    /// Set the namespace
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.metadata.namespace = namespace.into();
+        self
+    }
+
+    /// Get the qualified name (namespace/name)
+    pub fn qualified_name(&self) -> String {
+        format!("{}/{}", self.metadata.namespace, self.metadata.name)
+    }
+}
+
+impl VirtualEndpointSpec {
+    /// Check the spec can route traffic
+    pub fn validate(&self) -> Result<(), String> {
+        if self.backends.is_empty() {
+            return Err("spec.backends needs at least one pipeline".to_string());
+        }
+        let mut seen = HashSet::new();
+        for backend in &self.backends {
+            if backend.pipeline.is_empty() {
+                return Err("spec.backends[].pipeline is required".to_string());
+            }
+            if !seen.insert(backend.pipeline.as_str()) {
+                return Err(format!(
+                    "Pipeline '{}' is listed more than once",
+                    backend.pipeline
+                ));
+            }
+            if let (Some(from), Some(until)) = (backend.active_from, backend.active_until) {
+                if from >= until {
+                    return Err(format!(
+                        "Backend '{}' has activeFrom after activeUntil",
+                        backend.pipeline
+                    ));
+                }
+            }
+        }
+        if self.backends.iter().all(|b| b.weight == 0) {
+            return Err("At least one backend needs a weight above 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl EndpointBackend {
+    /// Send `weight` shares of the traffic to a pipeline
+    pub fn new(pipeline: impl Into<String>, weight: u32) -> Self {
+        Self {
+            pipeline: pipeline.into(),
+            weight,
+            active_from: None,
+            active_until: None,
+        }
+    }
+
+    /// Only take traffic inside a time window
+    pub fn with_window(
+        mut self,
+        from: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.active_from = from;
+        self.active_until = until;
+        self
+    }
+
+    /// Whether the backend takes traffic at `now`
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.weight > 0
+            && self.active_from.is_none_or(|from| now >= from)
+            && self.active_until.is_none_or(|until| now < until)
+    }
+}
+
+impl VariantStats {
+    /// Fraction of requests that failed (0.0 with no requests)
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+
+    /// Mean time to the response headers (0.0 with no requests)
+    pub fn mean_latency_ms(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.total_latency_ms as f64 / self.requests as f64
+        }
+    }
+}
+
+/// Backend to send the `n`th request to
+///
+/// Each backend active at `now` gets exactly its weight's share of every
+/// run of as many requests as the weights add up to. Requests step through
+/// the run by a stride near the golden ratio, so a 90/10 split sends one in
+/// ten requests to the second backend rather than ten in a row. None when
+/// no backend is active.
+pub fn pick_backend(
+    backends: &[EndpointBackend],
+    now: DateTime<Utc>,
+    n: u64,
+) -> Option<&EndpointBackend> {
+    let active: Vec<&EndpointBackend> = backends.iter().filter(|b| b.is_active(now)).collect();
+    let total: u64 = active.iter().map(|b| b.weight as u64).sum();
+    if total == 0 {
+        return None;
+    }
+
+    // A stride coprime with the total visits every slot once per run
+    let golden = (total as f64 * 0.618_033_988_75).round() as u64;
+    let stride = (golden..).find(|&s| gcd(s, total) == 1).unwrap_or(1);
+    let mut slot = ((n % total) as u128 * stride as u128 % total as u128) as u64;
+    active.into_iter().find(|backend| {
+        let weight = backend.weight as u64;
+        if slot < weight {
+            return true;
+        }
+        slot -= weight;
+        false
+    })
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_validate() {
+        let spec = |backends| VirtualEndpointSpec { backends };
+        assert!(spec(vec![
+            EndpointBackend::new("a", 90),
+            EndpointBackend::new("b", 10)
+        ])
+        .validate()
+        .is_ok());
+        assert!(spec(vec![]).validate().is_err());
+        assert!(spec(vec![
+            EndpointBackend::new("a", 1),
+            EndpointBackend::new("a", 1)
+        ])
+        .validate()
+        .is_err());
+        assert!(spec(vec![EndpointBackend::new("a", 0)]).validate().is_err());
+
+        let at = |day| Utc.with_ymd_and_hms(2026, 11, day, 0, 0, 0).unwrap();
+        let backwards = EndpointBackend::new("a", 1).with_window(Some(at(2)), Some(at(1)));
+        assert!(spec(vec![backwards]).validate().is_err());
+    }
+
+    #[test]
+    fn test_pick_backend_splits_by_weight() {
+        let backends = [EndpointBackend::new("a", 90), EndpointBackend::new("b", 10)];
+        let now = Utc::now();
+        let picks: Vec<&str> = (0..1000)
+            .map(|n| pick_backend(&backends, now, n).unwrap().pipeline.as_str())
+            .collect();
+        assert_eq!(picks.iter().filter(|p| **p == "b").count(), 100);
+        // Interleaved, not a run of ten
+        assert!(picks[..20].contains(&"b"));
+        assert!(!picks.windows(3).any(|w| w == ["b", "b", "b"]));
+    }
+
+    #[test]
+    fn test_pick_backend_honors_windows() {
+        let at = |day| Utc.with_ymd_and_hms(2026, 11, day, 0, 0, 0).unwrap();
+        let backends = [
+            EndpointBackend::new("old", 50).with_window(None, Some(at(10))),
+            EndpointBackend::new("new", 50).with_window(Some(at(5)), None),
+        ];
+
+        let picked = |day, n| {
+            pick_backend(&backends, at(day), n)
+                .unwrap()
+                .pipeline
+                .clone()
+        };
+        assert!((0..20).all(|n| picked(1, n) == "old"));
+        assert!((0..20).all(|n| picked(10, n) == "new"));
+        let during: HashSet<String> = (0..20).map(|n| picked(7, n)).collect();
+        assert_eq!(during.len(), 2);
+
+        let only_future = [EndpointBackend::new("new", 1).with_window(Some(at(5)), None)];
+        assert!(pick_backend(&only_future, at(1), 0).is_none());
+    }
+}
@@ -0,0 +1,628 @@
+//! Persistent assignment state for worker nodes
+//!
+//! A worker records the pipelines the control plane assigned to it, and the
+//! runner containers it started for them, in a local JSON file. After a
+//! restart it reconciles that file against the containers Docker still runs:
+//! surviving replicas are adopted instead of started again, leftovers are
+//! removed, and the recorded assignments are applied again so missing
+//! runners come back. The outcome is reported to the control plane as the
+//! node's `RunnersAdopted` condition.
+//!
+//! Runners that are plain processes (ollama, vLLM, llama.cpp) can't be
+//! re-attached; applying the assignment again starts them fresh.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use super::auth::control_plane_client;
+use super::gpus::pin_to_gpus;
+use super::node::{NodeCondition, NodeConditionType};
+use super::orchestrator::PipelineAssignment;
+use super::secret::SECRET_GRANT_HEADER;
+use crate::config::models::RunnerType;
+use crate::runtime::docker;
+use crate::runtime::{RunnerManager, RunnerRecord};
+
+/// Errors that can occur while reading or writing the worker state file
+#[derive(Error, Debug)]
+pub enum WorkerStateError {
+    #[error("Worker state I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid worker state file: {0}")]
+    Invalid(#[from] serde_json::Error),
+}
+
+/// Default location of the worker state file
+pub fn default_state_file() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".llmnet")
+        .join("worker-state.json")
+}
+
+/// Everything a worker needs to pick up where it left off
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkerState {
+    /// Pipelines assigned by the control plane
+    #[serde(default)]
+    pub assignments: Vec<PipelineAssignment>,
+
+    /// Runner containers started for them, in replica order per model
+    #[serde(default)]
+    pub runners: Vec<RunnerRecord>,
+}
+
+impl WorkerState {
+    /// Add an assignment, replacing an earlier one for the same pipeline
+    pub fn record_assignment(&mut self, assignment: PipelineAssignment) {
+        self.assignments
+            .retain(|a| a.namespace != assignment.namespace || a.name != assignment.name);
+        self.assignments.push(assignment);
+    }
+
+    /// Forget a pipeline's assignment
+    pub fn remove_assignment(&mut self, namespace: &str, name: &str) {
+        self.assignments
+            .retain(|a| a.namespace != namespace || a.name != name);
+    }
+
+    /// Models used by any recorded assignment
+    pub fn assigned_models(&self) -> HashSet<&str> {
+        self.assignments
+            .iter()
+            .flat_map(|a| a.composition.models.keys().map(String::as_str))
+            .collect()
+    }
+}
+
+// ============================================================================
+// SBIO: Pure reconciliation planning
+// ============================================================================
+
+/// What to do with the recorded containers after a restart
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReconcilePlan {
+    /// Records to track again, in replica order
+    pub adopt: Vec<RunnerRecord>,
+    /// Containers to force-remove
+    pub remove: Vec<String>,
+}
+
+/// Decide which recorded containers to adopt and which to remove
+///
+/// A container is adopted when it is still running and an assignment uses
+/// its model. Replica names follow their position, so once one replica of a
+/// model is gone the later ones are removed too and started again in order.
+pub fn plan_reconcile(state: &WorkerState, running: &HashSet<String>) -> ReconcilePlan {
+    let models = state.assigned_models();
+    let mut broken: HashSet<&str> = HashSet::new();
+    let mut plan = ReconcilePlan::default();
+
+    for record in &state.runners {
+        let model = record.model.as_str();
+        if models.contains(model) && !broken.contains(model) && running.contains(&record.container)
+        {
+            plan.adopt.push(record.clone());
+        } else {
+            broken.insert(model);
+            plan.remove.push(record.container.clone());
+        }
+    }
+
+    plan
+}
+
+/// Outcome of reconciling the state file at startup
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdoptionReport {
+    /// Containers tracked again
+    pub adopted: Vec<String>,
+    /// Orphaned containers removed
+    pub removed: Vec<String>,
+    /// Orphaned containers that could not be removed
+    pub failed: Vec<String>,
+}
+
+impl AdoptionReport {
+    /// The `RunnersAdopted` condition reported to the control plane
+    pub fn condition(&self) -> NodeCondition {
+        let mut message = format!(
+            "adopted {} container(s), removed {} orphan(s)",
+            self.adopted.len(),
+            self.removed.len()
+        );
+        if !self.failed.is_empty() {
+            message.push_str(&format!(", failed to remove {}", self.failed.join(", ")));
+        }
+
+        let (status, reason) = if self.failed.is_empty() {
+            (true, "Reconciled")
+        } else {
+            (false, "OrphansRemaining")
+        };
+        NodeCondition::new(NodeConditionType::RunnersAdopted, status, reason, &message)
+    }
+}
+
+// ============================================================================
+// I/O: State file
+// ============================================================================
+
+/// The worker state file and an in-memory copy of its contents
+pub struct WorkerStateStore {
+    path: PathBuf,
+    state: Mutex<WorkerState>,
+}
+
+impl WorkerStateStore {
+    /// Load the state file; a missing file means a fresh worker
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self, WorkerStateError> {
+        let path = path.into();
+        let state = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => WorkerState::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A copy of the current state
+    pub async fn state(&self) -> WorkerState {
+        self.state.lock().await.clone()
+    }
+
+    /// Record an assignment along with the runners now running
+    pub async fn record_assignment(
+        &self,
+        assignment: PipelineAssignment,
+        runners: Vec<RunnerRecord>,
+    ) -> Result<(), WorkerStateError> {
+        let mut state = self.state.lock().await;
+        state.record_assignment(assignment);
+        state.runners = runners;
+        self.save(&state).await
+    }
+
+    /// Forget an assignment and record the runners still running
+    pub async fn remove_assignment(
+        &self,
+        namespace: &str,
+        name: &str,
+        runners: Vec<RunnerRecord>,
+    ) -> Result<(), WorkerStateError> {
+        let mut state = self.state.lock().await;
+        state.remove_assignment(namespace, name);
+        state.runners = runners;
+        self.save(&state).await
+    }
+
+    /// Record the runners now running
+    pub async fn record_runners(&self, runners: Vec<RunnerRecord>) -> Result<(), WorkerStateError> {
+        let mut state = self.state.lock().await;
+        state.runners = runners;
+        self.save(&state).await
+    }
+
+    async fn save(&self, state: &WorkerState) -> Result<(), WorkerStateError> {
+        let contents = serde_json::to_string_pretty(state)?;
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Write aside and rename so a crash never leaves a truncated file
+        let tmp = self.path.with_extension("tmp");
+        // Only this user may read it: assignments carry resolved secrets
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(&tmp).await?;
+        file.write_all(contents.as_bytes()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+// ============================================================================
+// I/O: Runners
+// ============================================================================
+
+/// Start the runners an assignment needs, keeping replicas already running
+///
+/// Models of a split pipeline run on the worker hosting their handlers.
+///
+/// Cluster Secrets the assignment references are fetched from the control
+/// plane first, with `api_key` if it requires one, so their values never
+/// reach the worker state file.
+pub async fn spawn_assignment_runners(
+    manager: &RunnerManager,
+    assignment: &PipelineAssignment,
+    control_plane_url: Option<&str>,
+    api_key: Option<&str>,
+) -> Result<(), String> {
+    let mut env = assignment.env.clone();
+    env.extend(fetch_secret_env(assignment, control_plane_url, api_key).await?);
+
+    let embedding_models = assignment.composition.embedding_models();
+    for (model_name, model_def) in &assignment.composition.models {
+        // Peer workers run the models of the handlers placed on them
+        if !assignment.runs_model(model_name) {
+            continue;
+        }
+        let mut config = model_def.to_config();
+        if embedding_models.contains(&model_name.as_str()) {
+            config = config.for_embeddings();
+        }
+        config.env = env.clone();
+        if let Some(devices) = assignment.gpu_devices.get(model_name) {
+            pin_to_gpus(&mut config, devices);
+        }
+
+        let needs_runner = matches!(
+            config.runner,
+            RunnerType::Docker
+                | RunnerType::Ollama
+                | RunnerType::Vllm
+                | RunnerType::LlamaCpp
+                | RunnerType::Llamafile
+                | RunnerType::Tgi
+                | RunnerType::Whisper
+        );
+        if !needs_runner {
+            continue;
+        }
+
+        info!(
+            "Spawning {} runner for model '{}'...",
+            config.type_name(),
+            model_name
+        );
+
+        let (replicas, balancing) = assignment.composition.runner_replicas(model_name);
+        match manager
+            .spawn_replicas(model_name, &config, replicas, balancing)
+            .await
+        {
+            Ok(endpoints) => {
+                info!(
+                    "Runner for '{}' ready at {}",
+                    model_name,
+                    endpoints.join(", ")
+                );
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Failed to spawn runner for '{}': {}",
+                    model_name, e
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Names of the containers Docker is running
+async fn running_containers() -> HashSet<String> {
+    let args = docker::generate_ps_names_args();
+    match Command::new("docker").args(&args).output().await {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+        Ok(output) => {
+            warn!(
+                "Could not list Docker containers: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            HashSet::new()
+        }
+        Err(e) => {
+            warn!("Could not list Docker containers: {}", e);
+            HashSet::new()
+        }
+    }
+}
+
+/// Adopt surviving runner containers and remove orphaned ones
+///
+/// Call before any runner is spawned. The recorded assignments still need
+/// applying afterwards to bring back runners that didn't survive.
+pub async fn reconcile_runners(
+    manager: &RunnerManager,
+    store: &WorkerStateStore,
+) -> Result<AdoptionReport, WorkerStateError> {
+    let state = store.state().await;
+    let mut report = AdoptionReport::default();
+    if state.runners.is_empty() {
+        return Ok(report);
+    }
+
+    let plan = plan_reconcile(&state, &running_containers().await);
+
+    for record in &plan.adopt {
+        manager.adopt_container(record);
+        report.adopted.push(record.container.clone());
+    }
+
+    for container in plan.remove {
+        let args = docker::generate_rm_args(&container);
+        match Command::new("docker").args(&args).output().await {
+            Ok(output) if output.status.success() => {
+                info!("Removed orphaned runner container {}", container);
+                report.removed.push(container);
+            }
+            // Already gone, e.g. started with --rm
+            Ok(output) if String::from_utf8_lossy(&output.stderr).contains("No such container") => {
+            }
+            Ok(output) => {
+                warn!(
+                    "Failed to remove orphaned container {}: {}",
+                    container,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                report.failed.push(container);
+            }
+            Err(e) => {
+                warn!("Failed to remove orphaned container {}: {}", container, e);
+                report.failed.push(container);
+            }
+        }
+    }
+
+    store.record_runners(manager.runner_records()).await?;
+    Ok(report)
+}
+
+/// Environment variables an assignment takes from cluster Secrets
+async fn fetch_secret_env(
+    assignment: &PipelineAssignment,
+    control_plane_url: Option<&str>,
+    api_key: Option<&str>,
+) -> Result<BTreeMap<String, String>, String> {
+    if assignment.secret_refs.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let control_plane_url = control_plane_url.ok_or_else(|| {
+        format!(
+            "Pipeline {}/{} references cluster secrets but this worker has no --control-plane-url",
+            assignment.namespace, assignment.name
+        )
+    })?;
+
+    let client = control_plane_client(api_key);
+    let mut secrets: HashMap<&str, BTreeMap<String, String>> = HashMap::new();
+    let mut env = BTreeMap::new();
+    for secret_ref in &assignment.secret_refs {
+        let name = secret_ref.secret.as_str();
+        if !secrets.contains_key(name) {
+            let url = format!(
+                "{}/v1/namespaces/{}/secrets/{}/values",
+                control_plane_url.trim_end_matches('/'),
+                assignment.namespace,
+                name
+            );
+            let mut request = client.get(&url);
+            if let Some(grant) = &assignment.secret_grant {
+                request = request.header(SECRET_GRANT_HEADER, grant);
+            }
+            let values = request
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| format!("Failed to fetch secret '{}': {}", name, e))?
+                .json()
+                .await
+                .map_err(|e| format!("Failed to read secret '{}': {}", name, e))?;
+            secrets.insert(name, values);
+        }
+        let value = secrets[name]
+            .get(secret_ref.key())
+            .ok_or_else(|| format!("Secret '{}' has no {}", name, secret_ref.key()))?;
+        env.insert(secret_ref.name.clone(), value.clone());
+    }
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Composition;
+
+    fn assignment(name: &str, model: &str) -> PipelineAssignment {
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "{}": {{"type": "external", "interface": "openai-api", "url": "http://localhost:8080"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "adapter": "openai-api"}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#,
+            model
+        );
+        PipelineAssignment {
+            namespace: "default".to_string(),
+            name: name.to_string(),
+            composition: Composition::from_str(&json).unwrap(),
+            port: 8080,
+            replicas: 1,
+            env: Default::default(),
+            secret_refs: vec![],
+            secret_grant: None,
+            gpu_devices: Default::default(),
+            peers: Default::default(),
+            mesh_nodes: vec![],
+            mesh_key: None,
+        }
+    }
+
+    fn record(model: &str, container: &str, port: u16) -> RunnerRecord {
+        RunnerRecord {
+            model: model.to_string(),
+            container: container.to_string(),
+            endpoint: format!("http://127.0.0.1:{}/v1", port),
+            runner: RunnerType::Tgi,
+        }
+    }
+
+    #[test]
+    fn test_record_assignment_replaces_same_pipeline() {
+        let mut state = WorkerState::default();
+        state.record_assignment(assignment("chat", "llama"));
+        state.record_assignment(assignment("search", "mistral"));
+        state.record_assignment(assignment("chat", "qwen"));
+
+        assert_eq!(state.assignments.len(), 2);
+        let models = state.assigned_models();
+        assert!(models.contains("qwen") && models.contains("mistral"));
+        assert!(!models.contains("llama"));
+    }
+
+    #[test]
+    fn test_plan_reconcile() {
+        let mut state = WorkerState::default();
+        state.record_assignment(assignment("chat", "llama"));
+        state.record_assignment(assignment("search", "mistral"));
+        state.runners = vec![
+            record("llama", "llmnet-llama", 8080),
+            record("llama", "llmnet-llama-1", 8081),
+            // First replica died: the second must go too so names stay in order
+            record("mistral", "llmnet-mistral", 8082),
+            record("mistral", "llmnet-mistral-1", 8083),
+            // No assignment uses this model anymore
+            record("old", "llmnet-old", 8084),
+        ];
+        let running: HashSet<String> = [
+            "llmnet-llama",
+            "llmnet-llama-1",
+            "llmnet-mistral-1",
+            "llmnet-old",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        let plan = plan_reconcile(&state, &running);
+        let adopted: Vec<&str> = plan.adopt.iter().map(|r| r.container.as_str()).collect();
+        assert_eq!(adopted, ["llmnet-llama", "llmnet-llama-1"]);
+        assert_eq!(
+            plan.remove,
+            ["llmnet-mistral", "llmnet-mistral-1", "llmnet-old"]
+        );
+    }
+
+    #[test]
+    fn test_adoption_report_condition() {
+        let report = AdoptionReport {
+            adopted: vec!["llmnet-llama".to_string()],
+            removed: vec!["llmnet-old".to_string()],
+            failed: vec![],
+        };
+        let condition = report.condition();
+        assert_eq!(condition.condition_type, NodeConditionType::RunnersAdopted);
+        assert_eq!(condition.status, "True");
+        assert_eq!(
+            condition.message,
+            "adopted 1 container(s), removed 1 orphan(s)"
+        );
+
+        let report = AdoptionReport {
+            failed: vec!["llmnet-stuck".to_string()],
+            ..report
+        };
+        let condition = report.condition();
+        assert_eq!(condition.status, "False");
+        assert_eq!(condition.reason, "OrphansRemaining");
+        assert!(condition.message.ends_with("failed to remove llmnet-stuck"));
+    }
+
+    #[tokio::test]
+    async fn test_store_persists_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("llmnet-worker-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("worker-state.json");
+
+        let store = WorkerStateStore::open(&path).await.unwrap();
+        assert!(store.state().await.assignments.is_empty());
+        store
+            .record_assignment(
+                assignment("chat", "llama"),
+                vec![record("llama", "llmnet-llama", 8080)],
+            )
+            .await
+            .unwrap();
+
+        let reopened = WorkerStateStore::open(&path).await.unwrap();
+        let state = reopened.state().await;
+        assert_eq!(state.assignments.len(), 1);
+        assert_eq!(state.assignments[0].name, "chat");
+        assert_eq!(state.runners, vec![record("llama", "llmnet-llama", 8080)]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        reopened.record_runners(vec![]).await.unwrap();
+        let state = WorkerStateStore::open(&path).await.unwrap().state().await;
+        assert!(state.runners.is_empty());
+        assert_eq!(state.assignments.len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_secret_env() {
+        use super::super::pipeline::SecretEnvRef;
+        use axum::{routing::get, Json, Router};
+
+        let app = Router::new().route(
+            "/v1/namespaces/default/secrets/openai/values",
+            get(|headers: axum::http::HeaderMap| async move {
+                assert_eq!(headers[SECRET_GRANT_HEADER], "grant");
+                Json(serde_json::json!({"key": "sk-123"}))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut bot = assignment("bot", "chat");
+        assert!(fetch_secret_env(&bot, None, None).await.unwrap().is_empty());
+
+        bot.secret_refs = vec![SecretEnvRef {
+            name: "OPENAI_API_KEY".to_string(),
+            secret: "openai".to_string(),
+            key: Some("key".to_string()),
+        }];
+        bot.secret_grant = Some("grant".to_string());
+        let env = fetch_secret_env(&bot, Some(&url), None).await.unwrap();
+        assert_eq!(env["OPENAI_API_KEY"], "sk-123");
+        assert!(fetch_secret_env(&bot, None, None).await.is_err());
+
+        bot.secret_refs[0].key = Some("org".to_string());
+        let err = fetch_secret_env(&bot, Some(&url), None).await.unwrap_err();
+        assert_eq!(err, "Secret 'openai' has no org");
+    }
+}
@@ -14,6 +14,9 @@ pub enum HookMode {
     Observe,
     /// Result can modify pipeline input/output
     Transform,
+    /// Post-hook that rewrites or suppresses each chunk of a streamed
+    /// answer; answers that aren't streamed are one chunk
+    StreamTransform,
 }
 
 /// Action to take when a hook fails
@@ -44,6 +47,21 @@ pub struct HookConfig {
     /// Optional condition for hook execution (uses same syntax as node conditions)
     #[serde(rename = "if", skip_serializing_if = "Option::is_none")]
     pub condition: Option<String>,
+
+    /// Milliseconds the call may take before the hook fails, whatever the
+    /// function's own timeout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Time a hook may take when it doesn't set `timeout_ms`
+pub const DEFAULT_HOOK_TIMEOUT_MS: u64 = 30_000;
+
+impl HookConfig {
+    /// Milliseconds the hook may take
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms.unwrap_or(DEFAULT_HOOK_TIMEOUT_MS)
+    }
 }
 
 /// Pre and post hooks for an architecture node
@@ -58,10 +76,304 @@ pub struct NodeHooks {
     pub post: Vec<HookConfig>,
 }
 
+impl NodeHooks {
+    /// Whether a post-hook needs the node's whole output, so it can't be
+    /// streamed
+    pub fn needs_whole_output(&self) -> bool {
+        self.post.iter().any(|h| h.mode == HookMode::Transform)
+    }
+}
+
+// ============================================================================
+// Retriever configuration types
+// ============================================================================
+
+/// Vector store backing a retriever node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VectorStoreKind {
+    /// Qdrant over its REST API
+    Qdrant,
+    /// PostgreSQL with the pgvector extension
+    Pgvector,
+}
+
+/// Configuration for a "retriever" node
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RetrieverConfig {
+    /// Which vector store to query
+    pub store: VectorStoreKind,
+
+    /// Qdrant base URL, or a PostgreSQL connection string for pgvector
+    pub url: String,
+
+    /// Qdrant collection, or pgvector table
+    pub collection: String,
+
+    /// Number of documents to inject
+    #[serde(rename = "top-k", default = "default_top_k")]
+    pub top_k: usize,
+
+    /// Drop documents scoring below this similarity
+    #[serde(rename = "score-threshold", skip_serializing_if = "Option::is_none")]
+    pub score_threshold: Option<f32>,
+
+    /// Payload field (Qdrant) or column (pgvector) holding the document text
+    #[serde(rename = "content-field", default = "default_content_field")]
+    pub content_field: String,
+
+    /// Column holding the document ID (pgvector only)
+    #[serde(rename = "id-field", default = "default_id_field")]
+    pub id_field: String,
+
+    /// Column holding the vector (pgvector only)
+    #[serde(rename = "vector-field", default = "default_vector_field")]
+    pub vector_field: String,
+
+    /// Qdrant API key
+    #[serde(rename = "api-key", skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// PEM bundle of CA certificates trusted for `sslmode=require`, on top
+    /// of the public roots (pgvector only)
+    #[serde(rename = "ca-cert", skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+
+    /// Template for the injected content; `{documents}` and `{input}` are
+    /// replaced with the retrieved documents and the incoming content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+fn default_top_k() -> usize {
+    3
+}
+
+fn default_content_field() -> String {
+    "text".to_string()
+}
+
+fn default_id_field() -> String {
+    "id".to_string()
+}
+
+fn default_vector_field() -> String {
+    "embedding".to_string()
+}
+
+// ============================================================================
+// Guard configuration types
+// ============================================================================
+
+/// What a guard node does when a check fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum GuardAction {
+    /// End the pipeline with the refusal message
+    #[default]
+    Block,
+    /// Replace denylisted text and truncate over-long content, then continue
+    Redact,
+    /// Send the content to the fallback node instead
+    Fallback,
+}
+
+/// Configuration for a "guard" node
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GuardConfig {
+    /// Regular expressions that must not match the content
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denylist: Vec<String>,
+
+    /// Maximum content length in characters
+    #[serde(rename = "max-length", skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+
+    /// Ask the node's model to classify the content as SAFE or UNSAFE
+    #[serde(default)]
+    pub moderation: bool,
+
+    /// Action taken on a violation
+    #[serde(rename = "on-violation", default)]
+    pub on_violation: GuardAction,
+
+    /// Refusal returned when blocking; `{reason}` is replaced with the
+    /// failed check
+    #[serde(default = "default_refusal")]
+    pub refusal: String,
+
+    /// Replacement for denylisted text when redacting
+    #[serde(default = "default_redaction")]
+    pub redaction: String,
+
+    /// Node that receives the content when the action is "fallback"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+}
+
+fn default_refusal() -> String {
+    "I can't help with that request.".to_string()
+}
+
+fn default_redaction() -> String {
+    "[REDACTED]".to_string()
+}
+
+// ============================================================================
+// Aggregator configuration types
+// ============================================================================
+
+/// How an aggregator node combines the outputs of the handlers fanned out to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AggregateStrategy {
+    /// The most common answer wins, e.g. for classifiers
+    Vote,
+    /// The aggregator's model judges which answer is best
+    BestOf,
+    /// Answers are joined in handler order
+    Concat,
+    /// Answers are JSON objects merged into one
+    MergeJson,
+}
+
+/// Configuration for an "aggregator" node
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AggregateConfig {
+    pub strategy: AggregateStrategy,
+
+    /// Text placed between answers by "concat"
+    #[serde(default = "default_separator")]
+    pub separator: String,
+
+    /// Instructions for the "best-of" judge, placed ahead of the question
+    /// and the numbered candidates
+    #[serde(rename = "judge-prompt", skip_serializing_if = "Option::is_none")]
+    pub judge_prompt: Option<String>,
+}
+
+fn default_separator() -> String {
+    "\n\n".to_string()
+}
+
+// ============================================================================
+// Evaluator configuration types
+// ============================================================================
+
+/// Configuration for an "evaluator" node
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EvaluatorConfig {
+    /// Criteria the node's model grades the output against with a score
+    /// from 0 to 10
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rubric: Option<String>,
+
+    /// Regular expressions the output must match
+    #[serde(rename = "must-match", default, skip_serializing_if = "Vec::is_empty")]
+    pub must_match: Vec<String>,
+
+    /// Regular expressions the output must not match
+    #[serde(
+        rename = "must-not-match",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub must_not_match: Vec<String>,
+
+    /// Scores (0 to 1) below this send the request back to the handler
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+
+    /// How many times the handler may answer again before the best answer
+    /// so far is kept
+    #[serde(rename = "max-retries", default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_threshold() -> f64 {
+    0.7
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+// ============================================================================
+// Handoff configuration types
+// ============================================================================
+
+/// Pipeline a "handoff" node forwards requests to
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HandoffConfig {
+    /// Control plane serving the pipeline, this cluster's or another's
+    pub url: String,
+
+    /// Namespace of the pipeline
+    #[serde(default = "default_handoff_namespace")]
+    pub namespace: String,
+
+    /// Name of the pipeline
+    pub pipeline: String,
+
+    /// Key for a control plane that requires one
+    #[serde(rename = "api-key", skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+fn default_handoff_namespace() -> String {
+    "default".to_string()
+}
+
+// ============================================================================
+// Hedging configuration types
+// ============================================================================
+
+/// When a handler's model call is hedged with one to a fallback model
+///
+/// If the node's model hasn't answered within the `percentile` of its recent
+/// latencies, the same request goes to `fallback` too and the first answer
+/// wins. Hedged calls are paid for out of the pipeline's `retry-budget`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HedgeConfig {
+    /// Model, from the models map, the hedged call goes to
+    pub fallback: String,
+
+    /// Percentile of the model's recent latencies to wait for (default: 95)
+    #[serde(default = "default_hedge_percentile")]
+    pub percentile: f64,
+
+    /// Least time to wait before hedging, in milliseconds, and how long to
+    /// wait until enough latencies are known (default: 1000)
+    #[serde(rename = "min-delay-ms", default = "default_hedge_min_delay_ms")]
+    pub min_delay_ms: u64,
+}
+
+fn default_hedge_percentile() -> f64 {
+    95.0
+}
+
+fn default_hedge_min_delay_ms() -> u64 {
+    1000
+}
+
 // ============================================================================
 // Architecture node definition
 // ============================================================================
 
+/// Adapter types a node's `adapter` can name
+pub const ADAPTER_TYPES: &[&str] = &[
+    "openai-api",
+    "output",
+    "ws",
+    "embedding",
+    "retriever",
+    "guard",
+    "aggregator",
+    "audio-input",
+    "evaluator",
+    "handoff",
+];
+
 /// Architecture node definition from the composition file
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ArchitectureNode {
@@ -73,7 +385,8 @@ pub struct ArchitectureNode {
     /// Reference to a model in the models map
     pub model: Option<String>,
 
-    /// Adapter type: "openai-api", "output", "ws"
+    /// Adapter type, one of [`ADAPTER_TYPES`], or the name of a registered
+    /// plugin adapter
     pub adapter: String,
 
     #[serde(rename = "bind-addr")]
@@ -85,6 +398,17 @@ pub struct ArchitectureNode {
     #[serde(rename = "output-to")]
     pub output_to: Option<OutputTarget>,
 
+    /// Node to go back to after this one, until `max-iterations` passes
+    /// have run; `output-to` is followed after the last pass
+    #[serde(rename = "loop-to")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loop_to: Option<String>,
+
+    /// Passes through the loop, the first included (default: 3)
+    #[serde(rename = "max-iterations")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_iterations: Option<u32>,
+
     /// Use-case description for routing decisions
     #[serde(rename = "use-case")]
     pub use_case: Option<String>,
@@ -101,12 +425,228 @@ pub struct ArchitectureNode {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub context: Option<String>,
 
+    /// Settings for plugin adapters
     #[serde(rename = "extra-options", default)]
     pub extra_options: HashMap<String, serde_json::Value>,
 
     /// Pre and post execution hooks
     #[serde(default)]
     pub hooks: NodeHooks,
+
+    /// System message sent ahead of the conversation to this node's model
+    #[serde(rename = "system-prompt")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    /// Wraps the node's input before it is sent, e.g. "Translate: $INPUT".
+    /// `$INPUT` is the node's input, `$PREV_OUTPUT` the previous node's
+    /// output, and request variables are filled in by name.
+    #[serde(rename = "prompt-template")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_template: Option<String>,
+
+    /// Vector store settings for the "retriever" adapter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retriever: Option<RetrieverConfig>,
+
+    /// Checks for the "guard" adapter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guard: Option<GuardConfig>,
+
+    /// How the "aggregator" adapter combines fanned-out answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<AggregateConfig>,
+
+    /// Checks for the "evaluator" adapter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evaluator: Option<EvaluatorConfig>,
+
+    /// Pipeline the "handoff" adapter forwards to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff: Option<HandoffConfig>,
+
+    /// Send slow calls to a fallback model as well, taking the first answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hedge: Option<HedgeConfig>,
+
+    /// Number of local runner processes to start for this node's model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<u32>,
+
+    /// How requests are spread across runner replicas
+    #[serde(rename = "load-balancing", default)]
+    pub load_balancing: LoadBalancing,
+
+    /// Most time a hop through this node may take, in milliseconds
+    /// (default: only the pipeline's `deadline-ms` applies)
+    #[serde(rename = "timeout-ms", alias = "timeout_ms")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+
+    /// How a router weighs the cost and latency of the models it picks
+    /// between (default: the router model's choice alone)
+    #[serde(rename = "routing-policy")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_policy: Option<RoutingPolicy>,
+
+    /// How a router picks its target (default: asking its model)
+    #[serde(rename = "routing-mode", alias = "mode", default)]
+    pub routing_mode: RoutingMode,
+
+    /// Embedding model and threshold of a router in the "embedding" mode
+    #[serde(rename = "embedding-routing")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_routing: Option<EmbeddingRoutingConfig>,
+
+    /// Function, from the functions map, that picks the target of a router
+    /// in the "function" mode
+    #[serde(rename = "routing-function")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_function: Option<String>,
+
+    /// Prompts this node should get, shown to the router model as few-shot
+    /// examples alongside the `use-case`
+    #[serde(rename = "routing-examples")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routing_examples: Vec<String>,
+
+    /// Remember a router's picks, so repeating a prompt doesn't cost
+    /// another call to the router model
+    #[serde(rename = "routing-cache")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing_cache: Option<RoutingCacheConfig>,
+
+    /// Prompt caching for this node's model calls, replacing the
+    /// composition's `prompt-cache`
+    #[serde(rename = "prompt-cache")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_cache: Option<PromptCacheConfig>,
+}
+
+/// How long a provider keeps a cached prompt prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CacheTtl {
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+impl CacheTtl {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheTtl::FiveMinutes => "5m",
+            CacheTtl::OneHour => "1h",
+        }
+    }
+}
+
+/// Which parts of a node's prompt are marked for caching
+///
+/// Markers are Anthropic-style `cache_control` breakpoints: the provider
+/// caches the prompt up to and including each marked message. They are
+/// only sent to models with `prompt-caching` support.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PromptCacheConfig {
+    /// Mark the system prompt (default: true)
+    #[serde(default = "default_cache_system")]
+    pub system: bool,
+
+    /// Mark the last message of the conversation history, so the next
+    /// turn reuses everything before it
+    #[serde(default)]
+    pub history: bool,
+
+    /// Cache lifetime, "5m" or "1h" (default: the provider's)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<CacheTtl>,
+}
+
+impl PromptCacheConfig {
+    /// Whether any part of the prompt is marked
+    pub fn is_enabled(&self) -> bool {
+        self.system || self.history
+    }
+}
+
+fn default_cache_system() -> bool {
+    true
+}
+
+/// Strategy for spreading requests across local runner replicas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoadBalancing {
+    /// Take replicas in turn
+    #[default]
+    RoundRobin,
+    /// Pick the replica with the fewest requests in flight
+    LeastConnections,
+}
+
+/// How a router picks among the targets its model finds capable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoutingPolicy {
+    /// The capable target with the cheapest model
+    CheapestCapable,
+    /// The capable target with the lowest expected latency
+    Fastest,
+    /// The target the router model ranks best
+    QualityFirst,
+}
+
+/// How a router picks its target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoutingMode {
+    /// Ask the router model
+    #[default]
+    Llm,
+    /// Pick the target whose use-case or routing examples are most similar
+    /// to the prompt, asking the router model when none is similar enough
+    Embedding,
+    /// Let the `routing-function` pick, asking the router model, if it has
+    /// one, when the function fails
+    Function,
+}
+
+/// Configuration of a router in the "embedding" mode
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EmbeddingRoutingConfig {
+    /// Model, from the models map, the prompt and use-cases are embedded with
+    pub model: String,
+
+    /// Cosine similarity (0 to 1) the best target needs to be picked without
+    /// asking the router model
+    #[serde(default = "default_embedding_routing_threshold")]
+    pub threshold: f32,
+}
+
+fn default_embedding_routing_threshold() -> f32 {
+    0.75
+}
+
+/// How long a router remembers the target it picked for a prompt
+///
+/// Prompts are compared case- and whitespace-insensitively.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RoutingCacheConfig {
+    /// Seconds a pick is reused for (default: 300)
+    #[serde(rename = "ttl-secs", default = "default_routing_cache_ttl_secs")]
+    pub ttl_secs: u64,
+
+    /// Most prompts remembered; the oldest pick goes first (default: 1024)
+    #[serde(rename = "max-entries", default = "default_routing_cache_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_routing_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_routing_cache_max_entries() -> usize {
+    1024
 }
 
 /// Output target specification - can be layers or specific nodes
@@ -119,6 +659,9 @@ pub enum OutputTarget {
     Nodes(Vec<String>),
 }
 
+/// Passes through a loop when `max-iterations` isn't set
+pub const DEFAULT_MAX_ITERATIONS: u32 = 3;
+
 impl ArchitectureNode {
     /// Check if this is a router node (layer 0 with output-to)
     pub fn is_router(&self) -> bool {
@@ -130,6 +673,51 @@ impl ArchitectureNode {
         self.adapter == "output"
     }
 
+    /// Check if this node produces embeddings
+    pub fn is_embedding(&self) -> bool {
+        self.adapter == "embedding"
+    }
+
+    /// Check if this node retrieves documents from a vector store
+    pub fn is_retriever(&self) -> bool {
+        self.adapter == "retriever"
+    }
+
+    /// Check if this node enforces content checks
+    pub fn is_guard(&self) -> bool {
+        self.adapter == "guard"
+    }
+
+    /// Check if this node combines the answers of several handlers
+    pub fn is_aggregator(&self) -> bool {
+        self.adapter == "aggregator"
+    }
+
+    /// Passes through this node's loop, the first included
+    pub fn loop_iterations(&self) -> u32 {
+        self.max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS)
+    }
+
+    /// Check if this node scores the previous node's output
+    pub fn is_evaluator(&self) -> bool {
+        self.adapter == "evaluator"
+    }
+
+    /// Check if this node forwards requests to another pipeline
+    pub fn is_handoff(&self) -> bool {
+        self.adapter == "handoff"
+    }
+
+    /// Check if this node transcribes audio uploads for the pipeline
+    pub fn is_audio_input(&self) -> bool {
+        self.adapter == "audio-input"
+    }
+
+    /// Requested runner replicas (at least one)
+    pub fn effective_replicas(&self) -> usize {
+        self.replicas.unwrap_or(1).max(1) as usize
+    }
+
     /// Get the bind address with default
     pub fn effective_bind_addr(&self) -> &str {
         self.bind_addr.as_deref().unwrap_or("0.0.0.0")
@@ -220,6 +808,88 @@ mod tests {
         assert_eq!(node.url, Some("ws://localhost:3000".to_string()));
     }
 
+    #[test]
+    fn test_parse_routing_policy() {
+        let json = r#"{
+            "name": "router",
+            "layer": 0,
+            "adapter": "openai-api",
+            "routing-policy": "cheapest-capable"
+        }"#;
+
+        let node: ArchitectureNode = serde_json::from_str(json).unwrap();
+        assert_eq!(node.routing_policy, Some(RoutingPolicy::CheapestCapable));
+        assert!(node.routing_examples.is_empty());
+        assert!(node.routing_cache.is_none());
+        assert_eq!(node.routing_mode, RoutingMode::Llm);
+    }
+
+    #[test]
+    fn test_parse_embedding_routing() {
+        let json = r#"{
+            "name": "router",
+            "layer": 0,
+            "adapter": "openai-api",
+            "mode": "embedding",
+            "embedding-routing": {"model": "embedder"}
+        }"#;
+
+        let node: ArchitectureNode = serde_json::from_str(json).unwrap();
+        assert_eq!(node.routing_mode, RoutingMode::Embedding);
+        let config = node.embedding_routing.unwrap();
+        assert_eq!(config.model, "embedder");
+        assert_eq!(config.threshold, 0.75);
+    }
+
+    #[test]
+    fn test_parse_function_routing() {
+        let json = r#"{
+            "name": "router",
+            "layer": 0,
+            "adapter": "openai-api",
+            "routing-mode": "function",
+            "routing-function": "pick_target"
+        }"#;
+
+        let node: ArchitectureNode = serde_json::from_str(json).unwrap();
+        assert_eq!(node.routing_mode, RoutingMode::Function);
+        assert_eq!(node.routing_function.as_deref(), Some("pick_target"));
+    }
+
+    #[test]
+    fn test_parse_handoff() {
+        let json = r#"{
+            "name": "billing",
+            "layer": 1,
+            "adapter": "handoff",
+            "handoff": {"url": "http://control-plane:8181", "pipeline": "billing-bot"}
+        }"#;
+
+        let node: ArchitectureNode = serde_json::from_str(json).unwrap();
+        assert!(node.is_handoff());
+        let handoff = node.handoff.unwrap();
+        assert_eq!(handoff.pipeline, "billing-bot");
+        assert_eq!(handoff.namespace, "default");
+        assert_eq!(handoff.api_key, None);
+    }
+
+    #[test]
+    fn test_parse_routing_examples_and_cache() {
+        let json = r#"{
+            "name": "router",
+            "layer": 0,
+            "adapter": "openai-api",
+            "routing-cache": {"ttl-secs": 60},
+            "routing-examples": ["What were our Q3 earnings?"]
+        }"#;
+
+        let node: ArchitectureNode = serde_json::from_str(json).unwrap();
+        assert_eq!(node.routing_examples, ["What were our Q3 earnings?"]);
+        let cache = node.routing_cache.unwrap();
+        assert_eq!(cache.ttl_secs, 60);
+        assert_eq!(cache.max_entries, 1024);
+    }
+
     #[test]
     fn test_effective_bind_addr_default() {
         let node = ArchitectureNode {
@@ -230,12 +900,32 @@ mod tests {
             bind_addr: None,
             bind_port: None,
             output_to: None,
+            loop_to: None,
+            max_iterations: None,
             use_case: None,
             condition: None,
             url: None,
             context: None,
             extra_options: HashMap::new(),
             hooks: NodeHooks::default(),
+            system_prompt: None,
+            prompt_template: None,
+            retriever: None,
+            guard: None,
+            aggregate: None,
+            evaluator: None,
+            handoff: None,
+            hedge: None,
+            replicas: None,
+            load_balancing: LoadBalancing::default(),
+            timeout_ms: None,
+            routing_policy: None,
+            routing_mode: Default::default(),
+            embedding_routing: None,
+            routing_function: None,
+            prompt_cache: None,
+            routing_examples: vec![],
+            routing_cache: None,
         };
         assert_eq!(node.effective_bind_addr(), "0.0.0.0");
     }
@@ -295,6 +985,21 @@ mod tests {
         assert_eq!(hook.on_failure, FailureAction::Continue); // default
     }
 
+    #[test]
+    fn test_parse_stream_transform_hook() {
+        let hooks: NodeHooks = serde_json::from_str(
+            r#"{"post": [{"function": "scrub-pii", "mode": "stream-transform"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(hooks.post[0].mode, HookMode::StreamTransform);
+        assert!(!hooks.needs_whole_output());
+
+        let hooks: NodeHooks =
+            serde_json::from_str(r#"{"post": [{"function": "validate", "mode": "transform"}]}"#)
+                .unwrap();
+        assert!(hooks.needs_whole_output());
+    }
+
     #[test]
     fn test_parse_node_with_hooks() {
         let json = r#"{
@@ -327,4 +1032,26 @@ mod tests {
         assert!(hooks.pre.is_empty());
         assert!(hooks.post.is_empty());
     }
+
+    #[test]
+    fn test_parse_aggregator_node() {
+        let json = r#"{
+            "name": "combine",
+            "layer": 2,
+            "adapter": "aggregator",
+            "aggregate": {"strategy": "concat"},
+            "output-to": ["output"]
+        }"#;
+
+        let node: ArchitectureNode = serde_json::from_str(json).unwrap();
+        assert!(node.is_aggregator());
+        let aggregate = node.aggregate.unwrap();
+        assert_eq!(aggregate.strategy, AggregateStrategy::Concat);
+        assert_eq!(aggregate.separator, "\n\n");
+        assert!(aggregate.judge_prompt.is_none());
+
+        let json = r#"{"strategy": "merge-json"}"#;
+        let aggregate: AggregateConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(aggregate.strategy, AggregateStrategy::MergeJson);
+    }
 }
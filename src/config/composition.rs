@@ -1,17 +1,21 @@
 use std::collections::HashMap;
+use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use super::architecture::{ArchitectureNode, OutputTarget};
+use super::architecture::LoadBalancing;
+use super::architecture::{
+    AggregateStrategy, ArchitectureNode, GuardAction, OutputTarget, PromptCacheConfig, RoutingMode,
+};
 use super::functions::FunctionType;
-use super::models::ModelDefinition;
+use super::models::{ModelDefinition, RunnerType};
 use super::secrets::SecretSource;
 
 /// Errors that can occur during composition parsing and validation
 #[derive(Error, Debug, PartialEq)]
 pub enum CompositionError {
-    #[error("JSON parse error: {0}")]
+    #[error("Parse error: {0}")]
     ParseError(String),
 
     #[error("Model '{0}' referenced by node '{1}' is not defined")]
@@ -31,6 +35,131 @@ pub enum CompositionError {
 
     #[error("Function '{0}' referenced by hook in node '{1}' is not defined")]
     UndefinedFunction(String, String),
+
+    #[error("Embedding node '{0}' has no model")]
+    EmbeddingWithoutModel(String),
+
+    #[error("Audio input node '{0}' has no model")]
+    AudioInputWithoutModel(String),
+
+    #[error(
+        "Audio input node '{0}' can't have a layer or output-to; its transcripts go to the router"
+    )]
+    AudioInputInPipeline(String),
+
+    #[error("Retriever node '{0}' has no retriever configuration")]
+    RetrieverWithoutConfig(String),
+
+    #[error("Handoff node '{0}' has no handoff configuration")]
+    HandoffWithoutConfig(String),
+
+    #[error("Guard node '{0}' has no guard configuration")]
+    GuardWithoutConfig(String),
+
+    #[error("Guard node '{0}' uses on-violation \"fallback\" without a fallback node")]
+    GuardMissingFallback(String),
+
+    #[error("Guard node '{0}' enables moderation without a model")]
+    GuardModerationWithoutModel(String),
+
+    #[error("Invalid denylist pattern '{1}' in guard node '{0}': {2}")]
+    InvalidGuardPattern(String, String, String),
+
+    #[error("Aggregator node '{0}' has no aggregate configuration")]
+    AggregatorWithoutConfig(String),
+
+    #[error("Aggregator node '{0}' uses strategy \"best-of\" without a judge model")]
+    AggregatorJudgeWithoutModel(String),
+
+    #[error("Evaluator node '{0}' has no evaluator configuration")]
+    EvaluatorWithoutConfig(String),
+
+    #[error("Evaluator node '{0}' needs a rubric or at least one pattern")]
+    EvaluatorWithoutChecks(String),
+
+    #[error("Evaluator node '{0}' has a rubric without a model")]
+    EvaluatorRubricWithoutModel(String),
+
+    #[error("Invalid pattern '{1}' in evaluator node '{0}': {2}")]
+    InvalidEvaluatorPattern(String, String, String),
+
+    #[error("Evaluator node '{0}' threshold must be between 0 and 1")]
+    InvalidEvaluatorThreshold(String),
+
+    #[error("Router '{0}' uses routing-mode \"embedding\" without embedding-routing")]
+    EmbeddingRoutingWithoutConfig(String),
+
+    #[error("Router '{0}' embedding-routing threshold must be between 0 and 1")]
+    InvalidEmbeddingRoutingThreshold(String),
+
+    #[error("Router '{0}' uses routing-mode \"function\" without a routing-function")]
+    RoutingFunctionWithoutName(String),
+
+    #[error("Routing function '{1}' of router '{0}' is not defined")]
+    UndefinedRoutingFunction(String, String),
+
+    #[error("Session store \"redis\" requires a url")]
+    SessionStoreWithoutUrl,
+
+    #[error("Route override '{0}' must be a node other than the router and output")]
+    InvalidRouteOverride(String),
+
+    #[error("Node '{0}' loops to '{1}', which must be a node other than the router and output")]
+    InvalidLoopTarget(String, String),
+
+    #[error("Node '{0}' must allow at least one iteration")]
+    InvalidMaxIterations(String),
+
+    #[error("Hook '{0}' in node '{1}' must have a timeout_ms above zero")]
+    InvalidHookTimeout(String, String),
+
+    #[error("Node '{0}' must have a timeout-ms above zero")]
+    InvalidNodeTimeout(String),
+
+    #[error("The pipeline's deadline-ms must be above zero")]
+    InvalidDeadline,
+
+    #[error("Request limit '{0}' must be above zero")]
+    InvalidRequestLimit(String),
+
+    #[error("Header variable '{0}' must be lowercase letters, digits and underscores")]
+    InvalidHeaderVariable(String),
+
+    #[error("Queue ingestion requires at least one broker")]
+    QueueWithoutBrokers,
+
+    #[error("Queue ingestion requires an input-topic and an output-topic")]
+    QueueWithoutTopic,
+
+    #[error("Node '{0}' must request at least one replica")]
+    InvalidReplicaCount(String),
+
+    #[error("Node '{0}' requests replicas but its model does not use a local runner")]
+    ReplicasWithoutRunner(String),
+
+    #[error("Model '{0}' must allow at least one concurrent call")]
+    InvalidMaxConcurrent(String),
+
+    #[error("Model '{0}' sha256 must be 64 hexadecimal characters")]
+    InvalidSha256(String),
+
+    #[error("Secret '{1}' holding the credentials of model '{0}' is not defined")]
+    UndefinedCredentials(String, String),
+
+    #[error("Node '{0}' hedge percentile must be above 0 and at most 100")]
+    InvalidHedgePercentile(String),
+
+    #[error("The pipeline's retry-budget ratio must be between 0 and 1")]
+    InvalidRetryBudget,
+
+    #[error("Budget of model '{0}' must set monthly-dollars or monthly-tokens above zero")]
+    InvalidBudget(String),
+
+    #[error("Budget of model '{0}' sets monthly-dollars but the model has no cost.per-1k-tokens")]
+    BudgetWithoutCost(String),
+
+    #[error("Fallback '{1}' of model '{0}''s budget is not defined")]
+    UndefinedBudgetFallback(String, String),
 }
 
 /// The complete composition file structure
@@ -44,6 +173,222 @@ pub struct Composition {
     /// Reusable function definitions for hooks
     #[serde(default)]
     pub functions: HashMap<String, FunctionType>,
+    /// Where conversation sessions are kept (default: in memory)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<SessionConfig>,
+    /// Nodes clients may route to directly with `X-LLMNet-Route` or the
+    /// request's `model`, skipping the router (none by default)
+    #[serde(
+        rename = "route-overrides",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub route_overrides: Vec<String>,
+    /// Variables clients may set per request with `X-LLMNet-Var-<name>`
+    /// headers, for `if` conditions and hooks (none by default)
+    #[serde(
+        rename = "header-variables",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub header_variables: Vec<String>,
+    /// Describe each request's route and hop latencies in `X-LLMNet-*`
+    /// headers on chat completion responses (off by default)
+    #[serde(
+        rename = "trace-headers",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub trace_headers: bool,
+    /// Message queue the pipeline consumes prompts from, in addition to
+    /// serving HTTP (none by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueConfig>,
+    /// Prompt caching for nodes that don't set their own `prompt-cache`
+    /// (none by default)
+    #[serde(
+        rename = "prompt-cache",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub prompt_cache: Option<PromptCacheConfig>,
+    /// Most time a request may take through the whole pipeline, in
+    /// milliseconds (none by default)
+    #[serde(
+        rename = "deadline-ms",
+        alias = "deadline_ms",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub deadline_ms: Option<u64>,
+    /// Limits on chat completion requests, checked before they're processed
+    /// (only a 2 MiB body limit by default)
+    #[serde(
+        rename = "request-limits",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub request_limits: Option<RequestLimits>,
+    /// How many extra calls hedged nodes may make (by default a tenth of
+    /// the handler calls, in bursts of up to 10)
+    #[serde(
+        rename = "retry-budget",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub retry_budget: Option<RetryBudgetConfig>,
+}
+
+/// Body size largest chat completion requests may have when the
+/// composition sets no `max-body-bytes`
+pub const DEFAULT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Limits on chat completion requests, so a giant prompt is refused before
+/// it can exhaust a small device's memory
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RequestLimits {
+    /// Largest request body, in bytes (default: 2 MiB)
+    #[serde(rename = "max-body-bytes", skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<usize>,
+
+    /// Most messages a request may carry
+    #[serde(rename = "max-messages", skip_serializing_if = "Option::is_none")]
+    pub max_messages: Option<usize>,
+
+    /// Most tokens the messages may add up to, estimated at four
+    /// characters per token
+    #[serde(rename = "max-prompt-tokens", skip_serializing_if = "Option::is_none")]
+    pub max_prompt_tokens: Option<usize>,
+}
+
+impl RequestLimits {
+    /// Largest request body, in bytes
+    pub fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES)
+    }
+}
+
+/// Extra calls hedging may add to a pipeline's handler calls, so a slow
+/// upstream doesn't double what the pipeline costs
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RetryBudgetConfig {
+    /// Extra calls earned by each handler call (default: 0.1)
+    #[serde(default = "default_retry_budget_ratio")]
+    pub ratio: f64,
+
+    /// Most extra calls that may be saved up and made in a row (default: 10)
+    #[serde(default = "default_retry_budget_burst")]
+    pub burst: u32,
+}
+
+impl Default for RetryBudgetConfig {
+    fn default() -> Self {
+        Self {
+            ratio: default_retry_budget_ratio(),
+            burst: default_retry_budget_burst(),
+        }
+    }
+}
+
+fn default_retry_budget_ratio() -> f64 {
+    0.1
+}
+
+fn default_retry_budget_burst() -> u32 {
+    10
+}
+
+/// Backend for conversation sessions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStoreKind {
+    /// Per-worker memory; sessions are lost on restart
+    #[default]
+    Memory,
+    /// Redis, shared between replicas
+    Redis,
+}
+
+/// Conversation session settings
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SessionConfig {
+    #[serde(default)]
+    pub store: SessionStoreKind,
+
+    /// Connection URL (redis only), e.g. `redis://localhost:6379`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// Approximate token budget for the history replayed to handlers
+    #[serde(rename = "max-tokens", default = "default_session_max_tokens")]
+    pub max_tokens: usize,
+
+    /// Seconds of inactivity before a session expires
+    #[serde(rename = "ttl-secs", default = "default_session_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+fn default_session_max_tokens() -> usize {
+    4000
+}
+
+fn default_session_ttl_secs() -> u64 {
+    3600
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            store: SessionStoreKind::default(),
+            url: None,
+            max_tokens: default_session_max_tokens(),
+            ttl_secs: default_session_ttl_secs(),
+        }
+    }
+}
+
+/// Message broker for queue ingestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueKind {
+    Nats,
+    Kafka,
+}
+
+/// Queue ingestion settings
+///
+/// Prompts are read from `input-topic` and each result is published to
+/// `output-topic`, so the pipeline can run as a batch or async worker.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct QueueConfig {
+    pub kind: QueueKind,
+
+    /// Broker addresses, e.g. `nats://localhost:4222` or `kafka:9092`
+    pub brokers: Vec<String>,
+
+    /// NATS subject or Kafka topic prompts are read from
+    #[serde(rename = "input-topic")]
+    pub input_topic: String,
+
+    /// NATS subject or Kafka topic results are written to
+    #[serde(rename = "output-topic")]
+    pub output_topic: String,
+
+    /// NATS queue group shared by replicas, so each prompt is handled once
+    #[serde(default = "default_queue_group")]
+    pub group: String,
+
+    /// Prompts processed at the same time
+    #[serde(default = "default_queue_concurrency")]
+    pub concurrency: usize,
+}
+
+fn default_queue_group() -> String {
+    "llmnet".to_string()
+}
+
+fn default_queue_concurrency() -> usize {
+    4
 }
 
 // ============================================================================
@@ -115,6 +460,81 @@ pub fn parse_composition(content: &str) -> Result<Composition, CompositionError>
     serde_json::from_str(&stripped).map_err(|e| CompositionError::ParseError(e.to_string()))
 }
 
+/// Formats a composition can be written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CompositionFormat {
+    /// JSON, with comments allowed
+    #[default]
+    Json,
+    Toml,
+    /// YAML, possibly split over several documents
+    Yaml,
+}
+
+impl CompositionFormat {
+    /// The format of a file by its extension: `.toml`, `.yaml` and `.yml`
+    /// are recognised, anything else is read as JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::Toml,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Parse a composition written in `format`.
+/// This is a pure function - no I/O.
+///
+/// TOML and YAML are read into the same structure as JSON, so every format
+/// accepts the same keys. The documents of a multi-document YAML file are
+/// combined, each setting its own top-level keys: one can hold the
+/// `models` and another the `architecture`.
+pub fn parse_composition_as(
+    content: &str,
+    format: CompositionFormat,
+) -> Result<Composition, CompositionError> {
+    let parse_error = |e: String| CompositionError::ParseError(e);
+    let value = match format {
+        CompositionFormat::Json => return parse_composition(content),
+        CompositionFormat::Toml => {
+            let table: toml::Table =
+                toml::from_str(content).map_err(|e| parse_error(e.to_string()))?;
+            serde_json::to_value(table).map_err(|e| parse_error(e.to_string()))?
+        }
+        CompositionFormat::Yaml => merge_yaml_documents(content)?,
+    };
+    serde_json::from_value(value).map_err(|e| parse_error(e.to_string()))
+}
+
+/// The documents of a YAML file as one object
+fn merge_yaml_documents(content: &str) -> Result<serde_json::Value, CompositionError> {
+    let mut merged = serde_json::Map::new();
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = serde_json::Value::deserialize(document)
+            .map_err(|e| CompositionError::ParseError(e.to_string()))?;
+        let fields = match value {
+            serde_json::Value::Object(fields) => fields,
+            // Empty documents, such as after a trailing `---`
+            serde_json::Value::Null => continue,
+            _ => {
+                return Err(CompositionError::ParseError(
+                    "every YAML document must be a mapping".to_string(),
+                ))
+            }
+        };
+        for (key, value) in fields {
+            if merged.insert(key.clone(), value).is_some() {
+                return Err(CompositionError::ParseError(format!(
+                    "'{}' is set by more than one YAML document",
+                    key
+                )));
+            }
+        }
+    }
+    Ok(serde_json::Value::Object(merged))
+}
+
 /// Validate a composition for consistency.
 /// This is a pure function - no I/O.
 pub fn validate_composition(composition: &Composition) -> Result<(), CompositionError> {
@@ -146,6 +566,260 @@ pub fn validate_composition(composition: &Composition) -> Result<(), Composition
         }
     }
 
+    // Hedged calls go to a defined model, after a sensible percentile
+    for node in &composition.architecture {
+        let Some(hedge) = &node.hedge else {
+            continue;
+        };
+        if !composition.models.contains_key(&hedge.fallback) {
+            return Err(CompositionError::UndefinedModel(
+                hedge.fallback.clone(),
+                node.name.clone(),
+            ));
+        }
+        if !(hedge.percentile > 0.0 && hedge.percentile <= 100.0) {
+            return Err(CompositionError::InvalidHedgePercentile(node.name.clone()));
+        }
+    }
+    if composition
+        .retry_budget
+        .as_ref()
+        .is_some_and(|b| !(0.0..=1.0).contains(&b.ratio))
+    {
+        return Err(CompositionError::InvalidRetryBudget);
+    }
+
+    // Embedding nodes need a model to vectorize with, retrievers a store,
+    // handoffs a pipeline
+    for node in &composition.architecture {
+        if node.is_embedding() && node.model.is_none() {
+            return Err(CompositionError::EmbeddingWithoutModel(node.name.clone()));
+        }
+        if node.is_retriever() && node.retriever.is_none() {
+            return Err(CompositionError::RetrieverWithoutConfig(node.name.clone()));
+        }
+        if node.is_handoff() && node.handoff.is_none() {
+            return Err(CompositionError::HandoffWithoutConfig(node.name.clone()));
+        }
+    }
+
+    // Audio input nodes transcribe with their model outside the layers
+    for node in composition
+        .architecture
+        .iter()
+        .filter(|n| n.is_audio_input())
+    {
+        if node.model.is_none() {
+            return Err(CompositionError::AudioInputWithoutModel(node.name.clone()));
+        }
+        if node.layer.is_some() || node.output_to.is_some() {
+            return Err(CompositionError::AudioInputInPipeline(node.name.clone()));
+        }
+    }
+
+    // Replicas are extra runner processes, so only spawned models can have them
+    for node in &composition.architecture {
+        match node.replicas {
+            Some(0) => return Err(CompositionError::InvalidReplicaCount(node.name.clone())),
+            Some(n) if n > 1 => {
+                let spawned = composition
+                    .model_for_node(node)
+                    .is_some_and(|m| !matches!(m.to_config().runner, RunnerType::External));
+                if !spawned {
+                    return Err(CompositionError::ReplicasWithoutRunner(node.name.clone()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (name, model) in &composition.models {
+        let config = model.to_config();
+        if config.max_concurrent == Some(0) {
+            return Err(CompositionError::InvalidMaxConcurrent(name.clone()));
+        }
+        if config
+            .sha256
+            .as_deref()
+            .is_some_and(|digest| !crate::runtime::fetch::is_sha256_hex(digest))
+        {
+            return Err(CompositionError::InvalidSha256(name.clone()));
+        }
+        if let Some(secret) = &config.credentials {
+            if !composition.secrets.contains_key(secret) {
+                return Err(CompositionError::UndefinedCredentials(
+                    name.clone(),
+                    secret.clone(),
+                ));
+            }
+        }
+        if let Some(budget) = &config.budget {
+            if (budget.monthly_dollars.is_none() && budget.monthly_tokens.is_none())
+                || budget.monthly_dollars.is_some_and(|d| d <= 0.0)
+                || budget.monthly_tokens == Some(0)
+            {
+                return Err(CompositionError::InvalidBudget(name.clone()));
+            }
+            if budget.monthly_dollars.is_some() && config.cost.is_none() {
+                return Err(CompositionError::BudgetWithoutCost(name.clone()));
+            }
+            if let Some(fallback) = &budget.fallback {
+                if fallback == name || !composition.models.contains_key(fallback) {
+                    return Err(CompositionError::UndefinedBudgetFallback(
+                        name.clone(),
+                        fallback.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Guard nodes need valid checks and, for fallback, a real target
+    for node in composition.architecture.iter().filter(|n| n.is_guard()) {
+        let Some(guard) = &node.guard else {
+            return Err(CompositionError::GuardWithoutConfig(node.name.clone()));
+        };
+        for pattern in &guard.denylist {
+            if let Err(e) = regex::Regex::new(pattern) {
+                return Err(CompositionError::InvalidGuardPattern(
+                    node.name.clone(),
+                    pattern.clone(),
+                    e.to_string(),
+                ));
+            }
+        }
+        if guard.moderation && node.model.is_none() {
+            return Err(CompositionError::GuardModerationWithoutModel(
+                node.name.clone(),
+            ));
+        }
+        match (&guard.on_violation, &guard.fallback) {
+            (GuardAction::Fallback, None) => {
+                return Err(CompositionError::GuardMissingFallback(node.name.clone()));
+            }
+            (_, Some(fallback)) if !node_names.contains_key(fallback) => {
+                return Err(CompositionError::UndefinedNode(fallback.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    // Aggregators need a strategy, and "best-of" a model to judge with
+    for node in composition
+        .architecture
+        .iter()
+        .filter(|n| n.is_aggregator())
+    {
+        let Some(aggregate) = &node.aggregate else {
+            return Err(CompositionError::AggregatorWithoutConfig(node.name.clone()));
+        };
+        if aggregate.strategy == AggregateStrategy::BestOf && node.model.is_none() {
+            return Err(CompositionError::AggregatorJudgeWithoutModel(
+                node.name.clone(),
+            ));
+        }
+    }
+
+    // Evaluators need something to score with
+    for node in composition.architecture.iter().filter(|n| n.is_evaluator()) {
+        let Some(evaluator) = &node.evaluator else {
+            return Err(CompositionError::EvaluatorWithoutConfig(node.name.clone()));
+        };
+        if evaluator.rubric.is_none()
+            && evaluator.must_match.is_empty()
+            && evaluator.must_not_match.is_empty()
+        {
+            return Err(CompositionError::EvaluatorWithoutChecks(node.name.clone()));
+        }
+        if evaluator.rubric.is_some() && node.model.is_none() {
+            return Err(CompositionError::EvaluatorRubricWithoutModel(
+                node.name.clone(),
+            ));
+        }
+        for pattern in evaluator.must_match.iter().chain(&evaluator.must_not_match) {
+            if let Err(e) = regex::Regex::new(pattern) {
+                return Err(CompositionError::InvalidEvaluatorPattern(
+                    node.name.clone(),
+                    pattern.clone(),
+                    e.to_string(),
+                ));
+            }
+        }
+        if !(0.0..=1.0).contains(&evaluator.threshold) {
+            return Err(CompositionError::InvalidEvaluatorThreshold(
+                node.name.clone(),
+            ));
+        }
+    }
+
+    // Embedding routers need a model to compare prompts and use-cases with
+    for node in composition
+        .architecture
+        .iter()
+        .filter(|n| n.routing_mode == RoutingMode::Embedding)
+    {
+        let Some(routing) = &node.embedding_routing else {
+            return Err(CompositionError::EmbeddingRoutingWithoutConfig(
+                node.name.clone(),
+            ));
+        };
+        if !composition.models.contains_key(&routing.model) {
+            return Err(CompositionError::UndefinedModel(
+                routing.model.clone(),
+                node.name.clone(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&routing.threshold) {
+            return Err(CompositionError::InvalidEmbeddingRoutingThreshold(
+                node.name.clone(),
+            ));
+        }
+    }
+
+    for node in composition
+        .architecture
+        .iter()
+        .filter(|n| n.routing_mode == RoutingMode::Function)
+    {
+        let Some(function) = &node.routing_function else {
+            return Err(CompositionError::RoutingFunctionWithoutName(
+                node.name.clone(),
+            ));
+        };
+        if !composition.functions.contains_key(function) {
+            return Err(CompositionError::UndefinedRoutingFunction(
+                node.name.clone(),
+                function.clone(),
+            ));
+        }
+    }
+
+    if let Some(sessions) = &composition.sessions {
+        if sessions.store == SessionStoreKind::Redis && sessions.url.is_none() {
+            return Err(CompositionError::SessionStoreWithoutUrl);
+        }
+    }
+
+    // Lowercase names can't shadow the built-in variables, which are uppercase
+    for name in &composition.header_variables {
+        let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid {
+            return Err(CompositionError::InvalidHeaderVariable(name.clone()));
+        }
+    }
+
+    if let Some(queue) = &composition.queue {
+        if queue.brokers.is_empty() {
+            return Err(CompositionError::QueueWithoutBrokers);
+        }
+        if queue.input_topic.is_empty() || queue.output_topic.is_empty() {
+            return Err(CompositionError::QueueWithoutTopic);
+        }
+    }
+
     // Check that output-to node references exist
     for node in &composition.architecture {
         if let Some(OutputTarget::Nodes(targets)) = &node.output_to {
@@ -157,6 +831,35 @@ pub fn validate_composition(composition: &Composition) -> Result<(), Composition
         }
     }
 
+    // Loops go back to a handler and run at least once
+    for node in &composition.architecture {
+        if node.max_iterations == Some(0) {
+            return Err(CompositionError::InvalidMaxIterations(node.name.clone()));
+        }
+        let Some(target) = &node.loop_to else {
+            continue;
+        };
+        let target_node = node_names
+            .get(target)
+            .ok_or_else(|| CompositionError::UndefinedNode(target.clone()))?;
+        if target_node.is_output() || target_node.layer == Some(0) {
+            return Err(CompositionError::InvalidLoopTarget(
+                node.name.clone(),
+                target.clone(),
+            ));
+        }
+    }
+
+    // Route overrides must name a node the router could hand off to
+    for route in &composition.route_overrides {
+        let node = node_names
+            .get(route)
+            .ok_or_else(|| CompositionError::UndefinedNode(route.clone()))?;
+        if node.is_output() || node.layer == Some(0) {
+            return Err(CompositionError::InvalidRouteOverride(route.clone()));
+        }
+    }
+
     // Check for at least one router node (layer 0)
     let has_router = composition.architecture.iter().any(|n| n.layer == Some(0));
     if !has_router {
@@ -171,22 +874,37 @@ pub fn validate_composition(composition: &Composition) -> Result<(), Composition
 
     // Check that all hook function references exist
     for node in &composition.architecture {
-        for hook in &node.hooks.pre {
+        for hook in node.hooks.pre.iter().chain(&node.hooks.post) {
             if !composition.functions.contains_key(&hook.function) {
                 return Err(CompositionError::UndefinedFunction(
                     hook.function.clone(),
                     node.name.clone(),
                 ));
             }
-        }
-        for hook in &node.hooks.post {
-            if !composition.functions.contains_key(&hook.function) {
-                return Err(CompositionError::UndefinedFunction(
+            if hook.timeout_ms == Some(0) {
+                return Err(CompositionError::InvalidHookTimeout(
                     hook.function.clone(),
                     node.name.clone(),
                 ));
             }
         }
+        if node.timeout_ms == Some(0) {
+            return Err(CompositionError::InvalidNodeTimeout(node.name.clone()));
+        }
+    }
+    if composition.deadline_ms == Some(0) {
+        return Err(CompositionError::InvalidDeadline);
+    }
+    if let Some(limits) = &composition.request_limits {
+        for (name, limit) in [
+            ("max-body-bytes", limits.max_body_bytes),
+            ("max-messages", limits.max_messages),
+            ("max-prompt-tokens", limits.max_prompt_tokens),
+        ] {
+            if limit == Some(0) {
+                return Err(CompositionError::InvalidRequestLimit(name.to_string()));
+            }
+        }
     }
 
     Ok(())
@@ -197,7 +915,13 @@ impl Composition {
     /// Pure function - no I/O.
     #[allow(clippy::should_implement_trait)]
     pub fn from_str(content: &str) -> Result<Self, CompositionError> {
-        let composition = parse_composition(content)?;
+        Self::from_str_as(content, CompositionFormat::Json)
+    }
+
+    /// Parse and validate a composition written in `format`.
+    /// Pure function - no I/O.
+    pub fn from_str_as(content: &str, format: CompositionFormat) -> Result<Self, CompositionError> {
+        let composition = parse_composition_as(content, format)?;
         validate_composition(&composition)?;
         Ok(composition)
     }
@@ -220,6 +944,18 @@ impl Composition {
         self.architecture.iter().find(|n| n.name == name)
     }
 
+    /// Prompt caching for a node: its own `prompt-cache`, else the
+    /// composition's, unless nothing is marked
+    pub fn prompt_cache_for<'a>(
+        &'a self,
+        node: &'a ArchitectureNode,
+    ) -> Option<&'a PromptCacheConfig> {
+        node.prompt_cache
+            .as_ref()
+            .or(self.prompt_cache.as_ref())
+            .filter(|c| c.is_enabled())
+    }
+
     /// Get the model definition for a node
     pub fn model_for_node(&self, node: &ArchitectureNode) -> Option<&ModelDefinition> {
         node.model.as_ref().and_then(|m| self.models.get(m))
@@ -229,11 +965,43 @@ impl Composition {
     pub fn output_nodes(&self) -> Vec<&ArchitectureNode> {
         self.architecture.iter().filter(|n| n.is_output()).collect()
     }
+
+    /// Names of models used by embedding nodes and embedding routers
+    pub fn embedding_models(&self) -> Vec<&str> {
+        let routers = self
+            .architecture
+            .iter()
+            .filter(|n| n.routing_mode == RoutingMode::Embedding)
+            .filter_map(|n| n.embedding_routing.as_ref())
+            .map(|r| r.model.as_str());
+        let mut models: Vec<&str> = self
+            .architecture
+            .iter()
+            .filter(|n| n.is_embedding())
+            .filter_map(|n| n.model.as_deref())
+            .chain(routers)
+            .collect();
+        models.sort_unstable();
+        models.dedup();
+        models
+    }
+
+    /// Runner replicas to start per model, with the balancing of the node
+    /// that asked for the most
+    pub fn runner_replicas(&self, model: &str) -> (usize, LoadBalancing) {
+        self.architecture
+            .iter()
+            .filter(|n| n.model.as_deref() == Some(model))
+            .max_by_key(|n| n.effective_replicas())
+            .map(|n| (n.effective_replicas(), n.load_balancing))
+            .unwrap_or((1, LoadBalancing::RoundRobin))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::CacheTtl;
 
     #[test]
     fn test_strip_line_comments() {
@@ -246,6 +1014,59 @@ mod tests {
         assert!(result.contains("\"key\": \"value\""));
     }
 
+    #[test]
+    fn test_parse_toml_and_yaml() {
+        let toml = r#"
+            deadline-ms = 30000
+
+            [models.small]
+            runner = "external"
+            endpoint = "http://127.0.0.1:8080/v1"
+
+            [[architecture]]
+            name = "router"
+            layer = 0
+            model = "small"
+            adapter = "openai-api"
+            output-to = ["output"]
+
+            [[architecture]]
+            name = "output"
+            adapter = "output"
+        "#;
+        let composition = Composition::from_str_as(toml, CompositionFormat::Toml).unwrap();
+        assert_eq!(composition.deadline_ms, Some(30000));
+        assert_eq!(composition.architecture.len(), 2);
+
+        // Models in one document, the architecture in the next
+        let yaml = "models:\n  small:\n    runner: external\n    endpoint: http://127.0.0.1:8080/v1\n---\narchitecture:\n  - {name: router, layer: 0, model: small, adapter: openai-api, output-to: [output]}\n  - {name: output, adapter: output}\n---\n";
+        let from_yaml = Composition::from_str_as(yaml, CompositionFormat::Yaml).unwrap();
+        assert_eq!(from_yaml.models.len(), 1);
+        assert_eq!(from_yaml.architecture[0].name, "router");
+
+        // The same validation as JSON
+        let no_router = "models: {}\narchitecture:\n  - {name: output, adapter: output}\n";
+        assert_eq!(
+            Composition::from_str_as(no_router, CompositionFormat::Yaml).unwrap_err(),
+            CompositionError::NoRouterNode
+        );
+        let twice = "models: {}\n---\nmodels: {}\n";
+        assert!(matches!(
+            parse_composition_as(twice, CompositionFormat::Yaml),
+            Err(CompositionError::ParseError(e)) if e.contains("'models'")
+        ));
+        assert!(parse_composition_as("models = [", CompositionFormat::Toml).is_err());
+
+        assert_eq!(
+            CompositionFormat::from_path(Path::new("chat.yml")),
+            CompositionFormat::Yaml
+        );
+        assert_eq!(
+            CompositionFormat::from_path(Path::new("chat.jsonc")),
+            CompositionFormat::Json
+        );
+    }
+
     #[test]
     fn test_strip_block_comments() {
         let input = r#"{
@@ -412,6 +1233,607 @@ mod tests {
         assert!(matches!(result, Err(CompositionError::NoOutputNode)));
     }
 
+    #[test]
+    fn test_validate_embedding_requires_model() {
+        let json = r#"{
+            "models": {},
+            "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api", "output-to": [1]},
+                {"name": "embedder", "layer": 1, "adapter": "embedding", "output-to": ["output"]},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+
+        let result = Composition::from_str(json);
+        assert_eq!(
+            result.unwrap_err(),
+            CompositionError::EmbeddingWithoutModel("embedder".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_audio_input() {
+        let with_audio = |node: &str| {
+            format!(
+                r#"{{
+                    "models": {{"whisper": {{"runner": "whisper", "source": "ggml-base.bin"}}}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": ["output"]}},
+                        {node},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        assert!(Composition::from_str(&with_audio(
+            r#"{"name": "ears", "model": "whisper", "adapter": "audio-input"}"#
+        ))
+        .is_ok());
+        assert_eq!(
+            Composition::from_str(&with_audio(r#"{"name": "ears", "adapter": "audio-input"}"#))
+                .unwrap_err(),
+            CompositionError::AudioInputWithoutModel("ears".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_audio(
+                r#"{"name": "ears", "layer": 0, "model": "whisper", "adapter": "audio-input", "output-to": [1]}"#
+            ))
+            .unwrap_err(),
+            CompositionError::AudioInputInPipeline("ears".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_guard_configuration() {
+        let with_guard = |guard: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "check", "layer": 1, "adapter": "guard", {guard} "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        assert_eq!(
+            Composition::from_str(&with_guard("")).unwrap_err(),
+            CompositionError::GuardWithoutConfig("check".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_guard(r#""guard": {"on-violation": "fallback"},"#))
+                .unwrap_err(),
+            CompositionError::GuardMissingFallback("check".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_guard(
+                r#""guard": {"on-violation": "fallback", "fallback": "nowhere"},"#
+            ))
+            .unwrap_err(),
+            CompositionError::UndefinedNode("nowhere".to_string())
+        );
+        assert!(matches!(
+            Composition::from_str(&with_guard(r#""guard": {"denylist": ["("]},"#)),
+            Err(CompositionError::InvalidGuardPattern(..))
+        ));
+        assert_eq!(
+            Composition::from_str(&with_guard(r#""guard": {"moderation": true},"#)).unwrap_err(),
+            CompositionError::GuardModerationWithoutModel("check".to_string())
+        );
+        assert!(Composition::from_str(&with_guard(
+            r#""guard": {"denylist": ["(?i)password"], "max-length": 100},"#
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate_aggregator_configuration() {
+        let with_aggregate = |aggregate: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "a", "layer": 1, "adapter": "openai-api", "output-to": ["combine"]}},
+                        {{"name": "combine", "layer": 2, "adapter": "aggregator", {aggregate} "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        assert_eq!(
+            Composition::from_str(&with_aggregate("")).unwrap_err(),
+            CompositionError::AggregatorWithoutConfig("combine".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_aggregate(r#""aggregate": {"strategy": "best-of"},"#))
+                .unwrap_err(),
+            CompositionError::AggregatorJudgeWithoutModel("combine".to_string())
+        );
+        assert!(
+            Composition::from_str(&with_aggregate(r#""aggregate": {"strategy": "vote"},"#)).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_validate_evaluator_configuration() {
+        let with_evaluator = |evaluator: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "writer", "layer": 1, "adapter": "openai-api", "output-to": ["judge"]}},
+                        {{"name": "judge", "layer": 2, "adapter": "evaluator", {evaluator} "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        assert_eq!(
+            Composition::from_str(&with_evaluator("")).unwrap_err(),
+            CompositionError::EvaluatorWithoutConfig("judge".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_evaluator(r#""evaluator": {},"#)).unwrap_err(),
+            CompositionError::EvaluatorWithoutChecks("judge".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_evaluator(
+                r#""evaluator": {"rubric": "Is it polite?"},"#
+            ))
+            .unwrap_err(),
+            CompositionError::EvaluatorRubricWithoutModel("judge".to_string())
+        );
+        assert!(matches!(
+            Composition::from_str(&with_evaluator(
+                r#""evaluator": {"must-not-match": ["("]},"#
+            )),
+            Err(CompositionError::InvalidEvaluatorPattern(..))
+        ));
+        assert_eq!(
+            Composition::from_str(&with_evaluator(
+                r#""evaluator": {"must-match": ["[0-9]"], "threshold": 1.5},"#
+            ))
+            .unwrap_err(),
+            CompositionError::InvalidEvaluatorThreshold("judge".to_string())
+        );
+        assert!(Composition::from_str(&with_evaluator(
+            r#""evaluator": {"must-match": ["(?i)sources:"], "max-retries": 1},"#
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate_embedding_routing() {
+        let with_router = |fields: &str| {
+            format!(
+                r#"{{
+                    "models": {{
+                        "embedder": {{"type": "external", "interface": "openai-api", "url": "http://e/v1"}}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", {fields} "output-to": [1]}},
+                        {{"name": "sales", "layer": 1, "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        assert_eq!(
+            Composition::from_str(&with_router(r#""routing-mode": "embedding","#)).unwrap_err(),
+            CompositionError::EmbeddingRoutingWithoutConfig("router".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_router(
+                r#""routing-mode": "embedding", "embedding-routing": {"model": "missing"},"#
+            ))
+            .unwrap_err(),
+            CompositionError::UndefinedModel("missing".to_string(), "router".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_router(
+                r#""routing-mode": "embedding",
+                   "embedding-routing": {"model": "embedder", "threshold": 2},"#
+            ))
+            .unwrap_err(),
+            CompositionError::InvalidEmbeddingRoutingThreshold("router".to_string())
+        );
+        let composition = Composition::from_str(&with_router(
+            r#""routing-mode": "embedding", "embedding-routing": {"model": "embedder"},"#,
+        ))
+        .unwrap();
+        assert_eq!(composition.embedding_models(), ["embedder"]);
+    }
+
+    #[test]
+    fn test_validate_function_routing() {
+        let with_router = |fields: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "functions": {{
+                        "pick_target": {{"type": "rest", "method": "POST", "url": "http://policy/route"}}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", {fields} "output-to": [1]}},
+                        {{"name": "sales", "layer": 1, "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        assert_eq!(
+            Composition::from_str(&with_router(r#""routing-mode": "function","#)).unwrap_err(),
+            CompositionError::RoutingFunctionWithoutName("router".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_router(
+                r#""routing-mode": "function", "routing-function": "missing","#
+            ))
+            .unwrap_err(),
+            CompositionError::UndefinedRoutingFunction("router".to_string(), "missing".to_string())
+        );
+        assert!(Composition::from_str(&with_router(
+            r#""routing-mode": "function", "routing-function": "pick_target","#
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate_loops() {
+        let with_loop = |fields: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "draft", "layer": 1, "adapter": "openai-api", "output-to": [2]}},
+                        {{"name": "critique", "layer": 2, "adapter": "openai-api", "output-to": [3]}},
+                        {{"name": "revise", "layer": 3, "adapter": "openai-api", {fields} "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        assert_eq!(
+            Composition::from_str(&with_loop(r#""loop-to": "nowhere","#)).unwrap_err(),
+            CompositionError::UndefinedNode("nowhere".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_loop(r#""loop-to": "output","#)).unwrap_err(),
+            CompositionError::InvalidLoopTarget("revise".to_string(), "output".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_loop(r#""loop-to": "critique", "max-iterations": 0,"#))
+                .unwrap_err(),
+            CompositionError::InvalidMaxIterations("revise".to_string())
+        );
+        let composition =
+            Composition::from_str(&with_loop(r#""loop-to": "critique", "max-iterations": 2,"#))
+                .unwrap();
+        assert_eq!(composition.architecture[3].loop_iterations(), 2);
+        assert_eq!(composition.architecture[2].loop_iterations(), 3);
+    }
+
+    #[test]
+    fn test_validate_route_overrides() {
+        let with_overrides = |overrides: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "billing", "layer": 1, "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ],
+                    "route-overrides": {overrides}
+                }}"#
+            )
+        };
+
+        let comp = Composition::from_str(&with_overrides(r#"["billing"]"#)).unwrap();
+        assert_eq!(comp.route_overrides, vec!["billing"]);
+
+        assert_eq!(
+            Composition::from_str(&with_overrides(r#"["nowhere"]"#)).unwrap_err(),
+            CompositionError::UndefinedNode("nowhere".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_overrides(r#"["router"]"#)).unwrap_err(),
+            CompositionError::InvalidRouteOverride("router".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_overrides(r#"["output"]"#)).unwrap_err(),
+            CompositionError::InvalidRouteOverride("output".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_session_config() {
+        let with_sessions = |sessions: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ],
+                    "sessions": {sessions}
+                }}"#
+            )
+        };
+
+        let comp = Composition::from_str(&with_sessions(
+            r#"{"store": "redis", "url": "redis://cache:6379", "max-tokens": 2000}"#,
+        ))
+        .unwrap();
+        let sessions = comp.sessions.unwrap();
+        assert_eq!(sessions.store, SessionStoreKind::Redis);
+        assert_eq!(sessions.max_tokens, 2000);
+        assert_eq!(sessions.ttl_secs, 3600);
+
+        assert_eq!(
+            Composition::from_str(&with_sessions(r#"{"store": "redis"}"#)).unwrap_err(),
+            CompositionError::SessionStoreWithoutUrl
+        );
+    }
+
+    #[test]
+    fn test_parse_header_variables() {
+        let with_vars = |vars: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ],
+                    "header-variables": {vars}
+                }}"#
+            )
+        };
+
+        let comp = Composition::from_str(&with_vars(r#"["tenant", "user_tier"]"#)).unwrap();
+        assert_eq!(comp.header_variables, vec!["tenant", "user_tier"]);
+
+        for bad in ["INITIAL_INPUT", "user-tier", "1st", ""] {
+            assert_eq!(
+                Composition::from_str(&with_vars(&format!(r#"["{}"]"#, bad))).unwrap_err(),
+                CompositionError::InvalidHeaderVariable(bad.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_queue_config() {
+        let with_queue = |queue: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ],
+                    "queue": {queue}
+                }}"#
+            )
+        };
+
+        let comp = Composition::from_str(&with_queue(
+            r#"{"kind": "kafka", "brokers": ["kafka:9092"], "input-topic": "prompts", "output-topic": "results"}"#,
+        ))
+        .unwrap();
+        let queue = comp.queue.unwrap();
+        assert_eq!(queue.kind, QueueKind::Kafka);
+        assert_eq!(queue.input_topic, "prompts");
+        assert_eq!(queue.group, "llmnet");
+        assert_eq!(queue.concurrency, 4);
+
+        assert_eq!(
+            Composition::from_str(&with_queue(
+                r#"{"kind": "nats", "brokers": [], "input-topic": "a", "output-topic": "b"}"#
+            ))
+            .unwrap_err(),
+            CompositionError::QueueWithoutBrokers
+        );
+        assert_eq!(
+            Composition::from_str(&with_queue(
+                r#"{"kind": "nats", "brokers": ["nats://n:4222"], "input-topic": "a", "output-topic": ""}"#
+            ))
+            .unwrap_err(),
+            CompositionError::QueueWithoutTopic
+        );
+    }
+
+    #[test]
+    fn test_parse_runner_replicas() {
+        let with_models = |models: &str, replicas: u32| {
+            format!(
+                r#"{{
+                    "models": {models},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "m", "adapter": "openai-api", "output-to": ["a", "b"]}},
+                        {{"name": "a", "layer": 1, "model": "m", "adapter": "openai-api", "replicas": {replicas}, "load-balancing": "least-connections", "output-to": ["output"]}},
+                        {{"name": "b", "layer": 1, "model": "m", "adapter": "openai-api", "replicas": 2, "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+        let local = r#"{"m": {"runner": "llama-cpp", "source": "/models/m.gguf"}}"#;
+        let external =
+            r#"{"m": {"type": "external", "interface": "openai-api", "url": "http://m"}}"#;
+
+        let comp = Composition::from_str(&with_models(local, 3)).unwrap();
+        assert_eq!(
+            comp.runner_replicas("m"),
+            (3, LoadBalancing::LeastConnections)
+        );
+        assert_eq!(
+            comp.runner_replicas("other"),
+            (1, LoadBalancing::RoundRobin)
+        );
+
+        assert_eq!(
+            Composition::from_str(&with_models(local, 0)).unwrap_err(),
+            CompositionError::InvalidReplicaCount("a".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_models(external, 3)).unwrap_err(),
+            CompositionError::ReplicasWithoutRunner("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_max_concurrent() {
+        let with_limit = |limit: &str| {
+            format!(
+                r#"{{
+                    "models": {{"m": {{"runner": "external", "endpoint": "http://m/v1", {limit}}}}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "m", "adapter": "openai-api"}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        let comp = Composition::from_str(&with_limit(r#""max-concurrent": 4"#)).unwrap();
+        assert_eq!(comp.models["m"].to_config().max_concurrent, Some(4));
+        let comp = Composition::from_str(&with_limit(r#""max_concurrent": 2"#)).unwrap();
+        assert_eq!(comp.models["m"].to_config().max_concurrent, Some(2));
+
+        assert_eq!(
+            Composition::from_str(&with_limit(r#""max-concurrent": 0"#)).unwrap_err(),
+            CompositionError::InvalidMaxConcurrent("m".to_string())
+        );
+    }
+
+    #[test]
+    fn test_model_checksum_and_credentials() {
+        let with_model = |extra: &str| {
+            format!(
+                r#"{{
+                    "secrets": {{"aws": {{"source": "env-file", "path": "~/.aws.env"}}}},
+                    "models": {{"m": {{"runner": "llama-cpp", "source": "s3://models/q4.gguf"{}}}}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "m", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#,
+                extra
+            )
+        };
+        let digest = "a1".repeat(32);
+
+        let comp = Composition::from_str(&with_model(&format!(
+            r#", "sha256": "{}", "credentials": "aws""#,
+            digest
+        )))
+        .unwrap();
+        let config = comp.models["m"].to_config();
+        assert_eq!(config.sha256.as_deref(), Some(digest.as_str()));
+        assert_eq!(config.credentials.as_deref(), Some("aws"));
+
+        assert_eq!(
+            Composition::from_str(&with_model(r#", "sha256": "abc""#)).unwrap_err(),
+            CompositionError::InvalidSha256("m".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&with_model(r#", "credentials": "gcp""#)).unwrap_err(),
+            CompositionError::UndefinedCredentials("m".to_string(), "gcp".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_model_budget() {
+        let with_gpt = |extra: &str| {
+            format!(
+                r#"{{
+                    "models": {{
+                        "gpt": {{"runner": "external", "endpoint": "https://api.openai.com/v1"{}}},
+                        "local": {{"runner": "external", "endpoint": "http://localhost:8080/v1"}}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#,
+                extra
+            )
+        };
+        let cost = r#", "cost": {"per-1k-tokens": 0.01}"#;
+
+        let comp = Composition::from_str(&with_gpt(&format!(
+            r#"{}, "budget": {{"monthly-dollars": 50, "monthly-tokens": 2000000, "fallback": "local"}}"#,
+            cost
+        )))
+        .unwrap();
+        let budget = comp.models["gpt"].to_config().budget.unwrap();
+        assert_eq!(budget.monthly_dollars, Some(50.0));
+        assert_eq!(budget.monthly_tokens, Some(2_000_000));
+        assert_eq!(budget.fallback.as_deref(), Some("local"));
+
+        // A token budget needs no price
+        assert!(
+            Composition::from_str(&with_gpt(r#", "budget": {"monthly-tokens": 1000}"#)).is_ok()
+        );
+
+        let err = |extra: &str| Composition::from_str(&with_gpt(extra)).unwrap_err();
+        assert_eq!(
+            err(&format!(r#"{}, "budget": {{}}"#, cost)),
+            CompositionError::InvalidBudget("gpt".to_string())
+        );
+        assert_eq!(
+            err(&format!(r#"{}, "budget": {{"monthly-dollars": 0}}"#, cost)),
+            CompositionError::InvalidBudget("gpt".to_string())
+        );
+        assert_eq!(
+            err(r#", "budget": {"monthly-dollars": 50}"#),
+            CompositionError::BudgetWithoutCost("gpt".to_string())
+        );
+        assert_eq!(
+            err(r#", "budget": {"monthly-tokens": 1000, "fallback": "gemini"}"#),
+            CompositionError::UndefinedBudgetFallback("gpt".to_string(), "gemini".to_string())
+        );
+    }
+
+    #[test]
+    fn test_prompt_cache_for() {
+        let comp = Composition::from_str(
+            r#"{
+                "models": {"m": {"type": "external", "interface": "openai-api", "url": "http://a/v1"}},
+                "prompt-cache": {"ttl": "1h"},
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "m", "adapter": "openai-api", "output-to": [1]},
+                    {"name": "chat", "layer": 1, "model": "m", "adapter": "openai-api", "prompt-cache": {"history": true}, "output-to": ["output"]},
+                    {"name": "plain", "layer": 1, "model": "m", "adapter": "openai-api", "prompt-cache": {"system": false}, "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let cache = |node: &str| comp.prompt_cache_for(comp.node_by_name(node).unwrap());
+
+        let router = cache("router").unwrap();
+        assert!(router.system && !router.history);
+        assert_eq!(router.ttl, Some(CacheTtl::OneHour));
+        // A node's own setting replaces the default as a whole
+        let chat = cache("chat").unwrap();
+        assert!(chat.system && chat.history);
+        assert_eq!(chat.ttl, None);
+        assert!(cache("plain").is_none());
+    }
+
     #[test]
     fn test_nodes_in_layer() {
         let json = r#"{
@@ -515,6 +1937,135 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_request_limits() {
+        let json = |limits: &str| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "request-limits": {limits},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api"}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        let comp =
+            Composition::from_str(&json(r#"{"max-messages": 20, "max-prompt-tokens": 2000}"#))
+                .unwrap();
+        let limits = comp.request_limits.unwrap();
+        assert_eq!(limits.max_messages, Some(20));
+        assert_eq!(limits.max_prompt_tokens, Some(2000));
+        assert_eq!(limits.max_body_bytes(), DEFAULT_MAX_BODY_BYTES);
+
+        assert_eq!(
+            Composition::from_str(&json(r#"{"max-body-bytes": 0}"#)).unwrap_err(),
+            CompositionError::InvalidRequestLimit("max-body-bytes".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_hedge_and_retry_budget() {
+        let json = |hedge: &str, budget: &str| {
+            format!(
+                r#"{{
+                    "models": {{
+                        "fast": {{"type": "external", "interface": "openai-api", "url": "http://a"}},
+                        "backup": {{"type": "external", "interface": "openai-api", "url": "http://b"}}
+                    }},
+                    "retry-budget": {budget},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "fast", "adapter": "openai-api", "output-to": ["handler"]}},
+                        {{"name": "handler", "layer": 1, "model": "fast", "adapter": "openai-api", "hedge": {hedge}}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        let comp =
+            Composition::from_str(&json(r#"{"fallback": "backup"}"#, r#"{"ratio": 0.2}"#)).unwrap();
+        let hedge = comp.node_by_name("handler").unwrap().hedge.clone().unwrap();
+        assert_eq!(hedge.fallback, "backup");
+        assert_eq!(hedge.percentile, 95.0);
+        assert_eq!(hedge.min_delay_ms, 1000);
+        let budget = comp.retry_budget.unwrap();
+        assert_eq!((budget.ratio, budget.burst), (0.2, 10));
+
+        assert_eq!(
+            Composition::from_str(&json(r#"{"fallback": "missing"}"#, "null")).unwrap_err(),
+            CompositionError::UndefinedModel("missing".to_string(), "handler".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&json(r#"{"fallback": "backup", "percentile": 0}"#, "null"))
+                .unwrap_err(),
+            CompositionError::InvalidHedgePercentile("handler".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&json(r#"{"fallback": "backup"}"#, r#"{"ratio": 2}"#))
+                .unwrap_err(),
+            CompositionError::InvalidRetryBudget
+        );
+    }
+
+    #[test]
+    fn test_validate_hook_timeout() {
+        let json = |timeout: u64| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "functions": {{"log": {{"type": "rest", "url": "http://a/log"}}}},
+                    "architecture": [
+                        {{
+                            "name": "router",
+                            "layer": 0,
+                            "adapter": "openai-api",
+                            "hooks": {{"post": [{{"function": "log", "timeout_ms": {timeout}}}]}}
+                        }},
+                        {{"name": "final-output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        assert_eq!(
+            Composition::from_str(&json(0)).unwrap_err(),
+            CompositionError::InvalidHookTimeout("log".to_string(), "router".to_string())
+        );
+        let comp = Composition::from_str(&json(250)).unwrap();
+        assert_eq!(comp.architecture[0].hooks.post[0].timeout_ms(), 250);
+    }
+
+    #[test]
+    fn test_validate_timeouts() {
+        let json = |deadline: u64, timeout: u64| {
+            format!(
+                r#"{{
+                    "models": {{}},
+                    "deadline-ms": {deadline},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "adapter": "openai-api", "timeout-ms": {timeout}}},
+                        {{"name": "final-output", "adapter": "output"}}
+                    ]
+                }}"#
+            )
+        };
+
+        let comp = Composition::from_str(&json(5000, 2000)).unwrap();
+        assert_eq!(comp.deadline_ms, Some(5000));
+        assert_eq!(comp.architecture[0].timeout_ms, Some(2000));
+        assert_eq!(
+            Composition::from_str(&json(5000, 0)).unwrap_err(),
+            CompositionError::InvalidNodeTimeout("router".to_string())
+        );
+        assert_eq!(
+            Composition::from_str(&json(0, 2000)).unwrap_err(),
+            CompositionError::InvalidDeadline
+        );
+    }
+
     #[test]
     fn test_valid_hook_function_reference() {
         let json = r#"{
@@ -3,6 +3,7 @@
 //! This module provides reusable function definitions that can be called from hooks:
 //! - REST: HTTP requests
 //! - Shell: Command execution
+//! - Command: Sandboxed local subprocess
 //! - WebSocket: Real-time messaging
 //! - gRPC: RPC calls
 
@@ -13,6 +14,7 @@ use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
 
 use super::secrets::SecretsManager;
@@ -62,6 +64,32 @@ pub enum FunctionType {
         #[serde(default = "default_timeout")]
         timeout: u64,
     },
+    /// Local subprocess with a restricted environment
+    ///
+    /// Unlike `shell`, the child starts with an empty environment (only
+    /// `env-allowlist` variables are inherited), can be fed the hook input on
+    /// stdin, and is killed if it overruns its timeout or output limit.
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        /// Variables set explicitly (secrets and variables are substituted)
+        #[serde(default)]
+        env: HashMap<String, String>,
+        /// Worker environment variables passed through to the child
+        #[serde(rename = "env-allowlist", default)]
+        env_allowlist: Vec<String>,
+        #[serde(rename = "working-dir", default)]
+        working_dir: Option<String>,
+        /// Text written to the child's stdin, e.g. "$INPUT"
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stdin: Option<String>,
+        #[serde(default = "default_timeout")]
+        timeout: u64,
+        /// Larger stdout fails the call rather than flooding the pipeline
+        #[serde(rename = "max-output-bytes", default = "default_max_output_bytes")]
+        max_output_bytes: usize,
+    },
     /// WebSocket message
     Websocket {
         url: String,
@@ -86,6 +114,10 @@ fn default_timeout() -> u64 {
     30
 }
 
+fn default_max_output_bytes() -> usize {
+    1024 * 1024
+}
+
 /// Result of function execution
 #[derive(Debug, Clone)]
 pub struct FunctionResult {
@@ -124,6 +156,9 @@ pub enum FunctionError {
     #[error("Shell command failed: {0}")]
     ShellError(String),
 
+    #[error("Command failed: {0}")]
+    CommandError(String),
+
     #[error("WebSocket error: {0}")]
     WebsocketError(String),
 
@@ -192,10 +227,43 @@ pub fn substitute_variables_in_value(value: &Value, variables: &HashMap<String,
     }
 }
 
+/// Environment for a sandboxed command: allowlisted variables from the
+/// worker's environment, overridden by the explicitly configured ones
+pub fn command_env(
+    allowlist: &[String],
+    explicit: HashMap<String, String>,
+    inherited: impl IntoIterator<Item = (String, String)>,
+) -> HashMap<String, String> {
+    let mut env: HashMap<String, String> = inherited
+        .into_iter()
+        .filter(|(key, _)| allowlist.contains(key))
+        .collect();
+    env.extend(explicit);
+    env
+}
+
+/// Interpret a command's stdout: JSON if it parses, otherwise a string
+pub fn parse_command_output(stdout: &[u8]) -> Option<Value> {
+    let stdout = String::from_utf8_lossy(stdout).trim().to_string();
+    if stdout.is_empty() {
+        return None;
+    }
+    Some(serde_json::from_str::<Value>(&stdout).unwrap_or(Value::String(stdout)))
+}
+
 // ============================================================================
 // SBIO: I/O - FunctionExecutor
 // ============================================================================
 
+/// Restrictions applied to a Command function's subprocess
+struct CommandSandbox<'a> {
+    env_allowlist: &'a [String],
+    working_dir: Option<&'a str>,
+    stdin: Option<&'a str>,
+    timeout: u64,
+    max_output_bytes: usize,
+}
+
 /// Executor for running functions with variable and secret substitution
 pub struct FunctionExecutor {
     client: reqwest::Client,
@@ -239,6 +307,26 @@ impl FunctionExecutor {
                 self.execute_shell(command, args, env, cwd.as_deref(), *timeout, variables)
                     .await
             }
+            FunctionType::Command {
+                command,
+                args,
+                env,
+                env_allowlist,
+                working_dir,
+                stdin,
+                timeout,
+                max_output_bytes,
+            } => {
+                let sandbox = CommandSandbox {
+                    env_allowlist,
+                    working_dir: working_dir.as_deref(),
+                    stdin: stdin.as_deref(),
+                    timeout: *timeout,
+                    max_output_bytes: *max_output_bytes,
+                };
+                self.execute_command(command, args, env, &sandbox, variables)
+                    .await
+            }
             FunctionType::Websocket {
                 url,
                 message,
@@ -374,17 +462,114 @@ impl FunctionExecutor {
             )));
         }
 
-        // Return stdout as JSON string value
-        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if stdout.is_empty() {
-            Ok(None)
-        } else {
-            // Try to parse as JSON, fall back to string
-            match serde_json::from_str::<Value>(&stdout) {
-                Ok(v) => Ok(Some(v)),
-                Err(_) => Ok(Some(Value::String(stdout))),
+        // Return stdout as JSON, falling back to a string
+        Ok(parse_command_output(&output.stdout))
+    }
+
+    /// Execute a sandboxed Command function
+    async fn execute_command(
+        &self,
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        sandbox: &CommandSandbox<'_>,
+        variables: &HashMap<String, Value>,
+    ) -> Result<Option<Value>, FunctionError> {
+        let command = self.substitute_all(command, variables);
+        let args: Vec<String> = args
+            .iter()
+            .map(|a| self.substitute_all(a, variables))
+            .collect();
+        let env = command_env(
+            sandbox.env_allowlist,
+            self.substitute_all_map(env, variables),
+            std::env::vars(),
+        );
+
+        let mut cmd = Command::new(&command);
+        cmd.args(&args)
+            .env_clear()
+            .envs(&env)
+            .stdin(if sandbox.stdin.is_some() {
+                std::process::Stdio::piped()
+            } else {
+                std::process::Stdio::null()
+            })
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            // Dropping the child on timeout must not leave it running
+            .kill_on_drop(true);
+
+        if let Some(dir) = sandbox.working_dir {
+            cmd.current_dir(self.substitute_all(dir, variables));
+        }
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| FunctionError::CommandError(format!("{}: {}", command, e)))?;
+
+        if let (Some(template), Some(mut pipe)) = (sandbox.stdin, child.stdin.take()) {
+            let input = self.substitute_all(template, variables);
+            // Written alongside the run, so a child that never reads its input
+            // can't block past the timeout; one that exits without reading it
+            // is not an error
+            tokio::spawn(async move {
+                let _ = pipe.write_all(input.as_bytes()).await;
+            });
+        }
+
+        let limit = sandbox.max_output_bytes;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut stderr = child.stderr.take().expect("stderr is piped");
+        // Keep the start of stderr for the error message and drain the rest,
+        // so a chatty child doesn't stall on a full pipe
+        let stderr = tokio::spawn(async move {
+            let mut kept = Vec::new();
+            let _ = (&mut stderr)
+                .take(limit as u64)
+                .read_to_end(&mut kept)
+                .await;
+            let _ = tokio::io::copy(&mut stderr, &mut tokio::io::sink()).await;
+            kept
+        });
+
+        let run = async {
+            let mut output = Vec::new();
+            stdout
+                .take(limit as u64 + 1)
+                .read_to_end(&mut output)
+                .await
+                .map_err(|e| FunctionError::CommandError(e.to_string()))?;
+            if output.len() > limit {
+                // Stop the child instead of waiting on output that is thrown away
+                let _ = child.kill().await;
+                return Err(FunctionError::CommandError(format!(
+                    "Output exceeds the {} byte limit",
+                    limit
+                )));
             }
+
+            let status = child
+                .wait()
+                .await
+                .map_err(|e| FunctionError::CommandError(e.to_string()))?;
+            Ok((status, output))
+        };
+
+        let (status, output) = tokio::time::timeout(Duration::from_secs(sandbox.timeout), run)
+            .await
+            .map_err(|_| FunctionError::Timeout(sandbox.timeout))??;
+
+        if !status.success() {
+            let stderr = stderr.await.unwrap_or_default();
+            return Err(FunctionError::CommandError(format!(
+                "Exit code {}: {}",
+                status.code().unwrap_or(-1),
+                String::from_utf8_lossy(&stderr).trim()
+            )));
         }
+
+        Ok(parse_command_output(&output))
     }
 
     /// Execute WebSocket function
@@ -567,6 +752,153 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_function_type_command_deserialize() {
+        let json = r#"{
+            "type": "command",
+            "command": "./enrich.sh",
+            "env-allowlist": ["PATH", "HOME"],
+            "working-dir": "/opt/hooks",
+            "stdin": "$OUTPUT",
+            "timeout": 5
+        }"#;
+
+        let func: FunctionType = serde_json::from_str(json).unwrap();
+        match func {
+            FunctionType::Command {
+                command,
+                env_allowlist,
+                working_dir,
+                stdin,
+                timeout,
+                max_output_bytes,
+                ..
+            } => {
+                assert_eq!(command, "./enrich.sh");
+                assert_eq!(env_allowlist, vec!["PATH", "HOME"]);
+                assert_eq!(working_dir.as_deref(), Some("/opt/hooks"));
+                assert_eq!(stdin.as_deref(), Some("$OUTPUT"));
+                assert_eq!(timeout, 5);
+                assert_eq!(max_output_bytes, 1024 * 1024);
+            }
+            _ => panic!("Expected Command function"),
+        }
+    }
+
+    #[test]
+    fn test_command_env_only_passes_allowlist() {
+        let inherited = vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("AWS_SECRET_ACCESS_KEY".to_string(), "hunter2".to_string()),
+            ("LANG".to_string(), "C".to_string()),
+        ];
+        let explicit = HashMap::from([("LANG".to_string(), "en_US.UTF-8".to_string())]);
+
+        let env = command_env(&["PATH".to_string()], explicit, inherited);
+        assert_eq!(env.len(), 2);
+        assert_eq!(env["PATH"], "/usr/bin");
+        assert_eq!(env["LANG"], "en_US.UTF-8");
+        assert!(!env.contains_key("AWS_SECRET_ACCESS_KEY"));
+    }
+
+    #[test]
+    fn test_parse_command_output() {
+        assert_eq!(parse_command_output(b"  \n"), None);
+        assert_eq!(
+            parse_command_output(b"{\"ok\": true}\n"),
+            Some(serde_json::json!({"ok": true}))
+        );
+        assert_eq!(
+            parse_command_output(b"plain text\n"),
+            Some(Value::String("plain text".to_string()))
+        );
+    }
+
+    #[cfg(unix)]
+    fn command(script: &str, stdin: Option<&str>, timeout: u64) -> FunctionType {
+        FunctionType::Command {
+            command: "/bin/sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: HashMap::from([("GREETING".to_string(), "$NODE".to_string())]),
+            env_allowlist: Vec::new(),
+            working_dir: Some("/".to_string()),
+            stdin: stdin.map(String::from),
+            timeout,
+            max_output_bytes: 64,
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_command_sandbox() {
+        let executor = FunctionExecutor::new(Arc::new(SecretsManager::new()));
+        let vars = HashMap::from([
+            ("NODE".to_string(), Value::String("router".to_string())),
+            ("OUTPUT".to_string(), Value::String("draft".to_string())),
+        ]);
+
+        // Explicit env, stdin and working dir reach the child; HOME does not
+        let result = executor
+            .execute(
+                &command(
+                    r#"read x; echo "$GREETING $x $(pwd) ${HOME:-none}""#,
+                    Some("$OUTPUT"),
+                    5,
+                ),
+                &vars,
+            )
+            .await
+            .unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(
+            result.output,
+            Some(Value::String("router draft / none".to_string()))
+        );
+
+        let result = executor
+            .execute(&command("echo nope >&2; exit 3", None, 5), &vars)
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Exit code 3: nope"));
+
+        let result = executor
+            .execute(
+                &command("head -c 100 /dev/zero | tr '\\0' a", None, 5),
+                &vars,
+            )
+            .await
+            .unwrap();
+        assert!(result.error.unwrap().contains("byte limit"));
+
+        // Endless output is cut off at the limit rather than run to the timeout
+        let result = executor
+            .execute(&command("yes", None, 30), &vars)
+            .await
+            .unwrap();
+        assert!(result.error.unwrap().contains("byte limit"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_command_timeout() {
+        let executor = FunctionExecutor::new(Arc::new(SecretsManager::new()));
+        let result = executor
+            .execute(&command("sleep 5", None, 0), &HashMap::new())
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert_eq!(result.error.unwrap(), "Timeout after 0s");
+
+        // A child that never reads a large input still times out
+        let vars = HashMap::from([("BIG".to_string(), Value::String("x".repeat(1 << 20)))]);
+        let result = executor
+            .execute(&command("sleep 5", Some("$BIG"), 1), &vars)
+            .await
+            .unwrap();
+        assert_eq!(result.error.unwrap(), "Timeout after 1s");
+    }
+
     #[test]
     fn test_function_type_websocket_deserialize() {
         let json = r#"{
@@ -4,20 +4,31 @@ pub mod functions;
 pub mod models;
 pub mod secrets;
 pub mod validation;
+pub mod values;
 
 pub use architecture::{
-    ArchitectureNode, FailureAction, HookConfig, HookMode, NodeHooks, OutputTarget,
+    AggregateConfig, AggregateStrategy, ArchitectureNode, CacheTtl, EmbeddingRoutingConfig,
+    EvaluatorConfig, FailureAction, GuardAction, GuardConfig, HandoffConfig, HedgeConfig,
+    HookConfig, HookMode, LoadBalancing, NodeHooks, OutputTarget, PromptCacheConfig,
+    RetrieverConfig, RoutingCacheConfig, RoutingMode, RoutingPolicy, VectorStoreKind,
+    ADAPTER_TYPES,
 };
 pub use composition::{
-    parse_composition, strip_jsonc_comments, validate_composition, Composition, CompositionError,
+    parse_composition, parse_composition_as, strip_jsonc_comments, validate_composition,
+    Composition, CompositionError, CompositionFormat, QueueConfig, QueueKind, RequestLimits,
+    RetryBudgetConfig, SessionConfig, SessionStoreKind, DEFAULT_MAX_BODY_BYTES,
 };
 pub use functions::{FunctionError, FunctionExecutor, FunctionResult, FunctionType, HttpMethod};
-pub use models::{DockerModel, ExternalModel, HuggingfaceModel, ModelDefinition};
+pub use models::{
+    DockerModel, ExternalModel, HuggingfaceModel, ModelBudget, ModelCost, ModelDefinition,
+    RunnerType, WarmUpConfig,
+};
 pub use secrets::{SecretError, SecretSource, SecretsManager};
 pub use validation::{
-    known_devices, validate_model_for_device, validate_models, DeviceProfile, ValidationMessage,
-    ValidationResult, ValidationSeverity,
+    detect_device_profile, known_devices, validate_gguf_for_device, validate_model_for_device,
+    validate_models, DeviceProfile, ValidationMessage, ValidationResult, ValidationSeverity,
 };
+pub use values::{load_values, render_template, ValuesError};
 
 use std::path::Path;
 use thiserror::Error;
@@ -30,17 +41,30 @@ pub enum ConfigError {
 
     #[error("Composition error: {0}")]
     CompositionError(#[from] CompositionError),
+
+    #[error("Values error: {0}")]
+    ValuesError(#[from] ValuesError),
 }
 
 // ============================================================================
 // SBIO: I/O wrapper - thin layer over pure functions
 // ============================================================================
 
-/// Load and parse a composition file from disk.
+/// Load and parse a composition file from disk, in the format its extension
+/// names (JSON, TOML or YAML).
 /// This is the I/O boundary - it reads the file and delegates to pure parsing functions.
 pub fn load_composition_file(path: &Path) -> Result<Composition, ConfigError> {
+    load_composition_file_with_values(path, &serde_json::Value::Null)
+}
+
+/// Load a composition file, rendering `{{ .values.x }}` placeholders first.
+pub fn load_composition_file_with_values(
+    path: &Path,
+    values: &serde_json::Value,
+) -> Result<Composition, ConfigError> {
     let content = std::fs::read_to_string(path)?;
-    let composition = Composition::from_str(&content)?;
+    let rendered = render_template(&content, values)?;
+    let composition = Composition::from_str_as(&rendered, CompositionFormat::from_path(path))?;
     Ok(composition)
 }
 
@@ -71,6 +95,53 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_load_composition_file_with_values() {
+        let content = r#"{
+            "models": {
+                "router-model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "{{ .values.router.url }}"
+                }
+            },
+            "architecture": [
+                {"name": "router", "layer": 0, "model": "router-model", "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+        let file = create_temp_file(content);
+
+        let values = serde_json::json!({"router": {"url": "http://gpu-1:8080"}});
+        let composition = load_composition_file_with_values(file.path(), &values).unwrap();
+        let router = composition.models.get("router-model").unwrap();
+        assert_eq!(
+            router.to_config().endpoint.as_deref(),
+            Some("http://gpu-1:8080")
+        );
+
+        let result = load_composition_file(file.path());
+        assert!(matches!(result, Err(ConfigError::ValuesError(_))));
+    }
+
+    #[test]
+    fn test_load_composition_file_by_extension() {
+        let content = "[models]\n\n[[architecture]]\nname = \"router\"\nlayer = 0\nadapter = \"openai-api\"\n\n[[architecture]]\nname = \"output\"\nadapter = \"output\"\n";
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        let composition = load_composition_file(file.path()).unwrap();
+        assert_eq!(composition.architecture.len(), 2);
+
+        // Read as JSON without the extension
+        let file = create_temp_file(content);
+        assert!(matches!(
+            load_composition_file(file.path()),
+            Err(ConfigError::CompositionError(CompositionError::ParseError(
+                _
+            )))
+        ));
+    }
+
     #[test]
     fn test_load_nonexistent_file() {
         let result = load_composition_file(Path::new("/nonexistent/file.json"));
@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::client::SafetySetting;
 use crate::runtime::docker::DockerConfig;
 
 /// Runner type for model execution
@@ -18,11 +19,17 @@ pub enum RunnerType {
     Vllm,
     /// llama.cpp local runner
     LlamaCpp,
+    /// A llamafile: one executable with llama.cpp and the weights built in
+    Llamafile,
     /// Docker-based runner
     Docker,
     /// TensorRT-LLM runner for NVIDIA Jetson and GPU edge devices
     #[serde(rename = "tensorrt-llm")]
     TensorRtLlm,
+    /// HuggingFace text-generation-inference, launched via Docker
+    Tgi,
+    /// Speech-to-text with whisper.cpp's server, or faster-whisper via Docker
+    Whisper,
 }
 
 impl RunnerType {
@@ -33,8 +40,26 @@ impl RunnerType {
             RunnerType::Ollama => Some(11434),
             RunnerType::Vllm => Some(8000),
             RunnerType::LlamaCpp => Some(8080),
+            RunnerType::Llamafile => Some(8080),
             RunnerType::Docker => None,
             RunnerType::TensorRtLlm => Some(8000),
+            RunnerType::Tgi => Some(3000),
+            RunnerType::Whisper => Some(8178),
+        }
+    }
+
+    /// Get the runner type name as used in configuration files
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunnerType::External => "external",
+            RunnerType::Ollama => "ollama",
+            RunnerType::Vllm => "vllm",
+            RunnerType::LlamaCpp => "llama-cpp",
+            RunnerType::Llamafile => "llamafile",
+            RunnerType::Docker => "docker",
+            RunnerType::TensorRtLlm => "tensorrt-llm",
+            RunnerType::Tgi => "tgi",
+            RunnerType::Whisper => "whisper",
         }
     }
 
@@ -42,7 +67,13 @@ impl RunnerType {
     pub fn is_local_runner(&self) -> bool {
         matches!(
             self,
-            RunnerType::Ollama | RunnerType::Vllm | RunnerType::LlamaCpp | RunnerType::TensorRtLlm
+            RunnerType::Ollama
+                | RunnerType::Vllm
+                | RunnerType::LlamaCpp
+                | RunnerType::Llamafile
+                | RunnerType::TensorRtLlm
+                | RunnerType::Tgi
+                | RunnerType::Whisper
         )
     }
 }
@@ -50,29 +81,43 @@ impl RunnerType {
 /// Unified model configuration
 ///
 /// This structure supports all model types through a common interface:
-/// - `runner`: The execution backend (external, ollama, vllm, llama-cpp, docker)
-/// - `interface`: The API protocol (openai-api)
+/// - `runner`: The execution backend (external, ollama, vllm, llama-cpp, llamafile,
+///   docker, tgi, whisper)
+/// - `interface`: The API protocol (openai-api, gemini)
 /// - `source`: Model file, URL, HuggingFace repo, or model name
 /// - `endpoint`: Explicit endpoint URL (for external runners)
 /// - `parameters`: Runner-specific parameters
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct ModelConfig {
-    /// Runner type: external, ollama, vllm, llama-cpp, docker
+    /// Runner type: external, ollama, vllm, llama-cpp, llamafile, docker, tgi,
+    /// whisper
     #[serde(default)]
     pub runner: RunnerType,
 
-    /// API interface: openai-api (default)
+    /// API interface: openai-api (default) or gemini
     #[serde(default = "default_interface")]
     pub interface: String,
 
     /// Model source: URL, local path, HF repo, or model name
     /// - External: not used (use endpoint instead)
     /// - Ollama: model name (e.g., "tinyllama:1.1b") or Modelfile path
-    /// - vLLM: HuggingFace repo (e.g., "meta-llama/Llama-2-7b-hf")
+    /// - vLLM, TGI: HuggingFace repo (e.g., "meta-llama/Llama-2-7b-hf")
     /// - llama.cpp: GGUF file path or URL
+    /// - llamafile: llamafile path or URL
+    /// - Whisper: ggml model file path or URL, or a HuggingFace repo for
+    ///   faster-whisper
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
 
+    /// SHA256 digest the file fetched from `source` must have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    /// Secret in the composition's `secrets` holding the credentials for
+    /// `s3://` and `gs://` sources
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<String>,
+
     /// Endpoint URL (required for external, auto-generated for local runners)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub endpoint: Option<String>,
@@ -88,8 +133,137 @@ pub struct ModelConfig {
     /// Docker configuration (for runner: "docker")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub docker: Option<DockerConfig>,
+
+    /// Whether the model accepts OpenAI tool definitions (default depends on
+    /// the runner, see [`ModelConfig::supports_tools`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<bool>,
+
+    /// Whether the model accepts image input (default: guessed from the
+    /// source, see [`ModelConfig::supports_vision`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vision: Option<bool>,
+
+    /// Whether the model takes Anthropic-style `cache_control` markers
+    /// (default: guessed from the source, see
+    /// [`ModelConfig::supports_prompt_caching`])
+    #[serde(rename = "prompt-caching", skip_serializing_if = "Option::is_none")]
+    pub prompt_caching: Option<bool>,
+
+    /// Most calls a worker sends the model at once; further calls wait
+    /// (default: no limit)
+    #[serde(
+        rename = "max-concurrent",
+        alias = "max_concurrent",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_concurrent: Option<usize>,
+
+    /// What a call to the model costs, for cost-aware routing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<ModelCost>,
+
+    /// Most each namespace may spend on the model a month
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<ModelBudget>,
+
+    /// Prompts sent to a local runner once it's ready, so the first real
+    /// request doesn't wait for the model to load
+    #[serde(rename = "warm-up", skip_serializing_if = "Option::is_none")]
+    pub warm_up: Option<WarmUpConfig>,
+
+    /// Environment variables for the runner, set by the pipeline the model
+    /// is deployed in rather than the composition
+    #[serde(skip)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Price and speed of a model, weighed by routers with a `routing-policy`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct ModelCost {
+    /// Dollars per 1K tokens
+    #[serde(rename = "per-1k-tokens", skip_serializing_if = "Option::is_none")]
+    pub per_1k_tokens: Option<f64>,
+
+    /// Expected time to answer, in milliseconds
+    #[serde(rename = "latency-ms", skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+/// Monthly spending limit of a model, kept per namespace
+///
+/// Once a namespace has spent either limit in a calendar month (UTC), calls
+/// to the model go to `fallback`, or are rejected without one.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ModelBudget {
+    /// Most dollars to spend a month, priced with `cost.per-1k-tokens`
+    #[serde(rename = "monthly-dollars", skip_serializing_if = "Option::is_none")]
+    pub monthly_dollars: Option<f64>,
+
+    /// Most tokens to spend a month, prompts and completions together
+    #[serde(rename = "monthly-tokens", skip_serializing_if = "Option::is_none")]
+    pub monthly_tokens: Option<u64>,
+
+    /// Model calls go to once the budget is spent, usually a local one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<String>,
+}
+
+/// Warm-up requests sent to a runner after it starts
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WarmUpConfig {
+    /// Requests to send, one after the other
+    #[serde(default = "default_warm_up_requests")]
+    pub requests: u32,
+
+    /// Prompt of each request
+    #[serde(default = "default_warm_up_prompt")]
+    pub prompt: String,
+
+    /// Most tokens each request may generate
+    #[serde(rename = "max-tokens", default = "default_warm_up_max_tokens")]
+    pub max_tokens: u32,
+}
+
+impl Default for WarmUpConfig {
+    fn default() -> Self {
+        Self {
+            requests: default_warm_up_requests(),
+            prompt: default_warm_up_prompt(),
+            max_tokens: default_warm_up_max_tokens(),
+        }
+    }
 }
 
+fn default_warm_up_requests() -> u32 {
+    1
+}
+
+fn default_warm_up_prompt() -> String {
+    "Hello".to_string()
+}
+
+fn default_warm_up_max_tokens() -> u32 {
+    8
+}
+
+/// Substrings of model names that usually mean the model accepts images
+const VISION_MODEL_HINTS: &[&str] = &[
+    "vision",
+    "llava",
+    "-vl",
+    "pixtral",
+    "moondream",
+    "minicpm-v",
+    "gpt-4o",
+    "gemma-3",
+    "gemma3",
+];
+
+/// Substrings of model sources and endpoints that mean the model takes
+/// `cache_control` markers
+const PROMPT_CACHING_HINTS: &[&str] = &["claude", "anthropic"];
+
 fn default_interface() -> String {
     "openai-api".to_string()
 }
@@ -104,6 +278,16 @@ impl Default for ModelConfig {
             api_key: None,
             parameters: HashMap::new(),
             docker: None,
+            tools: None,
+            vision: None,
+            prompt_caching: None,
+            cost: None,
+            budget: None,
+            warm_up: None,
+            max_concurrent: None,
+            sha256: None,
+            credentials: None,
+            env: BTreeMap::new(),
         }
     }
 }
@@ -164,6 +348,33 @@ impl ModelConfig {
         }
     }
 
+    /// Create a new llamafile model configuration
+    pub fn llamafile(source: impl Into<String>) -> Self {
+        Self {
+            runner: RunnerType::Llamafile,
+            source: Some(source.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new text-generation-inference model configuration
+    pub fn tgi(source: impl Into<String>) -> Self {
+        Self {
+            runner: RunnerType::Tgi,
+            source: Some(source.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Create a new speech-to-text model configuration
+    pub fn whisper(source: impl Into<String>) -> Self {
+        Self {
+            runner: RunnerType::Whisper,
+            source: Some(source.into()),
+            ..Default::default()
+        }
+    }
+
     /// Add Docker configuration
     pub fn with_docker(mut self, docker_config: DockerConfig) -> Self {
         self.docker = Some(docker_config);
@@ -176,6 +387,12 @@ impl ModelConfig {
         self
     }
 
+    /// Cap the calls sent to the model at once
+    pub fn with_max_concurrent(mut self, max: usize) -> Self {
+        self.max_concurrent = Some(max);
+        self
+    }
+
     /// Add parameters
     pub fn with_parameters(mut self, params: HashMap<String, Value>) -> Self {
         self.parameters = params;
@@ -188,6 +405,26 @@ impl ModelConfig {
         self
     }
 
+    /// Configure the runner to serve embeddings
+    ///
+    /// llama.cpp only exposes `/v1/embeddings` with `--embedding`, and vLLM
+    /// needs the embed task. Ollama serves embeddings out of the box.
+    pub fn for_embeddings(mut self) -> Self {
+        match self.runner {
+            RunnerType::LlamaCpp => {
+                self.parameters
+                    .insert("embedding".to_string(), Value::Bool(true));
+            }
+            RunnerType::Vllm => {
+                self.parameters
+                    .entry("task".to_string())
+                    .or_insert_with(|| Value::String("embed".to_string()));
+            }
+            _ => {}
+        }
+        self
+    }
+
     /// Get the effective endpoint URL
     ///
     /// For external runners, returns the configured endpoint.
@@ -204,21 +441,73 @@ impl ModelConfig {
             RunnerType::Ollama => format!("http://{}:{}/v1", host, port),
             RunnerType::Vllm => format!("http://{}:{}/v1", host, port),
             RunnerType::LlamaCpp => format!("http://{}:{}/v1", host, port),
+            RunnerType::Llamafile => format!("http://{}:{}/v1", host, port),
             RunnerType::Docker => return None,
             RunnerType::TensorRtLlm => format!("http://{}:{}/v1", host, port),
+            RunnerType::Tgi => format!("http://{}:{}/v1", host, port),
+            RunnerType::Whisper => format!("http://{}:{}/v1", host, port),
+        })
+    }
+
+    /// Whether tool definitions from clients should be forwarded to this model
+    ///
+    /// An explicit `tools` setting wins. Otherwise llama.cpp and llamafile
+    /// only handle tools when started with `--jinja`, and TensorRT-LLM's
+    /// OpenAI server and Whisper do not support them; the other runners do.
+    pub fn supports_tools(&self) -> bool {
+        self.tools.unwrap_or(match self.runner {
+            RunnerType::LlamaCpp | RunnerType::Llamafile => {
+                self.parameters.get("jinja") == Some(&Value::Bool(true))
+            }
+            RunnerType::TensorRtLlm | RunnerType::Whisper => false,
+            _ => true,
         })
     }
 
+    /// Whether image parts of messages should be forwarded to this model
+    ///
+    /// An explicit `vision` setting wins. Otherwise Gemini models take
+    /// images and the rest are guessed from the model source, so other
+    /// external models need `vision: true` to get images.
+    pub fn supports_vision(&self) -> bool {
+        self.vision.unwrap_or_else(|| {
+            let source = self.source.as_deref().unwrap_or_default().to_lowercase();
+            self.is_gemini() || VISION_MODEL_HINTS.iter().any(|hint| source.contains(hint))
+        })
+    }
+
+    /// Whether prompt cache markers should be sent to this model
+    ///
+    /// An explicit `prompt-caching` setting wins. Otherwise only models
+    /// whose source or endpoint names Claude or Anthropic get them; Gemini
+    /// caches on its own.
+    pub fn supports_prompt_caching(&self) -> bool {
+        self.prompt_caching.unwrap_or_else(|| {
+            let names = [self.source.as_deref(), self.endpoint.as_deref()];
+            !self.is_gemini()
+                && names.into_iter().flatten().any(|name| {
+                    let name = name.to_lowercase();
+                    PROMPT_CACHING_HINTS.iter().any(|hint| name.contains(hint))
+                })
+        })
+    }
+
+    /// Whether the model is served through the Gemini API
+    pub fn is_gemini(&self) -> bool {
+        self.interface == "gemini"
+    }
+
+    /// Gemini safety settings from the `safety_settings` parameter
+    pub fn safety_settings(&self) -> Result<Vec<SafetySetting>, serde_json::Error> {
+        self.parameters
+            .get("safety_settings")
+            .map(|v| serde_json::from_value(v.clone()))
+            .unwrap_or_else(|| Ok(Vec::new()))
+    }
+
     /// Get the runner type name as a string
     pub fn type_name(&self) -> &'static str {
-        match self.runner {
-            RunnerType::External => "external",
-            RunnerType::Ollama => "ollama",
-            RunnerType::Vllm => "vllm",
-            RunnerType::LlamaCpp => "llama-cpp",
-            RunnerType::Docker => "docker",
-            RunnerType::TensorRtLlm => "tensorrt-llm",
-        }
+        self.runner.as_str()
     }
 }
 
@@ -287,6 +576,16 @@ impl ModelDefinition {
                 source: None,
                 parameters: HashMap::new(),
                 docker: None,
+                tools: None,
+                vision: None,
+                prompt_caching: None,
+                cost: None,
+                budget: None,
+                warm_up: None,
+                max_concurrent: None,
+                sha256: None,
+                credentials: None,
+                env: BTreeMap::new(),
             },
             ModelDefinition::Docker(docker_legacy) => ModelConfig {
                 runner: RunnerType::Docker,
@@ -296,13 +595,26 @@ impl ModelDefinition {
                 api_key: None,
                 parameters: HashMap::new(),
                 docker: None, // Legacy format doesn't have full Docker config
+                tools: None,
+                vision: None,
+                prompt_caching: None,
+                cost: None,
+                budget: None,
+                warm_up: None,
+                max_concurrent: None,
+                sha256: None,
+                credentials: None,
+                env: BTreeMap::new(),
             },
             ModelDefinition::Huggingface(hf) => {
                 let runner = match hf.runner.as_str() {
                     "ollama" => RunnerType::Ollama,
                     "vllm" => RunnerType::Vllm,
                     "llama-cpp" | "llamacpp" => RunnerType::LlamaCpp,
+                    "llamafile" => RunnerType::Llamafile,
                     "tensorrt-llm" | "tensorrt_llm" => RunnerType::TensorRtLlm,
+                    "tgi" | "text-generation-inference" => RunnerType::Tgi,
+                    "whisper" | "whisper.cpp" | "faster-whisper" => RunnerType::Whisper,
                     _ => RunnerType::External,
                 };
                 ModelConfig {
@@ -313,6 +625,16 @@ impl ModelDefinition {
                     api_key: None,
                     parameters: HashMap::new(),
                     docker: None,
+                    tools: None,
+                    vision: None,
+                    prompt_caching: None,
+                    cost: None,
+                    budget: None,
+                    warm_up: None,
+                    max_concurrent: None,
+                    sha256: None,
+                    credentials: None,
+                    env: BTreeMap::new(),
                 }
             }
             ModelDefinition::Unified(config) => config.clone(),
@@ -344,6 +666,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_supports_tools() {
+        assert!(ModelConfig::external("http://a").supports_tools());
+        assert!(ModelConfig::vllm("m").supports_tools());
+        assert!(!ModelConfig::tensorrt_llm("m").supports_tools());
+        assert!(!ModelConfig::llamacpp("m.gguf").supports_tools());
+        assert!(ModelConfig::llamacpp("m.gguf")
+            .with_parameter("jinja", Value::Bool(true))
+            .supports_tools());
+
+        let config: ModelConfig =
+            serde_json::from_str(r#"{"runner": "ollama", "source": "phi3", "tools": false}"#)
+                .unwrap();
+        assert!(!config.supports_tools());
+    }
+
+    #[test]
+    fn test_supports_vision() {
+        assert!(ModelConfig::ollama("llava:13b").supports_vision());
+        assert!(ModelConfig::vllm("Qwen/Qwen2.5-VL-7B-Instruct").supports_vision());
+        assert!(!ModelConfig::vllm("meta-llama/Llama-3.1-8B-Instruct").supports_vision());
+        assert!(!ModelConfig::external("http://a").supports_vision());
+
+        let config: ModelConfig = serde_json::from_str(
+            r#"{"runner": "external", "endpoint": "http://a", "vision": true}"#,
+        )
+        .unwrap();
+        assert!(config.supports_vision());
+    }
+
+    #[test]
+    fn test_supports_prompt_caching() {
+        let mut claude = ModelConfig::external("https://openrouter.ai/api/v1");
+        claude.source = Some("anthropic/claude-sonnet-4".to_string());
+        assert!(claude.supports_prompt_caching());
+        assert!(!ModelConfig::external("http://a").supports_prompt_caching());
+
+        let config: ModelConfig = serde_json::from_str(
+            r#"{"runner": "external", "endpoint": "http://a", "prompt-caching": true}"#,
+        )
+        .unwrap();
+        assert!(config.supports_prompt_caching());
+    }
+
+    #[test]
+    fn test_parse_model_cost() {
+        let config: ModelConfig = serde_json::from_str(
+            r#"{"runner": "external", "endpoint": "http://a",
+                "cost": {"per-1k-tokens": 0.15, "latency-ms": 800}}"#,
+        )
+        .unwrap();
+        let cost = config.cost.unwrap();
+        assert_eq!(cost.per_1k_tokens, Some(0.15));
+        assert_eq!(cost.latency_ms, Some(800));
+        assert!(ModelConfig::external("http://a").cost.is_none());
+    }
+
+    #[test]
+    fn test_parse_warm_up() {
+        let config: ModelConfig = serde_json::from_str(
+            r#"{"runner": "vllm", "source": "m", "warm-up": {"requests": 3}}"#,
+        )
+        .unwrap();
+        let warm_up = config.warm_up.unwrap();
+        assert_eq!(warm_up.requests, 3);
+        assert_eq!(warm_up.prompt, "Hello");
+        assert_eq!(warm_up.max_tokens, 8);
+        assert!(ModelConfig::vllm("m").warm_up.is_none());
+    }
+
     #[test]
     fn test_parse_unified_model() {
         let json = r#"{
@@ -614,4 +1006,84 @@ mod tests {
     fn test_tensorrt_llm_default_port() {
         assert_eq!(RunnerType::TensorRtLlm.default_port(), Some(8000));
     }
+
+    #[test]
+    fn test_parse_tgi_model() {
+        let json = r#"{
+            "runner": "tgi",
+            "source": "mistralai/Mistral-7B-Instruct-v0.3",
+            "parameters": {"num_shard": 2, "quantize": "awq"}
+        }"#;
+
+        let config: ModelConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.runner, RunnerType::Tgi);
+        assert!(config.runner.is_local_runner());
+        assert_eq!(
+            config.effective_endpoint("localhost", None),
+            Some("http://localhost:3000/v1".to_string())
+        );
+        assert_eq!(ModelConfig::tgi("gpt2").runner, RunnerType::Tgi);
+    }
+
+    #[test]
+    fn test_parse_llamafile_model() {
+        let json = r#"{
+            "runner": "llamafile",
+            "source": "https://huggingface.co/Mozilla/Llama-3.2-1B-Instruct-llamafile/resolve/main/Llama-3.2-1B-Instruct.Q6_K.llamafile",
+            "parameters": {"n_ctx": 4096}
+        }"#;
+
+        let config: ModelConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.runner, RunnerType::Llamafile);
+        assert!(config.runner.is_local_runner());
+        assert!(!config.supports_tools());
+        assert_eq!(
+            config.effective_endpoint("localhost", None),
+            Some("http://localhost:8080/v1".to_string())
+        );
+        assert_eq!(
+            ModelConfig::llamafile("tiny.llamafile").runner,
+            RunnerType::Llamafile
+        );
+    }
+
+    #[test]
+    fn test_parse_whisper_model() {
+        let json = r#"{
+            "runner": "whisper",
+            "source": "models/ggml-base.en.bin",
+            "parameters": {"threads": 4}
+        }"#;
+
+        let config: ModelConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.runner, RunnerType::Whisper);
+        assert!(config.runner.is_local_runner());
+        assert!(!config.supports_tools());
+        assert_eq!(
+            config.effective_endpoint("localhost", None),
+            Some("http://localhost:8178/v1".to_string())
+        );
+        assert_eq!(
+            ModelConfig::whisper("ggml-base.bin").runner,
+            RunnerType::Whisper
+        );
+    }
+
+    #[test]
+    fn test_for_embeddings_enables_runner_mode() {
+        let llamacpp = ModelConfig::llamacpp("nomic-embed.gguf").for_embeddings();
+        assert_eq!(
+            llamacpp.parameters.get("embedding"),
+            Some(&Value::Bool(true))
+        );
+
+        let vllm = ModelConfig::vllm("BAAI/bge-small-en").for_embeddings();
+        assert_eq!(
+            vllm.parameters.get("task"),
+            Some(&Value::String("embed".to_string()))
+        );
+
+        let ollama = ModelConfig::ollama("nomic-embed-text").for_embeddings();
+        assert!(ollama.parameters.is_empty());
+    }
 }
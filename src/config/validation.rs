@@ -8,6 +8,12 @@
 //! - NVIDIA Jetson Orin NX (16GB) - Medium models up to 13B
 //! - NVIDIA Jetson AGX Orin (32/64GB) - Large models up to 34B
 //! - Raspberry Pi 5 (8GB) - Very small models (1-3B) with llama.cpp
+//!
+//! The same profiles tune llama.cpp on ARM boards (see
+//! [`crate::runtime::llamacpp::with_device_params`]) and check a GGUF file
+//! fits before it is loaded. Cluster nodes get a profile derived from the
+//! memory they report ([`DeviceProfile::for_memory`]), which the control
+//! plane checks a pipeline's models against when scheduling it.
 
 use std::collections::HashMap;
 
@@ -34,6 +40,9 @@ pub struct DeviceProfile {
     pub recommended_quantization: String,
     /// Maximum context length recommendation
     pub max_context_length: u32,
+    /// CPU cores available for CPU inference (0 if unknown)
+    #[serde(default)]
+    pub cpu_cores: u32,
 }
 
 /// Validation result with severity levels
@@ -139,6 +148,7 @@ pub fn known_devices() -> HashMap<String, DeviceProfile> {
             tensorrt_support: true,
             max_model_params_b: 7.0,
             recommended_quantization: "int4_awq".to_string(),
+            cpu_cores: 6,
             max_context_length: 2048,
         },
     );
@@ -153,6 +163,7 @@ pub fn known_devices() -> HashMap<String, DeviceProfile> {
             tensorrt_support: true,
             max_model_params_b: 13.0,
             recommended_quantization: "int8".to_string(),
+            cpu_cores: 8,
             max_context_length: 4096,
         },
     );
@@ -167,6 +178,7 @@ pub fn known_devices() -> HashMap<String, DeviceProfile> {
             tensorrt_support: true,
             max_model_params_b: 34.0,
             recommended_quantization: "fp16".to_string(),
+            cpu_cores: 12,
             max_context_length: 8192,
         },
     );
@@ -181,6 +193,7 @@ pub fn known_devices() -> HashMap<String, DeviceProfile> {
             tensorrt_support: false,
             max_model_params_b: 3.0,
             recommended_quantization: "q4_k_m".to_string(),
+            cpu_cores: 4,
             max_context_length: 2048,
         },
     );
@@ -188,6 +201,39 @@ pub fn known_devices() -> HashMap<String, DeviceProfile> {
     devices
 }
 
+impl DeviceProfile {
+    /// Profile of a machine known only by its memory, e.g. a cluster node
+    ///
+    /// The size limit is the largest model that fits at 4-bit
+    /// quantization; the memory check catches heavier quantizations.
+    pub fn for_memory(name: &str, memory_gb: f32, cpu_cores: u32, cuda_support: bool) -> Self {
+        let recommended_quantization = match (memory_gb, cuda_support) {
+            (m, true) if m <= 16.0 => "int4_awq",
+            (m, false) if m <= 16.0 => "q4_k_m",
+            (m, true) if m <= 48.0 => "int8",
+            (m, false) if m <= 48.0 => "q8_0",
+            _ => "fp16",
+        };
+        let max_context_length = match memory_gb {
+            m if m <= 8.0 => 2048,
+            m if m <= 16.0 => 4096,
+            m if m <= 32.0 => 8192,
+            _ => 32768,
+        };
+        Self {
+            name: name.to_string(),
+            memory_gb,
+            compute_capability: None,
+            cuda_support,
+            tensorrt_support: cuda_support,
+            max_model_params_b: memory_gb / estimate_memory_requirement(1.0, "int4"),
+            recommended_quantization: recommended_quantization.to_string(),
+            max_context_length,
+            cpu_cores,
+        }
+    }
+}
+
 /// Estimate model size from source name (heuristic)
 pub fn estimate_model_size(source: &str) -> Option<f32> {
     let source_lower = source.to_lowercase();
@@ -227,28 +273,65 @@ pub fn estimate_model_size(source: &str) -> Option<f32> {
     None
 }
 
-/// Estimate memory requirement based on model size and quantization
-pub fn estimate_memory_requirement(params_billions: f32, quantization: &str) -> f32 {
-    let bytes_per_param = match quantization.to_lowercase().as_str() {
-        "int4" | "int4_awq" | "int4_gptq" | "q4_0" | "q4_k_m" | "q4_k_s" => 0.5,
+/// GGUF quantization types, longer names first so "bf16" isn't read as "f16"
+const GGUF_QUANTIZATIONS: &[&str] = &[
+    "q3_k_s", "q3_k_m", "q3_k_l", "q4_k_s", "q4_k_m", "q5_k_s", "q5_k_m", "q2_k", "q4_0", "q4_1",
+    "q5_0", "q5_1", "q6_k", "q8_0", "bf16", "f16", "f32",
+];
+
+/// Quantization named in a GGUF file name
+///
+/// "tinyllama-1.1b-chat.Q4_K_M.gguf" gives "q4_k_m"; None for other files
+/// or names without one.
+pub fn gguf_quantization(source: &str) -> Option<String> {
+    let file_name = source.rsplit('/').next()?.to_lowercase();
+    let stem = file_name.strip_suffix(".gguf")?;
+    GGUF_QUANTIZATIONS
+        .iter()
+        .find(|quantization| stem.contains(*quantization))
+        .map(|quantization| quantization.to_string())
+}
+
+/// GGUF quantization that matches a device's recommended quantization
+pub fn recommended_gguf_quantization(device: &DeviceProfile) -> &'static str {
+    match bytes_per_param(&device.recommended_quantization) {
+        b if b <= 0.5 => "q4_k_m",
+        b if b <= 1.0 => "q8_0",
+        _ => "f16",
+    }
+}
+
+/// Approximate bytes per parameter of a quantization
+fn bytes_per_param(quantization: &str) -> f32 {
+    match quantization.to_lowercase().as_str() {
+        "q2_k" => 0.33,
+        "q3_k_s" | "q3_k_m" | "q3_k_l" => 0.44,
+        "int4" | "int4_awq" | "int4_gptq" | "q4_0" | "q4_1" | "q4_k_m" | "q4_k_s" => 0.5,
+        "q5_0" | "q5_1" | "q5_k_s" | "q5_k_m" => 0.63,
+        "q6_k" => 0.82,
         "int8" | "int8_sq" | "q8_0" => 1.0,
         "fp8" => 1.0,
-        "fp16" | "float16" | "bfloat16" | "q6_k" => 2.0,
-        "fp32" | "float32" => 4.0,
+        "fp16" | "float16" | "bfloat16" | "f16" | "bf16" => 2.0,
+        "fp32" | "float32" | "f32" => 4.0,
         _ => 2.0, // Default to FP16
-    };
+    }
+}
 
+/// Estimate memory requirement based on model size and quantization
+pub fn estimate_memory_requirement(params_billions: f32, quantization: &str) -> f32 {
     // Model weights + overhead for KV cache and activations (~30%)
-    params_billions * bytes_per_param * 1.3
+    params_billions * bytes_per_param(quantization) * 1.3
 }
 
-/// Get quantization from model config parameters
+/// Get quantization from model config parameters, or from the name of a
+/// GGUF source
 pub fn get_quantization(config: &ModelConfig) -> String {
     config
         .parameters
         .get("quantization")
         .and_then(|v| v.as_str())
         .map(String::from)
+        .or_else(|| config.source.as_deref().and_then(gguf_quantization))
         .unwrap_or_else(|| "fp16".to_string())
 }
 
@@ -275,6 +358,16 @@ pub fn validate_model_for_device(config: &ModelConfig, device: &DeviceProfile) -
                 Some("Use llama-cpp runner for CPU-based inference"),
             );
         }
+        RunnerType::Tgi if !device.cuda_support => {
+            result = result.error(
+                "RUNNER_UNSUPPORTED",
+                &format!(
+                    "TGI requires CUDA which is not available on {}",
+                    device.name
+                ),
+                Some("Use llama-cpp runner for CPU-based inference"),
+            );
+        }
         _ => {}
     }
 
@@ -409,6 +502,68 @@ pub fn validate_model_for_device(config: &ModelConfig, device: &DeviceProfile) -
     result
 }
 
+/// Check a GGUF file fits on the device before llama.cpp loads it
+///
+/// A file larger than the device's memory is an error: llama.cpp would
+/// page it from storage on every token, or be killed. A heavier
+/// quantization than the device's recommendation is a warning.
+pub fn validate_gguf_for_device(
+    file_name: &str,
+    file_bytes: u64,
+    device: &DeviceProfile,
+) -> ValidationResult {
+    let mut result = ValidationResult::new();
+    let file_gb = file_bytes as f32 / (1024.0 * 1024.0 * 1024.0);
+    let recommended = recommended_gguf_quantization(device);
+
+    if file_gb > device.memory_gb {
+        result = result.error(
+            "GGUF_TOO_LARGE",
+            &format!(
+                "{} ({:.1}GB) does not fit in the {:.1}GB of memory on {}",
+                file_name, file_gb, device.memory_gb, device.name
+            ),
+            Some(&format!(
+                "Use a {} GGUF or a smaller model",
+                recommended.to_uppercase()
+            )),
+        );
+    } else if file_gb * 1.1 > device.memory_gb * 0.85 {
+        // ~10% on top of the weights for the KV cache and compute buffers
+        result = result.warning(
+            "MEMORY_PRESSURE",
+            &format!(
+                "{} will use about {:.0}% of memory on {}",
+                file_name,
+                file_gb * 1.1 / device.memory_gb * 100.0,
+                device.name
+            ),
+            Some("Reduce n_ctx or use a smaller quantization"),
+        );
+    }
+
+    if let Some(quantization) = gguf_quantization(file_name) {
+        if bytes_per_param(&quantization) > bytes_per_param(recommended) {
+            result = result.warning(
+                "SUBOPTIMAL_QUANTIZATION",
+                &format!(
+                    "{} is {}; {} is recommended for {}",
+                    file_name,
+                    quantization.to_uppercase(),
+                    recommended.to_uppercase(),
+                    device.name
+                ),
+                Some(&format!(
+                    "Use a {} GGUF for faster, lighter inference",
+                    recommended.to_uppercase()
+                )),
+            );
+        }
+    }
+
+    result
+}
+
 /// Validate all models in a configuration against available device profiles
 pub fn validate_models(
     models: &HashMap<String, ModelConfig>,
@@ -437,7 +592,10 @@ pub fn validate_models(
 }
 
 /// Try to detect the current device profile
-fn detect_device_profile(devices: &HashMap<String, DeviceProfile>) -> Option<&DeviceProfile> {
+///
+/// Jetson boards are recognized by their tegra release file, Raspberry Pi
+/// boards by the device tree model. None on other hosts.
+pub fn detect_device_profile(devices: &HashMap<String, DeviceProfile>) -> Option<&DeviceProfile> {
     // Check for Jetson
     if std::path::Path::new("/etc/nv_tegra_release").exists() {
         // Read device tree model to determine specific Jetson
@@ -507,6 +665,36 @@ mod tests {
         assert_eq!(estimate_model_size("phi-3-mini"), None);
     }
 
+    #[test]
+    fn test_profile_for_memory() {
+        let gpu = DeviceProfile::for_memory("node 'gpu-1'", 24.0, 16, true);
+        assert_eq!(gpu.recommended_quantization, "int8");
+        assert!(gpu.tensorrt_support);
+
+        // 13B at 4-bit fits in 24GB, 70B doesn't even then
+        let config = |source: &str, quantization: &str| ModelConfig {
+            runner: RunnerType::Vllm,
+            source: Some(source.to_string()),
+            parameters: HashMap::from([(
+                "quantization".to_string(),
+                Value::String(quantization.to_string()),
+            )]),
+            ..Default::default()
+        };
+        assert!(validate_model_for_device(&config("llama-13b", "int4_awq"), &gpu).passed);
+        let fp16 = validate_model_for_device(&config("llama-13b", "fp16"), &gpu);
+        assert!(fp16
+            .messages
+            .iter()
+            .any(|m| m.code == "INSUFFICIENT_MEMORY"));
+        let huge = validate_model_for_device(&config("llama-70b", "int4_awq"), &gpu);
+        assert!(huge.messages.iter().any(|m| m.code == "MODEL_TOO_LARGE"));
+
+        let cpu = DeviceProfile::for_memory("node 'pi'", 8.0, 4, false);
+        assert_eq!(cpu.recommended_quantization, "q4_k_m");
+        assert_eq!(cpu.max_context_length, 2048);
+    }
+
     #[test]
     fn test_estimate_memory_requirement() {
         // 7B with INT4 should be ~4.55GB (7 * 0.5 * 1.3)
@@ -567,6 +755,45 @@ mod tests {
             .any(|m| m.code == "RUNNER_UNSUPPORTED"));
     }
 
+    #[test]
+    fn test_gguf_quantization() {
+        assert_eq!(
+            gguf_quantization("~/models/tinyllama-1.1b-chat-v1.0.Q4_K_M.gguf").as_deref(),
+            Some("q4_k_m")
+        );
+        assert_eq!(
+            gguf_quantization("llama-3b-BF16.gguf").as_deref(),
+            Some("bf16")
+        );
+        assert_eq!(gguf_quantization("llama-3b.gguf"), None);
+        assert_eq!(gguf_quantization("meta-llama/Llama-3.2-3B-q8_0"), None);
+
+        let config = ModelConfig::llamacpp("models/qwen2.5-3b-instruct-q8_0.gguf");
+        assert_eq!(get_quantization(&config), "q8_0");
+    }
+
+    #[test]
+    fn test_validate_gguf_for_device() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        let pi = &known_devices()["raspberry-pi-5"];
+
+        let result = validate_gguf_for_device("phi-3-mini.Q4_K_M.gguf", 2 * GIB, pi);
+        assert!(result.messages.is_empty());
+
+        let result = validate_gguf_for_device("llama-13b.Q4_K_M.gguf", 9 * GIB, pi);
+        assert!(result.has_errors());
+        assert!(result.messages.iter().any(|m| m.code == "GGUF_TOO_LARGE"));
+
+        let result = validate_gguf_for_device("llama-3b.Q8_0.gguf", 7 * GIB, pi);
+        assert!(!result.has_errors());
+        let codes: Vec<&str> = result.messages.iter().map(|m| m.code.as_str()).collect();
+        assert_eq!(codes, ["MEMORY_PRESSURE", "SUBOPTIMAL_QUANTIZATION"]);
+        assert_eq!(
+            result.messages[1].suggestion.as_deref(),
+            Some("Use a Q4_K_M GGUF for faster, lighter inference")
+        );
+    }
+
     #[test]
     fn test_validation_result_builder() {
         let result = ValidationResult::new()
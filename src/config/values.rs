@@ -0,0 +1,307 @@
+//! Helm-style values for parameterizing composition files
+//!
+//! A composition can reference values with `{{ .values.path.to.key }}`,
+//! optionally with a fallback: `{{ .values.replicas | default 1 }}`. Values
+//! come from YAML files (`--values`) merged in order, then `--set key=value`
+//! overrides, so one composition can serve several environments.
+//!
+//! Templating is plain text substitution done before the file is parsed.
+//! Strings are inserted as-is, so quote them in the composition
+//! (`"url": "{{ .values.url }}"`); numbers and booleans can go unquoted.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// Errors that can occur while loading or applying values
+#[derive(Error, Debug)]
+pub enum ValuesError {
+    #[error("Failed to read values file {0}: {1}")]
+    Read(PathBuf, std::io::Error),
+
+    #[error("Invalid values file {0}: {1}")]
+    Parse(PathBuf, String),
+
+    #[error("Values file {0} must contain a mapping at the top level")]
+    NotAMapping(PathBuf),
+
+    #[error("Invalid --set '{0}': expected key=value")]
+    InvalidSet(String),
+
+    #[error("Missing value '.values.{0}' (pass it with --set or --values, or add a default)")]
+    Missing(String),
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"\{\{-?\s*\.values\.([A-Za-z0-9_\-]+(?:\.[A-Za-z0-9_\-]+)*)\s*(?:\|\s*default\s+("(?:[^"\\]|\\.)*"|[^\s}]+)\s*)?-?\}\}"#,
+        )
+        .unwrap()
+    })
+}
+
+/// Parse a `--set key.path=value` argument
+///
+/// The value is read as a YAML scalar, so `replicas=3` is a number and
+/// `debug=true` a boolean; anything else is kept as a string.
+pub fn parse_set(arg: &str) -> Result<(String, Value), ValuesError> {
+    let (key, raw) = arg
+        .split_once('=')
+        .ok_or_else(|| ValuesError::InvalidSet(arg.to_string()))?;
+    let key = key.trim();
+    if key.is_empty() || key.split('.').any(str::is_empty) {
+        return Err(ValuesError::InvalidSet(arg.to_string()));
+    }
+
+    Ok((key.to_string(), scalar(raw)))
+}
+
+/// Interpret a raw string as a YAML scalar
+fn scalar(raw: &str) -> Value {
+    match serde_yaml::from_str::<Value>(raw) {
+        Ok(v @ (Value::Bool(_) | Value::Number(_))) => v,
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+/// Set a dotted key, creating intermediate mappings as needed
+pub fn set_value(values: &mut Value, key: &str, value: Value) {
+    if !values.is_object() {
+        *values = Value::Object(Map::new());
+    }
+
+    let mut parts = key.split('.').peekable();
+    let mut current = values;
+    while let Some(part) = parts.next() {
+        let map = current.as_object_mut().expect("checked above");
+        if parts.peek().is_none() {
+            map.insert(part.to_string(), value);
+            return;
+        }
+        let next = map
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !next.is_object() {
+            *next = Value::Object(Map::new());
+        }
+        current = next;
+    }
+}
+
+/// Deep-merge `overlay` into `base`; mappings merge, everything else replaces
+pub fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Look up a dotted key
+pub fn lookup<'a>(values: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(values, |current, part| current.get(part))
+        .filter(|v| !v.is_null())
+}
+
+/// Text inserted for a value
+fn render_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Replace every `{{ .values.x }}` placeholder in `content`
+///
+/// Other `{{ ... }}` text is left alone. A placeholder with no value and no
+/// default is an error, so a forgotten `--set` fails loudly instead of
+/// deploying an empty URL.
+pub fn render_template(content: &str, values: &Value) -> Result<String, ValuesError> {
+    let mut missing = None;
+
+    let rendered = placeholder_regex().replace_all(content, |caps: &Captures| {
+        let key = &caps[1];
+        if let Some(value) = lookup(values, key) {
+            return render_value(value);
+        }
+        match caps.get(2).map(|m| m.as_str()) {
+            Some(default) if default.starts_with('"') => {
+                serde_json::from_str::<String>(default).unwrap_or_else(|_| default.to_string())
+            }
+            Some(default) => render_value(&scalar(default)),
+            None => {
+                missing.get_or_insert_with(|| key.to_string());
+                String::new()
+            }
+        }
+    });
+
+    match missing {
+        Some(key) => Err(ValuesError::Missing(key)),
+        None => Ok(rendered.into_owned()),
+    }
+}
+
+// ============================================================================
+// SBIO: I/O wrapper - thin layer over pure functions
+// ============================================================================
+
+/// Build the values for a command from `--values` files and `--set` overrides
+///
+/// Files are merged in the order given, then each `--set` is applied on top.
+pub fn load_values(files: &[PathBuf], sets: &[String]) -> Result<Value, ValuesError> {
+    let mut values = Value::Object(Map::new());
+
+    for path in files {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| ValuesError::Read(path.clone(), e))?;
+        let file_values: Value = serde_yaml::from_str(&content)
+            .map_err(|e| ValuesError::Parse(path.clone(), e.to_string()))?;
+        match file_values {
+            Value::Object(_) => merge_values(&mut values, file_values),
+            // An empty file parses as null
+            Value::Null => {}
+            _ => return Err(ValuesError::NotAMapping(path.clone())),
+        }
+    }
+
+    for arg in sets {
+        let (key, value) = parse_set(arg)?;
+        set_value(&mut values, &key, value);
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_parse_set_types() {
+        assert_eq!(
+            parse_set("replicas=3").unwrap(),
+            ("replicas".to_string(), json!(3))
+        );
+        assert_eq!(
+            parse_set("debug=true").unwrap(),
+            ("debug".to_string(), json!(true))
+        );
+        assert_eq!(
+            parse_set("router.url=http://10.0.0.5:8080/v1").unwrap(),
+            ("router.url".to_string(), json!("http://10.0.0.5:8080/v1"))
+        );
+        assert_eq!(
+            parse_set("key=a=b").unwrap(),
+            ("key".to_string(), json!("a=b"))
+        );
+        assert_eq!(parse_set("empty=").unwrap().1, json!(""));
+
+        assert!(parse_set("no-equals").is_err());
+        assert!(parse_set("=value").is_err());
+        assert!(parse_set("a..b=1").is_err());
+    }
+
+    #[test]
+    fn test_set_and_merge_values() {
+        let mut values = json!({"router": {"url": "http://a", "replicas": 1}, "name": "x"});
+        merge_values(
+            &mut values,
+            json!({"router": {"url": "http://b"}, "extra": [1, 2]}),
+        );
+        set_value(&mut values, "router.auth.key", json!("secret"));
+        set_value(&mut values, "name.first", json!("y"));
+
+        assert_eq!(
+            values,
+            json!({
+                "router": {"url": "http://b", "replicas": 1, "auth": {"key": "secret"}},
+                "name": {"first": "y"},
+                "extra": [1, 2]
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let values = json!({"router": {"url": "http://gpu-1:8080", "replicas": 2}});
+        let content =
+            r#"{"url": "{{ .values.router.url }}", "replicas": {{.values.router.replicas}}}"#;
+
+        assert_eq!(
+            render_template(content, &values).unwrap(),
+            r#"{"url": "http://gpu-1:8080", "replicas": 2}"#
+        );
+    }
+
+    #[test]
+    fn test_render_template_defaults() {
+        let content = r#"{{ .values.model | default "llama3" }} {{ .values.port | default 8080 }} {{ .values.model | default x }}"#;
+
+        assert_eq!(
+            render_template(content, &json!({})).unwrap(),
+            "llama3 8080 x"
+        );
+        assert_eq!(
+            render_template(content, &json!({"model": "qwen"})).unwrap(),
+            "qwen 8080 qwen"
+        );
+    }
+
+    #[test]
+    fn test_render_template_missing_and_untouched() {
+        let err = render_template("{{ .values.api.key }}", &json!({"api": {}})).unwrap_err();
+        assert!(matches!(err, ValuesError::Missing(ref k) if k == "api.key"));
+
+        // Only .values placeholders are templated
+        let content = "prompt: {{ input }} and {documents}";
+        assert_eq!(render_template(content, &json!({})).unwrap(), content);
+    }
+
+    #[test]
+    fn test_load_values_merges_files_then_sets() {
+        let mut base = NamedTempFile::new().unwrap();
+        writeln!(base, "router:\n  url: http://base\n  replicas: 1").unwrap();
+        let mut prod = NamedTempFile::new().unwrap();
+        writeln!(prod, "router:\n  replicas: 4").unwrap();
+
+        let values = load_values(
+            &[base.path().to_path_buf(), prod.path().to_path_buf()],
+            &["router.url=http://prod".to_string()],
+        )
+        .unwrap();
+
+        assert_eq!(
+            values,
+            json!({"router": {"url": "http://prod", "replicas": 4}})
+        );
+
+        let mut list = NamedTempFile::new().unwrap();
+        writeln!(list, "- a\n- b").unwrap();
+        assert!(matches!(
+            load_values(&[list.path().to_path_buf()], &[]),
+            Err(ValuesError::NotAMapping(_))
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+//! Exec credential helpers
+//!
+//! Like kubeconfig exec plugins, a context can name a command that prints a
+//! short-lived bearer token instead of storing an API key, so clusters behind
+//! SSO work without long-lived secrets in the config file:
+//!
+//! ```yaml
+//! contexts:
+//!   prod:
+//!     name: prod
+//!     url: https://llmnet.example.com
+//!     exec:
+//!       command: sso-helper
+//!       args: ["token", "--audience", "llmnet"]
+//! ```
+//!
+//! The command prints either the bare token or JSON with `token` and an
+//! optional RFC 3339 `expirationTimestamp`, at the top level or under
+//! `status` (so kubectl credential plugins work unchanged). Its stderr goes
+//! to the terminal, which lets interactive logins print prompts.
+
+use std::collections::HashMap;
+use std::process::Stdio;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use super::ContextError;
+
+/// Tokens are refreshed this long before they expire
+const EXPIRY_SKEW_SECS: i64 = 30;
+
+/// Command that prints a bearer token for a context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecConfig {
+    pub command: String,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+
+    /// Extra environment variables for the command
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+impl ExecConfig {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            args: Vec::new(),
+            env: HashMap::new(),
+        }
+    }
+
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+}
+
+/// A token printed by a credential helper
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecCredential {
+    pub token: String,
+    /// None when the helper didn't say; the token is then kept until the
+    /// client goes away
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl ExecCredential {
+    /// Whether the token can still be sent at `now`
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .is_none_or(|expiry| now + Duration::seconds(EXPIRY_SKEW_SECS) < expiry)
+    }
+}
+
+// ============================================================================
+// SBIO: Pure parsing
+// ============================================================================
+
+/// Parse what a credential helper printed
+pub fn parse_exec_output(stdout: &str) -> Result<ExecCredential, ContextError> {
+    let invalid = |msg: &str| ContextError::CredentialHelper(msg.to_string());
+    let output = stdout.trim();
+
+    if !output.starts_with('{') {
+        if output.is_empty() {
+            return Err(invalid("helper printed no token"));
+        }
+        return Ok(ExecCredential {
+            token: output.to_string(),
+            expires_at: None,
+        });
+    }
+
+    let json: Value = serde_json::from_str(output)
+        .map_err(|e| ContextError::CredentialHelper(format!("invalid JSON output: {}", e)))?;
+    let credential = json.get("status").unwrap_or(&json);
+
+    let token = credential["token"]
+        .as_str()
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| invalid("JSON output has no token"))?;
+
+    let expires_at = match credential["expirationTimestamp"].as_str() {
+        Some(ts) => Some(
+            DateTime::parse_from_rfc3339(ts)
+                .map_err(|e| {
+                    ContextError::CredentialHelper(format!(
+                        "invalid expirationTimestamp '{}': {}",
+                        ts, e
+                    ))
+                })?
+                .with_timezone(&Utc),
+        ),
+        None => None,
+    };
+
+    Ok(ExecCredential {
+        token: token.to_string(),
+        expires_at,
+    })
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Run a credential helper and parse its output
+pub async fn run_exec(config: &ExecConfig) -> Result<ExecCredential, ContextError> {
+    let output = Command::new(&config.command)
+        .args(&config.args)
+        .envs(&config.env)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .await
+        .map_err(|e| {
+            ContextError::CredentialHelper(format!("failed to run '{}': {}", config.command, e))
+        })?;
+
+    if !output.status.success() {
+        return Err(ContextError::CredentialHelper(format!(
+            "'{}' exited with {}",
+            config.command, output.status
+        )));
+    }
+
+    parse_exec_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Runs a credential helper on demand and caches its token until expiry
+pub struct ExecTokenSource {
+    config: ExecConfig,
+    cached: Mutex<Option<ExecCredential>>,
+}
+
+impl ExecTokenSource {
+    pub fn new(config: ExecConfig) -> Self {
+        Self {
+            config,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// A fresh token, running the helper only when the cached one expired
+    pub async fn token(&self) -> Result<String, ContextError> {
+        // Held across the helper so concurrent requests run it once
+        let mut cached = self.cached.lock().await;
+        if let Some(credential) = cached.as_ref().filter(|c| c.is_fresh(Utc::now())) {
+            return Ok(credential.token.clone());
+        }
+
+        let credential = run_exec(&self.config).await?;
+        let token = credential.token.clone();
+        *cached = Some(credential);
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_token() {
+        let credential = parse_exec_output("abc123\n").unwrap();
+        assert_eq!(credential.token, "abc123");
+        assert_eq!(credential.expires_at, None);
+
+        assert!(parse_exec_output("  \n").is_err());
+    }
+
+    #[test]
+    fn test_parse_json_token() {
+        let credential =
+            parse_exec_output(r#"{"token": "abc", "expirationTimestamp": "2026-01-01T12:00:00Z"}"#)
+                .unwrap();
+        assert_eq!(credential.token, "abc");
+        assert_eq!(
+            credential.expires_at.unwrap().to_rfc3339(),
+            "2026-01-01T12:00:00+00:00"
+        );
+
+        // kubectl ExecCredential
+        let credential = parse_exec_output(
+            r#"{"apiVersion": "client.authentication.k8s.io/v1", "kind": "ExecCredential",
+                "status": {"token": "k8s-token"}}"#,
+        )
+        .unwrap();
+        assert_eq!(credential.token, "k8s-token");
+
+        assert!(parse_exec_output(r#"{"status": {}}"#).is_err());
+        assert!(parse_exec_output(r#"{"token": "a", "expirationTimestamp": "soon"}"#).is_err());
+    }
+
+    #[test]
+    fn test_credential_freshness() {
+        let now = Utc::now();
+        let credential = |expires_at| ExecCredential {
+            token: "t".to_string(),
+            expires_at,
+        };
+
+        assert!(credential(None).is_fresh(now));
+        assert!(credential(Some(now + Duration::minutes(5))).is_fresh(now));
+        // Inside the refresh margin
+        assert!(!credential(Some(now + Duration::seconds(10))).is_fresh(now));
+        assert!(!credential(Some(now - Duration::seconds(1))).is_fresh(now));
+    }
+
+    #[tokio::test]
+    async fn test_token_source_caches_until_expiry() {
+        let dir = std::env::temp_dir().join(format!("llmnet-exec-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let calls = dir.join("calls");
+
+        // Each run appends a line to `calls`
+        let script = |expiry: &str| {
+            format!(
+                r#"echo run >> '{}'; echo '{{"token": "t", "expirationTimestamp": "{}"}}'"#,
+                calls.display(),
+                expiry
+            )
+        };
+        let config =
+            |script: String| ExecConfig::new("sh").with_args(vec!["-c".to_string(), script]);
+
+        let later = (Utc::now() + Duration::hours(1)).to_rfc3339();
+        let source = ExecTokenSource::new(config(script(&later)));
+        assert_eq!(source.token().await.unwrap(), "t");
+        assert_eq!(source.token().await.unwrap(), "t");
+        assert_eq!(std::fs::read_to_string(&calls).unwrap().lines().count(), 1);
+
+        // Already inside the refresh margin, so every call runs the helper
+        let soon = (Utc::now() + Duration::seconds(5)).to_rfc3339();
+        let source = ExecTokenSource::new(config(script(&soon)));
+        source.token().await.unwrap();
+        source.token().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&calls).unwrap().lines().count(), 3);
+
+        let failing = ExecTokenSource::new(config("exit 3".to_string()));
+        assert!(matches!(
+            failing.token().await,
+            Err(ContextError::CredentialHelper(_))
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
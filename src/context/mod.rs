@@ -1,6 +1,10 @@
+pub mod exec;
+
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+pub use exec::{ExecConfig, ExecCredential, ExecTokenSource};
+
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -10,6 +14,9 @@ pub const DEFAULT_CONTROL_PLANE_PORT: u16 = 8181;
 /// Default worker node port
 pub const DEFAULT_WORKER_PORT: u16 = 8080;
 
+/// Namespace used when neither `-n` nor the context names one
+pub const DEFAULT_NAMESPACE: &str = "default";
+
 /// Default config file location: ~/.llmnet/config
 pub fn default_config_path() -> PathBuf {
     dirs::home_dir()
@@ -41,6 +48,9 @@ pub enum ContextError {
 
     #[error("Connection failed to {0}: {1}")]
     ConnectionFailed(String, String),
+
+    #[error("Credential helper failed: {0}")]
+    CredentialHelper(String),
 }
 
 /// A single context representing a remote LLMNet cluster
@@ -53,9 +63,16 @@ pub struct Context {
     /// Optional API key for authentication
     #[serde(skip_serializing_if = "Option::is_none")]
     pub api_key: Option<String>,
+    /// Optional command that prints a short-lived token; takes precedence
+    /// over `api_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec: Option<ExecConfig>,
     /// Optional description
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// Namespace used by commands run without `-n`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
 /// The complete configuration file structure
@@ -73,6 +90,10 @@ pub struct Config {
     /// Local cluster configuration
     #[serde(default)]
     pub local: LocalConfig,
+
+    /// Settings for the built-in "worker" context
+    #[serde(default, skip_serializing_if = "WorkerConfig::is_empty")]
+    pub worker: WorkerConfig,
 }
 
 /// Local cluster configuration
@@ -84,6 +105,9 @@ pub struct LocalConfig {
     /// Default bind address
     #[serde(default = "default_bind_address")]
     pub bind_addr: String,
+    /// Namespace used by commands run without `-n`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
 }
 
 impl Default for LocalConfig {
@@ -91,10 +115,25 @@ impl Default for LocalConfig {
         Self {
             port: DEFAULT_CONTROL_PLANE_PORT,
             bind_addr: "0.0.0.0".to_string(),
+            namespace: None,
         }
     }
 }
 
+/// Settings for the built-in worker context
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkerConfig {
+    /// Namespace used by commands run without `-n`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+impl WorkerConfig {
+    fn is_empty(&self) -> bool {
+        self.namespace.is_none()
+    }
+}
+
 fn default_control_plane_port() -> u16 {
     DEFAULT_CONTROL_PLANE_PORT
 }
@@ -143,6 +182,28 @@ pub fn set_current_context(config: &mut Config, name: &str) -> Result<(), Contex
     Ok(())
 }
 
+/// Set the default namespace of a context, built-in ones included
+///
+/// `None` clears it, so commands fall back to [`DEFAULT_NAMESPACE`].
+pub fn set_context_namespace(
+    config: &mut Config,
+    name: &str,
+    namespace: Option<String>,
+) -> Result<(), ContextError> {
+    match name {
+        "local" => config.local.namespace = namespace,
+        "worker" => config.worker.namespace = namespace,
+        name => {
+            config
+                .contexts
+                .get_mut(name)
+                .ok_or_else(|| ContextError::ContextNotFound(name.to_string()))?
+                .namespace = namespace
+        }
+    }
+    Ok(())
+}
+
 /// Get the current context
 pub fn get_current_context(config: &Config) -> Result<&str, ContextError> {
     config
@@ -220,6 +281,26 @@ impl Config {
         }
     }
 
+    /// Namespace set on the current context, if any
+    pub fn context_namespace(&self) -> Option<&str> {
+        match self.current_context.as_deref().unwrap_or("local") {
+            "local" => self.local.namespace.as_deref(),
+            "worker" => self.worker.namespace.as_deref(),
+            name => self.contexts.get(name)?.namespace.as_deref(),
+        }
+    }
+
+    /// Namespace for commands run without `-n`: the current context's, or
+    /// [`DEFAULT_NAMESPACE`]
+    pub fn current_namespace(&self) -> &str {
+        self.context_namespace().unwrap_or(DEFAULT_NAMESPACE)
+    }
+
+    /// The namespace given with `-n`, else [`Config::current_namespace`]
+    pub fn resolve_namespace(&self, namespace: Option<String>) -> String {
+        namespace.unwrap_or_else(|| self.current_namespace().to_string())
+    }
+
     /// Check if currently using local context (control plane)
     pub fn is_local(&self) -> bool {
         self.current_context.as_deref().unwrap_or("local") == "local"
@@ -238,7 +319,9 @@ impl Context {
             name: name.into(),
             url: url.into(),
             api_key: None,
+            exec: None,
             description: None,
+            namespace: None,
         }
     }
 
@@ -248,11 +331,23 @@ impl Context {
         self
     }
 
+    /// Authenticate with a credential helper
+    pub fn with_exec(mut self, exec: ExecConfig) -> Self {
+        self.exec = Some(exec);
+        self
+    }
+
     /// Add a description
     pub fn with_description(mut self, desc: impl Into<String>) -> Self {
         self.description = Some(desc.into());
         self
     }
+
+    /// Set the default namespace
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
 }
 
 #[cfg(test)]
@@ -359,6 +454,49 @@ local:
         assert_eq!(url, "http://10.0.0.1:8181");
     }
 
+    #[test]
+    fn test_context_namespace() {
+        let mut config = Config::default();
+        assert_eq!(config.context_namespace(), None);
+        assert_eq!(config.current_namespace(), DEFAULT_NAMESPACE);
+
+        set_context_namespace(&mut config, "local", Some("team-a".to_string())).unwrap();
+        assert_eq!(config.current_namespace(), "team-a");
+
+        set_context_namespace(&mut config, "worker", Some("edge".to_string())).unwrap();
+        set_current_context(&mut config, "worker").unwrap();
+        assert_eq!(config.current_namespace(), "edge");
+
+        add_context(
+            &mut config,
+            Context::new("prod", "http://10.0.0.1:8181").with_namespace("prod"),
+        );
+        set_current_context(&mut config, "prod").unwrap();
+        assert_eq!(config.current_namespace(), "prod");
+        assert_eq!(config.resolve_namespace(None), "prod");
+        assert_eq!(config.resolve_namespace(Some("dev".to_string())), "dev");
+
+        set_context_namespace(&mut config, "prod", None).unwrap();
+        assert_eq!(config.current_namespace(), DEFAULT_NAMESPACE);
+
+        let result = set_context_namespace(&mut config, "missing", None);
+        assert!(matches!(result, Err(ContextError::ContextNotFound(_))));
+    }
+
+    #[test]
+    fn test_namespace_roundtrip() {
+        let mut config = Config::default();
+        let yaml = serialize_config(&config).unwrap();
+        assert!(!yaml.contains("namespace"));
+        assert!(!yaml.contains("worker"));
+
+        set_context_namespace(&mut config, "worker", Some("edge".to_string())).unwrap();
+        set_context_namespace(&mut config, "local", Some("team-a".to_string())).unwrap();
+        let parsed = parse_config(&serialize_config(&config).unwrap()).unwrap();
+        assert_eq!(parsed.worker.namespace.as_deref(), Some("edge"));
+        assert_eq!(parsed.local.namespace.as_deref(), Some("team-a"));
+    }
+
     #[test]
     fn test_context_builder() {
         let ctx = Context::new("test", "http://localhost:8181")
@@ -369,4 +507,26 @@ local:
         assert_eq!(ctx.api_key, Some("secret".to_string()));
         assert_eq!(ctx.description, Some("Test cluster".to_string()));
     }
+
+    #[test]
+    fn test_parse_exec_context() {
+        let yaml = r#"
+contexts:
+  sso:
+    name: sso
+    url: https://llmnet.example.com
+    exec:
+      command: sso-helper
+      args: ["token"]
+"#;
+        let config = parse_config(yaml).unwrap();
+        let exec = config.contexts["sso"].exec.as_ref().unwrap();
+        assert_eq!(exec.command, "sso-helper");
+        assert_eq!(exec.args, vec!["token"]);
+        assert!(exec.env.is_empty());
+
+        let yaml = serialize_config(&config).unwrap();
+        assert!(yaml.contains("command: sso-helper"));
+        assert!(!yaml.contains("env"));
+    }
 }
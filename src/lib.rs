@@ -7,3 +7,6 @@ pub mod context;
 pub mod metrics;
 pub mod runtime;
 pub mod server;
+
+#[cfg(test)]
+mod test_util;
@@ -1,3 +1,4 @@
+use std::io::IsTerminal;
 use std::process;
 
 use clap::Parser;
@@ -5,25 +6,46 @@ use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
 use llmnet::cli::{
-    check_server_status, format_cluster_status, format_container_list, format_context_list,
-    format_current_context, format_dry_run, format_namespace_list, format_node_list,
-    format_pipeline_detail, format_pipeline_list, format_runner_list, format_validation_result,
-    Cli, Commands, ContextAction, ControlPlaneClient, DeleteResource, GetResource, KillArgs,
-    ServerStatus, StopArgs, WorkerClient,
+    build_job, build_secret, check_server_status, diff_edit, diff_pipelines, edit_document,
+    format_alert_list, format_cached_model_list, format_cluster_status, format_container_list,
+    format_context_list, format_current_context, format_dead_letter_list, format_dry_run,
+    format_edit_diff, format_job_list, format_job_results, format_namespace_list, format_node_list,
+    format_node_pool_list, format_pipeline_detail, format_pipeline_diff, format_pipeline_list,
+    format_pipeline_output, format_prune_report, format_region_list, format_request_log_list,
+    format_request_trace, format_runner_list, format_scoring_weights, format_secret_list,
+    format_validation_result, format_virtual_endpoint_list, format_watch_header, highlight_changes,
+    install_plan, install_service, load_deploy_manifest, load_node_pool_manifest,
+    load_region_manifest, load_virtual_endpoint_manifest, open_in_editor, parse_edit,
+    parse_metadata_changes, reopen_with_error, run_dashboard, Cli, Commands, ContextAction,
+    ControlPlaneClient, CreateResource, DeleteResource, EditResource, Editable, GetResource,
+    JobAction, KillArgs, MetadataResource, PipelineDeletion, PruneResource, SecretKind,
+    ServerStatus, ServiceManager, StopArgs, WorkerClient, CLEAR_SCREEN,
 };
 use llmnet::cluster::{
-    create_control_plane_router, spawn_heartbeat_with_runner, spawn_orchestrator,
-    ControlPlaneState, HeartbeatConfig, Node, NodeCapacity, OrchestratorConfig, Pipeline,
-    CONTROL_PLANE_PORT,
+    create_control_plane_router, default_state_file, load_api_keys, reconcile_runners, serve_grpc,
+    spawn_alerting, spawn_container_gc, spawn_federation, spawn_orchestrator, AdmissionWebhooks,
+    AdoptionReport, ApiKeys, AssignmentRequest, AuditLog, AuditSink, ClusterController,
+    ContainerGcConfig, ControlPlaneState, FileAuditSink, HeartbeatClient, HeartbeatConfig,
+    MasterKey, MemoryAuditSink, MetadataField, Node, NodeCapabilities, NodeCapacity,
+    OrchestratorConfig, ReplicaReports, WorkerStateStore, ALERT_EVALUATION_INTERVAL_SECS,
+    CONTROL_PLANE_PORT, DEFAULT_FEDERATION_INTERVAL_SECS,
 };
-use llmnet::config::load_composition_file;
-use llmnet::context;
+use llmnet::config::{load_composition_file_with_values, Composition};
+use llmnet::context::{self, ExecConfig};
 use llmnet::metrics::new_shared_collector;
-use llmnet::runtime::new_shared_manager;
-use llmnet::server::{create_router, AppState};
+use llmnet::runtime::request_log::SHIP_INTERVAL as REQUEST_LOG_SHIP_INTERVAL;
+use llmnet::runtime::{
+    detect_host_capacity, new_shared_manager, spawn_request_log_shipper, BudgetLedger,
+    DeadLetterStore, ModelCache, Redactor, RequestLogQuery, RequestLogger,
+    DEFAULT_DEAD_LETTER_CAPACITY,
+};
+use llmnet::server::{apply_assignment, create_router, watch_composition, AppState};
 
 #[tokio::main]
 async fn main() {
+    // Shells call back in with COMPLETE set to ask for completions
+    llmnet::cli::complete_from_env();
+
     let cli = Cli::parse();
 
     // Initialize logging
@@ -44,20 +66,46 @@ async fn main() {
     let config_path = cli.config.unwrap_or_else(context::default_config_path);
     let mut config = context::load_config_from(&config_path).unwrap_or_default();
 
+    // `--context` picks the context for this command only; `llmnet context`
+    // saves the config, so it's left out there
+    if let Some(name) = &cli.context {
+        if !matches!(cli.command, Commands::Context(_)) {
+            if let Err(e) = context::set_current_context(&mut config, name) {
+                error!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
+
     // Execute command
     let result = match cli.command {
         Commands::Serve(args) => run_serve(args).await,
         Commands::Deploy(args) => run_deploy(&config, args).await,
+        Commands::Diff(args) => run_diff(&config, args).await,
+        Commands::Edit(args) => run_edit(&config, args).await,
+        Commands::Label(args) => run_metadata(&config, args, MetadataField::Labels).await,
+        Commands::Annotate(args) => run_metadata(&config, args, MetadataField::Annotations).await,
+        Commands::Create(args) => run_create(&config, args).await,
         Commands::Get(args) => run_get(&config, args).await,
         Commands::Delete(args) => run_delete(&config, args).await,
         Commands::Scale(args) => run_scale(&config, args).await,
+        Commands::Job(args) => run_job(&config, args).await,
         Commands::Context(args) => run_context(&mut config, &config_path, args),
+        Commands::ClusterConfig(args) => run_cluster_config(&config, args).await,
         Commands::Logs(args) => run_logs(&config, args).await,
-        Commands::Status => run_status(&config).await,
-        Commands::Validate(args) => run_validate(args),
+        Commands::Status(args) => run_status(&config, args).await,
+        Commands::Ui(args) => run_ui(&config, args).await,
+        Commands::Trace(args) => run_trace(&config, args).await,
+        Commands::Requeue(args) => run_requeue(&config, args).await,
+        Commands::Replay(args) => run_replay(&config, args).await,
+        Commands::Validate(args) => run_validate(&config, args).await,
+        Commands::Prune(args) => run_prune(&config, args).await,
         Commands::Run(args) => run_legacy(args).await,
         Commands::Stop(args) => run_stop(args).await,
         Commands::Kill(args) => run_kill(args).await,
+        Commands::Install(args) => run_install(args),
+        Commands::Completion(args) => run_completion(args),
+        Commands::Docs(args) => run_docs(args),
     };
 
     if let Err(e) = result {
@@ -79,6 +127,17 @@ async fn run_serve(args: llmnet::cli::ServeArgs) -> Result<(), Box<dyn std::erro
         }
     }
 
+    // Started by the Windows service control manager: report to it from
+    // its own thread while this one serves
+    #[cfg(windows)]
+    if let Some(name) = args.windows_service.clone() {
+        std::thread::spawn(move || {
+            if let Err(e) = llmnet::cli::windows_service_host::run(&name) {
+                error!("Failed to connect to the service control manager: {}", e);
+            }
+        });
+    }
+
     // Determine port and check if already running
     let port = if args.control_plane {
         args.port.unwrap_or(CONTROL_PLANE_PORT)
@@ -135,13 +194,93 @@ async fn run_serve(args: llmnet::cli::ServeArgs) -> Result<(), Box<dyn std::erro
 
         info!("Starting LLMNet control plane on {}", addr);
 
-        let state = ControlPlaneState::new();
+        let audit_sink: Box<dyn AuditSink> = match &args.audit_log {
+            Some(path) => {
+                info!("Recording audit log to {}", path.display());
+                Box::new(FileAuditSink::new(path))
+            }
+            None => Box::new(MemoryAuditSink::new()),
+        };
+        let audit = AuditLog::new(audit_sink).with_retention(std::time::Duration::from_secs(
+            args.audit_retention_days * 24 * 60 * 60,
+        ));
+        let master_key = match &args.secret_key_file {
+            Some(path) => {
+                let encoded = std::fs::read_to_string(path)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                MasterKey::from_base64(&encoded)?
+            }
+            None => {
+                warn!("No --secret-key-file given; secrets are encrypted with a key that is lost on restart");
+                MasterKey::generate()
+            }
+        };
+        let mut controller = ClusterController::new().with_master_key(master_key);
+        if let Some(dir) = &args.jobs_dir {
+            info!("Jobs may read input files from {}", dir.display());
+            controller = controller.with_jobs_dir(dir);
+        }
+        for url in &args.admission_webhooks {
+            info!("Pipelines are reviewed by admission webhook {}", url);
+        }
+        let api_keys = match &args.api_keys {
+            Some(path) => {
+                info!("Requests need an API key from {}", path.display());
+                load_api_keys(path)?
+            }
+            None => {
+                warn!("No --api-keys given; the API is open to anyone who can reach it");
+                ApiKeys::default()
+            }
+        };
+        let state = ControlPlaneState::with_controller(controller)
+            .with_audit(audit)
+            .with_admission_webhooks(AdmissionWebhooks::new(args.admission_webhooks.clone()))
+            .with_api_keys(api_keys);
+
+        // Enforce audit retention at startup and hourly after that
+        let audit = state.audit.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+            loop {
+                interval.tick().await;
+                match audit.prune_expired().await {
+                    Ok(0) => {}
+                    Ok(n) => info!("Pruned {} expired audit entries", n),
+                    Err(e) => error!("Failed to prune audit log: {}", e),
+                }
+            }
+        });
 
         // Spawn the orchestrator to schedule pipelines to workers
         let _orchestrator_shutdown =
             spawn_orchestrator(state.controller.clone(), OrchestratorConfig::default());
         info!("Orchestrator started - will schedule pipelines to workers");
 
+        // Forward regional pipelines to their regions' control planes
+        spawn_federation(
+            state.controller.clone(),
+            std::time::Duration::from_secs(DEFAULT_FEDERATION_INTERVAL_SECS),
+        );
+
+        // Evaluate alert rules and notify their sinks
+        spawn_alerting(
+            state.controller.clone(),
+            std::time::Duration::from_secs(ALERT_EVALUATION_INTERVAL_SECS),
+        );
+
+        if let Some(grpc_port) = args.grpc_port {
+            let grpc_addr: std::net::SocketAddr =
+                format!("{}:{}", args.bind_addr, grpc_port).parse()?;
+            let grpc_state = state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = serve_grpc(grpc_state, grpc_addr).await {
+                    error!("gRPC server failed: {}", e);
+                }
+            });
+            info!("gRPC API listening on {}", grpc_addr);
+        }
+
         let app = create_control_plane_router(state);
 
         let listener = tokio::net::TcpListener::bind(&addr).await?;
@@ -154,6 +293,7 @@ async fn run_serve(args: llmnet::cli::ServeArgs) -> Result<(), Box<dyn std::erro
         info!("  POST /v1/pipelines               - Deploy pipeline");
         info!("  GET  /v1/nodes                   - List nodes");
         info!("  POST /v1/nodes                   - Register node");
+        info!("  GET  /v1/audit                   - Audit log");
 
         axum::serve(listener, app).await?;
     } else {
@@ -172,55 +312,101 @@ async fn run_serve(args: llmnet::cli::ServeArgs) -> Result<(), Box<dyn std::erro
         // Create runner manager first (needed for heartbeat pipeline tracking)
         let runner_manager = new_shared_manager();
 
+        // Lets the control plane ask for an immediate heartbeat
+        let heartbeat_trigger = std::sync::Arc::new(tokio::sync::Notify::new());
+        let replica_reports = ReplicaReports::new();
+        // Spend on budgeted models, reported with the heartbeats
+        let budgets = std::sync::Arc::new(BudgetLedger::new());
+
+        // Assignments pushed over the node session
+        let (session_assignments, mut pushed_assignments) =
+            tokio::sync::mpsc::unbounded_channel::<AssignmentRequest>();
+
+        // Pick up the assignments and runner containers of a previous run
+        let state_path = args.state_file.clone().unwrap_or_else(default_state_file);
+        let worker_state = std::sync::Arc::new(WorkerStateStore::open(&state_path).await?);
+        let adoption = match reconcile_runners(&runner_manager, &worker_state).await {
+            Ok(report) => report,
+            Err(e) => {
+                warn!("Failed to reconcile worker state: {}", e);
+                AdoptionReport::default()
+            }
+        };
+        if !adoption.adopted.is_empty() || !adoption.removed.is_empty() {
+            info!(
+                "Adopted {} runner container(s), removed {} orphan(s)",
+                adoption.adopted.len(),
+                adoption.removed.len()
+            );
+        }
+
         // Optional: register with control plane and start heartbeat
-        let _heartbeat_shutdown = if let Some(ref cp_url) = args.control_plane_url {
+        let heartbeat = if let Some(ref cp_url) = args.control_plane_url {
             info!(
                 "Starting LLMNet worker '{}', registering with control plane at {}",
                 node_name, cp_url
             );
 
-            // Register node with control plane
+            // Register node with control plane (retried until it succeeds,
+            // and again if the control plane loses the node)
             // Use advertise_addr if specified, otherwise use bind_addr
             let advertise_addr = args.advertise_addr.as_deref().unwrap_or(&args.bind_addr);
-            let client = reqwest::Client::new();
-            let node = Node::new(&node_name, advertise_addr).with_port(port);
-
-            match client
-                .post(format!("{}/v1/nodes", cp_url))
-                .json(&node)
-                .send()
-                .await
-            {
-                Ok(resp) if resp.status().is_success() => {
-                    info!("Node '{}' registered with control plane", node_name);
-                }
-                Ok(resp) => {
-                    let status = resp.status();
-                    let body = resp.text().await.unwrap_or_default();
-                    warn!("Failed to register node ({}): {}", status, body);
-                }
-                Err(e) => {
-                    warn!("Failed to connect to control plane: {}", e);
-                }
-            }
+            let capabilities = NodeCapabilities::detect();
+            info!(
+                "Advertising runners [{}] and adapters [{}]",
+                capabilities.runners.join(", "),
+                capabilities.adapters.join(", ")
+            );
+            let node = Node::new(&node_name, advertise_addr)
+                .with_port(port)
+                .with_capabilities(capabilities)
+                .with_heartbeat_interval(args.heartbeat_interval);
+
+            // Offer pipelines what's left after the system's reservations
+            let capacity = NodeCapacity::from_host(&detect_host_capacity());
+            let reserved = NodeCapacity {
+                cpu: args.reserve_cpu,
+                memory: args.reserve_memory,
+                gpu: args.reserve_gpu,
+                ..NodeCapacity::default()
+            };
+            let allocatable = capacity
+                .without_reserved(&reserved)
+                .map_err(|e| format!("Can't reserve resources for the system: {}", e))?;
 
             // Start heartbeat client with runner manager for pipeline tracking
-            let heartbeat_config = HeartbeatConfig::new(cp_url.clone(), node_name.clone())
-                .with_capacity(NodeCapacity::default());
+            let mut heartbeat_config = HeartbeatConfig::new(cp_url.clone(), node_name.clone())
+                .with_interval(args.heartbeat_interval)
+                .with_capacity(capacity)
+                .with_allocatable(allocatable)
+                .with_trigger(heartbeat_trigger.clone())
+                .with_condition(adoption.condition())
+                .with_registration(node)
+                .with_replicas(replica_reports.clone())
+                .with_budgets(budgets.clone());
+            if let Some(api_key) = &args.api_key {
+                heartbeat_config = heartbeat_config.with_api_key(api_key);
+            }
+            if !args.no_session {
+                heartbeat_config = heartbeat_config.with_session(session_assignments);
+            }
 
-            Some(spawn_heartbeat_with_runner(
-                heartbeat_config,
+            // Report NotReady and deregister when told to stop
+            let client = HeartbeatClient::new(
+                heartbeat_config.with_deregistration(),
                 metrics_collector.clone(),
-                Some(runner_manager.clone()),
-            ))
+            )
+            .with_runner_manager(runner_manager.clone());
+            let (shutdown, stop) = tokio::sync::watch::channel(false);
+            Some((shutdown, tokio::spawn(client.run(stop))))
         } else {
             None
         };
 
         info!("Starting LLMNet worker '{}' on {}", node_name, addr);
 
-        // For now, run an empty worker that just responds to health checks
-        // and waits for pipeline assignments from the control plane
+        // The worker's own composition is empty: assigned pipelines are
+        // hosted next to it, under /pipelines/{name}/ or on their own ports
         let json = r#"{
             "models": {},
             "architecture": [
@@ -228,10 +414,72 @@ async fn run_serve(args: llmnet::cli::ServeArgs) -> Result<(), Box<dyn std::erro
                 {"name": "output", "adapter": "output"}
             ]
         }"#;
-        let composition = llmnet::config::Composition::from_str(json)?;
-        let state = AppState::new(composition)
-            .with_runner_manager(runner_manager)
-            .with_bind_addr(&args.bind_addr);
+        let composition = Composition::from_str(json)?;
+
+        // Remove containers left behind by crashes that no runner tracks
+        if !args.no_gc {
+            let mut gc_config = ContainerGcConfig::new(&node_name);
+            if let Some(url) = &args.control_plane_url {
+                gc_config = gc_config.with_control_plane(url);
+            }
+            if let Some(api_key) = &args.api_key {
+                gc_config = gc_config.with_api_key(api_key);
+            }
+            spawn_container_gc(runner_manager.clone(), gc_config);
+        }
+
+        let mut state = AppState::new(composition)
+            .with_runner_manager(runner_manager.clone())
+            .with_bind_addr(&args.bind_addr)
+            .with_port(port)
+            .with_heartbeat_trigger(heartbeat_trigger)
+            .with_metrics_collector(metrics_collector)
+            .with_replica_reports(replica_reports)
+            .with_budgets(budgets)
+            .with_worker_state(worker_state);
+        if let Some(budget) = args.model_cache_budget {
+            state = state.with_model_cache(ModelCache::default().with_budget(budget));
+        }
+        if let Some(url) = &args.control_plane_url {
+            state = state.with_control_plane_url(url);
+        }
+        if let Some(api_key) = &args.api_key {
+            state = state.with_control_plane_key(api_key);
+        }
+
+        // Serve recorded assignments again, restarting runners that didn't
+        // survive
+        let reapply_state = state.clone();
+        tokio::spawn(async move {
+            let Some(store) = reapply_state.worker_state.clone() else {
+                return;
+            };
+            for assignment in store.state().await.assignments {
+                info!(
+                    "Restoring pipeline {}/{} from {}",
+                    assignment.namespace,
+                    assignment.name,
+                    store.path().display()
+                );
+                let (_, response) = apply_assignment(&reapply_state, assignment).await;
+                if let Some(e) = response.error {
+                    error!("{}", e);
+                }
+            }
+        });
+
+        // Apply assignments pushed over the node session like POSTed ones
+        let session_state = state.clone();
+        tokio::spawn(async move {
+            while let Some(request) = pushed_assignments.recv().await {
+                let state = session_state.clone();
+                tokio::spawn(async move {
+                    let (_, response) = apply_assignment(&state, request.assignment).await;
+                    let _ = request.reply.send(response);
+                });
+            }
+        });
+        let pipelines = state.pipelines.clone();
         let app = create_router(state);
 
         let listener = tokio::net::TcpListener::bind(&addr).await?;
@@ -239,9 +487,53 @@ async fn run_serve(args: llmnet::cli::ServeArgs) -> Result<(), Box<dyn std::erro
         info!("Worker endpoints:");
         info!("  GET  /health          - Health check");
         info!("  POST /v1/assignments  - Receive pipeline assignments from control plane");
+        info!("  GET  /v1/pipelines    - List hosted pipelines");
+        info!("  *    /pipelines/{{name}}/... - A hosted pipeline's endpoints");
         info!("  POST /v1/runners/spawn - Spawn model runners");
+        info!("  POST /v1/heartbeat    - Send a heartbeat immediately");
+        info!("  POST /v1/embeddings   - OpenAI-compatible embeddings");
+
+        // On SIGTERM or Ctrl+C stop accepting requests and let the ones in
+        // flight finish, up to the drain timeout
+        let stopping = std::sync::Arc::new(tokio::sync::Notify::new());
+        let signalled = stopping.clone();
+        let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            signalled.notify_one();
+        });
+        let mut server = std::pin::pin!(std::future::IntoFuture::into_future(server));
+        tokio::select! {
+            result = &mut server => result?,
+            _ = stopping.notified() => {
+                info!(
+                    "Shutdown signal received, draining requests for up to {}s",
+                    args.drain_timeout
+                );
+                pipelines.stop_listening();
+                let drained = async {
+                    let _ = (&mut server).await;
+                    while pipelines.active_request_count() > 0 {
+                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    }
+                };
+                let timeout = std::time::Duration::from_secs(args.drain_timeout);
+                if tokio::time::timeout(timeout, drained).await.is_err() {
+                    warn!(
+                        "Drain timeout reached with {} request(s) in flight",
+                        pipelines.active_request_count()
+                    );
+                }
+            }
+        }
 
-        axum::serve(listener, app).await?;
+        // Tell the control plane we're going before the runners stop
+        if let Some((shutdown, task)) = heartbeat {
+            let _ = shutdown.send(true);
+            let _ = task.await;
+        }
+        info!("Stopping runners...");
+        runner_manager.shutdown_all().await;
+        info!("All runners stopped");
     }
 
     Ok(())
@@ -251,30 +543,19 @@ async fn run_deploy(
     config: &context::Config,
     args: llmnet::cli::DeployArgs,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Load the pipeline manifest
-    let pipeline = if args.file.extension().and_then(|e| e.to_str()) == Some("yaml")
-        || args.file.extension().and_then(|e| e.to_str()) == Some("yml")
-    {
-        // YAML pipeline manifest
-        let content = std::fs::read_to_string(&args.file)?;
-        serde_yaml::from_str::<Pipeline>(&content)?
-    } else {
-        // Try as pipeline JSON, fall back to composition
-        let content = std::fs::read_to_string(&args.file)?;
-        match serde_json::from_str::<Pipeline>(&content) {
-            Ok(p) => p,
-            Err(_) => {
-                // Fall back to composition format
-                let composition = load_composition_file(&args.file)?;
-                let name = args
-                    .file
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("pipeline");
-                Pipeline::new(name, composition).with_namespace(&args.namespace)
-            }
-        }
-    };
+    // Load the manifest, rendering any values placeholders first
+    let values = args.values.load()?;
+    if let Some(endpoint) = load_virtual_endpoint_manifest(&args.file, &values)? {
+        return deploy_virtual_endpoint(config, endpoint, args.dry_run).await;
+    }
+    if let Some(pool) = load_node_pool_manifest(&args.file, &values)? {
+        return deploy_node_pool(config, pool, args.dry_run).await;
+    }
+    if let Some(region) = load_region_manifest(&args.file, &values)? {
+        return deploy_region(config, region, args.dry_run).await;
+    }
+    let namespace = config.resolve_namespace(args.namespace);
+    let pipeline = load_deploy_manifest(&args.file, &namespace, &values)?;
 
     if args.dry_run {
         println!(
@@ -287,12 +568,274 @@ async fn run_deploy(
 
     // Deploy to current context
     let client = ControlPlaneClient::from_context(config)?;
-    let deployed = client.deploy(&pipeline).await?;
+    let (deployed, warnings) = client.deploy_with_force(&pipeline, args.force).await?;
+    for warning in &warnings {
+        warn!("{}", warning);
+    }
+
+    let rollout_started = deployed
+        .status
+        .as_ref()
+        .and_then(|s| s.rollout.as_ref())
+        .is_some_and(|r| r.is_progressing());
+    if rollout_started {
+        println!(
+            "pipeline.llmnet/{} rollout started in namespace {}",
+            deployed.metadata.name, deployed.metadata.namespace
+        );
+    } else {
+        println!(
+            "pipeline.llmnet/{} deployed to namespace {}",
+            deployed.metadata.name, deployed.metadata.namespace
+        );
+    }
+
+    Ok(())
+}
+
+async fn deploy_virtual_endpoint(
+    config: &context::Config,
+    endpoint: llmnet::cluster::VirtualEndpoint,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dry_run {
+        endpoint.spec.validate()?;
+        println!(
+            "Dry-run mode: would apply endpoint '{}'",
+            endpoint.metadata.name
+        );
+        print!("{}", format_virtual_endpoint_list(&[endpoint]));
+        return Ok(());
+    }
+
+    let client = ControlPlaneClient::from_context(config)?;
+    let applied = client.apply_virtual_endpoint(&endpoint).await?;
+    println!(
+        "endpoint.llmnet/{} applied in namespace {}",
+        applied.metadata.name, applied.metadata.namespace
+    );
+    Ok(())
+}
+
+async fn deploy_node_pool(
+    config: &context::Config,
+    pool: llmnet::cluster::NodePool,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dry_run {
+        pool.spec.validate()?;
+        println!("Dry-run mode: would apply node pool '{}'", pool.name());
+        print!("{}", format_node_pool_list(&[pool]));
+        return Ok(());
+    }
 
+    let client = ControlPlaneClient::from_context(config)?;
+    let applied = client.apply_node_pool(&pool).await?;
+    let nodes = applied.status.map(|s| s.nodes.len()).unwrap_or_default();
     println!(
-        "pipeline.llmnet/{} deployed to namespace {}",
-        deployed.metadata.name, deployed.metadata.namespace
+        "nodepool.llmnet/{} applied ({} node(s) match)",
+        applied.metadata.name, nodes
     );
+    Ok(())
+}
+
+async fn deploy_region(
+    config: &context::Config,
+    region: llmnet::cluster::Region,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if dry_run {
+        region.spec.validate()?;
+        println!("Dry-run mode: would apply region '{}'", region.name());
+        print!("{}", format_region_list(&[region]));
+        return Ok(());
+    }
+
+    let client = ControlPlaneClient::from_context(config)?;
+    let applied = client.apply_region(&region).await?;
+    println!(
+        "region.llmnet/{} applied ({})",
+        applied.metadata.name, applied.spec.url
+    );
+    Ok(())
+}
+
+async fn run_diff(
+    config: &context::Config,
+    args: llmnet::cli::DiffArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let values = args.values.load()?;
+    let namespace = config.resolve_namespace(args.namespace);
+    let local = load_deploy_manifest(&args.file, &namespace, &values)?;
+
+    let client = ControlPlaneClient::from_context(config)?;
+    let live = client
+        .get_pipeline(&local.metadata.namespace, &local.metadata.name)
+        .await?;
+
+    let changes = diff_pipelines(live.as_ref(), &local);
+    let color = !args.no_color && std::io::stdout().is_terminal();
+    print!(
+        "{}",
+        format_pipeline_diff(
+            &local.metadata.name,
+            &local.metadata.namespace,
+            live.is_some(),
+            &changes,
+            color,
+        )
+    );
+
+    // Like `diff`, exit 1 when there is something to apply
+    if !changes.is_empty() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+async fn run_edit(
+    config: &context::Config,
+    args: llmnet::cli::EditArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = ControlPlaneClient::from_context(config)?;
+    let color = std::io::stdout().is_terminal();
+
+    match args.resource {
+        EditResource::Pipeline { name, namespace } => {
+            let namespace = config.resolve_namespace(namespace);
+            let Some(live) = client.get_pipeline(&namespace, &name).await? else {
+                error!("Pipeline '{}' not found in namespace '{}'", name, namespace);
+                process::exit(1);
+            };
+            let Some(updated) = edit_in_editor(&live)? else {
+                println!("Edit cancelled, no changes made.");
+                return Ok(());
+            };
+            let resource = format!("pipeline.llmnet/{}", name);
+            print!(
+                "{}",
+                format_edit_diff(&resource, &diff_edit(&live, &updated), color)
+            );
+            client.deploy(&updated).await?;
+            println!("{} edited", resource);
+        }
+        EditResource::Node { name } => {
+            let Some(live) = client.get_node(&name).await? else {
+                error!("Node '{}' not found", name);
+                process::exit(1);
+            };
+            let Some(updated) = edit_in_editor(&live)? else {
+                println!("Edit cancelled, no changes made.");
+                return Ok(());
+            };
+            let resource = format!("node.llmnet/{}", name);
+            print!(
+                "{}",
+                format_edit_diff(&resource, &diff_edit(&live, &updated), color)
+            );
+            client.update_node(&updated).await?;
+            println!("{} edited", resource);
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_metadata(
+    config: &context::Config,
+    args: llmnet::cli::MetadataArgs,
+    field: MetadataField,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = ControlPlaneClient::from_context(config)?;
+    let verb = match field {
+        MetadataField::Labels => "labeled",
+        MetadataField::Annotations => "annotated",
+    };
+
+    match args.resource {
+        MetadataResource::Pipeline {
+            name,
+            changes,
+            namespace,
+        } => {
+            let namespace = config.resolve_namespace(namespace);
+            let patch = parse_metadata_changes(&changes)?;
+            client
+                .patch_pipeline_metadata(&namespace, &name, field, &patch)
+                .await?;
+            println!("pipeline.llmnet/{} {}", name, verb);
+        }
+        MetadataResource::Node { name, changes } => {
+            let patch = parse_metadata_changes(&changes)?;
+            client.patch_node_metadata(&name, field, &patch).await?;
+            println!("node.llmnet/{} {}", name, verb);
+        }
+    }
+
+    Ok(())
+}
+
+/// Open a resource in the user's editor until the edit is valid or cancelled
+///
+/// A rejected edit reopens with the error on top; saving it again unchanged
+/// gives up and leaves the file in place so the changes aren't lost.
+fn edit_in_editor<T: Editable>(live: &T) -> Result<Option<T>, Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("llmnet-edit-{}.yaml", uuid::Uuid::new_v4()));
+    let mut document = edit_document(live)?;
+
+    loop {
+        std::fs::write(&path, &document)?;
+        open_in_editor(&path)?;
+        let edited = std::fs::read_to_string(&path)?;
+
+        match parse_edit(live, &edited) {
+            Ok(updated) => {
+                let _ = std::fs::remove_file(&path);
+                return Ok(updated);
+            }
+            Err(e) if edited == document => {
+                return Err(format!(
+                    "Edit rejected: {}. Your changes are saved in {}",
+                    e,
+                    path.display()
+                )
+                .into());
+            }
+            Err(e) => {
+                warn!("Edit rejected: {}", e);
+                document = reopen_with_error(&edited, &e);
+            }
+        }
+    }
+}
+
+async fn run_create(
+    config: &context::Config,
+    args: llmnet::cli::CreateArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if config.is_worker() {
+        error!("'create' requires control plane context. Use 'llmnet context use local'");
+        std::process::exit(1);
+    }
+    let client = ControlPlaneClient::from_context(config)?;
+
+    match args.resource {
+        CreateResource::Secret {
+            kind:
+                SecretKind::Generic {
+                    name,
+                    from_literal,
+                    from_file,
+                    namespace,
+                },
+        } => {
+            let namespace = config.resolve_namespace(namespace);
+            let secret = build_secret(&name, &namespace, &from_literal, &from_file)?;
+            let secret = client.create_secret(&secret).await?;
+            println!("secret.llmnet/{} created", secret.metadata.name);
+        }
+    }
 
     Ok(())
 }
@@ -304,22 +847,91 @@ async fn run_get(
     match args.resource {
         // Control plane resources
         GetResource::Pipelines {
+            name,
             namespace,
             all_namespaces,
+            output,
         } => {
             if config.is_worker() {
                 error!("'get pipelines' requires control plane context. Use 'llmnet context use local'");
                 std::process::exit(1);
             }
             let client = ControlPlaneClient::from_context(config)?;
+            if let Some(name) = name {
+                let namespace = config.resolve_namespace(namespace);
+                let Some(pipeline) = client.get_pipeline(&namespace, &name).await? else {
+                    error!("Pipeline '{}' not found in namespace '{}'", name, namespace);
+                    std::process::exit(1);
+                };
+                match output {
+                    Some(output) => print!("{}", format_pipeline_output(&pipeline, output)?),
+                    None => println!("{}", format_pipeline_detail(&pipeline)),
+                }
+                return Ok(());
+            }
             let ns = if all_namespaces {
                 None
             } else {
-                namespace.as_deref()
+                namespace.as_deref().or(config.context_namespace())
             };
             let pipelines = client.list_pipelines(ns).await?;
             print!("{}", format_pipeline_list(&pipelines));
         }
+        GetResource::Jobs {
+            namespace,
+            all_namespaces,
+        } => {
+            if config.is_worker() {
+                error!("'get jobs' requires control plane context. Use 'llmnet context use local'");
+                std::process::exit(1);
+            }
+            let client = ControlPlaneClient::from_context(config)?;
+            let ns = if all_namespaces {
+                None
+            } else {
+                namespace.as_deref().or(config.context_namespace())
+            };
+            let jobs = client.list_jobs(ns).await?;
+            print!("{}", format_job_list(&jobs));
+        }
+        GetResource::Endpoints {
+            namespace,
+            all_namespaces,
+        } => {
+            if config.is_worker() {
+                error!(
+                    "'get endpoints' requires control plane context. Use 'llmnet context use local'"
+                );
+                std::process::exit(1);
+            }
+            let client = ControlPlaneClient::from_context(config)?;
+            let ns = if all_namespaces {
+                None
+            } else {
+                namespace.as_deref().or(config.context_namespace())
+            };
+            let endpoints = client.list_virtual_endpoints(ns).await?;
+            print!("{}", format_virtual_endpoint_list(&endpoints));
+        }
+        GetResource::Secrets {
+            namespace,
+            all_namespaces,
+        } => {
+            if config.is_worker() {
+                error!(
+                    "'get secrets' requires control plane context. Use 'llmnet context use local'"
+                );
+                std::process::exit(1);
+            }
+            let client = ControlPlaneClient::from_context(config)?;
+            let ns = if all_namespaces {
+                None
+            } else {
+                namespace.as_deref().or(config.context_namespace())
+            };
+            let secrets = client.list_secrets(ns).await?;
+            print!("{}", format_secret_list(&secrets));
+        }
         GetResource::Nodes => {
             if config.is_worker() {
                 error!(
@@ -331,6 +943,28 @@ async fn run_get(
             let nodes = client.list_nodes().await?;
             print!("{}", format_node_list(&nodes));
         }
+        GetResource::NodePools => {
+            if config.is_worker() {
+                error!(
+                    "'get nodepools' requires control plane context. Use 'llmnet context use local'"
+                );
+                std::process::exit(1);
+            }
+            let client = ControlPlaneClient::from_context(config)?;
+            let pools = client.list_node_pools().await?;
+            print!("{}", format_node_pool_list(&pools));
+        }
+        GetResource::Regions => {
+            if config.is_worker() {
+                error!(
+                    "'get regions' requires control plane context. Use 'llmnet context use local'"
+                );
+                std::process::exit(1);
+            }
+            let client = ControlPlaneClient::from_context(config)?;
+            let regions = client.list_regions().await?;
+            print!("{}", format_region_list(&regions));
+        }
         GetResource::Namespaces => {
             if config.is_worker() {
                 error!("'get namespaces' requires control plane context. Use 'llmnet context use local'");
@@ -340,6 +974,43 @@ async fn run_get(
             let namespaces = client.list_namespaces().await?;
             print!("{}", format_namespace_list(&namespaces));
         }
+        GetResource::Alerts => {
+            if config.is_worker() {
+                error!(
+                    "'get alerts' requires control plane context. Use 'llmnet context use local'"
+                );
+                std::process::exit(1);
+            }
+            let client = ControlPlaneClient::from_context(config)?;
+            let alerts = client.list_alerts().await?;
+            print!("{}", format_alert_list(&alerts));
+        }
+
+        GetResource::RequestLogs {
+            source,
+            failed,
+            limit,
+            json,
+        } => {
+            if config.is_worker() {
+                error!("'get requestlogs' requires control plane context. Use 'llmnet context use local'");
+                std::process::exit(1);
+            }
+            let client = ControlPlaneClient::from_context(config)?;
+            let query = RequestLogQuery {
+                source,
+                failed,
+                limit: Some(limit),
+            };
+            let logs = client.list_request_logs(&query).await?;
+            if json {
+                for log in &logs {
+                    println!("{}", serde_json::to_string(log)?);
+                }
+            } else {
+                print!("{}", format_request_log_list(&logs));
+            }
+        }
 
         // Worker resources
         GetResource::Containers => {
@@ -352,6 +1023,23 @@ async fn run_get(
             let runners = client.list_runners().await?;
             print!("{}", format_runner_list(&runners));
         }
+        GetResource::Models { json } => {
+            let client = WorkerClient::from_context(config)?;
+            let list = client.list_cached_models().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&list.models)?);
+            } else {
+                print!("{}", format_cached_model_list(&list));
+            }
+        }
+        GetResource::DeadLetters { url, json } => {
+            let letters = pipeline_worker(config, url)?.list_dead_letters().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&letters)?);
+            } else {
+                print!("{}", format_dead_letter_list(&letters));
+            }
+        }
     }
 
     Ok(())
@@ -364,11 +1052,48 @@ async fn run_delete(
     let client = ControlPlaneClient::from_context(config)?;
 
     match args.resource {
-        DeleteResource::Pipeline { name, namespace } => {
-            if client.delete_pipeline(&namespace, &name).await? {
-                println!("pipeline.llmnet/{} deleted", name);
+        DeleteResource::Pipeline {
+            name,
+            namespace,
+            force,
+        } => {
+            let namespace = config.resolve_namespace(namespace);
+            match client.delete_pipeline(&namespace, &name, force).await? {
+                PipelineDeletion::Deleted => println!("pipeline.llmnet/{} deleted", name),
+                PipelineDeletion::Terminating => println!(
+                    "pipeline.llmnet/{} terminating (removed once its runners are torn down)",
+                    name
+                ),
+                PipelineDeletion::NotFound => {
+                    error!("Pipeline '{}' not found in namespace '{}'", name, namespace);
+                    process::exit(1);
+                }
+            }
+        }
+        DeleteResource::Job { name, namespace } => {
+            let namespace = config.resolve_namespace(namespace);
+            if client.delete_job(&namespace, &name).await? {
+                println!("job.llmnet/{} deleted", name);
             } else {
-                error!("Pipeline '{}' not found in namespace '{}'", name, namespace);
+                error!("Job '{}' not found in namespace '{}'", name, namespace);
+                process::exit(1);
+            }
+        }
+        DeleteResource::Endpoint { name, namespace } => {
+            let namespace = config.resolve_namespace(namespace);
+            if client.delete_virtual_endpoint(&namespace, &name).await? {
+                println!("endpoint.llmnet/{} deleted", name);
+            } else {
+                error!("Endpoint '{}' not found in namespace '{}'", name, namespace);
+                process::exit(1);
+            }
+        }
+        DeleteResource::Secret { name, namespace } => {
+            let namespace = config.resolve_namespace(namespace);
+            if client.delete_secret(&namespace, &name).await? {
+                println!("secret.llmnet/{} deleted", name);
+            } else {
+                error!("Secret '{}' not found in namespace '{}'", name, namespace);
                 process::exit(1);
             }
         }
@@ -380,6 +1105,22 @@ async fn run_delete(
                 process::exit(1);
             }
         }
+        DeleteResource::NodePool { name } => {
+            if client.delete_node_pool(&name).await? {
+                println!("nodepool.llmnet/{} deleted", name);
+            } else {
+                error!("Node pool '{}' not found", name);
+                process::exit(1);
+            }
+        }
+        DeleteResource::Region { name } => {
+            if client.delete_region(&name).await? {
+                println!("region.llmnet/{} deleted", name);
+            } else {
+                error!("Region '{}' not found", name);
+                process::exit(1);
+            }
+        }
     }
 
     Ok(())
@@ -391,8 +1132,9 @@ async fn run_scale(
 ) -> Result<(), Box<dyn std::error::Error>> {
     let client = ControlPlaneClient::from_context(config)?;
 
+    let namespace = config.resolve_namespace(args.namespace);
     let pipeline = client
-        .scale_pipeline(&args.namespace, &args.name, args.replicas)
+        .scale_pipeline(&namespace, &args.name, args.replicas)
         .await?;
 
     println!(
@@ -403,6 +1145,95 @@ async fn run_scale(
     Ok(())
 }
 
+async fn run_job(
+    config: &context::Config,
+    args: llmnet::cli::JobArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = ControlPlaneClient::from_context(config)?;
+
+    match args.action {
+        JobAction::Create {
+            name,
+            pipeline,
+            input,
+            prompt,
+            parallelism,
+            namespace,
+        } => {
+            let namespace = config.resolve_namespace(namespace);
+            let job = build_job(
+                &name,
+                &pipeline,
+                &namespace,
+                input.as_deref(),
+                prompt,
+                parallelism,
+            )?;
+            let job = client.create_job(&job).await?;
+            println!("job.llmnet/{} created", job.metadata.name);
+        }
+        JobAction::Results {
+            name,
+            namespace,
+            json,
+        } => {
+            let namespace = config.resolve_namespace(namespace);
+            let Some(results) = client.job_results(&namespace, &name).await? else {
+                error!("Job '{}' not found in namespace '{}'", name, namespace);
+                process::exit(1);
+            };
+            if json {
+                for result in &results {
+                    println!("{}", serde_json::to_string(result)?);
+                }
+            } else {
+                print!("{}", format_job_results(&results));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_cluster_config(
+    config: &context::Config,
+    args: llmnet::cli::ClusterConfigArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = ControlPlaneClient::from_context(config)?;
+
+    match args.action {
+        llmnet::cli::ClusterConfigAction::Scoring(args) => {
+            let current = client.scoring_weights().await?;
+            if !args.is_update() {
+                println!("{}", format_scoring_weights(&current));
+                return Ok(());
+            }
+
+            let weights = client.set_scoring_weights(&args.apply(current)).await?;
+            println!("scoring weights updated");
+            println!("{}", format_scoring_weights(&weights));
+        }
+        llmnet::cli::ClusterConfigAction::Alerting(args) => {
+            let Some(file) = args.file else {
+                let current = client.alerting_config().await?;
+                print!("{}", serde_yaml::to_string(&current)?);
+                return Ok(());
+            };
+
+            let content = std::fs::read_to_string(&file)?;
+            let alerting: llmnet::cluster::AlertingConfig = serde_yaml::from_str(&content)?;
+            let alerting = client.set_alerting_config(&alerting).await?;
+            println!(
+                "alerting updated: {} rule(s), {} sink(s)",
+                alerting.rules.len(),
+                alerting.sinks.len()
+            );
+        }
+    }
+
+    Ok(())
+}
+
 fn run_context(
     config: &mut context::Config,
     config_path: &std::path::PathBuf,
@@ -422,11 +1253,33 @@ fn run_context(
             context::save_config_to(config, config_path)?;
             println!("Switched to context '{}'", name);
         }
-        ContextAction::Add { name, url, api_key } => {
-            llmnet::cli::context_add(config, &name, &url, api_key.as_deref())?;
+        ContextAction::Add {
+            name,
+            url,
+            api_key,
+            exec_command,
+            exec_args,
+        } => {
+            let exec = exec_command.map(|command| ExecConfig::new(command).with_args(exec_args));
+            llmnet::cli::context_add(config, &name, &url, api_key.as_deref(), exec)?;
             context::save_config_to(config, config_path)?;
             println!("Context '{}' added", name);
         }
+        ContextAction::SetNamespace {
+            namespace,
+            context,
+            clear,
+        } => {
+            let namespace = namespace.filter(|_| !clear);
+            let name =
+                llmnet::cli::context_set_namespace(config, context.as_deref(), namespace.clone())?;
+            context::save_config_to(config, config_path)?;
+            println!(
+                "Context '{}' now uses namespace '{}'",
+                name,
+                namespace.as_deref().unwrap_or(context::DEFAULT_NAMESPACE)
+            );
+        }
         ContextAction::Delete { name } => {
             if llmnet::cli::context_delete(config, &name)? {
                 context::save_config_to(config, config_path)?;
@@ -448,24 +1301,38 @@ async fn run_logs(
     use futures::StreamExt;
     use tokio::io::AsyncWriteExt;
 
-    // In worker context, treat name as container name and stream logs directly
-    let response = if config.is_worker() {
+    let response = if let Some(runner) = &args.runner {
         info!(
-            "Streaming logs for container '{}' (follow={}, tail={})",
-            args.name, args.follow, args.tail
+            "Streaming logs for runner '{}' (follow={}, tail={})",
+            runner, args.follow, args.tail
         );
-        let client = WorkerClient::from_context(config)?;
+        let client = match args.url {
+            Some(url) => WorkerClient::new(url),
+            None if config.is_worker() => WorkerClient::from_context(config)?,
+            None => WorkerClient::new(format!("http://localhost:{}", context::DEFAULT_WORKER_PORT)),
+        };
         client
-            .stream_logs(&args.name, args.follow, args.tail)
+            .stream_runner_logs(runner, args.follow, args.tail)
             .await?
+    } else if config.is_worker() {
+        // In worker context, treat name as container name and stream logs directly
+        let name = args.name.unwrap_or_default();
+        info!(
+            "Streaming logs for container '{}' (follow={}, tail={})",
+            name, args.follow, args.tail
+        );
+        let client = WorkerClient::from_context(config)?;
+        client.stream_logs(&name, args.follow, args.tail).await?
     } else {
+        let name = args.name.unwrap_or_default();
+        let namespace = config.resolve_namespace(args.namespace);
         info!(
             "Streaming logs for pipeline '{}/{}' (follow={}, tail={})",
-            args.namespace, args.name, args.follow, args.tail
+            namespace, name, args.follow, args.tail
         );
         let client = ControlPlaneClient::from_context(config)?;
         client
-            .stream_logs(&args.namespace, &args.name, args.follow, args.tail)
+            .stream_logs(&namespace, &name, args.follow, args.tail)
             .await?
     };
 
@@ -489,29 +1356,180 @@ async fn run_logs(
     Ok(())
 }
 
-async fn run_status(config: &context::Config) -> Result<(), Box<dyn std::error::Error>> {
+async fn run_ui(
+    config: &context::Config,
+    args: llmnet::cli::UiArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = ControlPlaneClient::from_context(config)?;
+    run_dashboard(client, std::time::Duration::from_secs(args.refresh)).await?;
+    Ok(())
+}
+
+async fn run_trace(
+    config: &context::Config,
+    args: llmnet::cli::TraceArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let trace = pipeline_worker(config, args.url)?
+        .request_trace(&args.request_id)
+        .await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&trace)?);
+    } else {
+        print!("{}", format_request_trace(&trace));
+    }
+    Ok(())
+}
+
+async fn run_requeue(
+    config: &context::Config,
+    args: llmnet::cli::RequeueArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = pipeline_worker(config, args.url)?;
+    let request_ids = if args.all {
+        client
+            .list_dead_letters()
+            .await?
+            .into_iter()
+            .map(|l| l.request_id.to_string())
+            .collect()
+    } else {
+        args.request_ids
+    };
+    if request_ids.is_empty() {
+        println!("No dead letters to requeue.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for request_id in &request_ids {
+        match client.requeue(request_id).await {
+            Ok(_) => println!("{} answered", request_id),
+            Err(e) => {
+                failed += 1;
+                eprintln!("{}", e);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} requests failed again", failed, request_ids.len()).into());
+    }
+    Ok(())
+}
+
+async fn run_replay(
+    config: &context::Config,
+    args: llmnet::cli::ReplayArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let replay = pipeline_worker(config, args.url)?
+        .replay(&args.request_id, args.node.as_deref())
+        .await?;
+    eprintln!("Replayed {} as {}", replay.replayed_from, replay.request_id);
+    println!("{}", replay.output);
+    Ok(())
+}
+
+async fn run_prune(
+    config: &context::Config,
+    args: llmnet::cli::PruneArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match args.resource {
+        PruneResource::Models { budget, dry_run } => {
+            let client = WorkerClient::from_context(config)?;
+            let report = client.prune_models(budget, dry_run).await?;
+            print!("{}", format_prune_report(&report));
+        }
+    }
+    Ok(())
+}
+
+/// Worker serving a pipeline: `url`, the worker context, or the local default
+fn pipeline_worker(
+    config: &context::Config,
+    url: Option<String>,
+) -> Result<WorkerClient, Box<dyn std::error::Error>> {
+    Ok(match url {
+        Some(url) => WorkerClient::new(url),
+        None if config.is_worker() => WorkerClient::from_context(config)?,
+        None => WorkerClient::new(format!("http://localhost:{}", context::DEFAULT_WORKER_PORT)),
+    })
+}
+
+async fn run_status(
+    config: &context::Config,
+    args: llmnet::cli::StatusArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !args.watch {
+        print!("{}", render_status(config).await?);
+        return Ok(());
+    }
+
+    let context_name = context::get_current_context(config).unwrap_or("local");
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(args.interval));
+    let mut previous: Option<String> = None;
+    // Listen once, so a Ctrl+C while a refresh is in flight isn't lost
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        tokio::select! {
+            _ = &mut ctrl_c => break,
+            _ = interval.tick() => {}
+        }
+
+        // Keep watching through transient failures instead of exiting
+        let current = render_status(config)
+            .await
+            .unwrap_or_else(|e| format!("\nFailed to fetch status: {}\n", e));
+
+        print!(
+            "{}{}{}",
+            CLEAR_SCREEN,
+            format_watch_header(args.interval, context_name),
+            highlight_changes(previous.as_deref(), &current)
+        );
+        std::io::Write::flush(&mut std::io::stdout())?;
+        previous = Some(current);
+    }
+
+    println!();
+    Ok(())
+}
+
+/// Fetch and format the status for the current context
+async fn render_status(config: &context::Config) -> Result<String, Box<dyn std::error::Error>> {
     if config.is_worker() {
         // Worker mode - show local worker status
         let client = WorkerClient::from_context(config)?;
         let status = client.status().await?;
-        println!("Worker Status");
-        println!("=============\n");
-        println!("Runners:    {}", status["runners"].as_u64().unwrap_or(0));
-        println!("Containers: {}", status["containers"].as_u64().unwrap_or(0));
+        let mut output = String::new();
+        output.push_str("Worker Status\n");
+        output.push_str("=============\n\n");
+        output.push_str(&format!(
+            "Runners:    {}\n",
+            status["runners"].as_u64().unwrap_or(0)
+        ));
+        output.push_str(&format!(
+            "Containers: {}\n",
+            status["containers"].as_u64().unwrap_or(0)
+        ));
         if let Some(pipelines) = status["pipelines"].as_array() {
-            println!("Pipelines:  {}", pipelines.len());
+            output.push_str(&format!("Pipelines:  {}\n", pipelines.len()));
         }
+        Ok(output)
     } else {
         // Control plane mode - show cluster status
         let client = ControlPlaneClient::from_context(config)?;
         let status = client.status().await?;
-        print!("{}", format_cluster_status(&status));
+        Ok(format_cluster_status(&status))
     }
-    Ok(())
 }
 
-fn run_validate(args: llmnet::cli::ValidateArgs) -> Result<(), Box<dyn std::error::Error>> {
-    let result = llmnet::cli::validate_composition(&args.file)?;
+async fn run_validate(
+    config: &context::Config,
+    args: llmnet::cli::ValidateArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let values = args.values.load()?;
+    let result = llmnet::cli::validate_composition(&args.file, &values)?;
     print!(
         "{}",
         format_validation_result(&result, &args.file.display().to_string())
@@ -521,12 +1539,114 @@ fn run_validate(args: llmnet::cli::ValidateArgs) -> Result<(), Box<dyn std::erro
         process::exit(1);
     }
 
+    if args.cluster {
+        // A manifest brings its replicas and resources; a plain composition
+        // is checked as `deploy` would wrap it
+        let namespace = config.resolve_namespace(args.namespace);
+        let pipeline = load_deploy_manifest(&args.file, &namespace, &values).or_else(|_| {
+            let name = args
+                .file
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("pipeline");
+            llmnet::cli::pipeline_from_composition(&args.file, name, &values)
+                .map(|p| p.with_namespace(&namespace))
+        })?;
+        let client = ControlPlaneClient::from_context(config)?;
+        let check = llmnet::cli::check_against_cluster(&client, &pipeline).await;
+        let (context_name, _) = llmnet::cli::context_current(config)?;
+        print!(
+            "{}",
+            llmnet::cli::format_cluster_check(&check, &context_name)
+        );
+        if !check.problems.is_empty() {
+            process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn run_install(args: llmnet::cli::InstallArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(manager) = args.manager.or_else(ServiceManager::detect) else {
+        return Err("no supported service manager on this platform; pass --manager".into());
+    };
+    let binary = match &args.binary {
+        Some(binary) => binary.clone(),
+        None => std::env::current_exe()?,
+    };
+    let spec = args.service_spec(binary);
+    let plan = install_plan(manager, &spec);
+
+    if args.dry_run {
+        if let Some((path, content)) = &plan.file {
+            println!("# {}", path.display());
+            println!("{}", content);
+        }
+        for command in &plan.commands {
+            println!("{}", command.join(" "));
+        }
+        return Ok(());
+    }
+
+    install_service(&plan)?;
+    println!("Service '{}' installed and started", spec.name);
+    Ok(())
+}
+
+fn run_completion(args: llmnet::cli::CompletionArgs) -> Result<(), Box<dyn std::error::Error>> {
+    llmnet::cli::write_completion_script(
+        args.shell,
+        !args.static_script,
+        "llmnet",
+        &mut std::io::stdout(),
+    )?;
+    Ok(())
+}
+
+fn run_docs(args: llmnet::cli::DocsArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match args.action {
+        llmnet::cli::DocsAction::Man { out_dir: Some(dir) } => {
+            llmnet::cli::write_man_pages(&dir)?;
+            println!("Man pages written to {}", dir.display());
+        }
+        llmnet::cli::DocsAction::Man { out_dir: None } => {
+            use clap::CommandFactory;
+            llmnet::cli::render_man_page(Cli::command(), &mut std::io::stdout())?;
+        }
+    }
     Ok(())
 }
 
+/// The request logger `--request-log-*` asks for, if any
+fn build_request_logger(
+    args: &llmnet::cli::RunArgs,
+) -> Result<Option<RequestLogger>, Box<dyn std::error::Error>> {
+    let Some(percent) = args.request_log_sample else {
+        return Ok(None);
+    };
+    if args.request_log_file.is_none() && args.request_log_url.is_none() {
+        return Err("--request-log-sample needs --request-log-file or --request-log-url".into());
+    }
+
+    let source = hostname::get()
+        .ok()
+        .and_then(|h| h.into_string().ok())
+        .unwrap_or_else(|| "worker".to_string());
+    let mut logger = RequestLogger::new(percent, Redactor::new(&args.request_log_redact)?, source);
+    if let Some(path) = &args.request_log_file {
+        info!("Logging {}% of requests to {}", percent, path.display());
+        logger = logger.with_file(path);
+    }
+    if let Some(url) = &args.request_log_url {
+        info!("Shipping {}% of requests to {}", percent, url);
+        logger = logger.with_shipping();
+    }
+    Ok(Some(logger))
+}
+
 async fn run_legacy(args: llmnet::cli::RunArgs) -> Result<(), Box<dyn std::error::Error>> {
     use llmnet::config::models::RunnerType;
-    use tokio::signal;
 
     // Load .env file if specified
     if let Some(ref env_file) = args.env_file {
@@ -537,7 +1657,8 @@ async fn run_legacy(args: llmnet::cli::RunArgs) -> Result<(), Box<dyn std::error
     }
 
     // Load and validate composition
-    let mut composition = load_composition_file(&args.composition_file)?;
+    let values = args.values.load()?;
+    let mut composition = load_composition_file_with_values(&args.composition_file, &values)?;
 
     // Dry-run mode: print pipeline info and exit
     if args.dry_run {
@@ -546,18 +1667,44 @@ async fn run_legacy(args: llmnet::cli::RunArgs) -> Result<(), Box<dyn std::error
         return Ok(());
     }
 
+    // Checked before any runner starts
+    let request_logger = build_request_logger(&args)?;
+
+    // Secrets models fetch their sources with
+    let credential_sources: std::collections::HashMap<_, _> = composition
+        .models
+        .values()
+        .filter_map(|model| model.to_config().credentials)
+        .filter_map(|secret| composition.secrets.get_key_value(&secret))
+        .map(|(name, source)| (name.clone(), source.clone()))
+        .collect();
+    let secrets = llmnet::config::SecretsManager::new();
+    secrets.load_all(&credential_sources).await?;
+
     // Create runner manager for local runners (Docker, Ollama, vLLM, llama.cpp)
-    let runner_manager = new_shared_manager();
+    let runner_manager = std::sync::Arc::new(
+        llmnet::runtime::RunnerManager::new().with_secrets(std::sync::Arc::new(secrets)),
+    );
 
     // Collect models that need runners
+    let embedding_models = composition.embedding_models();
     let models_needing_runners: Vec<_> = composition
         .models
         .iter()
         .filter_map(|(name, def)| {
-            let config = def.to_config();
+            let mut config = def.to_config();
+            if embedding_models.contains(&name.as_str()) {
+                config = config.for_embeddings();
+            }
             let needs_runner = matches!(
                 config.runner,
-                RunnerType::Docker | RunnerType::Ollama | RunnerType::Vllm | RunnerType::LlamaCpp
+                RunnerType::Docker
+                    | RunnerType::Ollama
+                    | RunnerType::Vllm
+                    | RunnerType::LlamaCpp
+                    | RunnerType::Llamafile
+                    | RunnerType::Tgi
+                    | RunnerType::Whisper
             );
             if needs_runner {
                 Some((name.clone(), config))
@@ -577,10 +1724,18 @@ async fn run_legacy(args: llmnet::cli::RunArgs) -> Result<(), Box<dyn std::error
             model_name
         );
 
-        match runner_manager.spawn_runner(&model_name, &config).await {
-            Ok(endpoint) => {
-                info!("Runner for '{}' ready at {}", model_name, endpoint);
-                endpoint_updates.push((model_name, endpoint));
+        let (replicas, balancing) = composition.runner_replicas(&model_name);
+        match runner_manager
+            .spawn_replicas(&model_name, &config, replicas, balancing)
+            .await
+        {
+            Ok(endpoints) => {
+                info!(
+                    "Runner for '{}' ready at {}",
+                    model_name,
+                    endpoints.join(", ")
+                );
+                endpoint_updates.push((model_name, endpoints[0].clone()));
             }
             Err(e) => {
                 error!("Failed to spawn runner for '{}': {}", model_name, e);
@@ -601,7 +1756,48 @@ async fn run_legacy(args: llmnet::cli::RunArgs) -> Result<(), Box<dyn std::error
     }
 
     // Create application state with updated composition
-    let state = AppState::new(composition);
+    let queue = composition.queue.clone();
+    let mut state = AppState::new(composition);
+    if let Some(path) = &args.dead_letter_file {
+        let store = match DeadLetterStore::open(path, DEFAULT_DEAD_LETTER_CAPACITY).await {
+            Ok(store) => store,
+            Err(e) => {
+                runner_manager.shutdown_all().await;
+                return Err(e.into());
+            }
+        };
+        info!(
+            "Keeping failed requests in {} ({} pending)",
+            path.display(),
+            store.list().await.len()
+        );
+        state = state.with_dead_letter_store(store);
+    }
+    if let Some(logger) = request_logger {
+        let logger = std::sync::Arc::new(logger);
+        if let Some(url) = &args.request_log_url {
+            spawn_request_log_shipper(
+                logger.clone(),
+                url.clone(),
+                args.api_key.clone(),
+                REQUEST_LOG_SHIP_INTERVAL,
+            );
+        }
+        state = state.with_request_logger(logger);
+    }
+    let state = state.with_runner_pools(&runner_manager);
+
+    // Consume prompts from the message queue alongside the HTTP API
+    if let Some(queue) = queue {
+        if state.processor.get().is_none() {
+            runner_manager.shutdown_all().await;
+            return Err("Queue ingestion requires a router with a model".into());
+        }
+        if let Err(e) = llmnet::runtime::spawn_queue_worker(&queue, state.processor.clone()).await {
+            runner_manager.shutdown_all().await;
+            return Err(e.into());
+        }
+    }
 
     // Get router node info for binding
     let bind_addr = args.bind_addr.as_deref().unwrap_or("0.0.0.0");
@@ -611,6 +1807,18 @@ async fn run_legacy(args: llmnet::cli::RunArgs) -> Result<(), Box<dyn std::error
     info!("Starting llmnet on {}", addr);
     info!("Loaded {} nodes", state.nodes.len());
 
+    // Serve edits to the composition without a restart
+    if !args.no_watch {
+        if let Err(e) = watch_composition(
+            args.composition_file.clone(),
+            values,
+            state.clone(),
+            runner_manager.clone(),
+        ) {
+            warn!("Composition changes will need a restart: {}", e);
+        }
+    }
+
     // Create the router
     let app = create_router(state);
 
@@ -621,34 +1829,16 @@ async fn run_legacy(args: llmnet::cli::RunArgs) -> Result<(), Box<dyn std::error
     info!("  GET  /health             - Health check");
     info!("  GET  /status             - Pipeline status");
     info!("  POST /v1/chat/completions - OpenAI-compatible chat endpoint");
+    info!("  POST /v1/embeddings      - OpenAI-compatible embeddings endpoint");
+    info!("  GET  /v1/stream          - WebSocket stream of pipeline hops");
+    info!("  DELETE /v1/sessions/{{id}} - Forget a conversation session");
 
     // Clone runner_manager for the shutdown handler
     let shutdown_manager = runner_manager.clone();
 
     // Spawn the server with graceful shutdown
     let server = axum::serve(listener, app).with_graceful_shutdown(async move {
-        let ctrl_c = async {
-            signal::ctrl_c()
-                .await
-                .expect("Failed to install Ctrl+C handler");
-        };
-
-        #[cfg(unix)]
-        let terminate = async {
-            signal::unix::signal(signal::unix::SignalKind::terminate())
-                .expect("Failed to install signal handler")
-                .recv()
-                .await;
-        };
-
-        #[cfg(not(unix))]
-        let terminate = std::future::pending::<()>();
-
-        tokio::select! {
-            _ = ctrl_c => {},
-            _ = terminate => {},
-        }
-
+        shutdown_signal().await;
         info!("Shutdown signal received, stopping runners...");
         shutdown_manager.shutdown_all().await;
         info!("All runners stopped");
@@ -659,6 +1849,33 @@ async fn run_legacy(args: llmnet::cli::RunArgs) -> Result<(), Box<dyn std::error
     Ok(())
 }
 
+/// Resolves on Ctrl+C, or SIGTERM on Unix
+async fn shutdown_signal() {
+    use tokio::signal;
+
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
 async fn run_stop(args: StopArgs) -> Result<(), Box<dyn std::error::Error>> {
     use tokio::process::Command;
 
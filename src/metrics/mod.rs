@@ -9,7 +9,7 @@ use std::sync::Arc;
 use chrono::Utc;
 use sysinfo::{Disks, System};
 
-use crate::cluster::node::NodeMetrics;
+use crate::cluster::node::{GpuMetrics, NodeMetrics};
 
 /// Metrics collector for a worker node
 ///
@@ -23,6 +23,9 @@ pub struct MetricsCollector {
     request_count: AtomicU64,
     active_requests: AtomicU32,
     total_latency_ms: AtomicU64,
+
+    // Heartbeats to the control plane that failed in a row
+    heartbeat_failures: AtomicU32,
 }
 
 impl MetricsCollector {
@@ -34,6 +37,7 @@ impl MetricsCollector {
             request_count: AtomicU64::new(0),
             active_requests: AtomicU32::new(0),
             total_latency_ms: AtomicU64::new(0),
+            heartbeat_failures: AtomicU32::new(0),
         }
     }
 
@@ -72,7 +76,8 @@ impl MetricsCollector {
         };
 
         // GPU metrics (requires feature flag)
-        let (gpu_usage, gpu_memory_usage) = self.collect_gpu_metrics();
+        let gpus = self.collect_gpu_metrics();
+        let (gpu_usage, gpu_memory_usage) = summarize_gpus(&gpus);
 
         // Request metrics - swap to reset counters
         let req_count = self.request_count.swap(0, Ordering::SeqCst);
@@ -89,6 +94,7 @@ impl MetricsCollector {
             memory_usage_percent: memory_usage,
             gpu_usage_percent: gpu_usage,
             gpu_memory_usage_percent: gpu_memory_usage,
+            gpus,
             disk_usage_percent: disk_usage,
             request_count: req_count,
             avg_latency_ms: avg_latency,
@@ -97,32 +103,38 @@ impl MetricsCollector {
         }
     }
 
-    /// Collect GPU metrics (NVIDIA only, requires `gpu` feature)
+    /// Collect metrics of every GPU (NVIDIA only, requires `gpu` feature)
+    ///
+    /// Devices that can't be read are left out.
     #[cfg(feature = "gpu")]
-    fn collect_gpu_metrics(&self) -> (Option<f64>, Option<f64>) {
+    fn collect_gpu_metrics(&self) -> Vec<GpuMetrics> {
         use nvml_wrapper::Nvml;
 
-        match Nvml::init() {
-            Ok(nvml) => {
-                // Try to get first GPU
-                if let Ok(device) = nvml.device_by_index(0) {
-                    let utilization = device.utilization_rates().map(|u| u.gpu as f64).ok();
-                    let memory = device
-                        .memory_info()
-                        .map(|m| (m.used as f64 / m.total as f64) * 100.0)
-                        .ok();
-                    return (utilization, memory);
-                }
-                (None, None)
-            }
-            Err(_) => (None, None),
-        }
+        let Ok(nvml) = Nvml::init() else {
+            return Vec::new();
+        };
+        let count = nvml.device_count().unwrap_or(0);
+        (0..count)
+            .filter_map(|index| {
+                let device = nvml.device_by_index(index).ok()?;
+                let memory = device.memory_info().ok()?;
+                Some(GpuMetrics {
+                    index,
+                    usage_percent: device
+                        .utilization_rates()
+                        .map(|u| u.gpu as f64)
+                        .unwrap_or(0.0),
+                    memory_used: memory.used,
+                    memory_total: memory.total,
+                })
+            })
+            .collect()
     }
 
     /// Collect GPU metrics - stub when GPU feature is disabled
     #[cfg(not(feature = "gpu"))]
-    fn collect_gpu_metrics(&self) -> (Option<f64>, Option<f64>) {
-        (None, None)
+    fn collect_gpu_metrics(&self) -> Vec<GpuMetrics> {
+        Vec::new()
     }
 
     /// Record the start of a request
@@ -152,6 +164,16 @@ impl MetricsCollector {
     pub fn request_count(&self) -> u64 {
         self.request_count.load(Ordering::SeqCst)
     }
+
+    /// Record how many heartbeats in a row have failed (0 once one succeeds)
+    pub fn set_heartbeat_failures(&self, failures: u32) {
+        self.heartbeat_failures.store(failures, Ordering::SeqCst);
+    }
+
+    /// Heartbeats to the control plane that have failed in a row
+    pub fn heartbeat_failures(&self) -> u32 {
+        self.heartbeat_failures.load(Ordering::SeqCst)
+    }
 }
 
 impl Default for MetricsCollector {
@@ -160,6 +182,26 @@ impl Default for MetricsCollector {
     }
 }
 
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Node-wide GPU utilization and memory utilization from the metrics of each
+/// GPU
+///
+/// Utilization is the average across GPUs, memory utilization is the memory
+/// in use across GPUs over their total. None when no GPU was read.
+pub fn summarize_gpus(gpus: &[GpuMetrics]) -> (Option<f64>, Option<f64>) {
+    if gpus.is_empty() {
+        return (None, None);
+    }
+    let usage = gpus.iter().map(|g| g.usage_percent).sum::<f64>() / gpus.len() as f64;
+    let used: u64 = gpus.iter().map(|g| g.memory_used).sum();
+    let total: u64 = gpus.iter().map(|g| g.memory_total).sum();
+    let memory = (total > 0).then(|| (used as f64 / total as f64) * 100.0);
+    (Some(usage), memory)
+}
+
 /// Shared metrics collector for use across async tasks
 pub type SharedMetricsCollector = Arc<tokio::sync::RwLock<MetricsCollector>>;
 
@@ -177,6 +219,7 @@ mod tests {
         let collector = MetricsCollector::new();
         assert_eq!(collector.active_requests(), 0);
         assert_eq!(collector.request_count(), 0);
+        assert_eq!(collector.heartbeat_failures(), 0);
     }
 
     #[test]
@@ -224,6 +267,29 @@ mod tests {
         assert_eq!(collector.request_count(), 0);
     }
 
+    #[test]
+    fn test_summarize_gpus() {
+        assert_eq!(summarize_gpus(&[]), (None, None));
+
+        const GIB: u64 = 1024 * 1024 * 1024;
+        let gpus = [
+            GpuMetrics {
+                index: 0,
+                usage_percent: 90.0,
+                memory_used: 30 * GIB,
+                memory_total: 40 * GIB,
+            },
+            GpuMetrics {
+                index: 1,
+                usage_percent: 10.0,
+                memory_used: 10 * GIB,
+                memory_total: 40 * GIB,
+            },
+        ];
+        assert_eq!(summarize_gpus(&gpus), (Some(50.0), Some(50.0)));
+        assert_eq!(gpus[0].memory_usage_percent(), 75.0);
+    }
+
     #[test]
     fn test_metrics_defaults() {
         let metrics = NodeMetrics::default();
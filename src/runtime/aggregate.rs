@@ -0,0 +1,173 @@
+//! Combining fanned-out answers at "aggregator" nodes
+//!
+//! When every handler a node could route to feeds the same aggregator, the
+//! processor sends the request to all of them at once and hands their
+//! answers to the aggregator instead of picking one. Vote, concat and
+//! merge-json are pure; for best-of the processor asks the aggregator's
+//! model to judge, using the prompt built here.
+
+use serde_json::{Map, Value};
+
+/// Default instructions for the best-of judge
+pub const JUDGE_PROMPT: &str = "You are judging candidate answers to a question. \
+Reply with only the number of the best answer.";
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// The most common answer, compared ignoring case, surrounding whitespace
+/// and trailing punctuation. Ties go to the answer given first.
+pub fn vote(answers: &[String]) -> Option<String> {
+    let keys: Vec<String> = answers.iter().map(|a| vote_key(a)).collect();
+
+    let mut best: Option<(usize, usize)> = None;
+    for (i, key) in keys.iter().enumerate() {
+        if keys[..i].contains(key) {
+            continue;
+        }
+        let count = keys.iter().filter(|k| *k == key).count();
+        if best.is_none_or(|(_, most)| count > most) {
+            best = Some((i, count));
+        }
+    }
+
+    best.map(|(i, _)| answers[i].trim().to_string())
+}
+
+fn vote_key(answer: &str) -> String {
+    answer
+        .trim()
+        .trim_end_matches(['.', '!', '?'])
+        .to_lowercase()
+}
+
+/// Join answers in handler order
+pub fn concat(answers: &[String], separator: &str) -> String {
+    answers
+        .iter()
+        .map(|a| a.trim())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Merge JSON object answers into one, later handlers winning on conflicts.
+/// Nested objects are merged key by key; anything else is replaced.
+pub fn merge_json(answers: &[String]) -> Result<String, String> {
+    let mut merged = Map::new();
+    for (i, answer) in answers.iter().enumerate() {
+        match serde_json::from_str::<Value>(strip_code_fence(answer)) {
+            Ok(Value::Object(object)) => merge_objects(&mut merged, object),
+            Ok(_) => return Err(format!("answer {} is not a JSON object", i + 1)),
+            Err(e) => return Err(format!("answer {} is not valid JSON: {}", i + 1, e)),
+        }
+    }
+    Ok(Value::Object(merged).to_string())
+}
+
+fn merge_objects(into: &mut Map<String, Value>, from: Map<String, Value>) {
+    for (key, value) in from {
+        match (into.get_mut(&key), value) {
+            (Some(Value::Object(existing)), Value::Object(value)) => merge_objects(existing, value),
+            (_, value) => {
+                into.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Models often wrap JSON in a Markdown code block
+fn strip_code_fence(answer: &str) -> &str {
+    let trimmed = answer.trim();
+    let Some(body) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = body.strip_prefix("json").unwrap_or(body);
+    body.strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// Prompt asking the judge to pick the best numbered candidate
+pub fn build_judge_prompt(instructions: &str, question: &str, answers: &[String]) -> String {
+    let mut prompt = format!("{}\n\nQuestion:\n{}\n", instructions, question.trim());
+    for (i, answer) in answers.iter().enumerate() {
+        prompt.push_str(&format!("\nAnswer {}:\n{}\n", i + 1, answer.trim()));
+    }
+    prompt
+}
+
+/// Index of the candidate the judge picked, from the first number in its
+/// reply that names one
+pub fn parse_judge_choice(reply: &str, count: usize) -> Option<usize> {
+    reply
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse::<usize>().ok())
+        .find(|n| (1..=count).contains(n))
+        .map(|n| n - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_vote() {
+        assert_eq!(
+            vote(&answers(&["spam", "Ham.", "ham", " SPAM ", "Spam!"])),
+            Some("spam".to_string())
+        );
+        // Ties go to the first answer
+        assert_eq!(
+            vote(&answers(&["billing", "support"])),
+            Some("billing".to_string())
+        );
+        assert_eq!(vote(&[]), None);
+    }
+
+    #[test]
+    fn test_concat() {
+        assert_eq!(
+            concat(&answers(&["one\n", " two"]), "\n---\n"),
+            "one\n---\ntwo"
+        );
+    }
+
+    #[test]
+    fn test_merge_json() {
+        let merged = merge_json(&answers(&[
+            r#"{"name": "Ada", "tags": ["a"], "address": {"city": "London"}}"#,
+            "```json\n{\"tags\": [\"b\"], \"address\": {\"zip\": \"N1\"}}\n```",
+        ]))
+        .unwrap();
+        let merged: Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(
+            merged,
+            serde_json::json!({
+                "name": "Ada",
+                "tags": ["b"],
+                "address": {"city": "London", "zip": "N1"}
+            })
+        );
+
+        assert!(merge_json(&answers(&["[1, 2]"])).is_err());
+        assert!(merge_json(&answers(&["not json"])).is_err());
+    }
+
+    #[test]
+    fn test_judge_prompt_and_choice() {
+        let prompt = build_judge_prompt(JUDGE_PROMPT, "2+2?", &answers(&["4", "5"]));
+        assert!(prompt.starts_with(JUDGE_PROMPT));
+        assert!(prompt.contains("Question:\n2+2?\n"));
+        assert!(prompt.contains("Answer 1:\n4\n"));
+        assert!(prompt.contains("Answer 2:\n5\n"));
+
+        assert_eq!(parse_judge_choice("2", 3), Some(1));
+        assert_eq!(parse_judge_choice("Answer 3 is best.", 3), Some(2));
+        // Numbers that don't name a candidate are skipped
+        assert_eq!(parse_judge_choice("Of the 5 options, 1", 3), Some(0));
+        assert_eq!(parse_judge_choice("none of them", 3), None);
+    }
+}
@@ -0,0 +1,145 @@
+//! Load balancing across local runner replicas
+//!
+//! A node can ask for several processes of the same runner. The runner
+//! manager keeps one pool per model, and every request takes a lease on one
+//! replica's endpoint. Leases count requests in flight, so least-connections
+//! balancing sees the load as it changes; dropping a lease releases it.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::config::LoadBalancing;
+
+/// Endpoints of one model's runner replicas
+#[derive(Debug)]
+pub struct RunnerPool {
+    endpoints: Vec<String>,
+    strategy: LoadBalancing,
+    /// Next replica for round-robin, and the tie-breaker for least-connections
+    cursor: AtomicUsize,
+    /// Requests in flight per replica
+    active: Vec<AtomicUsize>,
+}
+
+/// A replica picked for one request, held until the request finishes
+#[derive(Debug)]
+pub struct RunnerLease {
+    pool: Arc<RunnerPool>,
+    index: usize,
+}
+
+impl RunnerPool {
+    pub fn new(endpoints: Vec<String>, strategy: LoadBalancing) -> Self {
+        let active = endpoints.iter().map(|_| AtomicUsize::new(0)).collect();
+        Self {
+            endpoints,
+            strategy,
+            cursor: AtomicUsize::new(0),
+            active,
+        }
+    }
+
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+
+    pub fn strategy(&self) -> LoadBalancing {
+        self.strategy
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Requests currently in flight on each replica
+    pub fn active_connections(&self) -> Vec<usize> {
+        self.active
+            .iter()
+            .map(|a| a.load(Ordering::SeqCst))
+            .collect()
+    }
+
+    /// Pick a replica for the next request
+    pub fn acquire(self: &Arc<Self>) -> Option<RunnerLease> {
+        if self.endpoints.is_empty() {
+            return None;
+        }
+
+        let start = self.cursor.fetch_add(1, Ordering::SeqCst) % self.endpoints.len();
+        let index = match self.strategy {
+            LoadBalancing::RoundRobin => start,
+            // Scan from the cursor so equally loaded replicas still take turns
+            LoadBalancing::LeastConnections => (0..self.endpoints.len())
+                .map(|offset| (start + offset) % self.endpoints.len())
+                .min_by_key(|&i| self.active[i].load(Ordering::SeqCst))
+                .unwrap_or(start),
+        };
+
+        self.active[index].fetch_add(1, Ordering::SeqCst);
+        Some(RunnerLease {
+            pool: Arc::clone(self),
+            index,
+        })
+    }
+}
+
+impl RunnerLease {
+    pub fn endpoint(&self) -> &str {
+        &self.pool.endpoints[self.index]
+    }
+}
+
+impl Drop for RunnerLease {
+    fn drop(&mut self) {
+        self.pool.active[self.index].fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(strategy: LoadBalancing) -> Arc<RunnerPool> {
+        Arc::new(RunnerPool::new(
+            vec!["http://a".to_string(), "http://b".to_string()],
+            strategy,
+        ))
+    }
+
+    #[test]
+    fn test_round_robin_alternates() {
+        let pool = pool(LoadBalancing::RoundRobin);
+        let picked: Vec<String> = (0..4)
+            .map(|_| pool.acquire().unwrap().endpoint().to_string())
+            .collect();
+        assert_eq!(picked, ["http://a", "http://b", "http://a", "http://b"]);
+    }
+
+    #[test]
+    fn test_least_connections_avoids_busy_replica() {
+        let pool = pool(LoadBalancing::LeastConnections);
+
+        let busy = pool.acquire().unwrap();
+        assert_eq!(busy.endpoint(), "http://a");
+
+        // While "a" is busy every request goes to "b"
+        for _ in 0..3 {
+            assert_eq!(pool.acquire().unwrap().endpoint(), "http://b");
+        }
+        assert_eq!(pool.active_connections(), vec![1, 0]);
+
+        drop(busy);
+        assert_eq!(pool.active_connections(), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_empty_pool() {
+        let pool = Arc::new(RunnerPool::new(Vec::new(), LoadBalancing::RoundRobin));
+        assert!(pool.is_empty());
+        assert!(pool.acquire().is_none());
+    }
+}
@@ -0,0 +1,417 @@
+//! Monthly budgets of external models
+//!
+//! A model with a `budget` has what each namespace spends on it counted in
+//! a ledger shared by the worker's pipelines: the tokens of every answer,
+//! and their price from the model's `cost.per-1k-tokens`. Once either of
+//! the budget's limits is spent for the calendar month (UTC), the model's
+//! calls go to the budget's fallback model, or are rejected without one or
+//! once the fallback's own budget is spent, until the month turns over.
+//!
+//! Budgets hold across the cluster: with every heartbeat a worker reports
+//! its spend since the last report to the control plane, which keeps the
+//! month's totals and answers with them, and the ledger checks its own
+//! spend plus what the rest of the cluster spent as of that answer. The
+//! totals outlive worker restarts but not control plane ones, and spend
+//! between heartbeats is only seen by the worker it happened on, so the
+//! cluster can overshoot a budget by about a heartbeat's worth of calls.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::client::{ModelClient, Usage};
+use crate::config::ModelBudget;
+
+/// A budgeted model's limits and where its calls go once they're spent
+pub struct Budget {
+    config: ModelBudget,
+    per_1k_tokens: Option<f64>,
+    fallback: Option<ModelClient>,
+}
+
+impl Budget {
+    /// Budget priced at `per_1k_tokens`, with `fallback`, the fallback
+    /// model's client
+    pub fn new(
+        config: ModelBudget,
+        per_1k_tokens: Option<f64>,
+        fallback: Option<ModelClient>,
+    ) -> Self {
+        Self {
+            config,
+            per_1k_tokens,
+            fallback,
+        }
+    }
+
+    pub fn config(&self) -> &ModelBudget {
+        &self.config
+    }
+
+    /// Client of the fallback model, if the budget has one that can be
+    /// reached
+    pub fn fallback(&self) -> Option<&ModelClient> {
+        self.fallback.as_ref()
+    }
+
+    /// Name of the fallback model in the composition's models
+    pub fn fallback_model(&self) -> Option<&str> {
+        self.config.fallback.as_deref()
+    }
+
+    /// What an answer cost
+    pub fn price(&self, usage: &Usage) -> Spend {
+        self.price_tokens(usage.total_tokens as u64)
+    }
+
+    /// What `tokens` tokens cost
+    pub fn price_tokens(&self, tokens: u64) -> Spend {
+        Spend {
+            tokens,
+            dollars: self
+                .per_1k_tokens
+                .map_or(0.0, |price| tokens as f64 / 1000.0 * price),
+        }
+    }
+}
+
+/// Tokens and dollars spent on a model
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Spend {
+    pub tokens: u64,
+    pub dollars: f64,
+}
+
+impl Spend {
+    fn plus(self, other: Spend) -> Spend {
+        Spend {
+            tokens: self.tokens + other.tokens,
+            dollars: self.dollars + other.dollars,
+        }
+    }
+
+    fn minus(self, other: Spend) -> Spend {
+        Spend {
+            tokens: self.tokens.saturating_sub(other.tokens),
+            dollars: (self.dollars - other.dollars).max(0.0),
+        }
+    }
+}
+
+/// Spend on a budgeted model a worker reports to the control plane, or the
+/// cluster-wide total the control plane answers with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BudgetSpend {
+    pub namespace: String,
+    pub model: String,
+    /// Month the spend is for, as "YYYY-MM" (UTC)
+    pub month: String,
+    pub tokens: u64,
+    pub dollars: f64,
+}
+
+impl BudgetSpend {
+    pub fn spend(&self) -> Spend {
+        Spend {
+            tokens: self.tokens,
+            dollars: self.dollars,
+        }
+    }
+}
+
+/// What a namespace spent on a model this month, against its budget
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BudgetUsage {
+    pub namespace: String,
+    pub model: String,
+    /// Month the spend is for, as "YYYY-MM" (UTC)
+    pub month: String,
+    /// Spent across the cluster, as of the worker's last report
+    pub tokens: u64,
+    pub dollars: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly_dollars: Option<f64>,
+    /// Whether calls to the model now go to its fallback or are rejected
+    pub exhausted: bool,
+}
+
+#[derive(Debug)]
+struct Account {
+    budget: ModelBudget,
+    month: String,
+    /// Spent on this worker
+    spend: Spend,
+    /// Part of `spend` the control plane has counted
+    reported: Spend,
+    /// Spent on the rest of the cluster, as of the last report
+    elsewhere: Spend,
+}
+
+impl Account {
+    fn total(&self) -> Spend {
+        self.spend.plus(self.elsewhere)
+    }
+}
+
+/// What each namespace spent on each budgeted model this month, on this
+/// worker and, as last reported, on the rest of the cluster
+#[derive(Debug, Default)]
+pub struct BudgetLedger {
+    accounts: Mutex<BTreeMap<(String, String), Account>>,
+}
+
+impl BudgetLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a namespace's spend on `model` against `budget`
+    ///
+    /// The latest budget wins when pipelines of a namespace give the same
+    /// model different ones.
+    pub fn open(&self, namespace: &str, model: &str, budget: &ModelBudget) {
+        let mut accounts = self.accounts.lock().unwrap();
+        accounts
+            .entry((namespace.to_string(), model.to_string()))
+            .and_modify(|account| account.budget = budget.clone())
+            .or_insert_with(|| Account {
+                budget: budget.clone(),
+                month: month_of(Utc::now()),
+                spend: Spend::default(),
+                reported: Spend::default(),
+                elsewhere: Spend::default(),
+            });
+    }
+
+    /// Add an answer's cost to what the namespace spent on `model`
+    pub fn charge(&self, namespace: &str, model: &str, spend: Spend, now: DateTime<Utc>) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let Some(account) = accounts.get_mut(&(namespace.to_string(), model.to_string())) else {
+            return;
+        };
+        roll_over(account, now);
+        account.spend = account.spend.plus(spend);
+    }
+
+    /// Whether the namespace spent its budget for `model` this month,
+    /// across the cluster
+    pub fn is_exhausted(&self, namespace: &str, model: &str, now: DateTime<Utc>) -> bool {
+        let mut accounts = self.accounts.lock().unwrap();
+        accounts
+            .get_mut(&(namespace.to_string(), model.to_string()))
+            .is_some_and(|account| {
+                roll_over(account, now);
+                is_spent(&account.budget, &account.total())
+            })
+    }
+
+    /// This month's spend on every budgeted model, by namespace and model
+    pub fn usage(&self, now: DateTime<Utc>) -> Vec<BudgetUsage> {
+        let mut accounts = self.accounts.lock().unwrap();
+        accounts
+            .iter_mut()
+            .map(|((namespace, model), account)| {
+                roll_over(account, now);
+                let total = account.total();
+                BudgetUsage {
+                    namespace: namespace.clone(),
+                    model: model.clone(),
+                    month: account.month.clone(),
+                    tokens: total.tokens,
+                    dollars: total.dollars,
+                    monthly_tokens: account.budget.monthly_tokens,
+                    monthly_dollars: account.budget.monthly_dollars,
+                    exhausted: is_spent(&account.budget, &total),
+                }
+            })
+            .collect()
+    }
+
+    /// This month's spend the control plane hasn't counted yet, for every
+    /// budgeted model
+    pub fn unreported(&self, now: DateTime<Utc>) -> Vec<BudgetSpend> {
+        let mut accounts = self.accounts.lock().unwrap();
+        accounts
+            .iter_mut()
+            .map(|((namespace, model), account)| {
+                roll_over(account, now);
+                let unreported = account.spend.minus(account.reported);
+                BudgetSpend {
+                    namespace: namespace.clone(),
+                    model: model.clone(),
+                    month: account.month.clone(),
+                    tokens: unreported.tokens,
+                    dollars: unreported.dollars,
+                }
+            })
+            .collect()
+    }
+
+    /// Mark the spend in `report` counted, and take the cluster-wide
+    /// `totals` the control plane answered it with
+    ///
+    /// Spend for a month that's already over is ignored.
+    pub fn settle(&self, report: &[BudgetSpend], totals: &[BudgetSpend], now: DateTime<Utc>) {
+        let mut accounts = self.accounts.lock().unwrap();
+        for spend in report {
+            if let Some(account) = current_account(&mut accounts, spend, now) {
+                account.reported = account.reported.plus(spend.spend());
+            }
+        }
+        for total in totals {
+            if let Some(account) = current_account(&mut accounts, total, now) {
+                account.elsewhere = total.spend().minus(account.reported);
+            }
+        }
+    }
+}
+
+/// Account `spend` is for, if it's for the account's current month
+fn current_account<'a>(
+    accounts: &'a mut BTreeMap<(String, String), Account>,
+    spend: &BudgetSpend,
+    now: DateTime<Utc>,
+) -> Option<&'a mut Account> {
+    let account = accounts.get_mut(&(spend.namespace.clone(), spend.model.clone()))?;
+    roll_over(account, now);
+    (account.month == spend.month).then_some(account)
+}
+
+/// Start the account over once its month is past
+fn roll_over(account: &mut Account, now: DateTime<Utc>) {
+    let month = month_of(now);
+    if account.month != month {
+        account.month = month;
+        account.spend = Spend::default();
+        account.reported = Spend::default();
+        account.elsewhere = Spend::default();
+    }
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// The calendar month (UTC) of `now`, as "YYYY-MM"
+pub fn month_of(now: DateTime<Utc>) -> String {
+    now.format("%Y-%m").to_string()
+}
+
+/// Whether `spend` reached either of the budget's limits
+pub fn is_spent(budget: &ModelBudget, spend: &Spend) -> bool {
+    budget
+        .monthly_tokens
+        .is_some_and(|limit| spend.tokens >= limit)
+        || budget
+            .monthly_dollars
+            .is_some_and(|limit| spend.dollars >= limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_ledger_by_namespace_and_month() {
+        let budget = ModelBudget {
+            monthly_dollars: Some(1.0),
+            monthly_tokens: Some(100_000),
+            fallback: None,
+        };
+        let priced = Budget::new(budget.clone(), Some(0.01), None);
+        let usage = Usage {
+            prompt_tokens: 30_000,
+            completion_tokens: 20_000,
+            total_tokens: 50_000,
+        };
+        assert_eq!(
+            priced.price(&usage),
+            Spend {
+                tokens: 50_000,
+                dollars: 0.5
+            }
+        );
+
+        let october = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        let november = Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap();
+        assert_eq!(month_of(october), "2026-10");
+
+        let ledger = BudgetLedger::new();
+        ledger.open("team-a", "gpt", &budget);
+        ledger.open("team-b", "gpt", &budget);
+
+        ledger.charge("team-a", "gpt", priced.price(&usage), october);
+        assert!(!ledger.is_exhausted("team-a", "gpt", october));
+        ledger.charge("team-a", "gpt", priced.price(&usage), october);
+        assert!(ledger.is_exhausted("team-a", "gpt", october));
+
+        // Other namespaces and models without a budget are unaffected
+        assert!(!ledger.is_exhausted("team-b", "gpt", october));
+        assert!(!ledger.is_exhausted("team-a", "local", october));
+        ledger.charge("team-a", "local", priced.price(&usage), october);
+
+        let usage = ledger.usage(october);
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].namespace, "team-a");
+        assert_eq!(usage[0].tokens, 100_000);
+        assert!(usage[0].exhausted);
+        assert_eq!(usage[1].tokens, 0);
+
+        // A new month starts over
+        assert!(!ledger.is_exhausted("team-a", "gpt", november));
+        assert_eq!(ledger.usage(november)[0].month, "2026-11");
+    }
+
+    #[test]
+    fn test_ledger_counts_the_rest_of_the_cluster() {
+        let budget = ModelBudget {
+            monthly_dollars: None,
+            monthly_tokens: Some(1000),
+            fallback: None,
+        };
+        let october = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        let november = Utc.with_ymd_and_hms(2026, 11, 1, 0, 0, 0).unwrap();
+        let spent = |tokens| Spend {
+            tokens,
+            dollars: 0.0,
+        };
+        let total = |tokens| BudgetSpend {
+            namespace: "team-a".to_string(),
+            model: "gpt".to_string(),
+            month: "2026-10".to_string(),
+            tokens,
+            dollars: 0.0,
+        };
+
+        let ledger = BudgetLedger::new();
+        ledger.open("team-a", "gpt", &budget);
+        ledger.charge("team-a", "gpt", spent(300), october);
+
+        let report = ledger.unreported(october);
+        assert_eq!(report, [total(300)]);
+
+        // Spend between the report and the answer stays unreported
+        ledger.charge("team-a", "gpt", spent(100), october);
+        ledger.settle(&report, &[total(800)], october);
+        assert_eq!(ledger.unreported(october), [total(100)]);
+        assert_eq!(ledger.usage(october)[0].tokens, 900);
+        assert!(!ledger.is_exhausted("team-a", "gpt", october));
+
+        // Other workers' spend exhausts the budget here too
+        let report = ledger.unreported(october);
+        ledger.settle(&report, &[total(1000)], october);
+        assert_eq!(ledger.unreported(october), [total(0)]);
+        assert!(ledger.is_exhausted("team-a", "gpt", october));
+
+        // Totals for a month that's over are ignored
+        assert!(!ledger.is_exhausted("team-a", "gpt", november));
+        ledger.settle(&[], &[total(5000)], november);
+        assert!(!ledger.is_exhausted("team-a", "gpt", november));
+    }
+}
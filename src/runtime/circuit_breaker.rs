@@ -0,0 +1,345 @@
+//! Per-model circuit breaker
+//!
+//! Tracks failures for each upstream model. After too many consecutive
+//! failures, or a high error rate over recent calls, the breaker opens and
+//! requests fail fast until a cool-down elapses. The first request after the
+//! cool-down is let through as a probe: success closes the breaker, failure
+//! re-opens it.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Thresholds controlling when a breaker trips
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the breaker
+    pub failure_threshold: u32,
+    /// Error rate (0.0 - 1.0) over the window that opens the breaker
+    pub error_rate_threshold: f64,
+    /// Number of recent calls considered for the error rate
+    pub window_size: usize,
+    /// Minimum calls in the window before the error rate applies
+    pub min_requests: usize,
+    /// How long the breaker stays open before allowing a probe
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            error_rate_threshold: 0.5,
+            window_size: 20,
+            min_requests: 10,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum BreakerState {
+    /// Requests flow normally
+    Closed,
+    /// Requests fail fast until the cool-down ends
+    Open,
+    /// A single probe request is in flight
+    HalfOpen,
+}
+
+/// Outcome of asking a breaker to let a request through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// The breaker is open; fail fast
+    Rejected,
+    /// The breaker is closed
+    Admitted,
+    /// The caller holds the single half-open probe and must record an
+    /// outcome or release it
+    Probe,
+}
+
+/// Snapshot of a breaker for status reporting
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub error_rate: f64,
+    /// Seconds until a probe is allowed (only while open)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    /// Recent outcomes, true = success
+    window: VecDeque<bool>,
+    opened_at: Option<Instant>,
+}
+
+/// Circuit breaker for a single upstream model
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                window: VecDeque::new(),
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Check whether a request may proceed, claiming the probe slot if the
+    /// cool-down has elapsed
+    pub fn allow_request(&self) -> bool {
+        self.admit() != Admission::Rejected
+    }
+
+    /// Like [`allow_request`](Self::allow_request), but reports whether the
+    /// caller holds the probe slot and must settle it
+    pub fn admit(&self) -> Admission {
+        self.admit_at(Instant::now())
+    }
+
+    #[cfg(test)]
+    fn allow_request_at(&self, now: Instant) -> bool {
+        self.admit_at(now) != Admission::Rejected
+    }
+
+    fn admit_at(&self, now: Instant) -> Admission {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            BreakerState::Closed => Admission::Admitted,
+            BreakerState::HalfOpen => Admission::Rejected,
+            BreakerState::Open => {
+                let elapsed = inner
+                    .opened_at
+                    .map(|t| now.duration_since(t))
+                    .unwrap_or_default();
+                if elapsed >= self.config.cooldown {
+                    inner.state = BreakerState::HalfOpen;
+                    Admission::Probe
+                } else {
+                    Admission::Rejected
+                }
+            }
+        }
+    }
+
+    /// Give back a probe slot whose call ended without an outcome, e.g. when
+    /// it was cancelled. The breaker returns to open with its cool-down
+    /// already elapsed, so the next request probes again.
+    pub fn release_probe(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::HalfOpen {
+            inner.state = BreakerState::Open;
+        }
+    }
+
+    /// Whether requests would currently be rejected, without claiming a probe
+    pub fn is_open(&self) -> bool {
+        self.is_open_at(Instant::now())
+    }
+
+    fn is_open_at(&self, now: Instant) -> bool {
+        let inner = self.inner.lock().unwrap();
+        match inner.state {
+            BreakerState::Closed => false,
+            BreakerState::HalfOpen => true,
+            BreakerState::Open => inner
+                .opened_at
+                .map(|t| now.duration_since(t) < self.config.cooldown)
+                .unwrap_or(false),
+        }
+    }
+
+    /// Record a successful call
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures = 0;
+        self.push_outcome(&mut inner, true);
+        if inner.state != BreakerState::Closed {
+            // A successful probe starts over with a clean window
+            inner.state = BreakerState::Closed;
+            inner.opened_at = None;
+            inner.window.clear();
+        }
+    }
+
+    /// Record a failed call
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now());
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+        self.push_outcome(&mut inner, false);
+
+        let trip = inner.state == BreakerState::HalfOpen
+            || inner.consecutive_failures >= self.config.failure_threshold
+            || (inner.window.len() >= self.config.min_requests
+                && error_rate(&inner.window) >= self.config.error_rate_threshold);
+
+        if trip {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Some(now);
+        }
+    }
+
+    /// Snapshot the breaker for status reporting
+    pub fn status(&self) -> BreakerStatus {
+        let now = Instant::now();
+        let inner = self.inner.lock().unwrap();
+        let retry_after_secs = match (inner.state, inner.opened_at) {
+            (BreakerState::Open, Some(opened)) => Some(
+                self.config
+                    .cooldown
+                    .saturating_sub(now.duration_since(opened))
+                    .as_secs(),
+            ),
+            _ => None,
+        };
+        BreakerStatus {
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            error_rate: error_rate(&inner.window),
+            retry_after_secs,
+        }
+    }
+
+    fn push_outcome(&self, inner: &mut BreakerInner, success: bool) {
+        inner.window.push_back(success);
+        while inner.window.len() > self.config.window_size {
+            inner.window.pop_front();
+        }
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(CircuitBreakerConfig::default())
+    }
+}
+
+/// Fraction of failed outcomes in the window
+fn error_rate(window: &VecDeque<bool>) -> f64 {
+    if window.is_empty() {
+        return 0.0;
+    }
+    let failures = window.iter().filter(|ok| !**ok).count();
+    failures as f64 / window.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_threshold: 3,
+            error_rate_threshold: 0.5,
+            window_size: 10,
+            min_requests: 4,
+            cooldown: Duration::from_secs(10),
+        }
+    }
+
+    #[test]
+    fn test_trips_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(test_config());
+        let now = Instant::now();
+
+        breaker.record_failure_at(now);
+        breaker.record_failure_at(now);
+        assert!(breaker.allow_request_at(now));
+
+        breaker.record_failure_at(now);
+        assert!(!breaker.allow_request_at(now));
+        assert_eq!(breaker.status().state, BreakerState::Open);
+    }
+
+    #[test]
+    fn test_trips_on_error_rate() {
+        let breaker = CircuitBreaker::new(test_config());
+        let now = Instant::now();
+
+        // Alternating outcomes never hit 3 in a row but are 50% errors
+        breaker.record_success();
+        breaker.record_failure_at(now);
+        breaker.record_success();
+        assert!(breaker.allow_request_at(now));
+        breaker.record_failure_at(now);
+
+        assert!(!breaker.allow_request_at(now));
+    }
+
+    #[test]
+    fn test_half_open_probe_closes_on_success() {
+        let breaker = CircuitBreaker::new(test_config());
+        let now = Instant::now();
+        for _ in 0..3 {
+            breaker.record_failure_at(now);
+        }
+
+        let later = now + Duration::from_secs(11);
+        assert!(!breaker.is_open_at(later));
+        assert!(breaker.allow_request_at(later));
+        // Only one probe at a time
+        assert!(!breaker.allow_request_at(later));
+        assert_eq!(breaker.status().state, BreakerState::HalfOpen);
+
+        breaker.record_success();
+        assert_eq!(breaker.status().state, BreakerState::Closed);
+        assert!(breaker.allow_request_at(later));
+    }
+
+    #[test]
+    fn test_half_open_probe_reopens_on_failure() {
+        let breaker = CircuitBreaker::new(test_config());
+        let now = Instant::now();
+        for _ in 0..3 {
+            breaker.record_failure_at(now);
+        }
+
+        let later = now + Duration::from_secs(11);
+        assert!(breaker.allow_request_at(later));
+        breaker.record_failure_at(later);
+
+        assert!(!breaker.allow_request_at(later + Duration::from_secs(5)));
+        assert!(breaker.is_open_at(later + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_released_probe_can_be_claimed_again() {
+        let breaker = CircuitBreaker::new(test_config());
+        let now = Instant::now();
+        for _ in 0..3 {
+            breaker.record_failure_at(now);
+        }
+
+        let later = now + Duration::from_secs(11);
+        assert_eq!(breaker.admit_at(later), Admission::Probe);
+        assert_eq!(breaker.admit_at(later), Admission::Rejected);
+
+        // A cancelled probe must not leave the breaker stuck half-open
+        breaker.release_probe();
+        assert_eq!(breaker.status().state, BreakerState::Open);
+        assert_eq!(breaker.admit_at(later), Admission::Probe);
+    }
+}
@@ -0,0 +1,322 @@
+//! Dead letters: requests the pipeline failed to answer
+//!
+//! When a request fails (hops exhausted, a handler erroring, an open circuit
+//! breaker, ...) the processor keeps its original input, its trace and the
+//! error, so the prompt isn't lost with the response. Dead letters are
+//! listed with `GET /v1/deadletters` and sent through the pipeline again
+//! with `POST /v1/deadletters/{request_id}/requeue`; a requeued request that
+//! succeeds leaves the store.
+//!
+//! Workers keep dead letters in memory unless given a file with
+//! `--dead-letter-file`, in which case they survive restarts.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::client::{ImageUrl, Message, Tool, ToolChoice};
+use crate::runtime::request::PipelineRequest;
+use crate::runtime::trace::RequestTrace;
+
+/// Default number of dead letters a worker keeps
+pub const DEFAULT_DEAD_LETTER_CAPACITY: usize = 1000;
+
+/// Errors that can occur while reading or writing the dead letter file
+#[derive(Error, Debug)]
+pub enum DeadLetterError {
+    #[error("Dead letter I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid dead letter file: {0}")]
+    Invalid(#[from] serde_json::Error),
+}
+
+/// What the client sent, enough to run the request again
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetterInput {
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageUrl>,
+    /// Request variables, e.g. from `X-LLMNet-Var-*` headers
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
+    /// Earlier turns of the conversation the request continued
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_messages: Vec<Message>,
+    /// Node the client asked to be routed to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+}
+
+impl DeadLetterInput {
+    /// Capture a request before the pipeline runs it
+    pub fn capture(request: &PipelineRequest) -> Self {
+        Self {
+            prompt: request.original_prompt.clone(),
+            images: request.images.clone(),
+            variables: request.variables.clone(),
+            history: request.history.clone(),
+            tools: request.tools.clone(),
+            tool_choice: request.tool_choice.clone(),
+            tool_messages: request.tool_messages.clone(),
+            route: request.route.clone(),
+        }
+    }
+
+    /// A fresh request with the same ID and input
+    ///
+    /// System variables such as `TIMESTAMP` are set anew; the rest are
+    /// restored.
+    pub fn to_request(&self, request_id: Uuid) -> PipelineRequest {
+        let mut request = PipelineRequest::with_id(request_id, self.prompt.clone())
+            .with_images(self.images.clone())
+            .with_history(self.history.clone())
+            .with_tools(self.tools.clone(), self.tool_choice.clone())
+            .with_tool_messages(self.tool_messages.clone());
+        for (name, value) in &self.variables {
+            request
+                .variables
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if let Some(route) = &self.route {
+            request = request.with_route(route.clone());
+        }
+        request
+    }
+}
+
+/// A request the pipeline failed to answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetter {
+    pub request_id: Uuid,
+    pub input: DeadLetterInput,
+    /// The last failure
+    pub error: String,
+    /// Trace of the last attempt
+    pub trace: RequestTrace,
+    /// Times the request has failed, including requeues
+    pub attempts: u32,
+    pub failed_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DeadLetter {
+    pub fn new(input: DeadLetterInput, trace: RequestTrace) -> Self {
+        Self {
+            request_id: trace.request_id,
+            input,
+            error: trace.error.clone().unwrap_or_default(),
+            trace,
+            attempts: 1,
+            failed_at: chrono::Utc::now(),
+        }
+    }
+}
+
+// ============================================================================
+// SBIO: Pure functions
+// ============================================================================
+
+/// Add a dead letter, oldest first, evicting the oldest beyond `capacity`
+///
+/// A request that fails again replaces its earlier letter, moves to the end
+/// and counts the attempt.
+pub fn push_letter(letters: &mut Vec<DeadLetter>, mut letter: DeadLetter, capacity: usize) {
+    if let Some(pos) = letters
+        .iter()
+        .position(|l| l.request_id == letter.request_id)
+    {
+        letter.attempts += letters.remove(pos).attempts;
+    }
+    letters.push(letter);
+    let excess = letters.len().saturating_sub(capacity);
+    letters.drain(..excess);
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Dead letters, in memory or backed by a JSON file
+pub struct DeadLetterStore {
+    path: Option<PathBuf>,
+    capacity: usize,
+    letters: Mutex<Vec<DeadLetter>>,
+}
+
+impl DeadLetterStore {
+    /// Keep at most `capacity` dead letters in memory
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            path: None,
+            capacity,
+            letters: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Load dead letters from a file and keep it up to date; a missing
+    /// file means none yet
+    pub async fn open(path: impl Into<PathBuf>, capacity: usize) -> Result<Self, DeadLetterError> {
+        let path = path.into();
+        let letters = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => serde_json::from_str(&contents)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path: Some(path),
+            capacity,
+            letters: Mutex::new(letters),
+        })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Store a failed request
+    pub async fn record(&self, letter: DeadLetter) -> Result<(), DeadLetterError> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let mut letters = self.letters.lock().await;
+        push_letter(&mut letters, letter, self.capacity);
+        self.save(&letters).await
+    }
+
+    /// All dead letters, oldest first
+    pub async fn list(&self) -> Vec<DeadLetter> {
+        self.letters.lock().await.clone()
+    }
+
+    pub async fn get(&self, request_id: &Uuid) -> Option<DeadLetter> {
+        self.letters
+            .lock()
+            .await
+            .iter()
+            .find(|l| l.request_id == *request_id)
+            .cloned()
+    }
+
+    /// Drop a dead letter, returning it
+    pub async fn remove(&self, request_id: &Uuid) -> Result<Option<DeadLetter>, DeadLetterError> {
+        let mut letters = self.letters.lock().await;
+        let Some(pos) = letters.iter().position(|l| l.request_id == *request_id) else {
+            return Ok(None);
+        };
+        let letter = letters.remove(pos);
+        self.save(&letters).await?;
+        Ok(Some(letter))
+    }
+
+    async fn save(&self, letters: &[DeadLetter]) -> Result<(), DeadLetterError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_string_pretty(letters)?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Write aside and rename so a crash never leaves a truncated file
+        let tmp = path.with_extension("tmp");
+        // Only this user may read it: letters carry whole request payloads
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(&tmp).await?;
+        file.write_all(contents.as_bytes()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(())
+    }
+}
+
+impl Default for DeadLetterStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEAD_LETTER_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(prompt: &str) -> DeadLetter {
+        let request = PipelineRequest::new(prompt.to_string()).with_route("support");
+        let trace = RequestTrace::capture(&request, &Err::<String, _>("boom"));
+        DeadLetter::new(DeadLetterInput::capture(&request), trace)
+    }
+
+    #[test]
+    fn test_push_letter() {
+        let mut letters = Vec::new();
+        let first = failed("a");
+        push_letter(&mut letters, first.clone(), 2);
+        push_letter(&mut letters, failed("b"), 2);
+        push_letter(&mut letters, first.clone(), 2);
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[1].request_id, first.request_id);
+        assert_eq!(letters[1].attempts, 2);
+
+        push_letter(&mut letters, failed("c"), 2);
+        assert_eq!(letters.len(), 2);
+        assert_eq!(letters[0].request_id, first.request_id);
+        assert_eq!(letters[1].input.prompt, "c");
+    }
+
+    #[test]
+    fn test_input_round_trip() {
+        let letter = failed("hello");
+        assert_eq!(letter.error, "boom");
+
+        let request = letter.input.to_request(letter.request_id);
+        assert_eq!(request.request_id, letter.request_id);
+        assert_eq!(request.original_prompt, "hello");
+        assert_eq!(request.route.as_deref(), Some("support"));
+        assert_eq!(DeadLetterInput::capture(&request).prompt, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_store_persists_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("llmnet-dead-{}", Uuid::new_v4()));
+        let path = dir.join("dead-letters.json");
+
+        let store = DeadLetterStore::open(&path, 10).await.unwrap();
+        let letter = failed("hello");
+        store.record(letter.clone()).await.unwrap();
+        store.record(failed("again")).await.unwrap();
+
+        let reopened = DeadLetterStore::open(&path, 10).await.unwrap();
+        assert_eq!(reopened.list().await.len(), 2);
+        assert_eq!(reopened.get(&letter.request_id).await, Some(letter.clone()));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        reopened.remove(&letter.request_id).await.unwrap();
+        let store = DeadLetterStore::open(&path, 10).await.unwrap();
+        assert_eq!(store.list().await.len(), 1);
+        assert!(store.get(&letter.request_id).await.is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -4,7 +4,7 @@
 //! Docker containers with full configuration support including custom registries,
 //! GPU passthrough, and environment variable mapping.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -87,12 +87,25 @@ pub struct DockerConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gpus: Option<String>,
 
+    /// CPU limit in cores (e.g., 4.5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<f64>,
+
+    /// Memory limit (e.g., "64g", "512m")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+
+    /// Resource limits passed as `--ulimit name=value`
+    /// Example: {"memlock": "-1", "stack": "67108864", "nofile": "65536:65536"}
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub ulimits: HashMap<String, String>,
+
     /// IPC mode: "host", "private", "shareable"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ipc: Option<String>,
 
     /// Shared memory size (e.g., "16g")
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "shm-size", skip_serializing_if = "Option::is_none")]
     pub shm_size: Option<String>,
 
     /// Volume mounts: ["host:container", ...]
@@ -154,9 +167,73 @@ impl DockerConfig {
             _ => {}
         }
 
+        if let Some(cpus) = self.cpus {
+            if cpus <= 0.0 {
+                return Err(DockerError::ConfigError(format!(
+                    "'cpus' must be positive, got {}",
+                    cpus
+                )));
+            }
+        }
+
+        for (field, size) in [("memory", &self.memory), ("shm_size", &self.shm_size)] {
+            if let Some(size) = size {
+                if parse_memory_size(size).is_none() {
+                    return Err(DockerError::ConfigError(format!(
+                        "Invalid {} '{}' (expected e.g. \"512m\" or \"16g\")",
+                        field, size
+                    )));
+                }
+            }
+        }
+
         Ok(())
     }
 
+    /// Warn about limits the host cannot satisfy
+    ///
+    /// Docker refuses to start a container whose CPU or GPU request exceeds
+    /// the host, and a memory limit above physical memory is never enforced.
+    pub fn capacity_warnings(&self, host: &HostCapacity) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if let Some(cpus) = self.cpus {
+            if host.cpus > 0 && cpus > host.cpus as f64 {
+                warnings.push(format!(
+                    "cpus limit {} exceeds the host's {} cores",
+                    cpus, host.cpus
+                ));
+            }
+        }
+
+        let host_memory = (host.memory > 0).then_some(host.memory);
+        for (field, size) in [("memory", &self.memory), ("shm_size", &self.shm_size)] {
+            let requested = size.as_deref().and_then(parse_memory_size);
+            if let (Some(requested), Some(available)) = (requested, host_memory) {
+                if requested > available {
+                    warnings.push(format!(
+                        "{} limit {} exceeds the host's {} of memory",
+                        field,
+                        size.as_deref().unwrap_or_default(),
+                        format_memory_size(available)
+                    ));
+                }
+            }
+        }
+
+        let requested = self.gpus.as_deref().and_then(requested_gpu_count);
+        if let (Some(requested), Some(available)) = (requested, host.gpus) {
+            if requested > available {
+                warnings.push(format!(
+                    "gpus requests {} GPU(s) but {} detected on this host",
+                    requested, available
+                ));
+            }
+        }
+
+        warnings
+    }
+
     /// Check if this config uses a Dockerfile (needs build)
     pub fn needs_build(&self) -> bool {
         self.dockerfile.is_some()
@@ -173,10 +250,71 @@ impl DockerConfig {
     }
 }
 
+/// Resources available on the host that will run the containers
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HostCapacity {
+    /// Logical CPU cores
+    pub cpus: u32,
+    /// Physical memory in bytes
+    pub memory: u64,
+    /// NVIDIA GPUs, if they could be counted
+    pub gpus: Option<u32>,
+}
+
 // ============================================================================
 // SBIO: Pure business logic (no I/O)
 // ============================================================================
 
+/// Parse a Docker memory size ("512m", "16g", "1073741824") into bytes
+pub fn parse_memory_size(size: &str) -> Option<u64> {
+    let size = size.trim().to_lowercase();
+    let size = size.strip_suffix('b').unwrap_or(&size);
+    let (digits, multiplier) = match size.chars().last()? {
+        'k' => (&size[..size.len() - 1], 1024),
+        'm' => (&size[..size.len() - 1], 1024 * 1024),
+        'g' => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        't' => (&size[..size.len() - 1], 1024 * 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Format a byte count the way Docker sizes are written (e.g. "16g")
+pub fn format_memory_size(bytes: u64) -> String {
+    const GIB: u64 = 1024 * 1024 * 1024;
+    if bytes >= GIB {
+        format!("{:.1}g", bytes as f64 / GIB as f64)
+    } else {
+        format!("{}m", bytes / (1024 * 1024))
+    }
+}
+
+/// Convert the `gpus` setting into a `docker run --gpus` value
+///
+/// A bare count or "all" is passed through. Device IDs ("0,1") need
+/// Docker's `device=` form, quoted so the comma is not read as a separator.
+pub fn gpus_arg(gpus: &str) -> String {
+    let gpus = gpus.trim();
+    if gpus == "all" || gpus.parse::<u32>().is_ok() || gpus.contains('=') {
+        gpus.to_string()
+    } else {
+        format!("\"device={}\"", gpus.replace(' ', ""))
+    }
+}
+
+/// How many GPUs a `gpus` setting asks for, if it names a specific number
+fn requested_gpu_count(gpus: &str) -> Option<u32> {
+    let gpus = gpus.trim().trim_matches('"');
+    if gpus == "all" {
+        return None;
+    }
+    if let Ok(count) = gpus.parse::<u32>() {
+        return Some(count);
+    }
+    let devices = gpus.strip_prefix("device=").unwrap_or(gpus);
+    Some(devices.split(',').filter(|d| !d.trim().is_empty()).count() as u32)
+}
+
 /// Convert a parameter key to environment variable format
 /// e.g., "tensor_parallel_size" -> "TENSOR_PARALLEL_SIZE"
 pub fn param_to_env_var(key: &str) -> String {
@@ -193,12 +331,9 @@ pub fn extra_args_to_string(args: &HashMap<String, Value>) -> String {
         let flag = format!("--{}", key);
 
         match value {
-            Value::Bool(b) => {
-                if *b {
-                    parts.push(flag);
-                }
-                // false bools are omitted entirely
-            }
+            Value::Bool(true) => parts.push(flag),
+            // false bools are omitted entirely
+            Value::Bool(false) => {}
             Value::Number(n) => {
                 parts.push(flag);
                 parts.push(n.to_string());
@@ -263,7 +398,23 @@ pub fn generate_run_args(
     // GPU configuration
     if let Some(gpus) = &config.gpus {
         args.push("--gpus".to_string());
-        args.push(gpus.clone());
+        args.push(gpus_arg(gpus));
+    }
+
+    // Resource limits
+    if let Some(cpus) = config.cpus {
+        args.push("--cpus".to_string());
+        args.push(cpus.to_string());
+    }
+    if let Some(memory) = &config.memory {
+        args.push("--memory".to_string());
+        args.push(memory.clone());
+    }
+    let mut ulimits: Vec<_> = config.ulimits.iter().collect();
+    ulimits.sort();
+    for (name, value) in ulimits {
+        args.push("--ulimit".to_string());
+        args.push(format!("{}={}", name, value));
     }
 
     // IPC mode
@@ -392,6 +543,26 @@ pub fn generate_pull_args(image: &str, registry: &Option<RegistryConfig>) -> Vec
     vec!["pull".to_string(), full_image]
 }
 
+/// Add `-e NAME` flags to `docker run` arguments for each variable of `env`
+///
+/// Only names go on the command line; docker copies the values from its own
+/// environment, so secrets don't show up in process listings. The flags go
+/// right after `run`, so a model's own `-e` settings take precedence.
+pub fn with_passed_env(mut args: Vec<String>, env: &BTreeMap<String, String>) -> Vec<String> {
+    let at = args.iter().position(|a| a == "run").map_or(0, |i| i + 1);
+    let flags = env.keys().flat_map(|name| ["-e".to_string(), name.clone()]);
+    args.splice(at..at, flags);
+    args
+}
+
+/// Value following the flag `name` in `docker run` arguments
+pub fn flag_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
 /// Generate Docker stop arguments
 pub fn generate_stop_args(container_name: &str) -> Vec<String> {
     vec!["stop".to_string(), container_name.to_string()]
@@ -406,6 +577,28 @@ pub fn generate_rm_args(container_name: &str) -> Vec<String> {
     ]
 }
 
+/// Generate Docker ps arguments listing the names of running containers
+pub fn generate_ps_names_args() -> Vec<String> {
+    vec![
+        "ps".to_string(),
+        "--format".to_string(),
+        "{{.Names}}".to_string(),
+    ]
+}
+
+/// Generate Docker ps arguments listing the names of all containers whose
+/// name contains `filter`, stopped ones included
+pub fn generate_ps_all_names_args(filter: &str) -> Vec<String> {
+    vec![
+        "ps".to_string(),
+        "-a".to_string(),
+        "--filter".to_string(),
+        format!("name={}", filter),
+        "--format".to_string(),
+        "{{.Names}}".to_string(),
+    ]
+}
+
 /// Expand environment variables and home directory in a string
 /// Supports ${VAR} syntax and ~ for home directory
 pub fn expand_env_vars(input: &str) -> String {
@@ -466,6 +659,39 @@ pub fn generate_container_name(prefix: &str, model_name: &str) -> String {
     format!("{}-{}", prefix, sanitized)
 }
 
+// ============================================================================
+// I/O: Host detection
+// ============================================================================
+
+/// Detect the CPU, memory and GPUs of the current host
+pub fn detect_host_capacity() -> HostCapacity {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    HostCapacity {
+        cpus: std::thread::available_parallelism()
+            .map(|n| n.get() as u32)
+            .unwrap_or(0),
+        memory: system.total_memory(),
+        gpus: detect_gpu_count(),
+    }
+}
+
+/// Count NVIDIA GPUs (a host without the driver has none)
+#[cfg(feature = "gpu")]
+fn detect_gpu_count() -> Option<u32> {
+    Some(
+        nvml_wrapper::Nvml::init()
+            .and_then(|nvml| nvml.device_count())
+            .unwrap_or(0),
+    )
+}
+
+/// GPUs cannot be counted without NVML
+#[cfg(not(feature = "gpu"))]
+fn detect_gpu_count() -> Option<u32> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -605,6 +831,102 @@ mod tests {
             .any(|a| a.contains("VLLM_EXTRA_ARGS=--swap-space 32")));
     }
 
+    #[test]
+    fn test_generate_run_args_resource_limits() {
+        let config: DockerConfig = serde_json::from_str(
+            r#"{
+                "image": "vllm/vllm-openai",
+                "gpus": "0,1",
+                "cpus": 8.5,
+                "memory": "64g",
+                "shm-size": "16g",
+                "ulimits": {"stack": "67108864", "memlock": "-1"}
+            }"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.shm_size.as_deref(), Some("16g"));
+
+        let args = generate_run_args(&config, "m", 8000, &HashMap::new(), "c");
+        let joined = args.join(" ");
+        assert!(joined.contains("--gpus \"device=0,1\""));
+        assert!(joined.contains("--cpus 8.5"));
+        assert!(joined.contains("--memory 64g"));
+        assert!(joined.contains("--shm-size 16g"));
+        // Sorted, so the command line is stable across runs
+        assert!(joined.contains("--ulimit memlock=-1 --ulimit stack=67108864"));
+    }
+
+    #[test]
+    fn test_validate_resource_limits() {
+        let bad_memory = DockerConfig {
+            image: Some("image".to_string()),
+            memory: Some("lots".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_memory.validate().is_err());
+
+        let bad_cpus = DockerConfig {
+            image: Some("image".to_string()),
+            cpus: Some(0.0),
+            ..Default::default()
+        };
+        assert!(bad_cpus.validate().is_err());
+    }
+
+    #[test]
+    fn test_gpus_arg() {
+        assert_eq!(gpus_arg("all"), "all");
+        assert_eq!(gpus_arg("2"), "2");
+        assert_eq!(gpus_arg("0,1"), "\"device=0,1\"");
+        assert_eq!(gpus_arg("0, 1"), "\"device=0,1\"");
+        assert_eq!(gpus_arg("device=GPU-3a1b"), "device=GPU-3a1b");
+    }
+
+    #[test]
+    fn test_parse_memory_size() {
+        assert_eq!(parse_memory_size("512m"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_memory_size("16G"), Some(16 * 1024 * 1024 * 1024));
+        assert_eq!(parse_memory_size("1gb"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_memory_size("4096"), Some(4096));
+        assert_eq!(parse_memory_size("lots"), None);
+        assert_eq!(parse_memory_size(""), None);
+    }
+
+    #[test]
+    fn test_capacity_warnings() {
+        let host = HostCapacity {
+            cpus: 8,
+            memory: 32 * 1024 * 1024 * 1024,
+            gpus: Some(1),
+        };
+
+        let fits = DockerConfig {
+            cpus: Some(4.0),
+            memory: Some("16g".to_string()),
+            gpus: Some("all".to_string()),
+            ..Default::default()
+        };
+        assert!(fits.capacity_warnings(&host).is_empty());
+
+        let too_big = DockerConfig {
+            cpus: Some(16.0),
+            memory: Some("64g".to_string()),
+            shm_size: Some("48g".to_string()),
+            gpus: Some("0,1".to_string()),
+            ..Default::default()
+        };
+        let warnings = too_big.capacity_warnings(&host);
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].contains("16 exceeds the host's 8 cores"));
+        assert!(warnings[1].contains("memory limit 64g exceeds the host's 32.0g"));
+        assert!(warnings[3].contains("2 GPU(s) but 1 detected"));
+
+        // Unknown GPU count (built without NVML) never warns
+        let unknown = HostCapacity { gpus: None, ..host };
+        assert_eq!(too_big.capacity_warnings(&unknown).len(), 3);
+    }
+
     #[test]
     fn test_generate_build_args() {
         let args = generate_build_args("./Dockerfile", ".", "my-image:latest");
@@ -670,9 +992,45 @@ mod tests {
         assert_eq!(args, vec!["stop", "my-container"]);
     }
 
+    #[test]
+    fn test_with_passed_env() {
+        let env = BTreeMap::from([
+            ("HF_TOKEN".to_string(), "hf_secret".to_string()),
+            ("LOG_LEVEL".to_string(), "debug".to_string()),
+        ]);
+        let run = vec!["run".to_string(), "-d".to_string(), "image".to_string()];
+        assert_eq!(with_passed_env(run.clone(), &BTreeMap::new()), run);
+
+        let args = with_passed_env(run, &env);
+        assert_eq!(
+            args,
+            ["run", "-e", "HF_TOKEN", "-e", "LOG_LEVEL", "-d", "image"]
+        );
+        assert!(!args.iter().any(|a| a.contains("hf_secret")));
+    }
+
     #[test]
     fn test_generate_rm_args() {
         let args = generate_rm_args("my-container");
         assert_eq!(args, vec!["rm", "-f", "my-container"]);
     }
+
+    #[test]
+    fn test_generate_ps_names_args() {
+        let args = generate_ps_names_args();
+        assert_eq!(args, vec!["ps", "--format", "{{.Names}}"]);
+
+        let args = generate_ps_all_names_args("llmnet-");
+        assert_eq!(
+            args,
+            vec![
+                "ps",
+                "-a",
+                "--filter",
+                "name=llmnet-",
+                "--format",
+                "{{.Names}}"
+            ]
+        );
+    }
 }
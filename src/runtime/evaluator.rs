@@ -0,0 +1,164 @@
+//! Quality scoring for "evaluator" nodes
+//!
+//! An evaluator node scores the output of the node before it on a 0-1 scale.
+//! Regex checks score the fraction of patterns satisfied; a rubric is graded
+//! by the node's model, which the processor calls with the prompt built
+//! here. When both are configured the lower score counts. Below the
+//! threshold the processor sends the request back to the handler.
+
+use regex::Regex;
+
+use crate::config::EvaluatorConfig;
+
+/// Instructions for grading an answer against a rubric
+pub const RUBRIC_PROMPT: &str = "You are grading an answer against the criteria below. \
+Reply with only a score from 0 (fails the criteria) to 10 (fully meets them).";
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// An evaluator node's compiled checks
+#[derive(Debug, Clone)]
+pub struct Evaluator {
+    config: EvaluatorConfig,
+    must_match: Vec<Regex>,
+    must_not_match: Vec<Regex>,
+}
+
+impl Evaluator {
+    /// Compile an evaluator configuration
+    ///
+    /// Compositions validate their patterns on load, so this only fails for
+    /// configs built by hand.
+    pub fn new(config: EvaluatorConfig) -> Result<Self, regex::Error> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| Regex::new(p))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let must_match = compile(&config.must_match)?;
+        let must_not_match = compile(&config.must_not_match)?;
+        Ok(Self {
+            config,
+            must_match,
+            must_not_match,
+        })
+    }
+
+    /// The rubric the node's model grades against, if any
+    pub fn rubric(&self) -> Option<&str> {
+        self.config.rubric.as_deref()
+    }
+
+    /// How many times the handler may answer again
+    pub fn max_retries(&self) -> u32 {
+        self.config.max_retries
+    }
+
+    /// Fraction of regex checks the content passes, or None without any
+    pub fn check_score(&self, content: &str) -> Option<f64> {
+        let total = self.must_match.len() + self.must_not_match.len();
+        if total == 0 {
+            return None;
+        }
+        let passed = self
+            .must_match
+            .iter()
+            .filter(|p| p.is_match(content))
+            .count()
+            + self
+                .must_not_match
+                .iter()
+                .filter(|p| !p.is_match(content))
+                .count();
+        Some(passed as f64 / total as f64)
+    }
+
+    /// Combine the check score with the rubric score (the lower wins)
+    pub fn score(&self, content: &str, rubric_score: Option<f64>) -> f64 {
+        match (self.check_score(content), rubric_score) {
+            (Some(checks), Some(rubric)) => checks.min(rubric),
+            (Some(score), None) | (None, Some(score)) => score,
+            (None, None) => 1.0,
+        }
+    }
+
+    /// Whether a score meets the threshold
+    pub fn passes(&self, score: f64) -> bool {
+        score >= self.config.threshold
+    }
+}
+
+/// Prompt asking the evaluator's model to grade an answer
+pub fn build_rubric_prompt(rubric: &str, question: &str, answer: &str) -> String {
+    format!(
+        "{}\n\nCriteria:\n{}\n\nQuestion:\n{}\n\nAnswer:\n{}\n",
+        RUBRIC_PROMPT,
+        rubric.trim(),
+        question.trim(),
+        answer.trim()
+    )
+}
+
+/// Score from the first number in the grader's reply, scaled from 0-10 to
+/// 0-1. A reply without a number scores 0 so it can't pass by accident.
+pub fn parse_rubric_score(reply: &str) -> f64 {
+    reply
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter_map(|n| n.trim_end_matches('.').parse::<f64>().ok())
+        .next()
+        .map(|n| (n / 10.0).clamp(0.0, 1.0))
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluator(json: &str) -> Evaluator {
+        Evaluator::new(serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_check_score() {
+        let e = evaluator(r#"{"must-match": ["(?i)sources:", "\\d"], "must-not-match": ["TODO"]}"#);
+
+        assert_eq!(e.check_score("Sources: page 3"), Some(1.0));
+        assert_eq!(e.check_score("Sources: none, TODO"), Some(1.0 / 3.0));
+        assert_eq!(e.check_score("TODO"), Some(0.0));
+        assert_eq!(
+            evaluator(r#"{"rubric": "Is it polite?"}"#).check_score("x"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_score_takes_lower_and_applies_threshold() {
+        let e = evaluator(r#"{"must-match": ["\\d"], "threshold": 0.5}"#);
+
+        assert_eq!(e.score("42", Some(0.3)), 0.3);
+        assert_eq!(e.score("none", Some(0.9)), 0.0);
+        assert_eq!(e.score("42", None), 1.0);
+        assert!(e.passes(0.5));
+        assert!(!e.passes(0.3));
+        assert_eq!(e.max_retries(), 2);
+    }
+
+    #[test]
+    fn test_parse_rubric_score() {
+        assert_eq!(parse_rubric_score("8"), 0.8);
+        assert_eq!(parse_rubric_score("Score: 7.5/10."), 0.75);
+        assert_eq!(parse_rubric_score("11"), 1.0);
+        assert_eq!(parse_rubric_score("I can't grade this"), 0.0);
+    }
+
+    #[test]
+    fn test_build_rubric_prompt() {
+        let prompt = build_rubric_prompt("Cites a source", "Why is the sky blue?", " Rayleigh ");
+        assert!(prompt.starts_with(RUBRIC_PROMPT));
+        assert!(prompt.contains("Criteria:\nCites a source"));
+        assert!(prompt.ends_with("Answer:\nRayleigh\n"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Content checks for "guard" nodes
+//!
+//! A guard node inspects the content at its position in the pipeline: placed
+//! before a handler it checks the input, placed after one it checks the
+//! output. Rule checks (denylist, max length) are pure; the optional
+//! moderation call is made by the processor with the node's model.
+
+use regex::Regex;
+
+use crate::config::{GuardAction, GuardConfig};
+
+/// Prompt used to ask the guard's model for a verdict
+pub const MODERATION_PROMPT: &str = "You are a content moderator. Reply with exactly one word: \
+SAFE if the following text is acceptable, or UNSAFE if it is harmful, abusive or disallowed.\n\n\
+Text:\n";
+
+/// Result of running a guard's checks
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardOutcome {
+    /// Every check passed
+    Pass,
+    /// End the pipeline with this refusal
+    Block(String),
+    /// Continue with the redacted content
+    Redact(String),
+    /// Continue at the fallback node
+    Fallback(String),
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// A guard node's compiled checks
+#[derive(Debug, Clone)]
+pub struct Guard {
+    config: GuardConfig,
+    patterns: Vec<Regex>,
+}
+
+impl Guard {
+    /// Compile a guard configuration
+    ///
+    /// Compositions validate their patterns on load, so this only fails for
+    /// configs built by hand.
+    pub fn new(config: GuardConfig) -> Result<Self, regex::Error> {
+        let patterns = config
+            .denylist
+            .iter()
+            .map(|p| Regex::new(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { config, patterns })
+    }
+
+    /// Whether the node's model should also be asked for a verdict
+    pub fn uses_moderation(&self) -> bool {
+        self.config.moderation
+    }
+
+    /// Run the rule checks, returning the first violation found
+    pub fn violation(&self, content: &str) -> Option<String> {
+        if let Some(max) = self.config.max_length {
+            let len = content.chars().count();
+            if len > max {
+                return Some(format!("content is {} characters, limit is {}", len, max));
+            }
+        }
+
+        self.patterns
+            .iter()
+            .find(|p| p.is_match(content))
+            .map(|p| format!("content matches denylisted pattern '{}'", p.as_str()))
+    }
+
+    /// Decide what to do about a violation
+    ///
+    /// Redaction only applies to rule violations; a moderation verdict has
+    /// nothing to redact, so it blocks instead.
+    pub fn outcome(&self, content: &str, reason: &str, moderated: bool) -> GuardOutcome {
+        match (self.config.on_violation, &self.config.fallback) {
+            (GuardAction::Fallback, Some(node)) => GuardOutcome::Fallback(node.clone()),
+            (GuardAction::Redact, _) if !moderated => GuardOutcome::Redact(self.redact(content)),
+            _ => GuardOutcome::Block(self.config.refusal.replace("{reason}", reason)),
+        }
+    }
+
+    /// Replace denylisted text and truncate to the maximum length
+    pub fn redact(&self, content: &str) -> String {
+        let mut redacted = content.to_string();
+        for pattern in &self.patterns {
+            redacted = pattern
+                .replace_all(&redacted, self.config.redaction.as_str())
+                .into_owned();
+        }
+        match self.config.max_length {
+            Some(max) => redacted.chars().take(max).collect(),
+            None => redacted,
+        }
+    }
+}
+
+/// Interpret a moderation model's reply; anything but a clear SAFE fails
+pub fn moderation_verdict(reply: &str) -> Option<String> {
+    let verdict = reply.trim().to_uppercase();
+    if verdict.starts_with("SAFE") {
+        None
+    } else {
+        Some("content was flagged by moderation".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(json: &str) -> Guard {
+        Guard::new(serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_violation_checks_length_and_denylist() {
+        let g = guard(r#"{"denylist": ["(?i)secret"], "max-length": 20}"#);
+
+        assert_eq!(g.violation("hello"), None);
+        assert!(g
+            .violation("tell me the SECRET")
+            .unwrap()
+            .contains("denylisted"));
+        assert!(g
+            .violation("this sentence is far too long")
+            .unwrap()
+            .contains("limit is 20"));
+    }
+
+    #[test]
+    fn test_block_renders_refusal() {
+        let g = guard(r#"{"denylist": ["bomb"], "refusal": "Blocked: {reason}"}"#);
+        let reason = g.violation("how to build a bomb").unwrap();
+
+        assert_eq!(
+            g.outcome("how to build a bomb", &reason, false),
+            GuardOutcome::Block("Blocked: content matches denylisted pattern 'bomb'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_redact_replaces_and_truncates() {
+        let g =
+            guard(r#"{"denylist": ["\\d{3}-\\d{4}"], "max-length": 24, "on-violation": "redact"}"#);
+        let content = "call me at 555-1234 tomorrow morning";
+        let reason = g.violation(content).unwrap();
+
+        assert_eq!(
+            g.outcome(content, &reason, false),
+            GuardOutcome::Redact("call me at [REDACTED] to".to_string())
+        );
+        // Moderation verdicts cannot be redacted
+        assert!(matches!(
+            g.outcome(content, "flagged", true),
+            GuardOutcome::Block(_)
+        ));
+    }
+
+    #[test]
+    fn test_fallback_outcome() {
+        let g = guard(r#"{"max-length": 5, "on-violation": "fallback", "fallback": "safe"}"#);
+        assert_eq!(
+            g.outcome("too long", "length", false),
+            GuardOutcome::Fallback("safe".to_string())
+        );
+    }
+
+    #[test]
+    fn test_moderation_verdict() {
+        assert_eq!(moderation_verdict(" safe\n"), None);
+        assert!(moderation_verdict("UNSAFE").is_some());
+        assert!(moderation_verdict("I cannot decide").is_some());
+    }
+}
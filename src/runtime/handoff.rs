@@ -0,0 +1,139 @@
+//! Pipeline handoff for "handoff" nodes
+//!
+//! A handoff node sends the content that reached it to another llmnet
+//! pipeline through a control plane's proxy
+//! (`/v1/namespaces/{namespace}/pipelines/{name}/chat/completions`) and
+//! passes that pipeline's answer downstream. The other pipeline can run in
+//! this cluster or another one, so pipelines compose without copying each
+//! other's architecture blocks.
+//!
+//! Each handoff tells the next pipeline how many it took to get there, and
+//! a request that has been handed off [`MAX_HANDOFF_DEPTH`] times isn't
+//! handed off again, so pipelines handing off to each other can't loop.
+
+use crate::client::{ChatCompletionRequest, ChatCompletionResponse, ClientError, Message};
+use crate::config::HandoffConfig;
+
+/// Header carrying the number of handoffs a request took to reach a
+/// pipeline
+pub const HANDOFF_DEPTH_HEADER: &str = "x-llmnet-handoff-depth";
+
+/// Most handoffs a request may take
+pub const MAX_HANDOFF_DEPTH: u32 = 4;
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Chat completion URL of the pipeline a handoff node forwards to
+pub fn handoff_url(config: &HandoffConfig) -> String {
+    format!(
+        "{}/v1/namespaces/{}/pipelines/{}/chat/completions",
+        config.url.trim_end_matches('/'),
+        config.namespace,
+        config.pipeline
+    )
+}
+
+/// Chat completion asking the pipeline about `input`
+pub fn handoff_request(config: &HandoffConfig, input: &str) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: config.pipeline.clone(),
+        messages: vec![Message {
+            role: "user".to_string(),
+            content: input.to_string(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+/// Handoff depth a request arrived with, from its headers
+pub fn parse_handoff_depth(value: Option<&str>) -> u32 {
+    value.and_then(|v| v.trim().parse().ok()).unwrap_or(0)
+}
+
+// ============================================================================
+// I/O: Handing off
+// ============================================================================
+
+/// Client for the pipeline a handoff node forwards to
+pub struct HandoffClient {
+    config: HandoffConfig,
+    http: reqwest::Client,
+}
+
+impl HandoffClient {
+    pub fn new(config: HandoffConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// The pipeline's answer to `input`, for a request on its `depth`th
+    /// handoff
+    pub async fn send(&self, input: &str, depth: u32) -> Result<String, ClientError> {
+        let mut request = self
+            .http
+            .post(handoff_url(&self.config))
+            .header(HANDOFF_DEPTH_HEADER, depth.to_string())
+            .json(&handoff_request(&self.config, input));
+        if let Some(key) = &self.config.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ClientError::Http(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(ClientError::Api {
+                status: status.as_u16(),
+                message,
+            });
+        }
+
+        let completion: ChatCompletionResponse = response
+            .json()
+            .await
+            .map_err(|e| ClientError::Parse(e.to_string()))?;
+        Ok(completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content)
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handoff_request() {
+        let config: HandoffConfig = serde_json::from_str(
+            r#"{"url": "http://cp:8181/", "namespace": "billing", "pipeline": "invoices"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            handoff_url(&config),
+            "http://cp:8181/v1/namespaces/billing/pipelines/invoices/chat/completions"
+        );
+
+        let request = handoff_request(&config, "Where is my refund?");
+        assert_eq!(request.model, "invoices");
+        assert_eq!(request.messages.len(), 1);
+        assert_eq!(request.messages[0].content, "Where is my refund?");
+
+        assert_eq!(parse_handoff_depth(Some("2")), 2);
+        assert_eq!(parse_handoff_depth(Some("many")), 0);
+        assert_eq!(parse_handoff_depth(None), 0);
+    }
+}
@@ -0,0 +1,170 @@
+//! Hedged requests
+//!
+//! A handler with a `hedge` keeps the latencies of its model's recent
+//! answers. When a call runs past the configured percentile of them, the
+//! same request is sent to the fallback model as well and whichever answers
+//! first wins. Every hedged call is paid for out of the pipeline's retry
+//! budget, which each handler call tops up by a fraction of a call, so
+//! hedging can't multiply the pipeline's cost when an upstream slows down.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::client::ModelClient;
+use crate::config::{HedgeConfig, RetryBudgetConfig};
+
+/// Recent latencies kept per hedged node
+pub const LATENCY_WINDOW: usize = 100;
+
+/// Latencies needed before the percentile replaces `min-delay-ms`
+pub const MIN_LATENCY_SAMPLES: usize = 20;
+
+/// A node's hedge: the fallback model and its model's recent latencies
+pub struct Hedge {
+    config: HedgeConfig,
+    client: ModelClient,
+    latencies: Mutex<VecDeque<Duration>>,
+}
+
+impl Hedge {
+    /// Hedge with `client`, the fallback model's client
+    pub fn new(config: HedgeConfig, client: ModelClient) -> Self {
+        Self {
+            config,
+            client,
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+        }
+    }
+
+    /// Client of the fallback model
+    pub fn client(&self) -> &ModelClient {
+        &self.client
+    }
+
+    /// Name of the fallback model in the composition's models
+    pub fn fallback(&self) -> &str {
+        &self.config.fallback
+    }
+
+    /// Remember how long the node's model took to answer
+    pub fn record(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    /// How long to wait for the node's model before hedging
+    pub fn delay(&self) -> Duration {
+        let min_delay = Duration::from_millis(self.config.min_delay_ms);
+        let latencies = self.latencies.lock().unwrap();
+        if latencies.len() < MIN_LATENCY_SAMPLES {
+            return min_delay;
+        }
+        let samples: Vec<Duration> = latencies.iter().copied().collect();
+        percentile(&samples, self.config.percentile).max(min_delay)
+    }
+}
+
+/// Extra calls a pipeline's hedges may still make
+#[derive(Debug)]
+pub struct RetryBudget {
+    config: RetryBudgetConfig,
+    balance: Mutex<f64>,
+}
+
+impl RetryBudget {
+    /// A budget starting with its full burst saved up
+    pub fn new(config: RetryBudgetConfig) -> Self {
+        Self {
+            balance: Mutex::new(config.burst as f64),
+            config,
+        }
+    }
+
+    /// Earn a fraction of a call for a handler call
+    pub fn deposit(&self) {
+        let mut balance = self.balance.lock().unwrap();
+        *balance = (*balance + self.config.ratio).min(self.config.burst as f64);
+    }
+
+    /// Spend a call, if one is left
+    pub fn try_withdraw(&self) -> bool {
+        let mut balance = self.balance.lock().unwrap();
+        if *balance < 1.0 {
+            return false;
+        }
+        *balance -= 1.0;
+        true
+    }
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(RetryBudgetConfig::default())
+    }
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// The `p`th percentile (0 - 100) of `samples` by nearest rank
+pub fn percentile(samples: &[Duration], p: f64) -> Duration {
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted
+        .get(rank.clamp(1, sorted.len().max(1)) - 1)
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::OpenAiClient;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_delay_and_budget() {
+        let samples: Vec<Duration> = (1..=100).map(ms).collect();
+        assert_eq!(percentile(&samples, 95.0), ms(95));
+        assert_eq!(percentile(&samples, 100.0), ms(100));
+        assert_eq!(percentile(&[], 95.0), Duration::ZERO);
+
+        let config = HedgeConfig {
+            fallback: "backup".to_string(),
+            percentile: 90.0,
+            min_delay_ms: 50,
+        };
+        let client = OpenAiClient::new("http://b".to_string(), None, "backup".to_string());
+        let hedge = Hedge::new(config, client.into());
+
+        // Too few latencies to trust yet
+        hedge.record(ms(500));
+        assert_eq!(hedge.delay(), ms(50));
+        for latency in 1..=200 {
+            hedge.record(ms(latency));
+        }
+        assert_eq!(hedge.delay(), ms(190));
+
+        let budget = RetryBudget::new(RetryBudgetConfig {
+            ratio: 0.5,
+            burst: 1,
+        });
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+        budget.deposit();
+        assert!(!budget.try_withdraw());
+        budget.deposit();
+        budget.deposit();
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+    }
+}
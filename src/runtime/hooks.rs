@@ -2,16 +2,22 @@
 //!
 //! This module executes pre/post hooks on architecture nodes.
 //! Hooks can run in observe mode (fire-and-forget) or transform mode (modifies data).
+//! Stream-transform post-hooks rewrite a streamed answer chunk by chunk.
+//! Each call is cut off after the hook's `timeout_ms` and counted per
+//! function, so slow hooks show up in the worker's `GET /status`.
 
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use serde::Serialize;
 use serde_json::Value;
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
+use utoipa::ToSchema;
 
 use crate::config::architecture::{FailureAction, HookConfig, HookMode, NodeHooks};
-use crate::config::functions::{FunctionExecutor, FunctionType};
+use crate::config::functions::{FunctionError, FunctionExecutor, FunctionResult, FunctionType};
 
 /// Errors during hook execution
 #[derive(Error, Debug)]
@@ -82,10 +88,111 @@ impl HookContext {
     }
 }
 
+/// Hook calls to one function, for status reporting
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct HookStats {
+    pub calls: u64,
+    /// Calls that failed, timeouts included
+    pub failures: u64,
+    pub timeouts: u64,
+    pub avg_duration_ms: u64,
+    pub max_duration_ms: u64,
+}
+
+/// How a hook call ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookOutcome {
+    Succeeded,
+    Failed,
+    TimedOut,
+}
+
+/// Hook call counters by function name
+#[derive(Debug, Default)]
+pub struct HookMetrics {
+    functions: Mutex<HashMap<String, (HookStats, u64)>>,
+}
+
+impl HookMetrics {
+    /// Count a call and how long it took
+    pub fn record(&self, function: &str, duration_ms: u64, outcome: HookOutcome) {
+        let mut functions = self.functions.lock().unwrap();
+        let (stats, total_ms) = functions.entry(function.to_string()).or_default();
+        stats.calls += 1;
+        if outcome != HookOutcome::Succeeded {
+            stats.failures += 1;
+        }
+        if outcome == HookOutcome::TimedOut {
+            stats.timeouts += 1;
+        }
+        *total_ms += duration_ms;
+        stats.avg_duration_ms = *total_ms / stats.calls;
+        stats.max_duration_ms = stats.max_duration_ms.max(duration_ms);
+    }
+
+    /// The counters of every function called so far
+    pub fn snapshot(&self) -> BTreeMap<String, HookStats> {
+        self.functions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, (stats, _))| (name.clone(), stats.clone()))
+            .collect()
+    }
+}
+
+/// Variable holding the position of a streamed chunk
+pub const CHUNK_INDEX: &str = "CHUNK_INDEX";
+
+/// What is left of a chunk after a stream-transform hook returned `output`:
+/// no output keeps it, `null` or an empty string suppresses it
+pub fn chunk_after_hook(chunk: String, output: Option<Value>) -> Option<String> {
+    match output {
+        None => Some(chunk),
+        Some(Value::Null) => None,
+        Some(Value::String(s)) => Some(s).filter(|s| !s.is_empty()),
+        Some(other) => Some(other.to_string()),
+    }
+}
+
+/// Call a hook's function, failing the call once the hook's timeout has
+/// passed, and count it
+async fn run_hook(
+    executor: &FunctionExecutor,
+    metrics: &HookMetrics,
+    hook: &HookConfig,
+    func: &FunctionType,
+    vars: &HashMap<String, Value>,
+) -> Result<FunctionResult, FunctionError> {
+    let start = Instant::now();
+    let timeout_ms = hook.timeout_ms();
+    let call = tokio::time::timeout(
+        Duration::from_millis(timeout_ms),
+        executor.execute(func, vars),
+    )
+    .await;
+    let duration_ms = start.elapsed().as_millis() as u64;
+
+    let (result, outcome) = match call {
+        Ok(Ok(result)) if result.success => (Ok(result), HookOutcome::Succeeded),
+        Ok(result) => (result, HookOutcome::Failed),
+        Err(_) => (
+            Ok(FunctionResult::failure(
+                format!("timed out after {}ms", timeout_ms),
+                duration_ms,
+            )),
+            HookOutcome::TimedOut,
+        ),
+    };
+    metrics.record(&hook.function, duration_ms, outcome);
+    result
+}
+
 /// Executor for running hooks
 pub struct HookExecutor {
     function_executor: Arc<FunctionExecutor>,
     functions: HashMap<String, FunctionType>,
+    metrics: Arc<HookMetrics>,
 }
 
 impl HookExecutor {
@@ -96,9 +203,22 @@ impl HookExecutor {
         Self {
             function_executor,
             functions,
+            metrics: Arc::new(HookMetrics::default()),
         }
     }
 
+    /// Count calls in these metrics, e.g. those of the executor replaced on
+    /// reload
+    pub fn with_metrics(mut self, metrics: Arc<HookMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Call counters by function name
+    pub fn metrics(&self) -> &Arc<HookMetrics> {
+        &self.metrics
+    }
+
     /// Execute pre-hooks before node processing
     /// Returns modified input if any transform hooks succeed
     pub async fn execute_pre_hooks(
@@ -123,6 +243,96 @@ impl HookExecutor {
             .await
     }
 
+    /// Execute post-hooks after a node's answer was streamed, skipping the
+    /// stream-transform hooks its chunks already went through
+    pub async fn execute_streamed_post_hooks(
+        &self,
+        hooks: &NodeHooks,
+        input: &Value,
+        output: Value,
+        context: &HookContext,
+    ) -> Result<Value, HookError> {
+        let hooks: Vec<HookConfig> = hooks
+            .post
+            .iter()
+            .filter(|h| h.mode != HookMode::StreamTransform)
+            .cloned()
+            .collect();
+        self.execute_hooks(&hooks, output, Some(input), context)
+            .await
+    }
+
+    /// Pass one chunk of a streamed answer through the stream-transform
+    /// post-hooks. `None` means a hook suppressed it.
+    ///
+    /// Hooks see the chunk as `$OUTPUT` and its position as `$CHUNK_INDEX`.
+    pub async fn transform_chunk(
+        &self,
+        hooks: &NodeHooks,
+        input: &Value,
+        mut chunk: String,
+        index: usize,
+        context: &HookContext,
+    ) -> Result<Option<String>, HookError> {
+        for hook in hooks
+            .post
+            .iter()
+            .filter(|h| h.mode == HookMode::StreamTransform)
+        {
+            let mut vars = context.build_variables(input, Some(&Value::String(chunk.clone())));
+            vars.insert(CHUNK_INDEX.to_string(), Value::from(index));
+            if let Some(condition) = &hook.condition {
+                if !self.evaluate_condition(condition, &vars) {
+                    continue;
+                }
+            }
+            let func = self
+                .functions
+                .get(&hook.function)
+                .ok_or_else(|| HookError::FunctionNotFound(hook.function.clone()))?;
+            match self
+                .run_chunk_hook(hook, func, &vars, chunk, context)
+                .await?
+            {
+                Some(rewritten) => chunk = rewritten,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(chunk))
+    }
+
+    /// Run a stream-transform hook on a chunk
+    async fn run_chunk_hook(
+        &self,
+        hook: &HookConfig,
+        func: &FunctionType,
+        vars: &HashMap<String, Value>,
+        chunk: String,
+        context: &HookContext,
+    ) -> Result<Option<String>, HookError> {
+        let result = run_hook(&self.function_executor, &self.metrics, hook, func, vars)
+            .await
+            .map_err(|e| HookError::ExecutionFailed(e.to_string()))?;
+        if result.success {
+            return Ok(chunk_after_hook(chunk, result.output));
+        }
+
+        let err_msg = result.error.unwrap_or_else(|| "Unknown error".to_string());
+        match hook.on_failure {
+            FailureAction::Continue => {
+                warn!(
+                    "Stream-transform hook '{}' failed on node '{}': {}",
+                    hook.function, context.node_name, err_msg
+                );
+                Ok(Some(chunk))
+            }
+            FailureAction::Abort => Err(HookError::Aborted(format!(
+                "Hook '{}' failed: {}",
+                hook.function, err_msg
+            ))),
+        }
+    }
+
     /// Execute a list of hooks
     async fn execute_hooks(
         &self,
@@ -157,14 +367,16 @@ impl HookExecutor {
                 HookMode::Observe => {
                     // Fire and forget - spawn task
                     let executor = self.function_executor.clone();
+                    let metrics = self.metrics.clone();
                     let func = func.clone();
                     let vars = vars.clone();
+                    let hook = hook.clone();
                     let hook_name = hook.function.clone();
                     let node_name = context.node_name.clone();
                     let on_failure = hook.on_failure.clone();
 
                     tokio::spawn(async move {
-                        match executor.execute(&func, &vars).await {
+                        match run_hook(&executor, &metrics, &hook, &func, &vars).await {
                             Ok(result) => {
                                 if result.success {
                                     debug!(
@@ -197,13 +409,23 @@ impl HookExecutor {
                         }
                     });
                 }
+                HookMode::StreamTransform => {
+                    // Not streamed, so the whole data is the one chunk
+                    let chunk = match &data {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    let rewritten = self
+                        .run_chunk_hook(hook, func, &vars, chunk, context)
+                        .await?;
+                    data = Value::String(rewritten.unwrap_or_default());
+                }
                 HookMode::Transform => {
                     // Wait for result and potentially modify data
-                    let result = self
-                        .function_executor
-                        .execute(func, &vars)
-                        .await
-                        .map_err(|e| HookError::ExecutionFailed(e.to_string()))?;
+                    let result =
+                        run_hook(&self.function_executor, &self.metrics, hook, func, &vars)
+                            .await
+                            .map_err(|e| HookError::ExecutionFailed(e.to_string()))?;
 
                     if result.success {
                         info!(
@@ -424,6 +646,115 @@ mod tests {
         assert!(!hook_executor.evaluate_condition("$STATUS != \"ok\"", &vars));
     }
 
+    #[test]
+    fn test_hook_metrics_record() {
+        let metrics = HookMetrics::default();
+        metrics.record("audit", 10, HookOutcome::Succeeded);
+        metrics.record("audit", 30, HookOutcome::TimedOut);
+        metrics.record("notify", 5, HookOutcome::Failed);
+
+        let stats = metrics.snapshot();
+        assert_eq!(
+            stats["audit"],
+            HookStats {
+                calls: 2,
+                failures: 1,
+                timeouts: 1,
+                avg_duration_ms: 20,
+                max_duration_ms: 30,
+            }
+        );
+        assert_eq!(stats["notify"].failures, 1);
+        assert_eq!(stats["notify"].timeouts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_hook_timeout_enforced() {
+        let secrets = Arc::new(crate::config::secrets::SecretsManager::new());
+        let executor = Arc::new(FunctionExecutor::new(secrets));
+        let slow: FunctionType =
+            serde_json::from_str(r#"{"type": "command", "command": "sleep", "args": ["5"]}"#)
+                .unwrap();
+        let hook_executor =
+            HookExecutor::new(executor, HashMap::from([("slow".to_string(), slow)]));
+        let hooks: NodeHooks = serde_json::from_str(
+            r#"{"pre": [{"function": "slow", "mode": "transform", "on_failure": "abort", "timeout_ms": 50}]}"#,
+        )
+        .unwrap();
+
+        let start = Instant::now();
+        let result = hook_executor
+            .execute_pre_hooks(
+                &hooks,
+                Value::String("hi".to_string()),
+                &create_test_context(),
+            )
+            .await;
+        assert!(matches!(result, Err(HookError::Aborted(e)) if e.contains("timed out after 50ms")));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let stats = hook_executor.metrics().snapshot();
+        assert_eq!(stats["slow"].calls, 1);
+        assert_eq!(stats["slow"].timeouts, 1);
+    }
+
+    #[test]
+    fn test_chunk_after_hook() {
+        let chunk = || "call 555-0100".to_string();
+        assert_eq!(chunk_after_hook(chunk(), None), Some(chunk()));
+        assert_eq!(
+            chunk_after_hook(chunk(), Some(Value::String("call [phone]".to_string()))),
+            Some("call [phone]".to_string())
+        );
+        assert_eq!(chunk_after_hook(chunk(), Some(Value::Null)), None);
+        assert_eq!(
+            chunk_after_hook(chunk(), Some(Value::String(String::new()))),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transform_chunk() {
+        let secrets = Arc::new(crate::config::secrets::SecretsManager::new());
+        let executor = Arc::new(FunctionExecutor::new(secrets));
+        // Prints the chunk with digits masked, or nothing past the third chunk
+        let scrub: FunctionType = serde_json::from_str(
+            r#"{"type": "command", "command": "sh", "args": ["-c", "if [ $CHUNK_INDEX -gt 2 ]; then echo '\"\"'; else echo $OUTPUT | tr 0-9 '#'; fi"]}"#,
+        )
+        .unwrap();
+        let hook_executor =
+            HookExecutor::new(executor, HashMap::from([("scrub".to_string(), scrub)]));
+        let hooks: NodeHooks = serde_json::from_str(
+            r#"{"post": [{"function": "scrub", "mode": "stream-transform"}]}"#,
+        )
+        .unwrap();
+        let input = Value::String("hi".to_string());
+        let ctx = create_test_context();
+
+        let chunk = hook_executor
+            .transform_chunk(&hooks, &input, "room 42".to_string(), 0, &ctx)
+            .await
+            .unwrap();
+        assert_eq!(chunk.as_deref(), Some("room ##"));
+        let chunk = hook_executor
+            .transform_chunk(&hooks, &input, "bye".to_string(), 3, &ctx)
+            .await
+            .unwrap();
+        assert_eq!(chunk, None);
+
+        // Whole outputs are one chunk; streamed ones skip the hook
+        let output = hook_executor
+            .execute_post_hooks(&hooks, &input, Value::String("7 days".to_string()), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(output, Value::String("# days".to_string()));
+        let output = hook_executor
+            .execute_streamed_post_hooks(&hooks, &input, Value::String("7 days".to_string()), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(output, Value::String("7 days".to_string()));
+    }
+
     #[test]
     fn test_apply_transform_merge_objects() {
         let secrets = Arc::new(crate::config::secrets::SecretsManager::new());
@@ -0,0 +1,141 @@
+//! Per-model concurrency limits
+//!
+//! A model with `max-concurrent` set gets at most that many calls at once
+//! from a worker. Further calls wait for a slot, so a slow upstream only
+//! holds up the requests that need it instead of every request the worker
+//! is serving. Calls are counted for every model, limited or not, and
+//! reported on the worker's `/status`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use utoipa::ToSchema;
+
+/// Snapshot of a model's calls for status reporting
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ConcurrencyStatus {
+    /// Calls to the model running now
+    pub in_flight: usize,
+    /// Calls waiting for a slot
+    pub waiting: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<usize>,
+}
+
+/// Caps and counts the calls in flight to one model
+#[derive(Debug)]
+pub struct ConcurrencyLimit {
+    max: Option<usize>,
+    semaphore: Option<Semaphore>,
+    in_flight: AtomicUsize,
+    waiting: AtomicUsize,
+}
+
+impl ConcurrencyLimit {
+    /// A limit of `max` calls at once; `None` only counts calls
+    pub fn new(max: Option<usize>) -> Self {
+        Self {
+            max,
+            semaphore: max.map(Semaphore::new),
+            in_flight: AtomicUsize::new(0),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Wait for a slot. The call counts as in flight until the permit drops.
+    pub async fn acquire(&self) -> ConcurrencyPermit<'_> {
+        let permit = match &self.semaphore {
+            Some(semaphore) => {
+                // Dropped when the slot is granted or the caller gives up
+                let _waiting = Counted::new(&self.waiting);
+                Some(
+                    semaphore
+                        .acquire()
+                        .await
+                        .expect("concurrency semaphore is never closed"),
+                )
+            }
+            None => None,
+        };
+        ConcurrencyPermit {
+            _in_flight: Counted::new(&self.in_flight),
+            _permit: permit,
+        }
+    }
+
+    /// Snapshot the limit for status reporting
+    pub fn status(&self) -> ConcurrencyStatus {
+        ConcurrencyStatus {
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            waiting: self.waiting.load(Ordering::SeqCst),
+            max_concurrent: self.max,
+        }
+    }
+}
+
+/// A slot for one call to a model
+pub struct ConcurrencyPermit<'a> {
+    _in_flight: Counted<'a>,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+/// Holds a counter up by one while alive
+struct Counted<'a>(&'a AtomicUsize);
+
+impl<'a> Counted<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_limit_queues_extra_calls() {
+        let limit = ConcurrencyLimit::new(Some(2));
+        let first = limit.acquire().await;
+        let _second = limit.acquire().await;
+
+        // The third call waits until a slot frees up
+        let third = limit.acquire();
+        tokio::pin!(third);
+        assert!(tokio::time::timeout(Duration::from_millis(20), &mut third)
+            .await
+            .is_err());
+        assert_eq!(
+            limit.status(),
+            ConcurrencyStatus {
+                in_flight: 2,
+                waiting: 1,
+                max_concurrent: Some(2),
+            }
+        );
+
+        drop(first);
+        let _third = third.await;
+        assert_eq!(limit.status().in_flight, 2);
+        assert_eq!(limit.status().waiting, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_only_counts() {
+        let limit = ConcurrencyLimit::new(None);
+        let permits = vec![limit.acquire().await, limit.acquire().await];
+        assert_eq!(limit.status().in_flight, 2);
+        assert_eq!(limit.status().max_concurrent, None);
+
+        drop(permits);
+        assert_eq!(limit.status().in_flight, 0);
+    }
+}
@@ -2,11 +2,19 @@
 //!
 //! This module provides functionality to generate CLI arguments for
 //! llama.cpp's `llama-server` (OpenAI-compatible API server).
+//!
+//! On a known edge device (Raspberry Pi 5, or a Jetson running llama.cpp on
+//! its CPU) parameters the model doesn't set are tuned for the board: one
+//! thread per core, no GPU offload without CUDA, the device's context
+//! length, and the model locked in memory when it leaves room to spare.
+//! llama.cpp picks its ARM NEON kernels itself when built for aarch64.
 
 use std::collections::HashMap;
 
 use serde_json::Value;
 
+use crate::config::DeviceProfile;
+
 // ============================================================================
 // SBIO: Pure business logic (no I/O)
 // ============================================================================
@@ -41,24 +49,20 @@ pub fn generate_args(
         "--port".to_string(),
         port.to_string(),
     ];
+    args.extend(param_args(params));
+    args
+}
 
+/// llama-server flags for parameters: `true` adds a bare flag, `false`
+/// leaves it out, numbers and strings become its value
+pub fn param_args(params: &HashMap<String, Value>) -> Vec<String> {
+    let mut args = Vec::new();
     for (key, value) in params {
-        // Normalize parameter names
-        let arg_name = match key.as_str() {
-            "n_ctx" | "ctx_size" => "--ctx-size".to_string(),
-            "n_gpu_layers" | "ngl" => "--n-gpu-layers".to_string(),
-            "n_threads" | "threads" => "--threads".to_string(),
-            "n_batch" => "--batch-size".to_string(),
-            "flash_attn" | "fa" => "--flash-attn".to_string(),
-            _ => format!("--{}", key.replace('_', "-")),
-        };
+        let arg_name = arg_name(key);
 
         match value {
-            Value::Bool(b) => {
-                if *b {
-                    args.push(arg_name);
-                }
-            }
+            Value::Bool(true) => args.push(arg_name),
+            Value::Bool(false) => {}
             Value::Number(n) => {
                 args.push(arg_name);
                 args.push(n.to_string());
@@ -74,6 +78,54 @@ pub fn generate_args(
     args
 }
 
+/// llama-server flag for a parameter name, normalizing aliases
+fn arg_name(key: &str) -> String {
+    match key {
+        "n_ctx" | "ctx_size" => "--ctx-size".to_string(),
+        "n_gpu_layers" | "ngl" => "--n-gpu-layers".to_string(),
+        "n_threads" | "threads" => "--threads".to_string(),
+        "n_batch" => "--batch-size".to_string(),
+        "flash_attn" | "fa" => "--flash-attn".to_string(),
+        _ => format!("--{}", key.replace('_', "-")),
+    }
+}
+
+/// Parameters tuned for a device and a model file of `model_bytes`
+pub fn device_params(device: &DeviceProfile, model_bytes: u64) -> HashMap<String, Value> {
+    let mut params = HashMap::new();
+    if device.cpu_cores > 0 {
+        params.insert("n_threads".to_string(), Value::from(device.cpu_cores));
+    }
+    if !device.cuda_support {
+        params.insert("n_gpu_layers".to_string(), Value::from(0));
+    }
+    params.insert("n_ctx".to_string(), Value::from(device.max_context_length));
+
+    // Locking a model that takes most of the memory starves everything
+    // else; a larger one stays memory-mapped so the kernel can page it
+    let memory_bytes = (device.memory_gb as f64 * 1024.0 * 1024.0 * 1024.0) as u64;
+    params.insert(
+        "mlock".to_string(),
+        Value::Bool(model_bytes <= memory_bytes / 2),
+    );
+    params
+}
+
+/// The model's parameters, with device-tuned ones for those it doesn't set
+pub fn with_device_params(
+    params: &HashMap<String, Value>,
+    device: &DeviceProfile,
+    model_bytes: u64,
+) -> HashMap<String, Value> {
+    let set: Vec<String> = params.keys().map(|key| arg_name(key)).collect();
+    let mut tuned: HashMap<String, Value> = device_params(device, model_bytes)
+        .into_iter()
+        .filter(|(key, _)| !set.contains(&arg_name(key)))
+        .collect();
+    tuned.extend(params.iter().map(|(k, v)| (k.clone(), v.clone())));
+    tuned
+}
+
 /// Generate a command line string for llama-server
 pub fn generate_command(
     model: &str,
@@ -157,6 +209,35 @@ mod tests {
         assert!(args.contains(&"--flash-attn".to_string()));
     }
 
+    #[test]
+    fn test_with_device_params() {
+        let devices = crate::config::known_devices();
+        let pi = &devices["raspberry-pi-5"];
+        const GIB: u64 = 1024 * 1024 * 1024;
+
+        let mut params = HashMap::new();
+        params.insert("threads".to_string(), Value::Number(2.into()));
+        let tuned = with_device_params(&params, pi, GIB);
+
+        // The model's own setting wins, under any alias
+        assert_eq!(tuned["threads"], 2);
+        assert!(!tuned.contains_key("n_threads"));
+        assert_eq!(tuned["n_gpu_layers"], 0);
+        assert_eq!(tuned["n_ctx"], 2048);
+        assert_eq!(tuned["mlock"], true);
+
+        // A model taking most of the memory stays memory-mapped
+        let tuned = with_device_params(&HashMap::new(), pi, 6 * GIB);
+        assert_eq!(tuned["n_threads"], 4);
+        assert_eq!(tuned["mlock"], false);
+
+        // Jetson keeps whatever GPU offload llama.cpp was built with
+        let nano = &devices["jetson-orin-nano"];
+        let tuned = with_device_params(&HashMap::new(), nano, GIB);
+        assert!(!tuned.contains_key("n_gpu_layers"));
+        assert_eq!(tuned["n_threads"], 6);
+    }
+
     #[test]
     fn test_generate_command() {
         let params = HashMap::new();
@@ -0,0 +1,140 @@
+//! llamafile runner: a single executable holding llama.cpp and the weights
+//!
+//! A llamafile is fetched like any other model file, made executable, and
+//! run with `--server`, so a node needs nothing installed to serve it.
+//! Parameters become the same flags as for llama-server. Some kernels can't
+//! execute a llamafile directly (its Actually Portable Executable header
+//! gets an exec format error); it is then started through `sh`, which runs
+//! it as the shell script it also is.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::llamacpp;
+
+/// `ENOEXEC`, the errno of exec format errors on Linux and macOS
+const ENOEXEC: i32 = 8;
+
+// ============================================================================
+// SBIO: Pure business logic (no I/O)
+// ============================================================================
+
+/// Generate CLI arguments for a llamafile's server
+pub fn generate_args(host: &str, port: u16, params: &HashMap<String, Value>) -> Vec<String> {
+    let mut args = vec![
+        "--server".to_string(),
+        "--nobrowser".to_string(),
+        "--host".to_string(),
+        host.to_string(),
+        "--port".to_string(),
+        port.to_string(),
+    ];
+    args.extend(llamacpp::param_args(params));
+    args
+}
+
+/// Get the default port for a llamafile server
+pub const fn default_port() -> u16 {
+    8080
+}
+
+/// Generate the endpoint URL for a llamafile server
+pub fn endpoint_url(host: &str, port: u16) -> String {
+    format!("http://{}:{}/v1", host, port)
+}
+
+/// Permission bits with execute added wherever read is allowed
+pub fn executable_mode(mode: u32) -> u32 {
+    mode | ((mode & 0o444) >> 2)
+}
+
+/// Whether starting the file failed because the kernel can't execute it,
+/// so it should be started through `sh`
+pub fn is_exec_format_error(error: &std::io::Error) -> bool {
+    error.raw_os_error() == Some(ENOEXEC)
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Make a fetched llamafile executable
+#[cfg(unix)]
+pub fn make_executable(path: &std::path::Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    let mode = executable_mode(permissions.mode());
+    if mode != permissions.mode() {
+        permissions.set_mode(mode);
+        std::fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+/// Windows runs any file with an executable extension
+#[cfg(not(unix))]
+pub fn make_executable(_path: &std::path::Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_args() {
+        let mut params = HashMap::new();
+        params.insert("n_ctx".to_string(), Value::Number(4096.into()));
+        params.insert("ngl".to_string(), Value::Number(99.into()));
+
+        let args = generate_args("127.0.0.1", 8081, &params);
+        assert_eq!(
+            args[..6],
+            [
+                "--server",
+                "--nobrowser",
+                "--host",
+                "127.0.0.1",
+                "--port",
+                "8081"
+            ]
+        );
+        assert!(args.windows(2).any(|w| w == ["--ctx-size", "4096"]));
+        assert!(args.windows(2).any(|w| w == ["--n-gpu-layers", "99"]));
+        assert!(!args.contains(&"--model".to_string()));
+    }
+
+    #[test]
+    fn test_executable_mode() {
+        assert_eq!(executable_mode(0o644), 0o755);
+        assert_eq!(executable_mode(0o600), 0o700);
+        assert_eq!(executable_mode(0o755), 0o755);
+    }
+
+    #[test]
+    fn test_is_exec_format_error() {
+        assert!(is_exec_format_error(&std::io::Error::from_raw_os_error(
+            ENOEXEC
+        )));
+        assert!(!is_exec_format_error(&std::io::Error::from(
+            std::io::ErrorKind::NotFound
+        )));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_make_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("llmnet-{}.llamafile", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        make_executable(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! Direct hops between the workers of a split pipeline
+//!
+//! A pipeline whose `layerPlacement` puts some layers on other workers runs
+//! its router on its replicas as usual. When a request reaches a handler
+//! placed elsewhere, the replica sends the hop straight to the peer worker
+//! hosting it (`/v1/mesh/hops`) instead of through the control plane, and
+//! carries on with the peer's answer.
+//!
+//! The control plane gives every worker of the pipeline the same mesh key.
+//! Each hop carries a short-lived token signed with it over the hop's
+//! node, request ID and body, so peers only run hops for their own pipeline
+//! and a captured token can't be reused for another request.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::client::{ClientError, Message, Usage};
+use crate::runtime::dead_letter::DeadLetterInput;
+use crate::runtime::fetch::{hex, hmac_sha256};
+
+/// Header carrying a hop's mesh token
+pub const MESH_TOKEN_HEADER: &str = "x-llmnet-mesh-token";
+
+/// How long a mesh token stays valid
+pub const MESH_TOKEN_TTL_SECS: i64 = 60;
+
+/// How far apart the clocks of a pipeline's workers may be
+pub const MESH_CLOCK_SKEW_SECS: i64 = 30;
+
+/// Key the workers of a split pipeline sign their hops with
+#[derive(Clone)]
+pub struct MeshKey(Vec<u8>);
+
+impl MeshKey {
+    pub fn new(secret: &str) -> Self {
+        Self(secret.as_bytes().to_vec())
+    }
+
+    /// A random secret for a newly split pipeline
+    pub fn generate() -> String {
+        format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+    }
+
+    /// Token for the hop of request `request_id` to `node` with `body`,
+    /// valid until `expires` (Unix seconds)
+    pub fn sign(&self, node: &str, request_id: Uuid, body: &[u8], expires: i64) -> String {
+        let signature = self.signature(node, request_id, body, expires);
+        format!("{}.{}", expires, hex(&signature))
+    }
+
+    /// Whether `token` was signed with this key for the hop of request
+    /// `request_id` to `node` with `body`, and is valid at `now` (Unix
+    /// seconds) give or take the workers' clock skew
+    pub fn verify(&self, token: &str, node: &str, request_id: Uuid, body: &[u8], now: i64) -> bool {
+        let Some((expires, signature)) = token.split_once('.') else {
+            return false;
+        };
+        let Ok(expires) = expires.parse::<i64>() else {
+            return false;
+        };
+        expires + MESH_CLOCK_SKEW_SECS >= now
+            && expires - now <= MESH_TOKEN_TTL_SECS + MESH_CLOCK_SKEW_SECS
+            && constant_time_eq(
+                signature.as_bytes(),
+                hex(&self.signature(node, request_id, body, expires)).as_bytes(),
+            )
+    }
+
+    fn signature(&self, node: &str, request_id: Uuid, body: &[u8], expires: i64) -> Vec<u8> {
+        let message = format!(
+            "{}:{}:{}:{}",
+            node,
+            request_id,
+            hex(&Sha256::digest(body)),
+            expires
+        );
+        hmac_sha256(&self.0, message.as_bytes())
+    }
+}
+
+/// A pipeline's mesh: its key and the peers hosting its placed handlers
+#[derive(Clone)]
+pub struct Mesh {
+    pub key: MeshKey,
+    /// Endpoint of the pipeline on the worker hosting each placed handler,
+    /// keyed by node name
+    pub peers: BTreeMap<String, String>,
+}
+
+/// A handler call sent to the peer hosting the handler
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MeshHop {
+    /// Request the hop belongs to, so it can be followed across workers
+    pub request_id: Uuid,
+    /// Handler to run
+    pub node: String,
+    /// Content the handler gets
+    pub input: String,
+    /// The rest of the request: prompt, variables, history and tools
+    pub request: DeadLetterInput,
+}
+
+/// The handler's reply to a mesh hop
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MeshReply {
+    pub message: Message,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub usage: Option<Usage>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// URL a pipeline endpoint takes mesh hops at
+pub fn mesh_hop_url(endpoint: &str) -> String {
+    format!("{}/v1/mesh/hops", endpoint.trim_end_matches('/'))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ============================================================================
+// I/O: Sending hops
+// ============================================================================
+
+/// Client for the peer worker hosting one of a pipeline's handlers
+pub struct PeerClient {
+    url: String,
+    key: MeshKey,
+    http: reqwest::Client,
+}
+
+impl PeerClient {
+    /// Client for the pipeline at `endpoint` on the peer
+    pub fn new(endpoint: &str, key: MeshKey) -> Self {
+        Self {
+            url: mesh_hop_url(endpoint),
+            key,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// The peer's reply to the hop
+    pub async fn send(&self, hop: &MeshHop) -> Result<(Message, Option<Usage>), ClientError> {
+        let body = serde_json::to_vec(hop).map_err(|e| ClientError::Parse(e.to_string()))?;
+        let expires = chrono::Utc::now().timestamp() + MESH_TOKEN_TTL_SECS;
+        let token = self.key.sign(&hop.node, hop.request_id, &body, expires);
+        let response = self
+            .http
+            .post(&self.url)
+            .header(MESH_TOKEN_HEADER, token)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ClientError::Http(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(ClientError::Api {
+                status: status.as_u16(),
+                message,
+            });
+        }
+
+        let reply: MeshReply = response
+            .json()
+            .await
+            .map_err(|e| ClientError::Parse(e.to_string()))?;
+        Ok((reply.message, reply.usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mesh_tokens() {
+        let key = MeshKey::new(&MeshKey::generate());
+        let id = Uuid::new_v4();
+        let body = br#"{"node":"coder","input":"hi"}"#;
+        let token = key.sign("coder", id, body, 1_000);
+        let verify = |token: &str, now| key.verify(token, "coder", id, body, now);
+        assert!(verify(&token, 990));
+        assert!(verify(&token, 1_000));
+
+        // A receiver whose clock is behind the sender's
+        let token = key.sign("coder", id, body, 1_000 + MESH_TOKEN_TTL_SECS);
+        assert!(verify(&token, 995));
+        let token = key.sign("coder", id, body, 1_000);
+
+        // Expired beyond the skew, or too far out
+        assert!(verify(&token, 1_000 + MESH_CLOCK_SKEW_SECS));
+        assert!(!verify(&token, 1_001 + MESH_CLOCK_SKEW_SECS));
+        assert!(!verify(&key.sign("coder", id, body, 5_000), 990));
+
+        // For another node, request or body
+        assert!(!key.verify(&token, "writer", id, body, 990));
+        assert!(!key.verify(&token, "coder", Uuid::new_v4(), body, 990));
+        assert!(!key.verify(
+            &token,
+            "coder",
+            id,
+            br#"{"node":"coder","input":"rm"}"#,
+            990
+        ));
+
+        // Signed with another pipeline's key, or not a token at all
+        let other = MeshKey::new(&MeshKey::generate());
+        assert!(!other.verify(&token, "coder", id, body, 990));
+        assert!(!verify("1000", 990));
+        assert!(!verify("soon.abc", 990));
+        assert!(!verify(&token.replace("1000.", "1010."), 1_000));
+
+        assert_eq!(
+            mesh_hop_url("http://10.0.0.7:8080/pipelines/chat/"),
+            "http://10.0.0.7:8080/pipelines/chat/v1/mesh/hops"
+        );
+    }
+}
@@ -1,24 +1,72 @@
+pub mod aggregate;
+pub mod balancer;
+pub mod budget;
+pub mod circuit_breaker;
+pub mod dead_letter;
 pub mod docker;
+pub mod evaluator;
 pub mod fetch;
+pub mod guard;
+pub mod handoff;
+pub mod hedge;
 pub mod hooks;
+pub mod limiter;
 pub mod llamacpp;
+pub mod llamafile;
+pub mod mesh;
+pub mod model_cache;
 pub mod node;
 pub mod ollama;
 pub mod orchestrator;
 pub mod processor;
+pub mod queue;
 pub mod request;
+pub mod request_log;
+pub mod retriever;
 pub mod router;
 pub mod runner;
+pub mod runner_logs;
+pub mod session;
 pub mod tensorrt_llm;
+pub mod tgi;
+pub mod topology;
+pub mod trace;
 pub mod vllm;
+pub mod warm_up;
+pub mod whisper;
 
-pub use docker::DockerConfig;
+pub use balancer::{RunnerLease, RunnerPool};
+pub use budget::{Budget, BudgetLedger, BudgetSpend, BudgetUsage};
+pub use circuit_breaker::{
+    Admission, BreakerState, BreakerStatus, CircuitBreaker, CircuitBreakerConfig,
+};
+pub use dead_letter::{
+    DeadLetter, DeadLetterError, DeadLetterInput, DeadLetterStore, DEFAULT_DEAD_LETTER_CAPACITY,
+};
+pub use docker::{detect_host_capacity, DockerConfig, HostCapacity};
 pub use fetch::{classify_path, fetch_file, PathType};
-pub use hooks::{HookContext, HookError, HookExecutor};
+pub use handoff::{HandoffClient, HANDOFF_DEPTH_HEADER, MAX_HANDOFF_DEPTH};
+pub use hedge::{Hedge, RetryBudget};
+pub use hooks::{HookContext, HookError, HookExecutor, HookMetrics, HookOutcome, HookStats};
+pub use limiter::{ConcurrencyLimit, ConcurrencyStatus};
+pub use mesh::{Mesh, MeshHop, MeshKey, MeshReply, PeerClient, MESH_TOKEN_HEADER};
+pub use model_cache::{ArtifactKind, CachedArtifact, ModelCache, PruneReport};
 pub use node::RuntimeNode;
 pub use ollama::Modelfile;
 pub use orchestrator::Orchestrator;
-pub use processor::PipelineProcessor;
+pub use processor::{
+    PipelineEvent, PipelineOutput, PipelineProcessor, ProcessorError, RouteStep, SharedProcessor,
+};
+pub use queue::{spawn_queue_worker, QueueError, QueueSink, QueueSource};
 pub use request::{PipelineRequest, RequestHop};
-pub use router::Router;
-pub use runner::{new_shared_manager, RunnerManager, SharedRunnerManager};
+pub use request_log::{
+    spawn_request_log_shipper, Redactor, RequestLog, RequestLogError, RequestLogQuery,
+    RequestLogger,
+};
+pub use router::{EmbeddingRouter, Router};
+pub use runner::{new_shared_manager, RunnerManager, RunnerRecord, SharedRunnerManager};
+pub use session::{
+    build_session_store, MemorySessionStore, RedisSessionStore, SessionError, SessionStore,
+};
+pub use topology::{EdgeKind, Topology, TopologyEdge, TopologyNode};
+pub use trace::{HopTrace, RequestTrace, TraceStore};
@@ -0,0 +1,578 @@
+//! Node-local model cache
+//!
+//! Model artifacts stay on a worker's disk after the runners that used them
+//! stop: files fetched by llmnet (GGUF, llamafile, ...) under
+//! `~/.llmnet/cache`, blobs pulled by Ollama under `~/.ollama/models`, and
+//! Hugging Face repos downloaded by vLLM, TGI and TensorRT-LLM under
+//! `~/.cache/huggingface/hub`. The worker lists them from
+//! `GET /v1/cache/models` and evicts the least recently used ones down to a
+//! disk budget from `POST /v1/cache/models/prune`. An evicted model is
+//! downloaded again the next time a runner needs it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::fetch::default_cache_dir;
+
+/// Where a cached artifact came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    /// A GGUF file fetched by llmnet
+    Gguf,
+    /// Any other file fetched by llmnet, e.g. a llamafile
+    File,
+    /// A blob pulled by Ollama
+    OllamaBlob,
+    /// A Hugging Face repo with all its snapshots
+    HfSnapshot,
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            ArtifactKind::Gguf => "gguf",
+            ArtifactKind::File => "file",
+            ArtifactKind::OllamaBlob => "ollama-blob",
+            ArtifactKind::HfSnapshot => "hf-snapshot",
+        };
+        f.write_str(kind)
+    }
+}
+
+/// A model artifact on the worker's disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CachedArtifact {
+    pub kind: ArtifactKind,
+    /// Models the artifact belongs to, or its file name when unknown
+    pub name: String,
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    /// Bytes on disk
+    pub size: u64,
+    /// Last time the artifact was read or written
+    pub last_used: DateTime<Utc>,
+}
+
+/// What a prune removed, or would remove on a dry run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PruneReport {
+    /// Bytes the cache was pruned down to
+    pub budget: u64,
+    /// Bytes cached before the prune
+    pub before: u64,
+    /// Bytes cached after it
+    pub after: u64,
+    /// Artifacts evicted, least recently used first
+    pub evicted: Vec<CachedArtifact>,
+    /// Nothing was removed
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// The directories a worker's models are cached in
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelCache {
+    /// Files fetched by llmnet
+    pub fetch_dir: PathBuf,
+    /// Ollama's model store (`OLLAMA_MODELS`)
+    pub ollama_dir: PathBuf,
+    /// The Hugging Face hub cache (`HF_HUB_CACHE`)
+    pub huggingface_dir: PathBuf,
+    /// Disk space the artifacts may take, used when a prune names none
+    pub budget: Option<u64>,
+}
+
+impl Default for ModelCache {
+    fn default() -> Self {
+        Self {
+            fetch_dir: default_cache_dir(),
+            ollama_dir: default_ollama_dir(),
+            huggingface_dir: default_huggingface_dir(),
+            budget: None,
+        }
+    }
+}
+
+/// Ollama's model store: `$OLLAMA_MODELS`, or `~/.ollama/models`
+pub fn default_ollama_dir() -> PathBuf {
+    match std::env::var_os("OLLAMA_MODELS") {
+        Some(dir) => PathBuf::from(dir),
+        None => home_dir().join(".ollama").join("models"),
+    }
+}
+
+/// The Hugging Face hub cache: `$HF_HUB_CACHE`, `$HF_HOME/hub`, or
+/// `~/.cache/huggingface/hub`
+pub fn default_huggingface_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("HF_HUB_CACHE") {
+        return PathBuf::from(dir);
+    }
+    match std::env::var_os("HF_HOME") {
+        Some(home) => PathBuf::from(home).join("hub"),
+        None => home_dir().join(".cache").join("huggingface").join("hub"),
+    }
+}
+
+fn home_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Bytes a set of artifacts takes
+pub fn total_size(artifacts: &[CachedArtifact]) -> u64 {
+    artifacts.iter().map(|a| a.size).sum()
+}
+
+/// The artifacts to evict so the rest fit in `budget` bytes, least recently
+/// used first
+pub fn plan_eviction(artifacts: &[CachedArtifact], budget: u64) -> Vec<CachedArtifact> {
+    let mut by_age: Vec<&CachedArtifact> = artifacts.iter().collect();
+    by_age.sort_by_key(|a| a.last_used);
+
+    let mut remaining = total_size(artifacts);
+    by_age
+        .into_iter()
+        .take_while(|a| {
+            let over = remaining > budget;
+            if over {
+                remaining -= a.size;
+            }
+            over
+        })
+        .cloned()
+        .collect()
+}
+
+/// The model an Ollama manifest describes, from its path under
+/// `manifests/` (e.g. `registry.ollama.ai/library/llama3/8b` → `llama3:8b`)
+pub fn ollama_model_name(manifest: &Path) -> String {
+    let parts: Vec<String> = manifest
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    match parts.as_slice() {
+        [registry, namespace, model, tag] if registry == "registry.ollama.ai" => {
+            if namespace == "library" {
+                format!("{}:{}", model, tag)
+            } else {
+                format!("{}/{}:{}", namespace, model, tag)
+            }
+        }
+        [path @ .., tag] if !path.is_empty() => format!("{}:{}", path.join("/"), tag),
+        _ => manifest.display().to_string(),
+    }
+}
+
+/// The digest of an Ollama blob file (`sha256-abc` → `sha256:abc`)
+pub fn ollama_blob_digest(file_name: &str) -> String {
+    file_name.replacen('-', ":", 1)
+}
+
+/// The repo a Hugging Face hub cache directory holds
+/// (`models--org--name` → `org/name`); `None` for datasets, spaces and
+/// anything else
+pub fn hf_repo_name(dir_name: &str) -> Option<String> {
+    dir_name
+        .strip_prefix("models--")
+        .map(|repo| repo.replace("--", "/"))
+}
+
+/// The digests of the config and layers an Ollama manifest references
+fn manifest_digests(manifest: &serde_json::Value) -> Vec<String> {
+    std::iter::once(&manifest["config"])
+        .chain(manifest["layers"].as_array().into_iter().flatten())
+        .filter_map(|entry| entry["digest"].as_str().map(String::from))
+        .collect()
+}
+
+// ============================================================================
+// I/O: Reading and evicting the cache
+// ============================================================================
+
+impl ModelCache {
+    /// Evict down to this budget when a prune names none
+    pub fn with_budget(mut self, budget: u64) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Every cached artifact, least recently used first. Directories that
+    /// don't exist are skipped.
+    pub fn list(&self) -> io::Result<Vec<CachedArtifact>> {
+        let mut artifacts = self.fetched()?;
+        artifacts.extend(self.ollama_blobs()?);
+        artifacts.extend(self.huggingface_repos()?);
+        artifacts.sort_by_key(|a| a.last_used);
+        Ok(artifacts)
+    }
+
+    /// Evict the least recently used artifacts until the rest fit in
+    /// `budget` bytes
+    ///
+    /// Evicting an Ollama blob also removes the manifests of the models
+    /// that use it, so Ollama doesn't offer models it can no longer load.
+    pub fn prune(&self, budget: u64, dry_run: bool) -> io::Result<PruneReport> {
+        let artifacts = self.list()?;
+        let before = total_size(&artifacts);
+        let evicted = plan_eviction(&artifacts, budget);
+
+        if !dry_run {
+            let manifests = self.ollama_manifests()?;
+            for artifact in &evicted {
+                match artifact.kind {
+                    ArtifactKind::Gguf | ArtifactKind::File => fs::remove_file(&artifact.path)?,
+                    ArtifactKind::HfSnapshot => fs::remove_dir_all(&artifact.path)?,
+                    ArtifactKind::OllamaBlob => {
+                        let digest = ollama_blob_digest(&file_name(&artifact.path));
+                        for (manifest, digests) in &manifests {
+                            if digests.contains(&digest) && manifest.exists() {
+                                fs::remove_file(manifest)?;
+                            }
+                        }
+                        fs::remove_file(&artifact.path)?;
+                    }
+                }
+            }
+        }
+
+        Ok(PruneReport {
+            budget,
+            before,
+            after: before - total_size(&evicted),
+            evicted,
+            dry_run,
+        })
+    }
+
+    /// Files fetched by llmnet, by URL hash or by digest under `sha256/`.
+    /// Downloads still in progress are left out.
+    fn fetched(&self) -> io::Result<Vec<CachedArtifact>> {
+        let mut artifacts = Vec::new();
+        for dir in [self.fetch_dir.clone(), self.fetch_dir.join("sha256")] {
+            for (path, metadata) in files_in(&dir)? {
+                if path.extension().is_some_and(|e| e == "part") {
+                    continue;
+                }
+                let kind = if path.extension().is_some_and(|e| e == "gguf") {
+                    ArtifactKind::Gguf
+                } else {
+                    ArtifactKind::File
+                };
+                artifacts.push(CachedArtifact {
+                    kind,
+                    name: file_name(&path),
+                    size: metadata.len(),
+                    last_used: last_used(&metadata),
+                    path,
+                });
+            }
+        }
+        Ok(artifacts)
+    }
+
+    /// Ollama blobs, named after the models whose manifests use them
+    fn ollama_blobs(&self) -> io::Result<Vec<CachedArtifact>> {
+        let mut models: HashMap<String, Vec<String>> = HashMap::new();
+        let manifests_dir = self.ollama_dir.join("manifests");
+        for (manifest, digests) in self.ollama_manifests()? {
+            let relative = manifest.strip_prefix(&manifests_dir).unwrap_or(&manifest);
+            let name = ollama_model_name(relative);
+            for digest in digests {
+                models.entry(digest).or_default().push(name.clone());
+            }
+        }
+
+        let mut artifacts = Vec::new();
+        for (path, metadata) in files_in(&self.ollama_dir.join("blobs"))? {
+            let file = file_name(&path);
+            let name = match models.get_mut(&ollama_blob_digest(&file)) {
+                Some(names) => {
+                    names.sort();
+                    names.dedup();
+                    names.join(", ")
+                }
+                None => file,
+            };
+            artifacts.push(CachedArtifact {
+                kind: ArtifactKind::OllamaBlob,
+                name,
+                size: metadata.len(),
+                last_used: last_used(&metadata),
+                path,
+            });
+        }
+        Ok(artifacts)
+    }
+
+    /// Every Ollama manifest with the blob digests it references
+    fn ollama_manifests(&self) -> io::Result<Vec<(PathBuf, Vec<String>)>> {
+        let mut files = Vec::new();
+        walk(&self.ollama_dir.join("manifests"), &mut files)?;
+
+        let mut manifests = Vec::new();
+        for (path, _) in files {
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&content) {
+                manifests.push((path, manifest_digests(&manifest)));
+            }
+        }
+        Ok(manifests)
+    }
+
+    /// Hugging Face model repos, each with its blobs and snapshots
+    fn huggingface_repos(&self) -> io::Result<Vec<CachedArtifact>> {
+        let entries = match fs::read_dir(&self.huggingface_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut artifacts = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let Some(name) = hf_repo_name(&entry.file_name().to_string_lossy()) else {
+                continue;
+            };
+
+            // Snapshots are symlinks into blobs/, so only real files count
+            let mut files = Vec::new();
+            walk(&entry.path(), &mut files)?;
+            let size = files.iter().map(|(_, m)| m.len()).sum();
+            let used = files
+                .iter()
+                .map(|(_, m)| last_used(m))
+                .max()
+                .unwrap_or(DateTime::from(SystemTime::UNIX_EPOCH));
+            artifacts.push(CachedArtifact {
+                kind: ArtifactKind::HfSnapshot,
+                name,
+                path: entry.path(),
+                size,
+                last_used: used,
+            });
+        }
+        Ok(artifacts)
+    }
+}
+
+/// The regular files directly in `dir`, none if it doesn't exist
+fn files_in(dir: &Path) -> io::Result<Vec<(PathBuf, fs::Metadata)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.push((entry.path(), metadata));
+        }
+    }
+    Ok(files)
+}
+
+/// The regular files under `dir`, without following symlinks
+fn walk(dir: &Path, files: &mut Vec<(PathBuf, fs::Metadata)>) -> io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let metadata = fs::symlink_metadata(entry.path())?;
+        if metadata.is_dir() {
+            walk(&entry.path(), files)?;
+        } else if metadata.is_file() {
+            files.push((entry.path(), metadata));
+        }
+    }
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The later of a file's access and modification times. Access times are
+/// often only updated once a day (relatime), so a modification counts too.
+fn last_used(metadata: &fs::Metadata) -> DateTime<Utc> {
+    let time = [metadata.accessed(), metadata.modified()]
+        .into_iter()
+        .filter_map(Result::ok)
+        .max()
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    DateTime::from(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{File, FileTimes};
+    use std::time::Duration;
+
+    fn artifact(name: &str, size: u64, age_secs: i64) -> CachedArtifact {
+        CachedArtifact {
+            kind: ArtifactKind::Gguf,
+            name: name.to_string(),
+            path: PathBuf::from(format!("/cache/{}", name)),
+            size,
+            last_used: DateTime::from_timestamp(1_000_000 - age_secs, 0).unwrap(),
+        }
+    }
+
+    /// Write `size` bytes to `path`, last used `age_secs` ago
+    fn write_file(path: &Path, size: usize, age_secs: u64) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+        let time = SystemTime::now() - Duration::from_secs(age_secs);
+        let times = FileTimes::new().set_accessed(time).set_modified(time);
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_times(times)
+            .unwrap();
+    }
+
+    fn test_cache(dir: &Path) -> ModelCache {
+        ModelCache {
+            fetch_dir: dir.join("llmnet"),
+            ollama_dir: dir.join("ollama"),
+            huggingface_dir: dir.join("hf"),
+            budget: None,
+        }
+    }
+
+    /// An llmnet GGUF, an Ollama model with two blobs, and a Hugging Face
+    /// repo, from least to most recently used
+    fn populate(dir: &Path) {
+        write_file(&dir.join("llmnet/sha256/abc.gguf"), 400, 4000);
+        write_file(&dir.join("llmnet/def.gguf.part"), 50, 10);
+
+        write_file(&dir.join("ollama/blobs/sha256-111"), 300, 3000);
+        write_file(&dir.join("ollama/blobs/sha256-222"), 10, 3500);
+        let manifest = serde_json::json!({
+            "config": {"digest": "sha256:222"},
+            "layers": [{"digest": "sha256:111"}]
+        });
+        let manifest_path = dir.join("ollama/manifests/registry.ollama.ai/library/llama3/8b");
+        fs::create_dir_all(manifest_path.parent().unwrap()).unwrap();
+        fs::write(&manifest_path, manifest.to_string()).unwrap();
+
+        write_file(&dir.join("hf/models--org--model/blobs/aaa"), 200, 100);
+        write_file(&dir.join("hf/datasets--org--data/blobs/bbb"), 999, 100);
+    }
+
+    #[test]
+    fn test_plan_eviction_evicts_least_recently_used_first() {
+        let artifacts = vec![
+            artifact("new", 100, 10),
+            artifact("old", 100, 300),
+            artifact("middle", 100, 200),
+        ];
+
+        let evicted = plan_eviction(&artifacts, 150);
+        let names: Vec<&str> = evicted.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["old", "middle"]);
+
+        assert!(plan_eviction(&artifacts, 300).is_empty());
+        assert_eq!(plan_eviction(&artifacts, 0).len(), 3);
+    }
+
+    #[test]
+    fn test_model_names() {
+        assert_eq!(
+            ollama_model_name(Path::new("registry.ollama.ai/library/llama3/8b")),
+            "llama3:8b"
+        );
+        assert_eq!(
+            ollama_model_name(Path::new("registry.ollama.ai/someone/tuned/latest")),
+            "someone/tuned:latest"
+        );
+        assert_eq!(
+            ollama_model_name(Path::new("hf.co/org/repo/Q4_K_M")),
+            "hf.co/org/repo:Q4_K_M"
+        );
+        assert_eq!(ollama_blob_digest("sha256-abc"), "sha256:abc");
+        assert_eq!(
+            hf_repo_name("models--meta-llama--Llama-3.1-8B").as_deref(),
+            Some("meta-llama/Llama-3.1-8B")
+        );
+        assert_eq!(hf_repo_name("datasets--org--data"), None);
+    }
+
+    #[test]
+    fn test_list_reads_every_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        populate(dir.path());
+
+        let artifacts = test_cache(dir.path()).list().unwrap();
+        let listed: Vec<(ArtifactKind, &str, u64)> = artifacts
+            .iter()
+            .map(|a| (a.kind, a.name.as_str(), a.size))
+            .collect();
+        assert_eq!(listed.len(), 4);
+        assert_eq!(listed[0], (ArtifactKind::Gguf, "abc.gguf", 400));
+        assert!(listed.contains(&(ArtifactKind::OllamaBlob, "llama3:8b", 300)));
+        assert!(listed.contains(&(ArtifactKind::OllamaBlob, "llama3:8b", 10)));
+        assert_eq!(listed[3], (ArtifactKind::HfSnapshot, "org/model", 200));
+    }
+
+    #[test]
+    fn test_list_without_caches_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(test_cache(dir.path()).list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prune_evicts_down_to_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        populate(dir.path());
+        let cache = test_cache(dir.path());
+
+        let dry_run = cache.prune(250, true).unwrap();
+        assert!(dry_run.dry_run);
+        assert_eq!(dry_run.before, 910);
+        assert_eq!(dry_run.after, 200);
+        assert_eq!(dry_run.evicted.len(), 3);
+        assert_eq!(cache.list().unwrap().len(), 4);
+
+        let report = cache.prune(250, false).unwrap();
+        assert_eq!(report.evicted, dry_run.evicted);
+        let left = cache.list().unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].name, "org/model");
+
+        // The model whose blobs went is no longer offered to Ollama
+        assert!(!dir
+            .path()
+            .join("ollama/manifests/registry.ollama.ai/library/llama3/8b")
+            .exists());
+        // Downloads in progress are left alone
+        assert!(dir.path().join("llmnet/def.gguf.part").exists());
+    }
+}
@@ -5,13 +5,24 @@ use crate::config::{ArchitectureNode, ModelDefinition, OutputTarget};
 pub enum AdapterType {
     OpenAiApi,
     Output,
-    WebSocket { url: String },
+    WebSocket {
+        url: String,
+    },
+    /// Vectorizes the current content instead of generating text
+    Embedding,
+    /// Injects documents from a vector store into the content
+    Retriever,
+    /// Transcribes audio uploads into prompts for the router
+    AudioInput,
 }
 
 impl AdapterType {
     pub fn from_node(node: &ArchitectureNode) -> Self {
         match node.adapter.as_str() {
             "output" => AdapterType::Output,
+            "embedding" => AdapterType::Embedding,
+            "retriever" => AdapterType::Retriever,
+            "audio-input" => AdapterType::AudioInput,
             "ws" => AdapterType::WebSocket {
                 url: node.url.clone().unwrap_or_default(),
             },
@@ -31,6 +42,7 @@ pub struct RuntimeNode {
     pub model_config: Option<ModelDefinition>,
     pub output_targets: Option<OutputTarget>,
     pub use_case: Option<String>,
+    pub routing_examples: Vec<String>,
     pub condition: Option<String>,
     pub extra_options: std::collections::HashMap<String, serde_json::Value>,
 }
@@ -57,6 +69,7 @@ impl RuntimeNode {
             model_config,
             output_targets: node.output_to.clone(),
             use_case: node.use_case.clone(),
+            routing_examples: node.routing_examples.clone(),
             condition: node.condition.clone(),
             extra_options: node.extra_options.clone(),
         }
@@ -80,6 +93,21 @@ impl RuntimeNode {
         matches!(self.adapter, AdapterType::WebSocket { .. })
     }
 
+    /// Check if this node produces embeddings
+    pub fn is_embedding(&self) -> bool {
+        matches!(self.adapter, AdapterType::Embedding)
+    }
+
+    /// Check if this node retrieves documents from a vector store
+    pub fn is_retriever(&self) -> bool {
+        matches!(self.adapter, AdapterType::Retriever)
+    }
+
+    /// Check if this node transcribes audio uploads
+    pub fn is_audio_input(&self) -> bool {
+        matches!(self.adapter, AdapterType::AudioInput)
+    }
+
     /// Get the socket address for binding
     pub fn socket_addr(&self) -> String {
         format!("{}:{}", self.bind_addr, self.bind_port)
@@ -225,12 +253,32 @@ mod tests {
             bind_addr: None,
             bind_port: None,
             output_to: None,
+            loop_to: None,
+            max_iterations: None,
             use_case: None,
             condition: None,
             url: None,
             context: None,
             extra_options: HashMap::new(),
             hooks: NodeHooks::default(),
+            system_prompt: None,
+            prompt_template: None,
+            retriever: None,
+            guard: None,
+            aggregate: None,
+            evaluator: None,
+            handoff: None,
+            hedge: None,
+            replicas: None,
+            load_balancing: Default::default(),
+            timeout_ms: None,
+            routing_policy: None,
+            routing_mode: Default::default(),
+            embedding_routing: None,
+            routing_function: None,
+            prompt_cache: None,
+            routing_examples: vec![],
+            routing_cache: None,
         };
         assert_eq!(AdapterType::from_node(&node1), AdapterType::OpenAiApi);
 
@@ -242,12 +290,32 @@ mod tests {
             bind_addr: None,
             bind_port: None,
             output_to: None,
+            loop_to: None,
+            max_iterations: None,
             use_case: None,
             condition: None,
             url: None,
             context: None,
             extra_options: HashMap::new(),
             hooks: NodeHooks::default(),
+            system_prompt: None,
+            prompt_template: None,
+            retriever: None,
+            guard: None,
+            aggregate: None,
+            evaluator: None,
+            handoff: None,
+            hedge: None,
+            replicas: None,
+            load_balancing: Default::default(),
+            timeout_ms: None,
+            routing_policy: None,
+            routing_mode: Default::default(),
+            embedding_routing: None,
+            routing_function: None,
+            prompt_cache: None,
+            routing_examples: vec![],
+            routing_cache: None,
         };
         assert_eq!(AdapterType::from_node(&node2), AdapterType::Output);
 
@@ -259,12 +327,32 @@ mod tests {
             bind_addr: None,
             bind_port: None,
             output_to: None,
+            loop_to: None,
+            max_iterations: None,
             use_case: None,
             condition: None,
             url: Some("ws://localhost:3000".to_string()),
             context: None,
             extra_options: HashMap::new(),
             hooks: NodeHooks::default(),
+            system_prompt: None,
+            prompt_template: None,
+            retriever: None,
+            guard: None,
+            aggregate: None,
+            evaluator: None,
+            handoff: None,
+            hedge: None,
+            replicas: None,
+            load_balancing: Default::default(),
+            timeout_ms: None,
+            routing_policy: None,
+            routing_mode: Default::default(),
+            embedding_routing: None,
+            routing_function: None,
+            prompt_cache: None,
+            routing_examples: vec![],
+            routing_cache: None,
         };
         assert!(matches!(
             AdapterType::from_node(&node3),
@@ -282,12 +370,32 @@ mod tests {
             bind_addr: Some("127.0.0.1".to_string()),
             bind_port: Some("9000".to_string()),
             output_to: None,
+            loop_to: None,
+            max_iterations: None,
             use_case: Some("Test use case".to_string()),
             condition: None,
             url: None,
             context: None,
             extra_options: HashMap::new(),
             hooks: NodeHooks::default(),
+            system_prompt: None,
+            prompt_template: None,
+            retriever: None,
+            guard: None,
+            aggregate: None,
+            evaluator: None,
+            handoff: None,
+            hedge: None,
+            replicas: None,
+            load_balancing: Default::default(),
+            timeout_ms: None,
+            routing_policy: None,
+            routing_mode: Default::default(),
+            embedding_routing: None,
+            routing_function: None,
+            prompt_cache: None,
+            routing_examples: vec![],
+            routing_cache: None,
         };
 
         let runtime = RuntimeNode::from_architecture(&arch_node, None, 0);
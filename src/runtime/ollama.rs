@@ -135,11 +135,10 @@ pub fn parse_modelfile(content: &str) -> Result<Modelfile, ModelfileError> {
                     modelfile.template = Some(value.to_string());
                 }
             }
-            "ADAPTER" => {
-                if !value.is_empty() {
-                    modelfile.adapters.push(value.to_string());
-                }
+            "ADAPTER" if !value.is_empty() => {
+                modelfile.adapters.push(value.to_string());
             }
+            "ADAPTER" => {}
             "LICENSE" => {
                 if value.starts_with("\"\"\"") {
                     current_directive = Some("LICENSE");
@@ -283,6 +282,17 @@ pub fn endpoint_url(host: &str, port: u16) -> String {
     format!("http://{}:{}/v1", host, port)
 }
 
+/// URL an Ollama server reports its version on
+pub fn version_url(host: &str, port: u16) -> String {
+    format!("http://{}:{}/api/version", host, port)
+}
+
+/// The version in an `/api/version` answer, `None` if it isn't Ollama's
+pub fn parse_version(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    value.get("version")?.as_str().map(str::to_string)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -436,6 +446,20 @@ ADAPTER /path/to/lora2.gguf
         assert_eq!(parsed.adapters, original.adapters);
     }
 
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_version(r#"{"version": "0.5.7"}"#),
+            Some("0.5.7".to_string())
+        );
+        assert_eq!(parse_version(r#"{"status": "ok"}"#), None);
+        assert_eq!(parse_version("<html></html>"), None);
+        assert_eq!(
+            version_url("127.0.0.1", 11434),
+            "http://127.0.0.1:11434/api/version"
+        );
+    }
+
     #[test]
     fn test_endpoint_url() {
         assert_eq!(
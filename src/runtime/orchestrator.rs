@@ -209,7 +209,10 @@ impl From<&RuntimeNode> for NodeMetadata {
         Self {
             name: node.name.clone(),
             use_case: node.use_case.clone(),
+            examples: node.routing_examples.clone(),
+            ..Default::default()
         }
+        .with_cost(node.model_config.as_ref().and_then(|m| m.to_config().cost))
     }
 }
 
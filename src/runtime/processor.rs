@@ -1,18 +1,62 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
-use tracing::debug;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, warn};
+use uuid::Uuid;
 
+use crate::adapters::{Adapter, AdapterContext, AdapterRegistry};
 use crate::client::{
-    ChatCompletionRequest as ClientRequest, Message, OpenAiClient, OpenAiClientTrait,
+    ChatCompletionRequest as ClientRequest, ChatCompletionResponse, ClientError, EmbeddingInput,
+    EmbeddingRequest, EmbeddingResponse, GeminiClient, Message, ModelClient, OpenAiClient,
+    OpenAiClientTrait, Tool, ToolCall, ToolChoice, TranscriptionRequest, Usage, GEMINI_API_URL,
 };
-use crate::config::{Composition, FunctionExecutor, ModelDefinition, OutputTarget, SecretsManager};
-use crate::runtime::hooks::{HookContext, HookError, HookExecutor};
+use crate::config::models::{ModelConfig, RunnerType};
+use crate::config::{
+    AggregateConfig, AggregateStrategy, Composition, FunctionExecutor, FunctionType, OutputTarget,
+    PromptCacheConfig, RoutingMode, SecretsManager, SessionConfig,
+};
+use crate::runtime::aggregate::{
+    build_judge_prompt, concat, merge_json, parse_judge_choice, vote, JUDGE_PROMPT,
+};
+use crate::runtime::balancer::{RunnerLease, RunnerPool};
+use crate::runtime::budget::{Budget, BudgetLedger};
+use crate::runtime::circuit_breaker::{
+    Admission, BreakerStatus, CircuitBreaker, CircuitBreakerConfig,
+};
+use crate::runtime::dead_letter::{DeadLetter, DeadLetterInput, DeadLetterStore};
+use crate::runtime::evaluator::{build_rubric_prompt, parse_rubric_score, Evaluator};
+use crate::runtime::guard::{moderation_verdict, Guard, GuardOutcome, MODERATION_PROMPT};
+use crate::runtime::handoff::{HandoffClient, MAX_HANDOFF_DEPTH};
+use crate::runtime::hedge::{Hedge, RetryBudget};
+use crate::runtime::hooks::{HookContext, HookError, HookExecutor, HookStats};
+use crate::runtime::limiter::{ConcurrencyLimit, ConcurrencyStatus};
+use crate::runtime::mesh::{Mesh, MeshHop, MeshKey, PeerClient};
 use crate::runtime::node::{evaluate_condition, RuntimeNode};
-use crate::runtime::request::PipelineRequest;
-use crate::runtime::router::{build_routing_prompt, extract_node_selection, NodeMetadata};
+use crate::runtime::request::{vars, PipelineRequest};
+use crate::runtime::request_log::RequestLogger;
+use crate::runtime::retriever::{build_store, inject_documents, VectorStore};
+use crate::runtime::router::{
+    build_ranking_prompt, build_routing_prompt, choose_by_policy, extract_function_selection,
+    extract_node_ranking, extract_node_selection, routing_function_variables, EmbeddingRouter,
+    NodeMetadata, RouterError, RoutingCache,
+};
+use crate::runtime::runner::RunnerManager;
+use crate::runtime::session::{
+    append_turn, build_session_store, estimate_tokens, trim_history, SessionStore,
+};
+use crate::runtime::trace::{RequestTrace, TraceStore};
+
+/// Hops a request may take through the pipeline once; each extra loop pass
+/// or evaluator retry allows this many more
+const MAX_HOPS: usize = 10;
 
 #[derive(Error, Debug)]
 pub enum ProcessorError {
@@ -39,16 +83,197 @@ pub enum ProcessorError {
 
     #[error("Hook error: {0}")]
     HookError(#[from] HookError),
+
+    #[error("Circuit breaker open for '{0}'")]
+    CircuitOpen(String),
+
+    #[error("No embedding node configured")]
+    NoEmbeddingNode,
+
+    #[error("The model '{0}' is not served by any embedding node")]
+    ModelNotFound(String),
+
+    #[error("No audio input node configured")]
+    NoAudioInputNode,
+
+    #[error("Retrieval failed for '{0}': {1}")]
+    RetrievalFailed(String, String),
+
+    #[error("Invalid guard for '{0}': {1}")]
+    InvalidGuard(String, String),
+
+    #[error("Invalid evaluator for '{0}': {1}")]
+    InvalidEvaluator(String, String),
+
+    #[error("Invalid settings for model '{0}': {1}")]
+    InvalidModel(String, String),
+
+    #[error("Session error: {0}")]
+    Session(String),
+
+    #[error("Aggregation failed at '{0}': {1}")]
+    AggregationFailed(String, String),
+
+    #[error("Adapter failed at '{0}': {1}")]
+    AdapterFailed(String, String),
+
+    #[error("No dead letter for request {0}")]
+    DeadLetterNotFound(Uuid),
+
+    #[error("No trace or dead letter for request {0}")]
+    RequestNotFound(Uuid),
+
+    #[error("Node '{0}' can't be run on its own; only handler nodes can")]
+    NotReplayable(String),
+
+    #[error("Deadline exceeded at '{0}'")]
+    DeadlineExceeded(String),
+
+    #[error("Rate limited at '{0}': {1}")]
+    RateLimited(String, String),
+
+    #[error("Routing function failed at '{0}': {1}")]
+    RoutingFunctionFailed(String, String),
+
+    #[error("Monthly budget of model '{1}' spent at '{0}'")]
+    BudgetExhausted(String, String),
+}
+
+/// Progress of a request through the pipeline, as seen by stream subscribers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum PipelineEvent {
+    /// The request was accepted and processing began
+    Started { request_id: Uuid },
+    /// A node produced output (after its post-hooks ran)
+    Hop {
+        request_id: Uuid,
+        node: String,
+        layer: u32,
+        output: String,
+    },
+    /// A piece of the final answer as the node giving it streams it, after
+    /// the node's stream-transform hooks
+    Chunk {
+        request_id: Uuid,
+        node: String,
+        content: String,
+    },
+    /// The pipeline reached an output node with this final answer
+    Completed { request_id: Uuid, content: String },
+    /// The pipeline failed
+    Failed { request_id: Uuid, error: String },
+}
+
+/// Final result of a request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipelineOutput {
+    pub content: String,
+    /// Tool calls a handler asked the client to run, in place of an answer
+    pub tool_calls: Vec<ToolCall>,
+    /// Nodes the request went through, from the router on
+    pub route: Vec<RouteStep>,
+}
+
+/// A node a request went through on its way to the output
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteStep {
+    pub node: String,
+    /// Time spent in the node, including any routing decision it made
+    pub latency_ms: Option<u64>,
+}
+
+/// The processor requests are served with, replaceable while they run
+///
+/// Replacing it doesn't disturb requests in flight: each one holds on to the
+/// processor it started with until it finishes.
+#[derive(Clone, Default)]
+pub struct SharedProcessor(Arc<RwLock<Option<Arc<PipelineProcessor>>>>);
+
+impl SharedProcessor {
+    pub fn new(processor: Option<Arc<PipelineProcessor>>) -> Self {
+        Self(Arc::new(RwLock::new(processor)))
+    }
+
+    /// The current processor, if the pipeline has one
+    pub fn get(&self) -> Option<Arc<PipelineProcessor>> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// Serve new requests with another processor, returning the old one
+    pub fn replace(&self, processor: Arc<PipelineProcessor>) -> Option<Arc<PipelineProcessor>> {
+        self.0.write().unwrap().replace(processor)
+    }
+}
+
+impl From<Arc<PipelineProcessor>> for SharedProcessor {
+    fn from(processor: Arc<PipelineProcessor>) -> Self {
+        Self::new(Some(processor))
+    }
 }
 
 /// Processes requests through the LLM pipeline
 pub struct PipelineProcessor {
     nodes: HashMap<String, RuntimeNode>,
-    clients: HashMap<String, OpenAiClient>,
+    clients: HashMap<String, ModelClient>,
+    /// Runner replicas to spread each node's calls across
+    pools: HashMap<String, Arc<RunnerPool>>,
+    /// Nodes whose models accept client tool definitions
+    tool_nodes: HashSet<String>,
+    /// Nodes whose models accept image input
+    vision_nodes: HashSet<String>,
+    /// Prompt caching of nodes whose models take cache markers
+    prompt_caches: HashMap<String, PromptCacheConfig>,
+    /// Recent picks of routers with a `routing-cache`
+    routing_caches: HashMap<String, RoutingCache>,
+    /// Routers in the "embedding" mode whose embedding model can be reached
+    embedding_routers: HashMap<String, EmbeddingRouter<ModelClient>>,
+    /// Functions picking the targets of routers in the "function" mode
+    routing_functions: HashMap<String, FunctionType>,
+    /// Nodes clients may select directly, bypassing the router
+    route_overrides: HashSet<String>,
+    breakers: HashMap<String, CircuitBreaker>,
+    /// Calls in flight to each model, keyed by model name
+    limits: HashMap<String, ConcurrencyLimit>,
+    stores: HashMap<String, Box<dyn VectorStore>>,
+    /// Pipelines "handoff" nodes forward to, keyed by node name
+    handoffs: HashMap<String, HandoffClient>,
+    /// Fallback models slow calls of hedged nodes also go to
+    hedges: HashMap<String, Hedge>,
+    /// Extra calls the hedges may still make
+    retry_budget: RetryBudget,
+    /// Monthly budgets of models, keyed by model name
+    budgets: HashMap<String, Budget>,
+    /// What the namespace spent on the budgeted models
+    budget_ledger: Arc<BudgetLedger>,
+    /// Namespace the pipeline's spend is counted in
+    namespace: String,
+    /// Workers hosting handlers placed away from this one, keyed by node
+    /// name
+    peers: HashMap<String, PeerClient>,
+    /// Key hops from the pipeline's other workers are signed with
+    mesh_key: Option<MeshKey>,
+    guards: HashMap<String, Guard>,
+    aggregators: HashMap<String, AggregateConfig>,
+    evaluators: HashMap<String, Evaluator>,
+    /// Plugin adapters serving nodes, keyed by node name
+    plugins: HashMap<String, Arc<dyn Adapter>>,
+    traces: Arc<TraceStore>,
+    /// Failed requests, kept for requeueing
+    dead_letters: Arc<DeadLetterStore>,
+    /// Where a sample of requests is logged, when enabled
+    request_logger: Option<Arc<RequestLogger>>,
+    sessions: Arc<dyn SessionStore>,
+    session_config: SessionConfig,
     router_node_name: String,
     router_model_name: String,
+    function_executor: Arc<FunctionExecutor>,
     hook_executor: Option<HookExecutor>,
     arch_nodes: HashMap<String, crate::config::ArchitectureNode>,
+    /// Most hops a request may take; loops raise it
+    max_hops: usize,
+    /// Most time a request may take, from the composition's `deadline-ms`
+    deadline: Option<Duration>,
 }
 
 impl PipelineProcessor {
@@ -64,7 +289,20 @@ impl PipelineProcessor {
     ) -> Result<Self, ProcessorError> {
         let mut nodes = HashMap::new();
         let mut clients = HashMap::new();
+        let mut tool_nodes = HashSet::new();
+        let mut vision_nodes = HashSet::new();
+        let mut prompt_caches = HashMap::new();
+        let mut routing_caches = HashMap::new();
+        let mut embedding_routers = HashMap::new();
+        let mut routing_functions = HashMap::new();
         let mut arch_nodes = HashMap::new();
+        let mut stores = HashMap::new();
+        let mut handoffs = HashMap::new();
+        let mut hedges = HashMap::new();
+        let mut guards = HashMap::new();
+        let mut aggregators = HashMap::new();
+        let mut evaluators = HashMap::new();
+        let mut limits = HashMap::new();
         let mut router_node_name = None;
         let mut router_model_name = None;
 
@@ -80,17 +318,161 @@ impl PipelineProcessor {
             let runtime =
                 RuntimeNode::from_architecture(arch_node, model_config.clone(), port_offset);
 
-            // Create client for nodes with external models
-            if let Some(ModelDefinition::External(ext)) = &model_config {
-                let model_name = runtime.model_override().unwrap_or_else(|| {
+            // Create client for nodes whose model has a known endpoint: external
+            // models, and local runners once RunnerManager has filled theirs in
+            let config = model_config.as_ref().map(|m| m.to_config());
+            let model_name = runtime
+                .model_override()
+                .or_else(|| {
+                    let gemini = config.as_ref().filter(|c| c.is_gemini());
+                    gemini.and_then(|c| c.source.clone())
+                })
+                .unwrap_or_else(|| {
                     arch_node
                         .model
                         .clone()
                         .unwrap_or_else(|| "default".to_string())
                 });
-
-                let client = OpenAiClient::new(ext.url.clone(), ext.api_key.clone(), model_name);
+            let client = config
+                .as_ref()
+                .map(|c| build_client(c, model_name))
+                .transpose()
+                .map_err(|e| {
+                    let model = arch_node.model.clone().unwrap_or_default();
+                    ProcessorError::InvalidModel(model, e.to_string())
+                })?
+                .flatten();
+            if let Some(client) = client {
                 clients.insert(runtime.name.clone(), client);
+
+                // Nodes sharing a model share its limit
+                if let (Some(model), Some(config)) = (&arch_node.model, &model_config) {
+                    limits.entry(model.clone()).or_insert_with(|| {
+                        ConcurrencyLimit::new(config.to_config().max_concurrent)
+                    });
+                }
+            }
+            if model_config
+                .as_ref()
+                .is_some_and(|m| m.to_config().supports_tools())
+            {
+                tool_nodes.insert(runtime.name.clone());
+            }
+            if model_config
+                .as_ref()
+                .is_some_and(|m| m.to_config().supports_vision())
+            {
+                vision_nodes.insert(runtime.name.clone());
+            }
+            if let Some(cache) = composition.prompt_cache_for(arch_node).filter(|_| {
+                model_config
+                    .as_ref()
+                    .is_some_and(|m| m.to_config().supports_prompt_caching())
+            }) {
+                prompt_caches.insert(runtime.name.clone(), cache.clone());
+            }
+            if let Some(cache) = &arch_node.routing_cache {
+                routing_caches.insert(runtime.name.clone(), RoutingCache::new(cache));
+            }
+            if let Some(routing) = arch_node
+                .embedding_routing
+                .as_ref()
+                .filter(|_| arch_node.routing_mode == RoutingMode::Embedding)
+            {
+                let config = composition
+                    .models
+                    .get(&routing.model)
+                    .map(|m| m.to_config());
+                let model_name = config
+                    .as_ref()
+                    .filter(|c| c.is_gemini())
+                    .and_then(|c| c.source.clone())
+                    .unwrap_or_else(|| routing.model.clone());
+                let client = config
+                    .as_ref()
+                    .map(|c| build_client(c, model_name))
+                    .transpose()
+                    .map_err(|e| {
+                        ProcessorError::InvalidModel(routing.model.clone(), e.to_string())
+                    })?
+                    .flatten();
+                // Until a local embedding runner is up, the router model decides
+                if let Some(client) = client {
+                    let model = client.model().to_string();
+                    embedding_routers.insert(
+                        runtime.name.clone(),
+                        EmbeddingRouter::new(client, model, routing.threshold),
+                    );
+                }
+            }
+            if let Some(function) = arch_node
+                .routing_function
+                .as_ref()
+                .filter(|_| arch_node.routing_mode == RoutingMode::Function)
+                .and_then(|f| composition.functions.get(f))
+            {
+                routing_functions.insert(runtime.name.clone(), function.clone());
+            }
+
+            if let Some(hedge) = &arch_node.hedge {
+                let config = composition
+                    .models
+                    .get(&hedge.fallback)
+                    .map(|m| m.to_config());
+                let model_name = config
+                    .as_ref()
+                    .filter(|c| c.is_gemini())
+                    .and_then(|c| c.source.clone())
+                    .unwrap_or_else(|| hedge.fallback.clone());
+                let client = config
+                    .as_ref()
+                    .map(|c| build_client(c, model_name))
+                    .transpose()
+                    .map_err(|e| {
+                        ProcessorError::InvalidModel(hedge.fallback.clone(), e.to_string())
+                    })?
+                    .flatten();
+                // A local fallback can't be hedged with until its runner is up
+                if let (Some(client), Some(config)) = (client, &config) {
+                    limits
+                        .entry(hedge.fallback.clone())
+                        .or_insert_with(|| ConcurrencyLimit::new(config.max_concurrent));
+                    hedges.insert(runtime.name.clone(), Hedge::new(hedge.clone(), client));
+                }
+            }
+
+            if let Some(retriever) = &arch_node.retriever {
+                stores.insert(runtime.name.clone(), build_store(retriever));
+            }
+            if let Some(handoff) = arch_node
+                .handoff
+                .as_ref()
+                .filter(|_| arch_node.is_handoff())
+            {
+                handoffs.insert(runtime.name.clone(), HandoffClient::new(handoff.clone()));
+            }
+            if let Some(guard) = arch_node.guard.as_ref().filter(|_| arch_node.is_guard()) {
+                let guard = Guard::new(guard.clone()).map_err(|e| {
+                    ProcessorError::InvalidGuard(runtime.name.clone(), e.to_string())
+                })?;
+                guards.insert(runtime.name.clone(), guard);
+            }
+            if let Some(aggregate) = arch_node
+                .aggregate
+                .as_ref()
+                .filter(|_| arch_node.is_aggregator())
+            {
+                aggregators.insert(runtime.name.clone(), aggregate.clone());
+            }
+            if let Some(evaluator) = arch_node
+                .evaluator
+                .as_ref()
+                .filter(|_| arch_node.is_evaluator())
+            {
+                let evaluator = Evaluator::new(evaluator.clone()).map_err(|e| {
+                    ProcessorError::InvalidEvaluator(runtime.name.clone(), e.to_string())
+                })?;
+                evaluators.insert(runtime.name.clone(), evaluator);
             }
 
             // Track router node
@@ -103,533 +485,2963 @@ impl PipelineProcessor {
             nodes.insert(runtime.name.clone(), runtime);
         }
 
+        // Budgeted models, with the model their calls go to once spent
+        let mut budgets = HashMap::new();
+        for (name, model) in &composition.models {
+            let config = model.to_config();
+            let Some(budget) = config.budget.clone() else {
+                continue;
+            };
+            let fallback = match &budget.fallback {
+                Some(fallback) => {
+                    let fallback_config = composition.models.get(fallback).map(|m| m.to_config());
+                    let model_name = fallback_config
+                        .as_ref()
+                        .filter(|c| c.is_gemini())
+                        .and_then(|c| c.source.clone())
+                        .unwrap_or_else(|| fallback.clone());
+                    if let Some(config) = &fallback_config {
+                        limits
+                            .entry(fallback.clone())
+                            .or_insert_with(|| ConcurrencyLimit::new(config.max_concurrent));
+                    }
+                    fallback_config
+                        .as_ref()
+                        .map(|c| build_client(c, model_name))
+                        .transpose()
+                        .map_err(|e| ProcessorError::InvalidModel(fallback.clone(), e.to_string()))?
+                        .flatten()
+                }
+                None => None,
+            };
+            let per_1k_tokens = config.cost.as_ref().and_then(|c| c.per_1k_tokens);
+            budgets.insert(name.clone(), Budget::new(budget, per_1k_tokens, fallback));
+        }
+        let namespace = "default".to_string();
+        let budget_ledger = Arc::new(BudgetLedger::new());
+        for (model, budget) in &budgets {
+            budget_ledger.open(&namespace, model, budget.config());
+        }
+
         let router_node_name = router_node_name.ok_or(ProcessorError::NoRouter)?;
-        let router_model_name =
-            router_model_name.ok_or(ProcessorError::RouterModelNotConfigured)?;
+        // A routing function can stand in for the router model
+        let router_model_name = match router_model_name {
+            Some(model) => model,
+            None if routing_functions.contains_key(&router_node_name) => String::new(),
+            None => return Err(ProcessorError::RouterModelNotConfigured),
+        };
 
         // Initialize hook executor if functions are defined
+        let function_executor = Arc::new(FunctionExecutor::new(secrets));
         let hook_executor = if !composition.functions.is_empty() {
-            let function_executor = Arc::new(FunctionExecutor::new(secrets));
             Some(HookExecutor::new(
-                function_executor,
+                function_executor.clone(),
                 composition.functions.clone(),
             ))
         } else {
             None
         };
 
+        let breakers = clients
+            .keys()
+            .map(|name| (name.clone(), CircuitBreaker::default()))
+            .collect();
+
+        let session_config = composition.sessions.clone().unwrap_or_default();
+
+        let extra_passes: usize = composition
+            .architecture
+            .iter()
+            .map(|n| {
+                let loops = n.loop_to.as_ref().map_or(0, |_| n.loop_iterations() - 1);
+                let retries = n
+                    .evaluator
+                    .as_ref()
+                    .filter(|_| n.is_evaluator())
+                    .map_or(0, |e| e.max_retries);
+                (loops + retries) as usize
+            })
+            .sum();
+
         Ok(Self {
             nodes,
             clients,
+            pools: HashMap::new(),
+            tool_nodes,
+            vision_nodes,
+            prompt_caches,
+            routing_caches,
+            embedding_routers,
+            routing_functions,
+            route_overrides: composition.route_overrides.iter().cloned().collect(),
+            breakers,
+            limits,
+            stores,
+            handoffs,
+            hedges,
+            retry_budget: RetryBudget::new(composition.retry_budget.clone().unwrap_or_default()),
+            budgets,
+            budget_ledger,
+            namespace,
+            peers: HashMap::new(),
+            mesh_key: None,
+            guards,
+            aggregators,
+            evaluators,
+            plugins: HashMap::new(),
+            traces: Arc::new(TraceStore::default()),
+            dead_letters: Arc::new(DeadLetterStore::default()),
+            request_logger: None,
+            sessions: Arc::from(build_session_store(&session_config)),
+            session_config,
             router_node_name,
             router_model_name,
+            function_executor,
             hook_executor,
             arch_nodes,
+            max_hops: MAX_HOPS * (1 + extra_passes),
+            deadline: composition.deadline_ms.map(Duration::from_millis),
         })
     }
 
-    /// Process a user message through the pipeline
-    pub async fn process(&self, user_message: &str) -> Result<String, ProcessorError> {
-        let mut request = PipelineRequest::new(user_message.to_string());
-        let mut current_node_name = self.router_node_name.clone();
-        const MAX_HOPS: usize = 10;
-
-        loop {
-            let hop_count: usize = request.trace.len();
-            if hop_count >= MAX_HOPS {
-                return Err(ProcessorError::ApiError(format!(
-                    "Maximum hops ({}) exceeded",
-                    MAX_HOPS
-                )));
-            }
-
-            let current_node = self
-                .nodes
-                .get(&current_node_name)
-                .ok_or_else(|| ProcessorError::HandlerNotFound(current_node_name.clone()))?;
+    /// Whether clients may route straight to this node
+    pub fn allows_route(&self, node: &str) -> bool {
+        self.route_overrides.contains(node)
+    }
 
-            // Set current layer for condition evaluation
-            request.set_current_layer(current_node.layer);
+    /// Replace the circuit breakers with ones using the given thresholds
+    pub fn with_circuit_breaker_config(mut self, config: CircuitBreakerConfig) -> Self {
+        self.breakers = self
+            .clients
+            .keys()
+            .map(|name| (name.clone(), CircuitBreaker::new(config.clone())))
+            .collect();
+        self
+    }
 
-            // Determine next targets, filtering by conditions
-            let next_targets = self.get_next_targets_filtered(current_node, &request)?;
+    /// Spread each node's calls across the runner replicas of its model
+    pub fn with_runner_pools(mut self, manager: &RunnerManager) -> Self {
+        self.pools = self
+            .arch_nodes
+            .iter()
+            .filter(|(name, _)| self.clients.contains_key(*name))
+            .filter_map(|(name, node)| {
+                let pool = manager.pool(node.model.as_deref()?)?;
+                (pool.len() > 1).then(|| (name.clone(), pool))
+            })
+            .collect();
+        self
+    }
 
-            // If multiple targets, we need to route
-            let selected_target = if next_targets.len() > 1 {
-                self.route_to_target(&current_node_name, &request.current_content, &next_targets)
-                    .await?
-            } else if next_targets.len() == 1 {
-                next_targets[0].clone()
-            } else {
-                return Err(ProcessorError::ApiError(
-                    "No next targets found".to_string(),
-                ));
-            };
+    /// Count the pipeline's spend on budgeted models in `ledger`, under
+    /// `namespace`, shared with the namespace's other pipelines
+    pub fn with_budget_ledger(mut self, ledger: Arc<BudgetLedger>, namespace: &str) -> Self {
+        for (model, budget) in &self.budgets {
+            ledger.open(namespace, model, budget.config());
+        }
+        self.budget_ledger = ledger;
+        self.namespace = namespace.to_string();
+        self
+    }
 
-            // Check if we've reached output
-            if let Some(target_node) = self.nodes.get(&selected_target) {
-                if target_node.is_output() {
-                    request.add_hop(selected_target.clone(), target_node.layer, None);
-                    return Ok(request.current_content);
-                }
-            }
+    /// Send calls to the handlers placed on other workers to those workers,
+    /// and take hops from them signed with the mesh's key
+    pub fn with_mesh(mut self, mesh: &Mesh) -> Self {
+        self.peers = mesh
+            .peers
+            .iter()
+            .filter(|(name, _)| self.is_plain_handler(name))
+            .map(|(name, endpoint)| (name.clone(), PeerClient::new(endpoint, mesh.key.clone())))
+            .collect();
+        self.mesh_key = Some(mesh.key.clone());
+        self
+    }
 
-            // Record the hop before calling LLM
-            let target_layer = self
-                .nodes
-                .get(&selected_target)
-                .map(|n| n.layer)
-                .unwrap_or(0);
-            request.add_hop(
-                selected_target.clone(),
-                target_layer,
-                Some(selected_target.clone()),
-            );
+    /// Key hops from the pipeline's other workers must be signed with, if
+    /// the pipeline is split across workers
+    pub fn mesh_key(&self) -> Option<&MeshKey> {
+        self.mesh_key.as_ref()
+    }
 
-            // Execute pre-hooks for the target node
-            let input_content = self.execute_pre_hooks(&selected_target, &request).await?;
+    /// Run a hop another worker of the pipeline sent to one of the
+    /// handlers placed here
+    ///
+    /// The handler gets the hop's input with the request's history and
+    /// variables; its hooks ran on the worker that sent the hop.
+    pub async fn run_mesh_hop(
+        &self,
+        hop: &MeshHop,
+    ) -> Result<(Message, Option<Usage>), ProcessorError> {
+        if !self.is_plain_handler(&hop.node) || self.peers.contains_key(&hop.node) {
+            return Err(ProcessorError::HandlerNotFound(hop.node.clone()));
+        }
+        let mut request = hop.request.to_request(hop.request_id);
+        if let Some(deadline) = self.deadline {
+            request = request.with_deadline(deadline);
+        }
+        let deadline = self.hop_deadline(&hop.node, &request)?;
+        let (tools, tool_choice) = if self.tool_nodes.contains(&hop.node) {
+            (request.tools.as_slice(), request.tool_choice.as_ref())
+        } else {
+            (&[][..], None)
+        };
+        self.call_handler(
+            &hop.node,
+            &request,
+            &hop.input,
+            tools,
+            tool_choice,
+            deadline,
+        )
+        .await
+    }
 
-            // Call the selected node's LLM
-            let llm_output = self.call_node_llm(&selected_target, &input_content).await?;
+    /// Serve nodes whose adapter isn't built in with the plugins registered
+    /// under their adapter name
+    pub fn with_adapters(mut self, registry: &AdapterRegistry) -> Self {
+        self.plugins = self
+            .arch_nodes
+            .iter()
+            .filter_map(|(name, node)| Some((name.clone(), registry.get(&node.adapter)?)))
+            .collect();
+        self
+    }
 
-            // Execute post-hooks for the target node
-            let final_output = self
-                .execute_post_hooks(&selected_target, &request, &input_content, llm_output)
-                .await?;
+    /// Keep at most this many request traces
+    pub fn with_trace_capacity(mut self, capacity: usize) -> Self {
+        self.traces = Arc::new(TraceStore::new(capacity));
+        self
+    }
 
-            request.set_content(final_output);
-            current_node_name = selected_target;
+    /// Take over the request traces, dead letters, request logger and
+    /// conversation sessions of the processor this one replaces
+    ///
+    /// Sessions are only carried over while the session settings stay the
+    /// same. Circuit breakers, concurrency limits and the retry budget start
+    /// fresh.
+    pub fn with_state_of(mut self, previous: &PipelineProcessor) -> Self {
+        self.traces = previous.traces.clone();
+        self.dead_letters = previous.dead_letters.clone();
+        self.request_logger = previous.request_logger.clone();
+        if self.session_config == previous.session_config {
+            self.sessions = previous.sessions.clone();
+        }
+        if let Some(metrics) = previous.hook_executor.as_ref().map(|h| h.metrics().clone()) {
+            self.hook_executor = self.hook_executor.map(|h| h.with_metrics(metrics));
         }
+        self
     }
 
-    /// Execute pre-hooks for a node, returning potentially modified input
-    async fn execute_pre_hooks(
-        &self,
-        node_name: &str,
-        request: &PipelineRequest,
-    ) -> Result<String, ProcessorError> {
-        let Some(hook_executor) = &self.hook_executor else {
-            return Ok(request.current_content.clone());
-        };
+    /// Look up the trace of a finished request
+    pub fn trace(&self, request_id: &Uuid) -> Option<RequestTrace> {
+        self.traces.get(request_id)
+    }
 
-        let Some(arch_node) = self.arch_nodes.get(node_name) else {
-            return Ok(request.current_content.clone());
-        };
+    /// Keep failed requests in this store
+    pub fn with_dead_letter_store(mut self, store: Arc<DeadLetterStore>) -> Self {
+        self.dead_letters = store;
+        self
+    }
 
-        if arch_node.hooks.pre.is_empty() {
-            return Ok(request.current_content.clone());
-        }
+    /// Log a sample of finished requests with this logger
+    pub fn with_request_logger(mut self, logger: Arc<RequestLogger>) -> Self {
+        self.request_logger = Some(logger);
+        self
+    }
 
-        debug!(
-            "Executing {} pre-hooks for node '{}'",
-            arch_node.hooks.pre.len(),
-            node_name
-        );
+    /// Requests the pipeline failed to answer, oldest first
+    pub async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.list().await
+    }
 
-        let context = self.build_hook_context(node_name, request);
-        let input = Value::String(request.current_content.clone());
+    pub async fn dead_letter(&self, request_id: &Uuid) -> Option<DeadLetter> {
+        self.dead_letters.get(request_id).await
+    }
 
-        let result = hook_executor
-            .execute_pre_hooks(&arch_node.hooks, input, &context)
+    /// Run a failed request again under the same ID
+    ///
+    /// It leaves the dead letters once it succeeds; failing again records
+    /// another attempt. Session requests are replayed with the history they
+    /// had, but the new turn isn't saved to the session.
+    pub async fn requeue(&self, request_id: &Uuid) -> Result<PipelineOutput, ProcessorError> {
+        let letter = self
+            .dead_letters
+            .get(request_id)
+            .await
+            .ok_or(ProcessorError::DeadLetterNotFound(*request_id))?;
+
+        let output = self
+            .run_output(letter.input.to_request(letter.request_id), None)
             .await?;
+        if let Err(e) = self.dead_letters.remove(request_id).await {
+            warn!("Failed to remove dead letter {}: {}", request_id, e);
+        }
+        Ok(output)
+    }
 
-        // Extract string from result
-        Ok(match result {
-            Value::String(s) => s,
-            other => other.to_string(),
+    /// What a recent request was sent with: all of it when it failed, or
+    /// else the prompt its trace kept
+    pub async fn replay_input(&self, request_id: &Uuid) -> Option<DeadLetterInput> {
+        if let Some(letter) = self.dead_letters.get(request_id).await {
+            return Some(letter.input);
+        }
+        self.traces.get(request_id).map(|trace| DeadLetterInput {
+            prompt: trace.prompt,
+            ..Default::default()
         })
     }
 
-    /// Execute post-hooks for a node, returning potentially modified output
-    async fn execute_post_hooks(
+    /// Run a recent request's input through the pipeline again, or only
+    /// through the handler `node`, returning the new request's ID and output
+    ///
+    /// Unlike a requeue, the replay runs under a new ID and leaves any dead
+    /// letter of the request alone, so a fixed composition can be tried on
+    /// it first. A handler run on its own gets the request's original
+    /// prompt, not what the nodes before it would have passed it.
+    pub async fn replay(
         &self,
-        node_name: &str,
-        request: &PipelineRequest,
-        input: &str,
-        output: String,
-    ) -> Result<String, ProcessorError> {
-        let Some(hook_executor) = &self.hook_executor else {
-            return Ok(output);
-        };
+        request_id: &Uuid,
+        node: Option<&str>,
+    ) -> Result<(Uuid, PipelineOutput), ProcessorError> {
+        let input = self
+            .replay_input(request_id)
+            .await
+            .ok_or(ProcessorError::RequestNotFound(*request_id))?;
+        let request = input.to_request(Uuid::new_v4());
+        let replay_id = request.request_id;
+        debug!("Replaying request {} as {}", request_id, replay_id);
 
-        let Some(arch_node) = self.arch_nodes.get(node_name) else {
-            return Ok(output);
+        let output = match node {
+            Some(node) => self.run_node(request, node).await?,
+            None => self.run_output(request, None).await?,
         };
+        Ok((replay_id, output))
+    }
 
-        if arch_node.hooks.post.is_empty() {
-            return Ok(output);
-        }
+    /// Use a specific store for conversation sessions
+    pub fn with_session_store(mut self, store: Box<dyn SessionStore>) -> Self {
+        self.sessions = Arc::from(store);
+        self
+    }
 
-        debug!(
-            "Executing {} post-hooks for node '{}'",
-            arch_node.hooks.post.len(),
-            node_name
-        );
+    /// Use a specific vector store for a retriever node
+    pub fn with_vector_store(
+        mut self,
+        node_name: impl Into<String>,
+        store: Box<dyn VectorStore>,
+    ) -> Self {
+        self.stores.insert(node_name.into(), store);
+        self
+    }
 
-        let context = self.build_hook_context(node_name, request);
-        let input_value = Value::String(input.to_string());
-        let output_value = Value::String(output);
+    /// Snapshot every model's circuit breaker, keyed by node name
+    pub fn breaker_states(&self) -> BTreeMap<String, BreakerStatus> {
+        self.breakers
+            .iter()
+            .map(|(name, breaker)| (name.clone(), breaker.status()))
+            .collect()
+    }
 
-        let result = hook_executor
-            .execute_post_hooks(&arch_node.hooks, &input_value, output_value, &context)
-            .await?;
+    /// Base URLs each node's model is called at, keyed by node name; nodes
+    /// with runner replicas list one per replica
+    pub fn node_endpoints(&self) -> BTreeMap<String, Vec<String>> {
+        self.clients
+            .iter()
+            .map(|(name, client)| {
+                let endpoints = match self.pools.get(name) {
+                    Some(pool) if !pool.is_empty() => pool
+                        .endpoints()
+                        .iter()
+                        .map(|e| runner_base_url(e))
+                        .collect(),
+                    _ => vec![client.base_url().to_string()],
+                };
+                (name.clone(), endpoints)
+            })
+            .collect()
+    }
 
-        // Extract string from result
-        Ok(match result {
-            Value::String(s) => s,
-            other => other.to_string(),
-        })
+    /// Snapshot the calls in flight to every model, keyed by model name
+    pub fn concurrency_states(&self) -> BTreeMap<String, ConcurrencyStatus> {
+        self.limits
+            .iter()
+            .map(|(model, limit)| (model.clone(), limit.status()))
+            .collect()
     }
 
-    /// Build hook context from request
-    fn build_hook_context(&self, node_name: &str, request: &PipelineRequest) -> HookContext {
-        let mut context = HookContext::new(node_name, &request.request_id.to_string());
+    /// Hook calls so far, by function name
+    pub fn hook_stats(&self) -> BTreeMap<String, HookStats> {
+        self.hook_executor
+            .as_ref()
+            .map(|h| h.metrics().snapshot())
+            .unwrap_or_default()
+    }
 
-        // Set previous node if available
-        if let Some(prev_hop) = request.trace.last() {
-            context.prev_node = Some(prev_hop.node_name.clone());
-        }
+    /// Process a user message through the pipeline
+    pub async fn process(&self, user_message: &str) -> Result<String, ProcessorError> {
+        self.run(PipelineRequest::new(user_message.to_string()), None)
+            .await
+    }
 
-        // Add custom variables from request (String -> Value conversion)
-        for (key, value) in request.get_variables() {
-            context
-                .custom_vars
-                .insert(key.clone(), Value::String(value.clone()));
-        }
+    /// Process a prepared request, keeping its ID for tracing
+    pub async fn process_request(
+        &self,
+        request: PipelineRequest,
+    ) -> Result<String, ProcessorError> {
+        self.run(request, None).await
+    }
 
-        context
+    /// Process a prepared request, returning tool calls along with the answer
+    pub async fn complete(
+        &self,
+        request: PipelineRequest,
+    ) -> Result<PipelineOutput, ProcessorError> {
+        self.run_output(request, None).await
     }
 
-    /// Get the next target node names from a node's output_targets
-    fn get_next_targets(&self, node: &RuntimeNode) -> Result<Vec<String>, ProcessorError> {
-        match &node.output_targets {
-            Some(OutputTarget::Layers(layers)) => {
-                // Get all nodes in the target layers
-                let mut targets = Vec::new();
-                for layer in layers {
-                    let layer_nodes: Vec<String> = self
-                        .nodes
-                        .values()
-                        .filter(|n| n.layer == *layer && !n.is_output())
-                        .map(|n| n.name.clone())
-                        .collect();
-                    targets.extend(layer_nodes);
-                }
-                if targets.is_empty() {
-                    // Check for output nodes if no handler nodes found
-                    for layer in layers {
-                        let output_nodes: Vec<String> = self
-                            .nodes
-                            .values()
-                            .filter(|n| n.layer == *layer && n.is_output())
-                            .map(|n| n.name.clone())
-                            .collect();
-                        targets.extend(output_nodes);
-                    }
-                }
-                Ok(targets)
-            }
-            Some(OutputTarget::Nodes(nodes)) => Ok(nodes.clone()),
-            None => Err(ProcessorError::ApiError(
-                "Node has no output targets".to_string(),
-            )),
-        }
+    /// `complete`, publishing the request's progress like
+    /// `process_streaming`
+    pub async fn complete_streaming(
+        &self,
+        request: PipelineRequest,
+        events: &UnboundedSender<PipelineEvent>,
+    ) -> Result<PipelineOutput, ProcessorError> {
+        self.run_output(request, Some(events)).await
     }
 
-    /// Get next targets filtered by condition evaluation
-    fn get_next_targets_filtered(
+    /// Process a request as the next turn of a conversation
+    ///
+    /// The session's earlier turns are replayed to handler nodes, and the
+    /// new exchange is saved once the pipeline answers. Failed requests, and
+    /// requests that end in tool calls, leave the session untouched.
+    pub async fn process_session(
         &self,
-        node: &RuntimeNode,
-        request: &PipelineRequest,
-    ) -> Result<Vec<String>, ProcessorError> {
-        let all_targets = self.get_next_targets(node)?;
-
-        // Filter targets by their conditions
-        let filtered: Vec<String> = all_targets
-            .into_iter()
-            .filter(|target_name| {
-                if let Some(target_node) = self.nodes.get(target_name) {
-                    // If node has a condition, evaluate it; otherwise pass
-                    target_node
-                        .condition
-                        .as_ref()
-                        .map(|c| evaluate_condition(c, request.get_variables()))
-                        .unwrap_or(true)
-                } else {
-                    true // Node not found, let later code handle error
-                }
-            })
-            .collect();
-
-        // If all targets were filtered out by conditions, return all targets
-        // This prevents getting stuck - conditions act as preferences, not blockers
-        if filtered.is_empty() {
-            self.get_next_targets(node)
-        } else {
-            Ok(filtered)
-        }
+        session_id: &str,
+        request: PipelineRequest,
+    ) -> Result<PipelineOutput, ProcessorError> {
+        self.run_session(session_id, request, None).await
     }
 
-    /// Route to select one target from multiple options
-    async fn route_to_target(
+    /// Process a request as the next turn of a conversation, publishing its
+    /// progress like `process_streaming`
+    pub async fn process_session_streaming(
         &self,
-        router_name: &str,
-        content: &str,
-        targets: &[String],
-    ) -> Result<String, ProcessorError> {
-        let router_client = self
-            .clients
-            .get(router_name)
-            .ok_or_else(|| ProcessorError::HandlerNoModel(router_name.to_string()))?;
-
-        let router_node = self
-            .nodes
-            .get(router_name)
-            .ok_or_else(|| ProcessorError::HandlerNotFound(router_name.to_string()))?;
+        session_id: &str,
+        request: PipelineRequest,
+        events: &UnboundedSender<PipelineEvent>,
+    ) -> Result<PipelineOutput, ProcessorError> {
+        self.run_session(session_id, request, Some(events)).await
+    }
 
-        let metadata: Vec<NodeMetadata> = targets
-            .iter()
-            .filter_map(|name| self.nodes.get(name))
-            .map(|n| NodeMetadata {
-                name: n.name.clone(),
-                use_case: n.use_case.clone(),
-            })
-            .collect();
+    async fn run_session(
+        &self,
+        session_id: &str,
+        request: PipelineRequest,
+        events: Option<&UnboundedSender<PipelineEvent>>,
+    ) -> Result<PipelineOutput, ProcessorError> {
+        let history = self
+            .sessions
+            .load(session_id)
+            .await
+            .map_err(|e| ProcessorError::Session(e.to_string()))?;
+        let mut history = trim_history(history, self.session_config.max_tokens);
 
-        if metadata.is_empty() {
-            return Err(ProcessorError::ApiError(
-                "No valid targets for routing".to_string(),
-            ));
+        let prompt = request.original_prompt.clone();
+        let output = self
+            .run_output(request.with_history(history.clone()), events)
+            .await?;
+        if !output.tool_calls.is_empty() {
+            return Ok(output);
         }
 
-        let routing_prompt = build_routing_prompt(content, &metadata);
-        let model = router_node
-            .model_override()
-            .unwrap_or_else(|| self.router_model_name.clone());
+        append_turn(&mut history, &prompt, &output.content);
+        let history = trim_history(history, self.session_config.max_tokens);
+        self.sessions
+            .save(session_id, &history)
+            .await
+            .map_err(|e| ProcessorError::Session(e.to_string()))?;
 
-        let request = ClientRequest {
-            model,
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: routing_prompt,
-            }],
-            max_tokens: Some(100),
-            temperature: Some(0.1),
-        };
+        Ok(output)
+    }
 
-        let response = router_client
-            .chat_completion(&request)
+    /// Forget a conversation session
+    pub async fn clear_session(&self, session_id: &str) -> Result<(), ProcessorError> {
+        self.sessions
+            .delete(session_id)
             .await
-            .map_err(|e| ProcessorError::ApiError(e.to_string()))?;
+            .map_err(|e| ProcessorError::Session(e.to_string()))
+    }
 
-        let output = response
-            .choices
-            .first()
-            .map(|c| c.message.content.clone())
-            .unwrap_or_default();
+    /// Process a request, publishing each hop's output as it is produced,
+    /// and the final answer's chunks when the node giving it can stream
+    ///
+    /// The final answer is returned rather than sent, so callers decide how
+    /// to report completion or failure.
+    pub async fn process_streaming(
+        &self,
+        request: PipelineRequest,
+        events: &UnboundedSender<PipelineEvent>,
+    ) -> Result<String, ProcessorError> {
+        self.run(request, Some(events)).await
+    }
+
+    async fn run(
+        &self,
+        request: PipelineRequest,
+        events: Option<&UnboundedSender<PipelineEvent>>,
+    ) -> Result<String, ProcessorError> {
+        self.run_output(request, events).await.map(|o| o.content)
+    }
 
-        extract_node_selection(&output, &metadata)
-            .map_err(|e| ProcessorError::ApiError(e.to_string()))
+    async fn run_output(
+        &self,
+        mut request: PipelineRequest,
+        events: Option<&UnboundedSender<PipelineEvent>>,
+    ) -> Result<PipelineOutput, ProcessorError> {
+        let input = DeadLetterInput::capture(&request);
+        if let Some(deadline) = self.deadline.filter(|_| request.deadline.is_none()) {
+            request = request.with_deadline(deadline);
+        }
+        // Work between model calls, like hooks, can't overrun it either
+        let result = match request.deadline {
+            Some(deadline) => {
+                tokio::time::timeout_at(deadline.into(), self.run_hops(&mut request, events))
+                    .await
+                    .unwrap_or_else(|_| {
+                        let node = request.trace.last().map(|h| h.node_name.clone());
+                        Err(ProcessorError::DeadlineExceeded(
+                            node.unwrap_or_else(|| self.router_node_name.clone()),
+                        ))
+                    })
+            }
+            None => self.run_hops(&mut request, events).await,
+        };
+        let trace = RequestTrace::capture(&request, &result);
+        if result.is_err() {
+            let letter = DeadLetter::new(input, trace.clone());
+            if let Err(e) = self.dead_letters.record(letter).await {
+                warn!("Failed to record dead letter {}: {}", request.request_id, e);
+            }
+        }
+        if let Some(logger) = &self.request_logger {
+            if let Err(e) = logger.record(&trace).await {
+                warn!("Failed to log request {}: {}", request.request_id, e);
+            }
+        }
+        self.traces.record(trace);
+        let route = self.route_steps(&request);
+        result.map(|content| PipelineOutput {
+            content,
+            tool_calls: std::mem::take(&mut request.tool_calls),
+            route,
+        })
     }
 
-    /// Call a node's LLM with content
-    async fn call_node_llm(
+    /// Run a single handler on a request, as if the router had sent it
+    /// there and the handler led straight to the output, keeping its trace
+    async fn run_node(
         &self,
+        mut request: PipelineRequest,
         node_name: &str,
-        content: &str,
-    ) -> Result<String, ProcessorError> {
+    ) -> Result<PipelineOutput, ProcessorError> {
         let node = self
             .nodes
             .get(node_name)
             .ok_or_else(|| ProcessorError::HandlerNotFound(node_name.to_string()))?;
+        if !self.is_plain_handler(node_name) {
+            return Err(ProcessorError::NotReplayable(node_name.to_string()));
+        }
+        if let Some(deadline) = self.deadline.filter(|_| request.deadline.is_none()) {
+            request = request.with_deadline(deadline);
+        }
 
-        let client = self
-            .clients
-            .get(node_name)
-            .ok_or_else(|| ProcessorError::HandlerNoModel(node_name.to_string()))?;
-
-        let model = node
-            .model_override()
-            .unwrap_or_else(|| node_name.to_string());
+        request.set_current_layer(node.layer);
+        request.add_hop(
+            node_name.to_string(),
+            node.layer,
+            Some(node_name.to_string()),
+        );
+        let result = self.run_handler_hop(node_name, &mut request).await;
+        self.traces.record(RequestTrace::capture(&request, &result));
+        let route = self.route_steps(&request);
+        result.map(|content| PipelineOutput {
+            content,
+            tool_calls: std::mem::take(&mut request.tool_calls),
+            route,
+        })
+    }
 
-        let request = ClientRequest {
-            model,
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: content.to_string(),
-            }],
-            max_tokens: Some(1024),
-            temperature: Some(0.7),
+    /// A handler's answer to the request's current content, through its
+    /// hooks, completing the hop already recorded for it
+    async fn run_handler_hop(
+        &self,
+        node_name: &str,
+        request: &mut PipelineRequest,
+    ) -> Result<String, ProcessorError> {
+        let started = Instant::now();
+        let deadline = self.hop_deadline(node_name, request)?;
+        let input = self.execute_pre_hooks(node_name, request).await?;
+        let (tools, tool_choice) = if self.tool_nodes.contains(node_name) {
+            (request.tools.as_slice(), request.tool_choice.as_ref())
+        } else {
+            (&[][..], None)
         };
+        let (reply, usage) = self
+            .call_handler(node_name, request, &input, tools, tool_choice, deadline)
+            .await?;
 
-        let response = client
-            .chat_completion(&request)
-            .await
-            .map_err(|e| ProcessorError::ApiError(e.to_string()))?;
-
-        Ok(response
-            .choices
-            .first()
-            .map(|c| c.message.content.clone())
-            .unwrap_or_else(|| "No response generated".to_string()))
+        let output = if reply.tool_calls.is_empty() {
+            self.execute_post_hooks(node_name, request, &input, reply.content, false)
+                .await?
+        } else {
+            request.tool_calls = reply.tool_calls;
+            reply.content
+        };
+        request.complete_hop(
+            elapsed_ms(started),
+            usage.map(|u| (u.prompt_tokens, u.completion_tokens)),
+        );
+        Ok(output)
     }
 
-    /// Get number of nodes
-    pub fn node_count(&self) -> usize {
-        self.nodes.len()
+    /// The router and each node a request went through, without the output
+    fn route_steps(&self, request: &PipelineRequest) -> Vec<RouteStep> {
+        let routing = |node: &str| request.routing_ms.get(node).copied();
+        let router = RouteStep {
+            node: self.router_node_name.clone(),
+            latency_ms: routing(&self.router_node_name),
+        };
+        let hops = request
+            .trace
+            .iter()
+            .filter(|hop| {
+                self.nodes
+                    .get(&hop.node_name)
+                    .is_some_and(|node| !self.ends_pipeline(node))
+            })
+            .map(|hop| RouteStep {
+                node: hop.node_name.clone(),
+                latency_ms: match (hop.latency_ms, routing(&hop.node_name)) {
+                    (None, None) => None,
+                    (hop_ms, routing_ms) => Some(hop_ms.unwrap_or(0) + routing_ms.unwrap_or(0)),
+                },
+            });
+        std::iter::once(router).chain(hops).collect()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    async fn run_hops(
+        &self,
+        request: &mut PipelineRequest,
+        events: Option<&UnboundedSender<PipelineEvent>>,
+    ) -> Result<String, ProcessorError> {
+        let mut current_node_name = self.router_node_name.clone();
+        // Set when a guard diverts the request to its fallback node
+        let mut forced_target: Option<String> = None;
+        // Answers of the handlers fanned out to, for the aggregator to combine
+        let mut fanned_out: Option<Vec<String>> = None;
+        // What each node was given, so an evaluator can send it back
+        let mut node_inputs: HashMap<String, String> = HashMap::new();
+        // Best score and answer seen by each evaluator
+        let mut best_answers: HashMap<String, (f64, String)> = HashMap::new();
+        // Passes each looping node has made, the current one included
+        let mut loop_passes: HashMap<String, u32> = HashMap::new();
 
-    #[test]
-    fn test_processor_creation() {
-        let json = r#"{
-            "models": {
-                "router-model": {
-                    "type": "external",
-                    "interface": "openai-api",
-                    "url": "http://localhost:8080"
-                },
-                "handler-model": {
-                    "type": "external",
-                    "interface": "openai-api",
-                    "url": "http://localhost:8081"
+        loop {
+            let hop_count: usize = request.trace.len();
+            if hop_count >= self.max_hops {
+                return Err(ProcessorError::ApiError(format!(
+                    "Maximum hops ({}) exceeded",
+                    self.max_hops
+                )));
+            }
+
+            let current_node = self
+                .nodes
+                .get(&current_node_name)
+                .ok_or_else(|| ProcessorError::HandlerNotFound(current_node_name.clone()))?;
+
+            // Set current layer for condition evaluation
+            request.set_current_layer(current_node.layer);
+
+            // A looping node sends the request back until its passes run
+            // out or the loop target's condition fails
+            if forced_target.is_none() && current_node_name != self.router_node_name {
+                if let Some(arch_node) = self.arch_nodes.get(&current_node_name) {
+                    if let Some(target) = &arch_node.loop_to {
+                        let passes = loop_passes.entry(current_node_name.clone()).or_insert(1);
+                        let allowed = self
+                            .nodes
+                            .get(target)
+                            .and_then(|n| n.condition.as_ref())
+                            .is_none_or(|c| evaluate_condition(c, request.get_variables()));
+                        if *passes < arch_node.loop_iterations() && allowed {
+                            *passes += 1;
+                            debug!(
+                                "Looping from '{}' to '{}', pass {}",
+                                current_node_name, target, passes
+                            );
+                            request.set_variable(vars::ITERATION.to_string(), passes.to_string());
+                            forced_target = Some(target.clone());
+                        }
+                    }
                 }
-            },
-            "architecture": [
-                {
-                    "name": "router",
-                    "layer": 0,
-                    "model": "router-model",
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "handler",
-                    "layer": 1,
-                    "model": "handler-model",
-                    "adapter": "openai-api",
-                    "use-case": "General handler",
-                    "output-to": ["output"]
-                },
-                {
-                    "name": "output",
-                    "adapter": "output"
+            }
+
+            // A client-chosen route is taken as soon as it is reachable,
+            // regardless of conditions, without asking the router; while
+            // its breaker is open the request is routed as usual instead
+            if forced_target.is_none() {
+                if let Some(route) = request.route.clone() {
+                    if self.get_next_targets(current_node)?.contains(&route) {
+                        request.route = None;
+                        if self.breakers.get(&route).is_some_and(|b| b.is_open()) {
+                            debug!(
+                                "Breaker of '{}' is open, routing the client's request as usual",
+                                route
+                            );
+                        } else {
+                            debug!("Routing to '{}' as requested by the client", route);
+                            forced_target = Some(route);
+                        }
+                    }
                 }
-            ]
-        }"#;
+            }
 
-        let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
+            let selected_target = match forced_target.take() {
+                Some(target) => target,
+                None => {
+                    // Determine next targets, filtering by conditions and skipping
+                    // models whose breaker is open so routing falls back to the rest
+                    let next_targets = self.without_open_breakers(
+                        self.get_next_targets_filtered(current_node, request)?,
+                    );
 
-        assert_eq!(processor.node_count(), 3);
-        assert_eq!(processor.router_node_name, "router");
-    }
+                    // If multiple targets, either ask all of them when they
+                    // feed an aggregator, or route to one
+                    if next_targets.len() > 1 {
+                        if let Some(aggregator) = self.fan_out_target(&next_targets) {
+                            fanned_out = Some(self.fan_out(request, &next_targets, events).await?);
+                            aggregator
+                        } else {
+                            let routing_started = Instant::now();
+                            let deadline = self.hop_deadline(&current_node_name, request)?;
+                            let target = self
+                                .route_to_target(
+                                    &current_node_name,
+                                    &request.current_content,
+                                    &next_targets,
+                                    deadline,
+                                )
+                                .await?;
+                            *request
+                                .routing_ms
+                                .entry(current_node_name.clone())
+                                .or_default() += routing_started.elapsed().as_millis() as u64;
+                            target
+                        }
+                    } else if next_targets.len() == 1 {
+                        next_targets[0].clone()
+                    } else {
+                        return Err(ProcessorError::ApiError(
+                            "No next targets found".to_string(),
+                        ));
+                    }
+                }
+            };
 
-    #[test]
-    fn test_processor_no_router_model() {
-        // A composition with a router that has no model configured
-        let json = r#"{
-            "models": {},
-            "architecture": [
-                {
-                    "name": "router",
-                    "layer": 0,
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "handler",
-                    "layer": 1,
-                    "adapter": "openai-api",
-                    "output-to": ["output"]
+            // Check if we've reached output
+            if let Some(target_node) = self.nodes.get(&selected_target) {
+                if self.ends_pipeline(target_node) {
+                    request.add_hop(selected_target.clone(), target_node.layer, None);
+                    return match self.plugins.get(&selected_target) {
+                        Some(plugin) => {
+                            let content = request.current_content.clone();
+                            let ctx = self.adapter_context(&selected_target, request)?;
+                            plugin.handle_output(&ctx, &content).await.map_err(|e| {
+                                ProcessorError::AdapterFailed(
+                                    selected_target.clone(),
+                                    e.to_string(),
+                                )
+                            })
+                        }
+                        None => Ok(request.current_content.clone()),
+                    };
+                }
+            }
+
+            // Record the hop before calling LLM
+            let target_layer = self
+                .nodes
+                .get(&selected_target)
+                .map(|n| n.layer)
+                .unwrap_or(0);
+            request.add_hop(
+                selected_target.clone(),
+                target_layer,
+                Some(selected_target.clone()),
+            );
+            let hop_started = Instant::now();
+            let deadline = self.hop_deadline(&selected_target, request)?;
+            let mut usage: Option<Usage> = None;
+            let mut streamed = false;
+            node_inputs.insert(selected_target.clone(), request.current_content.clone());
+
+            // Execute pre-hooks for the target node
+            let input_content = self.execute_pre_hooks(&selected_target, request).await?;
+
+            // Call the selected node's LLM. Embedding nodes stash the vector
+            // in a variable and pass the content through unchanged; retrievers
+            // pass it on with the matching documents injected; handoffs
+            // with another pipeline's answer. Plugin nodes are handed to
+            // their adapter.
+            let target_node = self.nodes.get(&selected_target);
+            let llm_output = if let Some(plugin) = self.plugins.get(&selected_target) {
+                let ctx = self.adapter_context(&selected_target, request)?;
+                plugin
+                    .handle_input(&ctx, &input_content)
+                    .await
+                    .map_err(|e| {
+                        ProcessorError::AdapterFailed(selected_target.clone(), e.to_string())
+                    })?
+            } else if target_node.is_some_and(|n| n.is_embedding()) {
+                let vector = self
+                    .embed_content(&selected_target, &input_content, deadline)
+                    .await?;
+                request.set_variable(vars::EMBEDDING.to_string(), vector);
+                input_content.clone()
+            } else if target_node.is_some_and(|n| n.is_retriever()) {
+                self.retrieve(&selected_target, request, &input_content, deadline)
+                    .await?
+            } else if let Some(handoff) = self.handoffs.get(&selected_target) {
+                if request.handoff_depth >= MAX_HANDOFF_DEPTH {
+                    return Err(ProcessorError::AdapterFailed(
+                        selected_target.clone(),
+                        format!(
+                            "request was already handed off {} times",
+                            request.handoff_depth
+                        ),
+                    ));
+                }
+                let depth = request.handoff_depth + 1;
+                self.guarded(
+                    &selected_target,
+                    deadline,
+                    handoff.send(&input_content, depth),
+                )
+                .await?
+            } else if let Some(guard) = self.guards.get(&selected_target) {
+                let (outcome, reason) = self
+                    .check_guard(&selected_target, guard, &input_content, deadline)
+                    .await?;
+                if let Some(reason) = reason {
+                    debug!("Guard '{}' tripped: {}", selected_target, reason);
+                    request.set_variable(vars::GUARD_VIOLATION.to_string(), reason);
+                }
+                match outcome {
+                    GuardOutcome::Pass => input_content.clone(),
+                    GuardOutcome::Redact(redacted) => redacted,
+                    GuardOutcome::Block(refusal) => {
+                        request.complete_hop(elapsed_ms(hop_started), None);
+                        return Ok(refusal);
+                    }
+                    GuardOutcome::Fallback(node) => {
+                        forced_target = Some(node);
+                        input_content.clone()
+                    }
+                }
+            } else if let Some(config) = self.aggregators.get(&selected_target) {
+                // Reached without a fan-out, the single answer is the input
+                let answers = fanned_out
+                    .take()
+                    .unwrap_or_else(|| vec![input_content.clone()]);
+                let (output, reported) = self
+                    .aggregate(&selected_target, config, &input_content, answers, deadline)
+                    .await?;
+                usage = reported;
+                output
+            } else if let Some(evaluator) = self.evaluators.get(&selected_target) {
+                let rubric_score = match evaluator.rubric() {
+                    Some(rubric) => {
+                        let question = node_inputs
+                            .get(&current_node_name)
+                            .map(String::as_str)
+                            .unwrap_or(&request.original_prompt);
+                        let prompt = build_rubric_prompt(rubric, question, &input_content);
+                        let (reply, reported) = self
+                            .call_node_llm(&selected_target, &prompt, deadline)
+                            .await?;
+                        usage = reported;
+                        Some(parse_rubric_score(&reply))
+                    }
+                    None => None,
+                };
+                let score = evaluator.score(&input_content, rubric_score);
+                request.record_score(score);
+
+                let best = best_answers
+                    .entry(selected_target.clone())
+                    .or_insert_with(|| (score, input_content.clone()));
+                if score > best.0 {
+                    *best = (score, input_content.clone());
+                }
+
+                // Attempts so far, this one included
+                let attempts = request
+                    .trace
+                    .iter()
+                    .filter(|hop| hop.node_name == selected_target)
+                    .count();
+                let retry_input = node_inputs.get(&current_node_name).filter(|_| {
+                    current_node_name != self.router_node_name
+                        && !self.aggregators.contains_key(&current_node_name)
+                });
+                match retry_input {
+                    _ if evaluator.passes(score) => input_content.clone(),
+                    Some(input) if attempts <= evaluator.max_retries() as usize => {
+                        debug!(
+                            "Evaluator '{}' scored {:.2}, asking '{}' again",
+                            selected_target, score, current_node_name
+                        );
+                        forced_target = Some(current_node_name.clone());
+                        input.clone()
+                    }
+                    _ => best.1.clone(),
+                }
+            } else {
+                let (tools, tool_choice) = if self.tool_nodes.contains(&selected_target) {
+                    (request.tools.as_slice(), request.tool_choice.as_ref())
+                } else {
+                    (&[][..], None)
+                };
+                // The final answer reaches stream subscribers as it is
+                // generated, when nothing after the node needs it whole
+                let stream_to =
+                    events.filter(|_| tools.is_empty() && self.streams_answer(&selected_target));
+                if let Some(events) = stream_to {
+                    streamed = true;
+                    self.stream_handler(&selected_target, request, &input_content, events, deadline)
+                        .await?
+                } else {
+                    let (reply, reported) = self
+                        .call_handler(
+                            &selected_target,
+                            request,
+                            &input_content,
+                            tools,
+                            tool_choice,
+                            deadline,
+                        )
+                        .await?;
+                    usage = reported;
+
+                    // The client has to run the tools before the pipeline can
+                    // continue, so hand the calls back instead of an answer
+                    if !reply.tool_calls.is_empty() {
+                        request.complete_hop(
+                            elapsed_ms(hop_started),
+                            usage.map(|u| (u.prompt_tokens, u.completion_tokens)),
+                        );
+                        request.tool_calls = reply.tool_calls;
+                        return Ok(reply.content);
+                    }
+                    reply.content
+                }
+            };
+
+            // Execute post-hooks for the target node
+            let final_output = self
+                .execute_post_hooks(
+                    &selected_target,
+                    request,
+                    &input_content,
+                    llm_output,
+                    streamed,
+                )
+                .await?;
+
+            request.complete_hop(
+                elapsed_ms(hop_started),
+                usage.map(|u| (u.prompt_tokens, u.completion_tokens)),
+            );
+
+            if let Some(events) = events {
+                // A closed channel only means the subscriber went away
+                let _ = events.send(PipelineEvent::Hop {
+                    request_id: request.request_id,
+                    node: selected_target.clone(),
+                    layer: target_layer,
+                    output: final_output.clone(),
+                });
+            }
+
+            request.set_content(final_output);
+            current_node_name = selected_target;
+        }
+    }
+
+    /// Send a handler its input, wrapped in its prompt template and system
+    /// prompt. Images only go to models that accept them.
+    async fn call_handler(
+        &self,
+        node_name: &str,
+        request: &PipelineRequest,
+        input: &str,
+        tools: &[Tool],
+        tool_choice: Option<&ToolChoice>,
+        deadline: Option<Instant>,
+    ) -> Result<(Message, Option<Usage>), ProcessorError> {
+        // Handlers placed on another worker answer there
+        if let Some(peer) = self.peers.get(node_name) {
+            let hop = MeshHop {
+                request_id: request.request_id,
+                node: node_name.to_string(),
+                input: input.to_string(),
+                request: DeadLetterInput::capture(request),
+            };
+            return self.guarded(node_name, deadline, peer.send(&hop)).await;
+        }
+        let messages = self.handler_messages(node_name, request, input);
+        self.chat(node_name, messages, tools, tool_choice, deadline)
+            .await
+    }
+
+    /// Stream a handler's answer, passing each chunk through the node's
+    /// stream-transform hooks and publishing what is left of it
+    async fn stream_handler(
+        &self,
+        node_name: &str,
+        request: &PipelineRequest,
+        input: &str,
+        events: &UnboundedSender<PipelineEvent>,
+        deadline: Option<Instant>,
+    ) -> Result<String, ProcessorError> {
+        let messages = self.handler_messages(node_name, request, input);
+        let client_request = self.chat_request(node_name, messages, &[], None, deadline)?;
+        let (client, _lease) = self.client_for(node_name)?;
+        let hooks = self
+            .hook_executor
+            .as_ref()
+            .zip(self.arch_nodes.get(node_name).map(|n| &n.hooks));
+        let context = self.build_hook_context(node_name, request);
+        let input = Value::String(input.to_string());
+
+        // A failing hook isn't the model's fault, so it is kept apart from
+        // the errors the circuit breaker counts
+        let answer = self
+            .guarded(node_name, deadline, async {
+                let mut chunks = client.chat_completion_stream(&client_request).await?;
+                let mut answer = String::new();
+                let mut index = 0;
+                while let Some(chunk) = chunks.next().await {
+                    let chunk = match hooks {
+                        Some((executor, hooks)) => {
+                            match executor
+                                .transform_chunk(hooks, &input, chunk?, index, &context)
+                                .await
+                            {
+                                Ok(chunk) => chunk,
+                                Err(e) => return Ok(Err(e)),
+                            }
+                        }
+                        None => Some(chunk?),
+                    };
+                    index += 1;
+                    let Some(chunk) = chunk else {
+                        continue;
+                    };
+                    answer.push_str(&chunk);
+                    // A closed channel only means the subscriber went away
+                    let _ = events.send(PipelineEvent::Chunk {
+                        request_id: request.request_id,
+                        node: node_name.to_string(),
+                        content: chunk,
+                    });
+                }
+                Ok(Ok(answer))
+            })
+            .await?;
+        Ok(answer?)
+    }
+
+    /// Whether a handler's answer can be streamed: it goes straight to an
+    /// output node, without looping, and no post-hook needs it whole
+    fn streams_answer(&self, node_name: &str) -> bool {
+        let (Some(node), Some(arch_node)) =
+            (self.nodes.get(node_name), self.arch_nodes.get(node_name))
+        else {
+            return false;
+        };
+        arch_node.loop_to.is_none()
+            && !arch_node.hooks.needs_whole_output()
+            && !self.peers.contains_key(node_name)
+            && self.budget_for(node_name).is_none()
+            && self.get_next_targets(node).is_ok_and(|targets| {
+                !targets.is_empty()
+                    && targets.iter().all(|t| {
+                        self.nodes.get(t).is_some_and(|n| n.is_output())
+                            && !self.plugins.contains_key(t)
+                    })
+            })
+    }
+
+    /// A handler's conversation: its input wrapped in its prompt template
+    /// and system prompt, with the images its model accepts
+    fn handler_messages(
+        &self,
+        node_name: &str,
+        request: &PipelineRequest,
+        input: &str,
+    ) -> Vec<Message> {
+        let arch_node = self.arch_nodes.get(node_name);
+        let prompt = match arch_node.and_then(|n| n.prompt_template.as_deref()) {
+            Some(template) => request.render_prompt(template, input),
+            None => input.to_string(),
+        };
+        let system_prompt = arch_node
+            .and_then(|n| n.system_prompt.as_deref())
+            .map(|s| request.render_prompt(s, input));
+
+        let mut messages = request.handler_messages(system_prompt.as_deref(), &prompt);
+        if !self.vision_nodes.contains(node_name) {
+            let mut dropped = 0;
+            for message in &mut messages {
+                dropped += std::mem::take(&mut message.images).len();
+            }
+            if dropped > 0 {
+                debug!(
+                    "Dropped {} images for '{}', whose model doesn't accept images",
+                    dropped, node_name
+                );
+            }
+        }
+        if let Some(cache) = self.prompt_caches.get(node_name) {
+            request.mark_prompt_cache(&mut messages, cache);
+        }
+        messages
+    }
+
+    /// The aggregator every target feeds, when the request should go to all
+    /// of them instead of one
+    ///
+    /// Only plain handlers fan out; a guard, retriever, embedding, evaluator,
+    /// handoff or plugin node among the targets means the router picks as
+    /// usual.
+    fn fan_out_target(&self, targets: &[String]) -> Option<String> {
+        let mut aggregator: Option<String> = None;
+        for target in targets {
+            if !self.is_plain_handler(target) {
+                return None;
+            }
+            let node = self.nodes.get(target)?;
+
+            let next = self.get_next_targets(node).ok()?;
+            match next.as_slice() {
+                [next] if self.aggregators.contains_key(next) => {
+                    if aggregator.get_or_insert_with(|| next.clone()) != next {
+                        return None;
+                    }
+                }
+                _ => return None,
+            }
+        }
+        aggregator
+    }
+
+    /// Whether a node only sends its input to its model: not the router, an
+    /// output, or a guard, retriever, embedding, aggregator, evaluator,
+    /// handoff or plugin node
+    fn is_plain_handler(&self, node_name: &str) -> bool {
+        self.nodes.get(node_name).is_some_and(|node| {
+            node_name != self.router_node_name
+                && !self.ends_pipeline(node)
+                && !node.is_embedding()
+                && !node.is_retriever()
+                && !self.guards.contains_key(node_name)
+                && !self.aggregators.contains_key(node_name)
+                && !self.evaluators.contains_key(node_name)
+                && !self.handoffs.contains_key(node_name)
+                && !self.plugins.contains_key(node_name)
+        })
+    }
+
+    /// Run every target on the current content at once, recording a hop for
+    /// each, and return their answers in name order
+    ///
+    /// Handlers that fail are left out; the fan-out only fails when all of
+    /// them do. Client tools aren't offered to fanned-out handlers.
+    async fn fan_out(
+        &self,
+        request: &mut PipelineRequest,
+        targets: &[String],
+        events: Option<&UnboundedSender<PipelineEvent>>,
+    ) -> Result<Vec<String>, ProcessorError> {
+        let mut targets = targets.to_vec();
+        targets.sort();
+
+        let shared: &PipelineRequest = request;
+        let results = futures::future::join_all(targets.iter().map(|target| async move {
+            let started = Instant::now();
+            let deadline = self.hop_deadline(target, shared)?;
+            let input = self.execute_pre_hooks(target, shared).await?;
+            let (reply, usage) = self
+                .call_handler(target, shared, &input, &[], None, deadline)
+                .await?;
+            let output = self
+                .execute_post_hooks(target, shared, &input, reply.content, false)
+                .await?;
+            Ok::<_, ProcessorError>((output, usage, elapsed_ms(started)))
+        }))
+        .await;
+
+        let mut answers = Vec::new();
+        let mut first_error = None;
+        for (target, result) in targets.into_iter().zip(results) {
+            let (output, usage, latency_ms) = match result {
+                Ok(answer) => answer,
+                Err(e) => {
+                    debug!("Fanned-out handler '{}' failed: {}", target, e);
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            };
+
+            let layer = self.nodes.get(&target).map(|n| n.layer).unwrap_or(0);
+            request.add_hop(target.clone(), layer, Some(target.clone()));
+            request.complete_hop(
+                latency_ms,
+                usage.map(|u| (u.prompt_tokens, u.completion_tokens)),
+            );
+            if let Some(events) = events {
+                let _ = events.send(PipelineEvent::Hop {
+                    request_id: request.request_id,
+                    node: target,
+                    layer,
+                    output: output.clone(),
+                });
+            }
+            answers.push(output);
+        }
+
+        match first_error {
+            Some(e) if answers.is_empty() => Err(e),
+            _ => Ok(answers),
+        }
+    }
+
+    /// Combine answers at an aggregator node
+    ///
+    /// `question` is the content the handlers answered, shown to the
+    /// best-of judge.
+    async fn aggregate(
+        &self,
+        node_name: &str,
+        config: &AggregateConfig,
+        question: &str,
+        answers: Vec<String>,
+        deadline: Option<Instant>,
+    ) -> Result<(String, Option<Usage>), ProcessorError> {
+        let failed = |e: String| ProcessorError::AggregationFailed(node_name.to_string(), e);
+
+        match config.strategy {
+            AggregateStrategy::Vote => vote(&answers)
+                .map(|winner| (winner, None))
+                .ok_or_else(|| failed("no answers to vote on".to_string())),
+            AggregateStrategy::Concat => Ok((concat(&answers, &config.separator), None)),
+            AggregateStrategy::MergeJson => merge_json(&answers)
+                .map(|merged| (merged, None))
+                .map_err(failed),
+            AggregateStrategy::BestOf => {
+                if answers.len() < 2 {
+                    return Ok((answers.into_iter().next().unwrap_or_default(), None));
+                }
+
+                let instructions = config.judge_prompt.as_deref().unwrap_or(JUDGE_PROMPT);
+                let prompt = build_judge_prompt(instructions, question, &answers);
+                let (reply, usage) = self.call_node_llm(node_name, &prompt, deadline).await?;
+                let choice = parse_judge_choice(&reply, answers.len()).unwrap_or_else(|| {
+                    debug!(
+                        "Judge '{}' named no candidate, keeping the first: {}",
+                        node_name, reply
+                    );
+                    0
+                });
+                Ok((answers.into_iter().nth(choice).unwrap_or_default(), usage))
+            }
+        }
+    }
+
+    /// Execute pre-hooks for a node, returning potentially modified input
+    async fn execute_pre_hooks(
+        &self,
+        node_name: &str,
+        request: &PipelineRequest,
+    ) -> Result<String, ProcessorError> {
+        let Some(hook_executor) = &self.hook_executor else {
+            return Ok(request.current_content.clone());
+        };
+
+        let Some(arch_node) = self.arch_nodes.get(node_name) else {
+            return Ok(request.current_content.clone());
+        };
+
+        if arch_node.hooks.pre.is_empty() {
+            return Ok(request.current_content.clone());
+        }
+
+        debug!(
+            "Executing {} pre-hooks for node '{}'",
+            arch_node.hooks.pre.len(),
+            node_name
+        );
+
+        let context = self.build_hook_context(node_name, request);
+        let input = Value::String(request.current_content.clone());
+
+        let result = hook_executor
+            .execute_pre_hooks(&arch_node.hooks, input, &context)
+            .await?;
+
+        // Extract string from result
+        Ok(match result {
+            Value::String(s) => s,
+            other => other.to_string(),
+        })
+    }
+
+    /// Execute post-hooks for a node, returning potentially modified output.
+    /// A `streamed` output already went through the stream-transform hooks.
+    async fn execute_post_hooks(
+        &self,
+        node_name: &str,
+        request: &PipelineRequest,
+        input: &str,
+        output: String,
+        streamed: bool,
+    ) -> Result<String, ProcessorError> {
+        let Some(hook_executor) = &self.hook_executor else {
+            return Ok(output);
+        };
+
+        let Some(arch_node) = self.arch_nodes.get(node_name) else {
+            return Ok(output);
+        };
+
+        if arch_node.hooks.post.is_empty() {
+            return Ok(output);
+        }
+
+        debug!(
+            "Executing {} post-hooks for node '{}'",
+            arch_node.hooks.post.len(),
+            node_name
+        );
+
+        let context = self.build_hook_context(node_name, request);
+        let input_value = Value::String(input.to_string());
+        let output_value = Value::String(output);
+
+        let result = if streamed {
+            hook_executor
+                .execute_streamed_post_hooks(&arch_node.hooks, &input_value, output_value, &context)
+                .await?
+        } else {
+            hook_executor
+                .execute_post_hooks(&arch_node.hooks, &input_value, output_value, &context)
+                .await?
+        };
+
+        // Extract string from result
+        Ok(match result {
+            Value::String(s) => s,
+            other => other.to_string(),
+        })
+    }
+
+    /// Build hook context from request
+    fn build_hook_context(&self, node_name: &str, request: &PipelineRequest) -> HookContext {
+        let mut context = HookContext::new(node_name, &request.request_id.to_string());
+
+        // Set previous node if available
+        if let Some(prev_hop) = request.trace.last() {
+            context.prev_node = Some(prev_hop.node_name.clone());
+        }
+
+        // Add custom variables from request (String -> Value conversion)
+        for (key, value) in request.get_variables() {
+            context
+                .custom_vars
+                .insert(key.clone(), Value::String(value.clone()));
+        }
+
+        context
+    }
+
+    /// Get the next target node names from a node's output_targets
+    fn get_next_targets(&self, node: &RuntimeNode) -> Result<Vec<String>, ProcessorError> {
+        match &node.output_targets {
+            Some(OutputTarget::Layers(layers)) => {
+                // Get all nodes in the target layers
+                let mut targets = Vec::new();
+                for layer in layers {
+                    let layer_nodes: Vec<String> = self
+                        .nodes
+                        .values()
+                        .filter(|n| n.layer == *layer && !self.ends_pipeline(n))
+                        .map(|n| n.name.clone())
+                        .collect();
+                    targets.extend(layer_nodes);
+                }
+                if targets.is_empty() {
+                    // Check for output nodes if no handler nodes found
+                    for layer in layers {
+                        let output_nodes: Vec<String> = self
+                            .nodes
+                            .values()
+                            .filter(|n| n.layer == *layer && self.ends_pipeline(n))
+                            .map(|n| n.name.clone())
+                            .collect();
+                        targets.extend(output_nodes);
+                    }
+                }
+                Ok(targets)
+            }
+            Some(OutputTarget::Nodes(nodes)) => Ok(nodes.clone()),
+            None => Err(ProcessorError::ApiError(
+                "Node has no output targets".to_string(),
+            )),
+        }
+    }
+
+    /// Whether reaching this node ends the pipeline: an output node, or a
+    /// node whose plugin adapter acts as one
+    fn ends_pipeline(&self, node: &RuntimeNode) -> bool {
+        node.is_output()
+            || self
+                .plugins
+                .get(&node.name)
+                .is_some_and(|plugin| plugin.is_output())
+    }
+
+    /// What a plugin adapter gets to see when it runs for a node
+    fn adapter_context<'a>(
+        &'a self,
+        node_name: &str,
+        request: &'a PipelineRequest,
+    ) -> Result<AdapterContext<'a>, ProcessorError> {
+        let node = self
+            .arch_nodes
+            .get(node_name)
+            .ok_or_else(|| ProcessorError::HandlerNotFound(node_name.to_string()))?;
+        Ok(AdapterContext { node, request })
+    }
+
+    /// Get next targets filtered by condition evaluation
+    fn get_next_targets_filtered(
+        &self,
+        node: &RuntimeNode,
+        request: &PipelineRequest,
+    ) -> Result<Vec<String>, ProcessorError> {
+        let all_targets = self.get_next_targets(node)?;
+
+        // Filter targets by their conditions
+        let filtered: Vec<String> = all_targets
+            .into_iter()
+            .filter(|target_name| {
+                if let Some(target_node) = self.nodes.get(target_name) {
+                    // If node has a condition, evaluate it; otherwise pass
+                    target_node
+                        .condition
+                        .as_ref()
+                        .map(|c| evaluate_condition(c, request.get_variables()))
+                        .unwrap_or(true)
+                } else {
+                    true // Node not found, let later code handle error
+                }
+            })
+            .collect();
+
+        // If all targets were filtered out by conditions, return all targets
+        // This prevents getting stuck - conditions act as preferences, not blockers
+        if filtered.is_empty() {
+            self.get_next_targets(node)
+        } else {
+            Ok(filtered)
+        }
+    }
+
+    /// Drop targets whose circuit breaker is open
+    ///
+    /// If every target is open the list is returned unchanged, so the call
+    /// fails fast with a clear error instead of "no targets".
+    fn without_open_breakers(&self, targets: Vec<String>) -> Vec<String> {
+        let available: Vec<String> = targets
+            .iter()
+            .filter(|t| !self.breakers.get(*t).is_some_and(|b| b.is_open()))
+            .cloned()
+            .collect();
+
+        if available.is_empty() {
+            targets
+        } else {
+            available
+        }
+    }
+
+    /// Deadline of a hop: the request's or the node's `timeout-ms`, whichever is first
+    fn hop_deadline(
+        &self,
+        node_name: &str,
+        request: &PipelineRequest,
+    ) -> Result<Option<Instant>, ProcessorError> {
+        let now = Instant::now();
+        let timeout = self
+            .arch_nodes
+            .get(node_name)
+            .and_then(|n| n.timeout_ms)
+            .map(|ms| now + Duration::from_millis(ms));
+        let deadline = [request.deadline, timeout].into_iter().flatten().min();
+        if deadline.is_some_and(|d| d <= now) {
+            return Err(ProcessorError::DeadlineExceeded(node_name.to_string()));
+        }
+        Ok(deadline)
+    }
+
+    /// Run a call to a node's model, guarded by its circuit breaker and
+    /// waiting for a slot under the model's concurrency limit
+    async fn guarded<T>(
+        &self,
+        node_name: &str,
+        deadline: Option<Instant>,
+        call: impl Future<Output = Result<T, ClientError>>,
+    ) -> Result<T, ProcessorError> {
+        let model = self
+            .arch_nodes
+            .get(node_name)
+            .and_then(|n| n.model.as_deref());
+        self.guarded_as(node_name, model, deadline, call).await
+    }
+
+    /// Like [`guarded`](Self::guarded), but holding a permit of `model`'s
+    /// concurrency limit instead of the node's own model
+    async fn guarded_as<T>(
+        &self,
+        node_name: &str,
+        model: Option<&str>,
+        deadline: Option<Instant>,
+        call: impl Future<Output = Result<T, ClientError>>,
+    ) -> Result<T, ProcessorError> {
+        let breaker = self.breakers.get(node_name);
+        let probe = match breaker.map(|b| (b, b.admit())) {
+            Some((_, Admission::Rejected)) => {
+                return Err(ProcessorError::CircuitOpen(node_name.to_string()));
+            }
+            Some((breaker, Admission::Probe)) => ProbeGuard(Some(breaker)),
+            _ => ProbeGuard(None),
+        };
+
+        let limit = model.and_then(|m| self.limits.get(m));
+        let _permit = match limit {
+            Some(limit) => Some(limit.acquire().await),
+            None => None,
+        };
+
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), call)
+                .await
+                .unwrap_or_else(|_| Err(ClientError::Http("timed out".to_string()))),
+            None => call.await,
+        };
+        // Recording the outcome settles the probe
+        probe.settle();
+        match result {
+            Ok(response) => {
+                if let Some(breaker) = breaker {
+                    breaker.record_success();
+                }
+                Ok(response)
+            }
+            Err(e) => {
+                if let Some(breaker) = breaker {
+                    breaker.record_failure();
+                }
+                // The client's own timeout may have gone off first
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    return Err(ProcessorError::DeadlineExceeded(node_name.to_string()));
+                }
+                if let ClientError::Api {
+                    status: 429,
+                    message,
+                } = e
+                {
+                    return Err(ProcessorError::RateLimited(node_name.to_string(), message));
+                }
+                Err(ProcessorError::ApiError(e.to_string()))
+            }
+        }
+    }
+
+    /// Route to select one target from multiple options
+    async fn route_to_target(
+        &self,
+        router_name: &str,
+        content: &str,
+        targets: &[String],
+        deadline: Option<Instant>,
+    ) -> Result<String, ProcessorError> {
+        let cache = self.routing_caches.get(router_name);
+        if let Some(target) = cache.and_then(|c| c.get(content, targets)) {
+            return Ok(target);
+        }
+
+        let router_node = self
+            .nodes
+            .get(router_name)
+            .ok_or_else(|| ProcessorError::HandlerNotFound(router_name.to_string()))?;
+
+        let metadata: Vec<NodeMetadata> = targets
+            .iter()
+            .filter_map(|name| self.nodes.get(name))
+            .map(NodeMetadata::from)
+            .collect();
+
+        if metadata.is_empty() {
+            return Err(ProcessorError::ApiError(
+                "No valid targets for routing".to_string(),
+            ));
+        }
+
+        if let Some(function) = self.routing_functions.get(router_name) {
+            match self
+                .call_routing_function(router_name, function, content, &metadata, deadline)
+                .await
+            {
+                Ok(target) => {
+                    if let Some(cache) = cache {
+                        cache.insert(content, targets, target.clone());
+                    }
+                    return Ok(target);
+                }
+                Err(e) if self.clients.contains_key(router_name) => warn!(
+                    "Routing function of '{}' failed, asking its model: {}",
+                    router_name, e
+                ),
+                Err(e) => {
+                    return Err(ProcessorError::RoutingFunctionFailed(
+                        router_name.to_string(),
+                        e,
+                    ))
+                }
+            }
+        }
+
+        // A target similar enough to the prompt saves asking the router model
+        if let Some(router) = self.embedding_routers.get(router_name) {
+            let routed = router.route(content, &metadata);
+            let routed = match deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline.into(), routed)
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(RouterError::Client(ClientError::Http(
+                            "timed out".to_string(),
+                        )))
+                    }),
+                None => routed.await,
+            };
+            match routed {
+                Ok(Some(target)) => {
+                    if let Some(cache) = cache {
+                        cache.insert(content, targets, target.clone());
+                    }
+                    return Ok(target);
+                }
+                Ok(None) => debug!(
+                    "Router '{}' found no similar target, asking its model",
+                    router_name
+                ),
+                Err(e) => warn!(
+                    "Router '{}' couldn't route by embedding, asking its model: {}",
+                    router_name, e
+                ),
+            }
+        }
+
+        let (router_client, _lease) = self.client_for(router_name)?;
+
+        // With a policy the router model only says which targets are
+        // capable; the policy picks among them
+        let policy = self
+            .arch_nodes
+            .get(router_name)
+            .and_then(|n| n.routing_policy);
+        let routing_prompt = match policy {
+            Some(_) => build_ranking_prompt(content, &metadata),
+            None => build_routing_prompt(content, &metadata),
+        };
+        let model = router_node
+            .model_override()
+            .unwrap_or_else(|| self.router_model_name.clone());
+
+        let request = ClientRequest {
+            model,
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: routing_prompt,
+                ..Default::default()
+            }],
+            max_tokens: Some(100),
+            temperature: Some(0.1),
+            timeout: remaining(deadline),
+            ..Default::default()
+        };
+
+        let response = match self.over_budget(router_name)? {
+            Some((model, fallback)) => {
+                let request = ClientRequest {
+                    model: fallback.model().to_string(),
+                    ..request
+                };
+                let response = self
+                    .guarded_as(
+                        router_name,
+                        Some(model),
+                        deadline,
+                        fallback.chat_completion(&request),
+                    )
+                    .await?;
+                self.charge_budget(model, response.usage.as_ref());
+                response
+            }
+            None => {
+                let response = self
+                    .guarded(
+                        router_name,
+                        deadline,
+                        router_client.chat_completion(&request),
+                    )
+                    .await?;
+                self.charge_node_budget(router_name, response.usage.as_ref());
+                response
+            }
+        };
+
+        let output = response
+            .choices
+            .first()
+            .map(|c| c.message.content.clone())
+            .unwrap_or_default();
+
+        let target = match policy {
+            None => extract_node_selection(&output, &metadata)
+                .map_err(|e| ProcessorError::ApiError(e.to_string()))?,
+            Some(policy) => {
+                let ranking = extract_node_ranking(&output, &metadata)
+                    .map_err(|e| ProcessorError::ApiError(e.to_string()))?;
+                choose_by_policy(policy, &ranking, &metadata).ok_or_else(|| {
+                    ProcessorError::ApiError(format!("No target ranked: '{}'", output))
+                })?
+            }
+        };
+        if let Some(cache) = cache {
+            cache.insert(content, targets, target.clone());
+        }
+        Ok(target)
+    }
+
+    /// Ask a router's routing function which target to take
+    async fn call_routing_function(
+        &self,
+        router_name: &str,
+        function: &FunctionType,
+        content: &str,
+        metadata: &[NodeMetadata],
+        deadline: Option<Instant>,
+    ) -> Result<String, String> {
+        let vars = routing_function_variables(content, router_name, metadata);
+        let call = self.function_executor.execute(function, &vars);
+        let result = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), call)
+                .await
+                .map_err(|_| "timed out".to_string())?,
+            None => call.await,
+        }
+        .map_err(|e| e.to_string())?;
+        if !result.success {
+            return Err(result.error.unwrap_or_default());
+        }
+        extract_function_selection(result.output.as_ref(), metadata).map_err(|e| e.to_string())
+    }
+
+    /// Call a node's LLM with content
+    async fn call_node_llm(
+        &self,
+        node_name: &str,
+        content: &str,
+        deadline: Option<Instant>,
+    ) -> Result<(String, Option<Usage>), ProcessorError> {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }];
+        let (reply, usage) = self.chat(node_name, messages, &[], None, deadline).await?;
+        Ok((reply.content, usage))
+    }
+
+    /// Send a conversation to a node's LLM, returning the assistant's reply
+    async fn chat(
+        &self,
+        node_name: &str,
+        messages: Vec<Message>,
+        tools: &[Tool],
+        tool_choice: Option<&ToolChoice>,
+        deadline: Option<Instant>,
+    ) -> Result<(Message, Option<Usage>), ProcessorError> {
+        let request = self.chat_request(node_name, messages, tools, tool_choice, deadline)?;
+        let (client, _lease) = self.client_for(node_name)?;
+
+        let response = match (self.over_budget(node_name)?, self.hedges.get(node_name)) {
+            (Some((model, fallback)), _) => {
+                let request = ClientRequest {
+                    model: fallback.model().to_string(),
+                    ..request
+                };
+                let response = self
+                    .guarded_as(
+                        node_name,
+                        Some(model),
+                        deadline,
+                        fallback.chat_completion(&request),
+                    )
+                    .await?;
+                self.charge_budget(model, response.usage.as_ref());
+                response
+            }
+            // Charges the models of both of its calls
+            (None, Some(hedge)) => {
+                self.hedged(node_name, hedge, &client, &request, deadline)
+                    .await?
+            }
+            (None, None) => {
+                let response = self
+                    .guarded(node_name, deadline, client.chat_completion(&request))
+                    .await?;
+                self.charge_node_budget(node_name, response.usage.as_ref());
+                response
+            }
+        };
+
+        let reply = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message)
+            .unwrap_or_else(|| Message {
+                role: "assistant".to_string(),
+                content: "No response generated".to_string(),
+                ..Default::default()
+            });
+
+        Ok((reply, response.usage))
+    }
+
+    /// A node's model and its budget, if the model has one
+    fn budget_for(&self, node_name: &str) -> Option<(&str, &Budget)> {
+        let model = self.arch_nodes.get(node_name)?.model.as_deref()?;
+        Some((model, self.budgets.get(model)?))
+    }
+
+    /// The fallback model, and its client, a node's call goes to once the
+    /// namespace spent its model's budget, or `None` while there's budget
+    /// left
+    ///
+    /// The call is rejected when the fallback model's own budget is spent
+    /// too.
+    fn over_budget(&self, node_name: &str) -> Result<Option<(&str, &ModelClient)>, ProcessorError> {
+        let Some((model, budget)) = self.budget_for(node_name) else {
+            return Ok(None);
+        };
+        if !self.is_spent(model) {
+            return Ok(None);
+        }
+        match (budget.fallback_model(), budget.fallback()) {
+            (Some(fallback_model), Some(fallback)) if !self.is_spent(fallback_model) => {
+                debug!(
+                    "Budget of {} spent, sending {} to {}",
+                    model, node_name, fallback_model
+                );
+                Ok(Some((fallback_model, fallback)))
+            }
+            _ => Err(ProcessorError::BudgetExhausted(
+                node_name.to_string(),
+                model.to_string(),
+            )),
+        }
+    }
+
+    /// Whether the namespace spent its budget for `model` this month
+    fn is_spent(&self, model: &str) -> bool {
+        self.budget_ledger
+            .is_exhausted(&self.namespace, model, chrono::Utc::now())
+    }
+
+    /// Count an answer of a node's model against the model's budget
+    fn charge_node_budget(&self, node_name: &str, usage: Option<&Usage>) {
+        if let Some((model, _)) = self.budget_for(node_name) {
+            self.charge_budget(model, usage);
+        }
+    }
+
+    /// Count an answer against the budget of `model`, the model that gave
+    /// it, if it has one
+    fn charge_budget(&self, model: &str, usage: Option<&Usage>) {
+        if let (Some(budget), Some(usage)) = (self.budgets.get(model), usage) {
+            self.budget_ledger.charge(
+                &self.namespace,
+                model,
+                budget.price(usage),
+                chrono::Utc::now(),
+            );
+        }
+    }
+
+    /// Count a call to `model` that was dropped before it answered against
+    /// the model's budget, at the tokens of its prompt: the provider may
+    /// bill for them all the same
+    fn charge_dropped(&self, model: &str, request: &ClientRequest) {
+        if let Some(budget) = self.budgets.get(model) {
+            let tokens: usize = request
+                .messages
+                .iter()
+                .map(|m| estimate_tokens(&m.content))
+                .sum();
+            self.budget_ledger.charge(
+                &self.namespace,
+                model,
+                budget.price_tokens(tokens as u64),
+                chrono::Utc::now(),
+            );
+        }
+    }
+
+    /// Call a node's model, sending the request to its fallback model too
+    /// once the call is slower than usual and the retry budget allows
+    ///
+    /// The first answer wins; if one of the calls fails, the other one's
+    /// answer is waited for. Each answer is charged to the budget of the
+    /// model that gave it, and a call dropped for losing the race to that
+    /// of its model at its prompt's tokens. A fallback model whose budget
+    /// is spent isn't hedged with.
+    async fn hedged(
+        &self,
+        node_name: &str,
+        hedge: &Hedge,
+        client: &ModelClient,
+        request: &ClientRequest,
+        deadline: Option<Instant>,
+    ) -> Result<ChatCompletionResponse, ProcessorError> {
+        self.retry_budget.deposit();
+        let started = Instant::now();
+        let model = self
+            .arch_nodes
+            .get(node_name)
+            .and_then(|n| n.model.as_deref());
+        let primary = async {
+            let result = self
+                .guarded(node_name, deadline, client.chat_completion(request))
+                .await;
+            if let Ok(response) = &result {
+                hedge.record(started.elapsed());
+                if let Some(model) = model {
+                    self.charge_budget(model, response.usage.as_ref());
+                }
+            }
+            result
+        };
+        tokio::pin!(primary);
+
+        let delay = hedge.delay();
+        if let Ok(result) = tokio::time::timeout(delay, &mut primary).await {
+            return result;
+        }
+        if self.is_spent(hedge.fallback()) {
+            debug!(
+                "Budget of {} spent, not hedging {}",
+                hedge.fallback(),
+                node_name
+            );
+            return primary.await;
+        }
+        if !self.retry_budget.try_withdraw() {
+            debug!("Retry budget spent, not hedging {}", node_name);
+            return primary.await;
+        }
+
+        debug!(
+            "Hedging {} with {} after {}ms",
+            node_name,
+            hedge.client().model(),
+            delay.as_millis()
+        );
+        let fallback_request = ClientRequest {
+            model: hedge.client().model().to_string(),
+            timeout: remaining(deadline),
+            ..request.clone()
+        };
+        let fallback = async {
+            let result = self
+                .guarded_as(
+                    node_name,
+                    Some(hedge.fallback()),
+                    deadline,
+                    hedge.client().chat_completion(&fallback_request),
+                )
+                .await;
+            if let Ok(response) = &result {
+                self.charge_budget(hedge.fallback(), response.usage.as_ref());
+            }
+            result
+        };
+        tokio::pin!(fallback);
+
+        tokio::select! {
+            result = &mut primary => match result {
+                Ok(response) => {
+                    self.charge_dropped(hedge.fallback(), &fallback_request);
+                    Ok(response)
+                }
+                Err(e) => fallback.await.map_err(|_| e),
+            },
+            result = &mut fallback => match result {
+                Ok(response) => {
+                    if let Some(model) = model {
+                        self.charge_dropped(model, request);
+                    }
+                    Ok(response)
+                }
+                Err(_) => primary.await,
+            },
+        }
+    }
+
+    /// The request a conversation is sent to a node's LLM with
+    fn chat_request(
+        &self,
+        node_name: &str,
+        messages: Vec<Message>,
+        tools: &[Tool],
+        tool_choice: Option<&ToolChoice>,
+        deadline: Option<Instant>,
+    ) -> Result<ClientRequest, ProcessorError> {
+        let node = self
+            .nodes
+            .get(node_name)
+            .ok_or_else(|| ProcessorError::HandlerNotFound(node_name.to_string()))?;
+
+        let model = node
+            .model_override()
+            .unwrap_or_else(|| node_name.to_string());
+
+        Ok(ClientRequest {
+            model,
+            messages,
+            max_tokens: Some(1024),
+            temperature: Some(0.7),
+            tools: tools.to_vec(),
+            tool_choice: tool_choice.cloned(),
+            timeout: remaining(deadline),
+        })
+    }
+
+    /// Embed content with an embedding node, returning the vector as JSON
+    async fn embed_content(
+        &self,
+        node_name: &str,
+        content: &str,
+        deadline: Option<Instant>,
+    ) -> Result<String, ProcessorError> {
+        let input = EmbeddingInput::Single(content.to_string());
+        let response = self.embed_with(node_name, input, deadline).await?;
+
+        let vector = response
+            .data
+            .into_iter()
+            .next()
+            .map(|e| e.embedding)
+            .unwrap_or_default();
+
+        Ok(serde_json::to_string(&vector).unwrap_or_default())
+    }
+
+    /// Run a guard's checks, returning the outcome and the violation (if any)
+    ///
+    /// Rule checks run first; the moderation model is only consulted when
+    /// they pass.
+    async fn check_guard(
+        &self,
+        node_name: &str,
+        guard: &Guard,
+        content: &str,
+        deadline: Option<Instant>,
+    ) -> Result<(GuardOutcome, Option<String>), ProcessorError> {
+        if let Some(reason) = guard.violation(content) {
+            return Ok((guard.outcome(content, &reason, false), Some(reason)));
+        }
+
+        if guard.uses_moderation() {
+            let prompt = format!("{}{}", MODERATION_PROMPT, content);
+            let (reply, _) = self.call_node_llm(node_name, &prompt, deadline).await?;
+            if let Some(reason) = moderation_verdict(&reply) {
+                return Ok((guard.outcome(content, &reason, true), Some(reason)));
+            }
+        }
+
+        Ok((GuardOutcome::Pass, None))
+    }
+
+    /// Query a retriever node's vector store and inject the results
+    ///
+    /// The query vector comes from an upstream embedding node when one ran,
+    /// otherwise the retriever embeds the content with its own model.
+    async fn retrieve(
+        &self,
+        node_name: &str,
+        request: &mut PipelineRequest,
+        content: &str,
+        deadline: Option<Instant>,
+    ) -> Result<String, ProcessorError> {
+        let failed = |e: String| ProcessorError::RetrievalFailed(node_name.to_string(), e);
+
+        let store = self
+            .stores
+            .get(node_name)
+            .ok_or_else(|| failed("no vector store configured".to_string()))?;
+        let config = self
+            .arch_nodes
+            .get(node_name)
+            .and_then(|n| n.retriever.as_ref())
+            .ok_or_else(|| failed("no retriever configuration".to_string()))?;
+
+        let upstream = request
+            .get_variable(vars::EMBEDDING)
+            .and_then(|v| serde_json::from_str::<Vec<f32>>(v).ok());
+        let vector = match upstream {
+            Some(vector) => vector,
+            None => self
+                .embed_with(
+                    node_name,
+                    EmbeddingInput::Single(content.to_string()),
+                    deadline,
+                )
+                .await?
+                .data
+                .into_iter()
+                .next()
+                .map(|e| e.embedding)
+                .ok_or_else(|| failed("embedding model returned no vector".to_string()))?,
+        };
+
+        let documents = store
+            .search(&vector, config.top_k)
+            .await
+            .map_err(|e| failed(e.to_string()))?;
+
+        debug!(
+            "Retriever '{}' injected {} documents",
+            node_name,
+            documents.len()
+        );
+        request.record_documents(documents.iter().map(|d| d.id.clone()).collect());
+
+        Ok(inject_documents(
+            content,
+            &documents,
+            config.template.as_deref(),
+        ))
+    }
+
+    /// Pick the client for a node, on the least busy or next runner replica
+    /// when its model has several. The lease must be held for the call.
+    fn client_for(
+        &self,
+        node_name: &str,
+    ) -> Result<(Cow<'_, ModelClient>, Option<RunnerLease>), ProcessorError> {
+        let client = self
+            .clients
+            .get(node_name)
+            .ok_or_else(|| ProcessorError::HandlerNoModel(node_name.to_string()))?;
+
+        match self.pools.get(node_name).and_then(|p| p.acquire()) {
+            Some(lease) => {
+                let client = client.with_base_url(runner_base_url(lease.endpoint()));
+                Ok((Cow::Owned(client), Some(lease)))
+            }
+            None => Ok((Cow::Borrowed(client), None)),
+        }
+    }
+
+    /// Send an embeddings request to a node's model
+    async fn embed_with(
+        &self,
+        node_name: &str,
+        input: EmbeddingInput,
+        deadline: Option<Instant>,
+    ) -> Result<EmbeddingResponse, ProcessorError> {
+        let (client, _lease) = self.client_for(node_name)?;
+
+        let model = self
+            .nodes
+            .get(node_name)
+            .and_then(|n| n.model_override())
+            .unwrap_or_else(|| client.model().to_string());
+
+        let request = EmbeddingRequest { model, input };
+        self.guarded(node_name, deadline, client.embeddings(&request))
+            .await
+    }
+
+    /// Serve an embeddings request with one of the composition's embedding nodes
+    ///
+    /// The requested model must name either an embedding node or the model
+    /// it uses.
+    pub async fn embed(
+        &self,
+        request: EmbeddingRequest,
+    ) -> Result<EmbeddingResponse, ProcessorError> {
+        if !self.nodes.values().any(|n| n.is_embedding()) {
+            return Err(ProcessorError::NoEmbeddingNode);
+        }
+        let node_name = self
+            .embedding_node_for(&request.model)
+            .ok_or_else(|| ProcessorError::ModelNotFound(request.model.clone()))?;
+
+        self.embed_with(&node_name, request.input, None).await
+    }
+
+    /// Pick the embedding node that should serve a requested model
+    fn embedding_node_for(&self, model: &str) -> Option<String> {
+        let mut candidates: Vec<&RuntimeNode> =
+            self.nodes.values().filter(|n| n.is_embedding()).collect();
+        candidates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let uses_model = |n: &RuntimeNode| {
+            n.name == model
+                || self
+                    .arch_nodes
+                    .get(&n.name)
+                    .and_then(|a| a.model.as_deref())
+                    == Some(model)
+        };
+
+        candidates
+            .into_iter()
+            .find(|n| uses_model(n))
+            .map(|n| n.name.clone())
+    }
+
+    /// Transcribe an audio upload with one of the composition's audio input
+    /// nodes, by name or else the first
+    ///
+    /// The node's `language` option applies when the upload names none.
+    pub async fn transcribe(
+        &self,
+        node: Option<&str>,
+        mut request: TranscriptionRequest,
+    ) -> Result<String, ProcessorError> {
+        let node = match node {
+            Some(name) => self
+                .nodes
+                .get(name)
+                .filter(|n| n.is_audio_input())
+                .ok_or_else(|| ProcessorError::HandlerNotFound(name.to_string()))?,
+            None => self
+                .nodes
+                .values()
+                .filter(|n| n.is_audio_input())
+                .min_by(|a, b| a.name.cmp(&b.name))
+                .ok_or(ProcessorError::NoAudioInputNode)?,
+        };
+        let (client, _lease) = self.client_for(&node.name)?;
+
+        // faster-whisper loads the model it's asked for, so a whisper
+        // runner's source is the name to ask for
+        let config = node.model_config.as_ref().map(|m| m.to_config());
+        request.model = node
+            .model_override()
+            .or_else(|| {
+                config
+                    .filter(|c| c.runner == RunnerType::Whisper)
+                    .and_then(|c| c.source)
+            })
+            .unwrap_or_else(|| client.model().to_string());
+        if request.language.is_none() {
+            request.language = node
+                .extra_options
+                .get("language")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+        }
+
+        let response = self
+            .guarded(&node.name, None, client.transcribe(&request))
+            .await?;
+        debug!(
+            "Audio input '{}' transcribed {} bytes",
+            node.name,
+            request.audio.len()
+        );
+        Ok(response.text.trim().to_string())
+    }
+
+    /// Get number of nodes
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+/// Server root of a runner endpoint, which may end in "/v1"
+/// The client for a model, if it can be reached yet
+///
+/// Gemini models are called by name (a node's `model_override`, else the
+/// model's `source`, else its key) at their endpoint, the public API by
+/// default. Everything else speaks the OpenAI API at its endpoint.
+fn build_client(
+    config: &ModelConfig,
+    model_name: String,
+) -> Result<Option<ModelClient>, serde_json::Error> {
+    if config.is_gemini() {
+        let endpoint = config.endpoint.as_deref().unwrap_or(GEMINI_API_URL);
+        let client = GeminiClient::new(endpoint.to_string(), config.api_key.clone(), model_name)
+            .with_safety_settings(config.safety_settings()?);
+        return Ok(Some(client.into()));
+    }
+
+    Ok(config.endpoint.as_ref().map(|endpoint| {
+        OpenAiClient::new(
+            runner_base_url(endpoint),
+            config.api_key.clone(),
+            model_name,
+        )
+        .into()
+    }))
+}
+
+fn runner_base_url(endpoint: &str) -> String {
+    endpoint
+        .trim_end_matches('/')
+        .trim_end_matches("/v1")
+        .to_string()
+}
+
+/// Milliseconds since `start`
+/// Time left before a deadline, for the client's request timeout
+fn remaining(deadline: Option<Instant>) -> Option<Duration> {
+    deadline.map(|d| d.saturating_duration_since(Instant::now()))
+}
+
+fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// Half-open probe claimed by a call, released if the call is dropped
+/// before its outcome is recorded, e.g. when a hedged call loses the race
+struct ProbeGuard<'a>(Option<&'a CircuitBreaker>);
+
+impl ProbeGuard<'_> {
+    fn settle(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(breaker) = self.0 {
+            breaker.release_probe();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::handoff::HANDOFF_DEPTH_HEADER;
+    use crate::test_util::{completion, completion_with_usage, fake_completions, serve};
+
+    #[test]
+    fn test_processor_creation() {
+        let json = r#"{
+            "models": {
+                "router-model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8080"
+                },
+                "handler-model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8081"
+                }
+            },
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "model": "router-model",
+                    "adapter": "openai-api",
+                    "output-to": [1]
+                },
+                {
+                    "name": "handler",
+                    "layer": 1,
+                    "model": "handler-model",
+                    "adapter": "openai-api",
+                    "use-case": "General handler",
+                    "output-to": ["output"]
+                },
+                {
+                    "name": "output",
+                    "adapter": "output"
+                }
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+
+        assert_eq!(processor.node_count(), 3);
+        assert_eq!(processor.router_node_name, "router");
+    }
+
+    #[test]
+    fn test_processor_no_router_model() {
+        // A composition with a router that has no model configured
+        let json = r#"{
+            "models": {},
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "adapter": "openai-api",
+                    "output-to": [1]
+                },
+                {
+                    "name": "handler",
+                    "layer": 1,
+                    "adapter": "openai-api",
+                    "output-to": ["output"]
+                },
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let result = PipelineProcessor::new(&comp);
+
+        // Should fail because router has no model configured
+        assert!(matches!(
+            result,
+            Err(ProcessorError::RouterModelNotConfigured)
+        ));
+    }
+
+    #[test]
+    fn test_processor_multi_layer_creation() {
+        // Test 1-2-1-1 topology: router -> 2 handlers -> 1 aggregator -> output
+        let json = r#"{
+            "models": {
+                "router-model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8080"
+                },
+                "handler-model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8081"
+                },
+                "aggregator-model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8082"
+                }
+            },
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "model": "router-model",
+                    "adapter": "openai-api",
+                    "output-to": [1]
+                },
+                {
+                    "name": "handler-a",
+                    "layer": 1,
+                    "model": "handler-model",
+                    "adapter": "openai-api",
+                    "use-case": "Handle type A requests",
+                    "output-to": [2]
+                },
+                {
+                    "name": "handler-b",
+                    "layer": 1,
+                    "model": "handler-model",
+                    "adapter": "openai-api",
+                    "use-case": "Handle type B requests",
+                    "output-to": [2]
+                },
+                {
+                    "name": "aggregator",
+                    "layer": 2,
+                    "model": "aggregator-model",
+                    "adapter": "openai-api",
+                    "use-case": "Aggregate and refine responses",
+                    "output-to": ["output"]
+                },
+                {
+                    "name": "output",
+                    "adapter": "output"
+                }
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+
+        assert_eq!(processor.node_count(), 5);
+        assert_eq!(processor.router_node_name, "router");
+    }
+
+    #[test]
+    fn test_get_next_targets_by_layer() {
+        let json = r#"{
+            "models": {
+                "model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8080"
+                }
+            },
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "output-to": [1]
+                },
+                {
+                    "name": "handler-a",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "A",
+                    "output-to": ["output"]
+                },
+                {
+                    "name": "handler-b",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "B",
+                    "output-to": ["output"]
+                },
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+        let router = processor.nodes.get("router").unwrap();
+
+        let targets = processor.get_next_targets(router).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert!(targets.contains(&"handler-a".to_string()));
+        assert!(targets.contains(&"handler-b".to_string()));
+    }
+
+    #[test]
+    fn test_get_next_targets_by_node_name() {
+        let json = r#"{
+            "models": {
+                "model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8080"
+                }
+            },
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "output-to": [1]
+                },
+                {
+                    "name": "handler",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "output-to": ["output"]
+                },
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+        let handler = processor.nodes.get("handler").unwrap();
+
+        let targets = processor.get_next_targets(handler).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0], "output");
+    }
+
+    #[test]
+    fn test_three_layer_pipeline_creation() {
+        // Test 1-2-2-1 topology: router -> 2 domain handlers -> 2 refiners -> output
+        let json = r#"{
+            "models": {
+                "model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8080"
+                }
+            },
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "output-to": [1]
+                },
+                {
+                    "name": "sales",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Sales queries",
+                    "output-to": [2]
+                },
+                {
+                    "name": "support",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Support queries",
+                    "output-to": [2]
+                },
+                {
+                    "name": "formal-refiner",
+                    "layer": 2,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Formal tone refinement",
+                    "output-to": ["output"]
+                },
+                {
+                    "name": "casual-refiner",
+                    "layer": 2,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Casual tone refinement",
+                    "output-to": ["output"]
                 },
                 {"name": "output", "adapter": "output"}
             ]
         }"#;
 
         let comp = Composition::from_str(json).unwrap();
-        let result = PipelineProcessor::new(&comp);
+        let processor = PipelineProcessor::new(&comp).unwrap();
 
-        // Should fail because router has no model configured
-        assert!(matches!(
-            result,
-            Err(ProcessorError::RouterModelNotConfigured)
-        ));
+        assert_eq!(processor.node_count(), 6);
+
+        // Check layer 1 has 2 nodes
+        let layer1_count = processor.nodes.values().filter(|n| n.layer == 1).count();
+        assert_eq!(layer1_count, 2);
+
+        // Check layer 2 has 2 nodes
+        let layer2_count = processor.nodes.values().filter(|n| n.layer == 2).count();
+        assert_eq!(layer2_count, 2);
     }
 
+    // ========================================================================
+    // Conditional Routing Tests
+    // ========================================================================
+
     #[test]
-    fn test_processor_multi_layer_creation() {
-        // Test 1-2-1-1 topology: router -> 2 handlers -> 1 aggregator -> output
+    fn test_condition_filters_targets_by_word_count() {
+        // Test that conditions on WORD_COUNT correctly filter targets
         let json = r#"{
             "models": {
-                "router-model": {
+                "model": {
                     "type": "external",
                     "interface": "openai-api",
                     "url": "http://localhost:8080"
+                }
+            },
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "output-to": [1]
                 },
-                "handler-model": {
+                {
+                    "name": "short-handler",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Handle short inputs",
+                    "if": "$WORD_COUNT < 10",
+                    "output-to": ["output"]
+                },
+                {
+                    "name": "long-handler",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Handle long inputs",
+                    "if": "$WORD_COUNT >= 10",
+                    "output-to": ["output"]
+                },
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+        let router = processor.nodes.get("router").unwrap();
+
+        // Test short input (5 words)
+        let short_request = PipelineRequest::new("Hello world this is short".to_string());
+        let short_targets = processor
+            .get_next_targets_filtered(router, &short_request)
+            .unwrap();
+        assert_eq!(short_targets.len(), 1);
+        assert_eq!(short_targets[0], "short-handler");
+
+        // Test long input (15 words)
+        let long_request = PipelineRequest::new(
+            "This is a much longer input that should be routed to the long handler node"
+                .to_string(),
+        );
+        let long_targets = processor
+            .get_next_targets_filtered(router, &long_request)
+            .unwrap();
+        assert_eq!(long_targets.len(), 1);
+        assert_eq!(long_targets[0], "long-handler");
+    }
+
+    #[test]
+    fn test_condition_filters_targets_by_hop_count() {
+        // Test that conditions on HOP_COUNT correctly filter targets
+        let json = r#"{
+            "models": {
+                "model": {
                     "type": "external",
                     "interface": "openai-api",
-                    "url": "http://localhost:8081"
+                    "url": "http://localhost:8080"
+                }
+            },
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "output-to": [1]
                 },
-                "aggregator-model": {
+                {
+                    "name": "first-pass",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "First processing pass",
+                    "if": "$HOP_COUNT == \"0\"",
+                    "output-to": [2]
+                },
+                {
+                    "name": "second-pass",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Second processing pass",
+                    "if": "$HOP_COUNT > 0",
+                    "output-to": ["output"]
+                },
+                {
+                    "name": "refiner",
+                    "layer": 2,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Refine output",
+                    "output-to": ["output"]
+                },
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+        let router = processor.nodes.get("router").unwrap();
+
+        // Fresh request (HOP_COUNT = 0)
+        let request = PipelineRequest::new("Hello".to_string());
+        let targets = processor
+            .get_next_targets_filtered(router, &request)
+            .unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0], "first-pass");
+
+        // Request with 1 hop
+        let mut request_with_hop = PipelineRequest::new("Hello".to_string());
+        request_with_hop.add_hop("router".to_string(), 0, Some("first-pass".to_string()));
+        let targets = processor
+            .get_next_targets_filtered(router, &request_with_hop)
+            .unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0], "second-pass");
+    }
+
+    #[test]
+    fn test_condition_filters_by_prev_node() {
+        // Test that conditions on PREV_NODE correctly filter targets
+        let json = r#"{
+            "models": {
+                "model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://localhost:8080"
+                }
+            },
+            "architecture": [
+                {
+                    "name": "router",
+                    "layer": 0,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "output-to": [1]
+                },
+                {
+                    "name": "handler-a",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Handler A",
+                    "output-to": [2]
+                },
+                {
+                    "name": "handler-b",
+                    "layer": 1,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Handler B",
+                    "output-to": [2]
+                },
+                {
+                    "name": "refiner-for-a",
+                    "layer": 2,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Refine A output",
+                    "if": "$PREV_NODE == \"handler-a\"",
+                    "output-to": ["output"]
+                },
+                {
+                    "name": "refiner-for-b",
+                    "layer": 2,
+                    "model": "model",
+                    "adapter": "openai-api",
+                    "use-case": "Refine B output",
+                    "if": "$PREV_NODE == \"handler-b\"",
+                    "output-to": ["output"]
+                },
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+        let handler_a = processor.nodes.get("handler-a").unwrap();
+
+        // Request that came from handler-a
+        let mut request_from_a = PipelineRequest::new("Hello".to_string());
+        request_from_a.add_hop("handler-a".to_string(), 1, None);
+        let targets = processor
+            .get_next_targets_filtered(handler_a, &request_from_a)
+            .unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0], "refiner-for-a");
+
+        // Request that came from handler-b
+        let handler_b = processor.nodes.get("handler-b").unwrap();
+        let mut request_from_b = PipelineRequest::new("Hello".to_string());
+        request_from_b.add_hop("handler-b".to_string(), 1, None);
+        let targets = processor
+            .get_next_targets_filtered(handler_b, &request_from_b)
+            .unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0], "refiner-for-b");
+    }
+
+    #[test]
+    fn test_no_matching_conditions_returns_all_targets() {
+        // Test that when no conditions match, all targets are returned
+        let json = r#"{
+            "models": {
+                "model": {
                     "type": "external",
                     "interface": "openai-api",
-                    "url": "http://localhost:8082"
+                    "url": "http://localhost:8080"
                 }
             },
             "architecture": [
                 {
                     "name": "router",
                     "layer": 0,
-                    "model": "router-model",
+                    "model": "model",
                     "adapter": "openai-api",
                     "output-to": [1]
                 },
                 {
-                    "name": "handler-a",
+                    "name": "handler-impossible",
                     "layer": 1,
-                    "model": "handler-model",
+                    "model": "model",
                     "adapter": "openai-api",
-                    "use-case": "Handle type A requests",
-                    "output-to": [2]
+                    "use-case": "Impossible condition",
+                    "if": "$WORD_COUNT > 9999",
+                    "output-to": ["output"]
                 },
                 {
-                    "name": "handler-b",
+                    "name": "handler-also-impossible",
                     "layer": 1,
-                    "model": "handler-model",
-                    "adapter": "openai-api",
-                    "use-case": "Handle type B requests",
-                    "output-to": [2]
-                },
-                {
-                    "name": "aggregator",
-                    "layer": 2,
-                    "model": "aggregator-model",
+                    "model": "model",
                     "adapter": "openai-api",
-                    "use-case": "Aggregate and refine responses",
+                    "use-case": "Also impossible",
+                    "if": "$HOP_COUNT > 100",
                     "output-to": ["output"]
                 },
-                {
-                    "name": "output",
-                    "adapter": "output"
-                }
+                {"name": "output", "adapter": "output"}
             ]
         }"#;
 
         let comp = Composition::from_str(json).unwrap();
         let processor = PipelineProcessor::new(&comp).unwrap();
+        let router = processor.nodes.get("router").unwrap();
 
-        assert_eq!(processor.node_count(), 5);
-        assert_eq!(processor.router_node_name, "router");
+        // Neither condition can be satisfied, so we get all targets back
+        let request = PipelineRequest::new("Hello world".to_string());
+        let targets = processor
+            .get_next_targets_filtered(router, &request)
+            .unwrap();
+        assert_eq!(targets.len(), 2);
     }
 
     #[test]
-    fn test_get_next_targets_by_layer() {
+    fn test_mixed_conditions_and_no_conditions() {
+        // Test mixing nodes with and without conditions
         let json = r#"{
             "models": {
                 "model": {
@@ -647,398 +3459,1451 @@ mod tests {
                     "output-to": [1]
                 },
                 {
-                    "name": "handler-a",
+                    "name": "fallback",
                     "layer": 1,
                     "model": "model",
                     "adapter": "openai-api",
-                    "use-case": "A",
+                    "use-case": "Default fallback (no condition)",
                     "output-to": ["output"]
                 },
                 {
-                    "name": "handler-b",
+                    "name": "long-only",
                     "layer": 1,
                     "model": "model",
                     "adapter": "openai-api",
-                    "use-case": "B",
+                    "use-case": "Only for long inputs",
+                    "if": "$WORD_COUNT >= 20",
                     "output-to": ["output"]
                 },
                 {"name": "output", "adapter": "output"}
             ]
         }"#;
 
-        let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
-        let router = processor.nodes.get("router").unwrap();
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+        let router = processor.nodes.get("router").unwrap();
+
+        // Short input - only fallback passes (long-only condition fails)
+        let short_request = PipelineRequest::new("Hello world".to_string());
+        let targets = processor
+            .get_next_targets_filtered(router, &short_request)
+            .unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0], "fallback");
+
+        // Long input - both pass (fallback has no condition, long-only condition passes)
+        let long_request = PipelineRequest::new(
+            "This is a very long input that has many many words to ensure it passes the twenty word threshold easily and then some more words"
+                .to_string()
+        );
+        let targets = processor
+            .get_next_targets_filtered(router, &long_request)
+            .unwrap();
+        assert_eq!(targets.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_fails_fast() {
+        // Nothing listens on port 1, so every call fails
+        let json = r#"{
+            "models": {
+                "model": {
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://127.0.0.1:1"
+                }
+            },
+            "architecture": [
+                {"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]},
+                {"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+
+        let comp = Composition::from_str(json).unwrap();
+        let processor = PipelineProcessor::new(&comp)
+            .unwrap()
+            .with_circuit_breaker_config(CircuitBreakerConfig {
+                failure_threshold: 1,
+                ..Default::default()
+            });
+
+        let first = processor.process("hello").await;
+        assert!(matches!(first, Err(ProcessorError::ApiError(_))));
+
+        let second = processor.process("hello").await;
+        assert!(matches!(second, Err(ProcessorError::CircuitOpen(ref n)) if n == "handler"));
+
+        let states = processor.breaker_states();
+        assert_eq!(states["handler"].state, crate::runtime::BreakerState::Open);
+        assert_eq!(states["router"].state, crate::runtime::BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_client_route_skips_open_breaker() {
+        // Every model that can be reached answers "good"; nothing listens on
+        // port 1, so calls to "bad" fail
+        let addr = fake_completions(|_| async { completion("good") }).await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "model": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "broken": {{"runner": "external", "endpoint": "http://127.0.0.1:1/v1"}}
+                }},
+                "route-overrides": ["bad"],
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                    {{"name": "good", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "bad", "layer": 1, "model": "broken", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap())
+            .unwrap()
+            .with_circuit_breaker_config(CircuitBreakerConfig {
+                failure_threshold: 1,
+                ..Default::default()
+            });
+        let routed = || PipelineRequest::new("hello".to_string()).with_route("bad");
+
+        // The client's route is taken, and trips its breaker
+        assert!(matches!(
+            processor.process_request(routed()).await,
+            Err(ProcessorError::ApiError(_))
+        ));
+
+        // While it is open the request is routed as usual
+        assert_eq!(processor.process_request(routed()).await.unwrap(), "good");
+    }
+
+    #[tokio::test]
+    async fn test_deadlines_abort_slow_hops() {
+        // The router answers at once; handlers take 300ms
+        let addr = fake_completions(|body: Value| async move {
+            if body["model"] != "fast" {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+            }
+            completion("ok")
+        })
+        .await;
+
+        let composition = |deadline: &str, timeout: &str| {
+            let json = format!(
+                r#"{{
+                    "models": {{
+                        "fast": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                        "slow": {{"runner": "external", "endpoint": "http://{addr}/v1"}}
+                    }},
+                    {deadline}
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "fast", "adapter": "openai-api", "output-to": ["chat"]}},
+                        {{"name": "chat", "layer": 1, "model": "slow", "adapter": "openai-api",
+                          {timeout} "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            );
+            PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap()
+        };
+        let run = |processor: PipelineProcessor, request: PipelineRequest| async move {
+            let started = Instant::now();
+            let result = processor.process_request(request).await;
+            (result, started.elapsed())
+        };
+        let request = || PipelineRequest::new("hi".to_string());
+
+        // The node's own timeout
+        let (result, took) = run(composition("", r#""timeout-ms": 50,"#), request()).await;
+        assert!(matches!(result, Err(ProcessorError::DeadlineExceeded(n)) if n == "chat"));
+        assert!(took < Duration::from_millis(250));
+
+        // The pipeline's deadline, and one the request brings
+        let (result, took) = run(composition(r#""deadline-ms": 100,"#, ""), request()).await;
+        assert!(matches!(result, Err(ProcessorError::DeadlineExceeded(n)) if n == "chat"));
+        assert!(took < Duration::from_millis(250));
+        let (result, _) = run(
+            composition("", ""),
+            request().with_deadline(Duration::from_millis(100)),
+        )
+        .await;
+        assert!(matches!(result, Err(ProcessorError::DeadlineExceeded(_))));
+
+        let (result, _) = run(composition(r#""deadline-ms": 2000,"#, ""), request()).await;
+        assert_eq!(result.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_hedged_calls_within_retry_budget() {
+        // Answers with the requested model; the "chat" node's own model
+        // takes 500ms
+        let addr = fake_completions(|body: Value| async move {
+            if body["model"] == "chat" {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            completion(body["model"].clone())
+        })
+        .await;
+
+        // Room for a single hedge
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "slow": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "backup": {{"runner": "external", "endpoint": "http://{addr}/v1"}}
+                }},
+                "retry-budget": {{"ratio": 0, "burst": 1}},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "slow", "adapter": "openai-api", "output-to": ["chat"]}},
+                    {{"name": "chat", "layer": 1, "model": "slow", "adapter": "openai-api",
+                      "hedge": {{"fallback": "backup", "min-delay-ms": 50}}, "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+
+        let started = Instant::now();
+        let (answer, _) = processor.call_node_llm("chat", "hi", None).await.unwrap();
+        assert_eq!(answer, "backup");
+        assert!(started.elapsed() < Duration::from_millis(400));
+
+        // With the budget spent, the slow answer is waited for
+        let (answer, _) = processor.call_node_llm("chat", "hi", None).await.unwrap();
+        assert_eq!(answer, "chat");
+    }
+
+    #[tokio::test]
+    async fn test_model_budgets() {
+        // Each model answers with its name, for 60 tokens
+        let addr = fake_completions(|body: Value| async move {
+            completion_with_usage(body["model"].clone(), 40, 20)
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "gpt": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                             "budget": {{"monthly-tokens": 100, "fallback": "local"}}}},
+                    "claude": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                                "budget": {{"monthly-tokens": 100}}}},
+                    "local": {{"runner": "external", "endpoint": "http://{addr}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "local", "adapter": "openai-api", "output-to": ["gpt", "claude"]}},
+                    {{"name": "gpt", "layer": 1, "model": "gpt", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "claude", "layer": 1, "model": "claude", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let composition = Composition::from_str(&json).unwrap();
+        let ledger = Arc::new(BudgetLedger::new());
+        let processor = PipelineProcessor::new(&composition)
+            .unwrap()
+            .with_budget_ledger(ledger.clone(), "team-a");
+        assert!(!processor.streams_answer("gpt"));
+
+        for _ in 0..2 {
+            let (answer, _) = processor.call_node_llm("gpt", "hi", None).await.unwrap();
+            assert_eq!(answer, "gpt");
+            processor.call_node_llm("claude", "hi", None).await.unwrap();
+        }
+
+        // Spent: "gpt" goes to its fallback, "claude" is rejected
+        let (answer, _) = processor.call_node_llm("gpt", "hi", None).await.unwrap();
+        assert_eq!(answer, "local");
+        assert!(matches!(
+            processor.call_node_llm("claude", "hi", None).await,
+            Err(ProcessorError::BudgetExhausted(node, model)) if node == "claude" && model == "claude"
+        ));
+
+        // Another namespace has its own budget
+        let other = PipelineProcessor::new(&composition)
+            .unwrap()
+            .with_budget_ledger(ledger.clone(), "team-b");
+        let (answer, _) = other.call_node_llm("gpt", "hi", None).await.unwrap();
+        assert_eq!(answer, "gpt");
+
+        let usage = ledger.usage(chrono::Utc::now());
+        let spent: Vec<_> = usage
+            .iter()
+            .map(|u| {
+                (
+                    u.namespace.as_str(),
+                    u.model.as_str(),
+                    u.tokens,
+                    u.exhausted,
+                )
+            })
+            .collect();
+        assert_eq!(
+            spent,
+            vec![
+                ("team-a", "claude", 120, true),
+                ("team-a", "gpt", 120, true),
+                ("team-b", "claude", 0, false),
+                ("team-b", "gpt", 60, false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_budgets_charge_the_model_that_answered() {
+        // Answers with the requested model, for 60 tokens; the "chat" node's
+        // own model takes 500ms
+        let addr = fake_completions(|body: Value| async move {
+            if body["model"] == "chat" {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            completion_with_usage(body["model"].clone(), 40, 20)
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "slow": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                              "budget": {{"monthly-tokens": 1000}}}},
+                    "backup": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                                "budget": {{"monthly-tokens": 1000}}}},
+                    "gpt": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                             "budget": {{"monthly-tokens": 10, "fallback": "backup"}}}}
+                }},
+                "retry-budget": {{"ratio": 0, "burst": 1}},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": ["chat"]}},
+                    {{"name": "chat", "layer": 1, "model": "slow", "adapter": "openai-api",
+                      "hedge": {{"fallback": "backup", "min-delay-ms": 50}}, "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let ledger = Arc::new(BudgetLedger::new());
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap())
+            .unwrap()
+            .with_budget_ledger(ledger.clone(), "default");
+        let spent = |model: &str| {
+            ledger
+                .usage(chrono::Utc::now())
+                .into_iter()
+                .find(|u| u.model == model)
+                .map(|u| u.tokens)
+                .unwrap()
+        };
+
+        // The hedge answers; the slow call it beat still costs its prompt
+        let (answer, _) = processor.call_node_llm("chat", "hi", None).await.unwrap();
+        assert_eq!(answer, "backup");
+        assert_eq!(spent("backup"), 60);
+        assert_eq!(spent("slow"), 1);
+
+        // Once "gpt" is spent, its fallback's answers are charged to it
+        processor.call_node_llm("router", "hi", None).await.unwrap();
+        let (answer, _) = processor.call_node_llm("router", "hi", None).await.unwrap();
+        assert_eq!(answer, "backup");
+        assert_eq!(spent("gpt"), 60);
+        assert_eq!(spent("backup"), 120);
+    }
+
+    #[tokio::test]
+    async fn test_spent_fallback_budgets() {
+        // Answers with the requested model, for 60 tokens; the "chat" node's
+        // own model takes 200ms
+        let addr = fake_completions(|body: Value| async move {
+            if body["model"] == "chat" {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            completion_with_usage(body["model"].clone(), 40, 20)
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "slow": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "backup": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                                "budget": {{"monthly-tokens": 10}}}},
+                    "gpt": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                             "budget": {{"monthly-tokens": 10, "fallback": "backup"}}}}
+                }},
+                "retry-budget": {{"ratio": 0, "burst": 10}},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": ["chat"]}},
+                    {{"name": "chat", "layer": 1, "model": "slow", "adapter": "openai-api",
+                      "hedge": {{"fallback": "backup", "min-delay-ms": 50}}, "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let ledger = Arc::new(BudgetLedger::new());
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap())
+            .unwrap()
+            .with_budget_ledger(ledger.clone(), "default");
+        let spent = |model: &str| {
+            ledger
+                .usage(chrono::Utc::now())
+                .into_iter()
+                .find(|u| u.model == model)
+                .map(|u| u.tokens)
+                .unwrap()
+        };
+
+        // Spends "gpt", then its fallback "backup"
+        processor.call_node_llm("router", "hi", None).await.unwrap();
+        let (answer, _) = processor.call_node_llm("router", "hi", None).await.unwrap();
+        assert_eq!(answer, "backup");
+
+        // With both spent, the call is rejected
+        assert!(matches!(
+            processor.call_node_llm("router", "hi", None).await,
+            Err(ProcessorError::BudgetExhausted(node, model)) if node == "router" && model == "gpt"
+        ));
+        assert_eq!(spent("gpt"), 60);
+        assert_eq!(spent("backup"), 60);
+
+        // Nor is the slow call hedged with the spent "backup"
+        let (answer, _) = processor.call_node_llm("chat", "hi", None).await.unwrap();
+        assert_eq!(answer, "chat");
+        assert_eq!(spent("backup"), 60);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_per_model() {
+        // A model that takes a while to answer
+        let addr = fake_completions(|_| async {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            completion("ok")
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "slow": {{"runner": "external", "endpoint": "http://{addr}/v1", "max-concurrent": 1}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "slow", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let comp = Composition::from_str(&json).unwrap();
+        let processor = Arc::new(PipelineProcessor::new(&comp).unwrap());
+
+        let calls: Vec<_> = (0..2)
+            .map(|_| {
+                let processor = Arc::clone(&processor);
+                tokio::spawn(async move { processor.call_node_llm("router", "hi", None).await })
+            })
+            .collect();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let status = &processor.concurrency_states()["slow"];
+        assert_eq!(status.in_flight, 1);
+        assert_eq!(status.waiting, 1);
+        assert_eq!(status.max_concurrent, Some(1));
+
+        for call in calls {
+            assert_eq!(call.await.unwrap().unwrap().0, "ok");
+        }
+        assert_eq!(processor.concurrency_states()["slow"].in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_runner_pool_spreads_calls_across_replicas() {
+        use crate::config::LoadBalancing;
+
+        // A replica that always answers with its own name
+        async fn replica(name: &'static str) -> String {
+            let addr = fake_completions(move |_| async move { completion(name) }).await;
+            format!("http://{}/v1", addr)
+        }
+
+        let first = replica("first").await;
+        let second = replica("second").await;
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "model": {{"type": "external", "interface": "openai-api", "url": "{}"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                    {{"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#,
+            first
+        );
+
+        let comp = Composition::from_str(&json).unwrap();
+        let mut processor = PipelineProcessor::new(&comp).unwrap();
+        processor.pools.insert(
+            "handler".to_string(),
+            Arc::new(RunnerPool::new(
+                vec![first, second],
+                LoadBalancing::RoundRobin,
+            )),
+        );
+
+        let mut answers = Vec::new();
+        for _ in 0..4 {
+            answers.push(processor.process("hello").await.unwrap());
+        }
+        assert_eq!(answers, ["first", "second", "first", "second"]);
+        assert_eq!(processor.pools["handler"].active_connections(), vec![0, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_handler_prompt_template_and_system_prompt() {
+        // Answers with the conversation it received, one "role: content" per line
+        let addr = fake_completions(|body: Value| async move {
+            let transcript: Vec<String> = body["messages"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|m| {
+                    format!(
+                        "{}: {}",
+                        m["role"].as_str().unwrap(),
+                        m["content"].as_str().unwrap()
+                    )
+                })
+                .collect();
+            completion(transcript.join("\n"))
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "model": {{"type": "external", "interface": "openai-api", "url": "http://{}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                    {{
+                        "name": "translator", "layer": 1, "model": "model", "adapter": "openai-api",
+                        "system-prompt": "You translate for $TENANT.",
+                        "prompt-template": "Translate to French: $INPUT",
+                        "output-to": ["output"]
+                    }},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#,
+            addr
+        );
+        let comp = Composition::from_str(&json).unwrap();
+        let processor = PipelineProcessor::new(&comp).unwrap();
+
+        let mut request = PipelineRequest::new("good morning".to_string());
+        request.set_variable("TENANT".to_string(), "acme".to_string());
+        let output = processor.process_request(request).await.unwrap();
+        assert_eq!(
+            output,
+            "system: You translate for acme.\nuser: Translate to French: good morning"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prompt_cache_markers() {
+        // Answers with the cache markers of the messages it received
+        let addr = fake_completions(|body: Value| async move {
+            let markers: Vec<String> = body["messages"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|m| match m["content"][0]["cache_control"]["ttl"].as_str() {
+                    Some(ttl) => format!("{}@{}", m["role"].as_str().unwrap(), ttl),
+                    None => "-".to_string(),
+                })
+                .collect();
+            completion(markers.join(","))
+        })
+        .await;
+
+        let composition = |caching: bool| {
+            let json = format!(
+                r#"{{
+                    "models": {{
+                        "model": {{"runner": "external", "endpoint": "http://{}/v1", "prompt-caching": {}}}
+                    }},
+                    "prompt-cache": {{"history": true, "ttl": "1h"}},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                        {{
+                            "name": "support", "layer": 1, "model": "model", "adapter": "openai-api",
+                            "system-prompt": "You answer support questions.",
+                            "output-to": ["output"]
+                        }},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#,
+                addr, caching
+            );
+            Composition::from_str(&json).unwrap()
+        };
+        let request = || {
+            let message = |role: &str, content: &str| Message {
+                role: role.to_string(),
+                content: content.to_string(),
+                ..Default::default()
+            };
+            PipelineRequest::new("And my invoice?".to_string())
+                .with_history(vec![message("user", "Hi"), message("assistant", "Hello")])
+        };
+
+        let processor = PipelineProcessor::new(&composition(true)).unwrap();
+        let output = processor.process_request(request()).await.unwrap();
+        assert_eq!(output, "system@1h,-,assistant@1h,-");
+
+        let processor = PipelineProcessor::new(&composition(false)).unwrap();
+        let output = processor.process_request(request()).await.unwrap();
+        assert_eq!(output, "-,-,-,-");
+    }
+
+    #[tokio::test]
+    async fn test_images_only_reach_vision_models() {
+        // Answers with the number of image parts it received
+        let addr = fake_completions(|body: Value| async move {
+            let images = body["messages"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|m| m["content"].as_array())
+                .flatten()
+                .filter(|part| part["type"] == "image_url")
+                .count();
+            completion(format!("{} images", images))
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "text": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "seer": {{"runner": "external", "endpoint": "http://{addr}/v1", "vision": true}}
+                }},
+                "route-overrides": ["chat", "look"],
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "text", "adapter": "openai-api", "output-to": [1]}},
+                    {{"name": "chat", "layer": 1, "model": "text", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "look", "layer": 1, "model": "seer", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+        let request = |route: &str| {
+            PipelineRequest::new("What is this?".to_string())
+                .with_images(vec![crate::client::ImageUrl {
+                    url: "data:image/png;base64,iVBORw0K".to_string(),
+                    detail: None,
+                }])
+                .with_route(route)
+        };
+
+        assert_eq!(
+            processor.process_request(request("look")).await.unwrap(),
+            "1 images"
+        );
+        assert_eq!(
+            processor.process_request(request("chat")).await.unwrap(),
+            "0 images"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_routing_policy_picks_among_capable_targets() {
+        // The router model finds "large" and "small" capable, best first;
+        // handlers answer with their model's name
+        let addr = fake_completions(|body: Value| async move {
+            let prompt = body["messages"][0]["content"].as_str().unwrap_or_default();
+            let content = if prompt.contains("best first") {
+                "large\nsmall".to_string()
+            } else {
+                format!("answered by {}", body["model"].as_str().unwrap())
+            };
+            completion(content)
+        })
+        .await;
+
+        let composition = |policy: &str| {
+            let json = format!(
+                r#"{{
+                    "models": {{
+                        "large": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                                   "cost": {{"per-1k-tokens": 3.0, "latency-ms": 900}}}},
+                        "small": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                                   "cost": {{"per-1k-tokens": 0.2, "latency-ms": 1200}}}},
+                        "tiny": {{"runner": "external", "endpoint": "http://{addr}/v1",
+                                  "cost": {{"per-1k-tokens": 0.01, "latency-ms": 100}}}}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "large", "adapter": "openai-api",
+                          "routing-policy": "{policy}", "output-to": [1]}},
+                        {{"name": "large", "layer": 1, "model": "large", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "small", "layer": 1, "model": "small", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "tiny", "layer": 1, "model": "tiny", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            );
+            Composition::from_str(&json).unwrap()
+        };
+
+        for (policy, answer) in [
+            ("quality-first", "answered by large"),
+            ("cheapest-capable", "answered by small"),
+            ("fastest", "answered by large"),
+        ] {
+            let processor = PipelineProcessor::new(&composition(policy)).unwrap();
+            let output = processor
+                .process_request(PipelineRequest::new("Prove it".to_string()))
+                .await
+                .unwrap();
+            assert_eq!(output, answer, "policy {}", policy);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routing_cache_and_examples() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // The router model picks "billing" when shown its example; handlers
+        // answer with their model's name
+        let routed = Arc::new(AtomicUsize::new(0));
+        let counter = routed.clone();
+        let addr = fake_completions(move |body: Value| {
+            let counter = counter.clone();
+            async move {
+                let prompt = body["messages"][0]["content"].as_str().unwrap_or_default();
+                let content = if prompt.contains("outputting ONLY") {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    if prompt.contains("Prompt: Refund my order\nModel: billing") {
+                        "billing".to_string()
+                    } else {
+                        "general".to_string()
+                    }
+                } else {
+                    format!("answered by {}", body["model"].as_str().unwrap())
+                };
+                completion(content)
+            }
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "router": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "billing": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "general": {{"runner": "external", "endpoint": "http://{addr}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "router", "adapter": "openai-api",
+                      "routing-cache": {{"ttl-secs": 60}}, "output-to": [1]}},
+                    {{"name": "billing", "layer": 1, "model": "billing", "adapter": "openai-api",
+                      "use-case": "Payments", "routing-examples": ["Refund my order"],
+                      "output-to": ["output"]}},
+                    {{"name": "general", "layer": 1, "model": "general", "adapter": "openai-api",
+                      "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+
+        for prompt in ["Where is my refund?", "where is  my REFUND?"] {
+            let output = processor
+                .process_request(PipelineRequest::new(prompt.to_string()))
+                .await
+                .unwrap();
+            assert_eq!(output, "answered by billing");
+        }
+        // The second prompt only differs in case and spacing
+        assert_eq!(routed.load(Ordering::SeqCst), 1);
+
+        processor
+            .process_request(PipelineRequest::new("Something else".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(routed.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_embedding_router_falls_back_to_model() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Text about refunds embeds as [1, 0], about weather as [0, 1],
+        // anything else halfway; the router model always says "general"
+        let embed = |text: &str| {
+            let text = text.to_lowercase();
+            if text.contains("refund") {
+                vec![1.0, 0.0]
+            } else if text.contains("weather") {
+                vec![0.0, 1.0]
+            } else {
+                vec![0.7, 0.7]
+            }
+        };
+        let routed = Arc::new(AtomicUsize::new(0));
+        let counter = routed.clone();
+        let app = axum::Router::new()
+            .route(
+                "/v1/embeddings",
+                axum::routing::post(move |axum::Json(body): axum::Json<Value>| async move {
+                    let data: Vec<Value> = body["input"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, text)| {
+                            serde_json::json!({
+                                "index": i,
+                                "embedding": embed(text.as_str().unwrap())
+                            })
+                        })
+                        .collect();
+                    axum::Json(serde_json::json!({"data": data}))
+                }),
+            )
+            .route(
+                "/v1/chat/completions",
+                axum::routing::post(move |axum::Json(body): axum::Json<Value>| {
+                    let counter = counter.clone();
+                    async move {
+                        let prompt = body["messages"][0]["content"].as_str().unwrap_or_default();
+                        let content = if prompt.contains("outputting ONLY") {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            "general".to_string()
+                        } else {
+                            format!("answered by {}", body["model"].as_str().unwrap())
+                        };
+                        completion(content)
+                    }
+                }),
+            );
+        let addr = serve(app).await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "router": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "embedder": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "billing": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "general": {{"runner": "external", "endpoint": "http://{addr}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "router", "adapter": "openai-api",
+                      "routing-mode": "embedding",
+                      "embedding-routing": {{"model": "embedder", "threshold": 0.9}},
+                      "output-to": [1]}},
+                    {{"name": "billing", "layer": 1, "model": "billing", "adapter": "openai-api",
+                      "use-case": "Refunds and payments", "output-to": ["output"]}},
+                    {{"name": "general", "layer": 1, "model": "general", "adapter": "openai-api",
+                      "use-case": "Weather and small talk", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+        let output = processor
+            .process_request(PipelineRequest::new("I'd like a refund".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(output, "answered by billing");
+        assert_eq!(routed.load(Ordering::SeqCst), 0);
+
+        // Not close enough to either use-case
+        let output = processor
+            .process_request(PipelineRequest::new("Tell me a joke".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(output, "answered by general");
+        assert_eq!(routed.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_routing_function_picks_target_without_router_model() {
+        // The policy service sends refunds to billing, weather to a node
+        // that doesn't exist and the rest to general
+        let app = axum::Router::new()
+            .route(
+                "/route",
+                axum::routing::post(|axum::Json(body): axum::Json<Value>| async move {
+                    let prompt = body["prompt"].as_str().unwrap_or_default();
+                    let targets = body["targets"].as_str().unwrap_or_default();
+                    let target = if !targets.contains("billing") {
+                        "missing-targets"
+                    } else if prompt.contains("refund") {
+                        "billing"
+                    } else if prompt.contains("weather") {
+                        "forecast"
+                    } else {
+                        "general"
+                    };
+                    axum::Json(serde_json::json!({"target": target}))
+                }),
+            )
+            .route(
+                "/v1/chat/completions",
+                axum::routing::post(|axum::Json(body): axum::Json<Value>| async move {
+                    completion(format!("answered by {}", body["model"].as_str().unwrap()))
+                }),
+            );
+        let addr = serve(app).await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "billing": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "general": {{"runner": "external", "endpoint": "http://{addr}/v1"}}
+                }},
+                "functions": {{
+                    "pick_target": {{
+                        "type": "rest", "method": "POST", "url": "http://{addr}/route",
+                        "body": {{"prompt": "$INPUT", "targets": "$TARGETS"}}
+                    }}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "adapter": "openai-api",
+                      "routing-mode": "function", "routing-function": "pick_target",
+                      "output-to": [1]}},
+                    {{"name": "billing", "layer": 1, "model": "billing", "adapter": "openai-api",
+                      "output-to": ["output"]}},
+                    {{"name": "general", "layer": 1, "model": "general", "adapter": "openai-api",
+                      "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+        let output = processor
+            .process_request(PipelineRequest::new("I'd like a refund".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(output, "answered by billing");
+        let output = processor
+            .process_request(PipelineRequest::new("Tell me a joke".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(output, "answered by general");
 
-        let targets = processor.get_next_targets(router).unwrap();
-        assert_eq!(targets.len(), 2);
-        assert!(targets.contains(&"handler-a".to_string()));
-        assert!(targets.contains(&"handler-b".to_string()));
+        // Without a router model there is nothing to fall back to
+        let result = processor
+            .process_request(PipelineRequest::new("How's the weather?".to_string()))
+            .await;
+        assert!(matches!(
+            result,
+            Err(ProcessorError::RoutingFunctionFailed(router, _)) if router == "router"
+        ));
     }
 
-    #[test]
-    fn test_get_next_targets_by_node_name() {
-        let json = r#"{
-            "models": {
-                "model": {
-                    "type": "external",
-                    "interface": "openai-api",
-                    "url": "http://localhost:8080"
-                }
-            },
-            "architecture": [
-                {
-                    "name": "router",
-                    "layer": 0,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "handler",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "output-to": ["output"]
+    #[tokio::test]
+    async fn test_handoff_forwards_to_another_pipeline() {
+        // Stands in for a control plane proxying to the billing pipeline
+        let app = axum::Router::new().route(
+            "/v1/namespaces/support/pipelines/billing-bot/chat/completions",
+            axum::routing::post(
+                |headers: axum::http::HeaderMap, axum::Json(body): axum::Json<Value>| async move {
+                    let depth = headers
+                        .get(HANDOFF_DEPTH_HEADER)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or("none")
+                        .to_string();
+                    completion(format!(
+                        "{} answered '{}' at depth {}",
+                        body["model"].as_str().unwrap(),
+                        body["messages"][0]["content"].as_str().unwrap(),
+                        depth
+                    ))
                 },
-                {"name": "output", "adapter": "output"}
-            ]
-        }"#;
+            ),
+        );
+        let addr = serve(app).await;
 
-        let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
-        let handler = processor.nodes.get("handler").unwrap();
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "router": {{"runner": "external", "endpoint": "http://{addr}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "router", "adapter": "openai-api",
+                      "output-to": ["billing"]}},
+                    {{"name": "billing", "layer": 1, "adapter": "handoff",
+                      "handoff": {{"url": "http://{addr}", "namespace": "support", "pipeline": "billing-bot"}},
+                      "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+        let output = processor
+            .process_request(PipelineRequest::new("Where is my refund?".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            "billing-bot answered 'Where is my refund?' at depth 1"
+        );
 
-        let targets = processor.get_next_targets(handler).unwrap();
-        assert_eq!(targets.len(), 1);
-        assert_eq!(targets[0], "output");
+        // Requests handed off too often aren't handed off again
+        let request = PipelineRequest::new("Where is my refund?".to_string())
+            .with_handoff_depth(MAX_HANDOFF_DEPTH);
+        let result = processor.process_request(request).await;
+        assert!(matches!(
+            result,
+            Err(ProcessorError::AdapterFailed(node, _)) if node == "billing"
+        ));
     }
 
-    #[test]
-    fn test_three_layer_pipeline_creation() {
-        // Test 1-2-2-1 topology: router -> 2 domain handlers -> 2 refiners -> output
-        let json = r#"{
-            "models": {
-                "model": {
-                    "type": "external",
-                    "interface": "openai-api",
-                    "url": "http://localhost:8080"
-                }
-            },
-            "architecture": [
-                {
-                    "name": "router",
-                    "layer": 0,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "sales",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Sales queries",
-                    "output-to": [2]
-                },
-                {
-                    "name": "support",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Support queries",
-                    "output-to": [2]
-                },
-                {
-                    "name": "formal-refiner",
-                    "layer": 2,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Formal tone refinement",
-                    "output-to": ["output"]
-                },
-                {
-                    "name": "casual-refiner",
-                    "layer": 2,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Casual tone refinement",
-                    "output-to": ["output"]
-                },
-                {"name": "output", "adapter": "output"}
-            ]
-        }"#;
+    #[tokio::test]
+    async fn test_mesh_hop_runs_on_peer() {
+        use crate::runtime::mesh::{MeshReply, MESH_TOKEN_HEADER};
+        use std::sync::atomic::{AtomicUsize, Ordering};
 
-        let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "coder": {{"runner": "external", "endpoint": "http://{addr}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "coder", "adapter": "openai-api",
+                      "output-to": ["coder"]}},
+                    {{"name": "coder", "layer": 1, "model": "coder", "adapter": "openai-api",
+                      "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let composition = Composition::from_str(&json).unwrap();
+        let secret = MeshKey::generate();
 
-        assert_eq!(processor.node_count(), 6);
+        // The peer hosting "coder" takes the hop and calls its model
+        let peer = Arc::new(
+            PipelineProcessor::new(&composition)
+                .unwrap()
+                .with_mesh(&Mesh {
+                    key: MeshKey::new(&secret),
+                    peers: BTreeMap::new(),
+                }),
+        );
+        let hops = Arc::new(AtomicUsize::new(0));
+        let take_hop = {
+            let (peer, hops) = (peer.clone(), hops.clone());
+            move |headers: axum::http::HeaderMap, body: axum::body::Bytes| async move {
+                let hop: MeshHop = serde_json::from_slice(&body).unwrap();
+                let token = headers[MESH_TOKEN_HEADER].to_str().unwrap();
+                let now = chrono::Utc::now().timestamp();
+                let key = peer.mesh_key().unwrap();
+                assert!(key.verify(token, &hop.node, hop.request_id, &body, now));
+                hops.fetch_add(1, Ordering::SeqCst);
+                let (message, usage) = peer.run_mesh_hop(&hop).await.unwrap();
+                axum::Json(MeshReply { message, usage })
+            }
+        };
+        let app = axum::Router::new()
+            .route(
+                "/v1/chat/completions",
+                axum::routing::post(|axum::Json(body): axum::Json<Value>| async move {
+                    let prompt = body["messages"][0]["content"].as_str().unwrap().to_string();
+                    completion(format!("coded '{}'", prompt))
+                }),
+            )
+            .route(
+                "/pipelines/code/v1/mesh/hops",
+                axum::routing::post(take_hop),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
 
-        // Check layer 1 has 2 nodes
-        let layer1_count = processor.nodes.values().filter(|n| n.layer == 1).count();
-        assert_eq!(layer1_count, 2);
+        let main = PipelineProcessor::new(&composition)
+            .unwrap()
+            .with_mesh(&Mesh {
+                key: MeshKey::new(&secret),
+                peers: BTreeMap::from([(
+                    "coder".to_string(),
+                    format!("http://{addr}/pipelines/code"),
+                )]),
+            });
+        let output = main
+            .process_request(PipelineRequest::new("a parser".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(output, "coded 'a parser'");
+        assert_eq!(hops.load(Ordering::SeqCst), 1);
 
-        // Check layer 2 has 2 nodes
-        let layer2_count = processor.nodes.values().filter(|n| n.layer == 2).count();
-        assert_eq!(layer2_count, 2);
+        // Only placed handlers take hops
+        let hop = MeshHop {
+            request_id: Uuid::new_v4(),
+            node: "router".to_string(),
+            input: "a parser".to_string(),
+            request: DeadLetterInput::default(),
+        };
+        assert!(matches!(
+            peer.run_mesh_hop(&hop).await,
+            Err(ProcessorError::HandlerNotFound(node)) if node == "router"
+        ));
     }
 
-    // ========================================================================
-    // Conditional Routing Tests
-    // ========================================================================
-
-    #[test]
-    fn test_condition_filters_targets_by_word_count() {
-        // Test that conditions on WORD_COUNT correctly filter targets
-        let json = r#"{
-            "models": {
-                "model": {
-                    "type": "external",
-                    "interface": "openai-api",
-                    "url": "http://localhost:8080"
-                }
-            },
-            "architecture": [
-                {
-                    "name": "router",
-                    "layer": 0,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "short-handler",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Handle short inputs",
-                    "if": "$WORD_COUNT < 10",
-                    "output-to": ["output"]
-                },
-                {
-                    "name": "long-handler",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Handle long inputs",
-                    "if": "$WORD_COUNT >= 10",
-                    "output-to": ["output"]
-                },
-                {"name": "output", "adapter": "output"}
-            ]
-        }"#;
+    #[tokio::test]
+    async fn test_gemini_handler_behind_openai_router() {
+        let app = axum::Router::new()
+            .route(
+                "/v1/chat/completions",
+                axum::routing::post(|| async {
+                    completion("gemini")
+                }),
+            )
+            .route(
+                "/v1beta/models/{call}",
+                axum::routing::post(
+                    |axum::extract::Path(call): axum::extract::Path<String>,
+                     headers: axum::http::HeaderMap,
+                     axum::Json(body): axum::Json<Value>| async move {
+                        assert_eq!(call, "gemini-2.0-flash:generateContent");
+                        assert_eq!(headers["x-goog-api-key"], "test-key");
+                        let prompt = body["contents"][0]["parts"][0]["text"].clone();
+                        axum::Json(serde_json::json!({
+                            "candidates": [{
+                                "content": {"role": "model", "parts": [{"text": format!("Gemini: {}", prompt.as_str().unwrap())}]},
+                                "finishReason": "STOP"
+                            }],
+                            "usageMetadata": {"promptTokenCount": 3, "candidatesTokenCount": 2, "totalTokenCount": 5}
+                        }))
+                    },
+                ),
+            );
+        let addr = serve(app).await;
 
-        let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
-        let router = processor.nodes.get("router").unwrap();
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "gpt": {{"runner": "external", "endpoint": "http://{addr}/v1"}},
+                    "gemini": {{
+                        "interface": "gemini",
+                        "endpoint": "http://{addr}/v1beta",
+                        "source": "gemini-2.0-flash",
+                        "api-key": "test-key"
+                    }}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": [1]}},
+                    {{"name": "gemini", "layer": 1, "model": "gemini", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
 
-        // Test short input (5 words)
-        let short_request = PipelineRequest::new("Hello world this is short".to_string());
-        let short_targets = processor
-            .get_next_targets_filtered(router, &short_request)
+        let answer = processor
+            .process_request(PipelineRequest::new("Hello".to_string()))
+            .await
             .unwrap();
-        assert_eq!(short_targets.len(), 1);
-        assert_eq!(short_targets[0], "short-handler");
+        assert_eq!(answer, "Gemini: Hello");
+    }
 
-        // Test long input (15 words)
-        let long_request = PipelineRequest::new(
-            "This is a much longer input that should be routed to the long handler node"
-                .to_string(),
+    #[tokio::test]
+    async fn test_fan_out_to_aggregator() {
+        // Each node's model answers with a fixed reply; "judge" picks answer 1
+        let addr = fake_completions(|body: Value| async move {
+            let reply = match body["model"].as_str().unwrap() {
+                "a" => "positive",
+                "b" => "Negative.",
+                "c" => "negative",
+                "combine" => "Answer 1",
+                other => panic!("unexpected call to '{}'", other),
+            };
+            completion(reply)
+        })
+        .await;
+
+        let processor = |strategy: &str| {
+            let json = format!(
+                r#"{{
+                    "models": {{
+                        "model": {{"type": "external", "interface": "openai-api", "url": "http://{addr}/v1"}}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "a", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["combine"]}},
+                        {{"name": "b", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["combine"]}},
+                        {{"name": "c", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["combine"]}},
+                        {{
+                            "name": "combine", "layer": 2, "model": "model", "adapter": "aggregator",
+                            "aggregate": {{"strategy": "{strategy}"}},
+                            "output-to": ["output"]
+                        }},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            );
+            PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap()
+        };
+
+        let vote = processor("vote");
+        let request = PipelineRequest::new("Classify: meh".to_string());
+        let request_id = request.request_id;
+        assert_eq!(vote.process_request(request).await.unwrap(), "Negative.");
+        let hops: Vec<String> = vote
+            .trace(&request_id)
+            .unwrap()
+            .hops
+            .into_iter()
+            .map(|h| h.node)
+            .collect();
+        assert_eq!(hops, vec!["a", "b", "c", "combine", "output"]);
+
+        let best_of = processor("best-of");
+        assert_eq!(best_of.process("Classify: meh").await.unwrap(), "positive");
+
+        let concat = processor("concat");
+        assert_eq!(
+            concat.process("Classify: meh").await.unwrap(),
+            "positive\n\nNegative.\n\nnegative"
         );
-        let long_targets = processor
-            .get_next_targets_filtered(router, &long_request)
-            .unwrap();
-        assert_eq!(long_targets.len(), 1);
-        assert_eq!(long_targets[0], "long-handler");
     }
 
-    #[test]
-    fn test_condition_filters_targets_by_hop_count() {
-        // Test that conditions on HOP_COUNT correctly filter targets
-        let json = r#"{
-            "models": {
-                "model": {
-                    "type": "external",
-                    "interface": "openai-api",
-                    "url": "http://localhost:8080"
-                }
-            },
-            "architecture": [
-                {
-                    "name": "router",
-                    "layer": 0,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "first-pass",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "First processing pass",
-                    "if": "$HOP_COUNT == \"0\"",
-                    "output-to": [2]
-                },
-                {
-                    "name": "second-pass",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Second processing pass",
-                    "if": "$HOP_COUNT > 0",
-                    "output-to": ["output"]
-                },
-                {
-                    "name": "refiner",
-                    "layer": 2,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Refine output",
-                    "output-to": ["output"]
-                },
-                {"name": "output", "adapter": "output"}
-            ]
-        }"#;
+    #[tokio::test]
+    async fn test_plugin_adapters() {
+        use crate::adapters::AdapterError;
+        use async_trait::async_trait;
+
+        struct Upper;
+
+        #[async_trait]
+        impl Adapter for Upper {
+            async fn handle_input(
+                &self,
+                ctx: &AdapterContext<'_>,
+                input: &str,
+            ) -> Result<String, AdapterError> {
+                let suffix = ctx.option("suffix").and_then(Value::as_str).unwrap_or("");
+                Ok(format!("{}{}", input.to_uppercase(), suffix))
+            }
+        }
 
-        let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
-        let router = processor.nodes.get("router").unwrap();
+        struct Sink(Arc<std::sync::Mutex<Vec<String>>>);
 
-        // Fresh request (HOP_COUNT = 0)
-        let request = PipelineRequest::new("Hello".to_string());
-        let targets = processor
-            .get_next_targets_filtered(router, &request)
-            .unwrap();
-        assert_eq!(targets.len(), 1);
-        assert_eq!(targets[0], "first-pass");
+        #[async_trait]
+        impl Adapter for Sink {
+            async fn handle_input(
+                &self,
+                _ctx: &AdapterContext<'_>,
+                input: &str,
+            ) -> Result<String, AdapterError> {
+                Ok(input.to_string())
+            }
 
-        // Request with 1 hop
-        let mut request_with_hop = PipelineRequest::new("Hello".to_string());
-        request_with_hop.add_hop("router".to_string(), 0, Some("first-pass".to_string()));
-        let targets = processor
-            .get_next_targets_filtered(router, &request_with_hop)
-            .unwrap();
-        assert_eq!(targets.len(), 1);
-        assert_eq!(targets[0], "second-pass");
-    }
+            async fn handle_output(
+                &self,
+                ctx: &AdapterContext<'_>,
+                output: &str,
+            ) -> Result<String, AdapterError> {
+                self.0.lock().unwrap().push(output.to_string());
+                Ok(format!("delivered to {}", ctx.node.name))
+            }
 
-    #[test]
-    fn test_condition_filters_by_prev_node() {
-        // Test that conditions on PREV_NODE correctly filter targets
+            fn is_output(&self) -> bool {
+                true
+            }
+        }
+
+        // With a single handler the router never calls its model
         let json = r#"{
             "models": {
-                "model": {
-                    "type": "external",
-                    "interface": "openai-api",
-                    "url": "http://localhost:8080"
-                }
+                "model": {"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:1/v1"}
             },
             "architecture": [
+                {"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]},
                 {
-                    "name": "router",
-                    "layer": 0,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "handler-a",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Handler A",
-                    "output-to": [2]
-                },
-                {
-                    "name": "handler-b",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Handler B",
-                    "output-to": [2]
-                },
-                {
-                    "name": "refiner-for-a",
-                    "layer": 2,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Refine A output",
-                    "if": "$PREV_NODE == \"handler-a\"",
-                    "output-to": ["output"]
-                },
-                {
-                    "name": "refiner-for-b",
-                    "layer": 2,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Refine B output",
-                    "if": "$PREV_NODE == \"handler-b\"",
-                    "output-to": ["output"]
+                    "name": "shout", "layer": 1, "adapter": "upper",
+                    "extra-options": {"suffix": "!"}, "output-to": [2]
                 },
+                {"name": "notify", "layer": 2, "adapter": "sink"},
                 {"name": "output", "adapter": "output"}
             ]
         }"#;
+        let delivered = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut registry = AdapterRegistry::new();
+        registry.register("upper", Upper).unwrap();
+        registry.register("sink", Sink(delivered.clone())).unwrap();
 
-        let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
-        let handler_a = processor.nodes.get("handler-a").unwrap();
+        let processor = PipelineProcessor::new(&Composition::from_str(json).unwrap())
+            .unwrap()
+            .with_adapters(&registry);
+        let request = PipelineRequest::new("hello".to_string());
+        let request_id = request.request_id;
+        assert_eq!(
+            processor.process_request(request).await.unwrap(),
+            "delivered to notify"
+        );
+        assert_eq!(*delivered.lock().unwrap(), vec!["HELLO!"]);
+        let hops: Vec<String> = processor
+            .trace(&request_id)
+            .unwrap()
+            .hops
+            .into_iter()
+            .map(|h| h.node)
+            .collect();
+        assert_eq!(hops, vec!["shout", "notify"]);
+    }
 
-        // Request that came from handler-a
-        let mut request_from_a = PipelineRequest::new("Hello".to_string());
-        request_from_a.add_hop("handler-a".to_string(), 1, None);
-        let targets = processor
-            .get_next_targets_filtered(handler_a, &request_from_a)
-            .unwrap();
-        assert_eq!(targets.len(), 1);
-        assert_eq!(targets[0], "refiner-for-a");
+    #[tokio::test]
+    async fn test_failed_requests_are_dead_lettered_and_requeued() {
+        use std::sync::atomic::{AtomicBool, Ordering};
 
-        // Request that came from handler-b
-        let handler_b = processor.nodes.get("handler-b").unwrap();
-        let mut request_from_b = PipelineRequest::new("Hello".to_string());
-        request_from_b.add_hop("handler-b".to_string(), 1, None);
-        let targets = processor
-            .get_next_targets_filtered(handler_b, &request_from_b)
-            .unwrap();
-        assert_eq!(targets.len(), 1);
-        assert_eq!(targets[0], "refiner-for-b");
+        // The handler's model fails until it is brought back up
+        let up = Arc::new(AtomicBool::new(false));
+        let model_up = up.clone();
+        let addr = fake_completions(move |_| {
+            let up = model_up.load(Ordering::SeqCst);
+            async move {
+                if !up {
+                    return Err(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+                }
+                Ok(completion("answered"))
+            }
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "model": {{"type": "external", "interface": "openai-api", "url": "http://{addr}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                    {{"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+
+        let request = PipelineRequest::new("hello".to_string())
+            .with_variables([("tier".into(), "gold".into())]);
+        let request_id = request.request_id;
+        assert!(processor.process_request(request).await.is_err());
+
+        let letters = processor.dead_letters().await;
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].request_id, request_id);
+        assert_eq!(letters[0].input.prompt, "hello");
+        assert_eq!(letters[0].input.variables["tier"], "gold");
+        assert_eq!(letters[0].trace.hops[0].node, "handler");
+        assert_eq!(letters[0].attempts, 1);
+
+        assert!(processor.requeue(&request_id).await.is_err());
+        assert_eq!(
+            processor.dead_letter(&request_id).await.unwrap().attempts,
+            2
+        );
+
+        up.store(true, Ordering::SeqCst);
+        let output = processor.requeue(&request_id).await.unwrap();
+        assert_eq!(output.content, "answered");
+        assert!(processor.dead_letters().await.is_empty());
+        assert!(matches!(
+            processor.requeue(&request_id).await,
+            Err(ProcessorError::DeadLetterNotFound(_))
+        ));
     }
 
-    #[test]
-    fn test_no_matching_conditions_returns_all_targets() {
-        // Test that when no conditions match, all targets are returned
+    #[tokio::test]
+    async fn test_replay_runs_traced_requests_again() {
+        let addr = fake_completions(|body: Value| async move {
+            let content = body["messages"][0]["content"].as_str().unwrap_or_default();
+            completion(format!("echo: {}", content))
+        })
+        .await;
+
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "model": {{"type": "external", "interface": "openai-api", "url": "http://{addr}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                    {{"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": [2]}},
+                    {{"name": "polish", "layer": 2, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+
+        let request = PipelineRequest::new("hello".to_string());
+        let request_id = request.request_id;
+        assert_eq!(
+            processor.process_request(request).await.unwrap(),
+            "echo: echo: hello"
+        );
+
+        // The whole pipeline runs again under a new ID
+        let (replay_id, output) = processor.replay(&request_id, None).await.unwrap();
+        assert_ne!(replay_id, request_id);
+        assert_eq!(output.content, "echo: echo: hello");
+
+        // A single handler gets the original prompt
+        let (replay_id, output) = processor.replay(&request_id, Some("polish")).await.unwrap();
+        assert_eq!(output.content, "echo: hello");
+        let trace = processor.trace(&replay_id).unwrap();
+        assert_eq!(trace.prompt, "hello");
+        assert_eq!(trace.hops.len(), 1);
+        assert_eq!(trace.hops[0].node, "polish");
+
+        assert!(matches!(
+            processor.replay(&request_id, Some("output")).await,
+            Err(ProcessorError::NotReplayable(_))
+        ));
+        assert!(matches!(
+            processor.replay(&Uuid::new_v4(), None).await,
+            Err(ProcessorError::RequestNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_retriever_injects_documents_and_records_ids() {
+        use crate::runtime::retriever::{RetrievedDocument, RetrieverError};
+
+        struct FixedStore;
+
+        #[async_trait::async_trait]
+        impl VectorStore for FixedStore {
+            async fn search(
+                &self,
+                vector: &[f32],
+                top_k: usize,
+            ) -> Result<Vec<RetrievedDocument>, RetrieverError> {
+                assert_eq!(vector, &[0.25, 0.75]);
+                assert_eq!(top_k, 2);
+                Ok(vec![RetrievedDocument {
+                    id: "doc-9".to_string(),
+                    content: "Paris is the capital of France.".to_string(),
+                    score: 0.92,
+                }])
+            }
+        }
+
         let json = r#"{
             "models": {
                 "model": {
                     "type": "external",
                     "interface": "openai-api",
-                    "url": "http://localhost:8080"
+                    "url": "http://127.0.0.1:1"
                 }
             },
             "architecture": [
+                {"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]},
                 {
-                    "name": "router",
-                    "layer": 0,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "handler-impossible",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Impossible condition",
-                    "if": "$WORD_COUNT > 9999",
-                    "output-to": ["output"]
-                },
-                {
-                    "name": "handler-also-impossible",
+                    "name": "docs",
                     "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Also impossible",
-                    "if": "$HOP_COUNT > 100",
+                    "adapter": "retriever",
+                    "retriever": {"store": "qdrant", "url": "http://127.0.0.1:1", "collection": "kb", "top-k": 2},
                     "output-to": ["output"]
                 },
                 {"name": "output", "adapter": "output"}
@@ -1046,77 +4911,252 @@ mod tests {
         }"#;
 
         let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
-        let router = processor.nodes.get("router").unwrap();
+        let processor = PipelineProcessor::new(&comp)
+            .unwrap()
+            .with_vector_store("docs", Box::new(FixedStore));
 
-        // Neither condition can be satisfied, so we get all targets back
-        let request = PipelineRequest::new("Hello world".to_string());
-        let targets = processor
-            .get_next_targets_filtered(router, &request)
+        // An upstream embedding node already produced the query vector
+        let mut request = PipelineRequest::new("Capital of France?".to_string());
+        request.set_variable(vars::EMBEDDING.to_string(), "[0.25,0.75]".to_string());
+        request.add_hop("docs".to_string(), 1, None);
+
+        let content = processor
+            .retrieve("docs", &mut request, "Capital of France?", None)
+            .await
             .unwrap();
-        assert_eq!(targets.len(), 2);
+
+        assert!(content.contains("[doc-9] Paris is the capital of France."));
+        assert!(content.ends_with("Question: Capital of France?"));
+        assert_eq!(request.trace[0].documents, vec!["doc-9"]);
     }
 
-    #[test]
-    fn test_mixed_conditions_and_no_conditions() {
-        // Test mixing nodes with and without conditions
-        let json = r#"{
-            "models": {
-                "model": {
-                    "type": "external",
-                    "interface": "openai-api",
-                    "url": "http://localhost:8080"
-                }
-            },
-            "architecture": [
-                {
-                    "name": "router",
-                    "layer": 0,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "output-to": [1]
-                },
-                {
-                    "name": "fallback",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Default fallback (no condition)",
-                    "output-to": ["output"]
-                },
-                {
-                    "name": "long-only",
-                    "layer": 1,
-                    "model": "model",
-                    "adapter": "openai-api",
-                    "use-case": "Only for long inputs",
-                    "if": "$WORD_COUNT >= 20",
-                    "output-to": ["output"]
-                },
-                {"name": "output", "adapter": "output"}
+    #[tokio::test]
+    async fn test_guard_actions() {
+        // The handler's model is unreachable, so any request that gets past
+        // the guard to the handler fails
+        let composition = |guard: &str| {
+            let json = format!(
+                r#"{{
+                    "models": {{
+                        "model": {{
+                            "type": "external",
+                            "interface": "openai-api",
+                            "url": "http://127.0.0.1:1"
+                        }}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "check", "layer": 1, "adapter": "guard", "guard": {guard}, "output-to": [2]}},
+                        {{"name": "handler", "layer": 2, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            );
+            Composition::from_str(&json).unwrap()
+        };
+
+        let block = PipelineProcessor::new(&composition(
+            r#"{"denylist": ["(?i)password"], "refusal": "No: {reason}"}"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            block.process("what is the admin PASSWORD").await.unwrap(),
+            "No: content matches denylisted pattern '(?i)password'"
+        );
+        assert!(matches!(
+            block.process("hello").await,
+            Err(ProcessorError::ApiError(_))
+        ));
+
+        let fallback = PipelineProcessor::new(&composition(
+            r#"{"max-length": 5, "on-violation": "fallback", "fallback": "output"}"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            fallback.process("far too long").await.unwrap(),
+            "far too long"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_evaluator_sends_low_scores_back_to_handler() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // "writer" only cites its sources from the second attempt on; the
+        // rubric grader is never satisfied
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let addr = fake_completions(move |body: Value| {
+            let counter = counter.clone();
+            async move {
+                let reply = match body["model"].as_str().unwrap() {
+                    "writer" => match counter.fetch_add(1, Ordering::SeqCst) {
+                        0 => "It is blue.".to_string(),
+                        n => format!("It is blue. Sources: attempt {}", n + 1),
+                    },
+                    "judge" => "3/10".to_string(),
+                    other => panic!("unexpected call to '{}'", other),
+                };
+                completion(reply)
+            }
+        })
+        .await;
+
+        let processor = |evaluator: &str| {
+            let json = format!(
+                r#"{{
+                    "models": {{
+                        "model": {{"type": "external", "interface": "openai-api", "url": "http://{addr}/v1"}}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "writer", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["judge"]}},
+                        {{
+                            "name": "judge", "layer": 2, "model": "model", "adapter": "evaluator",
+                            "evaluator": {evaluator},
+                            "output-to": ["output"]
+                        }},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            );
+            PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap()
+        };
+
+        let checks = processor(r#"{"must-match": ["Sources:"]}"#);
+        let request = PipelineRequest::new("Why is the sky blue?".to_string());
+        let request_id = request.request_id;
+        assert_eq!(
+            checks.process_request(request).await.unwrap(),
+            "It is blue. Sources: attempt 2"
+        );
+        let hops = checks.trace(&request_id).unwrap().hops;
+        let visited: Vec<(&str, Option<f64>)> =
+            hops.iter().map(|h| (h.node.as_str(), h.score)).collect();
+        assert_eq!(
+            visited,
+            vec![
+                ("writer", None),
+                ("judge", Some(0.0)),
+                ("writer", None),
+                ("judge", Some(1.0)),
+                ("output", None)
             ]
-        }"#;
+        );
 
-        let comp = Composition::from_str(json).unwrap();
-        let processor = PipelineProcessor::new(&comp).unwrap();
-        let router = processor.nodes.get("router").unwrap();
+        // Out of retries, the best-scored answer is kept
+        attempts.store(0, Ordering::SeqCst);
+        let rubric = processor(
+            r#"{"must-match": ["Sources:"], "rubric": "Cites a source", "max-retries": 1}"#,
+        );
+        assert_eq!(
+            rubric.process("Why is the sky blue?").await.unwrap(),
+            "It is blue. Sources: attempt 2"
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
 
-        // Short input - only fallback passes (long-only condition fails)
-        let short_request = PipelineRequest::new("Hello world".to_string());
-        let targets = processor
-            .get_next_targets_filtered(router, &short_request)
-            .unwrap();
-        assert_eq!(targets.len(), 1);
-        assert_eq!(targets[0], "fallback");
+    #[tokio::test]
+    async fn test_loop_to_repeats_until_max_iterations() {
+        // Every node echoes its prompt, so the output shows the last pass
+        let addr = fake_completions(|body: Value| async move {
+            let messages = body["messages"].as_array().unwrap();
+            let prompt = messages.last().unwrap()["content"].clone();
+            completion(prompt)
+        })
+        .await;
 
-        // Long input - both pass (fallback has no condition, long-only condition passes)
-        let long_request = PipelineRequest::new(
-            "This is a very long input that has many many words to ensure it passes the twenty word threshold easily and then some more words"
-                .to_string()
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "model": {{"type": "external", "interface": "openai-api", "url": "http://{addr}/v1"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                    {{"name": "draft", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["critique"]}},
+                    {{"name": "critique", "layer": 2, "model": "model", "adapter": "openai-api", "output-to": ["revise"]}},
+                    {{
+                        "name": "revise", "layer": 3, "model": "model", "adapter": "openai-api",
+                        "prompt-template": "pass $ITERATION",
+                        "loop-to": "critique", "max-iterations": 3,
+                        "output-to": ["output"]
+                    }},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
         );
-        let targets = processor
-            .get_next_targets_filtered(router, &long_request)
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+
+        let request = PipelineRequest::new("Write a haiku".to_string());
+        let request_id = request.request_id;
+        assert_eq!(processor.process_request(request).await.unwrap(), "pass 3");
+        let hops: Vec<String> = processor
+            .trace(&request_id)
+            .unwrap()
+            .hops
+            .into_iter()
+            .map(|h| h.node)
+            .collect();
+        assert_eq!(
+            hops,
+            vec![
+                "draft", "critique", "revise", "critique", "revise", "critique", "revise", "output"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_transform_hooks_rewrite_chunks() {
+        let addr = fake_completions(|body: Value| async move {
+            assert_eq!(body["stream"], true);
+            let events: String = ["Call ", "555-0100", " today", "."]
+                .iter()
+                .map(|c| {
+                    let event = serde_json::json!({"choices": [{"delta": {"content": c}}]});
+                    format!("data: {}\n\n", event)
+                })
+                .collect();
+            format!("{}data: [DONE]\n\n", events)
+        })
+        .await;
+
+        // Masks digits and drops the third chunk
+        let json = format!(
+            r#"{{
+                "models": {{
+                    "model": {{"type": "external", "interface": "openai-api", "url": "http://{addr}/v1"}}
+                }},
+                "functions": {{
+                    "scrub": {{"type": "command", "command": "sh", "args": ["-c", "if [ $CHUNK_INDEX = 2 ]; then echo null; else printf '\"%s\"' '$OUTPUT' | tr 0-9 '#'; fi"]}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                    {{
+                        "name": "answer", "layer": 1, "model": "model", "adapter": "openai-api",
+                        "hooks": {{"post": [{{"function": "scrub", "mode": "stream-transform"}}]}},
+                        "output-to": ["output"]
+                    }},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let processor = PipelineProcessor::new(&Composition::from_str(&json).unwrap()).unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let answer = processor
+            .process_streaming(PipelineRequest::new("Who do I call?".to_string()), &tx)
+            .await
             .unwrap();
-        assert_eq!(targets.len(), 2);
+        assert_eq!(answer, "Call ###-####.");
+
+        drop(tx);
+        let mut chunks = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let PipelineEvent::Chunk { node, content, .. } = event {
+                assert_eq!(node, "answer");
+                chunks.push(content);
+            }
+        }
+        assert_eq!(chunks, ["Call ", "###-####", "."]);
     }
 }
@@ -0,0 +1,413 @@
+//! Message queue ingestion
+//!
+//! A composition with a `queue` section also consumes prompts from a NATS
+//! subject or Kafka topic and publishes each result to an output topic, which
+//! turns the pipeline into a batch or async inference worker alongside its
+//! HTTP API.
+//!
+//! Messages are either plain text, taken as the prompt, or JSON:
+//!
+//! ```json
+//! {"id": "job-1", "prompt": "Summarize this...", "session": "user-42"}
+//! ```
+//!
+//! Results are JSON with the job's `id` (the request ID when none was given)
+//! and either `output` or `error`.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::stream::{self, Stream, StreamExt};
+use rskafka::client::consumer::{StartOffset, StreamConsumerBuilder};
+use rskafka::client::partition::{Compression, PartitionClient, UnknownTopicHandling};
+use rskafka::client::ClientBuilder;
+use rskafka::record::Record;
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use super::processor::{PipelineProcessor, SharedProcessor};
+use super::request::PipelineRequest;
+use crate::config::{QueueConfig, QueueKind};
+
+/// Errors that can occur while talking to the message broker
+#[derive(Error, Debug)]
+pub enum QueueError {
+    #[error("Failed to connect to {0}: {1}")]
+    Connect(String, String),
+
+    #[error("Failed to receive message: {0}")]
+    Receive(String),
+
+    #[error("Failed to publish result: {0}")]
+    Publish(String),
+
+    #[error("Topic '{0}' has no partitions")]
+    NoPartitions(String),
+}
+
+/// A prompt read from the input topic
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct QueueJob {
+    /// Correlation ID echoed in the result
+    #[serde(default)]
+    pub id: Option<String>,
+    pub prompt: String,
+    /// Conversation session the prompt continues
+    #[serde(default)]
+    pub session: Option<String>,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Parse a message from the input topic
+///
+/// JSON objects must carry a `prompt`; anything else that is valid UTF-8 is
+/// taken as the prompt itself.
+pub fn parse_job(payload: &[u8]) -> Result<QueueJob, String> {
+    let text = std::str::from_utf8(payload).map_err(|e| format!("Invalid UTF-8: {}", e))?;
+
+    if text.trim_start().starts_with('{') {
+        return serde_json::from_str(text).map_err(|e| format!("Invalid job: {}", e));
+    }
+
+    if text.trim().is_empty() {
+        return Err("Empty prompt".to_string());
+    }
+
+    Ok(QueueJob {
+        id: None,
+        prompt: text.to_string(),
+        session: None,
+    })
+}
+
+/// Render the message published to the output topic
+pub fn render_result(id: Option<&str>, result: Result<&str, &str>) -> Vec<u8> {
+    let body = match result {
+        Ok(output) => json!({"id": id, "output": output}),
+        Err(error) => json!({"id": id, "error": error}),
+    };
+    body.to_string().into_bytes()
+}
+
+// ============================================================================
+// SBIO: Traits for abstraction (allows mocking in tests)
+// ============================================================================
+
+/// Where prompts come from
+#[async_trait]
+pub trait QueueSource: Send {
+    /// Wait for the next message; `None` once the subscription has ended
+    async fn next(&mut self) -> Option<Result<Vec<u8>, QueueError>>;
+}
+
+/// Where results go
+#[async_trait]
+pub trait QueueSink: Send + Sync {
+    async fn publish(&self, payload: Vec<u8>) -> Result<(), QueueError>;
+}
+
+// ============================================================================
+// SBIO: I/O implementations
+// ============================================================================
+
+/// Connect to the broker described by a composition's `queue` section
+pub async fn connect(
+    config: &QueueConfig,
+) -> Result<(Box<dyn QueueSource>, Arc<dyn QueueSink>), QueueError> {
+    match config.kind {
+        QueueKind::Nats => {
+            let client = async_nats::connect(&config.brokers)
+                .await
+                .map_err(|e| QueueError::Connect(config.brokers.join(","), e.to_string()))?;
+            let subscriber = client
+                .queue_subscribe(config.input_topic.clone(), config.group.clone())
+                .await
+                .map_err(|e| QueueError::Connect(config.input_topic.clone(), e.to_string()))?;
+
+            Ok((
+                Box::new(NatsSource { subscriber }),
+                Arc::new(NatsSink {
+                    client,
+                    subject: config.output_topic.clone(),
+                }),
+            ))
+        }
+        QueueKind::Kafka => {
+            let client = ClientBuilder::new(config.brokers.clone())
+                .build()
+                .await
+                .map_err(|e| QueueError::Connect(config.brokers.join(","), e.to_string()))?;
+            let topics = client
+                .list_topics()
+                .await
+                .map_err(|e| QueueError::Connect(config.brokers.join(","), e.to_string()))?;
+            let partitions_of = |name: &str| -> Vec<i32> {
+                topics
+                    .iter()
+                    .find(|t| t.name == name)
+                    .map(|t| t.partitions.iter().copied().collect())
+                    .unwrap_or_default()
+            };
+
+            // Read every partition of the input topic from the newest record
+            let mut streams = Vec::new();
+            for partition in partitions_of(&config.input_topic) {
+                let partition_client = client
+                    .partition_client(
+                        config.input_topic.clone(),
+                        partition,
+                        UnknownTopicHandling::Retry,
+                    )
+                    .await
+                    .map_err(|e| QueueError::Connect(config.input_topic.clone(), e.to_string()))?;
+                let consumer =
+                    StreamConsumerBuilder::new(Arc::new(partition_client), StartOffset::Latest)
+                        .build();
+                streams.push(consumer.boxed());
+            }
+            if streams.is_empty() {
+                return Err(QueueError::NoPartitions(config.input_topic.clone()));
+            }
+            let records = stream::select_all(streams).filter_map(|item| async move {
+                match item {
+                    Ok((record, _high_watermark)) => record.record.value.map(Ok),
+                    Err(e) => Some(Err(QueueError::Receive(e.to_string()))),
+                }
+            });
+
+            let mut partitions = Vec::new();
+            for partition in partitions_of(&config.output_topic) {
+                partitions.push(
+                    client
+                        .partition_client(
+                            config.output_topic.clone(),
+                            partition,
+                            UnknownTopicHandling::Retry,
+                        )
+                        .await
+                        .map_err(|e| {
+                            QueueError::Connect(config.output_topic.clone(), e.to_string())
+                        })?,
+                );
+            }
+            if partitions.is_empty() {
+                return Err(QueueError::NoPartitions(config.output_topic.clone()));
+            }
+
+            Ok((
+                Box::new(KafkaSource {
+                    records: Box::pin(records),
+                }),
+                Arc::new(KafkaSink {
+                    partitions,
+                    next: AtomicUsize::new(0),
+                }),
+            ))
+        }
+    }
+}
+
+/// Subscription to a NATS subject as a member of the queue group
+pub struct NatsSource {
+    subscriber: async_nats::Subscriber,
+}
+
+#[async_trait]
+impl QueueSource for NatsSource {
+    async fn next(&mut self) -> Option<Result<Vec<u8>, QueueError>> {
+        self.subscriber
+            .next()
+            .await
+            .map(|message| Ok(message.payload.to_vec()))
+    }
+}
+
+/// Publishes results to a NATS subject
+pub struct NatsSink {
+    client: async_nats::Client,
+    subject: String,
+}
+
+#[async_trait]
+impl QueueSink for NatsSink {
+    async fn publish(&self, payload: Vec<u8>) -> Result<(), QueueError> {
+        self.client
+            .publish(self.subject.clone(), payload.into())
+            .await
+            .map_err(|e| QueueError::Publish(e.to_string()))
+    }
+}
+
+/// Records from every partition of a Kafka topic
+pub struct KafkaSource {
+    records: Pin<Box<dyn Stream<Item = Result<Vec<u8>, QueueError>> + Send>>,
+}
+
+#[async_trait]
+impl QueueSource for KafkaSource {
+    async fn next(&mut self) -> Option<Result<Vec<u8>, QueueError>> {
+        self.records.next().await
+    }
+}
+
+/// Publishes results to a Kafka topic, spreading them over its partitions
+pub struct KafkaSink {
+    partitions: Vec<PartitionClient>,
+    next: AtomicUsize,
+}
+
+#[async_trait]
+impl QueueSink for KafkaSink {
+    async fn publish(&self, payload: Vec<u8>) -> Result<(), QueueError> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.partitions.len();
+        let record = Record {
+            key: None,
+            value: Some(payload),
+            headers: Default::default(),
+            timestamp: chrono::Utc::now(),
+        };
+        self.partitions[index]
+            .produce(vec![record], Compression::NoCompression)
+            .await
+            .map(|_| ())
+            .map_err(|e| QueueError::Publish(e.to_string()))
+    }
+}
+
+/// Run one job through the pipeline and publish its result
+async fn handle_message(processor: &PipelineProcessor, sink: &dyn QueueSink, payload: &[u8]) {
+    let result = match parse_job(payload) {
+        Ok(job) => {
+            let request = PipelineRequest::new(job.prompt);
+            let id = job.id.unwrap_or_else(|| request.request_id.to_string());
+            let output = match &job.session {
+                Some(session) => processor
+                    .process_session(session, request)
+                    .await
+                    .map(|output| output.content),
+                None => processor.process_request(request).await,
+            };
+            match output {
+                Ok(content) => render_result(Some(&id), Ok(&content)),
+                Err(e) => {
+                    warn!("Queue job {} failed: {}", id, e);
+                    render_result(Some(&id), Err(&e.to_string()))
+                }
+            }
+        }
+        Err(e) => {
+            warn!("Rejected queue message: {}", e);
+            render_result(None, Err(&e))
+        }
+    };
+
+    if let Err(e) = sink.publish(result).await {
+        warn!("{}", e);
+    }
+}
+
+/// Feed every message from `source` through the pipeline
+///
+/// Up to `concurrency` jobs run at once, each with the processor current
+/// when it was taken off the queue. Returns when the source ends.
+pub async fn run_queue_worker(
+    processor: SharedProcessor,
+    mut source: Box<dyn QueueSource>,
+    sink: Arc<dyn QueueSink>,
+    concurrency: usize,
+) {
+    let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+
+    while let Some(message) = source.next().await {
+        let payload = match message {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+        let Some(processor) = processor.get() else {
+            warn!("No pipeline processor to handle the message with");
+            continue;
+        };
+        let sink = sink.clone();
+        tokio::spawn(async move {
+            handle_message(&processor, sink.as_ref(), &payload).await;
+            drop(permit);
+        });
+    }
+
+    // Let the jobs still running publish their results
+    let _ = permits.acquire_many(concurrency.max(1) as u32).await;
+    debug!("Queue source ended");
+}
+
+/// Connect to the broker and start consuming in the background
+pub async fn spawn_queue_worker(
+    config: &QueueConfig,
+    processor: SharedProcessor,
+) -> Result<JoinHandle<()>, QueueError> {
+    let (source, sink) = connect(config).await?;
+    info!(
+        "Consuming prompts from {:?} topic '{}', publishing results to '{}'",
+        config.kind, config.input_topic, config.output_topic
+    );
+
+    Ok(tokio::spawn(run_queue_worker(
+        processor,
+        source,
+        sink,
+        config.concurrency,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_parse_job_plain_text() {
+        let job = parse_job(b"What is Rust?").unwrap();
+        assert_eq!(job.prompt, "What is Rust?");
+        assert!(job.id.is_none());
+
+        assert!(parse_job(b"  ").is_err());
+        assert!(parse_job(&[0xff, 0xfe]).is_err());
+    }
+
+    #[test]
+    fn test_parse_job_json() {
+        let job = parse_job(br#"{"id": "job-1", "prompt": "hi", "session": "s1"}"#).unwrap();
+        assert_eq!(job.id.as_deref(), Some("job-1"));
+        assert_eq!(job.prompt, "hi");
+        assert_eq!(job.session.as_deref(), Some("s1"));
+
+        assert!(parse_job(br#"{"id": "job-1"}"#).is_err());
+    }
+
+    #[test]
+    fn test_render_result() {
+        let ok: Value = serde_json::from_slice(&render_result(Some("job-1"), Ok("4"))).unwrap();
+        assert_eq!(ok["id"], "job-1");
+        assert_eq!(ok["output"], "4");
+        assert!(ok.get("error").is_none());
+
+        let err: Value = serde_json::from_slice(&render_result(None, Err("boom"))).unwrap();
+        assert!(err["id"].is_null());
+        assert_eq!(err["error"], "boom");
+    }
+}
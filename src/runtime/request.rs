@@ -1,6 +1,10 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+use crate::client::{CacheControl, ImageUrl, Message, Tool, ToolCall, ToolChoice};
+use crate::config::PromptCacheConfig;
+
 /// System variable names (constants for consistency)
 pub mod vars {
     pub const INITIAL_INPUT: &str = "INITIAL_INPUT";
@@ -14,6 +18,11 @@ pub mod vars {
     pub const ROUTE_DECISION: &str = "ROUTE_DECISION";
     pub const INPUT_LENGTH: &str = "INPUT_LENGTH";
     pub const WORD_COUNT: &str = "WORD_COUNT";
+    pub const EMBEDDING: &str = "EMBEDDING";
+    pub const RETRIEVED_DOCS: &str = "RETRIEVED_DOCS";
+    pub const GUARD_VIOLATION: &str = "GUARD_VIOLATION";
+    pub const EVALUATION_SCORE: &str = "EVALUATION_SCORE";
+    pub const ITERATION: &str = "ITERATION";
 }
 
 /// A request flowing through the pipeline
@@ -31,6 +40,27 @@ pub struct PipelineRequest {
     pub trace: Vec<RequestHop>,
     /// Start timestamp for this request
     pub start_time: chrono::DateTime<chrono::Utc>,
+    /// Earlier turns of the conversation, replayed to handler nodes
+    pub history: Vec<Message>,
+    /// Images attached to the prompt, sent to handlers that accept them
+    pub images: Vec<ImageUrl>,
+    /// Tools the client offers to handler nodes
+    pub tools: Vec<Tool>,
+    pub tool_choice: Option<ToolChoice>,
+    /// Tool calls and results that follow the prompt, when the client is
+    /// answering a previous tool call
+    pub tool_messages: Vec<Message>,
+    /// Tool calls a handler asked the client to run; the pipeline stops there
+    pub tool_calls: Vec<ToolCall>,
+    /// Node the client asked for; chosen without consulting the router as
+    /// soon as it is one of the candidates
+    pub route: Option<String>,
+    /// Time each node spent choosing where the request goes next
+    pub routing_ms: HashMap<String, u64>,
+    /// When the pipeline gives up on the request
+    pub deadline: Option<Instant>,
+    /// Handoffs from other pipelines the request took to get here
+    pub handoff_depth: u32,
 }
 
 /// A single hop in the pipeline trace
@@ -41,6 +71,15 @@ pub struct RequestHop {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     /// Router decision that led to this hop (if applicable)
     pub decision: Option<String>,
+    /// IDs of documents a retriever injected at this hop
+    pub documents: Vec<String>,
+    /// Time spent in the node, including its hooks
+    pub latency_ms: Option<u64>,
+    /// Tokens reported by the node's model
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    /// Quality score an evaluator gave at this hop
+    pub score: Option<f64>,
 }
 
 impl PipelineRequest {
@@ -54,6 +93,7 @@ impl PipelineRequest {
         variables.insert(vars::INITIAL_INPUT.to_string(), prompt.clone());
         variables.insert(vars::CURRENT_INPUT.to_string(), prompt.clone());
         variables.insert(vars::HOP_COUNT.to_string(), "0".to_string());
+        variables.insert(vars::ITERATION.to_string(), "1".to_string());
         variables.insert(vars::TIMESTAMP.to_string(), now.timestamp().to_string());
         variables.insert(vars::REQUEST_ID.to_string(), request_id.to_string());
         variables.insert(vars::INPUT_LENGTH.to_string(), prompt.len().to_string());
@@ -69,6 +109,16 @@ impl PipelineRequest {
             variables,
             trace: Vec::new(),
             start_time: now,
+            history: Vec::new(),
+            images: Vec::new(),
+            tools: Vec::new(),
+            tool_choice: None,
+            tool_messages: Vec::new(),
+            tool_calls: Vec::new(),
+            route: None,
+            routing_ms: HashMap::new(),
+            deadline: None,
+            handoff_depth: 0,
         }
     }
 
@@ -95,9 +145,142 @@ impl PipelineRequest {
             variables,
             trace: Vec::new(),
             start_time: now,
+            history: Vec::new(),
+            images: Vec::new(),
+            tools: Vec::new(),
+            tool_choice: None,
+            tool_messages: Vec::new(),
+            tool_calls: Vec::new(),
+            route: None,
+            routing_ms: HashMap::new(),
+            deadline: None,
+            handoff_depth: 0,
+        }
+    }
+
+    /// Attach earlier conversation turns to the request
+    pub fn with_history(mut self, history: Vec<Message>) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Attach images to the prompt
+    pub fn with_images(mut self, images: Vec<ImageUrl>) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Offer tools to the handler nodes
+    pub fn with_tools(mut self, tools: Vec<Tool>, tool_choice: Option<ToolChoice>) -> Self {
+        self.tools = tools;
+        self.tool_choice = tool_choice;
+        self
+    }
+
+    /// Attach the tool call round trip that follows the prompt
+    pub fn with_tool_messages(mut self, messages: Vec<Message>) -> Self {
+        self.tool_messages = messages;
+        self
+    }
+
+    /// Set custom variables, e.g. from request headers
+    pub fn with_variables(mut self, variables: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.variables.extend(variables);
+        self
+    }
+
+    /// Send the request to a specific node instead of asking the router
+    pub fn with_route(mut self, node: impl Into<String>) -> Self {
+        self.route = Some(node.into());
+        self
+    }
+
+    /// Record how many handoffs the request took to get here
+    pub fn with_handoff_depth(mut self, depth: u32) -> Self {
+        self.handoff_depth = depth;
+        self
+    }
+
+    /// Give up on the request once `budget` has passed
+    pub fn with_deadline(mut self, budget: Duration) -> Self {
+        self.deadline = Some(Instant::now() + budget);
+        self
+    }
+
+    /// Conversation sent to a handler: its system prompt, history, the
+    /// node's input with the prompt's images, then any tool call round trip
+    pub fn handler_messages(&self, system_prompt: Option<&str>, content: &str) -> Vec<Message> {
+        let mut messages: Vec<Message> = system_prompt
+            .map(|prompt| Message {
+                role: "system".to_string(),
+                content: prompt.to_string(),
+                ..Default::default()
+            })
+            .into_iter()
+            .collect();
+        messages.extend(self.history.iter().cloned());
+        messages.push(Message {
+            role: "user".to_string(),
+            content: content.to_string(),
+            images: self.images.clone(),
+            ..Default::default()
+        });
+        messages.extend(self.tool_messages.iter().cloned());
+        messages
+    }
+
+    /// Mark prompt cache breakpoints on a conversation built by
+    /// [`handler_messages`](Self::handler_messages): on the system prompt
+    /// and, with `history`, on the last message before the node's input
+    pub fn mark_prompt_cache(&self, messages: &mut [Message], cache: &PromptCacheConfig) {
+        let marker = CacheControl::ephemeral(cache.ttl.map(|ttl| ttl.as_str()));
+        if cache.system {
+            if let Some(system) = messages.first_mut().filter(|m| m.role == "system") {
+                system.cache_control = Some(marker.clone());
+            }
+        }
+        if cache.history && !self.history.is_empty() {
+            let input = messages.len().saturating_sub(self.tool_messages.len() + 1);
+            if let Some(last) = input.checked_sub(1).and_then(|i| messages.get_mut(i)) {
+                last.cache_control = Some(marker);
+            }
         }
     }
 
+    /// Fill a node's prompt template or system prompt
+    ///
+    /// `$INPUT` is the node's input, `$PREV_OUTPUT` the content the previous
+    /// node produced, and any other `$NAME` a request variable. Unknown
+    /// names are left as they are; filled-in values are not scanned again.
+    pub fn render_prompt(&self, template: &str, input: &str) -> String {
+        let value = |name: &str| match name {
+            "INPUT" => Some(input),
+            "PREV_OUTPUT" => Some(self.current_content.as_str()),
+            _ => self.variables.get(name).map(String::as_str),
+        };
+
+        let mut prompt = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(pos) = rest.find('$') {
+            prompt.push_str(&rest[..pos]);
+            let after = &rest[pos + 1..];
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            let name = &after[..len];
+            match value(name) {
+                Some(v) => prompt.push_str(v),
+                None => {
+                    prompt.push('$');
+                    prompt.push_str(name);
+                }
+            }
+            rest = &after[len..];
+        }
+        prompt.push_str(rest);
+        prompt
+    }
+
     /// Add a hop to the trace and update system variables
     pub fn add_hop(&mut self, node_name: String, layer: u32, decision: Option<String>) {
         self.trace.push(RequestHop {
@@ -105,6 +288,11 @@ impl PipelineRequest {
             layer,
             timestamp: chrono::Utc::now(),
             decision: decision.clone(),
+            documents: Vec::new(),
+            latency_ms: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            score: None,
         });
 
         // Update system variables after hop
@@ -119,6 +307,35 @@ impl PipelineRequest {
         }
     }
 
+    /// Record timing and token usage (prompt, completion) for the current hop
+    pub fn complete_hop(&mut self, latency_ms: u64, tokens: Option<(u32, u32)>) {
+        if let Some(hop) = self.trace.last_mut() {
+            hop.latency_ms = Some(latency_ms);
+            if let Some((prompt, completion)) = tokens {
+                hop.prompt_tokens = Some(prompt);
+                hop.completion_tokens = Some(completion);
+            }
+        }
+    }
+
+    /// Record the documents retrieved at the current hop
+    pub fn record_documents(&mut self, ids: Vec<String>) {
+        self.variables
+            .insert(vars::RETRIEVED_DOCS.to_string(), ids.join(","));
+        if let Some(hop) = self.trace.last_mut() {
+            hop.documents = ids;
+        }
+    }
+
+    /// Record the score an evaluator gave at the current hop
+    pub fn record_score(&mut self, score: f64) {
+        self.variables
+            .insert(vars::EVALUATION_SCORE.to_string(), format!("{:.2}", score));
+        if let Some(hop) = self.trace.last_mut() {
+            hop.score = Some(score);
+        }
+    }
+
     /// Set the current layer being evaluated
     pub fn set_current_layer(&mut self, layer: u32) {
         self.variables
@@ -198,6 +415,32 @@ mod tests {
         assert_eq!(req.trace[0].decision, Some("node1".to_string()));
     }
 
+    #[test]
+    fn test_record_documents() {
+        let mut req = PipelineRequest::new("Hello".to_string());
+        req.add_hop("retriever".to_string(), 1, None);
+        req.record_documents(vec!["doc-1".to_string(), "doc-7".to_string()]);
+
+        assert_eq!(req.trace[0].documents, vec!["doc-1", "doc-7"]);
+        assert_eq!(
+            req.get_variable(vars::RETRIEVED_DOCS),
+            Some(&"doc-1,doc-7".to_string())
+        );
+    }
+
+    #[test]
+    fn test_record_score() {
+        let mut req = PipelineRequest::new("Hello".to_string());
+        req.add_hop("judge".to_string(), 2, None);
+        req.record_score(0.75);
+
+        assert_eq!(req.trace[0].score, Some(0.75));
+        assert_eq!(
+            req.get_variable(vars::EVALUATION_SCORE),
+            Some(&"0.75".to_string())
+        );
+    }
+
     #[test]
     fn test_variables() {
         let mut req = PipelineRequest::new("Hello".to_string());
@@ -360,4 +603,90 @@ mod tests {
         assert_eq!(count_words("  hello   world  "), 2);
         assert_eq!(count_words("one\ttwo\nthree"), 3);
     }
+
+    #[test]
+    fn test_handler_messages_order() {
+        let message = |role: &str, content: &str| Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+
+        let mut tool_result = message("tool", "14C");
+        tool_result.tool_call_id = Some("call_1".to_string());
+
+        let req = PipelineRequest::new("Weather in Oslo?".to_string())
+            .with_history(vec![message("user", "Hi"), message("assistant", "Hello")])
+            .with_tool_messages(vec![message("assistant", ""), tool_result]);
+
+        let messages = req.handler_messages(None, "Weather in Oslo? (rewritten)");
+        let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(
+            roles,
+            vec!["user", "assistant", "user", "assistant", "tool"]
+        );
+        assert_eq!(messages[2].content, "Weather in Oslo? (rewritten)");
+        assert!(messages[2].images.is_empty());
+        assert_eq!(messages[4].tool_call_id.as_deref(), Some("call_1"));
+
+        let image = ImageUrl {
+            url: "https://example.com/cat.png".to_string(),
+            detail: None,
+        };
+        let with_image = req.clone().with_images(vec![image.clone()]);
+        let messages = with_image.handler_messages(None, "What is this?");
+        assert_eq!(messages[2].images, vec![image]);
+
+        let messages = req.handler_messages(Some("You are a forecaster"), "Weather?");
+        assert_eq!(messages[0].role, "system");
+        assert_eq!(messages[0].content, "You are a forecaster");
+        assert_eq!(messages.len(), 6);
+    }
+
+    #[test]
+    fn test_mark_prompt_cache() {
+        let message = |role: &str, content: &str| Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let req = PipelineRequest::new("And tomorrow?".to_string())
+            .with_history(vec![message("user", "Hi"), message("assistant", "Hello")])
+            .with_tool_messages(vec![message("assistant", ""), message("tool", "14C")]);
+        let marked = |cache: &PromptCacheConfig| -> Vec<bool> {
+            let mut messages = req.handler_messages(Some("You are a forecaster"), "Tomorrow?");
+            req.mark_prompt_cache(&mut messages, cache);
+            messages.iter().map(|m| m.cache_control.is_some()).collect()
+        };
+
+        let mut cache = PromptCacheConfig {
+            system: true,
+            history: false,
+            ttl: None,
+        };
+        assert_eq!(marked(&cache), [true, false, false, false, false, false]);
+        cache.history = true;
+        assert_eq!(marked(&cache), [true, false, true, false, false, false]);
+
+        // Without history there is nothing to mark but the system prompt
+        let fresh = PipelineRequest::new("Hi".to_string());
+        let mut messages = fresh.handler_messages(None, "Hi");
+        fresh.mark_prompt_cache(&mut messages, &cache);
+        assert!(messages[0].cache_control.is_none());
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let mut req = PipelineRequest::new("bonjour $TENANT".to_string());
+        req.set_variable("TENANT".to_string(), "acme".to_string());
+
+        let prompt = req.render_prompt(
+            "[$TENANT] Translate ($INPUT_LENGTH chars): $INPUT (was: $PREV_OUTPUT) $UNKNOWN",
+            "bonjour!",
+        );
+        assert_eq!(
+            prompt,
+            "[acme] Translate (15 chars): bonjour! (was: bonjour $TENANT) $UNKNOWN"
+        );
+    }
 }
@@ -0,0 +1,428 @@
+//! Request logs: a redacted sample of the requests a pipeline answered
+//!
+//! Logging is opt-in. A worker started with `--request-log-sample PERCENT`
+//! logs that share of its requests: the prompt, the answer or error, the
+//! nodes the request went through and how long it took. Text matching any
+//! `--request-log-redact` pattern is replaced with `[REDACTED]` before the
+//! log leaves the processor.
+//!
+//! Logs are appended to a JSON Lines file (`--request-log-file`), shipped to
+//! the control plane (`--request-log-url`) where `llmnet get requestlogs`
+//! reads them, or both.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::runtime::trace::RequestTrace;
+
+/// Text redacted values are replaced with
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Patterns that can be given by name instead of as a regex
+pub const REDACT_PRESETS: &[(&str, &str)] = &[
+    ("email", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}"),
+    (
+        "api-key",
+        r"\b(?:sk|pk|rk|ghp|gho|xox[abp])[-_][A-Za-z0-9_-]{8,}\b",
+    ),
+];
+
+/// Logs a worker holds for the control plane while it can't be reached
+pub const MAX_PENDING_LOGS: usize = 1000;
+
+/// How often logs are shipped to the control plane
+pub const SHIP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Errors that can occur while setting up or writing request logs
+#[derive(Error, Debug)]
+pub enum RequestLogError {
+    #[error("Invalid redaction pattern '{0}': {1}")]
+    Pattern(String, regex::Error),
+
+    #[error("Request log I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to encode request log: {0}")]
+    Encode(#[from] serde_json::Error),
+}
+
+/// One logged request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RequestLog {
+    pub request_id: Uuid,
+    /// Worker that answered the request
+    pub source: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    pub prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Nodes the request went through, in order
+    pub route: Vec<String>,
+    pub total_tokens: u32,
+}
+
+/// Filters for reading request logs
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RequestLogQuery {
+    /// Only logs from this worker
+    pub source: Option<String>,
+
+    /// Only failed requests
+    #[serde(default)]
+    pub failed: bool,
+
+    /// Return at most this many of the most recent logs
+    pub limit: Option<usize>,
+}
+
+/// Replaces text matching any of its patterns
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+// ============================================================================
+// SBIO: Pure functions
+// ============================================================================
+
+/// Whether a request falls in the sampled share
+///
+/// Decided by the request ID, so a request retried under the same ID is
+/// sampled the same way each time.
+pub fn is_sampled(request_id: &Uuid, percent: f64) -> bool {
+    let bucket = (request_id.as_u128() % 10_000) as f64;
+    bucket < percent * 100.0
+}
+
+impl Redactor {
+    /// Compile redaction patterns; `email` and `api-key` name the presets
+    pub fn new(patterns: &[String]) -> Result<Self, RequestLogError> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                let regex = REDACT_PRESETS
+                    .iter()
+                    .find(|(name, _)| name == pattern)
+                    .map_or(pattern.as_str(), |(_, regex)| regex);
+                Regex::new(regex).map_err(|e| RequestLogError::Pattern(pattern.clone(), e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { patterns })
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        self.patterns
+            .iter()
+            .fold(text.to_string(), |text, pattern| {
+                pattern.replace_all(&text, REDACTED).into_owned()
+            })
+    }
+}
+
+impl RequestLog {
+    /// Log a finished request with its text redacted
+    pub fn from_trace(trace: &RequestTrace, source: &str, redactor: &Redactor) -> Self {
+        Self {
+            request_id: trace.request_id,
+            source: source.to_string(),
+            started_at: trace.started_at,
+            duration_ms: trace.duration_ms,
+            prompt: redactor.redact(&trace.prompt),
+            output: trace.output.as_deref().map(|o| redactor.redact(o)),
+            error: trace.error.as_deref().map(|e| redactor.redact(e)),
+            route: trace.hops.iter().map(|h| h.node.clone()).collect(),
+            total_tokens: trace.total_tokens,
+        }
+    }
+}
+
+/// Logs matching a query, oldest first
+pub fn filter_logs<'a>(
+    logs: impl DoubleEndedIterator<Item = &'a RequestLog>,
+    query: &RequestLogQuery,
+) -> Vec<RequestLog> {
+    let mut matching: Vec<RequestLog> = logs
+        .rev()
+        .filter(|log| query.source.as_deref().is_none_or(|s| log.source == s))
+        .filter(|log| !query.failed || log.error.is_some())
+        .take(query.limit.unwrap_or(usize::MAX))
+        .cloned()
+        .collect();
+    matching.reverse();
+    matching
+}
+
+/// Add logs to a queue, dropping the oldest beyond `capacity`
+pub fn push_logs(queue: &mut VecDeque<RequestLog>, logs: Vec<RequestLog>, capacity: usize) {
+    queue.extend(logs);
+    let excess = queue.len().saturating_sub(capacity);
+    queue.drain(..excess);
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Samples, redacts and writes request logs for a processor
+pub struct RequestLogger {
+    sample_percent: f64,
+    redactor: Redactor,
+    source: String,
+    file: Option<PathBuf>,
+    /// Logs waiting to be shipped, when shipping
+    pending: Option<Mutex<VecDeque<RequestLog>>>,
+}
+
+impl RequestLogger {
+    /// Log `sample_percent` percent of requests under this worker's name
+    pub fn new(sample_percent: f64, redactor: Redactor, source: impl Into<String>) -> Self {
+        Self {
+            sample_percent,
+            redactor,
+            source: source.into(),
+            file: None,
+            pending: None,
+        }
+    }
+
+    /// Append logs to this JSON Lines file
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// Hold logs for [`spawn_request_log_shipper`] to send on
+    pub fn with_shipping(mut self) -> Self {
+        self.pending = Some(Mutex::new(VecDeque::new()));
+        self
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Log a finished request if it is sampled
+    pub async fn record(&self, trace: &RequestTrace) -> Result<(), RequestLogError> {
+        if !is_sampled(&trace.request_id, self.sample_percent) {
+            return Ok(());
+        }
+        let log = RequestLog::from_trace(trace, &self.source, &self.redactor);
+
+        if let Some(path) = &self.file {
+            let mut line = serde_json::to_string(&log)?;
+            line.push('\n');
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            // tokio hands writes to a blocking thread; wait for this one to land
+            file.flush().await?;
+        }
+        if let Some(pending) = &self.pending {
+            push_logs(&mut *pending.lock().await, vec![log], MAX_PENDING_LOGS);
+        }
+        Ok(())
+    }
+
+    /// Take the logs waiting to be shipped
+    pub async fn take_pending(&self) -> Vec<RequestLog> {
+        match &self.pending {
+            Some(pending) => pending.lock().await.drain(..).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Put back logs that couldn't be shipped, ahead of newer ones
+    pub async fn restore_pending(&self, logs: Vec<RequestLog>) {
+        if let Some(pending) = &self.pending {
+            let mut pending = pending.lock().await;
+            let newer: Vec<RequestLog> = pending.drain(..).collect();
+            push_logs(&mut pending, logs, MAX_PENDING_LOGS);
+            push_logs(&mut pending, newer, MAX_PENDING_LOGS);
+        }
+    }
+}
+
+/// Send pending logs to the control plane's `POST /v1/requestlogs` every
+/// `interval`, with `api_key` if it requires one; logs that fail to send
+/// are kept for the next attempt
+pub fn spawn_request_log_shipper(
+    logger: Arc<RequestLogger>,
+    control_plane_url: String,
+    api_key: Option<String>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    let url = format!("{}/v1/requestlogs", control_plane_url.trim_end_matches('/'));
+    let client = crate::cluster::auth::control_plane_client(api_key.as_deref());
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let logs = logger.take_pending().await;
+            if logs.is_empty() {
+                continue;
+            }
+            let sent = client
+                .post(&url)
+                .json(&logs)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+            match sent {
+                Ok(_) => debug!("Shipped {} request logs", logs.len()),
+                Err(e) => {
+                    warn!("Failed to ship request logs: {}", e);
+                    logger.restore_pending(logs).await;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::request::PipelineRequest;
+
+    fn trace(prompt: &str, result: Result<String, String>) -> RequestTrace {
+        RequestTrace::capture(&PipelineRequest::new(prompt.to_string()), &result)
+    }
+
+    #[test]
+    fn test_is_sampled() {
+        let ids: Vec<Uuid> = (0..1000).map(|_| Uuid::new_v4()).collect();
+        assert!(ids.iter().all(|id| is_sampled(id, 100.0)));
+        assert!(!ids.iter().any(|id| is_sampled(id, 0.0)));
+
+        let sampled = ids.iter().filter(|id| is_sampled(id, 10.0)).count();
+        assert!((50..150).contains(&sampled), "sampled {}", sampled);
+        assert_eq!(is_sampled(&ids[0], 10.0), is_sampled(&ids[0], 10.0));
+    }
+
+    #[test]
+    fn test_redactor() {
+        let redactor = Redactor::new(&["email".to_string(), r"\d{3}-\d{4}".to_string()]).unwrap();
+        assert_eq!(
+            redactor.redact("Mail jo@example.com or call 555-1234"),
+            "Mail [REDACTED] or call [REDACTED]"
+        );
+
+        let keys = Redactor::new(&["api-key".to_string()]).unwrap();
+        assert_eq!(keys.redact("use sk-abc123def456ghi"), "use [REDACTED]");
+        assert_eq!(Redactor::default().redact("as is"), "as is");
+        assert!(matches!(
+            Redactor::new(&["(".to_string()]),
+            Err(RequestLogError::Pattern(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_from_trace_redacts() {
+        let redactor = Redactor::new(&["email".to_string()]).unwrap();
+        let log = RequestLog::from_trace(
+            &trace("I'm jo@example.com", Ok("Hi jo@example.com".to_string())),
+            "worker-1",
+            &redactor,
+        );
+        assert_eq!(log.prompt, "I'm [REDACTED]");
+        assert_eq!(log.output.as_deref(), Some("Hi [REDACTED]"));
+        assert_eq!(log.source, "worker-1");
+        assert!(log.error.is_none());
+    }
+
+    #[test]
+    fn test_filter_and_push_logs() {
+        let redactor = Redactor::default();
+        let mut queue = VecDeque::new();
+        let logs = vec![
+            RequestLog::from_trace(&trace("a", Ok("1".into())), "w1", &redactor),
+            RequestLog::from_trace(&trace("b", Err("boom".into())), "w2", &redactor),
+            RequestLog::from_trace(&trace("c", Ok("3".into())), "w1", &redactor),
+        ];
+        push_logs(&mut queue, logs, 2);
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue[0].prompt, "b");
+
+        let w1 = RequestLogQuery {
+            source: Some("w1".into()),
+            ..Default::default()
+        };
+        assert_eq!(filter_logs(queue.iter(), &w1)[0].prompt, "c");
+        let failed = RequestLogQuery {
+            failed: true,
+            ..Default::default()
+        };
+        assert_eq!(filter_logs(queue.iter(), &failed)[0].prompt, "b");
+        let last = RequestLogQuery {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(filter_logs(queue.iter(), &last)[0].prompt, "c");
+    }
+
+    #[tokio::test]
+    async fn test_logger_writes_file_and_holds_pending() {
+        let path = std::env::temp_dir().join(format!("llmnet-requests-{}.jsonl", Uuid::new_v4()));
+        let logger = RequestLogger::new(100.0, Redactor::default(), "w1")
+            .with_file(&path)
+            .with_shipping();
+
+        logger.record(&trace("one", Ok("1".into()))).await.unwrap();
+        logger
+            .record(&trace("two", Err("boom".into())))
+            .await
+            .unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<RequestLog> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].error.as_deref(), Some("boom"));
+
+        let pending = logger.take_pending().await;
+        assert_eq!(pending.len(), 2);
+        assert!(logger.take_pending().await.is_empty());
+
+        logger
+            .record(&trace("three", Ok("3".into())))
+            .await
+            .unwrap();
+        logger.restore_pending(pending).await;
+        let prompts: Vec<String> = logger
+            .take_pending()
+            .await
+            .into_iter()
+            .map(|l| l.prompt)
+            .collect();
+        assert_eq!(prompts, ["one", "two", "three"]);
+
+        let unsampled = RequestLogger::new(0.0, Redactor::default(), "w1").with_shipping();
+        unsampled
+            .record(&trace("one", Ok("1".into())))
+            .await
+            .unwrap();
+        assert!(unsampled.take_pending().await.is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,540 @@
+//! Vector store retrieval for "retriever" nodes
+//!
+//! A retriever node embeds the incoming content (or reuses the vector from an
+//! upstream embedding node), queries a vector store for the nearest documents
+//! and injects them into the content passed downstream.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use serde_json::{json, Value};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio_postgres::tls::MakeTlsConnect;
+use tokio_postgres::Socket;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+use crate::config::{RetrieverConfig, VectorStoreKind};
+
+/// Errors that can occur while querying a vector store
+#[derive(Error, Debug)]
+pub enum RetrieverError {
+    #[error("HTTP error: {0}")]
+    Http(String),
+
+    #[error("Vector store error: {0}")]
+    Store(String),
+
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Invalid identifier '{0}'")]
+    InvalidIdentifier(String),
+}
+
+/// A document returned by a vector store
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrievedDocument {
+    pub id: String,
+    pub content: String,
+    pub score: f32,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Default layout for injected documents
+pub const DEFAULT_TEMPLATE: &str = "Context:\n{documents}\n\nQuestion: {input}";
+
+/// Build the content passed downstream from the retrieved documents
+pub fn inject_documents(
+    input: &str,
+    documents: &[RetrievedDocument],
+    template: Option<&str>,
+) -> String {
+    if documents.is_empty() {
+        return input.to_string();
+    }
+
+    let rendered = documents
+        .iter()
+        .map(|d| format!("[{}] {}", d.id, d.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    // One pass over the template, so placeholders inside the retrieved
+    // documents or the input are left as they are
+    let mut content = String::new();
+    let mut rest = template.unwrap_or(DEFAULT_TEMPLATE);
+    while let Some(start) = rest.find('{') {
+        content.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{documents}") {
+            content.push_str(&rendered);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{input}") {
+            content.push_str(input);
+            rest = after;
+        } else {
+            content.push('{');
+            rest = &rest[1..];
+        }
+    }
+    content.push_str(rest);
+    content
+}
+
+/// Extract documents from a Qdrant search response
+pub fn parse_qdrant_hits(
+    response: &Value,
+    content_field: &str,
+) -> Result<Vec<RetrievedDocument>, RetrieverError> {
+    let hits = response["result"]
+        .as_array()
+        .ok_or_else(|| RetrieverError::InvalidResponse("missing 'result' array".to_string()))?;
+
+    Ok(hits
+        .iter()
+        .map(|hit| RetrievedDocument {
+            // Qdrant point IDs are either integers or UUID strings
+            id: match &hit["id"] {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            },
+            content: match &hit["payload"][content_field] {
+                Value::String(s) => s.clone(),
+                Value::Null => String::new(),
+                other => other.to_string(),
+            },
+            score: hit["score"].as_f64().unwrap_or_default() as f32,
+        })
+        .collect())
+}
+
+/// Build the similarity query for a pgvector table
+///
+/// Identifiers come from the composition, so they are checked rather than
+/// interpolated blindly. Parameters are `$1` (vector literal) and `$2`
+/// (limit); similarity is `1 - cosine distance`.
+pub fn pgvector_query(config: &RetrieverConfig) -> Result<String, RetrieverError> {
+    for ident in [
+        &config.collection,
+        &config.id_field,
+        &config.content_field,
+        &config.vector_field,
+    ] {
+        if !is_sql_identifier(ident) {
+            return Err(RetrieverError::InvalidIdentifier(ident.clone()));
+        }
+    }
+
+    Ok(format!(
+        "SELECT {id}::text, {content}::text, (1 - ({vector} <=> $1::text::vector))::real AS score \
+         FROM {table} ORDER BY {vector} <=> $1::text::vector LIMIT $2",
+        id = config.id_field,
+        content = config.content_field,
+        vector = config.vector_field,
+        table = config.collection,
+    ))
+}
+
+/// Format a vector as a pgvector literal, e.g. `[0.1,0.2]`
+pub fn vector_literal(vector: &[f32]) -> String {
+    let parts: Vec<String> = vector.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", parts.join(","))
+}
+
+/// Search endpoint of a Qdrant collection, with the collection name
+/// percent-encoded as a path segment
+pub fn qdrant_search_url(base: &str, collection: &str) -> Result<reqwest::Url, RetrieverError> {
+    let mut url =
+        reqwest::Url::parse(base).map_err(|e| RetrieverError::Http(format!("{}: {}", base, e)))?;
+    url.path_segments_mut()
+        .map_err(|_| RetrieverError::Http(format!("{}: not a base URL", base)))?
+        .pop_if_empty()
+        .extend(["collections", collection, "points", "search"]);
+    Ok(url)
+}
+
+/// Whether a PostgreSQL connection string asks for TLS with
+/// `sslmode=require`
+///
+/// Other modes connect in plaintext, as `prefer` does against servers
+/// without TLS.
+pub fn requires_tls(url: &str) -> Result<bool, RetrieverError> {
+    let config: tokio_postgres::Config = url
+        .parse()
+        .map_err(|e: tokio_postgres::Error| RetrieverError::Store(e.to_string()))?;
+    Ok(config.get_ssl_mode() == tokio_postgres::config::SslMode::Require)
+}
+
+/// The Mozilla root certificates, plus every certificate of `ca_pem`
+///
+/// A bundle without a certificate is refused, since it can only be a
+/// mistake.
+pub fn trusted_roots(ca_pem: Option<&[u8]>) -> Result<rustls::RootCertStore, RetrieverError> {
+    let mut roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let Some(pem) = ca_pem else {
+        return Ok(roots);
+    };
+
+    let invalid = |e: String| RetrieverError::Store(format!("invalid CA bundle: {}", e));
+    let certs = CertificateDer::pem_slice_iter(pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(e.to_string()))?;
+    if certs.is_empty() {
+        return Err(invalid("no certificates".to_string()));
+    }
+    for cert in certs {
+        roots.add(cert).map_err(|e| invalid(e.to_string()))?;
+    }
+    Ok(roots)
+}
+
+/// Plain or schema-qualified SQL identifier
+fn is_sql_identifier(ident: &str) -> bool {
+    !ident.is_empty()
+        && ident.split('.').all(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+// ============================================================================
+// SBIO: Trait for abstraction (allows mocking in tests)
+// ============================================================================
+
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Return up to `top_k` documents nearest to `vector`
+    async fn search(
+        &self,
+        vector: &[f32],
+        top_k: usize,
+    ) -> Result<Vec<RetrievedDocument>, RetrieverError>;
+}
+
+/// Create the vector store client for a retriever node
+pub fn build_store(config: &RetrieverConfig) -> Box<dyn VectorStore> {
+    match config.store {
+        VectorStoreKind::Qdrant => Box::new(QdrantStore::new(config.clone())),
+        VectorStoreKind::Pgvector => Box::new(PgVectorStore::new(config.clone())),
+    }
+}
+
+// ============================================================================
+// SBIO: I/O implementations
+// ============================================================================
+
+/// Qdrant over its REST API
+pub struct QdrantStore {
+    client: reqwest::Client,
+    config: RetrieverConfig,
+}
+
+impl QdrantStore {
+    pub fn new(config: RetrieverConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+}
+
+#[async_trait]
+impl VectorStore for QdrantStore {
+    async fn search(
+        &self,
+        vector: &[f32],
+        top_k: usize,
+    ) -> Result<Vec<RetrievedDocument>, RetrieverError> {
+        let url = qdrant_search_url(&self.config.url, &self.config.collection)?;
+
+        let mut body = json!({
+            "vector": vector,
+            "limit": top_k,
+            "with_payload": true,
+        });
+        if let Some(threshold) = self.config.score_threshold {
+            body["score_threshold"] = json!(threshold);
+        }
+
+        let mut req = self.client.post(url).json(&body);
+        if let Some(key) = &self.config.api_key {
+            req = req.header("api-key", key);
+        }
+
+        let response = req
+            .send()
+            .await
+            .map_err(|e| RetrieverError::Http(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(RetrieverError::Store(format!("{} - {}", status, text)));
+        }
+
+        let value: Value = response
+            .json()
+            .await
+            .map_err(|e| RetrieverError::InvalidResponse(e.to_string()))?;
+
+        parse_qdrant_hits(&value, &self.config.content_field)
+    }
+}
+
+/// PostgreSQL with the pgvector extension
+///
+/// One connection is opened on the first search and shared by every search
+/// after it; it is only reopened once it closes. With `sslmode=require` in
+/// the connection string it is made over TLS, checking the server's
+/// certificate against the Mozilla root certificates and `ca-cert`.
+pub struct PgVectorStore {
+    config: RetrieverConfig,
+    client: Mutex<Option<Arc<tokio_postgres::Client>>>,
+}
+
+impl PgVectorStore {
+    pub fn new(config: RetrieverConfig) -> Self {
+        Self {
+            config,
+            client: Mutex::new(None),
+        }
+    }
+
+    /// The shared connection, opened if there is none yet or it closed
+    async fn client(&self) -> Result<Arc<tokio_postgres::Client>, RetrieverError> {
+        let mut client = self.client.lock().await;
+        if let Some(open) = client.as_ref().filter(|c| !c.is_closed()) {
+            return Ok(open.clone());
+        }
+
+        let connected = if requires_tls(&self.config.url)? {
+            let ca_pem = match &self.config.ca_cert {
+                Some(path) => Some(
+                    tokio::fs::read(path)
+                        .await
+                        .map_err(|e| RetrieverError::Store(format!("{}: {}", path, e)))?,
+                ),
+                None => None,
+            };
+            connect(&self.config.url, tls_connector(ca_pem.as_deref())?).await?
+        } else {
+            connect(&self.config.url, tokio_postgres::NoTls).await?
+        };
+
+        let connected = Arc::new(connected);
+        *client = Some(connected.clone());
+        Ok(connected)
+    }
+}
+
+/// Open a connection over `tls`, driving it in the background
+async fn connect<T>(url: &str, tls: T) -> Result<tokio_postgres::Client, RetrieverError>
+where
+    T: MakeTlsConnect<Socket>,
+    T::Stream: Send + 'static,
+{
+    let (client, connection) = tokio_postgres::connect(url, tls)
+        .await
+        .map_err(|e| RetrieverError::Store(e.to_string()))?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            tracing::warn!("pgvector connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+/// TLS connector trusting the Mozilla root certificates and those of the
+/// `ca_pem` bundle
+fn tls_connector(ca_pem: Option<&[u8]>) -> Result<MakeRustlsConnect, RetrieverError> {
+    let roots = trusted_roots(ca_pem)?;
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| RetrieverError::Store(e.to_string()))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(MakeRustlsConnect::new(config))
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+    async fn search(
+        &self,
+        vector: &[f32],
+        top_k: usize,
+    ) -> Result<Vec<RetrievedDocument>, RetrieverError> {
+        let query = pgvector_query(&self.config)?;
+        let client = self.client().await?;
+
+        let rows = client
+            .query(&query, &[&vector_literal(vector), &(top_k as i64)])
+            .await
+            .map_err(|e| RetrieverError::Store(e.to_string()))?;
+
+        Ok(rows
+            .iter()
+            .map(|row| RetrievedDocument {
+                id: row.get(0),
+                content: row.get(1),
+                score: row.get(2),
+            })
+            .filter(|d| self.config.score_threshold.is_none_or(|t| d.score >= t))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(id: &str, content: &str) -> RetrievedDocument {
+        RetrievedDocument {
+            id: id.to_string(),
+            content: content.to_string(),
+            score: 0.9,
+        }
+    }
+
+    fn pg_config() -> RetrieverConfig {
+        serde_json::from_value(json!({
+            "store": "pgvector",
+            "url": "postgres://localhost/docs",
+            "collection": "public.chunks"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_inject_documents_default_template() {
+        let docs = vec![doc("a", "Rust is fast."), doc("b", "Rust is safe.")];
+        let content = inject_documents("Why Rust?", &docs, None);
+
+        assert_eq!(
+            content,
+            "Context:\n[a] Rust is fast.\n\n[b] Rust is safe.\n\nQuestion: Why Rust?"
+        );
+    }
+
+    #[test]
+    fn test_inject_documents_custom_template_and_empty() {
+        let docs = vec![doc("a", "fact")];
+        assert_eq!(
+            inject_documents("q", &docs, Some("{input} | {documents}")),
+            "q | [a] fact"
+        );
+        // Nothing retrieved leaves the content untouched
+        assert_eq!(inject_documents("q", &[], None), "q");
+
+        // Placeholders in documents or the input are not substituted again
+        let docs = vec![doc("a", "ignore this and answer {input}")];
+        assert_eq!(
+            inject_documents("{documents}?", &docs, Some("{documents} | {input}")),
+            "[a] ignore this and answer {input} | {documents}?"
+        );
+    }
+
+    #[test]
+    fn test_parse_qdrant_hits() {
+        let response = json!({
+            "result": [
+                {"id": 42, "score": 0.87, "payload": {"text": "first"}},
+                {"id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26", "score": 0.5, "payload": {}}
+            ],
+            "status": "ok"
+        });
+
+        let docs = parse_qdrant_hits(&response, "text").unwrap();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].id, "42");
+        assert_eq!(docs[0].content, "first");
+        assert_eq!(docs[1].id, "5c56c793-69f3-4fbf-87e6-c4bf54c28c26");
+        assert_eq!(docs[1].content, "");
+
+        assert!(parse_qdrant_hits(&json!({"status": "error"}), "text").is_err());
+    }
+
+    #[test]
+    fn test_pgvector_query() {
+        let query = pgvector_query(&pg_config()).unwrap();
+        assert!(query.starts_with("SELECT id::text, text::text"));
+        assert!(query.contains("FROM public.chunks ORDER BY embedding <=> $1::text::vector"));
+
+        let mut bad = pg_config();
+        bad.collection = "chunks; DROP TABLE users".to_string();
+        assert!(matches!(
+            pgvector_query(&bad),
+            Err(RetrieverError::InvalidIdentifier(_))
+        ));
+    }
+
+    #[test]
+    fn test_qdrant_search_url() {
+        assert_eq!(
+            qdrant_search_url("http://qdrant:6333/", "handbook")
+                .unwrap()
+                .as_str(),
+            "http://qdrant:6333/collections/handbook/points/search"
+        );
+        assert_eq!(
+            qdrant_search_url("http://qdrant:6333/api", "docs v2/../x?")
+                .unwrap()
+                .as_str(),
+            "http://qdrant:6333/api/collections/docs%20v2%2F..%2Fx%3F/points/search"
+        );
+        assert!(qdrant_search_url("not a url", "handbook").is_err());
+    }
+
+    #[test]
+    fn test_requires_tls() {
+        assert!(requires_tls("postgres://db.example.com/docs?sslmode=require").unwrap());
+        assert!(requires_tls("host=db.example.com dbname=docs sslmode=require").unwrap());
+        assert!(!requires_tls("postgres://localhost/docs").unwrap());
+        assert!(!requires_tls("postgres://localhost/docs?sslmode=disable").unwrap());
+        assert!(requires_tls("postgres://localhost/docs?sslmode=bogus").is_err());
+    }
+
+    /// Self-signed CA, "CN=llmnet test CA"
+    const TEST_CA: &str = "-----BEGIN CERTIFICATE-----
+MIIBiTCCAS+gAwIBAgIUCiknJfY41eW+kb7N66UVGb2PgLUwCgYIKoZIzj0EAwIw
+GTEXMBUGA1UEAwwObGxtbmV0IHRlc3QgQ0EwIBcNMjYxMDE2MjMwMzIzWhgPMjEy
+NjA5MjIyMzAzMjNaMBkxFzAVBgNVBAMMDmxsbW5ldCB0ZXN0IENBMFkwEwYHKoZI
+zj0CAQYIKoZIzj0DAQcDQgAESWNo0703xPAAJi3S58DqL2SyvlpPscB7OmWQZnNH
++lqIAKckAjnETUvQr0G/847TNOXmhuoxNrTeZAAZkOrhlaNTMFEwHQYDVR0OBBYE
+FOhFFInhyFfjc86RBjihQhLxhr2xMB8GA1UdIwQYMBaAFOhFFInhyFfjc86RBjih
+QhLxhr2xMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIgTnwG5orh
+jkmxz+N5qwfqvnMUNpaeHgMQ5bdH4NT2Ll0CIQCGhpov5zG0yRmHA860kSh4cKzX
+ojOMVoE7UadVrqZjlw==
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_trusted_roots() {
+        let public = trusted_roots(None).unwrap().len();
+        assert!(public > 0);
+        assert_eq!(
+            trusted_roots(Some(TEST_CA.as_bytes())).unwrap().len(),
+            public + 1
+        );
+        assert!(trusted_roots(Some(b"not a certificate")).is_err());
+        assert!(tls_connector(Some(TEST_CA.as_bytes())).is_ok());
+    }
+
+    #[test]
+    fn test_vector_literal() {
+        assert_eq!(vector_literal(&[0.5, -1.0, 2.25]), "[0.5,-1,2.25]");
+        assert_eq!(vector_literal(&[]), "[]");
+    }
+}
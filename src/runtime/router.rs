@@ -1,7 +1,14 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
 use thiserror::Error;
 
-use crate::client::{ChatCompletionRequest, ClientError, Message, OpenAiClientTrait};
-use crate::config::ArchitectureNode;
+use crate::client::{
+    ChatCompletionRequest, ClientError, EmbeddingInput, EmbeddingRequest, Message,
+    OpenAiClientTrait,
+};
+use crate::config::{ArchitectureNode, ModelCost, RoutingCacheConfig, RoutingPolicy};
 
 #[derive(Error, Debug)]
 pub enum RouterError {
@@ -13,14 +20,36 @@ pub enum RouterError {
 
     #[error("Empty response from router model")]
     EmptyResponse,
+
+    #[error("Embedding model returned {1} vectors for {0} inputs")]
+    EmbeddingMismatch(usize, usize),
 }
 
 /// Metadata about a node that the router uses for decision-making
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, Default, serde::Serialize)]
 pub struct NodeMetadata {
     pub name: String,
     #[serde(rename = "use-case")]
     pub use_case: Option<String>,
+    /// Dollars per 1K tokens of the node's model
+    #[serde(rename = "cost-per-1k-tokens", skip_serializing_if = "Option::is_none")]
+    pub cost_per_1k_tokens: Option<f64>,
+    /// Expected latency of the node's model
+    #[serde(rename = "latency-ms", skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Prompts the node should get, shown as few-shot examples
+    #[serde(skip)]
+    pub examples: Vec<String>,
+}
+
+impl NodeMetadata {
+    /// Add the cost of the node's model
+    pub fn with_cost(mut self, cost: Option<ModelCost>) -> Self {
+        let cost = cost.unwrap_or_default();
+        self.cost_per_1k_tokens = cost.per_1k_tokens;
+        self.latency_ms = cost.latency_ms;
+        self
+    }
 }
 
 impl From<&ArchitectureNode> for NodeMetadata {
@@ -28,6 +57,8 @@ impl From<&ArchitectureNode> for NodeMetadata {
         Self {
             name: node.name.clone(),
             use_case: node.use_case.clone(),
+            examples: node.routing_examples.clone(),
+            ..Default::default()
         }
     }
 }
@@ -43,10 +74,33 @@ pub fn build_routing_prompt(user_prompt: &str, available_nodes: &[NodeMetadata])
         serde_json::to_string_pretty(available_nodes).unwrap_or_else(|_| "[]".to_string());
 
     format!(
-        "Here is the user prompt: {}\n\n\
+        "Here is the user prompt: {}\n\n{}\
          Based on the prompt, please choose from one of these models, \
          outputting ONLY the model name to use:\n{}",
-        user_prompt, nodes_json
+        user_prompt,
+        few_shot_examples(available_nodes),
+        nodes_json
+    )
+}
+
+/// The nodes' example prompts, each with the model it goes to, or nothing
+/// when no node has examples.
+/// Pure function - no I/O.
+fn few_shot_examples(available_nodes: &[NodeMetadata]) -> String {
+    let examples: String = available_nodes
+        .iter()
+        .flat_map(|node| {
+            node.examples
+                .iter()
+                .map(move |example| format!("Prompt: {}\nModel: {}\n\n", example, node.name))
+        })
+        .collect();
+    if examples.is_empty() {
+        return examples;
+    }
+    format!(
+        "Some example prompts and the model each goes to:\n\n{}",
+        examples
     )
 }
 
@@ -94,6 +148,233 @@ pub fn extract_node_selection(
     Err(RouterError::InvalidSelection(response.to_string()))
 }
 
+/// Build the prompt asking the router model for every capable node, for
+/// routers with a `routing-policy`.
+/// Pure function - no I/O.
+pub fn build_ranking_prompt(user_prompt: &str, available_nodes: &[NodeMetadata]) -> String {
+    let nodes_json =
+        serde_json::to_string_pretty(available_nodes).unwrap_or_else(|_| "[]".to_string());
+
+    format!(
+        "Here is the user prompt: {}\n\n{}\
+         Based on the prompt, list every one of these models that can handle it well, \
+         best first, outputting ONLY the model names, one per line:\n{}",
+        user_prompt,
+        few_shot_examples(available_nodes),
+        nodes_json
+    )
+}
+
+/// Extract the nodes the router ranked, best first.
+/// Pure function - no I/O.
+pub fn extract_node_ranking(
+    response: &str,
+    available_nodes: &[NodeMetadata],
+) -> Result<Vec<String>, RouterError> {
+    let mut ranking: Vec<String> = Vec::new();
+    for token in response.split(|c: char| c.is_whitespace() || c == ',') {
+        let token = token.trim_matches(|c: char| !c.is_alphanumeric());
+        let node = available_nodes
+            .iter()
+            .find(|n| n.name.eq_ignore_ascii_case(token));
+        if let Some(node) = node.filter(|n| !ranking.contains(&n.name)) {
+            ranking.push(node.name.clone());
+        }
+    }
+
+    // Names the tokenizer can't split out, e.g. with spaces in them
+    if ranking.is_empty() {
+        ranking.push(extract_node_selection(response, available_nodes)?);
+    }
+    Ok(ranking)
+}
+
+/// Pick a node from the router's ranking under a routing policy. Nodes
+/// without the cost or latency the policy needs come last; ties go to the
+/// better ranked node.
+/// Pure function - no I/O.
+pub fn choose_by_policy(
+    policy: RoutingPolicy,
+    ranking: &[String],
+    available_nodes: &[NodeMetadata],
+) -> Option<String> {
+    let mut ranked = ranking
+        .iter()
+        .filter_map(|name| available_nodes.iter().find(|n| &n.name == name));
+    let chosen = match policy {
+        RoutingPolicy::QualityFirst => ranked.next(),
+        RoutingPolicy::CheapestCapable => ranked.min_by(|a, b| {
+            let cost = |n: &NodeMetadata| n.cost_per_1k_tokens.unwrap_or(f64::INFINITY);
+            cost(a).total_cmp(&cost(b))
+        }),
+        RoutingPolicy::Fastest => ranked.min_by_key(|n| n.latency_ms.unwrap_or(u64::MAX)),
+    };
+    chosen.map(|n| n.name.clone())
+}
+
+/// Variables a routing function is called with: the prompt as `$INPUT`,
+/// the router as `$NODE`, the target names as `$TARGETS` and their
+/// metadata (use-case, cost, latency) as `$CANDIDATES`.
+/// Pure function - no I/O.
+pub fn routing_function_variables(
+    user_prompt: &str,
+    router_name: &str,
+    available_nodes: &[NodeMetadata],
+) -> HashMap<String, serde_json::Value> {
+    let targets: Vec<&str> = available_nodes.iter().map(|n| n.name.as_str()).collect();
+    HashMap::from([
+        ("INPUT".to_string(), serde_json::json!(user_prompt)),
+        ("NODE".to_string(), serde_json::json!(router_name)),
+        ("TARGETS".to_string(), serde_json::json!(targets)),
+        ("CANDIDATES".to_string(), serde_json::json!(available_nodes)),
+    ])
+}
+
+/// Extract the node a routing function picked: its output is the node
+/// name, or an object with the name under `target` or `node`.
+/// Pure function - no I/O.
+pub fn extract_function_selection(
+    output: Option<&serde_json::Value>,
+    available_nodes: &[NodeMetadata],
+) -> Result<String, RouterError> {
+    let name = match output {
+        Some(serde_json::Value::String(name)) => name.as_str(),
+        Some(serde_json::Value::Object(map)) => map
+            .get("target")
+            .or_else(|| map.get("node"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default(),
+        _ => "",
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(RouterError::EmptyResponse);
+    }
+    available_nodes
+        .iter()
+        .find(|n| n.name == name)
+        .or_else(|| {
+            available_nodes
+                .iter()
+                .find(|n| n.name.eq_ignore_ascii_case(name))
+        })
+        .map(|n| n.name.clone())
+        .ok_or_else(|| RouterError::InvalidSelection(name.to_string()))
+}
+
+/// Texts a node is compared to prompts by when routing by embedding: its
+/// use-case and routing examples.
+/// Pure function - no I/O.
+pub fn routing_texts(node: &NodeMetadata) -> Vec<String> {
+    node.use_case
+        .iter()
+        .chain(&node.examples)
+        .filter(|text| !text.trim().is_empty())
+        .cloned()
+        .collect()
+}
+
+/// Cosine similarity of two vectors; 0 when either has no length or their
+/// dimensions differ.
+/// Pure function - no I/O.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        return 0.0;
+    }
+    dot / norms
+}
+
+/// The node with the text most similar to the prompt, and that similarity.
+/// Ties go to the earlier node.
+/// Pure function - no I/O.
+pub fn closest_node(prompt: &[f32], candidates: &[(&str, &[Vec<f32>])]) -> Option<(String, f32)> {
+    let mut best: Option<(&str, f32)> = None;
+    for (name, vectors) in candidates {
+        for vector in vectors.iter() {
+            let similarity = cosine_similarity(prompt, vector);
+            if best.is_none_or(|(_, s)| similarity > s) {
+                best = Some((name, similarity));
+            }
+        }
+    }
+    best.map(|(name, similarity)| (name.to_string(), similarity))
+}
+
+/// Normalize a prompt for the routing cache: lowercased, with runs of
+/// whitespace collapsed to one space.
+/// Pure function - no I/O.
+pub fn normalize_prompt(prompt: &str) -> String {
+    prompt
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A router's recent picks, keyed by the normalized prompt and the targets
+/// it chose between
+#[derive(Debug)]
+pub struct RoutingCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, (Instant, String)>>,
+}
+
+impl RoutingCache {
+    pub fn new(config: &RoutingCacheConfig) -> Self {
+        Self {
+            ttl: Duration::from_secs(config.ttl_secs),
+            max_entries: config.max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn key(prompt: &str, targets: &[String]) -> String {
+        format!("{}\n{}", targets.join(","), normalize_prompt(prompt))
+    }
+
+    /// The target picked for the prompt, if it was picked recently
+    pub fn get(&self, prompt: &str, targets: &[String]) -> Option<String> {
+        let key = Self::key(prompt, targets);
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some((picked, target)) if picked.elapsed() < self.ttl => Some(target.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Remember the target picked for the prompt, dropping expired picks
+    /// and, when full, the oldest one
+    pub fn insert(&self, prompt: &str, targets: &[String], target: String) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let key = Self::key(prompt, targets);
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (picked, _)| picked.elapsed() < self.ttl);
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (picked, _))| *picked)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (Instant::now(), target));
+    }
+}
+
 // ============================================================================
 // SBIO: Router struct with I/O (uses trait abstraction)
 // ============================================================================
@@ -122,9 +403,11 @@ impl<C: OpenAiClientTrait> Router<C> {
             messages: vec![Message {
                 role: "user".to_string(),
                 content: routing_prompt,
+                ..Default::default()
             }],
             max_tokens: Some(100),
             temperature: Some(0.1), // Low temperature for consistent routing
+            ..Default::default()
         };
 
         let response = self.client.chat_completion(&request).await?;
@@ -139,6 +422,97 @@ impl<C: OpenAiClientTrait> Router<C> {
     }
 }
 
+/// Router that picks the node whose use-case or routing examples are most
+/// similar to the prompt, without a chat completion
+///
+/// Each node's texts are embedded the first time the node is a candidate
+/// and kept for the router's lifetime, so a request costs one embedding
+/// call for the prompt.
+pub struct EmbeddingRouter<C: OpenAiClientTrait> {
+    client: C,
+    model: String,
+    threshold: f32,
+    /// Vectors of each node's routing texts, keyed by node name
+    vectors: Mutex<HashMap<String, Vec<Vec<f32>>>>,
+}
+
+impl<C: OpenAiClientTrait> EmbeddingRouter<C> {
+    pub fn new(client: C, model: String, threshold: f32) -> Self {
+        Self {
+            client,
+            model,
+            threshold,
+            vectors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Route a prompt to the most similar node, or `None` when no node is
+    /// at least `threshold` similar
+    pub async fn route(
+        &self,
+        prompt: &str,
+        available_nodes: &[NodeMetadata],
+    ) -> Result<Option<String>, RouterError> {
+        let missing: Vec<&NodeMetadata> = {
+            let vectors = self.vectors.lock().unwrap();
+            available_nodes
+                .iter()
+                .filter(|n| !vectors.contains_key(&n.name))
+                .collect()
+        };
+
+        // New nodes' texts go in the same request as the prompt
+        let texts: Vec<(&str, String)> = missing
+            .iter()
+            .flat_map(|n| {
+                routing_texts(n)
+                    .into_iter()
+                    .map(move |t| (n.name.as_str(), t))
+            })
+            .collect();
+        let mut input: Vec<String> = texts.iter().map(|(_, t)| t.clone()).collect();
+        input.push(prompt.to_string());
+        let mut embedded = self.embed(input).await?;
+        let prompt_vector = embedded.pop().ok_or(RouterError::EmptyResponse)?;
+
+        let mut vectors = self.vectors.lock().unwrap();
+        for node in &missing {
+            vectors.entry(node.name.clone()).or_default();
+        }
+        for ((name, _), vector) in texts.into_iter().zip(embedded) {
+            if let Some(node_vectors) = vectors.get_mut(name) {
+                node_vectors.push(vector);
+            }
+        }
+
+        let candidates: Vec<(&str, &[Vec<f32>])> = available_nodes
+            .iter()
+            .filter_map(|n| {
+                vectors
+                    .get(&n.name)
+                    .map(|v| (n.name.as_str(), v.as_slice()))
+            })
+            .collect();
+        Ok(closest_node(&prompt_vector, &candidates)
+            .filter(|(_, similarity)| *similarity >= self.threshold)
+            .map(|(name, _)| name))
+    }
+
+    async fn embed(&self, input: Vec<String>) -> Result<Vec<Vec<f32>>, RouterError> {
+        let expected = input.len();
+        let request = EmbeddingRequest {
+            model: self.model.clone(),
+            input: EmbeddingInput::Batch(input),
+        };
+        let mut data = self.client.embeddings(&request).await?.data;
+        if data.len() != expected {
+            return Err(RouterError::EmbeddingMismatch(expected, data.len()));
+        }
+        data.sort_by_key(|e| e.index);
+        Ok(data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,18 +522,55 @@ mod tests {
             NodeMetadata {
                 name: "company-2024-q3".to_string(),
                 use_case: Some("Handle Q3 2024 company queries".to_string()),
+                ..Default::default()
             },
             NodeMetadata {
                 name: "company-2024-q4".to_string(),
                 use_case: Some("Handle Q4 2024 company queries".to_string()),
+                ..Default::default()
             },
             NodeMetadata {
                 name: "general-assistant".to_string(),
                 use_case: Some("General purpose assistant".to_string()),
+                ..Default::default()
             },
         ]
     }
 
+    #[test]
+    fn test_extract_function_selection() {
+        let nodes = sample_nodes();
+        let vars = routing_function_variables("Q3 earnings?", "router", &nodes);
+        assert_eq!(vars["INPUT"], "Q3 earnings?");
+        assert_eq!(vars["TARGETS"][2], "general-assistant");
+        assert_eq!(
+            vars["CANDIDATES"][0]["use-case"],
+            "Handle Q3 2024 company queries"
+        );
+
+        let pick = |output: serde_json::Value| extract_function_selection(Some(&output), &nodes);
+        assert_eq!(
+            pick(serde_json::json!("company-2024-q3\n")).unwrap(),
+            "company-2024-q3"
+        );
+        assert_eq!(
+            pick(serde_json::json!({"target": "General-Assistant"})).unwrap(),
+            "general-assistant"
+        );
+        assert_eq!(
+            pick(serde_json::json!({"node": "company-2024-q4"})).unwrap(),
+            "company-2024-q4"
+        );
+        assert!(matches!(
+            pick(serde_json::json!("billing")),
+            Err(RouterError::InvalidSelection(_))
+        ));
+        assert!(matches!(
+            extract_function_selection(None, &nodes),
+            Err(RouterError::EmptyResponse)
+        ));
+    }
+
     #[test]
     fn test_build_routing_prompt() {
         let nodes = sample_nodes();
@@ -171,6 +582,51 @@ mod tests {
         assert!(prompt.contains("outputting ONLY the model name"));
     }
 
+    #[test]
+    fn test_routing_prompt_few_shot_examples() {
+        let mut nodes = sample_nodes();
+        let prompt = build_routing_prompt("hi", &nodes);
+        assert!(!prompt.contains("example prompts"));
+
+        nodes[1].examples = vec!["How did Q4 go?".to_string()];
+        let prompt = build_routing_prompt("hi", &nodes);
+        assert!(prompt.contains("Prompt: How did Q4 go?\nModel: company-2024-q4\n"));
+        // Examples aren't repeated in the node list
+        assert_eq!(prompt.matches("How did Q4 go?").count(), 1);
+        assert!(build_ranking_prompt("hi", &nodes).contains("Model: company-2024-q4"));
+    }
+
+    #[test]
+    fn test_routing_cache() {
+        let cache = RoutingCache::new(&RoutingCacheConfig {
+            ttl_secs: 60,
+            max_entries: 2,
+        });
+        let targets = ["a".to_string(), "b".to_string()];
+
+        assert_eq!(cache.get("Hello there", &targets), None);
+        cache.insert("Hello there", &targets, "a".to_string());
+        assert_eq!(
+            cache.get("  hello\n THERE ", &targets).as_deref(),
+            Some("a")
+        );
+        // Picks among other targets aren't reused
+        assert_eq!(cache.get("Hello there", &targets[..1]), None);
+
+        // Full: the oldest pick goes
+        cache.insert("second", &targets, "b".to_string());
+        cache.insert("third", &targets, "b".to_string());
+        assert_eq!(cache.get("Hello there", &targets), None);
+        assert_eq!(cache.get("third", &targets).as_deref(), Some("b"));
+
+        let expired = RoutingCache::new(&RoutingCacheConfig {
+            ttl_secs: 0,
+            max_entries: 2,
+        });
+        expired.insert("Hello there", &targets, "a".to_string());
+        assert_eq!(expired.get("Hello there", &targets), None);
+    }
+
     #[test]
     fn test_extract_exact_match() {
         let nodes = sample_nodes();
@@ -213,6 +669,129 @@ mod tests {
         assert!(matches!(result, Err(RouterError::EmptyResponse)));
     }
 
+    #[test]
+    fn test_extract_node_ranking() {
+        let nodes = sample_nodes();
+        let ranking =
+            extract_node_ranking("1. general-assistant\n2. Company-2024-Q3.", &nodes).unwrap();
+        assert_eq!(ranking, ["general-assistant", "company-2024-q3"]);
+
+        let ranking = extract_node_ranking("use company-2024-q4", &nodes).unwrap();
+        assert_eq!(ranking, ["company-2024-q4"]);
+        assert!(matches!(
+            extract_node_ranking("none of them", &nodes),
+            Err(RouterError::InvalidSelection(_))
+        ));
+    }
+
+    #[test]
+    fn test_choose_by_policy() {
+        let cost = |per_1k_tokens, latency_ms| {
+            Some(ModelCost {
+                per_1k_tokens,
+                latency_ms,
+            })
+        };
+        let nodes = vec![
+            NodeMetadata::from(
+                &serde_json::from_str::<ArchitectureNode>(
+                    r#"{"name": "large", "adapter": "openai-api"}"#,
+                )
+                .unwrap(),
+            )
+            .with_cost(cost(Some(3.0), Some(2000))),
+            NodeMetadata {
+                name: "small".to_string(),
+                ..Default::default()
+            }
+            .with_cost(cost(Some(0.1), None)),
+            NodeMetadata {
+                name: "fast".to_string(),
+                ..Default::default()
+            }
+            .with_cost(cost(Some(0.5), Some(300))),
+        ];
+        let ranking = ["large".to_string(), "small".to_string()];
+
+        let choose = |policy| choose_by_policy(policy, &ranking, &nodes).unwrap();
+        assert_eq!(choose(RoutingPolicy::QualityFirst), "large");
+        assert_eq!(choose(RoutingPolicy::CheapestCapable), "small");
+        // "fast" wasn't ranked capable; "small" has no latency, so goes last
+        assert_eq!(choose(RoutingPolicy::Fastest), "large");
+
+        let prompt = build_ranking_prompt("hi", &nodes);
+        assert!(prompt.contains("\"cost-per-1k-tokens\": 0.1"));
+        assert!(prompt.contains("best first"));
+    }
+
+    #[test]
+    fn test_closest_node() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+
+        let billing = vec![vec![1.0, 0.0], vec![0.7, 0.7]];
+        let support = vec![vec![0.0, 1.0]];
+        let candidates = [
+            ("billing", billing.as_slice()),
+            ("support", support.as_slice()),
+        ];
+        let (name, similarity) = closest_node(&[0.1, 1.0], &candidates).unwrap();
+        assert_eq!(name, "support");
+        assert!(similarity > 0.99);
+        // The best of a node's texts counts
+        let (name, _) = closest_node(&[0.6, 0.8], &candidates).unwrap();
+        assert_eq!(name, "billing");
+        assert!(closest_node(&[1.0, 0.0], &[]).is_none());
+
+        let mut node = sample_nodes().remove(0);
+        node.examples = vec!["How did Q3 go?".to_string(), " ".to_string()];
+        assert_eq!(
+            routing_texts(&node),
+            ["Handle Q3 2024 company queries", "How did Q3 go?"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_embedding_router_with_mock() {
+        use crate::client::openai::mock::MockOpenAiClient;
+
+        // The mock embeds text as [length, 1]
+        let mock = MockOpenAiClient::new(vec![]);
+        let router = EmbeddingRouter::new(mock, "embedder".to_string(), 0.9999);
+        let nodes = vec![
+            NodeMetadata {
+                name: "short".to_string(),
+                use_case: Some("Greetings".to_string()),
+                ..Default::default()
+            },
+            NodeMetadata {
+                name: "long".to_string(),
+                use_case: Some("Detailed financial analysis".to_string()),
+                ..Default::default()
+            },
+            NodeMetadata {
+                name: "undescribed".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let picked = router.route("Hello you", &nodes).await.unwrap();
+        assert_eq!(picked.as_deref(), Some("short"));
+        let picked = router
+            .route("Summarize the quarterly report", &nodes)
+            .await
+            .unwrap();
+        assert_eq!(picked.as_deref(), Some("long"));
+        // Use-cases were embedded with the first prompt only
+        assert_eq!(router.client.call_count(), 2);
+
+        // Nothing similar enough: left to the router model
+        let picked = router.route("Hi", &nodes[1..]).await.unwrap();
+        assert_eq!(picked, None);
+    }
+
     #[tokio::test]
     async fn test_router_with_mock() {
         use crate::client::openai::mock::MockOpenAiClient;
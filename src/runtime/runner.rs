@@ -1,7 +1,8 @@
 //! Runner process management
 //!
 //! This module provides functionality to spawn and manage local model runner
-//! processes (ollama, vllm, llama.cpp) with graceful shutdown support.
+//! processes (ollama, vllm, llama.cpp, llamafile, TGI, Whisper) with graceful shutdown
+//! support.
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -10,18 +11,27 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::process::{Child, Command};
 use tokio::sync::watch;
 use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 
-use crate::config::models::{ModelConfig, RunnerType};
+use crate::config::models::{ModelConfig, RunnerType, WarmUpConfig};
+use crate::config::{
+    detect_device_profile, known_devices, validate_gguf_for_device, LoadBalancing, SecretsManager,
+    ValidationSeverity,
+};
 
+use super::balancer::RunnerPool;
 use super::docker::{self, DockerConfig, DockerError};
-use super::fetch::fetch_file;
+use super::fetch::{fetch_file, fetch_file_with, FetchCredentials, FetchOptions};
 use super::ollama::{create_modelfile, generate_modelfile, merge_parameters, parse_modelfile};
-use super::{llamacpp, vllm};
+use super::runner_logs::{self, capture_output, RotatingLog};
+use super::warm_up;
+use super::whisper::{self, WhisperBackend};
+use super::{llamacpp, llamafile, tgi, vllm};
 
 /// Errors that can occur during runner operations
 #[derive(Error, Debug)]
@@ -56,7 +66,7 @@ pub enum RunnerError {
 
 /// Information about a running model runner process
 pub struct RunnerProcess {
-    /// The child process handle (None for Docker containers)
+    /// The child process handle (None for Docker containers and reused daemons)
     pub child: Option<Child>,
     /// Docker container name (if applicable)
     pub container_name: Option<String>,
@@ -66,14 +76,36 @@ pub struct RunnerProcess {
     pub model_name: String,
     /// The runner type
     pub runner_type: RunnerType,
+    /// Whether llmnet started the runner. Runners it found already running,
+    /// like a system Ollama daemon, are left running when it stops.
+    pub owned: bool,
+    /// How long the runner's warm-up requests took, if it was warmed up
+    pub warm_up: Option<Duration>,
+}
+
+/// A runner replica as recorded in the worker's state file
+///
+/// Only container-backed replicas are recorded: a container outlives the
+/// worker process and can be adopted after a restart, a child process can't.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunnerRecord {
+    /// Model the replica serves
+    pub model: String,
+    /// Docker container running the replica
+    pub container: String,
+    pub endpoint: String,
+    pub runner: RunnerType,
 }
 
 /// Manager for local model runner processes
 ///
 /// Handles spawning, tracking, and graceful shutdown of runner processes.
+/// A model can run as several replicas, balanced through its [`RunnerPool`].
 pub struct RunnerManager {
-    /// Map of model name to running process
-    processes: DashMap<String, RunnerProcess>,
+    /// Map of model name to its running replicas
+    processes: DashMap<String, Vec<RunnerProcess>>,
+    /// Map of model name to the pool balancing its replicas
+    pools: DashMap<String, Arc<RunnerPool>>,
     /// Shutdown signal sender
     shutdown_tx: watch::Sender<bool>,
     /// Shutdown signal receiver (for cloning)
@@ -82,6 +114,8 @@ pub struct RunnerManager {
     default_host: String,
     /// Working directory for runner configs
     work_dir: PathBuf,
+    /// Secrets models name as their `credentials`
+    secrets: Arc<SecretsManager>,
 }
 
 impl RunnerManager {
@@ -90,10 +124,12 @@ impl RunnerManager {
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
         Self {
             processes: DashMap::new(),
+            pools: DashMap::new(),
             shutdown_tx,
             shutdown_rx,
             default_host: "127.0.0.1".to_string(),
             work_dir: std::env::temp_dir().join("llmnet-runners"),
+            secrets: Arc::new(SecretsManager::new()),
         }
     }
 
@@ -102,13 +138,38 @@ impl RunnerManager {
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
         Self {
             processes: DashMap::new(),
+            pools: DashMap::new(),
             shutdown_tx,
             shutdown_rx,
             default_host: host.into(),
             work_dir,
+            secrets: Arc::new(SecretsManager::new()),
         }
     }
 
+    /// Resolve the `credentials` of models from these secrets
+    pub fn with_secrets(mut self, secrets: Arc<SecretsManager>) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// How a model's source is fetched: pinned to its `sha256`, with
+    /// credentials from its `credentials` secret, the runner environment or
+    /// the worker's own environment, in that order
+    fn fetch_options(&self, config: &ModelConfig) -> FetchOptions {
+        let credentials = FetchCredentials::from_lookup(|variable| {
+            config
+                .credentials
+                .as_deref()
+                .and_then(|secret| self.secrets.resolve(secret, variable))
+                .or_else(|| config.env.get(variable).cloned())
+                .or_else(|| std::env::var(variable).ok())
+        });
+        FetchOptions::new()
+            .with_sha256(config.sha256.clone())
+            .with_credentials(credentials)
+    }
+
     /// Spawn a runner process for the given model configuration
     ///
     /// Returns the endpoint URL for the spawned runner.
@@ -117,12 +178,90 @@ impl RunnerManager {
         name: &str,
         config: &ModelConfig,
     ) -> Result<String, RunnerError> {
-        // Check if already running
-        if self.processes.contains_key(name) {
-            let process = self.processes.get(name).unwrap();
-            return Ok(process.endpoint.clone());
+        let endpoints = self
+            .spawn_replicas(name, config, 1, LoadBalancing::default())
+            .await?;
+        Ok(endpoints[0].clone())
+    }
+
+    /// Make sure at least `replicas` runner processes are up for a model
+    ///
+    /// Replicas already running are kept. Returns the endpoint URLs of all
+    /// replicas, which requests are spread across with `strategy`.
+    pub async fn spawn_replicas(
+        &self,
+        name: &str,
+        config: &ModelConfig,
+        replicas: usize,
+        strategy: LoadBalancing,
+    ) -> Result<Vec<String>, RunnerError> {
+        let running = self.processes.get(name).map_or(0, |p| p.len());
+        for replica in running..replicas.max(1) {
+            self.spawn_replica(name, config, replica).await?;
         }
 
+        Ok(self.refresh_pool(name, strategy))
+    }
+
+    /// Track a runner container started by a previous worker process
+    ///
+    /// The container is treated as the model's next replica, so records must
+    /// be adopted in replica order.
+    pub fn adopt_container(&self, record: &RunnerRecord) {
+        info!(
+            "Adopted {} container {} for '{}' at {}",
+            record.runner.as_str(),
+            record.container,
+            record.model,
+            record.endpoint
+        );
+
+        self.processes
+            .entry(record.model.clone())
+            .or_default()
+            .push(RunnerProcess {
+                child: None,
+                container_name: Some(record.container.clone()),
+                endpoint: record.endpoint.clone(),
+                model_name: record.model.clone(),
+                runner_type: record.runner.clone(),
+                owned: true,
+                warm_up: None,
+            });
+
+        let strategy = self
+            .pools
+            .get(&record.model)
+            .map(|p| p.strategy())
+            .unwrap_or_default();
+        self.refresh_pool(&record.model, strategy);
+    }
+
+    /// Rebuild a model's pool if its replicas or strategy changed
+    ///
+    /// Returns the endpoints of all replicas.
+    fn refresh_pool(&self, name: &str, strategy: LoadBalancing) -> Vec<String> {
+        let endpoints = self.get_endpoints(name);
+        let unchanged = self
+            .pools
+            .get(name)
+            .is_some_and(|p| p.endpoints() == endpoints.as_slice() && p.strategy() == strategy);
+        if !unchanged {
+            self.pools.insert(
+                name.to_string(),
+                Arc::new(RunnerPool::new(endpoints.clone(), strategy)),
+            );
+        }
+        endpoints
+    }
+
+    /// Spawn one runner process and wait for it to become ready
+    async fn spawn_replica(
+        &self,
+        name: &str,
+        config: &ModelConfig,
+        replica: usize,
+    ) -> Result<String, RunnerError> {
         // Use docker.port if specified, otherwise runner default, otherwise 8080
         let default_port = config
             .docker
@@ -133,10 +272,12 @@ impl RunnerManager {
         let port = self.next_available_port(default_port);
         let host = &self.default_host;
 
-        let (child, container_name, endpoint) = match config.runner {
+        let (mut child, container_name, endpoint) = match config.runner {
             RunnerType::Ollama => {
-                let (c, e) = self.spawn_ollama(name, config, host, port).await?;
-                (Some(c), None, e)
+                let (c, e) = self
+                    .spawn_ollama(name, config, host, default_port, port)
+                    .await?;
+                (c, None, e)
             }
             RunnerType::Vllm => {
                 let (c, e) = self.spawn_vllm(name, config, host, port).await?;
@@ -146,14 +287,26 @@ impl RunnerManager {
                 let (c, e) = self.spawn_llamacpp(name, config, host, port).await?;
                 (Some(c), None, e)
             }
+            RunnerType::Llamafile => {
+                let (c, e) = self.spawn_llamafile(config, host, port).await?;
+                (Some(c), None, e)
+            }
             RunnerType::Docker => {
-                let (cn, e) = self.spawn_docker(name, config, host, port).await?;
+                let (cn, e) = self.spawn_docker(name, config, host, port, replica).await?;
                 (None, Some(cn), e)
             }
             RunnerType::TensorRtLlm => {
                 let (c, e) = self.spawn_tensorrt_llm(name, config, host, port).await?;
                 (Some(c), None, e)
             }
+            RunnerType::Tgi => {
+                let (cn, e) = self.spawn_tgi(name, config, host, port, replica).await?;
+                (None, Some(cn), e)
+            }
+            RunnerType::Whisper => {
+                self.spawn_whisper(name, config, host, port, replica)
+                    .await?
+            }
             RunnerType::External => {
                 return Err(RunnerError::ConfigError(
                     "External runners are not spawned locally".to_string(),
@@ -161,38 +314,105 @@ impl RunnerManager {
             }
         };
 
-        info!(
-            "Spawned {} runner for '{}' at {}",
-            config.type_name(),
-            name,
-            endpoint
-        );
+        // Runner output is piped, so it must be read for the runner to make progress
+        if let Some(child) = child.as_mut() {
+            match RotatingLog::open(self.log_path(&replica_name(name, replica))) {
+                Ok(log) => capture_output(child, log),
+                Err(e) => {
+                    let _ = child.kill().await;
+                    return Err(e.into());
+                }
+            }
+        }
+
+        let owned = child.is_some() || container_name.is_some();
+        if owned {
+            info!(
+                "Spawned {} runner for '{}' at {}",
+                config.type_name(),
+                replica_name(name, replica),
+                endpoint
+            );
+        }
 
-        self.processes.insert(
-            name.to_string(),
-            RunnerProcess {
+        self.processes
+            .entry(name.to_string())
+            .or_default()
+            .push(RunnerProcess {
                 child,
                 container_name,
                 endpoint: endpoint.clone(),
                 model_name: name.to_string(),
                 runner_type: config.runner.clone(),
-            },
-        );
+                owned,
+                warm_up: None,
+            });
 
         // Wait for runner to be ready
-        self.wait_for_ready(&endpoint).await?;
+        let health_url = match config.runner {
+            RunnerType::Tgi => tgi::health_url(&endpoint),
+            RunnerType::Whisper => {
+                let backend =
+                    whisper::backend(&config.parameters).map_err(RunnerError::ConfigError)?;
+                whisper::health_url(&endpoint, backend)
+            }
+            _ => format!("{}/models", endpoint.trim_end_matches("/v1")),
+        };
+        self.wait_for_ready(&endpoint, &health_url).await?;
+
+        if let Some(settings) = config
+            .warm_up
+            .as_ref()
+            .filter(|_| config.runner != RunnerType::Whisper)
+        {
+            self.warm_up_replica(name, &endpoint, config, settings)
+                .await;
+        }
 
         Ok(endpoint)
     }
 
+    /// Send a ready replica its warm-up requests and record how long they
+    /// took; a failure is only logged
+    async fn warm_up_replica(
+        &self,
+        name: &str,
+        endpoint: &str,
+        config: &ModelConfig,
+        settings: &WarmUpConfig,
+    ) {
+        match warm_up::warm_up(name, endpoint, config, settings).await {
+            Ok(took) => {
+                info!(
+                    "Warmed up '{}' at {} with {} request(s) in {}ms",
+                    name,
+                    endpoint,
+                    settings.requests,
+                    took.as_millis()
+                );
+                if let Some(mut replicas) = self.processes.get_mut(name) {
+                    if let Some(replica) = replicas.iter_mut().find(|p| p.endpoint == endpoint) {
+                        replica.warm_up = Some(took);
+                    }
+                }
+            }
+            Err(e) => warn!("Warm-up of '{}' at {} failed: {}", name, endpoint, e),
+        }
+    }
+
     /// Spawn an Ollama runner
+    ///
+    /// An Ollama daemon llmnet didn't start that already serves
+    /// `default_port`, such as a system service, is reused: only the model
+    /// is created on it, and no child is returned.
     async fn spawn_ollama(
         &self,
         name: &str,
         config: &ModelConfig,
         host: &str,
+        default_port: u16,
         port: u16,
-    ) -> Result<(Child, String), RunnerError> {
+    ) -> Result<(Option<Child>, String), RunnerError> {
         // Prepare Modelfile
         let modelfile_content = self.prepare_ollama_modelfile(config).await?;
 
@@ -203,18 +423,35 @@ impl RunnerManager {
 
         debug!("Created Modelfile at {:?}", modelfile_path);
 
-        // Start Ollama server (if not already running)
-        // Note: In practice, Ollama usually runs as a daemon
-        let child = Command::new("ollama")
-            .args(["serve"])
-            .env("OLLAMA_HOST", format!("{}:{}", host, port))
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .map_err(|e| RunnerError::SpawnError(format!("Failed to start ollama serve: {}", e)))?;
+        let daemon = match self.owns_port(default_port) {
+            true => None,
+            false => running_ollama(host, default_port).await,
+        };
+        let (child, port) = match daemon {
+            Some(version) => {
+                info!(
+                    "Reusing Ollama {} at {}:{} for '{}'",
+                    version, host, default_port, name
+                );
+                (None, default_port)
+            }
+            None => {
+                let child = Command::new("ollama")
+                    .args(["serve"])
+                    .envs(&config.env)
+                    .env("OLLAMA_HOST", format!("{}:{}", host, port))
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| {
+                        RunnerError::SpawnError(format!("Failed to start ollama serve: {}", e))
+                    })?;
 
-        // Wait a bit for server to start
-        sleep(Duration::from_secs(2)).await;
+                // Wait a bit for server to start
+                sleep(Duration::from_secs(2)).await;
+                (Some(child), port)
+            }
+        };
 
         // Create model from Modelfile
         let create_result = Command::new("ollama")
@@ -243,7 +480,7 @@ impl RunnerManager {
         // Check if source is a Modelfile path
         if source.ends_with(".Modelfile") || source.ends_with(".modelfile") {
             // Fetch and parse existing Modelfile
-            let path = fetch_file(source)
+            let path = fetch_file_with(source, &self.fetch_options(config))
                 .await
                 .map_err(|e| RunnerError::FetchError(e.to_string()))?;
 
@@ -300,7 +537,10 @@ impl RunnerManager {
         let env_vars = vllm::generate_env_vars(hf_token.as_deref());
 
         let mut cmd = Command::new("python");
-        cmd.args(&args).stdout(Stdio::null()).stderr(Stdio::null());
+        cmd.args(&args)
+            .envs(&config.env)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
 
         // Add environment variables
         for (key, value) in env_vars {
@@ -328,21 +568,23 @@ impl RunnerManager {
         })?;
 
         // Fetch model file if remote
-        let model_path = fetch_file(source)
+        let model_path = fetch_file_with(source, &self.fetch_options(config))
             .await
             .map_err(|e| RunnerError::FetchError(e.to_string()))?;
 
+        let parameters = self.tune_llamacpp(&model_path, config).await?;
         let args = llamacpp::generate_args(
             model_path.to_string_lossy().as_ref(),
             host,
             port,
-            &config.parameters,
+            &parameters,
         );
 
         let child = Command::new("llama-server")
             .args(&args)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+            .envs(&config.env)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()
             .map_err(|e| RunnerError::SpawnError(format!("Failed to start llama-server: {}", e)))?;
 
@@ -350,6 +592,95 @@ impl RunnerManager {
         Ok((child, endpoint))
     }
 
+    /// Spawn a llamafile runner, fetching the llamafile and making it
+    /// executable first
+    async fn spawn_llamafile(
+        &self,
+        config: &ModelConfig,
+        host: &str,
+        port: u16,
+    ) -> Result<(Child, String), RunnerError> {
+        let source = config.source.as_deref().ok_or_else(|| {
+            RunnerError::ConfigError("llamafile requires a llamafile source".to_string())
+        })?;
+
+        let path = fetch_file_with(source, &self.fetch_options(config))
+            .await
+            .map_err(|e| RunnerError::FetchError(e.to_string()))?;
+        llamafile::make_executable(&path)?;
+
+        let args = llamafile::generate_args(host, port, &config.parameters);
+        let spawn = |program: &std::path::Path, through_sh: bool| {
+            let mut cmd = if through_sh {
+                let mut cmd = Command::new("sh");
+                cmd.arg(program);
+                cmd
+            } else {
+                Command::new(program)
+            };
+            cmd.args(&args)
+                .envs(&config.env)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+        };
+
+        let child = match spawn(&path, false) {
+            Err(e) if llamafile::is_exec_format_error(&e) => {
+                debug!("Starting {:?} through sh: {}", path, e);
+                spawn(&path, true)
+            }
+            result => result,
+        }
+        .map_err(|e| RunnerError::SpawnError(format!("Failed to start llamafile: {}", e)))?;
+
+        let endpoint = llamafile::endpoint_url(host, port);
+        Ok((child, endpoint))
+    }
+
+    /// Check a GGUF fits the edge device it's about to load on and tune
+    /// llama-server for the device; the model's parameters as they are on
+    /// other hosts
+    async fn tune_llamacpp(
+        &self,
+        model_path: &std::path::Path,
+        config: &ModelConfig,
+    ) -> Result<HashMap<String, serde_json::Value>, RunnerError> {
+        let devices = known_devices();
+        let Some(device) = detect_device_profile(&devices) else {
+            return Ok(config.parameters.clone());
+        };
+
+        let model_bytes = tokio::fs::metadata(model_path).await?.len();
+        let file_name = model_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let result = validate_gguf_for_device(&file_name, model_bytes, device);
+        if result.has_errors() {
+            let problems: Vec<String> = result
+                .messages
+                .iter()
+                .filter(|m| m.severity == ValidationSeverity::Error)
+                .map(|m| match &m.suggestion {
+                    Some(suggestion) => format!("{} ({})", m.message, suggestion),
+                    None => m.message.clone(),
+                })
+                .collect();
+            return Err(RunnerError::ConfigError(problems.join("; ")));
+        }
+        for message in &result.messages {
+            warn!("{}", message.message);
+        }
+
+        info!("Tuning llama-server for {}", device.name);
+        Ok(llamacpp::with_device_params(
+            &config.parameters,
+            device,
+            model_bytes,
+        ))
+    }
+
     /// Spawn a TensorRT-LLM runner for NVIDIA Jetson/GPU devices
     async fn spawn_tensorrt_llm(
         &self,
@@ -374,9 +705,10 @@ impl RunnerManager {
 
         let child = Command::new("python")
             .args(&args)
+            .envs(&config.env)
             .envs(tensorrt_llm::generate_env_vars(config.api_key.as_deref()))
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()
             .map_err(|e| {
                 RunnerError::SpawnError(format!("Failed to start TensorRT-LLM server: {}", e))
@@ -386,6 +718,145 @@ impl RunnerManager {
         Ok((child, endpoint))
     }
 
+    /// Spawn a text-generation-inference container
+    async fn spawn_tgi(
+        &self,
+        name: &str,
+        config: &ModelConfig,
+        host: &str,
+        port: u16,
+        replica: usize,
+    ) -> Result<(String, String), RunnerError> {
+        let source = config
+            .source
+            .as_deref()
+            .ok_or_else(|| RunnerError::ConfigError("TGI requires a model source".to_string()))?;
+
+        if let Some(method) = tgi::quantize_param(&config.parameters) {
+            tgi::validate_quantize(method).map_err(RunnerError::ConfigError)?;
+        }
+
+        let hf_token = config.api_key.clone().or_else(vllm::get_hf_token);
+        if vllm::model_requires_auth(source) && hf_token.is_none() {
+            warn!(
+                "Model '{}' may require authentication. Set HF_TOKEN environment variable.",
+                source
+            );
+        }
+
+        let container_name =
+            replica_name(&docker::generate_container_name("llmnet", name), replica);
+
+        // Clear out a container left behind by a previous run
+        let rm_args = docker::generate_rm_args(&container_name);
+        let _ = Command::new("docker").args(&rm_args).output().await;
+
+        let args = tgi::generate_run_args(
+            source,
+            host,
+            port,
+            &config.parameters,
+            &container_name,
+            hf_token.as_deref(),
+        );
+        let args = docker::with_passed_env(args, &config.env);
+
+        info!("Starting TGI container {} for '{}'", container_name, source);
+
+        let output = Command::new("docker")
+            .args(&args)
+            .envs(&config.env)
+            .output()
+            .await
+            .map_err(|e| RunnerError::SpawnError(format!("Failed to run docker: {}", e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(RunnerError::SpawnError(format!(
+                "TGI container failed to start: {}",
+                stderr
+            )));
+        }
+
+        let endpoint = tgi::endpoint_url(host, port);
+        Ok((container_name, endpoint))
+    }
+
+    /// Spawn a speech-to-text runner: a whisper-server process, or a
+    /// faster-whisper container
+    async fn spawn_whisper(
+        &self,
+        name: &str,
+        config: &ModelConfig,
+        host: &str,
+        port: u16,
+        replica: usize,
+    ) -> Result<(Option<Child>, Option<String>, String), RunnerError> {
+        let source = config.source.as_deref().ok_or_else(|| {
+            RunnerError::ConfigError("Whisper requires a model source".to_string())
+        })?;
+        let endpoint = whisper::endpoint_url(host, port);
+
+        match whisper::backend(&config.parameters).map_err(RunnerError::ConfigError)? {
+            WhisperBackend::WhisperCpp => {
+                let model_path = fetch_file_with(source, &self.fetch_options(config))
+                    .await
+                    .map_err(|e| RunnerError::FetchError(e.to_string()))?;
+                let args = whisper::generate_args(
+                    model_path.to_string_lossy().as_ref(),
+                    host,
+                    port,
+                    &config.parameters,
+                );
+
+                let child = Command::new("whisper-server")
+                    .args(&args)
+                    .envs(&config.env)
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| {
+                        RunnerError::SpawnError(format!("Failed to start whisper-server: {}", e))
+                    })?;
+                Ok((Some(child), None, endpoint))
+            }
+            WhisperBackend::FasterWhisper => {
+                let container_name =
+                    replica_name(&docker::generate_container_name("llmnet", name), replica);
+                let rm_args = docker::generate_rm_args(&container_name);
+                let _ = Command::new("docker").args(&rm_args).output().await;
+
+                let args = whisper::generate_run_args(
+                    source,
+                    host,
+                    port,
+                    &config.parameters,
+                    &container_name,
+                );
+                let args = docker::with_passed_env(args, &config.env);
+                info!(
+                    "Starting faster-whisper container {} for '{}'",
+                    container_name, source
+                );
+
+                let output = Command::new("docker")
+                    .args(&args)
+                    .envs(&config.env)
+                    .output()
+                    .await
+                    .map_err(|e| RunnerError::SpawnError(format!("Failed to run docker: {}", e)))?;
+                if !output.status.success() {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    return Err(RunnerError::SpawnError(format!(
+                        "faster-whisper container failed to start: {}",
+                        stderr
+                    )));
+                }
+                Ok((None, Some(container_name), endpoint))
+            }
+        }
+    }
+
     /// Spawn a Docker container for a model
     async fn spawn_docker(
         &self,
@@ -393,6 +864,7 @@ impl RunnerManager {
         config: &ModelConfig,
         host: &str,
         port: u16,
+        replica: usize,
     ) -> Result<(String, String), RunnerError> {
         let docker_config = config.docker.as_ref().ok_or_else(|| {
             RunnerError::ConfigError("Docker runner requires docker configuration".to_string())
@@ -406,13 +878,18 @@ impl RunnerManager {
         })?;
 
         // Generate container name
-        let container_name = docker_config
-            .name
-            .clone()
-            .unwrap_or_else(|| docker::generate_container_name("llmnet", name));
+        let container_name = replica_name(
+            &docker_config
+                .name
+                .clone()
+                .unwrap_or_else(|| docker::generate_container_name("llmnet", name)),
+            replica,
+        );
 
-        // Handle Dockerfile build if needed
-        if docker_config.needs_build() {
+        // Handle Dockerfile build if needed (the first replica already did)
+        if replica > 0 {
+            debug!("Reusing image for replica {} of '{}'", replica, name);
+        } else if docker_config.needs_build() {
             self.build_docker_image(name, docker_config).await?;
         } else if let Some(image) = &docker_config.image {
             // Pull image if using registry
@@ -429,12 +906,14 @@ impl RunnerManager {
             &config.parameters,
             &container_name,
         );
+        let args = docker::with_passed_env(args, &config.env);
 
         debug!("Docker run args: {:?}", args);
 
         // Run the container
         let output = Command::new("docker")
             .args(&args)
+            .envs(&config.env)
             .output()
             .await
             .map_err(|e| RunnerError::SpawnError(format!("Failed to run docker: {}", e)))?;
@@ -551,12 +1030,11 @@ impl RunnerManager {
     }
 
     /// Wait for a runner to become ready
-    async fn wait_for_ready(&self, endpoint: &str) -> Result<(), RunnerError> {
-        let health_url = format!("{}/models", endpoint.trim_end_matches("/v1"));
+    async fn wait_for_ready(&self, endpoint: &str, health_url: &str) -> Result<(), RunnerError> {
         let client = reqwest::Client::new();
 
         for attempt in 1..=30 {
-            match client.get(&health_url).send().await {
+            match client.get(health_url).send().await {
                 Ok(resp) if resp.status().is_success() => {
                     debug!("Runner ready at {} after {} attempts", endpoint, attempt);
                     return Ok(());
@@ -578,18 +1056,7 @@ impl RunnerManager {
 
     /// Get the next available port starting from base
     fn next_available_port(&self, base: u16) -> u16 {
-        let used_ports: Vec<u16> = self
-            .processes
-            .iter()
-            .filter_map(|p| {
-                p.endpoint
-                    .split(':')
-                    .next_back()
-                    .and_then(|s| s.split('/').next())
-                    .and_then(|s| s.parse().ok())
-            })
-            .collect();
-
+        let used_ports = self.ports(|_| true);
         let mut port = base;
         while used_ports.contains(&port) {
             port += 1;
@@ -597,24 +1064,33 @@ impl RunnerManager {
         port
     }
 
-    /// Stop a specific runner by name
-    pub async fn stop_runner(&self, name: &str) -> Result<(), RunnerError> {
-        if let Some((_, mut process)) = self.processes.remove(name) {
-            info!("Stopping runner for '{}'", name);
+    /// Whether a runner llmnet started listens on `port`
+    fn owns_port(&self, port: u16) -> bool {
+        self.ports(|p| p.owned).contains(&port)
+    }
 
-            // Handle Docker containers
-            if let Some(container_name) = &process.container_name {
-                let stop_args = docker::generate_stop_args(container_name);
-                let _ = Command::new("docker").args(&stop_args).output().await;
+    /// Ports of the tracked runners matching `filter`
+    fn ports(&self, filter: impl Fn(&RunnerProcess) -> bool) -> Vec<u16> {
+        self.processes
+            .iter()
+            .flat_map(|p| {
+                p.value()
+                    .iter()
+                    .filter(|p| filter(p))
+                    .filter_map(|p| endpoint_port(&p.endpoint))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
 
-                // Also remove the container
-                let rm_args = docker::generate_rm_args(container_name);
-                let _ = Command::new("docker").args(&rm_args).output().await;
-            }
+    /// Stop all replicas of a runner by name
+    pub async fn stop_runner(&self, name: &str) -> Result<(), RunnerError> {
+        if let Some((_, mut processes)) = self.processes.remove(name) {
+            info!("Stopping runner for '{}'", name);
+            self.pools.remove(name);
 
-            // Handle regular processes
-            if let Some(ref mut child) = process.child {
-                child.kill().await?;
+            for process in &mut processes {
+                stop_process(process).await?;
             }
 
             Ok(())
@@ -623,9 +1099,31 @@ impl RunnerManager {
         }
     }
 
-    /// Get the endpoint for a running model
+    /// Get the endpoint for a running model (its first replica)
     pub fn get_endpoint(&self, name: &str) -> Option<String> {
-        self.processes.get(name).map(|p| p.endpoint.clone())
+        self.processes
+            .get(name)
+            .and_then(|p| p.first().map(|p| p.endpoint.clone()))
+    }
+
+    /// Longest warm-up of a running model's replicas, if any was warmed up
+    pub fn warm_up_duration(&self, name: &str) -> Option<Duration> {
+        self.processes
+            .get(name)
+            .and_then(|p| p.iter().filter_map(|p| p.warm_up).max())
+    }
+
+    /// Get the endpoints of every replica of a running model
+    pub fn get_endpoints(&self, name: &str) -> Vec<String> {
+        self.processes
+            .get(name)
+            .map(|p| p.iter().map(|p| p.endpoint.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the pool balancing requests across a model's replicas
+    pub fn pool(&self, name: &str) -> Option<Arc<RunnerPool>> {
+        self.pools.get(name).map(|p| Arc::clone(&p))
     }
 
     /// Check if a model runner is running
@@ -642,17 +1140,52 @@ impl RunnerManager {
     pub fn get_container_name(&self, model_name: &str) -> Option<String> {
         self.processes
             .get(model_name)
-            .and_then(|p| p.container_name.clone())
+            .and_then(|p| p.first().and_then(|p| p.container_name.clone()))
     }
 
     /// List all Docker container names
     pub fn list_containers(&self) -> Vec<String> {
         self.processes
             .iter()
-            .filter_map(|p| p.container_name.clone())
+            .flat_map(|p| {
+                p.value()
+                    .iter()
+                    .filter_map(|p| p.container_name.clone())
+                    .collect::<Vec<_>>()
+            })
             .collect()
     }
 
+    /// Records of every container-backed replica, in replica order
+    pub fn runner_records(&self) -> Vec<RunnerRecord> {
+        let mut records: Vec<RunnerRecord> = self
+            .processes
+            .iter()
+            .flat_map(|p| {
+                p.value()
+                    .iter()
+                    .filter_map(|p| {
+                        p.container_name.as_ref().map(|container| RunnerRecord {
+                            model: p.model_name.clone(),
+                            container: container.clone(),
+                            endpoint: p.endpoint.clone(),
+                            runner: p.runner_type.clone(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        // Keep the file stable across DashMap iteration orders
+        records.sort_by(|a, b| a.model.cmp(&b.model));
+        records
+    }
+
+    /// Log file of a runner spawned as a local process; `runner` is the
+    /// replica name (`model`, `model-1`, ...). The file outlives the runner.
+    pub fn log_path(&self, runner: &str) -> PathBuf {
+        runner_logs::log_path(&self.work_dir, runner)
+    }
+
     /// Stream container logs (returns a child process whose stdout can be read)
     pub async fn stream_container_logs(
         &self,
@@ -693,27 +1226,17 @@ impl RunnerManager {
         // Kill all processes and containers
         for mut entry in self.processes.iter_mut() {
             let name = entry.key().clone();
-            let process = entry.value_mut();
             info!("Stopping runner for '{}'", name);
 
-            // Handle Docker containers
-            if let Some(container_name) = &process.container_name {
-                let stop_args = docker::generate_stop_args(container_name);
-                let _ = Command::new("docker").args(&stop_args).output().await;
-
-                let rm_args = docker::generate_rm_args(container_name);
-                let _ = Command::new("docker").args(&rm_args).output().await;
-            }
-
-            // Handle regular processes
-            if let Some(ref mut child) = process.child {
-                if let Err(e) = child.kill().await {
+            for process in entry.value_mut() {
+                if let Err(e) = stop_process(process).await {
                     error!("Failed to kill runner '{}': {}", name, e);
                 }
             }
         }
 
         self.processes.clear();
+        self.pools.clear();
     }
 
     /// Get shutdown receiver for spawning background tasks
@@ -728,6 +1251,62 @@ impl Default for RunnerManager {
     }
 }
 
+/// Name of one replica of a runner: the first keeps the plain name
+fn replica_name(name: &str, replica: usize) -> String {
+    if replica == 0 {
+        name.to_string()
+    } else {
+        format!("{}-{}", name, replica)
+    }
+}
+
+/// Port of a runner endpoint such as `http://127.0.0.1:8080/v1`
+fn endpoint_port(endpoint: &str) -> Option<u16> {
+    endpoint
+        .split(':')
+        .next_back()
+        .and_then(|s| s.split('/').next())
+        .and_then(|s| s.parse().ok())
+}
+
+/// Version of the Ollama server answering on `host:port`, if one does
+async fn running_ollama(host: &str, port: u16) -> Option<String> {
+    let response = reqwest::Client::new()
+        .get(super::ollama::version_url(host, port))
+        .timeout(Duration::from_secs(1))
+        .send()
+        .await
+        .ok()
+        .filter(|r| r.status().is_success())?;
+    super::ollama::parse_version(&response.text().await.ok()?)
+}
+
+/// Stop a runner process or container
+///
+/// Runners llmnet didn't start are only forgotten.
+async fn stop_process(process: &mut RunnerProcess) -> Result<(), RunnerError> {
+    if !process.owned {
+        return Ok(());
+    }
+
+    // Handle Docker containers
+    if let Some(container_name) = &process.container_name {
+        let stop_args = docker::generate_stop_args(container_name);
+        let _ = Command::new("docker").args(&stop_args).output().await;
+
+        // Also remove the container
+        let rm_args = docker::generate_rm_args(container_name);
+        let _ = Command::new("docker").args(&rm_args).output().await;
+    }
+
+    // Handle regular processes
+    if let Some(ref mut child) = process.child {
+        child.kill().await?;
+    }
+
+    Ok(())
+}
+
 /// Shared runner manager for use across async tasks
 pub type SharedRunnerManager = Arc<RunnerManager>;
 
@@ -769,6 +1348,39 @@ mod tests {
         assert_eq!(manager.next_available_port(8080), 8080);
     }
 
+    #[tokio::test]
+    async fn test_reused_ollama_is_not_owned() {
+        let app = axum::Router::new().route(
+            "/api/version",
+            axum::routing::get(|| async { axum::Json(serde_json::json!({"version": "0.5.7"})) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        assert_eq!(
+            running_ollama("127.0.0.1", port).await,
+            Some("0.5.7".to_string())
+        );
+        assert_eq!(running_ollama("127.0.0.1", 1).await, None);
+
+        let manager = RunnerManager::new();
+        let mut daemon = RunnerProcess {
+            child: None,
+            container_name: None,
+            endpoint: format!("http://127.0.0.1:{}/v1", port),
+            model_name: "llama".to_string(),
+            runner_type: RunnerType::Ollama,
+            owned: false,
+            warm_up: None,
+        };
+        // Shutting down leaves a daemon llmnet didn't start alone
+        stop_process(&mut daemon).await.unwrap();
+        manager.processes.insert("llama".to_string(), vec![daemon]);
+        assert!(!manager.owns_port(port));
+        assert_eq!(manager.next_available_port(port), port + 1);
+    }
+
     #[test]
     fn test_not_found_error() {
         let manager = RunnerManager::new();
@@ -784,6 +1396,47 @@ mod tests {
         assert!(matches!(result, Err(RunnerError::ConfigError(_))));
     }
 
+    #[test]
+    fn test_replica_name() {
+        assert_eq!(replica_name("llama", 0), "llama");
+        assert_eq!(replica_name("llama", 2), "llama-2");
+    }
+
+    #[tokio::test]
+    async fn test_external_runner_has_no_replicas() {
+        let manager = RunnerManager::new();
+        let config = ModelConfig::external("http://example.com");
+        let result = manager
+            .spawn_replicas("test", &config, 3, LoadBalancing::LeastConnections)
+            .await;
+        assert!(matches!(result, Err(RunnerError::ConfigError(_))));
+        assert!(manager.get_endpoints("test").is_empty());
+        assert!(manager.pool("test").is_none());
+    }
+
+    #[test]
+    fn test_adopt_container() {
+        let manager = RunnerManager::new();
+        for (container, port) in [("llmnet-llama", 8080), ("llmnet-llama-1", 8081)] {
+            manager.adopt_container(&RunnerRecord {
+                model: "llama".to_string(),
+                container: container.to_string(),
+                endpoint: format!("http://127.0.0.1:{}/v1", port),
+                runner: RunnerType::Tgi,
+            });
+        }
+
+        assert!(manager.is_running("llama"));
+        assert_eq!(manager.list_containers().len(), 2);
+        assert_eq!(manager.pool("llama").unwrap().endpoints().len(), 2);
+        // Adopted ports aren't handed out again
+        assert_eq!(manager.next_available_port(8080), 8082);
+
+        let records = manager.runner_records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].container, "llmnet-llama-1");
+    }
+
     #[test]
     fn test_shutdown_receiver() {
         let manager = RunnerManager::new();
@@ -0,0 +1,313 @@
+//! Runner log capture
+//!
+//! The stdout and stderr of locally spawned runners (vLLM, llama.cpp, ...)
+//! are written line by line to `<work_dir>/logs/<runner>.log`. When the file
+//! would grow past [`MAX_LOG_BYTES`] it is rotated to `<runner>.log.1`, and
+//! older files shift up to [`ROTATED_LOGS`] before the oldest is dropped.
+//! The worker serves these files from `GET /v1/runners/{name}/logs`.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::Stream;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, BufReader};
+use tokio::process::Child;
+use tracing::warn;
+
+/// Size a log file may reach before it is rotated
+pub const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Rotated files kept next to the current one
+pub const ROTATED_LOGS: usize = 3;
+
+/// How often a followed log is checked for new output
+const FOLLOW_POLL: Duration = Duration::from_millis(500);
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Log file of a runner replica under a runner work directory
+pub fn log_path(work_dir: &Path, runner: &str) -> PathBuf {
+    work_dir.join("logs").join(format!("{}.log", runner))
+}
+
+/// The `n`th rotated file of a log (`runner.log.1` is the most recent)
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Whether writing `incoming` bytes to a file of `size` bytes should rotate
+/// it first. A line longer than the limit still goes to an empty file.
+pub fn needs_rotation(size: u64, incoming: u64, max_bytes: u64) -> bool {
+    size > 0 && size + incoming > max_bytes
+}
+
+/// The last `n` lines of `text`
+pub fn tail_lines(text: &str, n: usize) -> &str {
+    if n == 0 {
+        return "";
+    }
+    // A trailing newline ends the last line rather than starting a new one
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    match body.rmatch_indices('\n').nth(n - 1) {
+        Some((i, _)) => &text[i + 1..],
+        None => text,
+    }
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// A log file that rotates itself once it reaches a size limit
+pub struct RotatingLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RotatingLog {
+    /// Open a log for appending, creating its directory
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_bytes: MAX_LOG_BYTES,
+            keep: ROTATED_LOGS,
+        })
+    }
+
+    /// Rotate at a different size and keep a different number of files
+    pub fn with_limits(mut self, max_bytes: u64, keep: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self.keep = keep;
+        self
+    }
+
+    /// Append one line (including its newline)
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if needs_rotation(self.size, line.len() as u64, self.max_bytes) {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    std::fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Copy a child's stdout and stderr into a log until the child exits
+///
+/// The child must have been spawned with piped output.
+pub fn capture_output(child: &mut Child, log: RotatingLog) {
+    let log = Arc::new(Mutex::new(log));
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(copy_lines(stdout, Arc::clone(&log)));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(copy_lines(stderr, log));
+    }
+}
+
+async fn copy_lines(output: impl AsyncRead + Unpin, log: Arc<Mutex<RotatingLog>>) {
+    let mut reader = BufReader::new(output);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) => return,
+            Ok(_) => {
+                if !line.ends_with(b"\n") {
+                    line.push(b'\n');
+                }
+                let mut log = log.lock().unwrap_or_else(|e| e.into_inner());
+                if let Err(e) = log.write_line(&line) {
+                    warn!("Failed to write runner log {:?}: {}", log.path, e);
+                    return;
+                }
+            }
+            Err(e) => {
+                warn!("Failed to read runner output: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// The last `n` lines of a log, reaching into the most recent rotated file
+/// when the current one is shorter
+pub fn read_tail(path: &Path, n: usize) -> io::Result<String> {
+    let current = String::from_utf8_lossy(&std::fs::read(path)?).into_owned();
+    let tail = tail_lines(&current, n);
+    if tail.len() < current.len() {
+        return Ok(tail.to_string());
+    }
+
+    let previous = match std::fs::read(rotated_path(path, 1)) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(_) => return Ok(current),
+    };
+    Ok(tail_lines(&(previous + &current), n).to_string())
+}
+
+/// Output appended to a log after `offset`, checked every half second.
+/// Starts over from the top of the file when it is rotated.
+pub fn follow_log(path: PathBuf, offset: u64) -> impl Stream<Item = io::Result<Vec<u8>>> {
+    futures::stream::unfold((path, offset), |(path, mut offset)| async move {
+        loop {
+            tokio::time::sleep(FOLLOW_POLL).await;
+            let len = match tokio::fs::metadata(&path).await {
+                Ok(metadata) => metadata.len(),
+                // Between a rotation's rename and the next write
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Some((Err(e), (path, offset))),
+            };
+            if len < offset {
+                offset = 0;
+            }
+            if len == offset {
+                continue;
+            }
+
+            let read = async {
+                let mut file = tokio::fs::File::open(&path).await?;
+                file.seek(io::SeekFrom::Start(offset)).await?;
+                let mut chunk = Vec::new();
+                file.take(len - offset).read_to_end(&mut chunk).await?;
+                Ok(chunk)
+            };
+            return match read.await {
+                Ok(chunk) => Some((Ok(chunk), (path, len))),
+                Err(e) => Some((Err(e), (path, offset))),
+            };
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::process::Command;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("llmnet-logs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_paths() {
+        let path = log_path(Path::new("/tmp/runners"), "llama-1");
+        assert_eq!(path, PathBuf::from("/tmp/runners/logs/llama-1.log"));
+        assert_eq!(
+            rotated_path(&path, 2),
+            PathBuf::from("/tmp/runners/logs/llama-1.log.2")
+        );
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\n", 2), "b\nc\n");
+        assert_eq!(tail_lines("a\nb\nc", 2), "b\nc");
+        assert_eq!(tail_lines("a\nb\n", 5), "a\nb\n");
+        assert_eq!(tail_lines("a\nb\n", 0), "");
+        assert!(!needs_rotation(0, 50, 10));
+        assert!(needs_rotation(8, 3, 10));
+        assert!(!needs_rotation(8, 2, 10));
+    }
+
+    #[test]
+    fn test_rotation_and_tail() {
+        let dir = temp_dir();
+        let path = log_path(&dir, "vllm");
+        let mut log = RotatingLog::open(&path).unwrap().with_limits(8, 2);
+
+        // Four-byte lines, so every file holds two
+        for line in ["l1\n", "l2\n", "l3\n", "l4\n", "l5\n", "l6\n", "l7\n"] {
+            log.write_line(line.as_bytes()).unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "l7\n");
+        assert_eq!(
+            std::fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "l5\nl6\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "l3\nl4\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+
+        assert_eq!(read_tail(&path, 1).unwrap(), "l7\n");
+        // Reaches into the previous file, but no further
+        assert_eq!(read_tail(&path, 2).unwrap(), "l6\nl7\n");
+        assert_eq!(read_tail(&path, 10).unwrap(), "l5\nl6\nl7\n");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_capture_output() {
+        let dir = temp_dir();
+        let path = log_path(&dir, "runner");
+
+        let mut child = Command::new("sh")
+            .args(["-c", "echo started; echo failed >&2; printf partial"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        capture_output(&mut child, RotatingLog::open(&path).unwrap());
+        child.wait().await.unwrap();
+
+        // The copy tasks finish shortly after the pipes close
+        let mut logged = String::new();
+        for _ in 0..50 {
+            logged = std::fs::read_to_string(&path).unwrap();
+            if logged.lines().count() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mut lines: Vec<&str> = logged.lines().collect();
+        lines.sort_unstable();
+        assert_eq!(lines, ["failed", "partial", "started"]);
+        assert!(logged.ends_with('\n'));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+//! Conversation sessions
+//!
+//! Clients that send a session ID get their earlier turns replayed to
+//! handler nodes, so a chatbot can keep context without the client resending
+//! the whole conversation. History lives in a pluggable store and is trimmed
+//! to a token budget before each request.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use thiserror::Error;
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::client::Message;
+use crate::config::{SessionConfig, SessionStoreKind};
+
+/// Errors that can occur while reading or writing sessions
+#[derive(Error, Debug)]
+pub enum SessionError {
+    #[error("Session store error: {0}")]
+    Store(String),
+
+    #[error("Invalid session data: {0}")]
+    InvalidData(String),
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Rough token count for budgeting (about four characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Drop the oldest messages until the history fits the token budget
+///
+/// Messages are dropped from the front so the most recent turns survive.
+pub fn trim_history(mut history: Vec<Message>, max_tokens: usize) -> Vec<Message> {
+    let mut total: usize = history.iter().map(|m| estimate_tokens(&m.content)).sum();
+    let mut drop = 0;
+    while total > max_tokens && drop < history.len() {
+        total -= estimate_tokens(&history[drop].content);
+        drop += 1;
+    }
+    history.drain(..drop);
+    history
+}
+
+/// Append a finished exchange to a session's history
+pub fn append_turn(history: &mut Vec<Message>, prompt: &str, answer: &str) {
+    history.push(Message {
+        role: "user".to_string(),
+        content: prompt.to_string(),
+        ..Default::default()
+    });
+    history.push(Message {
+        role: "assistant".to_string(),
+        content: answer.to_string(),
+        ..Default::default()
+    });
+}
+
+// ============================================================================
+// SBIO: Trait for abstraction (allows mocking in tests)
+// ============================================================================
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Load a session's history; unknown sessions are empty
+    async fn load(&self, session_id: &str) -> Result<Vec<Message>, SessionError>;
+
+    /// Replace a session's history
+    async fn save(&self, session_id: &str, history: &[Message]) -> Result<(), SessionError>;
+
+    /// Forget a session
+    async fn delete(&self, session_id: &str) -> Result<(), SessionError>;
+}
+
+/// Create the session store described by a composition
+pub fn build_session_store(config: &SessionConfig) -> Box<dyn SessionStore> {
+    let ttl = Duration::from_secs(config.ttl_secs);
+    match (config.store, &config.url) {
+        (SessionStoreKind::Redis, Some(url)) => Box::new(RedisSessionStore::new(url.clone(), ttl)),
+        _ => Box::new(MemorySessionStore::new(ttl)),
+    }
+}
+
+// ============================================================================
+// SBIO: I/O implementations
+// ============================================================================
+
+/// Sessions kept in the worker's memory
+pub struct MemorySessionStore {
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, (Vec<Message>, Instant)>>,
+}
+
+impl MemorySessionStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, session_id: &str) -> Result<Vec<Message>, SessionError> {
+        let mut sessions = self.sessions.lock().await;
+        // Expired sessions are dropped lazily, on the next write
+        Ok(sessions
+            .get_mut(session_id)
+            .filter(|(_, touched)| touched.elapsed() < self.ttl)
+            .map(|(history, _)| history.clone())
+            .unwrap_or_default())
+    }
+
+    async fn save(&self, session_id: &str, history: &[Message]) -> Result<(), SessionError> {
+        let mut sessions = self.sessions.lock().await;
+        sessions.retain(|_, (_, touched)| touched.elapsed() < self.ttl);
+        sessions.insert(session_id.to_string(), (history.to_vec(), Instant::now()));
+        Ok(())
+    }
+
+    async fn delete(&self, session_id: &str) -> Result<(), SessionError> {
+        self.sessions.lock().await.remove(session_id);
+        Ok(())
+    }
+}
+
+/// Sessions kept in Redis as JSON, shared by every replica
+pub struct RedisSessionStore {
+    url: String,
+    ttl: Duration,
+    connection: OnceCell<redis::aio::MultiplexedConnection>,
+}
+
+impl RedisSessionStore {
+    pub fn new(url: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            url: url.into(),
+            ttl,
+            connection: OnceCell::new(),
+        }
+    }
+
+    fn key(session_id: &str) -> String {
+        format!("llmnet:session:{}", session_id)
+    }
+
+    /// Connect on first use; the multiplexed connection is shared after that
+    async fn connection(&self) -> Result<redis::aio::MultiplexedConnection, SessionError> {
+        self.connection
+            .get_or_try_init(|| async {
+                let client = redis::Client::open(self.url.as_str())
+                    .map_err(|e| SessionError::Store(e.to_string()))?;
+                client
+                    .get_multiplexed_async_connection()
+                    .await
+                    .map_err(|e| SessionError::Store(e.to_string()))
+            })
+            .await
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    async fn load(&self, session_id: &str) -> Result<Vec<Message>, SessionError> {
+        let mut conn = self.connection().await?;
+        let data: Option<String> = conn
+            .get(Self::key(session_id))
+            .await
+            .map_err(|e| SessionError::Store(e.to_string()))?;
+
+        match data {
+            Some(json) => {
+                serde_json::from_str(&json).map_err(|e| SessionError::InvalidData(e.to_string()))
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn save(&self, session_id: &str, history: &[Message]) -> Result<(), SessionError> {
+        let json =
+            serde_json::to_string(history).map_err(|e| SessionError::InvalidData(e.to_string()))?;
+        let mut conn = self.connection().await?;
+        conn.set_ex::<_, _, ()>(Self::key(session_id), json, self.ttl.as_secs().max(1))
+            .await
+            .map_err(|e| SessionError::Store(e.to_string()))
+    }
+
+    async fn delete(&self, session_id: &str) -> Result<(), SessionError> {
+        let mut conn = self.connection().await?;
+        conn.del::<_, ()>(Self::key(session_id))
+            .await
+            .map_err(|e| SessionError::Store(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
+
+    #[test]
+    fn test_trim_history_keeps_latest() {
+        let history = vec![
+            msg("user", &"a".repeat(40)),      // 10 tokens
+            msg("assistant", &"b".repeat(40)), // 10 tokens
+            msg("user", &"c".repeat(20)),      // 5 tokens
+        ];
+
+        let trimmed = trim_history(history.clone(), 15);
+        assert_eq!(trimmed.len(), 2);
+        assert!(trimmed[0].content.starts_with('b'));
+
+        assert_eq!(trim_history(history.clone(), 100).len(), 3);
+        assert!(trim_history(history, 0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_round_trip() {
+        let store = MemorySessionStore::new(Duration::from_secs(60));
+        assert!(store.load("s1").await.unwrap().is_empty());
+
+        let mut history = Vec::new();
+        append_turn(&mut history, "Hi, I'm Ada", "Hello Ada!");
+        store.save("s1", &history).await.unwrap();
+
+        let loaded = store.load("s1").await.unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].role, "user");
+        assert_eq!(loaded[1].content, "Hello Ada!");
+        assert!(store.load("s2").await.unwrap().is_empty());
+
+        store.delete("s1").await.unwrap();
+        assert!(store.load("s1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_expires_sessions() {
+        let store = MemorySessionStore::new(Duration::ZERO);
+        store.save("s1", &[msg("user", "hi")]).await.unwrap();
+        assert!(store.load("s1").await.unwrap().is_empty());
+    }
+}
@@ -70,11 +70,8 @@ pub fn generate_args(
         let arg_name = format!("--{}", key.replace('_', "-"));
 
         match value {
-            Value::Bool(b) => {
-                if *b {
-                    args.push(arg_name);
-                }
-            }
+            Value::Bool(true) => args.push(arg_name),
+            Value::Bool(false) => {}
             Value::Number(n) => {
                 args.push(arg_name);
                 args.push(n.to_string());
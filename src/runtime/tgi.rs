@@ -0,0 +1,238 @@
+//! HuggingFace text-generation-inference (TGI) configuration
+//!
+//! TGI is launched as a Docker container. This module builds the
+//! `docker run` arguments, including the launcher's sharding and
+//! quantization flags; the container serves an OpenAI-compatible API under
+//! `/v1` and reports readiness on `/health`.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::docker::expand_env_vars;
+
+/// Default TGI image
+pub const DEFAULT_IMAGE: &str = "ghcr.io/huggingface/text-generation-inference:latest";
+
+/// Port the TGI router listens on inside the container
+pub const CONTAINER_PORT: u16 = 80;
+
+/// Quantization methods accepted by `--quantize`
+pub const QUANTIZE_METHODS: &[&str] = &[
+    "awq",
+    "compressed-tensors",
+    "eetq",
+    "exl2",
+    "gptq",
+    "marlin",
+    "bitsandbytes",
+    "bitsandbytes-nf4",
+    "bitsandbytes-fp4",
+    "fp8",
+];
+
+/// Parameters consumed by `docker run` rather than passed to the launcher
+const DOCKER_PARAMS: &[&str] = &["image", "gpus", "shm_size", "volume"];
+
+// ============================================================================
+// SBIO: Pure business logic (no I/O)
+// ============================================================================
+
+/// Check a quantization method against the ones TGI supports
+pub fn validate_quantize(method: &str) -> Result<(), String> {
+    if QUANTIZE_METHODS.contains(&method) {
+        Ok(())
+    } else {
+        Err(format!(
+            "unsupported TGI quantization '{}' (expected one of: {})",
+            method,
+            QUANTIZE_METHODS.join(", ")
+        ))
+    }
+}
+
+/// The quantization method requested in the parameters, if any
+///
+/// `quantization` is accepted as an alias so compositions can share
+/// parameters with the vLLM runner.
+pub fn quantize_param(params: &HashMap<String, Value>) -> Option<&str> {
+    params
+        .get("quantize")
+        .or_else(|| params.get("quantization"))
+        .and_then(|v| v.as_str())
+}
+
+/// Generate `docker run` arguments for a TGI container
+///
+/// # Supported Parameters
+/// - `image`: TGI image (default: [`DEFAULT_IMAGE`])
+/// - `gpus`: Value for `docker run --gpus` (default: `all`)
+/// - `shm_size`: Shared memory size, needed for NCCL when sharding (default: `1g`)
+/// - `volume`: Host directory mounted at `/data` for the weight cache
+/// - `num_shard`: Number of GPUs to shard the model across
+/// - `sharded`: Force sharding on or off
+/// - `quantize` (or `quantization`): awq, gptq, bitsandbytes, eetq, fp8, ...
+/// - Anything else is passed to the launcher as `--kebab-case-name value`,
+///   e.g. `max_input_tokens`, `max_total_tokens`, `dtype`
+pub fn generate_run_args(
+    model: &str,
+    host: &str,
+    port: u16,
+    params: &HashMap<String, Value>,
+    container_name: &str,
+    hf_token: Option<&str>,
+) -> Vec<String> {
+    let string_param = |key: &str| params.get(key).and_then(|v| v.as_str());
+
+    let mut args = vec![
+        "run".to_string(),
+        "-d".to_string(),
+        "--name".to_string(),
+        container_name.to_string(),
+        "--gpus".to_string(),
+        string_param("gpus").unwrap_or("all").to_string(),
+        "--shm-size".to_string(),
+        string_param("shm_size").unwrap_or("1g").to_string(),
+        "-p".to_string(),
+        format!("{}:{}:{}", host, port, CONTAINER_PORT),
+    ];
+
+    if let Some(volume) = string_param("volume") {
+        args.push("-v".to_string());
+        args.push(format!("{}:/data", expand_env_vars(volume)));
+    }
+
+    if let Some(token) = hf_token {
+        args.push("-e".to_string());
+        args.push(format!("HF_TOKEN={}", token));
+    }
+
+    args.push(string_param("image").unwrap_or(DEFAULT_IMAGE).to_string());
+    args.push("--model-id".to_string());
+    args.push(model.to_string());
+
+    if let Some(method) = quantize_param(params) {
+        args.push("--quantize".to_string());
+        args.push(method.to_string());
+    }
+
+    // Sorted so the generated command is stable
+    let mut launcher_params: Vec<_> = params
+        .iter()
+        .filter(|(key, _)| {
+            !DOCKER_PARAMS.contains(&key.as_str()) && *key != "quantize" && *key != "quantization"
+        })
+        .collect();
+    launcher_params.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (key, value) in launcher_params {
+        let arg_name = format!("--{}", key.replace('_', "-"));
+
+        match value {
+            // Launcher booleans take an explicit value (`--sharded false`)
+            Value::Bool(b) => {
+                args.push(arg_name);
+                args.push(b.to_string());
+            }
+            Value::Number(n) => {
+                args.push(arg_name);
+                args.push(n.to_string());
+            }
+            Value::String(s) => {
+                args.push(arg_name);
+                args.push(s.clone());
+            }
+            _ => {}
+        }
+    }
+
+    args
+}
+
+/// Get the default host port for TGI
+pub const fn default_port() -> u16 {
+    3000
+}
+
+/// Generate the endpoint URL for a TGI container
+pub fn endpoint_url(host: &str, port: u16) -> String {
+    format!("http://{}:{}/v1", host, port)
+}
+
+/// URL that returns 200 once the model is loaded
+pub fn health_url(endpoint: &str) -> String {
+    format!("{}/health", endpoint.trim_end_matches("/v1"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::docker::flag_value;
+
+    fn args_for(params: &HashMap<String, Value>) -> Vec<String> {
+        generate_run_args(
+            "mistralai/Mistral-7B-Instruct-v0.3",
+            "127.0.0.1",
+            3000,
+            params,
+            "llmnet-mistral",
+            None,
+        )
+    }
+
+    #[test]
+    fn test_generate_run_args_defaults() {
+        let args = args_for(&HashMap::new());
+
+        assert_eq!(&args[..4], ["run", "-d", "--name", "llmnet-mistral"]);
+        assert_eq!(flag_value(&args, "--gpus"), Some("all"));
+        assert_eq!(flag_value(&args, "--shm-size"), Some("1g"));
+        assert_eq!(flag_value(&args, "-p"), Some("127.0.0.1:3000:80"));
+        assert_eq!(
+            flag_value(&args, "--model-id"),
+            Some("mistralai/Mistral-7B-Instruct-v0.3")
+        );
+        // Launcher flags come after the image
+        let image = args.iter().position(|a| a == DEFAULT_IMAGE).unwrap();
+        assert_eq!(args[image + 1], "--model-id");
+        assert!(!args.contains(&"--quantize".to_string()));
+    }
+
+    #[test]
+    fn test_generate_run_args_sharding_and_quantization() {
+        let mut params = HashMap::new();
+        params.insert("num_shard".to_string(), Value::Number(2.into()));
+        params.insert("sharded".to_string(), Value::Bool(true));
+        params.insert("quantization".to_string(), Value::String("awq".into()));
+        params.insert("max_total_tokens".to_string(), Value::Number(8192.into()));
+        params.insert("gpus".to_string(), Value::String("\"device=0,1\"".into()));
+        params.insert("volume".to_string(), Value::String("/srv/hf".into()));
+
+        let args = generate_run_args("model", "0.0.0.0", 8081, &params, "tgi", Some("hf_abc"));
+
+        assert_eq!(flag_value(&args, "--num-shard"), Some("2"));
+        assert_eq!(flag_value(&args, "--sharded"), Some("true"));
+        assert_eq!(flag_value(&args, "--quantize"), Some("awq"));
+        assert_eq!(flag_value(&args, "--max-total-tokens"), Some("8192"));
+        assert_eq!(flag_value(&args, "--gpus"), Some("\"device=0,1\""));
+        assert_eq!(flag_value(&args, "-v"), Some("/srv/hf:/data"));
+        assert_eq!(flag_value(&args, "-e"), Some("HF_TOKEN=hf_abc"));
+        // Docker-only parameters are not passed to the launcher
+        assert!(!args.contains(&"--volume".to_string()));
+        assert!(!args.contains(&"--quantization".to_string()));
+    }
+
+    #[test]
+    fn test_validate_quantize() {
+        assert!(validate_quantize("gptq").is_ok());
+        assert!(validate_quantize("bitsandbytes-nf4").is_ok());
+        assert!(validate_quantize("int4_awq").is_err());
+    }
+
+    #[test]
+    fn test_urls() {
+        let endpoint = endpoint_url("127.0.0.1", 3000);
+        assert_eq!(endpoint, "http://127.0.0.1:3000/v1");
+        assert_eq!(health_url(&endpoint), "http://127.0.0.1:3000/health");
+    }
+}
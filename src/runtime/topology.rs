@@ -0,0 +1,208 @@
+//! The served composition as a graph
+//!
+//! `GET /v1/topology` describes the pipeline a worker has loaded: its nodes
+//! with their layers, conditions and models, the edges between them with
+//! `output-to` layers resolved to node names, and the endpoints each node's
+//! model is called at.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::{ArchitectureNode, Composition, OutputTarget};
+
+/// Nodes and edges of a composition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Topology {
+    /// The node requests enter at (layer 0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub router: Option<String>,
+    /// Nodes in layer order, output last
+    pub nodes: Vec<TopologyNode>,
+    pub edges: Vec<TopologyEdge>,
+}
+
+/// One node of the graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TopologyNode {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer: Option<u32>,
+    pub adapter: String,
+    /// Name of the model in the composition's `models`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_case: Option<String>,
+    /// The node's `if` condition
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+    /// Base URLs the node's model is called at, one per runner replica
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<String>,
+}
+
+/// A way from one node to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TopologyEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+}
+
+/// Why an edge exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeKind {
+    /// Listed in `output-to`
+    Output,
+    /// The node's `loop-to`
+    Loop,
+    /// A guard's `fallback`
+    Fallback,
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+impl Topology {
+    /// Describe a composition; endpoints are filled in separately
+    pub fn from_composition(composition: &Composition) -> Self {
+        let architecture = &composition.architecture;
+
+        let mut nodes: Vec<&ArchitectureNode> = architecture.iter().collect();
+        nodes.sort_by_key(|n| (n.is_output(), n.layer.unwrap_or(0)));
+
+        let mut edges = Vec::new();
+        for node in &nodes {
+            let edge = |to: &str, kind| TopologyEdge {
+                from: node.name.clone(),
+                to: to.to_string(),
+                kind,
+            };
+            for target in output_targets(node, architecture) {
+                edges.push(edge(&target, EdgeKind::Output));
+            }
+            if let Some(target) = &node.loop_to {
+                edges.push(edge(target, EdgeKind::Loop));
+            }
+            if let Some(fallback) = node.guard.as_ref().and_then(|g| g.fallback.as_ref()) {
+                edges.push(edge(fallback, EdgeKind::Fallback));
+            }
+        }
+
+        Self {
+            router: architecture
+                .iter()
+                .find(|n| n.is_router())
+                .map(|n| n.name.clone()),
+            nodes: nodes
+                .into_iter()
+                .map(|n| TopologyNode {
+                    name: n.name.clone(),
+                    layer: n.layer,
+                    adapter: n.adapter.clone(),
+                    model: n.model.clone(),
+                    use_case: n.use_case.clone(),
+                    condition: n.condition.clone(),
+                    endpoints: Vec::new(),
+                })
+                .collect(),
+            edges,
+        }
+    }
+
+    /// Set the endpoints each node's model is called at, by node name
+    pub fn with_endpoints(mut self, mut endpoints: BTreeMap<String, Vec<String>>) -> Self {
+        for node in &mut self.nodes {
+            node.endpoints = endpoints.remove(&node.name).unwrap_or_default();
+        }
+        self
+    }
+}
+
+/// Nodes a node sends its output to, resolving layers as the processor
+/// does: the layer's handlers, or its output nodes when it has none
+fn output_targets(node: &ArchitectureNode, architecture: &[ArchitectureNode]) -> Vec<String> {
+    match &node.output_to {
+        Some(OutputTarget::Nodes(names)) => names.clone(),
+        Some(OutputTarget::Layers(layers)) => {
+            let in_layers = |output: bool| -> Vec<String> {
+                architecture
+                    .iter()
+                    .filter(|n| layers.contains(&n.layer.unwrap_or(0)) && n.is_output() == output)
+                    .map(|n| n.name.clone())
+                    .collect()
+            };
+            let handlers = in_layers(false);
+            if handlers.is_empty() {
+                in_layers(true)
+            } else {
+                handlers
+            }
+        }
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_composition() {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {"small": {"type": "external", "interface": "openai-api", "url": "http://a/v1"}},
+                "architecture": [
+                    {"name": "output", "adapter": "output"},
+                    {"name": "router", "layer": 0, "model": "small", "adapter": "openai-api", "output-to": [1]},
+                    {"name": "sales", "layer": 1, "model": "small", "adapter": "openai-api", "if": "$WORD_COUNT > 3", "output-to": ["check"]},
+                    {"name": "support", "layer": 1, "model": "small", "adapter": "openai-api", "output-to": ["check"]},
+                    {
+                        "name": "check", "layer": 2, "adapter": "guard",
+                        "guard": {"max-length": 500, "on-violation": "fallback", "fallback": "support"},
+                        "loop-to": "sales",
+                        "output-to": ["output"]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let topology = Topology::from_composition(&composition).with_endpoints(BTreeMap::from([(
+            "sales".to_string(),
+            vec!["http://a/v1".to_string()],
+        )]));
+        assert_eq!(topology.router.as_deref(), Some("router"));
+
+        let names: Vec<&str> = topology.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["router", "sales", "support", "check", "output"]);
+        assert_eq!(
+            topology.nodes[1].condition.as_deref(),
+            Some("$WORD_COUNT > 3")
+        );
+        assert_eq!(topology.nodes[1].endpoints, ["http://a/v1"]);
+        assert!(topology.nodes[2].endpoints.is_empty());
+
+        let edges: Vec<(&str, &str, EdgeKind)> = topology
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.kind))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                ("router", "sales", EdgeKind::Output),
+                ("router", "support", EdgeKind::Output),
+                ("sales", "check", EdgeKind::Output),
+                ("support", "check", EdgeKind::Output),
+                ("check", "output", EdgeKind::Output),
+                ("check", "sales", EdgeKind::Loop),
+                ("check", "support", EdgeKind::Fallback),
+            ]
+        );
+    }
+}
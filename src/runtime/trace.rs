@@ -0,0 +1,210 @@
+//! Bounded store of completed request traces
+//!
+//! Every request that runs through the processor leaves a trace: which
+//! nodes it visited, how long each hop took and how many tokens it used.
+//! Workers keep the most recent traces in memory so routing decisions can be
+//! inspected after the fact with `GET /v1/requests/{request_id}`.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::runtime::request::{PipelineRequest, RequestHop};
+
+/// Default number of traces a worker keeps
+pub const DEFAULT_TRACE_CAPACITY: usize = 1000;
+
+/// One hop of a finished request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HopTrace {
+    pub node: String,
+    pub layer: u32,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub documents: Vec<String>,
+    /// Quality score from an evaluator node, 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+impl From<&RequestHop> for HopTrace {
+    fn from(hop: &RequestHop) -> Self {
+        Self {
+            node: hop.node_name.clone(),
+            layer: hop.layer,
+            timestamp: hop.timestamp,
+            decision: hop.decision.clone(),
+            latency_ms: hop.latency_ms,
+            prompt_tokens: hop.prompt_tokens,
+            completion_tokens: hop.completion_tokens,
+            documents: hop.documents.clone(),
+            score: hop.score,
+        }
+    }
+}
+
+/// Snapshot of a finished request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RequestTrace {
+    pub request_id: Uuid,
+    pub prompt: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    /// Final answer, when the request succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Failure message, when the request failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub total_tokens: u32,
+    pub hops: Vec<HopTrace>,
+}
+
+impl RequestTrace {
+    /// Capture a request after the pipeline finished with it
+    pub fn capture<E: std::fmt::Display>(
+        request: &PipelineRequest,
+        result: &Result<String, E>,
+    ) -> Self {
+        let hops: Vec<HopTrace> = request.trace.iter().map(HopTrace::from).collect();
+        let total_tokens = hops
+            .iter()
+            .map(|h| h.prompt_tokens.unwrap_or(0) + h.completion_tokens.unwrap_or(0))
+            .sum();
+        let duration_ms = (chrono::Utc::now() - request.start_time)
+            .num_milliseconds()
+            .max(0) as u64;
+
+        Self {
+            request_id: request.request_id,
+            prompt: request.original_prompt.clone(),
+            started_at: request.start_time,
+            duration_ms,
+            output: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            total_tokens,
+            hops,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TraceStoreInner {
+    traces: HashMap<Uuid, RequestTrace>,
+    /// Insertion order, oldest first, for eviction
+    order: VecDeque<Uuid>,
+}
+
+/// In-memory store that keeps the most recent traces
+#[derive(Debug)]
+pub struct TraceStore {
+    capacity: usize,
+    inner: Mutex<TraceStoreInner>,
+}
+
+impl TraceStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(TraceStoreInner::default()),
+        }
+    }
+
+    /// Store a trace, evicting the oldest once full
+    pub fn record(&self, trace: RequestTrace) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner
+            .traces
+            .insert(trace.request_id, trace.clone())
+            .is_none()
+        {
+            inner.order.push_back(trace.request_id);
+        }
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.traces.remove(&oldest);
+            }
+        }
+    }
+
+    /// Look up a trace by request ID
+    pub fn get(&self, request_id: &Uuid) -> Option<RequestTrace> {
+        self.inner.lock().unwrap().traces.get(request_id).cloned()
+    }
+
+    /// Number of traces currently held
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for TraceStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finished_request(prompt: &str) -> PipelineRequest {
+        let mut request = PipelineRequest::new(prompt.to_string());
+        request.add_hop("handler".to_string(), 1, Some("handler".to_string()));
+        request.complete_hop(42, Some((10, 5)));
+        request.add_hop("output".to_string(), 2, None);
+        request
+    }
+
+    #[test]
+    fn test_capture_success_and_failure() {
+        let request = finished_request("hi");
+
+        let ok = RequestTrace::capture::<String>(&request, &Ok("done".to_string()));
+        assert_eq!(ok.output.as_deref(), Some("done"));
+        assert_eq!(ok.error, None);
+        assert_eq!(ok.total_tokens, 15);
+        assert_eq!(ok.hops.len(), 2);
+        assert_eq!(ok.hops[0].latency_ms, Some(42));
+        assert_eq!(ok.hops[1].latency_ms, None);
+
+        let failed = RequestTrace::capture(&request, &Err::<String, _>("boom"));
+        assert_eq!(failed.output, None);
+        assert_eq!(failed.error.as_deref(), Some("boom"));
+    }
+
+    #[test]
+    fn test_store_evicts_oldest() {
+        let store = TraceStore::new(2);
+        let traces: Vec<RequestTrace> = (0..3)
+            .map(|i| RequestTrace::capture::<String>(&finished_request("q"), &Ok(i.to_string())))
+            .collect();
+
+        for trace in &traces {
+            store.record(trace.clone());
+        }
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get(&traces[0].request_id).is_none());
+        assert_eq!(store.get(&traces[2].request_id), Some(traces[2].clone()));
+    }
+}
@@ -48,11 +48,8 @@ pub fn generate_args(
         let arg_name = format!("--{}", key.replace('_', "-"));
 
         match value {
-            Value::Bool(b) => {
-                if *b {
-                    args.push(arg_name);
-                }
-            }
+            Value::Bool(true) => args.push(arg_name),
+            Value::Bool(false) => {}
             Value::Number(n) => {
                 args.push(arg_name);
                 args.push(n.to_string());
@@ -269,6 +266,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_get_then_check)]
     fn test_generate_env_vars_without_token() {
         let env = generate_env_vars(None);
         assert!(env.get("HF_TOKEN").is_none());
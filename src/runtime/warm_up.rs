@@ -0,0 +1,126 @@
+//! Warm-up requests for newly started runners
+//!
+//! A runner that passes its health check may still have to load weights
+//! onto the GPU or compile its graphs on the first request it gets. Models
+//! with a `warm-up` block are sent that many requests right after their
+//! runner is ready, so a real user never pays for it:
+//!
+//! ```json
+//! "llm": {
+//!   "runner": "vllm",
+//!   "source": "meta-llama/Llama-3.1-8B-Instruct",
+//!   "warm-up": {"requests": 2, "prompt": "Hello", "max-tokens": 8}
+//! }
+//! ```
+//!
+//! Runners serving embeddings are sent embedding requests instead. A failed
+//! warm-up is only logged: the runner is up, just not warm.
+
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::client::{
+    ChatCompletionRequest, ClientError, EmbeddingInput, EmbeddingRequest, Message, OpenAiClient,
+    OpenAiClientTrait,
+};
+use crate::config::models::{ModelConfig, RunnerType, WarmUpConfig};
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Model name warm-up requests ask the runner for
+///
+/// vLLM and TGI serve the model under its source, the other runners under
+/// the name llmnet gave it.
+pub fn warm_up_model(name: &str, config: &ModelConfig) -> String {
+    match config.runner {
+        RunnerType::Vllm | RunnerType::Tgi => {
+            config.source.clone().unwrap_or_else(|| name.to_string())
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// Whether a runner was started to serve embeddings (see
+/// [`ModelConfig::for_embeddings`])
+pub fn serves_embeddings(config: &ModelConfig) -> bool {
+    config.parameters.get("embedding") == Some(&Value::Bool(true))
+        || config.parameters.get("task").and_then(|t| t.as_str()) == Some("embed")
+}
+
+/// Chat completion a warm-up sends
+pub fn warm_up_request(model: &str, warm_up: &WarmUpConfig) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![Message {
+            role: "user".to_string(),
+            content: warm_up.prompt.clone(),
+            ..Default::default()
+        }],
+        max_tokens: Some(warm_up.max_tokens),
+        ..Default::default()
+    }
+}
+
+// ============================================================================
+// I/O: Warming up
+// ============================================================================
+
+/// Send a runner at `endpoint` its warm-up requests, returning how long
+/// they took
+pub async fn warm_up(
+    name: &str,
+    endpoint: &str,
+    config: &ModelConfig,
+    settings: &WarmUpConfig,
+) -> Result<Duration, ClientError> {
+    let model = warm_up_model(name, config);
+    let client = OpenAiClient::new(
+        endpoint
+            .trim_end_matches('/')
+            .trim_end_matches("/v1")
+            .to_string(),
+        config.api_key.clone(),
+        model.clone(),
+    );
+
+    let start = Instant::now();
+    for _ in 0..settings.requests {
+        if serves_embeddings(config) {
+            let request = EmbeddingRequest {
+                model: model.clone(),
+                input: EmbeddingInput::Single(settings.prompt.clone()),
+            };
+            client.embeddings(&request).await?;
+        } else {
+            client
+                .chat_completion(&warm_up_request(&model, settings))
+                .await?;
+        }
+    }
+    Ok(start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warm_up_request() {
+        let vllm = ModelConfig::vllm("meta-llama/Llama-3.1-8B-Instruct");
+        assert_eq!(
+            warm_up_model("llm", &vllm),
+            "meta-llama/Llama-3.1-8B-Instruct"
+        );
+        assert_eq!(warm_up_model("llm", &ModelConfig::ollama("llama3")), "llm");
+
+        assert!(!serves_embeddings(&vllm));
+        assert!(serves_embeddings(&vllm.for_embeddings()));
+
+        let request = warm_up_request("llm", &WarmUpConfig::default());
+        assert_eq!(request.messages[0].content, "Hello");
+        assert_eq!(request.max_tokens, Some(8));
+    }
+}
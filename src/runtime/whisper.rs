@@ -0,0 +1,252 @@
+//! Speech-to-text runner configuration
+//!
+//! The `whisper` runner serves a Whisper model behind OpenAI's
+//! `/v1/audio/transcriptions` API, with one of two backends picked by the
+//! `backend` parameter:
+//! - `whisper.cpp` (default): `whisper-server` from whisper.cpp, loading a
+//!   ggml model file
+//! - `faster-whisper`: the faster-whisper-server container, loading a
+//!   HuggingFace repo such as `Systran/faster-whisper-small`
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+/// Path both backends serve transcriptions on
+pub const TRANSCRIPTION_PATH: &str = "/v1/audio/transcriptions";
+
+/// Default faster-whisper-server image
+pub const DEFAULT_IMAGE: &str = "fedirz/faster-whisper-server:latest-cpu";
+
+/// Port faster-whisper-server listens on inside the container
+pub const CONTAINER_PORT: u16 = 8000;
+
+/// Parameters that pick or place the backend rather than configure it
+const RUNNER_PARAMS: &[&str] = &["backend", "image", "gpus"];
+
+/// Server that transcribes for a `whisper` model
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhisperBackend {
+    WhisperCpp,
+    FasterWhisper,
+}
+
+// ============================================================================
+// SBIO: Pure business logic (no I/O)
+// ============================================================================
+
+/// The backend requested by the `backend` parameter
+pub fn backend(params: &HashMap<String, Value>) -> Result<WhisperBackend, String> {
+    match params.get("backend").and_then(|v| v.as_str()) {
+        None | Some("whisper.cpp") | Some("whisper-cpp") => Ok(WhisperBackend::WhisperCpp),
+        Some("faster-whisper") => Ok(WhisperBackend::FasterWhisper),
+        Some(other) => Err(format!(
+            "unknown whisper backend '{}' (expected whisper.cpp or faster-whisper)",
+            other
+        )),
+    }
+}
+
+/// Generate `whisper-server` arguments
+///
+/// # Supported Parameters
+/// - `threads`, `processors`, `language`, `beam_size`, ...: passed as
+///   `--kebab-case-name value`
+/// - Booleans such as `convert` (let ffmpeg decode non-WAV uploads) or
+///   `translate` are passed as bare flags when true
+pub fn generate_args(
+    model_path: &str,
+    host: &str,
+    port: u16,
+    params: &HashMap<String, Value>,
+) -> Vec<String> {
+    let mut args = vec![
+        "--model".to_string(),
+        model_path.to_string(),
+        "--host".to_string(),
+        host.to_string(),
+        "--port".to_string(),
+        port.to_string(),
+        "--inference-path".to_string(),
+        TRANSCRIPTION_PATH.to_string(),
+    ];
+
+    // Sorted so the generated command is stable
+    let mut server_params: Vec<_> = params
+        .iter()
+        .filter(|(key, _)| !RUNNER_PARAMS.contains(&key.as_str()))
+        .collect();
+    server_params.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (key, value) in server_params {
+        let arg_name = format!("--{}", key.replace('_', "-"));
+        match value {
+            Value::Bool(true) => args.push(arg_name),
+            Value::Number(n) => {
+                args.push(arg_name);
+                args.push(n.to_string());
+            }
+            Value::String(s) => {
+                args.push(arg_name);
+                args.push(s.clone());
+            }
+            _ => {}
+        }
+    }
+
+    args
+}
+
+/// Generate `docker run` arguments for a faster-whisper-server container
+///
+/// # Supported Parameters
+/// - `image`: server image (default: [`DEFAULT_IMAGE`]; use a `-cuda` tag
+///   with `gpus`)
+/// - `gpus`: Value for `docker run --gpus`
+/// - Anything else sets the server's `WHISPER__*` settings, e.g.
+///   `compute_type: int8` becomes `WHISPER__COMPUTE_TYPE=int8`
+pub fn generate_run_args(
+    model: &str,
+    host: &str,
+    port: u16,
+    params: &HashMap<String, Value>,
+    container_name: &str,
+) -> Vec<String> {
+    let string_param = |key: &str| params.get(key).and_then(|v| v.as_str());
+
+    let mut args = vec![
+        "run".to_string(),
+        "-d".to_string(),
+        "--name".to_string(),
+        container_name.to_string(),
+        "-p".to_string(),
+        format!("{}:{}:{}", host, port, CONTAINER_PORT),
+    ];
+
+    if let Some(gpus) = string_param("gpus") {
+        args.push("--gpus".to_string());
+        args.push(gpus.to_string());
+    }
+
+    args.push("-e".to_string());
+    args.push(format!("WHISPER__MODEL={}", model));
+
+    let mut settings: Vec<_> = params
+        .iter()
+        .filter(|(key, _)| !RUNNER_PARAMS.contains(&key.as_str()))
+        .collect();
+    settings.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (key, value) in settings {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Bool(_) | Value::Number(_) => value.to_string(),
+            _ => continue,
+        };
+        args.push("-e".to_string());
+        args.push(format!("WHISPER__{}={}", key.to_uppercase(), value));
+    }
+
+    args.push(string_param("image").unwrap_or(DEFAULT_IMAGE).to_string());
+    args
+}
+
+/// Generate the endpoint URL for a whisper runner
+pub fn endpoint_url(host: &str, port: u16) -> String {
+    format!("http://{}:{}/v1", host, port)
+}
+
+/// URL that answers once the backend has loaded its model
+///
+/// whisper.cpp only starts listening after loading the model, and has no
+/// model list to poll.
+pub fn health_url(endpoint: &str, backend: WhisperBackend) -> String {
+    let root = endpoint.trim_end_matches("/v1");
+    match backend {
+        WhisperBackend::WhisperCpp => format!("{}/", root),
+        WhisperBackend::FasterWhisper => format!("{}/health", root),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::docker::flag_value;
+
+    #[test]
+    fn test_backend() {
+        let with =
+            |backend: &str| HashMap::from([("backend".to_string(), Value::String(backend.into()))]);
+        assert_eq!(backend(&HashMap::new()), Ok(WhisperBackend::WhisperCpp));
+        assert_eq!(
+            backend(&with("faster-whisper")),
+            Ok(WhisperBackend::FasterWhisper)
+        );
+        assert!(backend(&with("openai")).is_err());
+    }
+
+    #[test]
+    fn test_generate_args() {
+        let params = HashMap::from([
+            ("threads".to_string(), Value::Number(4.into())),
+            ("language".to_string(), Value::String("en".into())),
+            ("convert".to_string(), Value::Bool(true)),
+            ("translate".to_string(), Value::Bool(false)),
+            ("backend".to_string(), Value::String("whisper.cpp".into())),
+        ]);
+        let args = generate_args("ggml-base.en.bin", "127.0.0.1", 8178, &params);
+
+        assert_eq!(flag_value(&args, "--model"), Some("ggml-base.en.bin"));
+        assert_eq!(flag_value(&args, "--port"), Some("8178"));
+        assert_eq!(
+            flag_value(&args, "--inference-path"),
+            Some(TRANSCRIPTION_PATH)
+        );
+        assert_eq!(flag_value(&args, "--threads"), Some("4"));
+        assert_eq!(flag_value(&args, "--language"), Some("en"));
+        assert!(args.contains(&"--convert".to_string()));
+        assert!(!args.contains(&"--translate".to_string()));
+        assert!(!args.contains(&"--backend".to_string()));
+    }
+
+    #[test]
+    fn test_generate_run_args() {
+        let params = HashMap::from([
+            (
+                "backend".to_string(),
+                Value::String("faster-whisper".into()),
+            ),
+            ("compute_type".to_string(), Value::String("int8".into())),
+            ("gpus".to_string(), Value::String("all".into())),
+        ]);
+        let args = generate_run_args(
+            "Systran/faster-whisper-small",
+            "0.0.0.0",
+            8178,
+            &params,
+            "llmnet-ears",
+        );
+
+        assert_eq!(&args[..4], ["run", "-d", "--name", "llmnet-ears"]);
+        assert_eq!(flag_value(&args, "-p"), Some("0.0.0.0:8178:8000"));
+        assert_eq!(flag_value(&args, "--gpus"), Some("all"));
+        assert!(args.contains(&"WHISPER__MODEL=Systran/faster-whisper-small".to_string()));
+        assert!(args.contains(&"WHISPER__COMPUTE_TYPE=int8".to_string()));
+        assert!(!args.iter().any(|a| a.starts_with("WHISPER__BACKEND")));
+        assert_eq!(args.last().map(String::as_str), Some(DEFAULT_IMAGE));
+    }
+
+    #[test]
+    fn test_urls() {
+        let endpoint = endpoint_url("127.0.0.1", 8178);
+        assert_eq!(endpoint, "http://127.0.0.1:8178/v1");
+        assert_eq!(
+            health_url(&endpoint, WhisperBackend::WhisperCpp),
+            "http://127.0.0.1:8178/"
+        );
+        assert_eq!(
+            health_url(&endpoint, WhisperBackend::FasterWhisper),
+            "http://127.0.0.1:8178/health"
+        );
+    }
+}
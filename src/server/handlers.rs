@@ -1,23 +1,54 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
 use axum::{
-    body::Body,
-    extract::{Path, Query, State},
-    http::{HeaderMap, StatusCode},
-    response::IntoResponse,
-    routing::{delete, get, post},
+    body::{Body, Bytes},
+    extract::{
+        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+        DefaultBodyLimit, Path, Query, State,
+    },
+    http::{header, HeaderMap, Request, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{any, delete, get, post},
     Json, Router,
 };
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 use tokio_util::io::ReaderStream;
-use tracing::error;
+use tower::ServiceExt;
+use tracing::{debug, error};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 use uuid::Uuid;
 
-use crate::client::Message;
-use crate::cluster::{AssignmentResponse, PipelineAssignment};
-use crate::config::models::{ModelConfig, RunnerType};
+use crate::client::{
+    EmbeddingRequest, EmbeddingResponse, ImageUrl, Message, Tool, ToolCall, ToolChoice,
+    TranscriptionRequest,
+};
+use crate::cluster::{spawn_assignment_runners, AssignmentResponse, PipelineAssignment};
+use crate::config::models::ModelConfig;
+use crate::config::{RequestLimits, DEFAULT_MAX_BODY_BYTES};
+use crate::runtime::handoff::parse_handoff_depth;
+use crate::runtime::runner_logs::{follow_log, read_tail};
+use crate::runtime::session::estimate_tokens;
+use crate::runtime::{
+    model_cache, BreakerStatus, BudgetUsage, CachedArtifact, ConcurrencyStatus, DeadLetter,
+    HookError, HookStats, MeshHop, MeshReply, PipelineEvent, PipelineOutput, PipelineProcessor,
+    PipelineRequest, ProcessorError, PruneReport, RequestTrace, RouteStep, SharedRunnerManager,
+    Topology, HANDOFF_DEPTH_HEADER, MESH_TOKEN_HEADER,
+};
+use crate::server::pipelines::{
+    pipeline_path, released_models, with_runner_endpoints, HostedPipeline, HostedPipelineInfo,
+};
 use crate::server::state::AppState;
 
 /// OpenAI-compatible chat completion request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<Message>,
@@ -27,10 +58,18 @@ pub struct ChatCompletionRequest {
     pub temperature: Option<f32>,
     #[serde(default)]
     pub stream: bool,
+    /// Conversation to continue; the `X-Session-Id` header takes precedence
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Tools offered to handler nodes whose models support them
+    #[serde(default)]
+    pub tools: Vec<Tool>,
+    #[serde(default)]
+    pub tool_choice: Option<ToolChoice>,
 }
 
 /// OpenAI-compatible chat completion response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatCompletionResponse {
     pub id: String,
     pub object: String,
@@ -40,38 +79,324 @@ pub struct ChatCompletionResponse {
     pub usage: ResponseUsage,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ResponseChoice {
     pub index: u32,
     pub message: Message,
     pub finish_reason: String,
 }
 
-#[derive(Debug, Serialize)]
+/// One server-sent event of a streamed chat completion
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChunkChoice {
+    pub index: u32,
+    pub delta: ChunkDelta,
+    pub finish_reason: Option<String>,
+}
+
+/// What a chunk adds to the answer
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ChunkDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ResponseUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
 }
 
+/// Error returned by worker endpoints
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+    /// Machine-readable reason, e.g. "model_not_found"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl ErrorResponse {
+    pub fn new(error: impl Into<String>) -> Self {
+        Self {
+            error: error.into(),
+            code: None,
+        }
+    }
+}
+
+/// Error returned by the OpenAI-compatible endpoints, in OpenAI's format
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OpenAiErrorResponse {
+    pub error: OpenAiError,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OpenAiError {
+    pub message: String,
+    #[serde(rename = "type")]
+    pub error_type: String,
+    /// Request field at fault, if any
+    pub param: Option<String>,
+    pub code: Option<String>,
+    /// Request the error happened in, to find it in logs and traces
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<Uuid>,
+}
+
+impl OpenAiErrorResponse {
+    /// An `invalid_request_error` with a machine-readable code
+    pub fn invalid_request(message: impl Into<String>, param: Option<&str>, code: &str) -> Self {
+        Self {
+            error: OpenAiError {
+                message: message.into(),
+                error_type: "invalid_request_error".to_string(),
+                param: param.map(str::to_string),
+                code: Some(code.to_string()),
+                request_id: None,
+            },
+        }
+    }
+
+    /// The status and body a failed pipeline run is answered with
+    ///
+    /// Messages name the node that failed but not the underlying error,
+    /// which can carry upstream URLs and provider responses; that is logged
+    /// under the request ID instead.
+    pub fn from_processor_error(error: &ProcessorError, request_id: Uuid) -> (StatusCode, Self) {
+        let (status, error_type, code, message) = match error {
+            ProcessorError::HookError(HookError::Aborted(_)) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_request_error",
+                "request_rejected",
+                "The request was rejected by the pipeline".to_string(),
+            ),
+            ProcessorError::RateLimited(node, _) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limit_error",
+                "rate_limit_exceeded",
+                format!("The model behind '{}' is rate limited; retry later", node),
+            ),
+            ProcessorError::BudgetExhausted(node, _) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "insufficient_quota",
+                "insufficient_quota",
+                format!("The monthly budget of the model behind '{}' is spent", node),
+            ),
+            ProcessorError::ApiError(_) => (
+                StatusCode::BAD_GATEWAY,
+                "server_error",
+                "upstream_error",
+                "A model in the pipeline failed to answer".to_string(),
+            ),
+            ProcessorError::AdapterFailed(node, _)
+            | ProcessorError::AggregationFailed(node, _)
+            | ProcessorError::RetrievalFailed(node, _)
+            | ProcessorError::RoutingFunctionFailed(node, _) => (
+                StatusCode::BAD_GATEWAY,
+                "server_error",
+                "upstream_error",
+                format!("Node '{}' failed", node),
+            ),
+            ProcessorError::CircuitOpen(node) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "server_error",
+                "circuit_open",
+                format!("Node '{}' is failing and not taking requests", node),
+            ),
+            ProcessorError::DeadlineExceeded(node) => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "timeout_error",
+                "deadline_exceeded",
+                format!("The request ran out of time at '{}'", node),
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "server_error",
+                "pipeline_error",
+                "The pipeline failed to process the request".to_string(),
+            ),
+        };
+        let body = Self {
+            error: OpenAiError {
+                message,
+                error_type: error_type.to_string(),
+                param: None,
+                code: Some(code.to_string()),
+                request_id: Some(request_id),
+            },
+        };
+        (status, body)
+    }
+}
+
+/// Check a chat completion's messages against the composition's limits
+fn check_request_limits(
+    limits: &RequestLimits,
+    messages: &[Message],
+) -> Result<(), OpenAiErrorResponse> {
+    if let Some(max) = limits.max_messages.filter(|max| messages.len() > *max) {
+        return Err(OpenAiErrorResponse::invalid_request(
+            format!(
+                "The request has {} messages; at most {} are allowed",
+                messages.len(),
+                max
+            ),
+            Some("messages"),
+            "too_many_messages",
+        ));
+    }
+    let tokens: usize = messages.iter().map(|m| estimate_tokens(&m.content)).sum();
+    if let Some(max) = limits.max_prompt_tokens.filter(|max| tokens > *max) {
+        return Err(OpenAiErrorResponse::invalid_request(
+            format!(
+                "The messages are about {} tokens; at most {} are allowed",
+                tokens, max
+            ),
+            Some("messages"),
+            "context_length_exceeded",
+        ));
+    }
+    Ok(())
+}
+
+/// Refuse chat completion bodies over the composition's `max-body-bytes`
+/// before more than that is read
+async fn limit_body(State(state): State<AppState>, request: Request<Body>, next: Next) -> Response {
+    let limit = state
+        .composition()
+        .request_limits
+        .as_ref()
+        .map_or(DEFAULT_MAX_BODY_BYTES, |l| l.max_body_bytes());
+    let too_large = || {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(OpenAiErrorResponse::invalid_request(
+                format!("The request body is larger than {} bytes", limit),
+                None,
+                "request_too_large",
+            )),
+        )
+            .into_response()
+    };
+
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if declared.is_some_and(|length| length > limit) {
+        return too_large();
+    }
+    let (parts, body) = request.into_parts();
+    match axum::body::to_bytes(body, limit).await {
+        Ok(bytes) => {
+            next.run(Request::from_parts(parts, Body::from(bytes)))
+                .await
+        }
+        Err(_) => too_large(),
+    }
+}
+
+/// The OpenAI-style response to a failed pipeline run, after logging the
+/// full error under its request ID
+fn pipeline_error(request_id: Uuid, error: &ProcessorError) -> Response {
+    error!("Request {} failed: {}", request_id, error);
+    let (status, body) = OpenAiErrorResponse::from_processor_error(error, request_id);
+    (status, Json(body)).into_response()
+}
+
 /// Health check endpoint
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "status",
+    responses((status = 200, description = "Worker is up"))
+)]
 pub async fn health() -> impl IntoResponse {
     StatusCode::OK
 }
 
 /// Pipeline status endpoint
+#[utoipa::path(
+    get,
+    path = "/status",
+    tag = "status",
+    responses((status = 200, body = PipelineStatus))
+)]
 pub async fn status(State(state): State<AppState>) -> impl IntoResponse {
     let status = PipelineStatus {
         nodes: state.nodes.len(),
         active_requests: state.active_request_count(),
+        circuit_breakers: state
+            .processor
+            .get()
+            .map(|p| p.breaker_states())
+            .unwrap_or_default(),
+        models: state
+            .processor
+            .get()
+            .map(|p| p.concurrency_states())
+            .unwrap_or_default(),
+        hooks: state
+            .processor
+            .get()
+            .map(|p| p.hook_stats())
+            .unwrap_or_default(),
+        heartbeat_failures: match &state.metrics {
+            Some(metrics) => Some(metrics.read().await.heartbeat_failures()),
+            None => None,
+        },
     };
     Json(status)
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct PipelineStatus {
     nodes: usize,
     active_requests: usize,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    circuit_breakers: BTreeMap<String, BreakerStatus>,
+    /// Calls in flight to each model
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    models: BTreeMap<String, ConcurrencyStatus>,
+    /// Hook calls by function: counts, failures, timeouts and durations
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    hooks: BTreeMap<String, HookStats>,
+    /// Heartbeats to the control plane that failed in a row (workers only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heartbeat_failures: Option<u32>,
+}
+
+/// The loaded composition as a graph
+///
+/// Endpoints are only known once the pipeline's processor is running.
+#[utoipa::path(
+    get,
+    path = "/v1/topology",
+    tag = "status",
+    responses((status = 200, body = Topology))
+)]
+pub async fn topology(State(state): State<AppState>) -> impl IntoResponse {
+    let topology = Topology::from_composition(&state.composition());
+    Json(match state.processor.get() {
+        Some(processor) => topology.with_endpoints(processor.node_endpoints()),
+        None => topology,
+    })
 }
 
 // ============================================================================
@@ -79,14 +404,15 @@ struct PipelineStatus {
 // ============================================================================
 
 /// Request to spawn a runner
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SpawnRunnerRequest {
     pub name: String,
+    #[schema(value_type = Object)]
     pub config: ModelConfig,
 }
 
 /// Response from spawning a runner
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SpawnRunnerResponse {
     pub name: String,
     pub endpoint: String,
@@ -94,18 +420,32 @@ pub struct SpawnRunnerResponse {
 }
 
 /// List of running models
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct RunnerListResponse {
     pub runners: Vec<RunnerInfo>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct RunnerInfo {
     pub name: String,
     pub endpoint: Option<String>,
+    /// How long the runner's warm-up requests took
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warm_up_ms: Option<u64>,
 }
 
 /// Spawn a model runner (worker endpoint)
+#[utoipa::path(
+    post,
+    path = "/v1/runners/spawn",
+    tag = "runners",
+    request_body = SpawnRunnerRequest,
+    responses(
+        (status = 200, body = SpawnRunnerResponse),
+        (status = 500, description = "Runner failed to start", body = ErrorResponse),
+        (status = 503, description = "Not running as a worker", body = ErrorResponse)
+    )
+)]
 pub async fn spawn_runner(
     State(state): State<AppState>,
     Json(request): Json<SpawnRunnerRequest>,
@@ -113,31 +453,41 @@ pub async fn spawn_runner(
     let Some(manager) = &state.runner_manager else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({
-                "error": "Runner manager not available"
-            })),
+            Json(serde_json::json!(ErrorResponse::new(
+                "Runner manager not available"
+            ))),
         );
     };
 
     match manager.spawn_runner(&request.name, &request.config).await {
-        Ok(endpoint) => (
-            StatusCode::OK,
-            Json(serde_json::json!(SpawnRunnerResponse {
-                name: request.name,
-                endpoint,
-                status: "running".to_string(),
-            })),
-        ),
+        Ok(endpoint) => {
+            persist_runners(&state).await;
+            (
+                StatusCode::OK,
+                Json(serde_json::json!(SpawnRunnerResponse {
+                    name: request.name,
+                    endpoint,
+                    status: "running".to_string(),
+                })),
+            )
+        }
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": format!("Failed to spawn runner: {}", e)
-            })),
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Failed to spawn runner: {}",
+                e
+            )))),
         ),
     }
 }
 
 /// List running models (worker endpoint)
+#[utoipa::path(
+    get,
+    path = "/v1/runners",
+    tag = "runners",
+    responses((status = 200, body = RunnerListResponse))
+)]
 pub async fn list_runners(State(state): State<AppState>) -> impl IntoResponse {
     let Some(manager) = &state.runner_manager else {
         return Json(RunnerListResponse { runners: vec![] });
@@ -148,7 +498,14 @@ pub async fn list_runners(State(state): State<AppState>) -> impl IntoResponse {
         .into_iter()
         .map(|name| {
             let endpoint = manager.get_endpoint(&name);
-            RunnerInfo { name, endpoint }
+            let warm_up_ms = manager
+                .warm_up_duration(&name)
+                .map(|d| d.as_millis() as u64);
+            RunnerInfo {
+                name,
+                endpoint,
+                warm_up_ms,
+            }
         })
         .collect();
 
@@ -156,6 +513,17 @@ pub async fn list_runners(State(state): State<AppState>) -> impl IntoResponse {
 }
 
 /// Stop a running model (worker endpoint)
+#[utoipa::path(
+    delete,
+    path = "/v1/runners/{name}",
+    tag = "runners",
+    params(("name" = String, Path, description = "Model the runner serves")),
+    responses(
+        (status = 200, description = "Runner stopped"),
+        (status = 404, description = "Runner not found", body = ErrorResponse),
+        (status = 503, description = "Not running as a worker", body = ErrorResponse)
+    )
+)]
 pub async fn stop_runner(
     State(state): State<AppState>,
     Path(name): Path<String>,
@@ -163,29 +531,43 @@ pub async fn stop_runner(
     let Some(manager) = &state.runner_manager else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({
-                "error": "Runner manager not available"
-            })),
+            Json(serde_json::json!(ErrorResponse::new(
+                "Runner manager not available"
+            ))),
         );
     };
 
     match manager.stop_runner(&name).await {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(serde_json::json!({
-                "status": "stopped",
-                "name": name
-            })),
-        ),
+        Ok(_) => {
+            persist_runners(&state).await;
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "status": "stopped",
+                    "name": name
+                })),
+            )
+        }
         Err(e) => (
             StatusCode::NOT_FOUND,
-            Json(serde_json::json!({
-                "error": format!("Failed to stop runner: {}", e)
-            })),
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Failed to stop runner: {}",
+                e
+            )))),
         ),
     }
 }
 
+/// Record the runner containers now running in the worker state file
+async fn persist_runners(state: &AppState) {
+    let (Some(store), Some(manager)) = (&state.worker_state, &state.runner_manager) else {
+        return;
+    };
+    if let Err(e) = store.record_runners(manager.runner_records()).await {
+        tracing::warn!("Failed to persist runner state: {}", e);
+    }
+}
+
 // ============================================================================
 // Pipeline Assignment Endpoint (Worker Mode - receives work from Control Plane)
 // ============================================================================
@@ -195,12 +577,36 @@ pub async fn stop_runner(
 /// This endpoint is called by the control plane orchestrator when scheduling
 /// a pipeline to this worker node. It will:
 /// 1. Spawn any required model runners (Docker, Ollama, etc.)
-/// 2. Initialize the pipeline processor
-/// 3. Return the endpoint where the pipeline is accessible
+/// 2. Build the pipeline's own processor, next to any other pipelines it hosts
+/// 3. Serve it on the assigned port, or under `/pipelines/{name}/`
+/// 4. Return the endpoint where the pipeline is accessible
+#[utoipa::path(
+    post,
+    path = "/v1/assignments",
+    tag = "cluster",
+    request_body = PipelineAssignment,
+    responses(
+        (status = 200, body = AssignmentResponse),
+        (status = 500, description = "A runner failed to start", body = AssignmentResponse),
+        (status = 503, description = "Not running as a worker", body = AssignmentResponse)
+    )
+)]
 pub async fn receive_assignment(
     State(state): State<AppState>,
     Json(assignment): Json<PipelineAssignment>,
 ) -> impl IntoResponse {
+    let (status, response) = apply_assignment(&state, assignment).await;
+    (status, Json(response))
+}
+
+/// Start a pipeline assignment's runners and record the assignment
+///
+/// Shared by `POST /v1/assignments` and assignments pushed over the node
+/// session.
+pub async fn apply_assignment(
+    state: &AppState,
+    assignment: PipelineAssignment,
+) -> (StatusCode, AssignmentResponse) {
     tracing::info!(
         "Received pipeline assignment: {}/{} with {} replicas",
         assignment.namespace,
@@ -212,56 +618,105 @@ pub async fn receive_assignment(
     let Some(manager) = &state.runner_manager else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(AssignmentResponse {
+            AssignmentResponse {
                 success: false,
                 endpoint: None,
                 error: Some("Runner manager not available on this worker".to_string()),
-            }),
+            },
         );
     };
+    let (namespace, name) = (&assignment.namespace, &assignment.name);
+    state.replicas.starting(namespace, name, assignment.port);
 
-    // Spawn runners for each model that needs one
-    for (model_name, model_def) in &assignment.composition.models {
-        let config = model_def.to_config();
-
-        // Check if this model needs a runner (Docker, Ollama, vLLM, llama.cpp)
-        let needs_runner = matches!(
-            config.runner,
-            RunnerType::Docker | RunnerType::Ollama | RunnerType::Vllm | RunnerType::LlamaCpp
+    if let Err(e) = spawn_assignment_runners(
+        manager,
+        &assignment,
+        state.control_plane_url.as_deref(),
+        state.control_plane_key.as_deref(),
+    )
+    .await
+    {
+        tracing::error!("{}", e);
+        state.replicas.failed(namespace, name, &e);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AssignmentResponse {
+                success: false,
+                endpoint: None,
+                error: Some(e),
+            },
         );
+    }
 
-        if needs_runner {
-            tracing::info!(
-                "Spawning {} runner for model '{}'...",
-                config.type_name(),
-                model_name
-            );
-
-            match manager.spawn_runner(model_name, &config).await {
-                Ok(endpoint) => {
-                    tracing::info!("Runner for '{}' ready at {}", model_name, endpoint);
-                }
-                Err(e) => {
-                    tracing::error!("Failed to spawn runner for '{}': {}", model_name, e);
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(AssignmentResponse {
-                            success: false,
-                            endpoint: None,
-                            error: Some(format!(
-                                "Failed to spawn runner for '{}': {}",
-                                model_name, e
-                            )),
-                        }),
-                    );
-                }
+    let composition = with_runner_endpoints(assignment.composition.clone(), manager);
+    let pipeline_state =
+        match state.for_pipeline(namespace, composition, manager, assignment.mesh()) {
+            Ok(pipeline_state) => pipeline_state,
+            Err(e) => {
+                let error = format!("Can't serve pipeline {}: {}", assignment.name, e);
+                tracing::error!("{}", error);
+                state.replicas.failed(namespace, name, &error);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    AssignmentResponse {
+                        success: false,
+                        endpoint: None,
+                        error: Some(error),
+                    },
+                );
             }
+        };
+
+    if let Some(store) = &state.worker_state {
+        if let Err(e) = store
+            .record_assignment(assignment.clone(), manager.runner_records())
+            .await
+        {
+            tracing::warn!("Failed to persist assignment: {}", e);
         }
     }
 
-    // Build the endpoint URL for this pipeline
-    // In host networking mode, we use the assigned port
-    let endpoint = format!("http://{}:{}", state.bind_addr, assignment.port);
+    // Free the port of the version being replaced before binding it again
+    if let Some(mut previous) = state.pipelines.remove(&assignment.name) {
+        previous.close().await;
+    }
+
+    // Serve the pipeline on its assigned port, or under a path prefix on
+    // ours when that's the same port or can't be bound
+    let mut pipeline = HostedPipeline::new(&assignment.namespace, pipeline_state);
+    let worker_port = state.port.unwrap_or(assignment.port);
+    let prefixed = format!(
+        "http://{}:{}{}",
+        state.bind_addr,
+        worker_port,
+        pipeline_path(&assignment.name)
+    );
+    let endpoint = if state.port == Some(assignment.port) {
+        prefixed
+    } else {
+        match pipeline.listen(&state.bind_addr, assignment.port).await {
+            Ok(port) => format!("http://{}:{}", state.bind_addr, port),
+            Err(e) => {
+                tracing::warn!(
+                    "Can't bind port {} for pipeline {}, serving it at {}: {}",
+                    assignment.port,
+                    assignment.name,
+                    prefixed,
+                    e
+                );
+                prefixed
+            }
+        }
+    };
+    state.pipelines.insert(&assignment.name, pipeline);
+    let runners = assignment
+        .composition
+        .models
+        .keys()
+        .filter(|m| manager.is_running(m))
+        .cloned()
+        .collect();
+    state.replicas.running(namespace, name, runners);
 
     tracing::info!(
         "Pipeline {}/{} ready at {}",
@@ -272,161 +727,1419 @@ pub async fn receive_assignment(
 
     (
         StatusCode::OK,
-        Json(AssignmentResponse {
+        AssignmentResponse {
             success: true,
             endpoint: Some(endpoint),
             error: None,
-        }),
+        },
     )
 }
 
-/// Chat completions endpoint (OpenAI-compatible)
-pub async fn chat_completions(
+/// Stop hosting a pipeline (worker endpoint)
+///
+/// The control plane calls this when the pipeline is deleted. The runners
+/// of its models are stopped unless another pipeline on this worker uses
+/// them, and the assignment is dropped from the worker state file.
+#[utoipa::path(
+    delete,
+    path = "/v1/assignments/{namespace}/{name}",
+    tag = "cluster",
+    params(
+        ("namespace" = String, Path, description = "Pipeline namespace"),
+        ("name" = String, Path, description = "Pipeline name")
+    ),
+    responses(
+        (status = 200, description = "Pipeline no longer hosted"),
+        (status = 404, description = "Pipeline not hosted here", body = ErrorResponse)
+    )
+)]
+pub async fn remove_assignment(
     State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(request): Json<ChatCompletionRequest>,
+    Path((namespace, name)): Path<(String, String)>,
 ) -> impl IntoResponse {
-    // Extract request ID from headers or generate new one
-    let request_id = headers
-        .get("x-request-id")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| Uuid::parse_str(s).ok())
-        .unwrap_or_else(Uuid::new_v4);
-
-    // Extract the user prompt from messages
-    let user_prompt = request
-        .messages
-        .iter()
-        .rev()
-        .find(|m| m.role == "user")
-        .map(|m| m.content.clone())
-        .unwrap_or_default();
-
-    // Process through the pipeline if processor is available
-    let content = if let Some(processor) = &state.processor {
-        match processor.process(&user_prompt).await {
-            Ok(response) => response,
-            Err(e) => format!("Pipeline error: {}", e),
-        }
-    } else {
-        format!("No pipeline processor configured for: {}", user_prompt)
+    let Some(mut pipeline) = state.pipelines.remove_in(&namespace, &name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Pipeline {}/{} is not hosted here",
+                namespace, name
+            )))),
+        );
     };
+    pipeline.close().await;
+    state.replicas.remove(&namespace, &name);
 
-    let response = ChatCompletionResponse {
-        id: format!("chatcmpl-{}", request_id),
-        object: "chat.completion".to_string(),
-        created: chrono::Utc::now().timestamp(),
-        model: request.model.clone(),
-        choices: vec![ResponseChoice {
-            index: 0,
-            message: Message {
-                role: "assistant".to_string(),
-                content,
-            },
-            finish_reason: "stop".to_string(),
-        }],
-        usage: ResponseUsage {
-            prompt_tokens: 0,
-            completion_tokens: 0,
-            total_tokens: 0,
-        },
-    };
+    let mut in_use = state.pipelines.models_in_use();
+    in_use.extend(state.composition().models.keys().cloned());
+    let released = released_models(&pipeline.state.composition(), &in_use);
 
-    // Add request ID to response headers
-    let mut response_headers = HeaderMap::new();
-    response_headers.insert("x-request-id", request_id.to_string().parse().unwrap());
+    let mut stopped = Vec::new();
+    if let Some(manager) = &state.runner_manager {
+        for model in released.into_iter().filter(|m| manager.is_running(m)) {
+            match manager.stop_runner(&model).await {
+                Ok(()) => stopped.push(model),
+                Err(e) => tracing::warn!("Failed to stop runner for '{}': {}", model, e),
+            }
+        }
+        if let Some(store) = &state.worker_state {
+            if let Err(e) = store
+                .remove_assignment(&namespace, &name, manager.runner_records())
+                .await
+            {
+                tracing::warn!("Failed to persist assignment removal: {}", e);
+            }
+        }
+    }
 
-    (response_headers, Json(response))
+    tracing::info!(
+        "Stopped hosting pipeline {}/{}; stopped runners [{}]",
+        namespace,
+        name,
+        stopped.join(", ")
+    );
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "status": "removed",
+            "name": name,
+            "stopped": stopped,
+        })),
+    )
 }
 
-/// Query parameters for logs endpoint
-#[derive(Debug, Deserialize)]
-pub struct LogsQuery {
-    /// Follow log output (like tail -f)
-    #[serde(default)]
-    pub follow: bool,
-    /// Number of lines to show from the end
-    #[serde(default = "default_tail")]
-    pub tail: usize,
+/// Pipelines this worker hosts
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines",
+    tag = "cluster",
+    responses((status = 200, body = Vec<HostedPipelineInfo>))
+)]
+pub async fn list_pipelines(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.pipelines.list())
 }
 
-fn default_tail() -> usize {
-    100
+/// Forward a request to a hosted pipeline, e.g.
+/// `/pipelines/chat/v1/chat/completions` to the `chat` pipeline's
+/// `/v1/chat/completions`
+pub async fn pipeline_request(
+    State(state): State<AppState>,
+    Path((name, path)): Path<(String, String)>,
+    mut request: Request<Body>,
+) -> Response {
+    let Some(router) = state.pipelines.router(&name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse::new(format!("No pipeline named '{}'", name))),
+        )
+            .into_response();
+    };
+
+    let uri = match request.uri().query() {
+        Some(query) => format!("/{}?{}", path, query),
+        None => format!("/{}", path),
+    };
+    match uri.parse() {
+        Ok(uri) => *request.uri_mut() = uri,
+        Err(_) => return StatusCode::BAD_REQUEST.into_response(),
+    }
+    match router.oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
 }
 
-/// Stream container logs
-pub async fn stream_logs(
+/// Run a hop another worker of a split pipeline sent to a handler placed
+/// on this one
+///
+/// The hop must carry a token signed with the pipeline's mesh key for the
+/// handler it names, its request ID and its body.
+#[utoipa::path(
+    post,
+    path = "/v1/mesh/hops",
+    tag = "cluster",
+    request_body = MeshHop,
+    params(
+        ("x-llmnet-mesh-token" = String, Header, description = "Token signed with the pipeline's mesh key")
+    ),
+    responses(
+        (status = 200, body = MeshReply),
+        (status = 400, description = "Not a mesh hop", body = ErrorResponse),
+        (status = 401, description = "Missing, expired or forged mesh token", body = ErrorResponse),
+        (status = 404, description = "Not split across workers, or not a handler placed here", body = ErrorResponse),
+        (status = 502, description = "The handler failed", body = ErrorResponse),
+        (status = 503, description = "No pipeline processor configured", body = ErrorResponse)
+    )
+)]
+pub async fn mesh_hop(
     State(state): State<AppState>,
-    Path(container): Path<String>,
-    Query(params): Query<LogsQuery>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> impl IntoResponse {
-    let manager = match &state.runner_manager {
-        Some(m) => m,
-        None => {
+    // The token signs the body as sent, so it's checked before parsing
+    let hop: MeshHop = match serde_json::from_slice(&body) {
+        Ok(hop) => hop,
+        Err(e) => {
             return (
-                StatusCode::SERVICE_UNAVAILABLE,
-                Body::from("Runner manager not available"),
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!(ErrorResponse::new(e.to_string()))),
             )
-                .into_response();
         }
     };
-
-    // Check if container exists in our tracked processes
-    let containers = manager.list_containers();
-    if !containers.contains(&container) {
+    let Some(processor) = state.processor.get() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!(ErrorResponse::new(
+                "No pipeline processor configured"
+            ))),
+        );
+    };
+    let Some(key) = processor.mesh_key() else {
         return (
             StatusCode::NOT_FOUND,
-            Body::from(format!("Container '{}' not found", container)),
-        )
-            .into_response();
+            Json(serde_json::json!(ErrorResponse::new(
+                "Pipeline isn't split across workers"
+            ))),
+        );
+    };
+    let token = headers
+        .get(MESH_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let now = chrono::Utc::now().timestamp();
+    if !key.verify(token, &hop.node, hop.request_id, &body, now) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!(ErrorResponse::new("Invalid mesh token"))),
+        );
     }
 
-    // Start streaming logs
-    match manager
-        .stream_container_logs(&container, params.follow, Some(params.tail))
-        .await
-    {
-        Ok(mut child) => {
-            // Merge stdout and stderr into a single stream
-            let stdout = child.stdout.take();
-            let stderr = child.stderr.take();
-
-            match (stdout, stderr) {
-                (Some(stdout), Some(stderr)) => {
-                    // Create streams from both
-                    let stdout_stream = ReaderStream::new(stdout);
-                    let stderr_stream = ReaderStream::new(stderr);
-
-                    // Merge the streams
-                    let merged = futures::stream::select(stdout_stream, stderr_stream);
-
-                    let body = Body::from_stream(merged);
-                    (StatusCode::OK, body).into_response()
-                }
-                (Some(stdout), None) => {
-                    let stream = ReaderStream::new(stdout);
-                    let body = Body::from_stream(stream);
-                    (StatusCode::OK, body).into_response()
-                }
-                _ => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Body::from("Failed to capture log output"),
-                )
-                    .into_response(),
-            }
-        }
+    match processor.run_mesh_hop(&hop).await {
+        Ok((message, usage)) => (
+            StatusCode::OK,
+            Json(serde_json::json!(MeshReply { message, usage })),
+        ),
+        Err(e @ ProcessorError::HandlerNotFound(_)) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!(ErrorResponse::new(e.to_string()))),
+        ),
         Err(e) => {
-            error!("Failed to stream logs for '{}': {}", container, e);
+            error!(
+                "Mesh hop {} to '{}' failed: {}",
+                hop.request_id, hop.node, e
+            );
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Body::from(format!("Failed to stream logs: {}", e)),
+                StatusCode::BAD_GATEWAY,
+                Json(serde_json::json!(ErrorResponse::new(format!(
+                    "Node '{}' failed",
+                    hop.node
+                )))),
+            )
+        }
+    }
+}
+
+/// Request an immediate heartbeat (called by the control plane after
+/// scheduling so it sees the new pipelines without waiting an interval)
+#[utoipa::path(
+    post,
+    path = "/v1/heartbeat",
+    tag = "cluster",
+    responses(
+        (status = 202, description = "Heartbeat will be sent"),
+        (status = 503, description = "Not registered with a control plane")
+    )
+)]
+pub async fn request_heartbeat(State(state): State<AppState>) -> impl IntoResponse {
+    match &state.heartbeat_trigger {
+        Some(trigger) => {
+            trigger.notify_one();
+            StatusCode::ACCEPTED
+        }
+        None => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+/// Header naming the node a request should go to, bypassing the router;
+/// on responses with `trace-headers`, the route the request took
+pub const ROUTE_HEADER: &str = "x-llmnet-route";
+
+/// Prefix of headers that set pipeline variables, e.g. `X-LLMNet-Var-Tenant`
+pub const VAR_HEADER_PREFIX: &str = "x-llmnet-var-";
+
+/// Response header counting the nodes in `X-LLMNet-Route`
+pub const HOPS_HEADER: &str = "x-llmnet-hops";
+
+/// Response header with the time spent in each node of the route
+pub const HOP_LATENCY_HEADER: &str = "x-llmnet-hop-latency";
+
+/// Chat completions endpoint (OpenAI-compatible)
+///
+/// Clients that already know which handler they want can name it in the
+/// `X-LLMNet-Route` header or as the `model`, if the composition lists it
+/// under `route-overrides`. A disallowed header is rejected; a `model` that
+/// isn't an allowed node is ignored, as most clients always send one.
+///
+/// `X-LLMNet-Var-*` headers set the variables the composition declares
+/// under `header-variables`; others are ignored.
+///
+/// With `trace-headers` in the composition, the response describes the
+/// route taken: `X-LLMNet-Route: router>sales`, `X-LLMNet-Hops: 2` and
+/// `X-LLMNet-Hop-Latency: router=120ms, sales=340ms`.
+///
+/// With `"stream": true` the answer comes as server-sent
+/// `chat.completion.chunk` events ending with `data: [DONE]`. It is sent
+/// as the answering node generates it when the node can stream, and in one
+/// chunk otherwise. The route isn't known when the response starts, so
+/// streamed responses have no trace headers.
+#[utoipa::path(
+    post,
+    path = "/v1/chat/completions",
+    tag = "inference",
+    params(
+        ("x-request-id" = Option<String>, Header, description = "UUID to trace the request under"),
+        ("x-session-id" = Option<String>, Header, description = "Conversation to continue"),
+        ("x-llmnet-route" = Option<String>, Header, description = "Node to send the request to, bypassing the router")
+    ),
+    request_body = ChatCompletionRequest,
+    responses(
+        (status = 200, description = "Pipeline output; a streamed request that fails ends with an error event", content(
+            (ChatCompletionResponse = "application/json"),
+            (ChatCompletionChunk = "text/event-stream")
+        ),
+            headers(
+                ("x-llmnet-route" = String, description = "Nodes the request went through (with trace-headers)"),
+                ("x-llmnet-hops" = u32, description = "Number of nodes in the route (with trace-headers)"),
+                ("x-llmnet-hop-latency" = String, description = "Time spent in each node (with trace-headers)")
+            )
+        ),
+        (status = 400, description = "The messages exceed the composition's request-limits, the route header names a node that can't be routed to, or a hook rejected the request", body = OpenAiErrorResponse),
+        (status = 413, description = "The body exceeds the composition's max-body-bytes", body = OpenAiErrorResponse),
+        (status = 429, description = "A model in the pipeline is rate limited", body = OpenAiErrorResponse),
+        (status = 500, description = "The pipeline is misconfigured", body = OpenAiErrorResponse),
+        (status = 502, description = "A model or node in the pipeline failed", body = OpenAiErrorResponse),
+        (status = 503, description = "A node's circuit breaker is open", body = OpenAiErrorResponse),
+        (status = 504, description = "The pipeline's deadline-ms or a node's timeout-ms passed", body = OpenAiErrorResponse)
+    )
+)]
+pub async fn chat_completions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ChatCompletionRequest>,
+) -> impl IntoResponse {
+    if let Some(limits) = &state.composition().request_limits {
+        if let Err(error) = check_request_limits(limits, &request.messages) {
+            return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        }
+    }
+
+    // Extract request ID from headers or generate new one
+    let request_id = headers
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| Uuid::parse_str(s).ok())
+        .unwrap_or_else(Uuid::new_v4);
+
+    let session_id = headers
+        .get("x-session-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| request.session_id.clone())
+        .filter(|id| !id.is_empty());
+
+    let header_route = headers
+        .get(ROUTE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    if let (Some(route), Some(processor)) = (&header_route, state.processor.get()) {
+        if !processor.allows_route(route) {
+            let mut body = OpenAiErrorResponse::invalid_request(
+                format!("Route '{}' is not allowed by this pipeline", route),
+                None,
+                "route_not_allowed",
+            );
+            body.error.request_id = Some(request_id);
+            return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+        }
+    }
+
+    let user_prompt = last_user_prompt(&request.messages);
+
+    let processor = state.processor.get().map(|processor| {
+        let mut pipeline_request = PipelineRequest::with_id(request_id, user_prompt.clone())
+            .with_images(last_user_images(&request.messages))
+            .with_tools(request.tools.clone(), request.tool_choice.clone())
+            .with_tool_messages(messages_after_prompt(&request.messages))
+            .with_variables(header_variables(
+                &headers,
+                &state.composition().header_variables,
+            ))
+            .with_handoff_depth(parse_handoff_depth(
+                headers
+                    .get(HANDOFF_DEPTH_HEADER)
+                    .and_then(|v| v.to_str().ok()),
+            ));
+        let route = header_route
+            .or_else(|| Some(request.model.clone()).filter(|model| processor.allows_route(model)));
+        if let Some(route) = route {
+            pipeline_request = pipeline_request.with_route(route);
+        }
+        (processor, pipeline_request)
+    });
+    let no_processor = || PipelineOutput {
+        content: format!("No pipeline processor configured for: {}", user_prompt),
+        ..Default::default()
+    };
+
+    if request.stream {
+        let headers = response_headers(&state, request_id, session_id.clone(), &[]);
+        let (tx, rx) = mpsc::unbounded_channel();
+        let model = request.model.clone();
+        let fallback = no_processor();
+        tokio::spawn(async move {
+            let result = match processor {
+                Some((processor, pipeline_request)) => {
+                    stream_completion(&processor, pipeline_request, session_id, |chunk| {
+                        let _ = tx.send(completion_chunk(request_id, &model, chunk, None));
+                    })
+                    .await
+                }
+                None => Ok(fallback),
+            };
+            match result {
+                Ok(output) => {
+                    for (delta, finish_reason) in final_chunks(output) {
+                        let _ = tx.send(completion_chunk(request_id, &model, delta, finish_reason));
+                    }
+                }
+                // The status is already sent, so the error comes as an event
+                Err(e) => {
+                    error!("Request {} failed: {}", request_id, e);
+                    let (_, body) = OpenAiErrorResponse::from_processor_error(&e, request_id);
+                    let _ = tx.send(Event::default().data(serde_json::json!(body).to_string()));
+                }
+            }
+            let _ = tx.send(Event::default().data("[DONE]"));
+        });
+        let events = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv()
+                .await
+                .map(|event| (Ok::<_, std::convert::Infallible>(event), rx))
+        });
+        return (headers, Sse::new(events).keep_alive(KeepAlive::default())).into_response();
+    }
+
+    // Process through the pipeline if processor is available
+    let output = if let Some((processor, pipeline_request)) = processor {
+        let result = match &session_id {
+            Some(id) => processor.process_session(id, pipeline_request).await,
+            None => processor.complete(pipeline_request).await,
+        };
+        match result {
+            Ok(output) => output,
+            Err(e) => return pipeline_error(request_id, &e),
+        }
+    } else {
+        no_processor()
+    };
+
+    let headers = response_headers(&state, request_id, session_id, &output.route);
+    let response = completion_response(request_id, request.model.clone(), output);
+    (headers, Json(response)).into_response()
+}
+
+/// Run a request, handing each chunk of the answer to `send` as it is
+/// streamed. The output's content is left empty when it was streamed.
+async fn stream_completion(
+    processor: &PipelineProcessor,
+    request: PipelineRequest,
+    session_id: Option<String>,
+    mut send: impl FnMut(ChunkDelta),
+) -> Result<PipelineOutput, ProcessorError> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let run = async move {
+        match &session_id {
+            Some(id) => processor.process_session_streaming(id, request, &tx).await,
+            None => processor.complete_streaming(request, &tx).await,
+        }
+    };
+    let forward = async {
+        let mut streamed = false;
+        while let Some(event) = rx.recv().await {
+            if let PipelineEvent::Chunk { content, .. } = event {
+                let role = (!streamed).then(|| "assistant".to_string());
+                streamed = true;
+                send(ChunkDelta {
+                    role,
+                    content: Some(content),
+                    ..Default::default()
+                });
+            }
+        }
+        streamed
+    };
+    let (result, streamed) = tokio::join!(run, forward);
+    let mut output = result?;
+    if streamed {
+        output.content.clear();
+    }
+    Ok(output)
+}
+
+/// The chunks ending a streamed completion: what wasn't streamed of the
+/// output, then the finish reason
+fn final_chunks(output: PipelineOutput) -> Vec<(ChunkDelta, Option<&'static str>)> {
+    let finish_reason = if output.tool_calls.is_empty() {
+        "stop"
+    } else {
+        "tool_calls"
+    };
+    let mut chunks = Vec::new();
+    if !output.content.is_empty() || !output.tool_calls.is_empty() {
+        let delta = ChunkDelta {
+            role: Some("assistant".to_string()),
+            content: Some(output.content).filter(|c| !c.is_empty()),
+            tool_calls: output.tool_calls,
+        };
+        chunks.push((delta, None));
+    }
+    chunks.push((ChunkDelta::default(), Some(finish_reason)));
+    chunks
+}
+
+/// A server-sent event carrying a chunk of a chat completion
+fn completion_chunk(
+    request_id: Uuid,
+    model: &str,
+    delta: ChunkDelta,
+    finish_reason: Option<&str>,
+) -> Event {
+    let chunk = ChatCompletionChunk {
+        id: format!("chatcmpl-{}", request_id),
+        object: "chat.completion.chunk".to_string(),
+        created: chrono::Utc::now().timestamp(),
+        model: model.to_string(),
+        choices: vec![ChunkChoice {
+            index: 0,
+            delta,
+            finish_reason: finish_reason.map(str::to_string),
+        }],
+    };
+    Event::default().data(serde_json::to_string(&chunk).unwrap_or_default())
+}
+
+/// A pipeline's output as an OpenAI-style chat completion
+fn completion_response(
+    request_id: Uuid,
+    model: String,
+    output: PipelineOutput,
+) -> ChatCompletionResponse {
+    let finish_reason = if output.tool_calls.is_empty() {
+        "stop"
+    } else {
+        "tool_calls"
+    };
+
+    ChatCompletionResponse {
+        id: format!("chatcmpl-{}", request_id),
+        object: "chat.completion".to_string(),
+        created: chrono::Utc::now().timestamp(),
+        model,
+        choices: vec![ResponseChoice {
+            index: 0,
+            message: Message {
+                role: "assistant".to_string(),
+                content: output.content,
+                tool_calls: output.tool_calls,
+                ..Default::default()
+            },
+            finish_reason: finish_reason.to_string(),
+        }],
+        usage: ResponseUsage {
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+        },
+    }
+}
+
+/// Request and session IDs for a completion's response, plus the route
+/// taken with `trace-headers`
+fn response_headers(
+    state: &AppState,
+    request_id: Uuid,
+    session_id: Option<String>,
+    route: &[RouteStep],
+) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-request-id", request_id.to_string().parse().unwrap());
+    if let Some(value) = session_id.and_then(|id| id.parse().ok()) {
+        headers.insert("x-session-id", value);
+    }
+    if state.composition().trace_headers {
+        for (name, value) in route_headers(route) {
+            if let Ok(value) = value.parse() {
+                headers.insert(name, value);
+            }
+        }
+    }
+    headers
+}
+
+/// Query parameters of an audio upload
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct AudioQuery {
+    /// Spoken language as an ISO-639-1 code; detected when not set
+    pub language: Option<String>,
+    /// Audio input node to transcribe with (default: the first)
+    pub node: Option<String>,
+}
+
+/// Pipeline answer to an audio upload
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AudioCompletionResponse {
+    /// What the audio input node heard, as sent to the router
+    pub transcript: String,
+    #[serde(flatten)]
+    pub completion: ChatCompletionResponse,
+}
+
+/// Largest audio upload accepted, as with OpenAI's transcription API
+pub const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+
+/// Answer a spoken prompt
+///
+/// The request body is the audio file itself, e.g. `curl --data-binary
+/// @question.wav -H 'Content-Type: audio/wav'`. One of the composition's
+/// `audio-input` nodes transcribes it, and the transcript runs through the
+/// pipeline like the last user message of a chat completion. Pipeline
+/// failures are answered like the chat completion endpoint's.
+#[utoipa::path(
+    post,
+    path = "/v1/audio/completions",
+    tag = "inference",
+    params(
+        AudioQuery,
+        ("x-request-id" = Option<String>, Header, description = "UUID to trace the request under"),
+        ("x-session-id" = Option<String>, Header, description = "Conversation to continue")
+    ),
+    request_body(content = Vec<u8>, description = "Audio file (wav, mp3, ogg, webm, flac, m4a)", content_type = "audio/*"),
+    responses(
+        (status = 200, description = "Transcript and pipeline output", body = AudioCompletionResponse),
+        (status = 400, description = "The upload is empty", body = ErrorResponse),
+        (status = 404, description = "No such audio input node", body = ErrorResponse),
+        (status = 413, description = "The upload is larger than 25 MiB"),
+        (status = 422, description = "No speech was recognized", body = ErrorResponse),
+        (status = 502, description = "Transcription failed", body = ErrorResponse),
+        (status = 504, description = "The pipeline's deadline-ms or a node's timeout-ms passed", body = OpenAiErrorResponse),
+        (status = 503, description = "No pipeline processor, or the circuit breaker is open", body = ErrorResponse)
+    )
+)]
+pub async fn audio_completions(
+    State(state): State<AppState>,
+    Query(query): Query<AudioQuery>,
+    headers: HeaderMap,
+    audio: Bytes,
+) -> impl IntoResponse {
+    let error = |status: StatusCode, message: String| {
+        (status, Json(serde_json::json!(ErrorResponse::new(message)))).into_response()
+    };
+    let Some(processor) = state.processor.get() else {
+        return error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No pipeline processor configured".to_string(),
+        );
+    };
+    if audio.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "No audio uploaded".to_string());
+    }
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let upload = TranscriptionRequest {
+        model: String::new(),
+        file_name: audio_file_name(&content_type),
+        content_type,
+        audio: audio.to_vec(),
+        language: query.language,
+    };
+    let transcript = match processor.transcribe(query.node.as_deref(), upload).await {
+        Ok(text) if text.is_empty() => {
+            return error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "No speech recognized in the upload".to_string(),
+            )
+        }
+        Ok(text) => text,
+        Err(e) => {
+            let status = match e {
+                ProcessorError::NoAudioInputNode | ProcessorError::HandlerNotFound(_) => {
+                    StatusCode::NOT_FOUND
+                }
+                ProcessorError::CircuitOpen(_) => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            return error(status, e.to_string());
+        }
+    };
+
+    let request_id = headers
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| Uuid::parse_str(s).ok())
+        .unwrap_or_else(Uuid::new_v4);
+    let session_id = headers
+        .get("x-session-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .filter(|id| !id.is_empty());
+
+    let request = PipelineRequest::with_id(request_id, transcript.clone()).with_variables(
+        header_variables(&headers, &state.composition().header_variables),
+    );
+    let result = match &session_id {
+        Some(id) => processor.process_session(id, request).await,
+        None => processor.complete(request).await,
+    };
+    let output = match result {
+        Ok(output) => output,
+        Err(e) => return pipeline_error(request_id, &e),
+    };
+
+    let headers = response_headers(&state, request_id, session_id, &output.route);
+    let response = AudioCompletionResponse {
+        transcript,
+        completion: completion_response(request_id, "llmnet".to_string(), output),
+    };
+    (headers, Json(response)).into_response()
+}
+
+/// Forget a conversation session
+#[utoipa::path(
+    delete,
+    path = "/v1/sessions/{session_id}",
+    tag = "inference",
+    params(("session_id" = String, Path, description = "Session to forget")),
+    responses(
+        (status = 204, description = "Session forgotten"),
+        (status = 502, description = "The session store failed", body = ErrorResponse),
+        (status = 503, description = "No pipeline processor configured", body = ErrorResponse)
+    )
+)]
+pub async fn delete_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> impl IntoResponse {
+    let Some(processor) = state.processor.get() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!(ErrorResponse::new(
+                "No pipeline processor configured"
+            ))),
+        )
+            .into_response();
+    };
+
+    match processor.clear_session(&session_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!(ErrorResponse::new(e.to_string()))),
+        )
+            .into_response(),
+    }
+}
+
+/// Embeddings endpoint (OpenAI-compatible)
+///
+/// Served by the composition's embedding nodes, so clients can vectorize
+/// text with the same models the pipeline uses.
+#[utoipa::path(
+    post,
+    path = "/v1/embeddings",
+    tag = "inference",
+    request_body = EmbeddingRequest,
+    responses(
+        (status = 200, body = EmbeddingResponse),
+        (status = 404, description = "The composition has no embedding node, or none serves the model (code \"model_not_found\")", body = ErrorResponse),
+        (status = 502, description = "The model call failed", body = ErrorResponse),
+        (status = 503, description = "No pipeline processor, or the circuit breaker is open", body = ErrorResponse)
+    )
+)]
+pub async fn embeddings(
+    State(state): State<AppState>,
+    Json(request): Json<EmbeddingRequest>,
+) -> impl IntoResponse {
+    let Some(processor) = state.processor.get() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!(ErrorResponse::new(
+                "No pipeline processor configured"
+            ))),
+        );
+    };
+
+    match processor.embed(request).await {
+        Ok(response) => (StatusCode::OK, Json(serde_json::json!(response))),
+        Err(e) => {
+            let (status, code) = match e {
+                ProcessorError::NoEmbeddingNode => (StatusCode::NOT_FOUND, None),
+                ProcessorError::ModelNotFound(_) => {
+                    (StatusCode::NOT_FOUND, Some("model_not_found"))
+                }
+                ProcessorError::CircuitOpen(_) => (StatusCode::SERVICE_UNAVAILABLE, None),
+                _ => (StatusCode::BAD_GATEWAY, None),
+            };
+            (
+                status,
+                Json(serde_json::json!(ErrorResponse {
+                    code: code.map(String::from),
+                    ..ErrorResponse::new(e.to_string())
+                })),
+            )
+        }
+    }
+}
+
+/// Look up the trace of a recent request
+///
+/// Traces live in a bounded in-memory store, so old requests eventually
+/// return 404.
+#[utoipa::path(
+    get,
+    path = "/v1/requests/{request_id}",
+    tag = "inference",
+    params(("request_id" = String, Path, description = "ID from the `X-Request-Id` response header")),
+    responses(
+        (status = 200, body = RequestTrace),
+        (status = 400, description = "Not a UUID", body = ErrorResponse),
+        (status = 404, description = "No trace kept for the request", body = ErrorResponse),
+        (status = 503, description = "No pipeline processor configured", body = ErrorResponse)
+    )
+)]
+pub async fn get_request_trace(
+    State(state): State<AppState>,
+    Path(request_id): Path<String>,
+) -> impl IntoResponse {
+    let Ok(request_id) = Uuid::parse_str(&request_id) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Invalid request ID: {}",
+                request_id
+            )))),
+        );
+    };
+
+    let Some(processor) = state.processor.get() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!(ErrorResponse::new(
+                "No pipeline processor configured"
+            ))),
+        );
+    };
+
+    match processor.trace(&request_id) {
+        Some(trace) => (StatusCode::OK, Json(serde_json::json!(trace))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "No trace for request {}",
+                request_id
+            )))),
+        ),
+    }
+}
+
+/// Failed requests kept by the pipeline
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetterListResponse {
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+/// Outcome of running a dead letter again
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RequeueResponse {
+    pub request_id: Uuid,
+    pub output: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<crate::client::ToolCall>,
+}
+
+/// List requests the pipeline failed to answer, oldest first
+#[utoipa::path(
+    get,
+    path = "/v1/deadletters",
+    tag = "inference",
+    responses(
+        (status = 200, body = DeadLetterListResponse),
+        (status = 503, description = "No pipeline processor configured", body = ErrorResponse)
+    )
+)]
+pub async fn list_dead_letters(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(processor) = state.processor.get() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!(ErrorResponse::new(
+                "No pipeline processor configured"
+            ))),
+        );
+    };
+
+    let dead_letters = processor.dead_letters().await;
+    (
+        StatusCode::OK,
+        Json(serde_json::json!(DeadLetterListResponse { dead_letters })),
+    )
+}
+
+/// Get a failed request's input, trace and error
+#[utoipa::path(
+    get,
+    path = "/v1/deadletters/{request_id}",
+    tag = "inference",
+    params(("request_id" = String, Path, description = "ID of the failed request")),
+    responses(
+        (status = 200, body = DeadLetter),
+        (status = 400, description = "Not a UUID", body = ErrorResponse),
+        (status = 404, description = "No dead letter for the request", body = ErrorResponse),
+        (status = 503, description = "No pipeline processor configured", body = ErrorResponse)
+    )
+)]
+pub async fn get_dead_letter(
+    State(state): State<AppState>,
+    Path(request_id): Path<String>,
+) -> impl IntoResponse {
+    let (processor, request_id) = match dead_letter_target(&state, &request_id) {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+
+    match processor.dead_letter(&request_id).await {
+        Some(letter) => (StatusCode::OK, Json(serde_json::json!(letter))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!(ErrorResponse::new(
+                ProcessorError::DeadLetterNotFound(request_id).to_string()
+            ))),
+        ),
+    }
+}
+
+/// Run a failed request through the pipeline again
+///
+/// A request that succeeds leaves the dead letters; one that fails again
+/// stays with its attempt counted.
+#[utoipa::path(
+    post,
+    path = "/v1/deadletters/{request_id}/requeue",
+    tag = "inference",
+    params(("request_id" = String, Path, description = "ID of the failed request")),
+    responses(
+        (status = 200, body = RequeueResponse),
+        (status = 400, description = "Not a UUID", body = ErrorResponse),
+        (status = 404, description = "No dead letter for the request", body = ErrorResponse),
+        (status = 502, description = "The request failed again", body = ErrorResponse),
+        (status = 503, description = "No pipeline processor configured", body = ErrorResponse)
+    )
+)]
+pub async fn requeue_dead_letter(
+    State(state): State<AppState>,
+    Path(request_id): Path<String>,
+) -> impl IntoResponse {
+    let (processor, request_id) = match dead_letter_target(&state, &request_id) {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+
+    match processor.requeue(&request_id).await {
+        Ok(output) => (
+            StatusCode::OK,
+            Json(serde_json::json!(RequeueResponse {
+                request_id,
+                output: output.content,
+                tool_calls: output.tool_calls,
+            })),
+        ),
+        Err(e @ ProcessorError::DeadLetterNotFound(_)) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!(ErrorResponse::new(e.to_string()))),
+        ),
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Pipeline error: {}",
+                e
+            )))),
+        ),
+    }
+}
+
+/// Query parameters of a replay
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct ReplayQuery {
+    /// Handler to run the input through on its own, instead of the pipeline
+    pub node: Option<String>,
+}
+
+/// Outcome of running a past request's input again
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReplayResponse {
+    /// ID the replay ran under, to look up its trace
+    pub request_id: Uuid,
+    pub replayed_from: Uuid,
+    pub output: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<crate::client::ToolCall>,
+}
+
+/// Run a recent request's input through the pipeline again, or through a
+/// single handler
+///
+/// The input comes from the request's dead letter, or else its trace, which
+/// only keeps the prompt. The replay runs under a new request ID and leaves
+/// the original's dead letter in place.
+#[utoipa::path(
+    post,
+    path = "/v1/requests/{request_id}/replay",
+    tag = "inference",
+    params(
+        ("request_id" = String, Path, description = "ID of the request to replay"),
+        ReplayQuery
+    ),
+    responses(
+        (status = 200, body = ReplayResponse),
+        (status = 400, description = "Not a UUID, or not a handler node", body = ErrorResponse),
+        (status = 404, description = "No trace or dead letter for the request", body = ErrorResponse),
+        (status = 502, description = "The replay failed", body = ErrorResponse),
+        (status = 503, description = "No pipeline processor configured", body = ErrorResponse)
+    )
+)]
+pub async fn replay_request(
+    State(state): State<AppState>,
+    Path(request_id): Path<String>,
+    Query(query): Query<ReplayQuery>,
+) -> impl IntoResponse {
+    let (processor, request_id) = match dead_letter_target(&state, &request_id) {
+        Ok(target) => target,
+        Err(response) => return response,
+    };
+
+    match processor.replay(&request_id, query.node.as_deref()).await {
+        Ok((replay_id, output)) => (
+            StatusCode::OK,
+            Json(serde_json::json!(ReplayResponse {
+                request_id: replay_id,
+                replayed_from: request_id,
+                output: output.content,
+                tool_calls: output.tool_calls,
+            })),
+        ),
+        Err(e @ ProcessorError::RequestNotFound(_)) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!(ErrorResponse::new(e.to_string()))),
+        ),
+        Err(e @ (ProcessorError::HandlerNotFound(_) | ProcessorError::NotReplayable(_))) => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!(ErrorResponse::new(e.to_string()))),
+        ),
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Pipeline error: {}",
+                e
+            )))),
+        ),
+    }
+}
+
+/// Status and JSON body an endpoint answers with
+type JsonResponse = (StatusCode, Json<serde_json::Value>);
+
+/// The processor and parsed request ID a dead letter or replay endpoint
+/// works on
+fn dead_letter_target(
+    state: &AppState,
+    request_id: &str,
+) -> Result<(Arc<PipelineProcessor>, Uuid), JsonResponse> {
+    let Ok(request_id) = Uuid::parse_str(request_id) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Invalid request ID: {}",
+                request_id
+            )))),
+        ));
+    };
+    let Some(processor) = state.processor.get() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!(ErrorResponse::new(
+                "No pipeline processor configured"
+            ))),
+        ));
+    };
+    Ok((processor, request_id))
+}
+
+/// File name to upload audio under, so the transcription server can tell
+/// its format
+fn audio_file_name(content_type: &str) -> String {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    let extension = match mime {
+        "audio/wav" | "audio/wave" | "audio/x-wav" => "wav",
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/ogg" | "audio/opus" => "ogg",
+        "audio/webm" | "video/webm" => "webm",
+        "audio/flac" | "audio/x-flac" => "flac",
+        "audio/mp4" | "audio/m4a" | "audio/x-m4a" => "m4a",
+        _ => return "audio".to_string(),
+    };
+    format!("audio.{}", extension)
+}
+
+/// Extract the most recent user prompt from a conversation
+fn last_user_prompt(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.clone())
+        .unwrap_or_default()
+}
+
+/// Images attached to the last user message
+fn last_user_images(messages: &[Message]) -> Vec<ImageUrl> {
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.images.clone())
+        .unwrap_or_default()
+}
+
+/// Variables set by `X-LLMNet-Var-*` headers the composition declares
+///
+/// `X-LLMNet-Var-User-Tier` sets `user_tier`.
+fn header_variables(headers: &HeaderMap, declared: &[String]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let var = name
+                .as_str()
+                .strip_prefix(VAR_HEADER_PREFIX)?
+                .replace('-', "_");
+            let value = value.to_str().ok()?;
+            declared.contains(&var).then(|| (var, value.to_string()))
+        })
+        .collect()
+}
+
+/// Response headers describing the route a request took; none for a
+/// request that didn't get through the pipeline
+fn route_headers(route: &[RouteStep]) -> Vec<(&'static str, String)> {
+    if route.is_empty() {
+        return Vec::new();
+    }
+    let nodes: Vec<&str> = route.iter().map(|step| step.node.as_str()).collect();
+    let mut headers = vec![
+        (ROUTE_HEADER, nodes.join(">")),
+        (HOPS_HEADER, route.len().to_string()),
+    ];
+    let latencies: Vec<String> = route
+        .iter()
+        .filter_map(|step| Some(format!("{}={}ms", step.node, step.latency_ms?)))
+        .collect();
+    if !latencies.is_empty() {
+        headers.push((HOP_LATENCY_HEADER, latencies.join(", ")));
+    }
+    headers
+}
+
+/// Messages after the last user prompt: the assistant's tool calls and the
+/// client's tool results, when a client is answering a tool call
+fn messages_after_prompt(messages: &[Message]) -> Vec<Message> {
+    match messages.iter().rposition(|m| m.role == "user") {
+        Some(index) => messages[index + 1..].to_vec(),
+        None => Vec::new(),
+    }
+}
+
+// ============================================================================
+// WebSocket Streaming Endpoint
+// ============================================================================
+
+/// Stream pipeline progress over a WebSocket
+///
+/// Each text frame from the client is a chat completion request. The worker
+/// replies with `started`, one `hop` per node that produced output, the
+/// `chunk`s of a streamed answer, and a final `completed` or `failed`
+/// event, all tagged with the request ID.
+#[utoipa::path(
+    get,
+    path = "/v1/stream",
+    tag = "inference",
+    responses((status = 101, description = "Switching to the WebSocket protocol"))
+)]
+pub async fn pipeline_stream(
+    State(state): State<AppState>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| handle_stream_socket(socket, state))
+}
+
+async fn handle_stream_socket(mut socket: WebSocket, state: AppState) {
+    while let Some(Ok(frame)) = socket.recv().await {
+        let text = match frame {
+            WsMessage::Text(text) => text,
+            WsMessage::Close(_) => break,
+            _ => continue,
+        };
+
+        let request_id = Uuid::new_v4();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        match serde_json::from_str::<ChatCompletionRequest>(&text) {
+            Ok(request) => {
+                let processor = state.processor.get();
+                let pipeline_request =
+                    PipelineRequest::with_id(request_id, last_user_prompt(&request.messages))
+                        .with_images(last_user_images(&request.messages));
+                tokio::spawn(async move {
+                    let _ = tx.send(PipelineEvent::Started { request_id });
+                    let event = match processor {
+                        Some(processor) => {
+                            match processor.process_streaming(pipeline_request, &tx).await {
+                                Ok(content) => PipelineEvent::Completed {
+                                    request_id,
+                                    content,
+                                },
+                                Err(e) => PipelineEvent::Failed {
+                                    request_id,
+                                    error: e.to_string(),
+                                },
+                            }
+                        }
+                        None => PipelineEvent::Failed {
+                            request_id,
+                            error: "No pipeline processor configured".to_string(),
+                        },
+                    };
+                    let _ = tx.send(event);
+                });
+            }
+            Err(e) => {
+                let _ = tx.send(PipelineEvent::Failed {
+                    request_id,
+                    error: format!("Invalid request: {}", e),
+                });
+                drop(tx);
+            }
+        }
+
+        // The channel closes once the processing task drops its sender
+        while let Some(event) = rx.recv().await {
+            let payload = serde_json::to_string(&event).unwrap_or_default();
+            if socket.send(WsMessage::Text(payload.into())).await.is_err() {
+                debug!("Stream subscriber for request {} disconnected", request_id);
+                return;
+            }
+        }
+    }
+}
+
+/// Query parameters for logs endpoint
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LogsQuery {
+    /// Follow log output (like tail -f)
+    #[serde(default)]
+    pub follow: bool,
+    /// Number of lines to show from the end
+    #[serde(default = "default_tail")]
+    pub tail: usize,
+}
+
+fn default_tail() -> usize {
+    100
+}
+
+/// Stream container logs
+#[utoipa::path(
+    get,
+    path = "/v1/containers/{container}/logs",
+    tag = "runners",
+    params(
+        ("container" = String, Path, description = "Container name"),
+        LogsQuery
+    ),
+    responses(
+        (status = 200, description = "Log lines, streamed while following", content_type = "text/plain", body = String),
+        (status = 404, description = "Container not found"),
+        (status = 503, description = "Not running as a worker")
+    )
+)]
+pub async fn stream_logs(
+    State(state): State<AppState>,
+    Path(container): Path<String>,
+    Query(params): Query<LogsQuery>,
+) -> impl IntoResponse {
+    let manager = match &state.runner_manager {
+        Some(m) => m,
+        None => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Body::from("Runner manager not available"),
+            )
+                .into_response();
+        }
+    };
+
+    // Check if container exists in our tracked processes
+    let containers = manager.list_containers();
+    if !containers.contains(&container) {
+        return (
+            StatusCode::NOT_FOUND,
+            Body::from(format!("Container '{}' not found", container)),
+        )
+            .into_response();
+    }
+
+    container_logs(manager, &container, &params).await
+}
+
+/// Stream `docker logs` of a container, stdout and stderr merged
+async fn container_logs(
+    manager: &SharedRunnerManager,
+    container: &str,
+    params: &LogsQuery,
+) -> axum::response::Response {
+    match manager
+        .stream_container_logs(container, params.follow, Some(params.tail))
+        .await
+    {
+        Ok(mut child) => {
+            // Merge stdout and stderr into a single stream
+            let stdout = child.stdout.take();
+            let stderr = child.stderr.take();
+
+            match (stdout, stderr) {
+                (Some(stdout), Some(stderr)) => {
+                    // Create streams from both
+                    let stdout_stream = ReaderStream::new(stdout);
+                    let stderr_stream = ReaderStream::new(stderr);
+
+                    // Merge the streams
+                    let merged = futures::stream::select(stdout_stream, stderr_stream);
+
+                    let body = Body::from_stream(merged);
+                    (StatusCode::OK, body).into_response()
+                }
+                (Some(stdout), None) => {
+                    let stream = ReaderStream::new(stdout);
+                    let body = Body::from_stream(stream);
+                    (StatusCode::OK, body).into_response()
+                }
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Body::from("Failed to capture log output"),
+                )
+                    .into_response(),
+            }
+        }
+        Err(e) => {
+            error!("Failed to stream logs for '{}': {}", container, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Body::from(format!("Failed to stream logs: {}", e)),
             )
                 .into_response()
         }
     }
 }
 
+/// Runner output (worker endpoint)
+///
+/// Runners spawned as local processes log to files under the worker's runner
+/// directory, which outlive the runner so a crash can be looked into. For
+/// container runners this is the container's `docker logs`.
+#[utoipa::path(
+    get,
+    path = "/v1/runners/{name}/logs",
+    tag = "runners",
+    params(
+        ("name" = String, Path, description = "Runner replica (`model`, `model-1`, ...)"),
+        LogsQuery
+    ),
+    responses(
+        (status = 200, description = "Log lines, streamed while following", content_type = "text/plain", body = String),
+        (status = 404, description = "No logs for the runner"),
+        (status = 503, description = "Not running as a worker")
+    )
+)]
+pub async fn runner_logs(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(params): Query<LogsQuery>,
+) -> impl IntoResponse {
+    let Some(manager) = &state.runner_manager else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Body::from("Runner manager not available"),
+        )
+            .into_response();
+    };
+
+    if let Some(container) = manager.get_container_name(&name) {
+        return container_logs(manager, &container, &params).await;
+    }
+
+    let path = manager.log_path(&name);
+    let tail = match read_tail(&path, params.tail) {
+        Ok(tail) => tail,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return (
+                StatusCode::NOT_FOUND,
+                Body::from(format!("No logs for runner '{}'", name)),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            error!("Failed to read logs of runner '{}': {}", name, e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Body::from(format!("Failed to read logs: {}", e)),
+            )
+                .into_response();
+        }
+    };
+
+    if !params.follow {
+        return (StatusCode::OK, Body::from(tail)).into_response();
+    }
+
+    // Follow from the end of what was read, so nothing is sent twice
+    let offset = std::fs::metadata(&path).map_or(0, |m| m.len());
+    let stream =
+        futures::stream::once(async move { Ok(tail.into_bytes()) }).chain(follow_log(path, offset));
+    (StatusCode::OK, Body::from_stream(stream)).into_response()
+}
+
+/// Containers started by this worker's runners
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ContainerListResponse {
+    pub containers: Vec<String>,
+}
+
 /// List available containers
+#[utoipa::path(
+    get,
+    path = "/v1/containers",
+    tag = "runners",
+    responses((status = 200, body = ContainerListResponse))
+)]
 pub async fn list_containers(State(state): State<AppState>) -> impl IntoResponse {
     let containers = state
         .runner_manager
@@ -434,9 +2147,191 @@ pub async fn list_containers(State(state): State<AppState>) -> impl IntoResponse
         .map(|m| m.list_containers())
         .unwrap_or_default();
 
-    Json(serde_json::json!({
-        "containers": containers
-    }))
+    Json(ContainerListResponse { containers })
+}
+
+/// Model artifacts cached on this worker's disk
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CachedModelListResponse {
+    /// Least recently used first
+    pub models: Vec<CachedArtifact>,
+    /// Bytes the artifacts take
+    pub total: u64,
+    /// Bytes `llmnet prune models` evicts down to, if the worker has a budget
+    pub budget: Option<u64>,
+}
+
+/// Evict cached model artifacts
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct PruneModelsRequest {
+    /// Bytes to keep (default: the worker's `--model-cache-budget`)
+    #[serde(default)]
+    pub budget: Option<u64>,
+    /// Report what would be evicted without removing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// List the model artifacts cached on this worker's disk
+#[utoipa::path(
+    get,
+    path = "/v1/cache/models",
+    tag = "models",
+    responses(
+        (status = 200, body = CachedModelListResponse),
+        (status = 500, description = "Cache couldn't be read", body = ErrorResponse)
+    )
+)]
+pub async fn list_cached_models(State(state): State<AppState>) -> impl IntoResponse {
+    let cache = state.model_cache.clone();
+    match tokio::task::spawn_blocking(move || cache.list()).await {
+        Ok(Ok(models)) => (
+            StatusCode::OK,
+            Json(serde_json::json!(CachedModelListResponse {
+                total: model_cache::total_size(&models),
+                models,
+                budget: state.model_cache.budget,
+            })),
+        ),
+        Ok(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Failed to read the model cache: {}",
+                e
+            )))),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!(ErrorResponse::new(e.to_string()))),
+        ),
+    }
+}
+
+/// Evict the least recently used model artifacts down to a disk budget
+#[utoipa::path(
+    post,
+    path = "/v1/cache/models/prune",
+    tag = "models",
+    request_body = PruneModelsRequest,
+    responses(
+        (status = 200, body = PruneReport),
+        (status = 400, description = "No budget given or configured", body = ErrorResponse),
+        (status = 500, description = "Artifacts couldn't be removed", body = ErrorResponse)
+    )
+)]
+pub async fn prune_cached_models(
+    State(state): State<AppState>,
+    Json(request): Json<PruneModelsRequest>,
+) -> impl IntoResponse {
+    let Some(budget) = request.budget.or(state.model_cache.budget) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!(ErrorResponse::new(
+                "No budget given, and the worker has no --model-cache-budget"
+            ))),
+        );
+    };
+
+    let cache = state.model_cache.clone();
+    let dry_run = request.dry_run;
+    match tokio::task::spawn_blocking(move || cache.prune(budget, dry_run)).await {
+        Ok(Ok(report)) => {
+            if !dry_run && !report.evicted.is_empty() {
+                tracing::info!(
+                    "Evicted {} cached model artifact(s), {} -> {} bytes",
+                    report.evicted.len(),
+                    report.before,
+                    report.after
+                );
+            }
+            (StatusCode::OK, Json(serde_json::json!(report)))
+        }
+        Ok(Err(e)) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!(ErrorResponse::new(format!(
+                "Failed to prune the model cache: {}",
+                e
+            )))),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!(ErrorResponse::new(e.to_string()))),
+        ),
+    }
+}
+
+/// This month's spend on budgeted models
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BudgetListResponse {
+    /// By namespace, then model
+    pub budgets: Vec<BudgetUsage>,
+}
+
+/// List what each namespace spent on budgeted models this month
+///
+/// Covers the pipelines this worker hosts; spend is counted from when the
+/// worker started. Other workers keep their own, separate spend.
+#[utoipa::path(
+    get,
+    path = "/v1/budgets",
+    tag = "models",
+    responses((status = 200, body = BudgetListResponse))
+)]
+pub async fn list_budgets(State(state): State<AppState>) -> Json<BudgetListResponse> {
+    Json(BudgetListResponse {
+        budgets: state.budgets.usage(chrono::Utc::now()),
+    })
+}
+
+/// OpenAPI document for the worker API, generated from the handlers
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "LLMNet Worker API",
+        description = "Run pipelines: OpenAI-compatible inference plus runner management"
+    ),
+    paths(
+        health,
+        status,
+        topology,
+        chat_completions,
+        audio_completions,
+        embeddings,
+        get_request_trace,
+        replay_request,
+        list_dead_letters,
+        get_dead_letter,
+        requeue_dead_letter,
+        delete_session,
+        pipeline_stream,
+        list_runners,
+        spawn_runner,
+        stop_runner,
+        runner_logs,
+        receive_assignment,
+        remove_assignment,
+        list_pipelines,
+        mesh_hop,
+        request_heartbeat,
+        list_containers,
+        stream_logs,
+        list_cached_models,
+        prune_cached_models,
+        list_budgets,
+    ),
+    tags(
+        (name = "status", description = "Worker health"),
+        (name = "inference", description = "OpenAI-compatible pipeline endpoints"),
+        (name = "runners", description = "Local model runners and their containers"),
+        (name = "models", description = "Model artifacts cached on the worker's disk and spend on budgeted models"),
+        (name = "cluster", description = "Called by the control plane")
+    )
+)]
+pub struct WorkerApi;
+
+/// The worker's OpenAPI document
+pub async fn openapi_json() -> impl IntoResponse {
+    Json(WorkerApi::openapi())
 }
 
 /// Create the Axum router
@@ -444,92 +2339,656 @@ pub fn create_router(state: AppState) -> Router {
     Router::new()
         .route("/health", get(health))
         .route("/status", get(status))
-        .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/topology", get(topology))
+        .route(
+            "/v1/chat/completions",
+            post(chat_completions)
+                .layer::<_, Infallible>(middleware::from_fn_with_state(state.clone(), limit_body))
+                .layer(DefaultBodyLimit::disable()),
+        )
+        .route(
+            "/v1/audio/completions",
+            post(audio_completions).layer(DefaultBodyLimit::max(MAX_AUDIO_BYTES)),
+        )
+        .route("/v1/embeddings", post(embeddings))
+        .route("/v1/requests/{request_id}", get(get_request_trace))
+        .route("/v1/requests/{request_id}/replay", post(replay_request))
+        .route("/v1/deadletters", get(list_dead_letters))
+        .route("/v1/deadletters/{request_id}", get(get_dead_letter))
+        .route(
+            "/v1/deadletters/{request_id}/requeue",
+            post(requeue_dead_letter),
+        )
+        .route("/v1/sessions/{session_id}", delete(delete_session))
+        .route("/v1/stream", get(pipeline_stream))
         // Runner management endpoints (worker mode)
         .route("/v1/runners", get(list_runners))
         .route("/v1/runners/spawn", post(spawn_runner))
         .route("/v1/runners/{name}", delete(stop_runner))
+        .route("/v1/runners/{name}/logs", get(runner_logs))
         // Pipeline assignment endpoint (control plane -> worker)
         .route("/v1/assignments", post(receive_assignment))
+        .route(
+            "/v1/assignments/{namespace}/{name}",
+            delete(remove_assignment),
+        )
+        .route("/v1/pipelines", get(list_pipelines))
+        .route("/pipelines/{name}/{*path}", any(pipeline_request))
+        // Hops from the other workers of a split pipeline
+        .route("/v1/mesh/hops", post(mesh_hop))
+        .route("/v1/heartbeat", post(request_heartbeat))
         // Container logs endpoints
         .route("/v1/containers", get(list_containers))
         .route("/v1/containers/{container}/logs", get(stream_logs))
+        // Model cache endpoints (worker mode)
+        .route("/v1/cache/models", get(list_cached_models))
+        .route("/v1/cache/models/prune", post(prune_cached_models))
+        .route("/v1/budgets", get(list_budgets))
+        // API description
+        .route("/openapi.json", get(openapi_json))
+        .merge(super::openapi::swagger_ui())
         .with_state(state)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::Composition;
-    use axum::{
-        body::Body,
-        http::{Request, StatusCode},
-    };
-    use tower::ServiceExt;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Composition;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use tower::ServiceExt;
+
+    fn create_test_app() -> Router {
+        let json = r#"{
+            "models": {},
+            "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+        let comp = Composition::from_str(json).unwrap();
+        let state = AppState::new(comp);
+        create_router(state)
+    }
+
+    #[tokio::test]
+    async fn test_openapi_document_covers_routes() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let mut paths: Vec<&str> = doc["paths"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        paths.sort_unstable();
+        assert_eq!(
+            paths,
+            [
+                "/health",
+                "/status",
+                "/v1/assignments",
+                "/v1/assignments/{namespace}/{name}",
+                "/v1/audio/completions",
+                "/v1/budgets",
+                "/v1/cache/models",
+                "/v1/cache/models/prune",
+                "/v1/chat/completions",
+                "/v1/containers",
+                "/v1/containers/{container}/logs",
+                "/v1/deadletters",
+                "/v1/deadletters/{request_id}",
+                "/v1/deadletters/{request_id}/requeue",
+                "/v1/embeddings",
+                "/v1/heartbeat",
+                "/v1/mesh/hops",
+                "/v1/pipelines",
+                "/v1/requests/{request_id}",
+                "/v1/requests/{request_id}/replay",
+                "/v1/runners",
+                "/v1/runners/spawn",
+                "/v1/runners/{name}",
+                "/v1/runners/{name}/logs",
+                "/v1/sessions/{session_id}",
+                "/v1/stream",
+                "/v1/topology",
+            ]
+        );
+
+        let chat = &doc["paths"]["/v1/chat/completions"]["post"];
+        assert_eq!(
+            chat["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ChatCompletionRequest"
+        );
+        let schemas = &doc["components"]["schemas"];
+        for schema in [
+            "Message",
+            "MessageContent",
+            "ContentPart",
+            "ToolCall",
+            "RequestTrace",
+            "ErrorResponse",
+        ] {
+            assert!(schemas[schema].is_object(), "missing schema {}", schema);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoint() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_topology_endpoint() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/topology")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let topology: Topology = serde_json::from_slice(&body).unwrap();
+        assert_eq!(topology.router, None);
+        let names: Vec<&str> = topology.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["router", "output"]);
+    }
+
+    #[tokio::test]
+    async fn test_assignments_hosted_side_by_side() {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {"small": {"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:1/v1"}},
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "small", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let state = AppState::new(composition.clone())
+            .with_runner_manager(crate::runtime::new_shared_manager())
+            .with_bind_addr("127.0.0.1")
+            .with_port(8080);
+        let assignment = |name: &str, port| PipelineAssignment {
+            namespace: "default".to_string(),
+            name: name.to_string(),
+            composition: composition.clone(),
+            port,
+            replicas: 1,
+            env: BTreeMap::new(),
+            secret_refs: Vec::new(),
+            secret_grant: None,
+            gpu_devices: BTreeMap::new(),
+            peers: BTreeMap::new(),
+            mesh_nodes: Vec::new(),
+            mesh_key: None,
+        };
+
+        // The worker's own port is served under a prefix, others directly
+        let (status, chat) = apply_assignment(&state, assignment("chat", 8080)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            chat.endpoint.as_deref(),
+            Some("http://127.0.0.1:8080/pipelines/chat")
+        );
+        let (_, code) = apply_assignment(&state, assignment("code", 0)).await;
+        let code_endpoint = code.endpoint.unwrap();
+        assert!(!code_endpoint.ends_with(":0"));
+
+        let health = reqwest::get(format!("{}/health", code_endpoint))
+            .await
+            .unwrap();
+        assert!(health.status().is_success());
+
+        let app = create_router(state.clone());
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/pipelines/chat/v1/topology")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let topology: Topology = serde_json::from_slice(&body).unwrap();
+        assert_eq!(topology.router.as_deref(), Some("router"));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/pipelines/missing/v1/topology")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/pipelines")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let pipelines: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(pipelines[0]["name"], "chat");
+        assert!(pipelines[0]["port"].is_null());
+        assert_eq!(pipelines[1]["name"], "code");
+        assert!(pipelines[1]["port"].is_u64());
+        assert_eq!(pipelines[1]["ready"], true);
+
+        // Deleting the pipeline on the control plane tears it down here
+        let remove = |uri: &str| {
+            Request::builder()
+                .method("DELETE")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app
+            .clone()
+            .oneshot(remove("/v1/assignments/prod/code"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app
+            .oneshot(remove("/v1/assignments/default/code"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(reqwest::get(format!("{}/health", code_endpoint))
+            .await
+            .is_err());
+        assert_eq!(state.pipelines.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_status_endpoint() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/status")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_chat_completions_endpoint() {
+        let app = create_test_app();
+
+        let request_body = serde_json::json!({
+            "model": "test-model",
+            "messages": [
+                {"role": "user", "content": "Hello"}
+            ]
+        });
 
-    fn create_test_app() -> Router {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_request_limits() {
         let json = r#"{
             "models": {},
+            "request-limits": {"max-body-bytes": 400, "max-messages": 2, "max-prompt-tokens": 10},
             "architecture": [
                 {"name": "router", "layer": 0, "adapter": "openai-api"},
                 {"name": "output", "adapter": "output"}
             ]
         }"#;
-        let comp = Composition::from_str(json).unwrap();
-        let state = AppState::new(comp);
-        create_router(state)
+        let app = create_router(AppState::new(Composition::from_str(json).unwrap()));
+        let send = |body: String| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+        let chat = |messages: serde_json::Value| {
+            serde_json::json!({"model": "test-model", "messages": messages}).to_string()
+        };
+        let error = |body: &[u8]| -> serde_json::Value {
+            let body: serde_json::Value = serde_json::from_slice(body).unwrap();
+            assert_eq!(body["error"]["type"], "invalid_request_error");
+            body["error"].clone()
+        };
+
+        let response = send(chat(
+            serde_json::json!([{"role": "user", "content": "Hello"}]),
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(chat(serde_json::json!([
+            {"role": "user", "content": "a"},
+            {"role": "assistant", "content": "b"},
+            {"role": "user", "content": "c"}
+        ])))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(error(&body)["code"], "too_many_messages");
+
+        let response = send(chat(
+            serde_json::json!([{"role": "user", "content": "x".repeat(60)}]),
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = error(&body);
+        assert_eq!(body["code"], "context_length_exceeded");
+        assert_eq!(body["param"], "messages");
+
+        let response = send(chat(
+            serde_json::json!([{"role": "user", "content": "x".repeat(500)}]),
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(error(&body)["code"], "request_too_large");
     }
 
     #[tokio::test]
-    async fn test_health_endpoint() {
+    async fn test_streamed_chat_completion() {
         let app = create_test_app();
+        let request_body = serde_json::json!({
+            "model": "test-model",
+            "messages": [{"role": "user", "content": "Hello"}],
+            "stream": true
+        });
 
         let response = app
             .oneshot(
                 Request::builder()
-                    .uri("/health")
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let events: Vec<&str> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .collect();
+        assert_eq!(events.len(), 3);
+        let answer: serde_json::Value = serde_json::from_str(events[0]).unwrap();
+        assert_eq!(answer["object"], "chat.completion.chunk");
+        assert!(answer["choices"][0]["delta"]["content"]
+            .as_str()
+            .unwrap()
+            .contains("Hello"));
+        let finish: serde_json::Value = serde_json::from_str(events[1]).unwrap();
+        assert_eq!(finish["choices"][0]["finish_reason"], "stop");
+        assert_eq!(events[2], "[DONE]");
+    }
+
+    #[tokio::test]
+    async fn test_request_heartbeat() {
+        let app = create_test_app();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/heartbeat")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let trigger = std::sync::Arc::new(tokio::sync::Notify::new());
+        let comp = Composition::from_str(
+            r#"{
+                "models": {},
+                "architecture": [
+                    {"name": "router", "layer": 0, "adapter": "openai-api"},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let app = create_router(AppState::new(comp).with_heartbeat_trigger(trigger.clone()));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/heartbeat")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        // The stored permit completes immediately
+        tokio::time::timeout(std::time::Duration::from_secs(1), trigger.notified())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_runner_logs() {
+        let dir = std::env::temp_dir().join(format!("llmnet-runners-{}", Uuid::new_v4()));
+        let manager = std::sync::Arc::new(crate::runtime::RunnerManager::with_settings(
+            "127.0.0.1",
+            dir.clone(),
+        ));
+        let path = manager.log_path("llama");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "loading model\nout of memory\n").unwrap();
+
+        let comp = Composition::from_str(
+            r#"{
+                "models": {},
+                "architecture": [
+                    {"name": "router", "layer": 0, "adapter": "openai-api"},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let app = create_router(AppState::new(comp).with_runner_manager(manager));
 
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = app
+            .clone()
+            .oneshot(get("/v1/runners/llama/logs?tail=1"))
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"out of memory\n");
+
+        let response = app.oneshot(get("/v1/runners/other/logs")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 
     #[tokio::test]
-    async fn test_status_endpoint() {
-        let app = create_test_app();
+    async fn test_cached_models() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = crate::runtime::ModelCache {
+            fetch_dir: dir.path().join("llmnet"),
+            ollama_dir: dir.path().join("ollama"),
+            huggingface_dir: dir.path().join("hf"),
+            budget: None,
+        };
+        std::fs::create_dir_all(&cache.fetch_dir).unwrap();
+        std::fs::write(cache.fetch_dir.join("model.gguf"), vec![0u8; 64]).unwrap();
+
+        let comp = Composition::from_str(
+            r#"{
+                "models": {},
+                "architecture": [
+                    {"name": "router", "layer": 0, "adapter": "openai-api"},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let app = create_router(AppState::new(comp).with_model_cache(cache.clone()));
 
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
-                    .uri("/status")
+                    .uri("/v1/cache/models")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let list: CachedModelListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(list.total, 64);
+        assert_eq!(list.models[0].name, "model.gguf");
+
+        let prune = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/v1/cache/models/prune")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        // Without a budget there is nothing to prune down to
+        let response = app
+            .clone()
+            .oneshot(prune(serde_json::json!({})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
+        let response = app
+            .oneshot(prune(serde_json::json!({"budget": 0})))
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let report: PruneReport = serde_json::from_slice(&body).unwrap();
+        assert_eq!((report.before, report.after), (64, 0));
+        assert!(!cache.fetch_dir.join("model.gguf").exists());
     }
 
     #[tokio::test]
-    async fn test_chat_completions_endpoint() {
+    async fn test_embeddings_without_processor() {
         let app = create_test_app();
 
         let request_body = serde_json::json!({
-            "model": "test-model",
-            "messages": [
-                {"role": "user", "content": "Hello"}
-            ]
+            "model": "embedder",
+            "input": "Hello"
         });
 
         let response = app
             .oneshot(
                 Request::builder()
                     .method("POST")
-                    .uri("/v1/chat/completions")
+                    .uri("/v1/embeddings")
                     .header("content-type", "application/json")
                     .body(Body::from(serde_json::to_string(&request_body).unwrap()))
                     .unwrap(),
@@ -537,6 +2996,376 @@ mod tests {
             .await
             .unwrap();
 
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_audio_completions_without_processor() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/audio/completions")
+                    .header("content-type", "audio/wav")
+                    .body(Body::from(&b"RIFF"[..]))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn test_audio_file_name() {
+        assert_eq!(audio_file_name("audio/wav"), "audio.wav");
+        assert_eq!(audio_file_name("audio/webm;codecs=opus"), "audio.webm");
+        assert_eq!(audio_file_name("application/octet-stream"), "audio");
+    }
+
+    #[tokio::test]
+    async fn test_request_trace_rejects_invalid_id() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/requests/not-a-uuid")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_exceeded_is_504() {
+        let app = axum::Router::new().route(
+            "/v1/chat/completions",
+            axum::routing::post(|| async {
+                tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                StatusCode::OK
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let json = format!(
+            r#"{{
+                "models": {{"model": {{"runner": "external", "endpoint": "http://{addr}/v1"}}}},
+                "deadline-ms": 50,
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": ["chat"]}},
+                    {{"name": "chat", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#
+        );
+        let app = create_router(AppState::new(Composition::from_str(&json).unwrap()));
+        let request_id = Uuid::new_v4();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/v1/chat/completions")
+                    .header("content-type", "application/json")
+                    .header("x-request-id", request_id.to_string())
+                    .body(Body::from(
+                        r#"{"model": "pipeline", "messages": [{"role": "user", "content": "hi"}]}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["type"], "timeout_error");
+        assert_eq!(body["error"]["code"], "deadline_exceeded");
+        assert_eq!(body["error"]["request_id"], request_id.to_string());
+        assert_eq!(
+            body["error"]["message"],
+            "The request ran out of time at 'chat'"
+        );
+    }
+
+    #[test]
+    fn test_processor_error_responses() {
+        let request_id = Uuid::new_v4();
+        let cases = [
+            (
+                ProcessorError::HookError(HookError::Aborted("profanity".to_string())),
+                StatusCode::BAD_REQUEST,
+                "request_rejected",
+            ),
+            (
+                ProcessorError::RateLimited("chat".to_string(), "slow down".to_string()),
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limit_exceeded",
+            ),
+            (
+                ProcessorError::BudgetExhausted("chat".to_string(), "gpt".to_string()),
+                StatusCode::TOO_MANY_REQUESTS,
+                "insufficient_quota",
+            ),
+            (
+                ProcessorError::ApiError("HTTP error: http://10.0.0.5:8080 refused".to_string()),
+                StatusCode::BAD_GATEWAY,
+                "upstream_error",
+            ),
+            (
+                ProcessorError::DeadlineExceeded("chat".to_string()),
+                StatusCode::GATEWAY_TIMEOUT,
+                "deadline_exceeded",
+            ),
+            (
+                ProcessorError::NoRouter,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "pipeline_error",
+            ),
+        ];
+        for (error, status, code) in cases {
+            let (actual, body) = OpenAiErrorResponse::from_processor_error(&error, request_id);
+            assert_eq!(actual, status, "{}", error);
+            assert_eq!(body.error.code.as_deref(), Some(code));
+            assert_eq!(body.error.request_id, Some(request_id));
+            assert!(!body.error.message.contains("10.0.0.5"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mesh_hop_requires_token() {
+        use crate::runtime::{Mesh, MeshKey, RunnerManager};
+
+        let composition = Composition::from_str(
+            r#"{
+                "models": {
+                    "model": {"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:1/v1"}
+                },
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]},
+                    {"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let key = MeshKey::new("secret");
+        let id = Uuid::new_v4();
+        let hop = serde_json::json!({
+            "request_id": id,
+            "node": "handler",
+            "input": "hi",
+            "request": {"prompt": "hi"}
+        });
+        let body = hop.to_string();
+        let send = |app: Router, token: Option<String>| {
+            let hop = hop.clone();
+            async move {
+                let mut request = Request::builder()
+                    .method("POST")
+                    .uri("/v1/mesh/hops")
+                    .header("content-type", "application/json");
+                if let Some(token) = token {
+                    request = request.header(MESH_TOKEN_HEADER, token);
+                }
+                let request = request.body(Body::from(hop.to_string())).unwrap();
+                app.oneshot(request).await.unwrap().status()
+            }
+        };
+        let expires = chrono::Utc::now().timestamp() + 30;
+
+        // A pipeline running whole on one worker takes no hops
+        let whole = create_router(AppState::new(composition.clone()));
+        let token = key.sign("handler", id, body.as_bytes(), expires);
+        assert_eq!(send(whole, Some(token)).await, StatusCode::NOT_FOUND);
+
+        let mesh = Mesh {
+            key: key.clone(),
+            peers: BTreeMap::new(),
+        };
+        let state = AppState::new(composition.clone())
+            .for_pipeline("default", composition, &RunnerManager::new(), Some(mesh))
+            .unwrap();
+        let app = create_router(state);
+        assert_eq!(send(app.clone(), None).await, StatusCode::UNAUTHORIZED);
+        let forged = MeshKey::new("guess").sign("handler", id, body.as_bytes(), expires);
+        assert_eq!(
+            send(app.clone(), Some(forged)).await,
+            StatusCode::UNAUTHORIZED
+        );
+        // A token signed for another node doesn't carry over
+        let for_router = key.sign("router", id, body.as_bytes(), expires);
+        assert_eq!(
+            send(app.clone(), Some(for_router)).await,
+            StatusCode::UNAUTHORIZED
+        );
+        // Nor does one signed for another request's body
+        let for_other = key.sign("handler", id, b"{}", expires);
+        assert_eq!(
+            send(app.clone(), Some(for_other)).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // Signed hops reach the handler, whose model is down here
+        let token = key.sign("handler", id, body.as_bytes(), expires);
+        assert_eq!(send(app, Some(token)).await, StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_endpoints() {
+        // Nothing listens on the model's port, so every request fails
+        let json = r#"{
+            "models": {
+                "model": {"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:1/v1"}
+            },
+            "architecture": [
+                {"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]},
+                {"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#;
+        let app = create_router(AppState::new(Composition::from_str(json).unwrap()));
+        let request_id = Uuid::new_v4();
+
+        let send = |request: Request<Body>| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+                )
+            }
+        };
+
+        let (status, body) = send(
+            Request::builder()
+                .method("POST")
+                .uri("/v1/chat/completions")
+                .header("content-type", "application/json")
+                .header("x-request-id", request_id.to_string())
+                .body(Body::from(
+                    r#"{"model": "pipeline", "messages": [{"role": "user", "content": "hi"}]}"#,
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(body["error"]["code"], "upstream_error");
+
+        let (status, body) = send(
+            Request::builder()
+                .uri("/v1/deadletters")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let letters: Vec<DeadLetter> =
+            serde_json::from_value(body["dead_letters"].clone()).unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].request_id, request_id);
+        assert_eq!(letters[0].input.prompt, "hi");
+
+        let (status, body) = send(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/v1/deadletters/{}/requeue", request_id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(body["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Pipeline error"));
+
+        let (status, body) = send(
+            Request::builder()
+                .uri(format!("/v1/deadletters/{}", request_id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["attempts"], 2);
+
+        let (status, _) = send(
+            Request::builder()
+                .uri(format!("/v1/deadletters/{}", Uuid::new_v4()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_delete_session_without_processor() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/v1/sessions/abc")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn test_header_variables_only_declared() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-llmnet-var-user-tier", "premium".parse().unwrap());
+        headers.insert("x-llmnet-var-locale", "de-DE".parse().unwrap());
+        headers.insert("x-llmnet-route", "billing".parse().unwrap());
+
+        let declared = vec!["user_tier".to_string(), "tenant".to_string()];
+        assert_eq!(
+            header_variables(&headers, &declared),
+            vec![("user_tier".to_string(), "premium".to_string())]
+        );
+        assert!(header_variables(&headers, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_route_headers() {
+        let step = |node: &str, latency_ms| RouteStep {
+            node: node.to_string(),
+            latency_ms,
+        };
+        let route = [
+            step("router", Some(120)),
+            step("sales", Some(340)),
+            step("formal-refiner", None),
+        ];
+        assert_eq!(
+            route_headers(&route),
+            vec![
+                (ROUTE_HEADER, "router>sales>formal-refiner".to_string()),
+                (HOPS_HEADER, "3".to_string()),
+                (HOP_LATENCY_HEADER, "router=120ms, sales=340ms".to_string()),
+            ]
+        );
+        assert!(route_headers(&[]).is_empty());
     }
 }
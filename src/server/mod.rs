@@ -1,5 +1,10 @@
 pub mod handlers;
+pub mod openapi;
+pub mod pipelines;
+pub mod reload;
 pub mod state;
 
-pub use handlers::create_router;
+pub use handlers::{apply_assignment, create_router};
+pub use pipelines::{HostedPipeline, HostedPipelineInfo, HostedPipelines};
+pub use reload::{reload_composition, watch_composition, ReloadError};
 pub use state::AppState;
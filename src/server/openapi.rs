@@ -0,0 +1,43 @@
+//! Swagger UI for the OpenAPI documents
+//!
+//! Both the worker and the control plane describe their routes at
+//! `/openapi.json`. Builds with the `swagger-ui` feature also serve an
+//! interactive viewer for that document at `/docs`; its scripts and styles
+//! load from a CDN, so the viewer needs internet access but the binary
+//! doesn't grow.
+
+use axum::Router;
+
+/// Page rendering `/openapi.json` with Swagger UI
+#[cfg(feature = "swagger-ui")]
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>LLMNet API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// Routes serving Swagger UI at `/docs` (none without the `swagger-ui` feature)
+pub fn swagger_ui<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    #[cfg(feature = "swagger-ui")]
+    {
+        use axum::{response::Html, routing::get};
+        Router::new().route("/docs", get(|| async { Html(SWAGGER_UI_HTML) }))
+    }
+
+    #[cfg(not(feature = "swagger-ui"))]
+    Router::new()
+}
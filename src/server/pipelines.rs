@@ -0,0 +1,311 @@
+//! Pipelines hosted by a worker
+//!
+//! Each pipeline assigned to a worker is served from its own `AppState`, so
+//! it has its own processor, traces, dead letters and status. It's reachable
+//! under `/pipelines/{name}/` on the worker's port and, when its assigned
+//! port could be bound, on that port as well.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use axum::Router;
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+use crate::config::models::ModelDefinition;
+use crate::config::Composition;
+use crate::runtime::RunnerManager;
+use crate::server::state::AppState;
+
+/// A pipeline served by this worker
+pub struct HostedPipeline {
+    pub namespace: String,
+    pub state: AppState,
+    router: Router,
+    /// The dedicated port and the task serving it, if it could be bound
+    listener: Option<(u16, JoinHandle<()>)>,
+}
+
+impl HostedPipeline {
+    /// Host a pipeline served from `state`
+    pub fn new(namespace: impl Into<String>, state: AppState) -> Self {
+        Self {
+            namespace: namespace.into(),
+            router: super::create_router(state.clone()),
+            state,
+            listener: None,
+        }
+    }
+
+    /// The router serving this pipeline
+    pub fn router(&self) -> Router {
+        self.router.clone()
+    }
+
+    /// The dedicated port the pipeline is served on, if any
+    pub fn port(&self) -> Option<u16> {
+        self.listener.as_ref().map(|(port, _)| *port)
+    }
+
+    /// Serve the pipeline on its own port until it's replaced
+    pub async fn listen(&mut self, bind_addr: &str, port: u16) -> std::io::Result<u16> {
+        let listener = tokio::net::TcpListener::bind((bind_addr, port)).await?;
+        let port = listener.local_addr()?.port();
+        let router = self.router();
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                tracing::error!("Pipeline listener on port {} failed: {}", port, e);
+            }
+        });
+        self.listener = Some((port, task));
+        Ok(port)
+    }
+
+    /// Stop serving the dedicated port, freeing it for a replacement
+    pub async fn close(&mut self) {
+        if let Some((_, task)) = self.listener.take() {
+            task.abort();
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for HostedPipeline {
+    fn drop(&mut self) {
+        if let Some((_, task)) = &self.listener {
+            task.abort();
+        }
+    }
+}
+
+/// A hosted pipeline as listed by `GET /v1/pipelines`
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HostedPipelineInfo {
+    pub namespace: String,
+    pub name: String,
+    /// Path prefix on the worker's port
+    pub path: String,
+    /// Dedicated port, if the assigned one could be bound
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    pub nodes: usize,
+    pub active_requests: usize,
+    /// Whether a processor could be built for the composition
+    pub ready: bool,
+}
+
+/// The pipelines a worker hosts, by name
+#[derive(Clone, Default)]
+pub struct HostedPipelines {
+    pipelines: Arc<DashMap<String, HostedPipeline>>,
+}
+
+impl HostedPipelines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Host a pipeline, returning the one it replaces
+    pub fn insert(
+        &self,
+        name: impl Into<String>,
+        pipeline: HostedPipeline,
+    ) -> Option<HostedPipeline> {
+        self.pipelines.insert(name.into(), pipeline)
+    }
+
+    /// Stop hosting a pipeline
+    pub fn remove(&self, name: &str) -> Option<HostedPipeline> {
+        self.pipelines.remove(name).map(|(_, p)| p)
+    }
+
+    /// Stop hosting a pipeline of `namespace`
+    pub fn remove_in(&self, namespace: &str, name: &str) -> Option<HostedPipeline> {
+        self.pipelines
+            .remove_if(name, |_, p| p.namespace == namespace)
+            .map(|(_, p)| p)
+    }
+
+    /// Models the hosted pipelines use
+    pub fn models_in_use(&self) -> BTreeSet<String> {
+        self.pipelines
+            .iter()
+            .flat_map(|p| {
+                p.state
+                    .composition()
+                    .models
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// The router serving a pipeline
+    pub fn router(&self, name: &str) -> Option<Router> {
+        self.pipelines.get(name).map(|p| p.router())
+    }
+
+    /// The state a pipeline is served from
+    pub fn state(&self, name: &str) -> Option<AppState> {
+        self.pipelines.get(name).map(|p| p.state.clone())
+    }
+
+    /// Requests in flight across the hosted pipelines
+    pub fn active_request_count(&self) -> usize {
+        self.pipelines
+            .iter()
+            .map(|p| p.state.active_request_count())
+            .sum()
+    }
+
+    /// Stop accepting connections on the dedicated ports, letting the
+    /// ones already open finish
+    pub fn stop_listening(&self) {
+        for mut pipeline in self.pipelines.iter_mut() {
+            if let Some((_, task)) = pipeline.listener.take() {
+                task.abort();
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+
+    /// Every hosted pipeline, by name
+    pub fn list(&self) -> Vec<HostedPipelineInfo> {
+        let infos: BTreeMap<String, HostedPipelineInfo> = self
+            .pipelines
+            .iter()
+            .map(|entry| {
+                let (name, pipeline) = entry.pair();
+                let info = HostedPipelineInfo {
+                    namespace: pipeline.namespace.clone(),
+                    name: name.clone(),
+                    path: pipeline_path(name),
+                    port: pipeline.port(),
+                    nodes: pipeline.state.nodes.len(),
+                    active_requests: pipeline.state.active_request_count(),
+                    ready: pipeline.state.processor.get().is_some(),
+                };
+                (name.clone(), info)
+            })
+            .collect();
+        infos.into_values().collect()
+    }
+}
+
+// ============================================================================
+// SBIO: Pure functions (no I/O)
+// ============================================================================
+
+/// Path prefix a pipeline is served under on the worker's port
+pub fn pipeline_path(name: &str) -> String {
+    format!("/pipelines/{}", name)
+}
+
+/// Point a composition's models at the runners serving them, so the
+/// processor can call them
+pub fn with_runner_endpoints(mut composition: Composition, manager: &RunnerManager) -> Composition {
+    for (name, model) in composition.models.iter_mut() {
+        let Some(endpoint) = manager.get_endpoint(name) else {
+            continue;
+        };
+        let mut config = model.to_config();
+        config.endpoint = Some(endpoint);
+        *model = ModelDefinition::Unified(config);
+    }
+    composition
+}
+
+/// Models of a removed pipeline whose runners can stop: those nothing
+/// else uses
+pub fn released_models(removed: &Composition, in_use: &BTreeSet<String>) -> Vec<String> {
+    let mut models: Vec<String> = removed
+        .models
+        .keys()
+        .filter(|m| !in_use.contains(*m))
+        .cloned()
+        .collect();
+    models.sort();
+    models
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> AppState {
+        let composition = Composition::from_str(
+            r#"{
+                "models": {"small": {"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:1/v1"}},
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": "small", "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        AppState::new(composition)
+    }
+
+    #[tokio::test]
+    async fn test_list_and_replace() {
+        let pipelines = HostedPipelines::new();
+        pipelines.insert("chat", HostedPipeline::new("default", state()));
+        pipelines.insert("code", HostedPipeline::new("dev", state()));
+
+        let mut replacement = HostedPipeline::new("default", state());
+        let port = replacement.listen("127.0.0.1", 0).await.unwrap();
+        assert!(pipelines.insert("chat", replacement).is_some());
+
+        let list = pipelines.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].name, "chat");
+        assert_eq!(list[0].path, "/pipelines/chat");
+        assert_eq!(list[0].port, Some(port));
+        assert!(list[0].ready);
+        assert_eq!(list[1].namespace, "dev");
+        assert_eq!(list[1].port, None);
+
+        assert_eq!(
+            pipelines.models_in_use(),
+            BTreeSet::from(["small".to_string()])
+        );
+        assert!(pipelines.remove_in("prod", "code").is_none());
+        assert_eq!(pipelines.active_request_count(), 0);
+
+        // Closing frees the port for the next version
+        let mut chat = pipelines.remove("chat").unwrap();
+        chat.close().await;
+        assert!(tokio::net::TcpListener::bind(("127.0.0.1", port))
+            .await
+            .is_ok());
+        assert!(pipelines.router("chat").is_none());
+
+        // Stopping listeners on shutdown forgets the dedicated ports
+        let mut code = HostedPipeline::new("dev", state());
+        code.listen("127.0.0.1", 0).await.unwrap();
+        pipelines.insert("code", code);
+        pipelines.stop_listening();
+        assert_eq!(pipelines.list()[0].port, None);
+    }
+
+    #[test]
+    fn test_released_models() {
+        let composition = state().composition();
+        assert_eq!(
+            released_models(&composition, &BTreeSet::new()),
+            ["small".to_string()]
+        );
+        let in_use = BTreeSet::from(["small".to_string()]);
+        assert!(released_models(&composition, &in_use).is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+//! Hot reload of the composition served by `llmnet run`
+//!
+//! The composition file is watched for changes. Each new version is
+//! validated and, if it is good, served by a new processor swapped into the
+//! running [`AppState`]: requests in flight finish on the old processor and
+//! new ones go to the new. An invalid file, or one that needs runners the
+//! server didn't start, leaves the running pipeline untouched.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::config::models::{ModelDefinition, RunnerType};
+use crate::config::{load_composition_file_with_values, Composition, ConfigError};
+use crate::runtime::{ProcessorError, RunnerManager, SharedRunnerManager};
+use crate::server::state::AppState;
+
+/// How long to wait for an editor to finish writing before reloading
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Error, Debug)]
+pub enum ReloadError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    #[error("Model '{0}' needs a new runner; restart to apply")]
+    RunnerChanged(String),
+
+    #[error(transparent)]
+    Processor(#[from] ProcessorError),
+
+    #[error("Failed to watch the composition file: {0}")]
+    Watch(#[from] notify::Error),
+}
+
+// ============================================================================
+// SBIO: Pure functions
+// ============================================================================
+
+/// Whether `llmnet run` starts a local runner for this kind of model
+fn spawns_runner(runner: &RunnerType) -> bool {
+    matches!(
+        runner,
+        RunnerType::Docker
+            | RunnerType::Ollama
+            | RunnerType::Vllm
+            | RunnerType::LlamaCpp
+            | RunnerType::Llamafile
+            | RunnerType::Tgi
+            | RunnerType::Whisper
+    )
+}
+
+/// Point the new composition's local models at the runners already serving
+/// them
+///
+/// A local model that is new, or whose settings or replicas changed, needs
+/// a runner the server doesn't have, so the reload is refused.
+pub fn carry_runner_endpoints(
+    live: &Composition,
+    new: &mut Composition,
+) -> Result<(), ReloadError> {
+    let replicas: BTreeMap<String, _> = new
+        .models
+        .keys()
+        .map(|name| (name.clone(), new.runner_replicas(name)))
+        .collect();
+
+    for (name, definition) in new.models.iter_mut() {
+        let mut config = definition.to_config();
+        if !spawns_runner(&config.runner) {
+            continue;
+        }
+        let Some(live_config) = live.models.get(name).map(|d| d.to_config()) else {
+            return Err(ReloadError::RunnerChanged(name.clone()));
+        };
+
+        config.endpoint = live_config.endpoint.clone();
+        if config != live_config || replicas[name] != live.runner_replicas(name) {
+            return Err(ReloadError::RunnerChanged(name.clone()));
+        }
+        *definition = ModelDefinition::Unified(config);
+    }
+    Ok(())
+}
+
+/// Sections of a composition keyed by name, in a comparable form
+fn named<'a, T: Serialize + 'a>(
+    items: impl IntoIterator<Item = (&'a String, &'a T)>,
+) -> BTreeMap<&'a str, serde_json::Value> {
+    items
+        .into_iter()
+        .map(|(name, item)| {
+            (
+                name.as_str(),
+                serde_json::to_value(item).unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+/// What changed between two versions of a composition, one line per change
+pub fn describe_changes(live: &Composition, new: &Composition) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    let sections = [
+        (
+            "node",
+            named(live.architecture.iter().map(|n| (&n.name, n))),
+            named(new.architecture.iter().map(|n| (&n.name, n))),
+        ),
+        ("model", named(&live.models), named(&new.models)),
+        ("function", named(&live.functions), named(&new.functions)),
+        ("secret", named(&live.secrets), named(&new.secrets)),
+    ];
+    for (kind, old, new) in &sections {
+        for (name, value) in new {
+            match old.get(name) {
+                None => changes.push(format!("added {} '{}'", kind, name)),
+                Some(previous) if previous != value => {
+                    changes.push(format!("changed {} '{}'", kind, name))
+                }
+                Some(_) => {}
+            }
+        }
+        for name in old.keys().filter(|name| !new.contains_key(*name)) {
+            changes.push(format!("removed {} '{}'", kind, name));
+        }
+    }
+
+    if live.sessions != new.sessions {
+        changes.push("changed sessions".to_string());
+    }
+    if live.route_overrides != new.route_overrides {
+        changes.push("changed route-overrides".to_string());
+    }
+    if live.header_variables != new.header_variables {
+        changes.push("changed header-variables".to_string());
+    }
+    if live.trace_headers != new.trace_headers {
+        changes.push("changed trace-headers".to_string());
+    }
+    let queue = |c: &Composition| serde_json::to_value(&c.queue).unwrap_or_default();
+    if queue(live) != queue(new) {
+        changes.push("changed queue (takes effect after a restart)".to_string());
+    }
+    changes
+}
+
+// ============================================================================
+// I/O boundary
+// ============================================================================
+
+/// Load the composition file again and serve it if anything changed
+///
+/// Returns the changes applied; none means the file was saved unchanged.
+pub fn reload_composition(
+    state: &AppState,
+    path: &Path,
+    values: &serde_json::Value,
+    manager: Option<&RunnerManager>,
+) -> Result<Vec<String>, ReloadError> {
+    let mut composition = load_composition_file_with_values(path, values)?;
+    let live = state.composition();
+    carry_runner_endpoints(&live, &mut composition)?;
+
+    let changes = describe_changes(&live, &composition);
+    if !changes.is_empty() {
+        state.reload(composition, manager)?;
+    }
+    Ok(changes)
+}
+
+/// Reload the composition whenever its file changes, until the server stops
+pub fn watch_composition(
+    path: PathBuf,
+    values: serde_json::Value,
+    state: AppState,
+    manager: SharedRunnerManager,
+) -> Result<JoinHandle<()>, ReloadError> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let file_name = path.file_name().map(|n| n.to_os_string());
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        // Editors often replace the file instead of writing to it, so the
+        // directory is watched and other files in it ignored
+        if !event.kind.is_access()
+            && event
+                .paths
+                .iter()
+                .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name)
+        {
+            let _ = tx.send(());
+        }
+    })?;
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    info!("Watching {} for changes", path.display());
+
+    Ok(tokio::spawn(async move {
+        let _watcher = watcher;
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+
+            match reload_composition(&state, &path, &values, Some(&manager)) {
+                Ok(changes) if changes.is_empty() => {
+                    debug!("{} saved without changes", path.display())
+                }
+                Ok(changes) => info!("Reloaded {}: {}", path.display(), changes.join(", ")),
+                Err(e) => warn!(
+                    "Not reloading {}, still serving the previous version: {}",
+                    path.display(),
+                    e
+                ),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn composition(json: &str) -> Composition {
+        Composition::from_str(json).unwrap()
+    }
+
+    const BASE: &str = r#"{
+        "models": {
+            "llama": {"runner": "ollama", "source": "llama3"},
+            "gpt": {"type": "external", "interface": "openai-api", "url": "https://api.openai.com/v1"}
+        },
+        "architecture": [
+            {"name": "router", "layer": 0, "model": "gpt", "adapter": "openai-api", "output-to": [1]},
+            {"name": "support", "layer": 1, "model": "llama", "adapter": "openai-api", "output-to": ["output"]},
+            {"name": "output", "adapter": "output"}
+        ]
+    }"#;
+
+    /// The composition as the server runs it, with the runner's endpoint
+    fn live() -> Composition {
+        let mut live = composition(BASE);
+        let mut config = live.models["llama"].to_config();
+        config.endpoint = Some("http://localhost:9000".to_string());
+        live.models
+            .insert("llama".to_string(), ModelDefinition::Unified(config));
+        live
+    }
+
+    #[test]
+    fn test_carry_runner_endpoints() {
+        let live = live();
+
+        let mut same = composition(BASE);
+        carry_runner_endpoints(&live, &mut same).unwrap();
+        assert_eq!(
+            same.models["llama"].to_config().endpoint.as_deref(),
+            Some("http://localhost:9000")
+        );
+        assert!(describe_changes(&live, &same).is_empty());
+
+        let mut scaled = composition(&BASE.replace(
+            r#""name": "support", "layer": 1,"#,
+            r#""name": "support", "layer": 1, "replicas": 2,"#,
+        ));
+        assert!(matches!(
+            carry_runner_endpoints(&live, &mut scaled),
+            Err(ReloadError::RunnerChanged(model)) if model == "llama"
+        ));
+    }
+
+    #[test]
+    fn test_describe_changes() {
+        let live = live();
+        let mut new = composition(&BASE.replace(
+            r#""output-to": ["output"]}"#,
+            r#""system-prompt": "Be brief.", "output-to": ["output"]},
+            {"name": "sales", "layer": 1, "model": "gpt", "adapter": "openai-api", "output-to": ["output"]}"#,
+        ));
+        new.header_variables = vec!["tier".to_string()];
+        carry_runner_endpoints(&live, &mut new).unwrap();
+
+        assert_eq!(
+            describe_changes(&live, &new),
+            [
+                "added node 'sales'",
+                "changed node 'support'",
+                "changed header-variables"
+            ]
+        );
+        assert_eq!(
+            describe_changes(&new, &live),
+            [
+                "changed node 'support'",
+                "removed node 'sales'",
+                "changed header-variables"
+            ]
+        );
+    }
+}
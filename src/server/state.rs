@@ -1,50 +1,102 @@
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
 use dashmap::DashMap;
+use tokio::sync::Notify;
 use uuid::Uuid;
 
+use crate::adapters::AdapterRegistry;
+use crate::cluster::{ReplicaReports, WorkerStateStore};
 use crate::config::Composition;
-use crate::runtime::{PipelineProcessor, PipelineRequest, RuntimeNode, SharedRunnerManager};
+use crate::metrics::SharedMetricsCollector;
+use crate::runtime::{
+    BudgetLedger, DeadLetterStore, Mesh, ModelCache, PipelineProcessor, PipelineRequest,
+    ProcessorError, RequestLogger, RunnerManager, RuntimeNode, SharedProcessor,
+    SharedRunnerManager,
+};
+use crate::server::pipelines::HostedPipelines;
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
-    pub composition: Arc<Composition>,
+    /// The composition being served, replaced by `reload`
+    composition: Arc<RwLock<Arc<Composition>>>,
     pub nodes: Arc<DashMap<String, RuntimeNode>>,
     pub active_requests: Arc<DashMap<Uuid, PipelineRequest>>,
-    pub processor: Option<Arc<PipelineProcessor>>,
+    pub processor: SharedProcessor,
     pub runner_manager: Option<SharedRunnerManager>,
+    /// Plugin adapters the processor serves custom node types with
+    pub adapters: Arc<AdapterRegistry>,
+    /// Where the processor keeps failed requests
+    pub dead_letters: Arc<DeadLetterStore>,
+    /// Where a sample of requests is logged, when enabled
+    pub request_logger: Option<Arc<RequestLogger>>,
     /// Bind address for this worker (used in assignment responses)
     pub bind_addr: String,
+    /// Port this worker serves on (used in assignment responses)
+    pub port: Option<u16>,
+    /// Pipelines assigned to this worker (worker mode)
+    pub pipelines: HostedPipelines,
+    /// Status of the assigned pipelines, reported with heartbeats (worker
+    /// mode)
+    pub replicas: ReplicaReports,
+    /// Wakes the heartbeat client when the control plane asks for a heartbeat
+    pub heartbeat_trigger: Option<Arc<Notify>>,
+    /// Local metrics shared with the heartbeat client (worker mode)
+    pub metrics: Option<SharedMetricsCollector>,
+    /// Where assignments and runner containers are persisted (worker mode)
+    pub worker_state: Option<Arc<WorkerStateStore>>,
+    /// Control plane this worker registers with; cluster Secrets are
+    /// fetched from it (worker mode)
+    pub control_plane_url: Option<String>,
+    /// Key this worker sends to the control plane, if it requires one
+    pub control_plane_key: Option<String>,
+    /// Model artifacts on this worker's disk (worker mode)
+    pub model_cache: Arc<ModelCache>,
+    /// Workers the pipeline is split across and the key their hops are
+    /// signed with (worker mode)
+    pub mesh: Option<Mesh>,
+    /// What each namespace spent on budgeted models, shared by the
+    /// pipelines this worker hosts
+    pub budgets: Arc<BudgetLedger>,
+    /// Namespace the pipeline's spend on budgeted models is counted in
+    pub namespace: String,
 }
 
 impl AppState {
     pub fn new(composition: Composition) -> Self {
         let nodes = Arc::new(DashMap::new());
-
-        // Build runtime nodes
-        for (port_offset, arch_node) in composition.architecture.iter().enumerate() {
-            let port_offset = port_offset as u16;
-            let model_config = arch_node
-                .model
-                .as_ref()
-                .and_then(|m| composition.models.get(m))
-                .cloned();
-
-            let runtime = RuntimeNode::from_architecture(arch_node, model_config, port_offset);
+        for runtime in runtime_nodes(&composition) {
             nodes.insert(runtime.name.clone(), runtime);
         }
 
         // Create pipeline processor
-        let processor = PipelineProcessor::new(&composition).ok().map(Arc::new);
+        let dead_letters = Arc::new(DeadLetterStore::default());
+        let processor = PipelineProcessor::new(&composition)
+            .ok()
+            .map(|p| Arc::new(p.with_dead_letter_store(dead_letters.clone())));
 
         Self {
-            composition: Arc::new(composition),
+            composition: Arc::new(RwLock::new(Arc::new(composition))),
             nodes,
             active_requests: Arc::new(DashMap::new()),
-            processor,
+            processor: SharedProcessor::new(processor),
             runner_manager: None,
+            adapters: Arc::new(AdapterRegistry::new()),
+            dead_letters,
+            request_logger: None,
             bind_addr: "0.0.0.0".to_string(),
+            port: None,
+            pipelines: HostedPipelines::new(),
+            replicas: ReplicaReports::new(),
+            heartbeat_trigger: None,
+            metrics: None,
+            worker_state: None,
+            control_plane_url: None,
+            control_plane_key: None,
+            model_cache: Arc::new(ModelCache::default()),
+            mesh: None,
+            budgets: Arc::new(BudgetLedger::new()),
+            namespace: "default".to_string(),
         }
     }
 
@@ -54,12 +106,182 @@ impl AppState {
         self
     }
 
+    /// Serve custom node types with these plugin adapters. Call before
+    /// `with_runner_pools`, which keeps them.
+    pub fn with_adapters(mut self, registry: AdapterRegistry) -> Self {
+        self.adapters = Arc::new(registry);
+        self.processor = SharedProcessor::new(self.build_processor(None));
+        self
+    }
+
+    /// Keep failed requests in this store, e.g. one backed by a file. Call
+    /// before `with_runner_pools`, which keeps it.
+    pub fn with_dead_letter_store(mut self, store: DeadLetterStore) -> Self {
+        self.dead_letters = Arc::new(store);
+        self.processor = SharedProcessor::new(self.build_processor(None));
+        self
+    }
+
+    /// Log a sample of requests. Call before `with_runner_pools`, which
+    /// keeps it.
+    pub fn with_request_logger(mut self, logger: Arc<RequestLogger>) -> Self {
+        self.request_logger = Some(logger);
+        self.processor = SharedProcessor::new(self.build_processor(None));
+        self
+    }
+
+    /// Spread node calls across the runner replicas the manager started
+    pub fn with_runner_pools(mut self, manager: &RunnerManager) -> Self {
+        self.processor = SharedProcessor::new(self.build_processor(Some(manager)));
+        self
+    }
+
+    fn build_processor(&self, manager: Option<&RunnerManager>) -> Option<Arc<PipelineProcessor>> {
+        self.processor_for(&self.composition(), manager)
+            .ok()
+            .map(Arc::new)
+    }
+
+    fn processor_for(
+        &self,
+        composition: &Composition,
+        manager: Option<&RunnerManager>,
+    ) -> Result<PipelineProcessor, ProcessorError> {
+        let mut processor = PipelineProcessor::new(composition)?
+            .with_adapters(&self.adapters)
+            .with_dead_letter_store(self.dead_letters.clone())
+            .with_budget_ledger(self.budgets.clone(), &self.namespace);
+        if let Some(logger) = &self.request_logger {
+            processor = processor.with_request_logger(logger.clone());
+        }
+        if let Some(manager) = manager {
+            processor = processor.with_runner_pools(manager);
+        }
+        if let Some(mesh) = &self.mesh {
+            processor = processor.with_mesh(mesh);
+        }
+        Ok(processor)
+    }
+
+    /// The composition being served
+    pub fn composition(&self) -> Arc<Composition> {
+        self.composition.read().unwrap().clone()
+    }
+
+    /// Serve a new version of the composition
+    ///
+    /// New requests go to a processor built from it, which takes over the
+    /// old one's traces, dead letters and sessions; requests in flight finish
+    /// on the old one. If the processor can't be built nothing changes.
+    pub fn reload(
+        &self,
+        composition: Composition,
+        manager: Option<&RunnerManager>,
+    ) -> Result<(), ProcessorError> {
+        let mut processor = self.processor_for(&composition, manager)?;
+        if let Some(previous) = self.processor.get() {
+            processor = processor.with_state_of(&previous);
+        }
+
+        let nodes = runtime_nodes(&composition);
+        *self.composition.write().unwrap() = Arc::new(composition);
+        self.processor.replace(Arc::new(processor));
+        self.nodes
+            .retain(|name, _| nodes.iter().any(|n| &n.name == name));
+        for runtime in nodes {
+            self.nodes.insert(runtime.name.clone(), runtime);
+        }
+        Ok(())
+    }
+
+    /// State for one of the pipelines this worker hosts
+    ///
+    /// The pipeline gets its own composition, processor, traces and dead
+    /// letters, and shares this state's adapters, request logger and budget
+    /// ledger, its spend counted under `namespace`. Its processor spreads
+    /// calls across the manager's runners, and across the workers of the
+    /// mesh when the pipeline is split.
+    pub fn for_pipeline(
+        &self,
+        namespace: &str,
+        composition: Composition,
+        manager: &RunnerManager,
+        mesh: Option<Mesh>,
+    ) -> Result<Self, ProcessorError> {
+        let mut state = Self::new(composition);
+        state.adapters = self.adapters.clone();
+        state.request_logger = self.request_logger.clone();
+        state.bind_addr = self.bind_addr.clone();
+        state.model_cache = self.model_cache.clone();
+        state.mesh = mesh;
+        state.budgets = self.budgets.clone();
+        state.namespace = namespace.to_string();
+        let processor = state.processor_for(&state.composition(), Some(manager))?;
+        state.processor = SharedProcessor::new(Some(Arc::new(processor)));
+        Ok(state)
+    }
+
     /// Set the bind address
     pub fn with_bind_addr(mut self, addr: impl Into<String>) -> Self {
         self.bind_addr = addr.into();
         self
     }
 
+    /// Set the port this worker serves on
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Set the heartbeat trigger shared with the heartbeat client
+    pub fn with_heartbeat_trigger(mut self, trigger: Arc<Notify>) -> Self {
+        self.heartbeat_trigger = Some(trigger);
+        self
+    }
+
+    /// Share the replica reports the heartbeat client sends
+    pub fn with_replica_reports(mut self, replicas: ReplicaReports) -> Self {
+        self.replicas = replicas;
+        self
+    }
+
+    /// Count spend on budgeted models in this ledger, shared with the
+    /// heartbeat client that reports it
+    pub fn with_budgets(mut self, budgets: Arc<BudgetLedger>) -> Self {
+        self.budgets = budgets;
+        self
+    }
+
+    /// Share the metrics collector the heartbeat client reports from
+    pub fn with_metrics_collector(mut self, metrics: SharedMetricsCollector) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Persist assignments and runner containers to this store
+    pub fn with_worker_state(mut self, store: Arc<WorkerStateStore>) -> Self {
+        self.worker_state = Some(store);
+        self
+    }
+
+    /// List and prune the model artifacts in these directories
+    pub fn with_model_cache(mut self, cache: ModelCache) -> Self {
+        self.model_cache = Arc::new(cache);
+        self
+    }
+
+    /// Set the control plane cluster Secrets are fetched from
+    pub fn with_control_plane_url(mut self, url: impl Into<String>) -> Self {
+        self.control_plane_url = Some(url.into());
+        self
+    }
+
+    /// Set the key sent when fetching cluster Secrets
+    pub fn with_control_plane_key(mut self, api_key: impl Into<String>) -> Self {
+        self.control_plane_key = Some(api_key.into());
+        self
+    }
+
     /// Get the router node (layer 0)
     pub fn router_node(&self) -> Option<RuntimeNode> {
         self.nodes.iter().find(|r| r.layer == 0).map(|r| r.clone())
@@ -81,6 +303,23 @@ impl AppState {
     }
 }
 
+/// The runtime view of each node in a composition
+fn runtime_nodes(composition: &Composition) -> Vec<RuntimeNode> {
+    composition
+        .architecture
+        .iter()
+        .enumerate()
+        .map(|(port_offset, arch_node)| {
+            let model_config = arch_node
+                .model
+                .as_ref()
+                .and_then(|m| composition.models.get(m))
+                .cloned();
+            RuntimeNode::from_architecture(arch_node, model_config, port_offset as u16)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -120,4 +359,43 @@ mod tests {
         assert!(completed.is_some());
         assert_eq!(state.active_request_count(), 0);
     }
+
+    #[tokio::test]
+    async fn test_reload_swaps_processor() {
+        let composition = |handler: &str| {
+            let json = format!(
+                r#"{{
+                    "models": {{
+                        "model": {{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:1/v1"}}
+                    }},
+                    "architecture": [
+                        {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                        {{"name": "{handler}", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                        {{"name": "output", "adapter": "output"}}
+                    ]
+                }}"#
+            );
+            Composition::from_str(&json).unwrap()
+        };
+        let state = AppState::new(composition("support"));
+        let before = state.processor.get().unwrap();
+
+        // Leave a dead letter behind to check it survives the reload
+        assert!(before.process("hi").await.is_err());
+
+        state.reload(composition("sales"), None).unwrap();
+        let after = state.processor.get().unwrap();
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert_eq!(after.dead_letters().await.len(), 1);
+        assert_eq!(state.composition().architecture[1].name, "sales");
+        assert!(state.nodes.contains_key("sales"));
+        assert!(!state.nodes.contains_key("support"));
+
+        // A composition the processor can't be built from changes nothing
+        let mut broken = composition("billing");
+        broken.architecture[0].model = None;
+        assert!(state.reload(broken, None).is_err());
+        assert!(Arc::ptr_eq(&after, &state.processor.get().unwrap()));
+        assert!(state.nodes.contains_key("sales"));
+    }
 }
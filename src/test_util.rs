@@ -0,0 +1,58 @@
+//! Fixtures shared by the crate's unit tests
+
+use std::future::Future;
+use std::net::SocketAddr;
+
+use axum::response::IntoResponse;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+/// Serve `app` on an available local port, returning its address
+pub async fn serve(app: Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// A fake model server answering each chat completion request with what
+/// `answer` makes of its body, returning its address
+pub async fn fake_completions<F, Fut, R>(answer: F) -> SocketAddr
+where
+    F: Fn(Value) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = R> + Send + 'static,
+    R: IntoResponse + 'static,
+{
+    serve(Router::new().route(
+        "/v1/chat/completions",
+        axum::routing::post(move |Json(body): Json<Value>| answer(body)),
+    ))
+    .await
+}
+
+/// A model server's answer to a chat completion
+pub fn completion(content: impl Into<Value>) -> Json<Value> {
+    Json(json!({
+        "id": "chatcmpl-test",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content.into()},
+            "finish_reason": "stop"
+        }]
+    }))
+}
+
+/// The same answer, reporting the tokens it took
+pub fn completion_with_usage(
+    content: impl Into<Value>,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+) -> Json<Value> {
+    let Json(mut answer) = completion(content);
+    answer["usage"] = json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens
+    });
+    Json(answer)
+}
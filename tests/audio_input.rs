@@ -0,0 +1,127 @@
+//! Integration tests for audio input nodes and the worker's audio endpoint
+//!
+//! A fake backend transcribes any upload to a fixed question and answers
+//! chat completions by echoing the last user message, so tests can see the
+//! transcript went through the pipeline.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::body::Bytes;
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::{completion, find_available_port, last_message, serve_on, start_worker};
+use llmnet::config::Composition;
+
+/// Fake model backend; returns the multipart bodies of the uploads it got
+async fn start_model_server(port: u16) -> Arc<Mutex<Vec<String>>> {
+    let uploads = Arc::new(Mutex::new(Vec::new()));
+    let seen = uploads.clone();
+
+    let app = Router::new()
+        .route(
+            "/v1/chat/completions",
+            post(|Json(body): Json<Value>| async move {
+                let prompt = last_message(&body);
+                completion(format!("echo: {}", prompt))
+            }),
+        )
+        .route(
+            "/v1/audio/transcriptions",
+            post(move |headers: HeaderMap, body: Bytes| {
+                let seen = seen.clone();
+                async move {
+                    assert!(headers["content-type"]
+                        .to_str()
+                        .unwrap()
+                        .starts_with("multipart/form-data; boundary="));
+                    seen.lock()
+                        .unwrap()
+                        .push(String::from_utf8_lossy(&body).into_owned());
+                    Json(json!({"text": " What is the weather? "}))
+                }
+            }),
+        );
+
+    serve_on(port, app).await;
+    uploads
+}
+
+/// Audio input beside router -> handler -> output
+fn voice_composition(model_port: u16) -> Composition {
+    let json = format!(
+        r#"{{
+            "models": {{
+                "chat": {{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:{port}"}},
+                "whisper": {{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:{port}"}}
+            }},
+            "architecture": [
+                {{"name": "ears", "model": "whisper", "adapter": "audio-input", "extra-options": {{"language": "en"}}}},
+                {{"name": "router", "layer": 0, "model": "chat", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "answer", "layer": 1, "model": "chat", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ]
+        }}"#,
+        port = model_port
+    );
+    Composition::from_str(&json).unwrap()
+}
+
+#[tokio::test]
+async fn test_audio_upload_is_transcribed_and_routed() {
+    let model_port = find_available_port();
+    let uploads = start_model_server(model_port).await;
+    let worker_port = start_worker(voice_composition(model_port)).await;
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "http://127.0.0.1:{}/v1/audio/completions",
+            worker_port
+        ))
+        .header("content-type", "audio/wav")
+        .body(&b"RIFF....WAVEfmt "[..])
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().contains_key("x-request-id"));
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["transcript"], "What is the weather?");
+    assert_eq!(body["object"], "chat.completion");
+    assert_eq!(
+        body["choices"][0]["message"]["content"],
+        "echo: What is the weather?"
+    );
+
+    let uploads = uploads.lock().unwrap();
+    assert_eq!(uploads.len(), 1);
+    assert!(uploads[0].contains("filename=\"audio.wav\""));
+    assert!(uploads[0].contains("RIFF....WAVEfmt "));
+    // The node's language option applies when the upload names none
+    assert!(uploads[0].contains("name=\"language\"\r\n\r\nen\r\n"));
+}
+
+#[tokio::test]
+async fn test_audio_upload_errors() {
+    let model_port = find_available_port();
+    start_model_server(model_port).await;
+    let worker_port = start_worker(voice_composition(model_port)).await;
+    let client = reqwest::Client::new();
+    let url = format!("http://127.0.0.1:{}/v1/audio/completions", worker_port);
+
+    let empty = client.post(&url).send().await.unwrap();
+    assert_eq!(empty.status(), 400);
+
+    let unknown_node = client
+        .post(format!("{}?node=router", url))
+        .body(&b"RIFF"[..])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown_node.status(), 404);
+}
@@ -0,0 +1,229 @@
+//! Integration tests for canary rollouts through the control plane
+
+mod common;
+
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::serve;
+use llmnet::cluster::{
+    create_control_plane_router, rollout_decision, ClusterController, ControlPlaneState, Pipeline,
+    PipelineStatus, RolloutDecision, RolloutPhase,
+};
+
+/// A replica that answers every chat completion with `status` and its name
+async fn start_replica(name: &'static str, status: StatusCode) -> String {
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(_): Json<Value>| async move {
+            (
+                status,
+                Json(json!({
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": name},
+                        "finish_reason": "stop"
+                    }]
+                })),
+            )
+        }),
+    );
+    serve(app).await
+}
+
+fn manifest(model: &str) -> Value {
+    json!({
+        "apiVersion": "llmnet/v1",
+        "kind": "Pipeline",
+        "metadata": {"name": "bot", "namespace": "default"},
+        "spec": {
+            "composition": {
+                "models": {model: {"type": "external", "interface": "openai-api", "url": "http://a"}},
+                "architecture": [
+                    {"name": "router", "layer": 0, "model": model, "adapter": "openai-api", "output-to": ["output"]},
+                    {"name": "output", "adapter": "output"}
+                ]
+            },
+            "strategy": {
+                "type": "Canary",
+                "canary": {"weight": 50, "maxErrorRate": 0.1, "minRequests": 4}
+            }
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_apply_starts_rollout_and_proxy_splits_traffic() {
+    let stable = start_replica("stable", StatusCode::OK).await;
+    let canary = start_replica("canary", StatusCode::INTERNAL_SERVER_ERROR).await;
+
+    let state = ControlPlaneState::with_controller(ClusterController::new());
+    let controller = state.controller.clone();
+    let base = serve(create_control_plane_router(state)).await;
+    let client = reqwest::Client::new();
+    let pipeline_url = format!("{}/v1/namespaces/default/pipelines/bot", base);
+
+    // First apply creates the pipeline
+    let response = client
+        .put(&pipeline_url)
+        .json(&manifest("v1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // Pretend the orchestrator scheduled it
+    let mut status = PipelineStatus::initial();
+    status.replicas = 1;
+    status.endpoints = vec![stable];
+    controller
+        .update_pipeline_status("default", "bot", status)
+        .unwrap();
+
+    // A changed composition starts a rollout instead of a redeploy
+    let response = client
+        .put(&pipeline_url)
+        .json(&manifest("v2"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(
+        body["pipeline"]["status"]["rollout"]["phase"],
+        "Progressing"
+    );
+    assert_eq!(body["pipeline"]["status"]["rollout"]["weight"], 50);
+
+    let mut status = controller
+        .get_pipeline("default", "bot")
+        .and_then(|p| p.status)
+        .unwrap();
+    status.rollout.as_mut().unwrap().canary_endpoints = vec![canary];
+    controller
+        .update_pipeline_status("default", "bot", status)
+        .unwrap();
+
+    let mut answers = Vec::new();
+    for _ in 0..8 {
+        let response = client
+            .post(format!("{}/chat/completions", pipeline_url))
+            .json(&json!({"model": "llmnet", "messages": [{"role": "user", "content": "hi"}]}))
+            .send()
+            .await
+            .unwrap();
+        let body: Value = response.json().await.unwrap();
+        answers.push(
+            body["choices"][0]["message"]["content"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        );
+    }
+    assert_eq!(answers.iter().filter(|a| *a == "canary").count(), 4);
+    assert_eq!(answers.iter().filter(|a| *a == "stable").count(), 4);
+
+    let pipeline: Pipeline = controller.get_pipeline("default", "bot").unwrap();
+    let rollout = pipeline.status.unwrap().rollout.unwrap();
+    assert_eq!(rollout.phase, RolloutPhase::Progressing);
+    assert_eq!(rollout.canary.requests, 4);
+    assert_eq!(rollout.canary.errors, 4);
+    assert_eq!(rollout.stable.errors, 0);
+
+    let strategy = &pipeline.spec.strategy;
+    assert!(matches!(
+        rollout_decision(&rollout, strategy.kind(), &strategy.canary_params(), true),
+        RolloutDecision::RollBack(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_proxy_rejects_unknown_and_unscheduled_pipelines() {
+    let state = ControlPlaneState::new();
+    let controller = state.controller.clone();
+    let base = serve(create_control_plane_router(state)).await;
+    let client = reqwest::Client::new();
+    let url = format!(
+        "{}/v1/namespaces/default/pipelines/bot/chat/completions",
+        base
+    );
+    let request = json!({"model": "llmnet", "messages": [{"role": "user", "content": "hi"}]});
+
+    let response = client.post(&url).json(&request).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let pipeline: Pipeline = serde_json::from_value(manifest("v1")).unwrap();
+    controller.deploy_pipeline(pipeline).unwrap();
+    let response = client.post(&url).json(&request).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // Proxied inference isn't a control plane mutation
+    let audit: Value = client
+        .get(format!("{}/v1/audit", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(audit["items"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_proxy_skips_unhealthy_replicas() {
+    use llmnet::cluster::node::{NodeInfo, NodePipelineInfo, ReplicaStatus};
+    use llmnet::cluster::{Node, NodeCapacity, NodeStatus};
+
+    let healthy = start_replica("healthy", StatusCode::OK).await;
+    let broken = start_replica("broken", StatusCode::OK).await;
+
+    let state = ControlPlaneState::with_controller(ClusterController::new());
+    let controller = state.controller.clone();
+    let base = serve(create_control_plane_router(state)).await;
+
+    let pipeline: Pipeline = serde_json::from_value(manifest("v1")).unwrap();
+    controller.deploy_pipeline(pipeline).unwrap();
+    let mut status = PipelineStatus::initial();
+    status.replicas = 2;
+    status.endpoints = vec![healthy, broken.clone()];
+    controller
+        .update_pipeline_status("default", "bot", status)
+        .unwrap();
+
+    // The health checker found the node serving "broken" unhealthy
+    let port: u16 = broken.rsplit(':').next().unwrap().parse().unwrap();
+    controller
+        .register_node(Node::new("worker-2", "127.0.0.1"))
+        .unwrap();
+    let mut node_status = NodeStatus::new(NodeCapacity::default(), NodeInfo::from_system());
+    node_status.pipelines = vec![NodePipelineInfo {
+        name: "bot".to_string(),
+        namespace: "default".to_string(),
+        port,
+        status: ReplicaStatus::Unhealthy,
+        reason: None,
+        restarts: 0,
+    }];
+    controller
+        .update_node_status("worker-2", node_status)
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    for _ in 0..4 {
+        let body: Value = client
+            .post(format!(
+                "{}/v1/namespaces/default/pipelines/bot/chat/completions",
+                base
+            ))
+            .json(&json!({"model": "llmnet", "messages": [{"role": "user", "content": "hi"}]}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "healthy");
+    }
+}
@@ -0,0 +1,72 @@
+//! Fixtures shared by the integration tests
+//!
+//! Each test binary compiles its own copy and uses only some of them.
+#![allow(dead_code)]
+
+use std::net::TcpListener;
+use std::time::Duration;
+
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use tokio::time::sleep;
+
+use llmnet::config::Composition;
+use llmnet::server::{create_router, AppState};
+
+/// Find an available port for testing
+pub fn find_available_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .expect("Failed to bind to address")
+        .local_addr()
+        .expect("Failed to get local address")
+        .port()
+}
+
+/// Serve `app` on `port` in the background
+pub async fn serve_on(port: u16, app: Router) {
+    let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+        .await
+        .expect("Failed to bind");
+
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    sleep(Duration::from_millis(50)).await;
+}
+
+/// Serve `app` on an available port, returning its base URL
+pub async fn serve(app: Router) -> String {
+    let port = find_available_port();
+    serve_on(port, app).await;
+    format!("http://127.0.0.1:{}", port)
+}
+
+/// Start a worker serving `composition`, returning its port
+pub async fn start_worker(composition: Composition) -> u16 {
+    let port = find_available_port();
+    serve_on(port, create_router(AppState::new(composition))).await;
+    port
+}
+
+/// A model server's answer to a chat completion
+pub fn completion(content: impl Into<Value>) -> Json<Value> {
+    Json(json!({
+        "id": "chatcmpl-test",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content.into()},
+            "finish_reason": "stop"
+        }]
+    }))
+}
+
+/// Content of the last message of a chat completion request
+pub fn last_message(body: &Value) -> String {
+    body["messages"]
+        .as_array()
+        .and_then(|m| m.last())
+        .and_then(|m| m["content"].as_str())
+        .unwrap_or_default()
+        .to_string()
+}
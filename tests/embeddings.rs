@@ -0,0 +1,145 @@
+//! Integration tests for embedding nodes and the worker's embeddings endpoint
+//!
+//! A fake OpenAI-compatible backend serves both chat completions and
+//! embeddings, counting embedding calls so tests can see the pipeline used it.
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::{completion, find_available_port, serve_on, start_worker};
+use llmnet::config::Composition;
+
+/// Fake model backend; returns the number of embedding calls seen so far
+async fn start_model_server(port: u16) -> Arc<AtomicUsize> {
+    let embed_calls = Arc::new(AtomicUsize::new(0));
+    let counter = embed_calls.clone();
+
+    let app = Router::new()
+        .route(
+            "/v1/chat/completions",
+            post(|Json(_): Json<Value>| async { completion("handled") }),
+        )
+        .route(
+            "/v1/embeddings",
+            post(move |Json(body): Json<Value>| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let inputs = match &body["input"] {
+                        Value::Array(items) => items.len(),
+                        _ => 1,
+                    };
+                    let data: Vec<Value> = (0..inputs)
+                        .map(|i| json!({"object": "embedding", "index": i, "embedding": [0.5, i]}))
+                        .collect();
+                    Json(json!({
+                        "object": "list",
+                        "data": data,
+                        "model": body["model"]
+                    }))
+                }
+            }),
+        );
+
+    serve_on(port, app).await;
+    embed_calls
+}
+
+/// Router -> embedding step -> answering handler -> output
+fn rag_composition(model_port: u16) -> Composition {
+    let json = format!(
+        r#"{{
+            "models": {{
+                "chat": {{
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://127.0.0.1:{port}"
+                }},
+                "embedder": {{
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://127.0.0.1:{port}/v1"
+                }}
+            }},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "chat", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "vectorize", "layer": 1, "model": "embedder", "adapter": "embedding", "output-to": [2]}},
+                {{"name": "answer", "layer": 2, "model": "chat", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ]
+        }}"#,
+        port = model_port
+    );
+    Composition::from_str(&json).unwrap()
+}
+
+#[tokio::test]
+async fn test_embeddings_endpoint_uses_embedding_node() {
+    let model_port = find_available_port();
+    let embed_calls = start_model_server(model_port).await;
+    let worker_port = start_worker(rag_composition(model_port)).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/v1/embeddings", worker_port))
+        .json(&json!({"model": "embedder", "input": ["first", "second"]}))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["object"], "list");
+    assert_eq!(body["data"].as_array().unwrap().len(), 2);
+    assert_eq!(body["data"][1]["embedding"], json!([0.5, 1.0]));
+    assert_eq!(embed_calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_pipeline_passes_content_through_embedding_node() {
+    let model_port = find_available_port();
+    let embed_calls = start_model_server(model_port).await;
+    let worker_port = start_worker(rag_composition(model_port)).await;
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "http://127.0.0.1:{}/v1/chat/completions",
+            worker_port
+        ))
+        .json(&json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["choices"][0]["message"]["content"], "handled");
+    assert_eq!(embed_calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_embeddings_endpoint_rejects_unknown_model() {
+    let model_port = find_available_port();
+    let embed_calls = start_model_server(model_port).await;
+    let worker_port = start_worker(rag_composition(model_port)).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/v1/embeddings", worker_port))
+        .json(&json!({"model": "text-embedding-3-large", "input": "hello"}))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 404);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "model_not_found");
+    assert_eq!(embed_calls.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,135 @@
+//! Integration tests for the control plane's gRPC API
+
+mod common;
+
+use std::time::Duration;
+
+use futures::StreamExt;
+use tokio::time::{sleep, timeout};
+
+use common::find_available_port;
+use llmnet::cluster::grpc::proto::control_plane_client::ControlPlaneClient;
+use llmnet::cluster::grpc::proto::{
+    DeployPipelineRequest, EventType, ListNodesRequest, ListPipelinesRequest, PipelineRef,
+    ScalePipelineRequest, WatchPipelinesRequest,
+};
+use llmnet::cluster::{serve_grpc, ControlPlaneState};
+
+const MANIFEST: &str = r#"{
+    "apiVersion": "llmnet/v1",
+    "kind": "Pipeline",
+    "metadata": {"name": "chatbot", "namespace": "default"},
+    "spec": {
+        "replicas": 1,
+        "composition": {
+            "models": {},
+            "architecture": [{"name": "output", "adapter": "output"}]
+        }
+    }
+}"#;
+
+#[tokio::test]
+async fn test_grpc_deploy_list_and_watch() {
+    let port = find_available_port();
+    let state = ControlPlaneState::new();
+    tokio::spawn(serve_grpc(
+        state.clone(),
+        format!("127.0.0.1:{}", port).parse().unwrap(),
+    ));
+    sleep(Duration::from_millis(100)).await;
+
+    let mut client = ControlPlaneClient::connect(format!("http://127.0.0.1:{}", port))
+        .await
+        .unwrap();
+
+    let mut watch = client
+        .watch_pipelines(WatchPipelinesRequest::default())
+        .await
+        .unwrap()
+        .into_inner();
+
+    let deployed = client
+        .deploy_pipeline(DeployPipelineRequest {
+            manifest_json: MANIFEST.to_string(),
+            force: false,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .pipeline
+        .unwrap();
+    assert_eq!(deployed.name, "chatbot");
+    assert_eq!(deployed.namespace, "default");
+
+    // The same pipeline is visible through the shared controller
+    assert!(state
+        .controller
+        .get_pipeline("default", "chatbot")
+        .is_some());
+
+    let duplicate = client
+        .deploy_pipeline(DeployPipelineRequest {
+            manifest_json: MANIFEST.to_string(),
+            force: false,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(duplicate.code(), tonic::Code::AlreadyExists);
+
+    client
+        .scale_pipeline(ScalePipelineRequest {
+            name: "chatbot".to_string(),
+            namespace: String::new(),
+            replicas: 3,
+        })
+        .await
+        .unwrap();
+
+    let pipelines = client
+        .list_pipelines(ListPipelinesRequest::default())
+        .await
+        .unwrap()
+        .into_inner()
+        .pipelines;
+    assert_eq!(pipelines.len(), 1);
+    assert_eq!(pipelines[0].replicas, 3);
+
+    let nodes = client
+        .list_nodes(ListNodesRequest {})
+        .await
+        .unwrap()
+        .into_inner()
+        .nodes;
+    assert!(nodes.is_empty());
+
+    client
+        .delete_pipeline(PipelineRef {
+            name: "chatbot".to_string(),
+            namespace: "default".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let mut events = Vec::new();
+    for _ in 0..3 {
+        let event = timeout(Duration::from_secs(5), watch.next())
+            .await
+            .expect("watch event")
+            .unwrap()
+            .unwrap();
+        events.push(event.r#type());
+    }
+    assert_eq!(
+        events,
+        vec![EventType::Added, EventType::Modified, EventType::Deleted]
+    );
+
+    let missing = client
+        .get_pipeline(PipelineRef {
+            name: "chatbot".to_string(),
+            namespace: String::new(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(missing.code(), tonic::Code::NotFound);
+}
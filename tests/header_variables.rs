@@ -0,0 +1,91 @@
+//! Integration tests for request variables set by X-LLMNet-Var-* headers
+
+mod common;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::{completion, find_available_port, serve_on};
+use llmnet::config::Composition;
+
+/// Model backend whose router always picks the free handler; handlers answer
+/// with the model name they were called with (their node name)
+async fn start_model_server(port: u16) {
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(|Json(body): Json<Value>| async move {
+            let model = body["model"].as_str().unwrap_or_default().to_string();
+            let content = if model.starts_with("router") {
+                "free-handler".to_string()
+            } else {
+                format!("answer from {}", model)
+            };
+            completion(content)
+        }),
+    );
+    serve_on(port, app).await;
+}
+
+async fn start_worker() -> String {
+    let model_port = find_available_port();
+    start_model_server(model_port).await;
+
+    let url = format!("http://127.0.0.1:{}", model_port);
+    let json = format!(
+        r#"{{
+            "models": {{
+                "router-model": {{"type": "external", "interface": "openai-api", "url": "{url}"}},
+                "free-model": {{"type": "external", "interface": "openai-api", "url": "{url}"}},
+                "premium-model": {{"type": "external", "interface": "openai-api", "url": "{url}"}}
+            }},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "router-model", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "free-handler", "layer": 1, "model": "free-model", "adapter": "openai-api", "if": "$user_tier != \"premium\"", "output-to": ["output"]}},
+                {{"name": "premium-handler", "layer": 1, "model": "premium-model", "adapter": "openai-api", "if": "$user_tier == \"premium\"", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ],
+            "header-variables": ["user_tier"]
+        }}"#
+    );
+    let composition = Composition::from_str(&json).unwrap();
+    let worker_port = common::start_worker(composition).await;
+    format!("http://127.0.0.1:{}", worker_port)
+}
+
+async fn complete(base: &str, headers: &[(&str, &str)]) -> String {
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&json!({
+            "model": "llmnet",
+            "messages": [{"role": "user", "content": "Plan my trip"}]
+        }));
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let body: Value = request.send().await.unwrap().json().await.unwrap();
+    body["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_header_variable_drives_conditions() {
+    let base = start_worker().await;
+
+    let answer = complete(&base, &[("X-LLMNet-Var-User-Tier", "premium")]).await;
+    assert_eq!(answer, "answer from premium-handler");
+
+    let answer = complete(&base, &[("X-LLMNet-Var-User-Tier", "free")]).await;
+    assert_eq!(answer, "answer from free-handler");
+}
+
+#[tokio::test]
+async fn test_undeclared_header_variables_are_ignored() {
+    let base = start_worker().await;
+
+    // `tier` isn't declared, so `$user_tier` stays unset
+    let answer = complete(&base, &[("X-LLMNet-Var-Tier", "premium")]).await;
+    assert_eq!(answer, "answer from free-handler");
+}
@@ -0,0 +1,122 @@
+//! Integration tests for node sessions
+//!
+//! A control plane with a reconcile interval far longer than the test, and a
+//! worker heartbeat client that keeps a session open. Assignments can only
+//! reach the worker in time by being pushed over the session.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch};
+use tokio::time::{sleep, timeout};
+
+use llmnet::cluster::{
+    create_control_plane_router, session_url, spawn_heartbeat, spawn_orchestrator,
+    AssignmentRequest, AssignmentResponse, ClusterController, ControlPlaneState, HeartbeatConfig,
+    Node, NodePhase, OrchestratorConfig, Pipeline,
+};
+use llmnet::config::Composition;
+use llmnet::metrics::new_shared_collector;
+
+/// Start a control plane and its orchestrator, returning its URL and the
+/// orchestrator's shutdown sender (dropping it stops the orchestrator)
+async fn start_control_plane(controller: Arc<ClusterController>) -> (String, watch::Sender<()>) {
+    let state = ControlPlaneState::with_controller((*controller).clone());
+    let app = create_control_plane_router(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let config = OrchestratorConfig {
+        reconcile_interval_secs: 3600,
+        ..Default::default()
+    };
+    let orchestrator = spawn_orchestrator(controller, config);
+    (format!("http://{}", addr), orchestrator)
+}
+
+fn chat_pipeline() -> Pipeline {
+    let composition = Composition::from_str(
+        r#"{
+            "models": {},
+            "architecture": [
+                {"name": "router", "layer": 0, "adapter": "openai-api"},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#,
+    )
+    .unwrap();
+    Pipeline::new("chat", composition)
+}
+
+#[tokio::test]
+async fn test_assignment_pushed_over_session() {
+    let controller = Arc::new(ClusterController::new());
+    let (cp_url, _orchestrator) = start_control_plane(controller.clone()).await;
+
+    let (assignments_tx, mut assignments) = mpsc::unbounded_channel::<AssignmentRequest>();
+    let config = HeartbeatConfig::new(&cp_url, "worker-1")
+        .with_interval(1)
+        .with_registration(Node::new("worker-1", "127.0.0.1"))
+        .with_session(assignments_tx);
+    let _shutdown = spawn_heartbeat(config, new_shared_collector());
+
+    // Wait for the session and a heartbeat sent over it
+    timeout(Duration::from_secs(10), async {
+        loop {
+            let ready = controller
+                .get_node("worker-1")
+                .and_then(|n| n.status)
+                .is_some_and(|s| s.phase == NodePhase::Ready);
+            if ready && controller.sessions().is_connected("worker-1") {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("worker never connected");
+
+    controller.deploy_pipeline(chat_pipeline()).unwrap();
+
+    let request = timeout(Duration::from_secs(2), assignments.recv())
+        .await
+        .expect("assignment wasn't pushed")
+        .unwrap();
+    assert_eq!(request.assignment.name, "chat");
+    request
+        .reply
+        .send(AssignmentResponse {
+            success: true,
+            endpoint: Some("http://127.0.0.1:9999".to_string()),
+            error: None,
+        })
+        .unwrap();
+
+    let endpoints = timeout(Duration::from_secs(2), async {
+        loop {
+            let status = controller.get_pipeline("default", "chat").unwrap().status;
+            if let Some(status) = status.filter(|s| !s.endpoints.is_empty()) {
+                return status.endpoints;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("pipeline never scheduled");
+    assert_eq!(endpoints, ["http://127.0.0.1:9999"]);
+}
+
+#[tokio::test]
+async fn test_session_rejected_for_unknown_node() {
+    let controller = Arc::new(ClusterController::new());
+    let (cp_url, _orchestrator) = start_control_plane(controller).await;
+
+    let url = session_url(&cp_url, "ghost");
+    match tokio_tungstenite::connect_async(&url).await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), 404);
+        }
+        other => panic!("expected a 404, got {:?}", other.map(|_| ())),
+    }
+}
@@ -0,0 +1,109 @@
+//! Integration tests for message queue ingestion
+
+mod common;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::Value;
+use tokio::sync::{mpsc, Mutex};
+
+use common::{completion, find_available_port, last_message, serve_on};
+use llmnet::config::Composition;
+use llmnet::runtime::queue::run_queue_worker;
+use llmnet::runtime::{PipelineProcessor, QueueError, QueueSink, QueueSource};
+
+/// Model backend that answers with the last message it was sent, uppercased
+async fn start_model_server() -> u16 {
+    let port = find_available_port();
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(|Json(body): Json<Value>| async move {
+            let last = last_message(&body).to_uppercase();
+
+            completion(last)
+        }),
+    );
+    serve_on(port, app).await;
+    port
+}
+
+struct ChannelSource(mpsc::UnboundedReceiver<Vec<u8>>);
+
+#[async_trait]
+impl QueueSource for ChannelSource {
+    async fn next(&mut self) -> Option<Result<Vec<u8>, QueueError>> {
+        self.0.recv().await.map(Ok)
+    }
+}
+
+#[derive(Default)]
+struct CollectingSink(Mutex<Vec<Value>>);
+
+#[async_trait]
+impl QueueSink for CollectingSink {
+    async fn publish(&self, payload: Vec<u8>) -> Result<(), QueueError> {
+        self.0
+            .lock()
+            .await
+            .push(serde_json::from_slice(&payload).unwrap());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_queue_worker_publishes_results() {
+    let model_port = start_model_server().await;
+    let composition = Composition::from_str(&format!(
+        r#"{{
+            "models": {{
+                "model": {{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:{}"}}
+            }},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ],
+            "queue": {{"kind": "nats", "brokers": ["nats://unused:4222"], "input-topic": "in", "output-topic": "out"}}
+        }}"#,
+        model_port
+    ))
+    .unwrap();
+    let processor = Arc::new(PipelineProcessor::new(&composition).unwrap());
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tx.send(br#"{"id": "job-1", "prompt": "hello"}"#.to_vec())
+        .unwrap();
+    tx.send(b"plain text prompt".to_vec()).unwrap();
+    tx.send(br#"{"id": "job-3"}"#.to_vec()).unwrap();
+    drop(tx);
+
+    let sink = Arc::new(CollectingSink::default());
+    run_queue_worker(
+        processor.into(),
+        Box::new(ChannelSource(rx)),
+        sink.clone(),
+        2,
+    )
+    .await;
+
+    let results = sink.0.lock().await;
+    assert_eq!(results.len(), 3);
+
+    let job = results.iter().find(|r| r["id"] == "job-1").unwrap();
+    assert_eq!(job["output"], "HELLO");
+
+    // Plain text jobs are answered under their request ID
+    let plain = results
+        .iter()
+        .find(|r| r["output"] == "PLAIN TEXT PROMPT")
+        .unwrap();
+    assert!(plain["id"].as_str().is_some_and(|id| !id.is_empty()));
+
+    // Malformed jobs still get a result, so producers aren't left waiting
+    assert!(results
+        .iter()
+        .any(|r| r["id"].is_null() && r["error"].is_string()));
+}
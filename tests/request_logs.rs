@@ -0,0 +1,98 @@
+//! Integration tests for request logging
+//!
+//! A worker logs every request with emails redacted and ships the logs to a
+//! control plane, where they are read back from `GET /v1/requestlogs`.
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use tokio::time::sleep;
+
+use common::{completion, last_message, serve};
+use llmnet::cluster::{create_control_plane_router, ControlPlaneState};
+use llmnet::config::Composition;
+use llmnet::runtime::{spawn_request_log_shipper, Redactor, RequestLogger};
+use llmnet::server::{create_router, AppState};
+
+/// Fake model backend echoing the last user message
+fn model_server() -> Router {
+    Router::new().route(
+        "/v1/chat/completions",
+        post(|Json(body): Json<Value>| async move {
+            let prompt = last_message(&body);
+            completion(format!("echo: {}", prompt))
+        }),
+    )
+}
+
+#[tokio::test]
+async fn test_requests_are_redacted_and_shipped() {
+    let model_url = serve(model_server()).await;
+    let control_plane_url = serve(create_control_plane_router(ControlPlaneState::new())).await;
+
+    let composition = Composition::from_str(&format!(
+        r#"{{
+            "models": {{"chat": {{"type": "external", "interface": "openai-api", "url": "{}"}}}},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "chat", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "answer", "layer": 1, "model": "chat", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ]
+        }}"#,
+        model_url
+    ))
+    .unwrap();
+    let redactor = Redactor::new(&["email".to_string()]).unwrap();
+    let logger = Arc::new(RequestLogger::new(100.0, redactor, "worker-1").with_shipping());
+    spawn_request_log_shipper(
+        logger.clone(),
+        control_plane_url.clone(),
+        None,
+        Duration::from_millis(50),
+    );
+    let worker_url = serve(create_router(
+        AppState::new(composition).with_request_logger(logger),
+    ))
+    .await;
+
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{}/v1/chat/completions", worker_url))
+        .json(&json!({
+            "model": "llmnet",
+            "messages": [{"role": "user", "content": "Reply to jo@example.com"}]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let mut items = Vec::new();
+    for _ in 0..40 {
+        let body: Value = client
+            .get(format!("{}/v1/requestlogs", control_plane_url))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        items = body["items"].as_array().cloned().unwrap_or_default();
+        if !items.is_empty() {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["source"], "worker-1");
+    assert_eq!(items[0]["prompt"], "Reply to [REDACTED]");
+    assert_eq!(items[0]["output"], "echo: Reply to [REDACTED]");
+    assert_eq!(items[0]["route"], json!(["answer", "output"]));
+    assert!(!items[0].to_string().contains("jo@example.com"));
+}
@@ -0,0 +1,150 @@
+//! Integration tests for the worker's request trace endpoint
+
+mod common;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::{find_available_port, serve_on, start_worker};
+use llmnet::config::Composition;
+
+/// Model backend that reports token usage with every answer
+async fn start_model_server(port: u16) {
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(|Json(_): Json<Value>| async {
+            Json(json!({
+                "id": "chatcmpl-test",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "handled"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15}
+            }))
+        }),
+    );
+    serve_on(port, app).await;
+}
+
+#[tokio::test]
+async fn test_trace_is_available_by_request_id() {
+    let model_port = find_available_port();
+    start_model_server(model_port).await;
+
+    let json = format!(
+        r#"{{
+            "models": {{
+                "model": {{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:{}"}}
+            }},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ]
+        }}"#,
+        model_port
+    );
+    let worker_port = start_worker(Composition::from_str(&json).unwrap()).await;
+
+    let client = reqwest::Client::new();
+    let base = format!("http://127.0.0.1:{}", worker_port);
+    let request_id = "5c56c793-69f3-4fbf-87e6-c4bf54c28c26";
+
+    let response = client
+        .post(format!("{}/v1/chat/completions", base))
+        .header("x-request-id", request_id)
+        .json(&json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "Hello"}]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-request-id"], request_id);
+
+    let trace: Value = client
+        .get(format!("{}/v1/requests/{}", base, request_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(trace["request_id"], request_id);
+    assert_eq!(trace["prompt"], "Hello");
+    assert_eq!(trace["output"], "handled");
+    assert_eq!(trace["total_tokens"], 15);
+
+    let hops = trace["hops"].as_array().unwrap();
+    let nodes: Vec<&str> = hops.iter().filter_map(|h| h["node"].as_str()).collect();
+    assert_eq!(nodes, vec!["handler", "output"]);
+    assert_eq!(hops[0]["prompt_tokens"], 12);
+    assert!(hops[0]["latency_ms"].is_u64());
+
+    let missing = client
+        .get(format!(
+            "{}/v1/requests/00000000-0000-0000-0000-000000000000",
+            base
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+}
+
+#[tokio::test]
+async fn test_trace_headers_describe_the_route() {
+    let model_port = find_available_port();
+    start_model_server(model_port).await;
+
+    let composition = |trace_headers: bool| {
+        Composition::from_str(&format!(
+            r#"{{
+                "trace-headers": {},
+                "models": {{
+                    "model": {{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:{}"}}
+                }},
+                "architecture": [
+                    {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                    {{"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                    {{"name": "output", "adapter": "output"}}
+                ]
+            }}"#,
+            trace_headers, model_port
+        ))
+        .unwrap()
+    };
+    let client = reqwest::Client::new();
+    let request = json!({"model": "test", "messages": [{"role": "user", "content": "Hello"}]});
+
+    let traced_port = start_worker(composition(true)).await;
+    let response = client
+        .post(format!(
+            "http://127.0.0.1:{}/v1/chat/completions",
+            traced_port
+        ))
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+    let headers = response.headers();
+    assert_eq!(headers["x-llmnet-route"], "router>handler");
+    assert_eq!(headers["x-llmnet-hops"], "2");
+    let latency = headers["x-llmnet-hop-latency"].to_str().unwrap();
+    assert!(latency.starts_with("handler=") && latency.ends_with("ms"));
+
+    let plain_port = start_worker(composition(false)).await;
+    let response = client
+        .post(format!(
+            "http://127.0.0.1:{}/v1/chat/completions",
+            plain_port
+        ))
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+    assert!(response.headers().get("x-llmnet-route").is_none());
+}
@@ -0,0 +1,117 @@
+//! Integration test for retriever nodes backed by Qdrant
+//!
+//! Fake Qdrant and model servers let a request flow router -> embedding ->
+//! retriever -> handler, with the handler echoing the prompt it was given.
+
+mod common;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::{completion, find_available_port, serve_on, start_worker};
+use llmnet::config::Composition;
+
+/// Model backend that embeds everything to the same vector and answers
+/// chat requests with the prompt it received
+async fn start_model_server(port: u16) {
+    let app = Router::new()
+        .route(
+            "/v1/chat/completions",
+            post(|Json(body): Json<Value>| async move {
+                let prompt = body["messages"][0]["content"].clone();
+                completion(prompt)
+            }),
+        )
+        .route(
+            "/v1/embeddings",
+            post(|Json(_): Json<Value>| async {
+                Json(json!({
+                    "data": [{"index": 0, "embedding": [0.1, 0.2, 0.3]}]
+                }))
+            }),
+        );
+    serve_on(port, app).await;
+}
+
+/// Qdrant stand-in that checks the query and returns two points
+async fn start_qdrant(port: u16) {
+    let app = Router::new().route(
+        "/collections/{collection}/points/search",
+        post(|Json(body): Json<Value>| async move {
+            // The vector arrives as f32, so compare loosely
+            let vector = body["vector"].as_array().unwrap();
+            assert_eq!(vector.len(), 3);
+            assert!((vector[2].as_f64().unwrap() - 0.3).abs() < 1e-6);
+            assert_eq!(body["limit"], 2);
+            Json(json!({
+                "result": [
+                    {"id": 7, "score": 0.91, "payload": {"text": "Llamas hum."}},
+                    {"id": 12, "score": 0.84, "payload": {"text": "Llamas spit."}}
+                ],
+                "status": "ok"
+            }))
+        }),
+    );
+    serve_on(port, app).await;
+}
+
+#[tokio::test]
+async fn test_retriever_injects_qdrant_documents() {
+    let model_port = find_available_port();
+    start_model_server(model_port).await;
+    let qdrant_port = find_available_port();
+    start_qdrant(qdrant_port).await;
+
+    let json = format!(
+        r#"{{
+            "models": {{
+                "model": {{
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://127.0.0.1:{model_port}"
+                }}
+            }},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "vectorize", "layer": 1, "model": "model", "adapter": "embedding", "output-to": [2]}},
+                {{
+                    "name": "docs",
+                    "layer": 2,
+                    "adapter": "retriever",
+                    "retriever": {{
+                        "store": "qdrant",
+                        "url": "http://127.0.0.1:{qdrant_port}",
+                        "collection": "animals",
+                        "top-k": 2
+                    }},
+                    "output-to": [3]
+                }},
+                {{"name": "answer", "layer": 3, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ]
+        }}"#
+    );
+
+    let worker_port = start_worker(Composition::from_str(&json).unwrap()).await;
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "http://127.0.0.1:{}/v1/chat/completions",
+            worker_port
+        ))
+        .json(&json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "What do llamas do?"}]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let body: Value = response.json().await.unwrap();
+    let content = body["choices"][0]["message"]["content"].as_str().unwrap();
+    assert_eq!(
+        content,
+        "Context:\n[7] Llamas hum.\n\n[12] Llamas spit.\n\nQuestion: What do llamas do?"
+    );
+}
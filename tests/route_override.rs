@@ -0,0 +1,121 @@
+//! Integration tests for client-selected routes that bypass the router
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::{completion, find_available_port, serve_on};
+use llmnet::config::Composition;
+
+/// Model backend whose router always picks handler-a; handlers answer with
+/// the model name they were called with. Counts how often the router was asked.
+async fn start_model_server(port: u16, router_calls: Arc<AtomicUsize>) {
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(body): Json<Value>| {
+            let router_calls = router_calls.clone();
+            async move {
+                let model = body["model"].as_str().unwrap_or_default().to_string();
+                let content = if model.starts_with("router") {
+                    router_calls.fetch_add(1, Ordering::SeqCst);
+                    "handler-a".to_string()
+                } else {
+                    format!("answer from {}", model)
+                };
+                completion(content)
+            }
+        }),
+    );
+    serve_on(port, app).await;
+}
+
+async fn start_worker(router_calls: Arc<AtomicUsize>) -> String {
+    let model_port = find_available_port();
+    start_model_server(model_port, router_calls).await;
+
+    let url = format!("http://127.0.0.1:{}", model_port);
+    let json = format!(
+        r#"{{
+            "models": {{
+                "router-model": {{"type": "external", "interface": "openai-api", "url": "{url}"}},
+                "a-model": {{"type": "external", "interface": "openai-api", "url": "{url}"}},
+                "b-model": {{"type": "external", "interface": "openai-api", "url": "{url}"}}
+            }},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "router-model", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "handler-a", "layer": 1, "model": "a-model", "adapter": "openai-api", "use-case": "General", "output-to": ["output"]}},
+                {{"name": "handler-b", "layer": 1, "model": "b-model", "adapter": "openai-api", "use-case": "Billing", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ],
+            "route-overrides": ["handler-b"]
+        }}"#
+    );
+    let composition = Composition::from_str(&json).unwrap();
+    let worker_port = common::start_worker(composition).await;
+    format!("http://127.0.0.1:{}", worker_port)
+}
+
+async fn complete(base: &str, route: Option<&str>, model: &str) -> (u16, Value) {
+    let mut request = reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&json!({
+            "model": model,
+            "messages": [{"role": "user", "content": "Why was I charged twice?"}]
+        }));
+    if let Some(route) = route {
+        request = request.header("X-LLMNet-Route", route);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+fn content(body: &Value) -> &str {
+    body["choices"][0]["message"]["content"].as_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_route_header_and_model_bypass_router() {
+    let router_calls = Arc::new(AtomicUsize::new(0));
+    let base = start_worker(router_calls.clone()).await;
+
+    let (status, body) = complete(&base, Some("handler-b"), "llmnet").await;
+    assert_eq!(status, 200);
+    assert_eq!(content(&body), "answer from handler-b");
+
+    let (status, body) = complete(&base, None, "handler-b").await;
+    assert_eq!(status, 200);
+    assert_eq!(content(&body), "answer from handler-b");
+
+    assert_eq!(router_calls.load(Ordering::SeqCst), 0);
+
+    // Without an override the router decides as usual
+    let (_, body) = complete(&base, None, "gpt-4o").await;
+    assert_eq!(content(&body), "answer from handler-a");
+    assert_eq!(router_calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_route_header_outside_allowlist_is_rejected() {
+    let router_calls = Arc::new(AtomicUsize::new(0));
+    let base = start_worker(router_calls.clone()).await;
+
+    let (status, body) = complete(&base, Some("handler-a"), "llmnet").await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["code"], "route_not_allowed");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("handler-a"));
+
+    // Naming a node that isn't allowed as the model is not an override
+    let (status, body) = complete(&base, None, "handler-a").await;
+    assert_eq!(status, 200);
+    assert_eq!(content(&body), "answer from handler-a");
+    assert_eq!(router_calls.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,174 @@
+//! Integration tests for conversation sessions
+
+mod common;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::{completion, find_available_port, serve_on};
+use llmnet::config::Composition;
+
+/// Model backend that answers with every message it was sent, joined by " | "
+async fn start_echo_model_server(port: u16) {
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(|Json(body): Json<Value>| async move {
+            let contents: Vec<&str> = body["messages"]
+                .as_array()
+                .map(|messages| {
+                    messages
+                        .iter()
+                        .filter_map(|m| m["content"].as_str())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            completion(contents.join(" | "))
+        }),
+    );
+    serve_on(port, app).await;
+}
+
+async fn start_worker(model_port: u16) -> String {
+    let json = format!(
+        r#"{{
+            "models": {{
+                "model": {{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:{}"}}
+            }},
+            "sessions": {{"store": "memory", "max-tokens": 1000}},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ]
+        }}"#,
+        model_port
+    );
+    let composition = Composition::from_str(&json).unwrap();
+    let worker_port = common::start_worker(composition).await;
+    format!("http://127.0.0.1:{}", worker_port)
+}
+
+async fn chat(client: &reqwest::Client, base: &str, body: Value) -> (Option<String>, String) {
+    let response = client
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+
+    let session = response
+        .headers()
+        .get("x-session-id")
+        .map(|v| v.to_str().unwrap().to_string());
+    let body: Value = response.json().await.unwrap();
+    let content = body["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    (session, content)
+}
+
+#[tokio::test]
+async fn test_session_history_is_replayed() {
+    let model_port = find_available_port();
+    start_echo_model_server(model_port).await;
+    let base = start_worker(model_port).await;
+    let client = reqwest::Client::new();
+
+    let (session, first) = chat(
+        &client,
+        &base,
+        json!({
+            "model": "test",
+            "session_id": "ada",
+            "messages": [{"role": "user", "content": "Hi, I'm Ada"}]
+        }),
+    )
+    .await;
+    assert_eq!(session.as_deref(), Some("ada"));
+    assert_eq!(first, "Hi, I'm Ada");
+
+    // The handler sees the earlier exchange before the new prompt
+    let (_, second) = chat(
+        &client,
+        &base,
+        json!({
+            "model": "test",
+            "session_id": "ada",
+            "messages": [{"role": "user", "content": "What's my name?"}]
+        }),
+    )
+    .await;
+    assert_eq!(second, "Hi, I'm Ada | Hi, I'm Ada | What's my name?");
+
+    // Requests without a session stay stateless
+    let (session, stateless) = chat(
+        &client,
+        &base,
+        json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "Who am I?"}]
+        }),
+    )
+    .await;
+    assert_eq!(session, None);
+    assert_eq!(stateless, "Who am I?");
+
+    let deleted = client
+        .delete(format!("{}/v1/sessions/ada", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), 204);
+
+    let (_, after_delete) = chat(
+        &client,
+        &base,
+        json!({
+            "model": "test",
+            "session_id": "ada",
+            "messages": [{"role": "user", "content": "Hello again"}]
+        }),
+    )
+    .await;
+    assert_eq!(after_delete, "Hello again");
+}
+
+#[tokio::test]
+async fn test_session_header_takes_precedence() {
+    let model_port = find_available_port();
+    start_echo_model_server(model_port).await;
+    let base = start_worker(model_port).await;
+    let client = reqwest::Client::new();
+
+    for prompt in ["one", "two"] {
+        let response = client
+            .post(format!("{}/v1/chat/completions", base))
+            .header("x-session-id", "from-header")
+            .json(&json!({
+                "model": "test",
+                "session_id": "from-body",
+                "messages": [{"role": "user", "content": prompt}]
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["x-session-id"], "from-header");
+    }
+
+    // The body's session was never used
+    let (_, content) = chat(
+        &client,
+        &base,
+        json!({
+            "model": "test",
+            "session_id": "from-body",
+            "messages": [{"role": "user", "content": "three"}]
+        }),
+    )
+    .await;
+    assert_eq!(content, "three");
+}
@@ -0,0 +1,170 @@
+//! Integration tests for OpenAI tool calling through the pipeline
+
+mod common;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::{find_available_port, serve_on};
+use llmnet::config::Composition;
+
+/// Model backend that calls `get_weather` when offered it, and answers with
+/// the tool's result once it has one
+async fn start_tool_model_server(port: u16) {
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(|Json(body): Json<Value>| async move {
+            let messages = body["messages"].as_array().cloned().unwrap_or_default();
+            let last = messages.last().cloned().unwrap_or_default();
+            let has_tools = body["tools"]
+                .as_array()
+                .is_some_and(|tools| tools.iter().any(|t| t["function"]["name"] == "get_weather"));
+
+            let message = if last["role"] == "tool" {
+                json!({"role": "assistant", "content": format!("It is {} in Oslo", last["content"].as_str().unwrap())})
+            } else if has_tools {
+                json!({
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"}
+                    }]
+                })
+            } else {
+                json!({"role": "assistant", "content": "no tools offered"})
+            };
+
+            Json(json!({
+                "id": "chatcmpl-test",
+                "choices": [{"index": 0, "message": message, "finish_reason": "stop"}]
+            }))
+        }),
+    );
+    serve_on(port, app).await;
+}
+
+async fn start_worker(handler_model: &str) -> String {
+    let model_port = find_available_port();
+    start_tool_model_server(model_port).await;
+
+    let handler_model = handler_model.replace("PORT", &model_port.to_string());
+    let json = format!(
+        r#"{{
+            "models": {{
+                "router-model": {{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:{}"}},
+                "handler-model": {}
+            }},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "router-model", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "handler", "layer": 1, "model": "handler-model", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ]
+        }}"#,
+        model_port, handler_model
+    );
+    let composition = Composition::from_str(&json).unwrap();
+    let worker_port = common::start_worker(composition).await;
+    format!("http://127.0.0.1:{}", worker_port)
+}
+
+fn weather_tool() -> Value {
+    json!([{
+        "type": "function",
+        "function": {
+            "name": "get_weather",
+            "description": "Current weather for a city",
+            "parameters": {
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+                "required": ["city"]
+            }
+        }
+    }])
+}
+
+async fn complete(base: &str, body: Value) -> Value {
+    reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base))
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_tool_calls_round_trip() {
+    let base = start_worker(
+        r#"{"type": "external", "interface": "openai-api", "url": "http://127.0.0.1:PORT"}"#,
+    )
+    .await;
+
+    let first = complete(
+        &base,
+        json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "Weather in Oslo?"}],
+            "tools": weather_tool(),
+            "tool_choice": "auto"
+        }),
+    )
+    .await;
+
+    let choice = &first["choices"][0];
+    assert_eq!(choice["finish_reason"], "tool_calls");
+    let call = &choice["message"]["tool_calls"][0];
+    assert_eq!(call["id"], "call_1");
+    assert_eq!(call["function"]["name"], "get_weather");
+    assert_eq!(call["function"]["arguments"], "{\"city\":\"Oslo\"}");
+
+    // The client runs the tool and sends the result back
+    let second = complete(
+        &base,
+        json!({
+            "model": "test",
+            "messages": [
+                {"role": "user", "content": "Weather in Oslo?"},
+                choice["message"].clone(),
+                {"role": "tool", "tool_call_id": "call_1", "content": "14C and raining"}
+            ],
+            "tools": weather_tool()
+        }),
+    )
+    .await;
+
+    assert_eq!(second["choices"][0]["finish_reason"], "stop");
+    assert_eq!(
+        second["choices"][0]["message"]["content"],
+        "It is 14C and raining in Oslo"
+    );
+    assert!(second["choices"][0]["message"].get("tool_calls").is_none());
+}
+
+#[tokio::test]
+async fn test_tools_not_forwarded_to_models_without_support() {
+    let base = start_worker(
+        r#"{"runner": "external", "endpoint": "http://127.0.0.1:PORT/v1", "tools": false}"#,
+    )
+    .await;
+
+    let response = complete(
+        &base,
+        json!({
+            "model": "test",
+            "messages": [{"role": "user", "content": "Weather in Oslo?"}],
+            "tools": weather_tool()
+        }),
+    )
+    .await;
+
+    assert_eq!(response["choices"][0]["finish_reason"], "stop");
+    assert_eq!(
+        response["choices"][0]["message"]["content"],
+        "no tools offered"
+    );
+}
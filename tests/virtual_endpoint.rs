@@ -0,0 +1,167 @@
+//! Integration tests for traffic splitting through virtual endpoints
+
+mod common;
+
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use common::serve;
+use llmnet::cluster::{
+    create_control_plane_router, ClusterController, ControlPlaneState, Pipeline, PipelineStatus,
+};
+use llmnet::config::Composition;
+
+/// A replica that answers every chat completion with `status` and its name
+async fn start_replica(name: &'static str, status: StatusCode) -> String {
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(_): Json<Value>| async move {
+            (
+                status,
+                Json(json!({
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": name},
+                        "finish_reason": "stop"
+                    }]
+                })),
+            )
+        }),
+    );
+    serve(app).await
+}
+
+/// Deploy a pipeline and pretend the orchestrator scheduled it on `endpoint`
+fn deploy(controller: &ClusterController, name: &str, endpoint: String) {
+    let composition = Composition::from_str(
+        r#"{
+            "models": {"m": {"type": "external", "interface": "openai-api", "url": "http://a"}},
+            "architecture": [
+                {"name": "router", "layer": 0, "model": "m", "adapter": "openai-api", "output-to": ["output"]},
+                {"name": "output", "adapter": "output"}
+            ]
+        }"#,
+    )
+    .unwrap();
+    controller
+        .deploy_pipeline(Pipeline::new(name, composition))
+        .unwrap();
+    let mut status = PipelineStatus::initial();
+    status.replicas = 1;
+    status.endpoints = vec![endpoint];
+    controller
+        .update_pipeline_status("default", name, status)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_endpoint_splits_traffic_and_counts_each_variant() {
+    let state = ControlPlaneState::new();
+    let controller = state.controller.clone();
+    deploy(
+        &controller,
+        "chat-v1",
+        start_replica("v1", StatusCode::OK).await,
+    );
+    deploy(
+        &controller,
+        "chat-v2",
+        start_replica("v2", StatusCode::INTERNAL_SERVER_ERROR).await,
+    );
+    let base = serve(create_control_plane_router(state)).await;
+    let client = reqwest::Client::new();
+    let endpoint_url = format!("{}/v1/namespaces/default/endpoints/chat", base);
+
+    let response = client
+        .put(&endpoint_url)
+        .json(&json!({
+            "apiVersion": "llmnet/v1",
+            "kind": "VirtualEndpoint",
+            "metadata": {"name": "chat", "namespace": "default"},
+            "spec": {"backends": [
+                {"pipeline": "chat-v1", "weight": 3},
+                {"pipeline": "chat-v2", "weight": 1}
+            ]}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = json!({"model": "llmnet", "messages": [{"role": "user", "content": "hi"}]});
+    let mut variants = Vec::new();
+    for _ in 0..20 {
+        let response = client
+            .post(format!("{}/chat/completions", endpoint_url))
+            .json(&request)
+            .send()
+            .await
+            .unwrap();
+        let variant = response.headers()["x-llmnet-variant"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body: Value = response.json().await.unwrap();
+        let answer = body["choices"][0]["message"]["content"].as_str().unwrap();
+        assert_eq!(variant.strip_prefix("chat-").unwrap(), answer);
+        variants.push(variant);
+    }
+    assert_eq!(variants.iter().filter(|v| *v == "chat-v2").count(), 5);
+
+    let endpoint: Value = client
+        .get(&endpoint_url)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let stats = &endpoint["status"]["variants"];
+    assert_eq!(stats["chat-v1"]["requests"], 15);
+    assert_eq!(stats["chat-v1"]["errors"], 0);
+    assert_eq!(stats["chat-v2"]["requests"], 5);
+    assert_eq!(stats["chat-v2"]["errors"], 5);
+}
+
+#[tokio::test]
+async fn test_endpoint_without_active_backends() {
+    let state = ControlPlaneState::new();
+    let base = serve(create_control_plane_router(state)).await;
+    let client = reqwest::Client::new();
+    let endpoint_url = format!("{}/v1/namespaces/default/endpoints/chat", base);
+    let request = json!({"model": "llmnet", "messages": [{"role": "user", "content": "hi"}]});
+
+    let response = client
+        .post(format!("{}/chat/completions", endpoint_url))
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The only backend starts taking traffic next year
+    let response = client
+        .put(&endpoint_url)
+        .json(&json!({
+            "apiVersion": "llmnet/v1",
+            "kind": "VirtualEndpoint",
+            "metadata": {"name": "chat", "namespace": "default"},
+            "spec": {"backends": [
+                {"pipeline": "chat-v2", "weight": 1, "activeFrom": "2099-01-01T00:00:00Z"}
+            ]}
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .post(format!("{}/chat/completions", endpoint_url))
+        .json(&request)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
@@ -0,0 +1,139 @@
+//! Integration tests for the worker's WebSocket streaming endpoint
+//!
+//! A thin HTTP server stands in for the OpenAI-compatible model backend so
+//! the pipeline can run end to end while a WebSocket client watches.
+
+mod common;
+
+use std::time::Duration;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::time::timeout;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use common::{completion, find_available_port, serve_on, start_worker};
+use llmnet::config::Composition;
+
+/// Fake model backend that answers every prompt the same way
+async fn start_model_server(port: u16) {
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(|Json(_): Json<Value>| async { completion("handled") }),
+    );
+    serve_on(port, app).await;
+}
+
+/// Read events until a terminal one arrives
+async fn collect_events(
+    ws: &mut tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+) -> Vec<Value> {
+    let mut events = Vec::new();
+    loop {
+        let frame = timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("Timed out waiting for event")
+            .expect("Stream closed")
+            .expect("WebSocket error");
+
+        if let Message::Text(text) = frame {
+            let event: Value = serde_json::from_str(&text).unwrap();
+            let done = matches!(event["type"].as_str(), Some("completed" | "failed"));
+            events.push(event);
+            if done {
+                return events;
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_stream_reports_hops_and_final_answer() {
+    let model_port = find_available_port();
+    start_model_server(model_port).await;
+
+    let json = format!(
+        r#"{{
+            "models": {{
+                "model": {{
+                    "type": "external",
+                    "interface": "openai-api",
+                    "url": "http://127.0.0.1:{}"
+                }}
+            }},
+            "architecture": [
+                {{"name": "router", "layer": 0, "model": "model", "adapter": "openai-api", "output-to": [1]}},
+                {{"name": "handler", "layer": 1, "model": "model", "adapter": "openai-api", "output-to": ["output"]}},
+                {{"name": "output", "adapter": "output"}}
+            ]
+        }}"#,
+        model_port
+    );
+    let worker_port = start_worker(Composition::from_str(&json).unwrap()).await;
+
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{}/v1/stream", worker_port))
+        .await
+        .expect("Failed to connect");
+
+    let request = json!({
+        "model": "test",
+        "messages": [{"role": "user", "content": "Hello"}]
+    });
+    ws.send(Message::Text(request.to_string().into()))
+        .await
+        .unwrap();
+
+    let events = collect_events(&mut ws).await;
+    let types: Vec<&str> = events.iter().filter_map(|e| e["type"].as_str()).collect();
+    assert_eq!(types, vec!["started", "chunk", "hop", "completed"]);
+
+    // The backend doesn't stream, so the answer is one chunk
+    assert_eq!(events[1]["node"], "handler");
+    assert_eq!(events[1]["content"], "handled");
+    assert_eq!(events[2]["node"], "handler");
+    assert_eq!(events[2]["layer"], 1);
+    assert_eq!(events[2]["output"], "handled");
+    assert_eq!(events[3]["content"], "handled");
+
+    // Every event belongs to the same request
+    let request_id = &events[0]["request_id"];
+    assert!(events.iter().all(|e| &e["request_id"] == request_id));
+}
+
+#[tokio::test]
+async fn test_stream_without_processor_fails_cleanly() {
+    let json = r#"{
+        "models": {},
+        "architecture": [
+            {"name": "router", "layer": 0, "adapter": "openai-api"},
+            {"name": "output", "adapter": "output"}
+        ]
+    }"#;
+    let worker_port = start_worker(Composition::from_str(json).unwrap()).await;
+
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{}/v1/stream", worker_port))
+        .await
+        .expect("Failed to connect");
+
+    // Malformed requests are reported without closing the socket
+    ws.send(Message::Text("not json".into())).await.unwrap();
+    let events = collect_events(&mut ws).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["type"], "failed");
+
+    let request = json!({
+        "model": "test",
+        "messages": [{"role": "user", "content": "Hello"}]
+    });
+    ws.send(Message::Text(request.to_string().into()))
+        .await
+        .unwrap();
+
+    let events = collect_events(&mut ws).await;
+    assert_eq!(events[0]["type"], "started");
+    assert_eq!(events[1]["type"], "failed");
+}